@@ -6,9 +6,10 @@ use proc_macro::TokenStream;
 
 use proc_macro2::{TokenStream as TokenStream2, TokenTree};
 use quote::{quote, ToTokens, TokenStreamExt};
+use syn::parse::{Parse, ParseStream};
 use syn::{
     parse_macro_input, parse_quote, spanned::Spanned, Error, Fields, FnArg, Ident, ItemFn,
-    ItemStruct, LitStr, Pat, Visibility,
+    ItemStruct, LitStr, Pat, Token, Visibility,
 };
 
 macro_rules! err {
@@ -20,10 +21,45 @@ macro_rules! err {
     };
 }
 
+/// Arguments of the [`unsafe_protocol`] macro.
+struct ProtocolArgs {
+    guid: LitStr,
+    service_binding: Option<LitStr>,
+}
+
+impl Parse for ProtocolArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let guid = input.parse()?;
+        let mut service_binding = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let name: Ident = input.parse()?;
+            if name != "service_binding" {
+                return Err(Error::new(
+                    name.span(),
+                    format!("unknown argument `{name}`, expected `service_binding`"),
+                ));
+            }
+            input.parse::<Token![=]>()?;
+            service_binding = Some(input.parse()?);
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(Self {
+            guid,
+            service_binding,
+        })
+    }
+}
+
 /// Attribute macro for marking structs as UEFI protocols.
 ///
 /// The macro takes one argument, a GUID string.
 ///
+/// For protocols that are created on child handles by a service binding
+/// protocol, the GUID of the service binding protocol can be passed as a
+/// second `service_binding = "..."` argument. The macro then also
+/// implements the `unsafe` [`ServiceBinding`] trait, so that the service
+/// binding protocol can be opened as [`ServiceBindingProtocol<P>`].
+///
 /// The macro can only be applied to a struct, and the struct must have
 /// named fields (i.e. not a unit or tuple struct). It implements the
 /// [`Protocol`] trait and the `unsafe` [`Identify`] trait for the
@@ -46,16 +82,24 @@ macro_rules! err {
 /// struct ExampleProtocol {}
 ///
 /// assert_eq!(ExampleProtocol::GUID, guid!("12345678-9abc-def0-1234-56789abcdef0"));
+///
+/// #[unsafe_protocol(
+///     "12345678-9abc-def0-1234-56789abcdef1",
+///     service_binding = "12345678-9abc-def0-1234-56789abcdef2"
+/// )]
+/// struct ExampleChildProtocol {}
 /// ```
 ///
 /// [`Identify`]: https://docs.rs/uefi/latest/uefi/trait.Identify.html
 /// [`Protocol`]: https://docs.rs/uefi/latest/uefi/proto/trait.Protocol.html
+/// [`ServiceBinding`]: https://docs.rs/uefi/latest/uefi/proto/service_binding/trait.ServiceBinding.html
+/// [`ServiceBindingProtocol<P>`]: https://docs.rs/uefi/latest/uefi/proto/service_binding/struct.ServiceBindingProtocol.html
 /// [send-and-sync]: https://doc.rust-lang.org/nomicon/send-and-sync.html
 #[proc_macro_attribute]
 pub fn unsafe_protocol(args: TokenStream, input: TokenStream) -> TokenStream {
-    // Parse `args` as a GUID string.
+    let args = parse_macro_input!(args as ProtocolArgs);
     let (time_low, time_mid, time_high_and_version, clock_seq_and_variant, node) =
-        match parse_guid(parse_macro_input!(args as LitStr)) {
+        match parse_guid(args.guid) {
             Ok(data) => data,
             Err(tokens) => return tokens.into(),
         };
@@ -73,6 +117,26 @@ pub fn unsafe_protocol(args: TokenStream, input: TokenStream) -> TokenStream {
     let struct_generics = &item_struct.generics;
     let (impl_generics, ty_generics, where_clause) = item_struct.generics.split_for_impl();
 
+    let service_binding_impl = match args.service_binding.map(parse_guid) {
+        Some(Ok((time_low, time_mid, time_high_and_version, clock_seq_and_variant, node))) => {
+            quote! {
+                unsafe impl #impl_generics ::uefi::proto::service_binding::ServiceBinding
+                    for #ident #ty_generics #where_clause
+                {
+                    const SERVICE_BINDING_GUID: ::uefi::Guid = ::uefi::Guid::from_values(
+                        #time_low,
+                        #time_mid,
+                        #time_high_and_version,
+                        #clock_seq_and_variant,
+                        #node,
+                    );
+                }
+            }
+        }
+        Some(Err(tokens)) => return tokens.into(),
+        None => quote! {},
+    };
+
     quote! {
         #(#struct_attrs)*
         #struct_vis struct #ident #struct_generics {
@@ -94,6 +158,8 @@ pub fn unsafe_protocol(args: TokenStream, input: TokenStream) -> TokenStream {
         }
 
         impl #impl_generics ::uefi::proto::Protocol for #ident #ty_generics #where_clause {}
+
+        #service_binding_impl
     }
     .into()
 }
@@ -314,9 +380,40 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     result.into()
 }
 
+/// Convert the characters of `lit` to code units with `convert`, appending
+/// a null terminator. Null characters and characters rejected by `convert`
+/// cause a compile error naming the character and its position.
+fn encode_cstr<T>(
+    lit: &LitStr,
+    encoding: &str,
+    convert: impl Fn(char) -> Option<T>,
+) -> Result<Vec<T>, TokenStream2> {
+    let mut units = Vec::new();
+    for (i, c) in lit.value().chars().enumerate() {
+        if c == '\0' {
+            return Err(err!(lit, "null character at position {} in string", i));
+        }
+        match convert(c) {
+            Some(unit) => units.push(unit),
+            None => {
+                return Err(err!(
+                    lit,
+                    "character {:?} (U+{:04X}) at position {} is not valid {}",
+                    c,
+                    u32::from(c),
+                    i,
+                    encoding
+                ))
+            }
+        }
+    }
+    Ok(units)
+}
+
 /// Builds a `CStr8` literal at compile time from a string literal.
 ///
-/// This will throw a compile error if an invalid character is in the passed string.
+/// This will throw a compile error if the string contains a null character
+/// or a character outside of Latin-1. The result can be used in constants.
 ///
 /// # Example
 /// ```
@@ -326,24 +423,19 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
 #[proc_macro]
 pub fn cstr8(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input: LitStr = parse_macro_input!(input);
-    let input = input.value();
-    match input
-        .chars()
-        .map(u8::try_from)
-        .collect::<Result<Vec<u8>, _>>()
-    {
+    match encode_cstr(&input, "Latin-1", |c| u8::try_from(c).ok()) {
         Ok(c) => {
-            quote!(unsafe { ::uefi::CStr8::from_bytes_with_nul_unchecked(&[ #(#c),* , 0 ]) }).into()
+            quote!(unsafe { ::uefi::CStr8::from_bytes_with_nul_unchecked(&[ #(#c,)* 0 ]) }).into()
         }
-        Err(_) => syn::Error::new_spanned(input, "invalid character in string")
-            .into_compile_error()
-            .into(),
+        Err(err) => err.into(),
     }
 }
 
 /// Builds a `CStr16` literal at compile time from a string literal.
 ///
-/// This will throw a compile error if an invalid character is in the passed string.
+/// This will throw a compile error if the string contains a null character
+/// or a character outside of UCS-2 (the Basic Multilingual Plane). The
+/// result can be used in constants.
 ///
 /// # Example
 /// ```
@@ -353,17 +445,10 @@ pub fn cstr8(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 #[proc_macro]
 pub fn cstr16(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input: LitStr = parse_macro_input!(input);
-    let input = input.value();
-    match input
-        .chars()
-        .map(|c| u16::try_from(c as u32))
-        .collect::<Result<Vec<u16>, _>>()
-    {
+    match encode_cstr(&input, "UCS-2", |c| u16::try_from(u32::from(c)).ok()) {
         Ok(c) => {
-            quote!(unsafe { ::uefi::CStr16::from_u16_with_nul_unchecked(&[ #(#c),* , 0 ]) }).into()
+            quote!(unsafe { ::uefi::CStr16::from_u16_with_nul_unchecked(&[ #(#c,)* 0 ]) }).into()
         }
-        Err(_) => syn::Error::new_spanned(input, "invalid character in string")
-            .into_compile_error()
-            .into(),
+        Err(err) => err.into(),
     }
 }
@@ -0,0 +1,7 @@
+use uefi::CStr16;
+use uefi_macros::cstr16;
+
+// Fail because the emoji is outside of UCS-2.
+const Emoji: &CStr16 = cstr16!("smile 😀");
+
+fn main() {}
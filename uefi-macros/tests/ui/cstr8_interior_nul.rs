@@ -0,0 +1,7 @@
+use uefi::CStr8;
+use uefi_macros::cstr8;
+
+// Fail because the string contains a null character.
+const WithNul: &CStr8 = cstr8!("a\0b");
+
+fn main() {}
@@ -0,0 +1,10 @@
+use uefi::proto::unsafe_protocol;
+
+// Fail because the second argument is unknown.
+#[unsafe_protocol(
+    "12345678-9abc-def0-1234-56789abcdef0",
+    binding = "12345678-9abc-def0-1234-56789abcdef1"
+)]
+struct BadArgProtocol {}
+
+fn main() {}
@@ -43,6 +43,7 @@ use core::ptr::NonNull;
 use cfg_if::cfg_if;
 
 use uefi::prelude::*;
+use uefi::proto::console::text::OutputLock;
 use uefi::table::boot::{EventType, Tpl};
 use uefi::table::{Boot, SystemTable};
 use uefi::{Event, Result};
@@ -58,6 +59,10 @@ static mut SYSTEM_TABLE: Option<SystemTable<Boot>> = None;
 #[cfg(feature = "logger")]
 static mut LOGGER: Option<uefi::logger::Logger> = None;
 
+/// Function called by the panic handler, set with [`set_panic_hook`].
+#[cfg(feature = "panic_handler")]
+static mut PANIC_HOOK: Option<fn(&core::panic::PanicInfo)> = None;
+
 /// Obtains a pointer to the system table.
 ///
 /// This is meant to be used by higher-level libraries,
@@ -118,6 +123,12 @@ pub fn _print(args: core::fmt::Arguments) {
             .as_mut()
             .expect("The system table handle is not available");
 
+        // Skip the output if it is reentered, e.g. from a log in the
+        // middle of another write.
+        let _lock = match OutputLock::try_lock() {
+            Some(lock) => lock,
+            None => return,
+        };
         st.stdout()
             .write_fmt(args)
             .expect("Failed to write to stdout");
@@ -157,6 +168,59 @@ macro_rules! println {
     ($($arg:tt)*) => ($crate::_print(core::format_args!("{}{}", core::format_args!($($arg)*), "\n")));
 }
 
+// Internal function for eprint macros.
+#[doc(hidden)]
+pub fn _eprint(args: core::fmt::Arguments) {
+    unsafe {
+        let st = SYSTEM_TABLE
+            .as_mut()
+            .expect("The system table handle is not available");
+
+        // Skip the output if it is reentered, e.g. from a log in the
+        // middle of another write.
+        let _lock = match OutputLock::try_lock() {
+            Some(lock) => lock,
+            None => return,
+        };
+        st.stderr()
+            .write_fmt(args)
+            .expect("Failed to write to stderr");
+    }
+}
+
+/// Prints to the standard error.
+///
+/// # Panics
+/// Will panic if `SYSTEM_TABLE` is `None` (Before [init()] and after [uefi::prelude::SystemTable::exit_boot_services()]).
+///
+/// # Examples
+/// ```
+/// eprint!("");
+/// eprint!("Hello World\n");
+/// eprint!("Hello {}", "World");
+/// ```
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => ($crate::_eprint(core::format_args!($($arg)*)));
+}
+
+/// Prints to the standard error, with a newline.
+///
+/// # Panics
+/// Will panic if `SYSTEM_TABLE` is `None` (Before [init()] and after [uefi::prelude::SystemTable::exit_boot_services()]).
+///
+/// # Examples
+/// ```
+/// eprintln!();
+/// eprintln!("Hello World");
+/// eprintln!("Hello {}", "World");
+/// ```
+#[macro_export]
+macro_rules! eprintln {
+    () => ($crate::eprint!("\n"));
+    ($($arg:tt)*) => ($crate::_eprint(core::format_args!("{}{}", core::format_args!($($arg)*), "\n")));
+}
+
 /// Set up logging
 ///
 /// This is unsafe because you must arrange for the logger to be reset with
@@ -197,11 +261,31 @@ unsafe extern "efiapi" fn exit_boot_services(_e: Event, _ctx: Option<NonNull<c_v
     uefi::global_allocator::exit_boot_services();
 }
 
+/// Set a function to be called by the panic handler after the panic
+/// message is printed, and before the system is shut down.
+///
+/// This can be used to report the panic in an application-specific way,
+/// e.g. to tell a test harness which test failed.
+#[cfg(feature = "panic_handler")]
+pub fn set_panic_hook(hook: fn(&core::panic::PanicInfo)) {
+    unsafe {
+        PANIC_HOOK = Some(hook);
+    }
+}
+
 #[cfg(feature = "panic_handler")]
 #[panic_handler]
 fn panic_handler(info: &core::panic::PanicInfo) -> ! {
+    // The panic may have happened while writing to the console. This
+    // function doesn't return, so that write will never resume.
+    unsafe { OutputLock::force_unlock() };
     println!("[PANIC]: {}", info);
 
+    // Take the hook so that a panic in the hook doesn't call it again.
+    if let Some(hook) = unsafe { core::ptr::addr_of_mut!(PANIC_HOOK).replace(None) } {
+        hook(info);
+    }
+
     // Give the user some time to read the message
     if let Some(st) = unsafe { SYSTEM_TABLE.as_ref() } {
         st.boot_services().stall(10_000_000);
@@ -248,6 +332,13 @@ fn panic_handler(info: &core::panic::PanicInfo) -> ! {
                             core::arch::asm!("hlt 420", options(nomem, nostack));
                         }
                     }
+                } else if #[cfg(any(target_arch = "arm", target_arch = "riscv64"))] {
+                    loop {
+                        unsafe {
+                            // Try to at least keep CPU from running at 100%
+                            core::arch::asm!("wfi", options(nomem, nostack));
+                        }
+                    }
                 } else {
                     loop {
                         // just run forever dammit how do you return never anyway
@@ -6,6 +6,7 @@ pub fn test(bt: &BootServices) {
     info!("Testing memory functions");
 
     allocate_pages(bt);
+    allocate_custom_pages(bt);
     vec_alloc();
     alloc_alignment();
     memmove(bt);
@@ -35,6 +36,28 @@ fn allocate_pages(bt: &BootServices) {
     bt.free_pages(pgs, 1).unwrap();
 }
 
+// Pages allocated with an OS-defined memory type keep it in the memory map.
+fn allocate_custom_pages(bt: &BootServices) {
+    info!("Allocating pages with a custom memory type");
+
+    let mem_ty = MemoryType::custom(0x8000_1234);
+    assert!(mem_ty.is_os_defined());
+    let pgs = bt
+        .allocate_pages(AllocateType::AnyPages, mem_ty, 2)
+        .expect("Failed to allocate pages with a custom memory type");
+
+    let (_key, descriptors) = bt
+        .memory_map_vec()
+        .expect("Failed to retrieve UEFI memory map");
+    let desc = descriptors
+        .iter()
+        .find(|desc| desc.phys_start <= pgs && pgs < desc.phys_start + desc.page_count * 4096)
+        .expect("Allocated pages are missing from the memory map");
+    assert_eq!(desc.ty, mem_ty);
+
+    bt.free_pages(pgs, 2).unwrap();
+}
+
 // Simple test to ensure our custom allocator works with the `alloc` crate.
 fn vec_alloc() {
     info!("Allocating a vector through the `alloc` crate");
@@ -112,4 +135,18 @@ fn memory_map(bt: &BootServices) {
     }
     let page_count = first_desc.page_count;
     assert!(page_count != 0, "Memory map entry has zero size");
+
+    // The allocating variant should return the same first entry.
+    let (_key, owned) = bt
+        .memory_map_vec()
+        .expect("Failed to retrieve UEFI memory map");
+    assert!(!owned.is_empty(), "Memory map is empty");
+    assert_eq!(owned[0].phys_start, first_desc.phys_start);
+    assert!(owned.iter().all(|desc| desc.page_count != 0));
+
+    let dump = bt
+        .dump_memory_map()
+        .expect("Failed to dump UEFI memory map");
+    assert!(!dump.descriptors.is_empty(), "Memory map dump is empty");
+    info!("{}", dump);
 }
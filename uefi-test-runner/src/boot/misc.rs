@@ -1,16 +1,22 @@
 use core::ffi::c_void;
 use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
 
+use uefi::executor::{block_on, EventFuture, Timer};
 use uefi::proto::unsafe_protocol;
-use uefi::table::boot::{BootServices, EventType, SearchType, TimerTrigger, Tpl};
-use uefi::{Event, Identify};
+use uefi::table::boot::{BootServices, EventGroup, EventType, SearchType, TimerTrigger, Tpl};
+use uefi::{guid, Event, Identify};
 
 pub fn test(bt: &BootServices) {
     info!("Testing timer...");
     test_timer(bt);
+    test_sleep(bt);
+    test_executor(bt);
     info!("Testing events...");
     test_event_callback(bt);
     test_callback_with_ctx(bt);
+    test_event_group(bt);
     info!("Testing watchdog...");
     test_watchdog(bt);
     info!("Testing protocol handler services...");
@@ -30,6 +36,29 @@ fn test_timer(bt: &BootServices) {
         .expect("Wait for event failed");
 }
 
+fn test_sleep(bt: &BootServices) {
+    info!("Testing stall_duration and sleep");
+    bt.stall_duration(Duration::from_micros(10));
+    uefi::time::sleep(bt, Duration::from_micros(10)).expect("Failed to sleep (stall)");
+    uefi::time::sleep(bt, Duration::from_millis(20)).expect("Failed to sleep (timer)");
+}
+
+fn test_executor(bt: &BootServices) {
+    info!("Testing the executor");
+
+    let event = unsafe { bt.create_event(EventType::empty(), Tpl::APPLICATION, None, None) }
+        .expect("Failed to create event");
+    bt.signal_event(&event).expect("Failed to signal event");
+
+    block_on(bt, async {
+        EventFuture::new(bt, &event).await?;
+        Timer::new(bt, Duration::from_millis(20))?.await
+    })
+    .expect("Executor failed");
+
+    bt.close_event(event).expect("Failed to close event");
+}
+
 fn test_event_callback(bt: &BootServices) {
     extern "efiapi" fn callback(_event: Event, _ctx: Option<NonNull<c_void>>) {
         info!("Inside the event callback");
@@ -73,10 +102,42 @@ fn test_callback_with_ctx(bt: &BootServices) {
     assert_eq!(data, 456);
 }
 
+fn test_event_group(bt: &BootServices) {
+    static SIGNALED: AtomicUsize = AtomicUsize::new(0);
+
+    // Use a group of our own, so that signaling it has no side effects.
+    let group = EventGroup(guid!("2d4f55d9-1a6a-4a8e-9b2b-1b7f2c0c6f3e"));
+    let first = bt
+        .subscribe_event_group(group, || {
+            SIGNALED.fetch_add(1, Ordering::Relaxed);
+        })
+        .expect("Failed to subscribe to event group");
+    let second = bt
+        .subscribe_event_group(group, || {
+            SIGNALED.fetch_add(1, Ordering::Relaxed);
+        })
+        .expect("Failed to subscribe to event group");
+
+    bt.signal_event(&first).expect("Failed to signal event");
+    assert_eq!(SIGNALED.load(Ordering::Relaxed), 2);
+
+    bt.close_event(first).expect("Failed to close event");
+    bt.close_event(second).expect("Failed to close event");
+}
+
 fn test_watchdog(bt: &BootServices) {
     // Disable the UEFI watchdog timer
     bt.set_watchdog_timer(0, 0x10000, None)
         .expect("Could not set watchdog timer");
+
+    // Temporarily extend the watchdog; it is disabled again afterwards.
+    let value = bt
+        .with_watchdog_extended(Duration::from_secs(10), || {
+            bt.stall(1000);
+            123
+        })
+        .expect("Could not extend watchdog timer");
+    assert_eq!(value, 123);
 }
 
 /// Dummy protocol for tests
@@ -1,17 +1,36 @@
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use uefi::cleanup;
 use uefi::proto::console::text::Output;
+use uefi::proto::device_path::build::{self, DevicePathBuilder};
+use uefi::proto::device_path::DevicePath;
+use uefi::proto::loaded_image::LoadedImage;
 use uefi::table::boot::{BootServices, SearchType};
-use uefi::Identify;
+use uefi::{cstr16, Identify, Status};
 
-pub fn test(bt: &BootServices) {
-    info!("Testing boot services");
-    memory::test(bt);
-    misc::test(bt);
-    test_locate_handle_buffer(bt);
-}
+use crate::registry::{Test, TestContext};
+
+pub const TESTS: &[Test] = &[
+    Test::new("boot/image_file_system", test_image_file_system),
+    Test::new("boot/memory", |cx| memory::test(cx.bt())),
+    Test::new("boot/misc", |cx| misc::test(cx.bt())),
+    Test::new("boot/locate_handle_buffer", |cx| {
+        test_locate_handle_buffer(cx.bt())
+    }),
+    Test::new("boot/launch", |cx| test_launch_missing(cx.bt())),
+    Test::new("boot/cleanup", |cx| test_cleanup(cx.bt())),
+];
 
 mod memory;
 mod misc;
 
+/// Try retrieving a handle to the file system the image was booted from.
+fn test_image_file_system(cx: &mut TestContext) {
+    cx.bt()
+        .get_image_file_system(cx.image)
+        .expect("Failed to retrieve boot file system");
+}
+
 fn test_locate_handle_buffer(bt: &BootServices) {
     info!("Testing the `locate_handle_buffer` function");
 
@@ -34,3 +53,50 @@ fn test_locate_handle_buffer(bt: &BootServices) {
         );
     }
 }
+
+/// Test that launching a file that doesn't exist fails cleanly.
+fn test_launch_missing(bt: &BootServices) {
+    info!("Testing the `launch` function");
+
+    let loaded_image = bt
+        .open_protocol_exclusive::<LoadedImage>(bt.image_handle())
+        .expect("Failed to open LoadedImage protocol");
+    let device_path = bt
+        .open_protocol_exclusive::<DevicePath>(loaded_image.device())
+        .expect("Failed to open DevicePath protocol");
+
+    let mut buf = [MaybeUninit::uninit(); 256];
+    let mut builder = DevicePathBuilder::with_buf(&mut buf);
+    for node in device_path.node_iter() {
+        builder = builder.push(&node).unwrap();
+    }
+    let path = builder
+        .push(&build::media::FilePath {
+            path_name: cstr16!(r"\efi\boot\does-not-exist.efi"),
+        })
+        .unwrap()
+        .finalize()
+        .unwrap();
+
+    let err = bt
+        .launch(path, Some(cstr16!("arg")))
+        .expect_err("Launching a missing file should fail");
+    assert_eq!(err.status(), Status::NOT_FOUND);
+}
+
+/// Test exit callbacks and exit boot services event subscriptions.
+fn test_cleanup(bt: &BootServices) {
+    info!("Testing exit callbacks");
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    cleanup::on_exit(|| assert_eq!(COUNTER.fetch_add(1, Ordering::SeqCst), 1)).unwrap();
+    cleanup::on_exit(|| assert_eq!(COUNTER.fetch_add(1, Ordering::SeqCst), 0)).unwrap();
+    cleanup::run_exit_callbacks();
+    assert_eq!(COUNTER.load(Ordering::SeqCst), 2);
+
+    // Boot services are exited at the end of the tests, so cancel the
+    // callback to keep it from running then.
+    let event = cleanup::on_exit_boot_services(bt, || unreachable!())
+        .expect("Failed to create exit boot services event");
+    bt.close_event(event).expect("Failed to close event");
+}
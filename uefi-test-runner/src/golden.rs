@@ -0,0 +1,45 @@
+//! Comparison of test output with golden data.
+//!
+//! Large outputs, such as screenshots, are compared by their hash so that
+//! the golden data doesn't need to be embedded in the runner. Each
+//! successful check prints a `TEST_CHECK` record; a mismatch panics with the
+//! actual value, so that the golden data can be updated if the change is
+//! intended.
+
+use crate::registry::TestRecord;
+
+/// Computes the 64-bit FNV-1a hash of `bytes`.
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+/// Asserts that the [`hash`] of `data` is `expected`. `what` describes the
+/// data in the report.
+pub fn assert_hash(what: &str, data: &[u8], expected: u64) {
+    let actual = hash(data);
+    // Use `assert` rather than `assert_eq` to report the hash in hex.
+    assert!(
+        actual == expected,
+        "{what} does not match the golden data: hash is {actual:#018x}, expected {expected:#018x}"
+    );
+    TestRecord::Check(what).print();
+}
+
+/// Asserts that `actual` is equal to `expected`. `what` describes the data
+/// in the report.
+pub fn assert_bytes(what: &str, actual: &[u8], expected: &[u8]) {
+    if let Some(offset) = actual.iter().zip(expected).position(|(a, e)| a != e) {
+        panic!(
+            "{what} does not match the golden data at offset {offset}: {:#04x}, expected {:#04x}",
+            actual[offset], expected[offset]
+        );
+    }
+    assert_eq!(
+        actual.len(),
+        expected.len(),
+        "{what} does not have the length of the golden data"
+    );
+    TestRecord::Check(what).print();
+}
@@ -9,15 +9,39 @@ extern crate log;
 extern crate alloc;
 
 use alloc::string::ToString;
+use registry::{Filter, Test, TestContext};
 use uefi::prelude::*;
 use uefi::proto::console::serial::Serial;
+use uefi::runtime_pointers;
+use uefi::table::boot::{MemoryDescriptor, MemoryType};
+use uefi::table::cfg;
+use uefi::table::fpdt::Fpdt;
+use uefi::table::runtime::VirtualAddressMap;
 use uefi::Result;
 use uefi_services::{print, println};
 
 mod boot;
+mod golden;
 mod proto;
+mod registry;
 mod runtime;
 
+/// Tests that don't belong to a more specific group.
+const SYSTEM_TESTS: &[Test] = &[
+    Test::new("system/revision", |cx| {
+        check_revision(cx.st.uefi_revision())
+    }),
+    Test::new("system/config_table", |cx| check_config_table(cx.st)),
+    Test::new("system/tables", |cx| {
+        if let Err(err) = cx.st.validate() {
+            panic!("{err}");
+        }
+    }),
+];
+
+/// All tests, in the order they are run.
+const TESTS: &[&[Test]] = &[SYSTEM_TESTS, boot::TESTS, proto::TESTS, runtime::TESTS];
+
 #[entry]
 fn efi_main(image: Handle, mut st: SystemTable<Boot>) -> Status {
     // Initialize utilities (logging, memory allocation...)
@@ -40,42 +64,82 @@ fn efi_main(image: Handle, mut st: SystemTable<Boot>) -> Status {
     // Reset the console before running all the other tests.
     st.stdout().reset(false).expect("Failed to reset stdout");
 
-    // Ensure the tests are run on a version of UEFI we support.
-    check_revision(st.uefi_revision());
-
-    // Test all the boot services.
-    let bt = st.boot_services();
-
-    // Try retrieving a handle to the file system the image was booted from.
-    bt.get_image_file_system(image)
-        .expect("Failed to retrieve boot file system");
-
-    boot::test(bt);
-
-    // Test all the supported protocols.
-    proto::test(image, &mut st);
+    info!(
+        "Firmware: {} (revision {:#x})",
+        st.firmware_vendor(),
+        st.firmware_revision()
+    );
 
+    // Run the tests selected with `--test` in the load options, or all of
+    // them if there is no such option.
+    //
     // TODO: runtime services work before boot services are exited, but we'd
     // probably want to test them after exit_boot_services. However,
     // exit_boot_services is currently called during shutdown.
-
-    runtime::test(st.runtime_services());
+    let filter = Filter::from_load_options(st.boot_services(), image);
+    let mut cx = TestContext::new(image, &mut st);
+    registry::run(TESTS, &filter, &mut cx);
 
     shutdown(st);
 }
 
+/// Ensure the tests are run on a version of UEFI we support.
 fn check_revision(rev: uefi::table::Revision) {
     let (major, minor) = (rev.major(), rev.minor());
 
     info!("UEFI {}.{}", major, minor / 10);
 
-    assert!(major >= 2, "Running on an old, unsupported version of UEFI");
     assert!(
-        minor >= 30,
+        rev.is_at_least(2, 0),
+        "Running on an old, unsupported version of UEFI"
+    );
+    assert!(
+        rev.is_at_least(2, 3),
         "Old version of UEFI 2, some features might not be available."
     );
 }
 
+fn check_config_table(st: &SystemTable<Boot>) {
+    info!("Testing config table");
+
+    for entry in st.config_table() {
+        info!(
+            "Config table entry: {} ({})",
+            entry.guid,
+            entry.name().unwrap_or("unknown")
+        );
+    }
+
+    // OVMF always provides ACPI 2.0 tables.
+    assert!(st.find_config_table_entry(&cfg::ACPI2_GUID).is_some());
+
+    // The FADT is mandatory, and QEMU always provides it.
+    let fadt = st
+        .find_acpi_table(b"FACP")
+        .expect("Failed to find the FADT");
+    info!("FADT: {:?}", fadt);
+    assert!(fadt.is_checksum_valid());
+
+    // The FPDT is optional.
+    if let Some(fbpt) = Fpdt::find(st).and_then(|fpdt| fpdt.fbpt()) {
+        let perf = fbpt.boot_performance().expect("Missing boot record");
+        info!("Boot performance: {:?}", perf);
+    }
+
+    // Newer OVMF builds provide a memory attributes table; if present,
+    // it must only describe runtime services memory.
+    if let Some(mat) = st.find_config_table::<cfg::MemoryAttributesTable>() {
+        info!("Memory attributes table version {}", mat.version);
+        let entries = mat.entries().expect("Invalid memory attributes table");
+        for desc in entries {
+            assert!(
+                desc.ty == MemoryType::RUNTIME_SERVICES_CODE
+                    || desc.ty == MemoryType::RUNTIME_SERVICES_DATA
+            );
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 enum HostRequest {
     /// Tell the host to take a screenshot and compare against the
@@ -163,8 +227,24 @@ fn shutdown(mut st: SystemTable<Boot>) -> ! {
     // than this.
     send_request_to_host(st.boot_services(), HostRequest::TestsComplete);
 
+    // Check that the runtime services signal the virtual address change.
+    static mut VIRTUAL_ADDRESS_CHANGED: bool = false;
+    runtime_pointers::on_virtual_address_change(&st, |_| unsafe {
+        VIRTUAL_ADDRESS_CHANGED = true;
+    })
+    .expect("Failed to subscribe to the virtual address change");
+
     // Exit boot services as a proof that it works :)
-    let (st, _iter) = st.exit_boot_services();
+    let (st, memory_map) = st.exit_boot_services();
+
+    // Switch the runtime services to virtual mode. The runtime regions are
+    // identity mapped, so no page tables need to be changed.
+    let mut buffer = [MemoryDescriptor::default(); 128];
+    let mut map =
+        VirtualAddressMap::new(memory_map, &mut buffer).expect("Too many runtime memory regions");
+    let st =
+        unsafe { st.enter_virtual_mode(&mut map) }.expect("Failed to set the virtual address map");
+    assert!(unsafe { VIRTUAL_ADDRESS_CHANGED });
 
     #[cfg(target_arch = "x86_64")]
     {
@@ -0,0 +1,36 @@
+use uefi::proto::console::config::{ConsoleDevicePaths, ConsoleKind};
+use uefi::table::{Boot, SystemTable};
+
+pub fn test(st: &SystemTable<Boot>) {
+    info!("Running console configuration test");
+    let rt = st.runtime_services();
+
+    for kind in [ConsoleKind::Input, ConsoleKind::Output, ConsoleKind::Error] {
+        let device = kind.device(st).expect("missing console handle");
+        let paths = ConsoleDevicePaths::read(rt, kind).expect("failed to read console variable");
+        let available = ConsoleDevicePaths::read_available(rt, kind)
+            .expect("failed to read available console devices");
+        info!(
+            "{}: splitter={}, {} devices, {} available",
+            kind.variable_name(),
+            device.is_splitter(),
+            paths.len(),
+            available.len()
+        );
+        assert!(!paths.is_empty());
+
+        // The console variable must survive a round trip unchanged.
+        let bytes = paths.to_bytes();
+        assert_eq!(ConsoleDevicePaths::from_bytes(&bytes).unwrap(), paths);
+        paths
+            .write(rt, kind)
+            .expect("failed to write console variable");
+        assert_eq!(ConsoleDevicePaths::read(rt, kind).unwrap(), paths);
+    }
+
+    // The output goes to both the graphics and serial consoles, through the
+    // console splitter.
+    let stdout = ConsoleKind::Output.device(st).unwrap();
+    assert!(stdout.is_splitter());
+    assert!(stdout.device_path().is_none());
+}
@@ -1,6 +1,11 @@
-use crate::{send_request_to_host, HostRequest};
+use crate::{golden, send_request_to_host, HostRequest};
+use alloc::vec::Vec;
 use uefi::prelude::*;
-use uefi::proto::console::gop::{BltOp, BltPixel, FrameBuffer, GraphicsOutput, PixelFormat};
+use uefi::proto::console::gop::{
+    BltOp, BltPixel, BltRegion, FrameBuffer, GraphicsDevices, GraphicsMirror, GraphicsOutput,
+    PixelFormat, Screenshot,
+};
+use uefi::proto::media::file::{File, FileAttribute, FileMode};
 use uefi::table::boot::{BootServices, OpenProtocolAttributes, OpenProtocolParams};
 
 pub unsafe fn test(image: Handle, bt: &BootServices) {
@@ -26,22 +31,102 @@ pub unsafe fn test(image: Handle, bt: &BootServices) {
     fill_color(gop);
     draw_fb(gop);
 
-    // `draw_fb` is skipped on aarch64, so the screenshot doesn't match.
-    if cfg!(not(target_arch = "aarch64")) {
+    // `draw_fb` is skipped on aarch64 and riscv64, so the screenshot
+    // doesn't match.
+    if cfg!(not(any(target_arch = "aarch64", target_arch = "riscv64"))) {
+        // Read the screen before anything else is drawn over it.
+        let screenshot = gop.screenshot().expect("Failed to read the screen");
         send_request_to_host(bt, HostRequest::Screenshot("gop_test"));
+        check_screenshot(image, bt, &screenshot);
     }
+
+    test_devices(bt);
+}
+
+// Enumerate the graphics devices, and mirror output to all of them.
+unsafe fn test_devices(bt: &BootServices) {
+    info!("Testing graphics device enumeration");
+    let devices = GraphicsDevices::find(bt).expect("failed to find graphics devices");
+    assert!(devices.len() >= 1);
+    let mut outputs = Vec::new();
+    for device in devices {
+        info!(
+            "Graphics device {:?}: connector={:?}, native resolution={:?}",
+            device.handle(),
+            device.connector(),
+            device.native_resolution()
+        );
+        outputs.push(
+            device
+                .open_shared()
+                .expect("failed to open graphics device"),
+        );
+    }
+
+    let mut mirror = GraphicsMirror::new(outputs);
+    assert_eq!(mirror.common_resolution(), Some((1024, 768)));
+    assert_eq!(
+        mirror.set_resolution(1024, 768).unwrap(),
+        mirror.outputs().len()
+    );
+    mirror
+        .blt(BltOp::VideoFill {
+            color: BltPixel::new(100, 149, 237),
+            dest: (0, 0),
+            dims: (16, 16),
+        })
+        .expect("failed to fill mirrored outputs");
+    let mut pixel = [BltPixel::new(0, 0, 0)];
+    mirror
+        .blt(BltOp::VideoToBltBuffer {
+            buffer: &mut pixel,
+            src: (8, 8),
+            dest: BltRegion::Full,
+            dims: (1, 1),
+        })
+        .expect("failed to read mirrored output");
+    assert_eq!(
+        (pixel[0].red, pixel[0].green, pixel[0].blue),
+        (100, 149, 237)
+    );
+}
+
+/// FNV-1a hash of `gop_test.ppm` in the `screenshots` directory, encoded
+/// with `Screenshot::to_bmp`.
+const GOP_TEST_BMP_HASH: u64 = 0x4382_e126_e7fe_8704;
+
+// Compare the screenshot taken through the GOP with the golden image, and
+// save it to the boot file system.
+fn check_screenshot(image: Handle, bt: &BootServices, screenshot: &Screenshot) {
+    info!("Checking GOP screenshot");
+    golden::assert_hash(
+        "gop screenshot",
+        &screenshot.to_bmp().unwrap(),
+        GOP_TEST_BMP_HASH,
+    );
+
+    let mut fs = bt
+        .get_image_file_system(image)
+        .expect("Failed to open the boot file system");
+    let mut root = fs.open_volume().expect("Failed to open the root directory");
+    let mut file = root
+        .open(
+            cstr16!("gop_test.bmp"),
+            FileMode::CreateReadWrite,
+            FileAttribute::empty(),
+        )
+        .expect("Failed to create gop_test.bmp")
+        .into_regular_file()
+        .expect("gop_test.bmp is not a regular file");
+    screenshot
+        .write_bmp(&mut file)
+        .expect("Failed to write gop_test.bmp");
 }
 
 // Set a larger graphics mode.
 fn set_graphics_mode(gop: &mut GraphicsOutput) {
     // We know for sure QEMU has a 1024x768 mode.
-    let mode = gop
-        .modes()
-        .find(|mode| {
-            let info = mode.info();
-            info.resolution() == (1024, 768)
-        })
-        .unwrap();
+    let mode = gop.find_mode(1024, 768).unwrap();
 
     gop.set_mode(&mode).expect("Failed to set graphics mode");
 }
@@ -60,9 +145,9 @@ fn fill_color(gop: &mut GraphicsOutput) {
 
 // Draw directly to the frame buffer.
 fn draw_fb(gop: &mut GraphicsOutput) {
-    // The `virtio-gpu-pci` graphics device we use on aarch64 doesn't
-    // support `PixelFormat::BltOnly`.
-    if cfg!(target_arch = "aarch64") {
+    // The `virtio-gpu-pci` graphics device we use on aarch64 and riscv64
+    // doesn't support `PixelFormat::BltOnly`.
+    if cfg!(any(target_arch = "aarch64", target_arch = "riscv64")) {
         return;
     }
 
@@ -1,3 +1,5 @@
+use uefi::proto::console::absolute_pointer::AbsolutePointer;
+use uefi::proto::console::events::InputEvents;
 use uefi::proto::console::pointer::Pointer;
 use uefi::table::boot::BootServices;
 
@@ -23,4 +25,34 @@ pub fn test(bt: &BootServices) {
     } else {
         info!("Pointer state has not changed since the last query");
     }
+
+    let mut events = InputEvents::new().with_pointer(&mut pointer);
+    let event = events.poll().expect("Failed to poll input events");
+    info!("Pending input event: {:?}", event);
+
+    test_absolute_pointer(bt);
+}
+
+fn test_absolute_pointer(bt: &BootServices) {
+    // QEMU only provides an absolute pointer with a USB tablet device,
+    // which the test VM doesn't have, so the protocol is optional.
+    let handle = match bt.get_handle_for_protocol::<AbsolutePointer>() {
+        Ok(handle) => handle,
+        Err(_) => {
+            info!("Absolute pointer protocol is not supported");
+            return;
+        }
+    };
+    let mut pointer = bt
+        .open_protocol_exclusive::<AbsolutePointer>(handle)
+        .expect("failed to open absolute pointer protocol");
+
+    pointer
+        .reset(false)
+        .expect("Failed to reset absolute pointer device");
+    info!("Absolute pointer mode: {:?}", pointer.mode());
+    let state = pointer
+        .read_state()
+        .expect("Failed to retrieve absolute pointer state");
+    info!("Absolute pointer state: {:?}", state);
 }
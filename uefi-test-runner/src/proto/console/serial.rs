@@ -1,13 +1,17 @@
-use uefi::proto::console::serial::{ControlBits, Serial};
+use crate::golden;
+use uefi::proto::console::serial::{ControlBits, Serial, SerialPort, SerialPorts};
 use uefi::table::boot::BootServices;
-use uefi::{Result, ResultExt, Status};
+use uefi::{Result, ResultExt};
+
+// Keep this message short, we need it to fit in the FIFO.
+const OUTPUT: &[u8] = b"Hello world!";
 
 // For the duration of this function, the serial device is opened in
 // exclusive mode. That means logs will not work, which means we should
 // avoid panicking here because the panic log would be hidden. Instead,
-// return a result that gets asserted in `test` *after* the logger has
-// been restored.
-fn serial_test_helper(serial: &mut Serial) -> Result {
+// return the bytes read back, which get checked in `test` *after* the
+// logger has been restored.
+fn serial_test_helper(serial: &mut Serial) -> Result<[u8; OUTPUT.len()]> {
     let old_ctrl_bits = serial.get_control_bits()?;
     let mut ctrl_bits = ControlBits::empty();
 
@@ -20,40 +24,53 @@ fn serial_test_helper(serial: &mut Serial) -> Result {
 
     serial.set_control_bits(ctrl_bits)?;
 
-    // Keep this message short, we need it to fit in the FIFO.
-    const OUTPUT: &[u8] = b"Hello world!";
-    const MSG_LEN: usize = OUTPUT.len();
-
     serial.write(OUTPUT).discard_errdata()?;
 
-    let mut input = [0u8; MSG_LEN];
+    let mut input = [0u8; OUTPUT.len()];
     serial.read(&mut input).discard_errdata()?;
 
     // Clean up after ourselves
     serial.reset()?;
     serial.set_control_bits(old_ctrl_bits & ControlBits::SETTABLE)?;
 
-    if OUTPUT == input {
-        Ok(())
-    } else {
-        Err(Status::ABORTED.into())
-    }
+    Ok(input)
 }
 
 pub unsafe fn test(bt: &BootServices) {
-    // The serial device under aarch64 doesn't support the software
-    // loopback feature needed for this test.
-    if cfg!(target_arch = "aarch64") {
-        return;
-    }
-
     info!("Running serial protocol test");
-    let handle = bt
-        .get_handle_for_protocol::<Serial>()
-        .expect("missing Serial protocol");
+    let ports = SerialPorts::find(bt).expect("missing Serial protocol");
+
+    // QEMU is configured with two serial ports on x86.
+    let min_ports = if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+        2
+    } else {
+        1
+    };
+    assert!(
+        ports.len() >= min_ports,
+        "expected at least {min_ports} serial ports, found {}",
+        ports.len()
+    );
+
+    for port in ports {
+        info!(
+            "Serial port {:?}: id={:?}, pci={:?}, acpi_uid={:?}",
+            port.handle(),
+            port.id().map(|id| id.0),
+            port.pci_location(),
+            port.acpi_uid()
+        );
+        if let Some(id) = port.id() {
+            let found = SerialPorts::find_by_id(bt, id).expect("failed to find serial port by ID");
+            assert_eq!(found.handle().as_ptr(), port.handle().as_ptr());
+        }
+        test_port(bt, &port);
+    }
+}
 
-    let mut serial = bt
-        .open_protocol_exclusive::<Serial>(handle)
+unsafe fn test_port(bt: &BootServices, port: &SerialPort) {
+    let mut serial = port
+        .open_exclusive()
         .expect("failed to open serial protocol");
 
     // Send the request, but don't check the result yet so that first
@@ -66,9 +83,10 @@ pub unsafe fn test(bt: &BootServices) {
     // device, which was broken when we opened the protocol in exclusive
     // mode above.
     drop(serial);
-    let _ = bt.connect_controller(handle, None, None, true);
+    let _ = bt.connect_controller(port.handle(), None, None, true);
 
-    if let Err(err) = res {
-        panic!("serial test failed: {:?}", err.status());
+    match res {
+        Ok(input) => golden::assert_bytes("serial loopback", &input, OUTPUT),
+        Err(err) => panic!("serial test failed: {:?}", err.status()),
     }
 }
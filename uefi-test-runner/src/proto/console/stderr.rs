@@ -0,0 +1,28 @@
+use core::fmt::Write;
+use uefi::logger::Logger;
+use uefi::table::{Boot, SystemTable};
+use uefi_services::eprintln;
+
+pub fn test(st: &mut SystemTable<Boot>) {
+    info!("Running standard error test");
+
+    let stderr = st.stderr();
+    stderr.current_mode().expect("failed to get stderr mode");
+    writeln!(stderr, "Hello from stderr").expect("failed to write to stderr");
+    eprintln!("Hello from eprintln, {}", 42);
+
+    // Error messages are written to the error output, others to the
+    // standard output.
+    let mut logger = unsafe { Logger::new(st.stdout()) };
+    unsafe { logger.set_error_output(st.stderr()) };
+    for level in [log::Level::Info, log::Level::Error] {
+        log::Log::log(
+            &logger,
+            &log::Record::builder()
+                .level(level)
+                .args(format_args!("{level} message from a stderr logger"))
+                .build(),
+        );
+    }
+    logger.disable();
+}
@@ -0,0 +1,18 @@
+use core::time::Duration;
+use uefi::prelude::*;
+use uefi::proto::console::text::Input;
+
+pub fn test(stdin: &mut Input, bt: &BootServices) {
+    info!("Running text input protocol test");
+
+    // Nothing is typed while the tests run, so both waits time out.
+    stdin.reset(false).unwrap();
+    assert_eq!(
+        stdin.wait_for_key_with_timeout(bt, Duration::ZERO),
+        Ok(None)
+    );
+    assert_eq!(
+        stdin.wait_for_key_with_timeout(bt, Duration::from_millis(20)),
+        Ok(None)
+    );
+}
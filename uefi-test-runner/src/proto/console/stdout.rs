@@ -31,10 +31,15 @@ fn get_current_mode(stdout: &mut Output) {
 
 // Switch to the maximum supported text mode.
 fn change_text_mode(stdout: &mut Output) {
-    let best_mode = stdout.modes().last().unwrap();
+    let best_mode = stdout.best_mode().unwrap();
+    assert_eq!(
+        stdout.find_mode(best_mode.columns(), best_mode.rows()),
+        Some(best_mode)
+    );
     stdout
-        .set_mode(best_mode)
+        .set_mode_by_size(best_mode.columns(), best_mode.rows())
         .expect("Failed to change text mode");
+    assert_eq!(stdout.current_mode().unwrap(), Some(best_mode));
 }
 
 // Set a new color, and paint the background with it.
@@ -43,18 +48,29 @@ fn change_color(stdout: &mut Output) {
         .set_color(Color::White, Color::Blue)
         .expect("Failed to change console color");
     stdout.clear().expect("Failed to clear screen");
+
+    // Temporarily switch colors, then check that they were restored.
+    let saved = stdout
+        .push_color(Color::Yellow, Color::Black)
+        .expect("Failed to push console color");
+    stdout
+        .with_colors(Color::LightRed, Color::Black, |stdout| {
+            stdout.output_string(cstr16!("nested colors\r\n"))
+        })
+        .expect("Failed to write with temporary colors");
+    stdout
+        .pop_color(saved)
+        .expect("Failed to pop console color");
 }
 
 // Print a text centered on screen.
 fn center_text(stdout: &mut Output) {
     // Move the cursor.
     // This will make this `info!` line below be (somewhat) centered.
-    stdout
-        .enable_cursor(true)
-        .unwrap_or_else(|e| match e.status() {
-            Status::UNSUPPORTED => info!("Cursor visibility control unavailable"),
-            _ => panic!("Failed to show cursor"),
-        });
+    stdout.show_cursor().unwrap_or_else(|e| match e.status() {
+        Status::UNSUPPORTED => info!("Cursor visibility control unavailable"),
+        _ => panic!("Failed to show cursor"),
+    });
     stdout
         .set_cursor_position(24, 0)
         .expect("Failed to move cursor");
@@ -1,5 +1,5 @@
 use core::ffi::c_void;
-use uefi::proto::debug::{DebugSupport, ExceptionType, ProcessorArch, SystemContext};
+use uefi::proto::debug::{DebugPort, DebugSupport, ExceptionType, ProcessorArch, SystemContext};
 use uefi::table::boot::BootServices;
 
 pub fn test(bt: &BootServices) {
@@ -77,6 +77,25 @@ pub fn test(bt: &BootServices) {
                         .register_exception_callback(0, None, ExceptionType::EXCEPT_AARCH64_SERROR)
                         .expect("Error while deregistering exception callback");
                 },
+                #[cfg(target_arch = "riscv64")]
+                ProcessorArch::RISCV_64 => unsafe {
+                    info!("Registering exception callback");
+                    debug_support
+                        .register_exception_callback(
+                            0,
+                            Some(exception_callback),
+                            ExceptionType::EXCEPT_RISCV_BREAKPOINT,
+                        )
+                        .expect("Error while registering exception callback");
+                    info!("Deregistering exception callback");
+                    debug_support
+                        .register_exception_callback(
+                            0,
+                            None,
+                            ExceptionType::EXCEPT_RISCV_BREAKPOINT,
+                        )
+                        .expect("Error while deregistering exception callback");
+                },
                 // if we reach this, we're running on an arch that `cargo xtask run` doesn't support
                 // TODO: Add match arms as we support testing on more archs
                 _ => unreachable!(),
@@ -85,6 +104,26 @@ pub fn test(bt: &BootServices) {
             test_invalidate_instruction_cache(&mut debug_support);
         }
     }
+
+    test_debug_port(bt);
+}
+
+fn test_debug_port(bt: &BootServices) {
+    // OVMF only provides a debug port if built with a source-level debug
+    // agent, so the protocol is optional.
+    let handle = match bt.get_handle_for_protocol::<DebugPort>() {
+        Ok(handle) => handle,
+        Err(_) => {
+            info!("No DebugPort protocol found, skipping test");
+            return;
+        }
+    };
+
+    info!("Running DebugPort protocol test");
+    let debug_port = bt
+        .open_protocol_exclusive::<DebugPort>(handle)
+        .expect("failed to open DebugPort protocol");
+    debug_port.poll().expect("failed to poll debug port");
 }
 
 fn test_invalidate_instruction_cache(debug_support: &mut DebugSupport) {
@@ -0,0 +1,32 @@
+use uefi::decompress::{self, Algorithm};
+use uefi::proto::decompress::Decompress;
+use uefi::table::boot::BootServices;
+
+/// "AAAA" compressed with the EFI algorithm, as a single block in which
+/// only one character is used.
+const COMPRESSED: [u8; 15] = [
+    7, 0, 0, 0, 4, 0, 0, 0, 0x00, 0x04, 0x00, 0x00, 0x04, 0x10, 0x00,
+];
+
+pub fn test(bt: &BootServices) {
+    info!("Running decompress protocol test");
+
+    let handle = bt
+        .get_handle_for_protocol::<Decompress>()
+        .expect("No Decompress handles");
+    let decompress = bt
+        .open_protocol_exclusive::<Decompress>(handle)
+        .expect("Failed to open Decompress protocol");
+
+    let info = decompress.get_info(&COMPRESSED).unwrap();
+    assert_eq!(info.destination_size, 4);
+
+    let data = decompress.decompress_to_vec(&COMPRESSED).unwrap();
+    assert_eq!(data, b"AAAA");
+
+    // The software implementation must agree with the firmware.
+    assert_eq!(
+        decompress::decompress_to_vec(&COMPRESSED, Algorithm::Efi).unwrap(),
+        data
+    );
+}
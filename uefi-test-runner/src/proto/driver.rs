@@ -1,5 +1,8 @@
 use uefi::prelude::*;
-use uefi::proto::driver::{ComponentName, ComponentName2, LanguageError, LanguageIter};
+use uefi::proto::driver::{
+    BusSpecificDriverOverride, ComponentName, ComponentName2, DriverHealth, LanguageError,
+    LanguageIter,
+};
 use uefi::table::boot::{BootServices, ScopedProtocol, SearchType};
 use uefi::{CStr16, Result};
 
@@ -145,4 +148,42 @@ pub fn test(boot_services: &BootServices) {
     test_component_name::<ScopedProtocol<ComponentName1>>(boot_services, "eng");
     test_component_name::<ScopedProtocol<ComponentName2>>(boot_services, "en");
     test_component_name::<ComponentName>(boot_services, "en");
+
+    test_driver_health(boot_services);
+    test_bus_specific_driver_override(boot_services);
+}
+
+fn test_driver_health(boot_services: &BootServices) {
+    info!("Running driver health test");
+
+    // Not all firmware has drivers that report their health.
+    let handles = boot_services
+        .find_handles::<DriverHealth>()
+        .unwrap_or_default();
+    for handle in handles {
+        let driver_health = boot_services
+            .open_protocol_exclusive::<DriverHealth>(handle)
+            .expect("failed to open DriverHealth protocol");
+        let report = driver_health
+            .health_status(boot_services, None, None)
+            .expect("failed to get driver health");
+        info!("Driver health: {:?}", report);
+    }
+}
+
+fn test_bus_specific_driver_override(boot_services: &BootServices) {
+    info!("Running bus specific driver override test");
+
+    // The protocol is only installed on controllers with an option ROM.
+    let handles = boot_services
+        .find_handles::<BusSpecificDriverOverride>()
+        .unwrap_or_default();
+    for handle in handles {
+        let driver_override = boot_services
+            .open_protocol_exclusive::<BusSpecificDriverOverride>(handle)
+            .expect("failed to open BusSpecificDriverOverride protocol");
+        for driver in driver_override.drivers() {
+            info!("Override driver: {:?}", driver);
+        }
+    }
 }
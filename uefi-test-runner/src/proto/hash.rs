@@ -0,0 +1,54 @@
+use crate::registry::TestContext;
+use uefi::proto::hash::{Hash2, Hash2Algorithm};
+use uefi::proto::service_binding::ServiceBindingProtocol;
+
+/// SHA-256 digest of `abc`.
+const SHA256_ABC: [u8; 32] = [
+    0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+    0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+];
+
+pub fn test(cx: &TestContext) {
+    let bt = cx.bt();
+    info!("Running hash2 protocol test");
+
+    let handles = bt
+        .find_handles::<ServiceBindingProtocol<Hash2>>()
+        .unwrap_or_default();
+    if handles.is_empty() {
+        cx.skip("no hash2 service binding");
+        return;
+    }
+
+    for handle in handles {
+        let mut binding = bt
+            .open_protocol_exclusive::<ServiceBindingProtocol<Hash2>>(handle)
+            .expect("Failed to open hash2 service binding");
+        let child = binding
+            .create_child()
+            .expect("Failed to create hash2 child");
+
+        {
+            let mut hash = bt
+                .open_protocol_exclusive::<Hash2>(child)
+                .expect("Failed to open hash2");
+
+            assert_eq!(hash.hash_size(Hash2Algorithm::SHA256).unwrap(), 32);
+            let digest = hash
+                .hash(Hash2Algorithm::SHA256, b"abc")
+                .expect("Failed to hash");
+            assert_eq!(digest.as_bytes(), SHA256_ABC);
+
+            let mut ctx = hash
+                .start(Hash2Algorithm::SHA256)
+                .expect("Failed to start hash");
+            ctx.update(b"a").unwrap();
+            ctx.update(b"bc").unwrap();
+            assert_eq!(ctx.finish().unwrap().as_bytes(), SHA256_ABC);
+        }
+
+        binding
+            .destroy_child(child)
+            .expect("Failed to destroy hash2 child");
+    }
+}
@@ -0,0 +1,36 @@
+use uefi::proto::legacy_bios::{Ia32RegisterSet, LegacyBios};
+use uefi::table::boot::BootServices;
+
+pub fn test(bt: &BootServices) {
+    info!("Running legacy BIOS protocol test");
+
+    // OVMF is usually built without CSM support, so the protocol is
+    // optional.
+    let handle = match bt.get_handle_for_protocol::<LegacyBios>() {
+        Ok(handle) => handle,
+        Err(_) => {
+            info!("Legacy BIOS protocol is not supported");
+            return;
+        }
+    };
+    let mut legacy_bios = bt
+        .open_protocol_exclusive::<LegacyBios>(handle)
+        .expect("Failed to open legacy BIOS protocol");
+
+    let table = legacy_bios.bbs_table().expect("Failed to get BBS table");
+    for entry in table.iter().filter(|entry| !entry.is_ignored()) {
+        info!(
+            "BBS entry: type {:?}, priority {:#x}, drive {:#x}",
+            entry.device_type(),
+            entry.boot_priority(),
+            entry.assigned_drive_number()
+        );
+    }
+
+    // INT 12h: get the size of the conventional memory in KiB.
+    let mut regs = Ia32RegisterSet::default();
+    unsafe { legacy_bios.int86(0x12, &mut regs) }.expect("INT 12h failed");
+    let size = regs.eax & 0xffff;
+    info!("Conventional memory size: {size} KiB");
+    assert!(size > 0 && size <= 640);
+}
@@ -1,3 +1,4 @@
+use uefi::pe::SectionFlags;
 use uefi::prelude::*;
 use uefi::proto::loaded_image::LoadedImage;
 use uefi::table::boot::BootServices;
@@ -17,4 +18,23 @@ pub fn test(image: Handle, bt: &BootServices) {
         "LoadedImage image address: {:?}, image size: {} bytes",
         image_base, image_size
     );
+
+    let pe = loaded_image
+        .pe_image()
+        .expect("Failed to parse the PE headers of the image");
+    assert_eq!(pe.base(), image_base.cast());
+    let entry_point = pe.entry_point().expect("Entry point is outside the image");
+    let text = pe
+        .section_containing(pe.entry_point_rva())
+        .expect("Entry point is not in a section");
+    assert!(text.flags().contains(SectionFlags::EXECUTE));
+    info!(
+        "Entry point {:?} is in section {:?}",
+        entry_point,
+        text.name()
+    );
+
+    // This function is part of the image.
+    let rva = pe.address_to_rva(test as *const u8).unwrap();
+    assert_eq!(pe.rva_to_address(rva), Some(test as *const u8));
 }
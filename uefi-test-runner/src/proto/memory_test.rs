@@ -0,0 +1,29 @@
+use crate::registry::TestContext;
+use core::ops::ControlFlow;
+use uefi::proto::memory_test::{GenericMemoryTest, MemoryTestLevel};
+
+pub fn test(cx: &TestContext) {
+    let bt = cx.bt();
+    info!("Running generic memory test protocol test");
+
+    let handle = match cx.require_protocol::<GenericMemoryTest>() {
+        Some(handle) => handle,
+        None => return,
+    };
+    let mut memory_test = bt
+        .open_protocol_exclusive::<GenericMemoryTest>(handle)
+        .expect("Failed to open generic memory test protocol");
+
+    // The memory was already tested during boot, so the test should end
+    // immediately.
+    let mut calls = 0;
+    let last = memory_test
+        .run(MemoryTestLevel::QUICK, |progress| {
+            assert!(progress.tested <= progress.total);
+            calls += 1;
+            ControlFlow::Continue(())
+        })
+        .expect("Failed to run memory test");
+    info!("Memory test made {} steps: {:?}", calls, last);
+    assert!(!last.error);
+}
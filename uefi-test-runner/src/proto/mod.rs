@@ -3,34 +3,95 @@ use uefi::prelude::*;
 use uefi::proto::loaded_image::LoadedImage;
 use uefi::{proto, Identify};
 
-pub fn test(image: Handle, st: &mut SystemTable<Boot>) {
-    info!("Testing various protocols");
-
-    console::test(image, st);
-
-    let bt = st.boot_services();
-    find_protocol(bt);
-    test_protocols_per_handle(image, bt);
-
-    debug::test(bt);
-    device_path::test(image, bt);
-    driver::test(bt);
-    loaded_image::test(image, bt);
-    media::test(bt);
-    network::test(bt);
-    pi::test(bt);
-    rng::test(bt);
-    string::test(bt);
+use crate::registry::Test;
 
+pub const TESTS: &[Test] = &[
+    Test::new("proto/console/stdout", |cx| {
+        console::stdout::test(cx.st.stdout())
+    }),
+    Test::new("proto/console/stdin", |cx| {
+        let st = unsafe { cx.st.unsafe_clone() };
+        console::stdin::test(cx.st.stdin(), st.boot_services())
+    }),
+    Test::new("proto/console/stderr", |cx| console::stderr::test(cx.st)),
+    Test::new("proto/console/config", |cx| console::config::test(&cx.st)),
+    // The serial device under aarch64 doesn't support the software
+    // loopback feature needed for this test.
+    Test::new("proto/console/serial", |cx| unsafe {
+        console::serial::test(cx.bt())
+    })
+    .skip_if(
+        cfg!(target_arch = "aarch64"),
+        "no serial loopback support on aarch64",
+    ),
+    Test::new("proto/console/gop", |cx| unsafe {
+        console::gop::test(cx.image, cx.bt())
+    }),
+    Test::new("proto/console/pointer", |cx| {
+        console::pointer::test(cx.bt())
+    }),
+    Test::new("proto/find_protocol", |cx| find_protocol(cx.bt())),
+    Test::new("proto/protocols_per_handle", |cx| {
+        test_protocols_per_handle(cx.image, cx.bt())
+    }),
+    Test::new("proto/debug", |cx| debug::test(cx.bt())),
+    Test::new("proto/decompress", |cx| decompress::test(cx.bt())),
+    Test::new("proto/device_path", |cx| {
+        device_path::test(cx.image, cx.bt())
+    }),
+    Test::new("proto/driver", |cx| driver::test(cx.bt())),
+    Test::new("proto/hash", |cx| hash::test(cx)),
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    Test::new("proto/legacy_bios", |cx| legacy_bios::test(cx.bt())),
+    Test::new("proto/loaded_image", |cx| {
+        loaded_image::test(cx.image, cx.bt())
+    }),
+    Test::new("proto/media", |cx| media::test(cx.bt())),
+    Test::new("proto/memory_test", |cx| memory_test::test(cx)),
+    Test::new("proto/network/dhcp", |cx| network::dhcp::test(cx.bt())),
+    Test::new("proto/network/iscsi", |cx| network::iscsi::test(cx.st)),
+    Test::new("proto/network/mnp", |cx| network::mnp::test(cx.bt())),
+    Test::new("proto/network/mtftp", |cx| network::mtftp::test(cx.bt())),
+    Test::new("proto/network/nii", |cx| network::nii::test(cx.bt())),
+    Test::new("proto/network/pxe", |cx| network::pxe::test(cx.bt())),
+    Test::new("proto/network/snp", |cx| network::snp::test(cx.bt())),
+    Test::new("proto/network/vlan", |cx| network::vlan::test(cx.bt())),
+    // The multi-processor test only works with KVM, which is not
+    // available in CI or on Windows.
+    Test::new("proto/pci", |cx| pci::test(cx.bt())),
+    Test::new("proto/pi/mp", |cx| pi::mp::test(cx.bt())).skip_if(
+        cfg!(not(feature = "multi_processor")),
+        "multi_processor feature not enabled",
+    ),
+    Test::new("proto/ram_disk", |cx| ram_disk::test(cx)),
+    Test::new("proto/riscv", |cx| riscv::test(cx.bt())).skip_if(
+        cfg!(not(target_arch = "riscv64")),
+        "only available on riscv64",
+    ),
+    Test::new("proto/rng", |cx| rng::test(cx.bt())),
+    Test::new("proto/string/unicode_collation", |cx| {
+        string::unicode_collation::test(cx.bt())
+    }),
     #[cfg(any(
-        target_arch = "i386",
+        target_arch = "x86",
         target_arch = "x86_64",
         target_arch = "arm",
         target_arch = "aarch64"
     ))]
-    shim::test(bt);
-    tcg::test(bt);
-}
+    Test::new("proto/shim", |cx| shim::test(cx.bt())),
+    Test::new("proto/shell/dynamic_command", |cx| {
+        shell::test_dynamic_command(cx.bt())
+    }),
+    Test::new("proto/tcg/v1", |cx| tcg::test_tcg_v1(cx.bt()))
+        .skip_if(cfg!(not(feature = "tpm_v1")), "tpm_v1 feature not enabled"),
+    Test::new("proto/tcg/v2", |cx| tcg::test_tcg_v2(cx.bt()))
+        .skip_if(cfg!(not(feature = "tpm_v2")), "tpm_v2 feature not enabled"),
+    Test::new("proto/usb", |cx| usb::test(cx.bt())),
+    Test::new("proto/variable_policy", |cx| variable_policy::test(cx)),
+    // Must run after the media test, which expects exactly two file
+    // systems. The virtual file system stays installed.
+    Test::new("proto/virtual_fs", |cx| virtual_fs::test(cx.bt())),
+];
 
 fn find_protocol(bt: &BootServices) {
     type SearchedProtocol<'boot> = proto::console::text::Output<'boot>;
@@ -54,19 +115,36 @@ fn test_protocols_per_handle(image: Handle, bt: &BootServices) {
 
     // Check that one of the image's protocols is `LoadedImage`.
     assert!(pph.iter().any(|guid| **guid == LoadedImage::GUID));
+    drop(pph);
+
+    let dump = bt.dump_handle(image).expect("Failed to dump image handle");
+    info!("{}", dump);
+    let loaded_image = dump
+        .protocol::<LoadedImage>()
+        .expect("LoadedImage is missing from the dump");
+    assert_eq!(loaded_image.name, Some("EFI_LOADED_IMAGE_PROTOCOL"));
 }
 
 mod console;
 mod debug;
+mod decompress;
 mod device_path;
 mod driver;
+mod hash;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod legacy_bios;
 mod loaded_image;
 mod media;
+mod memory_test;
 mod network;
+mod pci;
 mod pi;
+mod ram_disk;
+mod riscv;
 mod rng;
+mod shell;
 #[cfg(any(
-    target_arch = "i386",
+    target_arch = "x86",
     target_arch = "x86_64",
     target_arch = "arm",
     target_arch = "aarch64"
@@ -74,3 +152,6 @@ mod rng;
 mod shim;
 mod string;
 mod tcg;
+mod usb;
+mod variable_policy;
+mod virtual_fs;
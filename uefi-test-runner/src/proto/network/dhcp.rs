@@ -0,0 +1,61 @@
+use uefi::prelude::BootServices;
+use uefi::proto::network::dhcp4::{
+    Dhcp4, Dhcp4ConfigData, Dhcp4Option, Dhcp4OptionBuf, Dhcp4State,
+};
+use uefi::proto::service_binding::ServiceBindingProtocol;
+
+pub fn test(bt: &BootServices) {
+    info!("Testing the DHCPv4 protocol");
+
+    let handles = bt
+        .find_handles::<ServiceBindingProtocol<Dhcp4>>()
+        .unwrap_or_default();
+    for handle in handles {
+        let mut binding = bt
+            .open_protocol_exclusive::<ServiceBindingProtocol<Dhcp4>>(handle)
+            .expect("Failed to open DHCPv4 service binding");
+        let child = binding
+            .create_child()
+            .expect("Failed to create DHCPv4 child");
+
+        {
+            let mut dhcp = bt
+                .open_protocol_exclusive::<Dhcp4>(child)
+                .expect("Failed to open DHCPv4");
+            assert_eq!(dhcp.mode_data().unwrap().state, Dhcp4State::STOPPED);
+
+            let request = Dhcp4OptionBuf::new(&Dhcp4Option::ParameterRequestList(&[
+                Dhcp4Option::SUBNET_MASK,
+                Dhcp4Option::ROUTER,
+                Dhcp4Option::BOOT_FILE_NAME,
+            ]))
+            .unwrap();
+            let options = [&request];
+            let config_data = Dhcp4ConfigData::new().with_options(&options).unwrap();
+            dhcp.configure(Some(&config_data))
+                .expect("Failed to configure DHCPv4");
+            assert_eq!(dhcp.mode_data().unwrap().state, Dhcp4State::INIT);
+
+            info!("Acquiring a DHCPv4 lease");
+            dhcp.start(None).expect("Failed to acquire a lease");
+            let mode_data = dhcp.mode_data().unwrap();
+            assert_eq!(mode_data.state, Dhcp4State::BOUND);
+            assert_eq!(mode_data.router_address, [192, 168, 17, 2]);
+            assert_eq!(mode_data.subnet_mask, [255, 255, 255, 0]);
+
+            let reply = mode_data.reply_packet().expect("No DHCPv4 reply");
+            assert_eq!(
+                reply.find_option(Dhcp4Option::ROUTER),
+                Some(Dhcp4Option::Router(&[192, 168, 17, 2]))
+            );
+            info!("DHCPv4 boot file name: {:?}", reply.boot_file_name());
+
+            dhcp.release().expect("Failed to release the lease");
+            dhcp.configure(None).expect("Failed to reset DHCPv4");
+        }
+
+        binding
+            .destroy_child(child)
+            .expect("Failed to destroy DHCPv4 child");
+    }
+}
@@ -0,0 +1,29 @@
+use uefi::proto::network::iscsi::{Ibft, IscsiInitiatorName};
+use uefi::table::{Boot, SystemTable};
+
+pub fn test(st: &SystemTable<Boot>) {
+    info!("Testing iSCSI");
+
+    // The test VM doesn't boot from iSCSI.
+    assert!(Ibft::find(st).is_none());
+
+    // OVMF is usually built without the iSCSI driver, so the protocol is
+    // optional.
+    let bt = st.boot_services();
+    let handle = match bt.get_handle_for_protocol::<IscsiInitiatorName>() {
+        Ok(handle) => handle,
+        Err(_) => {
+            info!("iSCSI initiator name protocol is not supported");
+            return;
+        }
+    };
+    let protocol = bt
+        .open_protocol_exclusive::<IscsiInitiatorName>(handle)
+        .expect("Failed to open iSCSI initiator name protocol");
+
+    let mut buffer = [0; IscsiInitiatorName::MAX_NAME_SIZE];
+    match protocol.get(&mut buffer) {
+        Ok(name) => info!("iSCSI initiator name: {name}"),
+        Err(err) => info!("iSCSI initiator name is not available: {:?}", err.status()),
+    }
+}
@@ -0,0 +1,88 @@
+use uefi::prelude::BootServices;
+use uefi::proto::network::arp::{Arp, ArpConfigData};
+use uefi::proto::network::mnp::{ManagedNetwork, ManagedNetworkConfigData};
+use uefi::proto::service_binding::ServiceBindingProtocol;
+use uefi::Status;
+
+/// Station and gateway addresses of the QEMU user network.
+const STATION_ADDRESS: [u8; 4] = [192, 168, 17, 15];
+const GATEWAY_ADDRESS: [u8; 4] = [192, 168, 17, 2];
+
+pub fn test(bt: &BootServices) {
+    info!("Testing the managed network protocol");
+
+    let handles = bt
+        .find_handles::<ServiceBindingProtocol<ManagedNetwork>>()
+        .unwrap_or_default();
+    for handle in handles {
+        let mut binding = bt
+            .open_protocol_exclusive::<ServiceBindingProtocol<ManagedNetwork>>(handle)
+            .expect("Failed to open MNP service binding");
+        let child = binding.create_child().expect("Failed to create MNP child");
+
+        {
+            let mut mnp = bt
+                .open_protocol_exclusive::<ManagedNetwork>(child)
+                .expect("Failed to open MNP");
+
+            let mode = mnp.snp_mode_data().expect("Failed to get SNP mode");
+            assert_eq!(mode.hw_address_size, 6);
+
+            let config_data = ManagedNetworkConfigData {
+                protocol_type_filter: 0x0806,
+                enable_unicast_receive: true,
+                enable_broadcast_receive: true,
+                ..Default::default()
+            };
+            mnp.configure(Some(&config_data))
+                .expect("Failed to configure MNP");
+            assert_eq!(mnp.config_data().unwrap().protocol_type_filter, 0x0806);
+
+            // Nothing is queued.
+            assert_eq!(mnp.cancel(None).unwrap_err().status(), Status::NOT_FOUND);
+            mnp.configure(None).expect("Failed to reset MNP");
+        }
+
+        binding
+            .destroy_child(child)
+            .expect("Failed to destroy MNP child");
+    }
+
+    info!("Testing the ARP protocol");
+
+    let handles = bt
+        .find_handles::<ServiceBindingProtocol<Arp>>()
+        .unwrap_or_default();
+    for handle in handles {
+        let mut binding = bt
+            .open_protocol_exclusive::<ServiceBindingProtocol<Arp>>(handle)
+            .expect("Failed to open ARP service binding");
+        let child = binding.create_child().expect("Failed to create ARP child");
+
+        {
+            let mut arp = bt
+                .open_protocol_exclusive::<Arp>(child)
+                .expect("Failed to open ARP");
+            arp.configure(Some(&ArpConfigData::new_ipv4(&STATION_ADDRESS)))
+                .expect("Failed to configure ARP");
+
+            let gateway = arp
+                .resolve(bt, &GATEWAY_ADDRESS)
+                .expect("Failed to resolve the gateway address");
+            info!("Gateway hardware address: {:x?}", &gateway.0[..6]);
+
+            let entries = arp
+                .find(bt, true, Some(&GATEWAY_ADDRESS), false)
+                .expect("Failed to find the gateway in the ARP cache");
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries.iter().next().unwrap().hw_address(), &gateway.0[..6]);
+            drop(entries);
+
+            arp.configure(None).expect("Failed to reset ARP");
+        }
+
+        binding
+            .destroy_child(child)
+            .expect("Failed to destroy ARP child");
+    }
+}
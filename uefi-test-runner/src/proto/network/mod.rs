@@ -1,11 +1,8 @@
-use uefi::prelude::*;
-
-pub fn test(bt: &BootServices) {
-    info!("Testing Network protocols");
-
-    pxe::test(bt);
-    snp::test(bt);
-}
-
-mod pxe;
-mod snp;
+pub mod dhcp;
+pub mod iscsi;
+pub mod mnp;
+pub mod mtftp;
+pub mod nii;
+pub mod pxe;
+pub mod snp;
+pub mod vlan;
@@ -0,0 +1,89 @@
+use uefi::prelude::BootServices;
+use uefi::proto::network::mtftp::v4::{Mtftp4, Mtftp4ConfigData};
+use uefi::proto::network::mtftp::MtftpPacket;
+use uefi::proto::service_binding::ServiceBindingProtocol;
+use uefi::{CStr8, Status};
+
+/// Address of the TFTP server of the QEMU user network.
+const SERVER_ADDRESS: [u8; 4] = [192, 168, 17, 2];
+
+pub fn test(bt: &BootServices) {
+    info!("Testing the MTFTPv4 protocol");
+
+    let handles = bt
+        .find_handles::<ServiceBindingProtocol<Mtftp4>>()
+        .unwrap_or_default();
+    for handle in handles {
+        let mut binding = bt
+            .open_protocol_exclusive::<ServiceBindingProtocol<Mtftp4>>(handle)
+            .expect("Failed to open MTFTPv4 service binding");
+        let child = binding
+            .create_child()
+            .expect("Failed to create MTFTPv4 child");
+
+        {
+            let mut mtftp = bt
+                .open_protocol_exclusive::<Mtftp4>(child)
+                .expect("Failed to open MTFTPv4");
+
+            let config_data = Mtftp4ConfigData {
+                use_default_setting: false,
+                station_ip: [192, 168, 17, 15],
+                subnet_mask: [255, 255, 255, 0],
+                ..Mtftp4ConfigData::new(SERVER_ADDRESS)
+            };
+            mtftp
+                .configure(Some(&config_data))
+                .expect("Failed to configure MTFTPv4");
+            let mode_data = mtftp.mode_data().unwrap();
+            assert_eq!(mode_data.config_data.server_ip, SERVER_ADDRESS);
+            assert!(mode_data
+                .supported_options()
+                .any(|option| option.to_bytes().eq_ignore_ascii_case(b"tsize")));
+
+            const EXAMPLE_FILE_CONTENT: &[u8] = b"Hello world!";
+            let example_file_name = CStr8::from_bytes_with_nul(b"example-file.txt\0").unwrap();
+
+            info!("Getting remote file size");
+            let file_size = mtftp
+                .file_size(bt, example_file_name)
+                .expect("Failed to query file size");
+            assert_eq!(file_size, EXAMPLE_FILE_CONTENT.len() as u64);
+
+            info!("Reading remote file");
+            let mut buffer = [0; 512];
+            let mut blocks = 0;
+            let len = mtftp
+                .read_file(example_file_name, &[], Some(&mut buffer), |packet| {
+                    if let MtftpPacket::Data { .. } = packet {
+                        blocks += 1;
+                    }
+                    Ok(())
+                })
+                .expect("Failed to read file");
+            assert_eq!(
+                &buffer[..usize::try_from(len).unwrap()],
+                EXAMPLE_FILE_CONTENT
+            );
+            assert_eq!(blocks, 1);
+
+            info!("Aborting a transfer from the progress closure");
+            let status = mtftp
+                .read_file(
+                    example_file_name,
+                    &[],
+                    None,
+                    |_| Err(Status::ABORTED.into()),
+                )
+                .unwrap_err()
+                .status();
+            assert_eq!(status, Status::ABORTED);
+
+            mtftp.configure(None).expect("Failed to reset MTFTPv4");
+        }
+
+        binding
+            .destroy_child(child)
+            .expect("Failed to destroy MTFTPv4 child");
+    }
+}
@@ -0,0 +1,31 @@
+use uefi::prelude::BootServices;
+use uefi::proto::network::nii::{NetworkInterfaceIdentifier, NetworkInterfaceType};
+
+pub fn test(bt: &BootServices) {
+    info!("Testing the network interface identifier protocol");
+
+    let handles = bt
+        .find_handles::<NetworkInterfaceIdentifier>()
+        .unwrap_or_default();
+    for handle in handles {
+        let nii = bt
+            .open_protocol_exclusive::<NetworkInterfaceIdentifier>(handle)
+            .expect("Failed to open NII");
+        assert_eq!(nii.interface_type(), NetworkInterfaceType::UNDI);
+        info!(
+            "NII {:?} version {:?}, interface {}",
+            nii.string_id(),
+            nii.version(),
+            nii.interface_number()
+        );
+
+        if let Some(undi) = nii.undi() {
+            assert!(u32::from(nii.interface_number()) < undi.interface_count());
+            info!(
+                "UNDI version {:?}, features {:?}",
+                undi.version(),
+                undi.implementation()
+            );
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use uefi::prelude::BootServices;
+use uefi::proto::network::vlan::VlanConfig;
+use uefi::Status;
+
+pub fn test(bt: &BootServices) {
+    info!("Testing the VLAN configuration protocol");
+
+    let handles = bt.find_handles::<VlanConfig>().unwrap_or_default();
+    for handle in handles {
+        let mut vlan_config = bt
+            .open_protocol_exclusive::<VlanConfig>(handle)
+            .expect("Failed to open VLAN configuration protocol");
+
+        // The test network has no VLAN. Creating one would replace the
+        // untagged interface used by the other network tests.
+        match vlan_config.find(bt, None) {
+            Ok(entries) => info!("VLANs: {:?}", entries),
+            Err(err) => assert_eq!(err.status(), Status::NOT_FOUND),
+        }
+        assert_eq!(
+            vlan_config.remove(100).unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+        assert_eq!(
+            vlan_config
+                .set(VlanConfig::MAX_VLAN_ID + 1, 0)
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use uefi::proto::pci::PciIo;
+use uefi::table::boot::{BootServices, OpenProtocolAttributes, OpenProtocolParams, SearchType};
+use uefi::Identify;
+
+pub fn test(bt: &BootServices) {
+    info!("Running PCI I/O protocol test");
+
+    let handles = bt
+        .locate_handle_buffer(SearchType::ByProtocol(&PciIo::GUID))
+        .expect("Failed to get PCI I/O handles");
+    assert!(!handles.is_empty());
+
+    for &handle in handles.iter() {
+        // Don't open the protocol exclusively, that would disconnect the
+        // drivers of the controller, including the video driver.
+        let mut pci_io = unsafe {
+            bt.open_protocol::<PciIo>(
+                OpenProtocolParams {
+                    handle,
+                    agent: bt.image_handle(),
+                    controller: None,
+                },
+                OpenProtocolAttributes::GetProtocol,
+            )
+        }
+        .expect("Failed to open PCI I/O protocol");
+
+        let location = pci_io.location().expect("Failed to get PCI location");
+        info!(
+            "PCI device {:02x}:{:02x}.{}, ROM size {}",
+            location.bus,
+            location.device,
+            location.function,
+            pci_io.rom_size()
+        );
+
+        if let Some(rom) = pci_io.option_rom() {
+            for image in rom.images() {
+                let image = image.expect("Invalid option ROM image");
+                info!(
+                    "  ROM image for {:04x}:{:04x}, code type {:?}, {} bytes",
+                    image.vendor_id(),
+                    image.device_id(),
+                    image.code_type(),
+                    image.as_bytes().len()
+                );
+            }
+        }
+    }
+}
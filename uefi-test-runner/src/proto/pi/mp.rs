@@ -9,13 +9,6 @@ use uefi::Status;
 const NUM_CPUS: usize = 4;
 
 pub fn test(bt: &BootServices) {
-    // Skip the test if the `multi_processing` feature is not
-    // enabled. This toggle is needed because the test only works with
-    // KVM, which is not available in CI or on Windows.
-    if cfg!(not(feature = "multi_processor")) {
-        return;
-    }
-
     info!("Running UEFI multi-processor services protocol test");
     if let Ok(handle) = bt.get_handle_for_protocol::<MpServices>() {
         let mp_support = &bt
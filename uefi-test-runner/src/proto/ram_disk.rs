@@ -0,0 +1,107 @@
+use crate::registry::TestContext;
+use uefi::fat::{FatFileSystem, FatFormatOptions, FatType};
+use uefi::proto::device_path::media::RamDiskType;
+use uefi::proto::media::block::BlockIO;
+use uefi::proto::media::gpt::GptPartitionTable;
+use uefi::proto::media::partition::{GptPartitionEntry, GptPartitionType};
+use uefi::proto::media::ram_disk::RamDisk;
+use uefi::table::boot::{AllocateType, MemoryType};
+use uefi::{cstr16, guid};
+
+pub fn test(cx: &TestContext) {
+    let bt = cx.bt();
+    info!("Running RAM disk protocol test");
+
+    let handle = match cx.require_protocol::<RamDisk>() {
+        Some(handle) => handle,
+        None => return,
+    };
+    let ram_disk = bt
+        .open_protocol_exclusive::<RamDisk>(handle)
+        .expect("Failed to open RAM disk protocol");
+
+    const PAGES: usize = 16;
+    const SIZE: usize = PAGES * 4096;
+    let base = bt
+        .allocate_pages(
+            AllocateType::AnyPages,
+            MemoryType::BOOT_SERVICES_DATA,
+            PAGES,
+        )
+        .expect("Failed to allocate RAM disk memory");
+    let disk = unsafe { core::slice::from_raw_parts_mut(base as *mut u8, SIZE) };
+    disk.fill(0);
+    disk[..4].copy_from_slice(b"uefi");
+
+    let disk_path = unsafe {
+        ram_disk
+            .register(base, SIZE as u64, RamDiskType::VIRTUAL_DISK, None)
+            .expect("Failed to register RAM disk")
+    };
+
+    // The disk contents are readable through the block I/O protocol, and
+    // the disk can be partitioned.
+    {
+        let mut path = disk_path;
+        let disk_handle = bt
+            .locate_device_path::<BlockIO>(&mut path)
+            .expect("Failed to find the RAM disk handle");
+        let mut block_io = bt
+            .open_protocol_exclusive::<BlockIO>(disk_handle)
+            .expect("Failed to open block I/O protocol");
+        let media = block_io.media();
+        let block_size = media.block_size() as usize;
+        assert_eq!((media.last_block() as usize + 1) * block_size, SIZE);
+
+        let mut block = [0; 4096];
+        block_io
+            .read_blocks(media.media_id(), 0, &mut block[..block_size])
+            .expect("Failed to read the RAM disk");
+        assert_eq!(&block[..4], b"uefi");
+
+        // Partition the disk.
+        let mut table = GptPartitionTable::new(
+            block_io.media(),
+            guid!("6a8e5f3c-1d2b-4c7a-9e0f-123456789abc"),
+        )
+        .expect("Failed to create partition table");
+        let start = table
+            .find_free(8, 8)
+            .expect("No free space for the partition");
+        let entry = GptPartitionEntry::new(
+            GptPartitionType::EFI_SYSTEM_PARTITION,
+            guid!("0f1e2d3c-4b5a-6978-8796-a5b4c3d2e1f0"),
+            start,
+            start + 7,
+            cstr16!("test"),
+        )
+        .unwrap();
+        table.add(entry).expect("Failed to add partition");
+        table
+            .write(bt, &mut block_io)
+            .expect("Failed to write partition table");
+
+        let table = GptPartitionTable::read(&block_io).expect("Failed to read partition table");
+        assert_eq!(table.partitions().count(), 1);
+
+        // Replace the partition table with a FAT volume.
+        let options = FatFormatOptions::new().with_label("RAMDISK");
+        let mut fs = FatFileSystem::format(bt, &mut *block_io, &options)
+            .expect("Failed to format the RAM disk");
+        assert_eq!(fs.fat_type(), FatType::Fat12);
+        fs.create_dir("EFI/BOOT")
+            .expect("Failed to create directory");
+        fs.write_file(bt, "EFI/BOOT/test file.txt", b"test")
+            .expect("Failed to write file");
+        let mut fs = FatFileSystem::new(&mut *block_io).expect("Failed to open FAT volume");
+        assert_eq!(
+            fs.read_file("efi/boot/TEST FILE.TXT")
+                .expect("Failed to read file"),
+            b"test"
+        );
+    }
+
+    unsafe { ram_disk.unregister(disk_path) }.expect("Failed to unregister RAM disk");
+    bt.free_pages(base, PAGES)
+        .expect("Failed to free RAM disk memory");
+}
@@ -0,0 +1,21 @@
+use uefi::proto::riscv::RiscvBoot;
+use uefi::table::boot::BootServices;
+
+pub fn test(bt: &BootServices) {
+    info!("Running RISC-V boot protocol test");
+
+    let handle = bt
+        .get_handle_for_protocol::<RiscvBoot>()
+        .expect("Failed to get RISC-V boot protocol handle");
+    let riscv_boot = bt
+        .open_protocol_exclusive::<RiscvBoot>(handle)
+        .expect("Failed to open RISC-V boot protocol");
+
+    let hart_id = riscv_boot
+        .boot_hart_id()
+        .expect("Failed to get boot hart ID");
+    info!(
+        "Booted on hart {hart_id}, protocol revision {:#x}",
+        riscv_boot.revision()
+    );
+}
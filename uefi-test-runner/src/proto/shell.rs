@@ -0,0 +1,22 @@
+use uefi::prelude::*;
+use uefi::proto::shell::dynamic_command::{self, ShellDynamicCommand, ShellStatus};
+
+pub fn test_dynamic_command(bt: &BootServices) {
+    info!("Testing a shell dynamic command");
+
+    let handle = dynamic_command::install(
+        bt,
+        None,
+        cstr16!("uefi-rs-test"),
+        cstr16!(".TH uefi-rs-test 0 \"Test command\"\r\n"),
+        |_| ShellStatus::SUCCESS,
+    )
+    .expect("Failed to install dynamic command");
+
+    let mut command = bt
+        .open_protocol_exclusive::<ShellDynamicCommand>(handle)
+        .expect("Failed to open dynamic command protocol");
+    assert_eq!(command.name(), cstr16!("uefi-rs-test"));
+    let help = command.help(bt, None).expect("Failed to get the help text");
+    assert_eq!(&*help, cstr16!(".TH uefi-rs-test 0 \"Test command\"\r\n"));
+}
@@ -1,9 +1 @@
-use uefi::prelude::*;
-
-pub fn test(bt: &BootServices) {
-    info!("Testing String protocols");
-
-    unicode_collation::test(bt);
-}
-
-mod unicode_collation;
+pub mod unicode_collation;
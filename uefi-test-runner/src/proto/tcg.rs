@@ -1,5 +1,8 @@
 use alloc::vec::Vec;
 use core::mem::MaybeUninit;
+use uefi::proto::tcg::replay::{PcrMatch, PcrReplay};
+use uefi::proto::tcg::summary::LogSummary;
+use uefi::proto::tcg::v1::command::{CommandBuf, Ordinal, Tag};
 use uefi::proto::tcg::{v1, v2, AlgorithmId, EventType, HashAlgorithm, PcrIndex};
 use uefi::table::boot::BootServices;
 
@@ -45,12 +48,25 @@ fn tcg_v1_read_pcr(tcg: &mut v1::Tcg, pcr_index: PcrIndex) -> v1::Sha1Digest {
     output[10..].try_into().unwrap()
 }
 
-fn test_tcg_v1(bt: &BootServices) {
-    // Skip the test of the `tpm_v1` feature is not enabled.
-    if cfg!(not(feature = "tpm_v1")) {
-        return;
-    }
+/// Same as `tcg_v1_read_pcr`, but using the command helpers.
+fn tcg_v1_read_pcr_with_command_buf(tcg: &mut v1::Tcg, pcr_index: PcrIndex) -> v1::Sha1Digest {
+    let mut cmd_buf = [0; 14];
+    let cmd = CommandBuf::new(&mut cmd_buf, Tag::RQU_COMMAND, Ordinal::PCR_READ)
+        .unwrap()
+        .push_u32(pcr_index.0)
+        .unwrap()
+        .finish();
+
+    let mut rsp_buf = [0; 30];
+    let rsp = tcg
+        .submit_command(cmd, &mut rsp_buf)
+        .expect("failed to get PCR value");
+    assert_eq!(rsp.tag(), Tag::RSP_COMMAND);
+    let mut params = rsp.params().expect("TPM returned an error");
+    params.read_bytes(20).unwrap().try_into().unwrap()
+}
 
+pub fn test_tcg_v1(bt: &BootServices) {
     info!("Running TCG v1 test");
 
     let handle = bt
@@ -116,6 +132,10 @@ fn test_tcg_v1(bt: &BootServices) {
             0xe8, 0x29, 0x27, 0xc0, 0xb0,
         ]
     );
+    assert_eq!(
+        tcg_v1_read_pcr_with_command_buf(&mut tcg, pcr_index),
+        tcg_v1_read_pcr(&mut tcg, pcr_index)
+    );
 
     // Check the capabilities and feature flags.
     let status = tcg.status_check().expect("failed to call status_check");
@@ -215,11 +235,6 @@ fn tcg_v2_read_pcr_8(tcg: &mut v2::Tcg) -> v1::Sha1Digest {
 }
 
 pub fn test_tcg_v2(bt: &BootServices) {
-    // Skip the test of the `tpm_v2` feature is not enabled.
-    if cfg!(not(feature = "tpm_v2")) {
-        return;
-    }
-
     info!("Running TCG v2 test");
 
     let handle = bt
@@ -351,6 +366,18 @@ pub fn test_tcg_v2(bt: &BootServices) {
         ]
     );
 
+    // All events in the log have well-formed event data.
+    for entry in log.iter() {
+        entry
+            .parse_event_data()
+            .unwrap_or_else(|err| panic!("failed to parse {entry:?}: {err:?}"));
+    }
+
+    // The log can be summarized.
+    let summary = LogSummary::from_v2_log(&log, AlgorithmId::SHA256);
+    assert!(summary.event_count(pcr_index) > 0);
+    info!("{}", summary);
+
     // PCR 8 has been extended: `sha1([0; 20], sha1("some-data"))`.
     assert_eq!(
         tcg_v2_read_pcr_8(&mut tcg),
@@ -359,9 +386,22 @@ pub fn test_tcg_v2(bt: &BootServices) {
             0xd1, 0x50, 0x64, 0x73, 0x2f, 0x87,
         ]
     );
-}
 
-pub fn test(bt: &BootServices) {
-    test_tcg_v1(bt);
-    test_tcg_v2(bt);
+    // The command helpers agree with the raw command.
+    let mut digest = [0; 64];
+    let size = v2::command::pcr_read(&mut tcg, AlgorithmId::SHA1, pcr_index, &mut digest)
+        .expect("pcr_read failed");
+    assert_eq!(digest[..size], tcg_v2_read_pcr_8(&mut tcg));
+
+    let mut random = [0; 100];
+    v2::command::get_random(&mut tcg, &mut random).expect("get_random failed");
+    assert_ne!(random, [0; 100]);
+
+    // Replaying the event log gives the live PCR values.
+    for bank in [AlgorithmId::SHA1, AlgorithmId::SHA256] {
+        let replay = PcrReplay::from_tcg_v2(&mut tcg, bank).expect("failed to replay log");
+        let report = replay.verify_v2(&mut tcg).expect("failed to verify log");
+        assert!(report.all_match(), "{bank:?}: {report:?}");
+        assert_eq!(report.get(pcr_index), PcrMatch::Match);
+    }
 }
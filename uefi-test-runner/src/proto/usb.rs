@@ -0,0 +1,25 @@
+use uefi::proto::usb::hc::{HostControllerState, PortStatusFlags, Usb2HostController};
+use uefi::table::boot::BootServices;
+
+pub fn test(bt: &BootServices) {
+    info!("Testing the USB 2 host controller protocol");
+
+    let handles = bt.find_handles::<Usb2HostController>().unwrap_or_default();
+    for handle in handles {
+        let mut hc = bt
+            .open_protocol_exclusive::<Usb2HostController>(handle)
+            .expect("Failed to open USB 2 host controller protocol");
+
+        let capability = hc.capability().expect("Failed to get capabilities");
+        info!("USB {:?} host controller: {:?}", hc.revision(), capability);
+        assert_eq!(hc.state().unwrap(), HostControllerState::OPERATIONAL);
+
+        for port in 0..capability.port_count {
+            let status = hc
+                .root_hub_port_status(port)
+                .expect("Failed to get root hub port status");
+            // No device is attached in the test VM.
+            assert!(!status.status.contains(PortStatusFlags::CONNECTION));
+        }
+    }
+}
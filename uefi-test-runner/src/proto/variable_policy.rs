@@ -0,0 +1,61 @@
+use crate::registry::TestContext;
+use alloc::vec;
+use uefi::proto::security::{
+    VariableConstraints, VariableLockPolicy, VariablePolicy, VariablePolicyEntry,
+};
+use uefi::table::runtime::VariableVendor;
+use uefi::{cstr16, guid, Status};
+
+pub fn test(cx: &TestContext) {
+    let bt = cx.bt();
+    info!("Running variable policy protocol test");
+
+    let handle = match cx.require_protocol::<VariablePolicy>() {
+        Some(handle) => handle,
+        None => return,
+    };
+    let mut policy = bt
+        .open_protocol_exclusive::<VariablePolicy>(handle)
+        .expect("Failed to open variable policy protocol");
+
+    let enabled = policy
+        .is_enabled()
+        .expect("Failed to check if variable policies are enabled");
+    info!(
+        "Variable policy revision {:#x}, enabled: {}",
+        policy.revision(),
+        enabled
+    );
+    if !enabled {
+        return;
+    }
+
+    let size = policy
+        .dump(&mut [])
+        .expect_err("Dumping the policies to an empty buffer succeeded")
+        .data()
+        .expect("Failed to get the size of the policies");
+    let mut buf = vec![0; size];
+    let mut count = 0;
+    for entry in policy.dump(&mut buf).expect("Failed to dump the policies") {
+        debug!("{:?}", entry);
+        count += 1;
+    }
+    info!("Found {} variable policies", count);
+
+    // The policies are locked at the end of the DXE phase, before
+    // applications run.
+    let mut buf = [0; 128];
+    let entry = VariablePolicyEntry::new_in(
+        &mut buf,
+        VariableVendor(guid!("3f0e6a2c-54ad-4f7e-9a61-2c5a0e8b7d14")),
+        Some(cstr16!("UefiRsTest")),
+        &VariableConstraints::default(),
+        &VariableLockPolicy::LockNow,
+    )
+    .unwrap();
+    assert_eq!(
+        policy.register(entry).unwrap_err().status(),
+        Status::WRITE_PROTECTED
+    );
+}
@@ -0,0 +1,33 @@
+use uefi::prelude::*;
+use uefi::proto::media::file::{File, FileAttribute, FileMode, FileType};
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::proto::media::virtual_fs::{self, MemoryFileSystem};
+
+pub fn test(bt: &BootServices) {
+    info!("Testing a virtual file system");
+
+    let mut fs = MemoryFileSystem::new("VirtualFs");
+    fs.add_file("\\loader\\entry.conf", &b"title Test"[..])
+        .expect("Failed to add file");
+    let handle = virtual_fs::install(bt, None, fs).expect("Failed to install file system");
+
+    let mut sfs = bt
+        .open_protocol_exclusive::<SimpleFileSystem>(handle)
+        .expect("Failed to open virtual file system");
+    let mut root = sfs.open_volume().expect("Failed to open volume");
+    let file = root
+        .open(
+            cstr16!("LOADER\\entry.conf"),
+            FileMode::Read,
+            FileAttribute::empty(),
+        )
+        .expect("Failed to open file");
+    let mut file = match file.into_type().unwrap() {
+        FileType::Regular(file) => file,
+        FileType::Dir(_) => panic!("entry.conf is a directory"),
+    };
+
+    let mut buf = [0; 32];
+    let len = file.read(&mut buf).expect("Failed to read file");
+    assert_eq!(&buf[..len], b"title Test");
+}
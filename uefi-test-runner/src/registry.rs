@@ -0,0 +1,271 @@
+//! Registry of the tests run by the test runner.
+//!
+//! Each test module exports a `TESTS` list of named [`Test`]s, which are
+//! collected in `main`. The tests to run can be selected with `--test
+//! <name>` in the image's load options; a name matches a test if it is
+//! equal to the full test name, to a prefix of it ending at a `/`, or to
+//! one of its `/`-separated components. For example `--test gop` and
+//! `--test proto/console` both select `proto/console/gop`.
+//!
+//! Results are reported on the console (and so on the serial device) as
+//! [`report`] records that the host can parse. In the default human
+//! format, the output looks like:
+//!
+//! ```text
+//! TEST_START: name=proto/console/gop
+//! TEST_CHECK: name=gop screenshot
+//! TEST_PASS: name=proto/console/gop
+//! TEST_SKIP: name=proto/pi/mp, reason=multi_processor feature not enabled
+//! TEST_START: name=proto/ram_disk
+//! TEST_SKIP: name=proto/ram_disk, reason=EFI_RAM_DISK_PROTOCOL not available
+//! TEST_SUMMARY: passed=1, skipped=2
+//! ```
+//!
+//! A test can skip itself at runtime with [`TestContext::skip`] or
+//! [`TestContext::require_protocol`],
+//! e.g. if a protocol is missing in some QEMU configurations. `TEST_CHECK`
+//! records are printed by the assertions of the [`golden`] module.
+//!
+//! A failing test panics. The panic hook installed by [`run`] then prints
+//! a `TEST_FAIL` record for the running test, and no summary is printed.
+//! A `TEST_START` record without a matching `TEST_PASS`, `TEST_SKIP`, or
+//! `TEST_FAIL` means that the runner crashed or hung during the test.
+//!
+//! [`report`]: uefi::report
+//! [`golden`]: crate::golden
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt::{self, Display, Formatter};
+use core::panic::PanicInfo;
+use uefi::prelude::*;
+use uefi::proto::loaded_image::LoadedImage;
+use uefi::proto::{protocol_name, ProtocolPointer};
+use uefi::report::Record;
+use uefi::Identify;
+use uefi_services::print;
+
+/// Name of the running test, read by the panic hook. The runner is single
+/// threaded, and the hook has no other way to access the test context.
+static mut CURRENT_TEST: Option<&'static str> = None;
+
+/// State passed to each test.
+pub struct TestContext<'a> {
+    /// Handle of the test runner image.
+    pub image: Handle,
+    /// The system table.
+    pub st: &'a mut SystemTable<Boot>,
+    /// Reason the running test skipped itself, set by [`skip`].
+    ///
+    /// [`skip`]: Self::skip
+    skip_reason: Cell<Option<String>>,
+}
+
+impl<'a> TestContext<'a> {
+    /// Creates the context of the tests run by `image`.
+    pub fn new(image: Handle, st: &'a mut SystemTable<Boot>) -> Self {
+        Self {
+            image,
+            st,
+            skip_reason: Cell::new(None),
+        }
+    }
+
+    /// Returns the boot services.
+    pub fn bt(&self) -> &BootServices {
+        self.st.boot_services()
+    }
+
+    /// Marks the running test as skipped for `reason`. The test should
+    /// return right after calling this, without checking anything else.
+    pub fn skip(&self, reason: impl Into<String>) {
+        let reason = reason.into();
+        info!("Skipping the test: {reason}");
+        self.skip_reason.set(Some(reason));
+    }
+
+    /// Returns a handle supporting the protocol `P`. If there is none, the
+    /// running test is marked as skipped and `None` is returned.
+    pub fn require_protocol<P: ProtocolPointer + ?Sized>(&self) -> Option<Handle> {
+        match self.bt().get_handle_for_protocol::<P>() {
+            Ok(handle) => Some(handle),
+            Err(_) => {
+                let name = protocol_name(&P::GUID)
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| format!("protocol {}", P::GUID));
+                self.skip(format!("{name} not available"));
+                None
+            }
+        }
+    }
+}
+
+/// A named test.
+pub struct Test {
+    /// Name of the test, as a `/`-separated path such as
+    /// `proto/console/gop`.
+    pub name: &'static str,
+    /// Function running the test. Test failures are reported by
+    /// panicking.
+    pub run: fn(&mut TestContext),
+    /// If set, the test is not run, and is reported as skipped for the
+    /// given reason.
+    pub skip: Option<&'static str>,
+}
+
+impl Test {
+    /// Creates a test that always runs.
+    pub const fn new(name: &'static str, run: fn(&mut TestContext)) -> Self {
+        Self {
+            name,
+            run,
+            skip: None,
+        }
+    }
+
+    /// Skips the test for the given reason if `condition` is true.
+    pub const fn skip_if(mut self, condition: bool, reason: &'static str) -> Self {
+        if condition {
+            self.skip = Some(reason);
+        }
+        self
+    }
+}
+
+/// Set of test names selected with `--test` in the load options.
+pub struct Filter {
+    names: Vec<String>,
+}
+
+impl Filter {
+    /// Reads the filter from the load options of `image`. If no `--test`
+    /// option is present, all tests are selected.
+    pub fn from_load_options(bt: &BootServices, image: Handle) -> Self {
+        let loaded_image = bt
+            .open_protocol_exclusive::<LoadedImage>(image)
+            .expect("Failed to open LoadedImage protocol");
+        let options = loaded_image
+            .load_options_as_cstr16()
+            .map(|options| options.to_string())
+            .unwrap_or_default();
+        Self::parse(&options)
+    }
+
+    /// Parses `--test <name>` and `--test=<name>` options from a command
+    /// line. Any other arguments are ignored.
+    fn parse(options: &str) -> Self {
+        let mut names = Vec::new();
+        let mut args = options.split_whitespace();
+        while let Some(arg) = args.next() {
+            if arg == "--test" {
+                if let Some(name) = args.next() {
+                    names.push(name.to_string());
+                }
+            } else if let Some(name) = arg.strip_prefix("--test=") {
+                names.push(name.to_string());
+            }
+        }
+        Self { names }
+    }
+
+    /// Returns whether the test called `test_name` is selected.
+    fn matches(&self, test_name: &str) -> bool {
+        if self.names.is_empty() {
+            return true;
+        }
+        self.names.iter().any(|name| {
+            let is_prefix = test_name
+                .strip_prefix(name.as_str())
+                .map_or(false, |rest| rest.is_empty() || rest.starts_with('/'));
+            is_prefix || test_name.split('/').any(|component| component == name)
+        })
+    }
+}
+
+/// Result of a test, or of a check within a test, printed as a
+/// [`report`](uefi::report) record in the crate-wide format.
+pub enum TestRecord<'a> {
+    /// The test is about to run.
+    Start(&'a str),
+    /// A golden data check of the running test passed.
+    Check(&'a str),
+    /// The test passed.
+    Pass(&'a str),
+    /// The test was skipped for the given reason.
+    Skip(&'a str, &'a str),
+    /// The test failed.
+    Fail(&'a str),
+    /// All the selected tests have run.
+    Summary { passed: usize, skipped: usize },
+}
+
+impl TestRecord<'_> {
+    /// Prints the record on the console.
+    pub fn print(&self) {
+        print!("{self}");
+    }
+}
+
+impl Display for TestRecord<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Start(name) => Record::new(f, "TEST_START")?.field("name", name)?,
+            Self::Check(name) => Record::new(f, "TEST_CHECK")?.field("name", name)?,
+            Self::Pass(name) => Record::new(f, "TEST_PASS")?.field("name", name)?,
+            Self::Skip(name, reason) => Record::new(f, "TEST_SKIP")?
+                .field("name", name)?
+                .field("reason", reason)?,
+            Self::Fail(name) => Record::new(f, "TEST_FAIL")?.field("name", name)?,
+            Self::Summary { passed, skipped } => Record::new(f, "TEST_SUMMARY")?
+                .field("passed", passed)?
+                .field("skipped", skipped)?,
+        }
+        .finish()
+    }
+}
+
+/// Panic hook printing a `TEST_FAIL` record for the running test, so that
+/// the host can tell a failed test from a crash.
+fn report_failure(_info: &PanicInfo) {
+    if let Some(name) = unsafe { CURRENT_TEST } {
+        TestRecord::Fail(name).print();
+    }
+}
+
+/// Runs the tests of `groups` selected by `filter`, in order, and prints
+/// the result of each one followed by a summary.
+pub fn run(groups: &[&[Test]], filter: &Filter, cx: &mut TestContext) {
+    let (mut passed, mut skipped) = (0, 0);
+    uefi_services::set_panic_hook(report_failure);
+
+    for test in groups.iter().flat_map(|group| group.iter()) {
+        if !filter.matches(test.name) {
+            continue;
+        }
+        if let Some(reason) = test.skip {
+            TestRecord::Skip(test.name, reason).print();
+            skipped += 1;
+            continue;
+        }
+
+        TestRecord::Start(test.name).print();
+        unsafe { CURRENT_TEST = Some(test.name) };
+        (test.run)(cx);
+        unsafe { CURRENT_TEST = None };
+        match cx.skip_reason.take() {
+            Some(reason) => {
+                TestRecord::Skip(test.name, &reason).print();
+                skipped += 1;
+            }
+            None => {
+                TestRecord::Pass(test.name).print();
+                passed += 1;
+            }
+        }
+    }
+
+    // Failures panic before reaching this point, so there is no failure
+    // count.
+    TestRecord::Summary { passed, skipped }.print();
+}
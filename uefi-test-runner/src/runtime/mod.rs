@@ -1,8 +1,7 @@
-use uefi::table::runtime::RuntimeServices;
+use crate::registry::Test;
 
-pub fn test(rt: &RuntimeServices) {
-    info!("Testing runtime services");
-    vars::test(rt);
-}
+pub const TESTS: &[Test] = &[Test::new("runtime/vars", |cx| {
+    vars::test(cx.st.runtime_services())
+})];
 
 mod vars;
@@ -1,7 +1,7 @@
 use log::info;
-use uefi::guid;
 use uefi::prelude::*;
-use uefi::table::runtime::{VariableAttributes, VariableVendor};
+use uefi::table::runtime::{OsIndications, VariableAttributes, VariableVendor};
+use uefi::{guid, lang};
 
 fn test_variables(rt: &RuntimeServices) {
     let name = cstr16!("UefiRsTestVar");
@@ -29,6 +29,13 @@ fn test_variables(rt: &RuntimeServices) {
     assert_eq!(data, test_value);
     assert_eq!(attrs, test_attrs);
 
+    info!("Testing get_variable_boxed");
+    let (data, attrs) = rt
+        .get_variable_boxed(name, &vendor)
+        .expect("failed to get variable");
+    assert_eq!(&*data, test_value);
+    assert_eq!(attrs, test_attrs);
+
     info!("Testing variable_keys");
     let variable_keys = rt.variable_keys().expect("failed to get variable keys");
     info!("Found {} variables", variable_keys.len());
@@ -56,7 +63,44 @@ fn test_variable_info(rt: &RuntimeServices) {
     );
 }
 
+fn test_os_indications(rt: &RuntimeServices) {
+    let supported = rt.os_indications_supported().unwrap();
+    info!("Supported OS indications: {:?}", supported);
+    // OVMF can always boot to its setup menu.
+    assert!(supported.contains(OsIndications::BOOT_TO_FW_UI));
+
+    // Requesting a feature and clearing it again leaves the variable as it
+    // was.
+    let indications = rt.os_indications().unwrap();
+    rt.set_os_indications(indications | OsIndications::START_OS_RECOVERY)
+        .unwrap();
+    assert!(rt
+        .os_indications()
+        .unwrap()
+        .contains(OsIndications::START_OS_RECOVERY));
+    rt.set_os_indications(indications).unwrap();
+    assert_eq!(rt.os_indications().unwrap(), indications);
+}
+
+fn test_platform_lang(rt: &RuntimeServices) {
+    let mut lang_buf = [0; 64];
+    let mut codes_buf = [0; 256];
+    let platform_lang = lang::platform_lang(rt, &mut lang_buf).unwrap();
+    let codes = lang::platform_lang_codes(rt, &mut codes_buf).unwrap();
+    info!(
+        "Platform language: {platform_lang}, supported: {}",
+        codes.as_str()
+    );
+
+    assert!(codes.contains(platform_lang));
+    assert_eq!(codes.best_match([platform_lang]), Some(platform_lang));
+    // Setting the current language again is always allowed.
+    lang::set_platform_lang(rt, platform_lang).unwrap();
+}
+
 pub fn test(rt: &RuntimeServices) {
     test_variables(rt);
     test_variable_info(rt);
+    test_os_indications(rt);
+    test_platform_lang(rt);
 }
@@ -0,0 +1,137 @@
+//! AArch64 cache maintenance and memory barriers.
+//!
+//! UEFI runs with the MMU and caches enabled, and the instruction cache is
+//! not coherent with the data cache. Code written to memory, e.g. a kernel
+//! copied by a loader, must therefore be cleaned from the data cache and
+//! invalidated in the instruction cache before it is run. A kernel started
+//! with the caches disabled also needs the data it reads, such as its
+//! command line, to be cleaned to memory first.
+//!
+//! The cache line sizes are read from `CTR_EL0`.
+
+use super::cache_lines;
+use core::arch::asm;
+
+/// Data synchronization barrier for the full system: waits until all
+/// previous memory accesses and cache maintenance operations completed.
+#[inline]
+pub fn dsb_sy() {
+    unsafe { asm!("dsb sy", options(nostack, preserves_flags)) }
+}
+
+/// Data synchronization barrier for the inner shareable domain, i.e. all
+/// the processors.
+#[inline]
+pub fn dsb_ish() {
+    unsafe { asm!("dsb ish", options(nostack, preserves_flags)) }
+}
+
+/// Data memory barrier for the inner shareable domain: orders previous
+/// memory accesses before following ones, without waiting for them.
+#[inline]
+pub fn dmb_ish() {
+    unsafe { asm!("dmb ish", options(nostack, preserves_flags)) }
+}
+
+/// Instruction synchronization barrier: flushes the pipeline, so that the
+/// following instructions are fetched after previous cache maintenance.
+#[inline]
+pub fn isb() {
+    unsafe { asm!("isb", options(nostack, preserves_flags)) }
+}
+
+/// Reads the cache type register `CTR_EL0`.
+fn cache_type() -> u64 {
+    let ctr: u64;
+    unsafe { asm!("mrs {}, ctr_el0", out(reg) ctr, options(nomem, nostack, preserves_flags)) };
+    ctr
+}
+
+/// Returns the size in bytes of the smallest data cache line.
+#[must_use]
+pub fn data_cache_line_size() -> usize {
+    4 << ((cache_type() >> 16) & 0xf)
+}
+
+/// Returns the size in bytes of the smallest instruction cache line.
+#[must_use]
+pub fn instruction_cache_line_size() -> usize {
+    4 << (cache_type() & 0xf)
+}
+
+/// Cleans the data cache for the `len` bytes at `start` to the point of
+/// coherency, i.e. writes the cached data to memory, and waits for it.
+///
+/// # Safety
+///
+/// The range must be mapped.
+pub unsafe fn clean_data_cache_range(start: *const u8, len: usize) {
+    for line in cache_lines(start as usize, len, data_cache_line_size()) {
+        asm!("dc cvac, {}", in(reg) line, options(nostack, preserves_flags));
+    }
+    dsb_sy();
+}
+
+/// Cleans and invalidates the data cache for the `len` bytes at `start` to
+/// the point of coherency, and waits for it. Following reads of the range
+/// come from memory.
+///
+/// # Safety
+///
+/// The range must be mapped.
+pub unsafe fn clean_invalidate_data_cache_range(start: *const u8, len: usize) {
+    for line in cache_lines(start as usize, len, data_cache_line_size()) {
+        asm!("dc civac, {}", in(reg) line, options(nostack, preserves_flags));
+    }
+    dsb_sy();
+}
+
+/// Invalidates the instruction cache for the `len` bytes at `start`, and
+/// waits for it.
+///
+/// # Safety
+///
+/// The range must be mapped.
+pub unsafe fn invalidate_instruction_cache_range(start: *const u8, len: usize) {
+    for line in cache_lines(start as usize, len, instruction_cache_line_size()) {
+        asm!("ic ivau, {}", in(reg) line, options(nostack, preserves_flags));
+    }
+    dsb_ish();
+    isb();
+}
+
+/// Invalidates the whole instruction cache, and waits for it.
+pub fn invalidate_instruction_cache() {
+    unsafe { asm!("ic iallu", options(nostack, preserves_flags)) };
+    dsb_ish();
+    isb();
+}
+
+/// Makes the `len` bytes at `start`, which were just written as data,
+/// visible to instruction fetches.
+///
+/// This cleans the data cache to the point of unification and invalidates
+/// the instruction cache for the range. Either step is skipped if
+/// `CTR_EL0` reports that the processor doesn't need it.
+///
+/// # Safety
+///
+/// The range must be mapped.
+pub unsafe fn sync_instruction_cache(start: *const u8, len: usize) {
+    let ctr = cache_type();
+    // CTR_EL0.IDC: cleaning to the point of unification is not required.
+    if ctr & (1 << 28) == 0 {
+        for line in cache_lines(start as usize, len, 4 << ((ctr >> 16) & 0xf)) {
+            asm!("dc cvau, {}", in(reg) line, options(nostack, preserves_flags));
+        }
+    }
+    dsb_ish();
+    // CTR_EL0.DIC: invalidating the instruction cache is not required.
+    if ctr & (1 << 29) == 0 {
+        for line in cache_lines(start as usize, len, 4 << (ctr & 0xf)) {
+            asm!("ic ivau, {}", in(reg) line, options(nostack, preserves_flags));
+        }
+        dsb_ish();
+    }
+    isb();
+}
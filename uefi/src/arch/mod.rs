@@ -0,0 +1,83 @@
+//! Architecture-specific helpers.
+//!
+//! These wrap the few instructions a UEFI application needs outside of the
+//! firmware's protocols, so that loaders don't need `asm!` blocks of their
+//! own. The submodules are only available on their architecture, and the
+//! `x86` module also requires the `arch-intrinsics` feature;
+//! [`sync_instruction_cache`] is available on all architectures.
+//!
+//! # Example
+//!
+//! A loader that copies a kernel to memory must make the copied code
+//! visible to instruction fetches before jumping to it:
+//!
+//! ```no_run
+//! use uefi::arch;
+//!
+//! unsafe fn start_kernel(kernel: &[u8], load_address: *mut u8) -> ! {
+//!     core::ptr::copy_nonoverlapping(kernel.as_ptr(), load_address, kernel.len());
+//!     arch::sync_instruction_cache(load_address, kernel.len());
+//!
+//!     let entry: extern "C" fn() -> ! = core::mem::transmute(load_address);
+//!     entry()
+//! }
+//! ```
+
+#[cfg(target_arch = "aarch64")]
+pub mod aarch64;
+
+#[cfg(all(
+    feature = "arch-intrinsics",
+    any(target_arch = "x86", target_arch = "x86_64")
+))]
+pub mod x86;
+
+/// Makes the `len` bytes at `start`, which were just written as data,
+/// visible to instruction fetches, so that they can be run as code.
+///
+/// On AArch64, this cleans the data cache and invalidates the instruction
+/// cache for the range, see `aarch64::sync_instruction_cache`. On x86 and
+/// x86_64, the instruction cache is coherent with the data cache, so this
+/// only prevents the compiler from reordering the writes after it.
+///
+/// # Safety
+///
+/// The range must be mapped.
+pub unsafe fn sync_instruction_cache(start: *const u8, len: usize) {
+    #[cfg(target_arch = "aarch64")]
+    aarch64::sync_instruction_cache(start, len);
+
+    #[cfg(not(target_arch = "aarch64"))]
+    {
+        let _ = (start, len);
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+/// Returns the addresses of the cache lines of `line_size` bytes, a power
+/// of two, that overlap the `len` bytes at `start`.
+#[cfg_attr(not(target_arch = "aarch64"), allow(dead_code))]
+fn cache_lines(start: usize, len: usize, line_size: usize) -> impl Iterator<Item = usize> {
+    let first = start & !(line_size - 1);
+    let end = if len == 0 {
+        first
+    } else {
+        start.saturating_add(len)
+    };
+    (first..end).step_by(line_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_lines() {
+        let lines = |start, len| cache_lines(start, len, 64).collect::<alloc::vec::Vec<_>>();
+        assert_eq!(lines(0x1000, 0x80), [0x1000, 0x1040]);
+        assert_eq!(lines(0x1010, 0x80), [0x1000, 0x1040, 0x1080]);
+        assert_eq!(lines(0x103f, 2), [0x1000, 0x1040]);
+        assert_eq!(lines(0x1010, 1), [0x1000]);
+        assert!(lines(0x1010, 0).is_empty());
+    }
+}
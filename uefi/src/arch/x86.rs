@@ -0,0 +1,176 @@
+//! x86 and x86_64 port I/O, model-specific registers and `cpuid`.
+//!
+//! UEFI applications run in ring 0, so they can access I/O ports and MSRs
+//! directly. This is useful for diagnostics and bring-up tools, e.g. to
+//! talk to a legacy serial port or read the processor's configuration,
+//! but bypasses the firmware: prefer a protocol when there is one, such
+//! as the PCI root bridge I/O protocol for PCI configuration space.
+//!
+//! This module requires the `arch-intrinsics` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::arch::x86::{inb, outb};
+//!
+//! const COM1: u16 = 0x3f8;
+//!
+//! fn write_serial(byte: u8) {
+//!     unsafe {
+//!         // Wait until the transmit holding register is empty.
+//!         while inb(COM1 + 5) & 0x20 == 0 {}
+//!         outb(COM1, byte);
+//!     }
+//! }
+//! ```
+
+use core::arch::asm;
+
+#[cfg(target_arch = "x86")]
+use core::arch::x86::__cpuid_count;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::__cpuid_count;
+
+#[cfg(target_arch = "x86")]
+pub use core::arch::x86::CpuidResult;
+#[cfg(target_arch = "x86_64")]
+pub use core::arch::x86_64::CpuidResult;
+
+/// Reads a byte from the I/O `port`.
+///
+/// # Safety
+///
+/// Reading a port can have side effects on the device behind it, and
+/// conflict with the firmware's driver for that device.
+#[inline]
+#[must_use]
+pub unsafe fn inb(port: u16) -> u8 {
+    let value: u8;
+    asm!("in al, dx", in("dx") port, out("al") value, options(nomem, nostack, preserves_flags));
+    value
+}
+
+/// Writes a byte to the I/O `port`.
+///
+/// # Safety
+///
+/// Writing a port can put the device behind it in any state, and conflict
+/// with the firmware's driver for that device.
+#[inline]
+pub unsafe fn outb(port: u16, value: u8) {
+    asm!("out dx, al", in("dx") port, in("al") value, options(nomem, nostack, preserves_flags));
+}
+
+/// Reads a 16-bit word from the I/O `port`.
+///
+/// # Safety
+///
+/// See [`inb`].
+#[inline]
+#[must_use]
+pub unsafe fn inw(port: u16) -> u16 {
+    let value: u16;
+    asm!("in ax, dx", in("dx") port, out("ax") value, options(nomem, nostack, preserves_flags));
+    value
+}
+
+/// Writes a 16-bit word to the I/O `port`.
+///
+/// # Safety
+///
+/// See [`outb`].
+#[inline]
+pub unsafe fn outw(port: u16, value: u16) {
+    asm!("out dx, ax", in("dx") port, in("ax") value, options(nomem, nostack, preserves_flags));
+}
+
+/// Reads a 32-bit double word from the I/O `port`.
+///
+/// # Safety
+///
+/// See [`inb`].
+#[inline]
+#[must_use]
+pub unsafe fn inl(port: u16) -> u32 {
+    let value: u32;
+    asm!("in eax, dx", in("dx") port, out("eax") value, options(nomem, nostack, preserves_flags));
+    value
+}
+
+/// Writes a 32-bit double word to the I/O `port`.
+///
+/// # Safety
+///
+/// See [`outb`].
+#[inline]
+pub unsafe fn outl(port: u16, value: u32) {
+    asm!("out dx, eax", in("dx") port, in("eax") value, options(nomem, nostack, preserves_flags));
+}
+
+/// Reads the model-specific register `msr`.
+///
+/// # Safety
+///
+/// `msr` must be supported by the processor, otherwise a general
+/// protection fault is raised. Reading some MSRs has side effects.
+#[inline]
+#[must_use]
+pub unsafe fn rdmsr(msr: u32) -> u64 {
+    let (low, high): (u32, u32);
+    asm!("rdmsr", in("ecx") msr, out("eax") low, out("edx") high, options(nomem, nostack, preserves_flags));
+    (u64::from(high) << 32) | u64::from(low)
+}
+
+/// Writes `value` to the model-specific register `msr`.
+///
+/// # Safety
+///
+/// `msr` must be supported by the processor and `value` valid for it,
+/// otherwise a general protection fault is raised. MSRs control the
+/// processor, and the firmware relies on the values it configured.
+#[inline]
+pub unsafe fn wrmsr(msr: u32, value: u64) {
+    let (low, high) = (value as u32, (value >> 32) as u32);
+    asm!("wrmsr", in("ecx") msr, in("eax") low, in("edx") high, options(nostack, preserves_flags));
+}
+
+/// Runs the `cpuid` instruction for `leaf` and `subleaf`, which is ignored
+/// by leaves without subleaves.
+///
+/// Leaves above the maximum reported in `eax` by leaf 0, or by leaf
+/// `0x8000_0000` for the extended leaves, return unspecified values.
+#[inline]
+#[must_use]
+pub fn cpuid(leaf: u32, subleaf: u32) -> CpuidResult {
+    // `cpuid` is available on all processors that can run UEFI.
+    #[allow(unused_unsafe)]
+    unsafe {
+        __cpuid_count(leaf, subleaf)
+    }
+}
+
+/// Returns the processor vendor string, e.g. `GenuineIntel` or
+/// `AuthenticAMD`, as reported by `cpuid` leaf 0.
+#[must_use]
+pub fn cpu_vendor() -> [u8; 12] {
+    let result = cpuid(0, 0);
+    let mut vendor = [0; 12];
+    vendor[..4].copy_from_slice(&result.ebx.to_le_bytes());
+    vendor[4..8].copy_from_slice(&result.edx.to_le_bytes());
+    vendor[8..].copy_from_slice(&result.ecx.to_le_bytes());
+    vendor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cpuid() {
+        let vendor = cpu_vendor();
+        assert!(vendor.iter().all(u8::is_ascii_graphic));
+        // Leaf 1 is supported by all processors with a vendor string.
+        assert!(cpuid(0, 0).eax >= 1);
+        assert_ne!(cpuid(1, 0).eax, 0);
+    }
+}
@@ -0,0 +1,201 @@
+//! Staging of firmware update capsules for delivery on disk.
+//!
+//! Instead of passing a capsule to `UpdateCapsule` and resetting, an
+//! updater can write the capsule file to the `\EFI\UpdateCapsule`
+//! directory of the EFI system partition, and set the
+//! `EFI_OS_INDICATIONS_FILE_CAPSULE_DELIVERY_SUPPORTED` bit of the
+//! `OsIndications` variable. On the next boot, the firmware processes the
+//! capsule files in alphabetical order, deletes them, and clears the bit.
+//!
+//! This is known as "capsule on disk", and is described in section 8.5.5
+//! of the UEFI specification. [`stage_on_disk`] performs all of these
+//! steps.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::capsule;
+//! use uefi::prelude::*;
+//!
+//! fn stage(image: Handle, st: &SystemTable<Boot>, capsule: &[u8]) -> uefi::Result {
+//!     // Stage the capsule on the partition this application was loaded
+//!     // from, which is usually the EFI system partition.
+//!     let mut fs = st.boot_services().get_image_file_system(image)?;
+//!     let mut root = fs.open_volume()?;
+//!     capsule::stage_on_disk(
+//!         st.runtime_services(),
+//!         &mut root,
+//!         cstr16!("Update.cap"),
+//!         capsule,
+//!     )
+//! }
+//! ```
+
+use crate::proto::media::file::{Directory, File, FileAttribute, FileMode};
+use crate::table::runtime::{OsIndications, RuntimeServices};
+use crate::{cstr16, CStr16, Result, ResultExt, Status};
+
+/// Size of the `EFI_CAPSULE_HEADER` at the start of a capsule.
+const CAPSULE_HEADER_SIZE: usize = 28;
+
+/// Check whether the firmware supports capsule delivery on disk, according
+/// to the `OsIndicationsSupported` variable.
+///
+/// # Errors
+///
+/// See [`RuntimeServices::get_variable`].
+pub fn file_delivery_supported(rt: &RuntimeServices) -> Result<bool> {
+    Ok(rt
+        .os_indications_supported()?
+        .contains(OsIndications::FILE_CAPSULE_DELIVERY_SUPPORTED))
+}
+
+/// Stage `capsule` for delivery on disk on the next boot.
+///
+/// The capsule is written to `\EFI\UpdateCapsule\<file_name>` under `root`,
+/// which must be the root directory of the EFI system partition the
+/// firmware will boot from. Missing directories are created, and an
+/// existing file with the same name is replaced. The
+/// `EFI_OS_INDICATIONS_FILE_CAPSULE_DELIVERY_SUPPORTED` bit of
+/// `OsIndications` is then set, keeping the other bits.
+///
+/// The capsule is only processed after a reset, which is up to the caller.
+///
+/// # Errors
+///
+/// * [`Status::UNSUPPORTED`]: the firmware doesn't support capsule delivery
+///   on disk.
+/// * [`Status::INVALID_PARAMETER`]: `capsule` doesn't start with a valid
+///   `EFI_CAPSULE_HEADER`, or a file that is in the way of the
+///   `\EFI\UpdateCapsule` directory exists.
+///
+/// Errors from the file protocol and from
+/// [`RuntimeServices::set_variable`] are passed through.
+pub fn stage_on_disk(
+    rt: &RuntimeServices,
+    root: &mut Directory,
+    file_name: &CStr16,
+    capsule: &[u8],
+) -> Result {
+    if !file_delivery_supported(rt)? {
+        return Err(Status::UNSUPPORTED.into());
+    }
+    check_capsule_header(capsule)?;
+
+    let mut efi = open_or_create_dir(root, cstr16!("EFI"))?;
+    let mut dir = open_or_create_dir(&mut efi, cstr16!("UpdateCapsule"))?;
+
+    // Opening an existing file doesn't truncate it, so delete it first.
+    if let Ok(existing) = dir.open(file_name, FileMode::ReadWrite, FileAttribute::empty()) {
+        existing.delete()?;
+    }
+    let mut file = dir
+        .open(file_name, FileMode::CreateReadWrite, FileAttribute::empty())?
+        .into_regular_file()
+        .ok_or(Status::INVALID_PARAMETER)?;
+    file.write(capsule).discard_errdata()?;
+    file.flush()?;
+    file.close();
+
+    rt.set_os_indications(rt.os_indications()? | OsIndications::FILE_CAPSULE_DELIVERY_SUPPORTED)
+}
+
+/// Check that `capsule` starts with an `EFI_CAPSULE_HEADER` whose sizes
+/// match the capsule.
+fn check_capsule_header(capsule: &[u8]) -> Result {
+    let read_u32 = |offset: usize| {
+        capsule
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    let header_size = read_u32(16).ok_or(Status::INVALID_PARAMETER)?;
+    let image_size = read_u32(24).ok_or(Status::INVALID_PARAMETER)?;
+    if header_size < CAPSULE_HEADER_SIZE || header_size > image_size || image_size != capsule.len()
+    {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+    Ok(())
+}
+
+fn open_or_create_dir(parent: &mut Directory, name: &CStr16) -> Result<Directory> {
+    parent
+        .open(name, FileMode::CreateReadWrite, FileAttribute::DIRECTORY)?
+        .into_directory()
+        .ok_or_else(|| Status::INVALID_PARAMETER.into())
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::{MockFileSystem, MockFirmware};
+    use crate::proto::media::fs::SimpleFileSystem;
+    use crate::table::runtime::{VariableAttributes, VariableVendor};
+    use alloc::vec::Vec;
+
+    fn capsule(len: u32) -> Vec<u8> {
+        let mut capsule = [0x11; 16].to_vec();
+        capsule.extend(28u32.to_le_bytes());
+        capsule.extend(0u32.to_le_bytes());
+        capsule.extend(len.to_le_bytes());
+        capsule.resize(len as usize, 0xcc);
+        capsule
+    }
+
+    #[test]
+    fn test_stage_on_disk() {
+        let mut firmware = MockFirmware::new();
+        let fs = MockFileSystem::new("ESP");
+        fs.add_file("EFI/UpdateCapsule/Update.cap", &[0xff; 100]);
+        let handle = firmware.install_file_system(&fs);
+        let st = firmware.system_table();
+        let rt = st.runtime_services();
+        let mut sfs = st
+            .boot_services()
+            .open_protocol_exclusive::<SimpleFileSystem>(handle)
+            .unwrap();
+        let mut root = sfs.open_volume().unwrap();
+        let name = cstr16!("Update.cap");
+
+        // Not supported by the firmware.
+        assert!(!file_delivery_supported(rt).unwrap());
+        assert_eq!(
+            stage_on_disk(rt, &mut root, name, &capsule(32))
+                .unwrap_err()
+                .status(),
+            Status::UNSUPPORTED
+        );
+
+        let vendor = VariableVendor::GLOBAL_VARIABLE;
+        let attributes = VariableAttributes::BOOTSERVICE_ACCESS;
+        rt.set_variable(
+            cstr16!("OsIndicationsSupported"),
+            &vendor,
+            attributes,
+            &0x5u64.to_le_bytes(),
+        )
+        .unwrap();
+        rt.set_os_indications(OsIndications::BOOT_TO_FW_UI).unwrap();
+        assert!(file_delivery_supported(rt).unwrap());
+
+        // Sizes not matching the header.
+        let mut bad = capsule(32);
+        bad.push(0);
+        assert_eq!(
+            stage_on_disk(rt, &mut root, name, &bad)
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+
+        // The existing file is replaced, and the other bits are kept.
+        stage_on_disk(rt, &mut root, name, &capsule(32)).unwrap();
+        assert_eq!(
+            fs.read_file("EFI/UpdateCapsule/Update.cap").unwrap(),
+            capsule(32)
+        );
+        assert_eq!(
+            rt.os_indications().unwrap(),
+            OsIndications::BOOT_TO_FW_UI | OsIndications::FILE_CAPSULE_DELIVERY_SUPPORTED
+        );
+    }
+}
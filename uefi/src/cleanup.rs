@@ -0,0 +1,153 @@
+//! Cleanup callbacks run when the application exits.
+//!
+//! Firmware resources such as opened protocols, pool allocations, and open
+//! files are not released when an application returns control to the
+//! firmware or hands the machine over to an operating system. [`on_exit`]
+//! registers a callback that releases such a resource. The registered
+//! callbacks run in reverse order of registration (LIFO) when:
+//!
+//! - [`SystemTable::exit_boot_services`] is called,
+//! - [`BootServices::exit`] or [`BootServices::exit_with_data`] is called,
+//! - [`run_exit_callbacks`] is called, which an application should do
+//!   before returning from its entry point.
+//!
+//! [`on_exit_boot_services`] is the lower-level mechanism used by EDK2
+//! libraries: it subscribes a closure to the
+//! [`EventType::SIGNAL_EXIT_BOOT_SERVICES`] event group, so it also runs if
+//! another image, such as an OS loader started by this one, exits boot
+//! services.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::cleanup::{on_exit, run_exit_callbacks};
+//! use uefi::prelude::*;
+//!
+//! fn efi_main(_image: Handle, _st: SystemTable<Boot>) -> Status {
+//!     on_exit(|| log::info!("runs second")).unwrap();
+//!     on_exit(|| log::info!("runs first")).unwrap();
+//!
+//!     run_exit_callbacks();
+//!     Status::SUCCESS
+//! }
+//! ```
+//!
+//! [`BootServices::exit`]: crate::table::boot::BootServices::exit
+//! [`BootServices::exit_with_data`]: crate::table::boot::BootServices::exit_with_data
+//! [`SystemTable::exit_boot_services`]: crate::table::SystemTable::exit_boot_services
+
+use crate::mem::{try_box, try_reserve};
+use crate::table::boot::{BootServices, EventType, Tpl};
+use crate::{Event, Result};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::ptr::{self, NonNull};
+
+/// Callbacks registered with [`on_exit`], in registration order.
+///
+/// UEFI applications are single-threaded, and the callbacks are never run
+/// from an event notification function, so there is no concurrent access.
+static mut CALLBACKS: Vec<Box<dyn FnOnce()>> = Vec::new();
+
+/// Registers `callback` to run when the application exits. See the
+/// [module documentation](self) for when the callbacks run.
+///
+/// # Errors
+///
+/// * [`Status::OUT_OF_RESOURCES`]: the callback couldn't be allocated.
+///
+/// [`Status::OUT_OF_RESOURCES`]: crate::Status::OUT_OF_RESOURCES
+pub fn on_exit(callback: impl FnOnce() + 'static) -> Result {
+    let callback = try_box(callback)?;
+    let callbacks = unsafe { &mut *ptr::addr_of_mut!(CALLBACKS) };
+    try_reserve(callbacks, 1)?;
+    callbacks.push(callback);
+    Ok(())
+}
+
+/// Runs and unregisters all the callbacks registered with [`on_exit`], most
+/// recently registered first.
+///
+/// Callbacks registered by a running callback are run as well.
+pub fn run_exit_callbacks() {
+    // Pop each callback before running it, so that the callback can
+    // register more callbacks without aliasing the list.
+    while let Some(callback) = unsafe { (*ptr::addr_of_mut!(CALLBACKS)).pop() } {
+        callback();
+    }
+}
+
+/// Runs `callback` when boot services are exited.
+///
+/// This creates an event of type [`EventType::SIGNAL_EXIT_BOOT_SERVICES`],
+/// which the firmware signals during `ExitBootServices`, no matter which
+/// image calls it. Closing the returned event with
+/// [`BootServices::close_event`] cancels the callback.
+///
+/// The closure runs in an event notification function, after the memory
+/// map used to exit boot services has been retrieved. It must therefore not
+/// allocate or free memory, which includes dropping heap-allocated values.
+/// For the same reason, the closure itself is never freed.
+///
+/// # Errors
+///
+/// See [`BootServices::create_event`].
+pub fn on_exit_boot_services<F: FnOnce() + 'static>(
+    bt: &BootServices,
+    callback: F,
+) -> Result<Event> {
+    unsafe extern "efiapi" fn notify<F: FnOnce()>(_event: Event, ctx: Option<NonNull<c_void>>) {
+        if let Some(ctx) = ctx {
+            if let Some(callback) = (*ctx.cast::<Option<F>>().as_ptr()).take() {
+                callback();
+            }
+        }
+    }
+
+    let callback = Box::into_raw(try_box(Some(callback))?);
+    let event = unsafe {
+        bt.create_event(
+            EventType::SIGNAL_EXIT_BOOT_SERVICES,
+            Tpl::NOTIFY,
+            Some(notify::<F>),
+            NonNull::new(callback.cast()),
+        )
+    };
+    if event.is_err() {
+        // The event was not created, so the callback can be freed.
+        drop(unsafe { Box::from_raw(callback) });
+    }
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    #[test]
+    fn test_run_exit_callbacks() {
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        for i in 0..3 {
+            let order = order.clone();
+            on_exit(move || order.borrow_mut().push(i)).unwrap();
+        }
+        let nested = order.clone();
+        on_exit(move || {
+            let order = nested.clone();
+            nested.borrow_mut().push(3);
+            on_exit(move || order.borrow_mut().push(4)).unwrap();
+        })
+        .unwrap();
+
+        run_exit_callbacks();
+        assert_eq!(*order.borrow(), [3, 4, 2, 1, 0]);
+
+        // The callbacks were unregistered.
+        run_exit_callbacks();
+        assert_eq!(order.borrow().len(), 5);
+    }
+}
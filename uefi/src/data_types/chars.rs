@@ -6,14 +6,98 @@
 use core::fmt;
 
 /// Character conversion error
-#[derive(Clone, Copy, Debug)]
+///
+/// Returned when a character can't be represented in the target encoding,
+/// e.g. when converting a `char` outside of the Basic Multilingual Plane to
+/// a [`Char16`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct CharConversionError;
 
+impl fmt::Display for CharConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "character can't be represented in the target encoding")
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl core::error::Error for CharConversionError {}
+
+/// Returns `true` for the C0 and C1 control codes and DEL, which are the
+/// code points for which [`char::is_control`] returns `true`.
+const fn is_control(code_point: u16) -> bool {
+    matches!(code_point, 0..=0x1f | 0x7f..=0x9f)
+}
+
 /// A Latin-1 character
 #[derive(Clone, Copy, Default, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(transparent)]
 pub struct Char8(u8);
 
+impl Char8 {
+    /// Checks if the character is within the ASCII range.
+    #[must_use]
+    pub const fn is_ascii(self) -> bool {
+        self.0.is_ascii()
+    }
+
+    /// Checks if the character is a control character.
+    #[must_use]
+    pub const fn is_control(self) -> bool {
+        is_control(self.0 as u16)
+    }
+
+    /// Checks if the character is an ASCII decimal digit.
+    #[must_use]
+    pub const fn is_ascii_digit(self) -> bool {
+        self.0.is_ascii_digit()
+    }
+
+    /// Checks if the character is an ASCII letter.
+    #[must_use]
+    pub const fn is_ascii_alphabetic(self) -> bool {
+        self.0.is_ascii_alphabetic()
+    }
+
+    /// Checks if the character is ASCII whitespace, as defined by
+    /// [`u8::is_ascii_whitespace`].
+    #[must_use]
+    pub const fn is_ascii_whitespace(self) -> bool {
+        self.0.is_ascii_whitespace()
+    }
+
+    /// Returns the character with ASCII letters converted to upper case.
+    /// Other characters are returned unchanged.
+    #[must_use]
+    pub const fn to_ascii_uppercase(self) -> Self {
+        Self(self.0.to_ascii_uppercase())
+    }
+
+    /// Returns the character with ASCII letters converted to lower case.
+    /// Other characters are returned unchanged.
+    #[must_use]
+    pub const fn to_ascii_lowercase(self) -> Self {
+        Self(self.0.to_ascii_lowercase())
+    }
+
+    /// Checks if two characters are equal, ignoring the case of ASCII
+    /// letters.
+    #[must_use]
+    pub const fn eq_ignore_ascii_case(self, other: Self) -> bool {
+        self.0.eq_ignore_ascii_case(&other.0)
+    }
+
+    /// Converts the character to a digit in the given radix, as
+    /// [`char::to_digit`] does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` is not in the range `2..=36`.
+    #[must_use]
+    pub fn to_digit(self, radix: u32) -> Option<u32> {
+        char::from(self).to_digit(radix)
+    }
+}
+
 impl TryFrom<char> for Char8 {
     type Error = CharConversionError;
 
@@ -45,6 +129,28 @@ impl From<Char8> for u8 {
     }
 }
 
+impl TryFrom<Char16> for Char8 {
+    type Error = CharConversionError;
+
+    fn try_from(value: Char16) -> Result<Self, Self::Error> {
+        u8::try_from(value.0)
+            .map(Char8)
+            .map_err(|_| CharConversionError)
+    }
+}
+
+impl PartialEq<char> for Char8 {
+    fn eq(&self, other: &char) -> bool {
+        u32::from(self.0) == u32::from(*other)
+    }
+}
+
+impl PartialEq<Char8> for char {
+    fn eq(&self, other: &Char8) -> bool {
+        other == self
+    }
+}
+
 impl fmt::Debug for Char8 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         <char as fmt::Debug>::fmt(&From::from(self.0), f)
@@ -65,6 +171,90 @@ pub const NUL_8: Char8 = Char8(0);
 #[repr(transparent)]
 pub struct Char16(u16);
 
+impl Char16 {
+    /// Returns the ASCII value of the character, if it is within the ASCII
+    /// range.
+    const fn to_ascii(self) -> Option<u8> {
+        if self.is_ascii() {
+            Some(self.0 as u8)
+        } else {
+            None
+        }
+    }
+
+    /// Checks if the character is within the ASCII range.
+    #[must_use]
+    pub const fn is_ascii(self) -> bool {
+        self.0 < 0x80
+    }
+
+    /// Checks if the character is a control character.
+    #[must_use]
+    pub const fn is_control(self) -> bool {
+        is_control(self.0)
+    }
+
+    /// Checks if the character is an ASCII decimal digit.
+    #[must_use]
+    pub const fn is_ascii_digit(self) -> bool {
+        matches!(self.to_ascii(), Some(b'0'..=b'9'))
+    }
+
+    /// Checks if the character is an ASCII letter.
+    #[must_use]
+    pub const fn is_ascii_alphabetic(self) -> bool {
+        matches!(self.to_ascii(), Some(b'A'..=b'Z' | b'a'..=b'z'))
+    }
+
+    /// Checks if the character is ASCII whitespace, as defined by
+    /// [`u8::is_ascii_whitespace`].
+    #[must_use]
+    pub const fn is_ascii_whitespace(self) -> bool {
+        matches!(
+            self.to_ascii(),
+            Some(b'\t' | b'\n' | b'\x0c' | b'\r' | b' ')
+        )
+    }
+
+    /// Returns the character with ASCII letters converted to upper case.
+    /// Other characters are returned unchanged.
+    #[must_use]
+    pub const fn to_ascii_uppercase(self) -> Self {
+        match self.to_ascii() {
+            Some(c) => Self(c.to_ascii_uppercase() as u16),
+            None => self,
+        }
+    }
+
+    /// Returns the character with ASCII letters converted to lower case.
+    /// Other characters are returned unchanged.
+    #[must_use]
+    pub const fn to_ascii_lowercase(self) -> Self {
+        match self.to_ascii() {
+            Some(c) => Self(c.to_ascii_lowercase() as u16),
+            None => self,
+        }
+    }
+
+    /// Checks if two characters are equal, ignoring the case of ASCII
+    /// letters.
+    #[must_use]
+    pub const fn eq_ignore_ascii_case(self, other: Self) -> bool {
+        self.to_ascii_lowercase().0 == other.to_ascii_lowercase().0
+    }
+
+    /// Converts the character to a digit in the given radix, as
+    /// [`char::to_digit`] does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `radix` is not in the range `2..=36`.
+    #[must_use]
+    pub fn to_digit(self, radix: u32) -> Option<u32> {
+        char::from_u32(u32::from(self.0))?.to_digit(radix)
+    }
+}
+
 impl TryFrom<char> for Char16 {
     type Error = CharConversionError;
 
@@ -104,6 +294,24 @@ impl From<Char16> for u16 {
     }
 }
 
+impl From<Char8> for Char16 {
+    fn from(value: Char8) -> Self {
+        Char16(value.0.into())
+    }
+}
+
+impl PartialEq<char> for Char16 {
+    fn eq(&self, other: &char) -> bool {
+        u32::from(self.0) == u32::from(*other)
+    }
+}
+
+impl PartialEq<Char16> for char {
+    fn eq(&self, other: &Char16) -> bool {
+        other == self
+    }
+}
+
 impl fmt::Debug for Char16 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if let Ok(c) = u32::from(self.0).try_into() {
@@ -126,3 +334,49 @@ impl fmt::Display for Char16 {
 
 /// UCS-2 version of the NUL character
 pub const NUL_16: Char16 = Char16(0);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_char8_classification() {
+        let a = Char8::from(b'a');
+        assert!(a.is_ascii() && a.is_ascii_alphabetic());
+        assert!(!a.is_ascii_digit() && !a.is_control());
+        assert_eq!(a.to_ascii_uppercase(), 'A');
+        assert!(a.eq_ignore_ascii_case(Char8::from(b'A')));
+        assert!(Char8::from(0x85).is_control());
+        assert!(!Char8::from(0xe9).is_ascii());
+        assert_eq!(Char8::from(b'f').to_digit(16), Some(15));
+    }
+
+    #[test]
+    fn test_char16_classification() {
+        let digit = Char16::try_from('7').unwrap();
+        assert!(digit.is_ascii_digit());
+        assert_eq!(digit.to_digit(10), Some(7));
+        assert!(Char16::try_from('\t').unwrap().is_ascii_whitespace());
+        assert!(Char16::try_from('\u{9f}').unwrap().is_control());
+
+        let e = Char16::try_from('é').unwrap();
+        assert!(!e.is_ascii() && !e.is_ascii_alphabetic());
+        assert_eq!(e.to_ascii_uppercase(), 'é');
+        assert_eq!(Char16::try_from('x').unwrap().to_ascii_uppercase(), 'X');
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(Char16::try_from('😀'), Err(CharConversionError));
+        assert_eq!(Char8::try_from('€'), Err(CharConversionError));
+
+        let e = Char8::try_from('é').unwrap();
+        assert_eq!(Char16::from(e), 'é');
+        assert_eq!(Char8::try_from(Char16::from(e)), Ok(e));
+        assert_eq!(
+            Char8::try_from(Char16::try_from('€').unwrap()),
+            Err(CharConversionError)
+        );
+        assert!('é' == e);
+    }
+}
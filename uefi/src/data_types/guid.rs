@@ -1,4 +1,5 @@
 use core::fmt;
+use core::str::FromStr;
 
 /// A globally unique identifier
 ///
@@ -69,6 +70,78 @@ impl Guid {
         Self { a, b, c, d }
     }
 
+    /// Parse a GUID from its canonical string representation, such as
+    /// `"12345678-9abc-def0-1234-56789abcdef0"`. Hex digits may be upper or
+    /// lower case.
+    ///
+    /// This can be used in const context. The [`guid!`] macro also parses
+    /// GUIDs at compile time, with better error messages.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`GuidFromStrError`] if `s` is not a canonical GUID string.
+    ///
+    /// [`guid!`]: crate::guid
+    pub const fn try_parse(s: &str) -> Result<Self, GuidFromStrError> {
+        let s = s.as_bytes();
+        if s.len() != 36 {
+            return Err(GuidFromStrError::Length);
+        }
+
+        // Bytes in the order they appear in the string.
+        let mut bytes = [0u8; 16];
+        let mut i = 0;
+        let mut n = 0;
+        while i < s.len() {
+            if i == 8 || i == 13 || i == 18 || i == 23 {
+                if s[i] != b'-' {
+                    return Err(GuidFromStrError::Separator(i as u8));
+                }
+                i += 1;
+                continue;
+            }
+            let hi = match hex_digit(s[i]) {
+                Some(digit) => digit,
+                None => return Err(GuidFromStrError::Hex(i as u8)),
+            };
+            let lo = match hex_digit(s[i + 1]) {
+                Some(digit) => digit,
+                None => return Err(GuidFromStrError::Hex(i as u8 + 1)),
+            };
+            bytes[n] = (hi << 4) | lo;
+            n += 1;
+            i += 2;
+        }
+
+        Ok(Self {
+            a: u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            b: u16::from_be_bytes([bytes[4], bytes[5]]),
+            c: u16::from_be_bytes([bytes[6], bytes[7]]),
+            d: [
+                bytes[8], bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14],
+                bytes[15],
+            ],
+        })
+    }
+
+    /// Parse a GUID from its canonical string representation, panicking if
+    /// it is invalid. See [`try_parse`].
+    ///
+    /// In const context, an invalid GUID is a compile-time error.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `s` is not a canonical GUID string.
+    ///
+    /// [`try_parse`]: Self::try_parse
+    #[must_use]
+    pub const fn parse_or_panic(s: &str) -> Self {
+        match Self::try_parse(s) {
+            Ok(guid) => guid,
+            Err(_) => panic!("invalid GUID string"),
+        }
+    }
+
     /// Convert to a 16-byte array.
     #[must_use]
     #[rustfmt::skip]
@@ -110,6 +183,94 @@ impl fmt::Display for Guid {
     }
 }
 
+impl FromStr for Guid {
+    type Err = GuidFromStrError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_parse(s)
+    }
+}
+
+/// Serialized as a string in the canonical format if the format is human
+/// readable, e.g. JSON, and as the 16 bytes returned by [`Guid::to_bytes`]
+/// otherwise.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Guid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_bytes(&self.to_bytes())
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Guid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct GuidVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for GuidVisitor {
+            type Value = Guid;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a GUID string or 16 bytes")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Guid, E> {
+                Guid::try_parse(s).map_err(E::custom)
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Guid, E> {
+                let bytes = bytes
+                    .try_into()
+                    .map_err(|_| E::invalid_length(bytes.len(), &self))?;
+                Ok(Guid::from_bytes(bytes))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(GuidVisitor)
+        } else {
+            deserializer.deserialize_bytes(GuidVisitor)
+        }
+    }
+}
+
+/// Get the value of an ASCII hex digit.
+const fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Error returned by [`Guid::try_parse`] and [`Guid::from_str`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GuidFromStrError {
+    /// The string is not 36 bytes long.
+    Length,
+    /// The byte at the given offset should be a `-` separator.
+    Separator(u8),
+    /// The byte at the given offset is not a hex digit.
+    Hex(u8),
+}
+
+impl fmt::Display for GuidFromStrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Length => write!(f, "GUID string has the wrong length, expected 36 bytes"),
+            Self::Separator(offset) => write!(f, "missing GUID separator at offset {offset}"),
+            Self::Hex(offset) => write!(f, "invalid GUID hex digit at offset {offset}"),
+        }
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl core::error::Error for GuidFromStrError {}
+
 /// Several entities in the UEFI specification can be referred to by their GUID,
 /// this trait is a building block to interface them in uefi-rs.
 ///
@@ -157,6 +318,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_guid_parse() {
+        const GUID: Guid = Guid::parse_or_panic("12345678-9abc-def0-1234-56789abcdef0");
+        assert_eq!(GUID, guid!("12345678-9abc-def0-1234-56789abcdef0"));
+        assert_eq!("12345678-9ABC-DEF0-1234-56789ABCDEF0".parse(), Ok(GUID));
+
+        assert_eq!(
+            Guid::try_parse("12345678-9abc-def0-1234-56789abcdef"),
+            Err(GuidFromStrError::Length)
+        );
+        assert_eq!(
+            Guid::try_parse("12345678-9abc-def0-1234+56789abcdef0"),
+            Err(GuidFromStrError::Separator(23))
+        );
+        assert_eq!(
+            Guid::try_parse("12345678-9abc-def0-1234-56789abcdeg0"),
+            Err(GuidFromStrError::Hex(34))
+        );
+        assert_eq!(
+            Guid::try_parse("+2345678-9abc-def0-1234-56789abcdef0"),
+            Err(GuidFromStrError::Hex(0))
+        );
+
+        // Round trip through `Display`.
+        let guid = guid!("8be4df61-93ca-11d2-aa0d-00e098032b8c");
+        assert_eq!(alloc::format!("{guid}").parse(), Ok(guid));
+    }
+
     #[test]
     fn test_to_from_bytes() {
         #[rustfmt::skip]
@@ -173,4 +362,14 @@ mod tests {
         );
         assert_eq!(Guid::from_bytes(bytes).to_bytes(), bytes);
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let guid = guid!("8be4df61-93ca-11d2-aa0d-00e098032b8c");
+        let json = serde_json::to_string(&guid).unwrap();
+        assert_eq!(json, "\"8be4df61-93ca-11d2-aa0d-00e098032b8c\"");
+        assert_eq!(serde_json::from_str::<Guid>(&json).unwrap(), guid);
+        assert!(serde_json::from_str::<Guid>("\"8be4df61\"").is_err());
+    }
 }
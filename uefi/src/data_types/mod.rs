@@ -35,6 +35,12 @@ impl Handle {
         // shorthand for "|ptr| Self(ptr)"
         NonNull::new(ptr).map(Self)
     }
+
+    /// Get the underlying raw pointer.
+    #[must_use]
+    pub const fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
 }
 
 /// Handle to an event structure, guaranteed to be non-null.
@@ -44,6 +50,21 @@ impl Handle {
 pub struct Event(NonNull<c_void>);
 
 impl Event {
+    /// Creates a new [`Event`] from a raw pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the pointer is valid.
+    pub unsafe fn from_ptr(ptr: *mut c_void) -> Option<Self> {
+        NonNull::new(ptr).map(Self)
+    }
+
+    /// Get the underlying raw pointer.
+    #[must_use]
+    pub const fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+
     /// Clone this `Event`
     ///
     /// # Safety
@@ -120,8 +141,8 @@ pub type PhysicalAddress = u64;
 pub type VirtualAddress = u64;
 
 mod guid;
-pub use self::guid::Guid;
 pub use self::guid::Identify;
+pub use self::guid::{Guid, GuidFromStrError};
 
 pub mod chars;
 pub use self::chars::{Char16, Char8};
@@ -139,6 +160,9 @@ mod owned_strs;
 #[cfg(feature = "alloc")]
 pub use self::owned_strs::{CString16, FromStrError};
 
+#[cfg(feature = "std")]
+mod os_strs;
+
 mod unaligned_slice;
 pub use unaligned_slice::UnalignedSlice;
 
@@ -0,0 +1,137 @@
+//! Conversions between UCS-2 strings and the `OsStr` and `Path` types of
+//! `std`.
+//!
+//! On the `*-unknown-uefi` targets with `std`, `OsStr` is UTF-16, so the
+//! conversions are exact. On other targets, which are mostly useful for
+//! host-side tests, they go through UTF-8, and fail for strings that are
+//! not valid Unicode.
+
+use super::chars::{Char16, NUL_16};
+use super::owned_strs::{CString16, FromStrError};
+use super::strs::CStr16;
+use alloc::vec::Vec;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "uefi")]
+use std::os::uefi::ffi::{OsStrExt, OsStringExt};
+
+/// Builds a `CString16` from UTF-16 code units, replacing `/` by `\` if
+/// `is_path` is true.
+fn collect_ucs2(
+    units: impl Iterator<Item = u16>,
+    is_path: bool,
+) -> Result<CString16, FromStrError> {
+    let mut output = Vec::with_capacity(units.size_hint().0 + 1);
+    for unit in units {
+        let c = Char16::try_from(unit).map_err(|_| FromStrError::InvalidChar)?;
+        if c == NUL_16 {
+            return Err(FromStrError::InteriorNul);
+        }
+        output.push(if is_path && unit == u16::from(b'/') {
+            u16::from(b'\\')
+        } else {
+            unit
+        });
+    }
+    output.push(0);
+    CString16::try_from(output).map_err(|_| FromStrError::InvalidChar)
+}
+
+#[cfg(target_os = "uefi")]
+fn os_str_to_ucs2(s: &OsStr, is_path: bool) -> Result<CString16, FromStrError> {
+    collect_ucs2(s.encode_wide(), is_path)
+}
+
+#[cfg(not(target_os = "uefi"))]
+fn os_str_to_ucs2(s: &OsStr, is_path: bool) -> Result<CString16, FromStrError> {
+    let s = s.to_str().ok_or(FromStrError::InvalidChar)?;
+    collect_ucs2(s.encode_utf16(), is_path)
+}
+
+impl CStr16 {
+    /// Converts the string to an [`OsString`].
+    #[must_use]
+    pub fn to_os_string(&self) -> OsString {
+        #[cfg(target_os = "uefi")]
+        {
+            OsString::from_wide(self.to_u16_slice())
+        }
+        #[cfg(not(target_os = "uefi"))]
+        {
+            use alloc::string::ToString;
+            OsString::from(self.to_string())
+        }
+    }
+
+    /// Converts the string, such as a UEFI file path, to a [`PathBuf`].
+    #[must_use]
+    pub fn to_path_buf(&self) -> PathBuf {
+        PathBuf::from(self.to_os_string())
+    }
+}
+
+impl From<&CStr16> for OsString {
+    fn from(s: &CStr16) -> Self {
+        s.to_os_string()
+    }
+}
+
+impl From<&CStr16> for PathBuf {
+    fn from(s: &CStr16) -> Self {
+        s.to_path_buf()
+    }
+}
+
+impl TryFrom<&OsStr> for CString16 {
+    type Error = FromStrError;
+
+    fn try_from(input: &OsStr) -> Result<Self, Self::Error> {
+        os_str_to_ucs2(input, false)
+    }
+}
+
+/// Converts a path to the form used by UEFI file protocols, in which `/`
+/// separators are replaced by `\`. This lets the same [`Path`] be used
+/// with `std::fs` and with [`File::open`].
+///
+/// [`File::open`]: crate::proto::media::file::File::open
+impl TryFrom<&Path> for CString16 {
+    type Error = FromStrError;
+
+    fn try_from(input: &Path) -> Result<Self, Self::Error> {
+        os_str_to_ucs2(input.as_os_str(), true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cstr16;
+
+    #[test]
+    fn test_os_str_round_trip() {
+        let s = CString16::try_from(OsStr::new("héllo")).unwrap();
+        assert_eq!(&*s, cstr16!("héllo"));
+        assert_eq!(OsString::from(&*s), "héllo");
+
+        assert_eq!(
+            CString16::try_from(OsStr::new("a\0b")),
+            Err(FromStrError::InteriorNul)
+        );
+        assert_eq!(
+            CString16::try_from(OsStr::new("😀")),
+            Err(FromStrError::InvalidChar)
+        );
+    }
+
+    #[test]
+    fn test_path() {
+        let path = CString16::try_from(Path::new("/EFI/BOOT/bootx64.efi")).unwrap();
+        assert_eq!(&*path, cstr16!("\\EFI\\BOOT\\bootx64.efi"));
+        assert_eq!(
+            cstr16!("EFI\\BOOT").to_path_buf(),
+            PathBuf::from("EFI\\BOOT")
+        );
+    }
+}
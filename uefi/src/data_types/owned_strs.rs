@@ -145,6 +145,35 @@ impl PartialEq<&CStr16> for CString16 {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for CString16 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+/// Fails if the string can't be converted, see [`CString16::try_from::<&str>`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CString16 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CString16Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for CString16Visitor {
+            type Value = CString16;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a UCS-2 string")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<CString16, E> {
+                CString16::try_from(s).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_str(CString16Visitor)
+    }
+}
+
 impl<StrType: AsRef<str> + ?Sized> EqStrUntilNul<StrType> for CString16 {
     fn eq_str_until_nul(&self, other: &StrType) -> bool {
         let this = self.as_ref();
@@ -427,6 +427,14 @@ impl fmt::Display for CStr16 {
     }
 }
 
+/// Serialized as a string, without the null terminator.
+#[cfg(feature = "serde")]
+impl serde::Serialize for CStr16 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
 #[cfg(feature = "alloc")]
 impl PartialEq<CString16> for &CStr16 {
     fn eq(&self, other: &CString16) -> bool {
@@ -0,0 +1,639 @@
+//! Software implementation of the UEFI and Tiano decompression algorithms.
+//!
+//! Compressed firmware volume sections are compressed with one of two
+//! variants of the same LZ77 and Huffman coding based algorithm:
+//!
+//! - [`Algorithm::Efi`], the algorithm from the UEFI specification, which
+//!   the [`Decompress`] protocol implements.
+//! - [`Algorithm::Tiano`], a variant with a larger window, used by EDK2
+//!   tools for sections with the Tiano compression GUID.
+//!
+//! This implementation doesn't depend on the firmware, so it can also be
+//! used by tools that parse firmware images outside of UEFI.
+//!
+//! Compressed data starts with an eight-byte header containing the size of
+//! the compressed data and the size of the decompressed data, as
+//! little-endian `u32`s.
+//!
+//! [`Decompress`]: crate::proto::decompress::Decompress
+
+use crate::{Error, Result, Status};
+#[cfg(feature = "alloc")]
+use {crate::mem::try_vec, alloc::vec::Vec};
+
+/// Number of bits in the bit buffer.
+const BITBUFSIZ: u32 = 32;
+/// Maximum match length.
+const MAXMATCH: usize = 256;
+/// Minimum match length.
+const THRESHOLD: usize = 3;
+/// Maximum length of a Huffman code.
+const CODE_BIT: usize = 16;
+/// Size of the character and length alphabet.
+const NC: usize = 0xff + MAXMATCH + 2 - THRESHOLD;
+/// Number of bits used to encode the number of character and length code
+/// lengths.
+const CBIT: u32 = 9;
+/// Maximum number of bits used to encode the number of position code
+/// lengths.
+const MAXPBIT: usize = 5;
+/// Number of bits used to encode the number of extra code lengths.
+const TBIT: u32 = 5;
+/// Maximum size of the position alphabet.
+const MAXNP: usize = (1 << MAXPBIT) - 1;
+/// Size of the extra alphabet, used to encode the character and length
+/// code lengths.
+const NT: usize = CODE_BIT + 3;
+/// Size of the larger of the position and extra alphabets.
+const NPT: usize = if NT > MAXNP { NT } else { MAXNP };
+/// Number of nodes in the Huffman trees.
+const TREE_SIZE: usize = 2 * NC - 1;
+
+/// Variant of the compression algorithm.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Algorithm {
+    /// The algorithm from the UEFI specification.
+    Efi,
+    /// The Tiano variant, which uses a larger window.
+    Tiano,
+}
+
+impl Algorithm {
+    /// Number of bits used to encode the number of position code lengths.
+    const fn p_bit(self) -> u32 {
+        match self {
+            Self::Efi => 4,
+            Self::Tiano => 5,
+        }
+    }
+}
+
+/// Parse the header of `source`, returning the compressed data and the
+/// size of the decompressed data.
+fn parse_header(source: &[u8]) -> Result<(&[u8], usize)> {
+    let read_u32 = |offset: usize| -> Option<usize> {
+        let bytes = source.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+    };
+    let invalid = || Error::from(Status::INVALID_PARAMETER);
+
+    let compressed_size = read_u32(0).ok_or_else(invalid)?;
+    let original_size = read_u32(4).ok_or_else(invalid)?;
+    let data = source
+        .get(8..)
+        .and_then(|data| data.get(..compressed_size))
+        .ok_or_else(invalid)?;
+    Ok((data, original_size))
+}
+
+/// Get the size of the decompressed data of `source`.
+///
+/// # Errors
+///
+/// * [`Status::INVALID_PARAMETER`]: `source` is shorter than its header,
+///   or than the size of the compressed data recorded in the header.
+pub fn decompressed_size(source: &[u8]) -> Result<usize> {
+    parse_header(source).map(|(_, size)| size)
+}
+
+/// Decompress `source` into `destination`.
+///
+/// `destination` must be at least [`decompressed_size`] bytes long. Any
+/// bytes past that size are left untouched.
+///
+/// # Errors
+///
+/// * [`Status::BUFFER_TOO_SMALL`]: `destination` is too small.
+/// * [`Status::INVALID_PARAMETER`]: the header is invalid, or the
+///   compressed data is corrupt.
+pub fn decompress(source: &[u8], destination: &mut [u8], algorithm: Algorithm) -> Result {
+    let (data, original_size) = parse_header(source)?;
+    let destination = destination
+        .get_mut(..original_size)
+        .ok_or_else(|| Error::from(Status::BUFFER_TOO_SMALL))?;
+    if destination.is_empty() {
+        return Ok(());
+    }
+
+    let mut decoder = Decoder::new(data, algorithm);
+    decoder
+        .decode(destination)
+        .map_err(|()| Status::INVALID_PARAMETER.into())
+}
+
+/// Decompress `source` into a newly allocated [`Vec`].
+///
+/// # Errors
+///
+/// See [`decompress`].
+#[cfg(feature = "alloc")]
+pub fn decompress_to_vec(source: &[u8], algorithm: Algorithm) -> Result<Vec<u8>> {
+    let mut destination = try_vec(0, decompressed_size(source)?)?;
+    decompress(source, &mut destination, algorithm)?;
+    Ok(destination)
+}
+
+/// Error returned by the decoder for corrupt data.
+type DecodeResult<T = ()> = core::result::Result<T, ()>;
+
+/// Location of a Huffman table entry or tree link, used while building a
+/// table.
+#[derive(Clone, Copy)]
+enum Slot {
+    Table(usize),
+    Left(usize),
+    Right(usize),
+}
+
+/// Huffman tree links shared by all the tables of a decoder.
+struct Tree {
+    left: [u16; TREE_SIZE],
+    right: [u16; TREE_SIZE],
+}
+
+impl Tree {
+    /// Follow the tree from `node` using the bits of `bit_buf` that come
+    /// after the first `table_bits`, until reaching a symbol below `limit`.
+    fn walk(
+        &self,
+        mut node: u16,
+        bit_buf: u32,
+        table_bits: u32,
+        limit: usize,
+    ) -> DecodeResult<u16> {
+        let mut mask = 1u32 << (BITBUFSIZ - 1 - table_bits);
+        // Links always point to a node allocated later, or to a symbol, so a
+        // valid tree is never deeper than its number of nodes.
+        for _ in 0..TREE_SIZE {
+            if usize::from(node) < limit {
+                return Ok(node);
+            }
+            let links = if bit_buf & mask != 0 {
+                &self.right
+            } else {
+                &self.left
+            };
+            node = *links.get(usize::from(node)).ok_or(())?;
+            mask >>= 1;
+        }
+        Err(())
+    }
+
+    /// Build the lookup table for the code with the given code lengths.
+    /// Codes longer than `table_bits` continue in the tree.
+    fn make_table(&mut self, bit_len: &[u8], table_bits: u32, table: &mut [u16]) -> DecodeResult {
+        let num_of_char = bit_len.len();
+        let mut count = [0u16; 17];
+        let mut weight = [0u16; 17];
+        let mut start = [0u16; 18];
+
+        for &len in bit_len {
+            *count.get_mut(usize::from(len)).ok_or(())? += 1;
+        }
+
+        for i in 1..=16 {
+            let shifted = (u32::from(count[i]) << (16 - i)) as u16;
+            start[i + 1] = start[i].wrapping_add(shifted);
+        }
+        // The code lengths must describe a complete code.
+        if start[17] != 0 {
+            return Err(());
+        }
+
+        let ju_bits = 16 - table_bits;
+        for i in 1..=table_bits as usize {
+            start[i] >>= ju_bits;
+            weight[i] = 1 << (table_bits as usize - i);
+        }
+        for (i, weight) in weight.iter_mut().enumerate().skip(table_bits as usize + 1) {
+            *weight = 1 << (16 - i);
+        }
+
+        let index = usize::from(start[table_bits as usize + 1] >> ju_bits);
+        let max_table_len = 1usize << table_bits;
+        if index != 0 && index < max_table_len {
+            table[index..max_table_len].fill(0);
+        }
+
+        let mut avail = num_of_char;
+        let mask = 1u16 << (15 - table_bits);
+
+        for (ch, &len) in bit_len.iter().enumerate() {
+            let len = usize::from(len);
+            if len == 0 || len >= 17 {
+                continue;
+            }
+            let next_code = start[len].wrapping_add(weight[len]);
+
+            if len <= table_bits as usize {
+                if start[len] >= next_code || usize::from(next_code) > max_table_len {
+                    return Err(());
+                }
+                table[usize::from(start[len])..usize::from(next_code)].fill(ch as u16);
+            } else {
+                let mut code = start[len];
+                let mut slot = Slot::Table(usize::from(code >> ju_bits));
+                for _ in 0..len - table_bits as usize {
+                    if self.get(table, slot) == 0 && avail < TREE_SIZE {
+                        self.left[avail] = 0;
+                        self.right[avail] = 0;
+                        self.set(table, slot, avail as u16);
+                        avail += 1;
+                    }
+                    let node = usize::from(self.get(table, slot));
+                    if node < TREE_SIZE {
+                        slot = if code & mask != 0 {
+                            Slot::Right(node)
+                        } else {
+                            Slot::Left(node)
+                        };
+                    }
+                    code <<= 1;
+                }
+                self.set(table, slot, ch as u16);
+            }
+
+            start[len] = next_code;
+        }
+        Ok(())
+    }
+
+    fn get(&self, table: &[u16], slot: Slot) -> u16 {
+        match slot {
+            Slot::Table(i) => table[i],
+            Slot::Left(i) => self.left[i],
+            Slot::Right(i) => self.right[i],
+        }
+    }
+
+    fn set(&mut self, table: &mut [u16], slot: Slot, value: u16) {
+        match slot {
+            Slot::Table(i) => table[i] = value,
+            Slot::Left(i) => self.left[i] = value,
+            Slot::Right(i) => self.right[i] = value,
+        }
+    }
+}
+
+/// State of the decompression of one buffer.
+struct Decoder<'a> {
+    source: &'a [u8],
+    p_bit: u32,
+    bit_buf: u32,
+    sub_bit_buf: u32,
+    bit_count: u32,
+    block_size: u16,
+    tree: Tree,
+    c_len: [u8; NC],
+    pt_len: [u8; NPT],
+    c_table: [u16; 4096],
+    pt_table: [u16; 256],
+}
+
+impl<'a> Decoder<'a> {
+    fn new(source: &'a [u8], algorithm: Algorithm) -> Self {
+        let mut decoder = Self {
+            source,
+            p_bit: algorithm.p_bit(),
+            bit_buf: 0,
+            sub_bit_buf: 0,
+            bit_count: 0,
+            block_size: 0,
+            tree: Tree {
+                left: [0; TREE_SIZE],
+                right: [0; TREE_SIZE],
+            },
+            c_len: [0; NC],
+            pt_len: [0; NPT],
+            c_table: [0; 4096],
+            pt_table: [0; 256],
+        };
+        decoder.fill_buf(BITBUFSIZ);
+        decoder
+    }
+
+    /// Shift `num_bits` bits out of the bit buffer, and refill it from the
+    /// source. Zeros are shifted in once the source is exhausted.
+    fn fill_buf(&mut self, mut num_bits: u32) {
+        self.bit_buf = (u64::from(self.bit_buf) << num_bits) as u32;
+        while num_bits > self.bit_count {
+            num_bits -= self.bit_count;
+            self.bit_buf |= (u64::from(self.sub_bit_buf) << num_bits) as u32;
+            match self.source.split_first() {
+                Some((&byte, rest)) => {
+                    self.sub_bit_buf = u32::from(byte);
+                    self.source = rest;
+                }
+                None => self.sub_bit_buf = 0,
+            }
+            self.bit_count = 8;
+        }
+        self.bit_count -= num_bits;
+        self.bit_buf |= self.sub_bit_buf >> self.bit_count;
+    }
+
+    /// Read the next `num_bits` bits.
+    fn get_bits(&mut self, num_bits: u32) -> u32 {
+        let bits = self.bit_buf >> (BITBUFSIZ - num_bits);
+        self.fill_buf(num_bits);
+        bits
+    }
+
+    /// Read the code lengths of the extra or position alphabet, which has
+    /// `nn` symbols, and build its table. After `special` code lengths, a
+    /// 2-bit count of zero lengths follows.
+    fn read_pt_len(&mut self, nn: usize, nbit: u32, special: Option<usize>) -> DecodeResult {
+        let number = self.get_bits(nbit) as usize;
+        if number == 0 {
+            // Only one symbol is used, and its code is empty.
+            let ch = self.get_bits(nbit) as u16;
+            self.pt_table.fill(ch);
+            self.pt_len[..nn].fill(0);
+            return Ok(());
+        }
+
+        let mut index = 0;
+        while index < number && index < NPT {
+            // Code lengths below 7 are encoded in 3 bits. Longer lengths are
+            // encoded as 7 followed by a 1 bit for each additional length,
+            // and a terminating 0 bit.
+            let mut len = self.bit_buf >> (BITBUFSIZ - 3);
+            if len == 7 {
+                let mut mask = 1u32 << (BITBUFSIZ - 1 - 3);
+                while mask & self.bit_buf != 0 {
+                    mask >>= 1;
+                    len += 1;
+                }
+            }
+            self.fill_buf(if len < 7 { 3 } else { len - 3 });
+            self.pt_len[index] = len as u8;
+            index += 1;
+
+            if Some(index) == special {
+                let zeros = self.get_bits(2) as usize;
+                let end = (index + zeros).min(NPT);
+                self.pt_len[index..end].fill(0);
+                index = end;
+            }
+        }
+        if index < nn {
+            self.pt_len[index..nn].fill(0);
+        }
+
+        self.tree
+            .make_table(&self.pt_len[..nn], 8, &mut self.pt_table)
+    }
+
+    /// Read the code lengths of the character and length alphabet, and
+    /// build its table.
+    fn read_c_len(&mut self) -> DecodeResult {
+        let number = self.get_bits(CBIT) as usize;
+        if number == 0 {
+            // Only one symbol is used, and its code is empty.
+            let ch = self.get_bits(CBIT) as u16;
+            self.c_len.fill(0);
+            self.c_table.fill(ch);
+            return Ok(());
+        }
+
+        let mut index = 0;
+        while index < number && index < NC {
+            let ch = self.pt_table[(self.bit_buf >> (BITBUFSIZ - 8)) as usize];
+            let ch = usize::from(self.tree.walk(ch, self.bit_buf, 8, NT)?);
+            self.fill_buf(u32::from(self.pt_len[ch]));
+
+            if ch <= 2 {
+                // Symbols 0 to 2 encode runs of zero lengths.
+                let zeros = match ch {
+                    0 => 1,
+                    1 => self.get_bits(4) as usize + 3,
+                    _ => self.get_bits(CBIT) as usize + 20,
+                };
+                let end = (index + zeros).min(NC);
+                self.c_len[index..end].fill(0);
+                index = end;
+            } else {
+                self.c_len[index] = (ch - 2) as u8;
+                index += 1;
+            }
+        }
+        self.c_len[index..].fill(0);
+
+        // As in the reference implementation, an invalid table is not an
+        // error by itself; decoding with it fails instead.
+        let _ = self.tree.make_table(&self.c_len, 12, &mut self.c_table);
+        Ok(())
+    }
+
+    /// Decode the next character or match length symbol, reading a new
+    /// block header first if the current block is complete.
+    fn decode_c(&mut self) -> DecodeResult<usize> {
+        if self.block_size == 0 {
+            self.block_size = self.get_bits(16) as u16;
+            self.read_pt_len(NT, TBIT, Some(3))?;
+            self.read_c_len()?;
+            self.read_pt_len(MAXNP, self.p_bit, None)?;
+        }
+        self.block_size = self.block_size.wrapping_sub(1);
+
+        let ch = self.c_table[(self.bit_buf >> (BITBUFSIZ - 12)) as usize];
+        let ch = usize::from(self.tree.walk(ch, self.bit_buf, 12, NC)?);
+        self.fill_buf(u32::from(self.c_len[ch]));
+        Ok(ch)
+    }
+
+    /// Decode the next match position, as a distance before the current
+    /// position minus one.
+    fn decode_p(&mut self) -> DecodeResult<usize> {
+        let val = self.pt_table[(self.bit_buf >> (BITBUFSIZ - 8)) as usize];
+        let val = u32::from(self.tree.walk(val, self.bit_buf, 8, MAXNP)?);
+        self.fill_buf(u32::from(self.pt_len[val as usize]));
+
+        let pos = if val > 1 {
+            (1 << (val - 1)) + self.get_bits(val - 1)
+        } else {
+            val
+        };
+        Ok(pos as usize)
+    }
+
+    /// Decode the compressed data until `destination` is full.
+    fn decode(&mut self, destination: &mut [u8]) -> DecodeResult {
+        let mut out = 0;
+        while out < destination.len() {
+            let ch = self.decode_c()?;
+            if ch < 256 {
+                destination[out] = ch as u8;
+                out += 1;
+            } else {
+                let len = ch - (256 - THRESHOLD);
+                let distance = self.decode_p()? + 1;
+                if distance > out {
+                    return Err(());
+                }
+                // The match may overlap the bytes it produces, so copy it one
+                // byte at a time.
+                let end = (out + len).min(destination.len());
+                while out < end {
+                    destination[out] = destination[out - distance];
+                    out += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    /// Writes bits most significant bit first, like the compressor.
+    #[derive(Default)]
+    struct BitWriter {
+        bytes: Vec<u8>,
+        len: usize,
+    }
+
+    impl BitWriter {
+        fn write(&mut self, value: u32, bits: u32) {
+            for i in (0..bits).rev() {
+                if self.len % 8 == 0 {
+                    self.bytes.push(0);
+                }
+                if value & (1 << i) != 0 {
+                    *self.bytes.last_mut().unwrap() |= 0x80 >> (self.len % 8);
+                }
+                self.len += 1;
+            }
+        }
+
+        /// Get the data with the compression header.
+        fn finish(self, original_size: u32) -> Vec<u8> {
+            let mut out = Vec::new();
+            out.extend((self.bytes.len() as u32).to_le_bytes());
+            out.extend(original_size.to_le_bytes());
+            out.extend(self.bytes);
+            out
+        }
+    }
+
+    /// Compress "aaaa" as the literal 'a' followed by a match of length 3
+    /// at distance 1.
+    fn compressed_aaaa(algorithm: Algorithm) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        // Block of 2 symbols.
+        w.write(2, 16);
+
+        // Extra alphabet: symbols 2 and 3 have 1-bit codes, 0 and 1.
+        w.write(4, TBIT);
+        w.write(0, 3);
+        w.write(0, 3);
+        w.write(1, 3);
+        // No zero lengths after the third length.
+        w.write(0, 2);
+        w.write(1, 3);
+
+        // Character and length alphabet: 'a' and the match of length 3
+        // (symbol 256) have 1-bit codes, 0 and 1.
+        w.write(257, CBIT);
+        // 97 zeros, then a length of 1 for 'a'.
+        w.write(0, 1);
+        w.write(97 - 20, CBIT);
+        w.write(1, 1);
+        // 158 zeros, then a length of 1 for symbol 256.
+        w.write(0, 1);
+        w.write(158 - 20, CBIT);
+        w.write(1, 1);
+
+        // Position alphabet: only position 0 is used.
+        w.write(0, algorithm.p_bit());
+        w.write(0, algorithm.p_bit());
+
+        // 'a', then the match.
+        w.write(0, 1);
+        w.write(1, 1);
+
+        w.finish(4)
+    }
+
+    #[test]
+    fn test_decompress() {
+        for algorithm in [Algorithm::Efi, Algorithm::Tiano] {
+            let source = compressed_aaaa(algorithm);
+            assert_eq!(decompressed_size(&source), Ok(4));
+            assert_eq!(decompress_to_vec(&source, algorithm).unwrap(), b"aaaa");
+
+            // Bytes after the decompressed data are left untouched.
+            let mut destination = [0xff; 6];
+            decompress(&source, &mut destination, algorithm).unwrap();
+            assert_eq!(destination, *b"aaaa\xff\xff");
+        }
+    }
+
+    #[test]
+    fn test_decompress_single_char() {
+        // A block in which only 'A' is used, so its code is empty and the
+        // block contains no data bits.
+        let mut w = BitWriter::default();
+        w.write(4, 16);
+        w.write(0, TBIT);
+        w.write(0, TBIT);
+        w.write(0, CBIT);
+        w.write(u32::from(b'A'), CBIT);
+        w.write(0, 4);
+        w.write(0, 4);
+        let source = w.finish(4);
+        assert_eq!(decompress_to_vec(&source, Algorithm::Efi).unwrap(), b"AAAA");
+    }
+
+    #[test]
+    fn test_decompress_empty() {
+        let source = [0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(decompress_to_vec(&source, Algorithm::Efi).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_decompress_errors() {
+        // Truncated header and data.
+        assert_eq!(
+            decompressed_size(&[0; 7]).unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+        assert_eq!(
+            decompressed_size(&[1, 0, 0, 0, 4, 0, 0, 0])
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+
+        // Destination too small.
+        let source = compressed_aaaa(Algorithm::Efi);
+        assert_eq!(
+            decompress(&source, &mut [0; 3], Algorithm::Efi)
+                .unwrap_err()
+                .status(),
+            Status::BUFFER_TOO_SMALL
+        );
+
+        // A match before the start of the data.
+        let mut w = BitWriter::default();
+        w.write(1, 16);
+        // Extra alphabet with a single symbol.
+        w.write(0, TBIT);
+        w.write(0, TBIT);
+        // Character and length alphabet with only a match of length 3.
+        w.write(0, CBIT);
+        w.write(256, CBIT);
+        // Position alphabet with only position 0.
+        w.write(0, 4);
+        w.write(0, 4);
+        let source = w.finish(4);
+        assert_eq!(
+            decompress_to_vec(&source, Algorithm::Efi)
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+    }
+}
@@ -0,0 +1,178 @@
+//! Minimal executor for driving futures with UEFI events.
+//!
+//! UEFI has no threads, but many operations complete asynchronously
+//! by signaling an [`Event`]. This module adapts events to Rust
+//! [`Future`]s so such operations can be written with `async`/`await`:
+//!
+//! - [`EventFuture`] resolves when an event is signaled.
+//! - [`Timer`] resolves after a given duration.
+//! - [`block_on`] runs a future to completion, sleeping in
+//!   [`BootServices::wait_for_event`] while all the events the future
+//!   is waiting on are unsignaled.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use core::time::Duration;
+//! use uefi::executor::{block_on, Timer};
+//! use uefi::table::boot::BootServices;
+//!
+//! fn wait_a_bit(bt: &BootServices) -> uefi::Result {
+//!     block_on(bt, async {
+//!         Timer::new(bt, Duration::from_millis(100))?.await?;
+//!         Timer::new(bt, Duration::from_millis(100))?.await
+//!     })
+//! }
+//! ```
+
+use crate::table::boot::{BootServices, EventType, TimerTrigger, Tpl};
+use crate::time::timer_period;
+use crate::{Event, Result};
+use alloc::vec::Vec;
+use core::future::Future;
+use core::pin::Pin;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use core::time::Duration;
+
+/// Set when the waker passed to the future by [`block_on`] is woken.
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
+/// Events that the future polled by [`block_on`] is waiting on. Only
+/// non-null while [`block_on`] is polling.
+static WAIT_LIST: AtomicPtr<Vec<Event>> = AtomicPtr::new(ptr::null_mut());
+
+static WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(
+    |_| RawWaker::new(ptr::null(), &WAKER_VTABLE),
+    |_| WOKEN.store(true, Ordering::Relaxed),
+    |_| WOKEN.store(true, Ordering::Relaxed),
+    |_| {},
+);
+
+/// Poll `event`, registering it with [`block_on`] if it is not yet
+/// signaled.
+fn poll_event(bt: &BootServices, event: &Event, cx: &mut Context<'_>) -> Poll<Result> {
+    // Safety: the clone is only passed to firmware.
+    match bt.check_event(unsafe { event.unsafe_clone() }) {
+        Ok(true) => Poll::Ready(Ok(())),
+        Ok(false) => {
+            let wait_list = WAIT_LIST.load(Ordering::Relaxed);
+            if wait_list.is_null() {
+                // Polled by some other executor, which has no way to
+                // wait on the event. Ask to be polled again.
+                cx.waker().wake_by_ref();
+            } else {
+                // Safety: `block_on` keeps the list alive while polling.
+                unsafe { (*wait_list).push(event.unsafe_clone()) };
+            }
+            Poll::Pending
+        }
+        Err(err) => Poll::Ready(Err(err)),
+    }
+}
+
+/// Future that resolves when an event is signaled.
+///
+/// The event must not have the [`EventType::NOTIFY_SIGNAL`] type, since
+/// such events cannot be checked; polling one resolves to an
+/// [`INVALID_PARAMETER`] error.
+///
+/// [`INVALID_PARAMETER`]: crate::Status::INVALID_PARAMETER
+pub struct EventFuture<'a> {
+    bt: &'a BootServices,
+    event: &'a Event,
+}
+
+impl<'a> EventFuture<'a> {
+    /// Create a future that resolves when `event` is signaled.
+    #[must_use]
+    pub const fn new(bt: &'a BootServices, event: &'a Event) -> Self {
+        Self { bt, event }
+    }
+}
+
+impl Future for EventFuture<'_> {
+    type Output = Result;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result> {
+        poll_event(self.bt, self.event, cx)
+    }
+}
+
+/// Future that resolves once a duration has elapsed.
+///
+/// The underlying timer event is closed when the future is dropped.
+pub struct Timer<'a> {
+    bt: &'a BootServices,
+    event: Event,
+}
+
+impl<'a> Timer<'a> {
+    /// Create a timer that resolves after `duration`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the timer event cannot be created or armed.
+    pub fn new(bt: &'a BootServices, duration: Duration) -> Result<Self> {
+        let event = unsafe { bt.create_event(EventType::TIMER, Tpl::APPLICATION, None, None) }?;
+        let timer = Self { bt, event };
+        bt.set_timer(&timer.event, TimerTrigger::Relative(timer_period(duration)))?;
+        Ok(timer)
+    }
+}
+
+impl Future for Timer<'_> {
+    type Output = Result;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result> {
+        poll_event(self.bt, &self.event, cx)
+    }
+}
+
+impl Drop for Timer<'_> {
+    fn drop(&mut self) {
+        // Safety: the event is not used after this point.
+        let _ = self.bt.close_event(unsafe { self.event.unsafe_clone() });
+    }
+}
+
+/// Run `future` to completion.
+///
+/// While the future is pending on [`EventFuture`]s or [`Timer`]s, the
+/// CPU idles in [`BootServices::wait_for_event`]. Futures that are
+/// pending on anything else are polled again immediately.
+///
+/// This must be called at [`Tpl::APPLICATION`], since that is the only
+/// level at which [`BootServices::wait_for_event`] may be used. At any
+/// other level this degrades to busy polling.
+pub fn block_on<F: Future>(bt: &BootServices, future: F) -> F::Output {
+    let mut future = future;
+    // Safety: `future` is shadowed, so it is never moved again until it is
+    // dropped at the end of this function.
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+    // Safety: the vtable functions ignore the data pointer.
+    let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &WAKER_VTABLE)) };
+    let mut cx = Context::from_waker(&waker);
+    let mut events = Vec::new();
+
+    loop {
+        events.clear();
+        WOKEN.store(false, Ordering::Relaxed);
+        let prev = WAIT_LIST.swap(&mut events, Ordering::Relaxed);
+        let poll = future.as_mut().poll(&mut cx);
+        WAIT_LIST.store(prev, Ordering::Relaxed);
+
+        if let Poll::Ready(output) = poll {
+            return output;
+        }
+        if WOKEN.load(Ordering::Relaxed) || events.is_empty() {
+            continue;
+        }
+        if let Ok(index) = bt.wait_for_event(&mut events) {
+            // Waiting clears the signaled state of the event. Signal it
+            // again so that the next poll of its future sees it.
+            let _ = bt.signal_event(&events[index]);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::mem::try_vec;
+use crate::proto::media::block::{BlockIO, BlockIOMedia};
+use crate::proto::media::disk::DiskIo;
+use crate::Result;
+
+/// A device holding a FAT volume, accessed at byte granularity.
+///
+/// This is implemented for the [`BlockIO`] protocol and for
+/// [`DiskIoDevice`]. The volume starts at offset 0, so the device should be
+/// a partition rather than a whole disk.
+pub trait FatDevice {
+    /// Get the size of a sector of the device, in bytes. Formatted volumes
+    /// use this sector size.
+    fn sector_size(&self) -> u32;
+
+    /// Get the size of the device, in bytes.
+    fn size(&self) -> u64;
+
+    /// Read `buffer.len()` bytes at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Device-specific errors.
+    fn read(&mut self, offset: u64, buffer: &mut [u8]) -> Result;
+
+    /// Write `buffer` at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Device-specific errors.
+    fn write(&mut self, offset: u64, buffer: &[u8]) -> Result;
+
+    /// Flush the data written to the device.
+    ///
+    /// # Errors
+    ///
+    /// Device-specific errors.
+    fn flush(&mut self) -> Result {
+        Ok(())
+    }
+}
+
+impl FatDevice for BlockIO {
+    fn sector_size(&self) -> u32 {
+        self.media().block_size()
+    }
+
+    fn size(&self) -> u64 {
+        let media = self.media();
+        (media.last_block() + 1) * u64::from(media.block_size())
+    }
+
+    fn read(&mut self, offset: u64, buffer: &mut [u8]) -> Result {
+        let media_id = self.media().media_id();
+        let block_size = u64::from(self.media().block_size());
+        let mut block = try_vec(0, block_size as usize)?;
+        let mut offset = offset;
+        let mut buffer = buffer;
+        while !buffer.is_empty() {
+            let lba = offset / block_size;
+            let start = (offset % block_size) as usize;
+            if start == 0 && buffer.len() as u64 >= block_size {
+                // Read the whole blocks directly.
+                let len = (buffer.len() as u64 / block_size * block_size) as usize;
+                let (blocks, rest) = buffer.split_at_mut(len);
+                self.read_blocks(media_id, lba, blocks)?;
+                buffer = rest;
+                offset += len as u64;
+            } else {
+                let len = buffer.len().min(block.len() - start);
+                self.read_blocks(media_id, lba, &mut block)?;
+                let (part, rest) = buffer.split_at_mut(len);
+                part.copy_from_slice(&block[start..start + len]);
+                buffer = rest;
+                offset += len as u64;
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u64, buffer: &[u8]) -> Result {
+        let media_id = self.media().media_id();
+        let block_size = u64::from(self.media().block_size());
+        let mut block = try_vec(0, block_size as usize)?;
+        let mut offset = offset;
+        let mut buffer = buffer;
+        while !buffer.is_empty() {
+            let lba = offset / block_size;
+            let start = (offset % block_size) as usize;
+            if start == 0 && buffer.len() as u64 >= block_size {
+                // Write the whole blocks directly.
+                let len = (buffer.len() as u64 / block_size * block_size) as usize;
+                let (blocks, rest) = buffer.split_at(len);
+                self.write_blocks(media_id, lba, blocks)?;
+                buffer = rest;
+                offset += len as u64;
+            } else {
+                // Read-modify-write the partial block.
+                let len = buffer.len().min(block.len() - start);
+                self.read_blocks(media_id, lba, &mut block)?;
+                let (part, rest) = buffer.split_at(len);
+                block[start..start + len].copy_from_slice(part);
+                self.write_blocks(media_id, lba, &block)?;
+                buffer = rest;
+                offset += len as u64;
+            }
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result {
+        self.flush_blocks()
+    }
+}
+
+/// A [`FatDevice`] accessed through the [`DiskIo`] protocol.
+pub struct DiskIoDevice<'a> {
+    disk_io: &'a mut DiskIo,
+    media_id: u32,
+    sector_size: u32,
+    size: u64,
+}
+
+impl<'a> DiskIoDevice<'a> {
+    /// Create a device accessing the medium described by `media`, which is
+    /// the media of the [`BlockIO`] protocol on the same handle as
+    /// `disk_io`.
+    #[must_use]
+    pub fn new(disk_io: &'a mut DiskIo, media: &BlockIOMedia) -> Self {
+        Self {
+            disk_io,
+            media_id: media.media_id(),
+            sector_size: media.block_size(),
+            size: (media.last_block() + 1) * u64::from(media.block_size()),
+        }
+    }
+}
+
+impl FatDevice for DiskIoDevice<'_> {
+    fn sector_size(&self) -> u32 {
+        self.sector_size
+    }
+
+    fn size(&self) -> u64 {
+        self.size
+    }
+
+    fn read(&mut self, offset: u64, buffer: &mut [u8]) -> Result {
+        self.disk_io.read_disk(self.media_id, offset, buffer)
+    }
+
+    fn write(&mut self, offset: u64, buffer: &[u8]) -> Result {
+        self.disk_io.write_disk(self.media_id, offset, buffer)
+    }
+}
+
+impl<D: FatDevice + ?Sized> FatDevice for &mut D {
+    fn sector_size(&self) -> u32 {
+        (**self).sector_size()
+    }
+
+    fn size(&self) -> u64 {
+        (**self).size()
+    }
+
+    fn read(&mut self, offset: u64, buffer: &mut [u8]) -> Result {
+        (**self).read(offset, buffer)
+    }
+
+    fn write(&mut self, offset: u64, buffer: &[u8]) -> Result {
+        (**self).write(offset, buffer)
+    }
+
+    fn flush(&mut self) -> Result {
+        (**self).flush()
+    }
+}
@@ -0,0 +1,422 @@
+//! Directory entries and file names.
+
+use super::FatAttributes;
+use crate::mem::{try_reserve, try_vec};
+use crate::util::div_ceil_usize;
+use crate::{Result, Status};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Size of a directory entry, in bytes.
+pub(super) const ENTRY_SIZE: usize = 32;
+
+/// First byte of a deleted entry.
+pub(super) const DELETED: u8 = 0xe5;
+
+/// Attributes of a long name entry.
+const LONG_NAME: u8 = 0x0f;
+
+/// Number of UTF-16 characters in a long name entry.
+const LONG_NAME_CHARS: usize = 13;
+
+/// Offsets of the characters of a long name entry.
+const LONG_NAME_OFFSETS: [usize; LONG_NAME_CHARS] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+
+/// Maximum length of a long name, in UTF-16 characters.
+const MAX_LONG_NAME: usize = 255;
+
+/// Case flag of a short name whose base is lowercase.
+const LOWERCASE_BASE: u8 = 0x08;
+
+/// Case flag of a short name whose extension is lowercase.
+const LOWERCASE_EXT: u8 = 0x10;
+
+/// Creation and modification date of new entries, 1980-01-01.
+const DEFAULT_DATE: u16 = 0x0021;
+
+/// A directory entry, with its long name if it has one.
+#[derive(Clone, Debug)]
+pub(super) struct RawEntry {
+    /// Long name, or short name if the entry has no long name.
+    pub(super) name: String,
+    pub(super) short_name: [u8; 11],
+    pub(super) attributes: FatAttributes,
+    pub(super) first_cluster: u32,
+    pub(super) size: u32,
+    /// Index of the first slot of the entry, which is the first long name
+    /// slot if the entry has a long name.
+    pub(super) first_slot: usize,
+    /// Index of the short name slot.
+    pub(super) slot: usize,
+}
+
+impl RawEntry {
+    /// True for the `.` and `..` entries.
+    pub(super) fn is_dot(&self) -> bool {
+        self.short_name[0] == b'.'
+    }
+
+    /// True if `name` matches the long or short name, ignoring case.
+    pub(super) fn matches(&self, name: &str) -> bool {
+        eq_ignore_case(&self.name, name)
+            || eq_ignore_case(&short_name_str(&self.short_name, 0), name)
+    }
+}
+
+/// Compare two names, ignoring case.
+fn eq_ignore_case(a: &str, b: &str) -> bool {
+    a.chars()
+        .flat_map(char::to_lowercase)
+        .eq(b.chars().flat_map(char::to_lowercase))
+}
+
+/// Parse the entries of a directory, skipping deleted entries. Parsing stops
+/// at the end-of-directory marker.
+pub(super) fn parse_entries(bytes: &[u8]) -> Result<Vec<RawEntry>> {
+    let mut entries = Vec::new();
+    // Characters, checksum, next expected ordinal and first slot of the
+    // long name being parsed.
+    let mut long_name: Option<(Vec<u16>, u8, u8, usize)> = None;
+
+    for (slot, raw) in bytes.chunks_exact(ENTRY_SIZE).enumerate() {
+        match raw[0] {
+            0 => break,
+            DELETED => {
+                long_name = None;
+                continue;
+            }
+            _ => {}
+        }
+
+        if raw[11] & 0x3f == LONG_NAME {
+            let ordinal = raw[0] & 0x1f;
+            let chars = LONG_NAME_OFFSETS
+                .iter()
+                .map(|&offset| u16::from_le_bytes([raw[offset], raw[offset + 1]]));
+            if raw[0] & 0x40 != 0 {
+                let mut name = try_vec(0xffff, usize::from(ordinal) * LONG_NAME_CHARS)?;
+                let start = (usize::from(ordinal).max(1) - 1) * LONG_NAME_CHARS;
+                for (dst, c) in name[start..].iter_mut().zip(chars) {
+                    *dst = c;
+                }
+                long_name = Some((name, raw[13], ordinal.wrapping_sub(1), slot));
+            } else if let Some((name, checksum, next, _)) = &mut long_name {
+                if ordinal == *next && ordinal != 0 && raw[13] == *checksum {
+                    let start = (usize::from(ordinal) - 1) * LONG_NAME_CHARS;
+                    for (dst, c) in name[start..].iter_mut().zip(chars) {
+                        *dst = c;
+                    }
+                    *next -= 1;
+                } else {
+                    long_name = None;
+                }
+            }
+            continue;
+        }
+
+        let short_name: [u8; 11] = raw[..11].try_into().unwrap();
+        let (name, first_slot) = match long_name.take() {
+            Some((name, checksum, 0, first_slot)) if checksum == lfn_checksum(&short_name) => {
+                let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+                let name = char::decode_utf16(name[..len].iter().copied())
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect();
+                (name, first_slot)
+            }
+            _ => (short_name_str(&short_name, raw[12]), slot),
+        };
+        let cluster_hi = u16::from_le_bytes([raw[20], raw[21]]);
+        let cluster_lo = u16::from_le_bytes([raw[26], raw[27]]);
+        try_reserve(&mut entries, 1)?;
+        entries.push(RawEntry {
+            name,
+            short_name,
+            attributes: FatAttributes::from_bits_truncate(raw[11]),
+            first_cluster: u32::from(cluster_hi) << 16 | u32::from(cluster_lo),
+            size: u32::from_le_bytes(raw[28..32].try_into().unwrap()),
+            first_slot,
+            slot,
+        });
+    }
+    Ok(entries)
+}
+
+/// Format a short name as `BASE.EXT`, applying the case flags.
+fn short_name_str(short_name: &[u8; 11], case_flags: u8) -> String {
+    let part = |bytes: &[u8], lowercase: bool| -> String {
+        bytes
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| if i == 0 && b == 0x05 { DELETED } else { b })
+            .map(|b| match b {
+                b if !b.is_ascii() => char::REPLACEMENT_CHARACTER,
+                b if lowercase => char::from(b.to_ascii_lowercase()),
+                b => char::from(b),
+            })
+            .collect::<String>()
+            .trim_end_matches(' ')
+            .into()
+    };
+    let mut name = part(&short_name[..8], case_flags & LOWERCASE_BASE != 0);
+    let ext = part(&short_name[8..], case_flags & LOWERCASE_EXT != 0);
+    if !ext.is_empty() {
+        name.push('.');
+        name.push_str(&ext);
+    }
+    name
+}
+
+/// Compute the checksum of a short name, stored in its long name entries.
+pub(super) fn lfn_checksum(short_name: &[u8; 11]) -> u8 {
+    short_name.iter().fold(0u8, |sum, &b| {
+        (sum >> 1).wrapping_add(sum << 7).wrapping_add(b)
+    })
+}
+
+/// True if `c` is allowed in a short name, in addition to uppercase
+/// letters and digits.
+pub(super) fn is_short_name_symbol(c: char) -> bool {
+    "!#$%&'()-@^_`{}~".contains(c)
+}
+
+/// Check that `name` is a valid long name, and encode it in UTF-16.
+pub(super) fn encode_long_name(name: &str) -> Result<Vec<u16>> {
+    let invalid = |c: char| c < ' ' || "\"*/:<>?\\|".contains(c);
+    let encoded: Vec<u16> = name.encode_utf16().collect();
+    if encoded.is_empty()
+        || encoded.len() > MAX_LONG_NAME
+        || name.chars().any(invalid)
+        || name.ends_with(['.', ' '])
+    {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+    Ok(encoded)
+}
+
+/// Get the short name and case flags of `name` if it can be stored without
+/// a long name: an 8.3 name whose base and extension are each either
+/// uppercase or lowercase.
+pub(super) fn exact_short_name(name: &str) -> Option<([u8; 11], u8)> {
+    let (base, ext) = match name.split_once('.') {
+        Some((base, ext)) => (base, ext),
+        None => (name, ""),
+    };
+    if base.is_empty() || base.len() > 8 || ext.len() > 3 || ext.contains('.') {
+        return None;
+    }
+
+    let mut short_name = [b' '; 11];
+    let mut case_flags = 0;
+    let (base_dst, ext_dst) = short_name.split_at_mut(8);
+    for (part, dst, flag) in [
+        (base, base_dst, LOWERCASE_BASE),
+        (ext, ext_dst, LOWERCASE_EXT),
+    ] {
+        if !part
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || is_short_name_symbol(c))
+        {
+            return None;
+        }
+        let has_lower = part.chars().any(|c| c.is_ascii_lowercase());
+        let has_upper = part.chars().any(|c| c.is_ascii_uppercase());
+        match (has_lower, has_upper) {
+            (true, true) => return None,
+            (true, false) => case_flags |= flag,
+            _ => {}
+        }
+        for (dst, b) in dst.iter_mut().zip(part.bytes()) {
+            *dst = b.to_ascii_uppercase();
+        }
+    }
+    Some((short_name, case_flags))
+}
+
+/// Generate a unique short name `BASE~N.EXT` for a name that needs a long
+/// name. `exists` tells whether a short name is already used in the
+/// directory.
+pub(super) fn generate_short_name(
+    name: &str,
+    exists: impl Fn(&[u8; 11]) -> bool,
+) -> Result<[u8; 11]> {
+    let convert = |part: &str| -> Vec<u8> {
+        part.chars()
+            .filter(|&c| c != ' ' && c != '.')
+            .map(|c| {
+                let c = c.to_ascii_uppercase();
+                if c.is_ascii_alphanumeric() || is_short_name_symbol(c) {
+                    c as u8
+                } else {
+                    b'_'
+                }
+            })
+            .collect()
+    };
+    let name = name.trim_start_matches('.');
+    let (base, ext) = match name.rsplit_once('.') {
+        Some((base, ext)) => (convert(base), convert(ext)),
+        None => (convert(name), Vec::new()),
+    };
+
+    let mut short_name = [b' '; 11];
+    for (dst, &b) in short_name[8..].iter_mut().zip(&ext) {
+        *dst = b;
+    }
+    for n in 1..1_000_000u32 {
+        let mut digits = [0; 7];
+        let tail = {
+            let mut len = 0;
+            let mut n = n;
+            while n != 0 {
+                digits[6 - len] = b'0' + (n % 10) as u8;
+                n /= 10;
+                len += 1;
+            }
+            digits[6 - len] = b'~';
+            &digits[6 - len..]
+        };
+        let base_len = base.len().min(8 - tail.len());
+        short_name[..8].fill(b' ');
+        short_name[..base_len].copy_from_slice(&base[..base_len]);
+        short_name[base_len..base_len + tail.len()].copy_from_slice(tail);
+        if !exists(&short_name) {
+            return Ok(short_name);
+        }
+    }
+    Err(Status::VOLUME_FULL.into())
+}
+
+/// Build the slots of an entry: the long name slots, if any, followed by
+/// the short name slot.
+pub(super) fn build_entry(
+    long_name: Option<&[u16]>,
+    short_name: &[u8; 11],
+    case_flags: u8,
+    attributes: FatAttributes,
+    first_cluster: u32,
+) -> Vec<u8> {
+    let mut slots = Vec::new();
+
+    if let Some(long_name) = long_name {
+        let checksum = lfn_checksum(short_name);
+        let count = div_ceil_usize(long_name.len(), LONG_NAME_CHARS);
+        for ordinal in (1..=count).rev() {
+            let mut slot = [0; ENTRY_SIZE];
+            slot[0] = ordinal as u8 | if ordinal == count { 0x40 } else { 0 };
+            slot[11] = LONG_NAME;
+            slot[13] = checksum;
+            let start = (ordinal - 1) * LONG_NAME_CHARS;
+            for (i, &offset) in LONG_NAME_OFFSETS.iter().enumerate() {
+                // The name is terminated by a null character, then padded
+                // with 0xffff.
+                let c = match long_name.get(start + i) {
+                    Some(&c) => c,
+                    None if start + i == long_name.len() => 0,
+                    None => 0xffff,
+                };
+                slot[offset..offset + 2].copy_from_slice(&c.to_le_bytes());
+            }
+            slots.extend_from_slice(&slot);
+        }
+    }
+
+    let mut slot = [0; ENTRY_SIZE];
+    slot[..11].copy_from_slice(short_name);
+    slot[11] = attributes.bits();
+    slot[12] = case_flags;
+    for offset in [16, 18, 24] {
+        slot[offset..offset + 2].copy_from_slice(&DEFAULT_DATE.to_le_bytes());
+    }
+    set_first_cluster(&mut slot, first_cluster);
+    slots.extend_from_slice(&slot);
+    slots
+}
+
+/// Set the first cluster of a short name slot.
+pub(super) fn set_first_cluster(slot: &mut [u8], first_cluster: u32) {
+    slot[20..22].copy_from_slice(&((first_cluster >> 16) as u16).to_le_bytes());
+    slot[26..28].copy_from_slice(&(first_cluster as u16).to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_names() {
+        assert_eq!(exact_short_name("BOOTX64.EFI"), Some((*b"BOOTX64 EFI", 0)));
+        assert_eq!(
+            exact_short_name("grub.cfg"),
+            Some((*b"GRUB    CFG", LOWERCASE_BASE | LOWERCASE_EXT))
+        );
+        assert_eq!(exact_short_name("EFI"), Some((*b"EFI        ", 0)));
+        for name in [
+            "Boot.efi",
+            "toolongname.efi",
+            "a.b.c",
+            "a b",
+            ".x",
+            "a.long",
+        ] {
+            assert_eq!(exact_short_name(name), None, "{name}");
+        }
+
+        assert_eq!(
+            generate_short_name("Microsoft Boot Manager.efi", |_| false).unwrap(),
+            *b"MICROS~1EFI"
+        );
+        assert_eq!(
+            generate_short_name(".bashrc", |name| name == b"BASHRC~1   ").unwrap(),
+            *b"BASHRC~2   "
+        );
+        assert_eq!(
+            generate_short_name("a+b", |_| false).unwrap(),
+            *b"A_B~1      "
+        );
+
+        assert_eq!(short_name_str(b"GRUB    CFG", LOWERCASE_BASE), "grub.CFG");
+        assert_eq!(short_name_str(b"EFI        ", 0), "EFI");
+    }
+
+    #[test]
+    fn test_long_names() {
+        assert!(encode_long_name("a:b").is_err());
+        assert!(encode_long_name("name.").is_err());
+        assert!(encode_long_name("").is_err());
+
+        let long_name = encode_long_name("A long file name.txt").unwrap();
+        let short_name = generate_short_name("A long file name.txt", |_| false).unwrap();
+        let mut slots = build_entry(
+            Some(&long_name),
+            &short_name,
+            0,
+            FatAttributes::ARCHIVE,
+            0x12_3456,
+        );
+        // Deleted entries are skipped, and the end marker stops parsing.
+        slots.splice(0..0, [DELETED; ENTRY_SIZE]);
+        slots.extend_from_slice(&[0; ENTRY_SIZE]);
+        slots.extend_from_slice(&build_entry(
+            None,
+            b"IGNORED    ",
+            0,
+            FatAttributes::empty(),
+            0,
+        ));
+
+        let entries = parse_entries(&slots).unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.name, "A long file name.txt");
+        assert_eq!(entry.short_name, *b"ALONGF~1TXT");
+        assert_eq!(entry.first_cluster, 0x12_3456);
+        assert_eq!((entry.first_slot, entry.slot), (1, 3));
+        assert!(entry.matches("a LONG file NAME.TXT"));
+        assert!(entry.matches("alongf~1.txt"));
+
+        // A long name whose checksum doesn't match is ignored.
+        slots[ENTRY_SIZE + 13] ^= 1;
+        slots[2 * ENTRY_SIZE + 13] ^= 1;
+        assert_eq!(parse_entries(&slots).unwrap()[0].name, "ALONGF~1.TXT");
+    }
+}
@@ -0,0 +1,290 @@
+//! Creation of new volumes.
+
+use super::dir::{self, ENTRY_SIZE};
+use super::{FatAttributes, FatDevice, FatFileSystem, FatType};
+use crate::mem::try_vec;
+use crate::table::boot::{BootServices, LONG_OPERATION_WATCHDOG_TIMEOUT};
+use crate::util::div_ceil_u64;
+use crate::{Result, Status};
+
+/// Number of entries of the root directory of new FAT12 and FAT16 volumes.
+const ROOT_DIR_ENTRIES: u64 = 512;
+
+/// Number of reserved sectors of new FAT32 volumes.
+const FAT32_RESERVED_SECTORS: u64 = 32;
+
+/// Sector of the FS information sector of new FAT32 volumes.
+const FS_INFO_SECTOR: u64 = 1;
+
+/// Sector of the backup boot sector of new FAT32 volumes.
+const BACKUP_BOOT_SECTOR: u64 = 6;
+
+/// Largest cluster size of new volumes, in bytes.
+const MAX_CLUSTER_SIZE: u64 = 32 * 1024;
+
+/// Options of [`FatFileSystem::format`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FatFormatOptions<'a> {
+    label: Option<&'a str>,
+    volume_id: u32,
+    fat_type: Option<FatType>,
+}
+
+impl<'a> FatFormatOptions<'a> {
+    /// Create the default options: no label, a volume ID of 0, and a FAT type
+    /// chosen from the size of the device.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            label: None,
+            volume_id: 0,
+            fat_type: None,
+        }
+    }
+
+    /// Set the label of the volume, of up to 11 characters which are valid
+    /// in a short file name, or spaces. Letters are converted to uppercase.
+    #[must_use]
+    pub const fn with_label(mut self, label: &'a str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Set the serial number of the volume.
+    #[must_use]
+    pub const fn with_volume_id(mut self, volume_id: u32) -> Self {
+        self.volume_id = volume_id;
+        self
+    }
+
+    /// Set the type of the volume.
+    ///
+    /// By default, volumes of 512 MiB or more are formatted as FAT32, and
+    /// smaller volumes as FAT16 if they are large enough, or FAT12.
+    #[must_use]
+    pub const fn with_fat_type(mut self, fat_type: FatType) -> Self {
+        self.fat_type = Some(fat_type);
+        self
+    }
+}
+
+/// Size of the structures of a new volume, in sectors.
+struct Geometry {
+    fat_type: FatType,
+    sectors_per_cluster: u64,
+    reserved_sectors: u64,
+    root_dir_entries: u64,
+    fat_sectors: u64,
+}
+
+impl Geometry {
+    /// Compute the geometry of a volume of `fat_type` and `total_sectors`
+    /// sectors of `sector_size` bytes, or `None` if the volume would have
+    /// too few or too many clusters for this type.
+    fn new(fat_type: FatType, sector_size: u64, total_sectors: u64) -> Option<Self> {
+        let (reserved_sectors, root_dir_entries, clusters, entry_bits) = match fat_type {
+            FatType::Fat12 => (1, ROOT_DIR_ENTRIES, 1..=4084, 12),
+            FatType::Fat16 => (1, ROOT_DIR_ENTRIES, 4085..=65524, 16),
+            FatType::Fat32 => (FAT32_RESERVED_SECTORS, 0, 65525..=0x0fff_fff4, 32),
+        };
+        let root_dir_sectors = div_ceil_u64(root_dir_entries * ENTRY_SIZE as u64, sector_size);
+
+        // Large FAT32 volumes get larger clusters, to keep the FAT small.
+        let min_cluster_size = match (fat_type, total_sectors * sector_size) {
+            (FatType::Fat32, size) if size > 32 << 30 => 32 * 1024,
+            (FatType::Fat32, size) if size > 16 << 30 => 16 * 1024,
+            (FatType::Fat32, size) if size > 8 << 30 => 8 * 1024,
+            (FatType::Fat32, size) if size > 260 << 20 => 4 * 1024,
+            _ => 0,
+        };
+
+        let mut sectors_per_cluster = 1;
+        while sectors_per_cluster * sector_size <= MAX_CLUSTER_SIZE {
+            if sectors_per_cluster * sector_size >= min_cluster_size {
+                // Adding FAT sectors reduces the number of clusters, so this
+                // converges.
+                let mut fat_sectors = 1;
+                let cluster_count = loop {
+                    let data_sectors = total_sectors
+                        .checked_sub(reserved_sectors + root_dir_sectors + 2 * fat_sectors)?;
+                    let cluster_count = data_sectors / sectors_per_cluster;
+                    let needed = div_ceil_u64(
+                        div_ceil_u64((cluster_count + 2) * entry_bits, 8),
+                        sector_size,
+                    );
+                    if needed <= fat_sectors {
+                        break cluster_count;
+                    }
+                    fat_sectors = needed;
+                };
+                if clusters.contains(&cluster_count) {
+                    return Some(Self {
+                        fat_type,
+                        sectors_per_cluster,
+                        reserved_sectors,
+                        root_dir_entries,
+                        fat_sectors,
+                    });
+                }
+                if cluster_count < *clusters.start() {
+                    return None;
+                }
+            }
+            sectors_per_cluster *= 2;
+        }
+        None
+    }
+}
+
+/// Convert `label` to a volume label, or `None` if it's invalid.
+fn volume_label(label: &str) -> Option<[u8; 11]> {
+    let mut bytes = [b' '; 11];
+    if label.len() > bytes.len() || label.starts_with(' ') {
+        return None;
+    }
+    for (dst, c) in bytes.iter_mut().zip(label.chars()) {
+        let c = c.to_ascii_uppercase();
+        if !(c.is_ascii_alphanumeric() || c == ' ' || dir::is_short_name_symbol(c)) {
+            return None;
+        }
+        *dst = c as u8;
+    }
+    Some(bytes)
+}
+
+impl<D: FatDevice> FatFileSystem<D> {
+    /// Create a new, empty volume on `device`, and open it. The previous
+    /// contents of the device are lost.
+    ///
+    /// The watchdog timer is extended while the volume is created, see
+    /// [`BootServices::with_watchdog_extended`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the label is invalid.
+    /// * [`Status::BAD_BUFFER_SIZE`]: the device is too small or too large
+    ///   for the requested FAT type.
+    /// * [`Status::UNSUPPORTED`]: the sector size of the device is not 512,
+    ///   1024, 2048 or 4096 bytes.
+    /// * Errors of the [`FatDevice`] and of
+    ///   [`BootServices::with_watchdog_extended`].
+    pub fn format(bt: &BootServices, device: D, options: &FatFormatOptions) -> Result<Self> {
+        bt.with_watchdog_extended(LONG_OPERATION_WATCHDOG_TIMEOUT, || {
+            Self::format_device(device, options)
+        })?
+    }
+
+    fn format_device(mut device: D, options: &FatFormatOptions) -> Result<Self> {
+        let label = match options.label {
+            Some(label) => Some(volume_label(label).ok_or(Status::INVALID_PARAMETER)?),
+            None => None,
+        };
+        let sector_size = u64::from(device.sector_size());
+        if !matches!(sector_size, 512 | 1024 | 2048 | 4096) {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        let total_sectors = (device.size() / sector_size).min(u64::from(u32::MAX));
+
+        let types: &[FatType] = match options.fat_type {
+            Some(ref fat_type) => core::slice::from_ref(fat_type),
+            None if total_sectors * sector_size >= 512 << 20 => {
+                &[FatType::Fat32, FatType::Fat16, FatType::Fat12]
+            }
+            None => &[FatType::Fat16, FatType::Fat12, FatType::Fat32],
+        };
+        let geometry = types
+            .iter()
+            .find_map(|&fat_type| Geometry::new(fat_type, sector_size, total_sectors))
+            .ok_or(Status::BAD_BUFFER_SIZE)?;
+        let is_fat32 = geometry.fat_type == FatType::Fat32;
+
+        // Boot sector.
+        let mut boot = try_vec(0, sector_size as usize)?;
+        boot[0..3].copy_from_slice(if is_fat32 {
+            &[0xeb, 0x58, 0x90]
+        } else {
+            &[0xeb, 0x3c, 0x90]
+        });
+        boot[3..11].copy_from_slice(b"MSWIN4.1");
+        boot[11..13].copy_from_slice(&(sector_size as u16).to_le_bytes());
+        boot[13] = geometry.sectors_per_cluster as u8;
+        boot[14..16].copy_from_slice(&(geometry.reserved_sectors as u16).to_le_bytes());
+        boot[16] = 2;
+        boot[17..19].copy_from_slice(&(geometry.root_dir_entries as u16).to_le_bytes());
+        if total_sectors <= 0xffff && !is_fat32 {
+            boot[19..21].copy_from_slice(&(total_sectors as u16).to_le_bytes());
+        } else {
+            boot[32..36].copy_from_slice(&(total_sectors as u32).to_le_bytes());
+        }
+        boot[21] = 0xf8;
+        boot[24..26].copy_from_slice(&63u16.to_le_bytes());
+        boot[26..28].copy_from_slice(&255u16.to_le_bytes());
+        let (extended, fs_type): (usize, &[u8; 8]) = match geometry.fat_type {
+            FatType::Fat12 => (36, b"FAT12   "),
+            FatType::Fat16 => (36, b"FAT16   "),
+            FatType::Fat32 => (64, b"FAT32   "),
+        };
+        if is_fat32 {
+            boot[36..40].copy_from_slice(&(geometry.fat_sectors as u32).to_le_bytes());
+            boot[44..48].copy_from_slice(&2u32.to_le_bytes());
+            boot[48..50].copy_from_slice(&(FS_INFO_SECTOR as u16).to_le_bytes());
+            boot[50..52].copy_from_slice(&(BACKUP_BOOT_SECTOR as u16).to_le_bytes());
+        } else {
+            boot[22..24].copy_from_slice(&(geometry.fat_sectors as u16).to_le_bytes());
+        }
+        boot[extended] = 0x80;
+        boot[extended + 2] = 0x29;
+        boot[extended + 3..extended + 7].copy_from_slice(&options.volume_id.to_le_bytes());
+        boot[extended + 7..extended + 18].copy_from_slice(label.as_ref().unwrap_or(b"NO NAME    "));
+        boot[extended + 18..extended + 26].copy_from_slice(fs_type);
+        boot[510..512].copy_from_slice(&[0x55, 0xaa]);
+
+        // Clear the reserved sectors, the FATs and the fixed root directory.
+        let root_dir_sectors =
+            div_ceil_u64(geometry.root_dir_entries * ENTRY_SIZE as u64, sector_size);
+        let clear_sectors = geometry.reserved_sectors + 2 * geometry.fat_sectors + root_dir_sectors;
+        let zeros = try_vec(0, (64 * sector_size) as usize)?;
+        let mut sector = 0;
+        while sector < clear_sectors {
+            let count = (clear_sectors - sector).min(64);
+            device.write(
+                sector * sector_size,
+                &zeros[..(count * sector_size) as usize],
+            )?;
+            sector += count;
+        }
+
+        if is_fat32 {
+            let mut fs_info = try_vec(0, sector_size as usize)?;
+            fs_info[0..4].copy_from_slice(b"RRaA");
+            fs_info[484..488].copy_from_slice(b"rrAa");
+            fs_info[488..496].fill(0xff);
+            fs_info[508..512].copy_from_slice(&[0, 0, 0x55, 0xaa]);
+            for sector in [FS_INFO_SECTOR, BACKUP_BOOT_SECTOR + FS_INFO_SECTOR] {
+                device.write(sector * sector_size, &fs_info)?;
+            }
+            device.write(BACKUP_BOOT_SECTOR * sector_size, &boot)?;
+        }
+        device.write(0, &boot)?;
+
+        // Reserve the first two FAT entries, and the root directory cluster
+        // of FAT32 volumes.
+        let mut fs = Self::new(device)?;
+        let end_of_chain = geometry.fat_type.end_of_chain();
+        fs.set_fat(0, end_of_chain - 7);
+        fs.set_fat(1, end_of_chain);
+        if is_fat32 {
+            fs.set_fat(2, end_of_chain);
+            fs.zero_cluster(2)?;
+            fs.next_free = 3;
+        }
+
+        if let Some(label) = label {
+            let root = fs.read_dir_data(fs.root())?;
+            let slot = dir::build_entry(None, &label, 0, FatAttributes::VOLUME_ID, 0);
+            fs.write_slots(&root, 0, &slot)?;
+        }
+        fs.commit()?;
+        Ok(fs)
+    }
+}
@@ -0,0 +1,1211 @@
+//! Software implementation of the FAT12, FAT16 and FAT32 file systems.
+//!
+//! Some firmware mounts file systems read-only, or doesn't provide the
+//! [`SimpleFileSystem`] protocol for some devices. [`FatFileSystem`] reads
+//! and writes FAT volumes directly through the [`BlockIO`] or [`DiskIo`]
+//! protocols, so that installers can prepare an EFI system partition
+//! regardless of the firmware. It can also [format] a new volume.
+//!
+//! Long file names are supported. New entries get a long name only if the
+//! name can't be stored as an 8.3 short name. Timestamps are not
+//! maintained: new entries are dated 1980-01-01.
+//!
+//! The file allocation table is kept in memory, and written back to the
+//! device at the end of each operation which modifies the volume.
+//!
+//! ```no_run
+//! use uefi::fat::{FatFileSystem, FatFormatOptions};
+//! use uefi::prelude::*;
+//! use uefi::proto::media::block::BlockIO;
+//! # fn install(bt: &BootServices, block_io: &mut BlockIO, loader: &[u8]) -> uefi::Result {
+//!
+//! let options = FatFormatOptions::new().with_label("ESP");
+//! let mut fs = FatFileSystem::format(bt, block_io, &options)?;
+//! fs.create_dir("EFI/BOOT")?;
+//! fs.write_file(bt, "EFI/BOOT/BOOTX64.EFI", loader)?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`SimpleFileSystem`]: crate::proto::media::fs::SimpleFileSystem
+//! [`BlockIO`]: crate::proto::media::block::BlockIO
+//! [`DiskIo`]: crate::proto::media::disk::DiskIo
+//! [format]: FatFileSystem::format
+
+mod device;
+mod dir;
+mod format;
+
+pub use self::device::{DiskIoDevice, FatDevice};
+pub use self::format::FatFormatOptions;
+
+use self::dir::{RawEntry, DELETED, ENTRY_SIZE};
+use crate::mem::{try_vec, try_with_capacity};
+use crate::table::boot::{BootServices, LONG_OPERATION_WATCHDOG_TIMEOUT};
+use crate::util::div_ceil_u64;
+use crate::{Result, Status};
+use alloc::string::String;
+use alloc::vec::Vec;
+use bitflags::bitflags;
+use core::fmt::{self, Debug, Formatter};
+use core::ops::Range;
+
+/// The variant of the FAT file system, which depends on the number of
+/// clusters of the volume.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FatType {
+    /// 12-bit cluster numbers, for volumes of less than 4085 clusters.
+    Fat12,
+    /// 16-bit cluster numbers, for volumes of less than 65525 clusters.
+    Fat16,
+    /// 28-bit cluster numbers.
+    Fat32,
+}
+
+impl FatType {
+    /// Get the type of a volume of `cluster_count` clusters.
+    const fn from_cluster_count(cluster_count: u32) -> Self {
+        if cluster_count < 4085 {
+            Self::Fat12
+        } else if cluster_count < 65525 {
+            Self::Fat16
+        } else {
+            Self::Fat32
+        }
+    }
+
+    /// Get the value marking the end of a cluster chain.
+    const fn end_of_chain(self) -> u32 {
+        match self {
+            Self::Fat12 => 0xfff,
+            Self::Fat16 => 0xffff,
+            Self::Fat32 => 0x0fff_ffff,
+        }
+    }
+
+    /// True if `value` marks the end of a cluster chain.
+    const fn is_end_of_chain(self, value: u32) -> bool {
+        value >= self.end_of_chain() - 7
+    }
+}
+
+bitflags! {
+    /// Attributes of a file or directory of a FAT volume.
+    #[derive(Default)]
+    pub struct FatAttributes: u8 {
+        /// The file can't be modified.
+        const READ_ONLY = 0x01;
+        /// The file is hidden.
+        const HIDDEN = 0x02;
+        /// The file belongs to the operating system.
+        const SYSTEM = 0x04;
+        /// The entry is the label of the volume.
+        const VOLUME_ID = 0x08;
+        /// The entry is a directory.
+        const DIRECTORY = 0x10;
+        /// The file was modified since it was last backed up.
+        const ARCHIVE = 0x20;
+    }
+}
+
+/// A file or directory of a FAT volume.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FatDirEntry {
+    /// Name of the file, which is its long name if it has one.
+    pub name: String,
+    /// Attributes of the file.
+    pub attributes: FatAttributes,
+    /// Size of the file in bytes, or zero for a directory.
+    pub size: u32,
+}
+
+impl FatDirEntry {
+    /// True if the entry is a directory.
+    #[must_use]
+    pub const fn is_dir(&self) -> bool {
+        self.attributes.contains(FatAttributes::DIRECTORY)
+    }
+}
+
+impl From<&RawEntry> for FatDirEntry {
+    fn from(entry: &RawEntry) -> Self {
+        Self {
+            name: entry.name.clone(),
+            attributes: entry.attributes,
+            size: entry.size,
+        }
+    }
+}
+
+/// Location of the volume structures, from the boot sector.
+#[derive(Clone, Debug)]
+struct Layout {
+    fat_type: FatType,
+    sector_size: u64,
+    cluster_size: u64,
+    /// Offset of the first FAT, in bytes.
+    fat_offset: u64,
+    /// Size of each FAT, in bytes.
+    fat_size: u64,
+    num_fats: u64,
+    /// Offset of the root directory of FAT12 and FAT16 volumes, in bytes.
+    root_dir_offset: u64,
+    /// Number of entries of the root directory of FAT12 and FAT16 volumes.
+    root_dir_entries: usize,
+    /// First cluster of the root directory of FAT32 volumes.
+    root_cluster: u32,
+    /// Offset of cluster 2, in bytes.
+    data_offset: u64,
+    /// Number of data clusters. Cluster numbers start at 2.
+    cluster_count: u32,
+    /// Offset of the FAT32 FS information sector, in bytes.
+    fs_info_offset: Option<u64>,
+}
+
+impl Layout {
+    /// Parse the boot sector at the start of `boot`, of a volume on a
+    /// device of `device_size` bytes.
+    fn parse(boot: &[u8], device_size: u64) -> Result<Self> {
+        let corrupted = || Status::VOLUME_CORRUPTED;
+        let u16_at =
+            |offset: usize| u64::from(u16::from_le_bytes([boot[offset], boot[offset + 1]]));
+        let u32_at = |offset: usize| {
+            u64::from(u32::from_le_bytes(
+                boot[offset..offset + 4].try_into().unwrap(),
+            ))
+        };
+
+        if boot[510..512] != [0x55, 0xaa] {
+            return Err(corrupted().into());
+        }
+        let sector_size = u16_at(11);
+        let sectors_per_cluster = u64::from(boot[13]);
+        let reserved_sectors = u16_at(14);
+        let num_fats = u64::from(boot[16]);
+        let root_dir_entries = u16_at(17);
+        let total_sectors = match u16_at(19) {
+            0 => u32_at(32),
+            total => total,
+        };
+        let fat_sectors = match u16_at(22) {
+            0 => u32_at(36),
+            sectors => sectors,
+        };
+        if !matches!(sector_size, 512 | 1024 | 2048 | 4096)
+            || !sectors_per_cluster.is_power_of_two()
+            || reserved_sectors == 0
+            || num_fats == 0
+            || fat_sectors == 0
+            || total_sectors * sector_size > device_size
+        {
+            return Err(corrupted().into());
+        }
+
+        let root_dir_sectors = div_ceil_u64(root_dir_entries * ENTRY_SIZE as u64, sector_size);
+        let first_data_sector = reserved_sectors + num_fats * fat_sectors + root_dir_sectors;
+        let cluster_count = total_sectors
+            .checked_sub(first_data_sector)
+            .ok_or_else(corrupted)?
+            / sectors_per_cluster;
+        let cluster_count = u32::try_from(cluster_count)
+            .ok()
+            .filter(|&count| count != 0 && count <= 0x0fff_fff5)
+            .ok_or_else(corrupted)?;
+        let fat_type = FatType::from_cluster_count(cluster_count);
+
+        let (root_cluster, fs_info_offset) = if fat_type == FatType::Fat32 {
+            if root_dir_entries != 0 {
+                return Err(corrupted().into());
+            }
+            let fs_info_offset = match u16_at(48) {
+                0 | 0xffff => None,
+                sector => Some(sector * sector_size),
+            };
+            (u32_at(44) as u32, fs_info_offset)
+        } else {
+            if root_dir_entries == 0 {
+                return Err(corrupted().into());
+            }
+            (0, None)
+        };
+
+        let layout = Self {
+            fat_type,
+            sector_size,
+            cluster_size: sectors_per_cluster * sector_size,
+            fat_offset: reserved_sectors * sector_size,
+            fat_size: fat_sectors * sector_size,
+            num_fats,
+            root_dir_offset: (reserved_sectors + num_fats * fat_sectors) * sector_size,
+            root_dir_entries: root_dir_entries as usize,
+            root_cluster,
+            data_offset: first_data_sector * sector_size,
+            cluster_count,
+            fs_info_offset,
+        };
+        // The FAT must hold an entry for each cluster.
+        if layout.fat_bytes(cluster_count + 2) > layout.fat_size {
+            return Err(corrupted().into());
+        }
+        if fat_type == FatType::Fat32 && !layout.is_valid_cluster(root_cluster) {
+            return Err(corrupted().into());
+        }
+        Ok(layout)
+    }
+
+    /// Get the size of the FAT entries of `count` clusters, in bytes.
+    const fn fat_bytes(&self, count: u32) -> u64 {
+        let count = count as u64;
+        match self.fat_type {
+            FatType::Fat12 => div_ceil_u64(count * 3, 2),
+            FatType::Fat16 => count * 2,
+            FatType::Fat32 => count * 4,
+        }
+    }
+
+    /// True if `cluster` is a data cluster.
+    const fn is_valid_cluster(&self, cluster: u32) -> bool {
+        cluster >= 2 && cluster - 2 < self.cluster_count
+    }
+
+    /// Get the offset of `cluster`, in bytes.
+    const fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.data_offset + (cluster as u64 - 2) * self.cluster_size
+    }
+}
+
+/// A directory of the volume.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Dir {
+    /// The root directory of a FAT12 or FAT16 volume, which has a fixed
+    /// location and size.
+    FixedRoot,
+    /// A directory stored in clusters, starting at the given cluster.
+    Clusters(u32),
+}
+
+/// The slots of a directory, and the clusters storing them.
+struct DirData {
+    dir: Dir,
+    bytes: Vec<u8>,
+    clusters: Vec<u32>,
+}
+
+/// A FAT volume on a [`FatDevice`].
+pub struct FatFileSystem<D: FatDevice> {
+    device: D,
+    layout: Layout,
+    /// The first FAT.
+    fat: Vec<u8>,
+    /// Sectors of the FAT which were modified since the last commit.
+    dirty_sectors: Vec<bool>,
+    /// Cluster from which free clusters are searched.
+    next_free: u32,
+}
+
+impl<D: FatDevice> FatFileSystem<D> {
+    /// Open the FAT volume of `device`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::VOLUME_CORRUPTED`]: the device doesn't hold a valid FAT
+    ///   volume.
+    /// * Errors of [`FatDevice::read`].
+    pub fn new(mut device: D) -> Result<Self> {
+        let mut boot = [0; 512];
+        device.read(0, &mut boot)?;
+        let layout = Layout::parse(&boot, device.size())?;
+
+        let mut fat = try_vec(0, layout.fat_size as usize)?;
+        device.read(layout.fat_offset, &mut fat)?;
+        let sectors = (layout.fat_size / layout.sector_size) as usize;
+        Ok(Self {
+            device,
+            layout,
+            fat,
+            dirty_sectors: try_vec(false, sectors)?,
+            next_free: 2,
+        })
+    }
+
+    /// Get the type of the volume.
+    #[must_use]
+    pub const fn fat_type(&self) -> FatType {
+        self.layout.fat_type
+    }
+
+    /// Get the size of a cluster, the allocation unit of files, in bytes.
+    #[must_use]
+    pub const fn cluster_size(&self) -> u64 {
+        self.layout.cluster_size
+    }
+
+    /// Get the free space of the volume, in bytes.
+    #[must_use]
+    pub fn free_space(&self) -> u64 {
+        u64::from(self.free_clusters()) * self.layout.cluster_size
+    }
+
+    /// Read the whole file at `path`.
+    ///
+    /// Paths are relative to the root directory, and components are
+    /// separated by `/` or `\`. Names are case-insensitive.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the file doesn't exist.
+    /// * [`Status::ACCESS_DENIED`]: `path` is a directory.
+    /// * [`Status::VOLUME_CORRUPTED`]: the volume is inconsistent.
+    /// * Errors of [`FatDevice::read`].
+    pub fn read_file(&mut self, path: &str) -> Result<Vec<u8>> {
+        let entry = match self.lookup(path)? {
+            (_, Some(entry)) => entry,
+            (_, None) => return Err(Status::ACCESS_DENIED.into()),
+        };
+        if entry.attributes.contains(FatAttributes::DIRECTORY) {
+            return Err(Status::ACCESS_DENIED.into());
+        }
+
+        let size = entry.size as usize;
+        let clusters = self.chain(entry.first_cluster)?;
+        if (clusters.len() as u64) < div_ceil_u64(size as u64, self.layout.cluster_size) {
+            return Err(Status::VOLUME_CORRUPTED.into());
+        }
+        let mut data = try_vec(0, size)?;
+        self.transfer(&clusters, size, |device, offset, range| {
+            device.read(offset, &mut data[range])
+        })?;
+        Ok(data)
+    }
+
+    /// Write `data` to the file at `path`, replacing it if it exists. The
+    /// parent directory must exist.
+    ///
+    /// The watchdog timer is extended while the file is written, see
+    /// [`BootServices::with_watchdog_extended`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the parent directory doesn't exist.
+    /// * [`Status::ACCESS_DENIED`]: `path` is a directory.
+    /// * [`Status::INVALID_PARAMETER`]: the name is not a valid FAT name.
+    /// * [`Status::BAD_BUFFER_SIZE`]: `data` is 4 GiB or larger.
+    /// * [`Status::VOLUME_FULL`]: there is not enough free space.
+    /// * [`Status::VOLUME_CORRUPTED`]: the volume is inconsistent.
+    /// * Errors of the [`FatDevice`] and of
+    ///   [`BootServices::with_watchdog_extended`].
+    pub fn write_file(&mut self, bt: &BootServices, path: &str, data: &[u8]) -> Result {
+        bt.with_watchdog_extended(LONG_OPERATION_WATCHDOG_TIMEOUT, || {
+            self.write_file_data(path, data)
+        })?
+    }
+
+    fn write_file_data(&mut self, path: &str, data: &[u8]) -> Result {
+        let size = u32::try_from(data.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let (parent, name) = self.parent(path)?;
+        let existing = self.find(parent, name)?;
+        if let Some((_, entry)) = &existing {
+            if entry.attributes.contains(FatAttributes::DIRECTORY) {
+                return Err(Status::ACCESS_DENIED.into());
+            }
+        }
+
+        // Check the free space before freeing the old clusters, so that the
+        // file is left unchanged if the volume is full.
+        let needed = div_ceil_u64(data.len() as u64, self.layout.cluster_size) as u32;
+        let old_clusters = match &existing {
+            Some((_, entry)) => self.chain(entry.first_cluster)?,
+            None => Vec::new(),
+        };
+        if needed > self.free_clusters() + old_clusters.len() as u32 {
+            return Err(Status::VOLUME_FULL.into());
+        }
+        self.free_chain(&old_clusters);
+        let clusters = self.allocate(needed)?;
+        self.transfer(&clusters, data.len(), |device, offset, range| {
+            device.write(offset, &data[range])
+        })?;
+        let first_cluster = clusters.first().copied().unwrap_or(0);
+
+        match existing {
+            Some((dir_data, entry)) => {
+                let mut slot: [u8; ENTRY_SIZE] = dir_data.slot(entry.slot).try_into().unwrap();
+                dir::set_first_cluster(&mut slot, first_cluster);
+                slot[28..32].copy_from_slice(&size.to_le_bytes());
+                self.write_slots(&dir_data, entry.slot, &slot)?;
+            }
+            None => {
+                self.add_entry(parent, name, FatAttributes::ARCHIVE, first_cluster, size)?;
+            }
+        }
+        self.commit()
+    }
+
+    /// Create the directory at `path`, and its missing parents. Existing
+    /// directories are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: a component of `path` is a file.
+    /// * [`Status::INVALID_PARAMETER`]: a name is not a valid FAT name.
+    /// * [`Status::VOLUME_FULL`]: there is not enough free space.
+    /// * [`Status::VOLUME_CORRUPTED`]: the volume is inconsistent.
+    /// * Errors of the [`FatDevice`].
+    pub fn create_dir(&mut self, path: &str) -> Result {
+        let mut dir = self.root();
+        for name in components(path)? {
+            dir = match self.find(dir, name)? {
+                Some((_, entry)) if entry.attributes.contains(FatAttributes::DIRECTORY) => {
+                    self.dir_at(entry.first_cluster)
+                }
+                Some(_) => return Err(Status::ACCESS_DENIED.into()),
+                None => {
+                    let cluster = self.allocate(1)?[0];
+                    self.zero_cluster(cluster)?;
+                    let parent_cluster = match dir {
+                        Dir::Clusters(cluster) if dir != self.root() => cluster,
+                        _ => 0,
+                    };
+                    let mut dots = dir::build_entry(
+                        None,
+                        b".          ",
+                        0,
+                        FatAttributes::DIRECTORY,
+                        cluster,
+                    );
+                    dots.extend(dir::build_entry(
+                        None,
+                        b"..         ",
+                        0,
+                        FatAttributes::DIRECTORY,
+                        parent_cluster,
+                    ));
+                    self.device
+                        .write(self.layout.cluster_offset(cluster), &dots)?;
+                    self.add_entry(dir, name, FatAttributes::DIRECTORY, cluster, 0)?;
+                    Dir::Clusters(cluster)
+                }
+            };
+        }
+        self.commit()
+    }
+
+    /// Remove the file or empty directory at `path`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the file doesn't exist.
+    /// * [`Status::ACCESS_DENIED`]: `path` is the root directory or a
+    ///   directory which is not empty.
+    /// * [`Status::VOLUME_CORRUPTED`]: the volume is inconsistent.
+    /// * Errors of the [`FatDevice`].
+    pub fn remove(&mut self, path: &str) -> Result {
+        let (parent, name) = self.parent(path)?;
+        let (dir_data, entry) = self.find(parent, name)?.ok_or(Status::NOT_FOUND)?;
+        if entry.attributes.contains(FatAttributes::DIRECTORY) {
+            let dir = self.dir_at(entry.first_cluster);
+            let is_empty = dir::parse_entries(&self.read_dir_data(dir)?.bytes)?
+                .iter()
+                .all(RawEntry::is_dot);
+            if !is_empty {
+                return Err(Status::ACCESS_DENIED.into());
+            }
+        }
+
+        let clusters = self.chain(entry.first_cluster)?;
+        self.free_chain(&clusters);
+        for slot in entry.first_slot..=entry.slot {
+            self.write_slots(&dir_data, slot, &[DELETED])?;
+        }
+        self.commit()
+    }
+
+    /// Get the entries of the directory at `path`, without the `.` and `..`
+    /// entries.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the directory doesn't exist.
+    /// * [`Status::ACCESS_DENIED`]: `path` is a file.
+    /// * [`Status::VOLUME_CORRUPTED`]: the volume is inconsistent.
+    /// * Errors of [`FatDevice::read`].
+    pub fn read_dir(&mut self, path: &str) -> Result<Vec<FatDirEntry>> {
+        let dir = match self.lookup(path)? {
+            (_, None) => self.root(),
+            (_, Some(entry)) if entry.attributes.contains(FatAttributes::DIRECTORY) => {
+                self.dir_at(entry.first_cluster)
+            }
+            (_, Some(_)) => return Err(Status::ACCESS_DENIED.into()),
+        };
+        let data = self.read_dir_data(dir)?;
+        let raw_entries = dir::parse_entries(&data.bytes)?;
+        let mut entries = try_with_capacity(raw_entries.len())?;
+        entries.extend(
+            raw_entries
+                .iter()
+                .filter(|entry| {
+                    !entry.is_dot() && !entry.attributes.contains(FatAttributes::VOLUME_ID)
+                })
+                .map(FatDirEntry::from),
+        );
+        Ok(entries)
+    }
+
+    /// Get the entry of the file or directory at `path`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the file doesn't exist.
+    /// * [`Status::VOLUME_CORRUPTED`]: the volume is inconsistent.
+    /// * Errors of [`FatDevice::read`].
+    pub fn metadata(&mut self, path: &str) -> Result<FatDirEntry> {
+        Ok(match self.lookup(path)? {
+            (_, Some(entry)) => FatDirEntry::from(&entry),
+            (_, None) => FatDirEntry {
+                name: String::new(),
+                attributes: FatAttributes::DIRECTORY,
+                size: 0,
+            },
+        })
+    }
+
+    /// Get the root directory.
+    const fn root(&self) -> Dir {
+        match self.layout.fat_type {
+            FatType::Fat32 => Dir::Clusters(self.layout.root_cluster),
+            _ => Dir::FixedRoot,
+        }
+    }
+
+    /// Get the directory starting at `cluster`, where cluster 0 is the root
+    /// directory.
+    const fn dir_at(&self, cluster: u32) -> Dir {
+        if cluster == 0 {
+            self.root()
+        } else {
+            Dir::Clusters(cluster)
+        }
+    }
+
+    /// Find the entry at `path`. Returns the parent directory and the entry,
+    /// or `None` for the root directory.
+    fn lookup(&mut self, path: &str) -> Result<(Dir, Option<RawEntry>)> {
+        let components = components(path)?;
+        let (last, parents) = match components.split_last() {
+            Some(split) => split,
+            None => return Ok((self.root(), None)),
+        };
+        let parent = self.open_dir(parents)?;
+        let (_, entry) = self.find(parent, last)?.ok_or(Status::NOT_FOUND)?;
+        Ok((parent, Some(entry)))
+    }
+
+    /// Get the parent directory of `path` and the last component.
+    fn parent<'p>(&mut self, path: &'p str) -> Result<(Dir, &'p str)> {
+        let components = components(path)?;
+        match components.split_last() {
+            Some((last, parents)) => Ok((self.open_dir(parents)?, last)),
+            None => Err(Status::ACCESS_DENIED.into()),
+        }
+    }
+
+    /// Get the directory whose path components are `names`.
+    fn open_dir(&mut self, names: &[&str]) -> Result<Dir> {
+        let mut dir = self.root();
+        for name in names {
+            dir = match self.find(dir, name)? {
+                Some((_, entry)) if entry.attributes.contains(FatAttributes::DIRECTORY) => {
+                    self.dir_at(entry.first_cluster)
+                }
+                _ => return Err(Status::NOT_FOUND.into()),
+            };
+        }
+        Ok(dir)
+    }
+
+    /// Find the entry `name` of `dir`.
+    fn find(&mut self, dir: Dir, name: &str) -> Result<Option<(DirData, RawEntry)>> {
+        let data = self.read_dir_data(dir)?;
+        let entry = dir::parse_entries(&data.bytes)?.into_iter().find(|entry| {
+            !entry.attributes.contains(FatAttributes::VOLUME_ID) && entry.matches(name)
+        });
+        Ok(entry.map(|entry| (data, entry)))
+    }
+
+    /// Read the slots of `dir`.
+    fn read_dir_data(&mut self, dir: Dir) -> Result<DirData> {
+        match dir {
+            Dir::FixedRoot => {
+                let mut bytes = try_vec(0, self.layout.root_dir_entries * ENTRY_SIZE)?;
+                self.device.read(self.layout.root_dir_offset, &mut bytes)?;
+                Ok(DirData {
+                    dir,
+                    bytes,
+                    clusters: Vec::new(),
+                })
+            }
+            Dir::Clusters(first) => {
+                let clusters = self.chain(first)?;
+                let len = clusters.len() * self.layout.cluster_size as usize;
+                let mut bytes = try_vec(0, len)?;
+                self.transfer(&clusters, len, |device, offset, range| {
+                    device.read(offset, &mut bytes[range])
+                })?;
+                Ok(DirData {
+                    dir,
+                    bytes,
+                    clusters,
+                })
+            }
+        }
+    }
+
+    /// Write `bytes` at the start of `slot` of a directory.
+    fn write_slots(&mut self, data: &DirData, slot: usize, bytes: &[u8]) -> Result {
+        let offset = slot * ENTRY_SIZE;
+        let offset = match data.dir {
+            Dir::FixedRoot => self.layout.root_dir_offset + offset as u64,
+            Dir::Clusters(_) => {
+                let cluster_size = self.layout.cluster_size as usize;
+                let cluster = data.clusters[offset / cluster_size];
+                self.layout.cluster_offset(cluster) + (offset % cluster_size) as u64
+            }
+        };
+        self.device.write(offset, bytes)
+    }
+
+    /// Add an entry `name` to `dir`.
+    fn add_entry(
+        &mut self,
+        dir: Dir,
+        name: &str,
+        attributes: FatAttributes,
+        first_cluster: u32,
+        size: u32,
+    ) -> Result {
+        let long_name = dir::encode_long_name(name)?;
+        let mut data = self.read_dir_data(dir)?;
+        let entries = dir::parse_entries(&data.bytes)?;
+        let slots = match dir::exact_short_name(name) {
+            Some((short_name, case_flags)) => {
+                dir::build_entry(None, &short_name, case_flags, attributes, first_cluster)
+            }
+            None => {
+                let short_name = dir::generate_short_name(name, |short_name| {
+                    entries.iter().any(|entry| entry.short_name == *short_name)
+                })?;
+                dir::build_entry(Some(&long_name), &short_name, 0, attributes, first_cluster)
+            }
+        };
+        let mut slots = slots;
+        let len = slots.len();
+        slots[len - 4..].copy_from_slice(&size.to_le_bytes());
+        let count = len / ENTRY_SIZE;
+
+        // Find `count` consecutive free slots, extending the directory if
+        // needed.
+        let free = |data: &DirData| {
+            let mut run = 0;
+            for (i, slot) in data.bytes.chunks_exact(ENTRY_SIZE).enumerate() {
+                if slot[0] == 0 || slot[0] == DELETED {
+                    run += 1;
+                    if run == count {
+                        return Some(i + 1 - count);
+                    }
+                } else {
+                    run = 0;
+                }
+            }
+            None
+        };
+        let first = loop {
+            if let Some(first) = free(&data) {
+                break first;
+            }
+            let last = match (data.dir, data.clusters.last()) {
+                (Dir::Clusters(_), Some(&last)) => last,
+                _ => return Err(Status::VOLUME_FULL.into()),
+            };
+            let cluster = self.allocate(1)?[0];
+            self.zero_cluster(cluster)?;
+            self.set_fat(last, cluster);
+            data.clusters.push(cluster);
+            data.bytes
+                .resize(data.bytes.len() + self.layout.cluster_size as usize, 0);
+        };
+
+        for (i, slot) in slots.chunks_exact(ENTRY_SIZE).enumerate() {
+            self.write_slots(&data, first + i, slot)?;
+        }
+        Ok(())
+    }
+
+    /// Fill `cluster` with zeros.
+    fn zero_cluster(&mut self, cluster: u32) -> Result {
+        let zeros = try_vec(0, self.layout.cluster_size as usize)?;
+        self.device
+            .write(self.layout.cluster_offset(cluster), &zeros)
+    }
+
+    /// Read or write `len` bytes of data stored in `clusters`. `f` is called
+    /// with the device offset and the data range of each run of consecutive
+    /// clusters.
+    fn transfer(
+        &mut self,
+        clusters: &[u32],
+        len: usize,
+        mut f: impl FnMut(&mut D, u64, Range<usize>) -> Result,
+    ) -> Result {
+        let cluster_size = self.layout.cluster_size as usize;
+        let mut start = 0;
+        let mut i = 0;
+        while i < clusters.len() && start < len {
+            let mut run = 1;
+            while i + run < clusters.len() && clusters[i + run] == clusters[i] + run as u32 {
+                run += 1;
+            }
+            let end = (start + run * cluster_size).min(len);
+            f(
+                &mut self.device,
+                self.layout.cluster_offset(clusters[i]),
+                start..end,
+            )?;
+            start = end;
+            i += run;
+        }
+        Ok(())
+    }
+
+    /// Get the FAT entry of `cluster`.
+    fn get_fat(&self, cluster: u32) -> u32 {
+        let fat = &self.fat;
+        let n = cluster as usize;
+        match self.layout.fat_type {
+            FatType::Fat12 => {
+                let offset = n + n / 2;
+                let value = u16::from_le_bytes([fat[offset], fat[offset + 1]]);
+                u32::from(if n & 1 == 0 {
+                    value & 0xfff
+                } else {
+                    value >> 4
+                })
+            }
+            FatType::Fat16 => u32::from(u16::from_le_bytes([fat[2 * n], fat[2 * n + 1]])),
+            FatType::Fat32 => {
+                u32::from_le_bytes(fat[4 * n..4 * n + 4].try_into().unwrap()) & 0x0fff_ffff
+            }
+        }
+    }
+
+    /// Set the FAT entry of `cluster` to `value`.
+    fn set_fat(&mut self, cluster: u32, value: u32) {
+        let n = cluster as usize;
+        let (offset, len) = match self.layout.fat_type {
+            FatType::Fat12 => {
+                let offset = n + n / 2;
+                let old = u16::from_le_bytes([self.fat[offset], self.fat[offset + 1]]);
+                let new = if n & 1 == 0 {
+                    (old & 0xf000) | (value as u16 & 0xfff)
+                } else {
+                    (old & 0x000f) | ((value as u16) << 4)
+                };
+                self.fat[offset..offset + 2].copy_from_slice(&new.to_le_bytes());
+                (offset, 2)
+            }
+            FatType::Fat16 => {
+                self.fat[2 * n..2 * n + 2].copy_from_slice(&(value as u16).to_le_bytes());
+                (2 * n, 2)
+            }
+            FatType::Fat32 => {
+                // The high 4 bits are reserved, and must be preserved.
+                let old = u32::from_le_bytes(self.fat[4 * n..4 * n + 4].try_into().unwrap());
+                let new = (old & 0xf000_0000) | (value & 0x0fff_ffff);
+                self.fat[4 * n..4 * n + 4].copy_from_slice(&new.to_le_bytes());
+                (4 * n, 4)
+            }
+        };
+        let sector_size = self.layout.sector_size as usize;
+        self.dirty_sectors[offset / sector_size] = true;
+        self.dirty_sectors[(offset + len - 1) / sector_size] = true;
+    }
+
+    /// Get the clusters of the chain starting at `first`, which is empty if
+    /// `first` is 0.
+    fn chain(&self, first: u32) -> Result<Vec<u32>> {
+        let mut clusters = Vec::new();
+        let mut cluster = first;
+        if cluster == 0 {
+            return Ok(clusters);
+        }
+        loop {
+            if !self.layout.is_valid_cluster(cluster)
+                || clusters.len() >= self.layout.cluster_count as usize
+            {
+                return Err(Status::VOLUME_CORRUPTED.into());
+            }
+            clusters.push(cluster);
+            cluster = self.get_fat(cluster);
+            if self.layout.fat_type.is_end_of_chain(cluster) {
+                return Ok(clusters);
+            }
+        }
+    }
+
+    /// Get the number of free clusters.
+    fn free_clusters(&self) -> u32 {
+        (2..self.layout.cluster_count + 2)
+            .filter(|&cluster| self.get_fat(cluster) == 0)
+            .count() as u32
+    }
+
+    /// Allocate a chain of `count` clusters.
+    fn allocate(&mut self, count: u32) -> Result<Vec<u32>> {
+        let end = self.layout.cluster_count + 2;
+        let start = if self.next_free < end {
+            self.next_free
+        } else {
+            2
+        };
+        let clusters: Vec<u32> = (start..end)
+            .chain(2..start)
+            .filter(|&cluster| self.get_fat(cluster) == 0)
+            .take(count as usize)
+            .collect();
+        if clusters.len() < count as usize {
+            return Err(Status::VOLUME_FULL.into());
+        }
+
+        let end_of_chain = self.layout.fat_type.end_of_chain();
+        for (i, &cluster) in clusters.iter().enumerate() {
+            let next = clusters.get(i + 1).copied().unwrap_or(end_of_chain);
+            self.set_fat(cluster, next);
+        }
+        if let Some(&last) = clusters.last() {
+            self.next_free = last + 1;
+        }
+        Ok(clusters)
+    }
+
+    /// Free the clusters of a chain.
+    fn free_chain(&mut self, clusters: &[u32]) {
+        for &cluster in clusters {
+            self.set_fat(cluster, 0);
+        }
+    }
+
+    /// Write the modified FAT sectors to all the FATs, update the FS
+    /// information sector, and flush the device.
+    fn commit(&mut self) -> Result {
+        let sector_size = self.layout.sector_size as usize;
+        for sector in 0..self.dirty_sectors.len() {
+            if !self.dirty_sectors[sector] {
+                continue;
+            }
+            let bytes = &self.fat[sector * sector_size..(sector + 1) * sector_size];
+            for fat in 0..self.layout.num_fats {
+                let offset = self.layout.fat_offset
+                    + fat * self.layout.fat_size
+                    + (sector * sector_size) as u64;
+                self.device.write(offset, bytes)?;
+            }
+            self.dirty_sectors[sector] = false;
+        }
+
+        if let Some(offset) = self.layout.fs_info_offset {
+            let mut fs_info = [0; 512];
+            self.device.read(offset, &mut fs_info)?;
+            if fs_info[..4] == *b"RRaA" && fs_info[484..488] == *b"rrAa" {
+                fs_info[488..492].copy_from_slice(&self.free_clusters().to_le_bytes());
+                fs_info[492..496].copy_from_slice(&self.next_free.to_le_bytes());
+                self.device.write(offset + 488, &fs_info[488..496])?;
+            }
+        }
+        self.device.flush()
+    }
+}
+
+impl<D: FatDevice> Debug for FatFileSystem<D> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FatFileSystem")
+            .field("layout", &self.layout)
+            .field("next_free", &self.next_free)
+            .finish_non_exhaustive()
+    }
+}
+
+impl DirData {
+    /// Get the bytes of `slot`.
+    fn slot(&self, slot: usize) -> &[u8] {
+        &self.bytes[slot * ENTRY_SIZE..(slot + 1) * ENTRY_SIZE]
+    }
+}
+
+/// Split `path` into its components, resolving `.` and `..`.
+fn components(path: &str) -> Result<Vec<&str>> {
+    let mut components = Vec::new();
+    for name in path.split(['/', '\\']) {
+        match name {
+            "" | "." => {}
+            ".." => {
+                components.pop().ok_or(Status::INVALID_PARAMETER)?;
+            }
+            name => components.push(name),
+        }
+    }
+    Ok(components)
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use alloc::vec;
+
+    /// In-memory device.
+    struct MemDevice {
+        data: Vec<u8>,
+        sector_size: u32,
+    }
+
+    impl MemDevice {
+        fn new(size: usize) -> Self {
+            Self {
+                data: vec![0; size],
+                sector_size: 512,
+            }
+        }
+    }
+
+    impl FatDevice for MemDevice {
+        fn sector_size(&self) -> u32 {
+            self.sector_size
+        }
+
+        fn size(&self) -> u64 {
+            self.data.len() as u64
+        }
+
+        fn read(&mut self, offset: u64, buffer: &mut [u8]) -> Result {
+            let offset = offset as usize;
+            buffer.copy_from_slice(&self.data[offset..offset + buffer.len()]);
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u64, buffer: &[u8]) -> Result {
+            let offset = offset as usize;
+            self.data[offset..offset + buffer.len()].copy_from_slice(buffer);
+            Ok(())
+        }
+    }
+
+    fn names(entries: &[FatDirEntry]) -> Vec<&str> {
+        entries.iter().map(|entry| entry.name.as_str()).collect()
+    }
+
+    /// Test common operations on a freshly formatted device.
+    fn check_file_system(
+        bt: &BootServices,
+        device: &mut MemDevice,
+        fat_type: Option<FatType>,
+    ) -> FatType {
+        let mut options = FatFormatOptions::new()
+            .with_label("Test")
+            .with_volume_id(0x1234_5678);
+        if let Some(fat_type) = fat_type {
+            options = options.with_fat_type(fat_type);
+        }
+        let mut fs = FatFileSystem::format(bt, &mut *device, &options).unwrap();
+        let free = fs.free_space();
+        assert!(fs.read_dir("").unwrap().is_empty());
+
+        fs.create_dir("EFI/Boot").unwrap();
+        fs.create_dir("/EFI").unwrap();
+        let loader: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        fs.write_file(bt, "EFI\\Boot\\bootx64.efi", &loader)
+            .unwrap();
+        fs.write_file(bt, "EFI/Boot/A long name.conf", b"timeout 3")
+            .unwrap();
+        fs.write_file(bt, "empty", &[]).unwrap();
+
+        assert_eq!(names(&fs.read_dir("").unwrap()), ["EFI", "empty"]);
+        assert_eq!(
+            names(&fs.read_dir("efi/boot").unwrap()),
+            ["bootx64.efi", "A long name.conf"]
+        );
+        assert_eq!(fs.read_file("EFI/BOOT/BOOTX64.EFI").unwrap(), loader);
+        assert_eq!(
+            fs.read_file("efi/./boot/../boot/a LONG name.conf").unwrap(),
+            b"timeout 3"
+        );
+        assert!(fs.read_file("empty").unwrap().is_empty());
+        assert!(fs.metadata("EFI").unwrap().is_dir());
+        assert_eq!(fs.metadata("EFI/Boot/bootx64.efi").unwrap().size, 10_000);
+
+        assert_eq!(
+            fs.read_file("missing").unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+        assert_eq!(
+            fs.write_file(bt, "missing/file", b"").unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+        assert_eq!(
+            fs.write_file(bt, "EFI", b"").unwrap_err().status(),
+            Status::ACCESS_DENIED
+        );
+        assert_eq!(
+            fs.create_dir("empty/dir").unwrap_err().status(),
+            Status::ACCESS_DENIED
+        );
+        assert_eq!(
+            fs.write_file(bt, "EFI/a:b", b"").unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+        assert_eq!(
+            fs.remove("EFI").unwrap_err().status(),
+            Status::ACCESS_DENIED
+        );
+        assert_eq!(
+            fs.write_file(bt, "big", &vec![0; free as usize + 1])
+                .unwrap_err()
+                .status(),
+            Status::VOLUME_FULL
+        );
+
+        // Replace a file with a smaller one.
+        fs.write_file(bt, "EFI/Boot/bootx64.efi", b"small").unwrap();
+        assert_eq!(fs.read_file("EFI/Boot/bootx64.efi").unwrap(), b"small");
+
+        // The volume can be opened again.
+        let fat_type = fs.fat_type();
+        let mut fs = FatFileSystem::new(&mut *device).unwrap();
+        assert_eq!(fs.fat_type(), fat_type);
+        assert_eq!(fs.read_file("EFI/Boot/bootx64.efi").unwrap(), b"small");
+
+        for path in [
+            "EFI/Boot/bootx64.efi",
+            "EFI/Boot/A long name.conf",
+            "EFI/Boot",
+            "EFI",
+            "empty",
+        ] {
+            fs.remove(path).unwrap();
+        }
+        assert!(fs.read_dir("").unwrap().is_empty());
+        assert_eq!(fs.free_space(), free);
+
+        // Entries are reused after being removed.
+        fs.write_file(bt, "new.txt", b"new").unwrap();
+        assert_eq!(names(&fs.read_dir("/").unwrap()), ["new.txt"]);
+        fat_type
+    }
+
+    #[test]
+    fn test_fat12() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut device = MemDevice::new(1 << 20);
+        assert_eq!(
+            FatFileSystem::new(&mut device).unwrap_err().status(),
+            Status::VOLUME_CORRUPTED
+        );
+        assert_eq!(check_file_system(bt, &mut device, None), FatType::Fat12);
+        assert_eq!(&device.data[43..54], b"TEST       ");
+        assert_eq!(&device.data[54..62], b"FAT12   ");
+    }
+
+    #[test]
+    fn test_fat16() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut device = MemDevice::new(16 << 20);
+        assert_eq!(check_file_system(bt, &mut device, None), FatType::Fat16);
+        assert_eq!(&device.data[54..62], b"FAT16   ");
+
+        // The root directory has a fixed size of 512 entries, two of which
+        // are the label and `new.txt`.
+        let mut fs = FatFileSystem::new(&mut device).unwrap();
+        for i in 2..512 {
+            fs.write_file(bt, &alloc::format!("{i}"), b"").unwrap();
+        }
+        assert_eq!(
+            fs.write_file(bt, "full", b"").unwrap_err().status(),
+            Status::VOLUME_FULL
+        );
+    }
+
+    #[test]
+    fn test_fat32() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut device = MemDevice::new(64 << 20);
+        assert_eq!(
+            check_file_system(bt, &mut device, Some(FatType::Fat32)),
+            FatType::Fat32
+        );
+        assert_eq!(&device.data[82..90], b"FAT32   ");
+        assert_eq!(device.data[..512], device.data[6 * 512..7 * 512]);
+
+        // Directories grow as needed.
+        let mut fs = FatFileSystem::new(&mut device).unwrap();
+        let cluster_size = fs.cluster_size() as usize;
+        for i in 0..cluster_size / ENTRY_SIZE * 3 {
+            fs.write_file(bt, &alloc::format!("file {i}"), b"data")
+                .unwrap();
+        }
+        assert_eq!(
+            fs.read_dir("").unwrap().len(),
+            cluster_size / ENTRY_SIZE * 3 + 1
+        );
+        assert_eq!(fs.read_file("file 40").unwrap(), b"data");
+
+        // The free count of the FS information sector is kept up to date.
+        let free = fs.free_space() / cluster_size as u64;
+        assert_eq!(
+            device.data[512 + 488..512 + 492],
+            (free as u32).to_le_bytes()
+        );
+
+        // FAT16 requires more clusters.
+        let mut device = MemDevice::new(1 << 20);
+        let options = FatFormatOptions::new().with_fat_type(FatType::Fat16);
+        assert_eq!(
+            FatFileSystem::format(bt, &mut device, &options)
+                .unwrap_err()
+                .status(),
+            Status::BAD_BUFFER_SIZE
+        );
+        let options = FatFormatOptions::new().with_label("bad:label");
+        assert_eq!(
+            FatFileSystem::format(bt, &mut device, &options)
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+    }
+
+    #[test]
+    fn test_block_io() {
+        use crate::mock::MockBlockDevice;
+        use crate::proto::media::block::BlockIO;
+
+        let mut firmware = MockFirmware::new();
+        let device = MockBlockDevice::new(512, 4096);
+        let handle = firmware.install_block_device(&device);
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut block_io = bt.open_protocol_exclusive::<BlockIO>(handle).unwrap();
+
+        let options = FatFormatOptions::new().with_label("ESP");
+        let mut fs = FatFileSystem::format(bt, &mut *block_io, &options).unwrap();
+        fs.create_dir("EFI/BOOT").unwrap();
+        fs.write_file(bt, "EFI/BOOT/BOOTX64.EFI", b"MZ").unwrap();
+
+        let mut fs = FatFileSystem::new(&mut *block_io).unwrap();
+        assert_eq!(fs.read_file("EFI/BOOT/BOOTX64.EFI").unwrap(), b"MZ");
+        assert_eq!(&device.contents()[510..512], [0x55, 0xaa]);
+    }
+}
@@ -0,0 +1,255 @@
+use super::{align, corrupted, read_guid, read_u24, read_u64, Section, SectionType, Sections};
+use crate::{Guid, Result};
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+use bitflags::bitflags;
+
+/// Size of a file header for files smaller than 16 MiB.
+const HEADER_SIZE: usize = 24;
+
+/// Size of a file header for larger files, which have an extended size.
+const HEADER2_SIZE: usize = 32;
+
+// Bits of the file state, with an erase polarity of zero.
+const STATE_DATA_VALID: u8 = 0x04;
+const STATE_DELETED: u8 = 0x10;
+const STATE_HEADER_INVALID: u8 = 0x20;
+
+newtype_enum! {
+/// Type of a [`File`], which determines its contents.
+pub enum FileType: u8 => {
+    /// Raw data, without sections.
+    RAW                   = 0x01,
+    /// Sections without a specific format.
+    FREEFORM              = 0x02,
+    /// The SEC core.
+    SECURITY_CORE         = 0x03,
+    /// The PEI core.
+    PEI_CORE              = 0x04,
+    /// The DXE core.
+    DXE_CORE              = 0x05,
+    /// A PEI module.
+    PEIM                  = 0x06,
+    /// A DXE driver.
+    DRIVER                = 0x07,
+    /// A module that can run as a PEI module and as a DXE driver.
+    COMBINED_PEIM_DRIVER  = 0x08,
+    /// A UEFI application.
+    APPLICATION           = 0x09,
+    /// A management mode driver.
+    MM                    = 0x0a,
+    /// A nested firmware volume, in a
+    /// [`FIRMWARE_VOLUME_IMAGE`](SectionType::FIRMWARE_VOLUME_IMAGE)
+    /// section.
+    FIRMWARE_VOLUME_IMAGE = 0x0b,
+    /// A module that can run as a management mode driver and as a DXE
+    /// driver.
+    COMBINED_MM_DXE       = 0x0c,
+    /// The management mode core.
+    MM_CORE               = 0x0d,
+    /// A standalone management mode driver.
+    MM_STANDALONE         = 0x0e,
+    /// The standalone management mode core.
+    MM_CORE_STANDALONE    = 0x0f,
+    /// Padding between files, without sections.
+    FFS_PAD               = 0xf0,
+}}
+
+bitflags! {
+    /// Attributes of a [`File`].
+    #[repr(transparent)]
+    pub struct FileAttributes: u8 {
+        /// The file has an extended header with a 64-bit size.
+        const LARGE_FILE = 0x01;
+        /// Extends the data alignment of [`DATA_ALIGNMENT`] to larger
+        /// values.
+        ///
+        /// [`DATA_ALIGNMENT`]: Self::DATA_ALIGNMENT
+        const DATA_ALIGNMENT_2 = 0x02;
+        /// The file must stay at a fixed offset in the volume.
+        const FIXED = 0x04;
+        /// Mask of the alignment of the file data.
+        const DATA_ALIGNMENT = 0x38;
+        /// The file data has a checksum.
+        const CHECKSUM = 0x40;
+    }
+}
+
+/// A file in a [`FirmwareVolume`].
+///
+/// The corresponding C type is `EFI_FFS_FILE_HEADER` or
+/// `EFI_FFS_FILE_HEADER2`, followed by the file data.
+///
+/// [`FirmwareVolume`]: super::FirmwareVolume
+#[derive(Clone, Copy, Debug)]
+pub struct File<'a> {
+    bytes: &'a [u8],
+    header_len: usize,
+}
+
+impl<'a> File<'a> {
+    /// Get the raw bytes of the file, including its header.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get the name of the file.
+    #[must_use]
+    pub fn name(&self) -> Guid {
+        // OK to unwrap: the header was checked when iterating.
+        read_guid(self.bytes, 0).unwrap()
+    }
+
+    /// Get the type of the file.
+    #[must_use]
+    pub const fn file_type(&self) -> FileType {
+        FileType(self.bytes[18])
+    }
+
+    /// Get the attributes of the file.
+    #[must_use]
+    pub const fn attributes(&self) -> FileAttributes {
+        FileAttributes::from_bits_truncate(self.bytes[19])
+    }
+
+    /// Get the data of the file, after its header.
+    #[must_use]
+    pub fn data(&self) -> &'a [u8] {
+        &self.bytes[self.header_len..]
+    }
+
+    /// Check whether the data of the file is made of sections. This is the
+    /// case for all file types but [`FileType::RAW`] and
+    /// [`FileType::FFS_PAD`].
+    #[must_use]
+    pub fn has_sections(&self) -> bool {
+        !matches!(self.file_type(), FileType::RAW | FileType::FFS_PAD)
+    }
+
+    /// Get an iterator over the top-level sections of the file.
+    ///
+    /// The iterator yields an error and stops if a section header is
+    /// corrupt, which is also the case for files without sections.
+    #[must_use]
+    pub fn sections(&self) -> Sections<'a> {
+        Sections::new(self.data())
+    }
+
+    /// Find the first top-level section with type `section_type`.
+    ///
+    /// # Errors
+    ///
+    /// See [`sections`].
+    ///
+    /// [`sections`]: Self::sections
+    pub fn find_section(&self, section_type: SectionType) -> Result<Option<Section<'a>>> {
+        for section in self.sections() {
+            let section = section?;
+            if section.section_type() == section_type {
+                return Ok(Some(section));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find the data of the first section with type `section_type`,
+    /// searching encapsulation sections recursively, depth first.
+    ///
+    /// Encapsulated sections are extracted with
+    /// [`Section::encapsulated_data`]. Encapsulation sections that can't be
+    /// extracted, such as sections compressed with an unsupported algorithm,
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// See [`sections`] and [`Section::encapsulated_data`].
+    ///
+    /// [`sections`]: Self::sections
+    #[cfg(feature = "alloc")]
+    pub fn find_section_data(&self, section_type: SectionType) -> Result<Option<Cow<'a, [u8]>>> {
+        super::section::find_section_data(self.sections(), section_type)
+    }
+}
+
+/// Iterator over the files of a [`FirmwareVolume`], returned by
+/// [`FirmwareVolume::files`].
+///
+/// [`FirmwareVolume`]: super::FirmwareVolume
+/// [`FirmwareVolume::files`]: super::FirmwareVolume::files
+#[derive(Clone, Debug)]
+pub struct Files<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    erase_polarity: bool,
+    done: bool,
+}
+
+impl<'a> Files<'a> {
+    pub(super) const fn new(bytes: &'a [u8], offset: usize, erase_polarity: bool) -> Self {
+        Self {
+            bytes,
+            offset,
+            erase_polarity,
+            done: false,
+        }
+    }
+
+    /// Parse the file at the current offset, and advance past it.
+    fn parse(&mut self) -> Result<Option<File<'a>>> {
+        loop {
+            let header = match self.bytes.get(self.offset..self.offset + HEADER_SIZE) {
+                Some(header) => header,
+                None => return Ok(None),
+            };
+            // The free space at the end of the volume is erased.
+            let erased = if self.erase_polarity { 0xff } else { 0 };
+            if header.iter().all(|&b| b == erased) {
+                return Ok(None);
+            }
+
+            let attributes = FileAttributes::from_bits_truncate(header[19]);
+            let (size, header_len) = if attributes.contains(FileAttributes::LARGE_FILE) {
+                let size = read_u64(self.bytes, self.offset + HEADER_SIZE)?;
+                (
+                    usize::try_from(size).map_err(|_| corrupted())?,
+                    HEADER2_SIZE,
+                )
+            } else {
+                (read_u24(header, 20)? as usize, HEADER_SIZE)
+            };
+            let bytes = self
+                .bytes
+                .get(self.offset..)
+                .and_then(|bytes| bytes.get(..size))
+                .filter(|_| size >= header_len)
+                .ok_or_else(corrupted)?;
+            self.offset = align(self.offset + size, 8);
+
+            let mut state = header[23];
+            if self.erase_polarity {
+                state = !state;
+            }
+            // Skip deleted or incomplete files.
+            if state & (STATE_DELETED | STATE_HEADER_INVALID) == 0 && state & STATE_DATA_VALID != 0
+            {
+                return Ok(Some(File { bytes, header_len }));
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for Files<'a> {
+    type Item = Result<File<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let file = self.parse().transpose();
+        if !matches!(file, Some(Ok(_))) {
+            self.done = true;
+        }
+        file
+    }
+}
@@ -0,0 +1,452 @@
+//! Parsing of firmware volumes, and of the files and sections they contain.
+//!
+//! Firmware images are made of firmware volumes, as defined in volume 3 of
+//! the [Platform Initialization specification][spec]. A volume formatted
+//! with the Firmware File System (FFS) contains [`File`]s identified by a
+//! GUID. Most files are made of [`Section`]s, some of which encapsulate
+//! other sections, possibly compressed.
+//!
+//! The parsers in this module work on byte slices, so they can be used on
+//! memory-mapped flash as well as on images loaded from a file. All the
+//! values in a firmware volume are little endian, and may not be aligned.
+//!
+//! # Example
+//!
+//! ```
+//! use uefi::fv::{FileType, FirmwareVolume, SectionType};
+//!
+//! fn print_drivers(image: &[u8]) -> uefi::Result {
+//!     let fv = FirmwareVolume::new(image)?;
+//!     for file in fv.files_of_type(FileType::DRIVER) {
+//!         let file = file?;
+//!         let pe32 = file.find_section(SectionType::PE32)?;
+//!         log::info!("{}: {:?}", file.name(), pe32.map(|s| s.data().len()));
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [spec]: https://uefi.org/specifications
+
+mod file;
+mod section;
+
+pub use file::{File, FileAttributes, FileType, Files};
+pub use section::{Section, SectionType, Sections};
+
+use crate::{guid, Guid, Result, Status};
+
+/// `_FVH`
+const SIGNATURE: u32 = u32::from_le_bytes(*b"_FVH");
+
+/// Size of the fixed part of the volume header, up to the block map.
+const HEADER_SIZE: usize = 56;
+
+/// Attribute bit set if erased flash reads as all ones.
+const ERASE_POLARITY: u32 = 0x800;
+
+/// A firmware volume.
+///
+/// The corresponding C type is `EFI_FIRMWARE_VOLUME_HEADER`, followed by the
+/// contents of the volume.
+#[derive(Clone, Copy, Debug)]
+pub struct FirmwareVolume<'a> {
+    bytes: &'a [u8],
+    header_len: usize,
+    files_offset: usize,
+}
+
+impl<'a> FirmwareVolume<'a> {
+    /// File system GUID of volumes formatted with the FFS, version 2.
+    pub const FFS2_GUID: Guid = guid!("8c8ce578-8a3d-4f1c-9935-896185c32dd3");
+
+    /// File system GUID of volumes formatted with the FFS, version 3, which
+    /// supports files larger than 16 MiB.
+    pub const FFS3_GUID: Guid = guid!("5473c07a-3dcb-4dca-bd6f-1e9689e7349a");
+
+    /// Parse the volume at the start of `bytes`. Bytes past the length of
+    /// the volume recorded in its header are ignored.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::VOLUME_CORRUPTED`]: `bytes` doesn't start with a valid
+    ///   volume header, or is shorter than the volume.
+    /// * [`Status::CRC_ERROR`]: the checksum of the volume header is wrong.
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        if read_u32(bytes, 40)? != SIGNATURE {
+            return Err(Status::VOLUME_CORRUPTED.into());
+        }
+
+        let len = usize::try_from(read_u64(bytes, 32)?).map_err(|_| corrupted())?;
+        let header_len = usize::from(read_u16(bytes, 48)?);
+        if len > bytes.len() || header_len < HEADER_SIZE || header_len > len {
+            return Err(Status::VOLUME_CORRUPTED.into());
+        }
+        let bytes = &bytes[..len];
+
+        let checksum = bytes[..header_len].chunks(2).fold(0u16, |sum, chunk| {
+            sum.wrapping_add(u16::from_le_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)]))
+        });
+        if checksum != 0 {
+            return Err(Status::CRC_ERROR.into());
+        }
+
+        let ext_offset = usize::from(read_u16(bytes, 52)?);
+        let files_offset = if ext_offset == 0 {
+            header_len
+        } else {
+            let ext_size = read_u32(bytes, ext_offset + 16)? as usize;
+            ext_offset.saturating_add(ext_size)
+        };
+
+        Ok(Self {
+            bytes,
+            header_len,
+            files_offset: align(files_offset, 8),
+        })
+    }
+
+    /// Get the raw bytes of the volume, including its header.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get the length of the volume header in bytes.
+    #[must_use]
+    pub const fn header_len(&self) -> usize {
+        self.header_len
+    }
+
+    /// Get the GUID of the file system the volume is formatted with, such as
+    /// [`FFS2_GUID`] or [`FFS3_GUID`].
+    ///
+    /// [`FFS2_GUID`]: Self::FFS2_GUID
+    /// [`FFS3_GUID`]: Self::FFS3_GUID
+    #[must_use]
+    pub fn file_system_guid(&self) -> Guid {
+        // OK to unwrap: the header was checked in `new`.
+        read_guid(self.bytes, 16).unwrap()
+    }
+
+    /// Check whether the volume is formatted with the FFS, and can be
+    /// parsed with [`files`].
+    ///
+    /// [`files`]: Self::files
+    #[must_use]
+    pub fn is_ffs(&self) -> bool {
+        let guid = self.file_system_guid();
+        guid == Self::FFS2_GUID || guid == Self::FFS3_GUID
+    }
+
+    /// Get the name of the volume, which is stored in the optional extended
+    /// header.
+    #[must_use]
+    pub fn name(&self) -> Option<Guid> {
+        match usize::from(read_u16(self.bytes, 52).ok()?) {
+            0 => None,
+            offset => read_guid(self.bytes, offset).ok(),
+        }
+    }
+
+    /// Get the raw `EFI_FVB_ATTRIBUTES_2` of the volume.
+    #[must_use]
+    pub fn attributes(&self) -> u32 {
+        // OK to unwrap: the header was checked in `new`.
+        read_u32(self.bytes, 44).unwrap()
+    }
+
+    /// Get the revision of the volume header format.
+    #[must_use]
+    pub fn revision(&self) -> u8 {
+        self.bytes[55]
+    }
+
+    /// Check whether erased flash reads as all ones, rather than all zeros.
+    #[must_use]
+    pub fn erase_polarity(&self) -> bool {
+        self.attributes() & ERASE_POLARITY != 0
+    }
+
+    /// Get an iterator over the files of the volume. Deleted files are
+    /// skipped, but pad files are returned.
+    ///
+    /// The iterator yields an error and stops if a file header is corrupt.
+    #[must_use]
+    pub fn files(&self) -> Files<'a> {
+        Files::new(self.bytes, self.files_offset, self.erase_polarity())
+    }
+
+    /// Get an iterator over the files of the volume with type `file_type`.
+    /// Errors from [`files`] are passed through.
+    ///
+    /// [`files`]: Self::files
+    pub fn files_of_type(&self, file_type: FileType) -> impl Iterator<Item = Result<File<'a>>> {
+        self.files()
+            .filter(move |file| !matches!(file, Ok(file) if file.file_type() != file_type))
+    }
+
+    /// Find the file named `name`.
+    ///
+    /// # Errors
+    ///
+    /// See [`files`].
+    ///
+    /// [`files`]: Self::files
+    pub fn find_file(&self, name: &Guid) -> Result<Option<File<'a>>> {
+        for file in self.files() {
+            let file = file?;
+            if file.name() == *name {
+                return Ok(Some(file));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Round `offset` up to a multiple of `alignment`, which must be a power of
+/// two.
+const fn align(offset: usize, alignment: usize) -> usize {
+    (offset + alignment - 1) & !(alignment - 1)
+}
+
+fn corrupted() -> crate::Error {
+    Status::VOLUME_CORRUPTED.into()
+}
+
+fn read_array<const N: usize>(bytes: &[u8], offset: usize) -> Result<[u8; N]> {
+    let bytes = bytes
+        .get(offset..)
+        .and_then(|bytes| bytes.get(..N))
+        .ok_or_else(corrupted)?;
+    // OK to unwrap: the slice has exactly `N` bytes.
+    Ok(bytes.try_into().unwrap())
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16> {
+    read_array(bytes, offset).map(u16::from_le_bytes)
+}
+
+/// Read a 24-bit size.
+fn read_u24(bytes: &[u8], offset: usize) -> Result<u32> {
+    read_array(bytes, offset).map(|[a, b, c]| u32::from_le_bytes([a, b, c, 0]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    read_array(bytes, offset).map(u32::from_le_bytes)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64> {
+    read_array(bytes, offset).map(u64::from_le_bytes)
+}
+
+fn read_guid(bytes: &[u8], offset: usize) -> Result<Guid> {
+    read_array(bytes, offset).map(Guid::from_bytes)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    const FILE_A: Guid = guid!("11111111-2222-3333-4444-555555555555");
+    const FILE_B: Guid = guid!("66666666-7777-8888-9999-aaaaaaaaaaaa");
+
+    /// Build a section with a small header.
+    fn section(section_type: SectionType, data: &[u8]) -> Vec<u8> {
+        let mut out = (data.len() as u32 + 4).to_le_bytes()[..3].to_vec();
+        out.push(section_type.0);
+        out.extend(data);
+        // Sections are 4-byte aligned.
+        out.resize(align(out.len(), 4), 0);
+        out
+    }
+
+    /// Build a file with an erase polarity of one.
+    fn file(name: Guid, file_type: FileType, data: &[u8], state: u8) -> Vec<u8> {
+        let mut out = name.to_bytes().to_vec();
+        out.extend([0, 0, file_type.0, 0]);
+        out.extend(&(data.len() as u32 + 24).to_le_bytes()[..3]);
+        out.push(!state);
+        out.extend(data);
+        out
+    }
+
+    const VALID: u8 = 0x07;
+    const DELETED: u8 = 0x17;
+
+    /// Build an FFS2 volume with an erase polarity of one.
+    fn volume(files: &[Vec<u8>]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for file in files {
+            body.resize(align(body.len(), 8), 0xff);
+            body.extend(file);
+        }
+        // Free space.
+        body.resize(align(body.len(), 8) + 32, 0xff);
+
+        let header_len = HEADER_SIZE + 16;
+        let len = header_len + body.len();
+        let mut out = vec![0; 16];
+        out.extend(FirmwareVolume::FFS2_GUID.to_bytes());
+        out.extend((len as u64).to_le_bytes());
+        out.extend(b"_FVH");
+        out.extend((ERASE_POLARITY | 0x7).to_le_bytes());
+        out.extend((header_len as u16).to_le_bytes());
+        // Checksum, extended header offset, reserved, revision.
+        out.extend([0, 0, 0, 0, 0, 2]);
+        // Block map: one block, and the terminator.
+        out.extend(1u32.to_le_bytes());
+        out.extend((len as u32).to_le_bytes());
+        out.extend([0; 8]);
+
+        let sum = out.chunks(2).fold(0u16, |sum, c| {
+            sum.wrapping_add(u16::from_le_bytes([c[0], c[1]]))
+        });
+        out[50..52].copy_from_slice(&0u16.wrapping_sub(sum).to_le_bytes());
+        out.extend(body);
+        out
+    }
+
+    #[test]
+    fn test_files() {
+        let driver = [
+            section(SectionType::PE32, b"MZ.."),
+            section(SectionType::USER_INTERFACE, b"a\0b\0\0\0"),
+        ]
+        .concat();
+        let bytes = volume(&[
+            file(FILE_A, FileType::DRIVER, &driver, VALID),
+            file(FILE_A, FileType::APPLICATION, &[], DELETED),
+            file(FILE_B, FileType::RAW, b"raw", VALID),
+        ]);
+
+        let fv = FirmwareVolume::new(&bytes).unwrap();
+        assert!(fv.is_ffs());
+        assert!(fv.erase_polarity());
+        assert_eq!(fv.name(), None);
+        assert_eq!(fv.revision(), 2);
+        assert_eq!(fv.header_len(), 72);
+
+        let files: Vec<_> = fv.files().map(Result::unwrap).collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].name(), FILE_A);
+        assert_eq!(files[0].file_type(), FileType::DRIVER);
+        assert_eq!(files[0].data(), driver);
+        assert_eq!(files[1].name(), FILE_B);
+        assert_eq!(files[1].data(), b"raw");
+
+        let raw: Vec<_> = fv.files_of_type(FileType::RAW).collect();
+        assert_eq!(raw.len(), 1);
+        assert_eq!(raw[0].as_ref().unwrap().name(), FILE_B);
+
+        let file = fv.find_file(&FILE_A).unwrap().unwrap();
+        let sections: Vec<_> = file.sections().map(Result::unwrap).collect();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].section_type(), SectionType::PE32);
+        assert_eq!(sections[0].data(), b"MZ..");
+        let ui = file.find_section(SectionType::USER_INTERFACE).unwrap();
+        assert_eq!(ui.unwrap().data(), b"a\0b\0\0\0");
+        assert!(file.find_section(SectionType::TE).unwrap().is_none());
+
+        assert!(fv.find_file(&Guid::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_encapsulation() {
+        let raw = section(SectionType::RAW, b"data");
+
+        // A GUID-defined section that doesn't need processing.
+        let mut guided = guid!("01234567-89ab-cdef-0123-456789abcdef")
+            .to_bytes()
+            .to_vec();
+        guided.extend(24u16.to_le_bytes());
+        guided.extend(0u16.to_le_bytes());
+        guided.extend(&raw);
+        let guided = section(SectionType::GUID_DEFINED, &guided);
+
+        // An uncompressed compression section.
+        let mut compressed = (guided.len() as u32).to_le_bytes().to_vec();
+        compressed.push(0);
+        compressed.extend(&guided);
+        let compressed = section(SectionType::COMPRESSION, &compressed);
+
+        // A GUID-defined section with an unknown format, which is skipped.
+        let mut unknown = guid!("fedcba98-7654-3210-fedc-ba9876543210")
+            .to_bytes()
+            .to_vec();
+        unknown.extend(24u16.to_le_bytes());
+        unknown.extend(1u16.to_le_bytes());
+        unknown.extend(&raw);
+        let unknown = section(SectionType::GUID_DEFINED, &unknown);
+
+        let data = [unknown, compressed].concat();
+        let bytes = volume(&[file(FILE_A, FileType::FREEFORM, &data, VALID)]);
+        let fv = FirmwareVolume::new(&bytes).unwrap();
+        let file = fv.find_file(&FILE_A).unwrap().unwrap();
+
+        // Only the top-level sections are searched by `find_section`.
+        assert!(file.find_section(SectionType::RAW).unwrap().is_none());
+        let found = file.find_section_data(SectionType::RAW).unwrap().unwrap();
+        assert_eq!(*found, *b"data");
+
+        let section = file.sections().next().unwrap().unwrap();
+        assert_eq!(
+            section.guid(),
+            Some(guid!("fedcba98-7654-3210-fedc-ba9876543210"))
+        );
+        assert_eq!(
+            section.encapsulated_data().unwrap_err().status(),
+            Status::UNSUPPORTED
+        );
+    }
+
+    #[cfg(feature = "decompress")]
+    #[test]
+    fn test_compressed_section() {
+        // "AAAA" compressed with the EFI algorithm.
+        let mut data = 4u32.to_le_bytes().to_vec();
+        data.push(1);
+        data.extend([
+            7, 0, 0, 0, 4, 0, 0, 0, 0x00, 0x04, 0x00, 0x00, 0x04, 0x10, 0x00,
+        ]);
+        let bytes = section(SectionType::COMPRESSION, &data);
+        let section = Sections::new(&bytes).next().unwrap().unwrap();
+        assert_eq!(*section.encapsulated_data().unwrap(), *b"AAAA");
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let mut bytes = volume(&[file(FILE_A, FileType::RAW, b"raw", VALID)]);
+
+        // Truncated volume.
+        assert_eq!(
+            FirmwareVolume::new(&bytes[..100]).unwrap_err().status(),
+            Status::VOLUME_CORRUPTED
+        );
+
+        // File larger than the volume.
+        bytes[72 + 20] = 0xf0;
+        let fv = FirmwareVolume::new(&bytes).unwrap();
+        let mut files = fv.files();
+        assert_eq!(
+            files.next().unwrap().unwrap_err().status(),
+            Status::VOLUME_CORRUPTED
+        );
+        assert!(files.next().is_none());
+
+        // Bad header checksum.
+        bytes[55] = 3;
+        assert_eq!(
+            FirmwareVolume::new(&bytes).unwrap_err().status(),
+            Status::CRC_ERROR
+        );
+
+        // Section larger than its file.
+        let section = [0x10, 0, 0, SectionType::RAW.0];
+        let mut sections = Sections::new(&section);
+        assert!(sections.next().unwrap().is_err());
+        assert!(sections.next().is_none());
+    }
+}
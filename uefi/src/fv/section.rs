@@ -0,0 +1,296 @@
+use super::{align, corrupted, read_guid, read_u24, read_u32};
+use crate::{Guid, Result};
+#[cfg(feature = "alloc")]
+use {super::read_u16, crate::guid, crate::Status, alloc::borrow::Cow};
+
+/// Size of a section header for sections smaller than 16 MiB.
+const HEADER_SIZE: usize = 4;
+
+/// Size of a section header for larger sections, which have an extended
+/// size.
+const HEADER2_SIZE: usize = 8;
+
+/// Value of the 24-bit size of sections with an extended size.
+const EXTENDED_SIZE: u32 = 0xff_ffff;
+
+/// Attribute of GUID-defined sections whose data must be processed to get
+/// the encapsulated sections.
+#[cfg(feature = "alloc")]
+const PROCESSING_REQUIRED: u16 = 0x01;
+
+/// GUID-defined sections compressed with the Tiano algorithm.
+#[cfg(feature = "alloc")]
+const TIANO_COMPRESS_GUID: Guid = guid!("a31280ad-481e-41b6-95e8-127f4c984779");
+
+/// GUID-defined sections whose data is preceded by a CRC32.
+#[cfg(feature = "alloc")]
+const CRC32_GUID: Guid = guid!("fc1bcdb0-7d31-49aa-936a-a4600d9dd083");
+
+newtype_enum! {
+/// Type of a [`Section`], which determines its contents.
+pub enum SectionType: u8 => {
+    /// Encapsulation section containing sections, possibly compressed with
+    /// the UEFI compression algorithm.
+    COMPRESSION           = 0x01,
+    /// Encapsulation section whose format is defined by a GUID.
+    GUID_DEFINED          = 0x02,
+    /// Encapsulation section that may be discarded once the sections it
+    /// contains have been processed.
+    DISPOSABLE            = 0x03,
+    /// PE32+ image.
+    PE32                  = 0x10,
+    /// Position-independent PE32+ image.
+    PIC                   = 0x11,
+    /// Terse Executable image.
+    TE                    = 0x12,
+    /// Dependency expression of a DXE driver.
+    DXE_DEPEX             = 0x13,
+    /// Build number and version string.
+    VERSION               = 0x14,
+    /// Null-terminated UCS-2 name of the file.
+    USER_INTERFACE        = 0x15,
+    /// 16-bit legacy image.
+    COMPATIBILITY16       = 0x16,
+    /// Nested firmware volume.
+    FIRMWARE_VOLUME_IMAGE = 0x17,
+    /// Data whose format is defined by a GUID.
+    FREEFORM_SUBTYPE_GUID = 0x18,
+    /// Raw data.
+    RAW                   = 0x19,
+    /// Dependency expression of a PEI module.
+    PEI_DEPEX             = 0x1b,
+    /// Dependency expression of a management mode driver.
+    MM_DEPEX              = 0x1c,
+}}
+
+/// A section of a [`File`].
+///
+/// The corresponding C type is `EFI_COMMON_SECTION_HEADER` or
+/// `EFI_COMMON_SECTION_HEADER2`, followed by the section data.
+///
+/// [`File`]: super::File
+#[derive(Clone, Copy, Debug)]
+pub struct Section<'a> {
+    bytes: &'a [u8],
+    header_len: usize,
+}
+
+impl<'a> Section<'a> {
+    /// Get the raw bytes of the section, including its header.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get the type of the section.
+    #[must_use]
+    pub const fn section_type(&self) -> SectionType {
+        SectionType(self.bytes[3])
+    }
+
+    /// Get the data of the section, after its common header. For some
+    /// section types, this data starts with a type-specific header.
+    #[must_use]
+    pub fn data(&self) -> &'a [u8] {
+        &self.bytes[self.header_len..]
+    }
+
+    /// Get the GUID of a [`GUID_DEFINED`] or [`FREEFORM_SUBTYPE_GUID`]
+    /// section, which defines the format of its data.
+    ///
+    /// [`GUID_DEFINED`]: SectionType::GUID_DEFINED
+    /// [`FREEFORM_SUBTYPE_GUID`]: SectionType::FREEFORM_SUBTYPE_GUID
+    #[must_use]
+    pub fn guid(&self) -> Option<Guid> {
+        match self.section_type() {
+            SectionType::GUID_DEFINED | SectionType::FREEFORM_SUBTYPE_GUID => {
+                read_guid(self.data(), 0).ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Check whether the section contains other sections.
+    #[must_use]
+    pub fn is_encapsulation(&self) -> bool {
+        matches!(
+            self.section_type(),
+            SectionType::COMPRESSION | SectionType::GUID_DEFINED | SectionType::DISPOSABLE
+        )
+    }
+
+    /// Get the sections contained in an encapsulation section, decompressing
+    /// them if needed. Iterate over them with [`Sections::new`].
+    ///
+    /// Uncompressed data is borrowed from the section. Compressed data can
+    /// only be extracted with the `decompress` feature.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the section is not an encapsulation
+    ///   section.
+    /// * [`Status::UNSUPPORTED`]: the section is compressed with an
+    ///   unsupported algorithm, or its GUID-defined format is unknown.
+    /// * [`Status::VOLUME_CORRUPTED`]: the section header or the compressed
+    ///   data is corrupt.
+    #[cfg(feature = "alloc")]
+    pub fn encapsulated_data(&self) -> Result<Cow<'a, [u8]>> {
+        let data = self.data();
+        match self.section_type() {
+            SectionType::COMPRESSION => {
+                let len = read_u32(data, 0)? as usize;
+                let compression_type = *data.get(4).ok_or_else(corrupted)?;
+                let compressed = &data[5..];
+                match compression_type {
+                    0 => compressed
+                        .get(..len)
+                        .map(Cow::Borrowed)
+                        .ok_or_else(corrupted),
+                    1 => decompress(compressed, Algorithm::Efi),
+                    _ => Err(Status::UNSUPPORTED.into()),
+                }
+            }
+            SectionType::GUID_DEFINED => {
+                let guid = read_guid(data, 0)?;
+                let data_offset = usize::from(read_u16(data, 16)?);
+                let attributes = read_u16(data, 18)?;
+                let contents = self.bytes.get(data_offset..).ok_or_else(corrupted)?;
+                if guid == TIANO_COMPRESS_GUID {
+                    decompress(contents, Algorithm::Tiano)
+                } else if guid == CRC32_GUID || attributes & PROCESSING_REQUIRED == 0 {
+                    // The CRC32 is part of the section header, so the data
+                    // can be used as is.
+                    Ok(Cow::Borrowed(contents))
+                } else {
+                    Err(Status::UNSUPPORTED.into())
+                }
+            }
+            SectionType::DISPOSABLE => Ok(Cow::Borrowed(data)),
+            _ => Err(Status::INVALID_PARAMETER.into()),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+enum Algorithm {
+    Efi,
+    Tiano,
+}
+
+#[cfg(feature = "alloc")]
+fn decompress(data: &[u8], algorithm: Algorithm) -> Result<Cow<'static, [u8]>> {
+    #[cfg(feature = "decompress")]
+    {
+        let algorithm = match algorithm {
+            Algorithm::Efi => crate::decompress::Algorithm::Efi,
+            Algorithm::Tiano => crate::decompress::Algorithm::Tiano,
+        };
+        crate::decompress::decompress_to_vec(data, algorithm)
+            .map(Cow::Owned)
+            .map_err(|_| corrupted())
+    }
+    #[cfg(not(feature = "decompress"))]
+    {
+        let _ = (data, algorithm);
+        Err(Status::UNSUPPORTED.into())
+    }
+}
+
+/// Find the data of the first section with type `section_type` in
+/// `sections`, searching encapsulation sections recursively.
+#[cfg(feature = "alloc")]
+pub(super) fn find_section_data(
+    sections: Sections<'_>,
+    section_type: SectionType,
+) -> Result<Option<Cow<'_, [u8]>>> {
+    for section in sections {
+        let section = section?;
+        if section.section_type() == section_type {
+            return Ok(Some(Cow::Borrowed(section.data())));
+        }
+        if !section.is_encapsulation() {
+            continue;
+        }
+
+        let found = match section.encapsulated_data() {
+            Ok(Cow::Borrowed(data)) => find_section_data(Sections::new(data), section_type)?,
+            Ok(Cow::Owned(data)) => find_section_data(Sections::new(&data), section_type)?
+                .map(|found| Cow::Owned(found.into_owned())),
+            Err(err) if err.status() == Status::UNSUPPORTED => None,
+            Err(err) => return Err(err),
+        };
+        if found.is_some() {
+            return Ok(found);
+        }
+    }
+    Ok(None)
+}
+
+/// Iterator over a list of sections.
+///
+/// This is returned by [`File::sections`], and can be created with
+/// [`Sections::new`] to iterate over the data returned by
+/// [`Section::encapsulated_data`].
+///
+/// [`File::sections`]: super::File::sections
+#[derive(Clone, Debug)]
+pub struct Sections<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> Sections<'a> {
+    /// Create an iterator over the sections in `bytes`.
+    ///
+    /// The iterator yields an error and stops if a section header is
+    /// corrupt.
+    #[must_use]
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            offset: 0,
+            done: false,
+        }
+    }
+
+    /// Parse the section at the current offset, and advance past it.
+    fn parse(&mut self) -> Result<Option<Section<'a>>> {
+        // Sections are 4-byte aligned, so the last section may be followed
+        // by up to three bytes of padding.
+        if self.offset + HEADER_SIZE > self.bytes.len() {
+            return Ok(None);
+        }
+        let (size, header_len) = match read_u24(self.bytes, self.offset)? {
+            EXTENDED_SIZE => (
+                read_u32(self.bytes, self.offset + HEADER_SIZE)?,
+                HEADER2_SIZE,
+            ),
+            size => (size, HEADER_SIZE),
+        };
+        let size = size as usize;
+        let bytes = self
+            .bytes
+            .get(self.offset..)
+            .and_then(|bytes| bytes.get(..size))
+            .filter(|_| size >= header_len)
+            .ok_or_else(corrupted)?;
+        self.offset = align(self.offset + size, 4);
+        Ok(Some(Section { bytes, header_len }))
+    }
+}
+
+impl<'a> Iterator for Sections<'a> {
+    type Item = Result<Section<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let section = self.parse().transpose();
+        if !matches!(section, Some(Ok(_))) {
+            self.done = true;
+        }
+        section
+    }
+}
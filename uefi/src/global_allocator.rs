@@ -10,6 +10,16 @@
 //!
 //! Call the `exit_boot_services` function before exiting UEFI boot services.
 //! Failure to do so will turn subsequent allocation into undefined behaviour.
+//!
+//! # Statistics
+//!
+//! With the `alloc-stats` feature, the allocator counts the live
+//! allocations and their size, see [`stats`], and records the layout of the
+//! live allocations, see [`log_leaks`]. In debug builds, the leaks are
+//! logged by [`SystemTable::exit_boot_services`], which helps finding the
+//! cause of pool exhaustion.
+//!
+//! [`SystemTable::exit_boot_services`]: crate::table::SystemTable::exit_boot_services
 
 use core::alloc::{GlobalAlloc, Layout};
 use core::ptr::{self, NonNull};
@@ -57,7 +67,7 @@ unsafe impl GlobalAlloc for Allocator {
         let size = layout.size();
         let align = layout.align();
 
-        if align > 8 {
+        let ptr = if align > 8 {
             // allocate more space for alignment
             let ptr = if let Ok(ptr) = boot_services().as_ref().allocate_pool(mem_ty, size + align)
             {
@@ -79,10 +89,17 @@ unsafe impl GlobalAlloc for Allocator {
                 .as_ref()
                 .allocate_pool(mem_ty, size)
                 .unwrap_or(ptr::null_mut())
-        }
+        };
+
+        #[cfg(feature = "alloc-stats")]
+        (*ptr::addr_of_mut!(TRACKER)).record_alloc(ptr, layout);
+        ptr
     }
 
     unsafe fn dealloc(&self, mut ptr: *mut u8, layout: Layout) {
+        #[cfg(feature = "alloc-stats")]
+        (*ptr::addr_of_mut!(TRACKER)).record_dealloc(ptr, layout);
+
         if layout.align() > 8 {
             ptr = (ptr as *const *mut u8).sub(1).read();
         }
@@ -90,5 +107,138 @@ unsafe impl GlobalAlloc for Allocator {
     }
 }
 
+/// Number of live allocations whose layout is recorded for [`log_leaks`].
+#[cfg(feature = "alloc-stats")]
+const TRACKED_ALLOCATIONS: usize = 256;
+
+/// Statistics of the allocations made with [`Allocator`], returned by
+/// [`stats`].
+///
+/// The allocator only makes [`MemoryType::LOADER_DATA`] pool allocations,
+/// so these are the statistics of that memory type.
+#[cfg(feature = "alloc-stats")]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AllocatorStats {
+    /// Number of allocations that are not freed yet.
+    pub live_allocations: usize,
+
+    /// Total size of the allocations that are not freed yet, in bytes.
+    pub live_bytes: usize,
+
+    /// Highest value of `live_bytes` so far.
+    pub peak_bytes: usize,
+
+    /// Number of successful allocations so far.
+    pub total_allocations: usize,
+
+    /// Number of failed allocations so far.
+    pub failed_allocations: usize,
+}
+
+/// Statistics and layouts of the live allocations.
+#[cfg(feature = "alloc-stats")]
+struct Tracker {
+    stats: AllocatorStats,
+    allocations: [Option<(NonNull<u8>, Layout)>; TRACKED_ALLOCATIONS],
+    /// Number of live allocations that didn't fit in `allocations`.
+    untracked: usize,
+}
+
+#[cfg(feature = "alloc-stats")]
+impl Tracker {
+    fn record_alloc(&mut self, ptr: *mut u8, layout: Layout) {
+        let ptr = match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => {
+                self.stats.failed_allocations += 1;
+                return;
+            }
+        };
+        self.stats.live_allocations += 1;
+        self.stats.live_bytes += layout.size();
+        self.stats.peak_bytes = self.stats.peak_bytes.max(self.stats.live_bytes);
+        self.stats.total_allocations += 1;
+        match self.allocations.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some((ptr, layout)),
+            None => self.untracked += 1,
+        }
+    }
+
+    fn record_dealloc(&mut self, ptr: *mut u8, layout: Layout) {
+        self.stats.live_allocations -= 1;
+        self.stats.live_bytes -= layout.size();
+        let slot = self
+            .allocations
+            .iter_mut()
+            .find(|slot| matches!(slot, Some((p, _)) if p.as_ptr() == ptr));
+        match slot {
+            Some(slot) => *slot = None,
+            None => self.untracked = self.untracked.saturating_sub(1),
+        }
+    }
+}
+
+/// Allocation statistics and layouts, updated by [`Allocator`].
+///
+/// UEFI applications are single-threaded, and the allocator can't be used
+/// at a TPL above `NOTIFY`, so there is no concurrent access.
+#[cfg(feature = "alloc-stats")]
+static mut TRACKER: Tracker = Tracker {
+    stats: AllocatorStats {
+        live_allocations: 0,
+        live_bytes: 0,
+        peak_bytes: 0,
+        total_allocations: 0,
+        failed_allocations: 0,
+    },
+    allocations: [None; TRACKED_ALLOCATIONS],
+    untracked: 0,
+};
+
+/// Returns the statistics of the allocations made so far.
+#[cfg(feature = "alloc-stats")]
+#[must_use]
+pub fn stats() -> AllocatorStats {
+    unsafe { (*ptr::addr_of!(TRACKER)).stats }
+}
+
+/// Calls `f` with the address and the layout of each live allocation.
+///
+/// Only the layouts of the first 256 live allocations are recorded, the
+/// number of other live allocations is in [`stats`].
+#[cfg(feature = "alloc-stats")]
+pub fn for_each_live_allocation(mut f: impl FnMut(NonNull<u8>, Layout)) {
+    let tracker = unsafe { &*ptr::addr_of!(TRACKER) };
+    for &(ptr, layout) in tracker.allocations.iter().flatten() {
+        f(ptr, layout);
+    }
+}
+
+/// Logs the allocation statistics, and the size and alignment of each live
+/// allocation, which is a leak if boot services are about to be exited.
+#[cfg(feature = "alloc-stats")]
+pub fn log_leaks() {
+    let stats = stats();
+    log::info!(
+        "{} live allocations ({} bytes), peak usage {} bytes, {} failed allocations",
+        stats.live_allocations,
+        stats.live_bytes,
+        stats.peak_bytes,
+        stats.failed_allocations
+    );
+    for_each_live_allocation(|ptr, layout| {
+        log::warn!(
+            "leaked allocation at {:p}: size {}, align {}",
+            ptr,
+            layout.size(),
+            layout.align()
+        );
+    });
+    let untracked = unsafe { (*ptr::addr_of!(TRACKER)).untracked };
+    if untracked != 0 {
+        log::warn!("{untracked} other allocations are not recorded");
+    }
+}
+
 #[global_allocator]
 static ALLOCATOR: Allocator = Allocator;
@@ -0,0 +1,289 @@
+//! Platform language and language lists.
+//!
+//! The firmware stores the language of its user interface in the
+//! `PlatformLang` variable, and the languages it supports in the
+//! `PlatformLangCodes` variable. Both use [RFC 4646] language tags, such as
+//! `en-US`; the supported languages are a list separated by semicolons, such
+//! as `en;fr;en-US;fr-FR`.
+//!
+//! [`LanguageList::best_match`] selects the best supported language for a
+//! list of requested languages, like the `GetBestLanguage` function of EDK2.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::lang;
+//! use uefi::prelude::*;
+//!
+//! fn menu_language(rt: &RuntimeServices) -> uefi::Result<&'static str> {
+//!     let mut lang_buf = [0; 64];
+//!     let mut codes_buf = [0; 256];
+//!     let platform_lang = lang::platform_lang(rt, &mut lang_buf)?;
+//!     let codes = lang::platform_lang_codes(rt, &mut codes_buf)?;
+//!     // Fall back to English if the platform language is not supported.
+//!     Ok(match codes.best_match([platform_lang, "en"]) {
+//!         Some(lang) if lang.starts_with("fr") => "fr",
+//!         _ => "en",
+//!     })
+//! }
+//! ```
+//!
+//! [RFC 4646]: https://www.rfc-editor.org/rfc/rfc4646
+
+use crate::table::runtime::{RuntimeServices, VariableAttributes, VariableVendor};
+use crate::{cstr16, CStr16, Result, Status};
+
+const PLATFORM_LANG: &CStr16 = cstr16!("PlatformLang");
+const PLATFORM_LANG_CODES: &CStr16 = cstr16!("PlatformLangCodes");
+
+/// Read the language of the firmware user interface from the `PlatformLang`
+/// variable, using `buf` as storage.
+///
+/// # Errors
+///
+/// * [`Status::BUFFER_TOO_SMALL`]: `buf` is too small for the variable.
+/// * [`Status::NOT_FOUND`]: the variable doesn't exist.
+/// * [`Status::VOLUME_CORRUPTED`]: the variable is not an ASCII string.
+///
+/// Other errors from [`RuntimeServices::get_variable`] are passed through.
+pub fn platform_lang<'a>(rt: &RuntimeServices, buf: &'a mut [u8]) -> Result<&'a str> {
+    read_ascii_variable(rt, PLATFORM_LANG, buf)
+}
+
+/// Read the languages supported by the firmware from the
+/// `PlatformLangCodes` variable, using `buf` as storage.
+///
+/// # Errors
+///
+/// See [`platform_lang`].
+pub fn platform_lang_codes<'a>(
+    rt: &RuntimeServices,
+    buf: &'a mut [u8],
+) -> Result<LanguageList<'a>> {
+    read_ascii_variable(rt, PLATFORM_LANG_CODES, buf).map(LanguageList::new)
+}
+
+/// Set the language of the firmware user interface in the `PlatformLang`
+/// variable. The firmware uses it from the next boot.
+///
+/// # Errors
+///
+/// * [`Status::INVALID_PARAMETER`]: `lang` is not an ASCII string, or is
+///   longer than the maximum length of an RFC 4646 tag.
+/// * [`Status::UNSUPPORTED`]: `lang` is not one of the languages of
+///   `PlatformLangCodes`, according to [`LanguageList::contains`].
+///
+/// Other errors from [`RuntimeServices::set_variable`] are passed through.
+pub fn set_platform_lang(rt: &RuntimeServices, lang: &str) -> Result {
+    let mut data = [0; LanguageList::MAX_TAG_LEN + 1];
+    if !lang.is_ascii() || lang.is_empty() || lang.len() > LanguageList::MAX_TAG_LEN {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+
+    // The firmware rejects unsupported languages, but only since UEFI 2.3.1.
+    let mut codes_buf = [0; 512];
+    match platform_lang_codes(rt, &mut codes_buf) {
+        Ok(codes) if !codes.contains(lang) => return Err(Status::UNSUPPORTED.into()),
+        Ok(_) => {}
+        Err(err) if err.status() == Status::NOT_FOUND => {}
+        Err(err) => return Err(err),
+    }
+
+    data[..lang.len()].copy_from_slice(lang.as_bytes());
+    rt.set_variable(
+        PLATFORM_LANG,
+        &VariableVendor::GLOBAL_VARIABLE,
+        VariableAttributes::NON_VOLATILE
+            | VariableAttributes::BOOTSERVICE_ACCESS
+            | VariableAttributes::RUNTIME_ACCESS,
+        &data[..=lang.len()],
+    )
+}
+
+/// Read a null-terminated ASCII global variable.
+fn read_ascii_variable<'a>(
+    rt: &RuntimeServices,
+    name: &CStr16,
+    buf: &'a mut [u8],
+) -> Result<&'a str> {
+    let (data, _) = rt.get_variable(name, &VariableVendor::GLOBAL_VARIABLE, buf)?;
+    let len = data.iter().position(|&c| c == 0).unwrap_or(data.len());
+    let data = &data[..len];
+    if !data.is_ascii() {
+        return Err(Status::VOLUME_CORRUPTED.into());
+    }
+    // OK to unwrap because we already checked the string is ASCII.
+    Ok(core::str::from_utf8(data).unwrap())
+}
+
+/// A list of RFC 4646 language tags separated by semicolons, such as
+/// `en;fr;en-US;fr-FR`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct LanguageList<'a> {
+    list: &'a str,
+}
+
+impl<'a> LanguageList<'a> {
+    /// Maximum length of an RFC 4646 language tag.
+    pub const MAX_TAG_LEN: usize = 42;
+
+    /// Create a list from the semicolon-separated `list`.
+    #[must_use]
+    pub const fn new(list: &'a str) -> Self {
+        Self { list }
+    }
+
+    /// Get the list as a semicolon-separated string.
+    #[must_use]
+    pub const fn as_str(&self) -> &'a str {
+        self.list
+    }
+
+    /// Get an iterator over the languages of the list. Empty entries are
+    /// skipped.
+    #[must_use]
+    pub fn iter(&self) -> LanguageListIter<'a> {
+        LanguageListIter {
+            languages: self.list.split(';'),
+        }
+    }
+
+    /// Whether the list contains `lang`, ignoring case as language tags are
+    /// case-insensitive.
+    #[must_use]
+    pub fn contains(&self, lang: &str) -> bool {
+        self.iter()
+            .any(|supported| supported.eq_ignore_ascii_case(lang))
+    }
+
+    /// Select the best language of this list for the `requested` languages,
+    /// in order of preference.
+    ///
+    /// Each requested language is looked up as in [RFC 4647]: if it is not
+    /// in the list, its last subtag is removed and the lookup is repeated,
+    /// so that `en-US` matches `en`. The first requested language with a
+    /// match wins. Returns `None` if none of them matches.
+    ///
+    /// [RFC 4647]: https://www.rfc-editor.org/rfc/rfc4647#section-3.4
+    #[must_use]
+    pub fn best_match<'b>(&self, requested: impl IntoIterator<Item = &'b str>) -> Option<&'a str> {
+        requested.into_iter().find_map(|mut lang| loop {
+            if lang.is_empty() {
+                return None;
+            }
+            if let Some(supported) = self.iter().find(|s| s.eq_ignore_ascii_case(lang)) {
+                return Some(supported);
+            }
+            lang = truncate_tag(lang);
+        })
+    }
+}
+
+impl<'a> IntoIterator for LanguageList<'a> {
+    type Item = &'a str;
+    type IntoIter = LanguageListIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Remove the last subtag of `lang`, along with a single-character subtag
+/// (such as the `x` of a private use subtag) that would end the result.
+fn truncate_tag(lang: &str) -> &str {
+    let mut lang = lang.rsplit_once('-').map_or("", |(prefix, _)| prefix);
+    if let Some((prefix, last)) = lang.rsplit_once('-') {
+        if last.len() == 1 {
+            lang = prefix;
+        }
+    }
+    lang
+}
+
+/// Iterator returned by [`LanguageList::iter`].
+#[derive(Debug, Clone)]
+pub struct LanguageListIter<'a> {
+    languages: core::str::Split<'a, char>,
+}
+
+impl<'a> Iterator for LanguageListIter<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.languages.find(|lang| !lang.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_language_list() {
+        let list = LanguageList::new("en;fr;;en-US;fr-FR");
+        assert_eq!(
+            list.iter().collect::<Vec<_>>(),
+            ["en", "fr", "en-US", "fr-FR"]
+        );
+        assert!(list.contains("EN-us"));
+        assert!(!list.contains("de"));
+        assert_eq!(LanguageList::new("").iter().next(), None);
+    }
+
+    #[test]
+    fn test_best_match() {
+        let list = LanguageList::new("en;fr;en-US;zh-Hant");
+        assert_eq!(list.best_match(["en-US"]), Some("en-US"));
+        assert_eq!(list.best_match(["fr-CA"]), Some("fr"));
+        assert_eq!(list.best_match(["ZH-hant-TW"]), Some("zh-Hant"));
+        assert_eq!(list.best_match(["de-DE", "en-GB"]), Some("en"));
+        assert_eq!(list.best_match(["de", ""]), None);
+        assert_eq!(list.best_match([]), None);
+        assert_eq!(truncate_tag("en-a-bbb"), "en");
+        assert_eq!(truncate_tag("en"), "");
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_platform_lang() {
+        let firmware = crate::mock::MockFirmware::new();
+        let st = firmware.system_table();
+        let rt = st.runtime_services();
+        let mut buf = [0; 64];
+
+        assert_eq!(
+            platform_lang(rt, &mut buf).unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+        set_platform_lang(rt, "fr-FR").unwrap();
+        assert_eq!(platform_lang(rt, &mut buf).unwrap(), "fr-FR");
+
+        rt.set_variable(
+            PLATFORM_LANG_CODES,
+            &VariableVendor::GLOBAL_VARIABLE,
+            VariableAttributes::BOOTSERVICE_ACCESS,
+            b"en;fr;en-US\0",
+        )
+        .unwrap();
+        let mut codes_buf = [0; 64];
+        let codes = platform_lang_codes(rt, &mut codes_buf).unwrap();
+        assert_eq!(codes.as_str(), "en;fr;en-US");
+        assert_eq!(
+            set_platform_lang(rt, "de").unwrap_err().status(),
+            Status::UNSUPPORTED
+        );
+        assert_eq!(
+            set_platform_lang(rt, "é").unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+        set_platform_lang(rt, "en-US").unwrap();
+        assert_eq!(platform_lang(rt, &mut buf).unwrap(), "en-US");
+
+        let mut small = [0; 2];
+        assert_eq!(
+            platform_lang(rt, &mut small).unwrap_err().status(),
+            Status::BUFFER_TOO_SMALL
+        );
+    }
+}
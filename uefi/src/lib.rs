@@ -15,6 +15,12 @@
 //! The `proto` module contains the standard UEFI protocols, which are normally provided
 //! by the various UEFI drivers and firmware layers.
 //!
+//! ## Diagnostic output
+//!
+//! Helpers that dump system information write it through the `report`
+//! module, which can switch from human-readable text to a line-oriented
+//! `key=value` or JSON format for consumption by automated tooling.
+//!
 //! ## Optional crate features
 //!
 //! - `alloc`: Enable functionality requiring the [`alloc`] crate from
@@ -22,6 +28,22 @@
 //!   `Vec` rather than filling a statically-sized array. This requires
 //!   a global allocator; you can use the `global_allocator` feature or
 //!   provide your own.
+//! - `alloc-stats`: Count the allocations of the global allocator and
+//!   record their layouts, to report leaks before exiting boot services.
+//!   Implies `global_allocator`.
+//! - `arch-intrinsics`: Port I/O, model-specific register and `cpuid`
+//!   helpers for x86 and x86_64 in `arch::x86`, for diagnostics and
+//!   bring-up tools.
+//! - `fallible-alloc`: Allocations made by functions that return a
+//!   [`Result`] fail with [`Status::OUT_OF_RESOURCES`] instead of
+//!   aborting the application when memory runs out. Implies `alloc`.
+//! - `decompress`: Software implementation of the UEFI and Tiano
+//!   decompression algorithms, for extracting compressed firmware volume
+//!   sections without relying on the firmware's decompress protocol.
+//! - `fat`: Software implementation of the FAT12, FAT16 and FAT32 file
+//!   systems over the `BlockIO` or `DiskIo` protocols, for writing to EFI
+//!   system partitions when the firmware's file system driver is read-only
+//!   or missing. Implies `alloc`.
 //! - `global_allocator`: Implement a [global allocator] using UEFI
 //!   functions. This is a simple allocator that relies on the UEFI pool
 //!   allocator. You can choose to provide your own allocator instead of
@@ -30,8 +52,21 @@
 //! - `logger`: Logging implementation for the standard [`log`] crate
 //!   that prints output to the UEFI console. No buffering is done; this
 //!   is not a high-performance logger.
+//! - `mock`: In-memory implementations of the system table, boot and
+//!   runtime services, and common protocols, for unit testing UEFI code
+//!   on the host with `cargo test`. This links against `std`, so it
+//!   should only be enabled as a dev-dependency feature.
 //! - `panic-on-logger-errors` (enabled by default): Panic if a text
 //!   output error occurs in the logger.
+//! - `serde`: Implement `Serialize` and `Deserialize` of the [`serde`]
+//!   crate for data types such as `Guid`, `CString16`, `Time` and
+//!   `MemoryDescriptor`, e.g. to dump memory maps or variables as JSON.
+//! - `std`: Conversions between UCS-2 strings and the `OsStr` and `Path`
+//!   types, for applications built for a UEFI target with `std`. Implies
+//!   `alloc`.
+//! - `trace-status`: Log the name, parameters, and returned status of
+//!   raw protocol calls at the `trace` level. This is useful when
+//!   reporting firmware bugs.
 //! - `unstable`: Enable functionality that depends on [unstable
 //!   features] in the nightly compiler. Note that currently the `uefi`
 //!   crate _always_ requires unstable features even if the `unstable`
@@ -55,6 +90,7 @@
 //! therefore all the network protocols will be unavailable.
 //!
 //! [`GlobalAlloc`]: alloc::alloc::GlobalAlloc
+//! [`serde`]: https://serde.rs
 //! [`uefi-services`]: https://crates.io/crates/uefi-services
 //! [unstable features]: https://doc.rust-lang.org/unstable-book/
 
@@ -72,10 +108,16 @@
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "std")]
+extern crate std;
+
 // allow referring to self as ::uefi for macros to work universally (from this crate and from others)
 // see https://github.com/rust-lang/rust/issues/54647
 extern crate self as uefi;
 
+#[macro_use]
+mod util;
+
 #[macro_use]
 pub mod data_types;
 #[cfg(feature = "alloc")]
@@ -85,14 +127,44 @@ pub use self::data_types::{CStr16, CStr8, Char16, Char8, Event, Guid, Handle};
 pub use uefi_macros::{cstr16, cstr8, entry, guid};
 
 mod result;
-pub use self::result::{Error, Result, ResultExt, Status};
+pub use self::result::{Completion, Error, Result, ResultExt, Status};
 
 pub mod table;
 
+pub mod raw;
+
 pub mod proto;
 
+pub mod arch;
+
+pub mod mmio;
+
+pub mod fv;
+
+pub mod capsule;
+
+pub mod pe;
+
+pub mod lang;
+
+pub mod perf;
+
 pub mod prelude;
 
+pub mod progress;
+
+pub mod report;
+
+pub mod sound;
+
+pub mod time;
+
+#[cfg(feature = "decompress")]
+pub mod decompress;
+
+#[cfg(feature = "fat")]
+pub mod fat;
+
 #[cfg(feature = "global_allocator")]
 pub mod global_allocator;
 
@@ -101,8 +173,21 @@ pub mod logger;
 
 // As long as this is behind "alloc", we can simplify cfg-feature attributes in this module.
 #[cfg(feature = "alloc")]
-pub(crate) mod mem;
+pub mod mem;
 
-pub(crate) mod polyfill;
+#[cfg(feature = "alloc")]
+pub mod executor;
 
-mod util;
+#[cfg(feature = "alloc")]
+pub mod cleanup;
+
+#[cfg(feature = "alloc")]
+pub mod runtime_pointers;
+
+#[cfg(feature = "alloc")]
+pub mod unload;
+
+#[cfg(feature = "mock")]
+pub mod mock;
+
+pub(crate) mod polyfill;
@@ -11,8 +11,15 @@
 //!
 //! The last part also means that some Unicode characters might not be
 //! supported by the UEFI console. Don't expect emoji output support.
+//!
+//! Error messages can be routed to another output, such as the standard
+//! error, with [`Logger::set_error_output`].
+//!
+//! Messages are written while holding the [`OutputLock`], so messages
+//! logged while another one is being written, e.g. by the `trace-status`
+//! logs of the output protocol, are dropped instead of reentering it.
 
-use crate::proto::console::text::Output;
+use crate::proto::console::text::{Output, OutputLock};
 
 use core::fmt::{self, Write};
 use core::ptr::NonNull;
@@ -24,6 +31,7 @@ use core::ptr::NonNull;
 /// undefined behaviour from inadvertent logging.
 pub struct Logger {
     writer: Option<NonNull<Output<'static>>>,
+    error_writer: Option<NonNull<Output<'static>>>,
 }
 
 impl Logger {
@@ -39,12 +47,28 @@ impl Logger {
     pub unsafe fn new(output: &mut Output) -> Self {
         Logger {
             writer: NonNull::new(output as *const _ as *mut _),
+            error_writer: None,
         }
     }
 
+    /// Write the messages of the [`Error`] level to `output`, such as the
+    /// standard error, instead of the output given to [`new`].
+    ///
+    /// [`Error`]: log::Level::Error
+    /// [`new`]: Self::new
+    ///
+    /// # Safety
+    ///
+    /// Same as [`new`]: undefined behaviour may occur if this logger is still
+    /// active after the application has exited the boot services stage.
+    pub unsafe fn set_error_output(&mut self, output: &mut Output) {
+        self.error_writer = NonNull::new(output as *const _ as *mut _);
+    }
+
     /// Disable the logger
     pub fn disable(&mut self) {
         self.writer = None;
+        self.error_writer = None;
     }
 }
 
@@ -54,7 +78,15 @@ impl log::Log for Logger {
     }
 
     fn log(&self, record: &log::Record) {
-        if let Some(mut ptr) = self.writer {
+        let writer = match self.error_writer {
+            Some(error_writer) if record.level() == log::Level::Error => Some(error_writer),
+            _ => self.writer,
+        };
+        if let Some(mut ptr) = writer {
+            let _lock = match OutputLock::try_lock() {
+                Some(lock) => lock,
+                None => return,
+            };
             let writer = unsafe { ptr.as_mut() };
             let result = DecoratedLog::write(
                 writer,
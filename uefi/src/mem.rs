@@ -1,11 +1,30 @@
 //! This is a utility module with helper methods for allocations/memory.
+//!
+//! Many UEFI functions follow the same pattern: call the function with
+//! an empty buffer to learn the required size, allocate a buffer of
+//! that size, and call the function again. The helpers in this module
+//! implement that pattern so that it does not have to be repeated for
+//! every such function.
+//!
+//! # Feature `fallible-alloc`
+//!
+//! By default, allocations made by this crate abort the application if
+//! there is not enough memory, like the collections of the [`alloc`]
+//! crate. With the `fallible-alloc` feature, functions that return a
+//! [`Result`] fail with [`Status::OUT_OF_RESOURCES`] instead, which lets
+//! a bootloader recover, e.g. by freeing memory or trying a smaller
+//! buffer. Functions that can't return an error still abort.
+//!
+//! [`alloc`]: ::alloc
 
 use crate::ResultExt;
 use crate::{Result, Status};
 use ::alloc::boxed::Box;
+use ::alloc::vec::Vec;
 use core::alloc::Layout;
 use core::fmt::Debug;
-use core::slice;
+use core::mem::MaybeUninit;
+use core::{cmp, slice};
 use uefi::data_types::Align;
 use uefi::Error;
 
@@ -29,7 +48,7 @@ use {core::alloc::Allocator, core::ptr::NonNull};
 /// `unstable`-feature, it uses the `allocator_api` instead. In that case, the function takes an
 /// additional parameter describing the specific [`Allocator`]. You can use [`alloc::alloc::Global`]
 /// as default.
-pub(crate) fn make_boxed<
+pub fn make_boxed<
     'a,
     // The UEFI data structure.
     Data: Align + ?Sized + Debug + 'a,
@@ -104,6 +123,135 @@ pub(crate) fn make_boxed<
     Ok(data)
 }
 
+/// Helper to read a variable-length list of `T` into a [`Vec`].
+///
+/// `fetch_data_fn` is called with the spare capacity of the vector. It
+/// must either return the number of elements it initialized, or fail
+/// with [`Status::BUFFER_TOO_SMALL`] and the required number of
+/// elements in the error payload. In the latter case the vector is
+/// grown and the function is called again. Since the required size can
+/// change between calls (for example, the memory map grows when memory
+/// is allocated for the buffer), this repeats until the call succeeds
+/// or fails with a different error.
+///
+/// `initial_len` is the number of elements to allocate for the first
+/// call. Passing zero makes the first call a pure size query.
+///
+/// # Errors
+///
+/// Any error other than [`Status::BUFFER_TOO_SMALL`] is returned as-is,
+/// as is [`Status::BUFFER_TOO_SMALL`] without a required size. If
+/// `fetch_data_fn` claims to have initialized more elements than fit in
+/// the buffer, [`Status::DEVICE_ERROR`] is returned.
+pub fn call_with_growing_buffer<T, F>(initial_len: usize, mut fetch_data_fn: F) -> Result<Vec<T>>
+where
+    F: FnMut(&mut [MaybeUninit<T>]) -> Result<usize, Option<usize>>,
+{
+    let mut data: Vec<T> = try_with_capacity(initial_len)?;
+    loop {
+        let capacity = data.capacity();
+        match fetch_data_fn(data.spare_capacity_mut()) {
+            Ok(len) if len > capacity => return Err(Status::DEVICE_ERROR.into()),
+            Ok(len) => {
+                // Safety: `fetch_data_fn` initialized `len` elements.
+                unsafe { data.set_len(len) };
+                return Ok(data);
+            }
+            Err(err) => match (err.status(), *err.data()) {
+                (Status::BUFFER_TOO_SMALL, Some(required_len)) => {
+                    // Always grow the buffer so that a misbehaving
+                    // function cannot cause an infinite loop.
+                    let new_len = cmp::max(required_len, capacity + 1);
+                    try_reserve_exact(&mut data, new_len)?;
+                }
+                _ => return Err(err.into_err_without_payload()),
+            },
+        }
+    }
+}
+
+/// Converts the error of a fallible allocation to
+/// [`Status::OUT_OF_RESOURCES`].
+#[cfg(feature = "fallible-alloc")]
+fn out_of_resources<E>(_: E) -> Error {
+    Status::OUT_OF_RESOURCES.into()
+}
+
+/// Reserves capacity for at least `additional` more elements in `vec`.
+///
+/// See the [module documentation](self) for how allocation failures are
+/// handled.
+pub(crate) fn try_reserve<T>(vec: &mut Vec<T>, additional: usize) -> Result {
+    #[cfg(feature = "fallible-alloc")]
+    return vec.try_reserve(additional).map_err(out_of_resources);
+
+    #[cfg(not(feature = "fallible-alloc"))]
+    {
+        vec.reserve(additional);
+        Ok(())
+    }
+}
+
+/// Reserves capacity for exactly `additional` more elements in `vec`.
+///
+/// See the [module documentation](self) for how allocation failures are
+/// handled.
+pub(crate) fn try_reserve_exact<T>(vec: &mut Vec<T>, additional: usize) -> Result {
+    #[cfg(feature = "fallible-alloc")]
+    return vec.try_reserve_exact(additional).map_err(out_of_resources);
+
+    #[cfg(not(feature = "fallible-alloc"))]
+    {
+        vec.reserve_exact(additional);
+        Ok(())
+    }
+}
+
+/// Creates an empty vector with space for exactly `capacity` elements.
+pub(crate) fn try_with_capacity<T>(capacity: usize) -> Result<Vec<T>> {
+    let mut vec = Vec::new();
+    try_reserve_exact(&mut vec, capacity)?;
+    Ok(vec)
+}
+
+/// Creates a vector of `len` copies of `value`, like `vec![value; len]`.
+pub(crate) fn try_vec<T: Clone>(value: T, len: usize) -> Result<Vec<T>> {
+    let mut vec = try_with_capacity(len)?;
+    vec.resize(len, value);
+    Ok(vec)
+}
+
+/// Copies `slice` into a new vector, like `slice.to_vec()`.
+pub(crate) fn try_to_vec<T: Clone>(slice: &[T]) -> Result<Vec<T>> {
+    let mut vec = try_with_capacity(slice.len())?;
+    vec.extend_from_slice(slice);
+    Ok(vec)
+}
+
+/// Moves `value` to the heap, like `Box::new(value)`.
+pub(crate) fn try_box<T>(value: T) -> Result<Box<T>> {
+    #[cfg(feature = "fallible-alloc")]
+    {
+        let layout = Layout::new::<T>();
+        if layout.size() == 0 {
+            return Ok(Box::new(value));
+        }
+        let ptr = unsafe { ::alloc::alloc::alloc(layout) }.cast::<T>();
+        if ptr.is_null() {
+            return Err(Status::OUT_OF_RESOURCES.into());
+        }
+        // Safety: the memory was allocated with the layout of `T`, as
+        // `Box` requires.
+        unsafe {
+            ptr.write(value);
+            Ok(Box::from_raw(ptr))
+        }
+    }
+
+    #[cfg(not(feature = "fallible-alloc"))]
+    Ok(Box::new(value))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -226,4 +374,64 @@ mod tests {
 
         assert_eq!(&data.0 .0, &[1, 2, 3, 4]);
     }
+
+    /// Function that behaves like a UEFI function returning a list
+    /// whose length grows between the first and second call.
+    fn uefi_function_stub_list(
+        calls: &mut usize,
+        buf: &mut [MaybeUninit<u32>],
+    ) -> Result<usize, Option<usize>> {
+        *calls += 1;
+        let required_len = if *calls == 1 { 3 } else { 4 };
+        if buf.len() < required_len {
+            return Status::BUFFER_TOO_SMALL.into_with(|| panic!(), |_| Some(required_len));
+        }
+        for (i, elem) in buf[..required_len].iter_mut().enumerate() {
+            elem.write(i as u32);
+        }
+        Ok(required_len)
+    }
+
+    #[test]
+    fn test_call_with_growing_buffer() {
+        let mut calls = 0;
+        let data =
+            call_with_growing_buffer(0, |buf| uefi_function_stub_list(&mut calls, buf)).unwrap();
+        // The size query asks for 3 elements, but by the time of the
+        // second call 4 are needed, so a third call is made.
+        assert_eq!(calls, 3);
+        assert_eq!(data, [0, 1, 2, 3]);
+
+        let err = call_with_growing_buffer::<u32, _>(0, |_| {
+            Status::DEVICE_ERROR.into_with(|| panic!(), |_| None)
+        })
+        .unwrap_err();
+        assert_eq!(err.status(), Status::DEVICE_ERROR);
+
+        // A function claiming to have written more than fits is an error.
+        let err = call_with_growing_buffer::<u32, _>(2, |buf| Ok(buf.len() + 1)).unwrap_err();
+        assert_eq!(err.status(), Status::DEVICE_ERROR);
+    }
+
+    #[test]
+    fn test_fallible_helpers() {
+        assert_eq!(try_vec(7u8, 3).unwrap(), [7, 7, 7]);
+        assert_eq!(try_to_vec(&[1u16, 2]).unwrap(), [1, 2]);
+        assert_eq!(*try_box(5u64).unwrap(), 5);
+        assert_eq!(*try_box(()).unwrap(), ());
+
+        let mut vec = try_with_capacity::<u32>(4).unwrap();
+        assert_eq!(vec.capacity(), 4);
+        try_reserve(&mut vec, 8).unwrap();
+        assert!(vec.capacity() >= 8);
+    }
+
+    #[test]
+    #[cfg(feature = "fallible-alloc")]
+    fn test_fallible_alloc_failure() {
+        let err = try_with_capacity::<u64>(usize::MAX).unwrap_err();
+        assert_eq!(err.status(), Status::OUT_OF_RESOURCES);
+        let err = try_vec(0u8, isize::MAX as usize + 1).unwrap_err();
+        assert_eq!(err.status(), Status::OUT_OF_RESOURCES);
+    }
 }
@@ -0,0 +1,352 @@
+//! Volatile access to memory-mapped device registers.
+//!
+//! Device registers, e.g. behind a PCI BAR or at an address found in a
+//! device tree, must be accessed with volatile reads and writes, each of
+//! the register's size, so that the compiler doesn't merge, reorder or
+//! remove them. [`MmioRegion`] wraps the address range of a device, and
+//! checks the bounds and the alignment of each access. [`VolatileCell`] is
+//! a single register, which can be used to describe a block of registers
+//! as a `#[repr(C)]` struct.
+//!
+//! UEFI identity maps memory, so the physical address of a region is also
+//! its virtual address until an operating system changes the mapping.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::mmio::{MmioError, MmioRegion, VolatileCell};
+//!
+//! #[repr(C)]
+//! struct Registers {
+//!     control: VolatileCell<u32>,
+//!     status: VolatileCell<u32>,
+//! }
+//!
+//! fn reset_device(bar: u64) -> Result<(), MmioError> {
+//!     let region = unsafe { MmioRegion::new(bar, 0x1000) }.ok_or(MmioError::OutOfBounds)?;
+//!     let regs = region.get::<Registers>(0)?;
+//!     regs.control.set(1);
+//!     while regs.status.get() & 1 != 0 {}
+//!
+//!     let version: u16 = region.read(0x10)?;
+//!     log::info!("device version {version:#x}");
+//!     Ok(())
+//! }
+//! ```
+
+use crate::data_types::PhysicalAddress;
+use core::cell::UnsafeCell;
+use core::fmt::{self, Debug, Display, Formatter};
+use core::mem;
+use core::ptr::NonNull;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Integer types that can be read and written with a single access.
+///
+/// 64-bit accesses are split in two on 32-bit processors.
+pub trait MmioValue: Copy + sealed::Sealed {}
+
+macro_rules! impl_mmio_value {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+            impl MmioValue for $t {}
+        )*
+    };
+}
+
+impl_mmio_value!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+/// A value that is always read and written with volatile accesses.
+///
+/// References to a `VolatileCell` in a [`MmioRegion`] are returned by
+/// [`MmioRegion::get`].
+#[repr(transparent)]
+pub struct VolatileCell<T> {
+    value: UnsafeCell<T>,
+}
+
+impl<T: Copy> VolatileCell<T> {
+    /// Creates a cell holding `value`.
+    #[must_use]
+    pub const fn new(value: T) -> Self {
+        Self {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Reads the value with a volatile read.
+    #[must_use]
+    pub fn get(&self) -> T {
+        unsafe { self.value.get().read_volatile() }
+    }
+
+    /// Writes `value` with a volatile write.
+    pub fn set(&self, value: T) {
+        unsafe { self.value.get().write_volatile(value) }
+    }
+
+    /// Reads the value, and writes the value returned by `f` for it.
+    pub fn update(&self, f: impl FnOnce(T) -> T) {
+        self.set(f(self.get()));
+    }
+}
+
+impl<T: Copy + Debug> Debug for VolatileCell<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("VolatileCell").field(&self.get()).finish()
+    }
+}
+
+/// Errors of the accesses to a [`MmioRegion`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MmioError {
+    /// The access is not entirely inside the region.
+    OutOfBounds,
+
+    /// The address of the access is not a multiple of the size of the
+    /// accessed type.
+    Misaligned,
+}
+
+impl Display for MmioError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds => f.write_str("MMIO access out of bounds"),
+            Self::Misaligned => f.write_str("misaligned MMIO access"),
+        }
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl core::error::Error for MmioError {}
+
+/// The address range of a memory-mapped device.
+///
+/// All accesses are checked to be inside the region and aligned, and are
+/// done with volatile reads and writes of the accessed type's size.
+pub struct MmioRegion {
+    base: NonNull<u8>,
+    len: usize,
+}
+
+impl MmioRegion {
+    /// Creates a region of `len` bytes at `phys_addr`.
+    ///
+    /// Returns `None` if the address is null, or if the region doesn't fit
+    /// in the address space.
+    ///
+    /// # Safety
+    ///
+    /// The range must be mapped, and must be device memory or memory that
+    /// is not used by anything else, for as long as the region is used.
+    /// Accesses to device registers can have any side effect on the device.
+    #[must_use]
+    pub unsafe fn new(phys_addr: PhysicalAddress, len: usize) -> Option<Self> {
+        let base = usize::try_from(phys_addr).ok()?;
+        base.checked_add(len)?;
+        Some(Self {
+            base: NonNull::new(base as *mut u8)?,
+            len,
+        })
+    }
+
+    /// Returns the address of the region.
+    #[must_use]
+    pub fn address(&self) -> PhysicalAddress {
+        self.base.as_ptr() as PhysicalAddress
+    }
+
+    /// Returns the length of the region in bytes.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the region is empty.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a pointer to the `size` bytes at `offset`, after checking
+    /// that they are inside the region, and that their address is a
+    /// multiple of `align`.
+    fn checked_ptr(&self, offset: usize, size: usize, align: usize) -> Result<*mut u8, MmioError> {
+        let end = offset.checked_add(size).ok_or(MmioError::OutOfBounds)?;
+        if end > self.len {
+            return Err(MmioError::OutOfBounds);
+        }
+        let ptr = unsafe { self.base.as_ptr().add(offset) };
+        if ptr as usize & (align - 1) != 0 {
+            return Err(MmioError::Misaligned);
+        }
+        Ok(ptr)
+    }
+
+    /// Reads the value at `offset` bytes from the start of the region.
+    ///
+    /// # Errors
+    ///
+    /// * [`MmioError::OutOfBounds`]: the value is not inside the region.
+    /// * [`MmioError::Misaligned`]: the address is not aligned for `T`.
+    pub fn read<T: MmioValue>(&self, offset: usize) -> Result<T, MmioError> {
+        let ptr = self.checked_ptr(offset, mem::size_of::<T>(), mem::align_of::<T>())?;
+        Ok(unsafe { ptr.cast::<T>().read_volatile() })
+    }
+
+    /// Writes `value` at `offset` bytes from the start of the region.
+    ///
+    /// # Errors
+    ///
+    /// See [`read`](Self::read).
+    pub fn write<T: MmioValue>(&self, offset: usize, value: T) -> Result<(), MmioError> {
+        let ptr = self.checked_ptr(offset, mem::size_of::<T>(), mem::align_of::<T>())?;
+        unsafe { ptr.cast::<T>().write_volatile(value) };
+        Ok(())
+    }
+
+    /// Reads consecutive values starting at `offset` into `values`, with one
+    /// access per value.
+    ///
+    /// # Errors
+    ///
+    /// See [`read`](Self::read). Nothing is read if the range is invalid.
+    pub fn read_slice<T: MmioValue>(
+        &self,
+        offset: usize,
+        values: &mut [T],
+    ) -> Result<(), MmioError> {
+        let size = mem::size_of_val(values);
+        let ptr = self
+            .checked_ptr(offset, size, mem::align_of::<T>())?
+            .cast::<T>();
+        for (i, value) in values.iter_mut().enumerate() {
+            *value = unsafe { ptr.add(i).read_volatile() };
+        }
+        Ok(())
+    }
+
+    /// Writes `values` consecutively starting at `offset`, with one access
+    /// per value.
+    ///
+    /// # Errors
+    ///
+    /// See [`read`](Self::read). Nothing is written if the range is invalid.
+    pub fn write_slice<T: MmioValue>(&self, offset: usize, values: &[T]) -> Result<(), MmioError> {
+        let size = mem::size_of_val(values);
+        let ptr = self
+            .checked_ptr(offset, size, mem::align_of::<T>())?
+            .cast::<T>();
+        for (i, value) in values.iter().enumerate() {
+            unsafe { ptr.add(i).write_volatile(*value) };
+        }
+        Ok(())
+    }
+
+    /// Returns a reference to the registers of type `T` at `offset`, which
+    /// is typically a `#[repr(C)]` struct of [`VolatileCell`]s, or a single
+    /// `VolatileCell`.
+    ///
+    /// `T` should only contain `VolatileCell`s and padding, so that all the
+    /// accesses through the reference are volatile.
+    ///
+    /// # Errors
+    ///
+    /// See [`read`](Self::read).
+    pub fn get<T>(&self, offset: usize) -> Result<&T, MmioError> {
+        let ptr = self.checked_ptr(offset, mem::size_of::<T>(), mem::align_of::<T>())?;
+        Ok(unsafe { &*ptr.cast::<T>() })
+    }
+
+    /// Returns the part of the region of `len` bytes at `offset`, e.g. the
+    /// registers of one function of a device.
+    ///
+    /// # Errors
+    ///
+    /// * [`MmioError::OutOfBounds`]: the part is not inside the region.
+    pub fn subregion(&self, offset: usize, len: usize) -> Result<Self, MmioError> {
+        let ptr = self.checked_ptr(offset, len, 1)?;
+        Ok(Self {
+            base: unsafe { NonNull::new_unchecked(ptr) },
+            len,
+        })
+    }
+}
+
+impl Debug for MmioRegion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MmioRegion")
+            .field("address", &self.base)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    struct Registers {
+        control: VolatileCell<u32>,
+        status: VolatileCell<u32>,
+    }
+
+    /// Returns a region over `memory`, which stands in for device memory.
+    fn region(memory: &mut [u64]) -> MmioRegion {
+        unsafe {
+            MmioRegion::new(
+                memory.as_mut_ptr() as PhysicalAddress,
+                mem::size_of_val(memory),
+            )
+        }
+        .unwrap()
+    }
+
+    #[test]
+    fn test_read_write() {
+        let mut memory = [0u64; 4];
+        let region = region(&mut memory);
+        assert_eq!(region.len(), 32);
+
+        region.write(0, 0x1122_3344u32).unwrap();
+        region.write(4, 0x55u8).unwrap();
+        assert_eq!(region.read::<u16>(0), Ok(0x3344));
+        assert_eq!(region.read::<u64>(0), Ok(0x55_1122_3344));
+
+        region.write_slice(8, &[1u16, 2, 3, 4]).unwrap();
+        let mut values = [0u32; 2];
+        region.read_slice(8, &mut values).unwrap();
+        assert_eq!(values, [0x2_0001, 0x4_0003]);
+
+        assert_eq!(region.read::<u32>(30), Err(MmioError::OutOfBounds));
+        assert_eq!(region.read::<u32>(usize::MAX), Err(MmioError::OutOfBounds));
+        assert_eq!(region.read::<u32>(2), Err(MmioError::Misaligned));
+        assert_eq!(
+            region.write_slice(24, &[0u32; 3]),
+            Err(MmioError::OutOfBounds)
+        );
+        assert_eq!(memory[3], 0);
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut memory = [0u64; 4];
+        let region = region(&mut memory);
+        let sub = region.subregion(16, 16).unwrap();
+        let regs = sub.get::<Registers>(8).unwrap();
+        regs.control.set(7);
+        regs.status.update(|status| status | 0x100);
+        assert_eq!(region.read::<u32>(24), Ok(7));
+        assert_eq!(region.read::<u32>(28), Ok(0x100));
+
+        assert!(sub.get::<Registers>(12).is_err());
+        assert!(region.subregion(16, 17).is_err());
+        assert!(unsafe { MmioRegion::new(0, 16) }.is_none());
+        assert!(unsafe { MmioRegion::new(usize::MAX as PhysicalAddress, 16) }.is_none());
+    }
+}
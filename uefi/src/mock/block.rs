@@ -0,0 +1,196 @@
+//! Mock in-memory block device.
+
+use crate::proto::media::block::{BlockIO, BlockIOMedia, Lba};
+use crate::Status;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::mem::MaybeUninit;
+use core::{ptr, slice};
+
+/// In-memory block device.
+///
+/// The contents are shared between clones, so blocks written through the
+/// [`BlockIO`] protocol of a [`MockFirmware`] can be inspected with the
+/// clone that was passed to [`MockFirmware::install_block_device`].
+///
+/// [`MockFirmware`]: super::MockFirmware
+/// [`MockFirmware::install_block_device`]: super::MockFirmware::install_block_device
+#[derive(Clone)]
+pub struct MockBlockDevice {
+    data: Rc<RefCell<Vec<u8>>>,
+    block_size: u32,
+    read_only: bool,
+}
+
+impl MockBlockDevice {
+    /// Creates a zeroed device of `blocks` blocks of `block_size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` or `blocks` is zero.
+    #[must_use]
+    pub fn new(block_size: u32, blocks: u64) -> Self {
+        assert!(block_size != 0 && blocks != 0, "empty block device");
+        Self {
+            data: Rc::new(RefCell::new(vec![0; block_size as usize * blocks as usize])),
+            block_size,
+            read_only: false,
+        }
+    }
+
+    /// Makes the device read-only, so that writes through the protocol fail
+    /// with `WRITE_PROTECTED`.
+    #[must_use]
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Returns the size of a block, in bytes.
+    #[must_use]
+    pub const fn block_size(&self) -> u32 {
+        self.block_size
+    }
+
+    /// Returns a copy of the contents of the device.
+    #[must_use]
+    pub fn contents(&self) -> Vec<u8> {
+        self.data.borrow().clone()
+    }
+
+    /// Overwrites the contents of the device at byte `offset` with `data`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` does not fit in the device.
+    pub fn write(&self, offset: usize, data: &[u8]) {
+        self.data.borrow_mut()[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    /// Returns the byte range of `size` bytes at `lba`, or an error status
+    /// if the access is invalid.
+    fn range(&self, lba: Lba, size: usize) -> Result<(usize, usize), Status> {
+        let block_size = self.block_size as usize;
+        if size.checked_rem(block_size) != Some(0) {
+            return Err(Status::BAD_BUFFER_SIZE);
+        }
+        let start = usize::try_from(lba)
+            .ok()
+            .and_then(|lba| lba.checked_mul(block_size))
+            .ok_or(Status::INVALID_PARAMETER)?;
+        match start.checked_add(size) {
+            Some(end) if end <= self.data.borrow().len() => Ok((start, end)),
+            _ => Err(Status::INVALID_PARAMETER),
+        }
+    }
+}
+
+/// A `BlockIO` protocol instance backed by a [`MockBlockDevice`].
+#[repr(C)]
+pub(super) struct BlockDeviceInstance {
+    // Must be the first field so that the protocol's `this` pointer can be
+    // converted back to a `BlockDeviceInstance`.
+    raw: BlockIO,
+    media: BlockIOMedia,
+    device: MockBlockDevice,
+}
+
+impl BlockDeviceInstance {
+    pub(super) fn new(device: MockBlockDevice) -> Box<Self> {
+        let last_block = (device.data.borrow().len() / device.block_size as usize - 1) as Lba;
+        let media = BlockIOMedia {
+            media_id: 1,
+            removable_media: false,
+            media_present: true,
+            logical_partition: false,
+            read_only: device.read_only,
+            write_caching: false,
+            block_size: device.block_size,
+            io_align: 1,
+            last_block,
+            lowest_aligned_lba: 0,
+            logical_blocks_per_physical_block: 1,
+            optimal_transfer_length_granularity: 0,
+        };
+
+        // The protocol struct has a private marker field, so it can't be
+        // built with a struct literal. The marker is zero-sized and needs no
+        // initialization.
+        let instance = Box::into_raw(Box::new(MaybeUninit::<Self>::uninit())).cast::<Self>();
+        unsafe {
+            ptr::addr_of_mut!((*instance).media).write(media);
+            ptr::addr_of_mut!((*instance).device).write(device);
+            ptr::addr_of_mut!((*instance).raw.revision).write(0x0002_0031);
+            ptr::addr_of_mut!((*instance).raw.media).write(ptr::addr_of!((*instance).media));
+            ptr::addr_of_mut!((*instance).raw.reset).write(reset);
+            ptr::addr_of_mut!((*instance).raw.read_blocks).write(read_blocks);
+            ptr::addr_of_mut!((*instance).raw.write_blocks).write(write_blocks);
+            ptr::addr_of_mut!((*instance).raw.flush_blocks).write(flush_blocks);
+            Box::from_raw(instance)
+        }
+    }
+
+    pub(super) fn as_raw(&mut self) -> *mut BlockIO {
+        &mut self.raw
+    }
+}
+
+fn instance_from(this: &BlockIO) -> &BlockDeviceInstance {
+    unsafe { &*(this as *const BlockIO).cast::<BlockDeviceInstance>() }
+}
+
+extern "efiapi" fn reset(_this: &BlockIO, _extended_verification: bool) -> Status {
+    Status::SUCCESS
+}
+
+extern "efiapi" fn read_blocks(
+    this: &BlockIO,
+    media_id: u32,
+    lba: Lba,
+    buffer_size: usize,
+    buffer: *mut u8,
+) -> Status {
+    let instance = instance_from(this);
+    if media_id != instance.media.media_id {
+        return Status::MEDIA_CHANGED;
+    }
+    match instance.device.range(lba, buffer_size) {
+        Ok((start, end)) => {
+            let buffer = unsafe { slice::from_raw_parts_mut(buffer, buffer_size) };
+            buffer.copy_from_slice(&instance.device.data.borrow()[start..end]);
+            Status::SUCCESS
+        }
+        Err(status) => status,
+    }
+}
+
+extern "efiapi" fn write_blocks(
+    this: &BlockIO,
+    media_id: u32,
+    lba: Lba,
+    buffer_size: usize,
+    buffer: *const u8,
+) -> Status {
+    let instance = instance_from(this);
+    if media_id != instance.media.media_id {
+        return Status::MEDIA_CHANGED;
+    }
+    if instance.device.read_only {
+        return Status::WRITE_PROTECTED;
+    }
+    match instance.device.range(lba, buffer_size) {
+        Ok((start, end)) => {
+            let buffer = unsafe { slice::from_raw_parts(buffer, buffer_size) };
+            instance.device.data.borrow_mut()[start..end].copy_from_slice(buffer);
+            Status::SUCCESS
+        }
+        Err(status) => status,
+    }
+}
+
+extern "efiapi" fn flush_blocks(_this: &BlockIO) -> Status {
+    Status::SUCCESS
+}
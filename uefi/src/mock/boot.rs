@@ -0,0 +1,926 @@
+//! Mock boot services backed by an in-memory handle database.
+
+use crate::data_types::PhysicalAddress;
+use crate::raw::protocol::device_path::DevicePathProtocol;
+use crate::raw::table::boot as raw;
+use crate::table::boot::{
+    BootServices, EventNotifyFn, EventType, InterfaceType, MemoryAttribute, MemoryDescriptor,
+    MemoryType, OpenProtocolInformationEntry, Tpl, MEMORY_DESCRIPTOR_VERSION,
+};
+use crate::table::{Header, Revision, Table};
+use crate::{Char16, Event, Guid, Handle, Status};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::ffi::c_void;
+use core::mem::{self, MaybeUninit};
+use core::ptr::{self, NonNull};
+use std::alloc::{alloc, dealloc, Layout};
+
+const PAGE_SIZE: usize = 4096;
+const POOL_HEADER_SIZE: usize = 8;
+
+const SEARCH_ALL_HANDLES: i32 = 0;
+const SEARCH_BY_PROTOCOL: i32 = 2;
+
+const OPEN_TEST_PROTOCOL: u32 = 0x04;
+const OPEN_EXCLUSIVE: u32 = 0x20;
+
+/// A protocol interface installed on a handle.
+struct Interface {
+    /// Boxed so that pointers returned by `protocols_per_handle` stay valid
+    /// while other interfaces are installed.
+    guid: Box<Guid>,
+    interface: *mut c_void,
+    exclusive: bool,
+    /// Agents that opened the interface.
+    opens: Vec<OpenProtocolInformationEntry>,
+}
+
+struct HandleEntry {
+    handle: Handle,
+    _storage: Box<u64>,
+    interfaces: Vec<Interface>,
+}
+
+struct EventEntry {
+    ptr: *mut c_void,
+    _storage: Box<u64>,
+    ty: EventType,
+    notify_fn: Option<EventNotifyFn>,
+    notify_ctx: Option<NonNull<c_void>>,
+    signaled: bool,
+    /// Timer type set by `set_timer`, or `None` if the timer is not set.
+    timer: Option<u32>,
+    /// Event group set by `create_event_ex`.
+    group: Option<Guid>,
+}
+
+#[derive(Default)]
+struct Database {
+    handles: Vec<HandleEntry>,
+    events: Vec<EventEntry>,
+}
+
+impl Database {
+    fn handle(&mut self, handle: Handle) -> Option<&mut HandleEntry> {
+        self.handles
+            .iter_mut()
+            .find(|entry| entry.handle.as_ptr() == handle.as_ptr())
+    }
+
+    fn interface(&mut self, handle: Handle, guid: &Guid) -> Result<&mut Interface, Status> {
+        let entry = self.handle(handle).ok_or(Status::INVALID_PARAMETER)?;
+        entry
+            .interfaces
+            .iter_mut()
+            .find(|iface| *iface.guid == *guid)
+            .ok_or(Status::UNSUPPORTED)
+    }
+
+    fn event(&mut self, event: &Event) -> Option<&mut EventEntry> {
+        self.events
+            .iter_mut()
+            .find(|entry| entry.ptr == event.as_ptr())
+    }
+
+    fn matching_handles(
+        &self,
+        search_ty: i32,
+        proto: Option<&Guid>,
+    ) -> Result<Vec<Handle>, Status> {
+        let handles: Vec<Handle> = match (search_ty, proto) {
+            (SEARCH_ALL_HANDLES, _) => self.handles.iter().map(|entry| entry.handle).collect(),
+            (SEARCH_BY_PROTOCOL, Some(guid)) => self
+                .handles
+                .iter()
+                .filter(|entry| entry.interfaces.iter().any(|iface| *iface.guid == *guid))
+                .map(|entry| entry.handle)
+                .collect(),
+            (SEARCH_BY_PROTOCOL, None) => return Err(Status::INVALID_PARAMETER),
+            _ => return Err(Status::UNSUPPORTED),
+        };
+        if handles.is_empty() {
+            Err(Status::NOT_FOUND)
+        } else {
+            Ok(handles)
+        }
+    }
+}
+
+std::thread_local! {
+    static DATABASE: RefCell<Database> = RefCell::new(Database::default());
+    static TPL: Cell<Tpl> = const { Cell::new(Tpl::APPLICATION) };
+    static WATCHDOG: Cell<(usize, u64)> = const { Cell::new((0, 0)) };
+}
+
+fn with_database<R>(f: impl FnOnce(&mut Database) -> R) -> R {
+    DATABASE.with(|db| f(&mut db.borrow_mut()))
+}
+
+/// Removes all handles and events of the current thread.
+pub(super) fn reset() {
+    let old = DATABASE.with(|db| db.replace(Database::default()));
+    drop(old);
+    TPL.with(|tpl| tpl.set(Tpl::APPLICATION));
+    WATCHDOG.with(|watchdog| watchdog.set((0, 0)));
+}
+
+/// Returns the timeout and code the watchdog was last set to.
+#[cfg(test)]
+pub(super) fn watchdog() -> (usize, u64) {
+    WATCHDOG.with(Cell::get)
+}
+
+/// Returns the number of open events.
+#[cfg(test)]
+pub(super) fn event_count() -> usize {
+    with_database(|db| db.events.len())
+}
+
+/// Creates a new handle with no protocols installed on it.
+pub(super) fn create_handle() -> Handle {
+    let storage = Box::new(0u64);
+    let handle = unsafe { Handle::from_ptr(ptr::addr_of!(*storage) as *mut c_void) }.unwrap();
+    with_database(|db| {
+        db.handles.push(HandleEntry {
+            handle,
+            _storage: storage,
+            interfaces: Vec::new(),
+        })
+    });
+    handle
+}
+
+/// Installs a protocol interface, creating a new handle if `handle` is
+/// `None`.
+pub(super) fn install(
+    handle: Option<Handle>,
+    guid: &Guid,
+    interface: *mut c_void,
+) -> Result<Handle, Status> {
+    let handle = handle.unwrap_or_else(create_handle);
+    with_database(|db| {
+        let entry = db.handle(handle).ok_or(Status::INVALID_PARAMETER)?;
+        if entry.interfaces.iter().any(|iface| *iface.guid == *guid) {
+            return Err(Status::INVALID_PARAMETER);
+        }
+        entry.interfaces.push(Interface {
+            guid: Box::new(*guid),
+            interface,
+            exclusive: false,
+            opens: Vec::new(),
+        });
+        Ok(handle)
+    })
+}
+
+/// Creates an event that is not connected to a notification function.
+pub(super) fn create_plain_event() -> Event {
+    let mut event = MaybeUninit::uninit();
+    let status = unsafe {
+        create_event(
+            EventType::empty(),
+            Tpl::APPLICATION,
+            None,
+            None,
+            event.as_mut_ptr(),
+        )
+    };
+    assert_eq!(status, Status::SUCCESS);
+    unsafe { event.assume_init() }
+}
+
+/// Sets the signaled state of an event without calling its notification
+/// function.
+pub(super) fn set_signaled(event: &Event, signaled: bool) {
+    with_database(|db| {
+        if let Some(entry) = db.event(event) {
+            entry.signaled = signaled;
+        }
+    });
+}
+
+fn pool_alloc(size: usize) -> *mut u8 {
+    let layout = Layout::from_size_align(size + POOL_HEADER_SIZE, POOL_HEADER_SIZE).unwrap();
+    unsafe {
+        let base = alloc(layout);
+        if base.is_null() {
+            return base;
+        }
+        base.cast::<usize>().write(layout.size());
+        base.add(POOL_HEADER_SIZE)
+    }
+}
+
+unsafe fn pool_free(buffer: *mut u8) {
+    let base = buffer.sub(POOL_HEADER_SIZE);
+    let size = base.cast::<usize>().read();
+    dealloc(
+        base,
+        Layout::from_size_align_unchecked(size, POOL_HEADER_SIZE),
+    );
+}
+
+/// Copies `items` into a new pool allocation.
+fn pool_copy<T: Copy>(items: &[T]) -> *mut T {
+    let buffer = pool_alloc(mem::size_of_val(items)).cast::<T>();
+    if !buffer.is_null() {
+        unsafe { ptr::copy_nonoverlapping(items.as_ptr(), buffer, items.len()) };
+    }
+    buffer
+}
+
+/// Builds a boot services table whose functions operate on the handle
+/// database of the current thread.
+pub(super) fn table() -> raw::BootServices {
+    raw::BootServices {
+        header: Header {
+            signature: BootServices::SIGNATURE,
+            revision: Revision::EFI_2_70,
+            size: mem::size_of::<raw::BootServices>() as u32,
+            crc: 0,
+            reserved: 0,
+        },
+        raise_tpl,
+        restore_tpl,
+        allocate_pages,
+        free_pages,
+        get_memory_map,
+        allocate_pool,
+        free_pool,
+        create_event,
+        set_timer,
+        wait_for_event,
+        signal_event,
+        close_event,
+        check_event,
+        install_protocol_interface,
+        reinstall_protocol_interface,
+        uninstall_protocol_interface,
+        handle_protocol,
+        reserved: 0,
+        register_protocol_notify,
+        locate_handle,
+        locate_device_path,
+        install_configuration_table: 0,
+        load_image,
+        start_image,
+        exit,
+        unload_image,
+        exit_boot_services,
+        get_next_monotonic_count: 0,
+        stall,
+        set_watchdog_timer,
+        connect_controller,
+        disconnect_controller,
+        open_protocol,
+        close_protocol,
+        open_protocol_information,
+        protocols_per_handle,
+        locate_handle_buffer,
+        locate_protocol,
+        install_multiple_protocol_interfaces: 0,
+        uninstall_multiple_protocol_interfaces: 0,
+        calculate_crc32: 0,
+        copy_mem,
+        set_mem,
+        create_event_ex,
+    }
+}
+
+unsafe extern "efiapi" fn raise_tpl(new_tpl: Tpl) -> Tpl {
+    TPL.with(|tpl| tpl.replace(new_tpl))
+}
+
+unsafe extern "efiapi" fn restore_tpl(old_tpl: Tpl) {
+    TPL.with(|tpl| tpl.set(old_tpl));
+}
+
+unsafe extern "efiapi" fn allocate_pages(
+    alloc_ty: u32,
+    _mem_ty: MemoryType,
+    count: usize,
+    addr: *mut PhysicalAddress,
+) -> Status {
+    // Only `AllocateType::AnyPages` is supported.
+    if alloc_ty != 0 {
+        return Status::UNSUPPORTED;
+    }
+    if count == 0 {
+        return Status::INVALID_PARAMETER;
+    }
+    let layout = match Layout::from_size_align(count * PAGE_SIZE, PAGE_SIZE) {
+        Ok(layout) => layout,
+        Err(_) => return Status::OUT_OF_RESOURCES,
+    };
+    let pages = alloc(layout);
+    if pages.is_null() {
+        return Status::OUT_OF_RESOURCES;
+    }
+    *addr = pages as PhysicalAddress;
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn free_pages(addr: PhysicalAddress, pages: usize) -> Status {
+    if addr == 0 || pages == 0 {
+        return Status::INVALID_PARAMETER;
+    }
+    dealloc(
+        addr as usize as *mut u8,
+        Layout::from_size_align_unchecked(pages * PAGE_SIZE, PAGE_SIZE),
+    );
+    Status::SUCCESS
+}
+
+/// Memory map reported by `get_memory_map`.
+const MEMORY_MAP: [MemoryDescriptor; 2] = [
+    MemoryDescriptor {
+        ty: MemoryType::BOOT_SERVICES_CODE,
+        phys_start: 0x10_0000,
+        virt_start: 0,
+        page_count: 16,
+        att: MemoryAttribute::WRITE_BACK,
+    },
+    MemoryDescriptor {
+        ty: MemoryType::CONVENTIONAL,
+        phys_start: 0x20_0000,
+        virt_start: 0,
+        page_count: 256,
+        att: MemoryAttribute::WRITE_BACK,
+    },
+];
+
+/// Descriptor size reported by `get_memory_map`. Larger than
+/// `MemoryDescriptor`, like on real firmware.
+const MEMORY_DESCRIPTOR_SIZE: usize = mem::size_of::<MemoryDescriptor>() + 8;
+
+unsafe extern "efiapi" fn get_memory_map(
+    size: *mut usize,
+    map: *mut MemoryDescriptor,
+    key: *mut usize,
+    desc_size: *mut usize,
+    desc_version: *mut u32,
+) -> Status {
+    if size.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    let required = MEMORY_MAP.len() * MEMORY_DESCRIPTOR_SIZE;
+    let available = *size;
+    *size = required;
+    *desc_size = MEMORY_DESCRIPTOR_SIZE;
+    *desc_version = MEMORY_DESCRIPTOR_VERSION;
+    if available < required {
+        return Status::BUFFER_TOO_SMALL;
+    }
+    if map.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    for (i, desc) in MEMORY_MAP.iter().enumerate() {
+        map.cast::<u8>()
+            .add(i * MEMORY_DESCRIPTOR_SIZE)
+            .cast::<MemoryDescriptor>()
+            .write_unaligned(*desc);
+    }
+    *key = 1;
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn allocate_pool(
+    _pool_type: MemoryType,
+    size: usize,
+    buffer: *mut *mut u8,
+) -> Status {
+    *buffer = pool_alloc(size);
+    if (*buffer).is_null() {
+        Status::OUT_OF_RESOURCES
+    } else {
+        Status::SUCCESS
+    }
+}
+
+unsafe extern "efiapi" fn free_pool(buffer: *mut u8) -> Status {
+    if buffer.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    pool_free(buffer);
+    Status::SUCCESS
+}
+
+#[allow(improper_ctypes_definitions)]
+unsafe extern "efiapi" fn create_event(
+    ty: EventType,
+    _notify_tpl: Tpl,
+    notify_func: Option<EventNotifyFn>,
+    notify_ctx: Option<NonNull<c_void>>,
+    out_event: *mut Event,
+) -> Status {
+    if out_event.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    let storage = Box::new(0u64);
+    let ptr = ptr::addr_of!(*storage) as *mut c_void;
+    with_database(|db| {
+        db.events.push(EventEntry {
+            ptr,
+            _storage: storage,
+            ty,
+            notify_fn: notify_func,
+            notify_ctx,
+            signaled: false,
+            timer: None,
+            group: None,
+        })
+    });
+    out_event.write(Event::from_ptr(ptr).unwrap());
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn set_timer(event: Event, ty: u32, _trigger_time: u64) -> Status {
+    with_database(|db| match db.event(&event) {
+        Some(entry) if entry.ty.contains(EventType::TIMER) && ty <= 2 => {
+            entry.timer = if ty == 0 { None } else { Some(ty) };
+            Status::SUCCESS
+        }
+        _ => Status::INVALID_PARAMETER,
+    })
+}
+
+/// Checks whether an event is signaled, clearing its signaled state. Wait
+/// events get their notification function called first.
+fn poll_event(event: &Event) -> Status {
+    let entry = with_database(|db| {
+        db.event(event)
+            .map(|entry| (entry.ty, entry.notify_fn, entry.notify_ctx, entry.signaled))
+    });
+    let (ty, notify_fn, notify_ctx, signaled) = match entry {
+        Some(entry) => entry,
+        None => return Status::INVALID_PARAMETER,
+    };
+    if ty.contains(EventType::NOTIFY_SIGNAL) {
+        return Status::INVALID_PARAMETER;
+    }
+    if !signaled && ty.contains(EventType::NOTIFY_WAIT) {
+        if let Some(notify_fn) = notify_fn {
+            unsafe { notify_fn(event.unsafe_clone(), notify_ctx) };
+        }
+    }
+    with_database(|db| match db.event(event) {
+        Some(entry) if entry.signaled => {
+            entry.signaled = false;
+            Status::SUCCESS
+        }
+        _ => Status::NOT_READY,
+    })
+}
+
+unsafe extern "efiapi" fn wait_for_event(
+    number_of_events: usize,
+    events: *mut Event,
+    out_index: *mut usize,
+) -> Status {
+    if number_of_events == 0 || events.is_null() || out_index.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    // Nothing else can signal an event while the caller is blocked, so
+    // instead of waiting forever, report that no event is ready.
+    for index in 0..number_of_events {
+        match poll_event(&*events.add(index)) {
+            Status::SUCCESS => {
+                out_index.write(index);
+                return Status::SUCCESS;
+            }
+            Status::NOT_READY => {}
+            other => {
+                out_index.write(index);
+                return other;
+            }
+        }
+    }
+    // Time doesn't pass in the mock, so skip ahead to the first set timer.
+    // Relative timers only fire once, periodic timers stay set.
+    for index in 0..number_of_events {
+        let fired = with_database(|db| match db.event(&*events.add(index)) {
+            Some(entry) if entry.timer.is_some() => {
+                if entry.timer == Some(2) {
+                    entry.timer = None;
+                }
+                true
+            }
+            _ => false,
+        });
+        if fired {
+            out_index.write(index);
+            return Status::SUCCESS;
+        }
+    }
+    Status::NOT_READY
+}
+
+/// Signals `event`, or all events of its group, then runs their
+/// notification functions.
+unsafe extern "efiapi" fn signal_event(event: Event) -> Status {
+    let notify = with_database(|db| {
+        let group = db.event(&event)?.group;
+        let mut notify = Vec::new();
+        for entry in &mut db.events {
+            let in_group = match group {
+                Some(group) => entry.group == Some(group),
+                None => entry.ptr == event.as_ptr(),
+            };
+            if !in_group {
+                continue;
+            }
+            entry.signaled = true;
+            if let Some(notify_fn) = entry.notify_fn {
+                if entry.ty.contains(EventType::NOTIFY_SIGNAL) {
+                    notify.push((entry.ptr, notify_fn, entry.notify_ctx));
+                }
+            }
+        }
+        Some(notify)
+    });
+    match notify {
+        Some(notify) => {
+            for (ptr, notify_fn, notify_ctx) in notify {
+                unsafe { notify_fn(Event::from_ptr(ptr).unwrap(), notify_ctx) };
+            }
+            Status::SUCCESS
+        }
+        None => Status::INVALID_PARAMETER,
+    }
+}
+
+unsafe extern "efiapi" fn close_event(event: Event) -> Status {
+    with_database(|db| {
+        let len = db.events.len();
+        db.events.retain(|entry| entry.ptr != event.as_ptr());
+        if db.events.len() == len {
+            Status::INVALID_PARAMETER
+        } else {
+            Status::SUCCESS
+        }
+    })
+}
+
+unsafe extern "efiapi" fn check_event(event: Event) -> Status {
+    poll_event(&event)
+}
+
+unsafe extern "efiapi" fn install_protocol_interface(
+    handle: *mut Option<Handle>,
+    guid: *const Guid,
+    interface_type: InterfaceType,
+    interface: *mut c_void,
+) -> Status {
+    if interface_type != InterfaceType::NATIVE_INTERFACE {
+        return Status::INVALID_PARAMETER;
+    }
+    match install(*handle, &*guid, interface) {
+        Ok(new_handle) => {
+            *handle = Some(new_handle);
+            Status::SUCCESS
+        }
+        Err(status) => status,
+    }
+}
+
+unsafe extern "efiapi" fn reinstall_protocol_interface(
+    handle: Handle,
+    protocol: *const Guid,
+    old_interface: *mut c_void,
+    new_interface: *mut c_void,
+) -> Status {
+    with_database(|db| match db.interface(handle, &*protocol) {
+        Ok(iface) if iface.interface == old_interface => {
+            iface.interface = new_interface;
+            Status::SUCCESS
+        }
+        Ok(_) | Err(_) => Status::NOT_FOUND,
+    })
+}
+
+unsafe extern "efiapi" fn uninstall_protocol_interface(
+    handle: Handle,
+    protocol: *const Guid,
+    interface: *mut c_void,
+) -> Status {
+    with_database(|db| {
+        let entry = match db.handle(handle) {
+            Some(entry) => entry,
+            None => return Status::INVALID_PARAMETER,
+        };
+        let index = entry
+            .interfaces
+            .iter()
+            .position(|iface| *iface.guid == *protocol && iface.interface == interface);
+        match index {
+            Some(index) if entry.interfaces[index].exclusive => Status::ACCESS_DENIED,
+            Some(index) => {
+                entry.interfaces.remove(index);
+                if entry.interfaces.is_empty() {
+                    db.handles
+                        .retain(|entry| entry.handle.as_ptr() != handle.as_ptr());
+                }
+                Status::SUCCESS
+            }
+            None => Status::NOT_FOUND,
+        }
+    })
+}
+
+unsafe extern "efiapi" fn handle_protocol(
+    handle: Handle,
+    proto: *const Guid,
+    out_proto: *mut *mut c_void,
+) -> Status {
+    with_database(|db| match db.interface(handle, &*proto) {
+        Ok(iface) => {
+            *out_proto = iface.interface;
+            Status::SUCCESS
+        }
+        Err(status) => status,
+    })
+}
+
+unsafe extern "efiapi" fn register_protocol_notify(
+    _protocol: *const Guid,
+    _event: Event,
+    _registration: *mut *mut c_void,
+) -> Status {
+    Status::UNSUPPORTED
+}
+
+unsafe extern "efiapi" fn locate_handle(
+    search_ty: i32,
+    proto: *const Guid,
+    _key: *const c_void,
+    buf_sz: *mut usize,
+    buf: *mut Handle,
+) -> Status {
+    let handles = match with_database(|db| db.matching_handles(search_ty, proto.as_ref())) {
+        Ok(handles) => handles,
+        Err(status) => return status,
+    };
+    let required = mem::size_of_val(handles.as_slice());
+    if *buf_sz < required {
+        *buf_sz = required;
+        return Status::BUFFER_TOO_SMALL;
+    }
+    *buf_sz = required;
+    ptr::copy_nonoverlapping(handles.as_ptr(), buf, handles.len());
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn locate_device_path(
+    _proto: *const Guid,
+    _device_path: *mut *const DevicePathProtocol,
+    _out_handle: *mut Handle,
+) -> Status {
+    Status::NOT_FOUND
+}
+
+unsafe extern "efiapi" fn load_image(
+    _boot_policy: u8,
+    _parent_image_handle: Handle,
+    _device_path: *const DevicePathProtocol,
+    _source_buffer: *const u8,
+    _source_size: usize,
+    _image_handle: *mut Handle,
+) -> Status {
+    Status::UNSUPPORTED
+}
+
+unsafe extern "efiapi" fn start_image(
+    _image_handle: Handle,
+    _exit_data_size: *mut usize,
+    _exit_data: *mut *mut Char16,
+) -> Status {
+    Status::UNSUPPORTED
+}
+
+unsafe extern "efiapi" fn exit(
+    _image_handle: Handle,
+    exit_status: Status,
+    _exit_data_size: usize,
+    _exit_data: *mut Char16,
+) -> ! {
+    panic!("mock firmware: image exited with status {exit_status:?}");
+}
+
+unsafe extern "efiapi" fn unload_image(_image_handle: Handle) -> Status {
+    Status::UNSUPPORTED
+}
+
+unsafe extern "efiapi" fn exit_boot_services(_image_handle: Handle, _map_key: usize) -> Status {
+    Status::UNSUPPORTED
+}
+
+unsafe extern "efiapi" fn stall(_microseconds: usize) -> Status {
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn set_watchdog_timer(
+    timeout: usize,
+    watchdog_code: u64,
+    _data_size: usize,
+    _watchdog_data: *const u16,
+) -> Status {
+    WATCHDOG.with(|watchdog| watchdog.set((timeout, watchdog_code)));
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn connect_controller(
+    _controller: Handle,
+    _driver_image: Option<Handle>,
+    _remaining_device_path: *const DevicePathProtocol,
+    _recursive: bool,
+) -> Status {
+    Status::NOT_FOUND
+}
+
+unsafe extern "efiapi" fn disconnect_controller(
+    _controller: Handle,
+    _driver_image: Option<Handle>,
+    _child: Option<Handle>,
+) -> Status {
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn open_protocol(
+    handle: Handle,
+    protocol: *const Guid,
+    interface: *mut *mut c_void,
+    agent_handle: Handle,
+    controller_handle: Option<Handle>,
+    attributes: u32,
+) -> Status {
+    with_database(|db| {
+        let iface = match db.interface(handle, &*protocol) {
+            Ok(iface) => iface,
+            Err(status) => return status,
+        };
+        if attributes & OPEN_TEST_PROTOCOL != 0 {
+            return Status::SUCCESS;
+        }
+        if attributes & OPEN_EXCLUSIVE != 0 {
+            if iface.exclusive {
+                return Status::ACCESS_DENIED;
+            }
+            iface.exclusive = true;
+        }
+        let agent_handle = Some(agent_handle);
+        match iface.opens.iter_mut().find(|entry| {
+            same_handle(entry.agent_handle, agent_handle)
+                && same_handle(entry.controller_handle, controller_handle)
+                && entry.attributes == attributes
+        }) {
+            Some(entry) => entry.open_count += 1,
+            None => iface.opens.push(OpenProtocolInformationEntry {
+                agent_handle,
+                controller_handle,
+                attributes,
+                open_count: 1,
+            }),
+        }
+        *interface = iface.interface;
+        Status::SUCCESS
+    })
+}
+
+fn same_handle(a: Option<Handle>, b: Option<Handle>) -> bool {
+    a.map(|h| h.as_ptr()) == b.map(|h| h.as_ptr())
+}
+
+unsafe extern "efiapi" fn close_protocol(
+    handle: Handle,
+    protocol: *const Guid,
+    agent_handle: Handle,
+    controller_handle: Option<Handle>,
+) -> Status {
+    with_database(|db| match db.interface(handle, &*protocol) {
+        Ok(iface) => {
+            iface.exclusive = false;
+            iface.opens.retain(|entry| {
+                !same_handle(entry.agent_handle, Some(agent_handle))
+                    || !same_handle(entry.controller_handle, controller_handle)
+            });
+            Status::SUCCESS
+        }
+        Err(_) => Status::NOT_FOUND,
+    })
+}
+
+unsafe extern "efiapi" fn open_protocol_information(
+    handle: Handle,
+    protocol: *const Guid,
+    entry_buffer: *mut *mut OpenProtocolInformationEntry,
+    entry_count: *mut usize,
+) -> Status {
+    let opens = match with_database(|db| {
+        db.interface(handle, &*protocol)
+            .map(|iface| iface.opens.clone())
+    }) {
+        Ok(opens) => opens,
+        Err(_) => return Status::NOT_FOUND,
+    };
+    let buffer = pool_copy(&opens);
+    if buffer.is_null() {
+        return Status::OUT_OF_RESOURCES;
+    }
+    entry_buffer.write(buffer);
+    entry_count.write(opens.len());
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn protocols_per_handle(
+    handle: Handle,
+    protocol_buffer: *mut *mut *const Guid,
+    protocol_buffer_count: *mut usize,
+) -> Status {
+    let guids = with_database(|db| {
+        db.handle(handle).map(|entry| {
+            entry
+                .interfaces
+                .iter()
+                .map(|iface| ptr::addr_of!(*iface.guid))
+                .collect::<Vec<_>>()
+        })
+    });
+    let guids = match guids {
+        Some(guids) => guids,
+        None => return Status::INVALID_PARAMETER,
+    };
+    let buffer = pool_copy(&guids);
+    if buffer.is_null() {
+        return Status::OUT_OF_RESOURCES;
+    }
+    protocol_buffer.write(buffer);
+    protocol_buffer_count.write(guids.len());
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn locate_handle_buffer(
+    search_ty: i32,
+    proto: *const Guid,
+    _key: *const c_void,
+    no_handles: *mut usize,
+    buf: *mut *mut Handle,
+) -> Status {
+    let handles = match with_database(|db| db.matching_handles(search_ty, proto.as_ref())) {
+        Ok(handles) => handles,
+        Err(status) => return status,
+    };
+    *buf = pool_copy(&handles);
+    if (*buf).is_null() {
+        return Status::OUT_OF_RESOURCES;
+    }
+    *no_handles = handles.len();
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn locate_protocol(
+    proto: *const Guid,
+    _registration: *mut c_void,
+    out_proto: *mut *mut c_void,
+) -> Status {
+    with_database(|db| {
+        let iface = db
+            .handles
+            .iter()
+            .flat_map(|entry| entry.interfaces.iter())
+            .find(|iface| *iface.guid == *proto);
+        match iface {
+            Some(iface) => {
+                *out_proto = iface.interface;
+                Status::SUCCESS
+            }
+            None => Status::NOT_FOUND,
+        }
+    })
+}
+
+unsafe extern "efiapi" fn copy_mem(dest: *mut u8, src: *const u8, len: usize) {
+    ptr::copy(src, dest, len);
+}
+
+unsafe extern "efiapi" fn set_mem(buffer: *mut u8, len: usize, value: u8) {
+    ptr::write_bytes(buffer, value, len);
+}
+
+#[allow(improper_ctypes_definitions)]
+unsafe extern "efiapi" fn create_event_ex(
+    ty: EventType,
+    notify_tpl: Tpl,
+    notify_fn: Option<EventNotifyFn>,
+    notify_ctx: Option<NonNull<c_void>>,
+    event_group: *const Guid,
+    out_event: *mut Event,
+) -> Status {
+    let status = create_event(ty, notify_tpl, notify_fn, notify_ctx, out_event);
+    if status.is_success() {
+        let event = (*out_event).unsafe_clone();
+        with_database(|db| db.event(&event).unwrap().group = event_group.as_ref().copied());
+    }
+    status
+}
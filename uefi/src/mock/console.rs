@@ -0,0 +1,332 @@
+//! Mock text input and output protocols.
+
+use super::boot;
+use crate::proto::console::text::{Input, Output, RawKey, ScanCode};
+use crate::raw::protocol::console::text::{
+    InputKey, SimpleTextInputProtocol, SimpleTextOutputMode, SimpleTextOutputProtocol,
+};
+use crate::{Char16, Status};
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::slice;
+
+/// In-memory text output device.
+///
+/// Everything written to the device is captured as a string, which can be
+/// retrieved with [`text`]. The device supports the standard 80x25 and
+/// 80x50 text modes by default.
+///
+/// [`text`]: Self::text
+#[repr(C)]
+pub struct MockOutput {
+    // Must be the first field so that the protocol's `this` pointer can be
+    // converted back to a `MockOutput`.
+    raw: SimpleTextOutputProtocol,
+    mode: SimpleTextOutputMode,
+    modes: Vec<(usize, usize)>,
+    text: String,
+}
+
+impl MockOutput {
+    /// Creates a new output device.
+    ///
+    /// The device is boxed because the protocol interface must not move
+    /// while it is in use.
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        let mut output = Box::new(Self {
+            raw: SimpleTextOutputProtocol {
+                reset: output_reset,
+                output_string,
+                test_string,
+                query_mode,
+                set_mode,
+                set_attribute,
+                clear_screen,
+                set_cursor_position,
+                enable_cursor,
+                mode: core::ptr::null_mut(),
+            },
+            mode: SimpleTextOutputMode::default(),
+            modes: vec![(80, 25), (80, 50)],
+            text: String::new(),
+        });
+        output.raw.mode = &mut output.mode;
+        output.mode.max_mode = 2;
+        output.mode.attribute = 0x07;
+        output
+    }
+
+    /// Replaces the text modes supported by the device, as (columns, rows)
+    /// pairs. The current mode is reset to the first one.
+    pub fn set_modes(&mut self, modes: &[(usize, usize)]) {
+        self.modes = modes.to_vec();
+        self.mode.max_mode = modes.len() as i32;
+        self.mode.mode = if modes.is_empty() { -1 } else { 0 };
+    }
+
+    /// Returns the `Output` protocol of this device.
+    pub fn output(&mut self) -> &mut Output<'static> {
+        unsafe { &mut *(self as *mut Self).cast::<Output>() }
+    }
+
+    /// Returns the protocol interface pointer of this device.
+    pub(super) fn as_raw(&mut self) -> *mut SimpleTextOutputProtocol {
+        &mut self.raw
+    }
+
+    /// Returns everything written to the device so far. Line breaks are
+    /// included as written, so lines written through `fmt::Write` end with
+    /// `"\r\n"`.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns everything written to the device so far, and clears it.
+    pub fn take_text(&mut self) -> String {
+        core::mem::take(&mut self.text)
+    }
+
+    /// Returns the current character attribute.
+    #[must_use]
+    pub const fn attribute(&self) -> usize {
+        self.mode.attribute as usize
+    }
+
+    /// Returns the current cursor column and row.
+    #[must_use]
+    pub const fn cursor_position(&self) -> (usize, usize) {
+        (
+            self.mode.cursor_column as usize,
+            self.mode.cursor_row as usize,
+        )
+    }
+}
+
+unsafe fn output_from<'a>(this: *mut SimpleTextOutputProtocol) -> &'a mut MockOutput {
+    &mut *this.cast::<MockOutput>()
+}
+
+unsafe fn ucs2_str<'a>(string: *const Char16) -> &'a [u16] {
+    let string = string.cast::<u16>();
+    let mut len = 0;
+    while *string.add(len) != 0 {
+        len += 1;
+    }
+    slice::from_raw_parts(string, len)
+}
+
+unsafe extern "efiapi" fn output_reset(
+    this: *mut SimpleTextOutputProtocol,
+    _extended_verification: bool,
+) -> Status {
+    let output = output_from(this);
+    output.mode.cursor_column = 0;
+    output.mode.cursor_row = 0;
+    output.mode.attribute = 0x07;
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn output_string(
+    this: *mut SimpleTextOutputProtocol,
+    string: *const Char16,
+) -> Status {
+    let output = output_from(this);
+    for c in char::decode_utf16(ucs2_str(string).iter().copied()) {
+        let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+        match c {
+            '\r' => output.mode.cursor_column = 0,
+            '\n' => output.mode.cursor_row += 1,
+            _ => output.mode.cursor_column += 1,
+        }
+        output.text.push(c);
+    }
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn test_string(
+    _this: *mut SimpleTextOutputProtocol,
+    _string: *const Char16,
+) -> Status {
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn query_mode(
+    this: *mut SimpleTextOutputProtocol,
+    mode_number: usize,
+    columns: *mut usize,
+    rows: *mut usize,
+) -> Status {
+    match output_from(this).modes.get(mode_number) {
+        Some(&(mode_columns, mode_rows)) => {
+            columns.write(mode_columns);
+            rows.write(mode_rows);
+            Status::SUCCESS
+        }
+        None => Status::UNSUPPORTED,
+    }
+}
+
+unsafe extern "efiapi" fn set_mode(
+    this: *mut SimpleTextOutputProtocol,
+    mode_number: usize,
+) -> Status {
+    let output = output_from(this);
+    if mode_number >= output.modes.len() {
+        return Status::UNSUPPORTED;
+    }
+    output.mode.mode = mode_number as i32;
+    output.mode.cursor_column = 0;
+    output.mode.cursor_row = 0;
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn set_attribute(
+    this: *mut SimpleTextOutputProtocol,
+    attribute: usize,
+) -> Status {
+    output_from(this).mode.attribute = attribute as i32;
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn clear_screen(this: *mut SimpleTextOutputProtocol) -> Status {
+    let output = output_from(this);
+    output.mode.cursor_column = 0;
+    output.mode.cursor_row = 0;
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn set_cursor_position(
+    this: *mut SimpleTextOutputProtocol,
+    column: usize,
+    row: usize,
+) -> Status {
+    let output = output_from(this);
+    let (columns, rows) = match usize::try_from(output.mode.mode)
+        .ok()
+        .and_then(|mode| output.modes.get(mode))
+    {
+        Some(&dims) => dims,
+        None => return Status::UNSUPPORTED,
+    };
+    if column >= columns || row >= rows {
+        return Status::UNSUPPORTED;
+    }
+    output.mode.cursor_column = column as i32;
+    output.mode.cursor_row = row as i32;
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn enable_cursor(
+    this: *mut SimpleTextOutputProtocol,
+    visible: bool,
+) -> Status {
+    output_from(this).mode.cursor_visible = visible;
+    Status::SUCCESS
+}
+
+/// In-memory text input device.
+///
+/// Keys queued with [`push_key`] or [`push_str`] are returned by
+/// [`Input::read_key_ex`], and signal the device's `wait_for_key` event.
+///
+/// [`push_key`]: Self::push_key
+/// [`push_str`]: Self::push_str
+/// [`Input::read_key_ex`]: crate::proto::console::text::Input::read_key_ex
+#[repr(C)]
+pub struct MockInput {
+    // Must be the first field so that the protocol's `this` pointer can be
+    // converted back to a `MockInput`.
+    raw: SimpleTextInputProtocol,
+    keys: VecDeque<RawKey>,
+}
+
+impl MockInput {
+    /// Creates a new input device with no queued keys.
+    ///
+    /// The `wait_for_key` event is created with the mock boot services of
+    /// the current thread, so this should be called after creating a
+    /// [`MockFirmware`].
+    ///
+    /// [`MockFirmware`]: super::MockFirmware
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        Box::new(Self {
+            raw: SimpleTextInputProtocol {
+                reset: input_reset,
+                read_key_stroke,
+                wait_for_key: boot::create_plain_event(),
+            },
+            keys: VecDeque::new(),
+        })
+    }
+
+    /// Returns the `Input` protocol of this device.
+    pub fn input(&mut self) -> &mut Input {
+        unsafe { &mut *(self as *mut Self).cast::<Input>() }
+    }
+
+    /// Returns the protocol interface pointer of this device.
+    pub(super) fn as_raw(&mut self) -> *mut SimpleTextInputProtocol {
+        &mut self.raw
+    }
+
+    /// Queues a key.
+    pub fn push_key(&mut self, key: RawKey) {
+        self.keys.push_back(key);
+        boot::set_signaled(&self.raw.wait_for_key, true);
+    }
+
+    /// Queues one printable key for each character of `s`. Characters
+    /// outside the UCS-2 range are skipped.
+    pub fn push_str(&mut self, s: &str) {
+        for c in s.chars() {
+            if let Ok(unicode_char) = Char16::try_from(c) {
+                self.push_key(RawKey {
+                    scan_code: ScanCode::NULL,
+                    unicode_char,
+                });
+            }
+        }
+    }
+
+    /// Returns the number of queued keys.
+    #[must_use]
+    pub fn pending_keys(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+unsafe fn input_from<'a>(this: *mut SimpleTextInputProtocol) -> &'a mut MockInput {
+    &mut *this.cast::<MockInput>()
+}
+
+unsafe extern "efiapi" fn input_reset(
+    this: *mut SimpleTextInputProtocol,
+    _extended_verification: bool,
+) -> Status {
+    let input = input_from(this);
+    input.keys.clear();
+    boot::set_signaled(&input.raw.wait_for_key, false);
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn read_key_stroke(
+    this: *mut SimpleTextInputProtocol,
+    key: *mut InputKey,
+) -> Status {
+    let input = input_from(this);
+    let next = input.keys.pop_front();
+    boot::set_signaled(&input.raw.wait_for_key, !input.keys.is_empty());
+    match next {
+        Some(next) => {
+            key.cast::<RawKey>().write(next);
+            Status::SUCCESS
+        }
+        None => Status::NOT_READY,
+    }
+}
@@ -0,0 +1,514 @@
+//! Mock in-memory file system.
+
+use crate::proto::media::file::{
+    FileAttribute, FileImpl, FileInfo, FileMode, FileSystemInfo, FromUefi,
+};
+use crate::proto::media::fs::SimpleFileSystem;
+use crate::proto::media::virtual_fs::write_info;
+use crate::table::runtime::Time;
+use crate::{CString16, Char16, Guid, Identify, Status};
+use alloc::boxed::Box;
+use alloc::rc::{Rc, Weak};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::ffi::c_void;
+use core::mem::MaybeUninit;
+use core::{ptr, slice};
+
+type NodeRef = Rc<RefCell<Node>>;
+
+struct Node {
+    name: String,
+    attribute: FileAttribute,
+    /// Creation, last access and modification times.
+    times: [Time; 3],
+    data: Vec<u8>,
+    children: Vec<NodeRef>,
+    parent: Weak<RefCell<Node>>,
+}
+
+impl Node {
+    fn new_ref(name: &str, attribute: FileAttribute, parent: &NodeRef) -> NodeRef {
+        Rc::new(RefCell::new(Node {
+            name: name.to_string(),
+            attribute,
+            times: [Time::invalid(); 3],
+            data: Vec::new(),
+            children: Vec::new(),
+            parent: Rc::downgrade(parent),
+        }))
+    }
+
+    fn is_dir(&self) -> bool {
+        self.attribute.contains(FileAttribute::DIRECTORY)
+    }
+
+    fn child(&self, name: &str) -> Option<NodeRef> {
+        self.children
+            .iter()
+            .find(|child| child.borrow().name.eq_ignore_ascii_case(name))
+            .cloned()
+    }
+}
+
+/// In-memory file system.
+///
+/// The file system is shared between clones, so files written through the
+/// [`SimpleFileSystem`] protocol of a [`MockFirmware`] can be inspected
+/// with the clone that was passed to [`MockFirmware::install_file_system`].
+///
+/// Paths passed to the helper methods may use either `\` or `/` as the
+/// separator. Like FAT, names are case-insensitive.
+///
+/// [`MockFirmware`]: super::MockFirmware
+/// [`MockFirmware::install_file_system`]: super::MockFirmware::install_file_system
+#[derive(Clone)]
+pub struct MockFileSystem {
+    root: NodeRef,
+    label: Rc<str>,
+}
+
+impl MockFileSystem {
+    /// Creates an empty file system with the given volume label.
+    #[must_use]
+    pub fn new(label: &str) -> Self {
+        Self {
+            root: Rc::new(RefCell::new(Node {
+                name: String::new(),
+                attribute: FileAttribute::DIRECTORY,
+                times: [Time::invalid(); 3],
+                data: Vec::new(),
+                children: Vec::new(),
+                parent: Weak::new(),
+            })),
+            label: label.into(),
+        }
+    }
+
+    fn components(path: &str) -> impl Iterator<Item = &str> {
+        path.split(['\\', '/'])
+            .filter(|name| !name.is_empty() && *name != ".")
+    }
+
+    fn lookup(&self, path: &str) -> Option<NodeRef> {
+        let mut node = self.root.clone();
+        for name in Self::components(path) {
+            let next = node.borrow().child(name)?;
+            node = next;
+        }
+        Some(node)
+    }
+
+    /// Creates a directory, along with any missing parent directories.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a component of the path is an existing regular file.
+    pub fn add_dir(&self, path: &str) {
+        let mut node = self.root.clone();
+        for name in Self::components(path) {
+            let existing = node.borrow().child(name);
+            let next = match existing {
+                Some(next) => next,
+                None => {
+                    let next = Node::new_ref(name, FileAttribute::DIRECTORY, &node);
+                    node.borrow_mut().children.push(next.clone());
+                    next
+                }
+            };
+            assert!(next.borrow().is_dir(), "{name} is not a directory");
+            node = next;
+        }
+    }
+
+    /// Creates or replaces a regular file, along with any missing parent
+    /// directories.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `path` is empty or refers to an existing directory, or if a
+    /// parent component is an existing regular file.
+    pub fn add_file(&self, path: &str, data: &[u8]) {
+        let (dir, name) = match path.trim_end_matches(['\\', '/']).rsplit_once(['\\', '/']) {
+            Some((dir, name)) => (dir, name),
+            None => ("", path),
+        };
+        assert!(!name.is_empty(), "empty file name");
+        self.add_dir(dir);
+        let dir = self.lookup(dir).unwrap();
+        let existing = dir.borrow().child(name);
+        let file = match existing {
+            Some(file) => file,
+            None => {
+                let file = Node::new_ref(name, FileAttribute::empty(), &dir);
+                dir.borrow_mut().children.push(file.clone());
+                file
+            }
+        };
+        assert!(!file.borrow().is_dir(), "{name} is a directory");
+        file.borrow_mut().data = data.to_vec();
+    }
+
+    /// Returns the contents of a regular file, or `None` if it does not
+    /// exist or is a directory.
+    #[must_use]
+    pub fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+        let node = self.lookup(path)?;
+        let node = node.borrow();
+        if node.is_dir() {
+            None
+        } else {
+            Some(node.data.clone())
+        }
+    }
+
+    /// Returns true if a file or directory exists at `path`.
+    #[must_use]
+    pub fn exists(&self, path: &str) -> bool {
+        self.lookup(path).is_some()
+    }
+}
+
+/// A `SimpleFileSystem` protocol instance backed by a [`MockFileSystem`].
+#[repr(C)]
+pub(super) struct FileSystemInstance {
+    // Must be the first field so that the protocol's `this` pointer can be
+    // converted back to a `FileSystemInstance`.
+    raw: SimpleFileSystem,
+    fs: MockFileSystem,
+}
+
+impl FileSystemInstance {
+    pub(super) fn new(fs: MockFileSystem) -> Box<Self> {
+        // The protocol struct has a private marker field, so it can't be
+        // built with a struct literal. The marker is zero-sized and needs no
+        // initialization.
+        let instance = Box::into_raw(Box::new(MaybeUninit::<Self>::uninit())).cast::<Self>();
+        unsafe {
+            ptr::addr_of_mut!((*instance).raw.revision).write(0x0001_0000);
+            ptr::addr_of_mut!((*instance).raw.open_volume).write(open_volume);
+            ptr::addr_of_mut!((*instance).fs).write(fs);
+            Box::from_raw(instance)
+        }
+    }
+
+    pub(super) fn as_raw(&mut self) -> *mut SimpleFileSystem {
+        &mut self.raw
+    }
+}
+
+/// An open file.
+#[repr(C)]
+struct MockFile {
+    // Must be the first field so that the protocol's `this` pointer can be
+    // converted back to a `MockFile`.
+    imp: FileImpl,
+    fs: MockFileSystem,
+    node: NodeRef,
+    position: u64,
+    writable: bool,
+}
+
+impl MockFile {
+    fn open(fs: MockFileSystem, node: NodeRef, writable: bool) -> *mut FileImpl {
+        let file = Box::new(MockFile {
+            imp: FileImpl {
+                revision: 0x0001_0000,
+                open: file_open,
+                close: file_close,
+                delete: file_delete,
+                read: file_read,
+                write: file_write,
+                get_position: file_get_position,
+                set_position: file_set_position,
+                get_info: file_get_info,
+                set_info: file_set_info,
+                flush: file_flush,
+            },
+            fs,
+            node,
+            position: 0,
+            writable,
+        });
+        Box::into_raw(file).cast()
+    }
+}
+
+unsafe fn file_from<'a>(this: &mut FileImpl) -> &'a mut MockFile {
+    &mut *(this as *mut FileImpl).cast::<MockFile>()
+}
+
+unsafe fn write_file_info(node: &Node, buffer_size: &mut usize, buffer: *mut u8) -> Status {
+    let name = match CString16::try_from(node.name.as_str()) {
+        Ok(name) => name,
+        Err(_) => return Status::DEVICE_ERROR,
+    };
+    let size = node.data.len() as u64;
+    let [create, access, modification] = node.times;
+    write_info(buffer_size, buffer, |storage| {
+        FileInfo::new(
+            storage,
+            size,
+            size,
+            create,
+            access,
+            modification,
+            node.attribute,
+            &name,
+        )
+    })
+}
+
+unsafe fn ucs2_string(string: *const Char16) -> String {
+    let string = string.cast::<u16>();
+    let mut len = 0;
+    while *string.add(len) != 0 {
+        len += 1;
+    }
+    char::decode_utf16(slice::from_raw_parts(string, len).iter().copied())
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+extern "efiapi" fn open_volume(this: &mut SimpleFileSystem, root: &mut *mut FileImpl) -> Status {
+    let instance = unsafe { &*(this as *mut SimpleFileSystem).cast::<FileSystemInstance>() };
+    let fs = instance.fs.clone();
+    let node = fs.root.clone();
+    *root = MockFile::open(fs, node, true);
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn file_open(
+    this: &mut FileImpl,
+    new_handle: &mut *mut FileImpl,
+    filename: *const Char16,
+    open_mode: FileMode,
+    attributes: FileAttribute,
+) -> Status {
+    let file = file_from(this);
+    let path = ucs2_string(filename);
+
+    let mut node = if path.starts_with('\\') {
+        file.fs.root.clone()
+    } else if file.node.borrow().is_dir() {
+        file.node.clone()
+    } else {
+        match file.node.borrow().parent.upgrade() {
+            Some(parent) => parent,
+            None => file.fs.root.clone(),
+        }
+    };
+
+    let names: Vec<&str> = path
+        .split('\\')
+        .filter(|name| !name.is_empty() && *name != ".")
+        .collect();
+    for (i, name) in names.iter().enumerate() {
+        if !node.borrow().is_dir() {
+            return Status::NOT_FOUND;
+        }
+        if *name == ".." {
+            let parent = node.borrow().parent.upgrade();
+            if let Some(parent) = parent {
+                node = parent;
+            }
+            continue;
+        }
+        let existing = node.borrow().child(name);
+        node = match existing {
+            Some(next) => next,
+            None if i + 1 == names.len() && open_mode == FileMode::CreateReadWrite => {
+                let next = Node::new_ref(name, attributes & FileAttribute::VALID_ATTR, &node);
+                node.borrow_mut().children.push(next.clone());
+                next
+            }
+            None => return Status::NOT_FOUND,
+        };
+    }
+
+    let writable = open_mode != FileMode::Read;
+    if writable && node.borrow().attribute.contains(FileAttribute::READ_ONLY) {
+        return Status::ACCESS_DENIED;
+    }
+    *new_handle = MockFile::open(file.fs.clone(), node, writable);
+    Status::SUCCESS
+}
+
+extern "efiapi" fn file_close(this: &mut FileImpl) -> Status {
+    drop(unsafe { Box::from_raw((this as *mut FileImpl).cast::<MockFile>()) });
+    Status::SUCCESS
+}
+
+extern "efiapi" fn file_delete(this: &mut FileImpl) -> Status {
+    let file = unsafe { Box::from_raw((this as *mut FileImpl).cast::<MockFile>()) };
+    let parent = file.node.borrow().parent.upgrade();
+    match parent {
+        Some(parent) if file.writable => {
+            parent
+                .borrow_mut()
+                .children
+                .retain(|child| !Rc::ptr_eq(child, &file.node));
+            Status::SUCCESS
+        }
+        _ => Status::WARN_DELETE_FAILURE,
+    }
+}
+
+unsafe extern "efiapi" fn file_read(
+    this: &mut FileImpl,
+    buffer_size: &mut usize,
+    buffer: *mut u8,
+) -> Status {
+    let file = file_from(this);
+    let node = file.node.borrow();
+    if node.is_dir() {
+        let entry = match node.children.get(file.position as usize) {
+            Some(entry) => entry,
+            None => {
+                *buffer_size = 0;
+                return Status::SUCCESS;
+            }
+        };
+        let status = write_file_info(&entry.borrow(), buffer_size, buffer);
+        if status == Status::SUCCESS {
+            file.position += 1;
+        }
+        status
+    } else {
+        // Reading at the end of the file reads nothing, but reading beyond
+        // it is an error.
+        if file.position > node.data.len() as u64 {
+            return Status::DEVICE_ERROR;
+        }
+        let start = file.position as usize;
+        let len = (*buffer_size).min(node.data.len() - start);
+        ptr::copy_nonoverlapping(node.data[start..].as_ptr(), buffer, len);
+        *buffer_size = len;
+        file.position += len as u64;
+        Status::SUCCESS
+    }
+}
+
+unsafe extern "efiapi" fn file_write(
+    this: &mut FileImpl,
+    buffer_size: &mut usize,
+    buffer: *const u8,
+) -> Status {
+    let file = file_from(this);
+    let mut node = file.node.borrow_mut();
+    if node.is_dir() {
+        return Status::UNSUPPORTED;
+    }
+    if !file.writable {
+        return Status::ACCESS_DENIED;
+    }
+    let start = file.position as usize;
+    let end = start + *buffer_size;
+    if node.data.len() < end {
+        node.data.resize(end, 0);
+    }
+    ptr::copy_nonoverlapping(buffer, node.data[start..].as_mut_ptr(), *buffer_size);
+    file.position = end as u64;
+    Status::SUCCESS
+}
+
+extern "efiapi" fn file_get_position(this: &mut FileImpl, position: &mut u64) -> Status {
+    let file = unsafe { file_from(this) };
+    if file.node.borrow().is_dir() {
+        return Status::UNSUPPORTED;
+    }
+    *position = file.position;
+    Status::SUCCESS
+}
+
+extern "efiapi" fn file_set_position(this: &mut FileImpl, position: u64) -> Status {
+    let file = unsafe { file_from(this) };
+    let node = file.node.borrow();
+    if node.is_dir() {
+        if position != 0 {
+            return Status::UNSUPPORTED;
+        }
+        file.position = 0;
+    } else if position == u64::MAX {
+        file.position = node.data.len() as u64;
+    } else {
+        file.position = position;
+    }
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn file_get_info(
+    this: &mut FileImpl,
+    information_type: &Guid,
+    buffer_size: &mut usize,
+    buffer: *mut u8,
+) -> Status {
+    let file = file_from(this);
+    if *information_type == FileInfo::GUID {
+        write_file_info(&file.node.borrow(), buffer_size, buffer)
+    } else if *information_type == FileSystemInfo::GUID {
+        let label = match CString16::try_from(&*file.fs.label) {
+            Ok(label) => label,
+            Err(_) => return Status::DEVICE_ERROR,
+        };
+        write_info(buffer_size, buffer, |storage| {
+            FileSystemInfo::new(storage, false, 0, 0, 512, &label)
+        })
+    } else {
+        Status::UNSUPPORTED
+    }
+}
+
+unsafe extern "efiapi" fn file_set_info(
+    this: &mut FileImpl,
+    information_type: &Guid,
+    _buffer_size: usize,
+    buffer: *const c_void,
+) -> Status {
+    let file = file_from(this);
+    if *information_type != FileInfo::GUID {
+        return Status::UNSUPPORTED;
+    }
+    let info = FileInfo::from_uefi(buffer as *mut c_void);
+    let mut node = file.node.borrow_mut();
+    if info.attribute().contains(FileAttribute::DIRECTORY) != node.is_dir() {
+        return Status::ACCESS_DENIED;
+    }
+    let new_times = [
+        *info.create_time(),
+        *info.last_access_time(),
+        *info.modification_time(),
+    ];
+    // The only change allowed to a read-only file is to remove that
+    // attribute.
+    if node.attribute.contains(FileAttribute::READ_ONLY)
+        && (info.attribute() != node.attribute - FileAttribute::READ_ONLY
+            || new_times.iter().any(|time| *time != Time::invalid()))
+    {
+        return Status::ACCESS_DENIED;
+    }
+    for (time, new_time) in node.times.iter_mut().zip(new_times) {
+        if new_time != Time::invalid() {
+            *time = new_time;
+        }
+    }
+    let name = info.file_name().to_string();
+    if !name.eq_ignore_ascii_case(&node.name) {
+        if let Some(parent) = node.parent.upgrade() {
+            if parent.borrow().child(&name).is_some() {
+                return Status::ACCESS_DENIED;
+            }
+        }
+        node.name = name;
+    }
+    if !node.is_dir() {
+        node.data.resize(info.file_size() as usize, 0);
+    }
+    node.attribute = info.attribute() & FileAttribute::VALID_ATTR;
+    Status::SUCCESS
+}
+
+extern "efiapi" fn file_flush(_this: &mut FileImpl) -> Status {
+    Status::SUCCESS
+}
@@ -0,0 +1,643 @@
+//! In-memory firmware for host-side unit tests.
+//!
+//! This module provides a [`MockFirmware`] that implements the system table,
+//! the boot services, and the runtime services in plain Rust, so that code
+//! written against the safe wrappers of this crate can be exercised with
+//! `cargo test` on the host, without QEMU or real firmware.
+//!
+//! The mock provides:
+//! - a handle database supporting protocol installation, lookup, and
+//!   exclusive opening,
+//! - pool and page allocation,
+//! - events that can be signaled and checked (but not waited on, since
+//!   nothing else can signal them while the test is blocked),
+//! - a variable store,
+//! - text console devices ([`MockInput`] and [`MockOutput`]),
+//! - an in-memory file system ([`MockFileSystem`]), and
+//! - an in-memory block device ([`MockBlockDevice`]).
+//!
+//! Services that the mock does not implement return `UNSUPPORTED` or a
+//! similar error, and `exit` and `reset_system` panic.
+//!
+//! The handle database and variable store are thread-local, so tests can
+//! run in parallel as long as each test creates its own `MockFirmware` and
+//! uses it from a single thread.
+//!
+//! This module requires the `mock` feature, which links against `std`.
+//!
+//! ```
+//! use uefi::mock::MockFirmware;
+//! use uefi::table::runtime::{VariableAttributes, VariableVendor};
+//! use uefi::cstr16;
+//!
+//! let mut firmware = MockFirmware::new();
+//! let st = firmware.system_table();
+//!
+//! let rt = st.runtime_services();
+//! rt.set_variable(
+//!     cstr16!("Test"),
+//!     &VariableVendor::GLOBAL_VARIABLE,
+//!     VariableAttributes::BOOTSERVICE_ACCESS,
+//!     b"data",
+//! )
+//! .unwrap();
+//!
+//! let mut buf = [0; 4];
+//! let (data, _) = rt
+//!     .get_variable(cstr16!("Test"), &VariableVendor::GLOBAL_VARIABLE, &mut buf)
+//!     .unwrap();
+//! assert_eq!(data, b"data");
+//! ```
+
+mod block;
+mod boot;
+mod console;
+mod fs;
+mod runtime;
+
+pub use self::block::MockBlockDevice;
+pub use self::console::{MockInput, MockOutput};
+pub use self::fs::MockFileSystem;
+
+use self::block::BlockDeviceInstance;
+use self::fs::FileSystemInstance;
+use crate::proto::console::text::{Input, Output};
+use crate::proto::media::block::BlockIO;
+use crate::proto::media::fs::SimpleFileSystem;
+use crate::proto::Protocol;
+use crate::raw::table as raw;
+use crate::table::{Boot, Header, Revision, SystemTable, Table};
+use crate::util::crc32;
+use crate::{Handle, Identify};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::{mem, ptr, slice};
+
+struct Inner {
+    table: raw::SystemTable,
+    boot: raw::boot::BootServices,
+    runtime: raw::runtime::RuntimeServices,
+    vendor: Vec<u16>,
+    stdin: Box<MockInput>,
+    stdout: Box<MockOutput>,
+    stderr: Box<MockOutput>,
+    // Boxed so that the protocol interfaces don't move when the vector grows.
+    #[allow(clippy::vec_box)]
+    file_systems: Vec<Box<FileSystemInstance>>,
+    #[allow(clippy::vec_box)]
+    block_devices: Vec<Box<BlockDeviceInstance>>,
+    image_handle: Handle,
+}
+
+/// In-memory firmware providing a system table for host-side tests.
+///
+/// Creating a `MockFirmware` resets the handle database and variable store
+/// of the current thread, and dropping it clears them again. Only one
+/// `MockFirmware` should be alive per thread.
+///
+/// The tables and console devices are intentionally leaked, so that a
+/// [`SystemTable`] obtained from the mock stays valid for the rest of the
+/// program. After the `MockFirmware` is dropped, calls through such a table
+/// fail instead of returning stale data.
+pub struct MockFirmware {
+    inner: &'static mut Inner,
+}
+
+impl MockFirmware {
+    /// Creates the firmware, with an image handle and a console handle
+    /// carrying the standard input and output protocols.
+    #[must_use]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        boot::reset();
+        runtime::reset();
+
+        let vendor = "uefi-rs mock firmware"
+            .encode_utf16()
+            .chain([0])
+            .collect::<Vec<u16>>();
+        let inner = Box::leak(Box::new(Inner {
+            table: raw::SystemTable {
+                header: Header {
+                    signature: SystemTable::<Boot>::SIGNATURE,
+                    revision: Revision::EFI_2_70,
+                    size: mem::size_of::<raw::SystemTable>() as u32,
+                    crc: 0,
+                    reserved: 0,
+                },
+                firmware_vendor: ptr::null(),
+                firmware_revision: 0x0001_0000,
+                stdin_handle: None,
+                stdin: ptr::null_mut(),
+                stdout_handle: None,
+                stdout: ptr::null_mut(),
+                stderr_handle: None,
+                stderr: ptr::null_mut(),
+                runtime_services: ptr::null_mut(),
+                boot_services: ptr::null_mut(),
+                number_of_configuration_table_entries: 0,
+                configuration_table: ptr::null_mut(),
+            },
+            boot: boot::table(),
+            runtime: runtime::table(),
+            vendor,
+            stdin: MockInput::new(),
+            stdout: MockOutput::new(),
+            stderr: MockOutput::new(),
+            file_systems: Vec::new(),
+            block_devices: Vec::new(),
+            image_handle: boot::create_handle(),
+        }));
+
+        let console = boot::create_handle();
+        boot::install(Some(console), &Input::GUID, inner.stdin.as_raw().cast()).unwrap();
+        boot::install(Some(console), &Output::GUID, inner.stdout.as_raw().cast()).unwrap();
+
+        let table = &mut inner.table;
+        table.firmware_vendor = inner.vendor.as_ptr().cast();
+        table.stdin_handle = Some(console);
+        table.stdin = inner.stdin.as_raw();
+        table.stdout_handle = Some(console);
+        table.stdout = inner.stdout.as_raw();
+        table.stderr_handle = Some(console);
+        table.stderr = inner.stderr.as_raw();
+        table.runtime_services = &mut inner.runtime;
+        table.boot_services = &mut inner.boot;
+        update_crc(table);
+        update_crc(&mut inner.boot);
+        update_crc(&mut inner.runtime);
+
+        let firmware = Self { inner };
+        unsafe {
+            firmware
+                .system_table()
+                .boot_services()
+                .set_image_handle(firmware.inner.image_handle);
+        }
+        firmware
+    }
+
+    /// Returns a system table backed by this firmware.
+    ///
+    /// The table only works on the thread that created the firmware.
+    #[must_use]
+    pub fn system_table(&self) -> SystemTable<Boot> {
+        let table: *const raw::SystemTable = &self.inner.table;
+        unsafe { SystemTable::from_ptr(table as *mut c_void) }.unwrap()
+    }
+
+    /// Returns the handle of the image under test, which is also set as the
+    /// global image handle.
+    #[must_use]
+    pub fn image_handle(&self) -> Handle {
+        self.inner.image_handle
+    }
+
+    /// Returns the standard input device.
+    pub fn stdin(&mut self) -> &mut MockInput {
+        &mut self.inner.stdin
+    }
+
+    /// Returns the standard output device.
+    pub fn stdout(&mut self) -> &mut MockOutput {
+        &mut self.inner.stdout
+    }
+
+    /// Returns the standard error device.
+    pub fn stderr(&mut self) -> &mut MockOutput {
+        &mut self.inner.stderr
+    }
+
+    /// Creates a new handle with no protocols installed on it.
+    pub fn create_handle(&mut self) -> Handle {
+        boot::create_handle()
+    }
+
+    /// Installs a protocol interface on `handle`, or on a new handle if
+    /// `handle` is `None`, and returns the handle.
+    ///
+    /// # Safety
+    ///
+    /// `interface` must point to a valid instance of `P` that outlives the
+    /// firmware.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `handle` is unknown or already has the protocol installed.
+    pub unsafe fn install_protocol<P: Protocol>(
+        &mut self,
+        handle: Option<Handle>,
+        interface: *mut P,
+    ) -> Handle {
+        boot::install(handle, &P::GUID, interface.cast()).expect("failed to install protocol")
+    }
+
+    /// Installs a `SimpleFileSystem` protocol backed by `fs` on a new
+    /// handle, and returns the handle.
+    pub fn install_file_system(&mut self, fs: &MockFileSystem) -> Handle {
+        let mut instance = FileSystemInstance::new(fs.clone());
+        let handle = boot::install(None, &SimpleFileSystem::GUID, instance.as_raw().cast())
+            .expect("failed to install protocol");
+        self.inner.file_systems.push(instance);
+        handle
+    }
+
+    /// Installs a `BlockIO` protocol backed by `device` on a new handle,
+    /// and returns the handle.
+    pub fn install_block_device(&mut self, device: &MockBlockDevice) -> Handle {
+        let mut instance = BlockDeviceInstance::new(device.clone());
+        let handle = boot::install(None, &BlockIO::GUID, instance.as_raw().cast())
+            .expect("failed to install protocol");
+        self.inner.block_devices.push(instance);
+        handle
+    }
+}
+
+impl Drop for MockFirmware {
+    fn drop(&mut self) {
+        boot::reset();
+        runtime::reset();
+    }
+}
+
+/// Sets the CRC in the header of a table, which must begin with a
+/// [`Header`], as the firmware does after changing the table.
+fn update_crc<T>(table: &mut T) {
+    let header = (table as *mut T).cast::<Header>();
+    unsafe {
+        (*header).crc = 0;
+        let bytes = slice::from_raw_parts(header.cast::<u8>(), mem::size_of::<T>());
+        (*header).crc = crc32(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::console::text::{RawKey, ScanCode};
+    use crate::proto::media::file::{File, FileAttribute, FileMode, FileType};
+    use crate::report::Format;
+    use crate::table::boot::{
+        BootServices, MemoryType, OpenProtocolAttributes, OpenProtocolParams, SearchType,
+    };
+    use crate::table::TableErrorKind;
+    use crate::{cstr16, Char16, Status};
+    use core::fmt::Write;
+
+    #[test]
+    fn test_console() {
+        let mut firmware = MockFirmware::new();
+        let mut st = firmware.system_table();
+
+        writeln!(st.stdout(), "hello").unwrap();
+        st.stdout().set_cursor_position(3, 4).unwrap();
+        assert_eq!(firmware.stdout().text(), "hello\r\n");
+        assert_eq!(firmware.stdout().cursor_position(), (3, 4));
+
+        firmware.stdin().push_str("a");
+        firmware.stdin().push_key(RawKey {
+            scan_code: ScanCode::ESCAPE,
+            unicode_char: Char16::try_from('\0').unwrap(),
+        });
+        let bt = st.boot_services();
+        let mut events = [unsafe { firmware.stdin().input().wait_for_key_event().unsafe_clone() }];
+        assert_eq!(bt.wait_for_event(&mut events).unwrap(), 0);
+        assert_eq!(firmware.stdin().pending_keys(), 2);
+
+        firmware.system_table().stdin().reset(false).unwrap();
+        assert_eq!(firmware.stdin().pending_keys(), 0);
+        assert!(!bt.check_event(unsafe { events[0].unsafe_clone() }).unwrap());
+    }
+
+    #[test]
+    fn test_validate_tables() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        assert_eq!(st.validate(), Ok(()));
+
+        let table: *const raw::SystemTable = &firmware.inner.table;
+        let table = table as *mut c_void;
+        assert!(unsafe { SystemTable::<Boot>::from_ptr_validated(table) }.is_ok());
+        let error = unsafe { SystemTable::<Boot>::from_ptr_validated(ptr::null_mut()) }
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), TableErrorKind::Null);
+
+        firmware.inner.boot.header.revision = Revision::EFI_2_80;
+        let error = st.validate().unwrap_err();
+        assert_eq!(error.table(), BootServices::SIGNATURE);
+        assert!(matches!(error.kind(), TableErrorKind::Crc { .. }));
+    }
+
+    #[test]
+    fn test_dump_handle() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let handle = bt
+            .locate_handle_buffer(SearchType::from_proto::<Output>())
+            .unwrap()[0];
+
+        let stdout = bt.open_protocol_exclusive::<Output>(handle).unwrap();
+        let dump = bt.dump_handle(handle).unwrap();
+        let protocol = dump.protocol::<Output>().unwrap();
+        assert_eq!(protocol.name, Some("EFI_SIMPLE_TEXT_OUTPUT_PROTOCOL"));
+        assert_eq!(protocol.open_info.len(), 1);
+        assert_eq!(protocol.open_info[0].attributes, 0x20);
+        assert_eq!(protocol.open_info[0].open_count, 1);
+        let mut s = alloc::string::String::new();
+        dump.write_with_format(&mut s, Format::KeyValue).unwrap();
+        assert!(s.contains(
+            "record=protocol guid=387477c2-69c7-11d2-8e39-00a0c969723b \
+             name=EFI_SIMPLE_TEXT_OUTPUT_PROTOCOL opens=1\n"
+        ));
+        assert!(s.contains(" attributes=0x20 open_count=1\n"));
+
+        drop(stdout);
+        let dump = bt.dump_handle(handle).unwrap();
+        assert!(dump.protocol::<Output>().unwrap().open_info.is_empty());
+    }
+
+    #[test]
+    fn test_dump_memory_map() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+
+        let dump = bt.dump_memory_map().unwrap();
+        assert_eq!(dump.descriptors.len(), 2);
+        assert_eq!(dump.descriptors[1].ty, MemoryType::CONVENTIONAL);
+        assert_eq!(dump.descriptors[1].page_count, 256);
+        let mut s = alloc::string::String::new();
+        dump.write_with_format(&mut s, Format::KeyValue).unwrap();
+        assert!(s.starts_with("record=memory_map entries=2 pages=272\n"));
+        assert!(s.contains(
+            "record=memory type=CONVENTIONAL phys_start=0x200000 virt_start=0x0 \
+             pages=256 attributes=0x8\n"
+        ));
+    }
+
+    #[test]
+    fn test_protocols() {
+        let mut firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+
+        let handles = bt
+            .locate_handle_buffer(SearchType::from_proto::<Output>())
+            .unwrap();
+        assert_eq!(handles.len(), 1);
+
+        let stdout = bt.open_protocol_exclusive::<Output>(handles[0]).unwrap();
+        assert_eq!(
+            bt.open_protocol_exclusive::<Output>(handles[0])
+                .err()
+                .map(|err| err.status()),
+            Some(Status::ACCESS_DENIED)
+        );
+        drop(stdout);
+        assert!(bt.open_protocol_exclusive::<Output>(handles[0]).is_ok());
+
+        let mut output = MockOutput::new();
+        let handle = unsafe { firmware.install_protocol(None, output.output()) };
+        assert_eq!(bt.protocols_per_handle(handle).unwrap().len(), 1);
+        let params = OpenProtocolParams {
+            handle,
+            agent: firmware.image_handle(),
+            controller: None,
+        };
+        let mut opened = unsafe {
+            bt.open_protocol::<Output>(params, OpenProtocolAttributes::GetProtocol)
+                .unwrap()
+        };
+        opened.output_string(cstr16!("x")).unwrap();
+        assert_eq!(output.text(), "x");
+    }
+
+    #[test]
+    fn test_event_groups() {
+        use crate::table::boot::{EventGroup, EventType, Tpl};
+        use alloc::rc::Rc;
+        use core::cell::Cell;
+
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+
+        let count = Rc::new(Cell::new(0));
+        let subscribe = |group| {
+            let count = count.clone();
+            bt.subscribe_event_group(group, move || count.set(count.get() + 1))
+                .unwrap()
+        };
+        let first = subscribe(EventGroup::READY_TO_BOOT);
+        let _second = subscribe(EventGroup::READY_TO_BOOT);
+        let other = subscribe(EventGroup::MEMORY_MAP_CHANGE);
+
+        // Signaling one event of a group signals all of them.
+        bt.signal_event(&first).unwrap();
+        assert_eq!(count.get(), 2);
+        bt.signal_event(&other).unwrap();
+        assert_eq!(count.get(), 3);
+
+        bt.close_event(first).unwrap();
+        bt.signal_event(&other).unwrap();
+        assert_eq!(count.get(), 4);
+
+        // The legacy exit boot services event type can't join a group.
+        let result = unsafe {
+            bt.create_event_ex(
+                EventType::SIGNAL_EXIT_BOOT_SERVICES,
+                Tpl::NOTIFY,
+                None,
+                None,
+                Some(EventGroup::EXIT_BOOT_SERVICES),
+            )
+        };
+        assert_eq!(
+            result.err().map(|err| err.status()),
+            Some(Status::INVALID_PARAMETER)
+        );
+    }
+
+    #[test]
+    fn test_watchdog_extended() {
+        use core::time::Duration;
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+
+        let events = boot::event_count();
+        bt.set_watchdog_timer(60, 0x10001, None).unwrap();
+        bt.with_watchdog_extended(Duration::from_secs(600), || {
+            assert_eq!(boot::watchdog(), (600, 0x10001));
+            assert_eq!(boot::event_count(), events + 1);
+
+            // A nested call can't shorten the enclosing extension.
+            bt.with_watchdog_extended(Duration::from_secs(30), || {
+                assert_eq!(boot::watchdog(), (600, 0x10001));
+            })
+            .unwrap();
+            bt.with_watchdog_extended(Duration::from_secs(1200), || {
+                assert_eq!(boot::watchdog(), (1200, 0x10001));
+                assert_eq!(boot::event_count(), events + 2);
+            })
+            .unwrap();
+            assert_eq!(boot::watchdog(), (600, 0x10001));
+            assert_eq!(boot::event_count(), events + 1);
+        })
+        .unwrap();
+        assert_eq!(boot::watchdog(), (60, 0x10001));
+        assert_eq!(boot::event_count(), events);
+
+        // The watchdog is restored when `f` panics.
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            bt.with_watchdog_extended(Duration::from_secs(600), || panic!("oops"))
+        }));
+        assert!(result.is_err());
+        assert_eq!(boot::watchdog(), (60, 0x10001));
+        assert_eq!(boot::event_count(), events);
+    }
+
+    #[test]
+    fn test_variables() {
+        use crate::table::runtime::{VariableAttributes, VariableVendor};
+
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let rt = st.runtime_services();
+        let vendor = VariableVendor::GLOBAL_VARIABLE;
+        let attrs = VariableAttributes::BOOTSERVICE_ACCESS;
+
+        rt.set_variable(cstr16!("A"), &vendor, attrs, b"1").unwrap();
+        rt.set_variable(cstr16!("B"), &vendor, attrs, b"22")
+            .unwrap();
+        rt.set_variable(
+            cstr16!("B"),
+            &vendor,
+            attrs | VariableAttributes::APPEND_WRITE,
+            b"3",
+        )
+        .unwrap();
+
+        assert_eq!(rt.get_variable_size(cstr16!("B"), &vendor).unwrap(), 3);
+        let mut buf = [0; 3];
+        let (data, attributes) = rt.get_variable(cstr16!("B"), &vendor, &mut buf).unwrap();
+        assert_eq!(data, b"223");
+        assert_eq!(attributes, attrs);
+
+        let keys = rt.variable_keys().unwrap();
+        assert_eq!(keys.len(), 2);
+
+        rt.set_variable(cstr16!("A"), &vendor, attrs, &[]).unwrap();
+        assert_eq!(
+            rt.get_variable_size(cstr16!("A"), &vendor)
+                .unwrap_err()
+                .status(),
+            Status::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn test_file_system() {
+        let mut firmware = MockFirmware::new();
+        let fs = MockFileSystem::new("MOCK");
+        fs.add_file("efi/boot/config.txt", b"key=value");
+        let handle = firmware.install_file_system(&fs);
+
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut sfs = bt
+            .open_protocol_exclusive::<SimpleFileSystem>(handle)
+            .unwrap();
+        let mut root = sfs.open_volume().unwrap();
+
+        let file = root
+            .open(
+                cstr16!("\\EFI\\BOOT\\config.txt"),
+                FileMode::Read,
+                FileAttribute::empty(),
+            )
+            .unwrap();
+        let mut file = match file.into_type().unwrap() {
+            FileType::Regular(file) => file,
+            FileType::Dir(_) => panic!("expected a regular file"),
+        };
+        let mut buf = [0; 16];
+        let len = file.read(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"key=value");
+
+        let mut new = root
+            .open(
+                cstr16!("efi\\new.txt"),
+                FileMode::CreateReadWrite,
+                FileAttribute::empty(),
+            )
+            .unwrap()
+            .into_regular_file()
+            .unwrap();
+        new.write(b"written").unwrap();
+        new.close();
+        assert_eq!(fs.read_file("efi/new.txt").unwrap(), b"written");
+
+        let mut efi = root
+            .open(cstr16!("efi"), FileMode::Read, FileAttribute::empty())
+            .unwrap()
+            .into_directory()
+            .unwrap();
+        let mut names = Vec::new();
+        let mut info_buf = [0u64; 32];
+        let info_buf =
+            unsafe { core::slice::from_raw_parts_mut(info_buf.as_mut_ptr().cast::<u8>(), 256) };
+        while let Some(info) = efi.read_entry(info_buf).unwrap() {
+            names.push(alloc::string::ToString::to_string(info.file_name()));
+        }
+        assert_eq!(names, ["boot", "new.txt"]);
+    }
+
+    #[test]
+    fn test_block_device() {
+        let mut firmware = MockFirmware::new();
+        let device = MockBlockDevice::new(512, 4);
+        device.write(512, b"block 1");
+        let handle = firmware.install_block_device(&device);
+
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut block_io = bt.open_protocol_exclusive::<BlockIO>(handle).unwrap();
+        let media_id = block_io.media().media_id();
+        assert_eq!(block_io.media().block_size(), 512);
+        assert_eq!(block_io.media().last_block(), 3);
+
+        let mut buf = [0; 1024];
+        block_io.read_blocks(media_id, 1, &mut buf).unwrap();
+        assert_eq!(&buf[..7], b"block 1");
+        assert_eq!(
+            block_io
+                .read_blocks(media_id, 3, &mut buf)
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+        assert_eq!(
+            block_io
+                .read_blocks(media_id, 0, &mut buf[..100])
+                .unwrap_err()
+                .status(),
+            Status::BAD_BUFFER_SIZE
+        );
+
+        block_io.write_blocks(media_id, 2, &[7; 512]).unwrap();
+        assert_eq!(device.contents()[1024..1536], [7; 512]);
+        assert_eq!(
+            block_io
+                .write_blocks(media_id + 1, 2, &[7; 512])
+                .unwrap_err()
+                .status(),
+            Status::MEDIA_CHANGED
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! Mock runtime services backed by an in-memory variable store.
+
+use crate::raw::table::runtime as raw;
+use crate::table::boot::{MemoryDescriptor, MEMORY_DESCRIPTOR_VERSION};
+use crate::table::runtime::{
+    Daylight, RuntimeServices, Time, TimeCapabilities, TimeParams, VariableAttributes,
+};
+use crate::table::{Header, Revision, Table};
+use crate::{Char16, Guid, Status};
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::ffi::c_void;
+use core::{mem, ptr, slice};
+
+/// Total size reported by `query_variable_info`.
+const STORAGE_SIZE: u64 = 64 * 1024;
+
+/// Maximum size of a single variable reported by `query_variable_info`.
+const MAX_VARIABLE_SIZE: u64 = 32 * 1024;
+
+struct Variable {
+    /// Null-terminated UCS-2 name.
+    name: Vec<u16>,
+    vendor: Guid,
+    attributes: VariableAttributes,
+    data: Vec<u8>,
+}
+
+impl Variable {
+    fn matches(&self, name: &[u16], vendor: &Guid) -> bool {
+        self.name == name && self.vendor == *vendor
+    }
+}
+
+std::thread_local! {
+    static VARIABLES: RefCell<Vec<Variable>> = const { RefCell::new(Vec::new()) };
+    static TIME: Cell<Time> = Cell::new(default_time());
+    static WAKEUP: Cell<Option<Time>> = const { Cell::new(None) };
+    static VIRTUAL_MAP: RefCell<Option<Vec<MemoryDescriptor>>> = const { RefCell::new(None) };
+}
+
+fn default_time() -> Time {
+    Time::new(TimeParams {
+        year: 2000,
+        month: 1,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0,
+        nanosecond: 0,
+        time_zone: None,
+        daylight: Daylight::empty(),
+    })
+    .unwrap()
+}
+
+/// Removes all variables of the current thread, resets the clock and
+/// disables the wakeup alarm, and returns to physical mode.
+pub(super) fn reset() {
+    VARIABLES.with(|vars| vars.borrow_mut().clear());
+    TIME.with(|time| time.set(default_time()));
+    WAKEUP.with(|wakeup| wakeup.set(None));
+    VIRTUAL_MAP.with(|map| map.borrow_mut().take());
+}
+
+/// Returns the null-terminated name pointed to by `name`, including the
+/// terminator.
+unsafe fn name_with_nul<'a>(name: *const u16) -> &'a [u16] {
+    let mut len = 0;
+    while *name.add(len) != 0 {
+        len += 1;
+    }
+    slice::from_raw_parts(name, len + 1)
+}
+
+/// Builds a runtime services table whose functions operate on the variable
+/// store of the current thread.
+pub(super) fn table() -> raw::RuntimeServices {
+    raw::RuntimeServices {
+        header: Header {
+            signature: RuntimeServices::SIGNATURE,
+            revision: Revision::EFI_2_70,
+            size: mem::size_of::<raw::RuntimeServices>() as u32,
+            crc: 0,
+            reserved: 0,
+        },
+        get_time,
+        set_time,
+        get_wakeup_time,
+        set_wakeup_time,
+        set_virtual_address_map,
+        convert_pointer,
+        get_variable,
+        get_next_variable_name,
+        set_variable,
+        get_next_high_monotonic_count: 0,
+        reset_system,
+        update_capsule: 0,
+        query_capsule_capabilities: 0,
+        query_variable_info,
+    }
+}
+
+unsafe extern "efiapi" fn get_time(
+    time: *mut raw::Time,
+    capabilities: *mut TimeCapabilities,
+) -> Status {
+    if time.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    time.cast::<Time>().write(TIME.with(Cell::get));
+    if !capabilities.is_null() {
+        capabilities.write(TimeCapabilities {
+            resolution: 1,
+            accuracy: 50_000_000,
+            sets_to_zero: false,
+        });
+    }
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn set_time(time: *const raw::Time) -> Status {
+    if time.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    TIME.with(|t| t.set(time.cast::<Time>().read()));
+    Status::SUCCESS
+}
+
+/// The mock alarm never fires, so it is never pending.
+unsafe extern "efiapi" fn get_wakeup_time(
+    enabled: *mut bool,
+    pending: *mut bool,
+    time: *mut raw::Time,
+) -> Status {
+    if enabled.is_null() || pending.is_null() || time.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    let wakeup = WAKEUP.with(Cell::get);
+    enabled.write(wakeup.is_some());
+    pending.write(false);
+    time.cast::<Time>()
+        .write(wakeup.unwrap_or_else(default_time));
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn set_wakeup_time(enable: bool, time: *const raw::Time) -> Status {
+    if enable {
+        let time = time.cast::<Time>();
+        if time.is_null() || !(*time).is_valid() {
+            return Status::INVALID_PARAMETER;
+        }
+        WAKEUP.with(|wakeup| wakeup.set(Some(*time)));
+    } else {
+        WAKEUP.with(|wakeup| wakeup.set(None));
+    }
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn set_virtual_address_map(
+    map_size: usize,
+    desc_size: usize,
+    desc_version: u32,
+    virtual_map: *mut MemoryDescriptor,
+) -> Status {
+    if desc_size < mem::size_of::<MemoryDescriptor>()
+        || desc_version != MEMORY_DESCRIPTOR_VERSION
+        || virtual_map.is_null()
+    {
+        return Status::INVALID_PARAMETER;
+    }
+    VIRTUAL_MAP.with(|map| {
+        let mut map = map.borrow_mut();
+        if map.is_some() {
+            return Status::UNSUPPORTED;
+        }
+        let descriptors = (0..map_size / desc_size)
+            .map(|i| {
+                virtual_map
+                    .cast::<u8>()
+                    .add(i * desc_size)
+                    .cast::<MemoryDescriptor>()
+                    .read_unaligned()
+            })
+            .collect();
+        *map = Some(descriptors);
+        Status::SUCCESS
+    })
+}
+
+/// Converts pointers with the map set by `set_virtual_address_map`. Unlike
+/// real firmware, this works at any time after the map is set.
+unsafe extern "efiapi" fn convert_pointer(
+    debug_disposition: usize,
+    address: *mut *const c_void,
+) -> Status {
+    if address.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    let phys = *address as u64;
+    if phys == 0 {
+        return if debug_disposition & 1 != 0 {
+            Status::SUCCESS
+        } else {
+            Status::INVALID_PARAMETER
+        };
+    }
+    VIRTUAL_MAP.with(|map| {
+        let map = map.borrow();
+        let map = match map.as_ref() {
+            Some(map) => map,
+            None => return Status::UNSUPPORTED,
+        };
+        let desc = map.iter().find(|desc| {
+            phys >= desc.phys_start && phys - desc.phys_start < desc.page_count * 4096
+        });
+        match desc {
+            Some(desc) => {
+                *address = (desc.virt_start + (phys - desc.phys_start)) as *const c_void;
+                Status::SUCCESS
+            }
+            None => Status::NOT_FOUND,
+        }
+    })
+}
+
+unsafe extern "efiapi" fn get_variable(
+    variable_name: *const Char16,
+    vendor_guid: *const Guid,
+    attributes: *mut u32,
+    data_size: *mut usize,
+    data: *mut u8,
+) -> Status {
+    if variable_name.is_null() || vendor_guid.is_null() || data_size.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    let name = name_with_nul(variable_name.cast());
+    VARIABLES.with(|vars| {
+        let vars = vars.borrow();
+        let var = match vars.iter().find(|var| var.matches(name, &*vendor_guid)) {
+            Some(var) => var,
+            None => return Status::NOT_FOUND,
+        };
+        if !attributes.is_null() {
+            attributes.write(var.attributes.bits());
+        }
+        let size = var.data.len();
+        if *data_size < size {
+            *data_size = size;
+            return Status::BUFFER_TOO_SMALL;
+        }
+        *data_size = size;
+        ptr::copy_nonoverlapping(var.data.as_ptr(), data, size);
+        Status::SUCCESS
+    })
+}
+
+unsafe extern "efiapi" fn get_next_variable_name(
+    variable_name_size: *mut usize,
+    variable_name: *mut u16,
+    vendor_guid: *mut Guid,
+) -> Status {
+    if variable_name_size.is_null() || variable_name.is_null() || vendor_guid.is_null() {
+        return Status::INVALID_PARAMETER;
+    }
+    let name = name_with_nul(variable_name);
+    VARIABLES.with(|vars| {
+        let vars = vars.borrow();
+        let next = if name.len() == 1 {
+            0
+        } else {
+            match vars.iter().position(|var| var.matches(name, &*vendor_guid)) {
+                Some(index) => index + 1,
+                None => return Status::INVALID_PARAMETER,
+            }
+        };
+        let var = match vars.get(next) {
+            Some(var) => var,
+            None => return Status::NOT_FOUND,
+        };
+        let size = mem::size_of_val(var.name.as_slice());
+        if *variable_name_size < size {
+            *variable_name_size = size;
+            return Status::BUFFER_TOO_SMALL;
+        }
+        *variable_name_size = size;
+        ptr::copy_nonoverlapping(var.name.as_ptr(), variable_name, var.name.len());
+        vendor_guid.write(var.vendor);
+        Status::SUCCESS
+    })
+}
+
+unsafe extern "efiapi" fn set_variable(
+    variable_name: *const Char16,
+    vendor_guid: *const Guid,
+    attributes: u32,
+    data_size: usize,
+    data: *const u8,
+) -> Status {
+    let attributes = VariableAttributes::from_bits_truncate(attributes);
+    if variable_name.is_null() || vendor_guid.is_null() || (data_size != 0 && data.is_null()) {
+        return Status::INVALID_PARAMETER;
+    }
+    let name = name_with_nul(variable_name.cast());
+    if name.len() == 1 {
+        return Status::INVALID_PARAMETER;
+    }
+    let vendor = *vendor_guid;
+    let data = if data_size == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, data_size)
+    };
+    let append = attributes.contains(VariableAttributes::APPEND_WRITE);
+    VARIABLES.with(|vars| {
+        let mut vars = vars.borrow_mut();
+        let index = vars.iter().position(|var| var.matches(name, &vendor));
+        match index {
+            Some(index) if append => {
+                vars[index].data.extend_from_slice(data);
+                Status::SUCCESS
+            }
+            Some(index) if data.is_empty() => {
+                vars.remove(index);
+                Status::SUCCESS
+            }
+            Some(index) => {
+                let var = &mut vars[index];
+                var.attributes = attributes;
+                var.data = data.to_vec();
+                Status::SUCCESS
+            }
+            None if data.is_empty() => {
+                if append {
+                    Status::SUCCESS
+                } else {
+                    Status::NOT_FOUND
+                }
+            }
+            None => {
+                vars.push(Variable {
+                    name: name.to_vec(),
+                    vendor,
+                    attributes: attributes - VariableAttributes::APPEND_WRITE,
+                    data: data.to_vec(),
+                });
+                Status::SUCCESS
+            }
+        }
+    })
+}
+
+unsafe extern "efiapi" fn reset_system(
+    rt: u32,
+    status: Status,
+    _data_size: usize,
+    _data: *const u8,
+) -> ! {
+    panic!("mock firmware: system reset (type {rt}) with status {status:?}");
+}
+
+unsafe extern "efiapi" fn query_variable_info(
+    _attributes: u32,
+    maximum_variable_storage_size: *mut u64,
+    remaining_variable_storage_size: *mut u64,
+    maximum_variable_size: *mut u64,
+) -> Status {
+    let used: usize = VARIABLES.with(|vars| {
+        vars.borrow()
+            .iter()
+            .map(|var| mem::size_of_val(var.name.as_slice()) + var.data.len())
+            .sum()
+    });
+    maximum_variable_storage_size.write(STORAGE_SIZE);
+    remaining_variable_storage_size.write(STORAGE_SIZE.saturating_sub(used as u64));
+    maximum_variable_size.write(MAX_VARIABLE_SIZE);
+    Status::SUCCESS
+}
@@ -0,0 +1,398 @@
+//! Inspection of loaded PE/COFF images.
+//!
+//! UEFI images are PE/COFF files. When an image is loaded, its headers and
+//! sections are copied to memory at the offsets given by their relative
+//! virtual addresses (RVAs), and the image is relocated to the address it
+//! was loaded at. [`PeImage`] parses the headers of such an image, which
+//! lets an application find its own entry point and section layout, and
+//! convert between addresses and RVAs. This is useful, for example, to
+//! measure or patch the application's own code.
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::proto::loaded_image::LoadedImage;
+//!
+//! fn log_sections(image: Handle, bt: &BootServices) -> uefi::Result {
+//!     let loaded_image = bt.open_protocol_exclusive::<LoadedImage>(image)?;
+//!     let pe = loaded_image.pe_image().ok_or(Status::LOAD_ERROR)?;
+//!     for section in pe.sections() {
+//!         log::info!(
+//!             "{:?} at {:?}, {} bytes",
+//!             section.name(),
+//!             pe.rva_to_address(section.virtual_address()),
+//!             section.virtual_size()
+//!         );
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use bitflags::bitflags;
+use core::{fmt, str};
+
+/// Offset of the `e_lfanew` field of the DOS header.
+const DOS_LFANEW_OFFSET: usize = 0x3c;
+
+/// Size of the PE signature and the COFF file header.
+const NT_HEADERS_SIZE: usize = 24;
+
+/// `Magic` of a PE32 optional header.
+const PE32_MAGIC: u16 = 0x10b;
+
+/// `Magic` of a PE32+ optional header.
+const PE32_PLUS_MAGIC: u16 = 0x20b;
+
+/// Size of a section header.
+const SECTION_HEADER_SIZE: usize = 40;
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset.checked_add(2)?)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// The headers of a PE/COFF image loaded in memory.
+#[derive(Clone, Copy)]
+pub struct PeImage<'a> {
+    bytes: &'a [u8],
+    machine: u16,
+    entry_point: u32,
+    size_of_image: u32,
+    section_headers: &'a [u8],
+}
+
+impl<'a> PeImage<'a> {
+    /// Parse the headers of the image loaded in `bytes`, which must start
+    /// at the base of the image. Returns `None` if the headers are not
+    /// valid.
+    #[must_use]
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.get(..2)? != b"MZ" {
+            return None;
+        }
+        let nt_offset = usize::try_from(read_u32(bytes, DOS_LFANEW_OFFSET)?).ok()?;
+        if bytes.get(nt_offset..nt_offset.checked_add(4)?)? != b"PE\0\0" {
+            return None;
+        }
+        let machine = read_u16(bytes, nt_offset + 4)?;
+        let section_count = usize::from(read_u16(bytes, nt_offset + 6)?);
+        let optional_header_size = usize::from(read_u16(bytes, nt_offset + 20)?);
+
+        let optional_header = nt_offset + NT_HEADERS_SIZE;
+        if !matches!(
+            read_u16(bytes, optional_header)?,
+            PE32_MAGIC | PE32_PLUS_MAGIC
+        ) {
+            return None;
+        }
+        let entry_point = read_u32(bytes, optional_header + 16)?;
+        let size_of_image = read_u32(bytes, optional_header + 56)?;
+
+        let section_offset = optional_header + optional_header_size;
+        let section_headers =
+            bytes.get(section_offset..section_offset + section_count * SECTION_HEADER_SIZE)?;
+
+        Some(Self {
+            bytes,
+            machine,
+            entry_point,
+            size_of_image,
+            section_headers,
+        })
+    }
+
+    /// Get the bytes of the image.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get the address the image is loaded at.
+    #[must_use]
+    pub const fn base(&self) -> *const u8 {
+        self.bytes.as_ptr()
+    }
+
+    /// Get the `IMAGE_FILE_MACHINE_*` type of the image, e.g. `0x8664` for
+    /// x86_64 or `0xaa64` for AArch64.
+    #[must_use]
+    pub const fn machine(&self) -> u16 {
+        self.machine
+    }
+
+    /// Get the size of the image in memory, as given by its headers.
+    #[must_use]
+    pub const fn size_of_image(&self) -> u32 {
+        self.size_of_image
+    }
+
+    /// Get the RVA of the entry point.
+    #[must_use]
+    pub const fn entry_point_rva(&self) -> u32 {
+        self.entry_point
+    }
+
+    /// Get the address of the entry point.
+    #[must_use]
+    pub fn entry_point(&self) -> Option<*const u8> {
+        self.rva_to_address(self.entry_point)
+    }
+
+    /// Get an iterator over the sections of the image.
+    #[must_use]
+    pub fn sections(&self) -> Sections<'a> {
+        Sections {
+            bytes: self.bytes,
+            headers: self.section_headers,
+        }
+    }
+
+    /// Get the section containing `rva`, or `None` if `rva` is not in a
+    /// section, e.g. because it points to the headers.
+    #[must_use]
+    pub fn section_containing(&self, rva: u32) -> Option<Section<'a>> {
+        self.sections().find(|section| section.contains(rva))
+    }
+
+    /// Convert `rva` to an address in the loaded image. Returns `None` if
+    /// `rva` is outside the image.
+    #[must_use]
+    pub fn rva_to_address(&self, rva: u32) -> Option<*const u8> {
+        let offset = usize::try_from(rva).ok()?;
+        (offset < self.bytes.len()).then(|| self.bytes[offset..].as_ptr())
+    }
+
+    /// Convert `address` to an RVA. Returns `None` if `address` is outside
+    /// the image.
+    #[must_use]
+    pub fn address_to_rva<T>(&self, address: *const T) -> Option<u32> {
+        let offset = (address as usize).checked_sub(self.base() as usize)?;
+        if offset >= self.bytes.len() {
+            return None;
+        }
+        u32::try_from(offset).ok()
+    }
+}
+
+impl fmt::Debug for PeImage<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PeImage")
+            .field("base", &self.base())
+            .field("machine", &self.machine)
+            .field("size_of_image", &self.size_of_image)
+            .field("entry_point_rva", &self.entry_point)
+            .field("sections", &self.sections())
+            .finish()
+    }
+}
+
+bitflags! {
+    /// Characteristics of a [`Section`].
+    #[repr(transparent)]
+    pub struct SectionFlags: u32 {
+        /// The section contains executable code.
+        const CODE = 0x20;
+        /// The section contains initialized data.
+        const INITIALIZED_DATA = 0x40;
+        /// The section contains uninitialized data.
+        const UNINITIALIZED_DATA = 0x80;
+        /// The section can be discarded as needed.
+        const DISCARDABLE = 0x0200_0000;
+        /// The section can be executed.
+        const EXECUTE = 0x2000_0000;
+        /// The section can be read.
+        const READ = 0x4000_0000;
+        /// The section can be written to.
+        const WRITE = 0x8000_0000;
+    }
+}
+
+/// A section of a [`PeImage`].
+#[derive(Clone, Copy)]
+pub struct Section<'a> {
+    bytes: &'a [u8],
+    header: &'a [u8],
+}
+
+impl<'a> Section<'a> {
+    /// Get the name of the section, e.g. `.text`. Returns `None` if the
+    /// name is not valid UTF-8.
+    #[must_use]
+    pub fn name(&self) -> Option<&'a str> {
+        let name = &self.header[..8];
+        let len = name.iter().position(|b| *b == 0).unwrap_or(name.len());
+        str::from_utf8(&name[..len]).ok()
+    }
+
+    /// Get the size of the section in memory.
+    #[must_use]
+    pub fn virtual_size(&self) -> u32 {
+        read_u32(self.header, 8).unwrap()
+    }
+
+    /// Get the RVA of the start of the section.
+    #[must_use]
+    pub fn virtual_address(&self) -> u32 {
+        read_u32(self.header, 12).unwrap()
+    }
+
+    /// Get the characteristics of the section.
+    #[must_use]
+    pub fn flags(&self) -> SectionFlags {
+        SectionFlags::from_bits_truncate(read_u32(self.header, 36).unwrap())
+    }
+
+    /// Check whether the section contains `rva`.
+    #[must_use]
+    pub fn contains(&self, rva: u32) -> bool {
+        let start = self.virtual_address();
+        rva >= start && rva - start < self.virtual_size()
+    }
+
+    /// Get the bytes of the section in memory. Returns `None` if the section
+    /// extends past the end of the image.
+    #[must_use]
+    pub fn data(&self) -> Option<&'a [u8]> {
+        let start = usize::try_from(self.virtual_address()).ok()?;
+        let size = usize::try_from(self.virtual_size()).ok()?;
+        self.bytes.get(start..start.checked_add(size)?)
+    }
+}
+
+impl fmt::Debug for Section<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Section")
+            .field("name", &self.name())
+            .field("virtual_address", &self.virtual_address())
+            .field("virtual_size", &self.virtual_size())
+            .field("flags", &self.flags())
+            .finish()
+    }
+}
+
+/// Iterator over the sections of an image, returned by
+/// [`PeImage::sections`].
+#[derive(Clone)]
+pub struct Sections<'a> {
+    bytes: &'a [u8],
+    /// Section headers that have not been returned yet.
+    headers: &'a [u8],
+}
+
+impl<'a> Iterator for Sections<'a> {
+    type Item = Section<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.headers.len() < SECTION_HEADER_SIZE {
+            return None;
+        }
+        let (header, rest) = self.headers.split_at(SECTION_HEADER_SIZE);
+        self.headers = rest;
+        Some(Section {
+            bytes: self.bytes,
+            header,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.headers.len() / SECTION_HEADER_SIZE;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Sections<'_> {}
+
+impl fmt::Debug for Sections<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a loaded PE32+ image with a `.text` section at 0x100 and a
+    /// `.data` section at 0x180.
+    fn image() -> [u8; 0x200] {
+        let mut image = [0; 0x200];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3c..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+
+        let nt = 0x40;
+        image[nt..nt + 4].copy_from_slice(b"PE\0\0");
+        image[nt + 4..nt + 6].copy_from_slice(&0x8664u16.to_le_bytes());
+        image[nt + 6..nt + 8].copy_from_slice(&2u16.to_le_bytes());
+        image[nt + 20..nt + 22].copy_from_slice(&0x70u16.to_le_bytes());
+
+        let opt = nt + NT_HEADERS_SIZE;
+        image[opt..opt + 2].copy_from_slice(&PE32_PLUS_MAGIC.to_le_bytes());
+        image[opt + 16..opt + 20].copy_from_slice(&0x110u32.to_le_bytes());
+        image[opt + 56..opt + 60].copy_from_slice(&0x200u32.to_le_bytes());
+
+        let sections = opt + 0x70;
+        let mut add_section = |index: usize, name: &[u8], rva: u32, size: u32, flags: u32| {
+            let header = sections + index * SECTION_HEADER_SIZE;
+            image[header..header + name.len()].copy_from_slice(name);
+            image[header + 8..header + 12].copy_from_slice(&size.to_le_bytes());
+            image[header + 12..header + 16].copy_from_slice(&rva.to_le_bytes());
+            image[header + 36..header + 40].copy_from_slice(&flags.to_le_bytes());
+        };
+        add_section(0, b".text", 0x100, 0x80, 0x6000_0020);
+        add_section(1, b".data", 0x180, 0x80, 0xc000_0040);
+        image[0x180] = 0xaa;
+        image
+    }
+
+    #[test]
+    fn test_pe_image() {
+        let bytes = image();
+        let pe = PeImage::new(&bytes).unwrap();
+        assert_eq!(pe.machine(), 0x8664);
+        assert_eq!(pe.size_of_image(), 0x200);
+        assert_eq!(pe.entry_point_rva(), 0x110);
+        assert_eq!(pe.entry_point(), Some(bytes[0x110..].as_ptr()));
+
+        let mut sections = pe.sections();
+        assert_eq!(sections.len(), 2);
+        let text = sections.next().unwrap();
+        assert_eq!(text.name(), Some(".text"));
+        assert_eq!(text.virtual_address(), 0x100);
+        assert_eq!(text.virtual_size(), 0x80);
+        assert_eq!(
+            text.flags(),
+            SectionFlags::CODE | SectionFlags::EXECUTE | SectionFlags::READ
+        );
+        let data = sections.next().unwrap();
+        assert_eq!(data.name(), Some(".data"));
+        assert_eq!(data.data().unwrap()[0], 0xaa);
+        assert!(sections.next().is_none());
+
+        assert_eq!(pe.section_containing(0x17f).unwrap().name(), Some(".text"));
+        assert_eq!(pe.section_containing(0x180).unwrap().name(), Some(".data"));
+        assert!(pe.section_containing(0x10).is_none());
+    }
+
+    #[test]
+    fn test_address_conversion() {
+        let bytes = image();
+        let pe = PeImage::new(&bytes).unwrap();
+        assert_eq!(pe.address_to_rva(&bytes[0x123]), Some(0x123));
+        assert_eq!(pe.address_to_rva(pe.base().wrapping_add(0x200)), None);
+        assert_eq!(pe.address_to_rva(pe.base().wrapping_sub(1)), None);
+        assert_eq!(pe.rva_to_address(0x1ff), Some(&bytes[0x1ff] as *const u8));
+        assert_eq!(pe.rva_to_address(0x200), None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        let mut bytes = image();
+        assert!(PeImage::new(&bytes[..0x100]).is_none());
+        bytes[0x40] = b'X';
+        assert!(PeImage::new(&bytes).is_none());
+    }
+}
@@ -0,0 +1,562 @@
+//! Lightweight timing of boot phases.
+//!
+//! [`scope`] starts a named span, which ends when the returned [`Scope`]
+//! is dropped. Spans can be nested, e.g. to time the steps of loading a
+//! kernel. The recorded spans can then be written as a table with
+//! [`write`], logged when the application exits with `log_at_exit` (with
+//! the `alloc` feature), or encoded as records for the Firmware Basic Boot
+//! Performance Table with [`write_fbpt_records`].
+//!
+//! The timestamps come from the processor's counter (the TSC on x86, the
+//! generic timer on AArch64), not from the timestamp protocol, so spans
+//! can be recorded before any protocol is opened and after boot services
+//! are exited. They are converted to nanoseconds since the counter was
+//! reset, like the timestamps of the FBPT. On x86, the counter frequency
+//! is measured by [`init`], which must be called before the spans are
+//! read. Other architectures have no counter, and their spans are empty.
+//!
+//! At most [`MAX_SPANS`] spans are recorded; later spans are dropped and
+//! counted by [`dropped`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::perf;
+//! use uefi::prelude::*;
+//!
+//! fn boot(st: &mut SystemTable<Boot>) -> uefi::Result {
+//!     perf::init(st.boot_services());
+//!     {
+//!         let _span = perf::scope("load_kernel");
+//!         // ...
+//!     }
+//!     perf::write(st.stdout()).unwrap();
+//!     Ok(())
+//! }
+//! ```
+
+use crate::report::{self, Format, Record};
+use crate::table::boot::BootServices;
+use crate::{Guid, Status};
+use core::fmt::{self, Write};
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+
+/// Maximum number of spans recorded.
+pub const MAX_SPANS: usize = 64;
+
+/// Type of the FBPT record written for the start and end of a span, a
+/// dynamic string event record as defined by EDK2.
+const DYNAMIC_STRING_EVENT: u16 = 0x1011;
+
+/// EDK2 progress ID of the start of a span in a module.
+const INMODULE_START_ID: u16 = 0x40;
+
+/// EDK2 progress ID of the end of a span in a module.
+const INMODULE_END_ID: u16 = 0x41;
+
+/// Size of a dynamic string event record, without the string.
+const EVENT_RECORD_HEADER_LEN: usize = 34;
+
+/// Frequency of the counter in Hz, or zero if unknown.
+static FREQUENCY: AtomicU64 = AtomicU64::new(0);
+
+/// The spans recorded with [`scope`].
+///
+/// UEFI applications are single-threaded, and spans are not recorded from
+/// event notification functions, so there is no concurrent access.
+static mut RECORDER: Recorder = Recorder::new();
+
+/// Reads the processor's counter.
+fn counter() -> u64 {
+    #[cfg(target_arch = "x86")]
+    #[allow(unused_unsafe)]
+    return unsafe { core::arch::x86::_rdtsc() };
+
+    #[cfg(target_arch = "x86_64")]
+    #[allow(unused_unsafe)]
+    return unsafe { core::arch::x86_64::_rdtsc() };
+
+    #[cfg(target_arch = "aarch64")]
+    {
+        let ticks: u64;
+        unsafe {
+            core::arch::asm!("mrs {}, cntvct_el0", out(reg) ticks, options(nomem, nostack, preserves_flags));
+        }
+        ticks
+    }
+
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    0
+}
+
+/// Determines the frequency of the counter, used to convert the recorded
+/// timestamps to nanoseconds.
+///
+/// On x86, the counter is measured during a 1 ms [`BootServices::stall`].
+/// Calling this again measures the frequency again. On AArch64, the
+/// frequency is read from the processor, and this doesn't stall.
+pub fn init(bt: &BootServices) {
+    #[cfg(target_arch = "aarch64")]
+    let frequency = {
+        let _ = bt;
+        let frequency: u64;
+        unsafe {
+            core::arch::asm!("mrs {}, cntfrq_el0", out(reg) frequency, options(nomem, nostack, preserves_flags));
+        }
+        frequency
+    };
+
+    #[cfg(not(target_arch = "aarch64"))]
+    let frequency = {
+        let start = counter();
+        bt.stall(1000);
+        counter().wrapping_sub(start) * 1000
+    };
+
+    FREQUENCY.store(frequency, Ordering::Relaxed);
+}
+
+/// Converts counter `ticks` to nanoseconds with `frequency` in Hz, or
+/// returns 0 if the frequency is unknown.
+fn ticks_to_nanos(ticks: u64, frequency: u64) -> u64 {
+    if frequency == 0 {
+        0
+    } else {
+        u64::try_from(u128::from(ticks) * 1_000_000_000 / u128::from(frequency)).unwrap_or(u64::MAX)
+    }
+}
+
+/// Starts a span named `name`, which ends when the returned [`Scope`] is
+/// dropped. Spans started while this one is running are nested in it.
+pub fn scope(name: &'static str) -> Scope {
+    let recorder = unsafe { &mut *ptr::addr_of_mut!(RECORDER) };
+    Scope {
+        index: recorder.begin(name, counter()),
+        generation: recorder.generation,
+    }
+}
+
+/// A running span, created by [`scope`]. The span ends when this is
+/// dropped, or with [`end`].
+///
+/// [`end`]: Self::end
+#[derive(Debug)]
+#[must_use = "the span ends when the scope is dropped"]
+pub struct Scope {
+    index: Option<usize>,
+    generation: u32,
+}
+
+impl Scope {
+    /// Ends the span, like dropping the scope.
+    pub fn end(self) {}
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        unsafe { (*ptr::addr_of_mut!(RECORDER)).end(self.index, self.generation, counter()) }
+    }
+}
+
+/// A recorded span.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Span {
+    /// Name passed to [`scope`].
+    pub name: &'static str,
+    /// Number of spans this one is nested in.
+    pub depth: u8,
+    /// When the span started, in nanoseconds since the counter was reset.
+    pub start: u64,
+    /// When the span ended, in nanoseconds since the counter was reset,
+    /// or `None` if it is still running.
+    pub end: Option<u64>,
+}
+
+impl Span {
+    /// Returns how long the span ran, or `None` if it is still running.
+    #[must_use]
+    pub fn duration(&self) -> Option<Duration> {
+        Some(Duration::from_nanos(self.end?.saturating_sub(self.start)))
+    }
+}
+
+/// Returns the recorded spans, in the order they started.
+pub fn spans() -> impl Iterator<Item = Span> {
+    let frequency = FREQUENCY.load(Ordering::Relaxed);
+    let recorder = unsafe { &*ptr::addr_of!(RECORDER) };
+    (0..recorder.len).map(move |i| recorder.span(i, frequency))
+}
+
+/// Returns the number of spans that were dropped because [`MAX_SPANS`]
+/// spans were already recorded.
+#[must_use]
+pub fn dropped() -> usize {
+    unsafe { (*ptr::addr_of!(RECORDER)).dropped }
+}
+
+/// Forgets all the recorded spans. Running scopes are not recorded when
+/// they end.
+pub fn reset() {
+    unsafe { (*ptr::addr_of_mut!(RECORDER)).reset() }
+}
+
+/// Writes the recorded spans to `writer` in the crate-wide
+/// [`report::format`], as one `perf_span` record per span.
+pub fn write<W: Write>(writer: &mut W) -> fmt::Result {
+    write_with_format(writer, report::format())
+}
+
+/// Writes the recorded spans to `writer` in the given `format`.
+pub fn write_with_format<W: Write>(writer: &mut W, format: Format) -> fmt::Result {
+    for span in spans() {
+        write_span(writer, format, &span)?;
+    }
+    let dropped = dropped();
+    if dropped != 0 {
+        Record::with_format(writer, format, "perf_dropped")?
+            .field("spans", dropped)?
+            .finish()?;
+    }
+    Ok(())
+}
+
+fn write_span<W: Write>(writer: &mut W, format: Format, span: &Span) -> fmt::Result {
+    let record = Record::with_format(writer, format, "perf_span")?
+        .field("name", span.name)?
+        .field("depth", span.depth)?
+        .field("start_us", span.start / 1000)?;
+    match span.duration() {
+        Some(duration) => record.field("duration_us", duration.as_micros())?,
+        None => record.field("duration_us", "running")?,
+    }
+    .finish()
+}
+
+/// Logs the recorded spans at the `info` level when the application exits,
+/// with [`cleanup::on_exit`].
+///
+/// [`cleanup::on_exit`]: crate::cleanup::on_exit
+///
+/// # Errors
+///
+/// See [`cleanup::on_exit`].
+#[cfg(feature = "alloc")]
+pub fn log_at_exit() -> crate::Result {
+    crate::cleanup::on_exit(|| {
+        let mut table = alloc::string::String::new();
+        if write(&mut table).is_ok() {
+            for line in table.lines() {
+                log::info!("{line}");
+            }
+        }
+    })
+}
+
+/// Writes the recorded spans to `buffer` as FBPT records, and returns the
+/// number of bytes written.
+///
+/// Each span is written as two EDK2 dynamic string event records, for its
+/// start and its end, with the name of the span and `guid`, which should
+/// identify the image. Running spans only have a start record. Names that
+/// don't fit in a record are truncated.
+///
+/// # Errors
+///
+/// * [`uefi::Status::BUFFER_TOO_SMALL`]: `buffer` is too small. The
+///   required size is returned in the error data.
+pub fn write_fbpt_records(guid: &Guid, buffer: &mut [u8]) -> crate::Result<usize, Option<usize>> {
+    let frequency = FREQUENCY.load(Ordering::Relaxed);
+    unsafe { (*ptr::addr_of!(RECORDER)).write_fbpt_records(guid, frequency, buffer) }
+}
+
+/// A span as recorded, with timestamps in counter ticks.
+#[derive(Clone, Copy)]
+struct RawSpan {
+    name: &'static str,
+    depth: u8,
+    start: u64,
+    end: Option<u64>,
+}
+
+/// Storage of the recorded spans.
+struct Recorder {
+    spans: [RawSpan; MAX_SPANS],
+    len: usize,
+    dropped: usize,
+    /// Number of running scopes.
+    depth: u8,
+    /// Incremented by [`reset`](Self::reset), so that scopes started before
+    /// are ignored when they end.
+    generation: u32,
+}
+
+impl Recorder {
+    const fn new() -> Self {
+        const EMPTY: RawSpan = RawSpan {
+            name: "",
+            depth: 0,
+            start: 0,
+            end: None,
+        };
+        Self {
+            spans: [EMPTY; MAX_SPANS],
+            len: 0,
+            dropped: 0,
+            depth: 0,
+            generation: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self {
+            generation: self.generation.wrapping_add(1),
+            ..Self::new()
+        };
+    }
+
+    /// Records the start of a span, and returns its index, or `None` if it
+    /// was dropped.
+    fn begin(&mut self, name: &'static str, ticks: u64) -> Option<usize> {
+        let depth = self.depth;
+        self.depth = self.depth.saturating_add(1);
+        if self.len == MAX_SPANS {
+            self.dropped += 1;
+            return None;
+        }
+        self.spans[self.len] = RawSpan {
+            name,
+            depth,
+            start: ticks,
+            end: None,
+        };
+        self.len += 1;
+        Some(self.len - 1)
+    }
+
+    /// Records the end of the span at `index`, which is `None` if the span
+    /// was dropped but still counts towards the depth.
+    fn end(&mut self, index: Option<usize>, generation: u32, ticks: u64) {
+        if generation != self.generation {
+            return;
+        }
+        self.depth = self.depth.saturating_sub(1);
+        if let Some(index) = index {
+            self.spans[index].end = Some(ticks);
+        }
+    }
+
+    fn span(&self, index: usize, frequency: u64) -> Span {
+        let span = &self.spans[index];
+        Span {
+            name: span.name,
+            depth: span.depth,
+            start: ticks_to_nanos(span.start, frequency),
+            end: span.end.map(|end| ticks_to_nanos(end, frequency)),
+        }
+    }
+
+    fn write_fbpt_records(
+        &self,
+        guid: &Guid,
+        frequency: u64,
+        buffer: &mut [u8],
+    ) -> crate::Result<usize, Option<usize>> {
+        let required: usize = (0..self.len)
+            .map(|i| {
+                let span = self.span(i, frequency);
+                let records = if span.end.is_some() { 2 } else { 1 };
+                records * event_record_len(span.name)
+            })
+            .sum();
+        if buffer.len() < required {
+            return Err(crate::Error::new(Status::BUFFER_TOO_SMALL, Some(required)));
+        }
+
+        let mut offset = 0;
+        for i in 0..self.len {
+            let span = self.span(i, frequency);
+            let mut events = [(INMODULE_START_ID, span.start), (INMODULE_END_ID, 0)];
+            let count = match span.end {
+                Some(end) => {
+                    events[1].1 = end;
+                    2
+                }
+                None => 1,
+            };
+            for (progress_id, timestamp) in &events[..count] {
+                offset += write_event_record(
+                    &mut buffer[offset..],
+                    *progress_id,
+                    *timestamp,
+                    guid,
+                    span.name,
+                );
+            }
+        }
+        Ok(offset)
+    }
+}
+
+/// Returns the length of the event record for a span named `name`: the
+/// name is null-terminated, and truncated so that the length fits in the
+/// one-byte length field of the record.
+fn event_record_len(name: &str) -> usize {
+    EVENT_RECORD_HEADER_LEN
+        + name
+            .len()
+            .min(usize::from(u8::MAX) - EVENT_RECORD_HEADER_LEN - 1)
+        + 1
+}
+
+/// Writes a dynamic string event record to the start of `buffer`, which
+/// must be large enough, and returns its length.
+fn write_event_record(
+    buffer: &mut [u8],
+    progress_id: u16,
+    timestamp: u64,
+    guid: &Guid,
+    name: &str,
+) -> usize {
+    let len = event_record_len(name);
+    let record = &mut buffer[..len];
+    record[0..2].copy_from_slice(&DYNAMIC_STRING_EVENT.to_le_bytes());
+    record[2] = len as u8;
+    // Revision.
+    record[3] = 1;
+    record[4..6].copy_from_slice(&progress_id.to_le_bytes());
+    // APIC ID of the processor.
+    record[6..10].fill(0);
+    record[10..18].copy_from_slice(&timestamp.to_le_bytes());
+    record[18..34].copy_from_slice(&guid.to_bytes());
+    let name_len = len - EVENT_RECORD_HEADER_LEN - 1;
+    record[34..34 + name_len].copy_from_slice(&name.as_bytes()[..name_len]);
+    record[len - 1] = 0;
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guid;
+    use crate::table::fpdt::PerformanceRecords;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    /// Records nested spans on a local recorder, with a 1 MHz counter.
+    fn recorder() -> Recorder {
+        let mut recorder = Recorder::new();
+        let outer = recorder.begin("load_kernel", 1_000);
+        let inner = recorder.begin("read_file", 1_500);
+        recorder.end(inner, 0, 3_500);
+        // Never ends.
+        recorder.begin("decompress", 4_000);
+        recorder.end(outer, 0, 5_000);
+        recorder
+    }
+
+    #[test]
+    fn test_recorder() {
+        let recorder = recorder();
+        let spans: Vec<_> = (0..recorder.len)
+            .map(|i| recorder.span(i, 1_000_000))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                Span {
+                    name: "load_kernel",
+                    depth: 0,
+                    start: 1_000_000,
+                    end: Some(5_000_000),
+                },
+                Span {
+                    name: "read_file",
+                    depth: 1,
+                    start: 1_500_000,
+                    end: Some(3_500_000),
+                },
+                Span {
+                    name: "decompress",
+                    depth: 1,
+                    start: 4_000_000,
+                    end: None,
+                },
+            ]
+        );
+        assert_eq!(spans[1].duration(), Some(Duration::from_millis(2)));
+        assert_eq!(spans[2].duration(), None);
+
+        let mut line = String::new();
+        write_span(&mut line, Format::KeyValue, &spans[1]).unwrap();
+        assert_eq!(
+            line,
+            "record=perf_span name=read_file depth=1 start_us=1500 duration_us=2000\n"
+        );
+    }
+
+    #[test]
+    fn test_recorder_full() {
+        let mut recorder = Recorder::new();
+        let scopes: Vec<_> = (0..MAX_SPANS + 2)
+            .map(|i| recorder.begin("span", i as u64))
+            .collect();
+        assert_eq!(recorder.dropped, 2);
+        for index in scopes.into_iter().rev() {
+            recorder.end(index, 0, 100);
+        }
+        assert_eq!(recorder.depth, 0);
+
+        // Scopes started before a reset are ignored.
+        let index = recorder.begin("old", 0);
+        recorder.reset();
+        recorder.begin("new", 1);
+        recorder.end(index, 0, 2);
+        assert_eq!(recorder.depth, 1);
+        assert_eq!(recorder.spans[0].end, None);
+    }
+
+    #[test]
+    fn test_fbpt_records() {
+        let recorder = recorder();
+        let guid = guid!("01234567-89ab-cdef-0123-456789abcdef");
+        let mut buffer = [0; 256];
+        let err = recorder
+            .write_fbpt_records(&guid, 1_000_000, &mut buffer[..10])
+            .unwrap_err();
+        assert_eq!(err.status(), Status::BUFFER_TOO_SMALL);
+        let len = recorder
+            .write_fbpt_records(&guid, 1_000_000, &mut buffer)
+            .unwrap();
+        assert_eq!(Some(len), *err.data());
+
+        let records: Vec<_> = PerformanceRecords::new(&buffer[..len]).collect();
+        assert_eq!(records.len(), 5);
+        assert!(records
+            .iter()
+            .all(|record| record.record_type() == DYNAMIC_STRING_EVENT));
+        let read_file = records[2].as_bytes();
+        assert_eq!(read_file.len(), EVENT_RECORD_HEADER_LEN + 10);
+        assert_eq!(read_file[4..6], INMODULE_START_ID.to_le_bytes());
+        assert_eq!(read_file[10..18], 1_500_000u64.to_le_bytes());
+        assert_eq!(read_file[18..34], guid.to_bytes());
+        assert_eq!(&read_file[34..], b"read_file\0");
+    }
+
+    #[test]
+    fn test_long_name() {
+        let name = "x".repeat(300);
+        assert_eq!(event_record_len(&name), 255);
+        let mut buffer = [0; 255];
+        write_event_record(
+            &mut buffer,
+            INMODULE_START_ID,
+            0,
+            &guid!("01234567-89ab-cdef-0123-456789abcdef"),
+            &name,
+        );
+        assert_eq!(buffer[2], 255);
+        assert_eq!(buffer[254], 0);
+    }
+}
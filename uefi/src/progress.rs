@@ -0,0 +1,327 @@
+//! Progress reporting for long operations.
+//!
+//! Operations that can take a while, such as copying a large file with
+//! [`RegularFile::copy_to`] or running a memory test with
+//! [`GenericMemoryTest::run_with_reporter`], report their progress to a
+//! [`ProgressReporter`]. The reporter can display the progress, and can
+//! cancel the operation.
+//!
+//! Two reporters are provided: [`TextProgressBar`] draws a bar with text
+//! characters, e.g. on the console, and [`GopProgressBar`] draws a bar on
+//! the screen with the graphics output protocol. Closures taking the
+//! completed and total amount of work can also be used as reporters.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::progress::TextProgressBar;
+//! use uefi::proto::media::file::RegularFile;
+//!
+//! fn copy(st: &mut SystemTable<Boot>, src: &mut RegularFile, dest: &mut RegularFile) -> uefi::Result {
+//!     let mut buffer = [0; 4096];
+//!     let mut progress = TextProgressBar::new(st.stdout(), "Copying");
+//!     src.copy_to(dest, &mut buffer, &mut progress)?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`RegularFile::copy_to`]: crate::proto::media::file::RegularFile::copy_to
+//! [`GenericMemoryTest::run_with_reporter`]: crate::proto::memory_test::GenericMemoryTest::run_with_reporter
+
+use crate::proto::console::gop::{BltOp, BltPixel, GraphicsOutput};
+use core::fmt::{self, Write};
+use core::ops::ControlFlow;
+
+/// Receives the progress of a long operation.
+pub trait ProgressReporter {
+    /// Called when the operation starts, before the first [`update`].
+    ///
+    /// [`update`]: Self::update
+    fn start(&mut self) {}
+
+    /// Called when `done` of `total` units of work are completed. The
+    /// unit depends on the operation, e.g. bytes for a file copy.
+    ///
+    /// Returning [`ControlFlow::Break`] asks the operation to stop. How
+    /// quickly it stops, and whether it reports an error, is documented
+    /// by each operation.
+    fn update(&mut self, done: u64, total: u64) -> ControlFlow<()>;
+
+    /// Called when the operation ends, whether it completed, failed or
+    /// was cancelled.
+    fn finish(&mut self) {}
+}
+
+impl<F: FnMut(u64, u64) -> ControlFlow<()>> ProgressReporter for F {
+    fn update(&mut self, done: u64, total: u64) -> ControlFlow<()> {
+        self(done, total)
+    }
+}
+
+/// Returns `done` as a percentage of `total`, between 0 and 100. An
+/// operation without work is complete.
+#[must_use]
+pub const fn percent(done: u64, total: u64) -> u8 {
+    if total == 0 || done >= total {
+        100
+    } else {
+        (done as u128 * 100 / total as u128) as u8
+    }
+}
+
+/// A progress bar drawn with text characters, e.g. on the console:
+///
+/// ```text
+/// Copying [##########          ]  50%
+/// ```
+///
+/// The bar is redrawn on the same line with a carriage return, only when
+/// the percentage changes, and the line is ended when the operation
+/// finishes. Errors of the writer are ignored, since they must not abort
+/// the operation.
+#[derive(Debug)]
+pub struct TextProgressBar<'a, W: Write> {
+    writer: W,
+    label: &'a str,
+    width: usize,
+    last: Option<u8>,
+}
+
+impl<'a, W: Write> TextProgressBar<'a, W> {
+    /// Creates a progress bar labelled `label`, that is 20 characters
+    /// wide.
+    #[must_use]
+    pub const fn new(writer: W, label: &'a str) -> Self {
+        Self {
+            writer,
+            label,
+            width: 20,
+            last: None,
+        }
+    }
+
+    /// Sets the width of the bar, in characters.
+    #[must_use]
+    pub const fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Returns the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn draw(&mut self, percent: u8) -> fmt::Result {
+        let filled = self.width * usize::from(percent) / 100;
+        write!(self.writer, "\r{} [", self.label)?;
+        for i in 0..self.width {
+            self.writer.write_char(if i < filled { '#' } else { ' ' })?;
+        }
+        write!(self.writer, "] {percent:3}%")
+    }
+}
+
+impl<W: Write> ProgressReporter for TextProgressBar<'_, W> {
+    fn start(&mut self) {
+        self.last = None;
+    }
+
+    fn update(&mut self, done: u64, total: u64) -> ControlFlow<()> {
+        let percent = percent(done, total);
+        if self.last != Some(percent) {
+            self.last = Some(percent);
+            let _ = self.draw(percent);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn finish(&mut self) {
+        if self.last.is_some() {
+            let _ = self.writer.write_str("\r\n");
+        }
+    }
+}
+
+/// A progress bar drawn on the screen with the graphics output protocol.
+///
+/// The bar is a filled rectangle on a background of another color. Only
+/// the newly completed part is drawn on each update. Errors of the
+/// protocol are ignored, since they must not abort the operation.
+pub struct GopProgressBar<'a, 'boot> {
+    gop: &'a mut GraphicsOutput<'boot>,
+    position: (usize, usize),
+    size: (usize, usize),
+    foreground: BltPixel,
+    background: BltPixel,
+    filled: Option<usize>,
+}
+
+impl<'a, 'boot> GopProgressBar<'a, 'boot> {
+    /// Creates a white progress bar on a dark gray background, with its
+    /// top left corner at `position`, and of the given `(width, height)`.
+    #[must_use]
+    pub fn new(
+        gop: &'a mut GraphicsOutput<'boot>,
+        position: (usize, usize),
+        size: (usize, usize),
+    ) -> Self {
+        Self {
+            gop,
+            position,
+            size,
+            foreground: BltPixel::new(255, 255, 255),
+            background: BltPixel::new(64, 64, 64),
+            filled: None,
+        }
+    }
+
+    /// Creates a progress bar centered horizontally near the bottom of the
+    /// screen, half as wide as the screen.
+    #[must_use]
+    pub fn centered(gop: &'a mut GraphicsOutput<'boot>) -> Self {
+        let (width, height) = gop.current_mode_info().resolution();
+        let size = (width / 2, 8.min(height));
+        let position = (width / 4, height.saturating_sub(height / 8 + size.1));
+        Self::new(gop, position, size)
+    }
+
+    /// Sets the colors of the completed part of the bar and of the
+    /// background.
+    #[must_use]
+    pub const fn with_colors(mut self, foreground: BltPixel, background: BltPixel) -> Self {
+        self.foreground = foreground;
+        self.background = background;
+        self
+    }
+
+    fn fill(&mut self, color: BltPixel, x: usize, width: usize) {
+        if width == 0 {
+            return;
+        }
+        let _ = self.gop.blt(BltOp::VideoFill {
+            color,
+            dest: (self.position.0 + x, self.position.1),
+            dims: (width, self.size.1),
+        });
+    }
+}
+
+impl fmt::Debug for GopProgressBar<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GopProgressBar")
+            .field("position", &self.position)
+            .field("size", &self.size)
+            .field("foreground", &self.foreground)
+            .field("background", &self.background)
+            .field("filled", &self.filled)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProgressReporter for GopProgressBar<'_, '_> {
+    fn start(&mut self) {
+        self.fill(self.background, 0, self.size.0);
+        self.filled = Some(0);
+    }
+
+    fn update(&mut self, done: u64, total: u64) -> ControlFlow<()> {
+        let filled = self.filled.unwrap_or_else(|| {
+            self.start();
+            0
+        });
+        let new = self.size.0 * usize::from(percent(done, total)) / 100;
+        if new > filled {
+            self.fill(self.foreground, filled, new - filled);
+            self.filled = Some(new);
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writer that only keeps the text after the last carriage return, and
+    /// counts the carriage returns and line feeds.
+    #[derive(Default)]
+    struct Line {
+        text: [u8; 32],
+        len: usize,
+        draws: usize,
+        lines: usize,
+    }
+
+    impl Line {
+        fn as_str(&self) -> &str {
+            core::str::from_utf8(&self.text[..self.len]).unwrap()
+        }
+    }
+
+    impl Write for &mut Line {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            for byte in s.bytes() {
+                match byte {
+                    b'\r' => {
+                        self.len = 0;
+                        self.draws += 1;
+                    }
+                    b'\n' => self.lines += 1,
+                    _ => {
+                        self.text[self.len] = byte;
+                        self.len += 1;
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_percent() {
+        assert_eq!(percent(0, 10), 0);
+        assert_eq!(percent(5, 10), 50);
+        assert_eq!(percent(u64::MAX - 1, u64::MAX), 99);
+        assert_eq!(percent(11, 10), 100);
+        assert_eq!(percent(0, 0), 100);
+    }
+
+    #[test]
+    fn test_text_progress_bar() {
+        let mut line = Line::default();
+        let mut bar = TextProgressBar::new(&mut line, "Copying").with_width(10);
+        bar.start();
+        assert!(bar.update(0, 4).is_continue());
+        assert!(bar.update(1, 4).is_continue());
+        // The same percentage is not redrawn.
+        assert!(bar.update(1, 4).is_continue());
+        let line = bar.into_inner();
+        assert_eq!(line.as_str(), "Copying [##        ]  25%");
+        assert_eq!(line.draws, 2);
+
+        // The line is only ended if the bar was drawn.
+        let mut bar = TextProgressBar::new(&mut *line, "Copying").with_width(10);
+        bar.finish();
+        assert!(bar.update(4, 4).is_continue());
+        bar.finish();
+        assert_eq!(line.lines, 1);
+    }
+
+    #[test]
+    fn test_closure_reporter() {
+        let mut calls = 0;
+        let mut reporter = |done, total| {
+            calls += 1;
+            if done < total {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        };
+        assert!(reporter.update(1, 2).is_continue());
+        assert!(reporter.update(2, 2).is_break());
+        assert_eq!(calls, 2);
+    }
+}
@@ -0,0 +1,139 @@
+//! Absolute pointer device access.
+
+use crate::proto::unsafe_protocol;
+use crate::{Event, Result, Status};
+use bitflags::bitflags;
+use core::mem::MaybeUninit;
+
+/// Provides information about an absolute pointer device, such as a touch
+/// screen or a digitizer.
+///
+/// Unlike the [`Pointer`] protocol, which reports relative movements, this
+/// protocol reports the absolute position of the pointer.
+///
+/// The corresponding C type is `EFI_ABSOLUTE_POINTER_PROTOCOL`.
+///
+/// [`Pointer`]: super::pointer::Pointer
+#[repr(C)]
+#[unsafe_protocol("8d59d32b-c655-4ae9-9b15-f25904992a43")]
+pub struct AbsolutePointer {
+    reset: extern "efiapi" fn(this: &mut AbsolutePointer, ext_verif: bool) -> Status,
+    get_state:
+        extern "efiapi" fn(this: &AbsolutePointer, state: *mut AbsolutePointerState) -> Status,
+    wait_for_input: Event,
+    mode: *const AbsolutePointerMode,
+}
+
+impl AbsolutePointer {
+    /// Resets the pointer device hardware.
+    ///
+    /// The `extended_verification` parameter is used to request that UEFI
+    /// performs an extended check and reset of the input device.
+    ///
+    /// # Errors
+    ///
+    /// - `DeviceError` if the device is malfunctioning and cannot be reset.
+    pub fn reset(&mut self, extended_verification: bool) -> Result {
+        trace_status!(
+            "AbsolutePointer::reset",
+            (self.reset)(self, extended_verification),
+            "extended_verification={}",
+            extended_verification
+        )
+        .into()
+    }
+
+    /// Retrieves the pointer device's current state, if a state change occured
+    /// since the last time this function was called.
+    ///
+    /// Use `wait_for_input_event()` with the `BootServices::wait_for_event()`
+    /// interface in order to wait for input from the pointer device.
+    ///
+    /// # Errors
+    /// - `DeviceError` if there was an issue with the pointer device.
+    pub fn read_state(&mut self) -> Result<Option<AbsolutePointerState>> {
+        let mut state = MaybeUninit::<AbsolutePointerState>::uninit();
+
+        match trace_status!(
+            "AbsolutePointer::get_state",
+            (self.get_state)(self, state.as_mut_ptr())
+        ) {
+            Status::NOT_READY => Ok(None),
+            other => other.into_with_val(|| unsafe { Some(state.assume_init()) }),
+        }
+    }
+
+    /// Event to be used with `BootServices::wait_for_event()` in order to wait
+    /// for input from the pointer device
+    #[must_use]
+    pub const fn wait_for_input_event(&self) -> &Event {
+        &self.wait_for_input
+    }
+
+    /// Returns a reference to the pointer device information.
+    #[must_use]
+    pub fn mode(&self) -> &AbsolutePointerMode {
+        unsafe { &*self.mode }
+    }
+}
+
+bitflags! {
+    /// Capabilities of an absolute pointer device.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct AbsolutePointerAttributes: u32 {
+        /// The device has an alternate button, such as the side button of a
+        /// pen.
+        const SUPPORTS_ALT_ACTIVE = 0x0000_0001;
+        /// The Z axis reports the pressure applied to the device.
+        const SUPPORTS_PRESSURE_AS_Z = 0x0000_0002;
+    }
+}
+
+bitflags! {
+    /// Buttons of an absolute pointer device that are active.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct ActiveButtons: u32 {
+        /// The device is touched, or the main button is pressed.
+        const TOUCH_ACTIVE = 0x0000_0001;
+        /// The alternate button is pressed.
+        const ALT_ACTIVE = 0x0000_0002;
+    }
+}
+
+/// Information about this absolute pointer device.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+pub struct AbsolutePointerMode {
+    /// The minimum value on the X axis.
+    pub absolute_min_x: u64,
+    /// The minimum value on the Y axis.
+    pub absolute_min_y: u64,
+    /// The minimum value on the Z axis.
+    pub absolute_min_z: u64,
+    /// The maximum value on the X axis, or 0 if the axis is not supported.
+    pub absolute_max_x: u64,
+    /// The maximum value on the Y axis, or 0 if the axis is not supported.
+    pub absolute_max_y: u64,
+    /// The maximum value on the Z axis, or 0 if the axis is not supported.
+    pub absolute_max_z: u64,
+    /// Capabilities of the device.
+    pub attributes: AbsolutePointerAttributes,
+}
+
+/// The current state of an absolute pointer device.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(C)]
+pub struct AbsolutePointerState {
+    /// The position on the X axis, between the minimum and maximum values
+    /// in [`AbsolutePointerMode`].
+    pub current_x: u64,
+    /// The position on the Y axis.
+    pub current_y: u64,
+    /// The position on the Z axis, or the pressure if the device has
+    /// [`AbsolutePointerAttributes::SUPPORTS_PRESSURE_AS_Z`].
+    pub current_z: u64,
+    /// The buttons that are active.
+    pub active_buttons: ActiveButtons,
+}
@@ -0,0 +1,426 @@
+//! Console configuration.
+//!
+//! The firmware connects the consoles listed in the `ConIn`, `ConOut` and
+//! `ErrOut` variables. Each variable holds a multi-instance device path,
+//! with an instance per device, such as a keyboard, a graphics output or a
+//! serial terminal. The devices which can be used as consoles are listed in
+//! the `ConInDev`, `ConOutDev` and `ErrOutDev` variables.
+//!
+//! When several devices are connected, the console splitter driver gives a
+//! single console to applications, which is forwarded to all the devices.
+//! [`ConsoleDevice`] tells whether a console is the splitter or a specific
+//! device.
+
+use crate::proto::device_path::DevicePath;
+use crate::table::boot::{
+    BootServices, OpenProtocolAttributes, OpenProtocolParams, ScopedProtocol,
+};
+use crate::table::{Boot, SystemTable};
+use crate::{cstr16, CStr16, Handle};
+use core::fmt;
+#[cfg(feature = "alloc")]
+use {
+    crate::proto::device_path::{DevicePathHeader, DevicePathNode, DeviceSubType, DeviceType},
+    crate::table::runtime::{RuntimeServices, VariableAttributes, VariableVendor},
+    crate::{Result, Status},
+    alloc::vec::Vec,
+    core::{mem, slice},
+};
+
+/// A console of the system.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ConsoleKind {
+    /// The standard input.
+    Input,
+    /// The standard output.
+    Output,
+    /// The standard error.
+    Error,
+}
+
+impl ConsoleKind {
+    /// Get the name of the variable holding the devices of the console:
+    /// `ConIn`, `ConOut` or `ErrOut`.
+    #[must_use]
+    pub fn variable_name(self) -> &'static CStr16 {
+        match self {
+            Self::Input => cstr16!("ConIn"),
+            Self::Output => cstr16!("ConOut"),
+            Self::Error => cstr16!("ErrOut"),
+        }
+    }
+
+    /// Get the name of the variable holding the devices which can be used
+    /// for the console: `ConInDev`, `ConOutDev` or `ErrOutDev`.
+    #[must_use]
+    pub fn available_variable_name(self) -> &'static CStr16 {
+        match self {
+            Self::Input => cstr16!("ConInDev"),
+            Self::Output => cstr16!("ConOutDev"),
+            Self::Error => cstr16!("ErrOutDev"),
+        }
+    }
+
+    /// Get the handle of the console, or `None` if there is no console.
+    #[must_use]
+    pub fn handle(self, st: &SystemTable<Boot>) -> Option<Handle> {
+        match self {
+            Self::Input => st.stdin_handle(),
+            Self::Output => st.stdout_handle(),
+            Self::Error => st.stderr_handle(),
+        }
+    }
+
+    /// Get the device of the console, or `None` if there is no console.
+    #[must_use]
+    pub fn device(self, st: &SystemTable<Boot>) -> Option<ConsoleDevice<'_>> {
+        let handle = self.handle(st)?;
+        Some(match console_device_path(st.boot_services(), handle) {
+            Some(device_path) => ConsoleDevice::Device(device_path),
+            None => ConsoleDevice::Splitter,
+        })
+    }
+}
+
+/// Get the device path of the console `handle`.
+fn console_device_path<'boot>(
+    bt: &'boot BootServices,
+    handle: Handle,
+) -> Option<ScopedProtocol<'boot, DevicePath>> {
+    // Safety: the device path is only read.
+    unsafe {
+        bt.open_protocol::<DevicePath>(
+            OpenProtocolParams {
+                handle,
+                agent: bt.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }
+    .ok()
+}
+
+/// The device behind a console, returned by [`ConsoleKind::device`].
+pub enum ConsoleDevice<'boot> {
+    /// The console splitter, forwarding the console to all the devices of
+    /// the console variable. Its handle has no device path.
+    Splitter,
+    /// A specific device.
+    Device(ScopedProtocol<'boot, DevicePath>),
+}
+
+impl ConsoleDevice<'_> {
+    /// Whether the console is the console splitter.
+    #[must_use]
+    pub const fn is_splitter(&self) -> bool {
+        matches!(self, Self::Splitter)
+    }
+
+    /// Get the device path of the device, or `None` for the console
+    /// splitter.
+    #[must_use]
+    pub fn device_path(&self) -> Option<&DevicePath> {
+        match self {
+            Self::Splitter => None,
+            Self::Device(device_path) => Some(device_path),
+        }
+    }
+}
+
+impl fmt::Debug for ConsoleDevice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Splitter => f.write_str("Splitter"),
+            Self::Device(device_path) => f.debug_tuple("Device").field(&&**device_path).finish(),
+        }
+    }
+}
+
+/// The device paths of a console variable, such as `ConOut`.
+///
+/// The device paths are stored as a multi-instance device path, with an
+/// instance per device. For example, to enable a serial console:
+///
+/// ```no_run
+/// use uefi::proto::console::config::{ConsoleDevicePaths, ConsoleKind};
+/// use uefi::proto::device_path::{DeviceSubType, DeviceType};
+/// # use uefi::table::runtime::RuntimeServices;
+/// # fn enable_serial_console(rt: &RuntimeServices) -> uefi::Result {
+/// let available = ConsoleDevicePaths::read_available(rt, ConsoleKind::Output)?;
+/// let mut console = ConsoleDevicePaths::read(rt, ConsoleKind::Output)?;
+/// for device in available.iter() {
+///     let is_serial = device.node_iter().any(|node| {
+///         node.full_type() == (DeviceType::MESSAGING, DeviceSubType::MESSAGING_UART)
+///     });
+///     if is_serial {
+///         console.add(device);
+///     }
+/// }
+/// console.write(rt, ConsoleKind::Output)
+/// # }
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct ConsoleDevicePaths {
+    /// The device paths of the devices, each ending with an end-entire
+    /// node.
+    instances: Vec<Vec<u8>>,
+}
+
+#[cfg(feature = "alloc")]
+impl ConsoleDevicePaths {
+    /// Create an empty list of device paths.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse the multi-instance device path `bytes`. Returns `None` if it
+    /// is not a valid device path.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut paths = Self::new();
+        if !bytes.is_empty() {
+            let device_path = DevicePath::try_from_bytes(bytes)?;
+            paths.instances = device_path
+                .instance_iter()
+                .map(|instance| single_instance(instance.node_iter()))
+                .collect();
+        }
+        Some(paths)
+    }
+
+    /// Read the devices of the console `kind`, from the `ConIn`, `ConOut` or
+    /// `ErrOut` variable. The list is empty if the variable does not exist.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::VOLUME_CORRUPTED`]: the variable is not a valid device
+    ///   path.
+    ///
+    /// See also [`RuntimeServices::get_variable_boxed`].
+    pub fn read(rt: &RuntimeServices, kind: ConsoleKind) -> Result<Self> {
+        Self::read_variable(rt, kind.variable_name())
+    }
+
+    /// Read the devices which can be used for the console `kind`, from the
+    /// `ConInDev`, `ConOutDev` or `ErrOutDev` variable.
+    ///
+    /// # Errors
+    ///
+    /// See [`read`](Self::read).
+    pub fn read_available(rt: &RuntimeServices, kind: ConsoleKind) -> Result<Self> {
+        Self::read_variable(rt, kind.available_variable_name())
+    }
+
+    fn read_variable(rt: &RuntimeServices, name: &CStr16) -> Result<Self> {
+        match rt.get_variable_boxed(name, &VariableVendor::GLOBAL_VARIABLE) {
+            Ok((data, _)) => Self::from_bytes(&data).ok_or_else(|| Status::VOLUME_CORRUPTED.into()),
+            Err(err) if err.status() == Status::NOT_FOUND => Ok(Self::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Write the devices of the console `kind` to the `ConIn`, `ConOut` or
+    /// `ErrOut` variable. The variable is deleted if there is no device.
+    ///
+    /// The firmware uses the new devices at the next boot.
+    ///
+    /// # Errors
+    ///
+    /// See [`RuntimeServices::set_variable`].
+    pub fn write(&self, rt: &RuntimeServices, kind: ConsoleKind) -> Result {
+        let attributes = VariableAttributes::NON_VOLATILE
+            | VariableAttributes::BOOTSERVICE_ACCESS
+            | VariableAttributes::RUNTIME_ACCESS;
+        let data = self.to_bytes();
+        match rt.set_variable(
+            kind.variable_name(),
+            &VariableVendor::GLOBAL_VARIABLE,
+            attributes,
+            &data,
+        ) {
+            // Deleting a missing variable is fine.
+            Err(err) if data.is_empty() && err.status() == Status::NOT_FOUND => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Get the multi-instance device path, or an empty vector if there is no
+    /// device.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for instance in &self.instances {
+            if !bytes.is_empty() {
+                // Turn the end-entire node of the previous instance into an
+                // end-instance node.
+                let len = bytes.len();
+                bytes[len - END_NODE_SIZE + 1] = DeviceSubType::END_INSTANCE.0;
+            }
+            bytes.extend_from_slice(instance);
+        }
+        bytes
+    }
+
+    /// Get the number of devices.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Whether there is no device.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    /// Get an iterator over the device paths of the devices.
+    pub fn iter(&self) -> impl Iterator<Item = &DevicePath> + '_ {
+        // OK to unwrap: the instances are valid device paths.
+        self.instances
+            .iter()
+            .map(|instance| DevicePath::try_from_bytes(instance).unwrap())
+    }
+
+    /// Whether `device_path` is one of the devices.
+    #[must_use]
+    pub fn contains(&self, device_path: &DevicePath) -> bool {
+        self.position(device_path).is_some()
+    }
+
+    /// Add `device_path` to the devices, unless it is already one of them.
+    /// Returns whether it was added.
+    ///
+    /// Only the first instance of a multi-instance `device_path` is added.
+    pub fn add(&mut self, device_path: &DevicePath) -> bool {
+        if self.contains(device_path) {
+            false
+        } else {
+            self.instances
+                .push(single_instance(device_path.node_iter()));
+            true
+        }
+    }
+
+    /// Remove `device_path` from the devices. Returns whether it was one of
+    /// them.
+    pub fn remove(&mut self, device_path: &DevicePath) -> bool {
+        match self.position(device_path) {
+            Some(index) => {
+                self.instances.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn position(&self, device_path: &DevicePath) -> Option<usize> {
+        let instance = single_instance(device_path.node_iter());
+        self.instances.iter().position(|other| *other == instance)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for ConsoleDevicePaths {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Size of an end node.
+#[cfg(feature = "alloc")]
+const END_NODE_SIZE: usize = mem::size_of::<DevicePathHeader>();
+
+/// Build a single-instance device path from `nodes`.
+#[cfg(feature = "alloc")]
+fn single_instance<'a>(nodes: impl Iterator<Item = &'a DevicePathNode>) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for node in nodes {
+        bytes.extend_from_slice(unsafe {
+            slice::from_raw_parts(node.as_ffi_ptr().cast::<u8>(), usize::from(node.length()))
+        });
+    }
+    bytes.extend_from_slice(&[
+        DeviceType::END.0,
+        DeviceSubType::END_ENTIRE.0,
+        END_NODE_SIZE as u8,
+        0,
+    ]);
+    bytes
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+
+    /// Build a device path with a single node of `data`.
+    fn device_path(data: u8) -> Vec<u8> {
+        let mut path = Vec::from([
+            DeviceType::HARDWARE.0,
+            DeviceSubType::HARDWARE_VENDOR.0,
+            5,
+            0,
+            data,
+        ]);
+        path.extend_from_slice(&[DeviceType::END.0, DeviceSubType::END_ENTIRE.0, 4, 0]);
+        path
+    }
+
+    #[test]
+    fn test_console_device() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        // The consoles of the mock firmware have no device path.
+        assert!(ConsoleKind::Output.device(&st).unwrap().is_splitter());
+        assert!(ConsoleKind::Input
+            .device(&st)
+            .unwrap()
+            .device_path()
+            .is_none());
+    }
+
+    #[test]
+    fn test_console_device_paths() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let rt = st.runtime_services();
+
+        let mut paths = ConsoleDevicePaths::read(rt, ConsoleKind::Output).unwrap();
+        assert!(paths.is_empty());
+        let (a, b, c) = (device_path(1), device_path(2), device_path(3));
+        let a = DevicePath::try_from_bytes(&a).unwrap();
+        let b = DevicePath::try_from_bytes(&b).unwrap();
+        let c = DevicePath::try_from_bytes(&c).unwrap();
+        assert!(paths.add(a));
+        assert!(paths.add(b));
+        assert!(!paths.add(a));
+        assert!(paths.add(c));
+        assert_eq!(paths.len(), 3);
+        paths.write(rt, ConsoleKind::Output).unwrap();
+
+        let (data, _) = rt
+            .get_variable_boxed(cstr16!("ConOut"), &VariableVendor::GLOBAL_VARIABLE)
+            .unwrap();
+        assert_eq!(data.len(), 3 * 9);
+        assert_eq!(data[5..9], [0x7f, 0x01, 4, 0]);
+        assert_eq!(data[23..27], [0x7f, 0xff, 4, 0]);
+
+        let mut paths = ConsoleDevicePaths::read(rt, ConsoleKind::Output).unwrap();
+        assert!(paths.iter().eq([a, b, c]));
+        assert!(paths.remove(b));
+        assert!(!paths.remove(b));
+        assert!(paths.contains(c));
+        assert!(paths.iter().eq([a, c]));
+
+        paths = ConsoleDevicePaths::new();
+        paths.write(rt, ConsoleKind::Output).unwrap();
+        paths.write(rt, ConsoleKind::Output).unwrap();
+        assert!(ConsoleDevicePaths::read(rt, ConsoleKind::Output)
+            .unwrap()
+            .is_empty());
+        assert!(ConsoleDevicePaths::from_bytes(&[1, 2, 3]).is_none());
+    }
+}
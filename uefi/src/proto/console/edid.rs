@@ -0,0 +1,210 @@
+//! EDID protocols.
+//!
+//! The Extended Display Identification Data (EDID) describes the
+//! capabilities of a display, such as its name and native resolution. It is
+//! found on the handles of the video outputs, next to their
+//! [`GraphicsOutput`] protocol.
+//!
+//! [`EdidDiscovered`] holds the EDID read from the display, and
+//! [`EdidActive`] the EDID actually used by the video driver, which may be
+//! overridden by the platform.
+//!
+//! [`GraphicsOutput`]: super::gop::GraphicsOutput
+
+use crate::proto::unsafe_protocol;
+use core::slice;
+
+/// The EDID discovered protocol, holding the EDID read from the display.
+///
+/// The corresponding C type is `EFI_EDID_DISCOVERED_PROTOCOL`.
+#[derive(Debug)]
+#[repr(C)]
+#[unsafe_protocol("1c0c34f6-d380-41fa-a049-8ad06c1a66aa")]
+pub struct EdidDiscovered {
+    size_of_edid: u32,
+    edid: *const u8,
+}
+
+impl EdidDiscovered {
+    /// Get the EDID, or `None` if it is missing or invalid.
+    #[must_use]
+    pub fn edid(&self) -> Option<Edid<'_>> {
+        unsafe { edid_from_raw(self.size_of_edid, self.edid) }
+    }
+}
+
+/// The EDID active protocol, holding the EDID used by the video driver.
+///
+/// The corresponding C type is `EFI_EDID_ACTIVE_PROTOCOL`.
+#[derive(Debug)]
+#[repr(C)]
+#[unsafe_protocol("bd8c1056-9f36-44ec-92a8-a6337f817986")]
+pub struct EdidActive {
+    size_of_edid: u32,
+    edid: *const u8,
+}
+
+impl EdidActive {
+    /// Get the EDID, or `None` if it is missing or invalid.
+    #[must_use]
+    pub fn edid(&self) -> Option<Edid<'_>> {
+        unsafe { edid_from_raw(self.size_of_edid, self.edid) }
+    }
+}
+
+unsafe fn edid_from_raw<'a>(size: u32, edid: *const u8) -> Option<Edid<'a>> {
+    if edid.is_null() {
+        None
+    } else {
+        Edid::new(slice::from_raw_parts(edid, size as usize))
+    }
+}
+
+/// An EDID, version 1.x.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Edid<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Edid<'a> {
+    /// Header of the base block.
+    pub const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+    /// Size of the base block and of each extension block.
+    pub const BLOCK_SIZE: usize = 128;
+
+    /// Offsets of the four descriptors of the base block.
+    const DESCRIPTORS: [usize; 4] = [54, 72, 90, 108];
+
+    /// Tag of the display name descriptor.
+    const DISPLAY_NAME_TAG: u8 = 0xfc;
+
+    /// Parse `data` as an EDID, checking the header and checksum of the
+    /// base block. Returns `None` if they are invalid.
+    #[must_use]
+    pub fn new(data: &'a [u8]) -> Option<Self> {
+        let base = data.get(..Self::BLOCK_SIZE)?;
+        let checksum = base.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        (base[..8] == Self::HEADER && checksum == 0).then_some(Self { data })
+    }
+
+    /// Get the raw EDID, including the extension blocks.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Get the three-letter PNP ID of the manufacturer, such as `b"DEL"`.
+    #[must_use]
+    pub fn manufacturer_id(&self) -> [u8; 3] {
+        let id = u16::from_be_bytes([self.data[8], self.data[9]]);
+        // Three 5-bit letters, 1 being 'A'.
+        let letter = |shift: u16| b'A' - 1 + ((id >> shift) & 0x1f) as u8;
+        [letter(10), letter(5), letter(0)]
+    }
+
+    /// Get the product code assigned by the manufacturer.
+    #[must_use]
+    pub fn product_code(&self) -> u16 {
+        u16::from_le_bytes([self.data[10], self.data[11]])
+    }
+
+    /// Get the serial number, or 0 if not set.
+    #[must_use]
+    pub fn serial_number(&self) -> u32 {
+        u32::from_le_bytes(self.data[12..16].try_into().unwrap())
+    }
+
+    /// Get the version of the EDID, as a `(version, revision)` pair.
+    #[must_use]
+    pub fn version(&self) -> (u8, u8) {
+        (self.data[18], self.data[19])
+    }
+
+    /// Get the number of extension blocks following the base block.
+    #[must_use]
+    pub fn extension_count(&self) -> u8 {
+        self.data[126]
+    }
+
+    /// Get the `(width, height)` native resolution of the display, from its
+    /// preferred timing. Returns `None` if there is no preferred timing.
+    #[must_use]
+    pub fn native_resolution(&self) -> Option<(usize, usize)> {
+        // The first descriptor is the preferred timing, unless its pixel
+        // clock is 0.
+        let timing = &self.data[Self::DESCRIPTORS[0]..Self::DESCRIPTORS[0] + 18];
+        if timing[0] == 0 && timing[1] == 0 {
+            return None;
+        }
+        let width = usize::from(timing[2]) | usize::from(timing[4] & 0xf0) << 4;
+        let height = usize::from(timing[5]) | usize::from(timing[7] & 0xf0) << 4;
+        Some((width, height))
+    }
+
+    /// Get the name of the display, as ASCII bytes. Returns `None` if there
+    /// is no display name descriptor.
+    #[must_use]
+    pub fn display_name(&self) -> Option<&'a [u8]> {
+        Self::DESCRIPTORS.iter().find_map(|&offset| {
+            let descriptor = &self.data[offset..offset + 18];
+            if descriptor[..3] != [0, 0, 0] || descriptor[3] != Self::DISPLAY_NAME_TAG {
+                return None;
+            }
+            // The name ends with a line feed, followed by spaces.
+            let name = &descriptor[5..];
+            let len = name.iter().position(|&c| c == b'\n').unwrap_or(name.len());
+            Some(&name[..len])
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build the base block of a 1920x1080 display named "TEST".
+    fn edid() -> [u8; 128] {
+        let mut edid = [0; 128];
+        edid[..8].copy_from_slice(&Edid::HEADER);
+        // "QEM"
+        edid[8..10].copy_from_slice(&0x44adu16.to_be_bytes());
+        edid[10..12].copy_from_slice(&0x1234u16.to_le_bytes());
+        edid[12..16].copy_from_slice(&42u32.to_le_bytes());
+        edid[18] = 1;
+        edid[19] = 4;
+        // Preferred timing: 148.5 MHz, 1920x1080.
+        edid[54..56].copy_from_slice(&14850u16.to_le_bytes());
+        edid[56] = 0x80;
+        edid[58] = 0x70;
+        edid[59] = 0x38;
+        edid[61] = 0x40;
+        // Display name.
+        edid[72..77].copy_from_slice(&[0, 0, 0, 0xfc, 0]);
+        edid[77..90].copy_from_slice(b"TEST\n        ");
+        let sum = edid.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        edid[127] = 0u8.wrapping_sub(sum);
+        edid
+    }
+
+    #[test]
+    fn test_edid() {
+        let data = edid();
+        let edid = Edid::new(&data).unwrap();
+        assert_eq!(edid.manufacturer_id(), *b"QEM");
+        assert_eq!(edid.product_code(), 0x1234);
+        assert_eq!(edid.serial_number(), 42);
+        assert_eq!(edid.version(), (1, 4));
+        assert_eq!(edid.extension_count(), 0);
+        assert_eq!(edid.native_resolution(), Some((1920, 1080)));
+        assert_eq!(edid.display_name(), Some(&b"TEST"[..]));
+    }
+
+    #[test]
+    fn test_invalid_edid() {
+        let mut data = edid();
+        assert!(Edid::new(&data[..127]).is_none());
+        data[100] ^= 1;
+        assert!(Edid::new(&data).is_none());
+    }
+}
@@ -0,0 +1,216 @@
+//! Unified input event stream.
+//!
+//! UI code such as a boot menu usually reads the keyboard and, if present,
+//! a mouse or a touch screen. Each of these is a separate protocol with its
+//! own read function and wait event. [`InputEvents`] merges them into a
+//! single stream of [`InputEvent`]s:
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::proto::console::events::{InputEvent, InputEvents};
+//! use uefi::proto::console::pointer::Pointer;
+//! use uefi::proto::console::text::Key;
+//!
+//! fn run_menu(st: &mut SystemTable<Boot>) -> uefi::Result {
+//!     let bt = unsafe { st.unsafe_clone() };
+//!     let bt = bt.boot_services();
+//!     let mut pointer = bt
+//!         .get_handle_for_protocol::<Pointer>()
+//!         .and_then(|handle| bt.open_protocol_exclusive::<Pointer>(handle))
+//!         .ok();
+//!
+//!     let mut events = InputEvents::new().with_keyboard(st.stdin());
+//!     if let Some(pointer) = pointer.as_deref_mut() {
+//!         events = events.with_pointer(pointer);
+//!     }
+//!
+//!     loop {
+//!         match events.wait(bt)? {
+//!             InputEvent::Key(Key::Printable(c)) if u16::from(c) == 0x0d => return Ok(()),
+//!             InputEvent::Pointer(state) if state.button.0 => return Ok(()),
+//!             _ => {}
+//!         }
+//!     }
+//! }
+//! ```
+
+use super::absolute_pointer::{AbsolutePointer, AbsolutePointerState};
+use super::pointer::{Pointer, PointerState};
+use super::text::{Input, Key};
+use crate::table::boot::BootServices;
+use crate::{Event, Result, Status};
+
+/// An event read by [`InputEvents`].
+#[derive(Debug, Copy, Clone)]
+pub enum InputEvent {
+    /// A key was pressed on the keyboard.
+    Key(Key),
+    /// The state of the [`Pointer`] device changed.
+    Pointer(PointerState),
+    /// The state of the [`AbsolutePointer`] device changed.
+    AbsolutePointer(AbsolutePointerState),
+}
+
+/// Number of input sources of [`InputEvents`].
+const NUM_SOURCES: usize = 3;
+
+/// Merges the events of a keyboard, a [`Pointer`], and an
+/// [`AbsolutePointer`] into a single stream of [`InputEvent`]s.
+///
+/// Each source is optional, and added with the `with_*` methods. The
+/// sources are read in turn, so that a device reporting many events can't
+/// starve the others.
+#[derive(Default)]
+pub struct InputEvents<'a, 'boot> {
+    keyboard: Option<&'a mut Input>,
+    pointer: Option<&'a mut Pointer<'boot>>,
+    absolute_pointer: Option<&'a mut AbsolutePointer>,
+    /// Index of the source read first by the next call to `poll`.
+    next_source: usize,
+}
+
+impl<'a, 'boot> InputEvents<'a, 'boot> {
+    /// Create an event stream without any source.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read key presses from `keyboard`.
+    #[must_use]
+    pub fn with_keyboard(mut self, keyboard: &'a mut Input) -> Self {
+        self.keyboard = Some(keyboard);
+        self
+    }
+
+    /// Read state changes from the relative pointer device `pointer`.
+    #[must_use]
+    pub fn with_pointer(mut self, pointer: &'a mut Pointer<'boot>) -> Self {
+        self.pointer = Some(pointer);
+        self
+    }
+
+    /// Read state changes from the absolute pointer device
+    /// `absolute_pointer`.
+    #[must_use]
+    pub fn with_absolute_pointer(mut self, absolute_pointer: &'a mut AbsolutePointer) -> Self {
+        self.absolute_pointer = Some(absolute_pointer);
+        self
+    }
+
+    /// Read the next event, if any, without waiting.
+    ///
+    /// # Errors
+    ///
+    /// - `DeviceError` if there was an issue with one of the input devices.
+    pub fn poll(&mut self) -> Result<Option<InputEvent>> {
+        for i in 0..NUM_SOURCES {
+            let source = (self.next_source + i) % NUM_SOURCES;
+            if let Some(event) = self.read_source(source)? {
+                self.next_source = (source + 1) % NUM_SOURCES;
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Wait for the next event.
+    ///
+    /// This waits on the events of all the sources with
+    /// [`BootServices::wait_for_event`], so the current task priority level
+    /// must be [`Tpl::APPLICATION`].
+    ///
+    /// # Errors
+    ///
+    /// - `DeviceError` if there was an issue with one of the input devices.
+    /// - `InvalidParameter` if there is no source.
+    /// - `Unsupported` if the current task priority level is not
+    ///   [`Tpl::APPLICATION`].
+    ///
+    /// [`Tpl::APPLICATION`]: crate::table::boot::Tpl::APPLICATION
+    pub fn wait(&mut self, bt: &BootServices) -> Result<InputEvent> {
+        loop {
+            if let Some(event) = self.poll()? {
+                return Ok(event);
+            }
+
+            let mut events = self.wait_events()?;
+            bt.wait_for_event(&mut events)
+                .map_err(|err| err.into_err_without_payload())?;
+        }
+    }
+
+    /// Get the wait events of all the sources. Missing sources are replaced
+    /// with the event of another source, which doesn't change the result of
+    /// waiting on the events.
+    fn wait_events(&self) -> Result<[Event; NUM_SOURCES]> {
+        let events = [
+            self.keyboard.as_ref().map(|k| k.wait_for_key_event()),
+            self.pointer.as_ref().map(|p| p.wait_for_input_event()),
+            self.absolute_pointer
+                .as_ref()
+                .map(|p| p.wait_for_input_event()),
+        ];
+        let first = events
+            .iter()
+            .flatten()
+            .next()
+            .ok_or(Status::INVALID_PARAMETER)?;
+        Ok(events.map(|event| unsafe { event.unwrap_or(first).unsafe_clone() }))
+    }
+
+    /// Read the next event of the source at index `source`, if any.
+    fn read_source(&mut self, source: usize) -> Result<Option<InputEvent>> {
+        match source {
+            0 => match &mut self.keyboard {
+                Some(keyboard) => Ok(keyboard.read_key_stroke()?.map(InputEvent::Key)),
+                None => Ok(None),
+            },
+            1 => match &mut self.pointer {
+                Some(pointer) => Ok(pointer.read_state()?.map(InputEvent::Pointer)),
+                None => Ok(None),
+            },
+            _ => match &mut self.absolute_pointer {
+                Some(pointer) => Ok(pointer.read_state()?.map(InputEvent::AbsolutePointer)),
+                None => Ok(None),
+            },
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use crate::proto::console::text::{RawKey, ScanCode};
+    use crate::Char16;
+
+    #[test]
+    fn test_input_events() {
+        let mut firmware = MockFirmware::new();
+        firmware.stdin().push_str("a");
+        firmware.stdin().push_key(RawKey {
+            scan_code: ScanCode::ESCAPE,
+            unicode_char: Char16::try_from('\0').unwrap(),
+        });
+        let mut st = firmware.system_table();
+        let bt = unsafe { st.unsafe_clone() };
+        let bt = bt.boot_services();
+
+        assert_eq!(
+            InputEvents::new().wait(bt).unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+
+        let mut events = InputEvents::new().with_keyboard(st.stdin());
+        assert!(matches!(
+            events.poll(),
+            Ok(Some(InputEvent::Key(Key::Printable(c)))) if c == Char16::try_from('a').unwrap()
+        ));
+        assert!(matches!(
+            events.wait(bt),
+            Ok(InputEvent::Key(Key::Special(ScanCode::ESCAPE)))
+        ));
+        assert!(matches!(events.poll(), Ok(None)));
+    }
+}
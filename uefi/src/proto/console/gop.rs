@@ -50,12 +50,24 @@
 //! You will have to implement your own double buffering if you want to
 //! avoid tearing with animations.
 
-use crate::proto::unsafe_protocol;
+use super::edid::{Edid, EdidActive, EdidDiscovered};
+use crate::proto::device_path::{DevicePath, DeviceSubType, DeviceType};
+use crate::proto::{unsafe_protocol, ProtocolPointer};
+use crate::table::boot::{
+    BootServices, HandleBuffer, OpenProtocolAttributes, OpenProtocolParams, ScopedProtocol,
+    SearchType,
+};
 use crate::util::usize_from_u32;
-use crate::{Result, Status};
+use crate::{Handle, Result, Status};
 use core::marker::PhantomData;
-use core::mem;
-use core::ptr;
+use core::{fmt, mem, ptr, slice};
+#[cfg(feature = "alloc")]
+use {
+    crate::mem::{try_vec, try_with_capacity},
+    crate::proto::media::file::RegularFile,
+    crate::ResultExt,
+    alloc::vec::Vec,
+};
 
 /// Provides access to the video hardware's frame buffer.
 ///
@@ -94,7 +106,13 @@ impl<'boot> GraphicsOutput<'boot> {
         let mut info_sz = 0;
         let mut info = ptr::null();
 
-        (self.query_mode)(self, index, &mut info_sz, &mut info).into_with_val(|| {
+        trace_status!(
+            "GraphicsOutput::query_mode",
+            (self.query_mode)(self, index, &mut info_sz, &mut info),
+            "mode={}",
+            index
+        )
+        .into_with_val(|| {
             let info = unsafe { *info };
             Mode {
                 index,
@@ -114,20 +132,42 @@ impl<'boot> GraphicsOutput<'boot> {
         }
     }
 
+    /// Find the mode with the `(width, height)` resolution, such as the
+    /// native resolution of the display given by
+    /// [`GraphicsDevice::native_resolution`].
+    #[must_use]
+    pub fn find_mode(&self, width: usize, height: usize) -> Option<Mode> {
+        self.modes()
+            .find(|mode| mode.info().resolution() == (width, height))
+    }
+
     /// Sets the video device into the specified mode, clearing visible portions
     /// of the output display to black.
     ///
     /// This function will invalidate the current framebuffer.
     pub fn set_mode(&mut self, mode: &Mode) -> Result {
-        (self.set_mode)(self, mode.index).into()
+        trace_status!(
+            "GraphicsOutput::set_mode",
+            (self.set_mode)(self, mode.index),
+            "mode={}",
+            mode.index
+        )
+        .into()
     }
 
     /// Performs a blt (block transfer) operation on the frame buffer.
     ///
     /// Every operation requires different parameters.
     pub fn blt(&mut self, op: BltOp) -> Result {
+        let operation = match op {
+            BltOp::VideoFill { .. } => "VideoFill",
+            BltOp::VideoToBltBuffer { .. } => "VideoToBltBuffer",
+            BltOp::BufferToVideo { .. } => "BufferToVideo",
+            BltOp::VideoToVideo { .. } => "VideoToVideo",
+        };
+
         // Demultiplex the operation type.
-        unsafe {
+        let status = unsafe {
             match op {
                 BltOp::VideoFill {
                     color,
@@ -147,7 +187,6 @@ impl<'boot> GraphicsOutput<'boot> {
                         height,
                         0,
                     )
-                    .into()
                 }
                 BltOp::VideoToBltBuffer {
                     buffer,
@@ -169,8 +208,7 @@ impl<'boot> GraphicsOutput<'boot> {
                             width,
                             height,
                             0,
-                        )
-                        .into(),
+                        ),
                         BltRegion::SubRectangle {
                             coords: (dest_x, dest_y),
                             px_stride,
@@ -185,8 +223,7 @@ impl<'boot> GraphicsOutput<'boot> {
                             width,
                             height,
                             px_stride * core::mem::size_of::<BltPixel>(),
-                        )
-                        .into(),
+                        ),
                     }
                 }
                 BltOp::BufferToVideo {
@@ -209,8 +246,7 @@ impl<'boot> GraphicsOutput<'boot> {
                             width,
                             height,
                             0,
-                        )
-                        .into(),
+                        ),
                         BltRegion::SubRectangle {
                             coords: (src_x, src_y),
                             px_stride,
@@ -225,8 +261,7 @@ impl<'boot> GraphicsOutput<'boot> {
                             width,
                             height,
                             px_stride * core::mem::size_of::<BltPixel>(),
-                        )
-                        .into(),
+                        ),
                     }
                 }
                 BltOp::VideoToVideo {
@@ -248,10 +283,10 @@ impl<'boot> GraphicsOutput<'boot> {
                         height,
                         0,
                     )
-                    .into()
                 }
             }
-        }
+        };
+        trace_status!("GraphicsOutput::blt", status, "operation={}", operation).into()
     }
 
     /// Memory-safety check for accessing a region of the framebuffer
@@ -311,6 +346,370 @@ impl<'boot> GraphicsOutput<'boot> {
             _lifetime: PhantomData,
         }
     }
+
+    /// Read the visible contents of the screen in the current mode.
+    ///
+    /// This uses [`BltOp::VideoToBltBuffer`], so it also works in
+    /// [`PixelFormat::BltOnly`] modes.
+    #[cfg(feature = "alloc")]
+    pub fn screenshot(&mut self) -> Result<Screenshot> {
+        let (width, height) = self.current_mode_info().resolution();
+        let mut pixels = try_vec(BltPixel::new(0, 0, 0), width * height)?;
+        self.blt(BltOp::VideoToBltBuffer {
+            buffer: &mut pixels,
+            src: (0, 0),
+            dest: BltRegion::Full,
+            dims: (width, height),
+        })?;
+        Ok(Screenshot::new(width, height, pixels))
+    }
+}
+
+/// The graphics devices of the system.
+///
+/// Systems with several GPUs or video outputs have a [`GraphicsOutput`]
+/// protocol for each of them. Iterating over this gives a
+/// [`GraphicsDevice`] for each, so that one can be selected by its
+/// connector or display:
+///
+/// ```no_run
+/// use uefi::proto::console::gop::{DisplayConnector, GraphicsDevices};
+/// # use uefi::table::boot::BootServices;
+/// # fn select_display(bt: &BootServices) -> uefi::Result {
+/// // Prefer the internal panel, then the first output.
+/// let device = GraphicsDevices::find(bt)?
+///     .find(|device| device.connector() == Some(DisplayConnector::INTERNAL_DIGITAL))
+///     .or_else(|| GraphicsDevices::find(bt).ok()?.next())
+///     .ok_or(uefi::Status::NOT_FOUND)?;
+/// let mut gop = device.open_exclusive()?;
+/// if let Some((width, height)) = device.native_resolution() {
+///     if let Some(mode) = gop.find_mode(width, height) {
+///         gop.set_mode(&mode)?;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct GraphicsDevices<'boot> {
+    boot_services: &'boot BootServices,
+    handles: HandleBuffer<'boot>,
+    index: usize,
+}
+
+impl<'boot> GraphicsDevices<'boot> {
+    /// Find all the graphics devices.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: there is no graphics device.
+    pub fn find(bt: &'boot BootServices) -> Result<Self> {
+        let handles = bt.locate_handle_buffer(SearchType::from_proto::<GraphicsOutput>())?;
+        Ok(Self {
+            boot_services: bt,
+            handles,
+            index: 0,
+        })
+    }
+
+    /// Get the handles of all the graphics devices.
+    #[must_use]
+    pub fn handles(&self) -> &[Handle] {
+        &self.handles
+    }
+}
+
+impl<'boot> Iterator for GraphicsDevices<'boot> {
+    type Item = GraphicsDevice<'boot>;
+
+    fn next(&mut self) -> Option<GraphicsDevice<'boot>> {
+        let handle = *self.handles.get(self.index)?;
+        self.index += 1;
+        Some(GraphicsDevice::new(self.boot_services, handle))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.handles.len() - self.index;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for GraphicsDevices<'_> {}
+
+impl fmt::Debug for GraphicsDevices<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphicsDevices")
+            .field("handles", &self.handles())
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+/// A graphics device, found by [`GraphicsDevices`].
+pub struct GraphicsDevice<'boot> {
+    boot_services: &'boot BootServices,
+    handle: Handle,
+    device_path: Option<ScopedProtocol<'boot, DevicePath>>,
+    edid_active: Option<ScopedProtocol<'boot, EdidActive>>,
+    edid_discovered: Option<ScopedProtocol<'boot, EdidDiscovered>>,
+}
+
+impl<'boot> GraphicsDevice<'boot> {
+    fn new(bt: &'boot BootServices, handle: Handle) -> Self {
+        Self {
+            boot_services: bt,
+            handle,
+            device_path: get_protocol(bt, handle),
+            edid_active: get_protocol(bt, handle),
+            edid_discovered: get_protocol(bt, handle),
+        }
+    }
+
+    /// Get the handle of the graphics device.
+    #[must_use]
+    pub const fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// Get the device path of the graphics device, if it has one.
+    #[must_use]
+    pub fn device_path(&self) -> Option<&DevicePath> {
+        self.device_path.as_deref()
+    }
+
+    /// Get the ACPI `_ADR` value of the video output, from the device path.
+    /// Returns `None` if the device is not a video output of a GPU.
+    #[must_use]
+    pub fn acpi_adr(&self) -> Option<u32> {
+        let node = self
+            .device_path()?
+            .node_iter()
+            .filter(|node| node.full_type() == (DeviceType::ACPI, DeviceSubType::ACPI_ADR))
+            .last()?;
+        let bytes = unsafe {
+            slice::from_raw_parts(node.as_ffi_ptr().cast::<u8>(), usize::from(node.length()))
+        };
+        Some(u32::from_le_bytes(bytes.get(4..8)?.try_into().unwrap()))
+    }
+
+    /// Get the type of connector of the video output, from its ACPI `_ADR`
+    /// value.
+    #[must_use]
+    pub fn connector(&self) -> Option<DisplayConnector> {
+        self.acpi_adr().and_then(DisplayConnector::from_acpi_adr)
+    }
+
+    /// Get the EDID of the display: the active EDID if there is one,
+    /// otherwise the EDID read from the display.
+    #[must_use]
+    pub fn edid(&self) -> Option<Edid<'_>> {
+        self.edid_active
+            .as_ref()
+            .and_then(|edid| edid.edid())
+            .or_else(|| self.edid_discovered.as_ref()?.edid())
+    }
+
+    /// Get the `(width, height)` native resolution of the display, from its
+    /// EDID.
+    #[must_use]
+    pub fn native_resolution(&self) -> Option<(usize, usize)> {
+        self.edid()?.native_resolution()
+    }
+
+    /// Open the [`GraphicsOutput`] protocol of the device in exclusive mode.
+    ///
+    /// This disconnects the drivers using the device, such as the graphics
+    /// console. Use [`BootServices::connect_controller`] on [`handle`] after
+    /// closing the protocol to reconnect them.
+    ///
+    /// [`handle`]: Self::handle
+    ///
+    /// # Errors
+    ///
+    /// See [`BootServices::open_protocol_exclusive`].
+    pub fn open_exclusive(&self) -> Result<ScopedProtocol<'boot, GraphicsOutput<'boot>>> {
+        self.boot_services.open_protocol_exclusive(self.handle)
+    }
+
+    /// Open the [`GraphicsOutput`] protocol of the device without
+    /// disconnecting the drivers using it, such as the graphics console.
+    ///
+    /// # Safety
+    ///
+    /// The protocol may be uninstalled or used by another driver while it
+    /// is open, see [`OpenProtocolAttributes::GetProtocol`].
+    ///
+    /// # Errors
+    ///
+    /// See [`BootServices::open_protocol`].
+    pub unsafe fn open_shared(&self) -> Result<ScopedProtocol<'boot, GraphicsOutput<'boot>>> {
+        self.boot_services.open_protocol(
+            OpenProtocolParams {
+                handle: self.handle,
+                agent: self.boot_services.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }
+}
+
+impl fmt::Debug for GraphicsDevice<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphicsDevice")
+            .field("handle", &self.handle)
+            .field("device_path", &self.device_path())
+            .field("edid", &self.edid())
+            .finish()
+    }
+}
+
+/// Open the protocol `P` of `handle` to read it, or return `None` if the
+/// handle does not have it.
+fn get_protocol<P: ProtocolPointer + ?Sized>(
+    bt: &BootServices,
+    handle: Handle,
+) -> Option<ScopedProtocol<P>> {
+    // Safety: the protocols are only read.
+    unsafe {
+        bt.open_protocol::<P>(
+            OpenProtocolParams {
+                handle,
+                agent: bt.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }
+    .ok()
+}
+
+newtype_enum! {
+    /// Type of connector of a video output, as defined for the ACPI `_DOD`
+    /// method.
+    pub enum DisplayConnector: u8 => {
+        /// Another type of connector.
+        OTHER = 0,
+        /// A VGA connector, or another analog monitor connector.
+        VGA = 1,
+        /// A TV output.
+        TV = 2,
+        /// An external digital connector, such as DVI, HDMI or DisplayPort.
+        EXTERNAL_DIGITAL = 3,
+        /// An internal digital panel, such as a laptop screen.
+        INTERNAL_DIGITAL = 4,
+    }
+}
+
+impl DisplayConnector {
+    /// Get the connector from an ACPI `_ADR` value, or `None` if the value
+    /// does not follow the `_DOD` scheme.
+    #[must_use]
+    pub const fn from_acpi_adr(adr: u32) -> Option<Self> {
+        // Bit 31 marks the `_DOD` scheme, and bits 8 to 11 hold the type.
+        if adr & 0x8000_0000 == 0 {
+            None
+        } else {
+            Some(Self(((adr >> 8) & 0xf) as u8))
+        }
+    }
+}
+
+/// Several graphics outputs, mirroring the same picture.
+///
+/// This is useful for boot splash screens on systems with several displays.
+/// The picture is drawn at the same coordinates on each output, so the
+/// outputs should use the same resolution, see [`set_resolution`].
+///
+/// [`set_resolution`]: Self::set_resolution
+#[cfg(feature = "alloc")]
+pub struct GraphicsMirror<'boot> {
+    outputs: Vec<ScopedProtocol<'boot, GraphicsOutput<'boot>>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'boot> GraphicsMirror<'boot> {
+    /// Create a mirror of `outputs`.
+    #[must_use]
+    pub fn new(outputs: Vec<ScopedProtocol<'boot, GraphicsOutput<'boot>>>) -> Self {
+        Self { outputs }
+    }
+
+    /// Open all the graphics devices in exclusive mode, see
+    /// [`GraphicsDevice::open_exclusive`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: there is no graphics device.
+    ///
+    /// See also [`BootServices::open_protocol_exclusive`].
+    pub fn open_exclusive(bt: &'boot BootServices) -> Result<Self> {
+        let outputs = GraphicsDevices::find(bt)?
+            .map(|device| device.open_exclusive())
+            .collect::<Result<_>>()?;
+        Ok(Self::new(outputs))
+    }
+
+    /// Get the mirrored outputs.
+    #[must_use]
+    pub fn outputs(&mut self) -> &mut [ScopedProtocol<'boot, GraphicsOutput<'boot>>] {
+        &mut self.outputs
+    }
+
+    /// Set the `(width, height)` resolution on all the outputs supporting
+    /// it. Returns the number of outputs whose mode was set.
+    ///
+    /// # Errors
+    ///
+    /// See [`GraphicsOutput::set_mode`].
+    pub fn set_resolution(&mut self, width: usize, height: usize) -> Result<usize> {
+        let mut count = 0;
+        for output in &mut self.outputs {
+            if let Some(mode) = output.find_mode(width, height) {
+                output.set_mode(&mode)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Get the smallest resolution of the outputs, which is visible on all
+    /// of them, or `None` if there is no output.
+    #[must_use]
+    pub fn common_resolution(&self) -> Option<(usize, usize)> {
+        self.outputs
+            .iter()
+            .map(|output| output.current_mode_info().resolution())
+            .reduce(|(w1, h1), (w2, h2)| (w1.min(w2), h1.min(h2)))
+    }
+
+    /// Perform `op` on all the outputs. [`BltOp::VideoToBltBuffer`] only
+    /// reads the first output.
+    ///
+    /// # Errors
+    ///
+    /// See [`GraphicsOutput::blt`]. The operation is stopped at the first
+    /// output failing.
+    pub fn blt(&mut self, mut op: BltOp) -> Result {
+        if let BltOp::VideoToBltBuffer { .. } = op {
+            return match self.outputs.first_mut() {
+                Some(output) => output.blt(op),
+                None => Ok(()),
+            };
+        }
+        for output in &mut self.outputs {
+            output.blt(op.reborrow())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Debug for GraphicsMirror<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphicsMirror")
+            .field("outputs", &self.outputs.len())
+            .finish()
+    }
 }
 
 #[repr(C)]
@@ -567,6 +966,47 @@ pub enum BltOp<'buf> {
     },
 }
 
+impl BltOp<'_> {
+    /// Get a copy of the operation, borrowing its buffer.
+    #[cfg(feature = "alloc")]
+    fn reborrow(&mut self) -> BltOp<'_> {
+        match self {
+            Self::VideoFill { color, dest, dims } => BltOp::VideoFill {
+                color: *color,
+                dest: *dest,
+                dims: *dims,
+            },
+            Self::VideoToBltBuffer {
+                buffer,
+                src,
+                dest,
+                dims,
+            } => BltOp::VideoToBltBuffer {
+                buffer,
+                src: *src,
+                dest: *dest,
+                dims: *dims,
+            },
+            Self::BufferToVideo {
+                buffer,
+                src,
+                dest,
+                dims,
+            } => BltOp::BufferToVideo {
+                buffer,
+                src: *src,
+                dest: *dest,
+                dims: *dims,
+            },
+            Self::VideoToVideo { src, dest, dims } => BltOp::VideoToVideo {
+                src: *src,
+                dest: *dest,
+                dims: *dims,
+            },
+        }
+    }
+}
+
 /// Direct access to a memory-mapped frame buffer
 pub struct FrameBuffer<'gop> {
     base: *mut u8,
@@ -666,3 +1106,136 @@ impl<'gop> FrameBuffer<'gop> {
         (self.base.add(index) as *const T).read_volatile()
     }
 }
+
+/// Image captured with [`GraphicsOutput::screenshot`].
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct Screenshot {
+    width: usize,
+    height: usize,
+    pixels: Vec<BltPixel>,
+}
+
+#[cfg(feature = "alloc")]
+impl Screenshot {
+    /// Size of the BMP file and info headers.
+    const BMP_HEADER_SIZE: usize = 14 + 40;
+
+    /// Create an image from its pixels, in rows from top to bottom.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of pixels doesn't match the resolution.
+    #[must_use]
+    pub fn new(width: usize, height: usize, pixels: Vec<BltPixel>) -> Self {
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Get the width and height of the image.
+    #[must_use]
+    pub const fn resolution(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Get the pixels of the image, in rows from top to bottom.
+    #[must_use]
+    pub fn pixels(&self) -> &[BltPixel] {
+        &self.pixels
+    }
+
+    /// Encode the image as an uncompressed 24-bit BMP file.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::OUT_OF_RESOURCES`]: the file couldn't be allocated.
+    pub fn to_bmp(&self) -> Result<Vec<u8>> {
+        // Rows are padded to a multiple of four bytes.
+        let row_size = (self.width * 3 + 3) & !3;
+        let image_size = row_size * self.height;
+        let file_size = Self::BMP_HEADER_SIZE + image_size;
+        let mut bmp = try_with_capacity(file_size)?;
+
+        // BITMAPFILEHEADER
+        bmp.extend(b"BM");
+        bmp.extend((file_size as u32).to_le_bytes());
+        bmp.extend([0; 4]);
+        bmp.extend((Self::BMP_HEADER_SIZE as u32).to_le_bytes());
+
+        // BITMAPINFOHEADER
+        bmp.extend(40u32.to_le_bytes());
+        bmp.extend((self.width as i32).to_le_bytes());
+        bmp.extend((self.height as i32).to_le_bytes());
+        // Planes, and bits per pixel.
+        bmp.extend(1u16.to_le_bytes());
+        bmp.extend(24u16.to_le_bytes());
+        // No compression.
+        bmp.extend(0u32.to_le_bytes());
+        bmp.extend((image_size as u32).to_le_bytes());
+        // 72 DPI, in pixels per meter.
+        bmp.extend(2835u32.to_le_bytes());
+        bmp.extend(2835u32.to_le_bytes());
+        // No palette.
+        bmp.extend([0; 8]);
+
+        // Rows are stored from bottom to top, with pixels in BGR order.
+        if self.width > 0 {
+            for row in self.pixels.chunks(self.width).rev() {
+                for pixel in row {
+                    bmp.extend([pixel.blue, pixel.green, pixel.red]);
+                }
+                bmp.resize(bmp.len() + row_size - self.width * 3, 0);
+            }
+        }
+        Ok(bmp)
+    }
+
+    /// Encode the image as a BMP file with [`to_bmp`], and write it to
+    /// `file` at its current position.
+    ///
+    /// [`to_bmp`]: Self::to_bmp
+    ///
+    /// # Errors
+    ///
+    /// See [`to_bmp`] and [`RegularFile::write`].
+    pub fn write_bmp(&self, file: &mut RegularFile) -> Result {
+        file.write(&self.to_bmp()?).discard_errdata()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_screenshot_to_bmp() {
+        let pixels = vec![
+            BltPixel::new(1, 2, 3),
+            BltPixel::new(4, 5, 6),
+            BltPixel::new(7, 8, 9),
+            BltPixel::new(10, 11, 12),
+        ];
+        let bmp = Screenshot::new(2, 2, pixels).to_bmp().unwrap();
+
+        // Two rows of two pixels, padded to eight bytes.
+        assert_eq!(bmp.len(), 54 + 16);
+        assert_eq!(&bmp[..2], b"BM");
+        assert_eq!(bmp[2..6], 70u32.to_le_bytes());
+        assert_eq!(bmp[10..14], 54u32.to_le_bytes());
+        assert_eq!(bmp[18..22], 2i32.to_le_bytes());
+        assert_eq!(bmp[22..26], 2i32.to_le_bytes());
+        assert_eq!(bmp[28..30], 24u16.to_le_bytes());
+        assert_eq!(bmp[34..38], 16u32.to_le_bytes());
+
+        // The bottom row comes first.
+        assert_eq!(
+            bmp[54..],
+            [9, 8, 7, 12, 11, 10, 0, 0, 3, 2, 1, 6, 5, 4, 0, 0]
+        );
+    }
+}
@@ -3,6 +3,10 @@
 //! The console represents the various input and output methods
 //! used by the user to interact with the early boot platform.
 
+pub mod absolute_pointer;
+pub mod config;
+pub mod edid;
+pub mod events;
 pub mod gop;
 pub mod pointer;
 pub mod serial;
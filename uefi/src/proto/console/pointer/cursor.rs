@@ -0,0 +1,212 @@
+//! Drawing of a mouse cursor with the [`GraphicsOutput`] protocol.
+
+use crate::mem::{try_vec, try_with_capacity};
+use crate::proto::console::gop::{BltOp, BltPixel, BltRegion, GraphicsOutput};
+use crate::{Result, Status};
+use alloc::vec::Vec;
+
+/// Shape of the default cursor: `X` is black, `.` is white, and spaces are
+/// transparent.
+const ARROW: [&[u8; 12]; 17] = [
+    b"X           ",
+    b"XX          ",
+    b"X.X         ",
+    b"X..X        ",
+    b"X...X       ",
+    b"X....X      ",
+    b"X.....X     ",
+    b"X......X    ",
+    b"X.......X   ",
+    b"X........X  ",
+    b"X.........X ",
+    b"X......XXXXX",
+    b"X...X..X    ",
+    b"X..XX..X    ",
+    b"X.X  X..X   ",
+    b"XX   X..X   ",
+    b"      XX    ",
+];
+
+/// A mouse cursor sprite, drawn over the screen contents with
+/// [`GraphicsOutput::blt`].
+///
+/// The pixels under the cursor are saved when it is drawn, and restored
+/// when it is hidden or moved, so the cursor can be drawn over a screen
+/// that is not redrawn by the application. The screen must not be changed
+/// under the cursor while it is visible, otherwise the old contents are
+/// restored when it moves: hide the cursor before drawing.
+///
+/// See [`PointerTracker`] to get the position of the cursor.
+///
+/// [`PointerTracker`]: super::PointerTracker
+#[derive(Clone, Debug)]
+pub struct Cursor {
+    width: usize,
+    height: usize,
+    /// The pixels of the sprite, `None` if transparent.
+    sprite: Vec<Option<BltPixel>>,
+    /// The pixels under the cursor, while it is visible.
+    saved: Vec<BltPixel>,
+    /// Scratch buffer for the sprite blended with the saved pixels.
+    blended: Vec<BltPixel>,
+    /// Position and size of the visible part of the cursor.
+    visible: Option<((usize, usize), (usize, usize))>,
+}
+
+impl Cursor {
+    /// Create a cursor from its pixels, in rows from top to bottom. `None`
+    /// pixels are transparent. The hot spot of the cursor is its top-left
+    /// corner.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the number of pixels doesn't match
+    ///   the size.
+    /// * [`Status::OUT_OF_RESOURCES`]: the buffers for the pixels under the
+    ///   cursor couldn't be allocated.
+    pub fn new(width: usize, height: usize, sprite: Vec<Option<BltPixel>>) -> Result<Self> {
+        let len = width
+            .checked_mul(height)
+            .filter(|&len| len == sprite.len())
+            .ok_or(Status::INVALID_PARAMETER)?;
+        let black = BltPixel::new(0, 0, 0);
+        Ok(Self {
+            width,
+            height,
+            sprite,
+            saved: try_vec(black, len)?,
+            blended: try_vec(black, len)?,
+            visible: None,
+        })
+    }
+
+    /// Create a black and white arrow cursor.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::OUT_OF_RESOURCES`]: the cursor couldn't be allocated.
+    pub fn arrow() -> Result<Self> {
+        let (width, height) = (ARROW[0].len(), ARROW.len());
+        let mut sprite = try_with_capacity(width * height)?;
+        sprite.extend(
+            ARROW
+                .iter()
+                .flat_map(|row| row.iter())
+                .map(|pixel| match pixel {
+                    b'X' => Some(BltPixel::new(0, 0, 0)),
+                    b'.' => Some(BltPixel::new(0xff, 0xff, 0xff)),
+                    _ => None,
+                }),
+        );
+        Self::new(width, height, sprite)
+    }
+
+    /// Get the width and height of the cursor.
+    #[must_use]
+    pub const fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Whether the cursor is drawn on the screen.
+    #[must_use]
+    pub const fn is_visible(&self) -> bool {
+        self.visible.is_some()
+    }
+
+    /// Draw the cursor at `position`, or move it there if it is already
+    /// visible. The cursor is clipped at the edges of the screen.
+    ///
+    /// # Errors
+    ///
+    /// See [`GraphicsOutput::blt`].
+    pub fn draw(&mut self, gop: &mut GraphicsOutput, position: (usize, usize)) -> Result {
+        self.hide(gop)?;
+
+        let (x, y) = position;
+        let (screen_width, screen_height) = gop.current_mode_info().resolution();
+        let dims = (
+            self.width.min(screen_width.saturating_sub(x)),
+            self.height.min(screen_height.saturating_sub(y)),
+        );
+        if dims.0 == 0 || dims.1 == 0 {
+            return Ok(());
+        }
+        let len = dims.0 * dims.1;
+
+        gop.blt(BltOp::VideoToBltBuffer {
+            buffer: &mut self.saved[..len],
+            src: position,
+            dest: BltRegion::Full,
+            dims,
+        })?;
+        self.blend(dims.0);
+        gop.blt(BltOp::BufferToVideo {
+            buffer: &self.blended[..len],
+            src: BltRegion::Full,
+            dest: position,
+            dims,
+        })?;
+        self.visible = Some((position, dims));
+        Ok(())
+    }
+
+    /// Remove the cursor from the screen, restoring the pixels under it.
+    /// This does nothing if the cursor is not visible.
+    ///
+    /// # Errors
+    ///
+    /// See [`GraphicsOutput::blt`].
+    pub fn hide(&mut self, gop: &mut GraphicsOutput) -> Result {
+        if let Some((position, dims)) = self.visible {
+            gop.blt(BltOp::BufferToVideo {
+                buffer: &self.saved[..dims.0 * dims.1],
+                src: BltRegion::Full,
+                dest: position,
+                dims,
+            })?;
+            self.visible = None;
+        }
+        Ok(())
+    }
+
+    /// Fill `blended` with the first `width` columns of the sprite over the
+    /// saved pixels, which are `width` pixels wide.
+    fn blend(&mut self, width: usize) {
+        let rows = self.sprite.chunks(self.width);
+        let saved_rows = self.saved.chunks(width);
+        let blended_rows = self.blended.chunks_mut(width);
+        for ((row, saved), blended) in rows.zip(saved_rows).zip(blended_rows) {
+            for ((pixel, saved), blended) in row.iter().zip(saved).zip(blended) {
+                *blended = pixel.unwrap_or(*saved);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_cursor_blend() {
+        let red = BltPixel::new(0xff, 0, 0);
+        let mut cursor = Cursor::new(2, 2, vec![Some(red), None, None, Some(red)]).unwrap();
+        assert_eq!(cursor.size(), (2, 2));
+
+        // Only the first column is on the screen.
+        cursor.saved[..2].copy_from_slice(&[BltPixel::new(1, 1, 1), BltPixel::new(2, 2, 2)]);
+        cursor.blend(1);
+        let red_values: Vec<_> = cursor.blended[..2].iter().map(|p| p.red).collect();
+        assert_eq!(red_values, [0xff, 2]);
+
+        let arrow = Cursor::arrow().unwrap();
+        assert_eq!(arrow.size(), (12, 17));
+        assert!(!arrow.is_visible());
+
+        let err = Cursor::new(2, 2, vec![Some(red)]).unwrap_err();
+        assert_eq!(err.status(), Status::INVALID_PARAMETER);
+        let err = Cursor::new(usize::MAX, 2, vec![]).unwrap_err();
+        assert_eq!(err.status(), Status::INVALID_PARAMETER);
+    }
+}
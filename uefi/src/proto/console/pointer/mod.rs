@@ -1,5 +1,13 @@
 //! Pointer device access.
 
+#[cfg(feature = "alloc")]
+mod cursor;
+mod tracker;
+
+#[cfg(feature = "alloc")]
+pub use self::cursor::Cursor;
+pub use self::tracker::{PointerTracker, DEFAULT_PIXELS_PER_MM};
+
 use crate::proto::unsafe_protocol;
 use crate::{Event, Result, Status};
 use core::mem::MaybeUninit;
@@ -24,7 +32,13 @@ impl<'boot> Pointer<'boot> {
     ///
     /// - `DeviceError` if the device is malfunctioning and cannot be reset.
     pub fn reset(&mut self, extended_verification: bool) -> Result {
-        (self.reset)(self, extended_verification).into()
+        trace_status!(
+            "Pointer::reset",
+            (self.reset)(self, extended_verification),
+            "extended_verification={}",
+            extended_verification
+        )
+        .into()
     }
 
     /// Retrieves the pointer device's current state, if a state change occured
@@ -38,7 +52,10 @@ impl<'boot> Pointer<'boot> {
     pub fn read_state(&mut self) -> Result<Option<PointerState>> {
         let mut pointer_state = MaybeUninit::<PointerState>::uninit();
 
-        match (self.get_state)(self, pointer_state.as_mut_ptr()) {
+        match trace_status!(
+            "Pointer::get_state",
+            (self.get_state)(self, pointer_state.as_mut_ptr())
+        ) {
             Status::NOT_READY => Ok(None),
             other => other.into_with_val(|| unsafe { Some(pointer_state.assume_init()) }),
         }
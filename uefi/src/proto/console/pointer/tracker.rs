@@ -0,0 +1,199 @@
+//! Accumulation of the movements of a [`Pointer`] into a cursor position.
+//!
+//! [`Pointer`]: super::Pointer
+
+use super::{PointerMode, PointerState};
+
+/// Speed of the cursor used by [`PointerTracker::new`], in pixels per
+/// millimeter of movement of the device.
+pub const DEFAULT_PIXELS_PER_MM: u64 = 8;
+
+/// Converts the relative movements reported by a [`Pointer`] into the
+/// position of a cursor on a screen.
+///
+/// Movements are scaled from the counts of the device to pixels, and the
+/// position is kept inside the screen. Movements smaller than a pixel are
+/// accumulated, so that slow movements are not lost.
+///
+/// ```no_run
+/// use uefi::proto::console::pointer::{Pointer, PointerTracker};
+///
+/// fn track(pointer: &mut Pointer, width: usize, height: usize) -> uefi::Result {
+///     let mut tracker = PointerTracker::new(pointer.mode(), width, height);
+///     loop {
+///         if let Some(state) = pointer.read_state()? {
+///             let (x, y) = tracker.update(&state);
+///             if tracker.buttons().0 {
+///                 log::info!("click at ({x}, {y})");
+///             }
+///         }
+///     }
+/// }
+/// ```
+///
+/// [`Pointer`]: super::Pointer
+#[derive(Clone, Debug)]
+pub struct PointerTracker {
+    position: (usize, usize),
+    bounds: (usize, usize),
+    /// Counts of the device per pixel on each axis, 0 if the axis is not
+    /// supported.
+    counts_per_pixel: (i64, i64),
+    /// Counts that didn't amount to a whole pixel yet.
+    remainder: (i64, i64),
+    buttons: (bool, bool),
+}
+
+impl PointerTracker {
+    /// Create a tracker for a device with the given `mode`, and a screen of
+    /// `width` by `height` pixels. The cursor starts at the center of the
+    /// screen, and moves at [`DEFAULT_PIXELS_PER_MM`].
+    #[must_use]
+    pub fn new(mode: &PointerMode, width: usize, height: usize) -> Self {
+        let counts_per_pixel = |resolution: u64| {
+            if resolution == 0 {
+                0
+            } else {
+                i64::try_from(resolution / DEFAULT_PIXELS_PER_MM)
+                    .unwrap_or(i64::MAX)
+                    .max(1)
+            }
+        };
+        Self {
+            position: (width / 2, height / 2),
+            bounds: (width, height),
+            counts_per_pixel: (
+                counts_per_pixel(mode.resolution.0),
+                counts_per_pixel(mode.resolution.1),
+            ),
+            remainder: (0, 0),
+            buttons: (false, false),
+        }
+    }
+
+    /// Set the number of counts of the device that move the cursor by one
+    /// pixel on the X and Y axes. Smaller values make the cursor faster. A
+    /// value of 0 ignores the movements on that axis.
+    pub fn set_counts_per_pixel(&mut self, x: u32, y: u32) {
+        self.counts_per_pixel = (i64::from(x), i64::from(y));
+        self.remainder = (0, 0);
+    }
+
+    /// Apply the movement and the buttons of `state`, and get the new
+    /// position.
+    pub fn update(&mut self, state: &PointerState) -> (usize, usize) {
+        let (dx, dy, _) = state.relative_movement;
+        self.position.0 = move_axis(
+            self.position.0,
+            self.bounds.0,
+            self.counts_per_pixel.0,
+            &mut self.remainder.0,
+            dx,
+        );
+        self.position.1 = move_axis(
+            self.position.1,
+            self.bounds.1,
+            self.counts_per_pixel.1,
+            &mut self.remainder.1,
+            dy,
+        );
+        self.buttons = state.button;
+        self.position
+    }
+
+    /// Get the position of the cursor, with `(0, 0)` at the top-left of the
+    /// screen.
+    #[must_use]
+    pub const fn position(&self) -> (usize, usize) {
+        self.position
+    }
+
+    /// Move the cursor to `(x, y)`, clamped to the screen.
+    pub fn set_position(&mut self, x: usize, y: usize) {
+        self.position = (
+            x.min(self.bounds.0.saturating_sub(1)),
+            y.min(self.bounds.1.saturating_sub(1)),
+        );
+        self.remainder = (0, 0);
+    }
+
+    /// Change the size of the screen, e.g. after a mode change, and move
+    /// the cursor inside it.
+    pub fn set_bounds(&mut self, width: usize, height: usize) {
+        self.bounds = (width, height);
+        self.set_position(self.position.0, self.position.1);
+    }
+
+    /// Whether the left / right button was pressed in the last state
+    /// passed to [`update`].
+    ///
+    /// [`update`]: Self::update
+    #[must_use]
+    pub const fn buttons(&self) -> (bool, bool) {
+        self.buttons
+    }
+}
+
+/// Move `position` by `counts`, plus the counts left over in `remainder`,
+/// and clamp it to `0..bound`.
+fn move_axis(
+    position: usize,
+    bound: usize,
+    counts_per_pixel: i64,
+    remainder: &mut i64,
+    counts: i32,
+) -> usize {
+    if counts_per_pixel == 0 {
+        return position;
+    }
+    let counts = *remainder + i64::from(counts);
+    *remainder = counts % counts_per_pixel;
+    let pixels = counts / counts_per_pixel;
+    let max = bound.saturating_sub(1);
+    if pixels < 0 {
+        position.saturating_sub(usize::try_from(pixels.unsigned_abs()).unwrap_or(usize::MAX))
+    } else {
+        position
+            .saturating_add(usize::try_from(pixels).unwrap_or(usize::MAX))
+            .min(max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(dx: i32, dy: i32) -> PointerState {
+        PointerState {
+            relative_movement: (dx, dy, 0),
+            button: (true, false),
+        }
+    }
+
+    #[test]
+    fn test_pointer_tracker() {
+        let mode = PointerMode {
+            resolution: (16, 0, 0),
+            has_button: (true, true),
+        };
+        let mut tracker = PointerTracker::new(&mode, 100, 50);
+        assert_eq!(tracker.position(), (50, 25));
+
+        // Two counts per pixel, and the Y axis is not supported.
+        assert_eq!(tracker.update(&state(5, 7)), (52, 25));
+        assert_eq!(tracker.update(&state(1, 0)), (53, 25));
+        assert_eq!(tracker.buttons(), (true, false));
+        assert_eq!(tracker.update(&state(-1000, 0)), (0, 25));
+        assert_eq!(tracker.update(&state(i32::MAX, 0)), (99, 25));
+
+        tracker.set_counts_per_pixel(1, 1);
+        assert_eq!(tracker.update(&state(-9, 100)), (90, 49));
+        tracker.set_bounds(80, 40);
+        assert_eq!(tracker.position(), (79, 39));
+        tracker.set_position(10, 1000);
+        assert_eq!(tracker.position(), (10, 39));
+
+        let mut tracker = PointerTracker::new(&mode, 0, 0);
+        assert_eq!(tracker.update(&state(10, 10)), (0, 0));
+    }
+}
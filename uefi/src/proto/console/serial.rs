@@ -1,9 +1,15 @@
 //! Abstraction over byte stream devices, also known as serial I/O devices.
 
-use core::fmt::Write;
+use core::fmt::{self, Write};
+use core::slice;
 
+use crate::proto::device_path::{DevicePath, DevicePathNode, DeviceSubType, DeviceType};
 use crate::proto::unsafe_protocol;
-use crate::{Result, Status};
+use crate::table::boot::{
+    BootServices, HandleBuffer, OpenProtocolAttributes, OpenProtocolParams, ScopedProtocol,
+    SearchType,
+};
+use crate::{Handle, Result, Status};
 use bitflags::bitflags;
 
 /// Provides access to a serial I/O device.
@@ -39,7 +45,7 @@ pub struct Serial<'boot> {
 impl<'boot> Serial<'boot> {
     /// Reset the device.
     pub fn reset(&mut self) -> Result {
-        (self.reset)(self).into()
+        trace_status!("Serial::reset", (self.reset)(self)).into()
     }
 
     /// Returns the current I/O mode.
@@ -62,14 +68,19 @@ impl<'boot> Serial<'boot> {
     ///   the device's minimum, an error will be returned;
     ///   this value will be rounded down to the nearest value supported by the device;
     pub fn set_attributes(&mut self, mode: &IoMode) -> Result {
-        (self.set_attributes)(
-            self,
-            mode.baud_rate,
-            mode.receive_fifo_depth,
-            mode.timeout,
-            mode.parity,
-            mode.data_bits as u8,
-            mode.stop_bits,
+        trace_status!(
+            "Serial::set_attributes",
+            (self.set_attributes)(
+                self,
+                mode.baud_rate,
+                mode.receive_fifo_depth,
+                mode.timeout,
+                mode.parity,
+                mode.data_bits as u8,
+                mode.stop_bits,
+            ),
+            "{:?}",
+            mode
         )
         .into()
     }
@@ -77,7 +88,11 @@ impl<'boot> Serial<'boot> {
     /// Retrieve the device's current control bits.
     pub fn get_control_bits(&self) -> Result<ControlBits> {
         let mut bits = ControlBits::empty();
-        (self.get_control_bits)(self, &mut bits).into_with_val(|| bits)
+        trace_status!(
+            "Serial::get_control_bits",
+            (self.get_control_bits)(self, &mut bits)
+        )
+        .into_with_val(|| bits)
     }
 
     /// Sets the device's new control bits.
@@ -85,7 +100,13 @@ impl<'boot> Serial<'boot> {
     /// Not all bits can be modified with this function. A mask of the allowed
     /// bits is stored in the [`ControlBits::SETTABLE`] constant.
     pub fn set_control_bits(&mut self, bits: ControlBits) -> Result {
-        (self.set_control_bits)(self, bits).into()
+        trace_status!(
+            "Serial::set_control_bits",
+            (self.set_control_bits)(self, bits),
+            "{:?}",
+            bits
+        )
+        .into()
     }
 
     /// Reads data from this device.
@@ -121,6 +142,253 @@ impl<'boot> Write for Serial<'boot> {
     }
 }
 
+/// The serial ports of the system.
+///
+/// Iterating over this gives a [`SerialPort`] for each handle with a
+/// [`Serial`] protocol, so that a port can be selected by its location:
+///
+/// ```no_run
+/// use uefi::proto::console::serial::SerialPorts;
+/// # use uefi::table::boot::BootServices;
+/// # fn find_pci_uart(bt: &BootServices) -> uefi::Result {
+/// // Find the PCI UART at device 0x16, function 3.
+/// let port = SerialPorts::find(bt)?
+///     .find(|port| port.pci_location() == Some((0x16, 3)))
+///     .ok_or(uefi::Status::NOT_FOUND)?;
+/// let mut serial = port.open_exclusive()?;
+/// serial.write(b"Hello world!").map_err(|err| err.status())?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SerialPorts<'boot> {
+    boot_services: &'boot BootServices,
+    handles: HandleBuffer<'boot>,
+    index: usize,
+}
+
+impl<'boot> SerialPorts<'boot> {
+    /// Find all the serial ports.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: there is no serial port.
+    pub fn find(bt: &'boot BootServices) -> Result<Self> {
+        let handles = bt.locate_handle_buffer(SearchType::from_proto::<Serial>())?;
+        Ok(Self {
+            boot_services: bt,
+            handles,
+            index: 0,
+        })
+    }
+
+    /// Find the serial port identified by `id`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: there is no such serial port.
+    pub fn find_by_id(bt: &'boot BootServices, id: SerialPortId) -> Result<SerialPort<'boot>> {
+        Self::find(bt)?
+            .find(|port| port.id() == Some(id))
+            .ok_or_else(|| Status::NOT_FOUND.into())
+    }
+
+    /// Get the handles of all the serial ports.
+    #[must_use]
+    pub fn handles(&self) -> &[Handle] {
+        &self.handles
+    }
+}
+
+impl<'boot> Iterator for SerialPorts<'boot> {
+    type Item = SerialPort<'boot>;
+
+    fn next(&mut self) -> Option<SerialPort<'boot>> {
+        let handle = *self.handles.get(self.index)?;
+        self.index += 1;
+        Some(SerialPort::new(self.boot_services, handle))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.handles.len() - self.index;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for SerialPorts<'_> {}
+
+impl fmt::Debug for SerialPorts<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SerialPorts")
+            .field("handles", &self.handles())
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+/// A serial port, found by [`SerialPorts`].
+pub struct SerialPort<'boot> {
+    boot_services: &'boot BootServices,
+    handle: Handle,
+    device_path: Option<ScopedProtocol<'boot, DevicePath>>,
+}
+
+impl<'boot> SerialPort<'boot> {
+    fn new(bt: &'boot BootServices, handle: Handle) -> Self {
+        // Serial ports created by some drivers, such as for debug
+        // ports, may have no device path.
+        let device_path = unsafe {
+            bt.open_protocol::<DevicePath>(
+                OpenProtocolParams {
+                    handle,
+                    agent: bt.image_handle(),
+                    controller: None,
+                },
+                OpenProtocolAttributes::GetProtocol,
+            )
+        }
+        .ok();
+        Self {
+            boot_services: bt,
+            handle,
+            device_path,
+        }
+    }
+
+    /// Get the handle of the serial port.
+    #[must_use]
+    pub const fn handle(&self) -> Handle {
+        self.handle
+    }
+
+    /// Get the device path of the serial port, if it has one.
+    #[must_use]
+    pub fn device_path(&self) -> Option<&DevicePath> {
+        self.device_path.as_deref()
+    }
+
+    /// Get the stable identifier of the serial port, or `None` if it has no
+    /// device path.
+    #[must_use]
+    pub fn id(&self) -> Option<SerialPortId> {
+        self.device_path().map(SerialPortId::from_device_path)
+    }
+
+    /// Get the `(device, function)` numbers of the PCI device of the serial
+    /// port, or `None` if it is not a PCI device.
+    ///
+    /// This is the last PCI node of the device path, so the numbers are
+    /// relative to the bus of the nearest bridge.
+    #[must_use]
+    pub fn pci_location(&self) -> Option<(u8, u8)> {
+        self.device_path().and_then(pci_location)
+    }
+
+    /// Get the ACPI unique ID of an ISA serial port (`PNP0501`), which
+    /// usually is 0 for `COM1`, 1 for `COM2`, and so on. Returns `None` for
+    /// other serial ports.
+    #[must_use]
+    pub fn acpi_uid(&self) -> Option<u32> {
+        self.device_path().and_then(acpi_uart_uid)
+    }
+
+    /// Open the [`Serial`] protocol of the port in exclusive mode.
+    ///
+    /// This disconnects the drivers using the port, such as the console
+    /// driver if it is the console port. Use
+    /// [`BootServices::connect_controller`] on [`handle`] after closing the
+    /// protocol to reconnect them.
+    ///
+    /// [`handle`]: Self::handle
+    ///
+    /// # Errors
+    ///
+    /// See [`BootServices::open_protocol_exclusive`].
+    pub fn open_exclusive(&self) -> Result<ScopedProtocol<'boot, Serial<'boot>>> {
+        self.boot_services.open_protocol_exclusive(self.handle)
+    }
+}
+
+impl fmt::Debug for SerialPort<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SerialPort")
+            .field("handle", &self.handle)
+            .field("device_path", &self.device_path())
+            .finish()
+    }
+}
+
+/// A stable identifier of a serial port.
+///
+/// The identifier is a hash of the device path of the serial port, up to its
+/// UART node, which holds settings such as the baud rate. It stays the same
+/// across boots as long as the hardware is not changed, so it can be saved to
+/// select the same serial port later.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SerialPortId(pub u64);
+
+impl SerialPortId {
+    /// Compute the identifier of the serial port at `device_path`.
+    #[must_use]
+    pub fn from_device_path(device_path: &DevicePath) -> Self {
+        // 64-bit FNV-1a.
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        for node in controller_nodes(device_path) {
+            let bytes = unsafe {
+                slice::from_raw_parts(node.as_ffi_ptr().cast::<u8>(), usize::from(node.length()))
+            };
+            for byte in bytes {
+                hash = (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        Self(hash)
+    }
+}
+
+impl fmt::Display for SerialPortId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Get the nodes of `device_path` up to its UART node.
+fn controller_nodes(device_path: &DevicePath) -> impl Iterator<Item = &DevicePathNode> {
+    device_path.node_iter().take_while(|node| {
+        node.full_type() != (DeviceType::MESSAGING, DeviceSubType::MESSAGING_UART)
+    })
+}
+
+fn pci_location(device_path: &DevicePath) -> Option<(u8, u8)> {
+    controller_nodes(device_path)
+        .filter(|node| node.full_type() == (DeviceType::HARDWARE, DeviceSubType::HARDWARE_PCI))
+        .last()
+        .and_then(|node| {
+            let data = node_data(node);
+            // The node data is the function followed by the device.
+            Some((*data.get(1)?, *data.first()?))
+        })
+}
+
+fn acpi_uart_uid(device_path: &DevicePath) -> Option<u32> {
+    // EISA ID of `PNP0501`.
+    const UART_HID: u32 = 0x0501_41d0;
+    controller_nodes(device_path)
+        .filter(|node| node.full_type() == (DeviceType::ACPI, DeviceSubType::ACPI))
+        .find_map(|node| {
+            let data = node_data(node);
+            let hid = u32::from_le_bytes(data.get(..4)?.try_into().unwrap());
+            let uid = u32::from_le_bytes(data.get(4..8)?.try_into().unwrap());
+            (hid == UART_HID).then_some(uid)
+        })
+}
+
+/// Get the data of `node`, following its header.
+fn node_data(node: &DevicePathNode) -> &[u8] {
+    let bytes = unsafe {
+        slice::from_raw_parts(node.as_ffi_ptr().cast::<u8>(), usize::from(node.length()))
+    };
+    bytes.get(4..).unwrap_or(&[])
+}
+
 /// Structure representing the device's current parameters.
 ///
 /// The default values for all UART-like devices is:
@@ -230,3 +498,70 @@ pub enum StopBits {
     //         unlikely to be added at this point in time. Therefore, modeling
     //         this C enum as a Rust enum seems safe.
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    fn add_node(path: &mut Vec<u8>, device_type: DeviceType, sub_type: DeviceSubType, data: &[u8]) {
+        path.extend([device_type.0, sub_type.0]);
+        path.extend(u16::try_from(4 + data.len()).unwrap().to_le_bytes());
+        path.extend(data);
+    }
+
+    /// Create the device path of an ISA UART with `uid` and `baud_rate`.
+    fn isa_uart(uid: u32, baud_rate: u64) -> Vec<u8> {
+        let mut path = Vec::new();
+        add_node(
+            &mut path,
+            DeviceType::ACPI,
+            DeviceSubType::ACPI,
+            &[0xd0, 0x41, 0x03, 0x0a, 0, 0, 0, 0],
+        );
+        add_node(
+            &mut path,
+            DeviceType::HARDWARE,
+            DeviceSubType::HARDWARE_PCI,
+            &[0, 0x1f],
+        );
+        let mut acpi = Vec::from(0x0501_41d0u32.to_le_bytes());
+        acpi.extend(uid.to_le_bytes());
+        add_node(&mut path, DeviceType::ACPI, DeviceSubType::ACPI, &acpi);
+        let mut uart = Vec::from([0; 4]);
+        uart.extend(baud_rate.to_le_bytes());
+        uart.extend([8, 1, 1]);
+        add_node(
+            &mut path,
+            DeviceType::MESSAGING,
+            DeviceSubType::MESSAGING_UART,
+            &uart,
+        );
+        add_node(&mut path, DeviceType::END, DeviceSubType::END_ENTIRE, &[]);
+        path
+    }
+
+    #[test]
+    fn test_serial_port_location() {
+        let com2 = isa_uart(1, 115_200);
+        let com2 = DevicePath::try_from_bytes(&com2).unwrap();
+        assert_eq!(acpi_uart_uid(com2), Some(1));
+        assert_eq!(pci_location(com2), Some((0x1f, 0)));
+    }
+
+    #[test]
+    fn test_serial_port_id() {
+        let com1 = isa_uart(0, 115_200);
+        let com1_slow = isa_uart(0, 9600);
+        let com2 = isa_uart(1, 115_200);
+        let id =
+            |path: &[u8]| SerialPortId::from_device_path(DevicePath::try_from_bytes(path).unwrap());
+        // The baud rate is not part of the identifier.
+        assert_eq!(id(&com1), id(&com1_slow));
+        assert_ne!(id(&com1), id(&com2));
+        assert_eq!(
+            alloc::format!("{}", SerialPortId(0xabc)),
+            "0000000000000abc"
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use crate::proto::unsafe_protocol;
+use crate::raw::protocol::console::text as raw;
+use crate::table::boot::{BootServices, EventType, TimerTrigger, Tpl};
+use crate::time::timer_period;
 use crate::{Char16, Event, Result, Status};
 use core::mem::MaybeUninit;
+use core::time::Duration;
 
 #[repr(C)]
 pub struct KeyState{
@@ -13,12 +17,10 @@ pub struct KeyData{
 }
 
 /// Interface for text-based input devices.
-#[repr(C)]
+#[repr(transparent)]
 #[unsafe_protocol("387477c1-69c7-11d2-8e39-00a0c969723b")]
 pub struct Input {
-    reset: extern "efiapi" fn(this: &mut Input, extended: bool) -> Status,
-    read_key_stroke_ex: extern "efiapi" fn(this: &mut Input, key: *mut RawKey) -> Status,
-    wait_for_key: Event,
+    raw: raw::SimpleTextInputProtocol,
 }
 
 impl Input {
@@ -31,7 +33,13 @@ impl Input {
     ///
     /// - `DeviceError` if the device is malfunctioning and cannot be reset.
     pub fn reset(&mut self, extended_verification: bool) -> Result {
-        (self.reset)(self, extended_verification).into()
+        trace_status!(
+            "Input::reset",
+            unsafe { (self.raw.reset)(&mut self.raw, extended_verification) },
+            "extended_verification={}",
+            extended_verification
+        )
+        .into()
     }
 
     /// Reads the next keystroke from the input device, if any.
@@ -45,7 +53,9 @@ impl Input {
     pub fn read_key_ex(&mut self, key_data: KeyData) -> Result<Option<Key>> {
         let mut key = MaybeUninit::<RawKey>::uninit();
 
-        match (self.read_key_stroke_ex)(self, key.as_mut_ptr()) {
+        match trace_status!("Input::read_key_stroke", unsafe {
+            (self.raw.read_key_stroke)(&mut self.raw, key.as_mut_ptr().cast())
+        }) {
             Status::NOT_READY => Ok(None),
             other => other.into_with_val(|| Some(unsafe { key.assume_init() }.into())),
         }
@@ -55,7 +65,162 @@ impl Input {
     /// for a key to be available
     #[must_use]
     pub const fn wait_for_key_event(&self) -> &Event {
-        &self.wait_for_key
+        &self.raw.wait_for_key
+    }
+
+    /// Wait for the next keystroke, for at most `timeout`.
+    ///
+    /// Returns `None` if no key was pressed before the timeout elapsed. A key
+    /// that is already available is returned immediately, so a zero timeout
+    /// polls the input device without waiting.
+    ///
+    /// This waits on both the [`wait_for_key_event`] and a timer event with
+    /// [`BootServices::wait_for_event`], so the current task priority level
+    /// must be [`Tpl::APPLICATION`].
+    ///
+    /// # Errors
+    ///
+    /// - `DeviceError` if there was an issue with the input device
+    /// - `Unsupported` if the current task priority level is not
+    ///   [`Tpl::APPLICATION`]
+    ///
+    /// Errors from creating or setting the timer event are passed through.
+    ///
+    /// [`wait_for_key_event`]: Self::wait_for_key_event
+    pub fn wait_for_key_with_timeout(
+        &mut self,
+        bt: &BootServices,
+        timeout: Duration,
+    ) -> Result<Option<Key>> {
+        if let Some(key) = self.read_key_stroke()? {
+            return Ok(Some(key));
+        }
+        if timeout.is_zero() {
+            return Ok(None);
+        }
+
+        let timer = unsafe { bt.create_event(EventType::TIMER, Tpl::APPLICATION, None, None) }?;
+        let result = bt
+            .set_timer(&timer, TimerTrigger::Relative(timer_period(timeout)))
+            .and_then(|()| self.wait_for_key_or_timer(bt, &timer));
+        bt.close_event(timer)?;
+        result
+    }
+
+    /// Iterator of the keys pressed, which waits for each key.
+    ///
+    /// Each call to `next` reads the next keystroke, and if none is
+    /// available, waits for the [`wait_for_key_event`] with
+    /// [`BootServices::wait_for_event`], so the current task priority level
+    /// must be [`Tpl::APPLICATION`]. The iterator ends after an error.
+    ///
+    /// ```no_run
+    /// use uefi::prelude::*;
+    /// use uefi::proto::console::text::{Key, ScanCode};
+    ///
+    /// fn wait_for_escape(st: &mut SystemTable<Boot>) -> uefi::Result {
+    ///     let bt = unsafe { st.unsafe_clone() };
+    ///     for key in st.stdin().keys(bt.boot_services()) {
+    ///         if key? == Key::Special(ScanCode::ESCAPE) {
+    ///             break;
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    ///
+    /// [`wait_for_key_event`]: Self::wait_for_key_event
+    pub fn keys<'a>(&'a mut self, bt: &'a BootServices) -> Keys<'a> {
+        Keys {
+            input: self,
+            bt: Some(bt),
+            done: false,
+        }
+    }
+
+    /// Iterator of the keys that are already available, which doesn't
+    /// wait. It ends when no key is available, or after an error.
+    ///
+    /// This is useful to discard the keys pressed before a prompt, or to
+    /// handle the input in an event loop that does other work.
+    pub fn pending_keys(&mut self) -> Keys<'_> {
+        Keys {
+            input: self,
+            bt: None,
+            done: false,
+        }
+    }
+
+    /// Wait until a key is read or `timer` is signaled.
+    fn wait_for_key_or_timer(&mut self, bt: &BootServices, timer: &Event) -> Result<Option<Key>> {
+        loop {
+            let mut events =
+                unsafe { [self.raw.wait_for_key.unsafe_clone(), timer.unsafe_clone()] };
+            let index = bt
+                .wait_for_event(&mut events)
+                .map_err(|err| err.into_err_without_payload())?;
+            if index == 1 {
+                return Ok(None);
+            }
+            // The key event can be signaled without a key being available,
+            // for instance if the device was reset in the meantime.
+            if let Some(key) = self.read_key_stroke()? {
+                return Ok(Some(key));
+            }
+        }
+    }
+
+    /// Read the next keystroke, if any.
+    pub(crate) fn read_key_stroke(&mut self) -> Result<Option<Key>> {
+        let mut key = MaybeUninit::<RawKey>::uninit();
+
+        match trace_status!("Input::read_key_stroke", unsafe {
+            (self.raw.read_key_stroke)(&mut self.raw, key.as_mut_ptr().cast())
+        }) {
+            Status::NOT_READY => Ok(None),
+            other => other.into_with_val(|| Some(unsafe { key.assume_init() }.into())),
+        }
+    }
+}
+
+/// Iterator of the keys read from an [`Input`], returned by
+/// [`Input::keys`] and [`Input::pending_keys`].
+pub struct Keys<'a> {
+    input: &'a mut Input,
+    /// Boot services used to wait for keys, or `None` to not wait.
+    bt: Option<&'a BootServices>,
+    done: bool,
+}
+
+impl Keys<'_> {
+    fn read_key(&mut self) -> Result<Option<Key>> {
+        loop {
+            if let Some(key) = self.input.read_key_stroke()? {
+                return Ok(Some(key));
+            }
+            let bt = match self.bt {
+                Some(bt) => bt,
+                None => return Ok(None),
+            };
+            let mut events = unsafe { [self.input.raw.wait_for_key.unsafe_clone()] };
+            bt.wait_for_event(&mut events)
+                .map_err(|err| err.into_err_without_payload())?;
+        }
+    }
+}
+
+impl Iterator for Keys<'_> {
+    type Item = Result<Key>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.read_key().transpose();
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
     }
 }
 
@@ -80,6 +245,7 @@ impl From<RawKey> for Key {
 }
 
 /// A key read from the console (UEFI version)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(C)]
 pub struct RawKey {
     /// The key's scan code.
@@ -93,10 +259,18 @@ pub struct RawKey {
 newtype_enum! {
 /// A keyboard scan code
 ///
+/// The same scan codes are reported by both the [`Input`] and
+/// [`InputEx`] protocols, although only [`InputEx`] is expected to report
+/// codes beyond [`ESCAPE`].
+///
 /// Codes 0x8000 -> 0xFFFF are reserved for future OEM extensibility, therefore
 /// this C enum is _not_ safe to model as a Rust enum (where the compiler must
-/// know about all variants at compile time).
-pub enum ScanCode: u16 => #[allow(missing_docs)] {
+/// know about all variants at compile time). Use [`ScanCode::is_oem`] to
+/// check for these codes.
+///
+/// [`ESCAPE`]: ScanCode::ESCAPE
+/// [`InputEx`]: super::InputEx
+pub enum ScanCode: u16 => {
     /// Null scan code, indicates that the Unicode character should be used.
     NULL        = 0x00,
     /// Move cursor up 1 row.
@@ -107,48 +281,188 @@ pub enum ScanCode: u16 => #[allow(missing_docs)] {
     RIGHT       = 0x03,
     /// Move cursor left 1 column.
     LEFT        = 0x04,
+    /// Home key.
     HOME        = 0x05,
+    /// End key.
     END         = 0x06,
+    /// Insert key.
     INSERT      = 0x07,
+    /// Delete key.
     DELETE      = 0x08,
+    /// Page up key.
     PAGE_UP     = 0x09,
+    /// Page down key.
     PAGE_DOWN   = 0x0A,
+    /// F1 function key.
     FUNCTION_1  = 0x0B,
+    /// F2 function key.
     FUNCTION_2  = 0x0C,
+    /// F3 function key.
     FUNCTION_3  = 0x0D,
+    /// F4 function key.
     FUNCTION_4  = 0x0E,
+    /// F5 function key.
     FUNCTION_5  = 0x0F,
+    /// F6 function key.
     FUNCTION_6  = 0x10,
+    /// F7 function key.
     FUNCTION_7  = 0x11,
+    /// F8 function key.
     FUNCTION_8  = 0x12,
+    /// F9 function key.
     FUNCTION_9  = 0x13,
+    /// F10 function key.
     FUNCTION_10 = 0x14,
+    /// F11 function key.
     FUNCTION_11 = 0x15,
+    /// F12 function key.
     FUNCTION_12 = 0x16,
+    /// Escape key.
     ESCAPE      = 0x17,
 
+    /// Pause key.
+    PAUSE       = 0x48,
+
+    /// F13 function key.
     FUNCTION_13 = 0x68,
+    /// F14 function key.
     FUNCTION_14 = 0x69,
+    /// F15 function key.
     FUNCTION_15 = 0x6A,
+    /// F16 function key.
     FUNCTION_16 = 0x6B,
+    /// F17 function key.
     FUNCTION_17 = 0x6C,
+    /// F18 function key.
     FUNCTION_18 = 0x6D,
+    /// F19 function key.
     FUNCTION_19 = 0x6E,
+    /// F20 function key.
     FUNCTION_20 = 0x6F,
+    /// F21 function key.
     FUNCTION_21 = 0x70,
+    /// F22 function key.
     FUNCTION_22 = 0x71,
+    /// F23 function key.
     FUNCTION_23 = 0x72,
+    /// F24 function key.
     FUNCTION_24 = 0x73,
 
+    /// Mute audio.
     MUTE        = 0x7F,
+    /// Increase the volume.
     VOLUME_UP   = 0x80,
+    /// Decrease the volume.
     VOLUME_DOWN = 0x81,
 
+    /// Increase the display brightness.
     BRIGHTNESS_UP   = 0x100,
+    /// Decrease the display brightness.
     BRIGHTNESS_DOWN = 0x101,
+    /// Suspend the system.
     SUSPEND         = 0x102,
+    /// Hibernate the system.
     HIBERNATE       = 0x103,
+    /// Toggle the active display.
     TOGGLE_DISPLAY  = 0x104,
+    /// Start recovery.
     RECOVERY        = 0x105,
+    /// Eject media.
     EJECT           = 0x106,
 }}
+
+impl ScanCode {
+    /// Whether the scan code is in the range reserved for OEM
+    /// extensions (`0x8000..=0xFFFF`).
+    #[must_use]
+    pub const fn is_oem(self) -> bool {
+        self.0 >= 0x8000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_from_raw() {
+        let printable = RawKey {
+            scan_code: ScanCode::NULL,
+            unicode_char: Char16::try_from('a').unwrap(),
+        };
+        assert_eq!(
+            Key::from(printable),
+            Key::Printable(Char16::try_from('a').unwrap())
+        );
+
+        let special = RawKey {
+            scan_code: ScanCode::PAUSE,
+            unicode_char: Char16::try_from('\0').unwrap(),
+        };
+        assert_eq!(Key::from(special), Key::Special(ScanCode::PAUSE));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_wait_for_key_with_timeout() {
+        use crate::mock::MockFirmware;
+
+        let mut firmware = MockFirmware::new();
+        let mut st = firmware.system_table();
+        let bt = firmware.system_table();
+        let bt = bt.boot_services();
+        let timeout = Duration::from_secs(1);
+
+        firmware.stdin().push_str("ab");
+        let a = Key::Printable(Char16::try_from('a').unwrap());
+        let b = Key::Printable(Char16::try_from('b').unwrap());
+        assert_eq!(
+            st.stdin().wait_for_key_with_timeout(bt, timeout),
+            Ok(Some(a))
+        );
+        assert_eq!(
+            st.stdin().wait_for_key_with_timeout(bt, Duration::ZERO),
+            Ok(Some(b))
+        );
+        assert_eq!(
+            st.stdin().wait_for_key_with_timeout(bt, Duration::ZERO),
+            Ok(None)
+        );
+        assert_eq!(st.stdin().wait_for_key_with_timeout(bt, timeout), Ok(None));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_keys() {
+        use crate::mock::MockFirmware;
+        use alloc::vec;
+        use alloc::vec::Vec;
+
+        let mut firmware = MockFirmware::new();
+        let mut st = firmware.system_table();
+        let bt = firmware.system_table();
+        let bt = bt.boot_services();
+        let key = |c| Key::Printable(Char16::try_from(c).unwrap());
+
+        firmware.stdin().push_str("ab");
+        assert_eq!(
+            st.stdin().pending_keys().collect::<Result<Vec<_>>>(),
+            Ok(vec![key('a'), key('b')])
+        );
+        assert_eq!(st.stdin().pending_keys().next(), None);
+
+        // The mock reports that no event is ready instead of blocking.
+        firmware.stdin().push_str("c");
+        let mut keys = st.stdin().keys(bt);
+        assert_eq!(keys.next(), Some(Ok(key('c'))));
+        assert_eq!(keys.next(), Some(Err(Status::NOT_READY.into())));
+        assert_eq!(keys.next(), None);
+    }
+
+    #[test]
+    fn test_scan_code_oem() {
+        assert!(!ScanCode::EJECT.is_oem());
+        assert!(ScanCode(0x8000).is_oem());
+        assert!(ScanCode(0xffff).is_oem());
+    }
+}
@@ -1,35 +1,126 @@
-use core::ffi::c_void;
+use crate::data_types::chars::NUL_16;
+use crate::proto::console::text::input::{Key, RawKey, ScanCode};
 use crate::proto::unsafe_protocol;
-use crate::{Char16, Event, Result, Status};
-use crate::proto::console::text::input::{Key, RawKey};
+use crate::{Event, Result, Status};
+use bitflags::bitflags;
+use core::ffi::c_void;
 use core::mem::MaybeUninit;
 
+bitflags! {
+    /// State of the shift, control, alt, logo, and menu modifier keys.
+    ///
+    /// The other bits are only meaningful if [`ShiftState::SHIFT_STATE_VALID`]
+    /// is set.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct ShiftState: u32 {
+        /// The right shift key is pressed.
+        const RIGHT_SHIFT_PRESSED = 0x0000_0001;
+        /// The left shift key is pressed.
+        const LEFT_SHIFT_PRESSED = 0x0000_0002;
+        /// The right control key is pressed.
+        const RIGHT_CONTROL_PRESSED = 0x0000_0004;
+        /// The left control key is pressed.
+        const LEFT_CONTROL_PRESSED = 0x0000_0008;
+        /// The right alt key is pressed.
+        const RIGHT_ALT_PRESSED = 0x0000_0010;
+        /// The left alt key is pressed.
+        const LEFT_ALT_PRESSED = 0x0000_0020;
+        /// The right logo key is pressed.
+        const RIGHT_LOGO_PRESSED = 0x0000_0040;
+        /// The left logo key is pressed.
+        const LEFT_LOGO_PRESSED = 0x0000_0080;
+        /// The menu key is pressed.
+        const MENU_KEY_PRESSED = 0x0000_0100;
+        /// The SysReq key is pressed.
+        const SYS_REQ_PRESSED = 0x0000_0200;
+        /// The shift state is valid.
+        const SHIFT_STATE_VALID = 0x8000_0000;
+    }
+}
 
+bitflags! {
+    /// State of the lock keys, and of the partial key reporting mode.
+    ///
+    /// The other bits are only meaningful if
+    /// [`ToggleState::TOGGLE_STATE_VALID`] is set.
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct ToggleState: u8 {
+        /// Scroll lock is active.
+        const SCROLL_LOCK_ACTIVE = 0x01;
+        /// Num lock is active.
+        const NUM_LOCK_ACTIVE = 0x02;
+        /// Caps lock is active.
+        const CAPS_LOCK_ACTIVE = 0x04;
+        /// Keystrokes without a Unicode character or scan code, such as a
+        /// bare modifier key press, are reported.
+        const KEY_STATE_EXPOSED = 0x40;
+        /// The toggle state is valid.
+        const TOGGLE_STATE_VALID = 0x80;
+    }
+}
+
+/// State of the modifier and toggle keys when a key was read.
+///
+/// Layout compatible with the C type `EFI_KEY_STATE`.
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct KeyState {
-    pub key_shift_state: u32,
-    pub key_toggle_state: u8,
+    /// State of the modifier keys.
+    pub key_shift_state: ShiftState,
+    /// State of the toggle keys.
+    pub key_toggle_state: ToggleState,
 }
+
+/// A key read from the console, along with the modifier state.
+///
+/// Layout compatible with the C type `EFI_KEY_DATA`.
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct KeyData {
-    pub key: Key,
+    /// The key that was read.
+    pub key: RawKey,
+    /// State of the modifier and toggle keys.
     pub key_state: KeyState,
 }
 
+/// A key event read by [`InputEx::read_key_event`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum KeyEvent {
+    /// A key with a Unicode character or scan code was read.
+    Key(Key, KeyState),
+
+    /// Only the modifier state changed, e.g. a bare control key was
+    /// pressed. These events are only reported once partial keystrokes have
+    /// been enabled with [`ToggleState::KEY_STATE_EXPOSED`].
+    ModifierOnly(ShiftState),
+}
+
+impl From<KeyData> for KeyEvent {
+    fn from(data: KeyData) -> Self {
+        let key = data.key;
+        if key.scan_code == ScanCode::NULL && key.unicode_char == NUL_16 {
+            KeyEvent::ModifierOnly(data.key_state.key_shift_state)
+        } else {
+            KeyEvent::Key(key.into(), data.key_state)
+        }
+    }
+}
+
+/// Extended interface for text-based input devices, which also reports the
+/// state of the modifier keys.
 #[repr(C)]
 #[unsafe_protocol("dd9e7534-7762-4698-8c14-f58517a625aa")]
 pub struct InputEx {
-    reset: extern "efiapi" fn(this: &mut InputEx, extended: bool ) -> Status,
-    read_key_stroke_ex: extern "efiapi" fn(this: &mut InputEx, key: *mut RawKey) -> Status,
+    reset: extern "efiapi" fn(this: &mut InputEx, extended: bool) -> Status,
+    read_key_stroke_ex: extern "efiapi" fn(this: &mut InputEx, key_data: *mut KeyData) -> Status,
     wait_for_key_ex: Event,
-    set_state: extern "efiapi" fn(this: &mut InputEx, key_toggle_state: u8) -> Status,
-    register_key_notify: extern "efiapi" fn(this: &mut InputEx, key_data: KeyData, key_notify: &mut KeyData, c_void),
+    set_state:
+        extern "efiapi" fn(this: &mut InputEx, key_toggle_state: *const ToggleState) -> Status,
+    register_key_notify:
+        extern "efiapi" fn(this: &mut InputEx, key_data: KeyData, key_notify: &mut KeyData, c_void),
     unregister_key_notify: extern "efiapi" fn(this: &mut InputEx, c_void),
-
-
-
 }
 
 impl InputEx {
@@ -38,88 +129,94 @@ impl InputEx {
     }
 
     pub fn read_key_ex(&mut self) -> Result<Option<Key>> {
-        let mut key = MaybeUninit::<RawKey>::uninit();
+        let mut key_data = MaybeUninit::<KeyData>::uninit();
 
-        match (self.read_key_stroke_ex)(self, key.as_mut_ptr()) {
+        match (self.read_key_stroke_ex)(self, key_data.as_mut_ptr()) {
             Status::NOT_READY => Ok(None),
 
-            other => other.into_with_val(|| Some(unsafe { key.assume_init() }.into())),
+            other => other.into_with_val(|| Some(unsafe { key_data.assume_init() }.key.into())),
         }
     }
 
+    /// Reads the next key event from the input device, if any.
+    ///
+    /// Unlike [`read_key_ex`], this reports the modifier state, and reports
+    /// partial keystrokes as [`KeyEvent::ModifierOnly`] if they have been
+    /// enabled with [`set_state`].
+    ///
+    /// [`read_key_ex`]: Self::read_key_ex
+    /// [`set_state`]: Self::set_state
+    ///
+    /// # Errors
+    ///
+    /// - `DeviceError` if there was an issue with the input device
+    /// - `Unsupported` if the device does not support reading key data
+    pub fn read_key_event(&mut self) -> Result<Option<KeyEvent>> {
+        let mut key_data = MaybeUninit::<KeyData>::uninit();
+
+        match (self.read_key_stroke_ex)(self, key_data.as_mut_ptr()) {
+            Status::NOT_READY => Ok(None),
+
+            other => other.into_with_val(|| Some(unsafe { key_data.assume_init() }.into())),
+        }
+    }
+
+    /// Sets the state of the toggle keys.
+    ///
+    /// Set [`ToggleState::KEY_STATE_EXPOSED`] (along with
+    /// [`ToggleState::TOGGLE_STATE_VALID`]) to have modifier-only keystrokes
+    /// reported by [`read_key_event`].
+    ///
+    /// [`read_key_event`]: Self::read_key_event
+    ///
+    /// # Errors
+    ///
+    /// - `DeviceError` if there was an issue with the input device
+    /// - `Unsupported` if the device does not support the requested state
+    pub fn set_state(&mut self, toggle_state: ToggleState) -> Result {
+        (self.set_state)(self, &toggle_state).into()
+    }
+
     /// Event to be used with `BootServices::wait_for_event()` in order to wait
     /// for a key to be available
     #[must_use]
     pub const fn wait_for_key_event_ex(&self) -> &Event {
         &self.wait_for_key_ex
     }
-
 }
 
-
-newtype_enum! {
-/// A keyboard scan code
-///
-/// Codes 0x8000 -> 0xFFFF are reserved for future OEM extensibility, therefore
-/// this C enum is _not_ safe to model as a Rust enum (where the compiler must
-/// know about all variants at compile time).
-pub enum ScanCodeEx: u16 => #[allow(missing_docs)] {
-    /// Null scan code, indicates that the Unicode character should be used.
-    NULL        = 0x00,
-    /// Move cursor up 1 row.
-    UP          = 0x01,
-    /// Move cursor down 1 row.
-    DOWN        = 0x02,
-    /// Move cursor right 1 column.
-    RIGHT       = 0x03,
-    /// Move cursor left 1 column.
-    LEFT        = 0x04,
-    HOME        = 0x05,
-    END         = 0x06,
-    INSERT      = 0x07,
-    DELETE      = 0x08,
-    PAGE_UP     = 0x09,
-    PAGE_DOWN   = 0x0A,
-    FUNCTION_1  = 0x0B,
-    FUNCTION_2  = 0x0C,
-    FUNCTION_3  = 0x0D,
-    FUNCTION_4  = 0x0E,
-    FUNCTION_5  = 0x0F,
-    FUNCTION_6  = 0x10,
-    FUNCTION_7  = 0x11,
-    FUNCTION_8  = 0x12,
-    FUNCTION_9  = 0x13,
-    FUNCTION_10 = 0x14,
-    FUNCTION_11 = 0x15,
-    FUNCTION_12 = 0x16,
-    ESCAPE      = 0x17,
-
-    FUNCTION_13 = 0x68,
-    FUNCTION_14 = 0x69,
-    FUNCTION_15 = 0x6A,
-    FUNCTION_16 = 0x6B,
-    FUNCTION_17 = 0x6C,
-    FUNCTION_18 = 0x6D,
-    FUNCTION_19 = 0x6E,
-    FUNCTION_20 = 0x6F,
-    FUNCTION_21 = 0x70,
-    FUNCTION_22 = 0x71,
-    FUNCTION_23 = 0x72,
-    FUNCTION_24 = 0x73,
-
-    MUTE        = 0x7F,
-    VOLUME_UP   = 0x80,
-    VOLUME_DOWN = 0x81,
-
-    BRIGHTNESS_UP   = 0x100,
-    BRIGHTNESS_DOWN = 0x101,
-    SUSPEND         = 0x102,
-    HIBERNATE       = 0x103,
-    TOGGLE_DISPLAY  = 0x104,
-    RECOVERY        = 0x105,
-    EJECT           = 0x106,
-
-    DIGIT1          = 0x1E,
-}}
-
-
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Char16;
+
+    #[test]
+    fn test_key_event_from_key_data() {
+        let shift = ShiftState::SHIFT_STATE_VALID | ShiftState::LEFT_CONTROL_PRESSED;
+        let key_state = KeyState {
+            key_shift_state: shift,
+            key_toggle_state: ToggleState::empty(),
+        };
+
+        let printable = KeyData {
+            key: RawKey {
+                scan_code: ScanCode::NULL,
+                unicode_char: Char16::try_from('c').unwrap(),
+            },
+            key_state,
+        };
+        assert_eq!(
+            KeyEvent::from(printable),
+            KeyEvent::Key(Key::Printable(Char16::try_from('c').unwrap()), key_state)
+        );
+
+        let modifier_only = KeyData {
+            key: RawKey {
+                scan_code: ScanCode::NULL,
+                unicode_char: NUL_16,
+            },
+            key_state,
+        };
+        assert_eq!(KeyEvent::from(modifier_only), KeyEvent::ModifierOnly(shift));
+    }
+}
@@ -1,10 +1,15 @@
 //! Text I/O.
 
 mod input_ex;
-pub use self::input_ex::{InputEx, ScanCodeEx};
+pub use self::input_ex::{InputEx, KeyData, KeyEvent, KeyState, ShiftState, ToggleState};
 
 mod input;
-pub use self::input::{Input, Key, ScanCode};
+pub use self::input::{Input, Key, Keys, RawKey, ScanCode};
+
+/// Former name of the scan codes reported by [`InputEx`], which are now
+/// shared with [`Input`].
+#[deprecated = "use `ScanCode` instead"]
+pub type ScanCodeEx = ScanCode;
 
 mod output;
-pub use self::output::{Color, Output, OutputMode};
+pub use self::output::{Color, Output, OutputLock, OutputMode, SavedColors};
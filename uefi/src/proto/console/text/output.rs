@@ -1,7 +1,10 @@
 use crate::proto::unsafe_protocol;
-use crate::{CStr16, Char16, Result, ResultExt, Status};
+use crate::raw::protocol::console::text as raw;
+use crate::{CStr16, Result, ResultExt, Status};
 use core::fmt;
 use core::fmt::{Debug, Formatter};
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 /// Interface for text-based output devices.
 ///
@@ -20,30 +23,31 @@ use core::fmt::{Debug, Formatter};
 /// [`SystemTable::stdout`]: crate::table::SystemTable::stdout
 /// [`SystemTable::stderr`]: crate::table::SystemTable::stderr
 /// [`BootServices`]: crate::table::boot::BootServices#accessing-protocols
-#[repr(C)]
+#[repr(transparent)]
 #[unsafe_protocol("387477c2-69c7-11d2-8e39-00a0c969723b")]
 pub struct Output<'boot> {
-    reset: extern "efiapi" fn(this: &Output, extended: bool) -> Status,
-    output_string: unsafe extern "efiapi" fn(this: &Output, string: *const Char16) -> Status,
-    test_string: unsafe extern "efiapi" fn(this: &Output, string: *const Char16) -> Status,
-    query_mode: extern "efiapi" fn(
-        this: &Output,
-        mode: usize,
-        columns: &mut usize,
-        rows: &mut usize,
-    ) -> Status,
-    set_mode: extern "efiapi" fn(this: &mut Output, mode: usize) -> Status,
-    set_attribute: extern "efiapi" fn(this: &mut Output, attribute: usize) -> Status,
-    clear_screen: extern "efiapi" fn(this: &mut Output) -> Status,
-    set_cursor_position: extern "efiapi" fn(this: &mut Output, column: usize, row: usize) -> Status,
-    enable_cursor: extern "efiapi" fn(this: &mut Output, visible: bool) -> Status,
-    data: &'boot OutputData,
+    raw: raw::SimpleTextOutputProtocol,
+    _lifetime: PhantomData<&'boot ()>,
 }
 
 impl<'boot> Output<'boot> {
+    fn raw_ptr(&self) -> *mut raw::SimpleTextOutputProtocol {
+        &self.raw as *const raw::SimpleTextOutputProtocol as *mut _
+    }
+
+    const fn data(&self) -> &raw::SimpleTextOutputMode {
+        unsafe { &*self.raw.mode }
+    }
+
     /// Resets and clears the text output device hardware.
     pub fn reset(&mut self, extended: bool) -> Result {
-        (self.reset)(self, extended).into()
+        trace_status!(
+            "Output::reset",
+            unsafe { (self.raw.reset)(self.raw_ptr(), extended) },
+            "extended={}",
+            extended
+        )
+        .into()
     }
 
     /// Clears the output screen.
@@ -51,12 +55,15 @@ impl<'boot> Output<'boot> {
     /// The background is set to the current background color.
     /// The cursor is moved to (0, 0).
     pub fn clear(&mut self) -> Result {
-        (self.clear_screen)(self).into()
+        trace_status!("Output::clear_screen", unsafe {
+            (self.raw.clear_screen)(self.raw_ptr())
+        })
+        .into()
     }
 
     /// Writes a string to the output device.
     pub fn output_string(&mut self, string: &CStr16) -> Result {
-        unsafe { (self.output_string)(self, string.as_ptr()) }.into()
+        unsafe { (self.raw.output_string)(self.raw_ptr(), string.as_ptr()) }.into()
     }
 
     /// Writes a string to the output device. If the string contains
@@ -77,16 +84,23 @@ impl<'boot> Output<'boot> {
     /// UEFI applications are encouraged to try to print a string even if it contains
     /// some unsupported characters.
     pub fn test_string(&mut self, string: &CStr16) -> Result<bool> {
-        match unsafe { (self.test_string)(self, string.as_ptr()) } {
+        match unsafe { (self.raw.test_string)(self.raw_ptr(), string.as_ptr()) } {
             Status::UNSUPPORTED => Ok(false),
             other => other.into_with_val(|| true),
         }
     }
 
     /// Returns an iterator of all supported text modes.
+    ///
+    /// Each [`OutputMode`] carries the dimensions reported by the device, so
+    /// the modes can be kept and passed to [`set_mode`] later without
+    /// querying the device again.
+    ///
+    /// [`set_mode`]: Self::set_mode
     // TODO: Bring back impl Trait once the story around bounds improves
-    pub fn modes<'out>(&'out mut self) -> OutputModeIter<'out, 'boot> {
-        let max = self.data.max_mode as usize;
+    #[must_use]
+    pub fn modes<'out>(&'out self) -> OutputModeIter<'out, 'boot> {
+        let max = self.data().max_mode as usize;
         OutputModeIter {
             output: self,
             current: 0,
@@ -104,14 +118,20 @@ impl<'boot> Output<'boot> {
     /// If you want to iterate over all text modes supported by the device,
     /// consider using the iterator produced by `modes()` as a more ergonomic
     /// alternative to this method.
-    fn query_mode(&self, index: usize) -> Result<(usize, usize)> {
+    pub fn query_mode(&self, index: usize) -> Result<(usize, usize)> {
         let (mut columns, mut rows) = (0, 0);
-        (self.query_mode)(self, index, &mut columns, &mut rows).into_with_val(|| (columns, rows))
+        trace_status!(
+            "Output::query_mode",
+            unsafe { (self.raw.query_mode)(self.raw_ptr(), index, &mut columns, &mut rows) },
+            "mode={}",
+            index
+        )
+        .into_with_val(|| (columns, rows))
     }
 
     /// Returns the current text mode.
     pub fn current_mode(&self) -> Result<Option<OutputMode>> {
-        match self.data.mode {
+        match self.data().mode {
             -1 => Ok(None),
             n if n >= 0 => {
                 let index = n as usize;
@@ -122,15 +142,55 @@ impl<'boot> Output<'boot> {
         }
     }
 
+    /// Returns the supported text mode with the largest number of
+    /// characters, or `None` if no mode is supported.
+    ///
+    /// If several modes have the same size, the one with the most columns
+    /// is returned.
+    #[must_use]
+    pub fn best_mode(&self) -> Option<OutputMode> {
+        self.modes()
+            .max_by_key(|mode| (mode.columns() * mode.rows(), mode.columns()))
+    }
+
+    /// Returns the supported text mode with the given number of columns and
+    /// rows, if any.
+    #[must_use]
+    pub fn find_mode(&self, columns: usize, rows: usize) -> Option<OutputMode> {
+        self.modes()
+            .find(|mode| mode.columns() == columns && mode.rows() == rows)
+    }
+
+    /// Sets the text mode with the given number of columns and rows as
+    /// current.
+    ///
+    /// # Errors
+    ///
+    /// - `Unsupported` if the device does not support a mode of that size.
+    /// - `DeviceError` if the device had an error and could not complete the
+    ///   request.
+    pub fn set_mode_by_size(&mut self, columns: usize, rows: usize) -> Result {
+        match self.find_mode(columns, rows) {
+            Some(mode) => self.set_mode(mode),
+            None => Err(Status::UNSUPPORTED.into()),
+        }
+    }
+
     /// Sets a mode as current.
     pub fn set_mode(&mut self, mode: OutputMode) -> Result {
-        (self.set_mode)(self, mode.index).into()
+        trace_status!(
+            "Output::set_mode",
+            unsafe { (self.raw.set_mode)(self.raw_ptr(), mode.index) },
+            "mode={}",
+            mode.index
+        )
+        .into()
     }
 
     /// Returns whether the cursor is currently shown or not.
     #[must_use]
     pub const fn cursor_visible(&self) -> bool {
-        self.data.cursor_visible
+        self.data().cursor_visible
     }
 
     /// Make the cursor visible or invisible.
@@ -138,14 +198,34 @@ impl<'boot> Output<'boot> {
     /// The output device may not support this operation, in which case an
     /// `Unsupported` error will be returned.
     pub fn enable_cursor(&mut self, visible: bool) -> Result {
-        (self.enable_cursor)(self, visible).into()
+        trace_status!(
+            "Output::enable_cursor",
+            unsafe { (self.raw.enable_cursor)(self.raw_ptr(), visible) },
+            "visible={}",
+            visible
+        )
+        .into()
+    }
+
+    /// Makes the cursor visible.
+    ///
+    /// Shorthand for `enable_cursor(true)`.
+    pub fn show_cursor(&mut self) -> Result {
+        self.enable_cursor(true)
+    }
+
+    /// Makes the cursor invisible.
+    ///
+    /// Shorthand for `enable_cursor(false)`.
+    pub fn hide_cursor(&mut self) -> Result {
+        self.enable_cursor(false)
     }
 
     /// Returns the column and row of the cursor.
     #[must_use]
     pub const fn cursor_position(&self) -> (usize, usize) {
-        let column = self.data.cursor_column;
-        let row = self.data.cursor_row;
+        let column = self.data().cursor_column;
+        let row = self.data().cursor_row;
         (column as usize, row as usize)
     }
 
@@ -153,7 +233,14 @@ impl<'boot> Output<'boot> {
     ///
     /// This function will fail if the cursor's new position would exceed the screen's bounds.
     pub fn set_cursor_position(&mut self, column: usize, row: usize) -> Result {
-        (self.set_cursor_position)(self, column, row).into()
+        trace_status!(
+            "Output::set_cursor_position",
+            unsafe { (self.raw.set_cursor_position)(self.raw_ptr(), column, row) },
+            "column={}, row={}",
+            column,
+            row
+        )
+        .into()
     }
 
     /// Sets the text and background colors for the console.
@@ -167,7 +254,115 @@ impl<'boot> Output<'boot> {
         assert!(bgc < 8, "An invalid background color was requested");
 
         let attr = ((bgc & 0x7) << 4) | (fgc & 0xF);
-        (self.set_attribute)(self, attr).into()
+        self.set_attribute(attr)
+    }
+
+    /// Sets new text and background colors, and returns the previous ones so
+    /// they can be restored with [`pop_color`].
+    ///
+    /// Nested calls form a stack: each [`SavedColors`] must be passed to
+    /// [`pop_color`] in the reverse order of the calls to this method.
+    ///
+    /// [`pop_color`]: Self::pop_color
+    pub fn push_color(&mut self, foreground: Color, background: Color) -> Result<SavedColors> {
+        let saved = SavedColors(self.data().attribute as usize);
+        self.set_color(foreground, background)?;
+        Ok(saved)
+    }
+
+    /// Restores the text and background colors saved by [`push_color`].
+    ///
+    /// [`push_color`]: Self::push_color
+    pub fn pop_color(&mut self, saved: SavedColors) -> Result {
+        self.set_attribute(saved.0)
+    }
+
+    /// Calls `f` with the text and background colors set to the given ones,
+    /// and restores the previous colors afterwards.
+    ///
+    /// The previous colors are restored even if `f` returns an error; an
+    /// error restoring them is only reported if `f` succeeded.
+    pub fn with_colors<T>(
+        &mut self,
+        foreground: Color,
+        background: Color,
+        f: impl FnOnce(&mut Self) -> Result<T>,
+    ) -> Result<T> {
+        let saved = self.push_color(foreground, background)?;
+        let res = f(self);
+        let restored = self.pop_color(saved);
+        let value = res?;
+        restored.map(|()| value)
+    }
+
+    fn set_attribute(&mut self, attr: usize) -> Result {
+        trace_status!(
+            "Output::set_attribute",
+            unsafe { (self.raw.set_attribute)(self.raw_ptr(), attr) },
+            "attribute={:#x}",
+            attr
+        )
+        .into()
+    }
+}
+
+/// Whether an [`OutputLock`] is held.
+static OUTPUT_LOCKED: AtomicBool = AtomicBool::new(false);
+
+/// Lock of the console outputs, held by the writers of the crate, such as
+/// the [`Logger`], while they write a message.
+///
+/// UEFI applications run on a single processor, so the lock can only be
+/// contended when a write is reentered: by a log from a notification
+/// function or from the `trace-status` logs of [`Output`] itself, or by a
+/// panic in the middle of a write. Writers don't wait for the lock, which
+/// would never be released, but skip their output instead. The panic
+/// handler can use [`force_unlock`] to print its message anyway.
+///
+/// There is a single lock for all the outputs, as the standard output and
+/// the standard error are usually the same screen. Applications writing to
+/// an [`Output`] directly don't have to take the lock.
+///
+/// [`Logger`]: crate::logger::Logger
+/// [`force_unlock`]: Self::force_unlock
+#[derive(Debug)]
+#[must_use = "the lock is released when the guard is dropped"]
+pub struct OutputLock {
+    _private: (),
+}
+
+impl OutputLock {
+    /// Take the lock, or return `None` if it is already held. The lock is
+    /// released when the returned guard is dropped.
+    pub fn try_lock() -> Option<Self> {
+        OUTPUT_LOCKED
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| Self { _private: () })
+    }
+
+    /// Whether the lock is held.
+    #[must_use]
+    pub fn is_locked() -> bool {
+        OUTPUT_LOCKED.load(Ordering::Relaxed)
+    }
+
+    /// Release the lock, even though it is held by someone else, e.g. in a
+    /// panic handler that must print its message.
+    ///
+    /// # Safety
+    ///
+    /// The writer holding the lock must never resume, which is the case if
+    /// the caller doesn't return, as it would write to the output at the
+    /// same time as the new holder of the lock.
+    pub unsafe fn force_unlock() {
+        OUTPUT_LOCKED.store(false, Ordering::Release);
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        OUTPUT_LOCKED.store(false, Ordering::Release);
     }
 }
 
@@ -225,28 +420,8 @@ impl<'boot> fmt::Write for Output<'boot> {
 impl<'boot> Debug for Output<'boot> {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("Output")
-            .field("reset (fn ptr)", &(self.reset as *const u64))
-            .field(
-                "output_string (fn ptr)",
-                &(self.output_string as *const u64),
-            )
-            .field("test_string (fn ptr)", &(self.test_string as *const u64))
-            .field("query_mode (fn ptr)", &(self.query_mode as *const u64))
-            .field("set_mode (fn ptr)", &(self.set_mode as *const u64))
-            .field(
-                "set_attribute (fn ptr)",
-                &(self.set_attribute as *const u64),
-            )
-            .field("clear_screen (fn ptr)", &(self.clear_screen as *const u64))
-            .field(
-                "set_cursor_position (fn ptr)",
-                &(self.set_cursor_position as *const u64),
-            )
-            .field(
-                "enable_cursor (fn ptr)",
-                &(self.enable_cursor as *const u64),
-            )
-            .field("data", &self.data)
+            .field("raw", &self.raw)
+            .field("data", self.data())
             .finish()
     }
 }
@@ -281,9 +456,14 @@ impl OutputMode {
     }
 }
 
+/// Text and background colors saved by [`Output::push_color`].
+#[derive(Debug)]
+#[must_use = "the saved colors should be restored with `Output::pop_color`"]
+pub struct SavedColors(usize);
+
 /// An iterator of the text modes (possibly) supported by a device.
 pub struct OutputModeIter<'out, 'boot: 'out> {
-    output: &'out mut Output<'boot>,
+    output: &'out Output<'boot>,
     current: usize,
     max: usize,
 }
@@ -307,25 +487,6 @@ impl<'out, 'boot> Iterator for OutputModeIter<'out, 'boot> {
     }
 }
 
-/// Additional data of the output device.
-#[derive(Debug)]
-#[repr(C)]
-struct OutputData {
-    /// The number of modes supported by the device.
-    max_mode: i32,
-    /// The current output mode.
-    /// Negative index -1 is used to notify that no valid mode is configured
-    mode: i32,
-    /// The current character output attribute.
-    attribute: i32,
-    /// The cursor’s column.
-    cursor_column: i32,
-    /// The cursor’s row.
-    cursor_row: i32,
-    /// Whether the cursor is currently visible or not.
-    cursor_visible: bool,
-}
-
 /// Colors for the UEFI console.
 ///
 /// All colors can be used as foreground colors.
@@ -350,3 +511,24 @@ pub enum Color {
     Yellow,
     White,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_lock() {
+        let lock = OutputLock::try_lock().unwrap();
+        assert!(OutputLock::is_locked());
+        assert!(OutputLock::try_lock().is_none());
+        drop(lock);
+        assert!(!OutputLock::is_locked());
+
+        let lock = OutputLock::try_lock().unwrap();
+        unsafe { OutputLock::force_unlock() };
+        let _lock = OutputLock::try_lock().unwrap();
+        // The forgotten guard must not release the new holder's lock.
+        core::mem::forget(lock);
+        assert!(OutputLock::is_locked());
+    }
+}
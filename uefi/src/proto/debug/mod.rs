@@ -170,3 +170,69 @@ pub enum ProcessorArch: u32 => {
     /// RISC-V 128-bit
     RISCV_128   = 0x5128,
 }}
+
+/// The debug port protocol abstracts the device used by a debug agent to
+/// communicate with a remote debugger, typically a serial port.
+///
+/// A debug agent that uses this protocol should open it exclusively, so that
+/// no other consumer (such as the console drivers) uses the same device.
+#[repr(C)]
+#[unsafe_protocol("eba4e8d2-3858-41ec-a281-2647ba9660d0")]
+pub struct DebugPort {
+    reset: extern "efiapi" fn(this: &DebugPort) -> Status,
+    write: unsafe extern "efiapi" fn(
+        this: &DebugPort,
+        timeout: u32,
+        buffer_size: &mut usize,
+        buffer: *const c_void,
+    ) -> Status,
+    read: unsafe extern "efiapi" fn(
+        this: &DebugPort,
+        timeout: u32,
+        buffer_size: &mut usize,
+        buffer: *mut c_void,
+    ) -> Status,
+    poll: extern "efiapi" fn(this: &DebugPort) -> Status,
+}
+
+impl DebugPort {
+    /// Resets the debug port device.
+    pub fn reset(&self) -> Result {
+        (self.reset)(self).into()
+    }
+
+    /// Writes data to the debug port.
+    ///
+    /// `timeout` is the maximum time to wait for each byte to be written, in
+    /// microseconds. If it expires, the error will indicate how many bytes
+    /// were actually written.
+    pub fn write(&self, timeout: u32, data: &[u8]) -> Result<(), usize> {
+        let mut buffer_size = data.len();
+        unsafe { (self.write)(self, timeout, &mut buffer_size, data.as_ptr().cast()) }.into_with(
+            || debug_assert_eq!(buffer_size, data.len()),
+            |_| buffer_size,
+        )
+    }
+
+    /// Reads data from the debug port.
+    ///
+    /// `timeout` is the maximum time to wait for each byte to be received, in
+    /// microseconds. If it expires, the error will indicate how many bytes
+    /// were actually read.
+    pub fn read(&self, timeout: u32, data: &mut [u8]) -> Result<(), usize> {
+        let mut buffer_size = data.len();
+        unsafe { (self.read)(self, timeout, &mut buffer_size, data.as_mut_ptr().cast()) }.into_with(
+            || debug_assert_eq!(buffer_size, data.len()),
+            |_| buffer_size,
+        )
+    }
+
+    /// Checks whether any data is available to be read, without blocking.
+    pub fn poll(&self) -> Result<bool> {
+        match (self.poll)(self) {
+            Status::SUCCESS => Ok(true),
+            Status::NOT_READY => Ok(false),
+            status => Err(status.into()),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+//! `Decompress` protocol.
+
+use crate::proto::unsafe_protocol;
+use crate::{Result, Status};
+use core::ffi::c_void;
+#[cfg(feature = "alloc")]
+use {crate::mem::try_vec, alloc::vec::Vec};
+
+/// Protocol for decompressing data compressed with the UEFI compression
+/// algorithm, as used in compressed firmware volume sections.
+///
+/// The data must have been compressed with the EFI variant of the
+/// algorithm. The `decompress` feature provides a software implementation in
+/// `uefi::decompress` that also supports the Tiano variant.
+///
+/// The corresponding C type is `EFI_DECOMPRESS_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("d8117cfe-94a6-11d4-9a3a-0090273fc14d")]
+pub struct Decompress {
+    get_info: unsafe extern "efiapi" fn(
+        this: *const Self,
+        source: *const c_void,
+        source_size: u32,
+        destination_size: *mut u32,
+        scratch_size: *mut u32,
+    ) -> Status,
+    decompress: unsafe extern "efiapi" fn(
+        this: *const Self,
+        source: *const c_void,
+        source_size: u32,
+        destination: *mut c_void,
+        destination_size: u32,
+        scratch: *mut c_void,
+        scratch_size: u32,
+    ) -> Status,
+}
+
+/// Buffer sizes needed to decompress data, returned by
+/// [`Decompress::get_info`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DecompressInfo {
+    /// Size of the decompressed data in bytes.
+    pub destination_size: usize,
+    /// Size of the scratch buffer needed by the implementation, in bytes.
+    pub scratch_size: usize,
+}
+
+impl Decompress {
+    /// Get the sizes of the buffers needed to decompress `source`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the size of the compressed data
+    ///   recorded in `source` is larger than `source`.
+    pub fn get_info(&self, source: &[u8]) -> Result<DecompressInfo> {
+        let source_size = buffer_size(source)?;
+        let mut destination_size = 0;
+        let mut scratch_size = 0;
+        unsafe {
+            (self.get_info)(
+                self,
+                source.as_ptr().cast(),
+                source_size,
+                &mut destination_size,
+                &mut scratch_size,
+            )
+        }
+        .into_with_val(|| DecompressInfo {
+            destination_size: destination_size as usize,
+            scratch_size: scratch_size as usize,
+        })
+    }
+
+    /// Decompress `source` into `destination`, using `scratch` as working
+    /// memory. The required sizes of both buffers are returned by
+    /// [`get_info`].
+    ///
+    /// [`get_info`]: Self::get_info
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: a buffer is too small, or the
+    ///   compressed data is corrupt.
+    pub fn decompress(&self, source: &[u8], destination: &mut [u8], scratch: &mut [u8]) -> Result {
+        let source_size = buffer_size(source)?;
+        let destination_size = buffer_size(destination)?;
+        let scratch_size = buffer_size(scratch)?;
+        unsafe {
+            (self.decompress)(
+                self,
+                source.as_ptr().cast(),
+                source_size,
+                destination.as_mut_ptr().cast(),
+                destination_size,
+                scratch.as_mut_ptr().cast(),
+                scratch_size,
+            )
+        }
+        .into()
+    }
+
+    /// Decompress `source` into a newly allocated [`Vec`].
+    ///
+    /// # Errors
+    ///
+    /// See [`get_info`] and [`decompress`].
+    ///
+    /// [`get_info`]: Self::get_info
+    /// [`decompress`]: Self::decompress
+    #[cfg(feature = "alloc")]
+    pub fn decompress_to_vec(&self, source: &[u8]) -> Result<Vec<u8>> {
+        let info = self.get_info(source)?;
+        let mut destination = try_vec(0, info.destination_size)?;
+        let mut scratch = try_vec(0, info.scratch_size)?;
+        self.decompress(source, &mut destination, &mut scratch)?;
+        Ok(destination)
+    }
+}
+
+/// Get the size of `buffer` as the `u32` used by the protocol.
+fn buffer_size(buffer: &[u8]) -> Result<u32> {
+    u32::try_from(buffer.len()).map_err(|_| Status::INVALID_PARAMETER.into())
+}
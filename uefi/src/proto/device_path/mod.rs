@@ -304,6 +304,31 @@ impl DevicePath {
         &*Self::ptr_from_ffi(ptr.cast::<c_void>())
     }
 
+    /// Get a [`DevicePath`] from the start of `bytes`, checking that the
+    /// nodes are well-formed and that the path ends with an
+    /// [`END_ENTIRE`] node within `bytes`. Any bytes after that node are
+    /// ignored.
+    ///
+    /// [`END_ENTIRE`]: DeviceSubType::END_ENTIRE
+    pub(crate) fn try_from_bytes(bytes: &[u8]) -> Option<&DevicePath> {
+        let mut offset = 0;
+        loop {
+            let header = bytes.get(offset..offset + mem::size_of::<DevicePathHeader>())?;
+            let length = usize::from(u16::from_le_bytes([header[2], header[3]]));
+            if length < mem::size_of::<DevicePathHeader>() || offset + length > bytes.len() {
+                return None;
+            }
+            offset += length;
+            if (DeviceType(header[0]), DeviceSubType(header[1]))
+                == (DeviceType::END, DeviceSubType::END_ENTIRE)
+            {
+                break;
+            }
+        }
+        // Safety: the nodes were validated above.
+        Some(unsafe { &*ptr_meta::from_raw_parts(bytes.as_ptr().cast(), offset) })
+    }
+
     /// Cast to a [`FfiDevicePath`] pointer.
     #[must_use]
     pub const fn as_ffi_ptr(&self) -> *const FfiDevicePath {
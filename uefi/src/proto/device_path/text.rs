@@ -48,7 +48,7 @@ pub struct PoolString<'a> {
 }
 
 impl<'a> PoolString<'a> {
-    fn new(boot_services: &'a BootServices, text: *const Char16) -> Result<Self> {
+    pub(crate) fn new(boot_services: &'a BootServices, text: *const Char16) -> Result<Self> {
         if text.is_null() {
             Err(Status::OUT_OF_RESOURCES.into())
         } else {
@@ -0,0 +1,222 @@
+use crate::proto::device_path::{DevicePath, FfiDevicePath};
+use crate::proto::unsafe_protocol;
+use crate::{Handle, Result, Status};
+use core::ptr;
+
+/// Protocol that lets the platform override which drivers are bound to a
+/// controller when [`BootServices::connect_controller`] is called.
+///
+/// Drivers returned by this protocol take precedence over the drivers
+/// returned by [`BusSpecificDriverOverride`] and over the default driver
+/// binding order.
+///
+/// The corresponding C type is `EFI_PLATFORM_DRIVER_OVERRIDE_PROTOCOL`.
+///
+/// [`BootServices::connect_controller`]: crate::table::boot::BootServices::connect_controller
+#[unsafe_protocol("6b30c738-a391-11d4-9a3b-0090273fc14d")]
+#[repr(C)]
+pub struct PlatformDriverOverride {
+    get_driver: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller_handle: Handle,
+        driver_image_handle: *mut Option<Handle>,
+    ) -> Status,
+    get_driver_path: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller_handle: Handle,
+        driver_image_path: *mut *const FfiDevicePath,
+    ) -> Status,
+    driver_loaded: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller_handle: Handle,
+        driver_image_path: *const FfiDevicePath,
+        driver_image_handle: Handle,
+    ) -> Status,
+}
+
+impl PlatformDriverOverride {
+    /// Get an iterator over the image handles of the override drivers for
+    /// `controller_handle`, from highest to lowest priority.
+    ///
+    /// Drivers that are not loaded yet are not returned; see
+    /// [`driver_paths`] to find them.
+    ///
+    /// [`driver_paths`]: Self::driver_paths
+    #[must_use]
+    pub const fn drivers(&self, controller_handle: Handle) -> PlatformDrivers<'_> {
+        PlatformDrivers {
+            protocol: self,
+            controller_handle,
+            previous: None,
+            done: false,
+        }
+    }
+
+    /// Get an iterator over the device paths of the override drivers for
+    /// `controller_handle`, from highest to lowest priority.
+    ///
+    /// This also includes drivers that are not loaded yet. After loading
+    /// such a driver, report it with [`driver_loaded`] so that it is
+    /// returned by [`drivers`].
+    ///
+    /// [`driver_loaded`]: Self::driver_loaded
+    /// [`drivers`]: Self::drivers
+    #[must_use]
+    pub const fn driver_paths(&self, controller_handle: Handle) -> PlatformDriverPaths<'_> {
+        PlatformDriverPaths {
+            protocol: self,
+            controller_handle,
+            previous: ptr::null(),
+            done: false,
+        }
+    }
+
+    /// Report that the driver at `driver_image_path`, returned by
+    /// [`driver_paths`], has been loaded as `driver_image_handle`.
+    ///
+    /// [`driver_paths`]: Self::driver_paths
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `driver_image_path` is not an
+    ///   override driver of `controller_handle`.
+    /// * [`Status::UNSUPPORTED`]: the protocol does not support this
+    ///   operation.
+    pub fn driver_loaded(
+        &self,
+        controller_handle: Handle,
+        driver_image_path: &DevicePath,
+        driver_image_handle: Handle,
+    ) -> Result {
+        unsafe {
+            (self.driver_loaded)(
+                self,
+                controller_handle,
+                driver_image_path.as_ffi_ptr(),
+                driver_image_handle,
+            )
+        }
+        .into()
+    }
+}
+
+/// Iterator over the override drivers of a controller, returned by
+/// [`PlatformDriverOverride::drivers`].
+pub struct PlatformDrivers<'a> {
+    protocol: &'a PlatformDriverOverride,
+    controller_handle: Handle,
+    previous: Option<Handle>,
+    done: bool,
+}
+
+impl<'a> Iterator for PlatformDrivers<'a> {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Handle> {
+        if self.done {
+            return None;
+        }
+        let mut handle = self.previous;
+        let status = unsafe {
+            (self.protocol.get_driver)(self.protocol, self.controller_handle, &mut handle)
+        };
+        // `NOT_FOUND` marks the end of the list. Other errors also end the
+        // iteration, since the previous handle can't be used to resume it.
+        if status.is_success() && handle.is_some() {
+            self.previous = handle;
+            handle
+        } else {
+            self.done = true;
+            None
+        }
+    }
+}
+
+/// Iterator over the device paths of the override drivers of a controller,
+/// returned by [`PlatformDriverOverride::driver_paths`].
+pub struct PlatformDriverPaths<'a> {
+    protocol: &'a PlatformDriverOverride,
+    controller_handle: Handle,
+    previous: *const FfiDevicePath,
+    done: bool,
+}
+
+impl<'a> Iterator for PlatformDriverPaths<'a> {
+    type Item = &'a DevicePath;
+
+    fn next(&mut self) -> Option<&'a DevicePath> {
+        if self.done {
+            return None;
+        }
+        let mut path = self.previous;
+        let status = unsafe {
+            (self.protocol.get_driver_path)(self.protocol, self.controller_handle, &mut path)
+        };
+        if status.is_success() && !path.is_null() {
+            self.previous = path;
+            // Safety: the path is owned by the protocol.
+            Some(unsafe { DevicePath::from_ffi_ptr(path) })
+        } else {
+            self.done = true;
+            None
+        }
+    }
+}
+
+/// Protocol that lets a bus driver specify which drivers should be bound to
+/// one of the controllers it produced, typically drivers loaded from the
+/// controller's option ROM.
+///
+/// This protocol is installed on the controller's handle. Its drivers take
+/// precedence over the default driver binding order, but not over the
+/// drivers returned by [`PlatformDriverOverride`].
+///
+/// The corresponding C type is `EFI_BUS_SPECIFIC_DRIVER_OVERRIDE_PROTOCOL`.
+#[unsafe_protocol("3bc1b285-8a15-4a82-aabf-4d7d13fb3265")]
+#[repr(C)]
+pub struct BusSpecificDriverOverride {
+    get_driver: unsafe extern "efiapi" fn(
+        this: *const Self,
+        driver_image_handle: *mut Option<Handle>,
+    ) -> Status,
+}
+
+impl BusSpecificDriverOverride {
+    /// Get an iterator over the image handles of the override drivers, from
+    /// highest to lowest priority.
+    #[must_use]
+    pub const fn drivers(&self) -> BusSpecificDrivers<'_> {
+        BusSpecificDrivers {
+            protocol: self,
+            previous: None,
+            done: false,
+        }
+    }
+}
+
+/// Iterator over the override drivers of a controller, returned by
+/// [`BusSpecificDriverOverride::drivers`].
+pub struct BusSpecificDrivers<'a> {
+    protocol: &'a BusSpecificDriverOverride,
+    previous: Option<Handle>,
+    done: bool,
+}
+
+impl<'a> Iterator for BusSpecificDrivers<'a> {
+    type Item = Handle;
+
+    fn next(&mut self) -> Option<Handle> {
+        if self.done {
+            return None;
+        }
+        let mut handle = self.previous;
+        let status = unsafe { (self.protocol.get_driver)(self.protocol, &mut handle) };
+        if status.is_success() && handle.is_some() {
+            self.previous = handle;
+            handle
+        } else {
+            self.done = true;
+            None
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::proto::unsafe_protocol;
+use crate::table::boot::BootServices;
+use crate::{Handle, Result, Status};
+use core::ffi::c_void;
+use core::fmt::{self, Debug, Formatter};
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
+use core::slice;
+
+/// Protocol that reports the health of the controllers managed by a driver,
+/// and that can repair them.
+///
+/// The protocol is installed on the driver's image handle. Drivers that need
+/// user interaction to repair a controller report it with
+/// [`DriverHealthStatus::CONFIGURATION_REQUIRED`], and provide an HII form
+/// for it.
+///
+/// The corresponding C type is `EFI_DRIVER_HEALTH_PROTOCOL`.
+#[unsafe_protocol("2a534210-9280-41d8-ae79-cada01a2b127")]
+#[repr(C)]
+pub struct DriverHealth {
+    get_health_status: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller_handle: Option<Handle>,
+        child_handle: Option<Handle>,
+        health_status: *mut DriverHealthStatus,
+        message_list: *mut *mut HiiMessage,
+        form_hii_handle: *mut Option<HiiHandle>,
+    ) -> Status,
+    repair: unsafe extern "efiapi" fn(
+        this: *const Self,
+        controller_handle: Handle,
+        child_handle: Option<Handle>,
+        repair_notify: Option<RepairNotifyFn>,
+    ) -> Status,
+}
+
+type RepairNotifyFn = extern "efiapi" fn(value: usize, limit: usize) -> Status;
+
+impl DriverHealth {
+    /// Get the health of the driver, of one of the controllers it manages,
+    /// or of one of the child controllers it produced.
+    ///
+    /// If `controller_handle` is `None`, the combined health of all the
+    /// controllers managed by the driver is returned. `child_handle` can
+    /// only be set together with `controller_handle`.
+    ///
+    /// The message list is allocated by the driver, and is freed when the
+    /// returned report is dropped, which is why `boot_services` is needed.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the controller is not managed by the driver,
+    ///   or the child controller was not produced by it.
+    /// * [`Status::INVALID_PARAMETER`]: `child_handle` is set without
+    ///   `controller_handle`.
+    pub fn health_status<'a>(
+        &self,
+        boot_services: &'a BootServices,
+        controller_handle: Option<Handle>,
+        child_handle: Option<Handle>,
+    ) -> Result<HealthReport<'a>> {
+        let mut status = DriverHealthStatus::HEALTHY;
+        let mut messages = ptr::null_mut();
+        let mut form = None;
+        unsafe {
+            (self.get_health_status)(
+                self,
+                controller_handle,
+                child_handle,
+                &mut status,
+                &mut messages,
+                &mut form,
+            )
+        }
+        .into_with_val(|| HealthReport {
+            status,
+            messages: NonNull::new(messages).map(|messages| HiiMessageList {
+                boot_services,
+                messages,
+            }),
+            form,
+        })
+    }
+
+    /// Repair a controller managed by the driver, or a child controller it
+    /// produced.
+    ///
+    /// `progress` is called by the driver with the current progress of the
+    /// repair, as a `(value, limit)` pair where the repair is complete when
+    /// `value` reaches `limit`. Afterwards, [`health_status`] should be called
+    /// again, as the repair may require more steps.
+    ///
+    /// [`health_status`]: Self::health_status
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the controller is not managed by the driver,
+    ///   or the child controller was not produced by it.
+    /// * [`Status::OUT_OF_RESOURCES`]: not enough resources to repair the
+    ///   controller.
+    pub fn repair(
+        &self,
+        controller_handle: Handle,
+        child_handle: Option<Handle>,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result {
+        // The notify function has no context parameter, so pass the closure
+        // to it through a global. Save and restore the previous value in
+        // case a driver repairs other controllers through this protocol.
+        let mut progress: &mut dyn FnMut(usize, usize) = &mut progress;
+        let progress_ptr = (&mut progress as *mut &mut dyn FnMut(usize, usize)).cast::<c_void>();
+        let previous = unsafe { ptr::replace(ptr::addr_of_mut!(REPAIR_PROGRESS), progress_ptr) };
+        let status =
+            unsafe { (self.repair)(self, controller_handle, child_handle, Some(repair_notify)) };
+        unsafe { REPAIR_PROGRESS = previous };
+        status.into()
+    }
+}
+
+/// Closure passed to the active call of [`DriverHealth::repair`], as a
+/// pointer to a `&mut dyn FnMut(usize, usize)`.
+static mut REPAIR_PROGRESS: *mut c_void = ptr::null_mut();
+
+extern "efiapi" fn repair_notify(value: usize, limit: usize) -> Status {
+    let progress = unsafe { REPAIR_PROGRESS.cast::<&mut dyn FnMut(usize, usize)>() };
+    if let Some(progress) = unsafe { progress.as_mut() } {
+        progress(value, limit);
+    }
+    Status::SUCCESS
+}
+
+newtype_enum! {
+/// Health of a driver or controller, reported by
+/// [`DriverHealth::health_status`].
+pub enum DriverHealthStatus: u32 => {
+    /// The controller is healthy.
+    HEALTHY                 = 0,
+    /// The controller requires a repair with [`DriverHealth::repair`].
+    REPAIR_REQUIRED         = 1,
+    /// The controller requires configuration by the user, through the HII
+    /// form returned in the [`HealthReport`].
+    CONFIGURATION_REQUIRED  = 2,
+    /// The controller failed and cannot be repaired.
+    FAILED                  = 3,
+    /// The controller must be reconnected with
+    /// [`BootServices::disconnect_controller`] and
+    /// [`BootServices::connect_controller`].
+    RECONNECT_REQUIRED      = 4,
+    /// The platform must be rebooted.
+    REBOOT_REQUIRED         = 5,
+}}
+
+/// Opaque handle to a package list in the HII database.
+///
+/// The corresponding C type is `EFI_HII_HANDLE`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct HiiHandle(NonNull<c_void>);
+
+impl HiiHandle {
+    /// Get the underlying raw pointer.
+    #[must_use]
+    pub const fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
+
+/// A message from a driver about the health of a controller. The text of
+/// the message is a string in the HII database.
+///
+/// The corresponding C type is `EFI_DRIVER_HEALTH_HII_MESSAGE`.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct HiiMessage {
+    /// HII package list containing the message string. A null handle
+    /// terminates the list in firmware memory.
+    pub hii_handle: Option<HiiHandle>,
+    /// ID of the message string in the package list.
+    pub string_id: u16,
+    /// Driver-specific code identifying the message.
+    pub message_code: u64,
+}
+
+/// Result of [`DriverHealth::health_status`].
+#[derive(Debug)]
+pub struct HealthReport<'a> {
+    /// Health of the driver or controller.
+    pub status: DriverHealthStatus,
+    /// Messages describing the health of the controller, if any.
+    pub messages: Option<HiiMessageList<'a>>,
+    /// HII form to show to the user to configure the controller, if the
+    /// status is [`DriverHealthStatus::CONFIGURATION_REQUIRED`].
+    pub form: Option<HiiHandle>,
+}
+
+/// List of [`HiiMessage`]s returned by [`DriverHealth::health_status`].
+///
+/// The list is freed when dropped.
+pub struct HiiMessageList<'a> {
+    // The list is allocated by the driver with `allocate_pool`, and has to be
+    // freed by the caller.
+    boot_services: &'a BootServices,
+    messages: NonNull<HiiMessage>,
+}
+
+impl<'a> Deref for HiiMessageList<'a> {
+    type Target = [HiiMessage];
+
+    fn deref(&self) -> &Self::Target {
+        let mut len = 0;
+        unsafe {
+            while (*self.messages.as_ptr().add(len)).hii_handle.is_some() {
+                len += 1;
+            }
+            slice::from_raw_parts(self.messages.as_ptr(), len)
+        }
+    }
+}
+
+impl<'a> Debug for HiiMessageList<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'a> Drop for HiiMessageList<'a> {
+    fn drop(&mut self) {
+        // Ignore the result, we can't do anything about an error here.
+        let _ = self.boot_services.free_pool(self.messages.as_ptr().cast());
+    }
+}
@@ -1,5 +1,9 @@
 //! UEFI driver model protocols.
 
 mod component_name;
+mod driver_override;
+mod health;
 
 pub use component_name::*;
+pub use driver_override::*;
+pub use health::*;
@@ -0,0 +1,470 @@
+//! `Hash2` protocol.
+//!
+//! The hash protocol gives access to the hash implementations of the
+//! firmware, such as SHA-256. It is created on child handles by its service
+//! binding protocol:
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::proto::hash::{Hash2, Hash2Algorithm};
+//! use uefi::proto::service_binding::ServiceBindingProtocol;
+//!
+//! fn sha256(bt: &BootServices, data: &[u8]) -> uefi::Result<[u8; 32]> {
+//!     let handle = bt.get_handle_for_protocol::<ServiceBindingProtocol<Hash2>>()?;
+//!     let mut binding =
+//!         bt.open_protocol_exclusive::<ServiceBindingProtocol<Hash2>>(handle)?;
+//!     let child = binding.create_child()?;
+//!
+//!     let digest = bt
+//!         .open_protocol_exclusive::<Hash2>(child)
+//!         .and_then(|mut hash| hash.hash(Hash2Algorithm::SHA256, data));
+//!     binding.destroy_child(child)?;
+//!
+//!     let mut out = [0; 32];
+//!     out.copy_from_slice(digest?.as_bytes());
+//!     Ok(out)
+//! }
+//! ```
+
+use crate::proto::media::file::RegularFile;
+use crate::proto::unsafe_protocol;
+use crate::table::boot::{BootServices, LONG_OPERATION_WATCHDOG_TIMEOUT};
+use crate::{guid, Guid, Result, Status};
+use core::fmt;
+use core::ops::Deref;
+
+newtype_enum! {
+    /// Hash algorithms of the [`Hash2`] protocol.
+    pub enum Hash2Algorithm: Guid => {
+        /// MD5, with a 16-byte digest.
+        MD5 = guid!("0af7c79c-65b5-4319-b0ae-44ec484e4ad7"),
+        /// SHA-1, with a 20-byte digest.
+        SHA1 = guid!("2ae9d80f-3fb2-4095-b7b1-e93157b946b6"),
+        /// SHA-224, with a 28-byte digest.
+        SHA224 = guid!("8df01a06-9bd5-4bf7-b021-db4fd9ccf45b"),
+        /// SHA-256, with a 32-byte digest.
+        SHA256 = guid!("51aa59de-fdf2-4ea3-bc63-875fb7842ee9"),
+        /// SHA-384, with a 48-byte digest.
+        SHA384 = guid!("efa96432-de33-4dd2-aee6-328c33df777a"),
+        /// SHA-512, with a 64-byte digest.
+        SHA512 = guid!("caa4381e-750c-4770-b870-7a23b4e42130"),
+    }
+}
+
+impl Hash2Algorithm {
+    /// Get the size of the digest of this algorithm, or `None` for
+    /// vendor-defined algorithms. Use [`Hash2::hash_size`] to query the
+    /// firmware instead.
+    #[must_use]
+    pub const fn digest_size(self) -> Option<usize> {
+        match self {
+            Self::MD5 => Some(16),
+            Self::SHA1 => Some(20),
+            Self::SHA224 => Some(28),
+            Self::SHA256 => Some(32),
+            Self::SHA384 => Some(48),
+            Self::SHA512 => Some(64),
+            _ => None,
+        }
+    }
+}
+
+/// Hash2 protocol.
+///
+/// Instances are created with the
+/// [`ServiceBindingProtocol<Hash2>`](crate::proto::service_binding::ServiceBindingProtocol)
+/// protocol. Each instance can only compute one multi-part hash at a time.
+///
+/// The corresponding C type is `EFI_HASH2_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol(
+    "55b1d734-c5e1-49db-9647-b16afb0e305b",
+    service_binding = "da836f8d-217f-4ca0-99c2-1ca4e16077ea"
+)]
+pub struct Hash2 {
+    get_hash_size: unsafe extern "efiapi" fn(
+        this: *const Self,
+        algorithm: *const Hash2Algorithm,
+        hash_size: *mut usize,
+    ) -> Status,
+    hash: unsafe extern "efiapi" fn(
+        this: *const Self,
+        algorithm: *const Hash2Algorithm,
+        message: *const u8,
+        message_size: usize,
+        hash: *mut [u8; Hash2Digest::MAX_SIZE],
+    ) -> Status,
+    hash_init:
+        unsafe extern "efiapi" fn(this: *const Self, algorithm: *const Hash2Algorithm) -> Status,
+    hash_update: unsafe extern "efiapi" fn(
+        this: *const Self,
+        message: *const u8,
+        message_size: usize,
+    ) -> Status,
+    hash_final: unsafe extern "efiapi" fn(
+        this: *const Self,
+        hash: *mut [u8; Hash2Digest::MAX_SIZE],
+    ) -> Status,
+}
+
+impl Hash2 {
+    /// Get the size of the digest of `algorithm`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `algorithm` is not supported.
+    pub fn hash_size(&self, algorithm: Hash2Algorithm) -> Result<usize> {
+        let mut size = 0;
+        unsafe { (self.get_hash_size)(self, &algorithm, &mut size) }.into_with_val(|| size)
+    }
+
+    /// Hash `message` with `algorithm`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `algorithm` is not supported.
+    /// * [`Status::OUT_OF_RESOURCES`]: not enough memory to hash `message`.
+    pub fn hash(&mut self, algorithm: Hash2Algorithm, message: &[u8]) -> Result<Hash2Digest> {
+        let len = self.hash_size(algorithm)?.min(Hash2Digest::MAX_SIZE);
+        let mut bytes = [0; Hash2Digest::MAX_SIZE];
+        unsafe {
+            (self.hash)(
+                self,
+                &algorithm,
+                message.as_ptr(),
+                message.len(),
+                &mut bytes,
+            )
+        }
+        .into_with_val(|| Hash2Digest { bytes, len })
+    }
+
+    /// Start a multi-part hash with `algorithm`, to hash data which is not
+    /// available all at once.
+    ///
+    /// Dropping the returned context without calling
+    /// [`Hash2Context::finish`] aborts the hash.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `algorithm` is not supported.
+    /// * [`Status::ALREADY_STARTED`]: a multi-part hash is in progress.
+    pub fn start(&mut self, algorithm: Hash2Algorithm) -> Result<Hash2Context<'_>> {
+        let len = self.hash_size(algorithm)?.min(Hash2Digest::MAX_SIZE);
+        unsafe { (self.hash_init)(self, &algorithm) }.into_with_val(|| Hash2Context {
+            hash: self,
+            len,
+            finished: false,
+        })
+    }
+
+    fn hash_final(&mut self) -> Result<[u8; Hash2Digest::MAX_SIZE]> {
+        let mut bytes = [0; Hash2Digest::MAX_SIZE];
+        unsafe { (self.hash_final)(self, &mut bytes) }.into_with_val(|| bytes)
+    }
+}
+
+impl fmt::Debug for Hash2 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hash2").finish_non_exhaustive()
+    }
+}
+
+/// A multi-part hash in progress, created with [`Hash2::start`].
+#[derive(Debug)]
+pub struct Hash2Context<'a> {
+    hash: &'a mut Hash2,
+    len: usize,
+    finished: bool,
+}
+
+impl Hash2Context<'_> {
+    /// Add `message` to the hashed data.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::OUT_OF_RESOURCES`]: not enough memory to hash `message`.
+    pub fn update(&mut self, message: &[u8]) -> Result {
+        unsafe { (self.hash.hash_update)(self.hash, message.as_ptr(), message.len()) }.into()
+    }
+
+    /// Add the contents of `file`, from its current position to its end, to
+    /// the hashed data, reading `buffer.len()` bytes at a time. Returns the
+    /// number of bytes hashed.
+    ///
+    /// The watchdog timer is extended while the file is hashed, see
+    /// [`BootServices::with_watchdog_extended`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `buffer` is empty.
+    /// * Errors of [`RegularFile::read`], [`Hash2Context::update`] and
+    ///   [`BootServices::with_watchdog_extended`].
+    pub fn update_from_file(
+        &mut self,
+        bt: &BootServices,
+        file: &mut RegularFile,
+        buffer: &mut [u8],
+    ) -> Result<u64> {
+        if buffer.is_empty() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        bt.with_watchdog_extended(LONG_OPERATION_WATCHDOG_TIMEOUT, || {
+            let mut done = 0;
+            loop {
+                let len = file
+                    .read(buffer)
+                    .map_err(|err| err.into_err_without_payload())?;
+                if len == 0 {
+                    return Ok(done);
+                }
+                self.update(&buffer[..len])?;
+                done += len as u64;
+            }
+        })?
+    }
+
+    /// Finish the hash and return the digest of the hashed data.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::OUT_OF_RESOURCES`]: not enough memory to finish the hash.
+    pub fn finish(mut self) -> Result<Hash2Digest> {
+        self.finished = true;
+        let bytes = self.hash.hash_final()?;
+        Ok(Hash2Digest {
+            bytes,
+            len: self.len,
+        })
+    }
+}
+
+impl Drop for Hash2Context<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            // Finish the hash so that the protocol can start another one.
+            let _ = self.hash.hash_final();
+        }
+    }
+}
+
+/// A digest computed by the [`Hash2`] protocol.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Hash2Digest {
+    bytes: [u8; Self::MAX_SIZE],
+    len: usize,
+}
+
+impl Hash2Digest {
+    /// Maximum size of a digest.
+    pub const MAX_SIZE: usize = 64;
+
+    /// Get the bytes of the digest.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl Deref for Hash2Digest {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for Hash2Digest {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl fmt::Debug for Hash2Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.as_bytes() {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use core::cell::Cell;
+    use core::marker::PhantomData;
+
+    /// Fake protocol whose algorithm sums the bytes of the message.
+    #[repr(C)]
+    struct FakeHash2 {
+        proto: Hash2,
+        /// Sum of the bytes of the multi-part hash in progress.
+        state: Cell<Option<u8>>,
+    }
+
+    unsafe fn state<'a>(this: *const Hash2) -> &'a Cell<Option<u8>> {
+        &(*this.cast::<FakeHash2>()).state
+    }
+
+    unsafe extern "efiapi" fn get_hash_size(
+        _this: *const Hash2,
+        algorithm: *const Hash2Algorithm,
+        hash_size: *mut usize,
+    ) -> Status {
+        if *algorithm != Hash2Algorithm::SHA256 {
+            return Status::UNSUPPORTED;
+        }
+        *hash_size = 32;
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn hash(
+        _this: *const Hash2,
+        _algorithm: *const Hash2Algorithm,
+        message: *const u8,
+        message_size: usize,
+        hash: *mut [u8; Hash2Digest::MAX_SIZE],
+    ) -> Status {
+        let message = core::slice::from_raw_parts(message, message_size);
+        (*hash)[0] = message.iter().fold(0, |sum, b| sum.wrapping_add(*b));
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn hash_init(
+        this: *const Hash2,
+        _algorithm: *const Hash2Algorithm,
+    ) -> Status {
+        if state(this).get().is_some() {
+            return Status::ALREADY_STARTED;
+        }
+        state(this).set(Some(0));
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn hash_update(
+        this: *const Hash2,
+        message: *const u8,
+        message_size: usize,
+    ) -> Status {
+        let message = core::slice::from_raw_parts(message, message_size);
+        match state(this).get() {
+            Some(sum) => {
+                let sum = message.iter().fold(sum, |sum, b| sum.wrapping_add(*b));
+                state(this).set(Some(sum));
+                Status::SUCCESS
+            }
+            None => Status::NOT_READY,
+        }
+    }
+
+    unsafe extern "efiapi" fn hash_final(
+        this: *const Hash2,
+        hash: *mut [u8; Hash2Digest::MAX_SIZE],
+    ) -> Status {
+        match state(this).take() {
+            Some(sum) => {
+                (*hash)[0] = sum;
+                Status::SUCCESS
+            }
+            None => Status::NOT_READY,
+        }
+    }
+
+    #[test]
+    fn test_hash2() {
+        let mut fake = FakeHash2 {
+            proto: Hash2 {
+                get_hash_size,
+                hash,
+                hash_init,
+                hash_update,
+                hash_final,
+                _no_send_or_sync: PhantomData,
+            },
+            state: Cell::new(None),
+        };
+        let hash2 = &mut fake.proto;
+
+        assert_eq!(hash2.hash_size(Hash2Algorithm::SHA256).unwrap(), 32);
+        assert_eq!(
+            hash2.hash(Hash2Algorithm::MD5, b"").unwrap_err().status(),
+            Status::UNSUPPORTED
+        );
+        let digest = hash2.hash(Hash2Algorithm::SHA256, &[1, 2, 3]).unwrap();
+        assert_eq!(digest.len(), 32);
+        assert_eq!(digest[0], 6);
+        assert_eq!(format!("{digest:?}"), format!("06{}", "00".repeat(31)));
+
+        let mut ctx = hash2.start(Hash2Algorithm::SHA256).unwrap();
+        ctx.update(&[1, 2]).unwrap();
+        ctx.update(&[3, 4]).unwrap();
+        assert_eq!(ctx.finish().unwrap().as_bytes()[0], 10);
+
+        // Dropping a context resets the protocol.
+        let mut ctx = hash2.start(Hash2Algorithm::SHA256).unwrap();
+        ctx.update(&[1]).unwrap();
+        drop(ctx);
+        let ctx = hash2.start(Hash2Algorithm::SHA256).unwrap();
+        assert_eq!(ctx.finish().unwrap()[0], 0);
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_update_from_file() {
+        use crate::mock::{MockFileSystem, MockFirmware};
+        use crate::proto::media::file::{File, FileAttribute, FileMode};
+        use crate::proto::media::fs::SimpleFileSystem;
+
+        let mut firmware = MockFirmware::new();
+        let fs = MockFileSystem::new("MOCK");
+        fs.add_file("image.bin", &[1, 2, 3, 4, 5]);
+        let handle = firmware.install_file_system(&fs);
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut sfs = bt
+            .open_protocol_exclusive::<SimpleFileSystem>(handle)
+            .unwrap();
+        let mut file = sfs
+            .open_volume()
+            .unwrap()
+            .open(
+                crate::cstr16!("image.bin"),
+                FileMode::Read,
+                FileAttribute::empty(),
+            )
+            .unwrap()
+            .into_regular_file()
+            .unwrap();
+
+        let mut fake = FakeHash2 {
+            proto: Hash2 {
+                get_hash_size,
+                hash,
+                hash_init,
+                hash_update,
+                hash_final,
+                _no_send_or_sync: PhantomData,
+            },
+            state: Cell::new(None),
+        };
+        let mut ctx = fake.proto.start(Hash2Algorithm::SHA256).unwrap();
+        assert_eq!(
+            ctx.update_from_file(bt, &mut file, &mut [])
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+        let mut buffer = [0; 2];
+        assert_eq!(ctx.update_from_file(bt, &mut file, &mut buffer), Ok(5));
+        assert_eq!(ctx.finish().unwrap()[0], 15);
+    }
+
+    #[test]
+    fn test_digest_size() {
+        assert_eq!(Hash2Algorithm::SHA384.digest_size(), Some(48));
+        assert_eq!(Hash2Algorithm(Guid::default()).digest_size(), None);
+    }
+}
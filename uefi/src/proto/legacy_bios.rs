@@ -0,0 +1,589 @@
+//! Legacy BIOS protocol.
+//!
+//! This protocol is part of the Compatibility Support Module (CSM), which
+//! some x86 firmware implementations provide to boot legacy operating
+//! systems and run legacy option ROMs. It is not part of the UEFI
+//! specification, but of the Intel Framework CSM specification, and is
+//! usually only available if CSM is enabled in the firmware settings.
+
+#![cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+
+use crate::proto::device_path::DevicePath;
+use crate::proto::unsafe_protocol;
+use crate::{Result, Status};
+use bitflags::bitflags;
+use core::ffi::c_void;
+use core::{ptr, slice};
+
+/// Registers passed to and returned from legacy BIOS code.
+///
+/// The 16-bit and 8-bit registers are the low bits of the corresponding
+/// 32-bit registers, for instance `AH` is `(eax >> 8) as u8`.
+///
+/// The corresponding C type is `EFI_IA32_REGISTER_SET`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Ia32RegisterSet {
+    /// The `EAX` register.
+    pub eax: u32,
+    /// The `EBX` register.
+    pub ebx: u32,
+    /// The `ECX` register.
+    pub ecx: u32,
+    /// The `EDX` register.
+    pub edx: u32,
+    /// The `ESI` register.
+    pub esi: u32,
+    /// The `EDI` register.
+    pub edi: u32,
+    /// The `EFLAGS` register.
+    pub eflags: u32,
+    /// The `ES` segment register.
+    pub es: u16,
+    /// The `CS` segment register.
+    pub cs: u16,
+    /// The `SS` segment register.
+    pub ss: u16,
+    /// The `DS` segment register.
+    pub ds: u16,
+    /// The `FS` segment register.
+    pub fs: u16,
+    /// The `GS` segment register.
+    pub gs: u16,
+    /// The `EBP` register.
+    pub ebp: u32,
+    /// The `ESP` register.
+    pub esp: u32,
+}
+
+impl Ia32RegisterSet {
+    /// Bit of [`eflags`] set by most BIOS services to report an error.
+    ///
+    /// [`eflags`]: Self::eflags
+    pub const CARRY_FLAG: u32 = 0x0001;
+
+    /// Check whether the carry flag is set.
+    #[must_use]
+    pub const fn carry(&self) -> bool {
+        self.eflags & Self::CARRY_FLAG != 0
+    }
+}
+
+bitflags! {
+    /// Keyboard LEDs, used by [`LegacyBios::update_keyboard_led_status`].
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct KeyboardLeds: u8 {
+        /// Scroll lock LED.
+        const SCROLL_LOCK = 0x01;
+        /// Num lock LED.
+        const NUM_LOCK = 0x02;
+        /// Caps lock LED.
+        const CAPS_LOCK = 0x04;
+    }
+}
+
+newtype_enum! {
+/// Type of the device of a [`BbsEntry`], as defined by the BIOS Boot
+/// Specification.
+pub enum BbsDeviceType: u16 => {
+    /// Floppy drive.
+    FLOPPY          = 0x01,
+    /// Hard disk.
+    HARD_DISK       = 0x02,
+    /// CD-ROM drive.
+    CDROM           = 0x03,
+    /// PCMCIA device.
+    PCMCIA          = 0x04,
+    /// USB device.
+    USB             = 0x05,
+    /// Embedded network device.
+    EMBEDDED_NETWORK = 0x06,
+    /// Boot entry vector device, such as a network option ROM.
+    BEV_DEVICE      = 0x80,
+    /// Unknown device.
+    UNKNOWN         = 0xff,
+}}
+
+/// An entry of the BIOS Boot Specification (BBS) table, describing a legacy
+/// boot device. Returned by [`LegacyBios::bbs_table`].
+///
+/// The corresponding C type is `BBS_TABLE`.
+#[repr(C, packed)]
+#[derive(Clone, Copy, Debug)]
+pub struct BbsEntry {
+    boot_priority: u16,
+    bus: u32,
+    device: u32,
+    function: u32,
+    class: u8,
+    sub_class: u8,
+    mfg_string_offset: u16,
+    mfg_string_segment: u16,
+    device_type: BbsDeviceType,
+    status_flags: u16,
+    boot_handler_offset: u16,
+    boot_handler_segment: u16,
+    desc_string_offset: u16,
+    desc_string_segment: u16,
+    init_per_reserved: u32,
+    additional_irq13_handler: u32,
+    additional_irq18_handler: u32,
+    additional_irq19_handler: u32,
+    additional_irq40_handler: u32,
+    assigned_drive_number: u8,
+    additional_irq41_handler: u32,
+    additional_irq46_handler: u32,
+    ibv1: u32,
+    ibv2: u32,
+}
+
+impl BbsEntry {
+    /// Boot priority of entries that must not be booted from.
+    pub const DO_NOT_BOOT_FROM: u16 = 0xfffc;
+    /// Boot priority of entries that are booted from last.
+    pub const LOWEST_PRIORITY: u16 = 0xfffd;
+    /// Boot priority of entries that have not been prioritized yet.
+    pub const UNPRIORITIZED_ENTRY: u16 = 0xfffe;
+    /// Boot priority of unused entries.
+    pub const IGNORE_ENTRY: u16 = 0xffff;
+
+    /// Get the boot priority of the entry, where lower values are booted
+    /// first. Values from [`DO_NOT_BOOT_FROM`] and above have special
+    /// meanings.
+    ///
+    /// [`DO_NOT_BOOT_FROM`]: Self::DO_NOT_BOOT_FROM
+    #[must_use]
+    pub const fn boot_priority(&self) -> u16 {
+        self.boot_priority
+    }
+
+    /// Check whether the entry is unused.
+    #[must_use]
+    pub const fn is_ignored(&self) -> bool {
+        self.boot_priority == Self::IGNORE_ENTRY
+    }
+
+    /// Get the PCI bus, device, and function of the device.
+    #[must_use]
+    pub const fn pci_location(&self) -> (u32, u32, u32) {
+        (self.bus, self.device, self.function)
+    }
+
+    /// Get the PCI class and sub-class of the device.
+    #[must_use]
+    pub const fn class_code(&self) -> (u8, u8) {
+        (self.class, self.sub_class)
+    }
+
+    /// Get the type of the device.
+    #[must_use]
+    pub const fn device_type(&self) -> BbsDeviceType {
+        self.device_type
+    }
+
+    /// Get the status flags of the device, as defined by the BIOS Boot
+    /// Specification.
+    #[must_use]
+    pub const fn status_flags(&self) -> u16 {
+        self.status_flags
+    }
+
+    /// Get the drive number assigned to the device, such as 0x80 for the
+    /// first hard disk.
+    #[must_use]
+    pub const fn assigned_drive_number(&self) -> u8 {
+        self.assigned_drive_number
+    }
+
+    /// Get the physical address of the null-terminated ASCII description of
+    /// the device, or zero if there is none.
+    #[must_use]
+    pub const fn description_address(&self) -> u32 {
+        real_mode_address(self.desc_string_segment, self.desc_string_offset)
+    }
+
+    /// Get the physical address of the null-terminated ASCII name of the
+    /// manufacturer of the device, or zero if there is none.
+    #[must_use]
+    pub const fn manufacturer_address(&self) -> u32 {
+        real_mode_address(self.mfg_string_segment, self.mfg_string_offset)
+    }
+}
+
+/// Convert a real mode `segment:offset` pair to a physical address.
+const fn real_mode_address(segment: u16, offset: u16) -> u32 {
+    ((segment as u32) << 4) + offset as u32
+}
+
+/// The legacy BIOS protocol, used to call into the legacy BIOS of CSM
+/// enabled platforms and to boot legacy operating systems.
+///
+/// Only the BIOS call, boot, and BBS table functions are currently bound.
+///
+/// The corresponding C type is `EFI_LEGACY_BIOS_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("db9a1e3d-45cb-4abb-853b-e5387fdb2e2d")]
+pub struct LegacyBios {
+    int86: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        bios_int: u8,
+        regs: *mut Ia32RegisterSet,
+    ) -> bool,
+    far_call86: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        segment: u16,
+        offset: u16,
+        regs: *mut Ia32RegisterSet,
+        stack: *const c_void,
+        stack_size: usize,
+    ) -> bool,
+    check_pci_rom: usize,
+    install_pci_rom: usize,
+    legacy_boot: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        boot_option: *const c_void,
+        load_options_size: u32,
+        load_options: *const c_void,
+    ) -> Status,
+    update_keyboard_led_status:
+        unsafe extern "efiapi" fn(this: *mut Self, leds: KeyboardLeds) -> Status,
+    get_bbs_info: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        hdd_count: *mut u16,
+        hdd_info: *mut *const c_void,
+        bbs_count: *mut u16,
+        bbs_table: *mut *const BbsEntry,
+    ) -> Status,
+    shadow_all_legacy_oproms: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+    prepare_to_boot_efi: usize,
+    get_legacy_region: usize,
+    copy_legacy_region: usize,
+    boot_unconventional_device: usize,
+}
+
+impl LegacyBios {
+    /// Issue the software interrupt `interrupt` in real mode, with the
+    /// registers set to `regs`. The registers returned by the interrupt
+    /// handler are written back to `regs`.
+    ///
+    /// # Safety
+    ///
+    /// The interrupt handler runs legacy code with full access to the
+    /// machine. The caller must ensure that the interrupt and registers
+    /// don't corrupt memory or devices used by the firmware or the
+    /// application.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::DEVICE_ERROR`]: the carry flag was set by the interrupt
+    ///   handler, which most BIOS services use to report an error. The error
+    ///   code, if any, is in `regs`.
+    pub unsafe fn int86(&mut self, interrupt: u8, regs: &mut Ia32RegisterSet) -> Result {
+        if (self.int86)(self, interrupt, regs) {
+            Err(Status::DEVICE_ERROR.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Call the real mode function at `segment:offset`, with the registers
+    /// set to `regs`. The registers returned by the function are written
+    /// back to `regs`.
+    ///
+    /// If `stack` is not empty, it is copied to the real mode stack before
+    /// the call, for functions taking arguments on the stack.
+    ///
+    /// # Safety
+    ///
+    /// The function runs legacy code with full access to the machine. The
+    /// caller must ensure that it is a valid real mode function, and that
+    /// it doesn't corrupt memory or devices used by the firmware or the
+    /// application.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::DEVICE_ERROR`]: the carry flag was set by the function.
+    pub unsafe fn far_call86(
+        &mut self,
+        segment: u16,
+        offset: u16,
+        regs: &mut Ia32RegisterSet,
+        stack: &[u8],
+    ) -> Result {
+        let stack_ptr = if stack.is_empty() {
+            ptr::null()
+        } else {
+            stack.as_ptr().cast()
+        };
+        if (self.far_call86)(self, segment, offset, regs, stack_ptr, stack.len()) {
+            Err(Status::DEVICE_ERROR.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Boot a legacy operating system from `boot_option`, which must be a
+    /// device path starting with a [`BootSpecification`] node. The device
+    /// types and status flags of the node are those of the [`BbsEntry`]
+    /// to boot from.
+    ///
+    /// On success this function doesn't return, unless the legacy operating
+    /// system hands control back to the firmware.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the device path is not a BBS device
+    ///   path.
+    /// * [`Status::DEVICE_ERROR`]: booting failed.
+    ///
+    /// [`BootSpecification`]: crate::proto::device_path::bios_boot_spec::BootSpecification
+    pub fn legacy_boot(&mut self, boot_option: &DevicePath, load_options: &[u8]) -> Result {
+        let load_options_size =
+            u32::try_from(load_options.len()).map_err(|_| Status::INVALID_PARAMETER)?;
+        unsafe {
+            (self.legacy_boot)(
+                self,
+                boot_option.as_ffi_ptr().cast(),
+                load_options_size,
+                load_options.as_ptr().cast(),
+            )
+        }
+        .into()
+    }
+
+    /// Set the keyboard LEDs, and the corresponding state in the BIOS data
+    /// area.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::DEVICE_ERROR`]: the LEDs could not be set.
+    pub fn update_keyboard_led_status(&mut self, leds: KeyboardLeds) -> Result {
+        unsafe { (self.update_keyboard_led_status)(self, leds) }.into()
+    }
+
+    /// Get the BIOS Boot Specification (BBS) table, listing the legacy boot
+    /// devices. Unused entries are [ignored].
+    ///
+    /// # Errors
+    ///
+    /// None are documented by the specification.
+    ///
+    /// [ignored]: BbsEntry::is_ignored
+    pub fn bbs_table(&mut self) -> Result<&[BbsEntry]> {
+        let mut hdd_count = 0;
+        let mut hdd_info = ptr::null();
+        let mut bbs_count = 0;
+        let mut bbs_table = ptr::null();
+        unsafe {
+            (self.get_bbs_info)(
+                self,
+                &mut hdd_count,
+                &mut hdd_info,
+                &mut bbs_count,
+                &mut bbs_table,
+            )
+        }
+        .into_with_val(|| {
+            if bbs_table.is_null() {
+                &[][..]
+            } else {
+                unsafe { slice::from_raw_parts(bbs_table, usize::from(bbs_count)) }
+            }
+        })
+    }
+
+    /// Load and initialize all the legacy option ROMs, so that the devices
+    /// they support are added to the [BBS table].
+    ///
+    /// # Errors
+    ///
+    /// None are documented by the specification.
+    ///
+    /// [BBS table]: Self::bbs_table
+    pub fn shadow_all_legacy_oproms(&mut self) -> Result {
+        unsafe { (self.shadow_all_legacy_oproms)(self) }.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::marker::PhantomData;
+    use core::mem::size_of;
+
+    const fn bbs_entry(boot_priority: u16, device_type: BbsDeviceType) -> BbsEntry {
+        BbsEntry {
+            boot_priority,
+            bus: 0,
+            device: 0x1f,
+            function: 2,
+            class: 1,
+            sub_class: 6,
+            mfg_string_offset: 0,
+            mfg_string_segment: 0,
+            device_type,
+            status_flags: 0,
+            boot_handler_offset: 0,
+            boot_handler_segment: 0,
+            desc_string_offset: 0x10,
+            desc_string_segment: 0xf000,
+            init_per_reserved: 0,
+            additional_irq13_handler: 0,
+            additional_irq18_handler: 0,
+            additional_irq19_handler: 0,
+            additional_irq40_handler: 0,
+            assigned_drive_number: 0x80,
+            additional_irq41_handler: 0,
+            additional_irq46_handler: 0,
+            ibv1: 0,
+            ibv2: 0,
+        }
+    }
+
+    static BBS_TABLE: [BbsEntry; 2] = [
+        bbs_entry(0, BbsDeviceType::HARD_DISK),
+        bbs_entry(BbsEntry::IGNORE_ENTRY, BbsDeviceType::UNKNOWN),
+    ];
+
+    unsafe extern "efiapi" fn int86(
+        _this: *mut LegacyBios,
+        bios_int: u8,
+        regs: *mut Ia32RegisterSet,
+    ) -> bool {
+        let regs = &mut *regs;
+        // INT 13h, AH=41h: check for the disk extensions.
+        if bios_int == 0x13 && regs.eax >> 8 == 0x41 && regs.ebx == 0x55aa {
+            regs.ebx = 0xaa55;
+            regs.eflags &= !Ia32RegisterSet::CARRY_FLAG;
+            false
+        } else {
+            regs.eax = 0x0100;
+            regs.eflags |= Ia32RegisterSet::CARRY_FLAG;
+            true
+        }
+    }
+
+    unsafe extern "efiapi" fn get_bbs_info(
+        _this: *mut LegacyBios,
+        hdd_count: *mut u16,
+        hdd_info: *mut *const c_void,
+        bbs_count: *mut u16,
+        bbs_table: *mut *const BbsEntry,
+    ) -> Status {
+        hdd_count.write(0);
+        hdd_info.write(ptr::null());
+        bbs_count.write(BBS_TABLE.len() as u16);
+        bbs_table.write(BBS_TABLE.as_ptr());
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn far_call86(
+        _this: *mut LegacyBios,
+        _segment: u16,
+        _offset: u16,
+        _regs: *mut Ia32RegisterSet,
+        _stack: *const c_void,
+        _stack_size: usize,
+    ) -> bool {
+        true
+    }
+
+    unsafe extern "efiapi" fn legacy_boot(
+        _this: *mut LegacyBios,
+        _boot_option: *const c_void,
+        _load_options_size: u32,
+        _load_options: *const c_void,
+    ) -> Status {
+        Status::DEVICE_ERROR
+    }
+
+    unsafe extern "efiapi" fn update_keyboard_led_status(
+        _this: *mut LegacyBios,
+        _leds: KeyboardLeds,
+    ) -> Status {
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn shadow_all_legacy_oproms(_this: *mut LegacyBios) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    fn legacy_bios() -> LegacyBios {
+        LegacyBios {
+            int86,
+            far_call86,
+            check_pci_rom: 0,
+            install_pci_rom: 0,
+            legacy_boot,
+            update_keyboard_led_status,
+            get_bbs_info,
+            shadow_all_legacy_oproms,
+            prepare_to_boot_efi: 0,
+            get_legacy_region: 0,
+            copy_legacy_region: 0,
+            boot_unconventional_device: 0,
+            _no_send_or_sync: PhantomData,
+        }
+    }
+
+    #[test]
+    fn test_layout() {
+        assert_eq!(size_of::<Ia32RegisterSet>(), 48);
+        assert_eq!(size_of::<BbsEntry>(), 69);
+    }
+
+    #[test]
+    fn test_int86() {
+        let mut legacy_bios = legacy_bios();
+
+        let mut regs = Ia32RegisterSet {
+            eax: 0x4100,
+            ebx: 0x55aa,
+            edx: 0x80,
+            ..Default::default()
+        };
+        unsafe { legacy_bios.int86(0x13, &mut regs) }.unwrap();
+        assert_eq!(regs.ebx, 0xaa55);
+        assert!(!regs.carry());
+
+        let mut regs = Ia32RegisterSet::default();
+        assert_eq!(
+            unsafe { legacy_bios.int86(0x13, &mut regs) }
+                .unwrap_err()
+                .status(),
+            Status::DEVICE_ERROR
+        );
+        assert!(regs.carry());
+        assert_eq!(regs.eax, 0x0100);
+
+        let mut regs = Ia32RegisterSet::default();
+        assert!(unsafe { legacy_bios.far_call86(0xf000, 0, &mut regs, &[]) }.is_err());
+        legacy_bios
+            .update_keyboard_led_status(KeyboardLeds::NUM_LOCK | KeyboardLeds::CAPS_LOCK)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_bbs_table() {
+        let mut legacy_bios = legacy_bios();
+        let table = legacy_bios.bbs_table().unwrap();
+        assert_eq!(table.len(), 2);
+
+        let entry = &table[0];
+        assert!(!entry.is_ignored());
+        assert_eq!(entry.boot_priority(), 0);
+        assert_eq!(entry.device_type(), BbsDeviceType::HARD_DISK);
+        assert_eq!(entry.pci_location(), (0, 0x1f, 2));
+        assert_eq!(entry.class_code(), (1, 6));
+        assert_eq!(entry.assigned_drive_number(), 0x80);
+        assert_eq!(entry.description_address(), 0xf0010);
+        assert_eq!(entry.manufacturer_address(), 0);
+        assert!(table[1].is_ignored());
+
+        assert_eq!(
+            legacy_bios.shadow_all_legacy_oproms().unwrap_err().status(),
+            Status::UNSUPPORTED
+        );
+    }
+}
@@ -2,6 +2,7 @@
 
 use crate::{
     data_types::FromSliceWithNulError,
+    pe::PeImage,
     proto::device_path::{DevicePath, FfiDevicePath},
     proto::unsafe_protocol,
     table::boot::MemoryType,
@@ -33,10 +34,15 @@ pub struct LoadedImage {
     image_code_type: MemoryType,
     image_data_type: MemoryType,
     /// This is a callback that a loaded image can use to do cleanup. It is called by the
-    /// `UnloadImage` boot service.
-    unload: extern "efiapi" fn(image_handle: Handle) -> Status,
+    /// `UnloadImage` boot service. Images without it can't be unloaded once started.
+    unload: Option<UnloadFn>,
 }
 
+/// Function called by the `UnloadImage` boot service to unload a started
+/// image. It gets the handle of the image, and returns an error to refuse
+/// being unloaded.
+pub type UnloadFn = unsafe extern "efiapi" fn(image_handle: Handle) -> Status;
+
 /// Errors that can be raised during parsing of the load options.
 #[derive(Debug)]
 pub enum LoadOptionsError {
@@ -166,9 +172,70 @@ impl LoadedImage {
         self.load_options_size = size;
     }
 
+    /// Returns whether the image has an unload function, so that it can be
+    /// unloaded with [`BootServices::unload_image`] after it was started.
+    ///
+    /// [`BootServices::unload_image`]: crate::table::boot::BootServices::unload_image
+    #[must_use]
+    pub const fn is_unloadable(&self) -> bool {
+        self.unload.is_some()
+    }
+
+    /// Set the function called when the image is unloaded with
+    /// [`BootServices::unload_image`], or remove it with `None`. This is
+    /// usually done by a driver for its own image; see [`uefi::unload`] for
+    /// a safe wrapper.
+    ///
+    /// # Safety
+    ///
+    /// `unload` must stay valid as long as the image is loaded, and must
+    /// release all the resources of the image, such as installed protocol
+    /// interfaces and events, before returning success.
+    ///
+    /// [`BootServices::unload_image`]: crate::table::boot::BootServices::unload_image
+    /// [`uefi::unload`]: crate::unload
+    pub unsafe fn set_unload(&mut self, unload: Option<UnloadFn>) {
+        self.unload = unload;
+    }
+
     /// Returns the base address and the size in bytes of the loaded image.
     #[must_use]
     pub const fn info(&self) -> (*const c_void, u64) {
         (self.image_base, self.image_size)
     }
+
+    /// Returns the memory type of the image's code sections.
+    #[must_use]
+    pub const fn code_type(&self) -> MemoryType {
+        self.image_code_type
+    }
+
+    /// Returns the memory type of the image's data sections.
+    #[must_use]
+    pub const fn data_type(&self) -> MemoryType {
+        self.image_data_type
+    }
+
+    /// Get the bytes of the loaded image.
+    ///
+    /// Returns `None` if the image base is null.
+    #[must_use]
+    pub fn image_bytes(&self) -> Option<&[u8]> {
+        if self.image_base.is_null() {
+            None
+        } else {
+            let size = usize::try_from(self.image_size).ok()?;
+            unsafe { Some(slice::from_raw_parts(self.image_base.cast(), size)) }
+        }
+    }
+
+    /// Parse the PE/COFF headers of the loaded image, to get its entry
+    /// point and section layout.
+    ///
+    /// Returns `None` if the image base is null or the headers are not
+    /// valid.
+    #[must_use]
+    pub fn pe_image(&self) -> Option<PeImage<'_>> {
+        PeImage::new(self.image_bytes()?)
+    }
 }
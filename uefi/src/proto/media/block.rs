@@ -7,25 +7,25 @@ use crate::{Result, Status};
 #[repr(C)]
 #[unsafe_protocol("964e5b21-6459-11d2-8e39-00a0c969723b")]
 pub struct BlockIO {
-    revision: u64,
-    media: *const BlockIOMedia,
+    pub(crate) revision: u64,
+    pub(crate) media: *const BlockIOMedia,
 
-    reset: extern "efiapi" fn(this: &BlockIO, extended_verification: bool) -> Status,
-    read_blocks: extern "efiapi" fn(
+    pub(crate) reset: extern "efiapi" fn(this: &BlockIO, extended_verification: bool) -> Status,
+    pub(crate) read_blocks: extern "efiapi" fn(
         this: &BlockIO,
         media_id: u32,
         lba: Lba,
         buffer_size: usize,
         buffer: *mut u8,
     ) -> Status,
-    write_blocks: extern "efiapi" fn(
+    pub(crate) write_blocks: extern "efiapi" fn(
         this: &BlockIO,
         media_id: u32,
         lba: Lba,
         buffer_size: usize,
         buffer: *const u8,
     ) -> Status,
-    flush_blocks: extern "efiapi" fn(this: &BlockIO) -> Status,
+    pub(crate) flush_blocks: extern "efiapi" fn(this: &BlockIO) -> Status,
 }
 
 impl BlockIO {
@@ -44,7 +44,13 @@ impl BlockIO {
     /// # Errors
     /// * `uefi::Status::DEVICE_ERROR`  The block device is not functioning correctly and could not be reset.
     pub fn reset(&mut self, extended_verification: bool) -> Result {
-        (self.reset)(self, extended_verification).into()
+        trace_status!(
+            "BlockIO::reset",
+            (self.reset)(self, extended_verification),
+            "extended_verification={}",
+            extended_verification
+        )
+        .into()
     }
 
     /// Read the requested number of blocks from the device.
@@ -65,7 +71,15 @@ impl BlockIO {
     ///     proper alignment.
     pub fn read_blocks(&self, media_id: u32, lba: Lba, buffer: &mut [u8]) -> Result {
         let buffer_size = buffer.len();
-        (self.read_blocks)(self, media_id, lba, buffer_size, buffer.as_mut_ptr()).into()
+        trace_status!(
+            "BlockIO::read_blocks",
+            (self.read_blocks)(self, media_id, lba, buffer_size, buffer.as_mut_ptr()),
+            "media_id={}, lba={}, size={}",
+            media_id,
+            lba,
+            buffer_size
+        )
+        .into()
     }
 
     /// Writes the requested number of blocks to the device.
@@ -87,7 +101,15 @@ impl BlockIO {
     ///     on proper alignment.
     pub fn write_blocks(&mut self, media_id: u32, lba: Lba, buffer: &[u8]) -> Result {
         let buffer_size = buffer.len();
-        (self.write_blocks)(self, media_id, lba, buffer_size, buffer.as_ptr()).into()
+        trace_status!(
+            "BlockIO::write_blocks",
+            (self.write_blocks)(self, media_id, lba, buffer_size, buffer.as_ptr()),
+            "media_id={}, lba={}, size={}",
+            media_id,
+            lba,
+            buffer_size
+        )
+        .into()
     }
 
     /// Flushes all modified data to a physical block device.
@@ -96,7 +118,7 @@ impl BlockIO {
     /// * `uefi::Status::DEVICE_ERROR`          The device reported an error while attempting to write data.
     /// * `uefi::Status::NO_MEDIA`              There is no media in the device.
     pub fn flush_blocks(&mut self) -> Result {
-        (self.flush_blocks)(self).into()
+        trace_status!("BlockIO::flush_blocks", (self.flush_blocks)(self)).into()
     }
 }
 
@@ -107,23 +129,23 @@ pub type Lba = u64;
 #[repr(C)]
 #[derive(Debug)]
 pub struct BlockIOMedia {
-    media_id: u32,
-    removable_media: bool,
-    media_present: bool,
-    logical_partition: bool,
-    read_only: bool,
-    write_caching: bool,
+    pub(crate) media_id: u32,
+    pub(crate) removable_media: bool,
+    pub(crate) media_present: bool,
+    pub(crate) logical_partition: bool,
+    pub(crate) read_only: bool,
+    pub(crate) write_caching: bool,
 
-    block_size: u32,
-    io_align: u32,
-    last_block: Lba,
+    pub(crate) block_size: u32,
+    pub(crate) io_align: u32,
+    pub(crate) last_block: Lba,
 
     // Revision 2
-    lowest_aligned_lba: Lba,
-    logical_blocks_per_physical_block: u32,
+    pub(crate) lowest_aligned_lba: Lba,
+    pub(crate) logical_blocks_per_physical_block: u32,
 
     // Revision 3
-    optimal_transfer_length_granularity: u32,
+    pub(crate) optimal_transfer_length_granularity: u32,
 }
 
 impl BlockIOMedia {
@@ -46,7 +46,15 @@ impl DiskIo {
     /// * `uefi::status::NO_MEDIA`          There is no medium in the device.
     /// * `uefi::status::MEDIA_CHANGED`     `media_id` is not for the current medium.
     pub fn read_disk(&self, media_id: u32, offset: u64, buffer: &mut [u8]) -> Result {
-        (self.read_disk)(self, media_id, offset, buffer.len(), buffer.as_mut_ptr()).into()
+        trace_status!(
+            "DiskIo::read_disk",
+            (self.read_disk)(self, media_id, offset, buffer.len(), buffer.as_mut_ptr()),
+            "media_id={}, offset={}, size={}",
+            media_id,
+            offset,
+            buffer.len()
+        )
+        .into()
     }
 
     /// Writes bytes to the disk device.
@@ -65,7 +73,15 @@ impl DiskIo {
     /// * `uefi::status::MEDIA_CHANGED`     `media_id` is not for the current medium.
     /// * `uefi::status::WRITE_PROTECTED`   The device cannot be written to.
     pub fn write_disk(&mut self, media_id: u32, offset: u64, buffer: &[u8]) -> Result {
-        (self.write_disk)(self, media_id, offset, buffer.len(), buffer.as_ptr()).into()
+        trace_status!(
+            "DiskIo::write_disk",
+            (self.write_disk)(self, media_id, offset, buffer.len(), buffer.as_ptr()),
+            "media_id={}, offset={}, size={}",
+            media_id,
+            offset,
+            buffer.len()
+        )
+        .into()
     }
 }
 
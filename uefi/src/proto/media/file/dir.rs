@@ -3,7 +3,14 @@ use crate::data_types::Align;
 use crate::Result;
 use core::ffi::c_void;
 #[cfg(feature = "alloc")]
-use {crate::mem::make_boxed, alloc::boxed::Box};
+use {
+    super::{FileAttribute, FileMode, FileType},
+    crate::mem::{make_boxed, try_vec},
+    crate::progress::ProgressReporter,
+    crate::table::boot::{BootServices, LONG_OPERATION_WATCHDOG_TIMEOUT},
+    crate::{CStr16, Status},
+    alloc::boxed::Box,
+};
 #[cfg(all(feature = "unstable", feature = "alloc"))]
 use {alloc::alloc::Global, core::alloc::Allocator};
 
@@ -123,6 +130,57 @@ impl Directory {
     pub fn reset_entry_readout(&mut self) -> Result {
         self.0.set_position(0)
     }
+
+    /// Copy the file at `src` to `dest`, both relative to this directory, reading and
+    /// writing `chunk_size` bytes at a time. Returns the size of the file.
+    ///
+    /// An existing file at `dest` is replaced. The progress is reported to `reporter`, as
+    /// with [`RegularFile::copy_to`], which can be used to copy files between volumes. The
+    /// watchdog timer is extended during the copy, see
+    /// [`BootServices::with_watchdog_extended`].
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::ACCESS_DENIED`]: `src` or `dest` is a directory.
+    /// * [`uefi::Status::OUT_OF_RESOURCES`]: the buffer of `chunk_size` bytes can't be
+    ///   allocated.
+    ///
+    /// See also [`File::open`], [`File::delete`], [`RegularFile::copy_to`] and
+    /// [`BootServices::with_watchdog_extended`].
+    #[cfg(feature = "alloc")]
+    pub fn copy_file(
+        &mut self,
+        bt: &BootServices,
+        src: &CStr16,
+        dest: &CStr16,
+        chunk_size: usize,
+        reporter: &mut impl ProgressReporter,
+    ) -> Result<u64> {
+        let mut buffer = try_vec(0, chunk_size)?;
+        let mut src = self
+            .open(src, FileMode::Read, FileAttribute::empty())?
+            .into_regular_file()
+            .ok_or(Status::ACCESS_DENIED)?;
+
+        // Delete the existing file, so that none of its data is left after
+        // the end of the copy.
+        match self.open(dest, FileMode::ReadWrite, FileAttribute::empty()) {
+            Ok(existing) => match existing.into_type()? {
+                FileType::Regular(existing) => existing.delete()?,
+                FileType::Dir(_) => return Err(Status::ACCESS_DENIED.into()),
+            },
+            Err(err) if err.status() == Status::NOT_FOUND => {}
+            Err(err) => return Err(err),
+        }
+        let mut dest = self
+            .open(dest, FileMode::CreateReadWrite, FileAttribute::empty())?
+            .into_regular_file()
+            .ok_or(Status::ACCESS_DENIED)?;
+
+        bt.with_watchdog_extended(LONG_OPERATION_WATCHDOG_TIMEOUT, || {
+            src.copy_to(&mut dest, &mut buffer, reporter)
+        })?
+    }
 }
 
 impl File for Directory {
@@ -139,3 +197,52 @@ impl File for Directory {
         Ok(true)
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::cstr16;
+    use crate::mock::{MockFileSystem, MockFirmware};
+    use crate::proto::media::fs::SimpleFileSystem;
+    use core::ops::ControlFlow;
+
+    #[test]
+    fn test_copy_file() {
+        let mut firmware = MockFirmware::new();
+        let fs = MockFileSystem::new("MOCK");
+        fs.add_file("image.wim", &[7; 10]);
+        fs.add_file("old.wim", &[1; 20]);
+        let handle = firmware.install_file_system(&fs);
+
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut sfs = bt
+            .open_protocol_exclusive::<SimpleFileSystem>(handle)
+            .unwrap();
+        let mut root = sfs.open_volume().unwrap();
+
+        let mut chunks = 0;
+        let mut reporter = |_, _| {
+            chunks += 1;
+            ControlFlow::Continue(())
+        };
+        let copied = root
+            .copy_file(
+                bt,
+                cstr16!("image.wim"),
+                cstr16!("old.wim"),
+                4,
+                &mut reporter,
+            )
+            .unwrap();
+        assert_eq!(copied, 10);
+        assert_eq!(chunks, 3);
+        assert_eq!(fs.read_file("old.wim").unwrap(), [7; 10]);
+
+        let mut reporter = |_, _| ControlFlow::Continue(());
+        let err = root
+            .copy_file(bt, cstr16!("missing"), cstr16!("new.wim"), 4, &mut reporter)
+            .unwrap_err();
+        assert_eq!(err.status(), Status::NOT_FOUND);
+    }
+}
@@ -1,7 +1,7 @@
 use super::FileAttribute;
 use crate::data_types::Align;
 use crate::table::runtime::Time;
-use crate::{guid, CStr16, Char16, Guid, Identify};
+use crate::{guid, CStr16, Char16, Guid, Identify, Status};
 use core::ffi::c_void;
 use core::{mem, ptr};
 use ptr_meta::Pointee;
@@ -229,6 +229,73 @@ impl FileInfo {
     pub fn file_name(&self) -> &CStr16 {
         unsafe { CStr16::from_ptr(self.file_name.as_ptr()) }
     }
+
+    /// Timestamps of the file, without the ones that are not valid.
+    #[must_use]
+    pub fn times(&self) -> FileTimes {
+        let valid = |time: &Time| time.is_valid().then_some(*time);
+        FileTimes {
+            create: valid(&self.create_time),
+            last_access: valid(&self.last_access_time),
+            modification: valid(&self.modification_time),
+        }
+    }
+
+    /// Change the attribute bits, to be applied with `set_info()`.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::INVALID_PARAMETER`]: the [`FileAttribute::DIRECTORY`]
+    ///   bit doesn't match the current one, as the type of a file can't be
+    ///   changed.
+    pub fn set_attribute(&mut self, attribute: FileAttribute) -> crate::Result {
+        let directory = FileAttribute::DIRECTORY;
+        if attribute.contains(directory) != self.attribute.contains(directory) {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        self.attribute = attribute & FileAttribute::VALID_ATTR;
+        Ok(())
+    }
+
+    /// Change the timestamps, to be applied with `set_info()`. The
+    /// timestamps that are `None` are set to zero, so that `set_info()`
+    /// leaves them unchanged.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::INVALID_PARAMETER`]: a timestamp is not valid. The
+    ///   timestamps are left unchanged.
+    pub fn set_times(&mut self, times: FileTimes) -> crate::Result {
+        let to_raw = |time: Option<Time>| match time {
+            Some(time) if !time.is_valid() => Err(Status::INVALID_PARAMETER),
+            Some(time) => Ok(time),
+            None => Ok(Time::invalid()),
+        };
+        let create_time = to_raw(times.create)?;
+        let last_access_time = to_raw(times.last_access)?;
+        let modification_time = to_raw(times.modification)?;
+        self.create_time = create_time;
+        self.last_access_time = last_access_time;
+        self.modification_time = modification_time;
+        Ok(())
+    }
+}
+
+/// Timestamps of a file.
+///
+/// Returned by [`FileInfo::times`], and used to change the timestamps with
+/// [`FileInfo::set_times`] or [`File::set_times`]. A timestamp that is
+/// `None` is not valid when read, and is left unchanged when written.
+///
+/// [`File::set_times`]: super::File::set_times
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FileTimes {
+    /// Time when the file was created.
+    pub create: Option<Time>,
+    /// Time when the file was last accessed.
+    pub last_access: Option<Time>,
+    /// Time when the file's contents were last modified.
+    pub modification: Option<Time>,
 }
 
 impl Align for FileInfo {
@@ -16,12 +16,21 @@ use core::ffi::c_void;
 use core::fmt::Debug;
 use core::mem;
 use core::ptr;
+#[cfg(feature = "alloc")]
+use {
+    crate::util::div_ceil_usize,
+    crate::Error,
+    alloc::{boxed::Box, vec::Vec},
+    core::{mem::MaybeUninit, slice},
+    uefi::mem::{call_with_growing_buffer, make_boxed},
+};
 #[cfg(all(feature = "unstable", feature = "alloc"))]
 use {alloc::alloc::Global, core::alloc::Allocator};
-#[cfg(feature = "alloc")]
-use {alloc::boxed::Box, uefi::mem::make_boxed};
 
-pub use self::info::{FileInfo, FileProtocolInfo, FileSystemInfo, FileSystemVolumeLabel, FromUefi};
+pub use self::info::{
+    FileInfo, FileInfoCreationError, FileProtocolInfo, FileSystemInfo, FileSystemVolumeLabel,
+    FileTimes, FromUefi,
+};
 pub use self::{dir::Directory, regular::RegularFile};
 
 /// Common interface to `FileHandle`, `RegularFile`, and `Directory`.
@@ -67,15 +76,22 @@ pub trait File: Sized {
     ) -> Result<FileHandle> {
         let mut ptr = ptr::null_mut();
 
-        unsafe {
-            (self.imp().open)(
-                self.imp(),
-                &mut ptr,
-                filename.as_ptr(),
-                open_mode,
-                attributes,
-            )
-        }
+        trace_status!(
+            "File::open",
+            unsafe {
+                (self.imp().open)(
+                    self.imp(),
+                    &mut ptr,
+                    filename.as_ptr(),
+                    open_mode,
+                    attributes,
+                )
+            },
+            "filename={}, mode={:?}, attributes={:?}",
+            filename,
+            open_mode,
+            attributes
+        )
         .into_with_val(|| unsafe { FileHandle::new(ptr) })
     }
 
@@ -90,7 +106,7 @@ pub trait File: Sized {
     ///
     /// * [`uefi::Status::WARN_DELETE_FAILURE`]
     fn delete(mut self) -> Result {
-        let result = (self.imp().delete)(self.imp()).into();
+        let result = trace_status!("File::delete", (self.imp().delete)(self.imp())).into();
         mem::forget(self);
         result
     }
@@ -120,14 +136,20 @@ pub trait File: Sized {
     ) -> Result<&'buf mut Info, Option<usize>> {
         let mut buffer_size = buffer.len();
         Info::assert_aligned(buffer);
-        unsafe {
-            (self.imp().get_info)(
-                self.imp(),
-                &Info::GUID,
-                &mut buffer_size,
-                buffer.as_mut_ptr(),
-            )
-        }
+        trace_status!(
+            "File::get_info",
+            unsafe {
+                (self.imp().get_info)(
+                    self.imp(),
+                    &Info::GUID,
+                    &mut buffer_size,
+                    buffer.as_mut_ptr(),
+                )
+            },
+            "type={}, size={}",
+            Info::GUID,
+            buffer.len()
+        )
         .into_with(
             || unsafe { Info::from_uefi(buffer.as_mut_ptr().cast::<c_void>()) },
             |s| {
@@ -165,7 +187,14 @@ pub trait File: Sized {
     fn set_info<Info: FileProtocolInfo + ?Sized>(&mut self, info: &Info) -> Result {
         let info_ptr = (info as *const Info).cast::<c_void>();
         let info_size = mem::size_of_val(info);
-        unsafe { (self.imp().set_info)(self.imp(), &Info::GUID, info_size, info_ptr).into() }
+        trace_status!(
+            "File::set_info",
+            unsafe { (self.imp().set_info)(self.imp(), &Info::GUID, info_size, info_ptr) },
+            "type={}, size={}",
+            Info::GUID,
+            info_size
+        )
+        .into()
     }
 
     /// Flushes all modified data associated with the file handle to the device
@@ -181,13 +210,81 @@ pub trait File: Sized {
     /// * [`uefi::Status::ACCESS_DENIED`]
     /// * [`uefi::Status::VOLUME_FULL`]
     fn flush(&mut self) -> Result {
-        (self.imp().flush)(self.imp()).into()
+        trace_status!("File::flush", (self.imp().flush)(self.imp())).into()
+    }
+
+    /// Change the attributes of the file, e.g. to make it read-only or
+    /// hidden. The [`FileAttribute::DIRECTORY`] bit of `attributes` is
+    /// ignored, since the type of a file can't be changed.
+    ///
+    /// The specification only allows the read-only attribute to be removed
+    /// on its own, so other changes to a read-only file are done with a
+    /// second call to [`set_info`].
+    ///
+    /// # Errors
+    ///
+    /// See [`get_info`] and [`set_info`]. [`uefi::Status::ACCESS_DENIED`] is
+    /// returned if the file was opened with [`FileMode::Read`].
+    ///
+    /// [`get_info`]: Self::get_info
+    /// [`set_info`]: Self::set_info
+    #[cfg(feature = "alloc")]
+    fn set_attributes(&mut self, attributes: FileAttribute) -> Result {
+        let mut info = self.get_boxed_info::<FileInfo>()?;
+        let current = info.attribute();
+        let attributes = ((attributes - FileAttribute::DIRECTORY)
+            | (current & FileAttribute::DIRECTORY))
+            & FileAttribute::VALID_ATTR;
+        if attributes == current {
+            return Ok(());
+        }
+
+        // Leave the timestamps unchanged.
+        info.set_times(FileTimes::default())?;
+        let writable = current - FileAttribute::READ_ONLY;
+        if current.contains(FileAttribute::READ_ONLY) && attributes != writable {
+            info.set_attribute(writable)?;
+            self.set_info(&*info)?;
+        }
+        info.set_attribute(attributes)?;
+        self.set_info(&*info)
+    }
+
+    /// Change the timestamps of the file, e.g. to restore them from a
+    /// backup. Timestamps that are `None` are left unchanged.
+    ///
+    /// The read-only attribute of a read-only file is removed while the
+    /// timestamps are changed, and is then restored.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::INVALID_PARAMETER`]: a timestamp is not valid.
+    ///
+    /// See also [`get_info`] and [`set_info`]. [`uefi::Status::ACCESS_DENIED`]
+    /// is returned if the file was opened with [`FileMode::Read`].
+    ///
+    /// [`get_info`]: Self::get_info
+    /// [`set_info`]: Self::set_info
+    #[cfg(feature = "alloc")]
+    fn set_times(&mut self, times: FileTimes) -> Result {
+        let mut info = self.get_boxed_info::<FileInfo>()?;
+        let attributes = info.attribute();
+        info.set_times(times)?;
+        if !attributes.contains(FileAttribute::READ_ONLY) {
+            return self.set_info(&*info);
+        }
+
+        self.set_attributes(attributes - FileAttribute::READ_ONLY)?;
+        info.set_attribute(attributes - FileAttribute::READ_ONLY)?;
+        self.set_info(&*info)?;
+        self.set_attributes(attributes)
     }
 
     /// Wrapper around [`Self::get_boxed_info_in`] that uses the [`Global`] allocator.
     #[cfg(feature = "alloc")]
     fn get_boxed_info<Info: FileProtocolInfo + ?Sized + Debug>(&mut self) -> Result<Box<Info>> {
-        let fetch_data_fn = |buf| self.get_info::<Info>(buf);
+        let (storage, size) = read_info::<Info>(self)?;
+        let fetch_data_fn = |buf| copy_info::<Info>(&storage, size, buf);
         #[cfg(not(feature = "unstable"))]
         let file_info = make_boxed::<Info, _>(fetch_data_fn)?;
         #[cfg(feature = "unstable")]
@@ -201,7 +298,8 @@ pub trait File: Sized {
         &mut self,
         allocator: A,
     ) -> Result<Box<Info>> {
-        let fetch_data_fn = |buf| self.get_info::<Info>(buf);
+        let (storage, size) = read_info::<Info>(self)?;
+        let fetch_data_fn = |buf| copy_info::<Info>(&storage, size, buf);
         let file_info = make_boxed::<Info, _, A>(fetch_data_fn, allocator)?;
         Ok(file_info)
     }
@@ -219,6 +317,47 @@ pub trait File: Sized {
     fn is_directory(&self) -> Result<bool>;
 }
 
+/// Reads the `Info` of `file` into 8-byte aligned storage, which is grown
+/// until the info fits, since the info can change between the size query
+/// and the read. Returns the storage and the size of the info in bytes.
+#[cfg(feature = "alloc")]
+fn read_info<Info: FileProtocolInfo + ?Sized>(file: &mut impl File) -> Result<(Vec<u64>, usize)> {
+    const WORD: usize = mem::size_of::<u64>();
+    let mut size = 0;
+    let storage = call_with_growing_buffer(0, |words: &mut [MaybeUninit<u64>]| {
+        for word in words.iter_mut() {
+            word.write(0);
+        }
+        let buffer = unsafe {
+            slice::from_raw_parts_mut(words.as_mut_ptr().cast::<u8>(), mem::size_of_val(words))
+        };
+        match file.get_info::<Info>(buffer) {
+            Ok(info) => {
+                size = mem::size_of_val(info);
+                Ok(div_ceil_usize(size, WORD))
+            }
+            Err(err) => Err(err.map_data(|required| required.map(|n| div_ceil_usize(n, WORD)))),
+        }
+    })?;
+    Ok((storage, size))
+}
+
+/// Copies the first `size` bytes of `storage`, read by [`read_info`], to
+/// `buffer`, in the form expected by [`make_boxed`].
+#[cfg(feature = "alloc")]
+fn copy_info<'buf, Info: FileProtocolInfo + ?Sized>(
+    storage: &[u64],
+    size: usize,
+    buffer: &'buf mut [u8],
+) -> Result<&'buf mut Info, Option<usize>> {
+    if buffer.len() < size {
+        return Err(Error::new(Status::BUFFER_TOO_SMALL, Some(size)));
+    }
+    let bytes = unsafe { slice::from_raw_parts(storage.as_ptr().cast::<u8>(), size) };
+    buffer[..size].copy_from_slice(bytes);
+    Ok(unsafe { Info::from_uefi(buffer.as_mut_ptr().cast()) })
+}
+
 // Internal File helper methods to access the function pointer table.
 trait FileInternal: File {
     fn imp(&mut self) -> &mut FileImpl {
@@ -314,17 +453,17 @@ impl Drop for FileHandle {
 
 /// The function pointer table for the File protocol.
 #[repr(C)]
-pub(super) struct FileImpl {
-    revision: u64,
-    open: unsafe extern "efiapi" fn(
+pub(crate) struct FileImpl {
+    pub(crate) revision: u64,
+    pub(crate) open: unsafe extern "efiapi" fn(
         this: &mut FileImpl,
         new_handle: &mut *mut FileImpl,
         filename: *const Char16,
         open_mode: FileMode,
         attributes: FileAttribute,
     ) -> Status,
-    close: extern "efiapi" fn(this: &mut FileImpl) -> Status,
-    delete: extern "efiapi" fn(this: &mut FileImpl) -> Status,
+    pub(crate) close: extern "efiapi" fn(this: &mut FileImpl) -> Status,
+    pub(crate) delete: extern "efiapi" fn(this: &mut FileImpl) -> Status,
     /// # Read from Regular Files
     /// If `self` is not a directory, the function reads the requested number of bytes from the file
     /// at the file’s current position and returns them in `buffer`. If the read goes beyond the end
@@ -338,31 +477,31 @@ pub(super) struct FileImpl {
     /// position is not updated. `buffer_size` is set to be the size of the buffer needed to read
     /// the entry. On success, the current position is updated to the next directory entry. If there
     /// are no more directory entries, the read returns a zero-length buffer.
-    read: unsafe extern "efiapi" fn(
+    pub(crate) read: unsafe extern "efiapi" fn(
         this: &mut FileImpl,
         buffer_size: &mut usize,
         buffer: *mut u8,
     ) -> Status,
-    write: unsafe extern "efiapi" fn(
+    pub(crate) write: unsafe extern "efiapi" fn(
         this: &mut FileImpl,
         buffer_size: &mut usize,
         buffer: *const u8,
     ) -> Status,
-    get_position: extern "efiapi" fn(this: &mut FileImpl, position: &mut u64) -> Status,
-    set_position: extern "efiapi" fn(this: &mut FileImpl, position: u64) -> Status,
-    get_info: unsafe extern "efiapi" fn(
+    pub(crate) get_position: extern "efiapi" fn(this: &mut FileImpl, position: &mut u64) -> Status,
+    pub(crate) set_position: extern "efiapi" fn(this: &mut FileImpl, position: u64) -> Status,
+    pub(crate) get_info: unsafe extern "efiapi" fn(
         this: &mut FileImpl,
         information_type: &Guid,
         buffer_size: &mut usize,
         buffer: *mut u8,
     ) -> Status,
-    set_info: unsafe extern "efiapi" fn(
+    pub(crate) set_info: unsafe extern "efiapi" fn(
         this: &mut FileImpl,
         information_type: &Guid,
         buffer_size: usize,
         buffer: *const c_void,
     ) -> Status,
-    flush: extern "efiapi" fn(this: &mut FileImpl) -> Status,
+    pub(crate) flush: extern "efiapi" fn(this: &mut FileImpl) -> Status,
 }
 
 /// Disambiguates the file type. Returned by `File::into_type()`.
@@ -416,6 +555,66 @@ mod tests {
     use crate::{CString16, Identify};
     use ::alloc::vec;
 
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_set_attributes_and_times() {
+        use crate::cstr16;
+        use crate::mock::{MockFileSystem, MockFirmware};
+        use crate::proto::media::fs::SimpleFileSystem;
+
+        let mut firmware = MockFirmware::new();
+        let fs = MockFileSystem::new("MOCK");
+        fs.add_file("backup.img", &[1, 2, 3]);
+        let handle = firmware.install_file_system(&fs);
+
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut sfs = bt
+            .open_protocol_exclusive::<SimpleFileSystem>(handle)
+            .unwrap();
+        let mut file = sfs
+            .open_volume()
+            .unwrap()
+            .open(
+                cstr16!("backup.img"),
+                FileMode::ReadWrite,
+                FileAttribute::empty(),
+            )
+            .unwrap()
+            .into_regular_file()
+            .unwrap();
+
+        let created = Time::from_unix_timestamp(1_000_000_000, 0).unwrap();
+        let modified = Time::from_unix_timestamp(1_700_000_000, 0).unwrap();
+        let times = FileTimes {
+            create: Some(created),
+            last_access: None,
+            modification: Some(modified),
+        };
+        file.set_attributes(FileAttribute::READ_ONLY | FileAttribute::DIRECTORY)
+            .unwrap();
+        file.set_times(times).unwrap();
+        let info = file.get_boxed_info::<FileInfo>().unwrap();
+        assert_eq!(info.times(), times);
+        assert_eq!(info.attribute(), FileAttribute::READ_ONLY);
+        assert_eq!(info.file_size(), 3);
+
+        // A read-only file needs two transactions to become hidden.
+        file.set_attributes(FileAttribute::HIDDEN).unwrap();
+        let info = file.get_boxed_info::<FileInfo>().unwrap();
+        assert_eq!(info.attribute(), FileAttribute::HIDDEN);
+        assert_eq!(info.times(), times);
+
+        let invalid = FileTimes {
+            create: Some(Time::invalid()),
+            ..FileTimes::default()
+        };
+        assert_eq!(
+            file.set_times(invalid).unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+    }
+
     // Test `get_boxed_info` by setting up a fake file, which is mostly
     // just function pointers. Most of the functions can be empty, only
     // get_info is actually implemented to return useful data.
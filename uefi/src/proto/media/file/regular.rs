@@ -1,4 +1,5 @@
 use super::{File, FileHandle, FileInternal};
+use crate::progress::ProgressReporter;
 use crate::{Result, Status};
 
 /// A `FileHandle` that is also a regular (data) file.
@@ -27,6 +28,12 @@ impl RegularFile {
     /// Try to read as much as possible into `buffer`. Returns the number of bytes that were
     /// actually read.
     ///
+    /// Zero bytes are read at the end of the file, but the specification requires
+    /// [`uefi::Status::DEVICE_ERROR`] to be returned if the position is beyond the end of
+    /// the file. See [`read_at`] to read at any position.
+    ///
+    /// [`read_at`]: Self::read_at
+    ///
     /// # Arguments
     /// * `buffer`  The target buffer of the read operation
     ///
@@ -40,8 +47,12 @@ impl RegularFile {
     /// * [`uefi::Status::BUFFER_TOO_SMALL`]
     pub fn read(&mut self, buffer: &mut [u8]) -> Result<usize, Option<usize>> {
         let mut buffer_size = buffer.len();
-        let status =
-            unsafe { (self.imp().read)(self.imp(), &mut buffer_size, buffer.as_mut_ptr()) };
+        let status = trace_status!(
+            "File::read",
+            unsafe { (self.imp().read)(self.imp(), &mut buffer_size, buffer.as_mut_ptr()) },
+            "size={}",
+            buffer.len()
+        );
 
         status.into_with(
             || buffer_size,
@@ -57,6 +68,25 @@ impl RegularFile {
         )
     }
 
+    /// Read data from the file at `position`, and return the number of bytes read. Zero
+    /// bytes are read if `position` is at or beyond the end of the file, as in a sparse
+    /// file.
+    ///
+    /// The position of the file is moved after the bytes read, or is left unchanged if
+    /// nothing was read.
+    ///
+    /// # Errors
+    ///
+    /// See [`RegularFile::read`], [`RegularFile::size`] and [`RegularFile::set_position`].
+    pub fn read_at(&mut self, position: u64, buffer: &mut [u8]) -> Result<usize> {
+        if position >= self.size()? {
+            return Ok(0);
+        }
+        self.set_position(position)?;
+        self.read(buffer)
+            .map_err(|err| err.into_err_without_payload())
+    }
+
     /// Write data to file
     ///
     /// Write `buffer` to file, increment the file pointer.
@@ -79,8 +109,13 @@ impl RegularFile {
     /// * [`uefi::Status::VOLUME_FULL`]
     pub fn write(&mut self, buffer: &[u8]) -> Result<(), usize> {
         let mut buffer_size = buffer.len();
-        unsafe { (self.imp().write)(self.imp(), &mut buffer_size, buffer.as_ptr()) }
-            .into_with_err(|_| buffer_size)
+        trace_status!(
+            "File::write",
+            unsafe { (self.imp().write)(self.imp(), &mut buffer_size, buffer.as_ptr()) },
+            "size={}",
+            buffer.len()
+        )
+        .into_with_err(|_| buffer_size)
     }
 
     /// Get the file's current position
@@ -92,7 +127,11 @@ impl RegularFile {
     /// * [`uefi::Status::DEVICE_ERROR`]
     pub fn get_position(&mut self) -> Result<u64> {
         let mut pos = 0u64;
-        (self.imp().get_position)(self.imp(), &mut pos).into_with_val(|| pos)
+        trace_status!(
+            "File::get_position",
+            (self.imp().get_position)(self.imp(), &mut pos)
+        )
+        .into_with_val(|| pos)
     }
 
     /// Sets the file's current position
@@ -100,7 +139,13 @@ impl RegularFile {
     /// Set the position of this file handle to the absolute position specified by `position`.
     ///
     /// Seeking past the end of the file is allowed, it will trigger file growth on the next write.
-    /// Using a position of RegularFile::END_OF_FILE will seek to the end of the file.
+    /// Using a position of RegularFile::END_OF_FILE will seek to the end of the file, so that
+    /// the next writes append to it, see also [`seek_to_end`].
+    ///
+    /// Positions are 64-bit on all targets, so files larger than 4 GiB can be used on 32-bit
+    /// processors.
+    ///
+    /// [`seek_to_end`]: Self::seek_to_end
     ///
     /// # Arguments
     /// * `position` The new absolution position of the file handle
@@ -111,7 +156,100 @@ impl RegularFile {
     ///
     /// * [`uefi::Status::DEVICE_ERROR`]
     pub fn set_position(&mut self, position: u64) -> Result {
-        (self.imp().set_position)(self.imp(), position).into()
+        trace_status!(
+            "File::set_position",
+            (self.imp().set_position)(self.imp(), position),
+            "position={:#x}",
+            position
+        )
+        .into()
+    }
+
+    /// Move the position to the end of the file, so that the next writes append to it, and
+    /// return the new position, which is the size of the file.
+    ///
+    /// # Errors
+    ///
+    /// See [`RegularFile::set_position`] and [`RegularFile::get_position`].
+    pub fn seek_to_end(&mut self) -> Result<u64> {
+        self.set_position(Self::END_OF_FILE)?;
+        self.get_position()
+    }
+
+    /// Get the size of the file in bytes, without moving its position.
+    ///
+    /// # Errors
+    ///
+    /// See [`RegularFile::set_position`] and [`RegularFile::get_position`].
+    pub fn size(&mut self) -> Result<u64> {
+        let position = self.get_position()?;
+        let size = self.seek_to_end()?;
+        self.set_position(position)?;
+        Ok(size)
+    }
+
+    /// Copy the rest of this file, from its current position, to the
+    /// current position of `dest`, using `buffer` for the data in transit.
+    /// Returns the number of bytes copied, which is zero if the position is beyond the end
+    /// of the file.
+    ///
+    /// The number of bytes copied and the number of bytes to copy are
+    /// reported to `reporter` after each chunk. If it returns
+    /// [`ControlFlow::Break`], the copy stops after that chunk.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::ABORTED`]: the reporter stopped the copy.
+    /// * [`uefi::Status::INVALID_PARAMETER`]: `buffer` is empty.
+    ///
+    /// See also [`RegularFile::read`], [`RegularFile::write`],
+    /// [`RegularFile::get_position`] and [`RegularFile::set_position`].
+    ///
+    /// [`ControlFlow::Break`]: core::ops::ControlFlow::Break
+    pub fn copy_to(
+        &mut self,
+        dest: &mut RegularFile,
+        buffer: &mut [u8],
+        reporter: &mut impl ProgressReporter,
+    ) -> Result<u64> {
+        if buffer.is_empty() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        let start = self.get_position()?;
+        let end = self.seek_to_end()?;
+        // Reading beyond the end of the file fails, so stay at the end.
+        self.set_position(start.min(end))?;
+        let total = end.saturating_sub(start);
+
+        reporter.start();
+        let result = self.copy_chunks(dest, buffer, total, reporter);
+        reporter.finish();
+        result
+    }
+
+    fn copy_chunks(
+        &mut self,
+        dest: &mut RegularFile,
+        buffer: &mut [u8],
+        total: u64,
+        reporter: &mut impl ProgressReporter,
+    ) -> Result<u64> {
+        let mut done = 0;
+        loop {
+            let len = self
+                .read(buffer)
+                .map_err(|err| err.into_err_without_payload())?;
+            if len == 0 {
+                return Ok(done);
+            }
+            dest.write(&buffer[..len])
+                .map_err(|err| err.into_err_without_payload())?;
+            done += len as u64;
+            if reporter.update(done, total.max(done)).is_break() {
+                return Err(Status::ABORTED.into());
+            }
+        }
     }
 }
 
@@ -129,3 +267,111 @@ impl File for RegularFile {
         Ok(false)
     }
 }
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::cstr16;
+    use crate::mock::{MockFileSystem, MockFirmware};
+    use crate::proto::media::file::{FileAttribute, FileMode};
+    use crate::proto::media::fs::SimpleFileSystem;
+    use core::ops::ControlFlow;
+
+    #[test]
+    fn test_copy_to() {
+        let mut firmware = MockFirmware::new();
+        let fs = MockFileSystem::new("MOCK");
+        fs.add_file("src.bin", &[7; 10]);
+        let handle = firmware.install_file_system(&fs);
+
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut sfs = bt
+            .open_protocol_exclusive::<SimpleFileSystem>(handle)
+            .unwrap();
+        let mut root = sfs.open_volume().unwrap();
+        let mut open = |name, mode| {
+            root.open(name, mode, FileAttribute::empty())
+                .unwrap()
+                .into_regular_file()
+                .unwrap()
+        };
+        let mut src = open(cstr16!("src.bin"), FileMode::Read);
+        let mut dest = open(cstr16!("dest.bin"), FileMode::CreateReadWrite);
+
+        let mut buffer = [0; 4];
+        let mut updates = [(0, 0); 3];
+        let mut calls = 0;
+        let mut reporter = |done, total| {
+            updates[calls] = (done, total);
+            calls += 1;
+            ControlFlow::Continue(())
+        };
+        let copied = src.copy_to(&mut dest, &mut buffer, &mut reporter).unwrap();
+        assert_eq!(copied, 10);
+        assert_eq!(updates, [(4, 10), (8, 10), (10, 10)]);
+        drop(dest);
+        assert_eq!(fs.read_file("dest.bin").unwrap(), [7; 10]);
+
+        // Stop after the first chunk.
+        src.set_position(0).unwrap();
+        let mut dest = open(cstr16!("dest2.bin"), FileMode::CreateReadWrite);
+        let err = src
+            .copy_to(&mut dest, &mut buffer, &mut |_, _| ControlFlow::Break(()))
+            .unwrap_err();
+        assert_eq!(err.status(), Status::ABORTED);
+        drop(dest);
+        assert_eq!(fs.read_file("dest2.bin").unwrap(), [7; 4]);
+
+        // Nothing is copied from beyond the end.
+        src.set_position(20).unwrap();
+        let mut dest = open(cstr16!("dest3.bin"), FileMode::CreateReadWrite);
+        let mut reporter = |_, _| ControlFlow::Continue(());
+        assert_eq!(src.copy_to(&mut dest, &mut buffer, &mut reporter), Ok(0));
+    }
+
+    #[test]
+    fn test_positions() {
+        let mut firmware = MockFirmware::new();
+        let fs = MockFileSystem::new("MOCK");
+        fs.add_file("file.bin", &[1, 2, 3, 4]);
+        let handle = firmware.install_file_system(&fs);
+
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut sfs = bt
+            .open_protocol_exclusive::<SimpleFileSystem>(handle)
+            .unwrap();
+        let mut file = sfs
+            .open_volume()
+            .unwrap()
+            .open(
+                cstr16!("file.bin"),
+                FileMode::ReadWrite,
+                FileAttribute::empty(),
+            )
+            .unwrap()
+            .into_regular_file()
+            .unwrap();
+
+        file.set_position(1).unwrap();
+        assert_eq!(file.size(), Ok(4));
+        assert_eq!(file.get_position(), Ok(1));
+        assert_eq!(file.seek_to_end(), Ok(4));
+        file.write(&[5]).unwrap();
+
+        let mut buffer = [0; 8];
+        assert_eq!(file.read_at(3, &mut buffer), Ok(2));
+        assert_eq!(buffer[..2], [4, 5]);
+        assert_eq!(file.read_at(5, &mut buffer), Ok(0));
+        assert_eq!(file.read_at(1 << 40, &mut buffer), Ok(0));
+
+        // Writing beyond the end fills the gap with zeros.
+        file.set_position(7).unwrap();
+        let err = file.read(&mut buffer).unwrap_err();
+        assert_eq!(err.status(), Status::DEVICE_ERROR);
+        file.write(&[8]).unwrap();
+        assert_eq!(file.read_at(4, &mut buffer), Ok(4));
+        assert_eq!(buffer[..4], [5, 0, 0, 8]);
+    }
+}
@@ -22,12 +22,25 @@ use core::ptr;
 #[repr(C)]
 #[unsafe_protocol("964e5b22-6459-11d2-8e39-00a0c969723b")]
 pub struct SimpleFileSystem {
-    revision: u64,
-    open_volume:
+    pub(crate) revision: u64,
+    pub(crate) open_volume:
         extern "efiapi" fn(this: &mut SimpleFileSystem, root: &mut *mut FileImpl) -> Status,
 }
 
 impl SimpleFileSystem {
+    /// Create a protocol instance whose `OpenVolume` function is
+    /// `open_volume`.
+    #[cfg(feature = "alloc")]
+    pub(crate) const fn new(
+        open_volume: extern "efiapi" fn(this: &mut Self, root: &mut *mut FileImpl) -> Status,
+    ) -> Self {
+        Self {
+            revision: 0x0001_0000,
+            open_volume,
+            _no_send_or_sync: core::marker::PhantomData,
+        }
+    }
+
     /// Open the root directory on a volume.
     ///
     /// # Errors
@@ -48,7 +61,10 @@ impl SimpleFileSystem {
     /// * [`uefi::Status::MEDIA_CHANGED`]
     pub fn open_volume(&mut self) -> Result<Directory> {
         let mut ptr = ptr::null_mut();
-        (self.open_volume)(self, &mut ptr)
-            .into_with_val(|| unsafe { Directory::new(FileHandle::new(ptr)) })
+        trace_status!(
+            "SimpleFileSystem::open_volume",
+            (self.open_volume)(self, &mut ptr)
+        )
+        .into_with_val(|| unsafe { Directory::new(FileHandle::new(ptr)) })
     }
 }
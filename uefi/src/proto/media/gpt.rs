@@ -0,0 +1,617 @@
+//! GUID partition table (GPT) editing.
+//!
+//! [`GptPartitionTable`] reads the partition table of a disk through the
+//! [`BlockIO`] protocol, lets partitions be added, removed and resized, and
+//! writes the table back with a protective MBR and both the primary and
+//! backup headers.
+
+use super::block::{BlockIO, BlockIOMedia, Lba};
+use super::partition::{GptPartitionAttributes, GptPartitionEntry, GptPartitionType, MbrOsType};
+use crate::mem::{try_vec, try_with_capacity};
+use crate::table::boot::{BootServices, LONG_OPERATION_WATCHDOG_TIMEOUT};
+use crate::util::{crc32, crc32_update, div_ceil_usize};
+use crate::{Char16, Guid, Result, Status};
+use alloc::vec::Vec;
+use core::{mem, ptr, slice};
+
+/// Get an unused partition entry.
+fn unused_entry() -> GptPartitionEntry {
+    GptPartitionEntry {
+        partition_type_guid: GptPartitionType::UNUSED_ENTRY,
+        unique_partition_guid: Guid::default(),
+        starting_lba: 0,
+        ending_lba: 0,
+        attributes: GptPartitionAttributes::empty(),
+        partition_name: [Char16::default(); 36],
+    }
+}
+
+/// Fields of a GPT header, without the signature and CRCs.
+///
+/// The corresponding C type is `EFI_PARTITION_TABLE_HEADER`.
+#[derive(Debug)]
+struct GptHeader {
+    my_lba: Lba,
+    alternate_lba: Lba,
+    first_usable_lba: Lba,
+    last_usable_lba: Lba,
+    disk_guid: Guid,
+    partition_entry_lba: Lba,
+    number_of_partition_entries: u32,
+    size_of_partition_entry: u32,
+    partition_entry_array_crc32: u32,
+}
+
+impl GptHeader {
+    const SIGNATURE: [u8; 8] = *b"EFI PART";
+    const REVISION: u32 = 0x0001_0000;
+    const SIZE: usize = 92;
+
+    /// Parse the header at the start of `block`. Returns `None` if the
+    /// signature, size or CRC is invalid.
+    fn parse(block: &[u8]) -> Option<Self> {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap());
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(block[offset..offset + 8].try_into().unwrap());
+
+        if block.len() < Self::SIZE || block[..8] != Self::SIGNATURE {
+            return None;
+        }
+        let header_size = u32_at(12) as usize;
+        if header_size < Self::SIZE || header_size > block.len() {
+            return None;
+        }
+        // The CRC is computed with the CRC field zeroed.
+        let crc = crc32_update(crc32(&block[..16]), &[0; 4]);
+        if crc32_update(crc, &block[20..header_size]) != u32_at(16) {
+            return None;
+        }
+
+        Some(Self {
+            my_lba: u64_at(24),
+            alternate_lba: u64_at(32),
+            first_usable_lba: u64_at(40),
+            last_usable_lba: u64_at(48),
+            disk_guid: Guid::from_bytes(block[56..72].try_into().unwrap()),
+            partition_entry_lba: u64_at(72),
+            number_of_partition_entries: u32_at(80),
+            size_of_partition_entry: u32_at(84),
+            partition_entry_array_crc32: u32_at(88),
+        })
+    }
+
+    /// Write the header and its CRC at the start of `block`.
+    fn write(&self, block: &mut [u8]) {
+        let block = &mut block[..Self::SIZE];
+        block[..8].copy_from_slice(&Self::SIGNATURE);
+        block[8..12].copy_from_slice(&Self::REVISION.to_le_bytes());
+        block[12..16].copy_from_slice(&(Self::SIZE as u32).to_le_bytes());
+        block[16..24].fill(0);
+        block[24..32].copy_from_slice(&self.my_lba.to_le_bytes());
+        block[32..40].copy_from_slice(&self.alternate_lba.to_le_bytes());
+        block[40..48].copy_from_slice(&self.first_usable_lba.to_le_bytes());
+        block[48..56].copy_from_slice(&self.last_usable_lba.to_le_bytes());
+        block[56..72].copy_from_slice(&self.disk_guid.to_bytes());
+        block[72..80].copy_from_slice(&self.partition_entry_lba.to_le_bytes());
+        block[80..84].copy_from_slice(&self.number_of_partition_entries.to_le_bytes());
+        block[84..88].copy_from_slice(&self.size_of_partition_entry.to_le_bytes());
+        block[88..92].copy_from_slice(&self.partition_entry_array_crc32.to_le_bytes());
+        let crc = crc32(block);
+        block[16..20].copy_from_slice(&crc.to_le_bytes());
+    }
+}
+
+/// An in-memory copy of the GUID partition table of a disk.
+///
+/// The table is read with [`read`] or created empty with [`new`], edited
+/// with [`add`], [`remove`] and [`resize`], and written back to the disk
+/// with [`write`]. Nothing is written to the disk before [`write`] is
+/// called.
+///
+/// Only tables whose entries are 128 bytes are supported, which is the size
+/// used by all known partitioning tools.
+///
+/// [`read`]: Self::read
+/// [`new`]: Self::new
+/// [`add`]: Self::add
+/// [`remove`]: Self::remove
+/// [`resize`]: Self::resize
+/// [`write`]: Self::write
+#[derive(Debug, Clone)]
+pub struct GptPartitionTable {
+    block_size: usize,
+    last_block: Lba,
+    disk_guid: Guid,
+    first_usable_lba: Lba,
+    last_usable_lba: Lba,
+    entries: Vec<GptPartitionEntry>,
+}
+
+impl GptPartitionTable {
+    /// Number of entries of a new table.
+    pub const DEFAULT_ENTRIES: usize = 128;
+
+    /// Size of a partition entry, in bytes.
+    pub const ENTRY_SIZE: usize = mem::size_of::<GptPartitionEntry>();
+
+    /// Maximum number of entries of a table read from a disk.
+    const MAX_ENTRIES: usize = 4096;
+
+    /// Create an empty table for the disk described by `media`, with
+    /// [`DEFAULT_ENTRIES`] entries. `disk_guid` must be unique, such as a
+    /// random GUID.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: the disk is too small to hold a
+    ///   partition table, or its block size is smaller than 512 bytes.
+    ///
+    /// [`DEFAULT_ENTRIES`]: Self::DEFAULT_ENTRIES
+    pub fn new(media: &BlockIOMedia, disk_guid: Guid) -> Result<Self> {
+        let block_size = media.block_size() as usize;
+        let last_block = media.last_block();
+        if block_size < 512 {
+            return Err(Status::BAD_BUFFER_SIZE.into());
+        }
+        let entry_blocks = Self::entry_blocks(block_size, Self::DEFAULT_ENTRIES);
+
+        // MBR, header and entries at the start of the disk, entries and
+        // header at the end, and at least one usable block.
+        let first_usable_lba = 2 + entry_blocks;
+        let last_usable_lba = last_block
+            .checked_sub(1 + entry_blocks)
+            .filter(|&lba| lba >= first_usable_lba)
+            .ok_or(Status::BAD_BUFFER_SIZE)?;
+
+        Ok(Self {
+            block_size,
+            last_block,
+            disk_guid,
+            first_usable_lba,
+            last_usable_lba,
+            entries: try_vec(unused_entry(), Self::DEFAULT_ENTRIES)?,
+        })
+    }
+
+    /// Read the partition table of a disk. If the primary table is
+    /// corrupted, the backup table at the end of the disk is used.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::VOLUME_CORRUPTED`]: neither table is valid.
+    /// * [`Status::UNSUPPORTED`]: the entries are not 128 bytes.
+    /// * Errors of [`BlockIO::read_blocks`].
+    pub fn read(block_io: &BlockIO) -> Result<Self> {
+        match Self::read_at(block_io, 1) {
+            Err(err) if err.status() == Status::VOLUME_CORRUPTED => {
+                Self::read_at(block_io, block_io.media().last_block())
+            }
+            result => result,
+        }
+    }
+
+    /// Read the table whose header is at `lba`.
+    fn read_at(block_io: &BlockIO, lba: Lba) -> Result<Self> {
+        let media = block_io.media();
+        let block_size = media.block_size() as usize;
+        let mut block = try_vec(0, block_size)?;
+        block_io.read_blocks(media.media_id(), lba, &mut block)?;
+
+        let header = GptHeader::parse(&block)
+            .filter(|header| {
+                header.my_lba == lba
+                    && header.first_usable_lba <= header.last_usable_lba
+                    && header.last_usable_lba < media.last_block()
+            })
+            .ok_or(Status::VOLUME_CORRUPTED)?;
+        if header.size_of_partition_entry as usize != Self::ENTRY_SIZE {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        let count = header.number_of_partition_entries as usize;
+        if count > Self::MAX_ENTRIES {
+            return Err(Status::VOLUME_CORRUPTED.into());
+        }
+
+        let mut bytes = try_vec(
+            0,
+            Self::entry_blocks(block_size, count) as usize * block_size,
+        )?;
+        block_io.read_blocks(media.media_id(), header.partition_entry_lba, &mut bytes)?;
+        let bytes = &bytes[..count * Self::ENTRY_SIZE];
+        if crc32(bytes) != header.partition_entry_array_crc32 {
+            return Err(Status::VOLUME_CORRUPTED.into());
+        }
+        let mut entries = try_with_capacity(count)?;
+        entries.extend(
+            bytes
+                .chunks_exact(Self::ENTRY_SIZE)
+                // Safety: the chunk has the size of an entry, and any bytes
+                // are a valid entry.
+                .map(|chunk| unsafe {
+                    ptr::read_unaligned(chunk.as_ptr().cast::<GptPartitionEntry>())
+                }),
+        );
+
+        Ok(Self {
+            block_size,
+            last_block: media.last_block(),
+            disk_guid: header.disk_guid,
+            first_usable_lba: header.first_usable_lba,
+            last_usable_lba: header.last_usable_lba,
+            entries,
+        })
+    }
+
+    /// Write the table to the disk: a protective MBR in the first block,
+    /// then the backup entries and header at the end of the disk, then the
+    /// primary header and entries. The CRCs of the headers and entries are
+    /// recomputed.
+    ///
+    /// The protective MBR replaces any existing MBR, including hybrid MBRs
+    /// and legacy boot code. The watchdog timer is extended while the table
+    /// is written, see [`BootServices::with_watchdog_extended`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::MEDIA_CHANGED`]: the size of the disk changed since the
+    ///   table was read or created.
+    /// * [`Status::VOLUME_FULL`]: the backup entries would overlap the last
+    ///   usable block.
+    /// * Errors of [`BlockIO::write_blocks`], [`BlockIO::flush_blocks`] and
+    ///   [`BootServices::with_watchdog_extended`].
+    pub fn write(&self, bt: &BootServices, block_io: &mut BlockIO) -> Result {
+        bt.with_watchdog_extended(LONG_OPERATION_WATCHDOG_TIMEOUT, || {
+            self.write_table(block_io)
+        })?
+    }
+
+    fn write_table(&self, block_io: &mut BlockIO) -> Result {
+        let media = block_io.media();
+        let media_id = media.media_id();
+        if media.block_size() as usize != self.block_size || media.last_block() != self.last_block {
+            return Err(Status::MEDIA_CHANGED.into());
+        }
+
+        let entry_blocks = Self::entry_blocks(self.block_size, self.entries.len());
+        let backup_entry_lba = self.last_block - entry_blocks;
+        if self.last_usable_lba >= backup_entry_lba || self.first_usable_lba < 2 + entry_blocks {
+            return Err(Status::VOLUME_FULL.into());
+        }
+
+        let mut entries = try_vec(0, entry_blocks as usize * self.block_size)?;
+        for (chunk, entry) in entries
+            .chunks_exact_mut(Self::ENTRY_SIZE)
+            .zip(&self.entries)
+        {
+            // Safety: an entry is plain old data.
+            let bytes = unsafe {
+                slice::from_raw_parts(
+                    (entry as *const GptPartitionEntry).cast::<u8>(),
+                    Self::ENTRY_SIZE,
+                )
+            };
+            chunk.copy_from_slice(bytes);
+        }
+        let mut header = GptHeader {
+            my_lba: self.last_block,
+            alternate_lba: 1,
+            first_usable_lba: self.first_usable_lba,
+            last_usable_lba: self.last_usable_lba,
+            disk_guid: self.disk_guid,
+            partition_entry_lba: backup_entry_lba,
+            number_of_partition_entries: self.entries.len() as u32,
+            size_of_partition_entry: Self::ENTRY_SIZE as u32,
+            partition_entry_array_crc32: crc32(&entries[..self.entries.len() * Self::ENTRY_SIZE]),
+        };
+        let mut block = try_vec(0, self.block_size)?;
+
+        self.write_protective_mbr(&mut block);
+        block_io.write_blocks(media_id, 0, &block)?;
+
+        // Write the backup first, so that the disk always has a valid
+        // table if the write is interrupted.
+        block_io.write_blocks(media_id, backup_entry_lba, &entries)?;
+        block.fill(0);
+        header.write(&mut block);
+        block_io.write_blocks(media_id, self.last_block, &block)?;
+
+        header.my_lba = 1;
+        header.alternate_lba = self.last_block;
+        header.partition_entry_lba = 2;
+        block_io.write_blocks(media_id, 2, &entries)?;
+        block.fill(0);
+        header.write(&mut block);
+        block_io.write_blocks(media_id, 1, &block)?;
+
+        block_io.flush_blocks()
+    }
+
+    /// Write a protective MBR, with a single partition covering the whole
+    /// disk, in `block`.
+    fn write_protective_mbr(&self, block: &mut [u8]) {
+        block.fill(0);
+        let size = self.last_block.min(u64::from(u32::MAX)) as u32;
+        let record = &mut block[446..462];
+        // Starting CHS of LBA 1.
+        record[1..4].copy_from_slice(&[0x00, 0x02, 0x00]);
+        record[4] = MbrOsType::GPT_PROTECTIVE.0;
+        record[5..8].copy_from_slice(&[0xff, 0xff, 0xff]);
+        record[8..12].copy_from_slice(&1u32.to_le_bytes());
+        record[12..16].copy_from_slice(&size.to_le_bytes());
+        block[510..512].copy_from_slice(&[0x55, 0xaa]);
+    }
+
+    /// Get the number of blocks holding `count` entries.
+    fn entry_blocks(block_size: usize, count: usize) -> u64 {
+        div_ceil_usize(count * Self::ENTRY_SIZE, block_size) as u64
+    }
+
+    /// Get the GUID of the disk.
+    #[must_use]
+    pub const fn disk_guid(&self) -> Guid {
+        self.disk_guid
+    }
+
+    /// Set the GUID of the disk.
+    pub fn set_disk_guid(&mut self, disk_guid: Guid) {
+        self.disk_guid = disk_guid;
+    }
+
+    /// Get the first block which can be used by a partition.
+    #[must_use]
+    pub const fn first_usable_lba(&self) -> Lba {
+        self.first_usable_lba
+    }
+
+    /// Get the last block which can be used by a partition.
+    #[must_use]
+    pub const fn last_usable_lba(&self) -> Lba {
+        self.last_usable_lba
+    }
+
+    /// Get all the entries of the table, including the unused ones.
+    #[must_use]
+    pub fn entries(&self) -> &[GptPartitionEntry] {
+        &self.entries
+    }
+
+    /// Get an iterator over the used entries of the table and their
+    /// indices.
+    pub fn partitions(&self) -> impl Iterator<Item = (usize, &GptPartitionEntry)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.is_used())
+    }
+
+    /// Add a partition in the first unused entry, and return the index of
+    /// the entry.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the entry is unused, its blocks are
+    ///   not usable, or it overlaps another partition.
+    /// * [`Status::OUT_OF_RESOURCES`]: all the entries are used.
+    pub fn add(&mut self, entry: GptPartitionEntry) -> Result<usize> {
+        if !entry.is_used() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        self.check_range(entry.starting_lba, entry.ending_lba, None)?;
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| !entry.is_used())
+            .ok_or(Status::OUT_OF_RESOURCES)?;
+        self.entries[index] = entry;
+        Ok(index)
+    }
+
+    /// Remove the partition of the entry at `index`, and return the entry.
+    /// The data of the partition is left on the disk.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the entry is unused or doesn't exist.
+    pub fn remove(&mut self, index: usize) -> Result<GptPartitionEntry> {
+        match self.entries.get_mut(index) {
+            Some(entry) if entry.is_used() => Ok(mem::replace(entry, unused_entry())),
+            _ => Err(Status::NOT_FOUND.into()),
+        }
+    }
+
+    /// Move the last block of the partition of the entry at `index` to
+    /// `ending_lba`. The data of the partition is not modified, so a file
+    /// system on it must be resized separately.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the entry is unused or doesn't exist.
+    /// * [`Status::INVALID_PARAMETER`]: the new blocks are not usable, or
+    ///   they overlap another partition.
+    pub fn resize(&mut self, index: usize, ending_lba: Lba) -> Result {
+        let starting_lba = match self.entries.get(index) {
+            Some(entry) if entry.is_used() => entry.starting_lba,
+            _ => return Err(Status::NOT_FOUND.into()),
+        };
+        self.check_range(starting_lba, ending_lba, Some(index))?;
+        self.entries[index].ending_lba = ending_lba;
+        Ok(())
+    }
+
+    /// Find the first free range of `num_blocks` blocks whose start is a
+    /// multiple of `alignment` blocks, and return its first block.
+    ///
+    /// Partitions are usually aligned to 1 MiB, which is 2048 blocks of
+    /// 512 bytes.
+    #[must_use]
+    pub fn find_free(&self, num_blocks: u64, alignment: u64) -> Option<Lba> {
+        let align = |lba: Lba| -> Option<Lba> {
+            let alignment = alignment.max(1);
+            lba.checked_add(alignment - 1)
+                .map(|lba| lba / alignment * alignment)
+        };
+        if num_blocks == 0 {
+            return None;
+        }
+
+        let mut used: Vec<(Lba, Lba)> = self
+            .partitions()
+            .map(|(_, entry)| (entry.starting_lba, entry.ending_lba))
+            .collect();
+        used.sort_unstable();
+
+        let mut start = align(self.first_usable_lba)?;
+        for (used_start, used_end) in used {
+            let end = start.checked_add(num_blocks - 1)?;
+            if end < used_start {
+                break;
+            }
+            start = start.max(align(used_end.checked_add(1)?)?);
+        }
+        let end = start.checked_add(num_blocks - 1)?;
+        (end <= self.last_usable_lba).then_some(start)
+    }
+
+    /// Check that the blocks from `start` to `end` can be used by the
+    /// partition of the entry at `index`.
+    fn check_range(&self, start: Lba, end: Lba, index: Option<usize>) -> Result {
+        let overlaps = self.partitions().any(|(i, entry)| {
+            Some(i) != index && start <= entry.ending_lba && entry.starting_lba <= end
+        });
+        if start > end || start < self.first_usable_lba || end > self.last_usable_lba || overlaps {
+            Err(Status::INVALID_PARAMETER.into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cstr16, guid};
+
+    const DISK_GUID: Guid = guid!("01234567-89ab-cdef-0123-456789abcdef");
+
+    fn media(last_block: Lba) -> BlockIOMedia {
+        BlockIOMedia {
+            media_id: 1,
+            removable_media: false,
+            media_present: true,
+            logical_partition: false,
+            read_only: false,
+            write_caching: false,
+            block_size: 512,
+            io_align: 1,
+            last_block,
+            lowest_aligned_lba: 0,
+            logical_blocks_per_physical_block: 1,
+            optimal_transfer_length_granularity: 0,
+        }
+    }
+
+    fn partition(start: Lba, end: Lba) -> GptPartitionEntry {
+        GptPartitionEntry::new(
+            GptPartitionType::EFI_SYSTEM_PARTITION,
+            guid!("76543210-89ab-cdef-0123-456789abcdef"),
+            start,
+            end,
+            cstr16!("EFI system partition"),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_gpt_edit() {
+        assert_eq!(
+            GptPartitionTable::new(&media(60), DISK_GUID)
+                .unwrap_err()
+                .status(),
+            Status::BAD_BUFFER_SIZE
+        );
+
+        let mut table = GptPartitionTable::new(&media(8191), DISK_GUID).unwrap();
+        assert_eq!(table.first_usable_lba(), 34);
+        assert_eq!(table.last_usable_lba(), 8158);
+        assert_eq!(table.partitions().count(), 0);
+
+        let start = table.find_free(2048, 2048).unwrap();
+        assert_eq!(start, 2048);
+        assert_eq!(table.add(partition(start, start + 2047)).unwrap(), 0);
+        assert_eq!(table.find_free(2048, 2048), Some(4096));
+        assert_eq!(table.find_free(6000, 1), None);
+        assert_eq!(table.find_free(100, 1), Some(34));
+
+        for (start, end) in [(4096, 4095), (33, 100), (8000, 8159), (3000, 5000)] {
+            assert_eq!(
+                table.add(partition(start, end)).unwrap_err().status(),
+                Status::INVALID_PARAMETER
+            );
+        }
+        assert_eq!(table.add(partition(4096, 8158)).unwrap(), 1);
+        assert_eq!(table.partitions().count(), 2);
+
+        assert_eq!(
+            table.resize(0, 4096).unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+        table.resize(0, 3000).unwrap();
+        assert_eq!({ table.entries()[0].num_blocks() }, Some(953));
+
+        let removed = table.remove(1).unwrap();
+        assert_eq!({ removed.starting_lba }, 4096);
+        assert_eq!(table.remove(1).unwrap_err().status(), Status::NOT_FOUND);
+        assert_eq!(
+            table.resize(1, 5000).unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+        assert_eq!(table.find_free(2048, 2048), Some(4096));
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn test_gpt_read_write() {
+        use crate::mock::{MockBlockDevice, MockFirmware};
+
+        let mut firmware = MockFirmware::new();
+        let device = MockBlockDevice::new(512, 8192);
+        let handle = firmware.install_block_device(&device);
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut block_io = bt.open_protocol_exclusive::<BlockIO>(handle).unwrap();
+
+        assert_eq!(
+            GptPartitionTable::read(&block_io).unwrap_err().status(),
+            Status::VOLUME_CORRUPTED
+        );
+
+        let mut table = GptPartitionTable::new(block_io.media(), DISK_GUID).unwrap();
+        table.add(partition(2048, 4095)).unwrap();
+        table.write(bt, &mut block_io).unwrap();
+
+        let contents = device.contents();
+        assert_eq!(contents[450], 0xee);
+        assert_eq!(contents[510..512], [0x55, 0xaa]);
+        assert_eq!(&contents[512..520], b"EFI PART");
+        assert_eq!(&contents[8191 * 512..8191 * 512 + 8], b"EFI PART");
+
+        let read = GptPartitionTable::read(&block_io).unwrap();
+        assert_eq!(read.disk_guid(), DISK_GUID);
+        assert_eq!(read.entries().len(), 128);
+        let partitions: Vec<_> = read
+            .partitions()
+            .map(|(i, entry)| (i, entry.starting_lba, entry.ending_lba))
+            .collect();
+        assert_eq!(partitions, [(0, 2048, 4095)]);
+
+        // Corrupt the primary header: the backup is used.
+        device.write(512 + 24, &[0xff]);
+        let read = GptPartitionTable::read(&block_io).unwrap();
+        assert_eq!(read.partitions().count(), 1);
+
+        // Writing the table again repairs the primary header.
+        read.write(bt, &mut block_io).unwrap();
+        assert_eq!(device.contents(), contents);
+    }
+}
@@ -9,4 +9,9 @@ pub mod file;
 pub mod block;
 pub mod disk;
 pub mod fs;
+#[cfg(feature = "alloc")]
+pub mod gpt;
 pub mod partition;
+pub mod ram_disk;
+#[cfg(feature = "alloc")]
+pub mod virtual_fs;
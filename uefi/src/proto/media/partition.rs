@@ -1,7 +1,7 @@
 //! Partition information protocol.
 
 use crate::proto::unsafe_protocol;
-use crate::{guid, Char16, Guid};
+use crate::{guid, CStr16, Char16, Guid};
 use bitflags::bitflags;
 
 newtype_enum! {
@@ -134,6 +134,39 @@ pub struct GptPartitionEntry {
 }
 
 impl GptPartitionEntry {
+    /// Create an entry for the partition from `starting_lba` to
+    /// `ending_lba` (inclusive), with no attributes.
+    ///
+    /// Returns `None` if `name` is longer than 36 characters.
+    #[must_use]
+    pub fn new(
+        partition_type_guid: GptPartitionType,
+        unique_partition_guid: Guid,
+        starting_lba: u64,
+        ending_lba: u64,
+        name: &CStr16,
+    ) -> Option<Self> {
+        let name = name.as_slice_with_nul();
+        let name = &name[..name.len() - 1];
+        let mut partition_name = [Char16::default(); 36];
+        partition_name.get_mut(..name.len())?.copy_from_slice(name);
+        Some(Self {
+            partition_type_guid,
+            unique_partition_guid,
+            starting_lba,
+            ending_lba,
+            attributes: GptPartitionAttributes::empty(),
+            partition_name,
+        })
+    }
+
+    /// True if the entry describes a partition, rather than being unused.
+    #[must_use]
+    pub fn is_used(&self) -> bool {
+        let partition_type = self.partition_type_guid;
+        partition_type != GptPartitionType::UNUSED_ENTRY
+    }
+
     /// Get the number of blocks in the partition. Returns `None` if the
     /// end block is before the start block, or if the number doesn't
     /// fit in a `u64`.
@@ -0,0 +1,219 @@
+//! RAM disk protocol.
+
+use crate::data_types::PhysicalAddress;
+use crate::proto::device_path::media::RamDiskType;
+use crate::proto::device_path::{DevicePath, FfiDevicePath};
+use crate::proto::unsafe_protocol;
+use crate::{Guid, Result, Status};
+use core::{fmt, ptr};
+
+/// The RAM disk protocol, registering a range of memory as a virtual disk.
+///
+/// The firmware creates a handle for the disk with a RAM disk device path
+/// node and the [`BlockIO`] protocol, so that the partitions and file
+/// systems of the disk are exposed like those of a real disk. This is
+/// typically used to boot an ISO or disk image downloaded over the network.
+///
+/// The corresponding C type is `EFI_RAM_DISK_PROTOCOL`.
+///
+/// [`BlockIO`]: crate::proto::media::block::BlockIO
+#[repr(C)]
+#[unsafe_protocol("ab38a0df-6873-44a9-87e6-d4eb56148449")]
+pub struct RamDisk {
+    register: unsafe extern "efiapi" fn(
+        ram_disk_base: u64,
+        ram_disk_size: u64,
+        ram_disk_type: *const Guid,
+        parent_device_path: *const FfiDevicePath,
+        device_path: *mut *const FfiDevicePath,
+    ) -> Status,
+    unregister: unsafe extern "efiapi" fn(device_path: *const FfiDevicePath) -> Status,
+}
+
+impl RamDisk {
+    /// Register the `size` bytes of memory at `base` as a RAM disk of type
+    /// `disk_type`, and return the device path of the new disk.
+    ///
+    /// If `parent` is given, the device path of the disk is a child of
+    /// `parent`. Otherwise, it is a child of a vendor-defined node.
+    ///
+    /// The device path is owned by the firmware, and stays valid until the
+    /// disk is [unregistered].
+    ///
+    /// # Safety
+    ///
+    /// The memory range must stay valid and must not be accessed by other
+    /// means until the disk is unregistered. To keep a disk usable by the
+    /// OS after [`exit_boot_services`], the memory must be of a type the OS
+    /// does not reclaim, such as [`MemoryType::RESERVED`], and the disk
+    /// type must be one of the persistent types.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `disk_type` is not supported.
+    /// * [`Status::INVALID_PARAMETER`]: `size` is zero.
+    /// * [`Status::ALREADY_STARTED`]: the memory range is already
+    ///   registered.
+    /// * [`Status::OUT_OF_RESOURCES`]: the disk could not be created.
+    ///
+    /// [unregistered]: Self::unregister
+    /// [`exit_boot_services`]: crate::table::SystemTable::exit_boot_services
+    /// [`MemoryType::RESERVED`]: crate::table::boot::MemoryType::RESERVED
+    pub unsafe fn register(
+        &self,
+        base: PhysicalAddress,
+        size: u64,
+        disk_type: RamDiskType,
+        parent: Option<&DevicePath>,
+    ) -> Result<&'static DevicePath> {
+        let parent = parent.map_or(ptr::null(), DevicePath::as_ffi_ptr);
+        let mut device_path = ptr::null();
+        (self.register)(base, size, &disk_type.0, parent, &mut device_path)
+            .into_with_val(|| DevicePath::from_ffi_ptr(device_path))
+    }
+
+    /// Register `buffer` as a RAM disk of type `disk_type`, and return the
+    /// device path of the new disk.
+    ///
+    /// See [`register`] for details.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must not be accessed until the disk is unregistered.
+    ///
+    /// # Errors
+    ///
+    /// See [`register`].
+    ///
+    /// [`register`]: Self::register
+    pub unsafe fn register_buffer(
+        &self,
+        buffer: &'static mut [u8],
+        disk_type: RamDiskType,
+        parent: Option<&DevicePath>,
+    ) -> Result<&'static DevicePath> {
+        self.register(
+            buffer.as_mut_ptr() as PhysicalAddress,
+            buffer.len() as u64,
+            disk_type,
+            parent,
+        )
+    }
+
+    /// Unregister the RAM disk whose device path is `device_path`, as
+    /// returned by [`register`]. The handle of the disk is removed, and its
+    /// memory can be reused.
+    ///
+    /// # Safety
+    ///
+    /// The device path returned by [`register`] is freed, and must not be
+    /// used afterwards.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: `device_path` is not the device path of a
+    ///   registered RAM disk.
+    /// * [`Status::UNSUPPORTED`]: `device_path` is not a RAM disk device
+    ///   path.
+    ///
+    /// [`register`]: Self::register
+    pub unsafe fn unregister(&self, device_path: &DevicePath) -> Result {
+        (self.unregister)(device_path.as_ffi_ptr()).into()
+    }
+}
+
+impl fmt::Debug for RamDisk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RamDisk").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::device_path::{DevicePathNodeEnum, DeviceSubType, DeviceType};
+    use core::marker::PhantomData;
+
+    /// Device path of the fake disk: a RAM disk node of a 512 byte virtual
+    /// disk at 0x1000, followed by an end node.
+    static DISK_PATH: [u8; 42] = [
+        0x04, 0x09, 38, 0, // Header.
+        0x00, 0x10, 0, 0, 0, 0, 0, 0, // Starting address.
+        0xff, 0x11, 0, 0, 0, 0, 0, 0, // Ending address.
+        0x5a, 0x53, 0xab, 0x77, 0xfc, 0x45, 0x4b, 0x62, // Disk type.
+        0x55, 0x60, 0xf7, 0xb2, 0x81, 0xd1, 0xf9, 0x6e, //
+        0x00, 0x00, // Disk instance.
+        0x7f, 0xff, 4, 0, // End node.
+    ];
+
+    unsafe extern "efiapi" fn register(
+        ram_disk_base: u64,
+        ram_disk_size: u64,
+        ram_disk_type: *const Guid,
+        _parent_device_path: *const FfiDevicePath,
+        device_path: *mut *const FfiDevicePath,
+    ) -> Status {
+        if ram_disk_size == 0 {
+            Status::INVALID_PARAMETER
+        } else if *ram_disk_type != RamDiskType::VIRTUAL_DISK.0
+            || (ram_disk_base, ram_disk_size) != (0x1000, 0x200)
+        {
+            Status::UNSUPPORTED
+        } else {
+            *device_path = DISK_PATH.as_ptr().cast();
+            Status::SUCCESS
+        }
+    }
+
+    unsafe extern "efiapi" fn unregister(device_path: *const FfiDevicePath) -> Status {
+        if device_path == DISK_PATH.as_ptr().cast() {
+            Status::SUCCESS
+        } else {
+            Status::NOT_FOUND
+        }
+    }
+
+    #[test]
+    fn test_ram_disk() {
+        let ram_disk = RamDisk {
+            register,
+            unregister,
+            _no_send_or_sync: PhantomData,
+        };
+
+        unsafe {
+            let path = ram_disk
+                .register(0x1000, 0x200, RamDiskType::VIRTUAL_DISK, None)
+                .unwrap();
+            let node = path.node_iter().next().unwrap();
+            assert_eq!(
+                node.full_type(),
+                (DeviceType::MEDIA, DeviceSubType::MEDIA_RAM_DISK)
+            );
+            match node.as_enum().unwrap() {
+                DevicePathNodeEnum::MediaRamDisk(node) => {
+                    assert_eq!(node.starting_address(), 0x1000);
+                    assert_eq!(node.ending_address(), 0x11ff);
+                    assert_eq!(node.disk_type(), RamDiskType::VIRTUAL_DISK);
+                }
+                _ => panic!("unexpected node"),
+            }
+            ram_disk.unregister(path).unwrap();
+
+            assert_eq!(
+                ram_disk
+                    .register(0x1000, 0, RamDiskType::VIRTUAL_DISK, None)
+                    .unwrap_err()
+                    .status(),
+                Status::INVALID_PARAMETER
+            );
+            assert_eq!(
+                ram_disk
+                    .register(0x1000, 0x200, RamDiskType::VIRTUAL_CD, None)
+                    .unwrap_err()
+                    .status(),
+                Status::UNSUPPORTED
+            );
+        }
+    }
+}
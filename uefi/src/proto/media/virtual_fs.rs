@@ -0,0 +1,754 @@
+//! File systems implemented in Rust and exposed to the firmware.
+//!
+//! A type implementing [`VirtualFileSystem`] can be installed with
+//! [`install`] as a [`SimpleFileSystem`] protocol. Images loaded afterwards,
+//! such as an OS loader or the UEFI shell, can then open its files like the
+//! files of a disk.
+//!
+//! [`MemoryFileSystem`] is a read-only file system whose files are stored in
+//! memory, which is enough to expose synthesized files:
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::proto::media::virtual_fs::{self, MemoryFileSystem};
+//!
+//! fn expose_initrd(bt: &BootServices, initrd: &'static [u8]) -> uefi::Result<Handle> {
+//!     let mut fs = MemoryFileSystem::new("LOADER");
+//!     fs.add_file("\\initrd.img", initrd)?;
+//!     virtual_fs::install(bt, None, fs)
+//! }
+//! ```
+
+use super::file::{
+    FileAttribute, FileImpl, FileInfo, FileInfoCreationError, FileMode, FileSystemInfo,
+    FileSystemVolumeLabel, FromUefi,
+};
+use super::fs::SimpleFileSystem;
+use crate::mem::{try_box, try_reserve, try_to_vec, try_vec};
+use crate::table::boot::BootServices;
+use crate::table::runtime::Time;
+use crate::{CStr16, CString16, Char16, Guid, Handle, Identify, Result, Status};
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::{mem, ptr, slice};
+
+/// Information about a file or directory of a [`VirtualFileSystem`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NodeInfo {
+    /// Name of the file, without its directory.
+    pub name: String,
+    /// Size of the file in bytes, or zero for directories.
+    pub size: u64,
+    /// Attributes of the file. Directories must have
+    /// [`FileAttribute::DIRECTORY`].
+    pub attribute: FileAttribute,
+    /// Time of the last modification of the file.
+    pub modification_time: Time,
+}
+
+impl NodeInfo {
+    /// Create the information of a file without a modification time.
+    #[must_use]
+    pub fn new(name: &str, size: u64, attribute: FileAttribute) -> Self {
+        Self {
+            name: name.to_string(),
+            size,
+            attribute,
+            modification_time: Time::invalid(),
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        self.attribute.contains(FileAttribute::DIRECTORY)
+    }
+}
+
+/// A file system implemented in Rust, which can be exposed to the firmware
+/// with [`install`].
+///
+/// Files and directories are identified by [`Node`]s. The protocol plumbing
+/// is handled by [`install`]: parsing paths, including `.` and `..`,
+/// tracking the position of each open file, and serializing the info
+/// structures.
+///
+/// The file system is read-only unless the methods changing files are
+/// implemented. Their default implementations return
+/// [`Status::WRITE_PROTECTED`].
+///
+/// [`Node`]: Self::Node
+pub trait VirtualFileSystem: 'static {
+    /// Identifier of a file or directory.
+    type Node: Clone + 'static;
+
+    /// Get the root directory.
+    fn root(&self) -> Self::Node;
+
+    /// Get the information of `node`.
+    fn info(&self, node: &Self::Node) -> NodeInfo;
+
+    /// Find the entry called `name` in the directory `dir`.
+    fn lookup(&self, dir: &Self::Node, name: &str) -> Option<Self::Node>;
+
+    /// Get the entry at `index` in the directory `dir`, or `None` past the
+    /// last entry.
+    fn entry(&self, dir: &Self::Node, index: usize) -> Option<Self::Node>;
+
+    /// Read the data of `file` at `offset` into `buffer`, and return the
+    /// number of bytes read, which is zero past the end of the file.
+    fn read(&self, file: &Self::Node, offset: u64, buffer: &mut [u8]) -> Result<usize>;
+
+    /// Get the label of the volume.
+    fn volume_label(&self) -> String;
+
+    /// Whether the file system can't be changed.
+    fn is_read_only(&self) -> bool {
+        true
+    }
+
+    /// Create the entry `name`, with `attribute`, in the directory `dir`.
+    fn create(
+        &self,
+        _dir: &Self::Node,
+        _name: &str,
+        _attribute: FileAttribute,
+    ) -> Result<Self::Node> {
+        Err(Status::WRITE_PROTECTED.into())
+    }
+
+    /// Write `data` to `file` at `offset`, extending the file if needed,
+    /// and return the number of bytes written.
+    fn write(&self, _file: &Self::Node, _offset: u64, _data: &[u8]) -> Result<usize> {
+        Err(Status::WRITE_PROTECTED.into())
+    }
+
+    /// Change the name, size or attributes of `node` to those of `info`.
+    fn set_info(&self, _node: &Self::Node, _info: &FileInfo) -> Result {
+        Err(Status::WRITE_PROTECTED.into())
+    }
+
+    /// Delete `node`.
+    fn delete(&self, _node: &Self::Node) -> Result {
+        Err(Status::WRITE_PROTECTED.into())
+    }
+
+    /// Write the pending changes of `node` to the storage.
+    fn flush(&self, _node: &Self::Node) -> Result {
+        Ok(())
+    }
+}
+
+/// Install `fs` as a [`SimpleFileSystem`] protocol on `handle`, or on a new
+/// handle if `handle` is `None`. Returns the handle.
+///
+/// The file system is never freed: open files may outlive the application
+/// which installed it, so the protocol stays installed until the end of
+/// boot services.
+///
+/// To load images from the file system with
+/// [`BootServices::load_image`], install a device path protocol on the
+/// handle as well.
+///
+/// # Errors
+///
+/// See [`BootServices::install_protocol_interface`].
+pub fn install<F: VirtualFileSystem>(
+    bt: &BootServices,
+    handle: Option<Handle>,
+    fs: F,
+) -> Result<Handle> {
+    let instance = Box::into_raw(try_box(FileSystemInstance {
+        raw: SimpleFileSystem::new(open_volume::<F>),
+        fs: Rc::new(fs),
+    })?);
+    let result =
+        unsafe { bt.install_protocol_interface(handle, &SimpleFileSystem::GUID, instance.cast()) };
+    if result.is_err() {
+        drop(unsafe { Box::from_raw(instance) });
+    }
+    result
+}
+
+/// A `SimpleFileSystem` protocol instance backed by a [`VirtualFileSystem`].
+#[repr(C)]
+struct FileSystemInstance<F: VirtualFileSystem> {
+    // Must be the first field so that the protocol's `this` pointer can be
+    // converted back to a `FileSystemInstance`.
+    raw: SimpleFileSystem,
+    fs: Rc<F>,
+}
+
+/// An open file.
+#[repr(C)]
+struct OpenFile<F: VirtualFileSystem> {
+    // Must be the first field so that the protocol's `this` pointer can be
+    // converted back to an `OpenFile`.
+    imp: FileImpl,
+    fs: Rc<F>,
+    /// Directories from the root to the file, followed by the file, to
+    /// resolve `..`.
+    path: Vec<F::Node>,
+    position: u64,
+    writable: bool,
+}
+
+impl<F: VirtualFileSystem> OpenFile<F> {
+    fn open(fs: Rc<F>, path: Vec<F::Node>, writable: bool) -> Result<*mut FileImpl> {
+        let file = try_box(Self {
+            imp: FileImpl {
+                revision: 0x0001_0000,
+                open: file_open::<F>,
+                close: file_close::<F>,
+                delete: file_delete::<F>,
+                read: file_read::<F>,
+                write: file_write::<F>,
+                get_position: file_get_position::<F>,
+                set_position: file_set_position::<F>,
+                get_info: file_get_info::<F>,
+                set_info: file_set_info::<F>,
+                flush: file_flush::<F>,
+            },
+            fs,
+            path,
+            position: 0,
+            writable,
+        })?;
+        Ok(Box::into_raw(file).cast())
+    }
+
+    fn node(&self) -> &F::Node {
+        // OK to unwrap: the path always holds at least the root.
+        self.path.last().unwrap()
+    }
+
+    fn info(&self) -> NodeInfo {
+        self.fs.info(self.node())
+    }
+}
+
+unsafe fn file_from<'a, F: VirtualFileSystem>(this: &mut FileImpl) -> &'a mut OpenFile<F> {
+    &mut *(this as *mut FileImpl).cast::<OpenFile<F>>()
+}
+
+/// Serializes an info structure with `create` and copies it to `buffer`.
+pub(crate) unsafe fn write_info<T: ?Sized>(
+    buffer_size: &mut usize,
+    buffer: *mut u8,
+    create: impl Fn(&mut [u8]) -> core::result::Result<&mut T, FileInfoCreationError>,
+) -> Status {
+    let mut storage = match try_vec(0u64, 16) {
+        Ok(storage) => storage,
+        Err(err) => return err.status(),
+    };
+    let info = loop {
+        let bytes = slice::from_raw_parts_mut(
+            storage.as_mut_ptr().cast::<u8>(),
+            mem::size_of_val(storage.as_slice()),
+        );
+        match create(bytes) {
+            Ok(info) => break info,
+            Err(FileInfoCreationError::InsufficientStorage(size)) => {
+                storage = match try_vec(0u64, size / 8 + 1) {
+                    Ok(storage) => storage,
+                    Err(err) => return err.status(),
+                };
+            }
+        }
+    };
+    let size = mem::size_of_val(info);
+    if *buffer_size < size {
+        *buffer_size = size;
+        return Status::BUFFER_TOO_SMALL;
+    }
+    *buffer_size = size;
+    ptr::copy_nonoverlapping((info as *const T).cast::<u8>(), buffer, size);
+    Status::SUCCESS
+}
+
+unsafe fn write_file_info(info: &NodeInfo, buffer_size: &mut usize, buffer: *mut u8) -> Status {
+    let name = match CString16::try_from(info.name.as_str()) {
+        Ok(name) => name,
+        Err(_) => return Status::DEVICE_ERROR,
+    };
+    let time = info.modification_time;
+    write_info(buffer_size, buffer, |storage| {
+        FileInfo::new(
+            storage,
+            info.size,
+            info.size,
+            time,
+            time,
+            time,
+            info.attribute,
+            &name,
+        )
+    })
+}
+
+fn status_of(result: Result) -> Status {
+    match result {
+        Ok(()) => Status::SUCCESS,
+        Err(err) => err.status(),
+    }
+}
+
+extern "efiapi" fn open_volume<F: VirtualFileSystem>(
+    this: &mut SimpleFileSystem,
+    root: &mut *mut FileImpl,
+) -> Status {
+    let instance = unsafe { &*(this as *mut SimpleFileSystem).cast::<FileSystemInstance<F>>() };
+    let writable = !instance.fs.is_read_only();
+    let result = try_to_vec(&[instance.fs.root()])
+        .and_then(|path| OpenFile::open(instance.fs.clone(), path, writable));
+    match result {
+        Ok(file) => {
+            *root = file;
+            Status::SUCCESS
+        }
+        Err(err) => err.status(),
+    }
+}
+
+unsafe extern "efiapi" fn file_open<F: VirtualFileSystem>(
+    this: &mut FileImpl,
+    new_handle: &mut *mut FileImpl,
+    filename: *const Char16,
+    open_mode: FileMode,
+    attributes: FileAttribute,
+) -> Status {
+    let file = file_from::<F>(this);
+    let fs = &file.fs;
+    let path = CStr16::from_ptr(filename).to_string();
+
+    let writable = open_mode != FileMode::Read;
+    if writable && fs.is_read_only() {
+        return Status::WRITE_PROTECTED;
+    }
+
+    let nodes = if path.starts_with('\\') {
+        try_to_vec(&[fs.root()])
+    } else {
+        try_to_vec(&file.path)
+    };
+    let mut nodes = match nodes {
+        Ok(nodes) => nodes,
+        Err(err) => return err.status(),
+    };
+    if !path.starts_with('\\') && !file.info().is_dir() && nodes.len() > 1 {
+        nodes.pop();
+    }
+
+    let names: Vec<&str> = path
+        .split('\\')
+        .filter(|name| !name.is_empty() && *name != ".")
+        .collect();
+    for (i, name) in names.iter().enumerate() {
+        let dir = nodes.last().unwrap();
+        if !fs.info(dir).is_dir() {
+            return Status::NOT_FOUND;
+        }
+        if *name == ".." {
+            if nodes.len() > 1 {
+                nodes.pop();
+            }
+            continue;
+        }
+        let next = match fs.lookup(dir, name) {
+            Some(next) => next,
+            None if i + 1 == names.len() && open_mode == FileMode::CreateReadWrite => {
+                match fs.create(dir, name, attributes & FileAttribute::VALID_ATTR) {
+                    Ok(next) => next,
+                    Err(err) => return err.status(),
+                }
+            }
+            None => return Status::NOT_FOUND,
+        };
+        nodes.push(next);
+    }
+
+    let node = nodes.last().unwrap();
+    if writable && fs.info(node).attribute.contains(FileAttribute::READ_ONLY) {
+        return Status::ACCESS_DENIED;
+    }
+    *new_handle = match OpenFile::open(fs.clone(), nodes, writable) {
+        Ok(file) => file,
+        Err(err) => return err.status(),
+    };
+    Status::SUCCESS
+}
+
+extern "efiapi" fn file_close<F: VirtualFileSystem>(this: &mut FileImpl) -> Status {
+    drop(unsafe { Box::from_raw((this as *mut FileImpl).cast::<OpenFile<F>>()) });
+    Status::SUCCESS
+}
+
+extern "efiapi" fn file_delete<F: VirtualFileSystem>(this: &mut FileImpl) -> Status {
+    let file = unsafe { Box::from_raw((this as *mut FileImpl).cast::<OpenFile<F>>()) };
+    // The root directory can't be deleted.
+    if !file.writable || file.path.len() == 1 || file.fs.delete(file.node()).is_err() {
+        return Status::WARN_DELETE_FAILURE;
+    }
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn file_read<F: VirtualFileSystem>(
+    this: &mut FileImpl,
+    buffer_size: &mut usize,
+    buffer: *mut u8,
+) -> Status {
+    let file = file_from::<F>(this);
+    if file.info().is_dir() {
+        let entry = match file.fs.entry(file.node(), file.position as usize) {
+            Some(entry) => entry,
+            None => {
+                *buffer_size = 0;
+                return Status::SUCCESS;
+            }
+        };
+        let status = write_file_info(&file.fs.info(&entry), buffer_size, buffer);
+        if status == Status::SUCCESS {
+            file.position += 1;
+        }
+        status
+    } else {
+        let buffer = slice::from_raw_parts_mut(buffer, *buffer_size);
+        match file.fs.read(file.node(), file.position, buffer) {
+            Ok(len) => {
+                *buffer_size = len;
+                file.position += len as u64;
+                Status::SUCCESS
+            }
+            Err(err) => err.status(),
+        }
+    }
+}
+
+unsafe extern "efiapi" fn file_write<F: VirtualFileSystem>(
+    this: &mut FileImpl,
+    buffer_size: &mut usize,
+    buffer: *const u8,
+) -> Status {
+    let file = file_from::<F>(this);
+    if file.info().is_dir() {
+        return Status::UNSUPPORTED;
+    }
+    if !file.writable {
+        return Status::ACCESS_DENIED;
+    }
+    let data = slice::from_raw_parts(buffer, *buffer_size);
+    match file.fs.write(file.node(), file.position, data) {
+        Ok(len) => {
+            *buffer_size = len;
+            file.position += len as u64;
+            Status::SUCCESS
+        }
+        Err(err) => err.status(),
+    }
+}
+
+extern "efiapi" fn file_get_position<F: VirtualFileSystem>(
+    this: &mut FileImpl,
+    position: &mut u64,
+) -> Status {
+    let file = unsafe { file_from::<F>(this) };
+    if file.info().is_dir() {
+        return Status::UNSUPPORTED;
+    }
+    *position = file.position;
+    Status::SUCCESS
+}
+
+extern "efiapi" fn file_set_position<F: VirtualFileSystem>(
+    this: &mut FileImpl,
+    position: u64,
+) -> Status {
+    let file = unsafe { file_from::<F>(this) };
+    let info = file.info();
+    if info.is_dir() {
+        if position != 0 {
+            return Status::UNSUPPORTED;
+        }
+        file.position = 0;
+    } else if position == u64::MAX {
+        file.position = info.size;
+    } else {
+        file.position = position;
+    }
+    Status::SUCCESS
+}
+
+unsafe extern "efiapi" fn file_get_info<F: VirtualFileSystem>(
+    this: &mut FileImpl,
+    information_type: &Guid,
+    buffer_size: &mut usize,
+    buffer: *mut u8,
+) -> Status {
+    let file = file_from::<F>(this);
+    if *information_type == FileInfo::GUID {
+        return write_file_info(&file.info(), buffer_size, buffer);
+    }
+
+    let label = match CString16::try_from(file.fs.volume_label().as_str()) {
+        Ok(label) => label,
+        Err(_) => return Status::DEVICE_ERROR,
+    };
+    if *information_type == FileSystemInfo::GUID {
+        let read_only = file.fs.is_read_only();
+        write_info(buffer_size, buffer, |storage| {
+            FileSystemInfo::new(storage, read_only, 0, 0, 512, &label)
+        })
+    } else if *information_type == FileSystemVolumeLabel::GUID {
+        write_info(buffer_size, buffer, |storage| {
+            FileSystemVolumeLabel::new(storage, &label)
+        })
+    } else {
+        Status::UNSUPPORTED
+    }
+}
+
+unsafe extern "efiapi" fn file_set_info<F: VirtualFileSystem>(
+    this: &mut FileImpl,
+    information_type: &Guid,
+    _buffer_size: usize,
+    buffer: *const c_void,
+) -> Status {
+    let file = file_from::<F>(this);
+    if *information_type != FileInfo::GUID {
+        return Status::UNSUPPORTED;
+    }
+    if file.fs.is_read_only() {
+        return Status::WRITE_PROTECTED;
+    }
+    if !file.writable {
+        return Status::ACCESS_DENIED;
+    }
+    let info = FileInfo::from_uefi(buffer as *mut c_void);
+    status_of(file.fs.set_info(file.node(), info))
+}
+
+extern "efiapi" fn file_flush<F: VirtualFileSystem>(this: &mut FileImpl) -> Status {
+    let file = unsafe { file_from::<F>(this) };
+    status_of(file.fs.flush(file.node()))
+}
+
+struct MemoryNode {
+    name: String,
+    data: Cow<'static, [u8]>,
+    dir: bool,
+    children: Vec<usize>,
+}
+
+/// A read-only [`VirtualFileSystem`] whose files are stored in memory.
+///
+/// Like FAT, names are case-insensitive.
+pub struct MemoryFileSystem {
+    label: String,
+    nodes: Vec<MemoryNode>,
+}
+
+impl MemoryFileSystem {
+    /// Create an empty file system with the volume label `label`.
+    #[must_use]
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            nodes: vec![MemoryNode {
+                name: String::new(),
+                data: Cow::Borrowed(&[]),
+                dir: true,
+                children: Vec::new(),
+            }],
+        }
+    }
+
+    /// Add the file `path` with the contents `data`, creating the missing
+    /// parent directories. Replaces the file if it exists. The components of
+    /// `path` may be separated with `\` or `/`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `path` has no file name, or contains
+    ///   characters which can't be converted to UCS-2.
+    /// * [`Status::ACCESS_DENIED`]: `path` is a directory, or one of its
+    ///   parents is a file.
+    pub fn add_file(&mut self, path: &str, data: impl Into<Cow<'static, [u8]>>) -> Result {
+        let mut names = path
+            .split(['\\', '/'])
+            .filter(|name| !name.is_empty() && *name != ".")
+            .peekable();
+        if names.peek().is_none() || CString16::try_from(path).is_err() {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+
+        let mut index = 0;
+        while let Some(name) = names.next() {
+            let is_last = names.peek().is_none();
+            index = match self.lookup(&index, name) {
+                Some(child) if self.nodes[child].dir == is_last => {
+                    return Err(Status::ACCESS_DENIED.into())
+                }
+                Some(child) => child,
+                None => {
+                    try_reserve(&mut self.nodes, 1)?;
+                    try_reserve(&mut self.nodes[index].children, 1)?;
+                    self.nodes.push(MemoryNode {
+                        name: name.to_string(),
+                        data: Cow::Borrowed(&[]),
+                        dir: !is_last,
+                        children: Vec::new(),
+                    });
+                    let child = self.nodes.len() - 1;
+                    self.nodes[index].children.push(child);
+                    child
+                }
+            };
+        }
+        self.nodes[index].data = data.into();
+        Ok(())
+    }
+}
+
+impl VirtualFileSystem for MemoryFileSystem {
+    type Node = usize;
+
+    fn root(&self) -> usize {
+        0
+    }
+
+    fn info(&self, node: &usize) -> NodeInfo {
+        let node = &self.nodes[*node];
+        let attribute = if node.dir {
+            FileAttribute::DIRECTORY | FileAttribute::READ_ONLY
+        } else {
+            FileAttribute::READ_ONLY
+        };
+        NodeInfo::new(&node.name, node.data.len() as u64, attribute)
+    }
+
+    fn lookup(&self, dir: &usize, name: &str) -> Option<usize> {
+        self.nodes[*dir]
+            .children
+            .iter()
+            .copied()
+            .find(|&child| self.nodes[child].name.eq_ignore_ascii_case(name))
+    }
+
+    fn entry(&self, dir: &usize, index: usize) -> Option<usize> {
+        self.nodes[*dir].children.get(index).copied()
+    }
+
+    fn read(&self, file: &usize, offset: u64, buffer: &mut [u8]) -> Result<usize> {
+        let data = &self.nodes[*file].data;
+        let start = usize::try_from(offset)
+            .unwrap_or(usize::MAX)
+            .min(data.len());
+        let len = buffer.len().min(data.len() - start);
+        buffer[..len].copy_from_slice(&data[start..start + len]);
+        Ok(len)
+    }
+
+    fn volume_label(&self) -> String {
+        self.label.clone()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::cstr16;
+    use crate::mock::MockFirmware;
+    use crate::proto::media::file::{File, FileType};
+
+    #[test]
+    fn test_memory_file_system() {
+        let mut fs = MemoryFileSystem::new("LOADER");
+        fs.add_file("\\EFI\\Linux\\initrd.img", &b"initrd"[..])
+            .unwrap();
+        fs.add_file("/EFI/linux/cmdline", b"quiet".to_vec())
+            .unwrap();
+        assert_eq!(
+            fs.add_file("\\EFI", &b""[..]).unwrap_err().status(),
+            Status::ACCESS_DENIED
+        );
+        assert_eq!(
+            fs.add_file("\\EFI\\Linux\\cmdline\\x", &b""[..])
+                .unwrap_err()
+                .status(),
+            Status::ACCESS_DENIED
+        );
+        assert_eq!(
+            fs.add_file("\\", &b""[..]).unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let handle = install(bt, None, fs).unwrap();
+
+        let mut sfs = bt
+            .open_protocol_exclusive::<SimpleFileSystem>(handle)
+            .unwrap();
+        let mut root = sfs.open_volume().unwrap();
+        let mut label_buf = [0; 64];
+        let label = root
+            .get_info::<FileSystemVolumeLabel>(&mut label_buf)
+            .unwrap();
+        assert_eq!(label.volume_label(), cstr16!("LOADER"));
+        assert!(root.get_boxed_info::<FileSystemInfo>().unwrap().read_only());
+        assert_eq!(
+            root.open(
+                cstr16!("new"),
+                FileMode::CreateReadWrite,
+                FileAttribute::empty()
+            )
+            .err()
+            .unwrap()
+            .status(),
+            Status::WRITE_PROTECTED
+        );
+
+        let linux = root
+            .open(
+                cstr16!("efi\\LINUX"),
+                FileMode::Read,
+                FileAttribute::empty(),
+            )
+            .unwrap();
+        let mut linux = match linux.into_type().unwrap() {
+            FileType::Dir(dir) => dir,
+            FileType::Regular(_) => panic!("not a directory"),
+        };
+        let mut names = Vec::new();
+        while let Some(entry) = linux.read_entry_boxed().unwrap() {
+            names.push(entry.file_name().to_string());
+        }
+        assert_eq!(names, ["initrd.img", "cmdline"]);
+
+        let initrd = linux
+            .open(
+                cstr16!("..\\Linux\\.\\initrd.img"),
+                FileMode::Read,
+                FileAttribute::empty(),
+            )
+            .unwrap();
+        let mut initrd = match initrd.into_type().unwrap() {
+            FileType::Regular(file) => file,
+            FileType::Dir(_) => panic!("not a regular file"),
+        };
+        let mut buf = [0; 4];
+        initrd.set_position(2).unwrap();
+        assert_eq!(initrd.read(&mut buf).unwrap(), 4);
+        assert_eq!(&buf, b"itrd");
+        assert_eq!(initrd.read(&mut buf).unwrap(), 0);
+        assert_eq!(
+            initrd.write(b"x").unwrap_err().status(),
+            Status::ACCESS_DENIED
+        );
+        assert_eq!(initrd.get_boxed_info::<FileInfo>().unwrap().file_size(), 6);
+    }
+}
@@ -0,0 +1,392 @@
+//! `GenericMemoryTest` protocol.
+
+use crate::data_types::PhysicalAddress;
+use crate::progress::ProgressReporter;
+use crate::proto::unsafe_protocol;
+use crate::{Result, Status};
+use core::fmt;
+use core::ops::ControlFlow;
+
+newtype_enum! {
+    /// How much of the memory is tested by [`GenericMemoryTest`].
+    pub enum MemoryTestLevel: u32 => {
+        /// Skip the test.
+        IGNORE = 0,
+        /// Quick test of a few addresses.
+        QUICK = 1,
+        /// Test a sample of the memory.
+        SPARSE = 2,
+        /// Test all of the memory.
+        EXTENSIVE = 3,
+    }
+}
+
+/// Progress of a memory test.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryTestProgress {
+    /// Size of the memory tested so far, in bytes.
+    pub tested: u64,
+    /// Size of the memory to test, in bytes.
+    pub total: u64,
+    /// Whether a memory error was found.
+    pub error: bool,
+}
+
+impl MemoryTestProgress {
+    /// Get the tested memory as a percentage of the memory to test.
+    #[must_use]
+    pub const fn percent(&self) -> u8 {
+        crate::progress::percent(self.tested, self.total)
+    }
+}
+
+/// The generic memory test protocol, testing the memory which was not
+/// tested during the early boot.
+///
+/// [`run`] runs a whole test. The test can also be driven manually with
+/// [`init`], [`perform`] and [`finish`].
+///
+/// The corresponding C type is `EFI_GENERIC_MEMORY_TEST_PROTOCOL`.
+///
+/// [`run`]: Self::run
+/// [`init`]: Self::init
+/// [`perform`]: Self::perform
+/// [`finish`]: Self::finish
+#[repr(C)]
+#[unsafe_protocol("309de7f1-7f5e-4ace-b49c-531be5aa95ef")]
+pub struct GenericMemoryTest {
+    memory_test_init: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        level: MemoryTestLevel,
+        require_soft_ecc_init: *mut bool,
+    ) -> Status,
+    perform_memory_test: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        tested_memory_size: *mut u64,
+        total_memory_size: *mut u64,
+        error_out: *mut bool,
+        if_test_abort: bool,
+    ) -> Status,
+    finished: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+    compatible_range_test: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        start_address: PhysicalAddress,
+        length: u64,
+    ) -> Status,
+}
+
+impl GenericMemoryTest {
+    /// Prepare a memory test with the coverage `level`. Returns whether the
+    /// memory must be written before being read, to initialize the ECC of
+    /// the memory controller.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NO_MEDIA`]: there is no untested memory.
+    /// * [`Status::INVALID_PARAMETER`]: `level` is not supported.
+    pub fn init(&mut self, level: MemoryTestLevel) -> Result<bool> {
+        let mut require_soft_ecc_init = false;
+        unsafe { (self.memory_test_init)(self, level, &mut require_soft_ecc_init) }
+            .into_with_val(|| require_soft_ecc_init)
+    }
+
+    /// Test the next block of memory, and return the progress of the test,
+    /// or `None` once all the memory has been tested.
+    ///
+    /// If `abort` is set, the remaining memory is added to the system
+    /// without being tested.
+    ///
+    /// A memory error is reported in [`MemoryTestProgress::error`], and the
+    /// test can go on.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: [`init`] was not called.
+    ///
+    /// [`init`]: Self::init
+    pub fn perform(&mut self, abort: bool) -> Result<Option<MemoryTestProgress>> {
+        let mut progress = MemoryTestProgress::default();
+        let status = unsafe {
+            (self.perform_memory_test)(
+                self,
+                &mut progress.tested,
+                &mut progress.total,
+                &mut progress.error,
+                abort,
+            )
+        };
+        match status {
+            Status::NOT_FOUND => Ok(None),
+            Status::DEVICE_ERROR if progress.error => Ok(Some(progress)),
+            status => status.into_with_val(|| Some(progress)),
+        }
+    }
+
+    /// Finish the memory test, adding the tested memory to the memory map.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: [`init`] was not called.
+    ///
+    /// [`init`]: Self::init
+    pub fn finish(&mut self) -> Result {
+        unsafe { (self.finished)(self) }.into()
+    }
+
+    /// Test the `length` bytes of memory at `start`, and make them usable
+    /// by legacy code.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the range is not in the memory map.
+    /// * [`Status::DEVICE_ERROR`]: a memory error was found.
+    pub fn compatible_range_test(&mut self, start: PhysicalAddress, length: u64) -> Result {
+        unsafe { (self.compatible_range_test)(self, start, length) }.into()
+    }
+
+    /// Run a whole memory test with the coverage `level`, and return the
+    /// final progress.
+    ///
+    /// `progress` is called after each block of memory is tested. If it
+    /// returns [`ControlFlow::Break`], the remaining memory is not tested.
+    ///
+    /// # Errors
+    ///
+    /// See [`init`], [`perform`] and [`finish`]. Finding no untested memory
+    /// is not an error.
+    ///
+    /// [`init`]: Self::init
+    /// [`perform`]: Self::perform
+    /// [`finish`]: Self::finish
+    pub fn run(
+        &mut self,
+        level: MemoryTestLevel,
+        mut progress: impl FnMut(&MemoryTestProgress) -> ControlFlow<()>,
+    ) -> Result<MemoryTestProgress> {
+        if let Err(err) = self.init(level) {
+            return if err.status() == Status::NO_MEDIA {
+                Ok(MemoryTestProgress::default())
+            } else {
+                Err(err)
+            };
+        }
+
+        let mut last = MemoryTestProgress::default();
+        let mut abort = false;
+        let result = loop {
+            match self.perform(abort) {
+                Ok(Some(current)) => {
+                    last = MemoryTestProgress {
+                        error: last.error || current.error,
+                        ..current
+                    };
+                    abort |= progress(&last).is_break();
+                }
+                Ok(None) => break Ok(()),
+                Err(err) => break Err(err),
+            }
+        };
+
+        // Always finish the test, so that the memory tested so far can be
+        // used.
+        let finished = self.finish();
+        result.and(finished).map(|()| last)
+    }
+
+    /// Run a whole memory test with the coverage `level`, reporting the
+    /// tested and total memory in bytes to `reporter`, and return the final
+    /// progress.
+    ///
+    /// This is like [`run`], with the test stopping when the reporter
+    /// returns [`ControlFlow::Break`].
+    ///
+    /// # Errors
+    ///
+    /// See [`run`].
+    ///
+    /// [`run`]: Self::run
+    pub fn run_with_reporter(
+        &mut self,
+        level: MemoryTestLevel,
+        reporter: &mut impl ProgressReporter,
+    ) -> Result<MemoryTestProgress> {
+        reporter.start();
+        let result = self.run(level, |progress| {
+            reporter.update(progress.tested, progress.total)
+        });
+        reporter.finish();
+        result
+    }
+}
+
+impl fmt::Debug for GenericMemoryTest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GenericMemoryTest").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use core::marker::PhantomData;
+
+    /// Fake protocol testing 4 blocks of 16 bytes, with an error in the
+    /// second one.
+    #[repr(C)]
+    struct FakeMemoryTest {
+        proto: GenericMemoryTest,
+        /// Number of tested blocks, or `None` before `init`.
+        tested: Cell<Option<u64>>,
+    }
+
+    unsafe fn tested<'a>(this: *mut GenericMemoryTest) -> &'a Cell<Option<u64>> {
+        &(*this.cast::<FakeMemoryTest>()).tested
+    }
+
+    unsafe extern "efiapi" fn memory_test_init(
+        this: *mut GenericMemoryTest,
+        level: MemoryTestLevel,
+        require_soft_ecc_init: *mut bool,
+    ) -> Status {
+        if level == MemoryTestLevel::IGNORE {
+            return Status::NO_MEDIA;
+        }
+        tested(this).set(Some(0));
+        *require_soft_ecc_init = false;
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn perform_memory_test(
+        this: *mut GenericMemoryTest,
+        tested_memory_size: *mut u64,
+        total_memory_size: *mut u64,
+        error_out: *mut bool,
+        if_test_abort: bool,
+    ) -> Status {
+        let blocks = match tested(this).get() {
+            None => return Status::NOT_STARTED,
+            Some(4) => return Status::NOT_FOUND,
+            Some(_) if if_test_abort => 4,
+            Some(blocks) => blocks + 1,
+        };
+        tested(this).set(Some(blocks));
+        *tested_memory_size = blocks * 16;
+        *total_memory_size = 64;
+        *error_out = blocks == 2;
+        if blocks == 2 {
+            Status::DEVICE_ERROR
+        } else {
+            Status::SUCCESS
+        }
+    }
+
+    unsafe extern "efiapi" fn finished(this: *mut GenericMemoryTest) -> Status {
+        match tested(this).take() {
+            Some(_) => Status::SUCCESS,
+            None => Status::NOT_STARTED,
+        }
+    }
+
+    unsafe extern "efiapi" fn compatible_range_test(
+        _this: *mut GenericMemoryTest,
+        _start_address: PhysicalAddress,
+        _length: u64,
+    ) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    fn fake() -> FakeMemoryTest {
+        FakeMemoryTest {
+            proto: GenericMemoryTest {
+                memory_test_init,
+                perform_memory_test,
+                finished,
+                compatible_range_test,
+                _no_send_or_sync: PhantomData,
+            },
+            tested: Cell::new(None),
+        }
+    }
+
+    #[test]
+    fn test_memory_test_run() {
+        let mut fake = fake();
+        let mut percents = [0; 4];
+        let mut calls = 0;
+        let last = fake
+            .proto
+            .run(MemoryTestLevel::SPARSE, |progress| {
+                percents[calls] = progress.percent();
+                calls += 1;
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(percents, [25, 50, 75, 100]);
+        assert_eq!(
+            last,
+            MemoryTestProgress {
+                tested: 64,
+                total: 64,
+                error: true,
+            }
+        );
+        assert_eq!(
+            fake.proto.finish().unwrap_err().status(),
+            Status::NOT_STARTED
+        );
+
+        // Abort after the first block.
+        let last = fake
+            .proto
+            .run(MemoryTestLevel::QUICK, |_| ControlFlow::Break(()))
+            .unwrap();
+        assert_eq!(last.tested, 64);
+        assert!(!last.error);
+
+        // No untested memory.
+        let last = fake
+            .proto
+            .run(MemoryTestLevel::IGNORE, |_| unreachable!())
+            .unwrap();
+        assert_eq!(last, MemoryTestProgress::default());
+    }
+
+    #[test]
+    fn test_memory_test_run_with_reporter() {
+        let mut fake = fake();
+        let mut updates = 0;
+        let mut reporter = |tested, total| {
+            assert_eq!(total, 64);
+            updates += 1;
+            if tested < 32 {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        };
+        let last = fake
+            .proto
+            .run_with_reporter(MemoryTestLevel::SPARSE, &mut reporter)
+            .unwrap();
+        // The second block has an error, and the test is aborted after it,
+        // which is reported once more.
+        assert!(last.error);
+        assert_eq!(updates, 3);
+    }
+
+    #[test]
+    fn test_memory_test_perform() {
+        let mut fake = fake();
+        assert_eq!(
+            fake.proto.perform(false).unwrap_err().status(),
+            Status::NOT_STARTED
+        );
+        assert!(!fake.proto.init(MemoryTestLevel::EXTENSIVE).unwrap());
+        assert_eq!(fake.proto.perform(false).unwrap().unwrap().tested, 16);
+        assert!(fake.proto.perform(false).unwrap().unwrap().error);
+        assert_eq!(fake.proto.perform(true).unwrap().unwrap().percent(), 100);
+        assert_eq!(fake.proto.perform(false).unwrap(), None);
+        fake.proto.finish().unwrap();
+    }
+}
@@ -68,16 +68,29 @@ where
 
 pub use uefi_macros::unsafe_protocol;
 
+mod registry;
+pub use registry::protocol_name;
+
 pub mod console;
 pub mod debug;
+pub mod decompress;
 pub mod device_path;
 pub mod driver;
+pub mod hash;
+pub mod legacy_bios;
 pub mod loaded_image;
 pub mod media;
+pub mod memory_test;
 pub mod network;
+pub mod pci;
 pub mod pi;
+pub mod riscv;
 pub mod rng;
 pub mod security;
+pub mod service_binding;
+pub mod shell;
 pub mod shim;
+pub mod speaker;
 pub mod string;
 pub mod tcg;
+pub mod usb;
@@ -0,0 +1,744 @@
+//! Address Resolution Protocol.
+//!
+//! The ARP protocol resolves the protocol (software) addresses of the local
+//! network, such as IPv4 addresses, to hardware addresses, and maintains a
+//! cache of the resolved addresses. Instances of this protocol are created
+//! with the [`ServiceBindingProtocol<Arp>`] installed on the network
+//! controller handle, and send their requests through a [`ManagedNetwork`]
+//! instance.
+//!
+//! [`ServiceBindingProtocol<Arp>`]: crate::proto::service_binding::ServiceBindingProtocol
+//! [`ManagedNetwork`]: super::mnp::ManagedNetwork
+
+use super::MacAddress;
+use crate::proto::unsafe_protocol;
+use crate::table::boot::{BootServices, EventType, Tpl};
+use crate::{Event, Result, Status};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::ptr::{self, NonNull};
+use core::{fmt, mem, slice};
+
+/// The Address Resolution Protocol.
+///
+/// The corresponding C type is `EFI_ARP_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol(
+    "f4b427bb-ba21-4f16-bc4e-43e416ab619c",
+    service_binding = "f44c00ee-1f2c-4a00-aa09-1c9f3e0800a3"
+)]
+pub struct Arp {
+    configure:
+        unsafe extern "efiapi" fn(this: *mut Self, config_data: *const ArpConfigData<'_>) -> Status,
+    add: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        deny: bool,
+        target_sw_address: *const c_void,
+        target_hw_address: *const c_void,
+        timeout: u32,
+        overwrite: bool,
+    ) -> Status,
+    find: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        by_sw_address: bool,
+        address: *const c_void,
+        entry_length: *mut u32,
+        entry_count: *mut u32,
+        entries: *mut *mut ArpFindData,
+        refresh: bool,
+    ) -> Status,
+    delete: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        by_sw_address: bool,
+        address: *const c_void,
+    ) -> Status,
+    flush: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+    request: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        target_sw_address: *const c_void,
+        resolved_event: Option<Event>,
+        target_hw_address: *mut c_void,
+    ) -> Status,
+    cancel: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        target_sw_address: *const c_void,
+        resolved_event: Option<Event>,
+    ) -> Status,
+}
+
+/// Get a pointer to the address in `address`, or null if `None`.
+fn address_ptr(address: Option<&[u8]>) -> *const c_void {
+    address.map_or(ptr::null(), |address| address.as_ptr().cast())
+}
+
+impl Arp {
+    /// Configure this instance with `config_data`, or reset it to the
+    /// unconfigured state if `config_data` is `None`. Resetting cancels
+    /// all the pending requests, and removes the cache entries added by
+    /// this instance.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `config_data` is invalid.
+    /// * [`Status::ACCESS_DENIED`]: the station address is already used by
+    ///   another instance.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   configure the instance.
+    pub fn configure(&mut self, config_data: Option<&ArpConfigData<'_>>) -> Result {
+        let config_data = config_data.map_or(ptr::null(), |data| data as *const _);
+        unsafe { (self.configure)(self, config_data) }.into()
+    }
+
+    /// Add a static entry to the ARP cache, resolving `sw_address` to
+    /// `hw_address`.
+    ///
+    /// The entry is removed after `timeout` in units of 100ns, or never if
+    /// `timeout` is 0. An existing entry for `sw_address` is replaced if
+    /// `overwrite` is true.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: an address has the wrong length.
+    /// * [`Status::ACCESS_DENIED`]: an entry already exists, and
+    ///   `overwrite` is false.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   add the entry.
+    pub fn add(
+        &mut self,
+        sw_address: &[u8],
+        hw_address: &[u8],
+        timeout: u32,
+        overwrite: bool,
+    ) -> Result {
+        unsafe {
+            (self.add)(
+                self,
+                false,
+                sw_address.as_ptr().cast(),
+                hw_address.as_ptr().cast(),
+                timeout,
+                overwrite,
+            )
+        }
+        .into()
+    }
+
+    /// Add a deny entry to the ARP cache, so that the requests from or to
+    /// the given software or hardware address are ignored. Exactly one of
+    /// `sw_address` and `hw_address` must be set.
+    ///
+    /// The entry is removed after `timeout` in units of 100ns, or never if
+    /// `timeout` is 0.
+    ///
+    /// # Errors
+    ///
+    /// See [`add`].
+    ///
+    /// [`add`]: Self::add
+    pub fn deny(
+        &mut self,
+        sw_address: Option<&[u8]>,
+        hw_address: Option<&[u8]>,
+        timeout: u32,
+        overwrite: bool,
+    ) -> Result {
+        unsafe {
+            (self.add)(
+                self,
+                true,
+                address_ptr(sw_address),
+                address_ptr(hw_address),
+                timeout,
+                overwrite,
+            )
+        }
+        .into()
+    }
+
+    /// Find the entries of the ARP cache matching `address`, which is a
+    /// software address if `by_sw_address` is true, and a hardware address
+    /// otherwise. All the entries are returned if `address` is `None`.
+    ///
+    /// If `refresh` is true, the timeouts of the matching entries are
+    /// reset.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    /// * [`Status::NOT_FOUND`]: no entry matches `address`.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::OUT_OF_RESOURCES`]: the entries could not be allocated.
+    pub fn find<'boot>(
+        &mut self,
+        bt: &'boot BootServices,
+        by_sw_address: bool,
+        address: Option<&[u8]>,
+        refresh: bool,
+    ) -> Result<ArpFindEntries<'boot>> {
+        let mut entry_length = 0;
+        let mut entry_count = 0;
+        let mut entries = ptr::null_mut();
+        unsafe {
+            (self.find)(
+                self,
+                by_sw_address,
+                address_ptr(address),
+                &mut entry_length,
+                &mut entry_count,
+                &mut entries,
+                refresh,
+            )
+        }
+        .into_with_val(|| ArpFindEntries {
+            boot_services: bt,
+            entries: NonNull::new(entries.cast()),
+            entry_length: entry_length as usize,
+            entry_count: if entries.is_null() {
+                0
+            } else {
+                entry_count as usize
+            },
+        })
+    }
+
+    /// Remove the entries of the ARP cache matching `address`, which is a
+    /// software address if `by_sw_address` is true, and a hardware address
+    /// otherwise. All the entries are removed if `address` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    /// * [`Status::NOT_FOUND`]: no entry matches `address`.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    pub fn delete(&mut self, by_sw_address: bool, address: Option<&[u8]>) -> Result {
+        unsafe { (self.delete)(self, by_sw_address, address_ptr(address)) }.into()
+    }
+
+    /// Remove all the dynamic entries of the ARP cache.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: there is no dynamic entry.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    pub fn flush(&mut self) -> Result {
+        unsafe { (self.flush)(self) }.into()
+    }
+
+    /// Start resolving `target_sw_address`. The hardware address is written
+    /// to `target_hw_address` and `resolved_event` is signaled once the
+    /// address is resolved, or once the request timed out.
+    ///
+    /// Returns `true` if the address was found in the cache, in which case
+    /// `target_hw_address` is written immediately and `resolved_event` is
+    /// not signaled.
+    ///
+    /// [`resolve`] is a blocking alternative to this function.
+    ///
+    /// # Safety
+    ///
+    /// `target_hw_address` must stay valid until `resolved_event` is
+    /// signaled, or the request is cancelled with [`cancel`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    /// * [`Status::ACCESS_DENIED`]: `target_sw_address` is denied by the
+    ///   cache.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   send the request.
+    ///
+    /// [`resolve`]: Self::resolve
+    /// [`cancel`]: Self::cancel
+    pub unsafe fn request(
+        &mut self,
+        target_sw_address: &[u8],
+        resolved_event: &Event,
+        target_hw_address: *mut MacAddress,
+    ) -> Result<bool> {
+        match (self.request)(
+            self,
+            target_sw_address.as_ptr().cast(),
+            Some(resolved_event.unsafe_clone()),
+            target_hw_address.cast(),
+        ) {
+            Status::SUCCESS => Ok(true),
+            Status::NOT_READY => Ok(false),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Resolve `target_sw_address` to a hardware address, waiting for the
+    /// reply if the address is not in the cache.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the request timed out.
+    ///
+    /// See [`request`] and [`BootServices::wait_for_event`] for the other
+    /// errors.
+    ///
+    /// [`request`]: Self::request
+    pub fn resolve(&mut self, bt: &BootServices, target_sw_address: &[u8]) -> Result<MacAddress> {
+        let event = unsafe { bt.create_event(EventType::empty(), Tpl::CALLBACK, None, None) }?;
+        let mut hw_address = MacAddress([0; 32]);
+        let result = self.resolve_with_event(bt, target_sw_address, &event, &mut hw_address);
+        // Ignore the result, the resolution result is more useful.
+        let _ = bt.close_event(event);
+        result?;
+        Ok(hw_address)
+    }
+
+    fn resolve_with_event(
+        &mut self,
+        bt: &BootServices,
+        target_sw_address: &[u8],
+        event: &Event,
+        hw_address: &mut MacAddress,
+    ) -> Result {
+        if unsafe { self.request(target_sw_address, event, hw_address) }? {
+            return Ok(());
+        }
+
+        let mut events = [unsafe { event.unsafe_clone() }];
+        if let Err(err) = bt.wait_for_event(&mut events) {
+            // Don't leave `hw_address` in the request queue.
+            let _ = self.cancel(Some(target_sw_address), Some(event));
+            return Err(err.into_err_without_payload());
+        }
+
+        // The event is also signaled on timeout, leaving the address unset.
+        if hw_address.0.iter().all(|&b| b == 0) {
+            Err(Status::NOT_FOUND.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Cancel the pending requests for `target_sw_address` with
+    /// `resolved_event`. If either is `None`, it matches all the pending
+    /// requests. The events of the cancelled requests are signaled.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: no pending request matches.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    pub fn cancel(
+        &mut self,
+        target_sw_address: Option<&[u8]>,
+        resolved_event: Option<&Event>,
+    ) -> Result {
+        let resolved_event = resolved_event.map(|event| unsafe { event.unsafe_clone() });
+        unsafe { (self.cancel)(self, address_ptr(target_sw_address), resolved_event) }.into()
+    }
+}
+
+/// Configuration of an [`Arp`] instance.
+///
+/// The corresponding C type is `EFI_ARP_CONFIG_DATA`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct ArpConfigData<'a> {
+    sw_address_type: u16,
+    sw_address_length: u8,
+    station_address: *const c_void,
+    /// Timeout of the dynamic entries of the cache in units of 100ns, or 0
+    /// for the default timeout.
+    pub entry_timeout: u32,
+    /// Number of retries before a request fails, or 0 for the default.
+    pub retry_count: u32,
+    /// Timeout of a request in units of 100ns, or 0 for the default
+    /// timeout.
+    pub retry_timeout: u32,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> ArpConfigData<'a> {
+    /// Software address type of IPv4 addresses.
+    pub const SW_ADDRESS_TYPE_IPV4: u16 = 0x0800;
+
+    /// Create a configuration for the station address `station_address`,
+    /// of type `sw_address_type` such as [`SW_ADDRESS_TYPE_IPV4`], with the
+    /// default timeouts.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: `station_address` is longer than 255
+    ///   bytes.
+    ///
+    /// [`SW_ADDRESS_TYPE_IPV4`]: Self::SW_ADDRESS_TYPE_IPV4
+    pub fn new(sw_address_type: u16, station_address: &'a [u8]) -> Result<Self> {
+        let sw_address_length =
+            u8::try_from(station_address.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        Ok(Self::with_length(
+            sw_address_type,
+            sw_address_length,
+            station_address,
+        ))
+    }
+
+    /// Create a configuration for the IPv4 station address
+    /// `station_address`, with the default timeouts.
+    #[must_use]
+    pub fn new_ipv4(station_address: &'a [u8; 4]) -> Self {
+        Self::with_length(Self::SW_ADDRESS_TYPE_IPV4, 4, station_address)
+    }
+
+    const fn with_length(
+        sw_address_type: u16,
+        sw_address_length: u8,
+        station_address: &'a [u8],
+    ) -> Self {
+        Self {
+            sw_address_type,
+            sw_address_length,
+            station_address: station_address.as_ptr().cast(),
+            entry_timeout: 0,
+            retry_count: 0,
+            retry_timeout: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// An entry of the ARP cache, returned by [`Arp::find`].
+///
+/// The corresponding C type is `EFI_ARP_FIND_DATA`. It is followed in
+/// memory by the software and hardware addresses.
+#[repr(C)]
+pub struct ArpFindData {
+    size: u32,
+    deny: bool,
+    is_static: bool,
+    hw_address_type: u16,
+    sw_address_type: u16,
+    hw_address_length: u8,
+    sw_address_length: u8,
+}
+
+impl ArpFindData {
+    /// Whether this is a deny entry.
+    #[must_use]
+    pub const fn is_deny(&self) -> bool {
+        self.deny
+    }
+
+    /// Whether this is a static entry, added with [`Arp::add`].
+    #[must_use]
+    pub const fn is_static(&self) -> bool {
+        self.is_static
+    }
+
+    /// Get the type of the hardware address, such as 1 for Ethernet.
+    #[must_use]
+    pub const fn hw_address_type(&self) -> u16 {
+        self.hw_address_type
+    }
+
+    /// Get the type of the software address, such as
+    /// [`ArpConfigData::SW_ADDRESS_TYPE_IPV4`].
+    #[must_use]
+    pub const fn sw_address_type(&self) -> u16 {
+        self.sw_address_type
+    }
+
+    /// Get the software address.
+    #[must_use]
+    pub fn sw_address(&self) -> &[u8] {
+        unsafe {
+            let address = (self as *const Self).add(1).cast::<u8>();
+            slice::from_raw_parts(address, usize::from(self.sw_address_length))
+        }
+    }
+
+    /// Get the hardware address.
+    #[must_use]
+    pub fn hw_address(&self) -> &[u8] {
+        unsafe {
+            let address = (self as *const Self)
+                .add(1)
+                .cast::<u8>()
+                .add(usize::from(self.sw_address_length));
+            slice::from_raw_parts(address, usize::from(self.hw_address_length))
+        }
+    }
+}
+
+impl fmt::Debug for ArpFindData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArpFindData")
+            .field("deny", &self.deny)
+            .field("is_static", &self.is_static)
+            .field("hw_address_type", &self.hw_address_type)
+            .field("sw_address_type", &self.sw_address_type)
+            .field("sw_address", &self.sw_address())
+            .field("hw_address", &self.hw_address())
+            .finish()
+    }
+}
+
+/// The entries returned by [`Arp::find`].
+///
+/// The entries are allocated by the driver, and freed when this is dropped.
+pub struct ArpFindEntries<'boot> {
+    boot_services: &'boot BootServices,
+    entries: Option<NonNull<u8>>,
+    entry_length: usize,
+    entry_count: usize,
+}
+
+impl ArpFindEntries<'_> {
+    /// Get the number of entries.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.entry_count
+    }
+
+    /// Whether there is no entry.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.entry_count == 0
+    }
+
+    /// Iterate over the entries.
+    pub fn iter(&self) -> impl Iterator<Item = &ArpFindData> + '_ {
+        // The entries are `entry_length` bytes apart, which may be more than
+        // the size of their addresses.
+        let stride = self.entry_length.max(mem::size_of::<ArpFindData>());
+        (0..self.entry_count).map(move |i| {
+            let entries = self.entries.unwrap().as_ptr();
+            unsafe { &*entries.add(i * stride).cast::<ArpFindData>() }
+        })
+    }
+}
+
+impl fmt::Debug for ArpFindEntries<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Drop for ArpFindEntries<'_> {
+    fn drop(&mut self) {
+        if let Some(entries) = self.entries {
+            // Ignore the result, we can't do anything about an error here.
+            let _ = self.boot_services.free_pool(entries.as_ptr());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use crate::table::boot::MemoryType;
+    use core::cell::Cell;
+
+    const STATION: [u8; 4] = [10, 0, 2, 15];
+    const GATEWAY: [u8; 4] = [10, 0, 2, 2];
+    const GATEWAY_MAC: [u8; 6] = [0x52, 0x55, 10, 0, 2, 2];
+    const UNKNOWN: [u8; 4] = [10, 0, 2, 3];
+
+    std::thread_local! {
+        /// Boot services used by the fake protocol to allocate and signal.
+        static BOOT_SERVICES: Cell<*const BootServices> = const { Cell::new(ptr::null()) };
+        /// Hardware address of the cache entry for `GATEWAY`, if any.
+        static ENTRY: Cell<Option<[u8; 6]>> = const { Cell::new(None) };
+    }
+
+    fn bt() -> &'static BootServices {
+        unsafe { &*BOOT_SERVICES.with(Cell::get) }
+    }
+
+    unsafe extern "efiapi" fn configure(
+        _this: *mut Arp,
+        config_data: *const ArpConfigData<'_>,
+    ) -> Status {
+        match config_data.as_ref() {
+            Some(data) if data.sw_address_type == ArpConfigData::SW_ADDRESS_TYPE_IPV4 => {
+                let address = slice::from_raw_parts(data.station_address.cast::<u8>(), 4);
+                if data.sw_address_length == 4 && address == STATION {
+                    Status::SUCCESS
+                } else {
+                    Status::INVALID_PARAMETER
+                }
+            }
+            Some(_) => Status::INVALID_PARAMETER,
+            None => Status::SUCCESS,
+        }
+    }
+
+    unsafe extern "efiapi" fn add(
+        _this: *mut Arp,
+        deny: bool,
+        target_sw_address: *const c_void,
+        target_hw_address: *const c_void,
+        _timeout: u32,
+        overwrite: bool,
+    ) -> Status {
+        if deny || target_sw_address.cast::<[u8; 4]>().read() != GATEWAY {
+            return Status::UNSUPPORTED;
+        }
+        if ENTRY.with(Cell::get).is_some() && !overwrite {
+            return Status::ACCESS_DENIED;
+        }
+        ENTRY.with(|entry| entry.set(Some(target_hw_address.cast::<[u8; 6]>().read())));
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn find(
+        _this: *mut Arp,
+        _by_sw_address: bool,
+        _address: *const c_void,
+        entry_length: *mut u32,
+        entry_count: *mut u32,
+        entries: *mut *mut ArpFindData,
+        _refresh: bool,
+    ) -> Status {
+        let hw_address = match ENTRY.with(Cell::get) {
+            Some(hw_address) => hw_address,
+            None => return Status::NOT_FOUND,
+        };
+        // Pad the entry to check that `entry_length` is used as the stride.
+        let length = mem::size_of::<ArpFindData>() + 4 + 6 + 2;
+        let buffer = bt()
+            .allocate_pool(MemoryType::BOOT_SERVICES_DATA, length)
+            .unwrap();
+        buffer.cast::<ArpFindData>().write(ArpFindData {
+            size: length as u32,
+            deny: false,
+            is_static: true,
+            hw_address_type: 1,
+            sw_address_type: ArpConfigData::SW_ADDRESS_TYPE_IPV4,
+            hw_address_length: 6,
+            sw_address_length: 4,
+        });
+        let addresses = buffer.add(mem::size_of::<ArpFindData>());
+        addresses.copy_from(GATEWAY.as_ptr(), 4);
+        addresses.add(4).copy_from(hw_address.as_ptr(), 6);
+        *entry_length = length as u32;
+        *entry_count = 1;
+        *entries = buffer.cast();
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn delete(
+        _this: *mut Arp,
+        _by_sw_address: bool,
+        _address: *const c_void,
+    ) -> Status {
+        match ENTRY.with(|entry| entry.take()) {
+            Some(_) => Status::SUCCESS,
+            None => Status::NOT_FOUND,
+        }
+    }
+
+    unsafe extern "efiapi" fn flush(_this: *mut Arp) -> Status {
+        Status::NOT_FOUND
+    }
+
+    unsafe extern "efiapi" fn request(
+        _this: *mut Arp,
+        target_sw_address: *const c_void,
+        resolved_event: Option<Event>,
+        target_hw_address: *mut c_void,
+    ) -> Status {
+        if let Some(hw_address) = ENTRY.with(Cell::get) {
+            target_hw_address.cast::<[u8; 6]>().write(hw_address);
+            return Status::SUCCESS;
+        }
+        // Reply immediately for `GATEWAY`, and time out for other addresses.
+        if target_sw_address.cast::<[u8; 4]>().read() == GATEWAY {
+            target_hw_address.cast::<[u8; 6]>().write(GATEWAY_MAC);
+        }
+        bt().signal_event(&resolved_event.unwrap()).unwrap();
+        Status::NOT_READY
+    }
+
+    unsafe extern "efiapi" fn cancel(
+        _this: *mut Arp,
+        _target_sw_address: *const c_void,
+        _resolved_event: Option<Event>,
+    ) -> Status {
+        Status::NOT_FOUND
+    }
+
+    #[test]
+    fn test_arp() {
+        let mut firmware = MockFirmware::new();
+        let mut protocol = Arp {
+            configure,
+            add,
+            find,
+            delete,
+            flush,
+            request,
+            cancel,
+            _no_send_or_sync: PhantomData,
+        };
+        let handle = unsafe { firmware.install_protocol(None, &mut protocol) };
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        BOOT_SERVICES.with(|cell| cell.set(bt));
+        let mut arp = bt.open_protocol_exclusive::<Arp>(handle).unwrap();
+
+        arp.configure(Some(&ArpConfigData::new_ipv4(&STATION)))
+            .unwrap();
+        assert_eq!(
+            arp.configure(Some(&ArpConfigData::new_ipv4(&GATEWAY)))
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+        assert_eq!(
+            ArpConfigData::new(ArpConfigData::SW_ADDRESS_TYPE_IPV4, &[0; 256])
+                .unwrap_err()
+                .status(),
+            Status::BAD_BUFFER_SIZE
+        );
+
+        // Resolve through the network.
+        assert_eq!(arp.resolve(bt, &GATEWAY).unwrap().0[..6], GATEWAY_MAC);
+        assert_eq!(
+            arp.resolve(bt, &UNKNOWN)
+                .map(|mac| mac.0)
+                .unwrap_err()
+                .status(),
+            Status::NOT_FOUND
+        );
+        assert_eq!(
+            arp.find(bt, true, None, false).unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+
+        // Resolve through the cache.
+        let hw_address = [0x52, 0x54, 0, 0, 0, 2];
+        arp.add(&GATEWAY, &hw_address, 0, false).unwrap();
+        assert_eq!(
+            arp.add(&GATEWAY, &hw_address, 0, false)
+                .unwrap_err()
+                .status(),
+            Status::ACCESS_DENIED
+        );
+        assert_eq!(arp.resolve(bt, &GATEWAY).unwrap().0[..6], hw_address);
+
+        let entries = arp.find(bt, true, Some(&GATEWAY), false).unwrap();
+        assert_eq!(entries.len(), 1);
+        let entry = entries.iter().next().unwrap();
+        assert!(entry.is_static());
+        assert!(!entry.is_deny());
+        assert_eq!(entry.sw_address_type(), ArpConfigData::SW_ADDRESS_TYPE_IPV4);
+        assert_eq!(entry.sw_address(), GATEWAY);
+        assert_eq!(entry.hw_address(), hw_address);
+        drop(entries);
+
+        arp.delete(true, Some(&GATEWAY)).unwrap();
+        assert_eq!(arp.flush().unwrap_err().status(), Status::NOT_FOUND);
+        assert_eq!(
+            arp.cancel(None, None).unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+        arp.configure(None).unwrap();
+    }
+}
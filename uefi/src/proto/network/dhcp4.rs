@@ -0,0 +1,946 @@
+//! DHCPv4 protocol.
+//!
+//! The [`Dhcp4`] protocol runs the DHCPv4 transactions of a network
+//! interface: it acquires, renews, and releases a lease, and gives access to
+//! the reply of the server. Instances of this protocol are created with the
+//! [`ServiceBindingProtocol<Dhcp4>`] installed on the network controller
+//! handle.
+//!
+//! The options of a DHCP packet are parsed into [`Dhcp4Option`]s by
+//! [`Dhcp4Options`], which also works on the packets of the [`BaseCode`]
+//! protocol, and are encoded with [`Dhcp4OptionBuf`].
+//!
+//! [`ServiceBindingProtocol<Dhcp4>`]: crate::proto::service_binding::ServiceBindingProtocol
+//! [`BaseCode`]: super::pxe::BaseCode
+
+use super::MacAddress;
+use crate::proto::unsafe_protocol;
+use crate::table::boot::BootServices;
+use crate::{Event, Result, Status};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
+use core::{fmt, slice};
+
+/// The DHCPv4 protocol.
+///
+/// The corresponding C type is `EFI_DHCP4_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol(
+    "8a219718-4ef5-4761-91c8-c0f04bda9e56",
+    service_binding = "9d9a39d8-bd42-4a73-a4d5-8ee94be11380"
+)]
+pub struct Dhcp4 {
+    get_mode_data:
+        unsafe extern "efiapi" fn(this: *const Self, mode_data: *mut Dhcp4ModeData) -> Status,
+    configure: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        config_data: *const Dhcp4ConfigData<'_>,
+    ) -> Status,
+    start: unsafe extern "efiapi" fn(this: *mut Self, completion_event: Option<Event>) -> Status,
+    renew_rebind: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        rebind: bool,
+        completion_event: Option<Event>,
+    ) -> Status,
+    release: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+    stop: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+    build: unsafe extern "efiapi" fn(
+        this: *const Self,
+        seed_packet: *const Dhcp4Packet,
+        delete_count: u32,
+        delete_list: *const u8,
+        append_count: u32,
+        append_list: *const &Dhcp4OptionBuf,
+        new_packet: *mut *mut Dhcp4Packet,
+    ) -> Status,
+    transmit_receive: usize,
+    parse: usize,
+}
+
+impl Dhcp4 {
+    /// Get the state of the DHCP transaction and of the lease.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    pub fn mode_data(&self) -> Result<Dhcp4ModeData> {
+        let mut mode_data = MaybeUninit::uninit();
+        unsafe { (self.get_mode_data)(self, mode_data.as_mut_ptr()) }
+            .into_with_val(|| unsafe { mode_data.assume_init() })
+    }
+
+    /// Configure this instance with `config_data`, or reset it to the
+    /// stopped state if `config_data` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `config_data` is invalid.
+    /// * [`Status::ACCESS_DENIED`]: the instance is not in a state in which
+    ///   it can be configured, or another instance is already configured.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   configure the instance.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn configure(&mut self, config_data: Option<&Dhcp4ConfigData<'_>>) -> Result {
+        let config_data = config_data.map_or(ptr::null(), |data| data as *const _);
+        unsafe { (self.configure)(self, config_data) }.into()
+    }
+
+    /// Start acquiring a lease.
+    ///
+    /// If `completion_event` is `None`, this waits until the lease is
+    /// acquired or the transaction failed. Otherwise, this returns
+    /// immediately and `completion_event` is signaled once done, after
+    /// which the result can be read from [`mode_data`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::ALREADY_STARTED`]: the transaction is already running.
+    /// * [`Status::NO_MEDIA`]: the network cable is not connected.
+    /// * [`Status::TIMEOUT`]: no valid reply was received.
+    /// * [`Status::ABORTED`]: the transaction was stopped.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    ///
+    /// [`mode_data`]: Self::mode_data
+    pub fn start(&mut self, completion_event: Option<&Event>) -> Result {
+        let completion_event = completion_event.map(|event| unsafe { event.unsafe_clone() });
+        unsafe { (self.start)(self, completion_event) }.into()
+    }
+
+    /// Extend the lease, from the server that granted it, or from any server
+    /// if `rebind` is true.
+    ///
+    /// `completion_event` is used as in [`start`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: there is no lease to extend.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::ALREADY_STARTED`]: the transaction is already running.
+    /// * [`Status::TIMEOUT`]: no valid reply was received.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    ///
+    /// [`start`]: Self::start
+    pub fn renew_rebind(&mut self, rebind: bool, completion_event: Option<&Event>) -> Result {
+        let completion_event = completion_event.map(|event| unsafe { event.unsafe_clone() });
+        unsafe { (self.renew_rebind)(self, rebind, completion_event) }.into()
+    }
+
+    /// Release the lease, and return to the initial state.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: there is no lease to release.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn release(&mut self) -> Result {
+        unsafe { (self.release)(self) }.into()
+    }
+
+    /// Stop the transaction, and return to the stopped state.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    pub fn stop(&mut self) -> Result {
+        unsafe { (self.stop)(self) }.into()
+    }
+
+    /// Build a new packet from `seed`, removing the options whose code is
+    /// in `delete`, and adding the options in `append`, which replace the
+    /// options of `seed` with the same code.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `seed` is invalid.
+    /// * [`Status::BAD_BUFFER_SIZE`]: `delete` or `append` has more than
+    ///   `u32::MAX` items.
+    /// * [`Status::OUT_OF_RESOURCES`]: the packet could not be allocated.
+    pub fn build<'boot>(
+        &self,
+        bt: &'boot BootServices,
+        seed: &Dhcp4Packet,
+        delete: &[u8],
+        append: &[&Dhcp4OptionBuf],
+    ) -> Result<Dhcp4PacketBuf<'boot>> {
+        let delete_count = u32::try_from(delete.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let append_count = u32::try_from(append.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let mut packet = ptr::null_mut();
+        unsafe {
+            (self.build)(
+                self,
+                seed,
+                delete_count,
+                delete.as_ptr(),
+                append_count,
+                append.as_ptr(),
+                &mut packet,
+            )
+        }
+        .into_with_val(|| NonNull::new(packet))?
+        .map(|packet| Dhcp4PacketBuf {
+            boot_services: bt,
+            packet,
+        })
+        .ok_or_else(|| Status::OUT_OF_RESOURCES.into())
+    }
+}
+
+newtype_enum! {
+    /// State of a DHCPv4 transaction.
+    pub enum Dhcp4State: u32 => {
+        /// The instance is not configured.
+        STOPPED = 0,
+        /// The instance is configured, but has no lease.
+        INIT = 1,
+        /// Waiting for the offers of the servers.
+        SELECTING = 2,
+        /// Waiting for the acknowledgement of the selected server.
+        REQUESTING = 3,
+        /// The lease is acquired.
+        BOUND = 4,
+        /// Extending the lease with the server that granted it.
+        RENEWING = 5,
+        /// Extending the lease with any server.
+        REBINDING = 6,
+        /// Reusing a previous address after a reboot.
+        INIT_REBOOT = 7,
+        /// Waiting for the server to confirm a previous address.
+        REBOOTING = 8,
+    }
+}
+
+/// State of a [`Dhcp4`] instance and of its lease.
+///
+/// The corresponding C type is `EFI_DHCP4_MODE_DATA`.
+#[repr(C)]
+pub struct Dhcp4ModeData {
+    /// State of the DHCP transaction.
+    pub state: Dhcp4State,
+    config_data: Dhcp4ConfigData<'static>,
+    /// The leased address.
+    pub client_address: [u8; 4],
+    /// The hardware address of the network interface.
+    pub client_mac_address: MacAddress,
+    /// Address of the server that granted the lease.
+    pub server_address: [u8; 4],
+    /// Address of the default router.
+    pub router_address: [u8; 4],
+    /// Subnet mask of the leased address.
+    pub subnet_mask: [u8; 4],
+    /// Duration of the lease in seconds, or `u32::MAX` for an infinite
+    /// lease.
+    pub lease_time: u32,
+    reply_packet: *const Dhcp4Packet,
+}
+
+impl Dhcp4ModeData {
+    /// Get the last acknowledgement of the server, if a lease was acquired.
+    ///
+    /// The packet belongs to the driver, and is only valid until the state
+    /// of the instance changes.
+    #[must_use]
+    pub fn reply_packet(&self) -> Option<&Dhcp4Packet> {
+        unsafe { self.reply_packet.as_ref() }
+    }
+}
+
+impl fmt::Debug for Dhcp4ModeData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcp4ModeData")
+            .field("state", &self.state)
+            .field("client_address", &self.client_address)
+            .field("server_address", &self.server_address)
+            .field("router_address", &self.router_address)
+            .field("subnet_mask", &self.subnet_mask)
+            .field("lease_time", &self.lease_time)
+            .field("reply_packet", &self.reply_packet())
+            .finish()
+    }
+}
+
+/// Configuration of a [`Dhcp4`] instance.
+///
+/// The corresponding C type is `EFI_DHCP4_CONFIG_DATA`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct Dhcp4ConfigData<'a> {
+    discover_try_count: u32,
+    discover_timeout: *const u32,
+    request_try_count: u32,
+    request_timeout: *const u32,
+    /// The address to request, or `[0; 4]` to let the server choose. If the
+    /// address is set and the server grants it, the transaction starts in
+    /// the [`Dhcp4State::INIT_REBOOT`] state.
+    pub client_address: [u8; 4],
+    callback: usize,
+    callback_context: *mut c_void,
+    option_count: u32,
+    option_list: *const &'a Dhcp4OptionBuf,
+    _marker: PhantomData<&'a [u32]>,
+}
+
+impl<'a> Dhcp4ConfigData<'a> {
+    /// Create a configuration with the default timeouts and no options.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            discover_try_count: 0,
+            discover_timeout: ptr::null(),
+            request_try_count: 0,
+            request_timeout: ptr::null(),
+            client_address: [0; 4],
+            callback: 0,
+            callback_context: ptr::null_mut(),
+            option_count: 0,
+            option_list: ptr::null(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Send a discover packet for each item of `timeouts`, waiting for the
+    /// offers for the item's value in seconds.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: `timeouts` has more than `u32::MAX`
+    ///   items.
+    pub fn with_discover_timeouts(mut self, timeouts: &'a [u32]) -> Result<Self> {
+        self.discover_try_count =
+            u32::try_from(timeouts.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        self.discover_timeout = timeouts.as_ptr();
+        Ok(self)
+    }
+
+    /// Send a request packet for each item of `timeouts`, waiting for the
+    /// acknowledgement for the item's value in seconds.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: `timeouts` has more than `u32::MAX`
+    ///   items.
+    pub fn with_request_timeouts(mut self, timeouts: &'a [u32]) -> Result<Self> {
+        self.request_try_count =
+            u32::try_from(timeouts.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        self.request_timeout = timeouts.as_ptr();
+        Ok(self)
+    }
+
+    /// Add `options` to the packets sent to the servers, such as a
+    /// [`Dhcp4Option::ParameterRequestList`] or a
+    /// [`Dhcp4Option::VendorClassIdentifier`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: `options` has more than `u32::MAX`
+    ///   items.
+    pub fn with_options(mut self, options: &'a [&'a Dhcp4OptionBuf]) -> Result<Self> {
+        self.option_count = u32::try_from(options.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        self.option_list = options.as_ptr();
+        Ok(self)
+    }
+}
+
+impl Default for Dhcp4ConfigData<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The BOOTP header of a DHCPv4 packet. The fields are in network byte
+/// order.
+///
+/// The corresponding C type is `EFI_DHCP4_HEADER`.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Dhcp4Header {
+    /// Message op code: 1 for a request, 2 for a reply.
+    pub op_code: u8,
+    /// Hardware address type, such as 1 for Ethernet.
+    pub hw_type: u8,
+    /// Hardware address length.
+    pub hw_address_length: u8,
+    /// Number of relay agents that forwarded the packet.
+    pub hops: u8,
+    xid: [u8; 4],
+    seconds: [u8; 2],
+    reserved: [u8; 2],
+    /// Address of the client, if it already has one.
+    pub client_address: [u8; 4],
+    /// Address offered to the client.
+    pub your_address: [u8; 4],
+    /// Address of the next server to use in the boot process, such as a
+    /// TFTP server.
+    pub server_address: [u8; 4],
+    /// Address of the relay agent.
+    pub gateway_address: [u8; 4],
+    /// Hardware address of the client.
+    pub client_hw_address: [u8; 16],
+    /// Null-terminated host name of the server.
+    pub server_name: [u8; 64],
+    /// Null-terminated boot file name.
+    pub boot_file_name: [u8; 128],
+}
+
+impl Dhcp4Header {
+    /// Get the transaction ID, chosen by the client.
+    #[must_use]
+    pub const fn xid(&self) -> u32 {
+        u32::from_be_bytes(self.xid)
+    }
+
+    /// Get the number of seconds since the client started the transaction.
+    #[must_use]
+    pub const fn seconds(&self) -> u16 {
+        u16::from_be_bytes(self.seconds)
+    }
+}
+
+impl fmt::Debug for Dhcp4Header {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcp4Header")
+            .field("op_code", &self.op_code)
+            .field("xid", &self.xid())
+            .field("client_address", &self.client_address)
+            .field("your_address", &self.your_address)
+            .field("server_address", &self.server_address)
+            .field("gateway_address", &self.gateway_address)
+            .finish()
+    }
+}
+
+/// A DHCPv4 packet. The header is followed in memory by the options.
+///
+/// The corresponding C type is `EFI_DHCP4_PACKET`.
+#[repr(C)]
+pub struct Dhcp4Packet {
+    size: u32,
+    length: u32,
+    header: Dhcp4Header,
+    magik: [u8; 4],
+}
+
+impl Dhcp4Packet {
+    /// The magic cookie preceding the options.
+    pub const MAGIK: u32 = 0x6382_5363;
+
+    /// Get the BOOTP header.
+    #[must_use]
+    pub const fn header(&self) -> &Dhcp4Header {
+        &self.header
+    }
+
+    /// Get the magic cookie, which should be [`Self::MAGIK`].
+    #[must_use]
+    pub const fn magik(&self) -> u32 {
+        u32::from_be_bytes(self.magik)
+    }
+
+    /// Get the bytes of the options.
+    #[must_use]
+    pub fn options_bytes(&self) -> &[u8] {
+        // `length` covers the header, the magic cookie, and the options.
+        let len = (self.length as usize)
+            .saturating_sub(mem::size_of::<Dhcp4Header>() + mem::size_of::<u32>());
+        unsafe { slice::from_raw_parts((self as *const Self).add(1).cast::<u8>(), len) }
+    }
+
+    /// Iterate over the options.
+    #[must_use]
+    pub fn options(&self) -> Dhcp4Options<'_> {
+        Dhcp4Options::new(self.options_bytes())
+    }
+
+    /// Find the first option with the given `code`.
+    #[must_use]
+    pub fn find_option(&self, code: u8) -> Option<Dhcp4Option<'_>> {
+        self.options().find(|option| option.code() == code)
+    }
+
+    /// Get the boot file name, from the [`Dhcp4Option::BootFileName`]
+    /// option if present, and from the BOOTP header otherwise.
+    #[must_use]
+    pub fn boot_file_name(&self) -> Option<&[u8]> {
+        match self.find_option(Dhcp4Option::BOOT_FILE_NAME) {
+            Some(Dhcp4Option::BootFileName(name)) => Some(name),
+            _ => non_empty_c_str(&self.header.boot_file_name),
+        }
+    }
+
+    /// Get the name of the TFTP server, from the
+    /// [`Dhcp4Option::TftpServerName`] option if present, and from the
+    /// BOOTP header otherwise.
+    #[must_use]
+    pub fn tftp_server_name(&self) -> Option<&[u8]> {
+        match self.find_option(Dhcp4Option::TFTP_SERVER_NAME) {
+            Some(Dhcp4Option::TftpServerName(name)) => Some(name),
+            _ => non_empty_c_str(&self.header.server_name),
+        }
+    }
+}
+
+impl fmt::Debug for Dhcp4Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcp4Packet")
+            .field("header", &self.header)
+            .field("options", &self.options())
+            .finish()
+    }
+}
+
+/// Get the bytes of the null-terminated string in `bytes`, or `None` if
+/// the string is empty.
+fn non_empty_c_str(bytes: &[u8]) -> Option<&[u8]> {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    (len != 0).then_some(&bytes[..len])
+}
+
+/// A DHCPv4 packet allocated by [`Dhcp4::build`], freed when dropped.
+pub struct Dhcp4PacketBuf<'boot> {
+    boot_services: &'boot BootServices,
+    packet: NonNull<Dhcp4Packet>,
+}
+
+impl Deref for Dhcp4PacketBuf<'_> {
+    type Target = Dhcp4Packet;
+
+    fn deref(&self) -> &Dhcp4Packet {
+        unsafe { self.packet.as_ref() }
+    }
+}
+
+impl fmt::Debug for Dhcp4PacketBuf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+impl Drop for Dhcp4PacketBuf<'_> {
+    fn drop(&mut self) {
+        // Ignore the result, we can't do anything about an error here.
+        let _ = self.boot_services.free_pool(self.packet.as_ptr().cast());
+    }
+}
+
+newtype_enum! {
+    /// The type of a DHCPv4 message, in the [`Dhcp4Option::MessageType`]
+    /// option.
+    pub enum Dhcp4MessageType: u8 => {
+        /// The client looks for servers.
+        DISCOVER = 1,
+        /// A server offers an address.
+        OFFER = 2,
+        /// The client requests the offered address.
+        REQUEST = 3,
+        /// The client declines the offered address.
+        DECLINE = 4,
+        /// The server grants the address.
+        ACK = 5,
+        /// The server refuses the request.
+        NAK = 6,
+        /// The client releases its address.
+        RELEASE = 7,
+        /// The client asks for its configuration, without an address.
+        INFORM = 8,
+    }
+}
+
+/// A DHCPv4 option, as defined in RFC 2132 and RFC 4578.
+///
+/// Options with an unexpected length are returned as [`Other`].
+///
+/// [`Other`]: Self::Other
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Dhcp4Option<'a> {
+    /// The subnet mask of the client.
+    SubnetMask([u8; 4]),
+    /// The routers of the subnet, as a list of 4-byte addresses.
+    Router(&'a [u8]),
+    /// The DNS servers, as a list of 4-byte addresses.
+    DomainNameServer(&'a [u8]),
+    /// The host name of the client.
+    HostName(&'a [u8]),
+    /// The domain name of the client.
+    DomainName(&'a [u8]),
+    /// Vendor-specific information, such as the PXE options.
+    VendorSpecific(&'a [u8]),
+    /// The address requested by the client.
+    RequestedIpAddress([u8; 4]),
+    /// The duration of the lease in seconds.
+    LeaseTime(u32),
+    /// The type of the DHCP message.
+    MessageType(Dhcp4MessageType),
+    /// The address of the server.
+    ServerIdentifier([u8; 4]),
+    /// The codes of the options requested by the client.
+    ParameterRequestList(&'a [u8]),
+    /// The time in seconds after which the client renews its lease.
+    RenewalTime(u32),
+    /// The time in seconds after which the client rebinds its lease.
+    RebindingTime(u32),
+    /// The class of the client, such as `PXEClient:Arch:00007`.
+    VendorClassIdentifier(&'a [u8]),
+    /// The unique identifier of the client.
+    ClientIdentifier(&'a [u8]),
+    /// The name of the TFTP server.
+    TftpServerName(&'a [u8]),
+    /// The name of the boot file.
+    BootFileName(&'a [u8]),
+    /// The architecture of the client, such as 7 for x64 UEFI.
+    ClientSystemArchitecture(u16),
+    /// Any other option.
+    Other {
+        /// The code of the option.
+        code: u8,
+        /// The data of the option.
+        data: &'a [u8],
+    },
+}
+
+impl<'a> Dhcp4Option<'a> {
+    /// Code of the padding option, which has no length byte.
+    pub const PAD: u8 = 0;
+    /// Code of [`Self::SubnetMask`].
+    pub const SUBNET_MASK: u8 = 1;
+    /// Code of [`Self::Router`].
+    pub const ROUTER: u8 = 3;
+    /// Code of [`Self::DomainNameServer`].
+    pub const DOMAIN_NAME_SERVER: u8 = 6;
+    /// Code of [`Self::HostName`].
+    pub const HOST_NAME: u8 = 12;
+    /// Code of [`Self::DomainName`].
+    pub const DOMAIN_NAME: u8 = 15;
+    /// Code of [`Self::VendorSpecific`].
+    pub const VENDOR_SPECIFIC: u8 = 43;
+    /// Code of [`Self::RequestedIpAddress`].
+    pub const REQUESTED_IP_ADDRESS: u8 = 50;
+    /// Code of [`Self::LeaseTime`].
+    pub const LEASE_TIME: u8 = 51;
+    /// Code of [`Self::MessageType`].
+    pub const MESSAGE_TYPE: u8 = 53;
+    /// Code of [`Self::ServerIdentifier`].
+    pub const SERVER_IDENTIFIER: u8 = 54;
+    /// Code of [`Self::ParameterRequestList`].
+    pub const PARAMETER_REQUEST_LIST: u8 = 55;
+    /// Code of [`Self::RenewalTime`].
+    pub const RENEWAL_TIME: u8 = 58;
+    /// Code of [`Self::RebindingTime`].
+    pub const REBINDING_TIME: u8 = 59;
+    /// Code of [`Self::VendorClassIdentifier`].
+    pub const VENDOR_CLASS_IDENTIFIER: u8 = 60;
+    /// Code of [`Self::ClientIdentifier`].
+    pub const CLIENT_IDENTIFIER: u8 = 61;
+    /// Code of [`Self::TftpServerName`].
+    pub const TFTP_SERVER_NAME: u8 = 66;
+    /// Code of [`Self::BootFileName`].
+    pub const BOOT_FILE_NAME: u8 = 67;
+    /// Code of [`Self::ClientSystemArchitecture`].
+    pub const CLIENT_SYSTEM_ARCHITECTURE: u8 = 93;
+    /// Code of the end option, which has no length byte.
+    pub const END: u8 = 255;
+
+    /// Parse the option with the given `code` and `data`.
+    #[must_use]
+    pub fn parse(code: u8, data: &'a [u8]) -> Self {
+        let other = Self::Other { code, data };
+        let address = <[u8; 4]>::try_from(data);
+        let time = address.map(u32::from_be_bytes);
+        match code {
+            Self::SUBNET_MASK => address.map_or(other, Self::SubnetMask),
+            Self::ROUTER => Self::Router(data),
+            Self::DOMAIN_NAME_SERVER => Self::DomainNameServer(data),
+            Self::HOST_NAME => Self::HostName(data),
+            Self::DOMAIN_NAME => Self::DomainName(data),
+            Self::VENDOR_SPECIFIC => Self::VendorSpecific(data),
+            Self::REQUESTED_IP_ADDRESS => address.map_or(other, Self::RequestedIpAddress),
+            Self::LEASE_TIME => time.map_or(other, Self::LeaseTime),
+            Self::MESSAGE_TYPE => match data {
+                [message_type] => Self::MessageType(Dhcp4MessageType(*message_type)),
+                _ => other,
+            },
+            Self::SERVER_IDENTIFIER => address.map_or(other, Self::ServerIdentifier),
+            Self::PARAMETER_REQUEST_LIST => Self::ParameterRequestList(data),
+            Self::RENEWAL_TIME => time.map_or(other, Self::RenewalTime),
+            Self::REBINDING_TIME => time.map_or(other, Self::RebindingTime),
+            Self::VENDOR_CLASS_IDENTIFIER => Self::VendorClassIdentifier(data),
+            Self::CLIENT_IDENTIFIER => Self::ClientIdentifier(data),
+            Self::TFTP_SERVER_NAME => Self::TftpServerName(data),
+            Self::BOOT_FILE_NAME => Self::BootFileName(data),
+            Self::CLIENT_SYSTEM_ARCHITECTURE => <[u8; 2]>::try_from(data).map_or(other, |arch| {
+                Self::ClientSystemArchitecture(u16::from_be_bytes(arch))
+            }),
+            _ => other,
+        }
+    }
+
+    /// Get the code of the option.
+    #[must_use]
+    pub const fn code(&self) -> u8 {
+        match self {
+            Self::SubnetMask(_) => Self::SUBNET_MASK,
+            Self::Router(_) => Self::ROUTER,
+            Self::DomainNameServer(_) => Self::DOMAIN_NAME_SERVER,
+            Self::HostName(_) => Self::HOST_NAME,
+            Self::DomainName(_) => Self::DOMAIN_NAME,
+            Self::VendorSpecific(_) => Self::VENDOR_SPECIFIC,
+            Self::RequestedIpAddress(_) => Self::REQUESTED_IP_ADDRESS,
+            Self::LeaseTime(_) => Self::LEASE_TIME,
+            Self::MessageType(_) => Self::MESSAGE_TYPE,
+            Self::ServerIdentifier(_) => Self::SERVER_IDENTIFIER,
+            Self::ParameterRequestList(_) => Self::PARAMETER_REQUEST_LIST,
+            Self::RenewalTime(_) => Self::RENEWAL_TIME,
+            Self::RebindingTime(_) => Self::REBINDING_TIME,
+            Self::VendorClassIdentifier(_) => Self::VENDOR_CLASS_IDENTIFIER,
+            Self::ClientIdentifier(_) => Self::CLIENT_IDENTIFIER,
+            Self::TftpServerName(_) => Self::TFTP_SERVER_NAME,
+            Self::BootFileName(_) => Self::BOOT_FILE_NAME,
+            Self::ClientSystemArchitecture(_) => Self::CLIENT_SYSTEM_ARCHITECTURE,
+            Self::Other { code, .. } => *code,
+        }
+    }
+
+    /// Write the data of the option to `buf`, returning its length, or
+    /// `None` if `buf` is too small.
+    fn write_data(&self, buf: &mut [u8]) -> Option<usize> {
+        let mut write = |data: &[u8]| {
+            buf.get_mut(..data.len())?.copy_from_slice(data);
+            Some(data.len())
+        };
+        match *self {
+            Self::SubnetMask(address)
+            | Self::RequestedIpAddress(address)
+            | Self::ServerIdentifier(address) => write(&address),
+            Self::LeaseTime(time) | Self::RenewalTime(time) | Self::RebindingTime(time) => {
+                write(&time.to_be_bytes())
+            }
+            Self::MessageType(message_type) => write(&[message_type.0]),
+            Self::ClientSystemArchitecture(arch) => write(&arch.to_be_bytes()),
+            Self::Router(data)
+            | Self::DomainNameServer(data)
+            | Self::HostName(data)
+            | Self::DomainName(data)
+            | Self::VendorSpecific(data)
+            | Self::ParameterRequestList(data)
+            | Self::VendorClassIdentifier(data)
+            | Self::ClientIdentifier(data)
+            | Self::TftpServerName(data)
+            | Self::BootFileName(data)
+            | Self::Other { data, .. } => write(data),
+        }
+    }
+}
+
+/// Iterator over the [`Dhcp4Option`]s encoded in a byte slice.
+///
+/// Padding is skipped, and the iteration stops at the end option or at the
+/// first truncated option. Options split with the option overload option
+/// are not merged.
+#[derive(Clone)]
+pub struct Dhcp4Options<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Dhcp4Options<'a> {
+    /// Create an iterator over the options encoded in `data`, which starts
+    /// after the magic cookie of the packet.
+    #[must_use]
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for Dhcp4Options<'a> {
+    type Item = Dhcp4Option<'a>;
+
+    fn next(&mut self) -> Option<Dhcp4Option<'a>> {
+        loop {
+            match *self.data {
+                [Dhcp4Option::PAD, ref rest @ ..] => self.data = rest,
+                [code, len, ref rest @ ..] if code != Dhcp4Option::END => {
+                    let len = usize::from(len);
+                    if rest.len() < len {
+                        break;
+                    }
+                    let (data, rest) = rest.split_at(len);
+                    self.data = rest;
+                    return Some(Dhcp4Option::parse(code, data));
+                }
+                _ => break,
+            }
+        }
+        self.data = &[];
+        None
+    }
+}
+
+impl fmt::Debug for Dhcp4Options<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// An encoded DHCPv4 option, to pass to [`Dhcp4ConfigData::with_options`]
+/// and [`Dhcp4::build`].
+///
+/// The corresponding C type is `EFI_DHCP4_PACKET_OPTION`.
+#[derive(Clone)]
+#[repr(C)]
+pub struct Dhcp4OptionBuf {
+    code: u8,
+    length: u8,
+    data: [u8; 255],
+}
+
+impl Dhcp4OptionBuf {
+    /// Encode `option`, or return `None` if its data is longer than 255
+    /// bytes.
+    #[must_use]
+    pub fn new(option: &Dhcp4Option<'_>) -> Option<Self> {
+        let mut buf = Self {
+            code: option.code(),
+            length: 0,
+            data: [0; 255],
+        };
+        buf.length = u8::try_from(option.write_data(&mut buf.data)?).ok()?;
+        Some(buf)
+    }
+
+    /// Get the encoded option.
+    #[must_use]
+    pub fn option(&self) -> Dhcp4Option<'_> {
+        Dhcp4Option::parse(self.code, &self.data[..usize::from(self.length)])
+    }
+
+    /// Get the encoded bytes of the option, starting with its code and
+    /// length.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        let bytes = unsafe {
+            slice::from_raw_parts((self as *const Self).cast::<u8>(), mem::size_of::<Self>())
+        };
+        &bytes[..2 + usize::from(self.length)]
+    }
+}
+
+impl fmt::Debug for Dhcp4OptionBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.option().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let data = [
+            53, 1, 5, // message type
+            0, // padding
+            1, 4, 255, 255, 255, 0, // subnet mask
+            51, 4, 0, 0, 0x0e, 0x10, // lease time
+            67, 4, b'b', b'o', b'o', b't', // boot file name
+            93, 2, 0, 7, // client architecture
+            51, 1, 0, // lease time with a wrong length
+            200, 2, 1, 2,   // unknown option
+            255, // end
+            1, 4, 0, 0, 0, 0, // after the end
+        ];
+        let options = Dhcp4Options::new(&data);
+        let expected = [
+            Dhcp4Option::MessageType(Dhcp4MessageType::ACK),
+            Dhcp4Option::SubnetMask([255, 255, 255, 0]),
+            Dhcp4Option::LeaseTime(3600),
+            Dhcp4Option::BootFileName(b"boot"),
+            Dhcp4Option::ClientSystemArchitecture(7),
+            Dhcp4Option::Other {
+                code: 51,
+                data: &[0],
+            },
+            Dhcp4Option::Other {
+                code: 200,
+                data: &[1, 2],
+            },
+        ];
+        assert!(options.eq(expected));
+
+        // Truncated option.
+        let mut options = Dhcp4Options::new(&[53, 1, 5, 67, 10, b'b']);
+        assert_eq!(
+            options.next(),
+            Some(Dhcp4Option::MessageType(Dhcp4MessageType::ACK))
+        );
+        assert_eq!(options.next(), None);
+        assert_eq!(options.next(), None);
+    }
+
+    #[test]
+    fn test_option_buf() {
+        let options = [
+            Dhcp4Option::ServerIdentifier([192, 168, 17, 2]),
+            Dhcp4Option::RenewalTime(1800),
+            Dhcp4Option::ParameterRequestList(&[1, 3, 66, 67]),
+            Dhcp4Option::ClientSystemArchitecture(11),
+            Dhcp4Option::Other {
+                code: 250,
+                data: &[],
+            },
+        ];
+        for option in options {
+            let buf = Dhcp4OptionBuf::new(&option).unwrap();
+            assert_eq!(buf.option(), option);
+            assert_eq!(Dhcp4Options::new(buf.as_bytes()).next(), Some(option));
+        }
+        assert_eq!(
+            Dhcp4OptionBuf::new(&Dhcp4Option::RenewalTime(1800))
+                .unwrap()
+                .as_bytes(),
+            [58, 4, 0, 0, 7, 8]
+        );
+
+        let long = [0; 256];
+        assert!(Dhcp4OptionBuf::new(&Dhcp4Option::BootFileName(&long)).is_none());
+        assert!(Dhcp4OptionBuf::new(&Dhcp4Option::BootFileName(&long[..255])).is_some());
+    }
+
+    #[test]
+    fn test_packet() {
+        #[repr(C)]
+        struct Buffer {
+            packet: Dhcp4Packet,
+            options: [u8; 16],
+        }
+
+        let mut header: Dhcp4Header = unsafe { mem::zeroed() };
+        header.op_code = 2;
+        header.xid = [0x12, 0x34, 0x56, 0x78];
+        header.boot_file_name[..7].copy_from_slice(b"default");
+        header.server_name[..4].copy_from_slice(b"tftp");
+        let options = [
+            53, 1, 2, 67, 8, b'e', b'f', b'i', b'/', b'b', b'o', b'o', b't', 255, 0, 0,
+        ];
+        let buffer = Buffer {
+            packet: Dhcp4Packet {
+                size: mem::size_of::<Buffer>() as u32,
+                length: (mem::size_of::<Dhcp4Header>() + 4 + 14) as u32,
+                header,
+                magik: Dhcp4Packet::MAGIK.to_be_bytes(),
+            },
+            options,
+        };
+        let packet = &buffer.packet;
+
+        assert_eq!(packet.magik(), Dhcp4Packet::MAGIK);
+        assert_eq!(packet.header().xid(), 0x1234_5678);
+        assert_eq!(packet.options_bytes().len(), 14);
+        assert_eq!(
+            packet.find_option(Dhcp4Option::MESSAGE_TYPE),
+            Some(Dhcp4Option::MessageType(Dhcp4MessageType::OFFER))
+        );
+        assert_eq!(packet.boot_file_name(), Some(&b"efi/boot"[..]));
+        assert_eq!(packet.tftp_server_name(), Some(&b"tftp"[..]));
+        assert_eq!(packet.find_option(Dhcp4Option::ROUTER), None);
+    }
+}
@@ -0,0 +1,812 @@
+//! DHCPv6 protocol.
+//!
+//! The [`Dhcp6`] protocol runs the DHCPv6 transactions of a network
+//! interface, acquiring the addresses of an identity association (IA).
+//! Instances of this protocol are created with the
+//! [`ServiceBindingProtocol<Dhcp6>`] installed on the network controller
+//! handle.
+//!
+//! The options of a DHCPv6 packet are parsed into [`Dhcp6Option`]s by
+//! [`Dhcp6Options`], and are encoded with [`Dhcp6OptionBuf`].
+//!
+//! [`ServiceBindingProtocol<Dhcp6>`]: crate::proto::service_binding::ServiceBindingProtocol
+
+use crate::proto::unsafe_protocol;
+use crate::table::boot::BootServices;
+use crate::{Event, Result, Status};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::mem::{self, MaybeUninit};
+use core::ptr::{self, NonNull};
+use core::{fmt, slice};
+
+/// The DHCPv6 protocol.
+///
+/// The corresponding C type is `EFI_DHCP6_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol(
+    "87c8bad7-0595-4053-8297-dede395f5d5b",
+    service_binding = "9fb9a8a1-2f4a-43a6-889c-d0f7b6c47ad5"
+)]
+pub struct Dhcp6 {
+    get_mode_data: unsafe extern "efiapi" fn(
+        this: *const Self,
+        mode_data: *mut RawModeData,
+        config_data: *mut c_void,
+    ) -> Status,
+    configure: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        config_data: *const Dhcp6ConfigData<'_>,
+    ) -> Status,
+    start: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+    info_request: usize,
+    renew_rebind: unsafe extern "efiapi" fn(this: *mut Self, rebind: bool) -> Status,
+    decline: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        address_count: u32,
+        addresses: *const [u8; 16],
+    ) -> Status,
+    release: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        address_count: u32,
+        addresses: *const [u8; 16],
+    ) -> Status,
+    stop: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+    parse: usize,
+}
+
+impl Dhcp6 {
+    /// Get the client identifier and the state of the identity association
+    /// of this instance.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: this instance is not configured.
+    /// * [`Status::OUT_OF_RESOURCES`]: the mode data could not be
+    ///   allocated.
+    pub fn mode_data<'boot>(&self, bt: &'boot BootServices) -> Result<Dhcp6ModeData<'boot>> {
+        let mut mode_data = MaybeUninit::<RawModeData>::uninit();
+        unsafe { (self.get_mode_data)(self, mode_data.as_mut_ptr(), ptr::null_mut()) }
+            .into_with_val(|| {
+                let mode_data = unsafe { mode_data.assume_init() };
+                Dhcp6ModeData {
+                    boot_services: bt,
+                    client_id: NonNull::new(mode_data.client_id),
+                    ia: NonNull::new(mode_data.ia),
+                }
+            })
+    }
+
+    /// Configure this instance with `config_data`, or reset it to the
+    /// initial state if `config_data` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `config_data` is invalid.
+    /// * [`Status::ACCESS_DENIED`]: the instance is already configured, or
+    ///   the IA is already used by another instance.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   configure the instance.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn configure(&mut self, config_data: Option<&Dhcp6ConfigData<'_>>) -> Result {
+        let config_data = config_data.map_or(ptr::null(), |data| data as *const _);
+        unsafe { (self.configure)(self, config_data) }.into()
+    }
+
+    /// Start acquiring the addresses of the IA.
+    ///
+    /// If the configuration has no IA info event, this waits until the
+    /// addresses are acquired or the transaction failed. Otherwise, this
+    /// returns immediately and the IA info event is signaled once done.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: this instance is not configured.
+    /// * [`Status::ALREADY_STARTED`]: the transaction is already running.
+    /// * [`Status::NO_MAPPING`]: the link-local address is not ready.
+    /// * [`Status::TIMEOUT`]: no valid reply was received.
+    /// * [`Status::ABORTED`]: the transaction was stopped.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn start(&mut self) -> Result {
+        unsafe { (self.start)(self) }.into()
+    }
+
+    /// Extend the lifetimes of the addresses of the IA, from the server
+    /// that granted them, or from any server if `rebind` is true.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: this instance is not configured.
+    /// * [`Status::ALREADY_STARTED`]: the transaction is already running.
+    /// * [`Status::TIMEOUT`]: no valid reply was received.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn renew_rebind(&mut self, rebind: bool) -> Result {
+        unsafe { (self.renew_rebind)(self, rebind) }.into()
+    }
+
+    /// Inform the server that `addresses` are already used on the link.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `addresses` is empty, or contains
+    ///   an address that is not in the IA.
+    /// * [`Status::BAD_BUFFER_SIZE`]: `addresses` has more than `u32::MAX`
+    ///   items.
+    /// * [`Status::ACCESS_DENIED`]: this instance is not configured.
+    /// * [`Status::ABORTED`]: the transaction was stopped.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn decline(&mut self, addresses: &[[u8; 16]]) -> Result {
+        let count = u32::try_from(addresses.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        unsafe { (self.decline)(self, count, addresses.as_ptr()) }.into()
+    }
+
+    /// Release `addresses`, or all the addresses of the IA if `addresses`
+    /// is empty.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `addresses` contains an address
+    ///   that is not in the IA.
+    /// * [`Status::BAD_BUFFER_SIZE`]: `addresses` has more than `u32::MAX`
+    ///   items.
+    /// * [`Status::ACCESS_DENIED`]: this instance is not configured.
+    /// * [`Status::ABORTED`]: the transaction was stopped.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn release(&mut self, addresses: &[[u8; 16]]) -> Result {
+        let count = u32::try_from(addresses.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let addresses = if addresses.is_empty() {
+            ptr::null()
+        } else {
+            addresses.as_ptr()
+        };
+        unsafe { (self.release)(self, count, addresses) }.into()
+    }
+
+    /// Stop the transaction, and release the addresses of the IA.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    pub fn stop(&mut self) -> Result {
+        unsafe { (self.stop)(self) }.into()
+    }
+}
+
+/// The corresponding C type is `EFI_DHCP6_MODE_DATA`.
+#[repr(C)]
+struct RawModeData {
+    client_id: *mut u8,
+    ia: *mut Dhcp6Ia,
+}
+
+/// The client identifier and the IA of a [`Dhcp6`] instance.
+///
+/// The data is allocated by the driver, and freed when this is dropped.
+pub struct Dhcp6ModeData<'boot> {
+    boot_services: &'boot BootServices,
+    client_id: Option<NonNull<u8>>,
+    ia: Option<NonNull<Dhcp6Ia>>,
+}
+
+impl Dhcp6ModeData<'_> {
+    /// Get the DHCP unique identifier (DUID) of the client.
+    #[must_use]
+    pub fn client_id(&self) -> &[u8] {
+        match self.client_id {
+            Some(client_id) => unsafe {
+                // The DUID is prefixed with its length.
+                let len = client_id.as_ptr().cast::<u16>().read_unaligned();
+                slice::from_raw_parts(client_id.as_ptr().add(2), usize::from(len))
+            },
+            None => &[],
+        }
+    }
+
+    /// Get the IA of the instance.
+    #[must_use]
+    pub fn ia(&self) -> Option<&Dhcp6Ia> {
+        self.ia.map(|ia| unsafe { &*ia.as_ptr() })
+    }
+}
+
+impl fmt::Debug for Dhcp6ModeData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcp6ModeData")
+            .field("client_id", &self.client_id())
+            .field("ia", &self.ia())
+            .finish()
+    }
+}
+
+impl Drop for Dhcp6ModeData<'_> {
+    fn drop(&mut self) {
+        // Ignore the results, we can't do anything about an error here.
+        if let Some(client_id) = self.client_id {
+            let _ = self.boot_services.free_pool(client_id.as_ptr());
+        }
+        if let Some(ia) = self.ia {
+            let _ = self.boot_services.free_pool(ia.as_ptr().cast());
+        }
+    }
+}
+
+newtype_enum! {
+    /// State of the IA of a DHCPv6 instance.
+    pub enum Dhcp6State: u32 => {
+        /// The IA has no address.
+        INIT = 0,
+        /// Waiting for the advertisements of the servers.
+        SELECTING = 1,
+        /// Waiting for the reply of the selected server.
+        REQUESTING = 2,
+        /// Declining some addresses of the IA.
+        DECLINING = 3,
+        /// Confirming the addresses of the IA after moving to a new link.
+        CONFIRMING = 4,
+        /// Releasing some addresses of the IA.
+        RELEASING = 5,
+        /// The addresses of the IA are acquired.
+        BOUND = 6,
+        /// Extending the lifetimes with the server that granted the
+        /// addresses.
+        RENEWING = 7,
+        /// Extending the lifetimes with any server.
+        REBINDING = 8,
+    }
+}
+
+newtype_enum! {
+    /// Type of an identity association.
+    pub enum Dhcp6IaType: u16 => {
+        /// Non-temporary addresses.
+        NA = 3,
+        /// Temporary addresses.
+        TA = 4,
+    }
+}
+
+/// Identifies an IA.
+///
+/// The corresponding C type is `EFI_DHCP6_IA_DESCRIPTOR`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(C)]
+pub struct Dhcp6IaDescriptor {
+    /// The type of the IA.
+    pub ia_type: Dhcp6IaType,
+    /// The identifier of the IA, unique for the client.
+    pub ia_id: u32,
+}
+
+/// An address of an IA.
+///
+/// The corresponding C type is `EFI_DHCP6_IA_ADDRESS`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(C)]
+pub struct Dhcp6IaAddress {
+    /// The IPv6 address.
+    pub address: [u8; 16],
+    /// Time in seconds during which the address is preferred.
+    pub preferred_lifetime: u32,
+    /// Time in seconds during which the address is valid.
+    pub valid_lifetime: u32,
+}
+
+/// An identity association (IA), holding the addresses leased by a
+/// server. It is followed in memory by its addresses.
+///
+/// The corresponding C type is `EFI_DHCP6_IA`.
+#[repr(C)]
+pub struct Dhcp6Ia {
+    descriptor: Dhcp6IaDescriptor,
+    state: Dhcp6State,
+    reply_packet: *const Dhcp6Packet,
+    address_count: u32,
+    addresses: [Dhcp6IaAddress; 0],
+}
+
+impl Dhcp6Ia {
+    /// Get the descriptor of the IA.
+    #[must_use]
+    pub const fn descriptor(&self) -> Dhcp6IaDescriptor {
+        self.descriptor
+    }
+
+    /// Get the state of the IA.
+    #[must_use]
+    pub const fn state(&self) -> Dhcp6State {
+        self.state
+    }
+
+    /// Get the last reply of the server, if any.
+    #[must_use]
+    pub fn reply_packet(&self) -> Option<&Dhcp6Packet> {
+        unsafe { self.reply_packet.as_ref() }
+    }
+
+    /// Get the addresses of the IA.
+    #[must_use]
+    pub fn addresses(&self) -> &[Dhcp6IaAddress] {
+        unsafe { slice::from_raw_parts(self.addresses.as_ptr(), self.address_count as usize) }
+    }
+}
+
+impl fmt::Debug for Dhcp6Ia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcp6Ia")
+            .field("descriptor", &self.descriptor)
+            .field("state", &self.state)
+            .field("reply_packet", &self.reply_packet())
+            .field("addresses", &self.addresses())
+            .finish()
+    }
+}
+
+/// Retransmission parameters of a DHCPv6 message, as defined in RFC 3315.
+///
+/// The corresponding C type is `EFI_DHCP6_RETRANSMISSION`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(C)]
+pub struct Dhcp6Retransmission {
+    /// Initial retransmission timeout, in milliseconds.
+    pub irt: u32,
+    /// Maximum retransmission count, or 0 for no limit.
+    pub mrc: u32,
+    /// Maximum retransmission timeout, in milliseconds, or 0 for no limit.
+    pub mrt: u32,
+    /// Maximum retransmission duration, in milliseconds, or 0 for no limit.
+    pub mrd: u32,
+}
+
+/// Configuration of a [`Dhcp6`] instance.
+///
+/// The corresponding C type is `EFI_DHCP6_CONFIG_DATA`.
+#[repr(C)]
+pub struct Dhcp6ConfigData<'a> {
+    callback: usize,
+    callback_context: *mut c_void,
+    option_count: u32,
+    option_list: *const &'a Dhcp6OptionBuf,
+    /// The IA to acquire.
+    pub ia_descriptor: Dhcp6IaDescriptor,
+    ia_info_event: Option<Event>,
+    /// Accept the reconfigure requests of the server.
+    pub reconfigure_accept: bool,
+    /// Use the two-message exchange with rapid commit.
+    pub rapid_commit: bool,
+    solicit_retransmission: *const Dhcp6Retransmission,
+    _marker: PhantomData<&'a Event>,
+}
+
+impl<'a> Dhcp6ConfigData<'a> {
+    /// Create a configuration acquiring the IA `ia_descriptor`, soliciting
+    /// the servers with the `solicit_retransmission` parameters.
+    #[must_use]
+    pub const fn new(
+        ia_descriptor: Dhcp6IaDescriptor,
+        solicit_retransmission: &'a Dhcp6Retransmission,
+    ) -> Self {
+        Self {
+            callback: 0,
+            callback_context: ptr::null_mut(),
+            option_count: 0,
+            option_list: ptr::null(),
+            ia_descriptor,
+            ia_info_event: None,
+            reconfigure_accept: false,
+            rapid_commit: false,
+            solicit_retransmission,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Add `options` to the packets sent to the servers, such as a
+    /// [`Dhcp6Option::OptionRequest`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: `options` has more than `u32::MAX`
+    ///   items.
+    pub fn with_options(mut self, options: &'a [&'a Dhcp6OptionBuf]) -> Result<Self> {
+        self.option_count = u32::try_from(options.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        self.option_list = options.as_ptr();
+        Ok(self)
+    }
+
+    /// Make [`Dhcp6::start`] return immediately, and signal `event` once the
+    /// state of the IA changes.
+    #[must_use]
+    pub fn with_ia_info_event(mut self, event: &'a Event) -> Self {
+        self.ia_info_event = Some(unsafe { event.unsafe_clone() });
+        self
+    }
+}
+
+impl fmt::Debug for Dhcp6ConfigData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcp6ConfigData")
+            .field("option_count", &self.option_count)
+            .field("ia_descriptor", &self.ia_descriptor)
+            .field("reconfigure_accept", &self.reconfigure_accept)
+            .field("rapid_commit", &self.rapid_commit)
+            .field("solicit_retransmission", unsafe {
+                &*self.solicit_retransmission
+            })
+            .finish()
+    }
+}
+
+/// A DHCPv6 packet. The header is followed in memory by the options.
+///
+/// The corresponding C type is `EFI_DHCP6_PACKET`.
+#[repr(C, packed)]
+pub struct Dhcp6Packet {
+    size: u32,
+    length: u32,
+    message_type: u8,
+    transaction_id: [u8; 3],
+}
+
+impl Dhcp6Packet {
+    /// Get the type of the message, such as 7 for a reply.
+    #[must_use]
+    pub const fn message_type(&self) -> u8 {
+        self.message_type
+    }
+
+    /// Get the transaction ID, chosen by the client.
+    #[must_use]
+    pub const fn transaction_id(&self) -> u32 {
+        let [a, b, c] = self.transaction_id;
+        u32::from_be_bytes([0, a, b, c])
+    }
+
+    /// Get the bytes of the options.
+    #[must_use]
+    pub fn options_bytes(&self) -> &[u8] {
+        // `length` covers the header and the options.
+        let len = (self.length as usize).saturating_sub(4);
+        unsafe { slice::from_raw_parts((self as *const Self).add(1).cast::<u8>(), len) }
+    }
+
+    /// Iterate over the options.
+    #[must_use]
+    pub fn options(&self) -> Dhcp6Options<'_> {
+        Dhcp6Options::new(self.options_bytes())
+    }
+
+    /// Find the first option with the given `code`.
+    #[must_use]
+    pub fn find_option(&self, code: u16) -> Option<Dhcp6Option<'_>> {
+        self.options().find(|option| option.code() == code)
+    }
+}
+
+impl fmt::Debug for Dhcp6Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dhcp6Packet")
+            .field("message_type", &self.message_type)
+            .field("transaction_id", &self.transaction_id())
+            .field("options", &self.options())
+            .finish()
+    }
+}
+
+/// A DHCPv6 option, as defined in RFC 3315, RFC 3646, and RFC 5970.
+///
+/// Options with an unexpected length are returned as [`Other`].
+///
+/// [`Other`]: Self::Other
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Dhcp6Option<'a> {
+    /// The DHCP unique identifier (DUID) of the client.
+    ClientId(&'a [u8]),
+    /// The DUID of the server.
+    ServerId(&'a [u8]),
+    /// The codes of the options requested by the client, as a list of
+    /// 2-byte big-endian codes.
+    OptionRequest(&'a [u8]),
+    /// The preference of the server.
+    Preference(u8),
+    /// Time since the start of the transaction, in hundredths of seconds.
+    ElapsedTime(u16),
+    /// Request a two-message exchange.
+    RapidCommit,
+    /// The vendor class of the client.
+    VendorClass(&'a [u8]),
+    /// The DNS servers, as a list of 16-byte addresses.
+    DnsServers(&'a [u8]),
+    /// The domain search list.
+    DomainList(&'a [u8]),
+    /// The URL of the boot file.
+    BootFileUrl(&'a [u8]),
+    /// The parameters of the boot file.
+    BootFileParameters(&'a [u8]),
+    /// The architecture of the client, such as 7 for x64 UEFI.
+    ClientArchType(u16),
+    /// Any other option.
+    Other {
+        /// The code of the option.
+        code: u16,
+        /// The data of the option.
+        data: &'a [u8],
+    },
+}
+
+impl<'a> Dhcp6Option<'a> {
+    /// Code of [`Self::ClientId`].
+    pub const CLIENT_ID: u16 = 1;
+    /// Code of [`Self::ServerId`].
+    pub const SERVER_ID: u16 = 2;
+    /// Code of [`Self::OptionRequest`].
+    pub const OPTION_REQUEST: u16 = 6;
+    /// Code of [`Self::Preference`].
+    pub const PREFERENCE: u16 = 7;
+    /// Code of [`Self::ElapsedTime`].
+    pub const ELAPSED_TIME: u16 = 8;
+    /// Code of [`Self::RapidCommit`].
+    pub const RAPID_COMMIT: u16 = 14;
+    /// Code of [`Self::VendorClass`].
+    pub const VENDOR_CLASS: u16 = 16;
+    /// Code of [`Self::DnsServers`].
+    pub const DNS_SERVERS: u16 = 23;
+    /// Code of [`Self::DomainList`].
+    pub const DOMAIN_LIST: u16 = 24;
+    /// Code of [`Self::BootFileUrl`].
+    pub const BOOT_FILE_URL: u16 = 59;
+    /// Code of [`Self::BootFileParameters`].
+    pub const BOOT_FILE_PARAMETERS: u16 = 60;
+    /// Code of [`Self::ClientArchType`].
+    pub const CLIENT_ARCH_TYPE: u16 = 61;
+
+    /// Parse the option with the given `code` and `data`.
+    #[must_use]
+    pub fn parse(code: u16, data: &'a [u8]) -> Self {
+        let other = Self::Other { code, data };
+        let word = <[u8; 2]>::try_from(data).map(u16::from_be_bytes);
+        match code {
+            Self::CLIENT_ID => Self::ClientId(data),
+            Self::SERVER_ID => Self::ServerId(data),
+            Self::OPTION_REQUEST => Self::OptionRequest(data),
+            Self::PREFERENCE => match data {
+                [preference] => Self::Preference(*preference),
+                _ => other,
+            },
+            Self::ELAPSED_TIME => word.map_or(other, Self::ElapsedTime),
+            Self::RAPID_COMMIT if data.is_empty() => Self::RapidCommit,
+            Self::VENDOR_CLASS => Self::VendorClass(data),
+            Self::DNS_SERVERS => Self::DnsServers(data),
+            Self::DOMAIN_LIST => Self::DomainList(data),
+            Self::BOOT_FILE_URL => Self::BootFileUrl(data),
+            Self::BOOT_FILE_PARAMETERS => Self::BootFileParameters(data),
+            Self::CLIENT_ARCH_TYPE => word.map_or(other, Self::ClientArchType),
+            _ => other,
+        }
+    }
+
+    /// Get the code of the option.
+    #[must_use]
+    pub const fn code(&self) -> u16 {
+        match self {
+            Self::ClientId(_) => Self::CLIENT_ID,
+            Self::ServerId(_) => Self::SERVER_ID,
+            Self::OptionRequest(_) => Self::OPTION_REQUEST,
+            Self::Preference(_) => Self::PREFERENCE,
+            Self::ElapsedTime(_) => Self::ELAPSED_TIME,
+            Self::RapidCommit => Self::RAPID_COMMIT,
+            Self::VendorClass(_) => Self::VENDOR_CLASS,
+            Self::DnsServers(_) => Self::DNS_SERVERS,
+            Self::DomainList(_) => Self::DOMAIN_LIST,
+            Self::BootFileUrl(_) => Self::BOOT_FILE_URL,
+            Self::BootFileParameters(_) => Self::BOOT_FILE_PARAMETERS,
+            Self::ClientArchType(_) => Self::CLIENT_ARCH_TYPE,
+            Self::Other { code, .. } => *code,
+        }
+    }
+
+    /// Write the data of the option to `buf`, returning its length, or
+    /// `None` if `buf` is too small.
+    fn write_data(&self, buf: &mut [u8]) -> Option<usize> {
+        let mut write = |data: &[u8]| {
+            buf.get_mut(..data.len())?.copy_from_slice(data);
+            Some(data.len())
+        };
+        match *self {
+            Self::Preference(preference) => write(&[preference]),
+            Self::ElapsedTime(word) | Self::ClientArchType(word) => write(&word.to_be_bytes()),
+            Self::RapidCommit => Some(0),
+            Self::ClientId(data)
+            | Self::ServerId(data)
+            | Self::OptionRequest(data)
+            | Self::VendorClass(data)
+            | Self::DnsServers(data)
+            | Self::DomainList(data)
+            | Self::BootFileUrl(data)
+            | Self::BootFileParameters(data)
+            | Self::Other { data, .. } => write(data),
+        }
+    }
+}
+
+/// Iterator over the [`Dhcp6Option`]s encoded in a byte slice.
+///
+/// The iteration stops at the first truncated option.
+#[derive(Clone)]
+pub struct Dhcp6Options<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Dhcp6Options<'a> {
+    /// Create an iterator over the options encoded in `data`, which starts
+    /// after the header of the packet.
+    #[must_use]
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+}
+
+impl<'a> Iterator for Dhcp6Options<'a> {
+    type Item = Dhcp6Option<'a>;
+
+    fn next(&mut self) -> Option<Dhcp6Option<'a>> {
+        if let [c0, c1, l0, l1, ref rest @ ..] = *self.data {
+            let len = usize::from(u16::from_be_bytes([l0, l1]));
+            if rest.len() >= len {
+                let (data, rest) = rest.split_at(len);
+                self.data = rest;
+                return Some(Dhcp6Option::parse(u16::from_be_bytes([c0, c1]), data));
+            }
+        }
+        self.data = &[];
+        None
+    }
+}
+
+impl fmt::Debug for Dhcp6Options<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// An encoded DHCPv6 option, to pass to [`Dhcp6ConfigData::with_options`].
+///
+/// The corresponding C type is `EFI_DHCP6_PACKET_OPTION`. The code and
+/// length are in network byte order.
+#[derive(Clone)]
+#[repr(C)]
+pub struct Dhcp6OptionBuf {
+    code: [u8; 2],
+    length: [u8; 2],
+    data: [u8; 255],
+}
+
+impl Dhcp6OptionBuf {
+    /// Encode `option`, or return `None` if its data is longer than 255
+    /// bytes.
+    #[must_use]
+    pub fn new(option: &Dhcp6Option<'_>) -> Option<Self> {
+        let mut buf = Self {
+            code: option.code().to_be_bytes(),
+            length: [0; 2],
+            data: [0; 255],
+        };
+        let len = option.write_data(&mut buf.data)?;
+        buf.length = u16::try_from(len).ok()?.to_be_bytes();
+        Some(buf)
+    }
+
+    fn data_len(&self) -> usize {
+        usize::from(u16::from_be_bytes(self.length))
+    }
+
+    /// Get the encoded option.
+    #[must_use]
+    pub fn option(&self) -> Dhcp6Option<'_> {
+        Dhcp6Option::parse(u16::from_be_bytes(self.code), &self.data[..self.data_len()])
+    }
+
+    /// Get the encoded bytes of the option, starting with its code and
+    /// length.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        let bytes = unsafe {
+            slice::from_raw_parts((self as *const Self).cast::<u8>(), mem::size_of::<Self>())
+        };
+        &bytes[..4 + self.data_len()]
+    }
+}
+
+impl fmt::Debug for Dhcp6OptionBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.option().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options() {
+        let data = [
+            0, 1, 0, 4, 0, 3, 0, 1, // client ID
+            0, 7, 0, 1, 255, // preference
+            0, 14, 0, 0, // rapid commit
+            0, 59, 0, 3, b't', b'f', b'p', // boot file URL
+            0, 61, 0, 2, 0, 16, // client architecture
+            0, 8, 0, 1, 0, // elapsed time with a wrong length
+            0, 59, 0, 10, b'x', // truncated
+        ];
+        let options = Dhcp6Options::new(&data);
+        let expected = [
+            Dhcp6Option::ClientId(&[0, 3, 0, 1]),
+            Dhcp6Option::Preference(255),
+            Dhcp6Option::RapidCommit,
+            Dhcp6Option::BootFileUrl(b"tfp"),
+            Dhcp6Option::ClientArchType(16),
+            Dhcp6Option::Other {
+                code: 8,
+                data: &[0],
+            },
+        ];
+        assert!(options.eq(expected));
+    }
+
+    #[test]
+    fn test_option_buf() {
+        let options = [
+            Dhcp6Option::OptionRequest(&[0, 59, 0, 60]),
+            Dhcp6Option::ElapsedTime(100),
+            Dhcp6Option::RapidCommit,
+            Dhcp6Option::Other {
+                code: 0x1234,
+                data: &[5],
+            },
+        ];
+        for option in options {
+            let buf = Dhcp6OptionBuf::new(&option).unwrap();
+            assert_eq!(buf.option(), option);
+            assert_eq!(Dhcp6Options::new(buf.as_bytes()).next(), Some(option));
+        }
+        assert_eq!(
+            Dhcp6OptionBuf::new(&Dhcp6Option::ClientArchType(7))
+                .unwrap()
+                .as_bytes(),
+            [0, 61, 0, 2, 0, 7]
+        );
+        assert!(Dhcp6OptionBuf::new(&Dhcp6Option::BootFileUrl(&[0; 256])).is_none());
+    }
+
+    #[test]
+    fn test_ia() {
+        /// An IA followed by its addresses, with the layout of the C type.
+        #[repr(C)]
+        struct Buffer {
+            descriptor: Dhcp6IaDescriptor,
+            state: Dhcp6State,
+            reply_packet: *const Dhcp6Packet,
+            address_count: u32,
+            addresses: [Dhcp6IaAddress; 2],
+        }
+
+        let address = |last| Dhcp6IaAddress {
+            address: [0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, last],
+            preferred_lifetime: 100,
+            valid_lifetime: 200,
+        };
+        let buffer = Buffer {
+            descriptor: Dhcp6IaDescriptor {
+                ia_type: Dhcp6IaType::NA,
+                ia_id: 1,
+            },
+            state: Dhcp6State::BOUND,
+            reply_packet: ptr::null(),
+            address_count: 2,
+            addresses: [address(1), address(2)],
+        };
+        let ia = unsafe { &*(&buffer as *const Buffer).cast::<Dhcp6Ia>() };
+        assert_eq!(ia.state(), Dhcp6State::BOUND);
+        assert_eq!(ia.descriptor().ia_type, Dhcp6IaType::NA);
+        assert!(ia.reply_packet().is_none());
+        assert_eq!(ia.addresses(), [address(1), address(2)]);
+    }
+}
@@ -0,0 +1,599 @@
+//! HTTP message types.
+//!
+//! These types describe HTTP requests and responses, and the configuration
+//! of an HTTP instance. They are shared by the protocols built on HTTP, such
+//! as [`RestEx`].
+//!
+//! Requests are built by the caller, with borrowed headers and body. The
+//! responses are allocated by the driver, and freed when the
+//! [`HttpResponse`] holding them is dropped.
+//!
+//! [`RestEx`]: super::rest_ex::RestEx
+
+use crate::table::boot::BootServices;
+use crate::{CStr16, CStr8, Char16, Char8};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::ptr::{self, NonNull};
+use core::{fmt, slice};
+
+newtype_enum! {
+    /// The method of an HTTP request.
+    pub enum HttpMethod: u32 => {
+        /// `GET`
+        GET = 0,
+        /// `POST`
+        POST = 1,
+        /// `PATCH`
+        PATCH = 2,
+        /// `OPTIONS`
+        OPTIONS = 3,
+        /// `CONNECT`
+        CONNECT = 4,
+        /// `HEAD`
+        HEAD = 5,
+        /// `PUT`
+        PUT = 6,
+        /// `DELETE`
+        DELETE = 7,
+        /// `TRACE`
+        TRACE = 8,
+    }
+}
+
+newtype_enum! {
+    /// The version of the HTTP protocol.
+    pub enum HttpVersion: u32 => {
+        /// HTTP/1.0
+        HTTP_1_0 = 0,
+        /// HTTP/1.1
+        HTTP_1_1 = 1,
+    }
+}
+
+newtype_enum! {
+    /// The status of an HTTP response.
+    ///
+    /// The values are an index in the list of status codes known by UEFI,
+    /// use [`code`](Self::code) to get the numeric code.
+    pub enum HttpStatusCode: u32 => {
+        /// A status code not known by UEFI.
+        UNSUPPORTED_STATUS = 0,
+        /// 100 Continue
+        STATUS_100_CONTINUE = 1,
+        /// 101 Switching Protocols
+        STATUS_101_SWITCHING_PROTOCOLS = 2,
+        /// 200 OK
+        STATUS_200_OK = 3,
+        /// 201 Created
+        STATUS_201_CREATED = 4,
+        /// 202 Accepted
+        STATUS_202_ACCEPTED = 5,
+        /// 203 Non-Authoritative Information
+        STATUS_203_NON_AUTHORITATIVE_INFORMATION = 6,
+        /// 204 No Content
+        STATUS_204_NO_CONTENT = 7,
+        /// 205 Reset Content
+        STATUS_205_RESET_CONTENT = 8,
+        /// 206 Partial Content
+        STATUS_206_PARTIAL_CONTENT = 9,
+        /// 300 Multiple Choices
+        STATUS_300_MULTIPLE_CHOICES = 10,
+        /// 301 Moved Permanently
+        STATUS_301_MOVED_PERMANENTLY = 11,
+        /// 302 Found
+        STATUS_302_FOUND = 12,
+        /// 303 See Other
+        STATUS_303_SEE_OTHER = 13,
+        /// 304 Not Modified
+        STATUS_304_NOT_MODIFIED = 14,
+        /// 305 Use Proxy
+        STATUS_305_USE_PROXY = 15,
+        /// 307 Temporary Redirect
+        STATUS_307_TEMPORARY_REDIRECT = 16,
+        /// 400 Bad Request
+        STATUS_400_BAD_REQUEST = 17,
+        /// 401 Unauthorized
+        STATUS_401_UNAUTHORIZED = 18,
+        /// 402 Payment Required
+        STATUS_402_PAYMENT_REQUIRED = 19,
+        /// 403 Forbidden
+        STATUS_403_FORBIDDEN = 20,
+        /// 404 Not Found
+        STATUS_404_NOT_FOUND = 21,
+        /// 405 Method Not Allowed
+        STATUS_405_METHOD_NOT_ALLOWED = 22,
+        /// 406 Not Acceptable
+        STATUS_406_NOT_ACCEPTABLE = 23,
+        /// 407 Proxy Authentication Required
+        STATUS_407_PROXY_AUTHENTICATION_REQUIRED = 24,
+        /// 408 Request Timeout
+        STATUS_408_REQUEST_TIME_OUT = 25,
+        /// 409 Conflict
+        STATUS_409_CONFLICT = 26,
+        /// 410 Gone
+        STATUS_410_GONE = 27,
+        /// 411 Length Required
+        STATUS_411_LENGTH_REQUIRED = 28,
+        /// 412 Precondition Failed
+        STATUS_412_PRECONDITION_FAILED = 29,
+        /// 413 Payload Too Large
+        STATUS_413_REQUEST_ENTITY_TOO_LARGE = 30,
+        /// 414 URI Too Long
+        STATUS_414_REQUEST_URI_TOO_LARGE = 31,
+        /// 415 Unsupported Media Type
+        STATUS_415_UNSUPPORTED_MEDIA_TYPE = 32,
+        /// 416 Range Not Satisfiable
+        STATUS_416_REQUESTED_RANGE_NOT_SATISFIED = 33,
+        /// 417 Expectation Failed
+        STATUS_417_EXPECTATION_FAILED = 34,
+        /// 500 Internal Server Error
+        STATUS_500_INTERNAL_SERVER_ERROR = 35,
+        /// 501 Not Implemented
+        STATUS_501_NOT_IMPLEMENTED = 36,
+        /// 502 Bad Gateway
+        STATUS_502_BAD_GATEWAY = 37,
+        /// 503 Service Unavailable
+        STATUS_503_SERVICE_UNAVAILABLE = 38,
+        /// 504 Gateway Timeout
+        STATUS_504_GATEWAY_TIME_OUT = 39,
+        /// 505 HTTP Version Not Supported
+        STATUS_505_HTTP_VERSION_NOT_SUPPORTED = 40,
+        /// 308 Permanent Redirect
+        STATUS_308_PERMANENT_REDIRECT = 41,
+    }
+}
+
+impl HttpStatusCode {
+    /// Numeric codes of the known statuses, in order.
+    const CODES: [u16; 41] = [
+        100, 101, 200, 201, 202, 203, 204, 205, 206, 300, 301, 302, 303, 304, 305, 307, 400, 401,
+        402, 403, 404, 405, 406, 407, 408, 409, 410, 411, 412, 413, 414, 415, 416, 417, 500, 501,
+        502, 503, 504, 505, 308,
+    ];
+
+    /// Get the numeric code of the status, such as 200 for
+    /// [`STATUS_200_OK`](Self::STATUS_200_OK). Returns `None` for
+    /// [`UNSUPPORTED_STATUS`](Self::UNSUPPORTED_STATUS) and unknown values.
+    #[must_use]
+    pub fn code(self) -> Option<u16> {
+        let index = usize::try_from(self.0).ok()?.checked_sub(1)?;
+        Self::CODES.get(index).copied()
+    }
+
+    /// Whether the status is a success, in the 2xx range.
+    #[must_use]
+    pub fn is_success(self) -> bool {
+        matches!(self.code(), Some(200..=299))
+    }
+}
+
+/// A header of an HTTP message, such as `Content-Type: application/json`.
+///
+/// The corresponding C type is `EFI_HTTP_HEADER`.
+#[repr(C)]
+pub struct HttpHeader<'a> {
+    field_name: *const Char8,
+    field_value: *const Char8,
+    _marker: PhantomData<&'a CStr8>,
+}
+
+impl<'a> HttpHeader<'a> {
+    /// Create a header.
+    #[must_use]
+    pub const fn new(name: &'a CStr8, value: &'a CStr8) -> Self {
+        Self {
+            field_name: name.as_ptr(),
+            field_value: value.as_ptr(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the name of the header.
+    #[must_use]
+    pub fn name(&self) -> &'a CStr8 {
+        unsafe { CStr8::from_ptr(self.field_name) }
+    }
+
+    /// Get the value of the header.
+    #[must_use]
+    pub fn value(&self) -> &'a CStr8 {
+        unsafe { CStr8::from_ptr(self.field_value) }
+    }
+}
+
+impl fmt::Debug for HttpHeader<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpHeader")
+            .field("name", &self.name())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+/// The method and URL of an HTTP request.
+///
+/// The corresponding C type is `EFI_HTTP_REQUEST_DATA`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct HttpRequestData<'a> {
+    method: HttpMethod,
+    url: *const Char16,
+    _marker: PhantomData<&'a CStr16>,
+}
+
+impl<'a> HttpRequestData<'a> {
+    /// Create a request for `url` with `method`.
+    #[must_use]
+    pub const fn new(method: HttpMethod, url: &'a CStr16) -> Self {
+        Self {
+            method,
+            url: url.as_ptr(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the method of the request.
+    #[must_use]
+    pub const fn method(&self) -> HttpMethod {
+        self.method
+    }
+
+    /// Get the URL of the request.
+    #[must_use]
+    pub fn url(&self) -> &'a CStr16 {
+        unsafe { CStr16::from_ptr(self.url) }
+    }
+}
+
+/// The status of an HTTP response.
+///
+/// The corresponding C type is `EFI_HTTP_RESPONSE_DATA`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct HttpResponseData {
+    /// The status of the response.
+    pub status_code: HttpStatusCode,
+}
+
+/// An HTTP request.
+///
+/// The corresponding C type is `EFI_HTTP_MESSAGE`, with the request data.
+#[derive(Debug)]
+#[repr(C)]
+pub struct HttpRequestMessage<'a> {
+    data: *const HttpRequestData<'a>,
+    header_count: usize,
+    headers: *const HttpHeader<'a>,
+    body_length: usize,
+    body: *const c_void,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> HttpRequestMessage<'a> {
+    /// Create a request without headers nor body.
+    #[must_use]
+    pub const fn new(data: &'a HttpRequestData<'a>) -> Self {
+        Self {
+            data,
+            header_count: 0,
+            headers: ptr::null(),
+            body_length: 0,
+            body: ptr::null(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a continuation of a request, sending more of its body without
+    /// repeating the method, URL and headers.
+    #[must_use]
+    pub const fn continuation(body: &'a [u8]) -> Self {
+        Self {
+            data: ptr::null(),
+            header_count: 0,
+            headers: ptr::null(),
+            body_length: body.len(),
+            body: body.as_ptr().cast(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Send `headers` with the request.
+    #[must_use]
+    pub const fn with_headers(mut self, headers: &'a [HttpHeader<'a>]) -> Self {
+        self.header_count = headers.len();
+        self.headers = headers.as_ptr();
+        self
+    }
+
+    /// Send `body` with the request.
+    #[must_use]
+    pub const fn with_body(mut self, body: &'a [u8]) -> Self {
+        self.body_length = body.len();
+        self.body = body.as_ptr().cast();
+        self
+    }
+
+    /// Get the method and URL of the request, or `None` for a
+    /// [`continuation`](Self::continuation).
+    #[must_use]
+    pub fn data(&self) -> Option<&'a HttpRequestData<'a>> {
+        unsafe { self.data.as_ref() }
+    }
+
+    /// Get the headers of the request.
+    #[must_use]
+    pub fn headers(&self) -> &'a [HttpHeader<'a>] {
+        unsafe { items(self.headers, self.header_count) }
+    }
+
+    /// Get the body of the request.
+    #[must_use]
+    pub fn body(&self) -> &'a [u8] {
+        unsafe { items(self.body.cast(), self.body_length) }
+    }
+}
+
+/// An HTTP response, as filled by the driver.
+///
+/// The corresponding C type is `EFI_HTTP_MESSAGE`, with the response data.
+#[derive(Debug)]
+#[repr(C)]
+pub(crate) struct RawHttpResponse {
+    pub(crate) data: *mut HttpResponseData,
+    pub(crate) header_count: usize,
+    pub(crate) headers: *mut HttpHeader<'static>,
+    pub(crate) body_length: usize,
+    pub(crate) body: *mut c_void,
+}
+
+impl RawHttpResponse {
+    /// Create an empty response, for the driver to fill.
+    pub(crate) const fn new() -> Self {
+        Self {
+            data: ptr::null_mut(),
+            header_count: 0,
+            headers: ptr::null_mut(),
+            body_length: 0,
+            body: ptr::null_mut(),
+        }
+    }
+}
+
+/// An HTTP response received from the driver.
+///
+/// The status, headers and body are allocated by the driver, and freed when
+/// this is dropped.
+pub struct HttpResponse<'boot> {
+    boot_services: &'boot BootServices,
+    raw: RawHttpResponse,
+}
+
+impl<'boot> HttpResponse<'boot> {
+    /// Take ownership of the buffers of `raw`.
+    ///
+    /// # Safety
+    ///
+    /// The buffers of `raw` must be allocated from pool, and not be used
+    /// elsewhere.
+    pub(crate) unsafe fn new(boot_services: &'boot BootServices, raw: RawHttpResponse) -> Self {
+        Self { boot_services, raw }
+    }
+
+    /// Get the status of the response, or `None` if the driver didn't
+    /// provide it, such as for a continuation of a response.
+    #[must_use]
+    pub fn status_code(&self) -> Option<HttpStatusCode> {
+        unsafe { self.raw.data.as_ref() }.map(|data| data.status_code)
+    }
+
+    /// Get the headers of the response.
+    #[must_use]
+    pub fn headers(&self) -> &[HttpHeader<'_>] {
+        unsafe { items(self.raw.headers, self.raw.header_count) }
+    }
+
+    /// Get the value of the first header named `name`, ignoring case as
+    /// header names are case-insensitive.
+    #[must_use]
+    pub fn header(&self, name: &str) -> Option<&CStr8> {
+        self.headers()
+            .iter()
+            .find(|header| {
+                header
+                    .name()
+                    .to_bytes()
+                    .eq_ignore_ascii_case(name.as_bytes())
+            })
+            .map(HttpHeader::value)
+    }
+
+    /// Get the body of the response.
+    #[must_use]
+    pub fn body(&self) -> &[u8] {
+        unsafe { items(self.raw.body.cast(), self.raw.body_length) }
+    }
+}
+
+impl fmt::Debug for HttpResponse<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status_code", &self.status_code())
+            .field("headers", &self.headers())
+            .field("body_length", &self.raw.body_length)
+            .finish()
+    }
+}
+
+impl Drop for HttpResponse<'_> {
+    fn drop(&mut self) {
+        // Ignore the results, we can't do anything about an error here.
+        let bt = self.boot_services;
+        for header in self.headers() {
+            let _ = bt.free_pool(header.field_name as *mut u8);
+            let _ = bt.free_pool(header.field_value as *mut u8);
+        }
+        let buffers = [
+            self.raw.data.cast::<u8>(),
+            self.raw.headers.cast(),
+            self.raw.body.cast(),
+        ];
+        for buffer in buffers.into_iter().filter_map(NonNull::new) {
+            let _ = bt.free_pool(buffer.as_ptr());
+        }
+    }
+}
+
+/// Local IPv4 address and port of an HTTP instance.
+///
+/// The corresponding C type is `EFI_HTTPv4_ACCESS_POINT`.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct Httpv4AccessPoint {
+    /// Use the default address of the network interface, instead of
+    /// `local_address` and `local_subnet`.
+    pub use_default_address: bool,
+    /// The local address.
+    pub local_address: [u8; 4],
+    /// The subnet mask of the local address.
+    pub local_subnet: [u8; 4],
+    /// The local port, or 0 to pick an ephemeral port.
+    pub local_port: u16,
+}
+
+/// Local IPv6 address and port of an HTTP instance.
+///
+/// The corresponding C type is `EFI_HTTPv6_ACCESS_POINT`.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct Httpv6AccessPoint {
+    /// The local address.
+    pub local_address: [u8; 16],
+    /// The local port, or 0 to pick an ephemeral port.
+    pub local_port: u16,
+}
+
+/// Configuration of an HTTP instance.
+///
+/// The corresponding C type is `EFI_HTTP_CONFIG_DATA`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct HttpConfigData<'a> {
+    /// The version of the HTTP protocol.
+    pub http_version: HttpVersion,
+    /// The timeout of the requests in milliseconds, or 0 for no timeout.
+    pub timeout_millisec: u32,
+    local_address_is_ipv6: bool,
+    access_point: *const c_void,
+    _marker: PhantomData<&'a Httpv6AccessPoint>,
+}
+
+impl<'a> HttpConfigData<'a> {
+    /// Create an IPv4 configuration.
+    #[must_use]
+    pub const fn new_ipv4(
+        http_version: HttpVersion,
+        timeout_millisec: u32,
+        access_point: &'a Httpv4AccessPoint,
+    ) -> Self {
+        Self {
+            http_version,
+            timeout_millisec,
+            local_address_is_ipv6: false,
+            access_point: (access_point as *const Httpv4AccessPoint).cast(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create an IPv6 configuration.
+    #[must_use]
+    pub const fn new_ipv6(
+        http_version: HttpVersion,
+        timeout_millisec: u32,
+        access_point: &'a Httpv6AccessPoint,
+    ) -> Self {
+        Self {
+            http_version,
+            timeout_millisec,
+            local_address_is_ipv6: true,
+            access_point: (access_point as *const Httpv6AccessPoint).cast(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the IPv4 access point, or `None` for an IPv6 configuration.
+    #[must_use]
+    pub fn ipv4_access_point(&self) -> Option<&'a Httpv4AccessPoint> {
+        if self.local_address_is_ipv6 {
+            None
+        } else {
+            unsafe { self.access_point.cast::<Httpv4AccessPoint>().as_ref() }
+        }
+    }
+
+    /// Get the IPv6 access point, or `None` for an IPv4 configuration.
+    #[must_use]
+    pub fn ipv6_access_point(&self) -> Option<&'a Httpv6AccessPoint> {
+        if self.local_address_is_ipv6 {
+            unsafe { self.access_point.cast::<Httpv6AccessPoint>().as_ref() }
+        } else {
+            None
+        }
+    }
+}
+
+/// Get the `len` items at `data`, or an empty slice if `data` is null.
+unsafe fn items<'a, T>(data: *const T, len: usize) -> &'a [T] {
+    if data.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cstr16, cstr8};
+
+    #[test]
+    fn test_status_code() {
+        assert_eq!(HttpStatusCode::STATUS_200_OK.code(), Some(200));
+        assert_eq!(
+            HttpStatusCode::STATUS_308_PERMANENT_REDIRECT.code(),
+            Some(308)
+        );
+        assert_eq!(
+            HttpStatusCode::STATUS_505_HTTP_VERSION_NOT_SUPPORTED.code(),
+            Some(505)
+        );
+        assert_eq!(HttpStatusCode::UNSUPPORTED_STATUS.code(), None);
+        assert_eq!(HttpStatusCode(42).code(), None);
+        assert!(HttpStatusCode::STATUS_204_NO_CONTENT.is_success());
+        assert!(!HttpStatusCode::STATUS_404_NOT_FOUND.is_success());
+    }
+
+    #[test]
+    fn test_request_message() {
+        let data = HttpRequestData::new(HttpMethod::POST, cstr16!("/redfish/v1/Systems"));
+        let headers = [HttpHeader::new(
+            cstr8!("Content-Type"),
+            cstr8!("application/json"),
+        )];
+        let message = HttpRequestMessage::new(&data)
+            .with_headers(&headers)
+            .with_body(b"{}");
+        assert_eq!(message.data().unwrap().method(), HttpMethod::POST);
+        assert_eq!(
+            message.data().unwrap().url(),
+            cstr16!("/redfish/v1/Systems")
+        );
+        assert_eq!(message.headers()[0].value(), cstr8!("application/json"));
+        assert_eq!(message.body(), b"{}");
+
+        let continuation = HttpRequestMessage::continuation(b"more");
+        assert!(continuation.data().is_none());
+        assert!(continuation.headers().is_empty());
+        assert_eq!(continuation.body(), b"more");
+    }
+}
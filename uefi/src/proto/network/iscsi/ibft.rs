@@ -0,0 +1,552 @@
+use crate::table::{Boot, SystemTable};
+use core::str;
+
+/// Signature of the iBFT.
+const IBFT_SIGNATURE: [u8; 4] = *b"iBFT";
+
+/// Size of the iBFT header, which is followed by the control structure.
+const HEADER_SIZE: usize = 48;
+
+/// IDs of the structures of the iBFT.
+const CONTROL_ID: u8 = 1;
+const INITIATOR_ID: u8 = 2;
+const NIC_ID: u8 = 3;
+const TARGET_ID: u8 = 4;
+
+/// Minimum lengths of the structures of the iBFT.
+const CONTROL_LEN: usize = 18;
+const INITIATOR_LEN: usize = 74;
+const NIC_LEN: usize = 102;
+const TARGET_LEN: usize = 54;
+
+/// Bits of the flags of the structures.
+const FLAG_VALID: u8 = 0x01;
+const FLAG_BOOT_SELECTED: u8 = 0x02;
+
+/// The iSCSI Boot Firmware Table (iBFT), which describes the iSCSI
+/// initiator, the network interfaces, and the targets used by the firmware
+/// to boot from iSCSI. The OS reads it to connect to the same targets.
+///
+/// The table is an ACPI table, which can be located with [`find`], or
+/// parsed from its bytes with [`new`].
+///
+/// [`find`]: Self::find
+/// [`new`]: Self::new
+#[derive(Clone, Copy, Debug)]
+pub struct Ibft<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Ibft<'a> {
+    /// Parse the iBFT from its bytes, including the ACPI header.
+    ///
+    /// Returns `None` if the signature or the length of the table is
+    /// invalid. The checksum is not verified, since some firmware
+    /// implementations don't update it.
+    #[must_use]
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.get(..4)? != IBFT_SIGNATURE {
+            return None;
+        }
+        let len = usize::try_from(read_u32(bytes, 4)?).ok()?;
+        let bytes = bytes.get(..len)?;
+        let ibft = Self { bytes };
+        ibft.structure(HEADER_SIZE as u16, CONTROL_ID, CONTROL_LEN)?;
+        Some(ibft)
+    }
+
+    /// Find the iBFT in the ACPI tables of the system.
+    ///
+    /// Returns `None` if the firmware didn't boot from iSCSI, or doesn't
+    /// provide the table.
+    #[must_use]
+    pub fn find(st: &'a SystemTable<Boot>) -> Option<Self> {
+        Self::new(st.find_acpi_table(&IBFT_SIGNATURE)?.as_bytes())
+    }
+
+    /// Get the revision of the table format. Only revision 1 is currently
+    /// defined.
+    #[must_use]
+    pub fn revision(&self) -> u8 {
+        self.bytes[8]
+    }
+
+    /// Get the raw bytes of the table.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get the initiator structure, or `None` if it is missing or not
+    /// valid.
+    #[must_use]
+    pub fn initiator(&self) -> Option<IbftInitiator<'a>> {
+        let offset = read_u16(self.control(), 8)?;
+        let bytes = self.valid_structure(offset, INITIATOR_ID, INITIATOR_LEN)?;
+        Some(IbftInitiator { ibft: *self, bytes })
+    }
+
+    /// Get the network interface structure at `index`, or `None` if it is
+    /// missing or not valid.
+    ///
+    /// The table has room for at least two network interfaces.
+    #[must_use]
+    pub fn nic(&self, index: usize) -> Option<IbftNic<'a>> {
+        let offset = read_u16(self.control(), 10 + index * 4)?;
+        let bytes = self.valid_structure(offset, NIC_ID, NIC_LEN)?;
+        Some(IbftNic { ibft: *self, bytes })
+    }
+
+    /// Get the target structure at `index`, or `None` if it is missing or
+    /// not valid.
+    ///
+    /// The table has room for at least two targets.
+    #[must_use]
+    pub fn target(&self, index: usize) -> Option<IbftTarget<'a>> {
+        let offset = read_u16(self.control(), 12 + index * 4)?;
+        let bytes = self.valid_structure(offset, TARGET_ID, TARGET_LEN)?;
+        Some(IbftTarget { ibft: *self, bytes })
+    }
+
+    /// Get an iterator over the valid network interface structures.
+    pub fn nics(&self) -> impl Iterator<Item = IbftNic<'a>> + 'a {
+        let ibft = *self;
+        (0..ibft.num_slots()).filter_map(move |index| ibft.nic(index))
+    }
+
+    /// Get an iterator over the valid target structures.
+    pub fn targets(&self) -> impl Iterator<Item = IbftTarget<'a>> + 'a {
+        let ibft = *self;
+        (0..ibft.num_slots()).filter_map(move |index| ibft.target(index))
+    }
+
+    /// Get the control structure, which was checked by `new`.
+    fn control(&self) -> &'a [u8] {
+        self.structure(HEADER_SIZE as u16, CONTROL_ID, CONTROL_LEN)
+            .unwrap()
+    }
+
+    /// Number of network interface and target slots in the control
+    /// structure.
+    fn num_slots(&self) -> usize {
+        (self.control().len() - 10) / 4
+    }
+
+    /// Get the structure at `offset`, if it has the right ID and length.
+    fn structure(&self, offset: u16, id: u8, min_len: usize) -> Option<&'a [u8]> {
+        let offset = usize::from(offset);
+        let header = self.bytes.get(offset..offset + 6)?;
+        let len = usize::from(read_u16(header, 2)?);
+        if offset == 0 || header[0] != id || len < min_len {
+            return None;
+        }
+        self.bytes.get(offset..offset + len)
+    }
+
+    /// Get the structure at `offset`, if it is valid.
+    fn valid_structure(&self, offset: u16, id: u8, min_len: usize) -> Option<&'a [u8]> {
+        self.structure(offset, id, min_len)
+            .filter(|bytes| bytes[5] & FLAG_VALID != 0)
+    }
+
+    /// Get the string of `len` bytes at `offset` in the table.
+    fn string(&self, len: u16, offset: u16) -> Option<&'a str> {
+        if len == 0 || offset == 0 {
+            return None;
+        }
+        let offset = usize::from(offset);
+        let bytes = self.bytes.get(offset..offset + usize::from(len))?;
+        str::from_utf8(bytes).ok()
+    }
+
+    /// Get the string whose length and offset are at `field` in `bytes`.
+    fn string_field(&self, bytes: &[u8], field: usize) -> Option<&'a str> {
+        self.string(read_u16(bytes, field)?, read_u16(bytes, field + 2)?)
+    }
+}
+
+/// An IP address in the [`Ibft`].
+///
+/// IPv4 addresses are stored as IPv4-mapped IPv6 addresses, such as
+/// `::ffff:192.168.0.1`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct IbftAddress(pub [u8; 16]);
+
+impl IbftAddress {
+    /// Get the IPv4 address, or `None` if this is an IPv6 address.
+    #[must_use]
+    pub fn to_ipv4(&self) -> Option<[u8; 4]> {
+        let (prefix, ipv4) = self.0.split_at(12);
+        if prefix == [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff] {
+            Some([ipv4[0], ipv4[1], ipv4[2], ipv4[3]])
+        } else {
+            None
+        }
+    }
+
+    /// Check whether the address is all zeros, which means that it is not
+    /// set.
+    #[must_use]
+    pub fn is_unspecified(&self) -> bool {
+        self.0 == [0; 16]
+    }
+
+    fn read(bytes: &[u8], offset: usize) -> Self {
+        let mut address = [0; 16];
+        address.copy_from_slice(&bytes[offset..offset + 16]);
+        Self(address)
+    }
+}
+
+/// The initiator structure of the [`Ibft`].
+#[derive(Clone, Copy, Debug)]
+pub struct IbftInitiator<'a> {
+    ibft: Ibft<'a>,
+    bytes: &'a [u8],
+}
+
+impl<'a> IbftInitiator<'a> {
+    /// Check whether the firmware booted with this initiator.
+    #[must_use]
+    pub fn is_boot_selected(&self) -> bool {
+        self.bytes[5] & FLAG_BOOT_SELECTED != 0
+    }
+
+    /// Get the address of the iSNS server.
+    #[must_use]
+    pub fn isns_server(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 6)
+    }
+
+    /// Get the address of the SLP server.
+    #[must_use]
+    pub fn slp_server(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 22)
+    }
+
+    /// Get the address of the primary RADIUS server.
+    #[must_use]
+    pub fn primary_radius_server(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 38)
+    }
+
+    /// Get the address of the secondary RADIUS server.
+    #[must_use]
+    pub fn secondary_radius_server(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 54)
+    }
+
+    /// Get the iSCSI name of the initiator.
+    #[must_use]
+    pub fn name(&self) -> Option<&'a str> {
+        self.ibft.string_field(self.bytes, 70)
+    }
+}
+
+/// A network interface structure of the [`Ibft`].
+#[derive(Clone, Copy, Debug)]
+pub struct IbftNic<'a> {
+    ibft: Ibft<'a>,
+    bytes: &'a [u8],
+}
+
+impl<'a> IbftNic<'a> {
+    /// Get the index of the structure.
+    #[must_use]
+    pub fn index(&self) -> u8 {
+        self.bytes[4]
+    }
+
+    /// Check whether the firmware booted with this network interface.
+    #[must_use]
+    pub fn is_boot_selected(&self) -> bool {
+        self.bytes[5] & FLAG_BOOT_SELECTED != 0
+    }
+
+    /// Get the IP address of the interface.
+    #[must_use]
+    pub fn ip_address(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 6)
+    }
+
+    /// Get the length of the subnet mask prefix.
+    #[must_use]
+    pub fn subnet_mask_prefix(&self) -> u8 {
+        self.bytes[22]
+    }
+
+    /// Get the origin of the IP address, as defined for
+    /// `IpPrefixOrigin` by Windows: 1 for manual configuration, 3 for DHCP.
+    #[must_use]
+    pub fn origin(&self) -> u8 {
+        self.bytes[23]
+    }
+
+    /// Get the address of the gateway.
+    #[must_use]
+    pub fn gateway(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 24)
+    }
+
+    /// Get the address of the primary DNS server.
+    #[must_use]
+    pub fn primary_dns(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 40)
+    }
+
+    /// Get the address of the secondary DNS server.
+    #[must_use]
+    pub fn secondary_dns(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 56)
+    }
+
+    /// Get the address of the DHCP server.
+    #[must_use]
+    pub fn dhcp_server(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 72)
+    }
+
+    /// Get the VLAN ID of the interface, or zero if no VLAN is used.
+    #[must_use]
+    pub fn vlan(&self) -> u16 {
+        read_u16(self.bytes, 88).unwrap()
+    }
+
+    /// Get the MAC address of the interface.
+    #[must_use]
+    pub fn mac_address(&self) -> [u8; 6] {
+        let mut mac = [0; 6];
+        mac.copy_from_slice(&self.bytes[90..96]);
+        mac
+    }
+
+    /// Get the PCI location of the interface, as bus (bits 8-15), device
+    /// (bits 3-7), and function (bits 0-2).
+    #[must_use]
+    pub fn pci_bdf(&self) -> u16 {
+        read_u16(self.bytes, 96).unwrap()
+    }
+
+    /// Get the host name of the interface.
+    #[must_use]
+    pub fn host_name(&self) -> Option<&'a str> {
+        self.ibft.string_field(self.bytes, 98)
+    }
+}
+
+/// A target structure of the [`Ibft`].
+#[derive(Clone, Copy, Debug)]
+pub struct IbftTarget<'a> {
+    ibft: Ibft<'a>,
+    bytes: &'a [u8],
+}
+
+impl<'a> IbftTarget<'a> {
+    /// CHAP type of targets without authentication.
+    pub const CHAP_NONE: u8 = 0;
+    /// CHAP type of targets with one-way CHAP authentication.
+    pub const CHAP_ONE_WAY: u8 = 1;
+    /// CHAP type of targets with mutual CHAP authentication.
+    pub const CHAP_MUTUAL: u8 = 2;
+
+    /// Get the index of the structure.
+    #[must_use]
+    pub fn index(&self) -> u8 {
+        self.bytes[4]
+    }
+
+    /// Check whether the firmware booted from this target.
+    #[must_use]
+    pub fn is_boot_selected(&self) -> bool {
+        self.bytes[5] & FLAG_BOOT_SELECTED != 0
+    }
+
+    /// Get the IP address of the target.
+    #[must_use]
+    pub fn ip_address(&self) -> IbftAddress {
+        IbftAddress::read(self.bytes, 6)
+    }
+
+    /// Get the TCP port of the target.
+    #[must_use]
+    pub fn port(&self) -> u16 {
+        read_u16(self.bytes, 22).unwrap()
+    }
+
+    /// Get the boot LUN, in the SCSI format.
+    #[must_use]
+    pub fn lun(&self) -> [u8; 8] {
+        let mut lun = [0; 8];
+        lun.copy_from_slice(&self.bytes[24..32]);
+        lun
+    }
+
+    /// Get the CHAP authentication type, such as [`CHAP_NONE`].
+    ///
+    /// [`CHAP_NONE`]: Self::CHAP_NONE
+    #[must_use]
+    pub fn chap_type(&self) -> u8 {
+        self.bytes[32]
+    }
+
+    /// Get the index of the [`IbftNic`] used to connect to the target.
+    #[must_use]
+    pub fn nic_index(&self) -> u8 {
+        self.bytes[33]
+    }
+
+    /// Get the iSCSI name of the target.
+    #[must_use]
+    pub fn name(&self) -> Option<&'a str> {
+        self.ibft.string_field(self.bytes, 34)
+    }
+
+    /// Get the CHAP name used by the initiator.
+    #[must_use]
+    pub fn chap_name(&self) -> Option<&'a str> {
+        self.ibft.string_field(self.bytes, 38)
+    }
+
+    /// Get the CHAP secret used by the initiator.
+    #[must_use]
+    pub fn chap_secret(&self) -> Option<&'a str> {
+        self.ibft.string_field(self.bytes, 42)
+    }
+
+    /// Get the CHAP name used by the target, for mutual authentication.
+    #[must_use]
+    pub fn reverse_chap_name(&self) -> Option<&'a str> {
+        self.ibft.string_field(self.bytes, 46)
+    }
+
+    /// Get the CHAP secret used by the target, for mutual authentication.
+    #[must_use]
+    pub fn reverse_chap_secret(&self) -> Option<&'a str> {
+        self.ibft.string_field(self.bytes, 50)
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn put(table: &mut [u8], offset: usize, bytes: &[u8]) {
+        table[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    fn header(table: &mut [u8], offset: usize, id: u8, len: usize, index: u8, flags: u8) {
+        put(table, offset, &[id, 1]);
+        put(table, offset + 2, &(len as u16).to_le_bytes());
+        put(table, offset + 4, &[index, flags]);
+    }
+
+    fn string(table: &mut Vec<u8>, field: usize, s: &str) {
+        let offset = table.len() as u16;
+        table.extend_from_slice(s.as_bytes());
+        table.push(0);
+        put(table, field, &(s.len() as u16).to_le_bytes());
+        put(table, field + 2, &offset.to_le_bytes());
+    }
+
+    /// Build an iBFT with an initiator, one NIC, and one target.
+    fn ibft() -> Vec<u8> {
+        const CONTROL: usize = 48;
+        const INITIATOR: usize = 72;
+        const NIC: usize = 152;
+        const TARGET: usize = 256;
+
+        let mut table = vec![0; 312];
+        put(&mut table, 0, b"iBFT");
+        table[8] = 1;
+
+        header(&mut table, CONTROL, CONTROL_ID, CONTROL_LEN, 0, 0);
+        put(&mut table, CONTROL + 8, &(INITIATOR as u16).to_le_bytes());
+        put(&mut table, CONTROL + 10, &(NIC as u16).to_le_bytes());
+        put(&mut table, CONTROL + 12, &(TARGET as u16).to_le_bytes());
+
+        let flags = FLAG_VALID | FLAG_BOOT_SELECTED;
+        header(&mut table, INITIATOR, INITIATOR_ID, INITIATOR_LEN, 0, flags);
+        header(&mut table, NIC, NIC_ID, NIC_LEN, 0, flags);
+        put(
+            &mut table,
+            NIC + 6,
+            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff],
+        );
+        put(&mut table, NIC + 18, &[192, 168, 0, 2]);
+        table[NIC + 22] = 24;
+        put(&mut table, NIC + 88, &5u16.to_le_bytes());
+        put(&mut table, NIC + 90, &[0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+        header(&mut table, TARGET, TARGET_ID, TARGET_LEN, 0, flags);
+        put(&mut table, TARGET + 22, &3260u16.to_le_bytes());
+        table[TARGET + 32] = IbftTarget::CHAP_ONE_WAY;
+
+        string(&mut table, INITIATOR + 70, "iqn.2023-01.com.example:host");
+        string(&mut table, TARGET + 34, "iqn.2023-01.com.example:disk");
+        string(&mut table, TARGET + 38, "user");
+        let len = table.len() as u32;
+        put(&mut table, 4, &len.to_le_bytes());
+        table
+    }
+
+    #[test]
+    fn test_ibft() {
+        let table = ibft();
+        let ibft = Ibft::new(&table).unwrap();
+        assert_eq!(ibft.revision(), 1);
+
+        let initiator = ibft.initiator().unwrap();
+        assert!(initiator.is_boot_selected());
+        assert_eq!(initiator.name(), Some("iqn.2023-01.com.example:host"));
+        assert!(initiator.isns_server().is_unspecified());
+
+        let nic = ibft.nic(0).unwrap();
+        assert_eq!(nic.ip_address().to_ipv4(), Some([192, 168, 0, 2]));
+        assert_eq!(nic.subnet_mask_prefix(), 24);
+        assert_eq!(nic.vlan(), 5);
+        assert_eq!(nic.mac_address(), [0x52, 0x54, 0, 0x12, 0x34, 0x56]);
+        assert_eq!(nic.host_name(), None);
+        assert!(ibft.nic(1).is_none());
+        assert_eq!(ibft.nics().count(), 1);
+
+        let target = ibft.target(0).unwrap();
+        assert_eq!(target.port(), 3260);
+        assert_eq!(target.chap_type(), IbftTarget::CHAP_ONE_WAY);
+        assert_eq!(target.name(), Some("iqn.2023-01.com.example:disk"));
+        assert_eq!(target.chap_name(), Some("user"));
+        assert_eq!(target.chap_secret(), None);
+        assert_eq!(ibft.targets().count(), 1);
+    }
+
+    #[test]
+    fn test_ibft_invalid() {
+        assert!(Ibft::new(&[]).is_none());
+
+        let mut table = ibft();
+        table[0] = b'x';
+        assert!(Ibft::new(&table).is_none());
+
+        // Length larger than the table.
+        let mut table = ibft();
+        put(&mut table, 4, &1000u32.to_le_bytes());
+        assert!(Ibft::new(&table).is_none());
+
+        // Invalid structures are skipped.
+        let mut table = ibft();
+        table[152 + 5] = 0;
+        let ibft = Ibft::new(&table).unwrap();
+        assert!(ibft.nic(0).is_none());
+        assert!(ibft.target(0).is_some());
+    }
+}
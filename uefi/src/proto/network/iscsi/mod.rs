@@ -0,0 +1,158 @@
+//! iSCSI boot support.
+//!
+//! This module provides the [`IscsiInitiatorName`] protocol, used to get and
+//! set the name of the iSCSI initiator of the firmware, and a parser for
+//! the iSCSI Boot Firmware Table ([`Ibft`]), which describes the iSCSI
+//! targets the firmware booted from.
+
+mod ibft;
+
+pub use ibft::{Ibft, IbftAddress, IbftInitiator, IbftNic, IbftTarget};
+
+use crate::proto::unsafe_protocol;
+use crate::{CStr8, Error, Result, Status};
+use core::ffi::c_void;
+
+/// The iSCSI initiator name protocol, used to get and set the iSCSI
+/// Qualified Name of the initiator, such as `iqn.2023-01.com.example:host`.
+///
+/// The corresponding C type is `EFI_ISCSI_INITIATOR_NAME_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("59324945-ec44-4c0d-b1cd-9db139df070c")]
+pub struct IscsiInitiatorName {
+    get: unsafe extern "efiapi" fn(
+        this: *const Self,
+        buffer_size: *mut usize,
+        buffer: *mut c_void,
+    ) -> Status,
+    set: unsafe extern "efiapi" fn(
+        this: *const Self,
+        buffer_size: *mut usize,
+        buffer: *const c_void,
+    ) -> Status,
+}
+
+impl IscsiInitiatorName {
+    /// Maximum size in bytes of an initiator name, including the null
+    /// terminator. A buffer of this size is always large enough for
+    /// [`get`].
+    ///
+    /// [`get`]: Self::get
+    pub const MAX_NAME_SIZE: usize = 224;
+
+    /// Get the initiator name, reading it into `buffer`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buffer` is too small, the required
+    ///   size is returned in the error data.
+    /// * [`Status::NOT_FOUND`]: the initiator name is not set.
+    /// * [`Status::DEVICE_ERROR`]: the name could not be read.
+    /// * [`Status::PROTOCOL_ERROR`]: the returned name is not null-terminated.
+    pub fn get<'buf>(&self, buffer: &'buf mut [u8]) -> Result<&'buf CStr8, Option<usize>> {
+        let mut size = buffer.len();
+        unsafe { (self.get)(self, &mut size, buffer.as_mut_ptr().cast()) }.into_with(
+            || (),
+            |status| (status == Status::BUFFER_TOO_SMALL).then_some(size),
+        )?;
+
+        let name = &buffer[..size.min(buffer.len())];
+        let len = name
+            .iter()
+            .position(|&c| c == 0)
+            .ok_or_else(|| Error::new(Status::PROTOCOL_ERROR, None))?;
+        Ok(unsafe { CStr8::from_bytes_with_nul_unchecked(&name[..=len]) })
+    }
+
+    /// Set the initiator name. The name is stored in a non-volatile
+    /// variable, so it is preserved across reboots.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `name` is not a valid iSCSI name.
+    /// * [`Status::OUT_OF_RESOURCES`]: `name` is longer than
+    ///   [`MAX_NAME_SIZE`].
+    /// * [`Status::WRITE_PROTECTED`]: the name can't be changed.
+    /// * [`Status::DEVICE_ERROR`]: the name could not be written.
+    ///
+    /// [`MAX_NAME_SIZE`]: Self::MAX_NAME_SIZE
+    pub fn set(&mut self, name: &CStr8) -> Result {
+        let mut size = name.to_bytes_with_nul().len();
+        unsafe { (self.set)(self, &mut size, name.as_ptr().cast()) }.into()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use core::marker::PhantomData;
+    use core::{ptr, slice};
+
+    static mut NAME: [u8; IscsiInitiatorName::MAX_NAME_SIZE] =
+        [0; IscsiInitiatorName::MAX_NAME_SIZE];
+
+    unsafe extern "efiapi" fn get(
+        _this: *const IscsiInitiatorName,
+        buffer_size: *mut usize,
+        buffer: *mut c_void,
+    ) -> Status {
+        let name = &*ptr::addr_of!(NAME);
+        let len = name.iter().position(|&c| c == 0).unwrap() + 1;
+        if len == 1 {
+            return Status::NOT_FOUND;
+        }
+        let size = *buffer_size;
+        *buffer_size = len;
+        if size < len {
+            return Status::BUFFER_TOO_SMALL;
+        }
+        buffer.cast::<u8>().copy_from(name.as_ptr(), len);
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn set(
+        _this: *const IscsiInitiatorName,
+        buffer_size: *mut usize,
+        buffer: *const c_void,
+    ) -> Status {
+        let name = &mut *ptr::addr_of_mut!(NAME);
+        let size = *buffer_size;
+        if size > name.len() {
+            return Status::OUT_OF_RESOURCES;
+        }
+        name.fill(0);
+        name[..size].copy_from_slice(slice::from_raw_parts(buffer.cast(), size));
+        Status::SUCCESS
+    }
+
+    #[test]
+    fn test_initiator_name() {
+        let mut firmware = MockFirmware::new();
+        let mut protocol = IscsiInitiatorName {
+            get,
+            set,
+            _no_send_or_sync: PhantomData,
+        };
+        let handle = unsafe { firmware.install_protocol(None, &mut protocol) };
+        let st = firmware.system_table();
+        let mut protocol = st
+            .boot_services()
+            .open_protocol_exclusive::<IscsiInitiatorName>(handle)
+            .unwrap();
+
+        let mut buffer = [0; IscsiInitiatorName::MAX_NAME_SIZE];
+        assert_eq!(
+            protocol.get(&mut buffer).unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+
+        let name = CStr8::from_bytes_with_nul(b"iqn.2023-01.com.example:host\0").unwrap();
+        protocol.set(name).unwrap();
+        assert_eq!(protocol.get(&mut buffer).unwrap(), name);
+
+        let err = protocol.get(&mut buffer[..4]).unwrap_err();
+        assert_eq!(err.status(), Status::BUFFER_TOO_SMALL);
+        assert_eq!(*err.data(), Some(29));
+    }
+}
@@ -0,0 +1,805 @@
+//! Managed Network Protocol.
+//!
+//! The managed network protocol (MNP) sits between the [`SimpleNetwork`]
+//! protocol and the higher level network protocols, such as ARP or IP.
+//! Unlike SNP, it can be shared by several consumers: each consumer creates
+//! its own MNP instance with the [`ServiceBindingProtocol<ManagedNetwork>`]
+//! of the network controller, configures it with the packet types it is
+//! interested in, and only receives the matching packets.
+//!
+//! Packets are transmitted and received asynchronously with completion
+//! tokens. Each token holds an [`Event`] that is signaled by the driver once
+//! the operation completes, so that the consumer can wait for it with
+//! [`BootServices::wait_for_event`] or check it with
+//! [`BootServices::check_event`].
+//!
+//! [`SimpleNetwork`]: super::snp::SimpleNetwork
+//! [`ServiceBindingProtocol<ManagedNetwork>`]: crate::proto::service_binding::ServiceBindingProtocol
+//! [`BootServices::wait_for_event`]: crate::table::boot::BootServices::wait_for_event
+//! [`BootServices::check_event`]: crate::table::boot::BootServices::check_event
+
+use super::snp::NetworkMode;
+use super::{IpAddress, MacAddress};
+use crate::proto::unsafe_protocol;
+use crate::table::boot::BootServices;
+use crate::table::runtime::Time;
+use crate::{Event, Result, Status};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::{fmt, ptr, slice};
+
+/// The Managed Network Protocol.
+///
+/// Instances of this protocol are created with the
+/// [`ServiceBindingProtocol<ManagedNetwork>`] installed on the network
+/// controller handle.
+///
+/// The corresponding C type is `EFI_MANAGED_NETWORK_PROTOCOL`.
+///
+/// [`ServiceBindingProtocol<ManagedNetwork>`]: crate::proto::service_binding::ServiceBindingProtocol
+#[repr(C)]
+#[unsafe_protocol(
+    "7ab33a91-ace5-4326-b572-e7ee33d39f16",
+    service_binding = "f36ff770-a7e1-42cf-9ed2-56f0f271f44c"
+)]
+pub struct ManagedNetwork {
+    get_mode_data: unsafe extern "efiapi" fn(
+        this: *const Self,
+        mnp_config_data: *mut ManagedNetworkConfigData,
+        snp_mode_data: *mut NetworkMode,
+    ) -> Status,
+    configure: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        mnp_config_data: *const ManagedNetworkConfigData,
+    ) -> Status,
+    mcast_ip_to_mac: unsafe extern "efiapi" fn(
+        this: *const Self,
+        ipv6: bool,
+        ip_address: *const IpAddress,
+        mac_address: *mut MacAddress,
+    ) -> Status,
+    groups: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        join: bool,
+        mac_address: *const MacAddress,
+    ) -> Status,
+    transmit: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        token: *mut ManagedNetworkTransmitToken<'_>,
+    ) -> Status,
+    receive: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        token: *mut ManagedNetworkReceiveToken,
+    ) -> Status,
+    cancel: unsafe extern "efiapi" fn(this: *mut Self, token: *mut c_void) -> Status,
+    poll: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+}
+
+impl ManagedNetwork {
+    /// Get the current configuration of this MNP instance. If the instance
+    /// is not configured yet, the default configuration is returned.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    /// * [`Status::UNSUPPORTED`]: the requested feature is not supported.
+    pub fn config_data(&self) -> Result<ManagedNetworkConfigData> {
+        let mut config_data = MaybeUninit::uninit();
+        match unsafe { (self.get_mode_data)(self, config_data.as_mut_ptr(), ptr::null_mut()) } {
+            Status::SUCCESS | Status::NOT_STARTED => Ok(unsafe { config_data.assume_init() }),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Get the mode of the underlying [`SimpleNetwork`] protocol.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    /// * [`Status::UNSUPPORTED`]: the requested feature is not supported.
+    ///
+    /// [`SimpleNetwork`]: super::snp::SimpleNetwork
+    pub fn snp_mode_data(&self) -> Result<NetworkMode> {
+        let mut mode = MaybeUninit::uninit();
+        match unsafe { (self.get_mode_data)(self, ptr::null_mut(), mode.as_mut_ptr()) } {
+            Status::SUCCESS | Status::NOT_STARTED => Ok(unsafe { mode.assume_init() }),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Configure this MNP instance with `config_data`, or reset it to the
+    /// unconfigured state if `config_data` is `None`. Resetting cancels
+    /// all the pending transmit and receive requests.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `config_data` is invalid.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   configure the instance.
+    /// * [`Status::UNSUPPORTED`]: `config_data` requests a feature that is
+    ///   not supported.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn configure(&mut self, config_data: Option<&ManagedNetworkConfigData>) -> Result {
+        let config_data = config_data.map_or(ptr::null(), |data| data as *const _);
+        unsafe { (self.configure)(self, config_data) }.into()
+    }
+
+    /// Translate the multicast IPv4 or IPv6 address `ip_address` to a
+    /// multicast hardware address.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `ip_address` is not a multicast
+    ///   address.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::UNSUPPORTED`]: the translation is not supported.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn mcast_ip_to_mac(&self, ipv6: bool, ip_address: &IpAddress) -> Result<MacAddress> {
+        let mut mac_address = MaybeUninit::uninit();
+        unsafe { (self.mcast_ip_to_mac)(self, ipv6, ip_address, mac_address.as_mut_ptr()) }
+            .into_with_val(|| unsafe { mac_address.assume_init() })
+    }
+
+    /// Receive the packets sent to the multicast hardware address
+    /// `mac_address`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `mac_address` is not a multicast
+    ///   address.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::ALREADY_STARTED`]: the group was already joined.
+    /// * [`Status::UNSUPPORTED`]: multicast is not supported.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn join_group(&mut self, mac_address: &MacAddress) -> Result {
+        unsafe { (self.groups)(self, true, mac_address) }.into()
+    }
+
+    /// Stop receiving the packets sent to the multicast hardware address
+    /// `mac_address`, or to all the joined groups if `mac_address` is
+    /// `None`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `mac_address` is not a multicast
+    ///   address.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::NOT_FOUND`]: the group was not joined.
+    /// * [`Status::UNSUPPORTED`]: multicast is not supported.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn leave_group(&mut self, mac_address: Option<&MacAddress>) -> Result {
+        let mac_address = mac_address.map_or(ptr::null(), |mac| mac as *const _);
+        unsafe { (self.groups)(self, false, mac_address) }.into()
+    }
+
+    /// Queue a packet for transmission. The event of `token` is signaled
+    /// once the packet is sent, or once the transmission failed, after
+    /// which [`ManagedNetworkTransmitToken::status`] returns the result.
+    ///
+    /// # Safety
+    ///
+    /// `token` must not be moved or dropped until its event is signaled or
+    /// the request is cancelled with [`cancel`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the packet is invalid.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::ACCESS_DENIED`]: `token` is already queued.
+    /// * [`Status::OUT_OF_RESOURCES`]: the transmit queue is full.
+    /// * [`Status::NOT_READY`]: the transmit queue is full.
+    ///
+    /// [`cancel`]: Self::cancel
+    pub unsafe fn transmit(&mut self, token: &mut ManagedNetworkTransmitToken<'_>) -> Result {
+        token.status = Status::NOT_READY;
+        (self.transmit)(self, token).into()
+    }
+
+    /// Queue a request to receive a packet. The event of `token` is
+    /// signaled once a packet matching the configuration of this instance
+    /// is received, after which [`ManagedNetworkReceiveToken::data`] returns
+    /// the packet.
+    ///
+    /// # Safety
+    ///
+    /// `token` must not be moved or dropped until its event is signaled or
+    /// the request is cancelled with [`cancel`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::ACCESS_DENIED`]: `token` is already queued.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   queue the request.
+    ///
+    /// [`cancel`]: Self::cancel
+    pub unsafe fn receive(&mut self, token: &mut ManagedNetworkReceiveToken) -> Result {
+        token.status = Status::NOT_READY;
+        token.data = ptr::null_mut();
+        (self.receive)(self, token).into()
+    }
+
+    /// Cancel the pending transmit or receive request of `token`, or all
+    /// the pending requests if `token` is `None`. The events of the
+    /// cancelled tokens are signaled, with a status of
+    /// [`Status::ABORTED`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::NOT_FOUND`]: `token` is not queued.
+    pub fn cancel(&mut self, token: Option<ManagedNetworkToken<'_, '_>>) -> Result {
+        let token = match token {
+            Some(ManagedNetworkToken::Transmit(token)) => {
+                (token as *mut ManagedNetworkTransmitToken).cast::<c_void>()
+            }
+            Some(ManagedNetworkToken::Receive(token)) => {
+                (token as *mut ManagedNetworkReceiveToken).cast::<c_void>()
+            }
+            None => ptr::null_mut(),
+        };
+        unsafe { (self.cancel)(self, token) }.into()
+    }
+
+    /// Poll the network controller for transmitted and received packets,
+    /// completing the pending requests.
+    ///
+    /// The driver usually polls in the background, so this is only needed
+    /// to reduce latency, or if
+    /// [`ManagedNetworkConfigData::disable_background_polling`] is set.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::NOT_READY`]: no packet was transmitted or received.
+    /// * [`Status::TIMEOUT`]: the packets could not be processed in time.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn poll(&mut self) -> Result {
+        unsafe { (self.poll)(self) }.into()
+    }
+}
+
+/// Configuration of a [`ManagedNetwork`] instance.
+///
+/// The corresponding C type is `EFI_MANAGED_NETWORK_CONFIG_DATA`.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+#[repr(C)]
+pub struct ManagedNetworkConfigData {
+    /// Timeout in microseconds after which the received packets are
+    /// dropped if they are not delivered, or 0 for the default timeout.
+    pub received_queue_timeout: u32,
+    /// Timeout in microseconds after which the packets to transmit are
+    /// dropped if they are not sent, or 0 for the default timeout.
+    pub transmit_queue_timeout: u32,
+    /// The protocol type of the packets to receive, such as 0x0806 for ARP,
+    /// or 0 to receive all packets.
+    pub protocol_type_filter: u16,
+    /// Receive the packets sent to the station address.
+    pub enable_unicast_receive: bool,
+    /// Receive the packets sent to the joined multicast groups.
+    pub enable_multicast_receive: bool,
+    /// Receive the broadcast packets.
+    pub enable_broadcast_receive: bool,
+    /// Receive all the packets, whatever their destination.
+    pub enable_promiscuous_receive: bool,
+    /// Drop the queued packets when the instance is reset.
+    pub flush_queues_on_reset: bool,
+    /// Record the time at which the packets are received.
+    pub enable_receive_timestamps: bool,
+    /// Don't poll the network controller in the background. The consumer
+    /// must then call [`ManagedNetwork::poll`].
+    pub disable_background_polling: bool,
+}
+
+/// A pending request of a [`ManagedNetwork`], to cancel with
+/// [`ManagedNetwork::cancel`].
+#[derive(Debug)]
+pub enum ManagedNetworkToken<'a, 'data> {
+    /// A transmit request.
+    Transmit(&'a mut ManagedNetworkTransmitToken<'data>),
+    /// A receive request.
+    Receive(&'a mut ManagedNetworkReceiveToken),
+}
+
+/// Completion token of a receive request.
+///
+/// The corresponding C type is `EFI_MANAGED_NETWORK_COMPLETION_TOKEN`.
+#[repr(C)]
+pub struct ManagedNetworkReceiveToken {
+    event: Event,
+    status: Status,
+    data: *mut ManagedNetworkReceiveData,
+}
+
+impl ManagedNetworkReceiveToken {
+    /// Create a token signaling `event` on completion.
+    ///
+    /// The event must not be of type [`EventType::NOTIFY_SIGNAL`] to be
+    /// waited on, but a notification function can be used instead.
+    ///
+    /// [`EventType::NOTIFY_SIGNAL`]: crate::table::boot::EventType::NOTIFY_SIGNAL
+    #[must_use]
+    pub const fn new(event: Event) -> Self {
+        Self {
+            event,
+            status: Status::NOT_READY,
+            data: ptr::null_mut(),
+        }
+    }
+
+    /// Get the event signaled on completion.
+    #[must_use]
+    pub const fn event(&self) -> &Event {
+        &self.event
+    }
+
+    /// Get the result of the request. This is [`Status::NOT_READY`] while
+    /// the request is pending.
+    pub const fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Get the received packet, if the request succeeded.
+    ///
+    /// The packet must be returned to the driver with [`recycle`] before
+    /// the token is reused.
+    ///
+    /// [`recycle`]: Self::recycle
+    #[must_use]
+    pub fn data(&self) -> Option<&ManagedNetworkReceiveData> {
+        if self.status.is_success() {
+            unsafe { self.data.as_ref() }
+        } else {
+            None
+        }
+    }
+
+    /// Return the received packet to the driver, if any.
+    ///
+    /// # Errors
+    ///
+    /// See [`BootServices::signal_event`].
+    pub fn recycle(&mut self, bt: &BootServices) -> Result {
+        let data = match self.data() {
+            Some(data) => data,
+            None => return Ok(()),
+        };
+        bt.signal_event(&data.recycle_event)?;
+        self.data = ptr::null_mut();
+        self.status = Status::NOT_READY;
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ManagedNetworkReceiveToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManagedNetworkReceiveToken")
+            .field("event", &self.event.as_ptr())
+            .field("status", &self.status)
+            .field("data", &self.data())
+            .finish()
+    }
+}
+
+/// A received packet.
+///
+/// The corresponding C type is `EFI_MANAGED_NETWORK_RECEIVE_DATA`.
+#[repr(C)]
+pub struct ManagedNetworkReceiveData {
+    timestamp: Time,
+    recycle_event: Event,
+    packet_length: u32,
+    header_length: u32,
+    address_length: u32,
+    data_length: u32,
+    broadcast: bool,
+    multicast: bool,
+    promiscuous: bool,
+    protocol_type: u16,
+    destination_address: *const u8,
+    source_address: *const u8,
+    media_header: *const u8,
+    packet_data: *const u8,
+}
+
+impl ManagedNetworkReceiveData {
+    /// Get the time at which the packet was received, if
+    /// [`ManagedNetworkConfigData::enable_receive_timestamps`] is set.
+    #[must_use]
+    pub const fn timestamp(&self) -> Time {
+        self.timestamp
+    }
+
+    /// Get the length of the packet, including the media header.
+    #[must_use]
+    pub const fn packet_length(&self) -> u32 {
+        self.packet_length
+    }
+
+    /// Whether the packet was broadcast.
+    #[must_use]
+    pub const fn is_broadcast(&self) -> bool {
+        self.broadcast
+    }
+
+    /// Whether the packet was sent to a multicast group.
+    #[must_use]
+    pub const fn is_multicast(&self) -> bool {
+        self.multicast
+    }
+
+    /// Whether the packet was only received because of promiscuous mode.
+    #[must_use]
+    pub const fn is_promiscuous(&self) -> bool {
+        self.promiscuous
+    }
+
+    /// Get the protocol type of the packet, such as 0x0800 for IPv4.
+    #[must_use]
+    pub const fn protocol_type(&self) -> u16 {
+        self.protocol_type
+    }
+
+    /// Get the hardware address the packet was sent to.
+    #[must_use]
+    pub fn destination_address(&self) -> &[u8] {
+        unsafe { bytes(self.destination_address, self.address_length) }
+    }
+
+    /// Get the hardware address of the sender of the packet.
+    #[must_use]
+    pub fn source_address(&self) -> &[u8] {
+        unsafe { bytes(self.source_address, self.address_length) }
+    }
+
+    /// Get the media header of the packet, such as the Ethernet header.
+    #[must_use]
+    pub fn media_header(&self) -> &[u8] {
+        unsafe { bytes(self.media_header, self.header_length) }
+    }
+
+    /// Get the data of the packet, following the media header.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        unsafe { bytes(self.packet_data, self.data_length) }
+    }
+}
+
+impl fmt::Debug for ManagedNetworkReceiveData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManagedNetworkReceiveData")
+            .field("packet_length", &self.packet_length)
+            .field("broadcast", &self.broadcast)
+            .field("multicast", &self.multicast)
+            .field("promiscuous", &self.promiscuous)
+            .field("protocol_type", &self.protocol_type)
+            .field("destination_address", &self.destination_address())
+            .field("source_address", &self.source_address())
+            .field("data_length", &self.data_length)
+            .finish()
+    }
+}
+
+/// Get the `len` bytes at `data`, or an empty slice if `data` is null.
+unsafe fn bytes<'a>(data: *const u8, len: u32) -> &'a [u8] {
+    if data.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len as usize)
+    }
+}
+
+/// Completion token of a transmit request.
+///
+/// The corresponding C type is `EFI_MANAGED_NETWORK_COMPLETION_TOKEN`.
+#[repr(C)]
+pub struct ManagedNetworkTransmitToken<'a> {
+    event: Event,
+    status: Status,
+    data: *const ManagedNetworkTransmitData<'a>,
+}
+
+impl<'a> ManagedNetworkTransmitToken<'a> {
+    /// Create a token transmitting `data`, and signaling `event` on
+    /// completion.
+    ///
+    /// The event must not be of type [`EventType::NOTIFY_SIGNAL`] to be
+    /// waited on, but a notification function can be used instead.
+    ///
+    /// [`EventType::NOTIFY_SIGNAL`]: crate::table::boot::EventType::NOTIFY_SIGNAL
+    #[must_use]
+    pub const fn new(event: Event, data: &'a ManagedNetworkTransmitData<'a>) -> Self {
+        Self {
+            event,
+            status: Status::NOT_READY,
+            data,
+        }
+    }
+
+    /// Get the event signaled on completion.
+    #[must_use]
+    pub const fn event(&self) -> &Event {
+        &self.event
+    }
+
+    /// Get the result of the request. This is [`Status::NOT_READY`] while
+    /// the request is pending.
+    pub const fn status(&self) -> Status {
+        self.status
+    }
+}
+
+impl fmt::Debug for ManagedNetworkTransmitToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManagedNetworkTransmitToken")
+            .field("event", &self.event.as_ptr())
+            .field("status", &self.status)
+            .field("data", &self.data)
+            .finish()
+    }
+}
+
+/// A fragment of a packet to transmit.
+#[derive(Debug)]
+#[repr(C)]
+struct FragmentData {
+    length: u32,
+    buffer: *const c_void,
+}
+
+/// A packet to transmit.
+///
+/// The corresponding C type is `EFI_MANAGED_NETWORK_TRANSMIT_DATA`, with a
+/// single fragment.
+#[derive(Debug)]
+#[repr(C)]
+pub struct ManagedNetworkTransmitData<'a> {
+    destination_address: *const MacAddress,
+    source_address: *const MacAddress,
+    protocol_type: u16,
+    data_length: u32,
+    header_length: u16,
+    fragment_count: u16,
+    fragment: FragmentData,
+    _marker: PhantomData<&'a [u8]>,
+}
+
+impl<'a> ManagedNetworkTransmitData<'a> {
+    /// Create a packet sending `data` to `destination_address`. The media
+    /// header is built by the driver, from the station address and
+    /// `protocol_type`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: `data` is longer than `u32::MAX` bytes.
+    pub fn new(
+        destination_address: &'a MacAddress,
+        protocol_type: u16,
+        data: &'a [u8],
+    ) -> Result<Self> {
+        let length = u32::try_from(data.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        Ok(Self {
+            destination_address,
+            source_address: ptr::null(),
+            protocol_type,
+            data_length: length,
+            header_length: 0,
+            fragment_count: 1,
+            fragment: FragmentData {
+                length,
+                buffer: data.as_ptr().cast(),
+            },
+            _marker: PhantomData,
+        })
+    }
+
+    /// Create a packet sending `packet` as is. `packet` starts with a media
+    /// header of `header_length` bytes, built by the caller.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: `packet` is longer than `u32::MAX`
+    ///   bytes.
+    /// * [`Status::INVALID_PARAMETER`]: `packet` is shorter than
+    ///   `header_length`.
+    pub fn with_media_header(header_length: u16, packet: &'a [u8]) -> Result<Self> {
+        let length = u32::try_from(packet.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let data_length = length
+            .checked_sub(u32::from(header_length))
+            .ok_or(Status::INVALID_PARAMETER)?;
+        Ok(Self {
+            destination_address: ptr::null(),
+            source_address: ptr::null(),
+            protocol_type: 0,
+            data_length,
+            header_length,
+            fragment_count: 1,
+            fragment: FragmentData {
+                length,
+                buffer: packet.as_ptr().cast(),
+            },
+            _marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use crate::table::boot::{EventType, Tpl};
+
+    const PACKET: [u8; 4] = [1, 2, 3, 4];
+    const SOURCE: [u8; 6] = [0x52, 0x54, 0, 0, 0, 1];
+    const BROADCAST: [u8; 6] = [0xff; 6];
+
+    /// The packet returned by `receive`, and its recycle event.
+    static mut RECEIVE_DATA: MaybeUninit<ManagedNetworkReceiveData> = MaybeUninit::uninit();
+
+    unsafe extern "efiapi" fn get_mode_data(
+        _this: *const ManagedNetwork,
+        mnp_config_data: *mut ManagedNetworkConfigData,
+        _snp_mode_data: *mut NetworkMode,
+    ) -> Status {
+        if let Some(config_data) = mnp_config_data.as_mut() {
+            *config_data = ManagedNetworkConfigData {
+                protocol_type_filter: 0x0806,
+                ..Default::default()
+            };
+        }
+        Status::NOT_STARTED
+    }
+
+    unsafe extern "efiapi" fn configure(
+        _this: *mut ManagedNetwork,
+        _mnp_config_data: *const ManagedNetworkConfigData,
+    ) -> Status {
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn mcast_ip_to_mac(
+        _this: *const ManagedNetwork,
+        _ipv6: bool,
+        _ip_address: *const IpAddress,
+        _mac_address: *mut MacAddress,
+    ) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    unsafe extern "efiapi" fn groups(
+        _this: *mut ManagedNetwork,
+        _join: bool,
+        _mac_address: *const MacAddress,
+    ) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    unsafe extern "efiapi" fn transmit(
+        _this: *mut ManagedNetwork,
+        token: *mut ManagedNetworkTransmitToken<'_>,
+    ) -> Status {
+        let data = &*(*token).data;
+        let packet = slice::from_raw_parts(
+            data.fragment.buffer.cast::<u8>(),
+            data.fragment.length as usize,
+        );
+        (*token).status = if packet == PACKET && data.protocol_type == 0x0806 {
+            Status::SUCCESS
+        } else {
+            Status::INVALID_PARAMETER
+        };
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn receive(
+        _this: *mut ManagedNetwork,
+        token: *mut ManagedNetworkReceiveToken,
+    ) -> Status {
+        (*token).data = (*ptr::addr_of_mut!(RECEIVE_DATA)).as_mut_ptr();
+        (*token).status = Status::SUCCESS;
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn cancel(_this: *mut ManagedNetwork, token: *mut c_void) -> Status {
+        if token.is_null() {
+            Status::SUCCESS
+        } else {
+            Status::NOT_FOUND
+        }
+    }
+
+    unsafe extern "efiapi" fn poll(_this: *mut ManagedNetwork) -> Status {
+        Status::NOT_READY
+    }
+
+    #[test]
+    fn test_managed_network() {
+        let mut firmware = MockFirmware::new();
+        let mut protocol = ManagedNetwork {
+            get_mode_data,
+            configure,
+            mcast_ip_to_mac,
+            groups,
+            transmit,
+            receive,
+            cancel,
+            poll,
+            _no_send_or_sync: PhantomData,
+        };
+        let handle = unsafe { firmware.install_protocol(None, &mut protocol) };
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut mnp = bt
+            .open_protocol_exclusive::<ManagedNetwork>(handle)
+            .unwrap();
+
+        assert_eq!(mnp.config_data().unwrap().protocol_type_filter, 0x0806);
+        mnp.configure(Some(&ManagedNetworkConfigData::default()))
+            .unwrap();
+        assert_eq!(mnp.poll().unwrap_err().status(), Status::NOT_READY);
+
+        let new_event = || unsafe {
+            bt.create_event(EventType::empty(), Tpl::APPLICATION, None, None)
+                .unwrap()
+        };
+
+        // Transmit.
+        let destination = MacAddress([0xff; 32]);
+        let data = ManagedNetworkTransmitData::new(&destination, 0x0806, &PACKET).unwrap();
+        assert_eq!(
+            ManagedNetworkTransmitData::with_media_header(14, &PACKET)
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+        let mut token = ManagedNetworkTransmitToken::new(new_event(), &data);
+        assert_eq!(token.status(), Status::NOT_READY);
+        unsafe { mnp.transmit(&mut token) }.unwrap();
+        assert_eq!(token.status(), Status::SUCCESS);
+        assert_eq!(
+            mnp.cancel(Some(ManagedNetworkToken::Transmit(&mut token)))
+                .unwrap_err()
+                .status(),
+            Status::NOT_FOUND
+        );
+
+        // Receive.
+        let recycle_event = new_event();
+        unsafe {
+            RECEIVE_DATA.write(ManagedNetworkReceiveData {
+                timestamp: MaybeUninit::zeroed().assume_init(),
+                recycle_event: recycle_event.unsafe_clone(),
+                packet_length: 4,
+                header_length: 0,
+                address_length: 6,
+                data_length: 4,
+                broadcast: true,
+                multicast: false,
+                promiscuous: false,
+                protocol_type: 0x0806,
+                destination_address: BROADCAST.as_ptr(),
+                source_address: SOURCE.as_ptr(),
+                media_header: ptr::null(),
+                packet_data: PACKET.as_ptr(),
+            });
+        }
+        let mut token = ManagedNetworkReceiveToken::new(new_event());
+        assert!(token.data().is_none());
+        unsafe { mnp.receive(&mut token) }.unwrap();
+        let data = token.data().unwrap();
+        assert!(data.is_broadcast());
+        assert_eq!(data.protocol_type(), 0x0806);
+        assert_eq!(data.destination_address(), BROADCAST);
+        assert_eq!(data.source_address(), SOURCE);
+        assert!(data.media_header().is_empty());
+        assert_eq!(data.data(), PACKET);
+
+        token.recycle(bt).unwrap();
+        assert!(token.data().is_none());
+        assert!(bt.check_event(recycle_event).unwrap());
+
+        mnp.cancel(None).unwrap();
+    }
+}
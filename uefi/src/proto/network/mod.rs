@@ -2,8 +2,18 @@
 //!
 //! These protocols can be used to interact with network resources.
 
+pub mod arp;
+pub mod dhcp4;
+pub mod dhcp6;
+pub mod http;
+pub mod iscsi;
+pub mod mnp;
+pub mod mtftp;
+pub mod nii;
 pub mod pxe;
+pub mod rest_ex;
 pub mod snp;
+pub mod vlan;
 
 /// Represents an IPv4/v6 address.
 ///
@@ -0,0 +1,483 @@
+//! MTFTP (multicast TFTP) protocols.
+//!
+//! These protocols download and upload files from and to a TFTP server,
+//! outside of the [`BaseCode`] flow. There are two versions of the
+//! protocol: [`v4`] for IPv4 networks and [`v6`] for IPv6 networks.
+//! Instances of these protocols are created with the service binding
+//! protocol installed on the network controller handle.
+//!
+//! Both versions exchange the same packets, which are parsed into
+//! [`MtftpPacket`]s. The transfer functions call a progress closure with
+//! each packet received from the server, which can abort the transfer by
+//! returning an error.
+//!
+//! [`BaseCode`]: super::pxe::BaseCode
+
+pub mod v4;
+pub mod v6;
+
+use crate::table::boot::BootServices;
+use crate::{CStr8, Char8, Event, Result, Status};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
+use core::{fmt, slice};
+
+/// A TFTP option sent with a request, as defined in RFC 2347.
+///
+/// The corresponding C types are `EFI_MTFTP4_OPTION` and
+/// `EFI_MTFTP6_OPTION`.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct MtftpOption<'a> {
+    name: *const Char8,
+    value: *const Char8,
+    _marker: PhantomData<&'a CStr8>,
+}
+
+impl<'a> MtftpOption<'a> {
+    /// Create an option, such as `blksize` with the block size as value.
+    #[must_use]
+    pub const fn new(name: &'a CStr8, value: &'a CStr8) -> Self {
+        Self {
+            name: name.as_ptr(),
+            value: value.as_ptr(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Get the name of the option.
+    #[must_use]
+    pub fn name(&self) -> &'a CStr8 {
+        unsafe { CStr8::from_ptr(self.name) }
+    }
+
+    /// Get the value of the option.
+    #[must_use]
+    pub fn value(&self) -> &'a CStr8 {
+        unsafe { CStr8::from_ptr(self.value) }
+    }
+}
+
+impl fmt::Debug for MtftpOption<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MtftpOption")
+            .field("name", &self.name())
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+/// A TFTP packet, as defined in RFC 1350 and RFC 2347.
+///
+/// The corresponding C types are `EFI_MTFTP4_PACKET` and
+/// `EFI_MTFTP6_PACKET`. Packets that can't be parsed are returned as
+/// [`Other`].
+///
+/// [`Other`]: Self::Other
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MtftpPacket<'a> {
+    /// A block of the file.
+    Data {
+        /// The number of the block, starting at 1.
+        block: u16,
+        /// The data of the block. A block shorter than the block size ends
+        /// the file.
+        data: &'a [u8],
+    },
+    /// The acknowledgement of a block of the file.
+    Ack {
+        /// The number of the acknowledged block.
+        block: u16,
+    },
+    /// An error, ending the transfer.
+    Error {
+        /// The TFTP error code, such as 1 for a missing file.
+        code: u16,
+        /// The message of the error, without the trailing nul.
+        message: &'a [u8],
+    },
+    /// The acknowledgement of the options of a request.
+    OptionAck(MtftpOptionAck<'a>),
+    /// A block of the file, with a 64-bit block number.
+    Data8 {
+        /// The number of the block, starting at 1.
+        block: u64,
+        /// The data of the block.
+        data: &'a [u8],
+    },
+    /// The acknowledgement of blocks of the file, with 64-bit block
+    /// numbers.
+    Ack8 {
+        /// The number of the acknowledged block.
+        block: u64,
+    },
+    /// Any other packet.
+    Other {
+        /// The opcode of the packet.
+        opcode: u16,
+        /// The data following the opcode.
+        data: &'a [u8],
+    },
+}
+
+impl<'a> MtftpPacket<'a> {
+    /// Opcode of a read request.
+    pub const OPCODE_RRQ: u16 = 1;
+    /// Opcode of a write request.
+    pub const OPCODE_WRQ: u16 = 2;
+    /// Opcode of [`Self::Data`].
+    pub const OPCODE_DATA: u16 = 3;
+    /// Opcode of [`Self::Ack`].
+    pub const OPCODE_ACK: u16 = 4;
+    /// Opcode of [`Self::Error`].
+    pub const OPCODE_ERROR: u16 = 5;
+    /// Opcode of [`Self::OptionAck`].
+    pub const OPCODE_OACK: u16 = 6;
+    /// Opcode of a directory request.
+    pub const OPCODE_DIR: u16 = 7;
+    /// Opcode of [`Self::Data8`].
+    pub const OPCODE_DATA8: u16 = 8;
+    /// Opcode of [`Self::Ack8`].
+    pub const OPCODE_ACK8: u16 = 9;
+
+    /// Parse the packet in `bytes`, or return `None` if it is shorter than
+    /// an opcode.
+    #[must_use]
+    pub fn parse(bytes: &'a [u8]) -> Option<Self> {
+        let (opcode, data) = split_be16(bytes)?;
+        let other = Self::Other { opcode, data };
+        let packet = match opcode {
+            Self::OPCODE_DATA => split_be16(data).map(|(block, data)| Self::Data { block, data }),
+            Self::OPCODE_ACK => split_be16(data).map(|(block, _)| Self::Ack { block }),
+            Self::OPCODE_ERROR => split_be16(data).map(|(code, message)| Self::Error {
+                code,
+                message: message.split(|&b| b == 0).next().unwrap_or_default(),
+            }),
+            Self::OPCODE_OACK => Some(Self::OptionAck(MtftpOptionAck { data })),
+            Self::OPCODE_DATA8 => split_be64(data).map(|(block, data)| Self::Data8 { block, data }),
+            Self::OPCODE_ACK8 => split_be64(data).map(|(block, _)| Self::Ack8 { block }),
+            _ => None,
+        };
+        Some(packet.unwrap_or(other))
+    }
+
+    /// Get the opcode of the packet.
+    #[must_use]
+    pub const fn opcode(&self) -> u16 {
+        match self {
+            Self::Data { .. } => Self::OPCODE_DATA,
+            Self::Ack { .. } => Self::OPCODE_ACK,
+            Self::Error { .. } => Self::OPCODE_ERROR,
+            Self::OptionAck(_) => Self::OPCODE_OACK,
+            Self::Data8 { .. } => Self::OPCODE_DATA8,
+            Self::Ack8 { .. } => Self::OPCODE_ACK8,
+            Self::Other { opcode, .. } => *opcode,
+        }
+    }
+}
+
+fn split_be16(bytes: &[u8]) -> Option<(u16, &[u8])> {
+    match *bytes {
+        [b0, b1, ref rest @ ..] => Some((u16::from_be_bytes([b0, b1]), rest)),
+        _ => None,
+    }
+}
+
+fn split_be64(bytes: &[u8]) -> Option<(u64, &[u8])> {
+    let value = bytes.get(..8)?.try_into().ok()?;
+    Some((u64::from_be_bytes(value), &bytes[8..]))
+}
+
+/// The options acknowledged by the server, as `(name, value)` pairs
+/// without the trailing nuls.
+///
+/// The iteration stops at the first pair that isn't nul-terminated.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct MtftpOptionAck<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> MtftpOptionAck<'a> {
+    /// Get the value of the option `name`, which is compared
+    /// case-insensitively.
+    #[must_use]
+    pub fn value(&self, name: &[u8]) -> Option<&'a [u8]> {
+        self.into_iter()
+            .find(|(option, _)| option.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    /// Get the file size acknowledged with the `tsize` option.
+    #[must_use]
+    pub fn transfer_size(&self) -> Option<u64> {
+        let value = self.value(b"tsize")?;
+        core::str::from_utf8(value).ok()?.parse().ok()
+    }
+}
+
+impl<'a> IntoIterator for MtftpOptionAck<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+    type IntoIter = MtftpOptionAckIter<'a>;
+
+    fn into_iter(self) -> MtftpOptionAckIter<'a> {
+        MtftpOptionAckIter { data: self.data }
+    }
+}
+
+impl fmt::Debug for MtftpOptionAck<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(*self).finish()
+    }
+}
+
+/// Iterator over the options of a [`MtftpOptionAck`].
+#[derive(Debug, Clone)]
+pub struct MtftpOptionAckIter<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for MtftpOptionAckIter<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut split = self.data.splitn(3, |&b| b == 0);
+        match (split.next(), split.next(), split.next()) {
+            (Some(name), Some(value), Some(rest)) => {
+                self.data = rest;
+                Some((name, value))
+            }
+            _ => {
+                self.data = &[];
+                None
+            }
+        }
+    }
+}
+
+/// A TFTP packet allocated by the driver, freed when this is dropped.
+pub struct MtftpPacketBuf<'boot> {
+    boot_services: &'boot BootServices,
+    packet: NonNull<u8>,
+    len: usize,
+}
+
+impl MtftpPacketBuf<'_> {
+    /// Parse the packet.
+    #[must_use]
+    pub fn packet(&self) -> Option<MtftpPacket<'_>> {
+        MtftpPacket::parse(self)
+    }
+}
+
+impl Deref for MtftpPacketBuf<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.packet.as_ptr(), self.len) }
+    }
+}
+
+impl fmt::Debug for MtftpPacketBuf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.packet().fmt(f)
+    }
+}
+
+impl Drop for MtftpPacketBuf<'_> {
+    fn drop(&mut self) {
+        // Ignore the result, we can't do anything about an error here.
+        let _ = self.boot_services.free_pool(self.packet.as_ptr());
+    }
+}
+
+/// Iterate over the `count` option names at `names`.
+unsafe fn option_names<'a>(
+    count: u8,
+    names: *const *const Char8,
+) -> impl Iterator<Item = &'a CStr8> {
+    let names = if names.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(names, usize::from(count))
+    };
+    names.iter().map(|&name| unsafe { CStr8::from_ptr(name) })
+}
+
+/// Formats the option names of a mode data structure as a list.
+struct DebugOptionNames(u8, *const *const Char8);
+
+impl fmt::Debug for DebugOptionNames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(unsafe { option_names(self.0, self.1) })
+            .finish()
+    }
+}
+
+/// The `tsize` option with a zero value, asking the server for the size
+/// of the file.
+const TSIZE_OPTION: MtftpOption<'static> = MtftpOption::new(
+    unsafe { CStr8::from_bytes_with_nul_unchecked(b"tsize\0") },
+    unsafe { CStr8::from_bytes_with_nul_unchecked(b"0\0") },
+);
+
+/// The closure passed to the transfer functions.
+type Progress<'a> = &'a mut dyn FnMut(MtftpPacket<'_>) -> Result;
+
+/// Callback called by the driver before processing a received packet.
+type CheckPacketFn<P> = unsafe extern "efiapi" fn(
+    this: *mut P,
+    token: *mut Token<P>,
+    packet_len: u16,
+    packet: *const u8,
+) -> Status;
+
+/// A transfer request of the protocol `P`.
+///
+/// The corresponding C types are `EFI_MTFTP4_TOKEN` and `EFI_MTFTP6_TOKEN`.
+#[repr(C)]
+struct Token<P> {
+    status: Status,
+    event: Option<Event>,
+    override_data: *const c_void,
+    filename: *const Char8,
+    mode_str: *const Char8,
+    option_count: u32,
+    option_list: *const MtftpOption<'static>,
+    buffer_size: u64,
+    buffer: *mut c_void,
+    context: *mut c_void,
+    check_packet: Option<CheckPacketFn<P>>,
+    timeout_callback: usize,
+    packet_needed: usize,
+}
+
+/// A transfer function of the protocol `P`.
+type TransferFn<P> = unsafe extern "efiapi" fn(this: *mut P, token: *mut Token<P>) -> Status;
+
+/// Run a blocking transfer of the file `filename` with `transfer`, calling
+/// `progress` with each received packet. Return the status of the transfer,
+/// and the size of the transferred data or the required buffer size. There
+/// can be at most `u32::MAX` options.
+fn run_transfer<P>(
+    this: *mut P,
+    transfer: TransferFn<P>,
+    filename: Option<&CStr8>,
+    options: &[MtftpOption<'_>],
+    buffer: *mut u8,
+    buffer_size: usize,
+    mut progress: impl FnMut(MtftpPacket<'_>) -> Result,
+) -> (Status, u64) {
+    let option_count = match u32::try_from(options.len()) {
+        Ok(count) => count,
+        Err(_) => return (Status::BAD_BUFFER_SIZE, 0),
+    };
+    let mut progress: Progress = &mut progress;
+    let mut token = Token {
+        status: Status::SUCCESS,
+        // Without an event, the transfer completes before returning.
+        event: None,
+        override_data: ptr::null(),
+        filename: filename.map_or(ptr::null(), CStr8::as_ptr),
+        mode_str: ptr::null(),
+        option_count,
+        option_list: options.as_ptr().cast(),
+        buffer_size: buffer_size as u64,
+        buffer: buffer.cast(),
+        context: (&mut progress as *mut Progress).cast(),
+        check_packet: Some(check_packet::<P>),
+        timeout_callback: 0,
+        packet_needed: 0,
+    };
+    let status = unsafe { transfer(this, &mut token) };
+    (status, token.buffer_size)
+}
+
+unsafe extern "efiapi" fn check_packet<P>(
+    _this: *mut P,
+    token: *mut Token<P>,
+    packet_len: u16,
+    packet: *const u8,
+) -> Status {
+    let progress = (*token).context.cast::<Progress>();
+    let packet = slice::from_raw_parts(packet, usize::from(packet_len));
+    match (progress.as_mut(), MtftpPacket::parse(packet)) {
+        (Some(progress), Some(packet)) => match progress(packet) {
+            Ok(()) => Status::SUCCESS,
+            Err(err) => err.status(),
+        },
+        _ => Status::SUCCESS,
+    }
+}
+
+/// Convert the result of a download to a `Result`, with the required
+/// buffer size as error data if the buffer is too small.
+fn download_result((status, size): (Status, u64)) -> Result<u64, Option<u64>> {
+    status.into_with(
+        || size,
+        |status| (status == Status::BUFFER_TOO_SMALL).then_some(size),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_packets() {
+        assert_eq!(
+            MtftpPacket::parse(&[0, 3, 0, 7, b'a', b'b']),
+            Some(MtftpPacket::Data {
+                block: 7,
+                data: b"ab"
+            })
+        );
+        assert_eq!(
+            MtftpPacket::parse(&[0, 4, 1, 0]),
+            Some(MtftpPacket::Ack { block: 256 })
+        );
+        assert_eq!(
+            MtftpPacket::parse(b"\0\x05\0\x01not found\0"),
+            Some(MtftpPacket::Error {
+                code: 1,
+                message: b"not found"
+            })
+        );
+        assert_eq!(
+            MtftpPacket::parse(&[0, 9, 0, 0, 0, 0, 0, 0, 1, 2]),
+            Some(MtftpPacket::Ack8 { block: 0x102 })
+        );
+        // Truncated block number.
+        assert_eq!(
+            MtftpPacket::parse(&[0, 3, 1]),
+            Some(MtftpPacket::Other {
+                opcode: 3,
+                data: &[1]
+            })
+        );
+        assert_eq!(MtftpPacket::parse(&[0]), None);
+    }
+
+    #[test]
+    fn test_option_ack() {
+        let packet = MtftpPacket::parse(b"\0\x06blksize\x001024\0TSIZE\x0012345\0trunc").unwrap();
+        let oack = match packet {
+            MtftpPacket::OptionAck(oack) => oack,
+            _ => panic!("unexpected packet: {packet:?}"),
+        };
+        assert!(oack
+            .into_iter()
+            .eq([(&b"blksize"[..], &b"1024"[..]), (b"TSIZE", b"12345")]));
+        assert_eq!(oack.value(b"BLKSIZE"), Some(&b"1024"[..]));
+        assert_eq!(oack.value(b"trunc"), None);
+        assert_eq!(oack.transfer_size(), Some(12345));
+    }
+
+    #[test]
+    fn test_option() {
+        assert_eq!(TSIZE_OPTION.name().to_bytes(), b"tsize");
+        assert_eq!(TSIZE_OPTION.value().to_bytes(), b"0");
+    }
+}
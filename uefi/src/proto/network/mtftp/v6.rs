@@ -0,0 +1,326 @@
+//! MTFTPv6 protocol.
+
+use super::{
+    download_result, option_names, run_transfer, DebugOptionNames, MtftpOption, MtftpPacket,
+    MtftpPacketBuf, Token, TSIZE_OPTION,
+};
+use crate::proto::unsafe_protocol;
+use crate::table::boot::BootServices;
+use crate::{CStr8, Char8, Result, Status};
+use core::ffi::c_void;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ptr::{self, NonNull};
+
+/// The MTFTPv6 protocol.
+///
+/// The corresponding C type is `EFI_MTFTP6_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol(
+    "bf0a78ba-ec29-49cf-a1c9-7ae54eab6a51",
+    service_binding = "d9760ff3-3cca-4267-80f9-7527fafa4223"
+)]
+pub struct Mtftp6 {
+    get_mode_data:
+        unsafe extern "efiapi" fn(this: *const Self, mode_data: *mut Mtftp6ModeData) -> Status,
+    configure:
+        unsafe extern "efiapi" fn(this: *mut Self, config_data: *const Mtftp6ConfigData) -> Status,
+    get_info: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        override_data: *const c_void,
+        filename: *const Char8,
+        mode_str: *const Char8,
+        option_count: u8,
+        option_list: *const MtftpOption<'_>,
+        packet_length: *mut u32,
+        packet: *mut *mut u8,
+    ) -> Status,
+    parse_options: usize,
+    read_file: unsafe extern "efiapi" fn(this: *mut Self, token: *mut Token<Self>) -> Status,
+    write_file: unsafe extern "efiapi" fn(this: *mut Self, token: *mut Token<Self>) -> Status,
+    read_directory: unsafe extern "efiapi" fn(this: *mut Self, token: *mut Token<Self>) -> Status,
+    poll: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+}
+
+impl Mtftp6 {
+    /// Get the configuration of this instance and the options supported by
+    /// the driver.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    pub fn mode_data(&self) -> Result<Mtftp6ModeData> {
+        let mut mode_data = MaybeUninit::uninit();
+        unsafe { (self.get_mode_data)(self, mode_data.as_mut_ptr()) }
+            .into_with_val(|| unsafe { mode_data.assume_init() })
+    }
+
+    /// Configure this instance with `config_data`, or reset it if
+    /// `config_data` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `config_data` is invalid.
+    /// * [`Status::ACCESS_DENIED`]: the instance is already configured, or
+    ///   a transfer is running.
+    /// * [`Status::NO_MAPPING`]: the local address is not ready.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   configure the instance.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn configure(&mut self, config_data: Option<&Mtftp6ConfigData>) -> Result {
+        let config_data = config_data.map_or(ptr::null(), |data| data as *const _);
+        unsafe { (self.configure)(self, config_data) }.into()
+    }
+
+    /// Send a read request for `filename` with `options`, and return the
+    /// reply of the server, usually an [`MtftpPacket::OptionAck`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: an option is invalid.
+    /// * [`Status::BAD_BUFFER_SIZE`]: more than 255 options are given.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::NO_MAPPING`]: the local address is not ready.
+    /// * [`Status::UNSUPPORTED`]: an option is not supported by the driver.
+    /// * [`Status::TFTP_ERROR`]: the server replied with an error.
+    /// * [`Status::TIMEOUT`]: the server did not reply.
+    /// * [`Status::ICMP_ERROR`]: an ICMP error was received.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn get_info<'boot>(
+        &mut self,
+        bt: &'boot BootServices,
+        filename: &CStr8,
+        options: &[MtftpOption<'_>],
+    ) -> Result<MtftpPacketBuf<'boot>> {
+        let option_count = u8::try_from(options.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let mut len = 0;
+        let mut packet = ptr::null_mut();
+        unsafe {
+            (self.get_info)(
+                self,
+                ptr::null(),
+                filename.as_ptr(),
+                ptr::null(),
+                option_count,
+                options.as_ptr(),
+                &mut len,
+                &mut packet,
+            )
+        }
+        .into_with_val(|| NonNull::new(packet))?
+        .map(|packet| MtftpPacketBuf {
+            boot_services: bt,
+            packet,
+            len: len as usize,
+        })
+        .ok_or_else(|| Status::NOT_FOUND.into())
+    }
+
+    /// Get the size of `filename` with the `tsize` option.
+    ///
+    /// # Errors
+    ///
+    /// See [`get_info`]. In addition:
+    ///
+    /// * [`Status::UNSUPPORTED`]: the server did not send the size.
+    ///
+    /// [`get_info`]: Self::get_info
+    pub fn file_size(&mut self, bt: &BootServices, filename: &CStr8) -> Result<u64> {
+        let reply = self.get_info(bt, filename, &[TSIZE_OPTION])?;
+        match reply.packet() {
+            Some(MtftpPacket::OptionAck(oack)) => oack.transfer_size(),
+            _ => None,
+        }
+        .ok_or_else(|| Status::UNSUPPORTED.into())
+    }
+
+    /// Download `filename` with `options` into `buffer`, and return the size
+    /// of the file.
+    ///
+    /// `progress` is called with each packet received from the server.
+    /// Returning an error from it aborts the transfer with the error's
+    /// status. If `buffer` is `None`, the file is only passed to `progress`
+    /// in [`MtftpPacket::Data`] packets.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buffer` is too small. The required
+    ///   size is returned in the error data, if known.
+    /// * [`Status::ACCESS_DENIED`]: a transfer is already running.
+    /// * [`Status::BAD_BUFFER_SIZE`]: more than `u32::MAX` options are given.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::NO_MAPPING`]: the local address is not ready.
+    /// * [`Status::UNSUPPORTED`]: an option is not supported by the driver.
+    /// * [`Status::TFTP_ERROR`]: the server replied with an error.
+    /// * [`Status::TIMEOUT`]: the server did not reply.
+    /// * [`Status::ABORTED`]: the transfer was aborted.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn read_file(
+        &mut self,
+        filename: &CStr8,
+        options: &[MtftpOption<'_>],
+        buffer: Option<&mut [u8]>,
+        progress: impl FnMut(MtftpPacket<'_>) -> Result,
+    ) -> Result<u64, Option<u64>> {
+        let read_file = self.read_file;
+        let (buffer, size) =
+            buffer.map_or((ptr::null_mut(), 0), |buf| (buf.as_mut_ptr(), buf.len()));
+        download_result(run_transfer(
+            self,
+            read_file,
+            Some(filename),
+            options,
+            buffer,
+            size,
+            progress,
+        ))
+    }
+
+    /// Upload `data` to `filename` with `options`.
+    ///
+    /// `progress` is called with each packet received from the server.
+    /// Returning an error from it aborts the transfer with the error's
+    /// status.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: a transfer is already running.
+    /// * [`Status::BAD_BUFFER_SIZE`]: more than `u32::MAX` options are given.
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::NO_MAPPING`]: the local address is not ready.
+    /// * [`Status::UNSUPPORTED`]: an option is not supported by the driver.
+    /// * [`Status::TFTP_ERROR`]: the server replied with an error.
+    /// * [`Status::TIMEOUT`]: the server did not reply.
+    /// * [`Status::ABORTED`]: the transfer was aborted.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn write_file(
+        &mut self,
+        filename: &CStr8,
+        options: &[MtftpOption<'_>],
+        data: &[u8],
+        progress: impl FnMut(MtftpPacket<'_>) -> Result,
+    ) -> Result {
+        let write_file = self.write_file;
+        let (status, _) = run_transfer(
+            self,
+            write_file,
+            Some(filename),
+            options,
+            data.as_ptr() as *mut u8,
+            data.len(),
+            progress,
+        );
+        status.into()
+    }
+
+    /// Download the listing of the directory `filename`, or of the server's
+    /// default directory if `filename` is `None`, into `buffer`. Return the
+    /// size of the listing.
+    ///
+    /// The format of the listing depends on the server. `progress` behaves
+    /// as in [`read_file`].
+    ///
+    /// [`read_file`]: Self::read_file
+    ///
+    /// # Errors
+    ///
+    /// See [`read_file`].
+    pub fn read_directory(
+        &mut self,
+        filename: Option<&CStr8>,
+        options: &[MtftpOption<'_>],
+        buffer: &mut [u8],
+        progress: impl FnMut(MtftpPacket<'_>) -> Result,
+    ) -> Result<u64, Option<u64>> {
+        let read_directory = self.read_directory;
+        download_result(run_transfer(
+            self,
+            read_directory,
+            filename,
+            options,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            progress,
+        ))
+    }
+
+    /// Poll the network controller for packets, to speed up transfers.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_STARTED`]: this instance is not configured.
+    /// * [`Status::NOT_READY`]: no packet was received.
+    /// * [`Status::TIMEOUT`]: the controller timed out.
+    /// * [`Status::DEVICE_ERROR`]: the network controller failed.
+    pub fn poll(&mut self) -> Result {
+        unsafe { (self.poll)(self) }.into()
+    }
+}
+
+/// Configuration of a [`Mtftp6`] instance.
+///
+/// The corresponding C type is `EFI_MTFTP6_CONFIG_DATA`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct Mtftp6ConfigData {
+    /// The local address, or `[0; 16]` to let the driver choose one.
+    pub station_ip: [u8; 16],
+    /// The local port, or 0 to use any port.
+    pub local_port: u16,
+    /// The address of the server.
+    pub server_ip: [u8; 16],
+    /// The port on which the server listens for requests.
+    pub initial_server_port: u16,
+    /// Number of times a packet is sent before giving up.
+    pub try_count: u16,
+    /// Time to wait for a reply before resending a packet, in seconds.
+    pub timeout_value: u16,
+}
+
+impl Mtftp6ConfigData {
+    /// Create a configuration talking to `server_ip` on port 69, from an
+    /// address chosen by the driver. Packets are sent up to 5 times, with a
+    /// 3 s timeout.
+    #[must_use]
+    pub const fn new(server_ip: [u8; 16]) -> Self {
+        Self {
+            station_ip: [0; 16],
+            local_port: 0,
+            server_ip,
+            initial_server_port: 69,
+            try_count: 5,
+            timeout_value: 3,
+        }
+    }
+}
+
+/// Configuration of a [`Mtftp6`] instance, and options supported by the
+/// driver.
+///
+/// The corresponding C type is `EFI_MTFTP6_MODE_DATA`.
+#[repr(C)]
+pub struct Mtftp6ModeData {
+    /// The configuration of the instance.
+    pub config_data: Mtftp6ConfigData,
+    supported_option_count: u8,
+    supported_options: *const *const Char8,
+}
+
+impl Mtftp6ModeData {
+    /// Get the names of the options supported by the driver.
+    pub fn supported_options(&self) -> impl Iterator<Item = &CStr8> {
+        unsafe { option_names(self.supported_option_count, self.supported_options) }
+    }
+}
+
+impl fmt::Debug for Mtftp6ModeData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mtftp6ModeData")
+            .field("config_data", &self.config_data)
+            .field(
+                "supported_options",
+                &DebugOptionNames(self.supported_option_count, self.supported_options),
+            )
+            .finish()
+    }
+}
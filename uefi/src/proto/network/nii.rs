@@ -0,0 +1,333 @@
+//! Network interface identifier protocol.
+//!
+//! The [`NetworkInterfaceIdentifier`] protocol (NII) is installed by network
+//! drivers built on a universal network driver interface (UNDI), and gives
+//! access to the description of that interface.
+
+use crate::proto::unsafe_protocol;
+use bitflags::bitflags;
+use core::slice;
+
+/// The network interface identifier protocol, version 3.1.
+///
+/// The corresponding C type is `EFI_NETWORK_INTERFACE_IDENTIFIER_PROTOCOL`.
+#[derive(Debug)]
+#[repr(C)]
+#[unsafe_protocol("1aced566-76ed-4218-bc81-767f1f977a89")]
+pub struct NetworkInterfaceIdentifier {
+    revision: u64,
+    id: u64,
+    image_addr: u64,
+    image_size: u32,
+    string_id: [u8; 4],
+    interface_type: NetworkInterfaceType,
+    major_ver: u8,
+    minor_ver: u8,
+    ipv6_supported: bool,
+    if_num: u16,
+}
+
+impl NetworkInterfaceIdentifier {
+    /// Get the revision of the protocol.
+    #[must_use]
+    pub const fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Get the address of the `!PXE` structure describing the UNDI.
+    #[must_use]
+    pub const fn undi_address(&self) -> u64 {
+        self.id
+    }
+
+    /// Get the `!PXE` structure describing the UNDI, or `None` if it is
+    /// missing or invalid.
+    #[must_use]
+    pub fn undi(&self) -> Option<&Undi> {
+        let undi = unsafe { (self.id as usize as *const Undi).as_ref() }?;
+        undi.is_valid().then_some(undi)
+    }
+
+    /// Get the address of the UNDI image, or 0 if the UNDI is implemented in
+    /// hardware.
+    #[must_use]
+    pub const fn image_address(&self) -> u64 {
+        self.image_addr
+    }
+
+    /// Get the size of the UNDI image in bytes.
+    #[must_use]
+    pub const fn image_size(&self) -> u32 {
+        self.image_size
+    }
+
+    /// Get the identifier of the interface, such as `b"UNDI"`.
+    #[must_use]
+    pub const fn string_id(&self) -> [u8; 4] {
+        self.string_id
+    }
+
+    /// Get the type of the interface.
+    #[must_use]
+    pub const fn interface_type(&self) -> NetworkInterfaceType {
+        self.interface_type
+    }
+
+    /// Get the version of the interface, as a `(major, minor)` pair.
+    #[must_use]
+    pub const fn version(&self) -> (u8, u8) {
+        (self.major_ver, self.minor_ver)
+    }
+
+    /// Whether the interface supports IPv6.
+    #[must_use]
+    pub const fn ipv6_supported(&self) -> bool {
+        self.ipv6_supported
+    }
+
+    /// Get the number of the network interface among the interfaces driven
+    /// by the UNDI.
+    #[must_use]
+    pub const fn interface_number(&self) -> u16 {
+        self.if_num
+    }
+}
+
+newtype_enum! {
+    /// Type of a network interface.
+    pub enum NetworkInterfaceType: u8 => {
+        /// A universal network driver interface.
+        UNDI = 1,
+    }
+}
+
+bitflags! {
+    /// Features of an UNDI.
+    #[repr(transparent)]
+    pub struct UndiImplementation: u32 {
+        /// Commands complete with an interrupt.
+        const CMD_COMPLETE_INT_SUPPORTED = 0x0000_0001;
+        /// Received packets trigger an interrupt.
+        const PACKET_RX_INT_SUPPORTED = 0x0000_0002;
+        /// Transmitted packets trigger an interrupt.
+        const TX_COMPLETE_INT_SUPPORTED = 0x0000_0004;
+        /// Software interrupts are supported.
+        const SOFTWARE_INT_SUPPORTED = 0x0000_0008;
+        /// Multicast packets can be filtered.
+        const FILTERED_MULTICAST_RX_SUPPORTED = 0x0000_0010;
+        /// Broadcast packets can be received.
+        const BROADCAST_RX_SUPPORTED = 0x0000_0020;
+        /// All packets can be received.
+        const PROMISCUOUS_RX_SUPPORTED = 0x0000_0040;
+        /// All multicast packets can be received.
+        const PROMISCUOUS_MULTICAST_RX_SUPPORTED = 0x0000_0080;
+        /// The station address can be changed.
+        const STATION_ADDR_SETTABLE = 0x0000_0100;
+        /// Statistics are collected.
+        const STATISTICS_SUPPORTED = 0x0000_0200;
+        /// Mask of the non-volatile data support.
+        const NVDATA_SUPPORT_MASK = 0x0000_0c00;
+        /// Several frames can be transmitted by one command.
+        const MULTI_FRAME_SUPPORTED = 0x0000_1000;
+        /// Commands can be queued.
+        const CMD_QUEUE_SUPPORTED = 0x0000_2000;
+        /// Commands can be linked.
+        const CMD_LINK_SUPPORTED = 0x0000_4000;
+        /// Fragmented transmit buffers are supported.
+        const FRAG_SUPPORTED = 0x0000_8000;
+        /// The device can access 64-bit addresses.
+        const DEVICE_64BIT = 0x0001_0000;
+        /// The entry point of the software UNDI is a virtual address.
+        const SW_VIRT_ADDR = 0x4000_0000;
+        /// The UNDI is implemented in hardware.
+        const HW_UNDI = 0x8000_0000;
+    }
+}
+
+/// The `!PXE` structure describing an UNDI.
+///
+/// This is the header shared by the `PXE_HW_UNDI` and `PXE_SW_UNDI` C types.
+/// The fields specific to software UNDIs are available through
+/// [`entry_point`] and [`bus_types`].
+///
+/// [`entry_point`]: Self::entry_point
+/// [`bus_types`]: Self::bus_types
+#[derive(Debug)]
+#[repr(C)]
+pub struct Undi {
+    signature: [u8; 4],
+    len: u8,
+    fudge: u8,
+    rev: u8,
+    if_cnt: u8,
+    major_ver: u8,
+    minor_ver: u8,
+    if_cnt_ext: u8,
+    reserved: u8,
+    implementation: UndiImplementation,
+}
+
+impl Undi {
+    /// Signature of the structure.
+    pub const SIGNATURE: [u8; 4] = *b"!PXE";
+
+    /// Size of the software UNDI fields, up to the first bus type.
+    const SW_UNDI_LEN: usize = 28;
+
+    /// Check the signature, length and checksum of the structure.
+    fn is_valid(&self) -> bool {
+        self.signature == Self::SIGNATURE
+            && usize::from(self.len) >= core::mem::size_of::<Self>()
+            && self
+                .as_bytes()
+                .iter()
+                .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+                == 0
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts((self as *const Self).cast(), usize::from(self.len)) }
+    }
+
+    /// Get the revision of the structure.
+    #[must_use]
+    pub const fn revision(&self) -> u8 {
+        self.rev
+    }
+
+    /// Get the number of network interfaces driven by the UNDI.
+    #[must_use]
+    pub const fn interface_count(&self) -> u32 {
+        // The fields hold the number of interfaces minus one.
+        u16::from_le_bytes([self.if_cnt, self.if_cnt_ext]) as u32 + 1
+    }
+
+    /// Get the version of the UNDI, as a `(major, minor)` pair.
+    #[must_use]
+    pub const fn version(&self) -> (u8, u8) {
+        (self.major_ver, self.minor_ver)
+    }
+
+    /// Get the features of the UNDI.
+    #[must_use]
+    pub const fn implementation(&self) -> UndiImplementation {
+        self.implementation
+    }
+
+    /// Whether the UNDI is implemented in hardware.
+    #[must_use]
+    pub const fn is_hardware(&self) -> bool {
+        self.implementation.contains(UndiImplementation::HW_UNDI)
+    }
+
+    /// Get the software UNDI fields following the header, if any.
+    fn sw_fields(&self) -> Option<&[u8]> {
+        let bytes = self.as_bytes();
+        if self.is_hardware() || bytes.len() < Self::SW_UNDI_LEN {
+            None
+        } else {
+            Some(&bytes[core::mem::size_of::<Self>()..])
+        }
+    }
+
+    /// Get the address of the entry point of a software UNDI, or `None` for
+    /// a hardware UNDI.
+    #[must_use]
+    pub fn entry_point(&self) -> Option<u64> {
+        let fields = self.sw_fields()?;
+        Some(u64::from_le_bytes(fields[..8].try_into().unwrap()))
+    }
+
+    /// Get the types of the buses supported by a software UNDI, such as
+    /// `b"PCIR"` for PCI, or `None` for a hardware UNDI.
+    #[must_use]
+    pub fn bus_types(&self) -> Option<impl Iterator<Item = [u8; 4]> + '_> {
+        let fields = self.sw_fields()?;
+        let bus_count = usize::from(fields[11]);
+        let bus_types = &fields[12..];
+        let bus_count = bus_count.min(bus_types.len() / 4);
+        Some(
+            bus_types[..bus_count * 4]
+                .chunks_exact(4)
+                .map(|bus_type| bus_type.try_into().unwrap()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::marker::PhantomData;
+
+    #[repr(C, align(8))]
+    struct Buffer([u8; 36]);
+
+    /// Build a software UNDI structure supporting PCI and PC Card buses.
+    fn sw_undi() -> Buffer {
+        let mut bytes = [0; 36];
+        bytes[..4].copy_from_slice(b"!PXE");
+        bytes[4] = 36;
+        bytes[6] = 3;
+        bytes[7] = 1;
+        bytes[8] = 3;
+        bytes[9] = 1;
+        bytes[12..16].copy_from_slice(&0x0000_0260u32.to_le_bytes());
+        bytes[16..24].copy_from_slice(&0x1234_5678u64.to_le_bytes());
+        bytes[27] = 2;
+        bytes[28..32].copy_from_slice(b"PCIR");
+        bytes[32..36].copy_from_slice(b"PCCR");
+        let sum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        bytes[5] = 0u8.wrapping_sub(sum);
+        Buffer(bytes)
+    }
+
+    fn nii(id: u64) -> NetworkInterfaceIdentifier {
+        NetworkInterfaceIdentifier {
+            revision: 0x0002_0000,
+            id,
+            image_addr: 0,
+            image_size: 0,
+            string_id: *b"UNDI",
+            interface_type: NetworkInterfaceType::UNDI,
+            major_ver: 3,
+            minor_ver: 1,
+            ipv6_supported: true,
+            if_num: 0,
+            _no_send_or_sync: PhantomData,
+        }
+    }
+
+    #[test]
+    fn test_sw_undi() {
+        let buffer = sw_undi();
+        let nii = nii(buffer.0.as_ptr() as u64);
+        assert_eq!(nii.string_id(), *b"UNDI");
+        assert_eq!(nii.version(), (3, 1));
+
+        let undi = nii.undi().unwrap();
+        assert_eq!(undi.revision(), 3);
+        assert_eq!(undi.interface_count(), 2);
+        assert_eq!(
+            undi.implementation(),
+            UndiImplementation::STATISTICS_SUPPORTED
+                | UndiImplementation::BROADCAST_RX_SUPPORTED
+                | UndiImplementation::PROMISCUOUS_RX_SUPPORTED
+        );
+        assert!(!undi.is_hardware());
+        assert_eq!(undi.entry_point(), Some(0x1234_5678));
+        assert!(undi.bus_types().unwrap().eq([*b"PCIR", *b"PCCR"]));
+    }
+
+    #[test]
+    fn test_invalid_undi() {
+        assert!(nii(0).undi().is_none());
+
+        let mut buffer = sw_undi();
+        buffer.0[5] ^= 1;
+        assert!(nii(buffer.0.as_ptr() as u64).undi().is_none());
+
+        let mut buffer = sw_undi();
+        buffer.0[0] = b'?';
+        assert!(nii(buffer.0.as_ptr() as u64).undi().is_none());
+    }
+}
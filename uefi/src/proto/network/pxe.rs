@@ -15,6 +15,7 @@ use ptr_meta::Pointee;
 
 use crate::{CStr8, Char8, Result, Status};
 
+use super::dhcp4::Dhcp4Options;
 use super::{IpAddress, MacAddress};
 
 /// PXE Base Code protocol
@@ -1011,6 +1012,12 @@ impl DhcpV4Packet {
     pub const fn dhcp_magik(&self) -> u32 {
         u32::from_be(self.dhcp_magik)
     }
+
+    /// Iterate over the DHCP options in [`Self::dhcp_options`].
+    #[must_use]
+    pub const fn options(&self) -> Dhcp4Options<'_> {
+        Dhcp4Options::new(&self.dhcp_options)
+    }
 }
 
 bitflags! {
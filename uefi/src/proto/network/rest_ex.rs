@@ -0,0 +1,819 @@
+//! REST EX protocol.
+//!
+//! The REST EX protocol sends HTTP requests to a REST service, such as the
+//! Redfish service of a baseboard management controller. The service may be
+//! reached through the network (in band), or through a dedicated channel to
+//! the BMC (out of band); the protocol hides the difference.
+//!
+//! Like the other network protocols, each consumer creates its own REST EX
+//! instance with the [`ServiceBindingProtocol<RestEx>`], configures it if
+//! the service requires it, then sends requests. Requests are either
+//! synchronous with [`RestEx::send_receive`], or asynchronous with a
+//! [`RestExToken`] whose [`Event`] is signaled by the driver once the
+//! response is received.
+//!
+//! [`ServiceBindingProtocol<RestEx>`]: crate::proto::service_binding::ServiceBindingProtocol
+
+use super::http::{
+    HttpConfigData, HttpHeader, HttpMethod, HttpRequestData, HttpRequestMessage, HttpResponse,
+    Httpv4AccessPoint, Httpv6AccessPoint, RawHttpResponse,
+};
+use crate::proto::unsafe_protocol;
+use crate::table::boot::BootServices;
+use crate::{cstr8, CStr16, CStr8, Event, Guid, Result, Status};
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
+use core::{fmt, slice};
+
+/// The REST EX protocol.
+///
+/// Instances of this protocol are created with the
+/// [`ServiceBindingProtocol<RestEx>`] installed on the handle of the REST
+/// service.
+///
+/// The corresponding C type is `EFI_REST_EX_PROTOCOL`.
+///
+/// [`ServiceBindingProtocol<RestEx>`]: crate::proto::service_binding::ServiceBindingProtocol
+#[repr(C)]
+#[unsafe_protocol(
+    "55648b91-e7d0-45c7-a66f-f16cf26a0f05",
+    service_binding = "456bbe01-99d0-45ea-bb5f-16d84bedc559"
+)]
+pub struct RestEx {
+    send_receive: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        request_message: *const HttpRequestMessage<'_>,
+        response_message: *mut RawHttpResponse,
+    ) -> Status,
+    get_service: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        rest_ex_service_info: *mut *mut RestExServiceInfoData,
+    ) -> Status,
+    get_mode_data:
+        unsafe extern "efiapi" fn(this: *mut Self, rest_ex_config_data: *mut *mut u8) -> Status,
+    configure: unsafe extern "efiapi" fn(this: *mut Self, rest_ex_config_data: *const u8) -> Status,
+    async_send_receive: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        request_message: *const HttpRequestMessage<'_>,
+        rest_ex_token: *mut RestExToken,
+        timeout_in_milli_seconds: *const usize,
+    ) -> Status,
+    event_service: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        request_message: *const HttpRequestMessage<'_>,
+        rest_ex_token: *mut RestExToken,
+    ) -> Status,
+}
+
+impl RestEx {
+    /// Send `request` to the REST service, and wait for its response.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `request` is invalid.
+    /// * [`Status::NOT_STARTED`]: this instance requires a configuration,
+    ///   and is not configured.
+    /// * [`Status::ACCESS_DENIED`]: the service rejected the request.
+    /// * [`Status::DEVICE_ERROR`]: the service could not be reached.
+    pub fn send_receive<'boot>(
+        &mut self,
+        bt: &'boot BootServices,
+        request: &HttpRequestMessage<'_>,
+    ) -> Result<HttpResponse<'boot>> {
+        let mut response = RawHttpResponse::new();
+        unsafe {
+            (self.send_receive)(self, request, &mut response)
+                .into_with_val(|| HttpResponse::new(bt, response))
+        }
+    }
+
+    /// Send `json` to `url` with `method`, such as [`HttpMethod::POST`] or
+    /// [`HttpMethod::PATCH`], and wait for the response.
+    ///
+    /// The `Content-Type` and `Content-Length` headers are set for the JSON
+    /// payload.
+    ///
+    /// # Errors
+    ///
+    /// See [`send_receive`](Self::send_receive).
+    pub fn send_json<'boot>(
+        &mut self,
+        bt: &'boot BootServices,
+        method: HttpMethod,
+        url: &CStr16,
+        json: &[u8],
+    ) -> Result<HttpResponse<'boot>> {
+        let mut length_buf = [0; 21];
+        let length = format_length(json.len(), &mut length_buf);
+        let headers = [
+            HttpHeader::new(cstr8!("Content-Type"), cstr8!("application/json")),
+            HttpHeader::new(cstr8!("Content-Length"), length),
+        ];
+        let data = HttpRequestData::new(method, url);
+        let request = HttpRequestMessage::new(&data)
+            .with_headers(&headers)
+            .with_body(json);
+        self.send_receive(bt, &request)
+    }
+
+    /// Get information about the REST service.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the information is not available.
+    pub fn service_info<'boot>(
+        &mut self,
+        bt: &'boot BootServices,
+    ) -> Result<RestExServiceInfo<'boot>> {
+        let mut info = ptr::null_mut();
+        let info = unsafe { (self.get_service)(self, &mut info) }.into_with_val(|| info)?;
+        NonNull::new(info)
+            .map(|info| RestExServiceInfo {
+                boot_services: bt,
+                info,
+            })
+            .ok_or_else(|| Status::UNSUPPORTED.into())
+    }
+
+    /// Get the HTTP configuration of this instance.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_READY`]: this instance is not configured.
+    /// * [`Status::UNSUPPORTED`]: the service is not configured with HTTP,
+    ///   according to [`RestExServiceInfo::config_type`].
+    pub fn http_config_data<'boot>(
+        &mut self,
+        bt: &'boot BootServices,
+    ) -> Result<RestExHttpConfigBuf<'boot>> {
+        if self.service_info(bt)?.config_type() != Some(RestExConfigType::HTTP) {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        let mut config = ptr::null_mut();
+        let config = unsafe { (self.get_mode_data)(self, &mut config) }.into_with_val(|| config)?;
+        NonNull::new(config.cast())
+            .map(|config| RestExHttpConfigBuf {
+                boot_services: bt,
+                config,
+            })
+            .ok_or_else(|| Status::NOT_READY.into())
+    }
+
+    /// Configure this instance with the HTTP `config`, or reset it to the
+    /// unconfigured state if `config` is `None`. Resetting cancels the
+    /// pending asynchronous requests.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `config` is invalid.
+    /// * [`Status::UNSUPPORTED`]: the service doesn't use HTTP
+    ///   configurations, or `config` requests an unsupported feature.
+    pub fn configure(&mut self, config: Option<&RestExHttpConfigData<'_>>) -> Result {
+        let config = config.map_or(ptr::null(), |config| {
+            (config as *const RestExHttpConfigData).cast()
+        });
+        unsafe { (self.configure)(self, config) }.into()
+    }
+
+    /// Send `request` to the REST service without waiting. The event of
+    /// `token` is signaled once the response is received, or after
+    /// `timeout_ms` milliseconds.
+    ///
+    /// If `request` is `None`, the token receives the next part of the
+    /// response of the previous request.
+    ///
+    /// # Safety
+    ///
+    /// `token` and `request` must stay valid until the event of `token` is
+    /// signaled, or the instance is reset.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `request` is invalid.
+    /// * [`Status::UNSUPPORTED`]: asynchronous requests are not supported.
+    /// * [`Status::NOT_STARTED`]: this instance requires a configuration,
+    ///   and is not configured.
+    pub unsafe fn async_send_receive(
+        &mut self,
+        request: Option<&HttpRequestMessage<'_>>,
+        token: &mut RestExToken,
+        timeout_ms: Option<usize>,
+    ) -> Result {
+        let request = request.map_or(ptr::null(), |request| request as *const _);
+        let timeout = timeout_ms
+            .as_ref()
+            .map_or(ptr::null(), |ms| ms as *const usize);
+        (self.async_send_receive)(self, request, token, timeout).into()
+    }
+
+    /// Subscribe to the events of the REST service, such as the Redfish
+    /// event service, with `request`. The event of `token` is signaled for
+    /// each event sent by the service, or once the subscription is
+    /// cancelled with a `None` `request`.
+    ///
+    /// # Safety
+    ///
+    /// `token` and `request` must stay valid until the subscription is
+    /// cancelled, or the instance is reset.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `request` is invalid.
+    /// * [`Status::UNSUPPORTED`]: the event service is not supported.
+    /// * [`Status::ALREADY_STARTED`]: a subscription is already active.
+    pub unsafe fn event_service(
+        &mut self,
+        request: Option<&HttpRequestMessage<'_>>,
+        token: &mut RestExToken,
+    ) -> Result {
+        let request = request.map_or(ptr::null(), |request| request as *const _);
+        (self.event_service)(self, request, token).into()
+    }
+}
+
+/// Format `len` as a null-terminated decimal string in `buf`.
+fn format_length(mut len: usize, buf: &mut [u8; 21]) -> &CStr8 {
+    let mut start = buf.len() - 1;
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (len % 10) as u8;
+        len /= 10;
+        if len == 0 {
+            break;
+        }
+    }
+    // OK to unwrap: the buffer holds digits followed by a null.
+    CStr8::from_bytes_with_nul(&buf[start..]).unwrap()
+}
+
+/// Completion token of an asynchronous request.
+///
+/// The corresponding C type is `EFI_REST_EX_TOKEN`.
+#[repr(C)]
+pub struct RestExToken {
+    event: Event,
+    status: Status,
+    response_message: *mut RawHttpResponse,
+}
+
+impl RestExToken {
+    /// Create a token signaling `event` on completion.
+    ///
+    /// The event must not be of type [`EventType::NOTIFY_SIGNAL`] to be
+    /// waited on, but a notification function can be used instead.
+    ///
+    /// [`EventType::NOTIFY_SIGNAL`]: crate::table::boot::EventType::NOTIFY_SIGNAL
+    #[must_use]
+    pub const fn new(event: Event) -> Self {
+        Self {
+            event,
+            status: Status::NOT_READY,
+            response_message: ptr::null_mut(),
+        }
+    }
+
+    /// Get the event signaled on completion.
+    #[must_use]
+    pub const fn event(&self) -> &Event {
+        &self.event
+    }
+
+    /// Get the result of the request. This is [`Status::NOT_READY`] while
+    /// the request is pending.
+    pub const fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Take the response received by the driver, if the request succeeded,
+    /// and reset the token so that it can be reused.
+    ///
+    /// The response is allocated by the driver, and freed when the returned
+    /// [`HttpResponse`] is dropped.
+    pub fn take_response<'boot>(&mut self, bt: &'boot BootServices) -> Option<HttpResponse<'boot>> {
+        if !self.status.is_success() {
+            return None;
+        }
+        let message = NonNull::new(self.response_message)?;
+        self.response_message = ptr::null_mut();
+        self.status = Status::NOT_READY;
+        unsafe {
+            let raw = message.as_ptr().read();
+            // Ignore the result, we can't do anything about an error here.
+            let _ = bt.free_pool(message.as_ptr().cast());
+            Some(HttpResponse::new(bt, raw))
+        }
+    }
+}
+
+impl fmt::Debug for RestExToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RestExToken")
+            .field("event", &self.event.as_ptr())
+            .field("status", &self.status)
+            .field("response_message", &self.response_message)
+            .finish()
+    }
+}
+
+newtype_enum! {
+    /// The type of a REST service.
+    pub enum RestExServiceType: u8 => {
+        /// A service of an unspecified type.
+        UNSPECIFIC = 1,
+        /// A Redfish service.
+        REDFISH = 2,
+        /// An OData service.
+        ODATA = 3,
+        /// A vendor-specific service, identified by
+        /// [`RestExServiceInfo::vendor_rest_service_name`].
+        VENDOR_SPECIFIC = 0xff,
+    }
+}
+
+newtype_enum! {
+    /// How a REST service is reached.
+    pub enum RestExServiceAccessMode: u8 => {
+        /// Through the network.
+        IN_BAND = 1,
+        /// Through a dedicated channel, such as a BMC interface.
+        OUT_OF_BAND = 2,
+    }
+}
+
+newtype_enum! {
+    /// The type of the configuration of a REST EX instance.
+    pub enum RestExConfigType: u8 => {
+        /// The instance is configured with a [`RestExHttpConfigData`].
+        HTTP = 0,
+        /// The configuration is specific to the driver.
+        UNSPECIFIC = 1,
+    }
+}
+
+/// The C type `EFI_REST_EX_SERVICE_INFO`, in its version 1.0.
+#[derive(Debug)]
+#[repr(C)]
+struct RestExServiceInfoData {
+    length: u32,
+    version_major: u8,
+    version_minor: u8,
+    rest_service_type: RestExServiceType,
+    rest_service_access_mode: RestExServiceAccessMode,
+    vendor_rest_service_name: Guid,
+    vendor_specific_data_length: u32,
+    vendor_specific_data: *const u8,
+    rest_ex_config_type: RestExConfigType,
+    reserved: [u8; 3],
+}
+
+/// Information about a REST service, returned by [`RestEx::service_info`].
+///
+/// The information is allocated by the driver, and freed when this is
+/// dropped.
+pub struct RestExServiceInfo<'boot> {
+    boot_services: &'boot BootServices,
+    info: NonNull<RestExServiceInfoData>,
+}
+
+impl RestExServiceInfo<'_> {
+    /// Size of the version 1.0 of the information.
+    const V1_0_SIZE: u32 = core::mem::size_of::<RestExServiceInfoData>() as u32;
+
+    /// Get the version of the information, as a `(major, minor)` pair.
+    #[must_use]
+    pub fn version(&self) -> (u8, u8) {
+        let info = self.header();
+        (info.version_major, info.version_minor)
+    }
+
+    /// Get the type of the service, or `None` if the information is older
+    /// than version 1.0.
+    #[must_use]
+    pub fn service_type(&self) -> Option<RestExServiceType> {
+        self.v1().map(|info| info.rest_service_type)
+    }
+
+    /// Get how the service is reached, or `None` if the information is
+    /// older than version 1.0.
+    #[must_use]
+    pub fn access_mode(&self) -> Option<RestExServiceAccessMode> {
+        self.v1().map(|info| info.rest_service_access_mode)
+    }
+
+    /// Get the GUID of a [`RestExServiceType::VENDOR_SPECIFIC`] service.
+    #[must_use]
+    pub fn vendor_rest_service_name(&self) -> Option<&Guid> {
+        self.v1()
+            .filter(|info| info.rest_service_type == RestExServiceType::VENDOR_SPECIFIC)
+            .map(|info| &info.vendor_rest_service_name)
+    }
+
+    /// Get the vendor-specific data of the service.
+    #[must_use]
+    pub fn vendor_specific_data(&self) -> &[u8] {
+        match self.v1() {
+            Some(info) if !info.vendor_specific_data.is_null() => unsafe {
+                slice::from_raw_parts(
+                    info.vendor_specific_data,
+                    info.vendor_specific_data_length as usize,
+                )
+            },
+            _ => &[],
+        }
+    }
+
+    /// Get the type of the configuration of the instances, or `None` if the
+    /// information is older than version 1.0.
+    #[must_use]
+    pub fn config_type(&self) -> Option<RestExConfigType> {
+        self.v1().map(|info| info.rest_ex_config_type)
+    }
+
+    fn header(&self) -> &RestExServiceInfoData {
+        unsafe { self.info.as_ref() }
+    }
+
+    /// Get the version 1.0 fields, if present.
+    fn v1(&self) -> Option<&RestExServiceInfoData> {
+        let info = self.header();
+        (info.version_major >= 1 && info.length >= Self::V1_0_SIZE).then_some(info)
+    }
+}
+
+impl fmt::Debug for RestExServiceInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RestExServiceInfo")
+            .field("version", &self.version())
+            .field("service_type", &self.service_type())
+            .field("access_mode", &self.access_mode())
+            .field("config_type", &self.config_type())
+            .finish()
+    }
+}
+
+impl Drop for RestExServiceInfo<'_> {
+    fn drop(&mut self) {
+        // Ignore the result, we can't do anything about an error here.
+        let _ = self.boot_services.free_pool(self.info.as_ptr().cast());
+    }
+}
+
+/// HTTP configuration of a REST EX instance.
+///
+/// The corresponding C type is `EFI_REST_EX_HTTP_CONFIG_DATA`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct RestExHttpConfigData<'a> {
+    /// The configuration of the HTTP connection.
+    pub http_config_data: HttpConfigData<'a>,
+    /// The timeout of [`RestEx::send_receive`] in milliseconds, or 0 for
+    /// no timeout.
+    pub send_receive_timeout: u32,
+}
+
+/// The HTTP configuration returned by [`RestEx::http_config_data`].
+///
+/// The configuration is allocated by the driver, and freed when this is
+/// dropped.
+pub struct RestExHttpConfigBuf<'boot> {
+    boot_services: &'boot BootServices,
+    config: NonNull<RestExHttpConfigData<'static>>,
+}
+
+impl<'boot> Deref for RestExHttpConfigBuf<'boot> {
+    type Target = RestExHttpConfigData<'boot>;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.config.as_ref() }
+    }
+}
+
+impl fmt::Debug for RestExHttpConfigBuf<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl Drop for RestExHttpConfigBuf<'_> {
+    fn drop(&mut self) {
+        // The access point is allocated separately. Ignore the results, we
+        // can't do anything about an error here.
+        let http = &self.http_config_data;
+        let access_point = match http.ipv4_access_point() {
+            Some(v4) => (v4 as *const Httpv4AccessPoint).cast::<u8>(),
+            None => http
+                .ipv6_access_point()
+                .map_or(ptr::null(), |v6| (v6 as *const Httpv6AccessPoint).cast()),
+        };
+        if !access_point.is_null() {
+            let _ = self.boot_services.free_pool(access_point as *mut u8);
+        }
+        let _ = self.boot_services.free_pool(self.config.as_ptr().cast());
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use crate::proto::network::http::{HttpResponseData, HttpStatusCode, HttpVersion};
+    use crate::table::boot::{EventType, MemoryType, Tpl};
+    use crate::{cstr16, guid, Char8};
+    use core::cell::{Cell, RefCell};
+    use core::marker::PhantomData;
+    use core::mem;
+    use std::string::{String, ToString};
+    use std::vec::Vec;
+
+    std::thread_local! {
+        /// Boot services used by the fake protocol to allocate.
+        static BOOT_SERVICES: Cell<*const BootServices> = const { Cell::new(ptr::null()) };
+        /// Requests received by the fake service.
+        static REQUESTS: RefCell<Vec<Request>> = const { RefCell::new(Vec::new()) };
+        /// Whether the fake instance is configured.
+        static CONFIGURED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// A request received by the fake service.
+    struct Request {
+        method: HttpMethod,
+        url: String,
+        headers: Vec<String>,
+        body: Vec<u8>,
+    }
+
+    /// Allocate `value` from pool.
+    unsafe fn pool<T>(value: T) -> *mut T {
+        let bt = &*BOOT_SERVICES.with(Cell::get);
+        let buffer = bt
+            .allocate_pool(MemoryType::BOOT_SERVICES_DATA, mem::size_of::<T>())
+            .unwrap()
+            .cast::<T>();
+        buffer.write(value);
+        buffer
+    }
+
+    /// Allocate a copy of the null-terminated `s` from pool.
+    unsafe fn pool_str(s: &[u8]) -> *const Char8 {
+        let bt = &*BOOT_SERVICES.with(Cell::get);
+        let buffer = bt
+            .allocate_pool(MemoryType::BOOT_SERVICES_DATA, s.len())
+            .unwrap();
+        buffer.copy_from(s.as_ptr(), s.len());
+        buffer.cast()
+    }
+
+    /// Record `request`, and build a `201 Created` response echoing its body.
+    unsafe fn handle(request: *const HttpRequestMessage<'_>) -> RawHttpResponse {
+        let request = &*request;
+        let data = request.data().unwrap();
+        REQUESTS.with(|requests| {
+            requests.borrow_mut().push(Request {
+                method: data.method(),
+                url: data.url().to_string(),
+                headers: request
+                    .headers()
+                    .iter()
+                    .map(|h| {
+                        let name = String::from_utf8_lossy(h.name().to_bytes());
+                        let value = String::from_utf8_lossy(h.value().to_bytes());
+                        std::format!("{name}: {value}")
+                    })
+                    .collect(),
+                body: request.body().to_vec(),
+            })
+        });
+
+        let mut response = RawHttpResponse::new();
+        response.data = pool(HttpResponseData {
+            status_code: HttpStatusCode::STATUS_201_CREATED,
+        });
+        response.headers = pool(HttpHeader::new(
+            CStr8::from_ptr(pool_str(b"Location\0")),
+            CStr8::from_ptr(pool_str(b"/redfish/v1/Sessions/1\0")),
+        ));
+        response.header_count = 1;
+        let body = request.body();
+        if !body.is_empty() {
+            let bt = &*BOOT_SERVICES.with(Cell::get);
+            let buffer = bt
+                .allocate_pool(MemoryType::BOOT_SERVICES_DATA, body.len())
+                .unwrap();
+            buffer.copy_from(body.as_ptr(), body.len());
+            response.body = buffer.cast();
+            response.body_length = body.len();
+        }
+        response
+    }
+
+    unsafe extern "efiapi" fn send_receive(
+        _this: *mut RestEx,
+        request_message: *const HttpRequestMessage<'_>,
+        response_message: *mut RawHttpResponse,
+    ) -> Status {
+        if !CONFIGURED.with(Cell::get) {
+            return Status::NOT_STARTED;
+        }
+        *response_message = handle(request_message);
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn get_service(
+        _this: *mut RestEx,
+        rest_ex_service_info: *mut *mut RestExServiceInfoData,
+    ) -> Status {
+        *rest_ex_service_info = pool(RestExServiceInfoData {
+            length: RestExServiceInfo::V1_0_SIZE,
+            version_major: 1,
+            version_minor: 0,
+            rest_service_type: RestExServiceType::REDFISH,
+            rest_service_access_mode: RestExServiceAccessMode::OUT_OF_BAND,
+            vendor_rest_service_name: guid!("00000000-0000-0000-0000-000000000000"),
+            vendor_specific_data_length: 0,
+            vendor_specific_data: ptr::null(),
+            rest_ex_config_type: RestExConfigType::HTTP,
+            reserved: [0; 3],
+        });
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn get_mode_data(
+        _this: *mut RestEx,
+        rest_ex_config_data: *mut *mut u8,
+    ) -> Status {
+        if !CONFIGURED.with(Cell::get) {
+            return Status::NOT_READY;
+        }
+        let access_point = pool(Httpv4AccessPoint {
+            use_default_address: true,
+            ..Default::default()
+        });
+        *rest_ex_config_data = pool(RestExHttpConfigData {
+            http_config_data: HttpConfigData::new_ipv4(HttpVersion::HTTP_1_1, 1000, &*access_point),
+            send_receive_timeout: 5000,
+        })
+        .cast();
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn configure(
+        _this: *mut RestEx,
+        rest_ex_config_data: *const u8,
+    ) -> Status {
+        CONFIGURED.with(|configured| configured.set(!rest_ex_config_data.is_null()));
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn async_send_receive(
+        _this: *mut RestEx,
+        request_message: *const HttpRequestMessage<'_>,
+        rest_ex_token: *mut RestExToken,
+        _timeout_in_milli_seconds: *const usize,
+    ) -> Status {
+        if request_message.is_null() {
+            return Status::INVALID_PARAMETER;
+        }
+        // Complete immediately.
+        let token = &mut *rest_ex_token;
+        token.response_message = pool(handle(request_message));
+        token.status = Status::SUCCESS;
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn event_service(
+        _this: *mut RestEx,
+        _request_message: *const HttpRequestMessage<'_>,
+        _rest_ex_token: *mut RestExToken,
+    ) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    #[test]
+    fn test_rest_ex() {
+        let mut firmware = MockFirmware::new();
+        let mut protocol = RestEx {
+            send_receive,
+            get_service,
+            get_mode_data,
+            configure,
+            async_send_receive,
+            event_service,
+            _no_send_or_sync: PhantomData,
+        };
+        let handle = unsafe { firmware.install_protocol(None, &mut protocol) };
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        BOOT_SERVICES.with(|cell| cell.set(bt));
+        let mut rest = bt.open_protocol_exclusive::<RestEx>(handle).unwrap();
+
+        let info = rest.service_info(bt).unwrap();
+        assert_eq!(info.version(), (1, 0));
+        assert_eq!(info.service_type(), Some(RestExServiceType::REDFISH));
+        assert_eq!(
+            info.access_mode(),
+            Some(RestExServiceAccessMode::OUT_OF_BAND)
+        );
+        assert_eq!(info.vendor_rest_service_name(), None);
+        assert!(info.vendor_specific_data().is_empty());
+        drop(info);
+
+        let url = cstr16!("/redfish/v1/SessionService/Sessions");
+        let data = HttpRequestData::new(HttpMethod::GET, url);
+        assert_eq!(
+            rest.send_receive(bt, &HttpRequestMessage::new(&data))
+                .unwrap_err()
+                .status(),
+            Status::NOT_STARTED
+        );
+        assert_eq!(
+            rest.http_config_data(bt).unwrap_err().status(),
+            Status::NOT_READY
+        );
+
+        let access_point = Httpv4AccessPoint {
+            use_default_address: true,
+            ..Default::default()
+        };
+        let config = RestExHttpConfigData {
+            http_config_data: HttpConfigData::new_ipv4(HttpVersion::HTTP_1_1, 1000, &access_point),
+            send_receive_timeout: 5000,
+        };
+        rest.configure(Some(&config)).unwrap();
+        let current = rest.http_config_data(bt).unwrap();
+        assert_eq!(current.send_receive_timeout, 5000);
+        assert_eq!(current.http_config_data.http_version, HttpVersion::HTTP_1_1);
+        assert_eq!(
+            current.http_config_data.ipv4_access_point(),
+            Some(&access_point)
+        );
+        assert!(current.http_config_data.ipv6_access_point().is_none());
+        drop(current);
+
+        let json = br#"{"UserName": "admin", "Password": "secret"}"#;
+        let response = rest.send_json(bt, HttpMethod::POST, url, json).unwrap();
+        assert_eq!(
+            response.status_code(),
+            Some(HttpStatusCode::STATUS_201_CREATED)
+        );
+        assert!(response.status_code().unwrap().is_success());
+        assert_eq!(
+            response.header("location").unwrap(),
+            cstr8!("/redfish/v1/Sessions/1")
+        );
+        assert!(response.header("Content-Type").is_none());
+        assert_eq!(response.body(), json);
+        drop(response);
+        REQUESTS.with(|requests| {
+            let requests = requests.borrow();
+            let request = requests.last().unwrap();
+            assert_eq!(request.method, HttpMethod::POST);
+            assert_eq!(request.url, "/redfish/v1/SessionService/Sessions");
+            assert_eq!(
+                request.headers,
+                ["Content-Type: application/json", "Content-Length: 43"]
+            );
+            assert_eq!(request.body, json);
+        });
+
+        let event =
+            unsafe { bt.create_event(EventType::empty(), Tpl::APPLICATION, None, None) }.unwrap();
+        let mut token = RestExToken::new(event);
+        assert_eq!(token.status(), Status::NOT_READY);
+        assert!(token.take_response(bt).is_none());
+        let request = HttpRequestMessage::new(&data);
+        unsafe { rest.async_send_receive(Some(&request), &mut token, Some(1000)) }.unwrap();
+        assert_eq!(token.status(), Status::SUCCESS);
+        let response = token.take_response(bt).unwrap();
+        assert_eq!(
+            response.status_code(),
+            Some(HttpStatusCode::STATUS_201_CREATED)
+        );
+        assert!(response.body().is_empty());
+        assert_eq!(token.status(), Status::NOT_READY);
+        assert_eq!(
+            unsafe { rest.event_service(None, &mut token) }
+                .unwrap_err()
+                .status(),
+            Status::UNSUPPORTED
+        );
+
+        rest.configure(None).unwrap();
+        assert_eq!(
+            rest.send_receive(bt, &HttpRequestMessage::new(&data))
+                .unwrap_err()
+                .status(),
+            Status::NOT_STARTED
+        );
+    }
+
+    #[test]
+    fn test_format_length() {
+        let mut buf = [0; 21];
+        assert_eq!(format_length(0, &mut buf), cstr8!("0"));
+        assert_eq!(format_length(1234, &mut buf), cstr8!("1234"));
+        assert_eq!(
+            format_length(usize::MAX, &mut buf).to_bytes().len(),
+            usize::MAX.to_string().len()
+        );
+    }
+}
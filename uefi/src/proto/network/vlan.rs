@@ -0,0 +1,258 @@
+//! VLAN configuration protocol.
+//!
+//! The [`VlanConfig`] protocol is installed on the handle of a network
+//! controller, and manages the IEEE 802.1Q VLANs of the controller. Each
+//! VLAN gets its own child handle, with the usual network protocols on it.
+
+use crate::proto::unsafe_protocol;
+use crate::table::boot::BootServices;
+use crate::{Result, Status};
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
+use core::{fmt, slice};
+
+/// The VLAN configuration protocol.
+///
+/// The corresponding C type is `EFI_VLAN_CONFIG_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("9e23d768-d2f3-4366-9fc3-3a7aba864374")]
+pub struct VlanConfig {
+    set: unsafe extern "efiapi" fn(this: *mut Self, vlan_id: u16, priority: u8) -> Status,
+    find: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        vlan_id: *const u16,
+        number_of_vlan: *mut u16,
+        entries: *mut *mut VlanFindData,
+    ) -> Status,
+    remove: unsafe extern "efiapi" fn(this: *mut Self, vlan_id: u16) -> Status,
+}
+
+impl VlanConfig {
+    /// Highest VLAN ID. ID 0 means no VLAN tagging.
+    pub const MAX_VLAN_ID: u16 = 4094;
+
+    /// Highest priority of a VLAN.
+    pub const MAX_PRIORITY: u8 = 7;
+
+    /// Create the VLAN `vlan_id` with the 802.1Q `priority`, or update the
+    /// priority of an existing VLAN.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `vlan_id` or `priority` is out of
+    ///   range.
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   create the VLAN.
+    pub fn set(&mut self, vlan_id: u16, priority: u8) -> Result {
+        unsafe { (self.set)(self, vlan_id, priority) }.into()
+    }
+
+    /// Find the VLAN `vlan_id`, or all the VLANs if `vlan_id` is `None`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `vlan_id` is out of range.
+    /// * [`Status::NOT_FOUND`]: no VLAN matches.
+    pub fn find<'boot>(
+        &mut self,
+        bt: &'boot BootServices,
+        vlan_id: Option<u16>,
+    ) -> Result<VlanEntries<'boot>> {
+        let vlan_id = vlan_id.as_ref().map_or(ptr::null(), |id| id as *const u16);
+        let mut count = 0;
+        let mut entries = ptr::null_mut();
+        unsafe { (self.find)(self, vlan_id, &mut count, &mut entries) }.into_with_val(|| {
+            VlanEntries {
+                boot_services: bt,
+                entries: NonNull::new(entries),
+                count: usize::from(count),
+            }
+        })
+    }
+
+    /// Remove the VLAN `vlan_id`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `vlan_id` is out of range.
+    /// * [`Status::NOT_FOUND`]: the VLAN does not exist.
+    pub fn remove(&mut self, vlan_id: u16) -> Result {
+        unsafe { (self.remove)(self, vlan_id) }.into()
+    }
+}
+
+/// A VLAN of a network controller.
+///
+/// The corresponding C type is `EFI_VLAN_FIND_DATA`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct VlanFindData {
+    /// The VLAN ID.
+    pub vlan_id: u16,
+    /// The 802.1Q priority of the VLAN.
+    pub priority: u8,
+}
+
+/// The VLANs returned by [`VlanConfig::find`].
+///
+/// The entries are allocated by the driver, and freed when this is dropped.
+pub struct VlanEntries<'boot> {
+    boot_services: &'boot BootServices,
+    entries: Option<NonNull<VlanFindData>>,
+    count: usize,
+}
+
+impl Deref for VlanEntries<'_> {
+    type Target = [VlanFindData];
+
+    fn deref(&self) -> &[VlanFindData] {
+        match self.entries {
+            Some(entries) => unsafe { slice::from_raw_parts(entries.as_ptr(), self.count) },
+            None => &[],
+        }
+    }
+}
+
+impl fmt::Debug for VlanEntries<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl Drop for VlanEntries<'_> {
+    fn drop(&mut self) {
+        if let Some(entries) = self.entries {
+            // Ignore the result, we can't do anything about an error here.
+            let _ = self.boot_services.free_pool(entries.as_ptr().cast());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use crate::table::boot::MemoryType;
+    use core::cell::{Cell, RefCell};
+    use core::marker::PhantomData;
+    use core::mem;
+    use std::vec::Vec;
+
+    std::thread_local! {
+        /// Boot services used by the fake protocol to allocate.
+        static BOOT_SERVICES: Cell<*const BootServices> = const { Cell::new(ptr::null()) };
+        /// VLANs of the fake controller.
+        static VLANS: RefCell<Vec<VlanFindData>> = const { RefCell::new(Vec::new()) };
+    }
+
+    unsafe extern "efiapi" fn set(_this: *mut VlanConfig, vlan_id: u16, priority: u8) -> Status {
+        if vlan_id > VlanConfig::MAX_VLAN_ID || priority > VlanConfig::MAX_PRIORITY {
+            return Status::INVALID_PARAMETER;
+        }
+        VLANS.with(|vlans| {
+            let mut vlans = vlans.borrow_mut();
+            vlans.retain(|vlan| vlan.vlan_id != vlan_id);
+            vlans.push(VlanFindData { vlan_id, priority });
+        });
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn find(
+        _this: *mut VlanConfig,
+        vlan_id: *const u16,
+        number_of_vlan: *mut u16,
+        entries: *mut *mut VlanFindData,
+    ) -> Status {
+        let found: Vec<_> = VLANS.with(|vlans| {
+            vlans
+                .borrow()
+                .iter()
+                .filter(|vlan| vlan_id.is_null() || vlan.vlan_id == *vlan_id)
+                .copied()
+                .collect()
+        });
+        if found.is_empty() {
+            return Status::NOT_FOUND;
+        }
+        let bt = &*BOOT_SERVICES.with(Cell::get);
+        let buffer = bt
+            .allocate_pool(
+                MemoryType::BOOT_SERVICES_DATA,
+                found.len() * mem::size_of::<VlanFindData>(),
+            )
+            .unwrap()
+            .cast::<VlanFindData>();
+        buffer.copy_from(found.as_ptr(), found.len());
+        *number_of_vlan = found.len() as u16;
+        *entries = buffer;
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn remove(_this: *mut VlanConfig, vlan_id: u16) -> Status {
+        VLANS.with(|vlans| {
+            let mut vlans = vlans.borrow_mut();
+            let len = vlans.len();
+            vlans.retain(|vlan| vlan.vlan_id != vlan_id);
+            if vlans.len() == len {
+                Status::NOT_FOUND
+            } else {
+                Status::SUCCESS
+            }
+        })
+    }
+
+    #[test]
+    fn test_vlan_config() {
+        let mut firmware = MockFirmware::new();
+        let mut protocol = VlanConfig {
+            set,
+            find,
+            remove,
+            _no_send_or_sync: PhantomData,
+        };
+        let handle = unsafe { firmware.install_protocol(None, &mut protocol) };
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        BOOT_SERVICES.with(|cell| cell.set(bt));
+        let mut vlan_config = bt.open_protocol_exclusive::<VlanConfig>(handle).unwrap();
+
+        assert_eq!(
+            vlan_config.find(bt, None).unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+        vlan_config.set(10, 0).unwrap();
+        vlan_config.set(20, 3).unwrap();
+        vlan_config.set(10, 5).unwrap();
+        assert_eq!(
+            vlan_config.set(4095, 0).unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+
+        let entries = vlan_config.find(bt, None).unwrap();
+        assert_eq!(
+            *entries,
+            [
+                VlanFindData {
+                    vlan_id: 20,
+                    priority: 3
+                },
+                VlanFindData {
+                    vlan_id: 10,
+                    priority: 5
+                },
+            ]
+        );
+        drop(entries);
+        assert_eq!(vlan_config.find(bt, Some(20)).unwrap().len(), 1);
+
+        vlan_config.remove(20).unwrap();
+        assert_eq!(
+            vlan_config.remove(20).unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+        assert_eq!(
+            vlan_config.find(bt, Some(20)).unwrap_err().status(),
+            Status::NOT_FOUND
+        );
+    }
+}
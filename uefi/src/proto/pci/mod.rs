@@ -0,0 +1,111 @@
+//! PCI protocols.
+
+mod option_rom;
+
+pub use option_rom::{CodeType, EfiRomHeader, OptionRom, RomImage, RomImages};
+
+use crate::proto::unsafe_protocol;
+use crate::{Result, Status};
+use core::ffi::c_void;
+use core::slice;
+
+/// The PCI I/O protocol, installed on the handle of each PCI controller.
+///
+/// Only the location and expansion ROM accessors are currently bound.
+///
+/// The corresponding C type is `EFI_PCI_IO_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("4cf5b200-68b8-4ca5-9eec-b23e3f50029a")]
+pub struct PciIo {
+    poll_mem: usize,
+    poll_io: usize,
+    mem: [usize; 2],
+    io: [usize; 2],
+    pci: [usize; 2],
+    copy_mem: usize,
+    map: usize,
+    unmap: usize,
+    allocate_buffer: usize,
+    free_buffer: usize,
+    flush: usize,
+    get_location: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        segment: *mut usize,
+        bus: *mut usize,
+        device: *mut usize,
+        function: *mut usize,
+    ) -> Status,
+    attributes: usize,
+    get_bar_attributes: usize,
+    set_bar_attributes: usize,
+    rom_size: u64,
+    rom_image: *const c_void,
+}
+
+impl PciIo {
+    /// Get the location of the controller on the PCI bus.
+    ///
+    /// # Errors
+    ///
+    /// None are documented by the UEFI specification.
+    pub fn location(&mut self) -> Result<PciLocation> {
+        let mut location = PciLocation::default();
+        unsafe {
+            (self.get_location)(
+                self,
+                &mut location.segment,
+                &mut location.bus,
+                &mut location.device,
+                &mut location.function,
+            )
+        }
+        .into_with_val(|| location)
+    }
+
+    /// Get a copy of the expansion ROM of the controller, as read by the PCI
+    /// bus driver, or `None` if the controller doesn't have one.
+    ///
+    /// The image can be parsed with [`OptionRom`], see [`option_rom`].
+    ///
+    /// [`option_rom`]: Self::option_rom
+    #[must_use]
+    pub fn rom_image(&self) -> Option<&[u8]> {
+        if self.rom_image.is_null() || self.rom_size == 0 {
+            return None;
+        }
+        let len = usize::try_from(self.rom_size).ok()?;
+        Some(unsafe { slice::from_raw_parts(self.rom_image.cast::<u8>(), len) })
+    }
+
+    /// Get the size of the expansion ROM of the controller, or zero if it
+    /// doesn't have one.
+    #[must_use]
+    pub fn rom_size(&self) -> u64 {
+        if self.rom_image.is_null() {
+            0
+        } else {
+            self.rom_size
+        }
+    }
+
+    /// Get the expansion ROM of the controller, or `None` if it doesn't have
+    /// one.
+    #[must_use]
+    pub fn option_rom(&self) -> Option<OptionRom<'_>> {
+        self.rom_image().map(OptionRom::new)
+    }
+}
+
+/// Location of a controller on the PCI bus, returned by
+/// [`PciIo::location`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct PciLocation {
+    /// PCI segment number.
+    pub segment: usize,
+    /// PCI bus number.
+    pub bus: usize,
+    /// PCI device number.
+    pub device: usize,
+    /// PCI function number.
+    pub function: usize,
+}
@@ -0,0 +1,334 @@
+use crate::{Result, Status};
+
+/// Signature at the start of each image of an option ROM.
+const ROM_SIGNATURE: u16 = 0xaa55;
+
+/// Signature of the PCI data structure.
+const PCIR_SIGNATURE: [u8; 4] = *b"PCIR";
+
+/// Signature of images with an `EFI_PCI_EXPANSION_ROM_HEADER`.
+const EFI_SIGNATURE: u32 = 0x0ef1;
+
+/// Unit of the image lengths in the headers.
+const BLOCK_SIZE: usize = 512;
+
+/// Bit of the PCIR indicator byte set on the last image of the ROM.
+const LAST_IMAGE: u8 = 0x80;
+
+newtype_enum! {
+/// Type of code of a [`RomImage`].
+pub enum CodeType: u8 => {
+    /// Intel x86 legacy BIOS code.
+    PC_AT         = 0x00,
+    /// Open Firmware standard for PCI.
+    OPEN_FIRMWARE = 0x01,
+    /// Hewlett-Packard PA RISC.
+    HP_PA_RISC    = 0x02,
+    /// UEFI driver, see [`RomImage::efi_header`].
+    EFI           = 0x03,
+}}
+
+/// A PCI expansion ROM, also known as option ROM.
+///
+/// The ROM is made of one or more images, such as a legacy BIOS image and a
+/// UEFI driver, which can be enumerated with [`images`].
+///
+/// This only parses the ROM, and can be used with ROMs from any source. The
+/// ROM of a PCI controller is available from [`PciIo::option_rom`].
+///
+/// [`images`]: Self::images
+/// [`PciIo::option_rom`]: super::PciIo::option_rom
+#[derive(Clone, Copy, Debug)]
+pub struct OptionRom<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> OptionRom<'a> {
+    /// Create an `OptionRom` from its raw bytes.
+    #[must_use]
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    /// Get the raw bytes of the ROM.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get an iterator over the images of the ROM.
+    ///
+    /// The iterator yields an error and stops if an image header is invalid.
+    #[must_use]
+    pub const fn images(&self) -> RomImages<'a> {
+        RomImages {
+            bytes: self.bytes,
+            offset: 0,
+            done: false,
+        }
+    }
+}
+
+/// An image of an [`OptionRom`].
+///
+/// Each image starts with a `PCI_EXPANSION_ROM_HEADER`, pointing to a
+/// `PCI_DATA_STRUCTURE` (PCIR) describing the image.
+#[derive(Clone, Copy, Debug)]
+pub struct RomImage<'a> {
+    bytes: &'a [u8],
+    pcir: &'a [u8],
+}
+
+impl<'a> RomImage<'a> {
+    /// Get the raw bytes of the image, including its headers.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get the PCI vendor ID the image is for.
+    #[must_use]
+    pub fn vendor_id(&self) -> u16 {
+        read_u16(self.pcir, 4).unwrap()
+    }
+
+    /// Get the PCI device ID the image is for.
+    #[must_use]
+    pub fn device_id(&self) -> u16 {
+        read_u16(self.pcir, 6).unwrap()
+    }
+
+    /// Get the revision of the PCI data structure.
+    #[must_use]
+    pub fn pcir_revision(&self) -> u8 {
+        self.pcir[0xc]
+    }
+
+    /// Get the PCI class code the image is for, as base class, sub-class,
+    /// and programming interface.
+    #[must_use]
+    pub fn class_code(&self) -> (u8, u8, u8) {
+        (self.pcir[0xf], self.pcir[0xe], self.pcir[0xd])
+    }
+
+    /// Get the vendor-defined revision of the code in the image.
+    #[must_use]
+    pub fn code_revision(&self) -> u16 {
+        read_u16(self.pcir, 0x12).unwrap()
+    }
+
+    /// Get the type of code in the image.
+    #[must_use]
+    pub fn code_type(&self) -> CodeType {
+        CodeType(self.pcir[0x14])
+    }
+
+    /// Check whether this is the last image of the ROM.
+    #[must_use]
+    pub fn is_last(&self) -> bool {
+        self.pcir[0x15] & LAST_IMAGE != 0
+    }
+
+    /// Get the UEFI-specific header of the image, or `None` if this is not
+    /// a UEFI image.
+    #[must_use]
+    pub fn efi_header(&self) -> Option<EfiRomHeader<'a>> {
+        if self.code_type() != CodeType::EFI || read_u32(self.bytes, 4)? != EFI_SIGNATURE {
+            return None;
+        }
+        Some(EfiRomHeader { bytes: self.bytes })
+    }
+}
+
+/// The header of a UEFI image in an [`OptionRom`].
+///
+/// The corresponding C type is `EFI_PCI_EXPANSION_ROM_HEADER`.
+#[derive(Clone, Copy, Debug)]
+pub struct EfiRomHeader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> EfiRomHeader<'a> {
+    /// Get the PE subsystem of the driver, such as
+    /// `EFI_BOOT_SERVICE_DRIVER` (11).
+    #[must_use]
+    pub fn subsystem(&self) -> u16 {
+        read_u16(self.bytes, 8).unwrap()
+    }
+
+    /// Get the PE machine type of the driver, such as x64 (0x8664).
+    #[must_use]
+    pub fn machine_type(&self) -> u16 {
+        read_u16(self.bytes, 0xa).unwrap()
+    }
+
+    /// Check whether the driver is compressed with the UEFI compression
+    /// algorithm.
+    #[must_use]
+    pub fn is_compressed(&self) -> bool {
+        read_u16(self.bytes, 0xc).unwrap() == 1
+    }
+
+    /// Get the PE/COFF image of the driver, which is compressed if
+    /// [`is_compressed`] is true.
+    ///
+    /// Returns `None` if the header points outside of the image.
+    ///
+    /// [`is_compressed`]: Self::is_compressed
+    #[must_use]
+    pub fn driver_image(&self) -> Option<&'a [u8]> {
+        let start = usize::from(read_u16(self.bytes, 0x16)?);
+        let end = usize::from(read_u16(self.bytes, 2)?) * BLOCK_SIZE;
+        self.bytes.get(start..end)
+    }
+}
+
+/// Iterator over the images of an [`OptionRom`], returned by
+/// [`OptionRom::images`].
+#[derive(Clone, Debug)]
+pub struct RomImages<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+impl<'a> RomImages<'a> {
+    /// Parse the image at the current offset, and advance past it.
+    fn parse(&mut self) -> Result<RomImage<'a>> {
+        let rest = &self.bytes[self.offset..];
+        if read_u16(rest, 0) != Some(ROM_SIGNATURE) {
+            return Err(Status::LOAD_ERROR.into());
+        }
+        let pcir_offset = usize::from(read_u16(rest, 0x18).ok_or(Status::LOAD_ERROR)?);
+        let pcir = rest
+            .get(pcir_offset..pcir_offset + 0x18)
+            .filter(|pcir| pcir[..4] == PCIR_SIGNATURE)
+            .ok_or(Status::LOAD_ERROR)?;
+        let len = usize::from(read_u16(pcir, 0x10).unwrap()) * BLOCK_SIZE;
+        let bytes = rest
+            .get(..len)
+            .filter(|_| len > pcir_offset)
+            .ok_or(Status::LOAD_ERROR)?;
+
+        let image = RomImage { bytes, pcir };
+        self.offset += len;
+        if image.is_last() {
+            self.done = true;
+        }
+        Ok(image)
+    }
+}
+
+impl<'a> Iterator for RomImages<'a> {
+    type Item = Result<RomImage<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.offset >= self.bytes.len() {
+            return None;
+        }
+        let image = self.parse();
+        if image.is_err() {
+            self.done = true;
+        }
+        Some(image)
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    let bytes = bytes.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    /// Build a ROM image of `blocks` 512-byte blocks.
+    fn image(code_type: CodeType, blocks: u16, last: bool) -> Vec<u8> {
+        let mut image = vec![0; usize::from(blocks) * BLOCK_SIZE];
+        image[..2].copy_from_slice(&ROM_SIGNATURE.to_le_bytes());
+        image[0x18..0x1a].copy_from_slice(&0x1cu16.to_le_bytes());
+        if code_type == CodeType::EFI {
+            image[2..4].copy_from_slice(&blocks.to_le_bytes());
+            image[4..8].copy_from_slice(&EFI_SIGNATURE.to_le_bytes());
+            image[8..0xa].copy_from_slice(&11u16.to_le_bytes());
+            image[0xa..0xc].copy_from_slice(&0x8664u16.to_le_bytes());
+            image[0x16..0x18].copy_from_slice(&0x40u16.to_le_bytes());
+            image[0x40..0x42].copy_from_slice(b"MZ");
+        }
+
+        let pcir = &mut image[0x1c..0x34];
+        pcir[..4].copy_from_slice(&PCIR_SIGNATURE);
+        pcir[4..6].copy_from_slice(&0x1234u16.to_le_bytes());
+        pcir[6..8].copy_from_slice(&0x1111u16.to_le_bytes());
+        pcir[0xc] = 3;
+        pcir[0xd..0x10].copy_from_slice(&[0x00, 0x00, 0x03]);
+        pcir[0x10..0x12].copy_from_slice(&blocks.to_le_bytes());
+        pcir[0x14] = code_type.0;
+        pcir[0x15] = if last { LAST_IMAGE } else { 0 };
+        image
+    }
+
+    #[test]
+    fn test_option_rom() {
+        let mut bytes = image(CodeType::PC_AT, 2, false);
+        bytes.extend(image(CodeType::EFI, 1, true));
+        // Padding after the last image is ignored.
+        bytes.extend([0xff; 512]);
+
+        let rom = OptionRom::new(&bytes);
+        let images: Vec<_> = rom.images().map(Result::unwrap).collect();
+        assert_eq!(images.len(), 2);
+
+        let legacy = &images[0];
+        assert_eq!(legacy.as_bytes().len(), 1024);
+        assert_eq!(legacy.vendor_id(), 0x1234);
+        assert_eq!(legacy.device_id(), 0x1111);
+        assert_eq!(legacy.class_code(), (0x03, 0x00, 0x00));
+        assert_eq!(legacy.code_type(), CodeType::PC_AT);
+        assert!(!legacy.is_last());
+        assert!(legacy.efi_header().is_none());
+
+        let efi = &images[1];
+        assert!(efi.is_last());
+        let header = efi.efi_header().unwrap();
+        assert_eq!(header.subsystem(), 11);
+        assert_eq!(header.machine_type(), 0x8664);
+        assert!(!header.is_compressed());
+        let driver = header.driver_image().unwrap();
+        assert_eq!(driver.len(), 512 - 0x40);
+        assert_eq!(&driver[..2], b"MZ");
+    }
+
+    #[test]
+    fn test_option_rom_errors() {
+        assert!(OptionRom::new(&[]).images().next().is_none());
+
+        // Bad signature.
+        let mut bytes = image(CodeType::PC_AT, 1, true);
+        bytes[0] = 0;
+        let mut images = OptionRom::new(&bytes).images();
+        assert_eq!(
+            images.next().unwrap().unwrap_err().status(),
+            Status::LOAD_ERROR
+        );
+        assert!(images.next().is_none());
+
+        // Image longer than the ROM.
+        let mut bytes = image(CodeType::PC_AT, 2, true);
+        bytes.truncate(512);
+        assert!(OptionRom::new(&bytes).images().next().unwrap().is_err());
+
+        // Bad PCIR signature.
+        let mut bytes = image(CodeType::PC_AT, 1, true);
+        bytes[0x1c] = 0;
+        assert!(OptionRom::new(&bytes).images().next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,481 @@
+//! I2C protocols.
+//!
+//! The [`I2cMaster`] protocol is installed by the driver of an I2C host
+//! controller, and sends requests to any address of the bus. The I2C bus
+//! driver uses it to install an [`I2cIo`] protocol on a child handle for
+//! each device described by the platform, which is the protocol device
+//! drivers should use.
+//!
+//! A request is a sequence of [`I2cOperation`]s, such as a register address
+//! write followed by a data read, grouped in an [`I2cRequestPacket`].
+
+use crate::proto::unsafe_protocol;
+use crate::{Event, Guid, Result, Status};
+use bitflags::bitflags;
+use core::marker::PhantomData;
+use core::ptr;
+
+/// The I2C IO protocol, giving access to an I2C device.
+///
+/// The corresponding C type is `EFI_I2C_IO_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("b60a3e6b-18c4-46e5-a29a-c9a10665a28e")]
+pub struct I2cIo {
+    queue_request: unsafe extern "efiapi" fn(
+        this: *const Self,
+        slave_address_index: usize,
+        event: Option<Event>,
+        request_packet: *mut I2cRequestPacket<'static, 1>,
+        i2c_status: *mut Status,
+    ) -> Status,
+    device_guid: *const Guid,
+    device_index: u32,
+    hardware_revision: u32,
+    i2c_controller_capabilities: *const I2cControllerCapabilities,
+}
+
+impl I2cIo {
+    /// Send `packet` to the device, and wait for its completion.
+    ///
+    /// A device can have several addresses, `slave_address_index` selects
+    /// one of them.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: an operation is too long for the
+    ///   controller.
+    /// * [`Status::INVALID_PARAMETER`]: `packet` is invalid.
+    /// * [`Status::NO_MAPPING`]: `slave_address_index` is out of range.
+    /// * [`Status::NO_RESPONSE`]: the device did not acknowledge its
+    ///   address.
+    /// * [`Status::NOT_FOUND`]: the device did not acknowledge its data.
+    /// * [`Status::DEVICE_ERROR`]: the transfer failed.
+    /// * [`Status::UNSUPPORTED`]: the controller does not support an
+    ///   operation of the packet.
+    pub fn queue_request<const N: usize>(
+        &mut self,
+        slave_address_index: usize,
+        packet: &mut I2cRequestPacket<'_, N>,
+    ) -> Result {
+        unsafe {
+            (self.queue_request)(
+                self,
+                slave_address_index,
+                None,
+                packet.as_raw(),
+                ptr::null_mut(),
+            )
+        }
+        .into()
+    }
+
+    /// Write `write` to the device, then read `read` from it, such as a
+    /// register address followed by the register value.
+    ///
+    /// # Errors
+    ///
+    /// See [`queue_request`](Self::queue_request).
+    pub fn write_read(
+        &mut self,
+        slave_address_index: usize,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result {
+        let mut packet =
+            I2cRequestPacket::new([I2cOperation::write(write), I2cOperation::read(read)]);
+        self.queue_request(slave_address_index, &mut packet)
+    }
+
+    /// Get the GUID identifying the type of the device.
+    #[must_use]
+    pub fn device_guid(&self) -> &Guid {
+        unsafe { &*self.device_guid }
+    }
+
+    /// Get the index of the device among the devices with the same GUID.
+    #[must_use]
+    pub const fn device_index(&self) -> u32 {
+        self.device_index
+    }
+
+    /// Get the hardware revision of the device, as described by the
+    /// platform.
+    #[must_use]
+    pub const fn hardware_revision(&self) -> u32 {
+        self.hardware_revision
+    }
+
+    /// Get the limits of the I2C host controller.
+    #[must_use]
+    pub fn capabilities(&self) -> &I2cControllerCapabilities {
+        unsafe { &*self.i2c_controller_capabilities }
+    }
+}
+
+/// The I2C master protocol, giving access to an I2C bus.
+///
+/// The corresponding C type is `EFI_I2C_MASTER_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("cd72881f-45b5-4feb-98c8-313da8117462")]
+pub struct I2cMaster {
+    set_bus_frequency:
+        unsafe extern "efiapi" fn(this: *const Self, bus_clock_hertz: *mut usize) -> Status,
+    reset: unsafe extern "efiapi" fn(this: *const Self) -> Status,
+    start_request: unsafe extern "efiapi" fn(
+        this: *const Self,
+        slave_address: usize,
+        request_packet: *mut I2cRequestPacket<'static, 1>,
+        event: Option<Event>,
+        i2c_status: *mut Status,
+    ) -> Status,
+    i2c_controller_capabilities: *const I2cControllerCapabilities,
+}
+
+impl I2cMaster {
+    /// Flag of a 10-bit slave address.
+    pub const ADDRESSING_10_BIT: usize = 0x8000_0000;
+
+    /// Set the frequency of the bus clock to at most `bus_clock_hertz`, and
+    /// return the selected frequency.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ALREADY_STARTED`]: a request is running.
+    /// * [`Status::INVALID_PARAMETER`]: `bus_clock_hertz` is invalid.
+    /// * [`Status::UNSUPPORTED`]: the controller can't run at or below
+    ///   `bus_clock_hertz`.
+    pub fn set_bus_frequency(&mut self, bus_clock_hertz: usize) -> Result<usize> {
+        let mut hertz = bus_clock_hertz;
+        unsafe { (self.set_bus_frequency)(self, &mut hertz) }.into_with_val(|| hertz)
+    }
+
+    /// Reset the controller.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ALREADY_STARTED`]: a request is running.
+    /// * [`Status::DEVICE_ERROR`]: the reset failed.
+    pub fn reset(&mut self) -> Result {
+        unsafe { (self.reset)(self) }.into()
+    }
+
+    /// Send `packet` to the device at `slave_address`, and wait for its
+    /// completion. 10-bit addresses are combined with
+    /// [`Self::ADDRESSING_10_BIT`].
+    ///
+    /// # Errors
+    ///
+    /// See [`I2cIo::queue_request`]. In addition:
+    ///
+    /// * [`Status::ALREADY_STARTED`]: a request is running.
+    pub fn start_request<const N: usize>(
+        &mut self,
+        slave_address: usize,
+        packet: &mut I2cRequestPacket<'_, N>,
+    ) -> Result {
+        unsafe { (self.start_request)(self, slave_address, packet.as_raw(), None, ptr::null_mut()) }
+            .into()
+    }
+
+    /// Get the limits of the controller.
+    #[must_use]
+    pub fn capabilities(&self) -> &I2cControllerCapabilities {
+        unsafe { &*self.i2c_controller_capabilities }
+    }
+}
+
+/// Limits of an I2C host controller.
+///
+/// The corresponding C type is `EFI_I2C_CONTROLLER_CAPABILITIES`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct I2cControllerCapabilities {
+    /// Size of this structure.
+    pub structure_size_in_bytes: u32,
+    /// Maximum length of a read operation.
+    pub maximum_receive_bytes: u32,
+    /// Maximum length of a write operation.
+    pub maximum_transmit_bytes: u32,
+    /// Maximum length of all the operations of a request.
+    pub maximum_total_bytes: u32,
+}
+
+bitflags! {
+    /// Flags of an [`I2cOperation`].
+    #[repr(transparent)]
+    pub struct I2cFlags: u32 {
+        /// Read from the device. Without this flag, the operation writes to
+        /// the device.
+        const READ = 0x0000_0001;
+        /// The request is an SMBus operation.
+        const SMBUS_OPERATION = 0x0001_0000;
+        /// The SMBus operation transfers a block, prefixed with its length.
+        const SMBUS_BLOCK = 0x0002_0000;
+        /// The SMBus operation is a process call.
+        const SMBUS_PROCESS_CALL = 0x0004_0000;
+        /// The SMBus operation uses packet error checking.
+        const SMBUS_PEC = 0x0008_0000;
+    }
+}
+
+/// A read or write operation of an I2C request.
+///
+/// The corresponding C type is `EFI_I2C_OPERATION`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct I2cOperation<'a> {
+    flags: I2cFlags,
+    length_in_bytes: u32,
+    buffer: *mut u8,
+    _marker: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> I2cOperation<'a> {
+    /// Create an operation reading `buffer` from the device.
+    #[must_use]
+    pub fn read(buffer: &'a mut [u8]) -> Self {
+        Self {
+            flags: I2cFlags::READ,
+            length_in_bytes: u32::try_from(buffer.len()).unwrap(),
+            buffer: buffer.as_mut_ptr(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create an operation writing `buffer` to the device.
+    #[must_use]
+    pub fn write(buffer: &'a [u8]) -> Self {
+        Self {
+            flags: I2cFlags::empty(),
+            length_in_bytes: u32::try_from(buffer.len()).unwrap(),
+            // The buffer is not written by the driver.
+            buffer: buffer.as_ptr() as *mut u8,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Add the SMBus `flags` to the operation.
+    #[must_use]
+    pub fn with_flags(mut self, flags: I2cFlags) -> Self {
+        self.flags |= flags & !I2cFlags::READ;
+        self
+    }
+
+    /// Get the flags of the operation.
+    #[must_use]
+    pub const fn flags(&self) -> I2cFlags {
+        self.flags
+    }
+
+    /// Get the length of the operation in bytes.
+    #[must_use]
+    pub const fn len(&self) -> u32 {
+        self.length_in_bytes
+    }
+
+    /// Whether the operation transfers no data.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.length_in_bytes == 0
+    }
+}
+
+/// A sequence of `N` operations, run with repeated starts between them and
+/// a stop at the end.
+///
+/// The corresponding C type is `EFI_I2C_REQUEST_PACKET`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct I2cRequestPacket<'a, const N: usize> {
+    operation_count: usize,
+    operations: [I2cOperation<'a>; N],
+}
+
+impl<'a, const N: usize> I2cRequestPacket<'a, N> {
+    /// Create a packet with `operations`.
+    #[must_use]
+    pub const fn new(operations: [I2cOperation<'a>; N]) -> Self {
+        Self {
+            operation_count: N,
+            operations,
+        }
+    }
+
+    /// Get the operations of the packet.
+    #[must_use]
+    pub const fn operations(&self) -> &[I2cOperation<'a>] {
+        &self.operations
+    }
+
+    /// Get the packet as passed to the driver, whose C type declares a
+    /// single operation.
+    fn as_raw(&mut self) -> *mut I2cRequestPacket<'static, 1> {
+        (self as *mut Self).cast()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::guid;
+    use crate::mock::MockFirmware;
+    use core::cell::RefCell;
+    use core::slice;
+
+    const EEPROM_GUID: Guid = guid!("4e9b0d0c-1f42-4d2e-a1c3-4b5e2f1a7d90");
+
+    const CAPABILITIES: I2cControllerCapabilities = I2cControllerCapabilities {
+        structure_size_in_bytes: 16,
+        maximum_receive_bytes: 32,
+        maximum_transmit_bytes: 32,
+        maximum_total_bytes: 64,
+    };
+
+    std::thread_local! {
+        /// Memory of the fake EEPROM, and its address pointer.
+        static EEPROM: RefCell<([u8; 256], u8)> = const { RefCell::new(([0; 256], 0)) };
+    }
+
+    /// Run the operations of `packet` on the fake EEPROM.
+    unsafe fn run_packet(packet: *mut I2cRequestPacket<'static, 1>) -> Status {
+        let count = (*packet).operation_count;
+        let operations = slice::from_raw_parts(
+            ptr::addr_of!((*packet).operations).cast::<I2cOperation>(),
+            count,
+        );
+        EEPROM.with(|eeprom| {
+            let (memory, address) = &mut *eeprom.borrow_mut();
+            for operation in operations {
+                let len = operation.length_in_bytes as usize;
+                if len > CAPABILITIES.maximum_receive_bytes as usize {
+                    return Status::BAD_BUFFER_SIZE;
+                }
+                let buffer = slice::from_raw_parts_mut(operation.buffer, len);
+                if operation.flags.contains(I2cFlags::READ) {
+                    for byte in buffer {
+                        *byte = memory[usize::from(*address)];
+                        *address = address.wrapping_add(1);
+                    }
+                } else if let [first, data @ ..] = buffer {
+                    // The first byte written sets the address.
+                    *address = *first;
+                    for byte in data {
+                        memory[usize::from(*address)] = *byte;
+                        *address = address.wrapping_add(1);
+                    }
+                }
+            }
+            Status::SUCCESS
+        })
+    }
+
+    unsafe extern "efiapi" fn queue_request(
+        _this: *const I2cIo,
+        slave_address_index: usize,
+        _event: Option<Event>,
+        request_packet: *mut I2cRequestPacket<'static, 1>,
+        _i2c_status: *mut Status,
+    ) -> Status {
+        if slave_address_index != 0 {
+            return Status::NO_MAPPING;
+        }
+        run_packet(request_packet)
+    }
+
+    unsafe extern "efiapi" fn set_bus_frequency(
+        _this: *const I2cMaster,
+        bus_clock_hertz: *mut usize,
+    ) -> Status {
+        match *bus_clock_hertz {
+            0..=99_999 => Status::UNSUPPORTED,
+            100_000..=399_999 => {
+                *bus_clock_hertz = 100_000;
+                Status::SUCCESS
+            }
+            _ => {
+                *bus_clock_hertz = 400_000;
+                Status::SUCCESS
+            }
+        }
+    }
+
+    unsafe extern "efiapi" fn reset(_this: *const I2cMaster) -> Status {
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn start_request(
+        _this: *const I2cMaster,
+        slave_address: usize,
+        request_packet: *mut I2cRequestPacket<'static, 1>,
+        _event: Option<Event>,
+        _i2c_status: *mut Status,
+    ) -> Status {
+        if slave_address != 0x50 {
+            return Status::NO_RESPONSE;
+        }
+        run_packet(request_packet)
+    }
+
+    #[test]
+    fn test_i2c() {
+        let mut firmware = MockFirmware::new();
+        let mut io = I2cIo {
+            queue_request,
+            device_guid: &EEPROM_GUID,
+            device_index: 0,
+            hardware_revision: 1,
+            i2c_controller_capabilities: &CAPABILITIES,
+            _no_send_or_sync: PhantomData,
+        };
+        let mut master = I2cMaster {
+            set_bus_frequency,
+            reset,
+            start_request,
+            i2c_controller_capabilities: &CAPABILITIES,
+            _no_send_or_sync: PhantomData,
+        };
+        let io_handle = unsafe { firmware.install_protocol(None, &mut io) };
+        let master_handle = unsafe { firmware.install_protocol(None, &mut master) };
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+
+        let mut master = bt
+            .open_protocol_exclusive::<I2cMaster>(master_handle)
+            .unwrap();
+        assert_eq!(master.capabilities().maximum_total_bytes, 64);
+        assert_eq!(master.set_bus_frequency(1_000_000).unwrap(), 400_000);
+        assert_eq!(
+            master.set_bus_frequency(10_000).unwrap_err().status(),
+            Status::UNSUPPORTED
+        );
+        master.reset().unwrap();
+        let data = [0x10, 1, 2, 3];
+        let mut packet = I2cRequestPacket::new([I2cOperation::write(&data)]);
+        master.start_request(0x50, &mut packet).unwrap();
+        assert_eq!(
+            master
+                .start_request(0x51, &mut packet)
+                .unwrap_err()
+                .status(),
+            Status::NO_RESPONSE
+        );
+
+        let mut io = bt.open_protocol_exclusive::<I2cIo>(io_handle).unwrap();
+        assert_eq!(*io.device_guid(), EEPROM_GUID);
+        assert_eq!(io.hardware_revision(), 1);
+        let mut buffer = [0; 4];
+        io.write_read(0, &[0x0f], &mut buffer).unwrap();
+        assert_eq!(buffer, [0, 1, 2, 3]);
+        assert_eq!(
+            io.write_read(1, &[0], &mut buffer).unwrap_err().status(),
+            Status::NO_MAPPING
+        );
+        let mut large = [0; 33];
+        let mut packet = I2cRequestPacket::new([I2cOperation::read(&mut large)]);
+        assert_eq!(packet.operations()[0].len(), 33);
+        assert_eq!(
+            io.queue_request(0, &mut packet).unwrap_err().status(),
+            Status::BAD_BUFFER_SIZE
+        );
+    }
+}
@@ -3,4 +3,6 @@
 //! Contains protocols defined in UEFI's
 //! Platform Initialization (PI) Specification.
 
+pub mod i2c;
 pub mod mp;
+pub mod spi;
@@ -0,0 +1,703 @@
+//! SPI protocols.
+//!
+//! The [`SpiHostController`] protocol is installed by the driver of an SPI
+//! host controller. The SPI bus driver uses it to install an [`SpiIo`]
+//! protocol on a child handle for each peripheral described by the
+//! platform, which is the protocol peripheral drivers should use.
+//!
+//! The SPI IO protocol has no GUID of its own: it is installed with the
+//! GUID of the driver expected to manage the peripheral, such as the SPI
+//! NOR flash driver. [`SpiIo`] is thus generic over a
+//! [`SpiPeripheralDriver`] type giving that GUID.
+
+use crate::proto::{unsafe_protocol, Protocol};
+use crate::{CStr16, Char16, Guid, Identify, Result, Status};
+use bitflags::bitflags;
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::{fmt, ptr};
+
+/// A driver of SPI peripherals, whose GUID identifies the [`SpiIo`]
+/// protocols of those peripherals.
+///
+/// # Safety
+///
+/// The GUID must be the one used to install the SPI IO protocol of the
+/// peripherals.
+pub unsafe trait SpiPeripheralDriver {
+    /// The GUID of the driver, found in [`SpiPeripheral::driver_guid`].
+    const DRIVER_GUID: Guid;
+}
+
+/// The SPI IO protocol, giving access to an SPI peripheral.
+///
+/// The corresponding C type is `EFI_SPI_IO_PROTOCOL`.
+#[repr(C)]
+pub struct SpiIo<D: SpiPeripheralDriver> {
+    spi_peripheral: *const SpiPeripheral,
+    original_spi_peripheral: *const SpiPeripheral,
+    frame_size_support_mask: u32,
+    maximum_transfer_bytes: u32,
+    attributes: SpiIoAttributes,
+    legacy_spi_protocol: *const c_void,
+    transaction: unsafe extern "efiapi" fn(
+        this: *const Self,
+        transaction_type: SpiTransactionType,
+        debug_transaction: bool,
+        clock_hz: u32,
+        bus_width: u32,
+        frame_size: u32,
+        write_bytes: u32,
+        write_buffer: *const u8,
+        read_bytes: u32,
+        read_buffer: *mut u8,
+    ) -> Status,
+    update_spi_peripheral: unsafe extern "efiapi" fn(
+        this: *const Self,
+        spi_peripheral: *const SpiPeripheral,
+    ) -> Status,
+    _marker: PhantomData<*const D>,
+}
+
+unsafe impl<D: SpiPeripheralDriver> Identify for SpiIo<D> {
+    const GUID: Guid = D::DRIVER_GUID;
+}
+
+impl<D: SpiPeripheralDriver> Protocol for SpiIo<D> {}
+
+impl<D: SpiPeripheralDriver> SpiIo<D> {
+    /// Get the peripheral, as currently configured.
+    #[must_use]
+    pub fn peripheral(&self) -> &SpiPeripheral {
+        unsafe { &*self.spi_peripheral }
+    }
+
+    /// Get the peripheral, as described by the platform.
+    #[must_use]
+    pub fn original_peripheral(&self) -> &SpiPeripheral {
+        unsafe { &*self.original_spi_peripheral }
+    }
+
+    /// Get the supported frame sizes: bit `n - 1` is set if frames of `n`
+    /// bits are supported.
+    #[must_use]
+    pub const fn frame_size_support_mask(&self) -> u32 {
+        self.frame_size_support_mask
+    }
+
+    /// Get the maximum length of a transaction in bytes, or 0 if unlimited.
+    #[must_use]
+    pub const fn maximum_transfer_bytes(&self) -> u32 {
+        self.maximum_transfer_bytes
+    }
+
+    /// Get the features of the bus.
+    #[must_use]
+    pub const fn attributes(&self) -> SpiIoAttributes {
+        self.attributes
+    }
+
+    /// Run a transaction on the peripheral, with `bus_width` data lines and
+    /// `frame_size`-bit frames, at most `clock_hz` Hz (0 selects the
+    /// maximum frequency of the peripheral).
+    ///
+    /// `write` is sent before `read` is received, or at the same time for
+    /// [`SpiTransactionType::FULL_DUPLEX`] transactions.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: the transaction is too long.
+    /// * [`Status::INVALID_PARAMETER`]: the buffers don't match the
+    ///   transaction type, or the frame size is not supported.
+    /// * [`Status::UNSUPPORTED`]: the bus width or transaction type is not
+    ///   supported.
+    /// * [`Status::DEVICE_ERROR`]: the transaction failed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn transaction(
+        &mut self,
+        transaction_type: SpiTransactionType,
+        clock_hz: u32,
+        bus_width: u32,
+        frame_size: u32,
+        write: &[u8],
+        read: &mut [u8],
+    ) -> Result {
+        let write_bytes = u32::try_from(write.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let read_bytes = u32::try_from(read.len()).map_err(|_| Status::BAD_BUFFER_SIZE)?;
+        let write_buffer = if write.is_empty() {
+            ptr::null()
+        } else {
+            write.as_ptr()
+        };
+        let read_buffer = if read.is_empty() {
+            ptr::null_mut()
+        } else {
+            read.as_mut_ptr()
+        };
+        unsafe {
+            (self.transaction)(
+                self,
+                transaction_type,
+                false,
+                clock_hz,
+                bus_width,
+                frame_size,
+                write_bytes,
+                write_buffer,
+                read_bytes,
+                read_buffer,
+            )
+        }
+        .into()
+    }
+
+    /// Send `write` to the peripheral, on a single data line with 8-bit
+    /// frames.
+    ///
+    /// # Errors
+    ///
+    /// See [`transaction`](Self::transaction).
+    pub fn write(&mut self, write: &[u8]) -> Result {
+        self.transaction(SpiTransactionType::WRITE_ONLY, 0, 1, 8, write, &mut [])
+    }
+
+    /// Receive `read` from the peripheral, on a single data line with 8-bit
+    /// frames.
+    ///
+    /// # Errors
+    ///
+    /// See [`transaction`](Self::transaction).
+    pub fn read(&mut self, read: &mut [u8]) -> Result {
+        self.transaction(SpiTransactionType::READ_ONLY, 0, 1, 8, &[], read)
+    }
+
+    /// Send `write` to the peripheral, then receive `read` from it, such as
+    /// a command followed by its response, on a single data line with 8-bit
+    /// frames.
+    ///
+    /// # Errors
+    ///
+    /// See [`transaction`](Self::transaction).
+    pub fn write_then_read(&mut self, write: &[u8], read: &mut [u8]) -> Result {
+        self.transaction(SpiTransactionType::WRITE_THEN_READ, 0, 1, 8, write, read)
+    }
+
+    /// Send `write` to the peripheral while receiving `read` from it, on a
+    /// single data line with 8-bit frames. Both buffers must have the same
+    /// length.
+    ///
+    /// # Errors
+    ///
+    /// See [`transaction`](Self::transaction).
+    pub fn full_duplex(&mut self, write: &[u8], read: &mut [u8]) -> Result {
+        self.transaction(SpiTransactionType::FULL_DUPLEX, 0, 1, 8, write, read)
+    }
+
+    /// Replace the configuration of the peripheral with `peripheral`, such
+    /// as to change its maximum clock frequency.
+    ///
+    /// # Safety
+    ///
+    /// `peripheral` is used by the driver until it is replaced again, so it
+    /// must stay valid until then.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `peripheral` is not on the same bus
+    ///   as the original peripheral.
+    pub unsafe fn update_peripheral(&mut self, peripheral: *const SpiPeripheral) -> Result {
+        (self.update_spi_peripheral)(self, peripheral).into()
+    }
+}
+
+impl<D: SpiPeripheralDriver> fmt::Debug for SpiIo<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpiIo")
+            .field("spi_peripheral", self.peripheral())
+            .field("frame_size_support_mask", &self.frame_size_support_mask)
+            .field("maximum_transfer_bytes", &self.maximum_transfer_bytes)
+            .field("attributes", &self.attributes)
+            .finish()
+    }
+}
+
+bitflags! {
+    /// Features of the bus of an [`SpiIo`] protocol.
+    #[repr(transparent)]
+    pub struct SpiIoAttributes: u32 {
+        /// Two data lines can be used.
+        const SUPPORTS_2_BIT_DATA_BUS_WIDTH = 0x0000_0001;
+        /// Four data lines can be used.
+        const SUPPORTS_4_BIT_DATA_BUS_WIDTH = 0x0000_0002;
+        /// Eight data lines can be used.
+        const SUPPORTS_8_BIT_DATA_BUS_WIDTH = 0x0000_0004;
+        /// The maximum transfer size includes the opcode.
+        const TRANSFER_SIZE_INCLUDES_OPCODE = 0x0000_0008;
+        /// The maximum transfer size includes the address.
+        const TRANSFER_SIZE_INCLUDES_ADDRESS = 0x0000_0010;
+    }
+}
+
+newtype_enum! {
+    /// Type of an SPI transaction.
+    ///
+    /// The corresponding C type is `EFI_SPI_TRANSACTION_TYPE`.
+    pub enum SpiTransactionType: u32 => {
+        /// Data is sent and received at the same time.
+        FULL_DUPLEX = 0,
+        /// Data is only sent.
+        WRITE_ONLY = 1,
+        /// Data is only received.
+        READ_ONLY = 2,
+        /// Data is sent, then received.
+        WRITE_THEN_READ = 3,
+    }
+}
+
+/// An SPI peripheral, as described by the platform.
+///
+/// The corresponding C type is `EFI_SPI_PERIPHERAL`.
+#[repr(C)]
+pub struct SpiPeripheral {
+    next_spi_peripheral: *const SpiPeripheral,
+    friendly_name: *const Char16,
+    spi_peripheral_driver_guid: *const Guid,
+    spi_part: *const SpiPart,
+    max_clock_hz: u32,
+    clock_polarity: bool,
+    clock_phase: bool,
+    attributes: u32,
+    configuration_data: *const c_void,
+    spi_bus: *const c_void,
+    chip_select: usize,
+    chip_select_parameter: *mut c_void,
+}
+
+impl SpiPeripheral {
+    /// Get the name of the peripheral, if any.
+    #[must_use]
+    pub fn friendly_name(&self) -> Option<&CStr16> {
+        if self.friendly_name.is_null() {
+            None
+        } else {
+            Some(unsafe { CStr16::from_ptr(self.friendly_name) })
+        }
+    }
+
+    /// Get the GUID of the driver of the peripheral.
+    #[must_use]
+    pub fn driver_guid(&self) -> &Guid {
+        unsafe { &*self.spi_peripheral_driver_guid }
+    }
+
+    /// Get the part used by the peripheral.
+    #[must_use]
+    pub fn part(&self) -> &SpiPart {
+        unsafe { &*self.spi_part }
+    }
+
+    /// Get the maximum clock frequency of the peripheral on its board, or 0
+    /// to use the maximum frequency of the part.
+    #[must_use]
+    pub const fn max_clock_hz(&self) -> u32 {
+        self.max_clock_hz
+    }
+
+    /// Get the clock polarity: whether the clock idles high.
+    #[must_use]
+    pub const fn clock_polarity(&self) -> bool {
+        self.clock_polarity
+    }
+
+    /// Get the clock phase: whether data is sampled on the second clock
+    /// edge.
+    #[must_use]
+    pub const fn clock_phase(&self) -> bool {
+        self.clock_phase
+    }
+
+    /// Get the attributes of the peripheral, with the same bits as
+    /// [`SpiIoAttributes`] for the supported bus widths.
+    #[must_use]
+    pub const fn attributes(&self) -> u32 {
+        self.attributes
+    }
+
+    /// Get the driver-specific configuration of the peripheral.
+    #[must_use]
+    pub const fn configuration_data(&self) -> *const c_void {
+        self.configuration_data
+    }
+}
+
+impl fmt::Debug for SpiPeripheral {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpiPeripheral")
+            .field("friendly_name", &self.friendly_name())
+            .field("driver_guid", self.driver_guid())
+            .field("part", self.part())
+            .field("max_clock_hz", &self.max_clock_hz)
+            .field("clock_polarity", &self.clock_polarity)
+            .field("clock_phase", &self.clock_phase)
+            .field("attributes", &self.attributes)
+            .finish()
+    }
+}
+
+/// An SPI part, such as a flash chip model.
+///
+/// The corresponding C type is `EFI_SPI_PART`.
+#[repr(C)]
+pub struct SpiPart {
+    vendor: *const Char16,
+    part_number: *const Char16,
+    min_clock_hz: u32,
+    max_clock_hz: u32,
+    chip_select_polarity: bool,
+}
+
+impl SpiPart {
+    /// Get the vendor of the part.
+    #[must_use]
+    pub fn vendor(&self) -> &CStr16 {
+        unsafe { CStr16::from_ptr(self.vendor) }
+    }
+
+    /// Get the part number.
+    #[must_use]
+    pub fn part_number(&self) -> &CStr16 {
+        unsafe { CStr16::from_ptr(self.part_number) }
+    }
+
+    /// Get the minimum clock frequency of the part.
+    #[must_use]
+    pub const fn min_clock_hz(&self) -> u32 {
+        self.min_clock_hz
+    }
+
+    /// Get the maximum clock frequency of the part.
+    #[must_use]
+    pub const fn max_clock_hz(&self) -> u32 {
+        self.max_clock_hz
+    }
+
+    /// Get the chip select polarity: whether the chip is selected by a high
+    /// level.
+    #[must_use]
+    pub const fn chip_select_polarity(&self) -> bool {
+        self.chip_select_polarity
+    }
+}
+
+impl fmt::Debug for SpiPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpiPart")
+            .field("vendor", &self.vendor())
+            .field("part_number", &self.part_number())
+            .field("min_clock_hz", &self.min_clock_hz)
+            .field("max_clock_hz", &self.max_clock_hz)
+            .field("chip_select_polarity", &self.chip_select_polarity)
+            .finish()
+    }
+}
+
+/// The SPI host controller protocol, used by the SPI bus driver.
+///
+/// The corresponding C type is `EFI_SPI_HC_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("c74e5db2-fa96-4ae2-b399-15977fe3002d")]
+pub struct SpiHostController {
+    attributes: SpiHostControllerAttributes,
+    frame_size_support_mask: u32,
+    maximum_transfer_bytes: u32,
+    chip_select: unsafe extern "efiapi" fn(
+        this: *const Self,
+        spi_peripheral: *const SpiPeripheral,
+        pin_value: bool,
+    ) -> Status,
+    clock: unsafe extern "efiapi" fn(
+        this: *const Self,
+        spi_peripheral: *const SpiPeripheral,
+        clock_hz: *mut u32,
+    ) -> Status,
+    transaction: unsafe extern "efiapi" fn(
+        this: *const Self,
+        bus_transaction: *mut SpiBusTransaction,
+    ) -> Status,
+}
+
+impl SpiHostController {
+    /// Get the features of the controller.
+    #[must_use]
+    pub const fn attributes(&self) -> SpiHostControllerAttributes {
+        self.attributes
+    }
+
+    /// Get the supported frame sizes: bit `n - 1` is set if frames of `n`
+    /// bits are supported.
+    #[must_use]
+    pub const fn frame_size_support_mask(&self) -> u32 {
+        self.frame_size_support_mask
+    }
+
+    /// Get the maximum length of a transaction in bytes, or 0 if unlimited.
+    #[must_use]
+    pub const fn maximum_transfer_bytes(&self) -> u32 {
+        self.maximum_transfer_bytes
+    }
+
+    /// Set the chip select line of `peripheral` to `pin_value`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the chip select is not handled by
+    ///   the controller.
+    pub fn chip_select(&mut self, peripheral: &SpiPeripheral, pin_value: bool) -> Result {
+        unsafe { (self.chip_select)(self, peripheral, pin_value) }.into()
+    }
+
+    /// Set the clock of the bus to at most `clock_hz` Hz for `peripheral`,
+    /// and return the selected frequency.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the frequency is too low for the
+    ///   controller.
+    pub fn clock(&mut self, peripheral: &SpiPeripheral, clock_hz: u32) -> Result<u32> {
+        let mut clock_hz = clock_hz;
+        unsafe { (self.clock)(self, peripheral, &mut clock_hz) }.into_with_val(|| clock_hz)
+    }
+
+    /// Run `transaction` on the bus. The chip select and clock must have
+    /// been set up beforehand.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BAD_BUFFER_SIZE`]: the transaction is too long.
+    /// * [`Status::INVALID_PARAMETER`]: the transaction is invalid.
+    /// * [`Status::UNSUPPORTED`]: the transaction type or bus width is not
+    ///   supported.
+    /// * [`Status::DEVICE_ERROR`]: the transaction failed.
+    pub fn transaction(&mut self, transaction: &mut SpiBusTransaction<'_>) -> Result {
+        unsafe { (self.transaction)(self, transaction) }.into()
+    }
+}
+
+bitflags! {
+    /// Features of an [`SpiHostController`].
+    #[repr(transparent)]
+    pub struct SpiHostControllerAttributes: u32 {
+        /// Write-only transactions are supported.
+        const SUPPORTS_WRITE_ONLY_OPERATIONS = 0x0000_0001;
+        /// Read-only transactions are supported.
+        const SUPPORTS_READ_ONLY_OPERATIONS = 0x0000_0002;
+        /// Write-then-read transactions are supported.
+        const SUPPORTS_WRITE_THEN_READ_OPERATIONS = 0x0000_0004;
+        /// Frames are sent from the most significant bits of the buffer.
+        const TX_FRAME_IN_MOST_SIGNIFICANT_BITS = 0x0000_0008;
+        /// Frames are received in the most significant bits of the buffer.
+        const RX_FRAME_IN_MOST_SIGNIFICANT_BITS = 0x0000_0010;
+        /// Two data lines can be used.
+        const SUPPORTS_2_BIT_DATA_BUS_WIDTH = 0x0000_0020;
+        /// Four data lines can be used.
+        const SUPPORTS_4_BIT_DATA_BUS_WIDTH = 0x0000_0040;
+        /// Eight data lines can be used.
+        const SUPPORTS_8_BIT_DATA_BUS_WIDTH = 0x0000_0080;
+        /// The maximum transfer size includes the opcode.
+        const TRANSFER_SIZE_INCLUDES_OPCODE = 0x0000_0100;
+        /// The maximum transfer size includes the address.
+        const TRANSFER_SIZE_INCLUDES_ADDRESS = 0x0000_0200;
+    }
+}
+
+/// A transaction run by [`SpiHostController::transaction`].
+///
+/// The corresponding C type is `EFI_SPI_BUS_TRANSACTION`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct SpiBusTransaction<'a> {
+    spi_peripheral: *const SpiPeripheral,
+    transaction_type: SpiTransactionType,
+    debug_transaction: bool,
+    bus_width: u32,
+    frame_size: u32,
+    write_bytes: u32,
+    write_buffer: *const u8,
+    read_bytes: u32,
+    read_buffer: *mut u8,
+    _marker: PhantomData<(&'a SpiPeripheral, &'a mut [u8])>,
+}
+
+impl<'a> SpiBusTransaction<'a> {
+    /// Create a `transaction_type` transaction with `peripheral`, on a
+    /// single data line with 8-bit frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a buffer is longer than `u32::MAX` bytes.
+    #[must_use]
+    pub fn new(
+        peripheral: &'a SpiPeripheral,
+        transaction_type: SpiTransactionType,
+        write: &'a [u8],
+        read: &'a mut [u8],
+    ) -> Self {
+        Self {
+            spi_peripheral: peripheral,
+            transaction_type,
+            debug_transaction: false,
+            bus_width: 1,
+            frame_size: 8,
+            write_bytes: u32::try_from(write.len()).unwrap(),
+            write_buffer: write.as_ptr(),
+            read_bytes: u32::try_from(read.len()).unwrap(),
+            read_buffer: read.as_mut_ptr(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Use `bus_width` data lines and `frame_size`-bit frames.
+    #[must_use]
+    pub const fn with_format(mut self, bus_width: u32, frame_size: u32) -> Self {
+        self.bus_width = bus_width;
+        self.frame_size = frame_size;
+        self
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use crate::{cstr16, guid};
+    use core::slice;
+
+    struct FlashDriver;
+
+    unsafe impl SpiPeripheralDriver for FlashDriver {
+        const DRIVER_GUID: Guid = guid!("aab9d5f9-4ac9-4b27-b1e1-2ae2a1e7b2ef");
+    }
+
+    /// JEDEC ID of the fake flash chip.
+    const JEDEC_ID: [u8; 3] = [0xef, 0x40, 0x18];
+
+    unsafe extern "efiapi" fn transaction(
+        this: *const SpiIo<FlashDriver>,
+        transaction_type: SpiTransactionType,
+        _debug_transaction: bool,
+        _clock_hz: u32,
+        bus_width: u32,
+        _frame_size: u32,
+        write_bytes: u32,
+        write_buffer: *const u8,
+        read_bytes: u32,
+        read_buffer: *mut u8,
+    ) -> Status {
+        if bus_width != 1 {
+            return Status::UNSUPPORTED;
+        }
+        if write_bytes + read_bytes > (*this).maximum_transfer_bytes {
+            return Status::BAD_BUFFER_SIZE;
+        }
+        // Empty buffers are passed as null pointers.
+        let write = if write_buffer.is_null() {
+            &[]
+        } else {
+            slice::from_raw_parts(write_buffer, write_bytes as usize)
+        };
+        let read = if read_buffer.is_null() {
+            &mut []
+        } else {
+            slice::from_raw_parts_mut(read_buffer, read_bytes as usize)
+        };
+        match (transaction_type, write) {
+            // Read JEDEC ID.
+            (SpiTransactionType::WRITE_THEN_READ, [0x9f]) => {
+                let len = read.len().min(JEDEC_ID.len());
+                read[..len].copy_from_slice(&JEDEC_ID[..len]);
+                Status::SUCCESS
+            }
+            (SpiTransactionType::WRITE_ONLY, [_, ..]) => Status::SUCCESS,
+            _ => Status::INVALID_PARAMETER,
+        }
+    }
+
+    unsafe extern "efiapi" fn update_spi_peripheral(
+        _this: *const SpiIo<FlashDriver>,
+        _spi_peripheral: *const SpiPeripheral,
+    ) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    #[test]
+    fn test_spi_io() {
+        let vendor = [b'W' as u16, 0];
+        let part_number = [b'Q' as u16, b'2' as u16, 0];
+        let part = SpiPart {
+            vendor: vendor.as_ptr().cast(),
+            part_number: part_number.as_ptr().cast(),
+            min_clock_hz: 0,
+            max_clock_hz: 50_000_000,
+            chip_select_polarity: false,
+        };
+        let name = [b'S' as u16, b'P' as u16, b'I' as u16, 0];
+        let peripheral = SpiPeripheral {
+            next_spi_peripheral: ptr::null(),
+            friendly_name: name.as_ptr().cast(),
+            spi_peripheral_driver_guid: &FlashDriver::DRIVER_GUID,
+            spi_part: &part,
+            max_clock_hz: 0,
+            clock_polarity: false,
+            clock_phase: false,
+            attributes: 0,
+            configuration_data: ptr::null(),
+            spi_bus: ptr::null(),
+            chip_select: 0,
+            chip_select_parameter: ptr::null_mut(),
+        };
+        let mut protocol = SpiIo::<FlashDriver> {
+            spi_peripheral: &peripheral,
+            original_spi_peripheral: &peripheral,
+            frame_size_support_mask: 1 << 7,
+            maximum_transfer_bytes: 16,
+            attributes: SpiIoAttributes::empty(),
+            legacy_spi_protocol: ptr::null(),
+            transaction,
+            update_spi_peripheral,
+            _marker: PhantomData,
+        };
+
+        let mut firmware = MockFirmware::new();
+        let handle = unsafe { firmware.install_protocol(None, &mut protocol) };
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut spi = bt
+            .open_protocol_exclusive::<SpiIo<FlashDriver>>(handle)
+            .unwrap();
+
+        let peripheral = spi.peripheral();
+        assert_eq!(peripheral.friendly_name().unwrap(), cstr16!("SPI"));
+        assert_eq!(*peripheral.driver_guid(), FlashDriver::DRIVER_GUID);
+        assert_eq!(peripheral.part().part_number(), cstr16!("Q2"));
+        assert_eq!(peripheral.part().max_clock_hz(), 50_000_000);
+
+        let mut id = [0; 3];
+        spi.write_then_read(&[0x9f], &mut id).unwrap();
+        assert_eq!(id, JEDEC_ID);
+        spi.write(&[0x06]).unwrap();
+        assert_eq!(
+            spi.read(&mut id).unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+        assert_eq!(
+            spi.write(&[0; 17]).unwrap_err().status(),
+            Status::BAD_BUFFER_SIZE
+        );
+        assert_eq!(
+            spi.transaction(SpiTransactionType::WRITE_ONLY, 0, 4, 8, &[0x06], &mut [])
+                .unwrap_err()
+                .status(),
+            Status::UNSUPPORTED
+        );
+    }
+}
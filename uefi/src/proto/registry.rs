@@ -0,0 +1,308 @@
+use super::console::absolute_pointer::AbsolutePointer;
+use super::console::edid::{EdidActive, EdidDiscovered};
+use super::console::gop::GraphicsOutput;
+use super::console::pointer::Pointer;
+use super::console::serial::Serial;
+use super::console::text::{Input, InputEx, Output};
+use super::debug::{DebugPort, DebugSupport};
+use super::decompress::Decompress;
+use super::device_path::text::{DevicePathFromText, DevicePathToText};
+use super::device_path::DevicePath;
+#[allow(deprecated)]
+use super::driver::{
+    BusSpecificDriverOverride, ComponentName1, ComponentName2, DriverHealth, PlatformDriverOverride,
+};
+use super::hash::Hash2;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use super::legacy_bios::LegacyBios;
+use super::loaded_image::LoadedImage;
+use super::media::block::BlockIO;
+use super::media::disk::{DiskIo, DiskIo2};
+use super::media::fs::SimpleFileSystem;
+use super::media::partition::PartitionInfo;
+use super::media::ram_disk::RamDisk;
+use super::memory_test::GenericMemoryTest;
+use super::network::arp::Arp;
+use super::network::dhcp4::Dhcp4;
+use super::network::dhcp6::Dhcp6;
+use super::network::iscsi::IscsiInitiatorName;
+use super::network::mnp::ManagedNetwork;
+use super::network::mtftp::v4::Mtftp4;
+use super::network::mtftp::v6::Mtftp6;
+use super::network::nii::NetworkInterfaceIdentifier;
+use super::network::pxe::BaseCode;
+use super::network::rest_ex::RestEx;
+use super::network::snp::SimpleNetwork;
+use super::network::vlan::VlanConfig;
+use super::pci::PciIo;
+use super::pi::i2c::{I2cIo, I2cMaster};
+use super::pi::mp::MpServices;
+use super::pi::spi::SpiHostController;
+use super::riscv::RiscvBoot;
+use super::rng::Rng;
+use super::security::{
+    MemoryProtection, Pkcs7Verify, UserCredential, UserManager, VariableLock, VariablePolicy,
+};
+use super::service_binding::ServiceBindingProtocol;
+use super::shell::dynamic_command::ShellDynamicCommand;
+use super::shell::{Shell, ShellParameters};
+#[cfg(any(
+    target_arch = "x86",
+    target_arch = "x86_64",
+    target_arch = "arm",
+    target_arch = "aarch64"
+))]
+use super::shim::ShimLock;
+use super::string::unicode_collation::UnicodeCollation;
+use super::tcg::{v1, v2};
+use super::usb::hc::Usb2HostController;
+use crate::{guid, Guid, Identify};
+
+/// Well-known protocol GUIDs and the names of the protocols in the UEFI and
+/// PI specifications.
+///
+/// This includes all the protocols implemented in this crate, and some
+/// commonly installed protocols that are not.
+#[allow(deprecated)]
+const KNOWN_PROTOCOLS: &[(Guid, &str)] = &[
+    // Protocols implemented in this crate.
+    (AbsolutePointer::GUID, "EFI_ABSOLUTE_POINTER_PROTOCOL"),
+    (Arp::GUID, "EFI_ARP_PROTOCOL"),
+    (
+        ServiceBindingProtocol::<Arp>::GUID,
+        "EFI_ARP_SERVICE_BINDING_PROTOCOL",
+    ),
+    (BaseCode::GUID, "EFI_PXE_BASE_CODE_PROTOCOL"),
+    (BlockIO::GUID, "EFI_BLOCK_IO_PROTOCOL"),
+    (
+        BusSpecificDriverOverride::GUID,
+        "EFI_BUS_SPECIFIC_DRIVER_OVERRIDE_PROTOCOL",
+    ),
+    (ComponentName1::GUID, "EFI_COMPONENT_NAME_PROTOCOL"),
+    (ComponentName2::GUID, "EFI_COMPONENT_NAME2_PROTOCOL"),
+    (DebugPort::GUID, "EFI_DEBUGPORT_PROTOCOL"),
+    (DebugSupport::GUID, "EFI_DEBUG_SUPPORT_PROTOCOL"),
+    (Decompress::GUID, "EFI_DECOMPRESS_PROTOCOL"),
+    (DevicePath::GUID, "EFI_DEVICE_PATH_PROTOCOL"),
+    (
+        DevicePathFromText::GUID,
+        "EFI_DEVICE_PATH_FROM_TEXT_PROTOCOL",
+    ),
+    (DevicePathToText::GUID, "EFI_DEVICE_PATH_TO_TEXT_PROTOCOL"),
+    (Dhcp4::GUID, "EFI_DHCP4_PROTOCOL"),
+    (
+        ServiceBindingProtocol::<Dhcp4>::GUID,
+        "EFI_DHCP4_SERVICE_BINDING_PROTOCOL",
+    ),
+    (Dhcp6::GUID, "EFI_DHCP6_PROTOCOL"),
+    (
+        ServiceBindingProtocol::<Dhcp6>::GUID,
+        "EFI_DHCP6_SERVICE_BINDING_PROTOCOL",
+    ),
+    (DiskIo::GUID, "EFI_DISK_IO_PROTOCOL"),
+    (DiskIo2::GUID, "EFI_DISK_IO2_PROTOCOL"),
+    (DriverHealth::GUID, "EFI_DRIVER_HEALTH_PROTOCOL"),
+    (EdidActive::GUID, "EFI_EDID_ACTIVE_PROTOCOL"),
+    (EdidDiscovered::GUID, "EFI_EDID_DISCOVERED_PROTOCOL"),
+    (GenericMemoryTest::GUID, "EFI_GENERIC_MEMORY_TEST_PROTOCOL"),
+    (GraphicsOutput::GUID, "EFI_GRAPHICS_OUTPUT_PROTOCOL"),
+    (Hash2::GUID, "EFI_HASH2_PROTOCOL"),
+    (
+        ServiceBindingProtocol::<Hash2>::GUID,
+        "EFI_HASH2_SERVICE_BINDING_PROTOCOL",
+    ),
+    (I2cIo::GUID, "EFI_I2C_IO_PROTOCOL"),
+    (I2cMaster::GUID, "EFI_I2C_MASTER_PROTOCOL"),
+    (Input::GUID, "EFI_SIMPLE_TEXT_INPUT_PROTOCOL"),
+    (InputEx::GUID, "EFI_SIMPLE_TEXT_INPUT_EX_PROTOCOL"),
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    (LegacyBios::GUID, "EFI_LEGACY_BIOS_PROTOCOL"),
+    (
+        IscsiInitiatorName::GUID,
+        "EFI_ISCSI_INITIATOR_NAME_PROTOCOL",
+    ),
+    (LoadedImage::GUID, "EFI_LOADED_IMAGE_PROTOCOL"),
+    (ManagedNetwork::GUID, "EFI_MANAGED_NETWORK_PROTOCOL"),
+    (
+        ServiceBindingProtocol::<ManagedNetwork>::GUID,
+        "EFI_MANAGED_NETWORK_SERVICE_BINDING_PROTOCOL",
+    ),
+    (MemoryProtection::GUID, "EFI_MEMORY_ATTRIBUTE_PROTOCOL"),
+    (MpServices::GUID, "EFI_MP_SERVICES_PROTOCOL"),
+    (Mtftp4::GUID, "EFI_MTFTP4_PROTOCOL"),
+    (
+        ServiceBindingProtocol::<Mtftp4>::GUID,
+        "EFI_MTFTP4_SERVICE_BINDING_PROTOCOL",
+    ),
+    (Mtftp6::GUID, "EFI_MTFTP6_PROTOCOL"),
+    (
+        ServiceBindingProtocol::<Mtftp6>::GUID,
+        "EFI_MTFTP6_SERVICE_BINDING_PROTOCOL",
+    ),
+    (
+        NetworkInterfaceIdentifier::GUID,
+        "EFI_NETWORK_INTERFACE_IDENTIFIER_PROTOCOL",
+    ),
+    (Output::GUID, "EFI_SIMPLE_TEXT_OUTPUT_PROTOCOL"),
+    (PartitionInfo::GUID, "EFI_PARTITION_INFO_PROTOCOL"),
+    (PciIo::GUID, "EFI_PCI_IO_PROTOCOL"),
+    (Pkcs7Verify::GUID, "EFI_PKCS7_VERIFY_PROTOCOL"),
+    (
+        PlatformDriverOverride::GUID,
+        "EFI_PLATFORM_DRIVER_OVERRIDE_PROTOCOL",
+    ),
+    (Pointer::GUID, "EFI_SIMPLE_POINTER_PROTOCOL"),
+    (RamDisk::GUID, "EFI_RAM_DISK_PROTOCOL"),
+    (RestEx::GUID, "EFI_REST_EX_PROTOCOL"),
+    (
+        ServiceBindingProtocol::<RestEx>::GUID,
+        "EFI_REST_EX_SERVICE_BINDING_PROTOCOL",
+    ),
+    (RiscvBoot::GUID, "RISCV_EFI_BOOT_PROTOCOL"),
+    (Rng::GUID, "EFI_RNG_PROTOCOL"),
+    (Serial::GUID, "EFI_SERIAL_IO_PROTOCOL"),
+    (Shell::GUID, "EFI_SHELL_PROTOCOL"),
+    (
+        ShellDynamicCommand::GUID,
+        "EFI_SHELL_DYNAMIC_COMMAND_PROTOCOL",
+    ),
+    (ShellParameters::GUID, "EFI_SHELL_PARAMETERS_PROTOCOL"),
+    #[cfg(any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "arm",
+        target_arch = "aarch64"
+    ))]
+    (ShimLock::GUID, "SHIM_LOCK"),
+    (SimpleFileSystem::GUID, "EFI_SIMPLE_FILE_SYSTEM_PROTOCOL"),
+    (SimpleNetwork::GUID, "EFI_SIMPLE_NETWORK_PROTOCOL"),
+    (SpiHostController::GUID, "EFI_SPI_HC_PROTOCOL"),
+    (v1::Tcg::GUID, "EFI_TCG_PROTOCOL"),
+    (v2::Tcg::GUID, "EFI_TCG2_PROTOCOL"),
+    (UnicodeCollation::GUID, "EFI_UNICODE_COLLATION_PROTOCOL2"),
+    (Usb2HostController::GUID, "EFI_USB2_HC_PROTOCOL"),
+    (UserCredential::GUID, "EFI_USER_CREDENTIAL2_PROTOCOL"),
+    (UserManager::GUID, "EFI_USER_MANAGER_PROTOCOL"),
+    (VariableLock::GUID, "EDKII_VARIABLE_LOCK_PROTOCOL"),
+    (VariablePolicy::GUID, "EDKII_VARIABLE_POLICY_PROTOCOL"),
+    (VlanConfig::GUID, "EFI_VLAN_CONFIG_PROTOCOL"),
+    // Other common protocols.
+    (
+        guid!("1d3de7f0-0807-424f-aa69-11a54e19a46f"),
+        "EFI_ATA_PASS_THRU_PROTOCOL",
+    ),
+    (
+        guid!("a77b2472-e282-4e9f-a245-c2c0e27bbcc1"),
+        "EFI_BLOCK_IO2_PROTOCOL",
+    ),
+    (
+        guid!("0379be4e-d706-437d-b037-edb82fb772a4"),
+        "EFI_DEVICE_PATH_UTILITIES_PROTOCOL",
+    ),
+    (
+        guid!("18a031ab-b443-4d1a-a5c0-0c09261e9f71"),
+        "EFI_DRIVER_BINDING_PROTOCOL",
+    ),
+    (
+        guid!("4d330321-025f-4aac-90d8-5ed900173b63"),
+        "EFI_DRIVER_DIAGNOSTICS2_PROTOCOL",
+    ),
+    (
+        guid!("b1ee129e-da36-4181-91f8-04a4923766a7"),
+        "EFI_DRIVER_FAMILY_OVERRIDE_PROTOCOL",
+    ),
+    (
+        guid!("5c198761-16a8-4e69-972c-89d67954f81d"),
+        "EFI_DRIVER_SUPPORTED_EFI_VERSION_PROTOCOL",
+    ),
+    (
+        guid!("13ac6dd1-73d0-11d4-b06b-00aa00bd6de7"),
+        "EFI_EBC_PROTOCOL",
+    ),
+    (
+        guid!("143b7632-b81b-4cb7-abd3-b625a5b9bffe"),
+        "EFI_EXT_SCSI_PASS_THRU_PROTOCOL",
+    ),
+    (
+        guid!("86c77a67-0b97-4633-a187-49104d0685c7"),
+        "EFI_FIRMWARE_MANAGEMENT_PROTOCOL",
+    ),
+    (
+        guid!("220e73b6-6bdb-4413-8405-b974b108619a"),
+        "EFI_FIRMWARE_VOLUME2_PROTOCOL",
+    ),
+    (
+        guid!("ef9fc172-a1b2-4693-b327-6d32fc416042"),
+        "EFI_HII_DATABASE_PROTOCOL",
+    ),
+    (
+        guid!("56ec3091-954c-11d2-8e3f-00a0c969723b"),
+        "EFI_LOAD_FILE_PROTOCOL",
+    ),
+    (
+        guid!("4006c0c1-fcb3-403e-996d-4a6c8724e06d"),
+        "EFI_LOAD_FILE2_PROTOCOL",
+    ),
+    (
+        guid!("bc62157e-3e33-4fec-9920-2d3b36d750df"),
+        "EFI_LOADED_IMAGE_DEVICE_PATH_PROTOCOL",
+    ),
+    (
+        guid!("52c78312-8edc-4233-98f2-1a1aa5e388a5"),
+        "EFI_NVM_EXPRESS_PASS_THRU_PROTOCOL",
+    ),
+    (
+        guid!("2f707ebb-4a1a-11d4-9a38-0090273fc14d"),
+        "EFI_PCI_ROOT_BRIDGE_IO_PROTOCOL",
+    ),
+    (
+        guid!("c88b0b6d-0dfc-49a7-9cb4-49074b4c3a78"),
+        "EFI_STORAGE_SECURITY_COMMAND_PROTOCOL",
+    ),
+    (
+        guid!("2b2f68d6-0cd2-44cf-8e8b-bba20b1b5b75"),
+        "EFI_USB_IO_PROTOCOL",
+    ),
+];
+
+/// Get the name of a well-known protocol from its GUID, for diagnostics.
+///
+/// The name is the one used in the UEFI or PI specification, such as
+/// `"EFI_SIMPLE_FILE_SYSTEM_PROTOCOL"`. This covers all the protocols
+/// implemented in this crate, and some other commonly installed protocols.
+/// Returns `None` for unknown GUIDs.
+#[must_use]
+pub fn protocol_name(guid: &Guid) -> Option<&'static str> {
+    KNOWN_PROTOCOLS
+        .iter()
+        .find(|(known, _)| known == guid)
+        .map(|&(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_name() {
+        assert_eq!(
+            protocol_name(&SimpleFileSystem::GUID),
+            Some("EFI_SIMPLE_FILE_SYSTEM_PROTOCOL")
+        );
+        assert_eq!(
+            protocol_name(&guid!("18a031ab-b443-4d1a-a5c0-0c09261e9f71")),
+            Some("EFI_DRIVER_BINDING_PROTOCOL")
+        );
+        assert_eq!(protocol_name(&Guid::default()), None);
+
+        // GUIDs are unique.
+        for (i, (guid, name)) in KNOWN_PROTOCOLS.iter().enumerate() {
+            assert!(
+                KNOWN_PROTOCOLS[i + 1..]
+                    .iter()
+                    .all(|(other, _)| other != guid),
+                "duplicate GUID for {name}"
+            );
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! RISC-V boot protocol.
+
+use crate::proto::unsafe_protocol;
+use crate::{Result, Status};
+
+/// The RISC-V boot protocol, used by RISC-V firmware to pass the ID of the
+/// boot hart (hardware thread) to the OS loader.
+///
+/// Before this protocol was defined, the boot hart ID was passed in the
+/// `/chosen/boot-hartid` property of the device tree. The protocol is
+/// preferred when both are available.
+///
+/// The corresponding C type is `RISCV_EFI_BOOT_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("ccd15fec-6f73-4eec-8395-3e69e4b940bf")]
+pub struct RiscvBoot {
+    revision: u64,
+    get_boot_hartid: unsafe extern "efiapi" fn(this: *const Self, hart_id: *mut usize) -> Status,
+}
+
+impl RiscvBoot {
+    /// Get the revision of the protocol.
+    #[must_use]
+    pub const fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Get the ID of the hart the OS loader is running on.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    pub fn boot_hart_id(&self) -> Result<usize> {
+        let mut hart_id = 0;
+        unsafe { (self.get_boot_hartid)(self, &mut hart_id) }.into_with_val(|| hart_id)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use core::marker::PhantomData;
+
+    unsafe extern "efiapi" fn get_boot_hartid(
+        this: *const RiscvBoot,
+        hart_id: *mut usize,
+    ) -> Status {
+        if (*this).revision == 0 {
+            return Status::INVALID_PARAMETER;
+        }
+        hart_id.write(3);
+        Status::SUCCESS
+    }
+
+    #[test]
+    fn test_boot_hart_id() {
+        let mut firmware = MockFirmware::new();
+        let mut boot = RiscvBoot {
+            revision: 0x0001_0000,
+            get_boot_hartid,
+            _no_send_or_sync: PhantomData,
+        };
+        let handle = unsafe { firmware.install_protocol(None, &mut boot) };
+        let st = firmware.system_table();
+        let boot = st
+            .boot_services()
+            .open_protocol_exclusive::<RiscvBoot>(handle)
+            .unwrap();
+        assert_eq!(boot.revision(), 0x0001_0000);
+        assert_eq!(boot.boot_hart_id(), Ok(3));
+    }
+}
@@ -59,7 +59,13 @@ impl Rng {
         let mut algorithm_list_size = algorithm_list.len() * mem::size_of::<RngAlgorithmType>();
 
         unsafe {
-            (self.get_info)(self, &mut algorithm_list_size, algorithm_list.as_mut_ptr()).into_with(
+            trace_status!(
+                "Rng::get_info",
+                (self.get_info)(self, &mut algorithm_list_size, algorithm_list.as_mut_ptr()),
+                "size={}",
+                algorithm_list_size
+            )
+            .into_with(
                 || {
                     let len = algorithm_list_size / mem::size_of::<RngAlgorithmType>();
                     &algorithm_list[..len]
@@ -84,6 +90,15 @@ impl Rng {
             Some(algo) => algo as *const RngAlgorithmType,
         };
 
-        unsafe { (self.get_rng)(self, algo, buffer_length, buffer.as_mut_ptr()).into() }
+        unsafe {
+            trace_status!(
+                "Rng::get_rng",
+                (self.get_rng)(self, algo, buffer_length, buffer.as_mut_ptr()),
+                "algorithm={:?}, length={}",
+                algorithm,
+                buffer_length
+            )
+            .into()
+        }
     }
 }
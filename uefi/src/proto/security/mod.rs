@@ -1,4 +1,19 @@
 //! Protocols related to secure technologies.
 
 mod memory_protection;
+mod pkcs7;
+mod signature;
+mod user;
+mod variable_policy;
 pub use memory_protection::MemoryProtection;
+pub use pkcs7::{Pkcs7Verify, SignatureDatabases};
+pub use signature::{SignatureData, SignatureList, SignatureLists, SignatureType, Signatures};
+pub use user::{
+    CredentialCapabilities, CredentialLogonFlags, CredentialTile, HiiHandle, UserCredential,
+    UserInfo, UserInfoAttributes, UserInfoHandle, UserInfoHandles, UserInfoType, UserManager,
+    UserProfile, UserProfiles,
+};
+pub use variable_policy::{
+    VariableConstraints, VariableLock, VariableLockPolicy, VariableLockPolicyType, VariablePolicy,
+    VariablePolicyEntries, VariablePolicyEntry, VariableStateLock,
+};
@@ -0,0 +1,339 @@
+use super::SignatureList;
+use crate::proto::unsafe_protocol;
+use crate::{Error, Result, Status};
+use core::ffi::c_void;
+use core::{fmt, ptr};
+
+/// The signature databases used to verify a PKCS#7 signature.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SignatureDatabases<'a> {
+    allowed: &'a [&'a SignatureList],
+    revoked: &'a [&'a SignatureList],
+    timestamp: &'a [&'a SignatureList],
+}
+
+impl<'a> SignatureDatabases<'a> {
+    /// Create databases trusting the certificates of `allowed`, such as the
+    /// lists of the Secure Boot `db` variable.
+    #[must_use]
+    pub const fn new(allowed: &'a [&'a SignatureList]) -> Self {
+        Self {
+            allowed,
+            revoked: &[],
+            timestamp: &[],
+        }
+    }
+
+    /// Reject signatures matching the certificates or hashes of `revoked`,
+    /// such as the lists of the Secure Boot `dbx` variable.
+    #[must_use]
+    pub const fn with_revoked(mut self, revoked: &'a [&'a SignatureList]) -> Self {
+        self.revoked = revoked;
+        self
+    }
+
+    /// Trust the time-stamping authorities of `timestamp` to accept
+    /// signatures made before a certificate was revoked, such as the lists
+    /// of the Secure Boot `dbt` variable.
+    #[must_use]
+    pub const fn with_timestamp(mut self, timestamp: &'a [&'a SignatureList]) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Get the allowed, revoked and timestamp databases in the protocol
+    /// format.
+    fn raw(&self) -> Result<[RawDatabase; 3]> {
+        Ok([
+            RawDatabase::new(self.allowed)?,
+            RawDatabase::new(self.revoked)?,
+            RawDatabase::new(self.timestamp)?,
+        ])
+    }
+}
+
+/// A null-terminated array of pointers to signature lists, as expected by
+/// the protocol.
+struct RawDatabase([*const c_void; Pkcs7Verify::MAX_DATABASE_LISTS + 1]);
+
+impl RawDatabase {
+    fn new(lists: &[&SignatureList]) -> Result<Self> {
+        if lists.len() > Pkcs7Verify::MAX_DATABASE_LISTS {
+            return Err(Status::INVALID_PARAMETER.into());
+        }
+        let mut ptrs = [ptr::null(); Pkcs7Verify::MAX_DATABASE_LISTS + 1];
+        for (ptr, list) in ptrs.iter_mut().zip(lists) {
+            *ptr = list.as_bytes().as_ptr().cast();
+        }
+        Ok(Self(ptrs))
+    }
+
+    /// Get the array, or null if it is empty.
+    fn as_ptr(&self) -> *const *const c_void {
+        if self.0[0].is_null() {
+            ptr::null()
+        } else {
+            self.0.as_ptr()
+        }
+    }
+}
+
+/// The PKCS#7 verification protocol, verifying signed data with the
+/// cryptographic code of the firmware.
+///
+/// The corresponding C type is `EFI_PKCS7_VERIFY_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("47889fb2-d671-4fab-a0ca-df0e44df70d6")]
+pub struct Pkcs7Verify {
+    verify_buffer: unsafe extern "efiapi" fn(
+        this: *const Self,
+        signed_data: *const c_void,
+        signed_data_size: usize,
+        in_data: *const c_void,
+        in_data_size: usize,
+        allowed_db: *const *const c_void,
+        revoked_db: *const *const c_void,
+        timestamp_db: *const *const c_void,
+        content: *mut c_void,
+        content_size: *mut usize,
+    ) -> Status,
+    verify_signature: unsafe extern "efiapi" fn(
+        this: *const Self,
+        signature: *const c_void,
+        signature_size: usize,
+        in_hash: *const c_void,
+        in_hash_size: usize,
+        allowed_db: *const *const c_void,
+        revoked_db: *const *const c_void,
+        timestamp_db: *const *const c_void,
+    ) -> Status,
+}
+
+impl Pkcs7Verify {
+    /// Maximum number of lists in each of the [`SignatureDatabases`].
+    pub const MAX_DATABASE_LISTS: usize = 32;
+
+    /// Verify the PKCS#7 `SignedData` of `signed_data` against `databases`.
+    ///
+    /// If the signature is detached, the signed data is passed as
+    /// `detached_data`. Otherwise, the signed data embedded in
+    /// `signed_data` is copied to `content` and returned.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::SECURITY_VIOLATION`]: the signature is not trusted by
+    ///   the allowed database, or it is revoked.
+    /// * [`Status::UNSUPPORTED`]: `signed_data` is not a valid PKCS#7
+    ///   `SignedData`.
+    /// * [`Status::BUFFER_TOO_SMALL`]: `content` is too small for the
+    ///   embedded data, the required size is returned in the error data.
+    /// * [`Status::INVALID_PARAMETER`]: a database holds more than
+    ///   [`MAX_DATABASE_LISTS`] lists.
+    ///
+    /// [`MAX_DATABASE_LISTS`]: Self::MAX_DATABASE_LISTS
+    pub fn verify_buffer<'buf>(
+        &self,
+        signed_data: &[u8],
+        detached_data: Option<&[u8]>,
+        databases: &SignatureDatabases,
+        content: &'buf mut [u8],
+    ) -> Result<&'buf [u8], Option<usize>> {
+        let [allowed, revoked, timestamp] = databases
+            .raw()
+            .map_err(|err| Error::new(err.status(), None))?;
+        let (in_data, in_data_size) =
+            detached_data.map_or((ptr::null(), 0), |data| (data.as_ptr().cast(), data.len()));
+
+        let mut size = content.len();
+        unsafe {
+            (self.verify_buffer)(
+                self,
+                signed_data.as_ptr().cast(),
+                signed_data.len(),
+                in_data,
+                in_data_size,
+                allowed.as_ptr(),
+                revoked.as_ptr(),
+                timestamp.as_ptr(),
+                content.as_mut_ptr().cast(),
+                &mut size,
+            )
+        }
+        .into_with(
+            || (),
+            |status| (status == Status::BUFFER_TOO_SMALL).then_some(size),
+        )?;
+        Ok(&content[..size.min(content.len())])
+    }
+
+    /// Verify the detached PKCS#7 signature `signature` of data whose hash
+    /// is `hash` against `databases`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::SECURITY_VIOLATION`]: the signature is not trusted by
+    ///   the allowed database, or it is revoked.
+    /// * [`Status::UNSUPPORTED`]: `signature` is not a valid PKCS#7
+    ///   signature, or the hash algorithm is not supported.
+    /// * [`Status::INVALID_PARAMETER`]: a database holds more than
+    ///   [`MAX_DATABASE_LISTS`] lists.
+    ///
+    /// [`MAX_DATABASE_LISTS`]: Self::MAX_DATABASE_LISTS
+    pub fn verify_signature(
+        &self,
+        signature: &[u8],
+        hash: &[u8],
+        databases: &SignatureDatabases,
+    ) -> Result {
+        let [allowed, revoked, timestamp] = databases.raw()?;
+        unsafe {
+            (self.verify_signature)(
+                self,
+                signature.as_ptr().cast(),
+                signature.len(),
+                hash.as_ptr().cast(),
+                hash.len(),
+                allowed.as_ptr(),
+                revoked.as_ptr(),
+                timestamp.as_ptr(),
+            )
+        }
+        .into()
+    }
+}
+
+impl fmt::Debug for Pkcs7Verify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs7Verify").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::security::SignatureType;
+    use crate::Guid;
+    use core::marker::PhantomData;
+    use core::slice;
+
+    /// Count the lists of a database.
+    unsafe fn db_len(db: *const *const c_void) -> usize {
+        if db.is_null() {
+            return 0;
+        }
+        (0..).take_while(|&i| !(*db.add(i)).is_null()).count()
+    }
+
+    // The fake protocol trusts signatures starting with a signature of the
+    // first allowed list, and embeds the data after the signature.
+    unsafe fn trusted(signature: &[u8], allowed: *const *const c_void) -> bool {
+        if db_len(allowed) == 0 {
+            return false;
+        }
+        let list = (*allowed).cast::<u8>();
+        let size = list.add(16).cast::<u32>().read_unaligned() as usize;
+        let list = SignatureList::from_bytes(slice::from_raw_parts(list, size)).unwrap();
+        list.signatures().any(|sig| signature.starts_with(sig.data))
+    }
+
+    unsafe extern "efiapi" fn verify_buffer(
+        _this: *const Pkcs7Verify,
+        signed_data: *const c_void,
+        signed_data_size: usize,
+        _in_data: *const c_void,
+        _in_data_size: usize,
+        allowed_db: *const *const c_void,
+        revoked_db: *const *const c_void,
+        _timestamp_db: *const *const c_void,
+        content: *mut c_void,
+        content_size: *mut usize,
+    ) -> Status {
+        let signed_data = slice::from_raw_parts(signed_data.cast::<u8>(), signed_data_size);
+        if !trusted(signed_data, allowed_db) || db_len(revoked_db) != 0 {
+            return Status::SECURITY_VIOLATION;
+        }
+        let data = &signed_data[4..];
+        if *content_size < data.len() {
+            *content_size = data.len();
+            return Status::BUFFER_TOO_SMALL;
+        }
+        *content_size = data.len();
+        slice::from_raw_parts_mut(content.cast::<u8>(), data.len()).copy_from_slice(data);
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn verify_signature(
+        _this: *const Pkcs7Verify,
+        signature: *const c_void,
+        signature_size: usize,
+        _in_hash: *const c_void,
+        _in_hash_size: usize,
+        allowed_db: *const *const c_void,
+        revoked_db: *const *const c_void,
+        _timestamp_db: *const *const c_void,
+    ) -> Status {
+        let signature = slice::from_raw_parts(signature.cast::<u8>(), signature_size);
+        if trusted(signature, allowed_db) && db_len(revoked_db) == 0 {
+            Status::SUCCESS
+        } else {
+            Status::SECURITY_VIOLATION
+        }
+    }
+
+    #[test]
+    fn test_pkcs7_verify() {
+        let pkcs7 = Pkcs7Verify {
+            verify_buffer,
+            verify_signature,
+            _no_send_or_sync: PhantomData,
+        };
+
+        let mut db_buf = [0; 64];
+        let cert = [1, 2, 3, 4];
+        let list = SignatureList::new_in(&mut db_buf, SignatureType::X509, Guid::default(), &cert)
+            .unwrap();
+        let allowed = [list];
+        let databases = SignatureDatabases::new(&allowed);
+
+        let mut content = [0; 8];
+        assert_eq!(
+            pkcs7
+                .verify_buffer(&[1, 2, 3, 4, 5, 6], None, &databases, &mut content)
+                .unwrap(),
+            [5, 6]
+        );
+        let err = pkcs7
+            .verify_buffer(
+                &[1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+                None,
+                &databases,
+                &mut content,
+            )
+            .unwrap_err();
+        assert_eq!(err.status(), Status::BUFFER_TOO_SMALL);
+        assert_eq!(*err.data(), Some(9));
+
+        pkcs7.verify_signature(&cert, &[0; 32], &databases).unwrap();
+        assert_eq!(
+            pkcs7
+                .verify_signature(&[4, 3, 2, 1], &[0; 32], &databases)
+                .unwrap_err()
+                .status(),
+            Status::SECURITY_VIOLATION
+        );
+        assert_eq!(
+            pkcs7
+                .verify_signature(&cert, &[0; 32], &databases.with_revoked(&allowed))
+                .unwrap_err()
+                .status(),
+            Status::SECURITY_VIOLATION
+        );
+        assert_eq!(
+            pkcs7
+                .verify_signature(&cert, &[0; 32], &SignatureDatabases::new(&[list; 33]))
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+    }
+}
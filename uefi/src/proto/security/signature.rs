@@ -0,0 +1,239 @@
+use crate::{guid, Guid};
+use core::fmt;
+
+newtype_enum! {
+    /// The type of the signatures of a [`SignatureList`].
+    pub enum SignatureType: Guid => {
+        /// SHA-1 hash, 20 bytes.
+        SHA1 = guid!("826ca512-cf10-4ac9-b187-be01496631bd"),
+        /// SHA-256 hash, 32 bytes.
+        SHA256 = guid!("c1c41626-504c-4092-aca9-41f936934328"),
+        /// SHA-384 hash, 48 bytes.
+        SHA384 = guid!("ff3e5307-9fd0-48c9-85f1-8ad56c701e01"),
+        /// SHA-512 hash, 64 bytes.
+        SHA512 = guid!("093e0fae-a6c4-4f50-9f1b-d41e2b89c19a"),
+        /// RSA-2048 public key modulus, 256 bytes.
+        RSA2048 = guid!("3c5766e8-269c-4e34-aa14-ed776e85b3b6"),
+        /// DER-encoded X.509 certificate.
+        X509 = guid!("a5c059a1-94e4-4aa7-87b5-ab155c2bf072"),
+        /// SHA-256 hash of the to-be-signed part of an X.509 certificate,
+        /// followed by its revocation time.
+        X509_SHA256 = guid!("3bd2a492-96c0-4079-b420-fcf98ef103ed"),
+        /// SHA-384 hash of the to-be-signed part of an X.509 certificate,
+        /// followed by its revocation time.
+        X509_SHA384 = guid!("7076876e-80c2-4ee6-aad2-28b349a6865b"),
+        /// SHA-512 hash of the to-be-signed part of an X.509 certificate,
+        /// followed by its revocation time.
+        X509_SHA512 = guid!("446dbf63-2502-4cda-bcfa-2465d2b0fe9d"),
+        /// DER-encoded PKCS#7 `SignedData`.
+        PKCS7 = guid!("4aafd29d-68df-49ee-8aa9-347d375665a7"),
+    }
+}
+
+/// A list of signatures of the same type, such as the certificates of the
+/// Secure Boot `db` variable. The variable holds several lists one after
+/// the other, which can be parsed with [`SignatureLists`].
+///
+/// The corresponding C type is `EFI_SIGNATURE_LIST`.
+#[derive(Eq, PartialEq)]
+#[repr(transparent)]
+pub struct SignatureList([u8]);
+
+impl SignatureList {
+    /// Size of the fixed part of the list header.
+    pub const HEADER_SIZE: usize = 28;
+
+    /// Size of the owner GUID at the start of each signature.
+    pub const OWNER_SIZE: usize = 16;
+
+    /// Parse the list at the start of `bytes`. Returns `None` if the sizes
+    /// in the header are inconsistent, or if `bytes` is too short.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<&Self> {
+        let field = |offset: usize| -> Option<usize> {
+            let field = bytes.get(offset..offset + 4)?;
+            Some(u32::from_le_bytes(field.try_into().unwrap()) as usize)
+        };
+        let list_size = field(16)?;
+        let header_size = field(20)?;
+        let signature_size = field(24)?;
+
+        let signatures_size = list_size.checked_sub(Self::HEADER_SIZE + header_size)?;
+        if signature_size < Self::OWNER_SIZE || signatures_size % signature_size != 0 {
+            return None;
+        }
+        let bytes = bytes.get(..list_size)?;
+        // Safety: `SignatureList` is a transparent wrapper of `[u8]`.
+        Some(unsafe { &*(bytes as *const [u8] as *const Self) })
+    }
+
+    /// Write a list holding a single signature in `buf`, and return it. If
+    /// `buf` is too small, the required size is returned as an error.
+    pub fn new_in<'buf>(
+        buf: &'buf mut [u8],
+        signature_type: SignatureType,
+        owner: Guid,
+        signature: &[u8],
+    ) -> Result<&'buf Self, usize> {
+        let signature_size = Self::OWNER_SIZE + signature.len();
+        let size = Self::HEADER_SIZE + signature_size;
+        let buf = buf.get_mut(..size).ok_or(size)?;
+        let size_field = |size: usize| u32::try_from(size).map(u32::to_le_bytes);
+        buf[..16].copy_from_slice(&signature_type.0.to_bytes());
+        buf[16..20].copy_from_slice(&size_field(size).map_err(|_| size)?);
+        buf[20..24].copy_from_slice(&[0; 4]);
+        buf[24..28].copy_from_slice(&size_field(signature_size).map_err(|_| size)?);
+        buf[28..44].copy_from_slice(&owner.to_bytes());
+        buf[44..].copy_from_slice(signature);
+        // OK to unwrap: the list is valid.
+        Ok(Self::from_bytes(buf).unwrap())
+    }
+
+    /// Get the type of the signatures.
+    #[must_use]
+    pub fn signature_type(&self) -> SignatureType {
+        SignatureType(Guid::from_bytes(self.0[..16].try_into().unwrap()))
+    }
+
+    /// Get the type-specific header following the fixed header.
+    #[must_use]
+    pub fn header(&self) -> &[u8] {
+        &self.0[Self::HEADER_SIZE..Self::HEADER_SIZE + self.header_size()]
+    }
+
+    /// Get an iterator over the signatures of the list.
+    #[must_use]
+    pub fn signatures(&self) -> Signatures<'_> {
+        let size = u32::from_le_bytes(self.0[24..28].try_into().unwrap()) as usize;
+        Signatures {
+            chunks: self.0[Self::HEADER_SIZE + self.header_size()..].chunks_exact(size),
+        }
+    }
+
+    /// Get the whole list, including the header.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    fn header_size(&self) -> usize {
+        u32::from_le_bytes(self.0[20..24].try_into().unwrap()) as usize
+    }
+}
+
+impl fmt::Debug for SignatureList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignatureList")
+            .field("signature_type", &self.signature_type())
+            .field("header", &self.header())
+            .field("signatures", &self.signatures().count())
+            .finish()
+    }
+}
+
+/// A signature of a [`SignatureList`].
+///
+/// The corresponding C type is `EFI_SIGNATURE_DATA`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SignatureData<'a> {
+    /// GUID of the agent which added the signature.
+    pub owner: Guid,
+    /// The signature, whose format depends on the [`SignatureType`] of the
+    /// list.
+    pub data: &'a [u8],
+}
+
+/// Iterator returned by [`SignatureList::signatures`].
+#[derive(Debug, Clone)]
+pub struct Signatures<'a> {
+    chunks: core::slice::ChunksExact<'a, u8>,
+}
+
+impl<'a> Iterator for Signatures<'a> {
+    type Item = SignatureData<'a>;
+
+    fn next(&mut self) -> Option<SignatureData<'a>> {
+        let chunk = self.chunks.next()?;
+        let (owner, data) = chunk.split_at(SignatureList::OWNER_SIZE);
+        Some(SignatureData {
+            owner: Guid::from_bytes(owner.try_into().unwrap()),
+            data,
+        })
+    }
+}
+
+/// Iterator over consecutive [`SignatureList`]s, such as the contents of
+/// the Secure Boot `db` and `dbx` variables.
+///
+/// Iteration stops at the first invalid list.
+#[derive(Debug, Clone)]
+pub struct SignatureLists<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> SignatureLists<'a> {
+    /// Create an iterator over the lists of `bytes`.
+    #[must_use]
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+}
+
+impl<'a> Iterator for SignatureLists<'a> {
+    type Item = &'a SignatureList;
+
+    fn next(&mut self) -> Option<&'a SignatureList> {
+        match SignatureList::from_bytes(self.bytes) {
+            Some(list) => {
+                self.bytes = &self.bytes[list.as_bytes().len()..];
+                Some(list)
+            }
+            None => {
+                self.bytes = &[];
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_list() {
+        let owner = guid!("12345678-9abc-def0-1234-56789abcdef0");
+        let mut buf = [0; 128];
+        assert_eq!(
+            SignatureList::new_in(&mut buf[..50], SignatureType::SHA256, owner, &[1; 32]),
+            Err(76)
+        );
+        let list = SignatureList::new_in(&mut buf, SignatureType::SHA256, owner, &[1; 32]).unwrap();
+        assert_eq!(list.as_bytes().len(), 76);
+        assert_eq!(list.signature_type(), SignatureType::SHA256);
+        assert_eq!(list.header(), [0u8; 0]);
+        let mut signatures = list.signatures();
+        assert_eq!(
+            signatures.next(),
+            Some(SignatureData {
+                owner,
+                data: &[1; 32]
+            })
+        );
+        assert_eq!(signatures.next(), None);
+
+        // Two consecutive lists, followed by garbage.
+        let mut db = [0xff; 160];
+        db[..76].copy_from_slice(&buf[..76]);
+        SignatureList::new_in(&mut db[76..], SignatureType::X509, owner, &[2; 40]).unwrap();
+        let types: [_; 2] = [SignatureType::SHA256, SignatureType::X509];
+        assert!(SignatureLists::new(&db)
+            .map(SignatureList::signature_type)
+            .eq(types));
+
+        // Inconsistent sizes.
+        buf[24] = 40;
+        assert!(SignatureList::from_bytes(&buf).is_none());
+        assert!(SignatureList::from_bytes(&buf[..20]).is_none());
+    }
+}
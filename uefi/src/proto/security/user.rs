@@ -0,0 +1,974 @@
+use crate::proto::unsafe_protocol;
+use crate::{Error, Guid, Handle, Result, Status};
+use bitflags::bitflags;
+use core::ffi::c_void;
+use core::fmt;
+use core::ptr::NonNull;
+
+/// The user manager protocol, giving access to the user profiles of the
+/// platform.
+///
+/// Each user profile holds a list of [`UserInfo`] records, such as the name
+/// of the user or the credentials required to log on. Users are identified
+/// with the [`UserCredential`] protocols of the credential providers, such
+/// as a password prompt or a smart card reader.
+///
+/// The corresponding C type is `EFI_USER_MANAGER_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("6fd5b00c-d426-4283-9887-6cf5cf1cb1fe")]
+pub struct UserManager {
+    create: unsafe extern "efiapi" fn(this: *const Self, user: *mut Option<UserProfile>) -> Status,
+    delete: unsafe extern "efiapi" fn(this: *const Self, user: UserProfile) -> Status,
+    get_next:
+        unsafe extern "efiapi" fn(this: *const Self, user: *mut Option<UserProfile>) -> Status,
+    current: unsafe extern "efiapi" fn(
+        this: *const Self,
+        current_user: *mut Option<UserProfile>,
+    ) -> Status,
+    identify:
+        unsafe extern "efiapi" fn(this: *const Self, user: *mut Option<UserProfile>) -> Status,
+    find: unsafe extern "efiapi" fn(
+        this: *const Self,
+        user: *mut Option<UserProfile>,
+        user_info: *mut Option<UserInfoHandle>,
+        info: *const u8,
+        info_size: usize,
+    ) -> Status,
+    notify: unsafe extern "efiapi" fn(this: *const Self, changed: Handle) -> Status,
+    get_info: unsafe extern "efiapi" fn(
+        this: *const Self,
+        user: UserProfile,
+        user_info: UserInfoHandle,
+        info: *mut u8,
+        info_size: *mut usize,
+    ) -> Status,
+    set_info: unsafe extern "efiapi" fn(
+        this: *const Self,
+        user: UserProfile,
+        user_info: *mut Option<UserInfoHandle>,
+        info: *const u8,
+        info_size: usize,
+    ) -> Status,
+    delete_info: unsafe extern "efiapi" fn(
+        this: *const Self,
+        user: UserProfile,
+        user_info: UserInfoHandle,
+    ) -> Status,
+    get_next_info: unsafe extern "efiapi" fn(
+        this: *const Self,
+        user: UserProfile,
+        user_info: *mut Option<UserInfoHandle>,
+    ) -> Status,
+}
+
+impl UserManager {
+    /// Create a new, empty, user profile.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the current user is not allowed to
+    ///   create users.
+    pub fn create(&mut self) -> Result<UserProfile> {
+        let mut user = None;
+        // OK to unwrap: a profile is returned on success.
+        unsafe { (self.create)(self, &mut user) }.into_with_val(|| user.unwrap())
+    }
+
+    /// Delete the user profile `user`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the current user is not allowed to
+    ///   delete users, or `user` is the current user.
+    /// * [`Status::NOT_FOUND`]: `user` does not exist.
+    pub fn delete(&mut self, user: UserProfile) -> Result {
+        unsafe { (self.delete)(self, user) }.into()
+    }
+
+    /// Get an iterator over the user profiles.
+    #[must_use]
+    pub const fn profiles(&self) -> UserProfiles<'_> {
+        UserProfiles {
+            manager: self,
+            user: None,
+            done: false,
+        }
+    }
+
+    /// Get the user profile of the current user.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: no user is logged on.
+    pub fn current(&self) -> Result<UserProfile> {
+        let mut user = None;
+        match unsafe { (self.current)(self, &mut user) } {
+            Status::SUCCESS => user.ok_or_else(|| Status::NOT_FOUND.into()),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Identify the user, using the credential providers, and make it the
+    /// current user.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the user could not be identified.
+    pub fn identify(&mut self) -> Result<UserProfile> {
+        let mut user = None;
+        match unsafe { (self.identify)(self, &mut user) } {
+            Status::SUCCESS => user.ok_or_else(|| Status::ACCESS_DENIED.into()),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Find the first user profile after `start` with a record matching
+    /// `info`, or the first matching profile if `start` is `None`. Returns
+    /// the profile and the matching record.
+    ///
+    /// The records match if they have the same type and data. If `info` has
+    /// no data, only the type is compared.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: no profile matches.
+    pub fn find(
+        &self,
+        start: Option<UserProfile>,
+        info: &UserInfo,
+    ) -> Result<(UserProfile, UserInfoHandle)> {
+        let mut user = start;
+        let mut user_info = None;
+        let bytes = info.as_bytes();
+        match unsafe { (self.find)(self, &mut user, &mut user_info, bytes.as_ptr(), bytes.len()) } {
+            Status::SUCCESS => user.zip(user_info).ok_or_else(|| Status::NOT_FOUND.into()),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Notify the user manager that the credential provider installed on
+    /// `changed` has been added or has changed.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: no credential provider is installed
+    ///   on `changed`.
+    pub fn notify(&mut self, changed: Handle) -> Result {
+        unsafe { (self.notify)(self, changed) }.into()
+    }
+
+    /// Read the record `info` of `user` into `buf`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buf` is too small, the required size
+    ///   is returned in the error data.
+    /// * [`Status::ACCESS_DENIED`]: the record is protected.
+    /// * [`Status::NOT_FOUND`]: `user` or `info` does not exist.
+    pub fn get_info<'buf>(
+        &self,
+        user: UserProfile,
+        info: UserInfoHandle,
+        buf: &'buf mut [u8],
+    ) -> Result<&'buf UserInfo, Option<usize>> {
+        let mut size = buf.len();
+        unsafe { (self.get_info)(self, user, info, buf.as_mut_ptr(), &mut size) }.into_with(
+            || (),
+            |status| (status == Status::BUFFER_TOO_SMALL).then_some(size),
+        )?;
+        UserInfo::from_bytes(&buf[..size.min(buf.len())])
+            .ok_or_else(|| Error::new(Status::VOLUME_CORRUPTED, None))
+    }
+
+    /// Add the record `info` to `user`, or replace the record `existing`
+    /// with it. Returns the handle of the record.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the current user is not allowed to
+    ///   change the record, or it is [`UserInfoAttributes::EXCLUSIVE`] and
+    ///   a record of the same type exists.
+    /// * [`Status::NOT_FOUND`]: `user` or `existing` does not exist.
+    pub fn set_info(
+        &mut self,
+        user: UserProfile,
+        existing: Option<UserInfoHandle>,
+        info: &UserInfo,
+    ) -> Result<UserInfoHandle> {
+        let mut handle = existing;
+        let bytes = info.as_bytes();
+        match unsafe { (self.set_info)(self, user, &mut handle, bytes.as_ptr(), bytes.len()) } {
+            Status::SUCCESS => handle.ok_or_else(|| Status::NOT_FOUND.into()),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Delete the record `info` of `user`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the current user is not allowed to
+    ///   delete the record.
+    /// * [`Status::NOT_FOUND`]: `user` or `info` does not exist.
+    pub fn delete_info(&mut self, user: UserProfile, info: UserInfoHandle) -> Result {
+        unsafe { (self.delete_info)(self, user, info) }.into()
+    }
+
+    /// Get an iterator over the records of `user`.
+    #[must_use]
+    pub const fn info_handles(&self, user: UserProfile) -> UserInfoHandles<'_> {
+        UserInfoHandles {
+            source: InfoSource::Manager(self, user),
+            info: None,
+            done: false,
+        }
+    }
+}
+
+/// Iterator returned by [`UserManager::profiles`].
+#[derive(Debug)]
+pub struct UserProfiles<'a> {
+    manager: &'a UserManager,
+    user: Option<UserProfile>,
+    done: bool,
+}
+
+impl Iterator for UserProfiles<'_> {
+    type Item = UserProfile;
+
+    fn next(&mut self) -> Option<UserProfile> {
+        if self.done {
+            return None;
+        }
+        let status = unsafe { (self.manager.get_next)(self.manager, &mut self.user) };
+        if status.is_error() || self.user.is_none() {
+            self.done = true;
+            return None;
+        }
+        self.user
+    }
+}
+
+impl fmt::Debug for UserManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserManager").finish_non_exhaustive()
+    }
+}
+
+/// The user credential protocol, installed by a credential provider such as
+/// a password prompt or a smart card reader.
+///
+/// The user manager selects a credential provider to identify the user, and
+/// asks it which user matches the provided credentials.
+///
+/// The corresponding C type is `EFI_USER_CREDENTIAL2_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("e98adb03-b8b9-4af8-ba20-26e9114cbce5")]
+pub struct UserCredential {
+    identifier: Guid,
+    credential_type: Guid,
+    enroll: unsafe extern "efiapi" fn(this: *const Self, user: UserProfile) -> Status,
+    form: unsafe extern "efiapi" fn(
+        this: *const Self,
+        hii: *mut Option<HiiHandle>,
+        form_set_id: *mut Guid,
+        form_id: *mut u16,
+    ) -> Status,
+    tile: unsafe extern "efiapi" fn(
+        this: *const Self,
+        width: *mut usize,
+        height: *mut usize,
+        hii: *mut Option<HiiHandle>,
+        image: *mut u16,
+    ) -> Status,
+    title: unsafe extern "efiapi" fn(
+        this: *const Self,
+        hii: *mut Option<HiiHandle>,
+        string: *mut u16,
+    ) -> Status,
+    user: unsafe extern "efiapi" fn(
+        this: *const Self,
+        user: Option<UserProfile>,
+        identifier: *mut [u8; 16],
+    ) -> Status,
+    select: unsafe extern "efiapi" fn(
+        this: *const Self,
+        auto_logon: *mut CredentialLogonFlags,
+    ) -> Status,
+    deselect: unsafe extern "efiapi" fn(this: *const Self) -> Status,
+    default: unsafe extern "efiapi" fn(
+        this: *const Self,
+        auto_logon: *mut CredentialLogonFlags,
+    ) -> Status,
+    get_info: unsafe extern "efiapi" fn(
+        this: *const Self,
+        user_info: UserInfoHandle,
+        info: *mut u8,
+        info_size: *mut usize,
+    ) -> Status,
+    get_next_info: unsafe extern "efiapi" fn(
+        this: *const Self,
+        user_info: *mut Option<UserInfoHandle>,
+    ) -> Status,
+    capabilities: CredentialCapabilities,
+    delete: unsafe extern "efiapi" fn(this: *const Self, user: UserProfile) -> Status,
+}
+
+impl UserCredential {
+    /// Get the GUID identifying this credential provider.
+    #[must_use]
+    pub const fn identifier(&self) -> &Guid {
+        &self.identifier
+    }
+
+    /// Get the GUID of the class of this credential provider, such as
+    /// password or smart card.
+    #[must_use]
+    pub const fn credential_type(&self) -> &Guid {
+        &self.credential_type
+    }
+
+    /// Get the capabilities of this credential provider.
+    #[must_use]
+    pub const fn capabilities(&self) -> CredentialCapabilities {
+        self.capabilities
+    }
+
+    /// Enroll `user`, recording its credentials, such as a new password.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the current user is not allowed to
+    ///   enroll `user`.
+    /// * [`Status::UNSUPPORTED`]: this provider doesn't support enrollment.
+    pub fn enroll(&mut self, user: UserProfile) -> Result {
+        unsafe { (self.enroll)(self, user) }.into()
+    }
+
+    /// Get the HII form used to enter the credentials, as the HII handle,
+    /// the form set GUID, and the form ID.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: this provider has no form.
+    pub fn form(&mut self) -> Result<(HiiHandle, Guid, u16)> {
+        let mut hii = None;
+        let mut form_set_id = Guid::default();
+        let mut form_id = 0;
+        match unsafe { (self.form)(self, &mut hii, &mut form_set_id, &mut form_id) } {
+            Status::SUCCESS => hii
+                .map(|hii| (hii, form_set_id, form_id))
+                .ok_or_else(|| Status::NOT_FOUND.into()),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Get the image representing this provider in the logon screen, with
+    /// a size up to `max_width` by `max_height` pixels.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: this provider has no image.
+    pub fn tile(&mut self, max_width: usize, max_height: usize) -> Result<CredentialTile> {
+        let mut width = max_width;
+        let mut height = max_height;
+        let mut hii = None;
+        let mut image = 0;
+        match unsafe { (self.tile)(self, &mut width, &mut height, &mut hii, &mut image) } {
+            Status::SUCCESS => hii
+                .map(|hii| CredentialTile {
+                    width,
+                    height,
+                    hii,
+                    image,
+                })
+                .ok_or_else(|| Status::NOT_FOUND.into()),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Get the name of this provider, as an HII handle and string ID.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: this provider has no name.
+    pub fn title(&mut self) -> Result<(HiiHandle, u16)> {
+        let mut hii = None;
+        let mut string = 0;
+        match unsafe { (self.title)(self, &mut hii, &mut string) } {
+            Status::SUCCESS => hii
+                .map(|hii| (hii, string))
+                .ok_or_else(|| Status::NOT_FOUND.into()),
+            status => Err(status.into()),
+        }
+    }
+
+    /// Get the identifier of the user matching the entered credentials. If
+    /// `user` is set, the credentials are checked against that user
+    /// instead.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_READY`]: no credentials were entered.
+    /// * [`Status::ACCESS_DENIED`]: the credentials don't match `user`.
+    /// * [`Status::NOT_FOUND`]: no user matches the credentials.
+    pub fn user(&mut self, user: Option<UserProfile>) -> Result<[u8; 16]> {
+        let mut identifier = [0; 16];
+        unsafe { (self.user)(self, user, &mut identifier) }.into_with_val(|| identifier)
+    }
+
+    /// Notify the provider that it was selected by the user, returning how
+    /// the user may be logged on.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_READY`]: the provider is not ready to be selected.
+    pub fn select(&mut self) -> Result<CredentialLogonFlags> {
+        let mut flags = CredentialLogonFlags::empty();
+        unsafe { (self.select)(self, &mut flags) }.into_with_val(|| flags)
+    }
+
+    /// Notify the provider that another provider was selected.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_READY`]: the provider is not ready.
+    pub fn deselect(&mut self) -> Result {
+        unsafe { (self.deselect)(self) }.into()
+    }
+
+    /// Get how the user may be logged on if this provider is the default
+    /// one, such as [`CredentialLogonFlags::AUTO`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_READY`]: the provider is not ready.
+    pub fn default_logon(&mut self) -> Result<CredentialLogonFlags> {
+        let mut flags = CredentialLogonFlags::empty();
+        unsafe { (self.default)(self, &mut flags) }.into_with_val(|| flags)
+    }
+
+    /// Read the record `info` of this provider into `buf`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buf` is too small, the required size
+    ///   is returned in the error data.
+    /// * [`Status::NOT_FOUND`]: `info` does not exist.
+    pub fn get_info<'buf>(
+        &self,
+        info: UserInfoHandle,
+        buf: &'buf mut [u8],
+    ) -> Result<&'buf UserInfo, Option<usize>> {
+        let mut size = buf.len();
+        unsafe { (self.get_info)(self, info, buf.as_mut_ptr(), &mut size) }.into_with(
+            || (),
+            |status| (status == Status::BUFFER_TOO_SMALL).then_some(size),
+        )?;
+        UserInfo::from_bytes(&buf[..size.min(buf.len())])
+            .ok_or_else(|| Error::new(Status::VOLUME_CORRUPTED, None))
+    }
+
+    /// Get an iterator over the records of this provider, such as the
+    /// enrolled users.
+    #[must_use]
+    pub const fn info_handles(&self) -> UserInfoHandles<'_> {
+        UserInfoHandles {
+            source: InfoSource::Credential(self),
+            info: None,
+            done: false,
+        }
+    }
+
+    /// Delete the credentials of `user` from this provider.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the current user is not allowed to
+    ///   delete the credentials.
+    pub fn delete(&mut self, user: UserProfile) -> Result {
+        unsafe { (self.delete)(self, user) }.into()
+    }
+}
+
+impl fmt::Debug for UserCredential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserCredential")
+            .field("identifier", &self.identifier)
+            .field("credential_type", &self.credential_type)
+            .field("capabilities", &self.capabilities)
+            .finish()
+    }
+}
+
+/// The image of a credential provider, returned by
+/// [`UserCredential::tile`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CredentialTile {
+    /// Width of the image in pixels.
+    pub width: usize,
+    /// Height of the image in pixels.
+    pub height: usize,
+    /// HII package list holding the image.
+    pub hii: HiiHandle,
+    /// ID of the image in the package list.
+    pub image: u16,
+}
+
+#[derive(Debug)]
+enum InfoSource<'a> {
+    Manager(&'a UserManager, UserProfile),
+    Credential(&'a UserCredential),
+}
+
+/// Iterator returned by [`UserManager::info_handles`] and
+/// [`UserCredential::info_handles`].
+#[derive(Debug)]
+pub struct UserInfoHandles<'a> {
+    source: InfoSource<'a>,
+    info: Option<UserInfoHandle>,
+    done: bool,
+}
+
+impl Iterator for UserInfoHandles<'_> {
+    type Item = UserInfoHandle;
+
+    fn next(&mut self) -> Option<UserInfoHandle> {
+        if self.done {
+            return None;
+        }
+        let status = match self.source {
+            InfoSource::Manager(manager, user) => unsafe {
+                (manager.get_next_info)(manager, user, &mut self.info)
+            },
+            InfoSource::Credential(credential) => unsafe {
+                (credential.get_next_info)(credential, &mut self.info)
+            },
+        };
+        if status.is_error() || self.info.is_none() {
+            self.done = true;
+            return None;
+        }
+        self.info
+    }
+}
+
+/// Opaque handle of a user profile.
+///
+/// The corresponding C type is `EFI_USER_PROFILE_HANDLE`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct UserProfile(NonNull<c_void>);
+
+impl UserProfile {
+    /// Get the underlying raw pointer.
+    #[must_use]
+    pub const fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
+
+/// Opaque handle of a [`UserInfo`] record.
+///
+/// The corresponding C type is `EFI_USER_INFO_HANDLE`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct UserInfoHandle(NonNull<c_void>);
+
+impl UserInfoHandle {
+    /// Get the underlying raw pointer.
+    #[must_use]
+    pub const fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
+
+/// Opaque handle of an HII package list, holding forms, strings and images.
+///
+/// The corresponding C type is `EFI_HII_HANDLE`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(transparent)]
+pub struct HiiHandle(NonNull<c_void>);
+
+impl HiiHandle {
+    /// Get the underlying raw pointer.
+    #[must_use]
+    pub const fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
+
+newtype_enum! {
+    /// The type of a [`UserInfo`] record.
+    pub enum UserInfoType: u8 => {
+        /// An empty record.
+        EMPTY = 0x00,
+        /// The name of the user, as a null-terminated UCS-2 string.
+        NAME = 0x01,
+        /// The creation date of the profile, as an `EFI_TIME`.
+        CREATE_DATE = 0x03,
+        /// The date of the last logon, as an `EFI_TIME`.
+        USAGE_DATE = 0x04,
+        /// The number of logons, as a `u64`.
+        USAGE_COUNT = 0x05,
+        /// The unique identifier of the user, as 16 bytes.
+        IDENTIFIER = 0x06,
+        /// The class of a credential provider, as a GUID.
+        CREDENTIAL_TYPE = 0x07,
+        /// The name of the class of a credential provider, as a
+        /// null-terminated UCS-2 string.
+        CREDENTIAL_TYPE_NAME = 0x08,
+        /// The identifier of a credential provider, as a GUID.
+        CREDENTIAL_PROVIDER = 0x09,
+        /// The name of a credential provider, as a null-terminated UCS-2
+        /// string.
+        CREDENTIAL_PROVIDER_NAME = 0x0a,
+        /// PKCS#11 credential data.
+        PKCS11 = 0x0b,
+        /// Biometric data, in the CBEFF format.
+        CBEFF = 0x0c,
+        /// The false accept rate required from biometric providers.
+        FAR = 0x0d,
+        /// The number of logon retries allowed.
+        RETRY = 0x0e,
+        /// The access policy of the user.
+        ACCESS_POLICY = 0x0f,
+        /// The credentials required to identify the user.
+        IDENTITY_POLICY = 0x10,
+        /// A vendor-specific record, starting with a GUID.
+        GUID = 0x11,
+    }
+}
+
+bitflags! {
+    /// Attributes of a [`UserInfo`] record.
+    ///
+    /// Records without storage attributes are volatile, and records without
+    /// access attributes can only be read by the user manager.
+    #[repr(transparent)]
+    pub struct UserInfoAttributes: u16 {
+        /// The record is stored by the credential provider.
+        const STORAGE_CREDENTIAL_NV = 0x0001;
+        /// The record is stored in the platform non-volatile storage.
+        const STORAGE_PLATFORM_NV = 0x0002;
+        /// The record can be read by any user.
+        const PUBLIC = 0x0010;
+        /// The record can only be read by its user, or by a user allowed to
+        /// change other users.
+        const PRIVATE = 0x0020;
+        /// The record can be read by any user, but only changed by a user
+        /// allowed to change other users.
+        const PROTECTED = 0x0030;
+        /// Only one record of this type can exist in a profile.
+        const EXCLUSIVE = 0x0080;
+    }
+}
+
+bitflags! {
+    /// How a user may be logged on by a [`UserCredential`] provider.
+    #[repr(transparent)]
+    pub struct CredentialLogonFlags: u32 {
+        /// The user can be logged on without interaction.
+        const AUTO = 0x0001;
+        /// The provider should be selected by default.
+        const DEFAULT = 0x0002;
+    }
+}
+
+bitflags! {
+    /// Capabilities of a [`UserCredential`] provider.
+    #[repr(transparent)]
+    pub struct CredentialCapabilities: u64 {
+        /// The provider can enroll users.
+        const ENROLL = 0x0001;
+    }
+}
+
+/// A record of a user profile or credential provider.
+///
+/// The record starts with a 24-byte header, followed by data whose format
+/// depends on its [`UserInfoType`].
+///
+/// The corresponding C type is `EFI_USER_INFO`.
+#[derive(Eq, PartialEq)]
+#[repr(transparent)]
+pub struct UserInfo([u8]);
+
+impl UserInfo {
+    /// Size of the header of a record.
+    pub const HEADER_SIZE: usize = 24;
+
+    /// Parse the record at the start of `bytes`. Returns `None` if `bytes`
+    /// is shorter than the size of the record.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<&Self> {
+        let size = bytes.get(20..24)?;
+        let size = u32::from_le_bytes(size.try_into().unwrap()) as usize;
+        if size < Self::HEADER_SIZE {
+            return None;
+        }
+        let bytes = bytes.get(..size)?;
+        // Safety: `UserInfo` is a transparent wrapper of `[u8]`.
+        Some(unsafe { &*(bytes as *const [u8] as *const Self) })
+    }
+
+    /// Write a record with `data` in `buf`, and return it. If `buf` is too
+    /// small, the required size is returned as an error.
+    pub fn new_in<'buf>(
+        buf: &'buf mut [u8],
+        credential: Guid,
+        info_type: UserInfoType,
+        attributes: UserInfoAttributes,
+        data: &[u8],
+    ) -> core::result::Result<&'buf Self, usize> {
+        let size = Self::HEADER_SIZE + data.len();
+        let buf = buf.get_mut(..size).ok_or(size)?;
+        buf[..16].copy_from_slice(&credential.to_bytes());
+        buf[16] = info_type.0;
+        buf[17] = 0;
+        buf[18..20].copy_from_slice(&attributes.bits().to_le_bytes());
+        buf[20..24].copy_from_slice(&u32::try_from(size).map_err(|_| size)?.to_le_bytes());
+        buf[Self::HEADER_SIZE..].copy_from_slice(data);
+        // OK to unwrap: the record is valid.
+        Ok(Self::from_bytes(buf).unwrap())
+    }
+
+    /// Get the GUID of the credential provider owning the record, or the
+    /// nil GUID if the record belongs to the user manager.
+    #[must_use]
+    pub fn credential(&self) -> Guid {
+        Guid::from_bytes(self.0[..16].try_into().unwrap())
+    }
+
+    /// Get the type of the record.
+    #[must_use]
+    pub fn info_type(&self) -> UserInfoType {
+        UserInfoType(self.0[16])
+    }
+
+    /// Get the attributes of the record.
+    #[must_use]
+    pub fn attributes(&self) -> UserInfoAttributes {
+        UserInfoAttributes::from_bits_truncate(u16::from_le_bytes([self.0[18], self.0[19]]))
+    }
+
+    /// Get the data following the header.
+    #[must_use]
+    pub fn data(&self) -> &[u8] {
+        &self.0[Self::HEADER_SIZE..]
+    }
+
+    /// Get the whole record, including the header.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for UserInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserInfo")
+            .field("credential", &self.credential())
+            .field("info_type", &self.info_type())
+            .field("attributes", &self.attributes())
+            .field("data", &self.data())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::guid;
+
+    #[test]
+    fn test_user_info() {
+        let credential = guid!("12345678-9abc-def0-1234-56789abcdef0");
+        let mut buf = [0; 64];
+        assert_eq!(
+            UserInfo::new_in(
+                &mut buf[..30],
+                credential,
+                UserInfoType::IDENTIFIER,
+                UserInfoAttributes::PUBLIC,
+                &[7; 16],
+            ),
+            Err(40)
+        );
+        let info = UserInfo::new_in(
+            &mut buf,
+            credential,
+            UserInfoType::IDENTIFIER,
+            UserInfoAttributes::PUBLIC | UserInfoAttributes::EXCLUSIVE,
+            &[7; 16],
+        )
+        .unwrap();
+        assert_eq!(info.as_bytes().len(), 40);
+        assert_eq!(info.credential(), credential);
+        assert_eq!(info.info_type(), UserInfoType::IDENTIFIER);
+        assert_eq!(
+            info.attributes(),
+            UserInfoAttributes::PUBLIC | UserInfoAttributes::EXCLUSIVE
+        );
+        assert_eq!(info.data(), [7; 16]);
+
+        // Trailing bytes are ignored, truncated records are rejected.
+        assert_eq!(UserInfo::from_bytes(&buf).unwrap().data(), [7; 16]);
+        assert!(UserInfo::from_bytes(&buf[..39]).is_none());
+        assert!(UserInfo::from_bytes(&buf[..20]).is_none());
+    }
+
+    static PROFILES: [u8; 2] = [0; 2];
+    static RECORDS: [u8; 1] = [0];
+
+    fn profile(index: usize) -> UserProfile {
+        UserProfile(NonNull::from(&PROFILES[index]).cast())
+    }
+
+    fn record() -> UserInfoHandle {
+        UserInfoHandle(NonNull::from(&RECORDS[0]).cast())
+    }
+
+    unsafe extern "efiapi" fn get_next(
+        _this: *const UserManager,
+        user: *mut Option<UserProfile>,
+    ) -> Status {
+        *user = match *user {
+            None => Some(profile(0)),
+            Some(prev) if prev == profile(0) => Some(profile(1)),
+            Some(_) => return Status::NOT_FOUND,
+        };
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn current(
+        _this: *const UserManager,
+        user: *mut Option<UserProfile>,
+    ) -> Status {
+        *user = Some(profile(1));
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn get_info(
+        _this: *const UserManager,
+        user: UserProfile,
+        user_info: UserInfoHandle,
+        info: *mut u8,
+        info_size: *mut usize,
+    ) -> Status {
+        if user != profile(1) || user_info != record() {
+            return Status::NOT_FOUND;
+        }
+        let buf = core::slice::from_raw_parts_mut(info, *info_size);
+        match UserInfo::new_in(
+            buf,
+            Guid::default(),
+            UserInfoType::USAGE_COUNT,
+            UserInfoAttributes::PUBLIC,
+            &3u64.to_le_bytes(),
+        ) {
+            Ok(_) => Status::SUCCESS,
+            Err(size) => {
+                *info_size = size;
+                Status::BUFFER_TOO_SMALL
+            }
+        }
+    }
+
+    unsafe extern "efiapi" fn get_next_info(
+        _this: *const UserManager,
+        user: UserProfile,
+        user_info: *mut Option<UserInfoHandle>,
+    ) -> Status {
+        if user != profile(1) || (*user_info).is_some() {
+            return Status::NOT_FOUND;
+        }
+        *user_info = Some(record());
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn create(
+        _this: *const UserManager,
+        _user: *mut Option<UserProfile>,
+    ) -> Status {
+        Status::ACCESS_DENIED
+    }
+
+    unsafe extern "efiapi" fn delete(_this: *const UserManager, _user: UserProfile) -> Status {
+        Status::ACCESS_DENIED
+    }
+
+    unsafe extern "efiapi" fn find(
+        _this: *const UserManager,
+        _user: *mut Option<UserProfile>,
+        _user_info: *mut Option<UserInfoHandle>,
+        _info: *const u8,
+        _info_size: usize,
+    ) -> Status {
+        Status::NOT_FOUND
+    }
+
+    unsafe extern "efiapi" fn notify(_this: *const UserManager, _changed: Handle) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    unsafe extern "efiapi" fn set_info(
+        _this: *const UserManager,
+        _user: UserProfile,
+        _user_info: *mut Option<UserInfoHandle>,
+        _info: *const u8,
+        _info_size: usize,
+    ) -> Status {
+        Status::ACCESS_DENIED
+    }
+
+    unsafe extern "efiapi" fn delete_info(
+        _this: *const UserManager,
+        _user: UserProfile,
+        _user_info: UserInfoHandle,
+    ) -> Status {
+        Status::ACCESS_DENIED
+    }
+
+    #[test]
+    fn test_user_manager() {
+        let mut manager = UserManager {
+            create,
+            delete,
+            get_next,
+            current,
+            identify: current,
+            find,
+            notify,
+            get_info,
+            set_info,
+            delete_info,
+            get_next_info,
+            _no_send_or_sync: core::marker::PhantomData,
+        };
+
+        let mut profiles = manager.profiles();
+        assert_eq!(profiles.next(), Some(profile(0)));
+        assert_eq!(profiles.next(), Some(profile(1)));
+        assert_eq!(profiles.next(), None);
+        assert_eq!(profiles.next(), None);
+
+        let user = manager.current().unwrap();
+        assert_eq!(manager.info_handles(profile(0)).next(), None);
+        assert!(manager.info_handles(user).eq([record()]));
+
+        let mut small = [0; 8];
+        let err = manager.get_info(user, record(), &mut small).unwrap_err();
+        assert_eq!(err.status(), Status::BUFFER_TOO_SMALL);
+        assert_eq!(*err.data(), Some(32));
+        let mut buf = [0; 32];
+        let info = manager.get_info(user, record(), &mut buf).unwrap();
+        assert_eq!(info.info_type(), UserInfoType::USAGE_COUNT);
+        assert_eq!(info.data(), 3u64.to_le_bytes());
+
+        assert_eq!(
+            manager.create().unwrap_err().status(),
+            Status::ACCESS_DENIED
+        );
+    }
+}
@@ -0,0 +1,529 @@
+use crate::data_types::UnalignedSlice;
+use crate::proto::unsafe_protocol;
+use crate::table::runtime::{VariableAttributes, VariableVendor};
+use crate::{CStr16, Char16, Guid, Result, Status};
+use core::{fmt, ptr};
+
+/// Protocol for registering and querying variable policies, which restrict
+/// the size and attributes of UEFI variables and lock them against further
+/// changes.
+///
+/// This protocol is defined by EDK2 rather than the UEFI specification. The
+/// policies are enforced by the variable driver, so they also apply to
+/// variables set by the operating system.
+///
+/// Corresponds to the C type `EDKII_VARIABLE_POLICY_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("81d1675c-86f6-48df-bd95-9a6e4f0925c3")]
+pub struct VariablePolicy {
+    revision: u64,
+    disable_variable_policy: extern "efiapi" fn() -> Status,
+    is_variable_policy_enabled: unsafe extern "efiapi" fn(state: *mut bool) -> Status,
+    register_variable_policy: unsafe extern "efiapi" fn(policy_entry: *const u8) -> Status,
+    dump_variable_policy: unsafe extern "efiapi" fn(policy: *mut u8, size: *mut u32) -> Status,
+    lock_variable_policy: extern "efiapi" fn() -> Status,
+}
+
+impl VariablePolicy {
+    /// Get the revision of the protocol.
+    #[must_use]
+    pub const fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Check whether variable policies are enforced.
+    pub fn is_enabled(&self) -> Result<bool> {
+        let mut state = false;
+        unsafe { (self.is_variable_policy_enabled)(&mut state) }.into_with_val(|| state)
+    }
+
+    /// Disable the enforcement of variable policies until the next reset.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::WRITE_PROTECTED`]: the policies are locked.
+    /// * [`Status::ALREADY_STARTED`]: the policies are already disabled.
+    pub fn disable(&mut self) -> Result {
+        (self.disable_variable_policy)().into()
+    }
+
+    /// Register a new policy.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: the policy is malformed.
+    /// * [`Status::ALREADY_STARTED`]: a policy with the same namespace and
+    ///   name is already registered.
+    /// * [`Status::WRITE_PROTECTED`]: the policies are locked.
+    /// * [`Status::OUT_OF_RESOURCES`]: there is no space for the policy.
+    pub fn register(&mut self, policy: &VariablePolicyEntry) -> Result {
+        unsafe { (self.register_variable_policy)(policy.as_bytes().as_ptr()) }.into()
+    }
+
+    /// Copy the registered policies to `buf`, and return an iterator over
+    /// them.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buf` is too small. The required size
+    ///   is returned in the error data.
+    /// * [`Status::NOT_READY`]: the policies are disabled.
+    pub fn dump<'buf>(
+        &self,
+        buf: &'buf mut [u8],
+    ) -> Result<VariablePolicyEntries<'buf>, Option<usize>> {
+        let mut size = u32::try_from(buf.len()).unwrap_or(u32::MAX);
+        let policy = if buf.is_empty() {
+            ptr::null_mut()
+        } else {
+            buf.as_mut_ptr()
+        };
+        unsafe { (self.dump_variable_policy)(policy, &mut size) }.into_with(
+            || VariablePolicyEntries::new(&buf[..size as usize]),
+            |status| (status == Status::BUFFER_TOO_SMALL).then_some(size as usize),
+        )
+    }
+
+    /// Prevent policies from being registered or disabled until the next
+    /// reset. This is usually done by the firmware at the end of the DXE
+    /// phase.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::WRITE_PROTECTED`]: the policies are already locked.
+    pub fn lock(&mut self) -> Result {
+        (self.lock_variable_policy)().into()
+    }
+}
+
+newtype_enum! {
+    /// The type of the lock of a [`VariablePolicyEntry`].
+    pub enum VariableLockPolicyType: u8 => {
+        /// The variable is not locked.
+        NO_LOCK = 0,
+        /// The variable is locked once the policies are locked.
+        LOCK_NOW = 1,
+        /// The variable is locked once it's created, after the policies are
+        /// locked.
+        LOCK_ON_CREATE = 2,
+        /// The variable is locked once another variable has a given value,
+        /// after the policies are locked.
+        LOCK_ON_VARIABLE_STATE = 3,
+    }
+}
+
+/// The lock of a new [`VariablePolicyEntry`].
+#[derive(Clone, Copy, Debug)]
+pub enum VariableLockPolicy<'a> {
+    /// The variable is not locked.
+    NoLock,
+    /// The variable is locked once the policies are locked.
+    LockNow,
+    /// The variable is locked once it's created, after the policies are
+    /// locked.
+    LockOnCreate,
+    /// The variable is locked once another variable holds a single byte
+    /// equal to `value`, after the policies are locked.
+    LockOnVariableState {
+        /// Vendor of the variable which triggers the lock.
+        namespace: VariableVendor,
+        /// Name of the variable which triggers the lock.
+        name: &'a CStr16,
+        /// Value of the variable which triggers the lock.
+        value: u8,
+    },
+}
+
+impl VariableLockPolicy<'_> {
+    const fn policy_type(&self) -> VariableLockPolicyType {
+        match self {
+            Self::NoLock => VariableLockPolicyType::NO_LOCK,
+            Self::LockNow => VariableLockPolicyType::LOCK_NOW,
+            Self::LockOnCreate => VariableLockPolicyType::LOCK_ON_CREATE,
+            Self::LockOnVariableState { .. } => VariableLockPolicyType::LOCK_ON_VARIABLE_STATE,
+        }
+    }
+
+    /// Size of the lock data following the policy header.
+    fn size(&self) -> usize {
+        match self {
+            Self::LockOnVariableState { name, .. } => {
+                VariableStateLock::HEADER_SIZE + name.num_bytes()
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// The constraints on the size and attributes of the variables of a
+/// [`VariablePolicyEntry`].
+///
+/// The default value has no constraints.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VariableConstraints {
+    /// Minimum size of the variables, in bytes.
+    pub min_size: u32,
+    /// Maximum size of the variables, in bytes.
+    pub max_size: u32,
+    /// Attributes the variables must have.
+    pub attributes_must_have: VariableAttributes,
+    /// Attributes the variables can't have.
+    pub attributes_cant_have: VariableAttributes,
+}
+
+impl Default for VariableConstraints {
+    fn default() -> Self {
+        Self {
+            min_size: 0,
+            max_size: u32::MAX,
+            attributes_must_have: VariableAttributes::empty(),
+            attributes_cant_have: VariableAttributes::empty(),
+        }
+    }
+}
+
+/// The lock of a [`VariablePolicyEntry`] of type
+/// [`VariableLockPolicyType::LOCK_ON_VARIABLE_STATE`].
+#[derive(Debug)]
+pub struct VariableStateLock<'a> {
+    /// Vendor of the variable which triggers the lock.
+    pub namespace: VariableVendor,
+    /// Value of the variable which triggers the lock.
+    pub value: u8,
+    /// Null-terminated name of the variable which triggers the lock.
+    pub name: UnalignedSlice<'a, u16>,
+}
+
+impl VariableStateLock<'_> {
+    /// Size of the lock data before the name.
+    const HEADER_SIZE: usize = 18;
+}
+
+/// A variable policy, which applies to the variables of a namespace, or to
+/// a single variable.
+///
+/// The corresponding C type is `VARIABLE_POLICY_ENTRY`.
+#[derive(Eq, PartialEq)]
+#[repr(transparent)]
+pub struct VariablePolicyEntry([u8]);
+
+impl VariablePolicyEntry {
+    /// Size of the fixed part of the entry.
+    pub const HEADER_SIZE: usize = 44;
+
+    /// Version of the entry format.
+    pub const VERSION: u32 = 0x0001_0000;
+
+    /// Parse the entry at the start of `bytes`. Returns `None` if the
+    /// version is unknown, if the sizes in the header are inconsistent, or
+    /// if `bytes` is too short.
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8]) -> Option<&Self> {
+        let u16_at = |offset: usize| -> Option<usize> {
+            let field = bytes.get(offset..offset + 2)?;
+            Some(u16::from_le_bytes(field.try_into().unwrap()).into())
+        };
+        let version = u32::from_le_bytes(bytes.get(..4)?.try_into().unwrap());
+        let size = u16_at(4)?;
+        let offset_to_name = u16_at(6)?;
+        if version != Self::VERSION
+            || !(Self::HEADER_SIZE..=size).contains(&offset_to_name)
+            || (size - offset_to_name) % 2 != 0
+        {
+            return None;
+        }
+        let bytes = bytes.get(..size)?;
+        // Safety: `VariablePolicyEntry` is a transparent wrapper of `[u8]`.
+        let entry = unsafe { &*(bytes as *const [u8] as *const Self) };
+
+        let lock_size = offset_to_name - Self::HEADER_SIZE;
+        let valid_lock = match entry.lock_policy_type() {
+            VariableLockPolicyType::LOCK_ON_VARIABLE_STATE => {
+                lock_size > VariableStateLock::HEADER_SIZE && lock_size % 2 == 0
+            }
+            _ => lock_size == 0,
+        };
+        valid_lock.then_some(entry)
+    }
+
+    /// Write a policy in `buf`, and return it. If `buf` is too small, the
+    /// required size is returned as an error.
+    ///
+    /// If `name` is `None`, the policy applies to all the variables of
+    /// `namespace`. Otherwise, `#` characters in `name` match any digit.
+    pub fn new_in<'buf>(
+        buf: &'buf mut [u8],
+        namespace: VariableVendor,
+        name: Option<&CStr16>,
+        constraints: &VariableConstraints,
+        lock: &VariableLockPolicy,
+    ) -> core::result::Result<&'buf Self, usize> {
+        let offset_to_name = Self::HEADER_SIZE + lock.size();
+        let size = offset_to_name + name.map_or(0, CStr16::num_bytes);
+        let size_field = u16::try_from(size).map_err(|_| size)?;
+        let buf = buf.get_mut(..size).ok_or(size)?;
+
+        buf[..4].copy_from_slice(&Self::VERSION.to_le_bytes());
+        buf[4..6].copy_from_slice(&size_field.to_le_bytes());
+        buf[6..8].copy_from_slice(&(offset_to_name as u16).to_le_bytes());
+        buf[8..24].copy_from_slice(&namespace.0.to_bytes());
+        buf[24..28].copy_from_slice(&constraints.min_size.to_le_bytes());
+        buf[28..32].copy_from_slice(&constraints.max_size.to_le_bytes());
+        buf[32..36].copy_from_slice(&constraints.attributes_must_have.bits().to_le_bytes());
+        buf[36..40].copy_from_slice(&constraints.attributes_cant_have.bits().to_le_bytes());
+        buf[40] = lock.policy_type().0;
+        buf[41..44].fill(0);
+        if let VariableLockPolicy::LockOnVariableState {
+            namespace,
+            name,
+            value,
+        } = lock
+        {
+            buf[44..60].copy_from_slice(&namespace.0.to_bytes());
+            buf[60] = *value;
+            buf[61] = 0;
+            write_name(&mut buf[62..offset_to_name], name);
+        }
+        if let Some(name) = name {
+            write_name(&mut buf[offset_to_name..], name);
+        }
+        // OK to unwrap: the entry is valid.
+        Ok(Self::from_bytes(buf).unwrap())
+    }
+
+    /// Get the namespace of the variables the policy applies to.
+    #[must_use]
+    pub fn namespace(&self) -> VariableVendor {
+        VariableVendor(Guid::from_bytes(self.0[8..24].try_into().unwrap()))
+    }
+
+    /// Get the null-terminated name of the variable the policy applies to,
+    /// or `None` if it applies to all the variables of the namespace.
+    #[must_use]
+    pub fn name(&self) -> Option<UnalignedSlice<'_, u16>> {
+        let name = &self.0[self.offset_to_name()..];
+        (!name.is_empty()).then(|| unaligned_u16(name))
+    }
+
+    /// Get the constraints on the size and attributes of the variables.
+    #[must_use]
+    pub fn constraints(&self) -> VariableConstraints {
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(self.0[offset..offset + 4].try_into().unwrap());
+        VariableConstraints {
+            min_size: u32_at(24),
+            max_size: u32_at(28),
+            attributes_must_have: VariableAttributes::from_bits_truncate(u32_at(32)),
+            attributes_cant_have: VariableAttributes::from_bits_truncate(u32_at(36)),
+        }
+    }
+
+    /// Get the type of the lock of the variables.
+    #[must_use]
+    pub fn lock_policy_type(&self) -> VariableLockPolicyType {
+        VariableLockPolicyType(self.0[40])
+    }
+
+    /// Get the variable state triggering the lock, if the lock type is
+    /// [`VariableLockPolicyType::LOCK_ON_VARIABLE_STATE`].
+    #[must_use]
+    pub fn variable_state_lock(&self) -> Option<VariableStateLock<'_>> {
+        if self.lock_policy_type() != VariableLockPolicyType::LOCK_ON_VARIABLE_STATE {
+            return None;
+        }
+        let lock = &self.0[Self::HEADER_SIZE..self.offset_to_name()];
+        Some(VariableStateLock {
+            namespace: VariableVendor(Guid::from_bytes(lock[..16].try_into().unwrap())),
+            value: lock[16],
+            name: unaligned_u16(&lock[VariableStateLock::HEADER_SIZE..]),
+        })
+    }
+
+    /// Get the whole entry.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    fn offset_to_name(&self) -> usize {
+        u16::from_le_bytes(self.0[6..8].try_into().unwrap()).into()
+    }
+}
+
+impl fmt::Debug for VariablePolicyEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VariablePolicyEntry")
+            .field("namespace", &self.namespace())
+            .field("name", &self.name())
+            .field("constraints", &self.constraints())
+            .field("lock_policy_type", &self.lock_policy_type())
+            .field("variable_state_lock", &self.variable_state_lock())
+            .finish()
+    }
+}
+
+/// Iterator over consecutive [`VariablePolicyEntry`]s, such as the output of
+/// [`VariablePolicy::dump`].
+///
+/// Iteration stops at the first invalid entry.
+#[derive(Debug, Clone)]
+pub struct VariablePolicyEntries<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> VariablePolicyEntries<'a> {
+    /// Create an iterator over the entries of `bytes`.
+    #[must_use]
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+}
+
+impl<'a> Iterator for VariablePolicyEntries<'a> {
+    type Item = &'a VariablePolicyEntry;
+
+    fn next(&mut self) -> Option<&'a VariablePolicyEntry> {
+        match VariablePolicyEntry::from_bytes(self.bytes) {
+            Some(entry) => {
+                self.bytes = &self.bytes[entry.as_bytes().len()..];
+                Some(entry)
+            }
+            None => {
+                self.bytes = &[];
+                None
+            }
+        }
+    }
+}
+
+/// Protocol for locking variables, superseded by [`VariablePolicy`].
+///
+/// This protocol is defined by EDK2 rather than the UEFI specification.
+///
+/// Corresponds to the C type `EDKII_VARIABLE_LOCK_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("cd3d0a05-9e24-437c-a891-1ee053db7638")]
+pub struct VariableLock {
+    request_to_lock: unsafe extern "efiapi" fn(
+        this: *const Self,
+        variable_name: *const Char16,
+        vendor_guid: *const Guid,
+    ) -> Status,
+}
+
+impl VariableLock {
+    /// Request that a variable be made read-only at the end of the DXE
+    /// phase.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::ACCESS_DENIED`]: the end of the DXE phase has already
+    ///   been reached.
+    /// * [`Status::OUT_OF_RESOURCES`]: there is no space for the request.
+    pub fn request_to_lock(&self, name: &CStr16, vendor: &VariableVendor) -> Result {
+        unsafe { (self.request_to_lock)(self, name.as_ptr(), &vendor.0) }.into()
+    }
+}
+
+/// Write `name`, including its null terminator, to `buf` in little-endian
+/// order.
+fn write_name(buf: &mut [u8], name: &CStr16) {
+    for (dst, c) in buf.chunks_exact_mut(2).zip(name.to_u16_slice_with_nul()) {
+        dst.copy_from_slice(&c.to_le_bytes());
+    }
+}
+
+/// Get the UTF-16 characters of `bytes`, whose length is even.
+fn unaligned_u16(bytes: &[u8]) -> UnalignedSlice<'_, u16> {
+    unsafe { UnalignedSlice::new(bytes.as_ptr().cast::<u16>(), bytes.len() / 2) }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::cstr16;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_variable_policy_entry() {
+        let constraints = VariableConstraints {
+            max_size: 8,
+            attributes_must_have: VariableAttributes::NON_VOLATILE,
+            ..Default::default()
+        };
+        let lock = VariableLockPolicy::LockOnVariableState {
+            namespace: VariableVendor::GLOBAL_VARIABLE,
+            name: cstr16!("Lock"),
+            value: 1,
+        };
+        let mut buf = [0; 256];
+        assert_eq!(
+            VariablePolicyEntry::new_in(
+                &mut buf[..64],
+                VariableVendor::GLOBAL_VARIABLE,
+                Some(cstr16!("Boot####")),
+                &constraints,
+                &lock,
+            ),
+            Err(44 + 18 + 10 + 18)
+        );
+        let entry = VariablePolicyEntry::new_in(
+            &mut buf,
+            VariableVendor::GLOBAL_VARIABLE,
+            Some(cstr16!("Boot####")),
+            &constraints,
+            &lock,
+        )
+        .unwrap();
+        assert_eq!(entry.as_bytes().len(), 90);
+        assert_eq!(entry.namespace(), VariableVendor::GLOBAL_VARIABLE);
+        assert_eq!(
+            entry.name().unwrap().to_cstring16().unwrap(),
+            cstr16!("Boot####")
+        );
+        assert_eq!(entry.constraints(), constraints);
+        assert_eq!(
+            entry.lock_policy_type(),
+            VariableLockPolicyType::LOCK_ON_VARIABLE_STATE
+        );
+        let state = entry.variable_state_lock().unwrap();
+        assert_eq!(state.namespace, VariableVendor::GLOBAL_VARIABLE);
+        assert_eq!(state.value, 1);
+        assert_eq!(state.name.to_cstring16().unwrap(), cstr16!("Lock"));
+
+        // A policy for a whole namespace, following the first one.
+        let size = entry.as_bytes().len();
+        let entry = VariablePolicyEntry::new_in(
+            &mut buf[size..],
+            VariableVendor::IMAGE_SECURITY_DATABASE,
+            None,
+            &VariableConstraints::default(),
+            &VariableLockPolicy::LockNow,
+        )
+        .unwrap();
+        assert!(entry.name().is_none());
+        assert!(entry.variable_state_lock().is_none());
+        assert_eq!(entry.constraints(), VariableConstraints::default());
+
+        let entries: Vec<_> = VariablePolicyEntries::new(&buf)
+            .map(|entry| (entry.namespace(), entry.lock_policy_type()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (
+                    VariableVendor::GLOBAL_VARIABLE,
+                    VariableLockPolicyType::LOCK_ON_VARIABLE_STATE
+                ),
+                (
+                    VariableVendor::IMAGE_SECURITY_DATABASE,
+                    VariableLockPolicyType::LOCK_NOW
+                ),
+            ]
+        );
+
+        // Unknown versions are rejected.
+        buf[0] = 1;
+        assert!(VariablePolicyEntry::from_bytes(&buf).is_none());
+    }
+}
@@ -0,0 +1,176 @@
+//! Service binding protocols.
+//!
+//! Some protocols, mostly network protocols such as TCP or HTTP, are not
+//! installed directly by their driver. Instead, the driver installs a
+//! service binding protocol on the controller handle, and each consumer
+//! calls [`ServiceBindingProtocol::create_child`] to get a new child handle
+//! with its own instance of the protocol. Once done, the child is destroyed
+//! with [`ServiceBindingProtocol::destroy_child`].
+//!
+//! All service binding protocols share the same interface, but each one has
+//! its own GUID. The GUID is tied to the child protocol with the
+//! [`ServiceBinding`] trait, which is implemented by passing the
+//! `service_binding` argument to [`unsafe_protocol`]:
+//!
+//! ```
+//! use uefi::proto::service_binding::{ServiceBinding, ServiceBindingProtocol};
+//! use uefi::proto::unsafe_protocol;
+//! use uefi::{guid, Identify};
+//!
+//! #[unsafe_protocol(
+//!     "65530bc7-a359-410f-b010-5aadc7ec2b62",
+//!     service_binding = "00720665-67eb-4a99-baf7-d3c33a1c7cc9"
+//! )]
+//! struct Tcp4 {}
+//!
+//! assert_eq!(
+//!     ServiceBindingProtocol::<Tcp4>::GUID,
+//!     guid!("00720665-67eb-4a99-baf7-d3c33a1c7cc9")
+//! );
+//! ```
+//!
+//! [`unsafe_protocol`]: super::unsafe_protocol
+
+use super::Protocol;
+use crate::{Guid, Handle, Identify, Result, Status};
+use core::marker::PhantomData;
+
+/// A protocol whose instances are created on child handles by a service
+/// binding protocol.
+///
+/// This is usually implemented with the `service_binding` argument of
+/// [`unsafe_protocol`].
+///
+/// # Safety
+///
+/// The implementer must ensure that [`SERVICE_BINDING_GUID`] is the GUID of
+/// the service binding protocol creating children with this protocol.
+///
+/// [`unsafe_protocol`]: super::unsafe_protocol
+/// [`SERVICE_BINDING_GUID`]: Self::SERVICE_BINDING_GUID
+pub unsafe trait ServiceBinding: Protocol {
+    /// GUID of the service binding protocol for this protocol.
+    const SERVICE_BINDING_GUID: Guid;
+}
+
+/// The service binding protocol creating children with the protocol `P`.
+///
+/// The corresponding C type is `EFI_SERVICE_BINDING_PROTOCOL`. Its GUID is
+/// [`P::SERVICE_BINDING_GUID`].
+///
+/// [`P::SERVICE_BINDING_GUID`]: ServiceBinding::SERVICE_BINDING_GUID
+#[repr(C)]
+pub struct ServiceBindingProtocol<P: ServiceBinding> {
+    create_child: unsafe extern "efiapi" fn(this: *mut Self, child: *mut Option<Handle>) -> Status,
+    destroy_child: unsafe extern "efiapi" fn(this: *mut Self, child: Handle) -> Status,
+    _marker: PhantomData<*const P>,
+}
+
+unsafe impl<P: ServiceBinding> Identify for ServiceBindingProtocol<P> {
+    const GUID: Guid = P::SERVICE_BINDING_GUID;
+}
+
+impl<P: ServiceBinding> Protocol for ServiceBindingProtocol<P> {}
+
+impl<P: ServiceBinding> ServiceBindingProtocol<P> {
+    /// Create a new child handle with the protocol `P` installed on it.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]
+    /// * [`Status::OUT_OF_RESOURCES`]: there are not enough resources to
+    ///   create the child.
+    ///
+    /// Other errors are specific to the protocol.
+    pub fn create_child(&mut self) -> Result<Handle> {
+        let mut child = None;
+        // OK to unwrap: a handle is returned on success.
+        unsafe { (self.create_child)(self, &mut child) }.into_with_val(|| child.unwrap())
+    }
+
+    /// Destroy a child handle created by [`create_child`], uninstalling the
+    /// protocol `P` from it.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `child` was not created by this service
+    ///   binding.
+    /// * [`Status::INVALID_PARAMETER`]: `child` is not a valid handle.
+    /// * [`Status::ACCESS_DENIED`]: the protocol `P` could not be
+    ///   uninstalled, for instance because it is still open.
+    ///
+    /// [`create_child`]: Self::create_child
+    pub fn destroy_child(&mut self, child: Handle) -> Result {
+        unsafe { (self.destroy_child)(self, child) }.into()
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use crate::proto::unsafe_protocol;
+    use core::ffi::c_void;
+    use core::ptr::NonNull;
+
+    #[unsafe_protocol(
+        "9b53e7b6-3b2c-4b0c-8f3e-6a3e0b6f8a11",
+        service_binding = "6d3e2f9a-4c1b-4e7f-a0d2-1b9c8e7f6a55"
+    )]
+    struct TestProtocol {}
+
+    fn child_handle() -> Handle {
+        unsafe { Handle::from_ptr(NonNull::<u64>::dangling().as_ptr().cast::<c_void>()) }.unwrap()
+    }
+
+    unsafe extern "efiapi" fn create_child(
+        _this: *mut ServiceBindingProtocol<TestProtocol>,
+        child: *mut Option<Handle>,
+    ) -> Status {
+        if (*child).is_some() {
+            return Status::UNSUPPORTED;
+        }
+        child.write(Some(child_handle()));
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn destroy_child(
+        _this: *mut ServiceBindingProtocol<TestProtocol>,
+        child: Handle,
+    ) -> Status {
+        if child.as_ptr() == child_handle().as_ptr() {
+            Status::SUCCESS
+        } else {
+            Status::UNSUPPORTED
+        }
+    }
+
+    #[test]
+    fn test_service_binding() {
+        assert_eq!(
+            ServiceBindingProtocol::<TestProtocol>::GUID,
+            TestProtocol::SERVICE_BINDING_GUID
+        );
+
+        let mut firmware = MockFirmware::new();
+        let mut binding = ServiceBindingProtocol::<TestProtocol> {
+            create_child,
+            destroy_child,
+            _marker: PhantomData,
+        };
+        let handle = unsafe { firmware.install_protocol(None, &mut binding) };
+        let st = firmware.system_table();
+        let mut binding = st
+            .boot_services()
+            .open_protocol_exclusive::<ServiceBindingProtocol<TestProtocol>>(handle)
+            .unwrap();
+
+        let child = binding.create_child().unwrap();
+        assert_eq!(child.as_ptr(), child_handle().as_ptr());
+        binding.destroy_child(child).unwrap();
+        assert_eq!(
+            binding.destroy_child(handle).unwrap_err().status(),
+            Status::UNSUPPORTED
+        );
+    }
+}
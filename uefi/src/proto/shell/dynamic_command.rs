@@ -0,0 +1,325 @@
+//! Commands added to the UEFI Shell by drivers.
+//!
+//! The shell looks up its built-in commands first, then the
+//! [`ShellDynamicCommand`] protocols installed on any handle. A command is
+//! implemented in Rust by passing a handler closure to [`install`]:
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::proto::shell::dynamic_command::{self, ShellStatus};
+//!
+//! fn install_hello(bt: &BootServices) -> uefi::Result<Handle> {
+//!     dynamic_command::install(
+//!         bt,
+//!         None,
+//!         cstr16!("hello"),
+//!         cstr16!(".TH hello 0 \"Print a greeting\"\r\n"),
+//!         |cx| {
+//!             let name = cx.parameters.args().nth(1).unwrap_or(cstr16!("world"));
+//!             let _ = cx.system_table.stdout().output_string(name);
+//!             ShellStatus::SUCCESS
+//!         },
+//!     )
+//! }
+//! ```
+//!
+//! The code of the handler must stay loaded, so commands are usually
+//! installed by drivers, which the shell loads with its `load` command.
+
+use super::{Shell, ShellParameters};
+use crate::proto::device_path::text::PoolString;
+use crate::proto::unsafe_protocol;
+use crate::table::boot::BootServices;
+use crate::{CStr16, CStr8, Char16, Char8, Result, Status};
+use core::ffi::c_void;
+use core::ptr;
+
+#[cfg(feature = "alloc")]
+use {
+    crate::mem::{try_box, try_to_vec},
+    crate::table::boot::MemoryType,
+    crate::table::{Boot, SystemTable},
+    crate::{Handle, Identify},
+    alloc::boxed::Box,
+    alloc::vec::Vec,
+    core::cell::RefCell,
+    core::marker::PhantomData,
+    core::mem,
+};
+
+newtype_enum! {
+    /// Exit status of a shell command.
+    ///
+    /// The values match the error codes of [`Status`] without the error
+    /// bit.
+    pub enum ShellStatus: usize => {
+        /// The command succeeded.
+        SUCCESS = 0,
+        /// The command could not be loaded.
+        LOAD_ERROR = 1,
+        /// A parameter was invalid.
+        INVALID_PARAMETER = 2,
+        /// The operation is not supported.
+        UNSUPPORTED = 3,
+        /// The buffer was not the proper size for the request.
+        BAD_BUFFER_SIZE = 4,
+        /// The buffer was too small.
+        BUFFER_TOO_SMALL = 5,
+        /// There is no data pending.
+        NOT_READY = 6,
+        /// A hardware error occurred.
+        DEVICE_ERROR = 7,
+        /// The device is write-protected.
+        WRITE_PROTECTED = 8,
+        /// A resource has run out.
+        OUT_OF_RESOURCES = 9,
+        /// The file system is corrupted.
+        VOLUME_CORRUPTED = 10,
+        /// There is no more space on the file system.
+        VOLUME_FULL = 11,
+        /// The device does not contain any medium.
+        NO_MEDIA = 12,
+        /// The medium has changed.
+        MEDIA_CHANGED = 13,
+        /// The item was not found.
+        NOT_FOUND = 14,
+        /// Access was denied.
+        ACCESS_DENIED = 15,
+        /// The timeout expired.
+        TIMEOUT = 18,
+        /// The operation was not started.
+        NOT_STARTED = 19,
+        /// The operation was already started.
+        ALREADY_STARTED = 20,
+        /// The operation was aborted.
+        ABORTED = 21,
+        /// The versions are incompatible.
+        INCOMPATIBLE_VERSION = 25,
+        /// The operation is not permitted by the security policy.
+        SECURITY_VIOLATION = 26,
+        /// The compared items are not equal.
+        NOT_EQUAL = 27,
+    }
+}
+
+impl From<Status> for ShellStatus {
+    fn from(status: Status) -> Self {
+        if status.is_error() {
+            // Clear the error bit.
+            Self(status.0 & (usize::MAX >> 1))
+        } else {
+            Self::SUCCESS
+        }
+    }
+}
+
+/// The EFI Shell Dynamic Command protocol.
+///
+/// Each instance of this protocol adds a command to the UEFI Shell. Use
+/// [`install`] to implement a command in Rust.
+#[repr(C)]
+#[unsafe_protocol("3c7200e9-005f-4ea4-87de-a3dfac8a27c3")]
+pub struct ShellDynamicCommand {
+    command_name: *const Char16,
+    handler: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        system_table: *mut c_void,
+        parameters: *mut ShellParameters,
+        shell: *mut Shell,
+    ) -> ShellStatus,
+    get_help: unsafe extern "efiapi" fn(this: *mut Self, language: *const Char8) -> *mut Char16,
+}
+
+impl ShellDynamicCommand {
+    /// Get the name of the command.
+    #[must_use]
+    pub fn name(&self) -> &CStr16 {
+        // Safety: the name is a null-terminated string owned by the
+        // protocol.
+        unsafe { CStr16::from_ptr(self.command_name) }
+    }
+
+    /// Get the help text of the command, in the format of the shell's
+    /// manual pages. If `language` is `None`, the command picks its
+    /// default language.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the command has no help text in `language`.
+    pub fn help<'boot>(
+        &mut self,
+        boot_services: &'boot BootServices,
+        language: Option<&CStr8>,
+    ) -> Result<PoolString<'boot>> {
+        let language = language.map_or(ptr::null(), CStr8::as_ptr);
+        let text = unsafe { (self.get_help)(self, language) };
+        if text.is_null() {
+            return Err(Status::NOT_FOUND.into());
+        }
+        PoolString::new(boot_services, text)
+    }
+}
+
+/// The arguments of a command handler installed with [`install`].
+#[cfg(feature = "alloc")]
+pub struct CommandContext<'a> {
+    /// The system table, whose console may be redirected by the shell.
+    pub system_table: &'a mut SystemTable<Boot>,
+    /// The command line. The first argument is the name of the command.
+    pub parameters: &'a ShellParameters,
+    /// The shell running the command.
+    pub shell: &'a mut Shell,
+}
+
+/// Install a [`ShellDynamicCommand`] protocol for the command `name` on
+/// `handle`, or on a new handle if `handle` is `None`. Returns the handle.
+///
+/// `help` is returned for all languages, and should be in the format of
+/// the shell's manual pages, starting with a `.TH` line. `handler` runs
+/// the command, and is not called again while it is running.
+///
+/// The command is never freed, so the protocol stays installed until the
+/// end of boot services.
+///
+/// # Errors
+///
+/// See [`BootServices::install_protocol_interface`].
+#[cfg(feature = "alloc")]
+pub fn install<H>(
+    bt: &BootServices,
+    handle: Option<Handle>,
+    name: &CStr16,
+    help: &CStr16,
+    handler: H,
+) -> Result<Handle>
+where
+    H: FnMut(CommandContext<'_>) -> ShellStatus + 'static,
+{
+    let name = try_to_vec(name.as_slice_with_nul())?;
+    let help = try_to_vec(help.as_slice_with_nul())?;
+    let instance = Box::into_raw(try_box(CommandInstance {
+        raw: ShellDynamicCommand {
+            // The characters are on the heap, so they don't move with
+            // `name`.
+            command_name: name.as_ptr(),
+            handler: run_command::<H>,
+            get_help: get_help::<H>,
+            _no_send_or_sync: PhantomData,
+        },
+        boot_services: bt,
+        name,
+        help,
+        handler: RefCell::new(handler),
+    })?);
+    let result = unsafe {
+        bt.install_protocol_interface(handle, &ShellDynamicCommand::GUID, instance.cast())
+    };
+    if result.is_err() {
+        drop(unsafe { Box::from_raw(instance) });
+    }
+    result
+}
+
+/// A `ShellDynamicCommand` protocol instance backed by a closure.
+#[cfg(feature = "alloc")]
+#[repr(C)]
+struct CommandInstance<H> {
+    // Must be the first field so that the protocol's `this` pointer can be
+    // converted back to a `CommandInstance`.
+    raw: ShellDynamicCommand,
+    boot_services: *const BootServices,
+    // Referenced by `raw.command_name`.
+    #[allow(dead_code)]
+    name: Vec<Char16>,
+    /// Null-terminated help text.
+    help: Vec<Char16>,
+    handler: RefCell<H>,
+}
+
+#[cfg(feature = "alloc")]
+unsafe extern "efiapi" fn run_command<H>(
+    this: *mut ShellDynamicCommand,
+    system_table: *mut c_void,
+    parameters: *mut ShellParameters,
+    shell: *mut Shell,
+) -> ShellStatus
+where
+    H: FnMut(CommandContext<'_>) -> ShellStatus + 'static,
+{
+    let instance = &*this.cast::<CommandInstance<H>>();
+    let mut system_table = match SystemTable::from_ptr(system_table) {
+        Some(system_table) => system_table,
+        None => return ShellStatus::INVALID_PARAMETER,
+    };
+    let (parameters, shell) = match (parameters.as_ref(), shell.as_mut()) {
+        (Some(parameters), Some(shell)) => (parameters, shell),
+        _ => return ShellStatus::INVALID_PARAMETER,
+    };
+    let mut handler = match instance.handler.try_borrow_mut() {
+        Ok(handler) => handler,
+        // The command is already running.
+        Err(_) => return ShellStatus::ACCESS_DENIED,
+    };
+    handler(CommandContext {
+        system_table: &mut system_table,
+        parameters,
+        shell,
+    })
+}
+
+#[cfg(feature = "alloc")]
+unsafe extern "efiapi" fn get_help<H>(
+    this: *mut ShellDynamicCommand,
+    _language: *const Char8,
+) -> *mut Char16 {
+    let instance = &*this.cast::<CommandInstance<H>>();
+    let help = &instance.help;
+    let size = mem::size_of_val(help.as_slice());
+    // The shell frees the text with `FreePool`.
+    match (*instance.boot_services).allocate_pool(MemoryType::BOOT_SERVICES_DATA, size) {
+        Ok(text) => {
+            ptr::copy_nonoverlapping(help.as_ptr(), text.cast::<Char16>(), help.len());
+            text.cast()
+        }
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::cstr16;
+    use crate::mock::MockFirmware;
+
+    #[test]
+    fn test_shell_status() {
+        assert_eq!(ShellStatus::from(Status::SUCCESS), ShellStatus::SUCCESS);
+        assert_eq!(
+            ShellStatus::from(Status::WARN_UNKNOWN_GLYPH),
+            ShellStatus::SUCCESS
+        );
+        assert_eq!(ShellStatus::from(Status::NOT_FOUND), ShellStatus::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_install() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let handle = install(
+            bt,
+            None,
+            cstr16!("hello"),
+            cstr16!(".TH hello 0\r\n"),
+            |_| ShellStatus::SUCCESS,
+        )
+        .unwrap();
+
+        let mut command = bt
+            .open_protocol_exclusive::<ShellDynamicCommand>(handle)
+            .unwrap();
+        assert_eq!(command.name(), cstr16!("hello"));
+        let help = command.help(bt, None).unwrap();
+        assert_eq!(&*help, cstr16!(".TH hello 0\r\n"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Wildcard expansion of file paths.
+//!
+//! [`expand`] finds the files matching a pattern such as `*.efi` or
+//! `FS0:\EFI\*\grub*.efi`. It uses [`Shell::find_files`] when the
+//! application was started from the UEFI Shell, and otherwise walks the
+//! file system the application was loaded from with [`find_files_in`].
+//!
+//! The wildcards follow the conventions of the shell:
+//!
+//! * `*` matches any number of characters.
+//! * `?` matches a single character.
+//! * `[abc]` matches one of the listed characters, and `[a-z]` one
+//!   character of a range.
+//!
+//! Matching is case-insensitive for ASCII letters, like the FAT file
+//! systems used by UEFI.
+
+use super::Shell;
+use crate::mem::{try_reserve, try_to_vec};
+use crate::proto::media::file::{Directory, File, FileAttribute, FileMode, FileType};
+use crate::table::boot::BootServices;
+use crate::{CStr16, CString16, Char16, Result, Status};
+use alloc::vec::Vec;
+
+const BACKSLASH: u16 = b'\\' as u16;
+const COLON: u16 = b':' as u16;
+
+/// Returns true if `path` contains a wildcard.
+#[must_use]
+pub fn is_pattern(path: &CStr16) -> bool {
+    has_wildcard(path.to_u16_slice())
+}
+
+fn has_wildcard(chars: &[u16]) -> bool {
+    chars
+        .iter()
+        .any(|&c| c == u16::from(b'*') || c == u16::from(b'?') || c == u16::from(b'['))
+}
+
+/// Returns true if the file name `name` matches `pattern`.
+///
+/// The pattern is matched against the whole name; use [`find_files_in`]
+/// to match patterns with several path components.
+#[must_use]
+pub fn matches(pattern: &CStr16, name: &CStr16) -> bool {
+    matches_chars(pattern.to_u16_slice(), name.to_u16_slice())
+}
+
+/// Converts an ASCII letter to lower case, for case-insensitive matching.
+fn fold(c: u16) -> u16 {
+    Char16::try_from(c).map_or(c, |c| u16::from(c.to_ascii_lowercase()))
+}
+
+/// Matches one character against the set `[...]` at the start of
+/// `pattern`. Returns whether the character matched and the length of the
+/// set, or `None` if the set is not terminated, in which case the `[`
+/// is matched literally.
+fn match_set(pattern: &[u16], c: u16) -> Option<(bool, usize)> {
+    let end = pattern.iter().skip(1).position(|&p| p == u16::from(b']'))? + 1;
+    let set = &pattern[1..end];
+    let c = fold(c);
+    let mut matched = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == u16::from(b'-') {
+            matched |= (fold(set[i])..=fold(set[i + 2])).contains(&c);
+            i += 3;
+        } else {
+            matched |= fold(set[i]) == c;
+            i += 1;
+        }
+    }
+    Some((matched, end + 1))
+}
+
+fn matches_chars(pattern: &[u16], name: &[u16]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` in the pattern, and the position in
+    // the name it is currently matched up to.
+    let mut backtrack = None;
+
+    while n < name.len() {
+        let step = match pattern.get(p) {
+            Some(&c) if c == u16::from(b'*') => {
+                p += 1;
+                backtrack = Some((p, n));
+                continue;
+            }
+            Some(&c) if c == u16::from(b'?') => Some(1),
+            Some(&c) if c == u16::from(b'[') => match match_set(&pattern[p..], name[n]) {
+                Some((true, len)) => Some(len),
+                Some((false, _)) => None,
+                None => (name[n] == c).then_some(1),
+            },
+            Some(&c) => (fold(c) == fold(name[n])).then_some(1),
+            None => None,
+        };
+
+        match (step, backtrack) {
+            (Some(len), _) => {
+                p += len;
+                n += 1;
+            }
+            // Let the last `*` match one more character and try again.
+            (None, Some((star_p, star_n))) => {
+                p = star_p;
+                n = star_n + 1;
+                backtrack = Some((star_p, star_n + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == u16::from(b'*'))
+}
+
+/// Find the files below `root` matching `pattern`, and return their
+/// paths, relative to `root` and starting with a backslash.
+///
+/// Each component of the pattern, separated by backslashes, may contain
+/// wildcards. Components without wildcards are opened directly, and keep
+/// the case they have in the pattern. `.` and `..` are never matched by
+/// wildcards.
+///
+/// # Errors
+///
+/// Returns errors of [`Directory::read_entry_boxed`] and [`File::open`],
+/// except [`Status::NOT_FOUND`], which means that a component doesn't
+/// exist and so nothing below it matches.
+pub fn find_files_in(root: &mut Directory, pattern: &CStr16) -> Result<Vec<CString16>> {
+    let components = pattern
+        .to_u16_slice()
+        .split(|&c| c == BACKSLASH)
+        .filter(|component| !component.is_empty())
+        .collect::<Vec<_>>();
+    let mut found = Vec::new();
+    if !components.is_empty() {
+        walk(root, &components, &mut Vec::new(), &mut found)?;
+    }
+    Ok(found)
+}
+
+/// Matches the first of `components` against the entries of `dir`, and
+/// recurses into matching directories with the remaining components.
+/// `path` holds the path of `dir`, without a null terminator.
+fn walk(
+    dir: &mut Directory,
+    components: &[&[u16]],
+    path: &mut Vec<u16>,
+    found: &mut Vec<CString16>,
+) -> Result {
+    let (component, rest) = components.split_first().unwrap();
+
+    // Collect the matching names first, since opening a file while reading
+    // the entries of a directory is not guaranteed to work.
+    let mut names = Vec::new();
+    if has_wildcard(component) {
+        dir.reset_entry_readout()?;
+        while let Some(info) = dir.read_entry_boxed()? {
+            let name = info.file_name().to_u16_slice();
+            let is_dir = info.attribute().contains(FileAttribute::DIRECTORY);
+            let is_dot = name == [u16::from(b'.')] || name == [u16::from(b'.'); 2];
+            if !is_dot && (rest.is_empty() || is_dir) && matches_chars(component, name) {
+                try_reserve(&mut names, 1)?;
+                names.push(try_to_vec(info.file_name().to_u16_slice_with_nul())?);
+            }
+        }
+    } else {
+        let mut name = try_to_vec(component)?;
+        try_reserve(&mut name, 1)?;
+        name.push(0);
+        names.push(name);
+    }
+
+    for name in names {
+        let len = path.len();
+        try_reserve(path, name.len())?;
+        path.push(BACKSLASH);
+        path.extend_from_slice(&name[..name.len() - 1]);
+
+        // The names are null-terminated and come from a `CStr16`.
+        let file_name = CStr16::from_u16_with_nul(&name).unwrap();
+        match dir.open(file_name, FileMode::Read, FileAttribute::empty()) {
+            Ok(file) if rest.is_empty() => {
+                drop(file);
+                let mut full = try_to_vec(path)?;
+                try_reserve(&mut full, 1)?;
+                full.push(0);
+                try_reserve(found, 1)?;
+                found.push(CString16::try_from(full).unwrap());
+            }
+            Ok(file) => {
+                if let FileType::Dir(mut subdir) = file.into_type()? {
+                    walk(&mut subdir, rest, path, found)?;
+                }
+            }
+            Err(err) if err.status() == Status::NOT_FOUND => {}
+            Err(err) => return Err(err),
+        }
+
+        path.truncate(len);
+    }
+    Ok(())
+}
+
+/// Find the files matching `pattern`, and return their paths.
+///
+/// If the UEFI Shell is running, this calls [`Shell::find_files`], which
+/// supports file system mappings such as `FS0:` and returns paths that
+/// start with the mapping. Otherwise the pattern is matched with
+/// [`find_files_in`] against the root of the file system the application
+/// was loaded from, and the returned paths start with a backslash.
+///
+/// # Errors
+///
+/// * [`uefi::Status::UNSUPPORTED`]: the shell is not running, and the
+///   pattern starts with a file system mapping.
+///
+/// See also [`Shell::find_files`], [`find_files_in`], and
+/// [`BootServices::get_image_file_system`].
+pub fn expand(bt: &BootServices, pattern: &CStr16) -> Result<Vec<CString16>> {
+    if let Ok(handle) = bt.get_handle_for_protocol::<Shell>() {
+        let shell = bt.open_protocol_exclusive::<Shell>(handle)?;
+        return shell.find_files(pattern);
+    }
+
+    // Mappings are defined by the shell, so they can't be resolved
+    // without it.
+    if pattern.to_u16_slice().contains(&COLON) {
+        return Err(Status::UNSUPPORTED.into());
+    }
+
+    let mut fs = bt.get_image_file_system(bt.image_handle())?;
+    let mut root = fs.open_volume()?;
+    find_files_in(&mut root, pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cstr16;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(cstr16!("*.efi"), cstr16!("grubx64.efi")));
+        assert!(matches(cstr16!("*.efi"), cstr16!("BOOTX64.EFI")));
+        assert!(!matches(cstr16!("*.efi"), cstr16!("grub.cfg")));
+        assert!(matches(cstr16!("grub*.efi"), cstr16!("grub.efi")));
+        assert!(matches(cstr16!("*a*b"), cstr16!("xaab")));
+        assert!(!matches(cstr16!("*a*b"), cstr16!("xaba")));
+        assert!(matches(cstr16!("boot??.efi"), cstr16!("boota4.efi")));
+        assert!(!matches(cstr16!("boot??.efi"), cstr16!("bootx64.efi")));
+        assert!(matches(cstr16!("*"), cstr16!("")));
+        assert!(!matches(cstr16!(""), cstr16!("a")));
+    }
+
+    #[test]
+    fn test_matches_sets() {
+        assert!(matches(cstr16!("file[0-9].txt"), cstr16!("file7.txt")));
+        assert!(!matches(cstr16!("file[0-9].txt"), cstr16!("fileA.txt")));
+        assert!(matches(cstr16!("[ab]*"), cstr16!("Bootmgr")));
+        assert!(!matches(cstr16!("[ab]*"), cstr16!("cat")));
+        // An unterminated set is matched literally.
+        assert!(matches(cstr16!("[x"), cstr16!("[x")));
+    }
+
+    #[test]
+    fn test_is_pattern() {
+        assert!(is_pattern(cstr16!("\\EFI\\*\\grub*.efi")));
+        assert!(is_pattern(cstr16!("file[12]")));
+        assert!(!is_pattern(cstr16!("\\EFI\\BOOT\\BOOTX64.EFI")));
+    }
+
+    #[test]
+    #[cfg(feature = "mock")]
+    fn test_find_files_in() {
+        use crate::mock::{MockFileSystem, MockFirmware};
+        use crate::proto::media::fs::SimpleFileSystem;
+
+        let mut firmware = MockFirmware::new();
+        let fs = MockFileSystem::new("ESP");
+        fs.add_file("EFI/BOOT/BOOTX64.EFI", b"");
+        fs.add_file("EFI/debian/grubx64.efi", b"");
+        fs.add_file("EFI/debian/grub.cfg", b"");
+        fs.add_file("EFI/fedora/grubx64.efi", b"");
+        fs.add_file("EFI/fedora/shimx64.efi", b"");
+        let handle = firmware.install_file_system(&fs);
+
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut sfs = bt
+            .open_protocol_exclusive::<SimpleFileSystem>(handle)
+            .unwrap();
+        let mut root = sfs.open_volume().unwrap();
+
+        let mut found = find_files_in(&mut root, cstr16!("\\efi\\*\\grub*.efi")).unwrap();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                cstr16!("\\efi\\debian\\grubx64.efi"),
+                cstr16!("\\efi\\fedora\\grubx64.efi"),
+            ]
+        );
+
+        let found = find_files_in(&mut root, cstr16!("EFI\\BOOT\\BOOTX64.EFI")).unwrap();
+        assert_eq!(found, [cstr16!("\\EFI\\BOOT\\BOOTX64.EFI")]);
+
+        assert!(find_files_in(&mut root, cstr16!("\\EFI\\missing\\*"))
+            .unwrap()
+            .is_empty());
+        assert!(find_files_in(&mut root, cstr16!("\\EFI\\*\\*.txt"))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,492 @@
+//! EFI Shell protocol.
+
+use crate::proto::unsafe_protocol;
+use crate::table::runtime::VariableAttributes;
+use crate::{CStr16, Char16, Event, Result, Status};
+use core::ffi::c_void;
+use core::marker::PhantomData;
+use core::ptr;
+
+#[cfg(feature = "alloc")]
+use {
+    crate::mem::{try_reserve, try_to_vec, try_with_capacity},
+    crate::CString16,
+    alloc::vec::Vec,
+};
+
+pub mod dynamic_command;
+#[cfg(feature = "alloc")]
+pub mod glob;
+mod parameters;
+
+pub use parameters::{ShellArgs, ShellParameters};
+
+/// Whether a shell environment variable or alias persists across
+/// reboots.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Volatility {
+    /// Lost when the shell exits or the system resets.
+    Volatile,
+    /// Stored in a non-volatile UEFI variable.
+    NonVolatile,
+}
+
+impl Volatility {
+    const fn from_bool(volatile: bool) -> Self {
+        if volatile {
+            Self::Volatile
+        } else {
+            Self::NonVolatile
+        }
+    }
+
+    const fn is_volatile(self) -> bool {
+        matches!(self, Self::Volatile)
+    }
+}
+
+/// The EFI Shell protocol.
+///
+/// This protocol is installed by the UEFI Shell on the image handle of
+/// every application it launches. It is not available when an
+/// application is started directly by the boot manager.
+///
+/// Only the environment variable, alias, and file search functions are
+/// currently bound.
+#[repr(C)]
+#[unsafe_protocol("6302d008-7f9b-4f30-87ac-60c9fef5da4e")]
+pub struct Shell {
+    execute: usize,
+    get_env: extern "efiapi" fn(name: *const Char16) -> *const Char16,
+    set_env:
+        extern "efiapi" fn(name: *const Char16, value: *const Char16, volatile: bool) -> Status,
+    get_alias: extern "efiapi" fn(alias: *const Char16, volatile: *mut bool) -> *const Char16,
+    set_alias: extern "efiapi" fn(
+        command: *const Char16,
+        alias: *const Char16,
+        replace: bool,
+        volatile: bool,
+    ) -> Status,
+    get_help_text: usize,
+    get_device_path_from_map: usize,
+    get_map_from_device_path: usize,
+    get_device_path_from_file_path: usize,
+    get_file_path_from_device_path: usize,
+    set_map: usize,
+    get_cur_dir: usize,
+    set_cur_dir: usize,
+    open_file_list: usize,
+    free_file_list: extern "efiapi" fn(file_list: *mut *mut ShellFileInfo) -> Status,
+    remove_dup_in_file_list: usize,
+    batch_is_active: usize,
+    is_root_shell: usize,
+    enable_page_break: usize,
+    disable_page_break: usize,
+    get_page_break: usize,
+    get_device_name: usize,
+    get_file_info: usize,
+    set_file_info: usize,
+    open_file_by_name: usize,
+    close_file: usize,
+    create_file: usize,
+    read_file: usize,
+    write_file: usize,
+    delete_file: usize,
+    delete_file_by_name: usize,
+    get_file_position: usize,
+    set_file_position: usize,
+    flush_file: usize,
+    find_files: extern "efiapi" fn(
+        file_pattern: *const Char16,
+        file_list: *mut *mut ShellFileInfo,
+    ) -> Status,
+    find_files_in_dir: usize,
+    get_file_size: usize,
+    open_root: usize,
+    open_root_by_handle: usize,
+    execution_break: Event,
+    major_version: u32,
+    minor_version: u32,
+
+    // Added in version 2.1.
+    register_guid_name: usize,
+    get_guid_name: usize,
+    get_guid_from_name: usize,
+    get_env_ex: extern "efiapi" fn(name: *const Char16, attributes: *mut u32) -> *const Char16,
+}
+
+impl Shell {
+    /// Get the shell version as a `(major, minor)` pair.
+    #[must_use]
+    pub const fn version(&self) -> (u32, u32) {
+        (self.major_version, self.minor_version)
+    }
+
+    /// Get the value of the environment variable `name`, or `None` if
+    /// it is not set.
+    #[must_use]
+    pub fn get_env(&self, name: &CStr16) -> Option<&CStr16> {
+        let value = (self.get_env)(name.as_ptr());
+        // Safety: the shell returns either null or a pointer to a
+        // null-terminated string that it owns.
+        (!value.is_null()).then(|| unsafe { CStr16::from_ptr(value) })
+    }
+
+    /// Get the value and volatility of the environment variable `name`,
+    /// or `None` if it is not set.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::UNSUPPORTED`]: the shell version is older than
+    ///   2.1, which does not report variable attributes.
+    pub fn get_env_with_volatility(&self, name: &CStr16) -> Result<Option<(&CStr16, Volatility)>> {
+        if self.version() < (2, 1) {
+            return Err(Status::UNSUPPORTED.into());
+        }
+
+        let mut attributes = 0;
+        let value = (self.get_env_ex)(name.as_ptr(), &mut attributes);
+        if value.is_null() {
+            return Ok(None);
+        }
+        let attributes = VariableAttributes::from_bits_truncate(attributes);
+        let volatility =
+            Volatility::from_bool(!attributes.contains(VariableAttributes::NON_VOLATILE));
+        // Safety: the shell returns either null or a pointer to a
+        // null-terminated string that it owns.
+        Ok(Some((unsafe { CStr16::from_ptr(value) }, volatility)))
+    }
+
+    /// Get an iterator over the names of all environment variables.
+    #[must_use]
+    pub fn env_names(&self) -> EnvNames<'_> {
+        EnvNames {
+            next: (self.get_env)(ptr::null()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Set the environment variable `name` to `value`. An empty `value`
+    /// deletes the variable.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::ACCESS_DENIED`]: the variable is read-only.
+    /// * [`uefi::Status::OUT_OF_RESOURCES`]
+    pub fn set_env(&mut self, name: &CStr16, value: &CStr16, volatility: Volatility) -> Result {
+        (self.set_env)(name.as_ptr(), value.as_ptr(), volatility.is_volatile()).into()
+    }
+
+    /// Get the command that `alias` expands to and whether the alias is
+    /// volatile, or `None` if no such alias exists.
+    #[must_use]
+    pub fn get_alias(&self, alias: &CStr16) -> Option<(&CStr16, Volatility)> {
+        let mut volatile = false;
+        let command = (self.get_alias)(alias.as_ptr(), &mut volatile);
+        // Safety: the shell returns either null or a pointer to a
+        // null-terminated string that it owns.
+        (!command.is_null())
+            .then(|| unsafe { (CStr16::from_ptr(command), Volatility::from_bool(volatile)) })
+    }
+
+    /// Get the names of all aliases.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::OUT_OF_RESOURCES`]: the names couldn't be allocated.
+    #[cfg(feature = "alloc")]
+    pub fn aliases(&self) -> Result<Vec<CString16>> {
+        let list = (self.get_alias)(ptr::null(), ptr::null_mut());
+        if list.is_null() {
+            return Ok(Vec::new());
+        }
+        // Safety: the shell returns a null-terminated, semicolon
+        // separated list.
+        split_alias_list(unsafe { CStr16::from_ptr(list) })
+    }
+
+    /// Create or replace the alias `alias` for `command`.
+    ///
+    /// If `replace` is false and the alias already exists, the alias is
+    /// left unchanged and an error is returned.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::ACCESS_DENIED`]: the alias already exists and
+    ///   `replace` is false.
+    /// * [`uefi::Status::INVALID_PARAMETER`]
+    pub fn set_alias(
+        &mut self,
+        command: &CStr16,
+        alias: &CStr16,
+        replace: bool,
+        volatility: Volatility,
+    ) -> Result {
+        (self.set_alias)(
+            command.as_ptr(),
+            alias.as_ptr(),
+            replace,
+            volatility.is_volatile(),
+        )
+        .into()
+    }
+
+    /// Find the files matching `pattern`, and return their full paths,
+    /// e.g. `FS0:\\EFI\\BOOT\\BOOTX64.EFI`.
+    ///
+    /// The pattern may start with a file system mapping such as `FS0:`,
+    /// and may contain the wildcards `*`, `?` and `[...]` in any path
+    /// component. A relative pattern is resolved against the current
+    /// directory of the shell. If no file matches, an empty list is
+    /// returned.
+    ///
+    /// See [`glob::expand`] for a version that also works outside of the
+    /// shell.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::INVALID_PARAMETER`]
+    /// * [`uefi::Status::OUT_OF_RESOURCES`]
+    #[cfg(feature = "alloc")]
+    pub fn find_files(&self, pattern: &CStr16) -> Result<Vec<CString16>> {
+        let mut list = ptr::null_mut();
+        let status = (self.find_files)(pattern.as_ptr(), &mut list);
+        if status == Status::NOT_FOUND || list.is_null() {
+            return Ok(Vec::new());
+        }
+        if status.is_error() {
+            return Err(status.into());
+        }
+
+        // Safety: the shell returned a valid list, which is freed below.
+        let names = unsafe { file_list_names(list) };
+        // Freeing only fails for invalid lists.
+        let _ = (self.free_file_list)(&mut list);
+        names
+    }
+
+    /// Delete the alias `alias`.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::NOT_FOUND`]: the alias does not exist.
+    pub fn delete_alias(&mut self, alias: &CStr16) -> Result {
+        // Passing the alias name as the command and a null alias deletes
+        // the alias.
+        (self.set_alias)(alias.as_ptr(), ptr::null(), true, false).into()
+    }
+}
+
+/// Iterator over environment variable names, returned by
+/// [`Shell::env_names`].
+#[derive(Debug)]
+pub struct EnvNames<'a> {
+    /// Pointer to the next name in a list of null-terminated strings,
+    /// terminated by an empty string.
+    next: *const Char16,
+    _marker: PhantomData<&'a Shell>,
+}
+
+impl<'a> Iterator for EnvNames<'a> {
+    type Item = &'a CStr16;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+
+        // Safety: the shell returns a list of null-terminated strings
+        // that it owns, terminated by an empty string.
+        let name = unsafe { CStr16::from_ptr(self.next) };
+        if name.to_u16_slice().is_empty() {
+            self.next = ptr::null();
+            return None;
+        }
+        self.next = unsafe { self.next.add(name.as_slice_with_nul().len()) };
+        Some(name)
+    }
+}
+
+/// An entry of a file list returned by the shell (`EFI_SHELL_FILE_INFO`).
+///
+/// The entries are linked in a circular list, whose head is an entry
+/// that is not part of the results.
+#[repr(C)]
+struct ShellFileInfo {
+    forward_link: *mut ShellFileInfo,
+    back_link: *mut ShellFileInfo,
+    status: Status,
+    full_name: *const Char16,
+    file_name: *const Char16,
+    handle: *mut c_void,
+    info: *mut c_void,
+}
+
+/// Copy the full names of the entries of a file list returned by the
+/// shell.
+///
+/// # Safety
+///
+/// `list` must point to the head of a valid file list.
+#[cfg(feature = "alloc")]
+unsafe fn file_list_names(list: *mut ShellFileInfo) -> Result<Vec<CString16>> {
+    let mut names = Vec::new();
+    let mut entry = (*list).forward_link;
+    while entry != list {
+        let name = CStr16::from_ptr((*entry).full_name);
+        let name = try_to_vec(name.to_u16_slice_with_nul())?;
+        try_reserve(&mut names, 1)?;
+        // The shell returns valid, null-terminated names.
+        names.push(CString16::try_from(name).unwrap());
+        entry = (*entry).forward_link;
+    }
+    Ok(names)
+}
+
+#[cfg(feature = "alloc")]
+fn split_alias_list(list: &CStr16) -> Result<Vec<CString16>> {
+    let mut aliases = Vec::new();
+    for alias in list
+        .to_u16_slice()
+        .split(|c| *c == u16::from(b';'))
+        .filter(|alias| !alias.is_empty())
+    {
+        let mut chars = try_with_capacity(alias.len() + 1)?;
+        chars.extend_from_slice(alias);
+        chars.push(0);
+        try_reserve(&mut aliases, 1)?;
+        // The input contains no nulls and only valid characters.
+        aliases.push(CString16::try_from(chars).unwrap());
+    }
+    Ok(aliases)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cstr16;
+    use core::mem::MaybeUninit;
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_env_names() {
+        let list: &[u16] = &[b'a' as u16, 0, b'b' as u16, b'c' as u16, 0, 0];
+        let names = EnvNames {
+            next: list.as_ptr().cast(),
+            _marker: PhantomData,
+        };
+        assert!(names.eq([cstr16!("a"), cstr16!("bc")]));
+    }
+
+    #[test]
+    fn test_file_list_names() {
+        fn entry(full_name: &CStr16) -> ShellFileInfo {
+            ShellFileInfo {
+                forward_link: ptr::null_mut(),
+                back_link: ptr::null_mut(),
+                status: Status::SUCCESS,
+                full_name: full_name.as_ptr(),
+                file_name: ptr::null(),
+                handle: ptr::null_mut(),
+                info: ptr::null_mut(),
+            }
+        }
+
+        let mut list = [
+            entry(cstr16!("")),
+            entry(cstr16!("FS0:\\a.efi")),
+            entry(cstr16!("FS0:\\b.efi")),
+        ];
+        let head = list.as_mut_ptr();
+        unsafe {
+            (*head).forward_link = head.add(1);
+            (*head.add(1)).forward_link = head.add(2);
+            (*head.add(2)).forward_link = head;
+            let names = file_list_names(head).unwrap();
+            assert_eq!(names, [cstr16!("FS0:\\a.efi"), cstr16!("FS0:\\b.efi")]);
+
+            (*head).forward_link = head;
+            assert!(file_list_names(head).unwrap().is_empty());
+        }
+    }
+
+    /// Whether the command and alias passed to `set_alias` were null.
+    static SET_ALIAS_COMMAND_NULL: AtomicBool = AtomicBool::new(false);
+    static SET_ALIAS_ALIAS_NULL: AtomicBool = AtomicBool::new(false);
+
+    fn set_alias_nulls() -> (bool, bool) {
+        (
+            SET_ALIAS_COMMAND_NULL.load(Ordering::Relaxed),
+            SET_ALIAS_ALIAS_NULL.load(Ordering::Relaxed),
+        )
+    }
+
+    extern "efiapi" fn stub_set_alias(
+        command: *const Char16,
+        alias: *const Char16,
+        _replace: bool,
+        _volatile: bool,
+    ) -> Status {
+        SET_ALIAS_COMMAND_NULL.store(command.is_null(), Ordering::Relaxed);
+        SET_ALIAS_ALIAS_NULL.store(alias.is_null(), Ordering::Relaxed);
+        Status::SUCCESS
+    }
+
+    #[test]
+    fn test_delete_alias() {
+        extern "efiapi" fn stub_get_env(_name: *const Char16) -> *const Char16 {
+            ptr::null()
+        }
+        extern "efiapi" fn stub_set_env(_: *const Char16, _: *const Char16, _: bool) -> Status {
+            Status::UNSUPPORTED
+        }
+        extern "efiapi" fn stub_free_file_list(_: *mut *mut ShellFileInfo) -> Status {
+            Status::UNSUPPORTED
+        }
+        extern "efiapi" fn stub_find_files(_: *const Char16, _: *mut *mut ShellFileInfo) -> Status {
+            Status::UNSUPPORTED
+        }
+        extern "efiapi" fn stub_get_alias(_: *const Char16, _: *mut bool) -> *const Char16 {
+            ptr::null()
+        }
+        extern "efiapi" fn stub_get_env_ex(_: *const Char16, _: *mut u32) -> *const Char16 {
+            ptr::null()
+        }
+
+        // The unbound functions are plain integers, so a zeroed table is
+        // valid once the function pointers and the event are filled in.
+        let mut shell = MaybeUninit::<Shell>::zeroed();
+        let p = shell.as_mut_ptr();
+        let mut event_storage = 0u8;
+        unsafe {
+            ptr::addr_of_mut!((*p).get_env).write(stub_get_env);
+            ptr::addr_of_mut!((*p).set_env).write(stub_set_env);
+            ptr::addr_of_mut!((*p).get_alias).write(stub_get_alias);
+            ptr::addr_of_mut!((*p).set_alias).write(stub_set_alias);
+            ptr::addr_of_mut!((*p).free_file_list).write(stub_free_file_list);
+            ptr::addr_of_mut!((*p).find_files).write(stub_find_files);
+            ptr::addr_of_mut!((*p).execution_break)
+                .write(Event::from_ptr(ptr::addr_of_mut!(event_storage).cast()).unwrap());
+            ptr::addr_of_mut!((*p).get_env_ex).write(stub_get_env_ex);
+        }
+        let mut shell = unsafe { shell.assume_init() };
+
+        shell.delete_alias(cstr16!("ls")).unwrap();
+        // The alias name is passed as the command, and the alias is null.
+        assert_eq!(set_alias_nulls(), (false, true));
+
+        shell
+            .set_alias(cstr16!("dir"), cstr16!("ls"), true, Volatility::Volatile)
+            .unwrap();
+        assert_eq!(set_alias_nulls(), (false, false));
+    }
+
+    #[test]
+    fn test_split_alias_list() {
+        assert_eq!(
+            split_alias_list(cstr16!("dir;md;;cd")).unwrap(),
+            [cstr16!("dir"), cstr16!("md"), cstr16!("cd")]
+        );
+        assert!(split_alias_list(cstr16!(";")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::proto::unsafe_protocol;
+use crate::{CStr16, Char16};
+use core::ffi::c_void;
+use core::{fmt, slice};
+
+/// The EFI Shell Parameters protocol.
+///
+/// This protocol is installed by the UEFI Shell on the image handle of
+/// every application it launches, and is passed to the handlers of
+/// dynamic commands. It holds the parsed command line.
+#[repr(C)]
+#[unsafe_protocol("752f3136-4e16-4fdc-a22a-e5f46812f4ca")]
+pub struct ShellParameters {
+    argv: *const *const Char16,
+    argc: usize,
+    std_in: *mut c_void,
+    std_out: *mut c_void,
+    std_err: *mut c_void,
+}
+
+impl ShellParameters {
+    /// Get an iterator over the arguments of the command line. The first
+    /// argument is the name of the command.
+    #[must_use]
+    pub fn args(&self) -> ShellArgs<'_> {
+        let argv = if self.argc == 0 {
+            &[]
+        } else {
+            // Safety: the shell provides `argc` valid string pointers.
+            unsafe { slice::from_raw_parts(self.argv, self.argc) }
+        };
+        ShellArgs { argv: argv.iter() }
+    }
+}
+
+impl fmt::Debug for ShellParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShellParameters")
+            .field("args", &self.args())
+            .finish()
+    }
+}
+
+/// Iterator over the arguments of a command line, returned by
+/// [`ShellParameters::args`].
+#[derive(Clone)]
+pub struct ShellArgs<'a> {
+    argv: slice::Iter<'a, *const Char16>,
+}
+
+impl<'a> Iterator for ShellArgs<'a> {
+    type Item = &'a CStr16;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Safety: each argument is a null-terminated string owned by the
+        // shell.
+        self.argv
+            .next()
+            .map(|arg| unsafe { CStr16::from_ptr(*arg) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.argv.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ShellArgs<'_> {}
+
+impl fmt::Debug for ShellArgs<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cstr16;
+    use core::marker::PhantomData;
+    use core::ptr;
+
+    #[test]
+    fn test_args() {
+        let argv = [cstr16!("hello").as_ptr(), cstr16!("-v").as_ptr()];
+        let params = ShellParameters {
+            argv: argv.as_ptr(),
+            argc: argv.len(),
+            std_in: ptr::null_mut(),
+            std_out: ptr::null_mut(),
+            std_err: ptr::null_mut(),
+            _no_send_or_sync: PhantomData,
+        };
+        assert_eq!(params.args().len(), 2);
+        assert!(params.args().eq([cstr16!("hello"), cstr16!("-v")]));
+    }
+}
@@ -0,0 +1,70 @@
+//! `Speaker` protocol.
+
+use crate::proto::unsafe_protocol;
+use crate::util::div_ceil_u128;
+use crate::{Result, Status};
+use core::time::Duration;
+
+/// The speaker interface protocol, which controls the beeper of the
+/// platform, typically the PC speaker.
+///
+/// This protocol is not part of the UEFI specification, but is defined
+/// by EDK2 (`EFI_SPEAKER_IF_PROTOCOL`) and installed by the legacy
+/// speaker driver of some platforms. See [`sound::beep`] for a helper
+/// that falls back to the PC speaker when the protocol is missing.
+///
+/// [`sound::beep`]: crate::sound::beep
+#[repr(C)]
+#[unsafe_protocol("400b4476-3081-11d6-87ed-00062945c3b9")]
+pub struct Speaker {
+    generate_beep: unsafe extern "efiapi" fn(
+        this: *mut Speaker,
+        number_of_beeps: usize,
+        beep_duration: usize,
+        time_interval: usize,
+    ) -> Status,
+    set_beep_frequency: unsafe extern "efiapi" fn(this: *mut Speaker, frequency: u16) -> Status,
+}
+
+impl Speaker {
+    /// Beeps `count` times, each beep lasting `duration`, with `interval`
+    /// of silence between beeps. The durations are rounded up to the
+    /// nearest microsecond.
+    ///
+    /// This returns once all the beeps are done.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::INVALID_PARAMETER`]: a duration doesn't fit in a
+    ///   `usize` of microseconds.
+    /// * [`uefi::Status::DEVICE_ERROR`]: the speaker failed to beep.
+    pub fn generate_beep(
+        &mut self,
+        count: usize,
+        duration: Duration,
+        interval: Duration,
+    ) -> Result {
+        let to_micros = |duration: Duration| {
+            usize::try_from(div_ceil_u128(duration.as_nanos(), 1000))
+                .map_err(|_| Status::INVALID_PARAMETER)
+        };
+        let duration = to_micros(duration)?;
+        let interval = to_micros(interval)?;
+        unsafe { (self.generate_beep)(self, count, duration, interval) }.into()
+    }
+
+    /// Sets the tone of the next beeps.
+    ///
+    /// The value is passed to the firmware as is. The EDK2 driver for the
+    /// PC speaker programs it as the divisor of the 8254 timer's clock,
+    /// see [`sound::pit_divisor`] to compute it from a frequency.
+    ///
+    /// # Errors
+    ///
+    /// * [`uefi::Status::INVALID_PARAMETER`]: the value is not supported.
+    ///
+    /// [`sound::pit_divisor`]: crate::sound::pit_divisor
+    pub fn set_beep_frequency(&mut self, frequency: u16) -> Result {
+        unsafe { (self.set_beep_frequency)(self, frequency) }.into()
+    }
+}
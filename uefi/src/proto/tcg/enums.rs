@@ -8,6 +8,8 @@
 // too broadly.
 #![allow(missing_docs)]
 
+use core::fmt;
+
 newtype_enum! {
     /// Algorithm identifiers.
     ///
@@ -64,6 +66,7 @@ newtype_enum! {
         NONHOST_CONFIG = 0x0000_0010,
         NONHOST_INFO = 0x0000_0011,
         OMIT_BOOT_DEVICE_EVENTS = 0x0000_0012,
+        POST_CODE2 = 0x0000_0013,
         EFI_EVENT_BASE = 0x8000_0000,
         EFI_VARIABLE_DRIVER_CONFIG = 0x8000_0001,
         EFI_VARIABLE_BOOT = 0x8000_0002,
@@ -77,9 +80,75 @@ newtype_enum! {
         EFI_PLATFORM_FIRMWARE_BLOB2 = 0x8000_000a,
         EFI_HANDOFF_TABLES2 = 0x8000_000b,
         EFI_VARIABLE_BOOT2 = 0x8000_000c,
+        EFI_GPT_EVENT2 = 0x8000_000d,
         EFI_HCRTM_EVENT = 0x8000_0010,
         EFI_VARIABLE_AUTHORITY = 0x8000_00e0,
         EFI_SPDM_FIRMWARE_BLOB = 0x8000_00e1,
         EFI_SPDM_FIRMWARE_CONFIG = 0x8000_00e2,
+        EFI_SPDM_DEVICE_POLICY = 0x8000_00e3,
+        EFI_SPDM_DEVICE_AUTHORITY = 0x8000_00e4,
+    }
+}
+
+impl EventType {
+    /// Name of the event type in the specification, e.g. `EV_EFI_ACTION`,
+    /// or `None` if the event type is unknown.
+    #[must_use]
+    pub const fn name(self) -> Option<&'static str> {
+        let name = match self {
+            Self::PREBOOT_CERT => "EV_PREBOOT_CERT",
+            Self::POST_CODE => "EV_POST_CODE",
+            Self::UNUSED => "EV_UNUSED",
+            Self::NO_ACTION => "EV_NO_ACTION",
+            Self::SEPARATOR => "EV_SEPARATOR",
+            Self::ACTION => "EV_ACTION",
+            Self::EVENT_TAG => "EV_EVENT_TAG",
+            Self::CRTM_CONTENTS => "EV_S_CRTM_CONTENTS",
+            Self::CRTM_VERSION => "EV_S_CRTM_VERSION",
+            Self::CPU_MICROCODE => "EV_CPU_MICROCODE",
+            Self::PLATFORM_CONFIG_FLAGS => "EV_PLATFORM_CONFIG_FLAGS",
+            Self::TABLE_OF_DEVICES => "EV_TABLE_OF_DEVICES",
+            Self::COMPACT_HASH => "EV_COMPACT_HASH",
+            Self::IPL => "EV_IPL",
+            Self::IPL_PARTITION_DATA => "EV_IPL_PARTITION_DATA",
+            Self::NONHOST_CODE => "EV_NONHOST_CODE",
+            Self::NONHOST_CONFIG => "EV_NONHOST_CONFIG",
+            Self::NONHOST_INFO => "EV_NONHOST_INFO",
+            Self::OMIT_BOOT_DEVICE_EVENTS => "EV_OMIT_BOOT_DEVICE_EVENTS",
+            Self::POST_CODE2 => "EV_POST_CODE2",
+            Self::EFI_EVENT_BASE => "EV_EFI_EVENT_BASE",
+            Self::EFI_VARIABLE_DRIVER_CONFIG => "EV_EFI_VARIABLE_DRIVER_CONFIG",
+            Self::EFI_VARIABLE_BOOT => "EV_EFI_VARIABLE_BOOT",
+            Self::EFI_BOOT_SERVICES_APPLICATION => "EV_EFI_BOOT_SERVICES_APPLICATION",
+            Self::EFI_BOOT_SERVICES_DRIVER => "EV_EFI_BOOT_SERVICES_DRIVER",
+            Self::EFI_RUNTIME_SERVICES_DRIVER => "EV_EFI_RUNTIME_SERVICES_DRIVER",
+            Self::EFI_GPT_EVENT => "EV_EFI_GPT_EVENT",
+            Self::EFI_ACTION => "EV_EFI_ACTION",
+            Self::EFI_PLATFORM_FIRMWARE_BLOB => "EV_EFI_PLATFORM_FIRMWARE_BLOB",
+            Self::EFI_HANDOFF_TABLES => "EV_EFI_HANDOFF_TABLES",
+            Self::EFI_PLATFORM_FIRMWARE_BLOB2 => "EV_EFI_PLATFORM_FIRMWARE_BLOB2",
+            Self::EFI_HANDOFF_TABLES2 => "EV_EFI_HANDOFF_TABLES2",
+            Self::EFI_VARIABLE_BOOT2 => "EV_EFI_VARIABLE_BOOT2",
+            Self::EFI_GPT_EVENT2 => "EV_EFI_GPT_EVENT2",
+            Self::EFI_HCRTM_EVENT => "EV_EFI_HCRTM_EVENT",
+            Self::EFI_VARIABLE_AUTHORITY => "EV_EFI_VARIABLE_AUTHORITY",
+            Self::EFI_SPDM_FIRMWARE_BLOB => "EV_EFI_SPDM_FIRMWARE_BLOB",
+            Self::EFI_SPDM_FIRMWARE_CONFIG => "EV_EFI_SPDM_FIRMWARE_CONFIG",
+            Self::EFI_SPDM_DEVICE_POLICY => "EV_EFI_SPDM_DEVICE_POLICY",
+            Self::EFI_SPDM_DEVICE_AUTHORITY => "EV_EFI_SPDM_DEVICE_AUTHORITY",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+/// Writes the [`name`](EventType::name) of the event type, or its value in
+/// hexadecimal if it is unknown.
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{:#010x}", self.0),
+        }
     }
 }
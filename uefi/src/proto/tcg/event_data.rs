@@ -0,0 +1,410 @@
+//! Parsers for the event data of common event types.
+//!
+//! The [`v1::PcrEvent`] and [`v2::PcrEvent`] types only provide the raw
+//! event data. The layout of that data depends on the [`EventType`];
+//! [`EventData::parse`] decodes the layouts defined in the [TCG PC
+//! Client Platform Firmware Profile Specification][spec] for the most
+//! commonly verified event types.
+//!
+//! All multi-byte values in the event data are little endian, and may not
+//! be aligned.
+//!
+//! [`v1::PcrEvent`]: super::v1::PcrEvent
+//! [`v2::PcrEvent`]: super::v2::PcrEvent
+//! [spec]: https://trustedcomputinggroup.org/resource/pc-client-specific-platform-firmware-profile-specification/
+
+use super::EventType;
+use crate::data_types::{PhysicalAddress, UnalignedSlice};
+use crate::proto::device_path::DevicePath;
+use crate::{CStr16, Guid, Result, Status};
+
+/// Event data decoded according to its event type.
+#[derive(Debug)]
+pub enum EventData<'a> {
+    /// Data of an [`EFI_VARIABLE_DRIVER_CONFIG`], [`EFI_VARIABLE_BOOT`],
+    /// [`EFI_VARIABLE_BOOT2`], or [`EFI_VARIABLE_AUTHORITY`] event.
+    ///
+    /// [`EFI_VARIABLE_AUTHORITY`]: EventType::EFI_VARIABLE_AUTHORITY
+    /// [`EFI_VARIABLE_BOOT2`]: EventType::EFI_VARIABLE_BOOT2
+    /// [`EFI_VARIABLE_BOOT`]: EventType::EFI_VARIABLE_BOOT
+    /// [`EFI_VARIABLE_DRIVER_CONFIG`]: EventType::EFI_VARIABLE_DRIVER_CONFIG
+    Variable(VariableData<'a>),
+
+    /// Data of an [`EFI_BOOT_SERVICES_APPLICATION`],
+    /// [`EFI_BOOT_SERVICES_DRIVER`], or [`EFI_RUNTIME_SERVICES_DRIVER`]
+    /// event.
+    ///
+    /// [`EFI_BOOT_SERVICES_APPLICATION`]: EventType::EFI_BOOT_SERVICES_APPLICATION
+    /// [`EFI_BOOT_SERVICES_DRIVER`]: EventType::EFI_BOOT_SERVICES_DRIVER
+    /// [`EFI_RUNTIME_SERVICES_DRIVER`]: EventType::EFI_RUNTIME_SERVICES_DRIVER
+    ImageLoad(ImageLoadEvent<'a>),
+
+    /// Data of a [`CRTM_VERSION`] (`EV_S_CRTM_VERSION`) event.
+    ///
+    /// [`CRTM_VERSION`]: EventType::CRTM_VERSION
+    CrtmVersion(CrtmVersion<'a>),
+
+    /// Data of a [`SEPARATOR`] event.
+    ///
+    /// [`SEPARATOR`]: EventType::SEPARATOR
+    Separator(Separator),
+
+    /// Raw data of any other event type.
+    Other(&'a [u8]),
+}
+
+impl<'a> EventData<'a> {
+    /// Decode `data` according to `event_type`. Event types without a
+    /// parser are returned as [`EventData::Other`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: the data does not match the layout
+    ///   for the event type.
+    pub fn parse(event_type: EventType, data: &'a [u8]) -> Result<Self> {
+        Ok(match event_type {
+            EventType::EFI_VARIABLE_DRIVER_CONFIG
+            | EventType::EFI_VARIABLE_BOOT
+            | EventType::EFI_VARIABLE_BOOT2
+            | EventType::EFI_VARIABLE_AUTHORITY => Self::Variable(VariableData::parse(data)?),
+            EventType::EFI_BOOT_SERVICES_APPLICATION
+            | EventType::EFI_BOOT_SERVICES_DRIVER
+            | EventType::EFI_RUNTIME_SERVICES_DRIVER => {
+                Self::ImageLoad(ImageLoadEvent::parse(data)?)
+            }
+            EventType::CRTM_VERSION => Self::CrtmVersion(CrtmVersion(data)),
+            EventType::SEPARATOR => Self::Separator(Separator::parse(data)?),
+            _ => Self::Other(data),
+        })
+    }
+}
+
+/// Little-endian reader over event data.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.0.len() {
+            return Err(Status::PROTOCOL_ERROR.into());
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        // OK to unwrap: `bytes` returns exactly `N` bytes.
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    /// Read `len` items of `item_size` bytes each.
+    fn sized_bytes(&mut self, len: u64, item_size: usize) -> Result<&'a [u8]> {
+        let len = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_mul(item_size))
+            .ok_or(Status::PROTOCOL_ERROR)?;
+        self.bytes(len)
+    }
+}
+
+/// Measured UEFI variable (`UEFI_VARIABLE_DATA`).
+#[derive(Debug)]
+pub struct VariableData<'a> {
+    vendor: Guid,
+    name: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> VariableData<'a> {
+    /// Decode a `UEFI_VARIABLE_DATA` structure.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: `data` is truncated.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let mut reader = Reader(data);
+        let vendor = Guid::from_bytes(reader.array()?);
+        let name_len = reader.u64()?;
+        let data_len = reader.u64()?;
+        let name = reader.sized_bytes(name_len, 2)?;
+        let data = reader.sized_bytes(data_len, 1)?;
+        Ok(Self { vendor, name, data })
+    }
+
+    /// Vendor GUID of the variable.
+    #[must_use]
+    pub const fn vendor(&self) -> Guid {
+        self.vendor
+    }
+
+    /// Name of the variable as UCS-2 characters, without a null
+    /// terminator.
+    #[must_use]
+    pub fn name(&self) -> UnalignedSlice<'a, u16> {
+        // Safety: `name` holds `len / 2` little-endian `u16` values.
+        unsafe { UnalignedSlice::new(self.name.as_ptr().cast(), self.name.len() / 2) }
+    }
+
+    /// Whether this is the variable `name` with vendor GUID `vendor`.
+    #[must_use]
+    pub fn is_variable(&self, vendor: &Guid, name: &CStr16) -> bool {
+        self.vendor == *vendor
+            && self
+                .name()
+                .into_iter()
+                .eq(name.to_u16_slice().iter().copied())
+    }
+
+    /// Measured contents of the variable.
+    ///
+    /// For [`EFI_VARIABLE_BOOT`] events, some firmware measures only
+    /// part of the variable or the device path it refers to.
+    ///
+    /// [`EFI_VARIABLE_BOOT`]: EventType::EFI_VARIABLE_BOOT
+    #[must_use]
+    pub const fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+/// Measured image (`UEFI_IMAGE_LOAD_EVENT`).
+#[derive(Debug)]
+pub struct ImageLoadEvent<'a> {
+    location_in_memory: PhysicalAddress,
+    length_in_memory: u64,
+    link_time_address: u64,
+    device_path: Option<&'a DevicePath>,
+}
+
+impl<'a> ImageLoadEvent<'a> {
+    /// Decode a `UEFI_IMAGE_LOAD_EVENT` structure.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: `data` is truncated, or the device
+    ///   path is malformed.
+    pub fn parse(data: &'a [u8]) -> Result<Self> {
+        let mut reader = Reader(data);
+        let location_in_memory = reader.u64()?;
+        let length_in_memory = reader.u64()?;
+        let link_time_address = reader.u64()?;
+        let device_path_len = reader.u64()?;
+        let device_path = reader.sized_bytes(device_path_len, 1)?;
+        let device_path = if device_path.is_empty() {
+            None
+        } else {
+            Some(DevicePath::try_from_bytes(device_path).ok_or(Status::PROTOCOL_ERROR)?)
+        };
+        Ok(Self {
+            location_in_memory,
+            length_in_memory,
+            link_time_address,
+            device_path,
+        })
+    }
+
+    /// Address the image was loaded at.
+    #[must_use]
+    pub const fn location_in_memory(&self) -> PhysicalAddress {
+        self.location_in_memory
+    }
+
+    /// Size of the loaded image in bytes.
+    #[must_use]
+    pub const fn length_in_memory(&self) -> u64 {
+        self.length_in_memory
+    }
+
+    /// Image base address from the image's PE/COFF header.
+    #[must_use]
+    pub const fn link_time_address(&self) -> u64 {
+        self.link_time_address
+    }
+
+    /// Device path the image was loaded from, if the firmware recorded
+    /// one.
+    #[must_use]
+    pub const fn device_path(&self) -> Option<&'a DevicePath> {
+        self.device_path
+    }
+}
+
+/// Version of the static core root of trust for measurement
+/// (`EV_S_CRTM_VERSION` data).
+#[derive(Clone, Copy, Debug)]
+pub struct CrtmVersion<'a>(&'a [u8]);
+
+impl<'a> CrtmVersion<'a> {
+    /// Raw event data.
+    #[must_use]
+    pub const fn data(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// The version as UCS-2 characters, without the null terminator.
+    ///
+    /// Returns `None` if the data is not a null-terminated UCS-2 string;
+    /// some platforms record a GUID instead.
+    #[must_use]
+    pub fn version_string(&self) -> Option<UnalignedSlice<'a, u16>> {
+        let data = self.0.strip_suffix(&[0, 0])?;
+        if data.len() % 2 != 0 {
+            return None;
+        }
+        // Safety: `data` holds `len / 2` little-endian `u16` values.
+        Some(unsafe { UnalignedSlice::new(data.as_ptr().cast(), data.len() / 2) })
+    }
+}
+
+/// Separator between pre-OS and OS-present measurements.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Separator {
+    /// The measurements before the separator completed normally (value
+    /// `0`).
+    Normal,
+    /// An error occurred while making measurements (value `1`).
+    Error,
+    /// Any other value. Some older firmware uses `0xffff_ffff`.
+    Other(u32),
+}
+
+impl Separator {
+    /// Decode the four-byte separator value.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: `data` is not four bytes long.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let value = u32::from_le_bytes(data.try_into().map_err(|_| Status::PROTOCOL_ERROR)?);
+        Ok(match value {
+            0 => Self::Normal,
+            1 => Self::Error,
+            value => Self::Other(value),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::device_path::{DeviceSubType, DeviceType};
+    use crate::{cstr16, guid};
+
+    #[test]
+    fn test_variable_data() {
+        #[rustfmt::skip]
+        let data = [
+            // VariableName: EFI_GLOBAL_VARIABLE
+            0x61, 0xdf, 0xe4, 0x8b, 0xca, 0x93, 0xd2, 0x11,
+            0xaa, 0x0d, 0x00, 0xe0, 0x98, 0x03, 0x2b, 0x8c,
+            // UnicodeNameLength
+            0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // VariableDataLength
+            0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // UnicodeName
+            b'P', 0x00, b'K', 0x00,
+            // VariableData
+            0x01,
+        ];
+        let global = guid!("8be4df61-93ca-11d2-aa0d-00e098032b8c");
+
+        let var = match EventData::parse(EventType::EFI_VARIABLE_DRIVER_CONFIG, &data).unwrap() {
+            EventData::Variable(var) => var,
+            data => panic!("wrong variant: {data:?}"),
+        };
+        assert_eq!(var.vendor(), global);
+        assert!(var
+            .name()
+            .into_iter()
+            .eq([u16::from(b'P'), u16::from(b'K')]));
+        assert!(var.is_variable(&global, cstr16!("PK")));
+        assert!(!var.is_variable(&global, cstr16!("KEK")));
+        assert_eq!(var.data(), [0x01]);
+
+        assert_eq!(
+            VariableData::parse(&data[..data.len() - 1])
+                .unwrap_err()
+                .status(),
+            Status::PROTOCOL_ERROR
+        );
+    }
+
+    #[test]
+    fn test_image_load_event() {
+        #[rustfmt::skip]
+        let data = [
+            // ImageLocationInMemory
+            0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // ImageLengthInMemory
+            0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // ImageLinkTimeAddress
+            0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // LengthOfDevicePath
+            0x0a, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // DevicePath: file path "a", end entire
+            0x04, 0x04, 0x06, 0x00, b'a', 0x00,
+            0x7f, 0xff, 0x04, 0x00,
+        ];
+        let image = match EventData::parse(EventType::EFI_BOOT_SERVICES_APPLICATION, &data).unwrap()
+        {
+            EventData::ImageLoad(image) => image,
+            data => panic!("wrong variant: {data:?}"),
+        };
+        assert_eq!(image.location_in_memory(), 0x1000);
+        assert_eq!(image.length_in_memory(), 0x2000);
+        assert_eq!(image.link_time_address(), 0x40_0000);
+        let node = image.device_path().unwrap().node_iter().next().unwrap();
+        assert_eq!(
+            node.full_type(),
+            (DeviceType::MEDIA, DeviceSubType::MEDIA_FILE_PATH)
+        );
+
+        // Missing end node.
+        let mut bad = data;
+        bad[38] = 0x01;
+        assert_eq!(
+            ImageLoadEvent::parse(&bad).unwrap_err().status(),
+            Status::PROTOCOL_ERROR
+        );
+    }
+
+    #[test]
+    fn test_crtm_version() {
+        let version = CrtmVersion(&[b'1', 0x00, b'2', 0x00, 0x00, 0x00]);
+        assert!(version
+            .version_string()
+            .unwrap()
+            .into_iter()
+            .eq([u16::from(b'1'), u16::from(b'2')]));
+        assert_eq!(
+            CrtmVersion(&[0x00, 0x00])
+                .version_string()
+                .unwrap()
+                .into_iter()
+                .count(),
+            0
+        );
+        assert!(CrtmVersion(&[0x01, 0x00, 0x00]).version_string().is_none());
+    }
+
+    #[test]
+    fn test_separator() {
+        assert!(matches!(
+            EventData::parse(EventType::SEPARATOR, &[0; 4]).unwrap(),
+            EventData::Separator(Separator::Normal)
+        ));
+        assert_eq!(Separator::parse(&[1, 0, 0, 0]).unwrap(), Separator::Error);
+        assert_eq!(
+            Separator::parse(&[0xff; 4]).unwrap(),
+            Separator::Other(0xffff_ffff)
+        );
+        assert_eq!(
+            Separator::parse(&[0; 3]).unwrap_err().status(),
+            Status::PROTOCOL_ERROR
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Framing shared by TPM 1.2 and TPM 2.0 command and response blocks.
+//!
+//! Both versions start each block with the same ten byte header: a
+//! 16-bit tag, the 32-bit size of the whole block, and a 32-bit command
+//! or result code. All values are big endian.
+
+use crate::{Error, Result, Status};
+
+/// Size of the tag, size, and code fields at the start of every command
+/// and response block.
+const HEADER_SIZE: usize = 10;
+
+/// Writer for a command block in a caller-provided buffer.
+#[derive(Debug)]
+pub(super) struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    /// Start a new block with the given header fields. The size field
+    /// is filled in by [`Writer::finish`].
+    pub(super) fn new(buf: &'a mut [u8], tag: u16, code: u32) -> Result<Self> {
+        let mut writer = Self { buf, len: 0 };
+        writer.push_bytes(&tag.to_be_bytes())?;
+        writer.push_bytes(&0u32.to_be_bytes())?;
+        writer.push_bytes(&code.to_be_bytes())?;
+        Ok(writer)
+    }
+
+    /// Append raw bytes.
+    pub(super) fn push_bytes(&mut self, data: &[u8]) -> Result {
+        let end = self
+            .len
+            .checked_add(data.len())
+            .filter(|end| *end <= self.buf.len() && u32::try_from(*end).is_ok())
+            .ok_or_else(|| Error::from(Status::BUFFER_TOO_SMALL))?;
+        self.buf[self.len..end].copy_from_slice(data);
+        self.len = end;
+        Ok(())
+    }
+
+    /// Fill in the size field and get the completed block.
+    pub(super) fn finish(self) -> &'a [u8] {
+        let Self { buf, len } = self;
+        // `push_bytes` ensures the length fits in a `u32`.
+        let size = len as u32;
+        buf[2..6].copy_from_slice(&size.to_be_bytes());
+        &buf[..len]
+    }
+}
+
+/// Split the response block at the start of `buf` into its tag, result
+/// code, and parameter area. Any bytes after the size given in the
+/// header are ignored.
+pub(super) fn parse_response(buf: &[u8]) -> Result<(u16, u32, &[u8])> {
+    let mut reader = ParamReader::new(buf);
+    let tag = reader.read_u16()?;
+    let size = usize::try_from(reader.read_u32()?).unwrap_or(usize::MAX);
+    let code = reader.read_u32()?;
+    if size < HEADER_SIZE || size > buf.len() {
+        return Err(Status::PROTOCOL_ERROR.into());
+    }
+    Ok((tag, code, &buf[HEADER_SIZE..size]))
+}
+
+/// Reader for big-endian values in a response block.
+#[derive(Clone, Debug)]
+pub struct ParamReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ParamReader<'a> {
+    /// Create a reader over `data`.
+    #[must_use]
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    /// Get the bytes that have not been read yet.
+    #[must_use]
+    pub const fn remaining(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Read `len` raw bytes.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: fewer than `len` bytes remain.
+    pub fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.data.len() {
+            return Err(Status::PROTOCOL_ERROR.into());
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    /// Read a byte.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: no bytes remain.
+    pub fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    /// Read a big-endian `u16`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: fewer than two bytes remain.
+    pub fn read_u16(&mut self) -> Result<u16> {
+        let bytes = self.read_bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Read a big-endian `u32`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: fewer than four bytes remain.
+    pub fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.read_bytes(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writer() {
+        let mut buf = [0; 14];
+        let mut writer = Writer::new(&mut buf, 0x8001, 0x17b).unwrap();
+        writer.push_bytes(&[0x00, 0x10]).unwrap();
+        assert_eq!(
+            writer.push_bytes(&[1, 2, 3]).unwrap_err().status(),
+            Status::BUFFER_TOO_SMALL
+        );
+        #[rustfmt::skip]
+        assert_eq!(writer.finish(), [
+            0x80, 0x01,
+            0x00, 0x00, 0x00, 0x0c,
+            0x00, 0x00, 0x01, 0x7b,
+            0x00, 0x10,
+        ]);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        #[rustfmt::skip]
+        let buf = [
+            // tag
+            0x00, 0xc4,
+            // size
+            0x00, 0x00, 0x00, 0x10,
+            // code
+            0x00, 0x00, 0x00, 0x00,
+            // params
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            // trailing bytes
+            0xff, 0xff,
+        ];
+        let (tag, code, params) = parse_response(&buf).unwrap();
+        assert_eq!((tag, code), (0x00c4, 0));
+
+        let mut params = ParamReader::new(params);
+        assert_eq!(params.read_u8().unwrap(), 0x01);
+        assert_eq!(params.read_u16().unwrap(), 0x0203);
+        assert_eq!(params.remaining(), [0x04, 0x05, 0x06]);
+        assert_eq!(
+            params.read_u32().unwrap_err().status(),
+            Status::PROTOCOL_ERROR
+        );
+
+        // Size larger than the buffer.
+        assert_eq!(
+            parse_response(&buf[..12]).unwrap_err().status(),
+            Status::PROTOCOL_ERROR
+        );
+    }
+}
@@ -11,13 +11,19 @@
 //! [TCG]: https://trustedcomputinggroup.org/
 //! [TPM]: https://en.wikipedia.org/wiki/Trusted_Platform_Module
 
+pub mod event_data;
+pub mod replay;
+pub mod summary;
 pub mod v1;
 pub mod v2;
 
 mod enums;
 pub use enums::*;
 
+mod marshal;
+
 use bitflags::bitflags;
+use core::fmt;
 
 /// Platform Configuration Register (PCR) index.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -47,3 +53,77 @@ bitflags! {
         const SM3_256 = 0x0000_0010;
     }
 }
+
+impl HashAlgorithm {
+    /// Names of the algorithms, in the order of their bits.
+    const NAMES: [(Self, &'static str); 5] = [
+        (Self::SHA1, "SHA1"),
+        (Self::SHA256, "SHA256"),
+        (Self::SHA384, "SHA384"),
+        (Self::SHA512, "SHA512"),
+        (Self::SM3_256, "SM3_256"),
+    ];
+
+    /// Name of the algorithm, e.g. `SHA256`, or `None` if `self` is not
+    /// exactly one known algorithm.
+    #[must_use]
+    pub fn name(self) -> Option<&'static str> {
+        Self::NAMES
+            .iter()
+            .find(|(algorithm, _)| *algorithm == self)
+            .map(|(_, name)| *name)
+    }
+}
+
+/// Writes the names of the algorithms separated by ` | `, e.g.
+/// `SHA1 | SHA256`, followed by the unknown bits in hexadecimal, or `none`
+/// if no bit is set.
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bits() == 0 {
+            return f.write_str("none");
+        }
+        let mut separator = "";
+        for (algorithm, name) in Self::NAMES {
+            if self.contains(algorithm) {
+                write!(f, "{separator}{name}")?;
+                separator = " | ";
+            }
+        }
+        let unknown = self.bits() & !Self::all().bits();
+        if unknown != 0 {
+            write!(f, "{separator}{unknown:#x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_event_type_names() {
+        assert_eq!(EventType::CRTM_VERSION.name(), Some("EV_S_CRTM_VERSION"));
+        assert_eq!(
+            EventType::EFI_SPDM_DEVICE_AUTHORITY.to_string(),
+            "EV_EFI_SPDM_DEVICE_AUTHORITY"
+        );
+        assert_eq!(EventType(0x8000_00ff).name(), None);
+        assert_eq!(EventType(0x8000_00ff).to_string(), "0x800000ff");
+    }
+
+    #[test]
+    fn test_hash_algorithm_names() {
+        assert_eq!(HashAlgorithm::SM3_256.name(), Some("SM3_256"));
+        assert_eq!((HashAlgorithm::SHA1 | HashAlgorithm::SHA256).name(), None);
+        assert_eq!(
+            (HashAlgorithm::SHA1 | HashAlgorithm::SHA384).to_string(),
+            "SHA1 | SHA384"
+        );
+        assert_eq!(HashAlgorithm::empty().to_string(), "none");
+        let unknown = unsafe { HashAlgorithm::from_bits_unchecked(0x41) };
+        assert_eq!(unknown.to_string(), "SHA1 | 0x40");
+    }
+}
@@ -0,0 +1,491 @@
+//! Replay of the TPM event log.
+//!
+//! Each event in the log records a digest that was extended into a PCR.
+//! Replaying the log recomputes the value each PCR should have, and
+//! comparing those values to the live PCRs shows whether the log is
+//! complete and accurate.
+//!
+//! This crate does not include hash implementations, so the hash
+//! function used to extend PCRs is provided through the [`PcrHasher`]
+//! trait. On TPM 2.0 devices, [`v2::Tcg`] implements it by having the
+//! TPM compute the hashes.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::proto::tcg::replay::PcrReplay;
+//! use uefi::proto::tcg::{v2, AlgorithmId};
+//!
+//! fn check_log(tcg: &mut v2::Tcg) -> uefi::Result<bool> {
+//!     let replay = PcrReplay::from_tcg_v2(tcg, AlgorithmId::SHA256)?;
+//!     let report = replay.verify_v2(tcg)?;
+//!     Ok(report.all_match())
+//! }
+//! ```
+
+use super::{v1, v2, AlgorithmId, EventType, PcrIndex};
+use crate::{Error, Result, Status};
+use core::fmt::{self, Debug, Formatter};
+
+/// Number of PCRs that are replayed.
+const NUM_PCRS: usize = 24;
+
+/// Size of the largest supported digest (SHA-512).
+const MAX_DIGEST_SIZE: usize = 64;
+
+/// Event data of the `EV_NO_ACTION` event that sets the initial value of
+/// PCR 0, followed by a one-byte locality.
+const STARTUP_LOCALITY_SIGNATURE: &[u8] = b"StartupLocality\0";
+
+/// Hash function used to extend PCRs during replay.
+pub trait PcrHasher {
+    /// Set `pcr` to `hash(pcr || digest)`, where `hash` is the hash
+    /// function identified by `algorithm`. The `pcr` and `digest` slices
+    /// are the size of that hash function's output.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the algorithm is not supported.
+    fn extend(&mut self, algorithm: AlgorithmId, pcr: &mut [u8], digest: &[u8]) -> Result;
+}
+
+impl PcrHasher for v2::Tcg {
+    fn extend(&mut self, algorithm: AlgorithmId, pcr: &mut [u8], digest: &[u8]) -> Result {
+        let mut data = [0; 2 * MAX_DIGEST_SIZE];
+        let data = &mut data[..pcr.len() + digest.len()];
+        data[..pcr.len()].copy_from_slice(pcr);
+        data[pcr.len()..].copy_from_slice(digest);
+
+        let size = v2::command::hash(self, algorithm, data, pcr)
+            .map_err(|err| err.into_err_without_payload())?;
+        if size == pcr.len() {
+            Ok(())
+        } else {
+            Err(Status::PROTOCOL_ERROR.into())
+        }
+    }
+}
+
+/// Get the digest size of a hash algorithm.
+const fn digest_size(algorithm: AlgorithmId) -> Option<usize> {
+    match algorithm {
+        AlgorithmId::SHA1 => Some(20),
+        AlgorithmId::SHA256 | AlgorithmId::SM3_256 => Some(32),
+        AlgorithmId::SHA384 => Some(48),
+        AlgorithmId::SHA512 => Some(64),
+        _ => None,
+    }
+}
+
+/// PCR values for a single bank, computed by replaying an event log.
+///
+/// Only the SRTM PCRs are modeled: all PCRs start out as zero, except
+/// that PCR 0 takes the locality from a `StartupLocality` event.
+#[derive(Clone)]
+pub struct PcrReplay {
+    algorithm: AlgorithmId,
+    digest_size: usize,
+    values: [[u8; MAX_DIGEST_SIZE]; NUM_PCRS],
+    /// Bit `n` is set if PCR `n` was extended by at least one event.
+    extended: u32,
+}
+
+impl PcrReplay {
+    /// Create a replay for the `algorithm` bank with all PCRs at their
+    /// initial value.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: `algorithm` is not a supported hash
+    ///   algorithm.
+    pub fn new(algorithm: AlgorithmId) -> Result<Self> {
+        let digest_size = digest_size(algorithm).ok_or(Error::from(Status::UNSUPPORTED))?;
+        Ok(Self {
+            algorithm,
+            digest_size,
+            values: [[0; MAX_DIGEST_SIZE]; NUM_PCRS],
+            extended: 0,
+        })
+    }
+
+    /// Replay the SHA-1 bank from a [`v1::EventLog`].
+    ///
+    /// # Errors
+    ///
+    /// See [`PcrReplay::replay_event`].
+    pub fn from_v1_log(log: &v1::EventLog, hasher: &mut impl PcrHasher) -> Result<Self> {
+        let mut replay = Self::new(AlgorithmId::SHA1)?;
+        for event in log.iter() {
+            replay.replay_event(
+                hasher,
+                event.pcr_index(),
+                event.event_type(),
+                event.event_data(),
+                &event.digest(),
+            )?;
+        }
+        Ok(replay)
+    }
+
+    /// Replay the `algorithm` bank from a [`v2::EventLog`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: an event has no digest for `algorithm`.
+    ///
+    /// See also [`PcrReplay::new`] and [`PcrReplay::replay_event`].
+    pub fn from_v2_log(
+        log: &v2::EventLog,
+        algorithm: AlgorithmId,
+        hasher: &mut impl PcrHasher,
+    ) -> Result<Self> {
+        let mut replay = Self::new(algorithm)?;
+        for event in log.iter() {
+            let digest = event
+                .digests()
+                .into_iter()
+                .find(|(alg, _)| *alg == algorithm)
+                .map(|(_, digest)| digest)
+                .ok_or(Error::from(Status::NOT_FOUND))?;
+            replay.replay_event(
+                hasher,
+                event.pcr_index(),
+                event.event_type(),
+                event.event_data(),
+                digest,
+            )?;
+        }
+        Ok(replay)
+    }
+
+    /// Replay the `algorithm` bank from the event log of a TPM 2.0
+    /// device, using the TPM to compute the hashes.
+    ///
+    /// # Errors
+    ///
+    /// See [`PcrReplay::from_v2_log`].
+    pub fn from_tcg_v2(tcg: &mut v2::Tcg, algorithm: AlgorithmId) -> Result<Self> {
+        // Safety: the log is in memory owned by the firmware. It stays
+        // valid while boot services are active, which the borrow of
+        // `tcg` guarantees, and hashing data does not modify it.
+        let log = unsafe { tcg.get_event_log_v2()?.detach() };
+        Self::from_v2_log(&log, algorithm, tcg)
+    }
+
+    /// Apply a single event.
+    ///
+    /// `EV_NO_ACTION` events are not extended; a `StartupLocality`
+    /// event sets the initial value of PCR 0.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `pcr_index` is not in `0..24`.
+    /// * [`Status::BAD_BUFFER_SIZE`]: `digest` does not have the size of
+    ///   the bank's hash algorithm.
+    ///
+    /// Errors from the `hasher` are passed through.
+    pub fn replay_event(
+        &mut self,
+        hasher: &mut impl PcrHasher,
+        pcr_index: PcrIndex,
+        event_type: EventType,
+        event_data: &[u8],
+        digest: &[u8],
+    ) -> Result {
+        let index = usize::try_from(pcr_index.0)
+            .ok()
+            .filter(|index| *index < NUM_PCRS)
+            .ok_or(Error::from(Status::INVALID_PARAMETER))?;
+
+        if event_type == EventType::NO_ACTION {
+            if let Some(&[locality]) = event_data.strip_prefix(STARTUP_LOCALITY_SIGNATURE) {
+                if index == 0 && self.extended & 1 == 0 {
+                    self.values[0][self.digest_size - 1] = locality;
+                }
+            }
+            return Ok(());
+        }
+
+        if digest.len() != self.digest_size {
+            return Err(Status::BAD_BUFFER_SIZE.into());
+        }
+        hasher.extend(
+            self.algorithm,
+            &mut self.values[index][..self.digest_size],
+            digest,
+        )?;
+        self.extended |= 1 << index;
+        Ok(())
+    }
+
+    /// Hash algorithm of the replayed bank.
+    #[must_use]
+    pub const fn algorithm(&self) -> AlgorithmId {
+        self.algorithm
+    }
+
+    /// Get the expected value of a PCR, or `None` if no event in the log
+    /// extended it.
+    #[must_use]
+    pub fn value(&self, pcr_index: PcrIndex) -> Option<&[u8]> {
+        let index = usize::try_from(pcr_index.0).ok()?;
+        if index < NUM_PCRS && self.extended & (1 << index) != 0 {
+            Some(&self.values[index][..self.digest_size])
+        } else {
+            None
+        }
+    }
+
+    /// Compare the replayed values with live PCR values.
+    ///
+    /// `read_pcr` is called for each PCR that was extended by the log. It
+    /// writes the live value to the start of its buffer and returns the
+    /// size of the value.
+    ///
+    /// # Errors
+    ///
+    /// Errors from `read_pcr` are passed through.
+    pub fn compare(
+        &self,
+        mut read_pcr: impl FnMut(PcrIndex, &mut [u8]) -> Result<usize>,
+    ) -> Result<ReplayReport> {
+        let mut report = ReplayReport {
+            logged: self.extended,
+            mismatched: 0,
+        };
+        for index in 0..NUM_PCRS as u32 {
+            let pcr_index = PcrIndex(index);
+            if let Some(expected) = self.value(pcr_index) {
+                let mut live = [0; MAX_DIGEST_SIZE];
+                let size = read_pcr(pcr_index, &mut live)?;
+                if live.get(..size) != Some(expected) {
+                    report.mismatched |= 1 << index;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Compare the replayed values with the PCRs of a TPM 1.2 device.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the replayed bank is not SHA-1.
+    ///
+    /// Errors from reading the PCRs are passed through.
+    pub fn verify_v1(&self, tcg: &mut v1::Tcg) -> Result<ReplayReport> {
+        if self.algorithm != AlgorithmId::SHA1 {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        self.compare(|pcr_index, live| read_pcr_v1(tcg, pcr_index, live))
+    }
+
+    /// Compare the replayed values with the PCRs of a TPM 2.0 device.
+    ///
+    /// # Errors
+    ///
+    /// Errors from reading the PCRs are passed through.
+    pub fn verify_v2(&self, tcg: &mut v2::Tcg) -> Result<ReplayReport> {
+        self.compare(|pcr_index, live| {
+            v2::command::pcr_read(tcg, self.algorithm, pcr_index, live)
+                .map_err(|err| err.into_err_without_payload())
+        })
+    }
+}
+
+impl Debug for PcrReplay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        struct Values<'a>(&'a PcrReplay);
+
+        impl<'a> Debug for Values<'a> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.debug_map()
+                    .entries(
+                        (0..NUM_PCRS as u32).filter_map(|i| Some((i, self.0.value(PcrIndex(i))?))),
+                    )
+                    .finish()
+            }
+        }
+
+        f.debug_struct("PcrReplay")
+            .field("algorithm", &self.algorithm)
+            .field("values", &Values(self))
+            .finish()
+    }
+}
+
+/// Read a PCR with `TPM_PcrRead`.
+fn read_pcr_v1(tcg: &mut v1::Tcg, pcr_index: PcrIndex, value: &mut [u8]) -> Result<usize> {
+    let mut cmd_buf = [0; 14];
+    let command = v1::command::CommandBuf::new(
+        &mut cmd_buf,
+        v1::command::Tag::RQU_COMMAND,
+        v1::command::Ordinal::PCR_READ,
+    )?
+    .push_u32(pcr_index.0)?
+    .finish();
+
+    let mut response_buf = [0; 30];
+    let response = tcg.submit_command(command, &mut response_buf)?;
+    let mut params = response
+        .params()
+        .map_err(|err| err.into_err_without_payload())?;
+    let digest = params.read_bytes(20)?;
+    value[..digest.len()].copy_from_slice(digest);
+    Ok(digest.len())
+}
+
+/// Whether a live PCR value matches the replayed log.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PcrMatch {
+    /// No event in the log extended the PCR, so it was not checked.
+    NotLogged,
+    /// The live value matches the replayed value.
+    Match,
+    /// The live value differs from the replayed value.
+    Mismatch,
+}
+
+/// Result of comparing a [`PcrReplay`] with live PCR values.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ReplayReport {
+    /// Bit `n` is set if PCR `n` was checked.
+    logged: u32,
+    /// Bit `n` is set if PCR `n` did not match.
+    mismatched: u32,
+}
+
+impl ReplayReport {
+    /// Get the result for a single PCR.
+    #[must_use]
+    pub fn get(&self, pcr_index: PcrIndex) -> PcrMatch {
+        let bit = 1u32.checked_shl(pcr_index.0).unwrap_or(0);
+        if self.logged & bit == 0 {
+            PcrMatch::NotLogged
+        } else if self.mismatched & bit == 0 {
+            PcrMatch::Match
+        } else {
+            PcrMatch::Mismatch
+        }
+    }
+
+    /// Whether every PCR extended by the log matches its live value.
+    #[must_use]
+    pub const fn all_match(&self) -> bool {
+        self.mismatched == 0
+    }
+
+    /// Iterate over the result for each PCR.
+    pub fn iter(&self) -> impl Iterator<Item = (PcrIndex, PcrMatch)> + '_ {
+        (0..NUM_PCRS as u32).map(|i| (PcrIndex(i), self.get(PcrIndex(i))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Not a real hash: XOR the digest into the PCR, then rotate it so
+    /// that the order of events matters.
+    struct XorHasher;
+
+    impl PcrHasher for XorHasher {
+        fn extend(&mut self, algorithm: AlgorithmId, pcr: &mut [u8], digest: &[u8]) -> Result {
+            assert_eq!(algorithm, AlgorithmId::SHA1);
+            for (p, d) in pcr.iter_mut().zip(digest) {
+                *p ^= d;
+            }
+            pcr.rotate_left(1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut replay = PcrReplay::new(AlgorithmId::SHA1).unwrap();
+        let mut locality = [0; 17];
+        locality[..16].copy_from_slice(STARTUP_LOCALITY_SIGNATURE);
+        locality[16] = 3;
+        replay
+            .replay_event(
+                &mut XorHasher,
+                PcrIndex(0),
+                EventType::NO_ACTION,
+                &locality,
+                &[0; 20],
+            )
+            .unwrap();
+        assert_eq!(replay.value(PcrIndex(0)), None);
+
+        let mut digest = [0; 20];
+        digest[0] = 1;
+        for pcr_index in [PcrIndex(0), PcrIndex(7), PcrIndex(7)] {
+            replay
+                .replay_event(
+                    &mut XorHasher,
+                    pcr_index,
+                    EventType::SEPARATOR,
+                    &[],
+                    &digest,
+                )
+                .unwrap();
+        }
+
+        let mut expected = [0; 20];
+        expected[18] = 3;
+        expected[19] = 1;
+        assert_eq!(replay.value(PcrIndex(0)), Some(expected.as_slice()));
+        let mut expected = [0; 20];
+        expected[18] = 1;
+        expected[19] = 1;
+        assert_eq!(replay.value(PcrIndex(7)), Some(expected.as_slice()));
+        assert_eq!(replay.value(PcrIndex(1)), None);
+
+        assert_eq!(
+            replay
+                .replay_event(&mut XorHasher, PcrIndex(24), EventType::IPL, &[], &digest)
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+        assert_eq!(
+            replay
+                .replay_event(&mut XorHasher, PcrIndex(1), EventType::IPL, &[], &[0; 32])
+                .unwrap_err()
+                .status(),
+            Status::BAD_BUFFER_SIZE
+        );
+
+        // PCR 7 matches, PCR 0 does not.
+        let report = replay
+            .compare(|pcr_index, live| {
+                let value = replay.value(pcr_index).unwrap();
+                live[..value.len()].copy_from_slice(value);
+                if pcr_index == PcrIndex(0) {
+                    live[0] ^= 0xff;
+                }
+                Ok(value.len())
+            })
+            .unwrap();
+        assert!(!report.all_match());
+        assert_eq!(report.get(PcrIndex(0)), PcrMatch::Mismatch);
+        assert_eq!(report.get(PcrIndex(1)), PcrMatch::NotLogged);
+        assert_eq!(report.get(PcrIndex(7)), PcrMatch::Match);
+        assert_eq!(report.get(PcrIndex(100)), PcrMatch::NotLogged);
+        assert_eq!(
+            report
+                .iter()
+                .filter(|(_, m)| *m != PcrMatch::NotLogged)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_unsupported_algorithm() {
+        assert_eq!(
+            PcrReplay::new(AlgorithmId::RSA).unwrap_err().status(),
+            Status::UNSUPPORTED
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! Summary of the measurements recorded in the TPM event log.
+//!
+//! [`LogSummary`] groups the events of an event log by PCR, and decodes a
+//! short description of each event, like the output of `tpm2_eventlog`.
+//! The summary is written as [`report`] records, so it follows the
+//! crate-wide report format, and can be printed to the console or written
+//! to a file.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::proto::tcg::summary::LogSummary;
+//! use uefi::proto::tcg::{v2, AlgorithmId};
+//!
+//! fn print_log(tcg: &mut v2::Tcg) -> uefi::Result {
+//!     let log = tcg.get_event_log_v2()?;
+//!     log::info!("{}", LogSummary::from_v2_log(&log, AlgorithmId::SHA256));
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`report`]: crate::report
+
+use super::event_data::{EventData, Separator};
+use super::{v1, v2, AlgorithmId, EventType, PcrIndex};
+use crate::data_types::UnalignedSlice;
+use crate::proto::device_path::{DevicePath, DevicePathNodeEnum};
+use crate::report::{self, Format, Record};
+use core::fmt::{self, Display, Formatter, Write};
+
+/// Number of PCRs that are summarized.
+const NUM_PCRS: u32 = 24;
+
+/// Event data of the `EV_NO_ACTION` event at the start of a crypto-agile
+/// log.
+const SPEC_ID_SIGNATURE: &[u8] = b"Spec ID Event03\0";
+
+/// Event data of the `EV_NO_ACTION` event that sets the initial value of
+/// PCR 0, followed by a one-byte locality.
+const STARTUP_LOCALITY_SIGNATURE: &[u8] = b"StartupLocality\0";
+
+/// Maximum number of bytes of raw event data included in descriptions.
+const MAX_RAW_DATA: usize = 32;
+
+/// Summary of an event log, grouped by PCR.
+///
+/// The summary is rendered with [`write`], or with its [`Display`]
+/// implementation, which uses the crate-wide [`report::format`]. It starts
+/// with an `event_log` record, followed for each extended PCR by a `pcr`
+/// record and an `event` record per event.
+///
+/// [`write`]: Self::write
+pub struct LogSummary<'a> {
+    log: Log<'a>,
+    algorithm: AlgorithmId,
+}
+
+enum Log<'a> {
+    V1(&'a v1::EventLog<'a>),
+    V2(&'a v2::EventLog<'a>),
+}
+
+/// An event of either log format.
+struct Event<'e> {
+    pcr_index: PcrIndex,
+    event_type: EventType,
+    data: &'e [u8],
+    digest: Option<&'e [u8]>,
+}
+
+impl<'a> LogSummary<'a> {
+    /// Summarize a [`v1::EventLog`], whose digests are SHA-1.
+    #[must_use]
+    pub const fn from_v1_log(log: &'a v1::EventLog<'a>) -> Self {
+        Self {
+            log: Log::V1(log),
+            algorithm: AlgorithmId::SHA1,
+        }
+    }
+
+    /// Summarize a [`v2::EventLog`], showing the digests of the
+    /// `algorithm` bank.
+    #[must_use]
+    pub const fn from_v2_log(log: &'a v2::EventLog<'a>, algorithm: AlgorithmId) -> Self {
+        Self {
+            log: Log::V2(log),
+            algorithm,
+        }
+    }
+
+    /// Get the number of events recorded for a PCR.
+    #[must_use]
+    pub fn event_count(&self, pcr_index: PcrIndex) -> usize {
+        let mut count = 0;
+        let _ = self.for_each_event(|event| {
+            if event.pcr_index == pcr_index {
+                count += 1;
+            }
+            Ok(())
+        });
+        count
+    }
+
+    /// Write the summary to `writer` in the crate-wide
+    /// [`report::format`].
+    pub fn write<W: Write>(&self, writer: &mut W) -> fmt::Result {
+        self.write_with_format(writer, report::format())
+    }
+
+    /// Write the summary to `writer` in the given `format`.
+    pub fn write_with_format<W: Write>(&self, writer: &mut W, format: Format) -> fmt::Result {
+        let mut total = 0;
+        self.for_each_event(|_| {
+            total += 1;
+            Ok(())
+        })?;
+        Record::with_format(writer, format, "event_log")?
+            .field("algorithm", format_args!("{:?}", self.algorithm))?
+            .field("events", total)?
+            .field("truncated", self.is_truncated())?
+            .finish()?;
+
+        for index in 0..NUM_PCRS {
+            let pcr_index = PcrIndex(index);
+            let count = self.event_count(pcr_index);
+            if count == 0 {
+                continue;
+            }
+            Record::with_format(writer, format, "pcr")?
+                .field("index", index)?
+                .field("purpose", pcr_purpose(pcr_index))?
+                .field("events", count)?
+                .finish()?;
+            self.for_each_event(|event| {
+                if event.pcr_index != pcr_index {
+                    return Ok(());
+                }
+                let record = Record::with_format(writer, format, "event")?
+                    .field("pcr", index)?
+                    .field("type", format_args!("{:?}", event.event_type))?;
+                let record = match event.digest {
+                    Some(digest) => record.field("digest", Hex(digest))?,
+                    None => record.field("digest", "none")?,
+                };
+                record.field("description", Description(event))?.finish()
+            })?;
+        }
+        Ok(())
+    }
+
+    fn is_truncated(&self) -> bool {
+        match self.log {
+            Log::V1(log) => log.is_truncated(),
+            Log::V2(log) => log.is_truncated(),
+        }
+    }
+
+    fn for_each_event(&self, mut f: impl FnMut(&Event) -> fmt::Result) -> fmt::Result {
+        match self.log {
+            Log::V1(log) => {
+                for event in log.iter() {
+                    let digest = event.digest();
+                    f(&Event {
+                        pcr_index: event.pcr_index(),
+                        event_type: event.event_type(),
+                        data: event.event_data(),
+                        digest: Some(&digest),
+                    })?;
+                }
+            }
+            Log::V2(log) => {
+                for event in log.iter() {
+                    f(&Event {
+                        pcr_index: event.pcr_index(),
+                        event_type: event.event_type(),
+                        data: event.event_data(),
+                        digest: event
+                            .digests()
+                            .into_iter()
+                            .find(|(algorithm, _)| *algorithm == self.algorithm)
+                            .map(|(_, digest)| digest),
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for LogSummary<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write(f)
+    }
+}
+
+/// Get what a PCR measures, according to the TCG PC Client Platform
+/// Firmware Profile Specification.
+const fn pcr_purpose(pcr_index: PcrIndex) -> &'static str {
+    match pcr_index.0 {
+        0 => "firmware code",
+        1 => "firmware configuration",
+        2 => "option ROM code",
+        3 => "option ROM configuration",
+        4 => "boot loader code",
+        5 => "boot loader configuration",
+        6 => "platform manufacturer",
+        7 => "Secure Boot policy",
+        8..=15 => "operating system",
+        16 => "debug",
+        17..=22 => "dynamic root of trust",
+        _ => "application",
+    }
+}
+
+/// Bytes formatted as lowercase hexadecimal.
+struct Hex<'a>(&'a [u8]);
+
+impl Display for Hex<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+/// UCS-2 characters, up to the first null.
+struct Ucs2<'a>(UnalignedSlice<'a, u16>);
+
+impl Display for Ucs2<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let chars = self.0.clone().into_iter().take_while(|c| *c != 0);
+        char::decode_utf16(chars)
+            .try_for_each(|c| f.write_char(c.unwrap_or(char::REPLACEMENT_CHARACTER)))
+    }
+}
+
+/// ASCII text, up to the first null. Other characters are escaped.
+struct Ascii<'a>(&'a [u8]);
+
+impl Display for Ascii<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0
+            .iter()
+            .take_while(|b| **b != 0)
+            .try_for_each(|b| write!(f, "{}", b.escape_ascii()))
+    }
+}
+
+/// The file path of an image, from the file path nodes of its device
+/// path.
+struct FilePath<'a>(&'a DevicePath);
+
+impl Display for FilePath<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for node in self.0.node_iter() {
+            if let Ok(DevicePathNodeEnum::MediaFilePath(node)) = node.as_enum() {
+                Ucs2(node.path_name()).fmt(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Short description of an event, decoded from its data.
+struct Description<'a, 'e>(&'a Event<'e>);
+
+impl Display for Description<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let Event {
+            event_type, data, ..
+        } = *self.0;
+
+        match event_type {
+            EventType::NO_ACTION => {
+                if data.starts_with(SPEC_ID_SIGNATURE) {
+                    return f.write_str("spec ID event");
+                }
+                if let Some(&[locality]) = data.strip_prefix(STARTUP_LOCALITY_SIGNATURE) {
+                    return write!(f, "startup locality {locality}");
+                }
+            }
+            EventType::ACTION | EventType::EFI_ACTION | EventType::POST_CODE
+                if data.iter().all(|b| b.is_ascii_graphic() || *b == b' ') =>
+            {
+                return write!(f, "\"{}\"", Ascii(data));
+            }
+            EventType::EFI_PLATFORM_FIRMWARE_BLOB if data.len() == 16 => {
+                let base = u64::from_le_bytes(data[..8].try_into().unwrap());
+                let len = u64::from_le_bytes(data[8..].try_into().unwrap());
+                return write!(f, "firmware blob at {base:#x}, {len} bytes");
+            }
+            EventType::EFI_PLATFORM_FIRMWARE_BLOB2 => {
+                let description = data
+                    .split_first()
+                    .and_then(|(len, rest)| rest.get(..usize::from(*len)));
+                if let Some(description) = description {
+                    return write!(f, "firmware blob \"{}\"", Ascii(description));
+                }
+            }
+            _ => {}
+        }
+
+        match EventData::parse(event_type, data) {
+            Ok(EventData::Variable(variable)) => write!(
+                f,
+                "variable {} ({}), {} bytes",
+                Ucs2(variable.name()),
+                variable.vendor(),
+                variable.data().len()
+            ),
+            Ok(EventData::ImageLoad(image)) => {
+                write!(
+                    f,
+                    "image at {:#x}, {} bytes",
+                    image.location_in_memory(),
+                    image.length_in_memory()
+                )?;
+                match image.device_path() {
+                    Some(path) => write!(f, ", path \"{}\"", FilePath(path)),
+                    None => Ok(()),
+                }
+            }
+            Ok(EventData::CrtmVersion(version)) => match version.version_string() {
+                Some(version) => write!(f, "CRTM version \"{}\"", Ucs2(version)),
+                None => write!(f, "CRTM version {}", Hex(version.data())),
+            },
+            Ok(EventData::Separator(separator)) => match separator {
+                Separator::Normal => f.write_str("separator"),
+                Separator::Error => f.write_str("separator (error)"),
+                Separator::Other(value) => write!(f, "separator ({value:#x})"),
+            },
+            Ok(EventData::Other(data)) if data.len() <= MAX_RAW_DATA => {
+                write!(f, "{} bytes: {}", data.len(), Hex(data))
+            }
+            Ok(EventData::Other(data)) => write!(f, "{} bytes", data.len()),
+            Err(_) => write!(f, "malformed data, {} bytes", data.len()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    fn event(pcr: u32, event_type: EventType, digest: u8, data: &[u8]) -> Vec<u8> {
+        let mut event = Vec::new();
+        event.extend_from_slice(&pcr.to_le_bytes());
+        event.extend_from_slice(&event_type.0.to_le_bytes());
+        event.extend_from_slice(&[digest; 20]);
+        event.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        event.extend_from_slice(data);
+        event
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut variable = Vec::new();
+        variable.extend_from_slice(&[0x11; 16]);
+        variable.extend_from_slice(&2u64.to_le_bytes());
+        variable.extend_from_slice(&1u64.to_le_bytes());
+        variable.extend_from_slice(&[b'P', 0, b'K', 0, 1]);
+
+        let events = [
+            event(0, EventType::CRTM_VERSION, 1, &[b'1', 0, 0, 0]),
+            event(7, EventType::EFI_VARIABLE_DRIVER_CONFIG, 2, &variable),
+            event(0, EventType::SEPARATOR, 3, &[0; 4]),
+            event(4, EventType::EFI_ACTION, 4, b"Calling EFI Application"),
+            event(7, EventType::SEPARATOR, 5, &[1, 0, 0, 0]),
+        ];
+        let bytes = events.concat();
+        let last = bytes.len() - events.last().unwrap().len();
+        let log = unsafe { v1::EventLog::new(bytes.as_ptr(), bytes.as_ptr().add(last), false) };
+        let summary = LogSummary::from_v1_log(&log);
+
+        assert_eq!(summary.event_count(PcrIndex(0)), 2);
+        assert_eq!(summary.event_count(PcrIndex(4)), 1);
+        assert_eq!(summary.event_count(PcrIndex(5)), 0);
+
+        let mut output = String::new();
+        summary
+            .write_with_format(&mut output, Format::Human)
+            .unwrap();
+        let lines: Vec<String> = output.lines().map(String::from).collect();
+        let digest = |b: u8| Hex(&[b; 20]).to_string();
+        assert_eq!(
+            lines,
+            [
+                "event_log: algorithm=SHA1, events=5, truncated=false".to_string(),
+                "pcr: index=0, purpose=firmware code, events=2".to_string(),
+                format!(
+                    "event: pcr=0, type=CRTM_VERSION, digest={}, description=CRTM version \"1\"",
+                    digest(1)
+                ),
+                format!(
+                    "event: pcr=0, type=SEPARATOR, digest={}, description=separator",
+                    digest(3)
+                ),
+                "pcr: index=4, purpose=boot loader code, events=1".to_string(),
+                format!(
+                    "event: pcr=4, type=EFI_ACTION, digest={}, description=\"Calling EFI Application\"",
+                    digest(4)
+                ),
+                "pcr: index=7, purpose=Secure Boot policy, events=2".to_string(),
+                format!(
+                    "event: pcr=7, type=EFI_VARIABLE_DRIVER_CONFIG, digest={}, description=variable PK (11111111-1111-1111-1111-111111111111), 1 bytes",
+                    digest(2)
+                ),
+                format!(
+                    "event: pcr=7, type=SEPARATOR, digest={}, description=separator (error)",
+                    digest(5)
+                ),
+            ]
+        );
+    }
+}
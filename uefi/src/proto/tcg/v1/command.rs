@@ -0,0 +1,297 @@
+//! TPM 1.2 command and response blocks.
+//!
+//! [`Tcg::pass_through_to_tpm`] sends a raw command block to the TPM
+//! and returns a raw response block. This module takes care of the
+//! framing of those blocks: use [`CommandBuf`] to build a command and
+//! [`Response`] to check the result code and read the returned
+//! parameters. [`Tcg::submit_command`] combines all three steps.
+//!
+//! The parameters of each command are described in Part 3, Commands, of
+//! the [TPM 1.2 Main Specification][spec]. All TPM structures are big
+//! endian; the `push_*` and `read_*` methods take care of the byte order.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::proto::tcg::v1::command::{CommandBuf, Ordinal, Tag};
+//! use uefi::proto::tcg::v1::Tcg;
+//!
+//! /// Read the TPM's manufacturer ID.
+//! fn manufacturer(tcg: &mut Tcg) -> uefi::Result<u32> {
+//!     // TPM_CAP_PROPERTY, TPM_CAP_PROP_MANUFACTURER
+//!     let mut cmd_buf = [0; 64];
+//!     let cmd = CommandBuf::new(&mut cmd_buf, Tag::RQU_COMMAND, Ordinal::GET_CAPABILITY)?
+//!         .push_u32(0x5)?
+//!         .push_u32(4)?
+//!         .push_u32(0x103)?
+//!         .finish();
+//!
+//!     let mut rsp_buf = [0; 64];
+//!     let rsp = tcg.submit_command(cmd, &mut rsp_buf)?;
+//!     let mut params = rsp.params().map_err(|err| err.into_err_without_payload())?;
+//!     let _size = params.read_u32()?;
+//!     params.read_u32()
+//! }
+//! ```
+//!
+//! [`Tcg::pass_through_to_tpm`]: super::Tcg::pass_through_to_tpm
+//! [`Tcg::submit_command`]: super::Tcg::submit_command
+//! [spec]: https://trustedcomputinggroup.org/resource/tpm-main-specification/
+
+use crate::proto::tcg::marshal::{self, Writer};
+use crate::{Error, Result, Status};
+
+pub use crate::proto::tcg::marshal::ParamReader;
+
+newtype_enum! {
+    /// Structure tag at the start of a command or response block
+    /// (`TPM_TAG`).
+    pub enum Tag: u16 => {
+        /// Command without authorization.
+        RQU_COMMAND = 0x00c1,
+        /// Command with one authorization session.
+        RQU_AUTH1_COMMAND = 0x00c2,
+        /// Command with two authorization sessions.
+        RQU_AUTH2_COMMAND = 0x00c3,
+        /// Response without authorization.
+        RSP_COMMAND = 0x00c4,
+        /// Response with one authorization session.
+        RSP_AUTH1_COMMAND = 0x00c5,
+        /// Response with two authorization sessions.
+        RSP_AUTH2_COMMAND = 0x00c6,
+    }
+}
+
+newtype_enum! {
+    /// Command ordinal (`TPM_COMMAND_CODE`).
+    ///
+    /// Only a few commonly-used ordinals are listed; any other value
+    /// from the spec can be used as well.
+    pub enum Ordinal: u32 => {
+        /// `TPM_ORD_Extend`
+        EXTEND = 0x0000_0014,
+        /// `TPM_ORD_PcrRead`
+        PCR_READ = 0x0000_0015,
+        /// `TPM_ORD_GetRandom`
+        GET_RANDOM = 0x0000_0046,
+        /// `TPM_ORD_GetCapability`
+        GET_CAPABILITY = 0x0000_0065,
+        /// `TPM_ORD_ReadPubek`
+        READ_PUBEK = 0x0000_007c,
+    }
+}
+
+newtype_enum! {
+    /// Result code in a response block (`TPM_RESULT`).
+    ///
+    /// Only a subset of the result codes is listed.
+    pub enum ReturnCode: u32 => {
+        /// The command succeeded.
+        SUCCESS = 0,
+        /// Authentication failed.
+        AUTHFAIL = 1,
+        /// The index to a PCR, DIR or other register is incorrect.
+        BADINDEX = 2,
+        /// One or more parameters is bad.
+        BAD_PARAMETER = 3,
+        /// The TPM is deactivated.
+        DEACTIVATED = 6,
+        /// The TPM is disabled.
+        DISABLED = 7,
+        /// The target command has been disabled.
+        DISABLED_CMD = 8,
+        /// The operation failed.
+        FAIL = 9,
+        /// The ordinal was unknown or inconsistent.
+        BAD_ORDINAL = 10,
+        /// The TPM does not have the space to perform the operation.
+        NOSPACE = 17,
+        /// The parameter size was incorrect.
+        BAD_PARAM_SIZE = 25,
+        /// The TPM is busy; the command should be retried.
+        RETRY = 0x800,
+    }
+}
+
+/// Builder for a command block.
+///
+/// The header is written by [`CommandBuf::new`], parameters are added
+/// with the `push_*` methods, and [`CommandBuf::finish`] fills in the
+/// total size and returns the completed block.
+#[derive(Debug)]
+pub struct CommandBuf<'a>(Writer<'a>);
+
+impl<'a> CommandBuf<'a> {
+    /// Start a new command in `buf`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buf` cannot hold the header.
+    pub fn new(buf: &'a mut [u8], tag: Tag, ordinal: Ordinal) -> Result<Self> {
+        Writer::new(buf, tag.0, ordinal.0).map(Self)
+    }
+
+    /// Append a byte.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is full.
+    pub fn push_u8(self, value: u8) -> Result<Self> {
+        self.push_bytes(&[value])
+    }
+
+    /// Append a big-endian `u16`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is full.
+    pub fn push_u16(self, value: u16) -> Result<Self> {
+        self.push_bytes(&value.to_be_bytes())
+    }
+
+    /// Append a big-endian `u32`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is full.
+    pub fn push_u32(self, value: u32) -> Result<Self> {
+        self.push_bytes(&value.to_be_bytes())
+    }
+
+    /// Append raw bytes.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is full.
+    pub fn push_bytes(mut self, data: &[u8]) -> Result<Self> {
+        self.0.push_bytes(data)?;
+        Ok(self)
+    }
+
+    /// Fill in the size field and get the completed command block.
+    #[must_use]
+    pub fn finish(self) -> &'a [u8] {
+        self.0.finish()
+    }
+}
+
+/// A parsed response block.
+#[derive(Clone, Copy, Debug)]
+pub struct Response<'a> {
+    tag: Tag,
+    return_code: ReturnCode,
+    params: &'a [u8],
+}
+
+impl<'a> Response<'a> {
+    /// Parse the response block at the start of `buf`. Any bytes after
+    /// the size given in the response header are ignored.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: the header is truncated or its size
+    ///   field is invalid.
+    pub fn parse(buf: &'a [u8]) -> Result<Self> {
+        let (tag, return_code, params) = marshal::parse_response(buf)?;
+        Ok(Self {
+            tag: Tag(tag),
+            return_code: ReturnCode(return_code),
+            params,
+        })
+    }
+
+    /// Structure tag of the response.
+    #[must_use]
+    pub const fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Result code of the command.
+    #[must_use]
+    pub const fn return_code(&self) -> ReturnCode {
+        self.return_code
+    }
+
+    /// Get a reader for the returned parameters.
+    ///
+    /// # Errors
+    ///
+    /// If the result code is not [`ReturnCode::SUCCESS`], returns a
+    /// [`Status::DEVICE_ERROR`] error with the result code as the
+    /// error data.
+    pub fn params(&self) -> Result<ParamReader<'a>, ReturnCode> {
+        if self.return_code == ReturnCode::SUCCESS {
+            Ok(ParamReader::new(self.params))
+        } else {
+            Err(Error::new(Status::DEVICE_ERROR, self.return_code))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_buf() {
+        let mut buf = [0; 16];
+        let cmd = CommandBuf::new(&mut buf, Tag::RQU_COMMAND, Ordinal::PCR_READ)
+            .unwrap()
+            .push_u32(8)
+            .unwrap()
+            .finish();
+        #[rustfmt::skip]
+        assert_eq!(cmd, [
+            // tag
+            0x00, 0xc1,
+            // paramSize
+            0x00, 0x00, 0x00, 0x0e,
+            // ordinal
+            0x00, 0x00, 0x00, 0x15,
+            // pcrIndex
+            0x00, 0x00, 0x00, 0x08,
+        ]);
+
+        let mut buf = [0; 12];
+        let cmd = CommandBuf::new(&mut buf, Tag::RQU_COMMAND, Ordinal::PCR_READ).unwrap();
+        assert_eq!(
+            cmd.push_u32(8).unwrap_err().status(),
+            Status::BUFFER_TOO_SMALL
+        );
+    }
+
+    #[test]
+    fn test_response() {
+        #[rustfmt::skip]
+        let buf = [
+            // tag
+            0x00, 0xc4,
+            // paramSize
+            0x00, 0x00, 0x00, 0x10,
+            // returnCode
+            0x00, 0x00, 0x00, 0x00,
+            // params
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06,
+            // trailing bytes
+            0xff, 0xff,
+        ];
+        let rsp = Response::parse(&buf).unwrap();
+        assert_eq!(rsp.tag(), Tag::RSP_COMMAND);
+        assert_eq!(rsp.return_code(), ReturnCode::SUCCESS);
+        assert_eq!(
+            rsp.params().unwrap().remaining(),
+            [0x01, 0x02, 0x03, 0x04, 0x05, 0x06]
+        );
+
+        // Error result code.
+        #[rustfmt::skip]
+        let buf = [
+            0x00, 0xc4,
+            0x00, 0x00, 0x00, 0x0a,
+            0x00, 0x00, 0x00, 0x0a,
+        ];
+        let err = Response::parse(&buf).unwrap().params().unwrap_err();
+        assert_eq!(err.status(), Status::DEVICE_ERROR);
+        assert_eq!(*err.data(), ReturnCode::BAD_ORDINAL);
+    }
+}
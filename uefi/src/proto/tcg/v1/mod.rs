@@ -8,8 +8,13 @@
 //! [TCG]: https://trustedcomputinggroup.org/
 //! [TPM]: https://en.wikipedia.org/wiki/Trusted_Platform_Module
 
+pub mod command;
+
+use super::event_data::EventData;
 use super::{AlgorithmId, EventType, HashAlgorithm, PcrIndex};
 use crate::data_types::PhysicalAddress;
+#[cfg(feature = "alloc")]
+use crate::mem::try_vec;
 use crate::polyfill::maybe_uninit_slice_as_mut_ptr;
 use crate::proto::unsafe_protocol;
 use crate::util::{ptr_write_unaligned_and_add, usize_from_u32};
@@ -17,9 +22,12 @@ use crate::{Error, Result, Status};
 use core::fmt::{self, Debug, Formatter};
 use core::marker::{PhantomData, PhantomPinned};
 use core::mem::{self, MaybeUninit};
-use core::ptr;
+use core::{ptr, slice};
 use ptr_meta::Pointee;
 
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
 /// 20-byte SHA-1 digest.
 pub type Sha1Digest = [u8; 20];
 
@@ -142,13 +150,7 @@ impl PcrEvent {
         let event_data_size =
             u32::try_from(event_data.len()).map_err(|_| Error::from(Status::INVALID_PARAMETER))?;
 
-        let required_size = mem::size_of::<PcrIndex>()
-            + mem::size_of::<EventType>()
-            + mem::size_of::<Sha1Digest>()
-            + mem::size_of::<u32>()
-            + event_data.len();
-
-        if buffer.len() < required_size {
+        if buffer.len() < Self::size_for(event_data) {
             return Err(Status::BUFFER_TOO_SMALL.into());
         }
 
@@ -167,6 +169,50 @@ impl PcrEvent {
         }
     }
 
+    /// Create a new `PcrEvent` in a [`Box`], e.g. to pass it to
+    /// [`Tcg::hash_log_extend_event`], or to build a synthetic event log
+    /// from the [`as_bytes`] of several events.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if the `event_data` size is too
+    /// large.
+    ///
+    /// [`as_bytes`]: Self::as_bytes
+    #[cfg(feature = "alloc")]
+    pub fn new_in_box(
+        pcr_index: PcrIndex,
+        event_type: EventType,
+        digest: Sha1Digest,
+        event_data: &[u8],
+    ) -> Result<Box<Self>> {
+        let mut buffer = try_vec(MaybeUninit::uninit(), Self::size_for(event_data))?;
+        Self::new_in_buffer(&mut buffer, pcr_index, event_type, digest, event_data)?;
+
+        // The event is packed, so it has the same layout as the buffer.
+        let ptr: *mut PcrEvent = ptr_meta::from_raw_parts_mut(
+            Box::into_raw(buffer.into_boxed_slice()).cast(),
+            event_data.len(),
+        );
+        Ok(unsafe { Box::from_raw(ptr) })
+    }
+
+    /// Size of an event with `event_data`, in bytes.
+    fn size_for(event_data: &[u8]) -> usize {
+        mem::size_of::<PcrIndex>()
+            + mem::size_of::<EventType>()
+            + mem::size_of::<Sha1Digest>()
+            + mem::size_of::<u32>()
+            + event_data.len()
+    }
+
+    /// Raw bytes of the event, as they appear in the [`EventLog`].
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        let ptr: *const PcrEvent = self;
+        unsafe { slice::from_raw_parts(ptr.cast::<u8>(), mem::size_of_val(self)) }
+    }
+
     /// PCR index for the event.
     #[must_use]
     pub fn pcr_index(&self) -> PcrIndex {
@@ -193,6 +239,17 @@ impl PcrEvent {
         &self.event_data
     }
 
+    /// Event data decoded according to the [`event_type`].
+    ///
+    /// # Errors
+    ///
+    /// See [`EventData::parse`].
+    ///
+    /// [`event_type`]: Self::event_type
+    pub fn parse_event_data(&self) -> Result<EventData<'_>> {
+        EventData::parse(self.event_type(), self.event_data())
+    }
+
     /// SHA-1 digest of the data hashed for this event.
     #[must_use]
     pub fn digest(&self) -> Sha1Digest {
@@ -277,6 +334,30 @@ impl<'a> EventLog<'a> {
         }
     }
 
+    /// Iterator of events in the log that doesn't read past the first
+    /// `max_size` bytes of the log.
+    ///
+    /// [`iter`] trusts the range of the log given by the firmware. This
+    /// iterator instead checks that each event, including its event data,
+    /// is inside the bounds, and that the last entry is reached without
+    /// going past it. If the log turns out to be corrupted, an error is
+    /// returned and the iteration stops.
+    ///
+    /// Neither TCG protocol reports the size of the log area, but it can
+    /// be found in the ACPI TCPA or TPM2 tables, or the size of the
+    /// buffer can be used for a log that has been copied.
+    ///
+    /// [`iter`]: Self::iter
+    #[must_use]
+    pub fn iter_bounded(&self, max_size: usize) -> BoundedEventLogIter {
+        BoundedEventLogIter {
+            log: self,
+            offset: 0,
+            max_size,
+            done: false,
+        }
+    }
+
     /// If true, the event log is missing one or more entries because
     /// additional events would have exceeded the space allocated for
     /// the log.
@@ -324,6 +405,77 @@ impl<'a> Iterator for EventLogIter<'a> {
     }
 }
 
+/// Bounds-checked iterator for events in [`EventLog`], see
+/// [`EventLog::iter_bounded`].
+///
+/// Items are [`Status::VOLUME_CORRUPTED`] errors if an event is not inside
+/// the bounds of the log, or if the last entry is skipped over. The
+/// iterator returns `None` after an error.
+pub struct BoundedEventLogIter<'a> {
+    log: &'a EventLog<'a>,
+    offset: usize,
+    max_size: usize,
+    done: bool,
+}
+
+impl<'a> BoundedEventLogIter<'a> {
+    /// Size of a `PcrEvent` without the event data.
+    const HEADER_SIZE: usize = 32;
+
+    /// Checks that the event at `self.offset` is inside the bounds, and
+    /// returns its total size.
+    fn checked_event_size(&self) -> Option<usize> {
+        let last_offset = (self.log.last_entry as usize).checked_sub(self.log.location as usize)?;
+        if self.offset > last_offset {
+            return None;
+        }
+        let remaining = self.max_size.checked_sub(self.offset)?;
+        if remaining < Self::HEADER_SIZE {
+            return None;
+        }
+        // Safety: the header is inside the bounds.
+        let event_data_size = unsafe {
+            self.log
+                .location
+                .add(self.offset + 28)
+                .cast::<u32>()
+                .read_unaligned()
+        };
+        let size = Self::HEADER_SIZE.checked_add(usize_from_u32(event_data_size))?;
+        (size <= remaining).then_some(size)
+    }
+}
+
+impl<'a> Iterator for BoundedEventLogIter<'a> {
+    type Item = Result<&'a PcrEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.log.location.is_null() || self.log.last_entry.is_null() {
+            return None;
+        }
+
+        let size = match self.checked_event_size() {
+            Some(size) => size,
+            None => {
+                self.done = true;
+                return Some(Err(Status::VOLUME_CORRUPTED.into()));
+            }
+        };
+
+        // Safety: the whole event is inside the bounds.
+        let location = unsafe { self.log.location.add(self.offset) };
+        let event = unsafe { PcrEvent::from_ptr(location) };
+
+        if location == self.log.last_entry {
+            self.done = true;
+        } else {
+            self.offset += size;
+        }
+
+        Some(Ok(event))
+    }
+}
+
 /// Protocol for interacting with TPM 1.1 and 1.2 devices.
 ///
 /// The corresponding C type is `EFI_TCG_PROTOCOL`.
@@ -496,13 +648,15 @@ impl Tcg {
 
     /// Send a command directly to the TPM.
     ///
-    /// Constructing the input block and parsing the output block are outside
-    /// the scope of this crate. See the [TPM 1.2 Main Specification][spec]
-    /// documents for details of these blocks, in particular Part 3, Commands.
+    /// See the [TPM 1.2 Main Specification][spec] documents for details
+    /// of the input and output blocks, in particular Part 3, Commands.
+    /// The [`command`] module can be used to build the input block and
+    /// parse the output block; see also [`submit_command`].
     ///
     /// Note that TPM structures are big endian.
     ///
     /// [spec]: https://trustedcomputinggroup.org/resource/tpm-main-specification/
+    /// [`submit_command`]: Self::submit_command
     pub fn pass_through_to_tpm(
         &mut self,
         input_parameter_block: &[u8],
@@ -524,6 +678,23 @@ impl Tcg {
             .into()
         }
     }
+
+    /// Send a command block built with [`CommandBuf`] to the TPM and
+    /// parse the response block written to `response_buf`.
+    ///
+    /// The returned [`Response`] carries the TPM's result code; errors
+    /// from the TPM are not turned into an `Err` here.
+    ///
+    /// [`CommandBuf`]: command::CommandBuf
+    /// [`Response`]: command::Response
+    pub fn submit_command<'r>(
+        &mut self,
+        command: &[u8],
+        response_buf: &'r mut [u8],
+    ) -> Result<command::Response<'r>> {
+        self.pass_through_to_tpm(command, response_buf)?;
+        command::Response::parse(response_buf)
+    }
 }
 
 #[cfg(test)]
@@ -573,6 +744,63 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_new_pcr_event_in_box() {
+        let mut event_buf = [MaybeUninit::uninit(); 256];
+        let digest = [0x42; 20];
+        let in_buffer =
+            PcrEvent::new_in_buffer(&mut event_buf, PcrIndex(7), EventType::IPL, digest, &[1, 2])
+                .unwrap();
+        let first = PcrEvent::new_in_box(PcrIndex(7), EventType::IPL, digest, &[1, 2]).unwrap();
+        assert_eq!(*first, *in_buffer);
+        assert_eq!(first.as_bytes().len(), 34);
+        assert_eq!(first.as_bytes(), in_buffer.as_bytes());
+
+        // Build a synthetic log from the raw bytes of the events.
+        let second =
+            PcrEvent::new_in_box(PcrIndex(8), EventType::SEPARATOR, [0; 20], &[0; 4]).unwrap();
+        let mut bytes = first.as_bytes().to_vec();
+        bytes.extend_from_slice(second.as_bytes());
+        let log = unsafe { EventLog::new(bytes.as_ptr(), bytes.as_ptr().add(34), false) };
+        let mut iter = log.iter();
+        assert_eq!(iter.next(), Some(&*first));
+        assert_eq!(iter.next(), Some(&*second));
+        assert_eq!(iter.next(), None);
+
+        let mut iter = log.iter_bounded(bytes.len());
+        assert_eq!(iter.next(), Some(Ok(&*first)));
+        assert_eq!(iter.next(), Some(Ok(&*second)));
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_event_log_v1_bounded() {
+        let event = PcrEvent::new_in_box(PcrIndex(7), EventType::IPL, [0; 20], &[1, 2]).unwrap();
+        let mut bytes = event.as_bytes().to_vec();
+        bytes.extend_from_slice(event.as_bytes());
+        let corrupted = Some(Err(Error::from(Status::VOLUME_CORRUPTED)));
+
+        // The second event is cut off.
+        let log = unsafe { EventLog::new(bytes.as_ptr(), bytes.as_ptr().add(34), false) };
+        let mut iter = log.iter_bounded(bytes.len() - 1);
+        assert_eq!(iter.next(), Some(Ok(&*event)));
+        assert_eq!(iter.next(), corrupted);
+        assert_eq!(iter.next(), None);
+        assert_eq!(log.iter_bounded(20).next(), corrupted);
+
+        // The size of the event data is out of bounds.
+        bytes[28..32].copy_from_slice(&u32::MAX.to_le_bytes());
+        let log = unsafe { EventLog::new(bytes.as_ptr(), bytes.as_ptr().add(34), false) };
+        assert_eq!(log.iter_bounded(bytes.len()).next(), corrupted);
+
+        // The last entry is not at the start of an event.
+        bytes[28..32].copy_from_slice(&2u32.to_le_bytes());
+        let log = unsafe { EventLog::new(bytes.as_ptr(), bytes.as_ptr().add(30), false) };
+        let mut iter = log.iter_bounded(bytes.len());
+        assert_eq!(iter.next(), Some(Ok(&*event)));
+        assert_eq!(iter.next(), corrupted);
+    }
+
     #[test]
     fn test_event_log_v1() {
         // This data comes from dumping the TPM event log in a VM
@@ -623,6 +851,12 @@ mod tests {
             ]
         );
         assert_eq!(entry.event_data(), [0x00, 0x00]);
+        match entry.parse_event_data().unwrap() {
+            EventData::CrtmVersion(version) => {
+                assert_eq!(version.version_string().unwrap().into_iter().count(), 0);
+            }
+            data => panic!("wrong variant: {data:?}"),
+        }
 
         // Entry 2
         let entry = iter.next().unwrap();
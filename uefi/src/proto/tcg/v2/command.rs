@@ -0,0 +1,735 @@
+//! TPM 2.0 command and response blocks.
+//!
+//! [`Tcg::submit_command`] sends a raw command block to the TPM and
+//! returns a raw response block. This module takes care of the framing
+//! of those blocks: use [`CommandBuf`] to build a command and
+//! [`Response`] to check the response code and read the returned
+//! parameters. [`Tcg::run_command`] combines all three steps.
+//!
+//! There are also helpers that marshal the parameters for a few common
+//! commands:
+//!
+//! - [`get_capability`] and [`get_tpm_property`]
+//! - [`pcr_read`]
+//! - [`get_random`]
+//! - [`hash`]
+//! - [`nv_read`]
+//!
+//! These helpers return an [`Error`] whose data is the TPM's response
+//! code if the TPM rejected the command, or `None` if the command could
+//! not be sent or the response could not be parsed.
+//!
+//! The parameters of each command are described in Part 3, Commands, of
+//! the [TPM 2.0 Library Specification][spec]. All TPM structures are
+//! big endian; the `push_*` and `read_*` methods take care of the byte
+//! order.
+//!
+//! [`Tcg::run_command`]: super::Tcg::run_command
+//! [`Tcg::submit_command`]: super::Tcg::submit_command
+//! [spec]: https://trustedcomputinggroup.org/resource/tpm-library-specification/
+
+use super::Tcg;
+use crate::proto::tcg::marshal::{self, Writer};
+use crate::proto::tcg::{AlgorithmId, PcrIndex};
+use crate::{Error, Result, Status};
+
+pub use crate::proto::tcg::marshal::ParamReader;
+
+/// Handle of the password authorization session (`TPM_RS_PW`).
+const RS_PW: u32 = 0x4000_0009;
+
+/// Handle of the null hierarchy (`TPM_RH_NULL`).
+const RH_NULL: u32 = 0x4000_0007;
+
+/// Largest input to `TPM2_Hash` that TPMs must accept
+/// (`MAX_DIGEST_BUFFER`).
+const MAX_HASH_INPUT_SIZE: u16 = 1024;
+
+/// Number of PCRs covered by a PCR selection.
+const NUM_PCRS: u32 = 24;
+
+/// Size of the largest digest the TPM can return.
+const MAX_DIGEST_SIZE: u16 = 64;
+
+/// Number of bytes to read from an NV index per command. TPMs must
+/// support at least this much (`TPM_PT_NV_BUFFER_MAX`).
+const NV_READ_CHUNK_SIZE: u16 = 512;
+
+newtype_enum! {
+    /// Structure tag at the start of a command or response block
+    /// (`TPM_ST`).
+    pub enum StructureTag: u16 => {
+        /// Command or response without an authorization area.
+        NO_SESSIONS = 0x8001,
+        /// Command or response with an authorization area.
+        SESSIONS = 0x8002,
+    }
+}
+
+newtype_enum! {
+    /// Command code (`TPM_CC`).
+    ///
+    /// Only a few commonly-used command codes are listed; any other
+    /// value from the spec can be used as well.
+    pub enum CommandCode: u32 => {
+        /// `TPM2_NV_Read`
+        NV_READ = 0x0000_014e,
+        /// `TPM2_NV_ReadPublic`
+        NV_READ_PUBLIC = 0x0000_0169,
+        /// `TPM2_GetCapability`
+        GET_CAPABILITY = 0x0000_017a,
+        /// `TPM2_GetRandom`
+        GET_RANDOM = 0x0000_017b,
+        /// `TPM2_Hash`
+        HASH = 0x0000_017d,
+        /// `TPM2_PCR_Read`
+        PCR_READ = 0x0000_017e,
+    }
+}
+
+newtype_enum! {
+    /// Response code in a response block (`TPM_RC`).
+    ///
+    /// Only a subset of the response codes is listed. Codes that refer
+    /// to a specific handle, session, or parameter have the number of
+    /// that item encoded in the upper bits and will not match any of
+    /// these values.
+    pub enum ResponseCode: u32 => {
+        /// The command succeeded.
+        SUCCESS = 0x000,
+        /// The tag is not valid for the command.
+        BAD_TAG = 0x01e,
+        /// The TPM has not been initialized.
+        INITIALIZE = 0x100,
+        /// The TPM is in failure mode.
+        FAILURE = 0x101,
+        /// An authorization session is missing.
+        AUTH_MISSING = 0x125,
+        /// The command size is inconsistent with the contents.
+        COMMAND_SIZE = 0x142,
+        /// The command code is not supported.
+        COMMAND_CODE = 0x143,
+        /// The NV index is locked.
+        NV_LOCKED = 0x148,
+        /// The NV index is not authorized for this operation.
+        NV_AUTHORIZATION = 0x149,
+        /// The NV index has not been written.
+        NV_UNINITIALIZED = 0x14a,
+        /// The TPM was canceled.
+        CANCELED = 0x909,
+        /// The TPM is running self tests.
+        TESTING = 0x90a,
+        /// The TPM is rate-limiting NV writes.
+        NV_RATE = 0x920,
+        /// Authorization is locked out.
+        LOCKOUT = 0x921,
+        /// The TPM is busy; the command should be retried.
+        RETRY = 0x922,
+    }
+}
+
+newtype_enum! {
+    /// Capability group queried with [`get_capability`] (`TPM_CAP`).
+    pub enum Capability: u32 => {
+        /// Supported algorithms.
+        ALGS = 0x0000_0000,
+        /// Handles.
+        HANDLES = 0x0000_0001,
+        /// Supported commands.
+        COMMANDS = 0x0000_0002,
+        /// Active PCR banks.
+        PCRS = 0x0000_0005,
+        /// TPM properties (`TPM_PT_*`).
+        TPM_PROPERTIES = 0x0000_0006,
+        /// PCR properties.
+        PCR_PROPERTIES = 0x0000_0007,
+        /// Supported ECC curves.
+        ECC_CURVES = 0x0000_0008,
+    }
+}
+
+/// Builder for a command block.
+///
+/// The header is written by [`CommandBuf::new`], parameters are added
+/// with the `push_*` methods, and [`CommandBuf::finish`] fills in the
+/// total size and returns the completed block.
+#[derive(Debug)]
+pub struct CommandBuf<'a>(Writer<'a>);
+
+impl<'a> CommandBuf<'a> {
+    /// Start a new command in `buf`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buf` cannot hold the header.
+    pub fn new(buf: &'a mut [u8], tag: StructureTag, code: CommandCode) -> Result<Self> {
+        Writer::new(buf, tag.0, code.0).map(Self)
+    }
+
+    /// Append a byte.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is full.
+    pub fn push_u8(self, value: u8) -> Result<Self> {
+        self.push_bytes(&[value])
+    }
+
+    /// Append a big-endian `u16`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is full.
+    pub fn push_u16(self, value: u16) -> Result<Self> {
+        self.push_bytes(&value.to_be_bytes())
+    }
+
+    /// Append a big-endian `u32`.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is full.
+    pub fn push_u32(self, value: u32) -> Result<Self> {
+        self.push_bytes(&value.to_be_bytes())
+    }
+
+    /// Append raw bytes.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: the buffer is full.
+    pub fn push_bytes(mut self, data: &[u8]) -> Result<Self> {
+        self.0.push_bytes(data)?;
+        Ok(self)
+    }
+
+    /// Fill in the size field and get the completed command block.
+    #[must_use]
+    pub fn finish(self) -> &'a [u8] {
+        self.0.finish()
+    }
+}
+
+/// A parsed response block.
+#[derive(Clone, Copy, Debug)]
+pub struct Response<'a> {
+    tag: StructureTag,
+    response_code: ResponseCode,
+    params: &'a [u8],
+}
+
+impl<'a> Response<'a> {
+    /// Parse the response block at the start of `buf`. Any bytes after
+    /// the size given in the response header are ignored.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::PROTOCOL_ERROR`]: the header is truncated or its size
+    ///   field is invalid.
+    pub fn parse(buf: &'a [u8]) -> Result<Self> {
+        let (tag, response_code, params) = marshal::parse_response(buf)?;
+        Ok(Self {
+            tag: StructureTag(tag),
+            response_code: ResponseCode(response_code),
+            params,
+        })
+    }
+
+    /// Structure tag of the response.
+    #[must_use]
+    pub const fn tag(&self) -> StructureTag {
+        self.tag
+    }
+
+    /// Response code of the command.
+    #[must_use]
+    pub const fn response_code(&self) -> ResponseCode {
+        self.response_code
+    }
+
+    /// Get a reader for the returned parameters.
+    ///
+    /// For responses with the [`StructureTag::SESSIONS`] tag, the
+    /// parameters start with a `u32` giving the size of the parameter
+    /// area, and are followed by the authorization area.
+    ///
+    /// # Errors
+    ///
+    /// If the response code is not [`ResponseCode::SUCCESS`], returns a
+    /// [`Status::DEVICE_ERROR`] error with the response code as the
+    /// error data.
+    pub fn params(&self) -> Result<ParamReader<'a>, ResponseCode> {
+        if self.response_code == ResponseCode::SUCCESS {
+            Ok(ParamReader::new(self.params))
+        } else {
+            Err(Error::new(Status::DEVICE_ERROR, self.response_code))
+        }
+    }
+}
+
+/// Convert an error that did not come from the TPM.
+fn no_code(err: Error) -> Error<Option<ResponseCode>> {
+    err.map_data(|()| None)
+}
+
+/// Run `command` and get a reader for the response parameters.
+fn execute<'r>(
+    tcg: &mut Tcg,
+    command: &[u8],
+    response_buf: &'r mut [u8],
+) -> Result<ParamReader<'r>, Option<ResponseCode>> {
+    let response = tcg.run_command(command, response_buf).map_err(no_code)?;
+    response.params().map_err(|err| err.map_data(Some))
+}
+
+/// Query the TPM with `TPM2_GetCapability`.
+///
+/// Returns whether more data is available, along with a reader
+/// positioned at the capability-specific data (the union in
+/// `TPMS_CAPABILITY_DATA`). The response is written to `response_buf`.
+///
+/// # Errors
+///
+/// See the [module documentation](self).
+pub fn get_capability<'r>(
+    tcg: &mut Tcg,
+    capability: Capability,
+    property: u32,
+    property_count: u32,
+    response_buf: &'r mut [u8],
+) -> Result<(bool, ParamReader<'r>), Option<ResponseCode>> {
+    let mut cmd_buf = [0; 22];
+    let command = CommandBuf::new(
+        &mut cmd_buf,
+        StructureTag::NO_SESSIONS,
+        CommandCode::GET_CAPABILITY,
+    )
+    .and_then(|cmd| cmd.push_u32(capability.0))
+    .and_then(|cmd| cmd.push_u32(property))
+    .and_then(|cmd| cmd.push_u32(property_count))
+    .map_err(no_code)?
+    .finish();
+
+    let mut params = execute(tcg, command, response_buf)?;
+    let more_data = parse_get_capability(&mut params, capability).map_err(no_code)?;
+    Ok((more_data, params))
+}
+
+fn parse_get_capability(params: &mut ParamReader, capability: Capability) -> Result<bool> {
+    let more_data = params.read_u8()? != 0;
+    if params.read_u32()? != capability.0 {
+        return Err(Status::PROTOCOL_ERROR.into());
+    }
+    Ok(more_data)
+}
+
+/// Read a single TPM property (`TPM_PT_*`), such as
+/// `TPM_PT_MANUFACTURER` (`0x105`).
+///
+/// # Errors
+///
+/// * [`Status::NOT_FOUND`]: the TPM does not report the property.
+///
+/// See also the [module documentation](self).
+pub fn get_tpm_property(tcg: &mut Tcg, property: u32) -> Result<u32, Option<ResponseCode>> {
+    let mut response_buf = [0; 32];
+    let mut cmd_buf = [0; 22];
+    let command = CommandBuf::new(
+        &mut cmd_buf,
+        StructureTag::NO_SESSIONS,
+        CommandCode::GET_CAPABILITY,
+    )
+    .and_then(|cmd| cmd.push_u32(Capability::TPM_PROPERTIES.0))
+    .and_then(|cmd| cmd.push_u32(property))
+    .and_then(|cmd| cmd.push_u32(1))
+    .map_err(no_code)?
+    .finish();
+
+    let mut params = execute(tcg, command, &mut response_buf)?;
+    parse_tpm_property(&mut params, property).map_err(no_code)
+}
+
+fn parse_tpm_property(params: &mut ParamReader, property: u32) -> Result<u32> {
+    parse_get_capability(params, Capability::TPM_PROPERTIES)?;
+    // TPML_TAGGED_TPM_PROPERTY
+    let count = params.read_u32()?;
+    // The TPM returns the properties starting at the requested one; if
+    // it doesn't exist the first returned property is a different one.
+    if count == 0 || params.read_u32()? != property {
+        return Err(Status::NOT_FOUND.into());
+    }
+    params.read_u32()
+}
+
+/// Read the value of a PCR with `TPM2_PCR_Read`.
+///
+/// The digest is written to the start of `digest`, and its size is
+/// returned.
+///
+/// # Errors
+///
+/// * [`Status::INVALID_PARAMETER`]: `pcr_index` is not in `0..24`.
+/// * [`Status::NOT_FOUND`]: the `bank` is not active.
+/// * [`Status::BUFFER_TOO_SMALL`]: `digest` is too small.
+///
+/// See also the [module documentation](self).
+pub fn pcr_read(
+    tcg: &mut Tcg,
+    bank: AlgorithmId,
+    pcr_index: PcrIndex,
+    digest: &mut [u8],
+) -> Result<usize, Option<ResponseCode>> {
+    let mut cmd_buf = [0; 20];
+    let command = pcr_read_command(&mut cmd_buf, bank, pcr_index).map_err(no_code)?;
+
+    let mut response_buf = [0; 128];
+    let mut params = execute(tcg, command, &mut response_buf)?;
+    parse_pcr_read(&mut params, digest).map_err(no_code)
+}
+
+fn pcr_read_command(buf: &mut [u8], bank: AlgorithmId, pcr_index: PcrIndex) -> Result<&[u8]> {
+    if pcr_index.0 >= NUM_PCRS {
+        return Err(Status::INVALID_PARAMETER.into());
+    }
+    let mut select = [0u8; 3];
+    select[(pcr_index.0 / 8) as usize] = 1 << (pcr_index.0 % 8);
+
+    // TPML_PCR_SELECTION with a single TPMS_PCR_SELECTION.
+    Ok(
+        CommandBuf::new(buf, StructureTag::NO_SESSIONS, CommandCode::PCR_READ)?
+            .push_u32(1)?
+            .push_u16(bank.0)?
+            .push_u8(select.len() as u8)?
+            .push_bytes(&select)?
+            .finish(),
+    )
+}
+
+fn parse_pcr_read(params: &mut ParamReader, digest: &mut [u8]) -> Result<usize> {
+    // pcrUpdateCounter
+    params.read_u32()?;
+
+    // pcrSelectionOut: TPML_PCR_SELECTION
+    let selection_count = params.read_u32()?;
+    for _ in 0..selection_count {
+        params.read_u16()?;
+        let size_of_select = params.read_u8()?;
+        params.read_bytes(usize::from(size_of_select))?;
+    }
+
+    // pcrValues: TPML_DIGEST
+    if params.read_u32()? == 0 {
+        return Err(Status::NOT_FOUND.into());
+    }
+    let size = usize::from(params.read_u16()?);
+    let value = params.read_bytes(size)?;
+    digest
+        .get_mut(..size)
+        .ok_or_else(|| Error::from(Status::BUFFER_TOO_SMALL))?
+        .copy_from_slice(value);
+    Ok(size)
+}
+
+/// Fill `buf` with random bytes from the TPM using `TPM2_GetRandom`.
+///
+/// # Errors
+///
+/// See the [module documentation](self).
+pub fn get_random(tcg: &mut Tcg, buf: &mut [u8]) -> Result<(), Option<ResponseCode>> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let requested = u16::try_from(buf.len() - filled)
+            .unwrap_or(u16::MAX)
+            .min(MAX_DIGEST_SIZE);
+
+        let mut cmd_buf = [0; 12];
+        let command = CommandBuf::new(
+            &mut cmd_buf,
+            StructureTag::NO_SESSIONS,
+            CommandCode::GET_RANDOM,
+        )
+        .and_then(|cmd| cmd.push_u16(requested))
+        .map_err(no_code)?
+        .finish();
+
+        let mut response_buf = [0; 12 + MAX_DIGEST_SIZE as usize];
+        let mut params = execute(tcg, command, &mut response_buf)?;
+        let bytes = read_sized_buffer(&mut params, requested).map_err(no_code)?;
+        if bytes.is_empty() {
+            // Avoid looping forever if the TPM stops returning data.
+            return Err(Error::new(Status::DEVICE_ERROR, None));
+        }
+        buf[filled..filled + bytes.len()].copy_from_slice(bytes);
+        filled += bytes.len();
+    }
+    Ok(())
+}
+
+/// Hash `data` with the TPM using `TPM2_Hash`.
+///
+/// The digest is written to the start of `digest`, and its size is
+/// returned.
+///
+/// # Errors
+///
+/// * [`Status::INVALID_PARAMETER`]: `data` is longer than 1024 bytes.
+/// * [`Status::BUFFER_TOO_SMALL`]: `digest` is too small.
+///
+/// See also the [module documentation](self).
+pub fn hash(
+    tcg: &mut Tcg,
+    algorithm: AlgorithmId,
+    data: &[u8],
+    digest: &mut [u8],
+) -> Result<usize, Option<ResponseCode>> {
+    let mut cmd_buf = [0; 18 + MAX_HASH_INPUT_SIZE as usize];
+    let command = hash_command(&mut cmd_buf, algorithm, data).map_err(no_code)?;
+
+    let mut response_buf = [0; 128];
+    let mut params = execute(tcg, command, &mut response_buf)?;
+    // outHash; the hash check ticket that follows is not needed.
+    let value = read_sized_buffer(&mut params, MAX_DIGEST_SIZE).map_err(no_code)?;
+    digest
+        .get_mut(..value.len())
+        .ok_or(Error::new(Status::BUFFER_TOO_SMALL, None))?
+        .copy_from_slice(value);
+    Ok(value.len())
+}
+
+fn hash_command<'a>(buf: &'a mut [u8], algorithm: AlgorithmId, data: &[u8]) -> Result<&'a [u8]> {
+    let size = u16::try_from(data.len())
+        .ok()
+        .filter(|size| *size <= MAX_HASH_INPUT_SIZE)
+        .ok_or_else(|| Error::from(Status::INVALID_PARAMETER))?;
+    Ok(
+        CommandBuf::new(buf, StructureTag::NO_SESSIONS, CommandCode::HASH)?
+            .push_u16(size)?
+            .push_bytes(data)?
+            .push_u16(algorithm.0)?
+            // hierarchy
+            .push_u32(RH_NULL)?
+            .finish(),
+    )
+}
+
+/// Read a `TPM2B` buffer of at most `max_size` bytes.
+fn read_sized_buffer<'a>(params: &mut ParamReader<'a>, max_size: u16) -> Result<&'a [u8]> {
+    let size = params.read_u16()?;
+    if size > max_size {
+        return Err(Status::PROTOCOL_ERROR.into());
+    }
+    params.read_bytes(usize::from(size))
+}
+
+/// Read `buf.len()` bytes starting at `offset` from an NV index using
+/// `TPM2_NV_Read`.
+///
+/// The index is used as its own authorization with an empty password,
+/// so this works for indices with the `TPMA_NV_AUTHREAD` attribute and
+/// no authorization value, such as the endorsement key certificate
+/// indices.
+///
+/// # Errors
+///
+/// * [`Status::INVALID_PARAMETER`]: the range to read does not fit in
+///   a 16-bit offset.
+///
+/// See also the [module documentation](self).
+pub fn nv_read(
+    tcg: &mut Tcg,
+    nv_index: u32,
+    offset: u16,
+    buf: &mut [u8],
+) -> Result<(), Option<ResponseCode>> {
+    if usize::from(offset) + buf.len() > usize::from(u16::MAX) + 1 {
+        return Err(Error::new(Status::INVALID_PARAMETER, None));
+    }
+
+    for (i, chunk) in buf.chunks_mut(usize::from(NV_READ_CHUNK_SIZE)).enumerate() {
+        // Both values fit in a u16 due to the check above.
+        let chunk_offset = offset + (i * usize::from(NV_READ_CHUNK_SIZE)) as u16;
+        let chunk_size = chunk.len() as u16;
+
+        let mut cmd_buf = [0; 35];
+        let command =
+            nv_read_command(&mut cmd_buf, nv_index, chunk_offset, chunk_size).map_err(no_code)?;
+
+        let mut response_buf = [0; 32 + NV_READ_CHUNK_SIZE as usize];
+        let mut params = execute(tcg, command, &mut response_buf)?;
+        // parameterSize, present because the command has sessions.
+        params.read_u32().map_err(no_code)?;
+        let data = read_sized_buffer(&mut params, chunk_size).map_err(no_code)?;
+        if data.len() != chunk.len() {
+            return Err(Error::new(Status::PROTOCOL_ERROR, None));
+        }
+        chunk.copy_from_slice(data);
+    }
+    Ok(())
+}
+
+fn nv_read_command(buf: &mut [u8], nv_index: u32, offset: u16, size: u16) -> Result<&[u8]> {
+    Ok(
+        CommandBuf::new(buf, StructureTag::SESSIONS, CommandCode::NV_READ)?
+            // authHandle
+            .push_u32(nv_index)?
+            // nvIndex
+            .push_u32(nv_index)?
+            // authorizationSize
+            .push_u32(9)?
+            // TPMS_AUTH_COMMAND: password session with an empty
+            // nonce, no attributes, and an empty password.
+            .push_u32(RS_PW)?
+            .push_u16(0)?
+            .push_u8(0)?
+            .push_u16(0)?
+            // size
+            .push_u16(size)?
+            // offset
+            .push_u16(offset)?
+            .finish(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcr_read() {
+        let mut buf = [0; 20];
+        #[rustfmt::skip]
+        assert_eq!(
+            pcr_read_command(&mut buf, AlgorithmId::SHA1, PcrIndex(8)).unwrap(),
+            [
+                // tag
+                0x80, 0x01,
+                // commandSize
+                0x00, 0x00, 0x00, 0x14,
+                // commandCode
+                0x00, 0x00, 0x01, 0x7e,
+                // pcrSelectionIn
+                0x00, 0x00, 0x00, 0x01,
+                0x00, 0x04,
+                0x03,
+                0x00, 0x01, 0x00,
+            ]
+        );
+        assert_eq!(
+            pcr_read_command(&mut buf, AlgorithmId::SHA1, PcrIndex(24))
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+
+        #[rustfmt::skip]
+        let response = [
+            // pcrUpdateCounter
+            0x00, 0x00, 0x00, 0x05,
+            // pcrSelectionOut
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x04,
+            0x03,
+            0x00, 0x01, 0x00,
+            // pcrValues
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x04,
+            0xaa, 0xbb, 0xcc, 0xdd,
+        ];
+        let mut digest = [0; 8];
+        let size = parse_pcr_read(&mut ParamReader::new(&response), &mut digest).unwrap();
+        assert_eq!(digest[..size], [0xaa, 0xbb, 0xcc, 0xdd]);
+
+        let mut digest = [0; 2];
+        assert_eq!(
+            parse_pcr_read(&mut ParamReader::new(&response), &mut digest)
+                .unwrap_err()
+                .status(),
+            Status::BUFFER_TOO_SMALL
+        );
+    }
+
+    #[test]
+    fn test_tpm_property() {
+        #[rustfmt::skip]
+        let response = [
+            // moreData
+            0x01,
+            // capability
+            0x00, 0x00, 0x00, 0x06,
+            // count
+            0x00, 0x00, 0x00, 0x01,
+            // property, value
+            0x00, 0x00, 0x01, 0x05,
+            0x49, 0x42, 0x4d, 0x00,
+        ];
+        assert_eq!(
+            parse_tpm_property(&mut ParamReader::new(&response), 0x105).unwrap(),
+            0x4942_4d00
+        );
+        assert_eq!(
+            parse_tpm_property(&mut ParamReader::new(&response), 0x106)
+                .unwrap_err()
+                .status(),
+            Status::NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn test_hash_command() {
+        let mut buf = [0; 32];
+        #[rustfmt::skip]
+        assert_eq!(
+            hash_command(&mut buf, AlgorithmId::SHA256, b"abc").unwrap(),
+            [
+                // tag
+                0x80, 0x01,
+                // commandSize
+                0x00, 0x00, 0x00, 0x15,
+                // commandCode
+                0x00, 0x00, 0x01, 0x7d,
+                // data
+                0x00, 0x03, b'a', b'b', b'c',
+                // hashAlg
+                0x00, 0x0b,
+                // hierarchy
+                0x40, 0x00, 0x00, 0x07,
+            ]
+        );
+        assert_eq!(
+            hash_command(&mut buf, AlgorithmId::SHA256, &[0; 1025])
+                .unwrap_err()
+                .status(),
+            Status::INVALID_PARAMETER
+        );
+    }
+
+    #[test]
+    fn test_nv_read_command() {
+        let mut buf = [0; 35];
+        #[rustfmt::skip]
+        assert_eq!(
+            nv_read_command(&mut buf, 0x01c0_0002, 0x200, 0x10).unwrap(),
+            [
+                // tag
+                0x80, 0x02,
+                // commandSize
+                0x00, 0x00, 0x00, 0x23,
+                // commandCode
+                0x00, 0x00, 0x01, 0x4e,
+                // authHandle, nvIndex
+                0x01, 0xc0, 0x00, 0x02,
+                0x01, 0xc0, 0x00, 0x02,
+                // authorizationSize
+                0x00, 0x00, 0x00, 0x09,
+                // sessionHandle, nonce, attributes, hmac
+                0x40, 0x00, 0x00, 0x09,
+                0x00, 0x00,
+                0x00,
+                0x00, 0x00,
+                // size, offset
+                0x00, 0x10,
+                0x02, 0x00,
+            ]
+        );
+    }
+}
@@ -10,8 +10,13 @@
 //! [TCG]: https://trustedcomputinggroup.org/
 //! [TPM]: https://en.wikipedia.org/wiki/Trusted_Platform_Module
 
+pub mod command;
+
+use super::event_data::EventData;
 use super::{v1, AlgorithmId, EventType, HashAlgorithm, PcrIndex};
 use crate::data_types::{PhysicalAddress, UnalignedSlice};
+#[cfg(feature = "alloc")]
+use crate::mem::try_vec;
 use crate::proto::unsafe_protocol;
 use crate::util::{ptr_write_unaligned_and_add, usize_from_u32};
 use crate::{Error, Result, Status};
@@ -22,6 +27,9 @@ use core::mem::MaybeUninit;
 use core::{mem, ptr, slice};
 use ptr_meta::{Pointee, PtrExt};
 
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
 /// Version information.
 ///
 /// Layout compatible with the C type `EFI_TG2_VERSION`.
@@ -182,8 +190,7 @@ impl PcrEventInputs {
         event_type: EventType,
         event_data: &[u8],
     ) -> Result<&'buf Self> {
-        let required_size =
-            mem::size_of::<u32>() + mem::size_of::<EventHeader>() + event_data.len();
+        let required_size = Self::size_for(event_data);
 
         if buffer.len() < required_size {
             return Err(Status::BUFFER_TOO_SMALL.into());
@@ -211,6 +218,41 @@ impl PcrEventInputs {
             Ok(&*ptr)
         }
     }
+
+    /// Create a new `PcrEventInputs` in a [`Box`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Status::INVALID_PARAMETER`] if the `event_data` size is too
+    /// large.
+    #[cfg(feature = "alloc")]
+    pub fn new_in_box(
+        pcr_index: PcrIndex,
+        event_type: EventType,
+        event_data: &[u8],
+    ) -> Result<Box<Self>> {
+        let mut buffer = try_vec(MaybeUninit::uninit(), Self::size_for(event_data))?;
+        Self::new_in_buffer(&mut buffer, pcr_index, event_type, event_data)?;
+
+        // The event is packed, so it has the same layout as the buffer.
+        let ptr: *mut PcrEventInputs = ptr_meta::from_raw_parts_mut(
+            Box::into_raw(buffer.into_boxed_slice()).cast(),
+            event_data.len(),
+        );
+        Ok(unsafe { Box::from_raw(ptr) })
+    }
+
+    /// Size of the inputs with `event_data`, in bytes.
+    fn size_for(event_data: &[u8]) -> usize {
+        mem::size_of::<u32>() + mem::size_of::<EventHeader>() + event_data.len()
+    }
+
+    /// Raw bytes of the inputs, as passed to the firmware.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        let ptr: *const PcrEventInputs = self;
+        unsafe { slice::from_raw_parts(ptr.cast::<u8>(), mem::size_of_val(self)) }
+    }
 }
 
 #[repr(C, packed)]
@@ -345,6 +387,21 @@ impl<'a> EventLog<'a> {
         }
     }
 
+    /// Get a copy of the log that is not tied to the borrow of the
+    /// protocol.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the log memory stays valid for `'b`.
+    pub(crate) unsafe fn detach<'b>(&self) -> EventLog<'b> {
+        EventLog {
+            _lifetime: PhantomData,
+            location: self.location,
+            last_entry: self.last_entry,
+            is_truncated: self.is_truncated,
+        }
+    }
+
     /// Header at the beginning of the event log.
     fn header(&self) -> Option<EventLogHeader> {
         // The spec is unclear if the header is present when there are
@@ -500,6 +557,17 @@ impl<'a> PcrEvent<'a> {
         self.event_data
     }
 
+    /// Event data decoded according to the [`event_type`].
+    ///
+    /// # Errors
+    ///
+    /// See [`EventData::parse`].
+    ///
+    /// [`event_type`]: Self::event_type
+    pub fn parse_event_data(&self) -> Result<EventData<'_>> {
+        EventData::parse(self.event_type(), self.event_data())
+    }
+
     /// Digests of the data hashed for this event.
     #[must_use]
     pub fn digests(&self) -> PcrEventDigests {
@@ -690,9 +758,11 @@ impl Tcg {
 
     /// Send a command directly to the TPM.
     ///
-    /// Constructing the input block and parsing the output block are outside
-    /// the scope of this crate. See the [TPM 2.0 Specification][spec], in
-    /// particular Part 2 (Structures) and Part 3 (Commands).
+    /// The [`command`] module has helpers for constructing the input
+    /// block and parsing the output block; see also [`Tcg::run_command`].
+    /// The commands themselves are described in the [TPM 2.0
+    /// Specification][spec], in particular Part 2 (Structures) and Part 3
+    /// (Commands).
     ///
     /// Note that TPM structures are big endian.
     ///
@@ -719,6 +789,23 @@ impl Tcg {
         }
     }
 
+    /// Send a command block built with [`CommandBuf`] to the TPM and
+    /// parse the response block written to `response_buf`.
+    ///
+    /// The returned [`Response`] carries the TPM's response code; errors
+    /// from the TPM are not turned into an `Err` here.
+    ///
+    /// [`CommandBuf`]: command::CommandBuf
+    /// [`Response`]: command::Response
+    pub fn run_command<'r>(
+        &mut self,
+        command: &[u8],
+        response_buf: &'r mut [u8],
+    ) -> Result<command::Response<'r>> {
+        self.submit_command(command, response_buf)?;
+        command::Response::parse(response_buf)
+    }
+
     /// Get a bitmap of the active PCR banks. Each bank corresponds to a hash
     /// algorithm.
     pub fn get_active_pcr_banks(&mut self) -> Result<HashAlgorithm> {
@@ -810,6 +897,10 @@ mod tests {
             // Event data
             0x12, 0x13, 0x14, 0x15,
         ]);
+
+        let boxed = PcrEventInputs::new_in_box(PcrIndex(4), EventType::IPL, &event_data).unwrap();
+        assert_eq!(boxed.as_bytes(), event_bytes);
+        assert_eq!(boxed.as_bytes(), event.as_bytes());
     }
 
     #[test]
@@ -973,7 +1064,7 @@ mod tests {
                 },
             ]
         );
-        assert_eq!(header.vendor_info, []);
+        assert_eq!(header.vendor_info, [0u8; 0]);
 
         let mut iter = log.iter();
 
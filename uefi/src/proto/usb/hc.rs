@@ -0,0 +1,888 @@
+//! USB 2 host controller protocol.
+
+use super::{DataDirection, DeviceRequest, UsbSpeed, UsbTransferResult};
+use crate::proto::unsafe_protocol;
+use crate::{Result, Status};
+use bitflags::bitflags;
+use core::ffi::c_void;
+use core::{ptr, slice};
+
+/// Callback of an asynchronous interrupt transfer.
+type AsyncTransferCallback = unsafe extern "efiapi" fn(
+    data: *mut c_void,
+    data_length: usize,
+    context: *mut c_void,
+    status: u32,
+) -> Status;
+
+/// The USB 2 host controller protocol.
+///
+/// This protocol is installed on the handle of each USB host controller, and
+/// is used by the USB bus driver to enumerate and talk to the devices. It
+/// can also be used by drivers managing the controller's devices directly.
+///
+/// The transfer functions return the number of transferred bytes. On
+/// failure, the error data holds the [`UsbTransferResult`] of the transfer.
+///
+/// The corresponding C type is `EFI_USB2_HC_PROTOCOL`.
+#[repr(C)]
+#[unsafe_protocol("3e745226-9818-45b6-a2ac-d7cd0e8ba2bc")]
+pub struct Usb2HostController {
+    get_capability: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        max_speed: *mut UsbSpeed,
+        port_number: *mut u8,
+        is_64bit_capable: *mut u8,
+    ) -> Status,
+    reset: unsafe extern "efiapi" fn(this: *mut Self, attributes: ResetAttributes) -> Status,
+    get_state:
+        unsafe extern "efiapi" fn(this: *mut Self, state: *mut HostControllerState) -> Status,
+    set_state: unsafe extern "efiapi" fn(this: *mut Self, state: HostControllerState) -> Status,
+    control_transfer: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        device_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        request: *const DeviceRequest,
+        transfer_direction: DataDirection,
+        data: *mut c_void,
+        data_length: *mut usize,
+        timeout: usize,
+        translator: *const TransactionTranslator,
+        transfer_result: *mut u32,
+    ) -> Status,
+    bulk_transfer: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        device_address: u8,
+        endpoint_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        data_buffers_number: u8,
+        data: *mut [*mut c_void; Self::MAX_BULK_BUFFER_NUM],
+        data_length: *mut usize,
+        data_toggle: *mut u8,
+        timeout: usize,
+        translator: *const TransactionTranslator,
+        transfer_result: *mut u32,
+    ) -> Status,
+    async_interrupt_transfer: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        device_address: u8,
+        endpoint_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        is_new_transfer: bool,
+        data_toggle: *mut u8,
+        polling_interval: usize,
+        data_length: usize,
+        translator: *const TransactionTranslator,
+        callback_function: Option<AsyncTransferCallback>,
+        context: *mut c_void,
+    ) -> Status,
+    sync_interrupt_transfer: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        device_address: u8,
+        endpoint_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        data: *mut c_void,
+        data_length: *mut usize,
+        data_toggle: *mut u8,
+        timeout: usize,
+        translator: *const TransactionTranslator,
+        transfer_result: *mut u32,
+    ) -> Status,
+    isochronous_transfer: usize,
+    async_isochronous_transfer: usize,
+    get_root_hub_port_status: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        port_number: u8,
+        port_status: *mut PortStatus,
+    ) -> Status,
+    set_root_hub_port_feature:
+        unsafe extern "efiapi" fn(this: *mut Self, port_number: u8, feature: PortFeature) -> Status,
+    clear_root_hub_port_feature:
+        unsafe extern "efiapi" fn(this: *mut Self, port_number: u8, feature: PortFeature) -> Status,
+    major_revision: u16,
+    minor_revision: u16,
+}
+
+impl Usb2HostController {
+    /// Maximum number of data buffers of a bulk transfer.
+    const MAX_BULK_BUFFER_NUM: usize = 10;
+
+    /// Get the version of the USB specification supported by the controller,
+    /// as a `(major, minor)` pair.
+    #[must_use]
+    pub const fn revision(&self) -> (u16, u16) {
+        (self.major_revision, self.minor_revision)
+    }
+
+    /// Get the capabilities of the controller.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::DEVICE_ERROR`]: the controller failed.
+    pub fn capability(&mut self) -> Result<HostControllerCapability> {
+        let mut max_speed = UsbSpeed::FULL;
+        let mut port_count = 0;
+        let mut is_64bit_capable = 0;
+        unsafe {
+            (self.get_capability)(self, &mut max_speed, &mut port_count, &mut is_64bit_capable)
+        }
+        .into_with_val(|| HostControllerCapability {
+            max_speed,
+            port_count,
+            is_64bit_capable: is_64bit_capable != 0,
+        })
+    }
+
+    /// Reset the controller, or the whole USB bus.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `attributes` is invalid.
+    /// * [`Status::UNSUPPORTED`]: the reset type is not supported.
+    /// * [`Status::ACCESS_DENIED`]: the controller is in use by a debugger.
+    /// * [`Status::DEVICE_ERROR`]: the controller failed.
+    pub fn reset(&mut self, attributes: ResetAttributes) -> Result {
+        unsafe { (self.reset)(self, attributes) }.into()
+    }
+
+    /// Get the state of the controller.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::DEVICE_ERROR`]: the controller failed.
+    pub fn state(&mut self) -> Result<HostControllerState> {
+        let mut state = HostControllerState::HALT;
+        unsafe { (self.get_state)(self, &mut state) }.into_with_val(|| state)
+    }
+
+    /// Set the state of the controller.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `state` is invalid.
+    /// * [`Status::DEVICE_ERROR`]: the controller failed.
+    pub fn set_state(&mut self, state: HostControllerState) -> Result {
+        unsafe { (self.set_state)(self, state) }.into()
+    }
+
+    /// Run a control transfer with the device at `device_address`, sending
+    /// `request` and transferring `data` in `direction`. `timeout` is in
+    /// milliseconds, and `translator` is needed for low and full speed
+    /// devices behind a high speed hub.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: a parameter is invalid.
+    /// * [`Status::OUT_OF_RESOURCES`]: the transfer could not be queued.
+    /// * [`Status::TIMEOUT`]: the transfer timed out.
+    /// * [`Status::DEVICE_ERROR`]: the transfer failed.
+    #[allow(clippy::too_many_arguments)]
+    pub fn control_transfer(
+        &mut self,
+        device_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        request: &DeviceRequest,
+        direction: DataDirection,
+        data: &mut [u8],
+        timeout: usize,
+        translator: Option<&TransactionTranslator>,
+    ) -> Result<usize, UsbTransferResult> {
+        let mut data_length = data.len();
+        let mut result = 0;
+        unsafe {
+            (self.control_transfer)(
+                self,
+                device_address,
+                device_speed,
+                maximum_packet_length,
+                request,
+                direction,
+                data_ptr(data),
+                &mut data_length,
+                timeout,
+                translator_ptr(translator),
+                &mut result,
+            )
+        }
+        .into_with(|| data_length, |_| transfer_result(result))
+    }
+
+    /// Run a bulk transfer with the endpoint `endpoint_address` of the
+    /// device at `device_address`, whose direction is given by bit 7 of the
+    /// endpoint address. `data_toggle` is updated for the next transfer.
+    ///
+    /// # Errors
+    ///
+    /// See [`control_transfer`](Self::control_transfer).
+    #[allow(clippy::too_many_arguments)]
+    pub fn bulk_transfer(
+        &mut self,
+        device_address: u8,
+        endpoint_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        data: &mut [u8],
+        data_toggle: &mut u8,
+        timeout: usize,
+        translator: Option<&TransactionTranslator>,
+    ) -> Result<usize, UsbTransferResult> {
+        let mut buffers = [ptr::null_mut(); Self::MAX_BULK_BUFFER_NUM];
+        buffers[0] = data_ptr(data);
+        let mut data_length = data.len();
+        let mut result = 0;
+        unsafe {
+            (self.bulk_transfer)(
+                self,
+                device_address,
+                endpoint_address,
+                device_speed,
+                maximum_packet_length,
+                1,
+                &mut buffers,
+                &mut data_length,
+                data_toggle,
+                timeout,
+                translator_ptr(translator),
+                &mut result,
+            )
+        }
+        .into_with(|| data_length, |_| transfer_result(result))
+    }
+
+    /// Run a synchronous interrupt transfer with the endpoint
+    /// `endpoint_address` of the device at `device_address`. `data_toggle`
+    /// is updated for the next transfer.
+    ///
+    /// # Errors
+    ///
+    /// See [`control_transfer`](Self::control_transfer).
+    #[allow(clippy::too_many_arguments)]
+    pub fn sync_interrupt_transfer(
+        &mut self,
+        device_address: u8,
+        endpoint_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        data: &mut [u8],
+        data_toggle: &mut u8,
+        timeout: usize,
+        translator: Option<&TransactionTranslator>,
+    ) -> Result<usize, UsbTransferResult> {
+        let mut data_length = data.len();
+        let mut result = 0;
+        unsafe {
+            (self.sync_interrupt_transfer)(
+                self,
+                device_address,
+                endpoint_address,
+                device_speed,
+                maximum_packet_length,
+                data_ptr(data),
+                &mut data_length,
+                data_toggle,
+                timeout,
+                translator_ptr(translator),
+                &mut result,
+            )
+        }
+        .into_with(|| data_length, |_| transfer_result(result))
+    }
+
+    /// Start polling the interrupt endpoint `endpoint_address` of the device
+    /// at `device_address` every `polling_interval` milliseconds, for up to
+    /// `data_length` bytes. `callback` is called with the data and result of
+    /// each completed transfer.
+    ///
+    /// # Safety
+    ///
+    /// `callback` is called from a timer event until the transfer is
+    /// stopped with [`cancel_async_interrupt_transfer`]. The caller must
+    /// cancel the transfer before `callback` is dropped or moved.
+    ///
+    /// [`cancel_async_interrupt_transfer`]: Self::cancel_async_interrupt_transfer
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: a parameter is invalid.
+    /// * [`Status::OUT_OF_RESOURCES`]: the transfer could not be queued.
+    /// * [`Status::DEVICE_ERROR`]: the controller failed.
+    #[allow(clippy::too_many_arguments)]
+    pub unsafe fn start_async_interrupt_transfer<F>(
+        &mut self,
+        device_address: u8,
+        endpoint_address: u8,
+        device_speed: UsbSpeed,
+        maximum_packet_length: usize,
+        data_toggle: &mut u8,
+        polling_interval: usize,
+        data_length: usize,
+        translator: Option<&TransactionTranslator>,
+        callback: &mut F,
+    ) -> Result
+    where
+        F: FnMut(&[u8], UsbTransferResult),
+    {
+        unsafe extern "efiapi" fn call_closure<F>(
+            data: *mut c_void,
+            data_length: usize,
+            context: *mut c_void,
+            status: u32,
+        ) -> Status
+        where
+            F: FnMut(&[u8], UsbTransferResult),
+        {
+            let data = if data.is_null() {
+                &[]
+            } else {
+                slice::from_raw_parts(data.cast::<u8>(), data_length)
+            };
+            let callback = &mut *context.cast::<F>();
+            callback(data, transfer_result(status));
+            Status::SUCCESS
+        }
+
+        (self.async_interrupt_transfer)(
+            self,
+            device_address,
+            endpoint_address,
+            device_speed,
+            maximum_packet_length,
+            true,
+            data_toggle,
+            polling_interval,
+            data_length,
+            translator_ptr(translator),
+            Some(call_closure::<F>),
+            (callback as *mut F).cast(),
+        )
+        .into()
+    }
+
+    /// Stop the asynchronous interrupt transfer of the endpoint
+    /// `endpoint_address` of the device at `device_address`. `data_toggle`
+    /// is set to the data toggle of the next transfer.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: no such transfer is running.
+    /// * [`Status::DEVICE_ERROR`]: the controller failed.
+    pub fn cancel_async_interrupt_transfer(
+        &mut self,
+        device_address: u8,
+        endpoint_address: u8,
+        data_toggle: &mut u8,
+    ) -> Result {
+        unsafe {
+            (self.async_interrupt_transfer)(
+                self,
+                device_address,
+                endpoint_address,
+                UsbSpeed::FULL,
+                0,
+                false,
+                data_toggle,
+                0,
+                0,
+                ptr::null(),
+                None,
+                ptr::null_mut(),
+            )
+        }
+        .into()
+    }
+
+    /// Get the status of the root hub port `port_number`, which is less
+    /// than [`HostControllerCapability::port_count`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `port_number` is invalid.
+    pub fn root_hub_port_status(&mut self, port_number: u8) -> Result<PortStatus> {
+        let mut status = PortStatus {
+            status: PortStatusFlags::empty(),
+            change: PortChangeFlags::empty(),
+        };
+        unsafe { (self.get_root_hub_port_status)(self, port_number, &mut status) }
+            .into_with_val(|| status)
+    }
+
+    /// Set `feature` on the root hub port `port_number`, such as
+    /// [`PortFeature::RESET`] to reset it.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `port_number` or `feature` is
+    ///   invalid.
+    pub fn set_root_hub_port_feature(&mut self, port_number: u8, feature: PortFeature) -> Result {
+        unsafe { (self.set_root_hub_port_feature)(self, port_number, feature) }.into()
+    }
+
+    /// Clear `feature` on the root hub port `port_number`, such as
+    /// [`PortFeature::CONNECT_CHANGE`] to acknowledge a connection change.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `port_number` or `feature` is
+    ///   invalid.
+    pub fn clear_root_hub_port_feature(&mut self, port_number: u8, feature: PortFeature) -> Result {
+        unsafe { (self.clear_root_hub_port_feature)(self, port_number, feature) }.into()
+    }
+}
+
+fn data_ptr(data: &mut [u8]) -> *mut c_void {
+    if data.is_empty() {
+        ptr::null_mut()
+    } else {
+        data.as_mut_ptr().cast()
+    }
+}
+
+fn translator_ptr(translator: Option<&TransactionTranslator>) -> *const TransactionTranslator {
+    translator.map_or(ptr::null(), |translator| translator as *const _)
+}
+
+const fn transfer_result(result: u32) -> UsbTransferResult {
+    UsbTransferResult::from_bits_truncate(result)
+}
+
+/// Capabilities of a USB host controller.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct HostControllerCapability {
+    /// The highest speed supported by the controller.
+    pub max_speed: UsbSpeed,
+    /// The number of root hub ports.
+    pub port_count: u8,
+    /// Whether the controller can access 64-bit addresses.
+    pub is_64bit_capable: bool,
+}
+
+newtype_enum! {
+    /// State of a USB host controller.
+    ///
+    /// The corresponding C type is `EFI_USB_HC_STATE`.
+    pub enum HostControllerState: u32 => {
+        /// The controller is halted.
+        HALT = 0,
+        /// The controller is running.
+        OPERATIONAL = 1,
+        /// The controller is suspended.
+        SUSPEND = 2,
+    }
+}
+
+bitflags! {
+    /// Type of reset of [`Usb2HostController::reset`].
+    #[repr(transparent)]
+    pub struct ResetAttributes: u16 {
+        /// Reset all the controllers and devices of the USB bus.
+        const GLOBAL = 0x0001;
+        /// Reset the controller.
+        const HOST_CONTROLLER = 0x0002;
+        /// Reset the whole bus, even if a debug port is in use.
+        const GLOBAL_WITH_DEBUG = 0x0004;
+        /// Reset the controller, even if a debug port is in use.
+        const HOST_WITH_DEBUG = 0x0008;
+    }
+}
+
+/// The hub translating the transactions of a low or full speed device
+/// attached to a high speed hub.
+///
+/// The corresponding C type is `EFI_USB2_HC_TRANSACTION_TRANSLATOR`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct TransactionTranslator {
+    /// The address of the high speed hub.
+    pub hub_address: u8,
+    /// The port of the hub the device is attached to.
+    pub port_number: u8,
+}
+
+/// Status of a root hub port.
+///
+/// The corresponding C type is `EFI_USB_PORT_STATUS`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct PortStatus {
+    /// The current state of the port.
+    pub status: PortStatusFlags,
+    /// The state changes that were not acknowledged yet.
+    pub change: PortChangeFlags,
+}
+
+bitflags! {
+    /// State of a root hub port.
+    #[repr(transparent)]
+    pub struct PortStatusFlags: u16 {
+        /// A device is attached.
+        const CONNECTION = 0x0001;
+        /// The port is enabled.
+        const ENABLE = 0x0002;
+        /// The port is suspended.
+        const SUSPEND = 0x0004;
+        /// An over-current condition exists.
+        const OVER_CURRENT = 0x0008;
+        /// The port is being reset.
+        const RESET = 0x0010;
+        /// The port is powered.
+        const POWER = 0x0100;
+        /// A low speed device is attached.
+        const LOW_SPEED = 0x0200;
+        /// A high speed device is attached.
+        const HIGH_SPEED = 0x0400;
+        /// A super speed device is attached.
+        const SUPER_SPEED = 0x0800;
+        /// The port is owned by a companion controller.
+        const OWNER = 0x2000;
+    }
+}
+
+bitflags! {
+    /// State changes of a root hub port.
+    #[repr(transparent)]
+    pub struct PortChangeFlags: u16 {
+        /// A device was attached or detached.
+        const CONNECTION = 0x0001;
+        /// The port was enabled or disabled.
+        const ENABLE = 0x0002;
+        /// The port was suspended or resumed.
+        const SUSPEND = 0x0004;
+        /// The over-current condition changed.
+        const OVER_CURRENT = 0x0008;
+        /// The reset of the port completed.
+        const RESET = 0x0010;
+    }
+}
+
+newtype_enum! {
+    /// Feature of a root hub port.
+    ///
+    /// The corresponding C type is `EFI_USB_PORT_FEATURE`.
+    pub enum PortFeature: u32 => {
+        /// Enable the port.
+        ENABLE = 1,
+        /// Suspend the port.
+        SUSPEND = 2,
+        /// Reset the port.
+        RESET = 4,
+        /// Power the port.
+        POWER = 8,
+        /// Give the port to a companion controller.
+        OWNER = 13,
+        /// Connection change indicator.
+        CONNECT_CHANGE = 16,
+        /// Enable change indicator.
+        ENABLE_CHANGE = 17,
+        /// Suspend change indicator.
+        SUSPEND_CHANGE = 18,
+        /// Over-current change indicator.
+        OVER_CURRENT_CHANGE = 19,
+        /// Reset change indicator.
+        RESET_CHANGE = 20,
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use core::marker::PhantomData;
+
+    /// Device descriptor of the fake device at address 1.
+    const DEVICE_DESCRIPTOR: [u8; 18] = [
+        18, 1, 0x00, 0x02, 0, 0, 0, 64, 0x34, 0x12, 0x78, 0x56, 0, 1, 1, 2, 3, 1,
+    ];
+
+    unsafe extern "efiapi" fn get_capability(
+        _this: *mut Usb2HostController,
+        max_speed: *mut UsbSpeed,
+        port_number: *mut u8,
+        is_64bit_capable: *mut u8,
+    ) -> Status {
+        *max_speed = UsbSpeed::HIGH;
+        *port_number = 2;
+        *is_64bit_capable = 1;
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn reset(
+        _this: *mut Usb2HostController,
+        attributes: ResetAttributes,
+    ) -> Status {
+        if attributes == ResetAttributes::HOST_CONTROLLER {
+            Status::SUCCESS
+        } else {
+            Status::UNSUPPORTED
+        }
+    }
+
+    unsafe extern "efiapi" fn get_state(
+        _this: *mut Usb2HostController,
+        state: *mut HostControllerState,
+    ) -> Status {
+        *state = HostControllerState::OPERATIONAL;
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn set_state(
+        _this: *mut Usb2HostController,
+        _state: HostControllerState,
+    ) -> Status {
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn control_transfer(
+        _this: *mut Usb2HostController,
+        device_address: u8,
+        _device_speed: UsbSpeed,
+        _maximum_packet_length: usize,
+        request: *const DeviceRequest,
+        transfer_direction: DataDirection,
+        data: *mut c_void,
+        data_length: *mut usize,
+        _timeout: usize,
+        _translator: *const TransactionTranslator,
+        transfer_result: *mut u32,
+    ) -> Status {
+        let request = &*request;
+        if device_address != 1 {
+            *transfer_result = UsbTransferResult::TIMEOUT.bits();
+            return Status::TIMEOUT;
+        }
+        if request.request != DeviceRequest::GET_DESCRIPTOR
+            || transfer_direction != DataDirection::IN
+        {
+            *transfer_result = UsbTransferResult::STALL.bits();
+            return Status::DEVICE_ERROR;
+        }
+        let len = (*data_length).min(DEVICE_DESCRIPTOR.len());
+        data.cast::<u8>().copy_from(DEVICE_DESCRIPTOR.as_ptr(), len);
+        *data_length = len;
+        *transfer_result = 0;
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn bulk_transfer(
+        _this: *mut Usb2HostController,
+        _device_address: u8,
+        _endpoint_address: u8,
+        _device_speed: UsbSpeed,
+        _maximum_packet_length: usize,
+        _data_buffers_number: u8,
+        _data: *mut [*mut c_void; Usb2HostController::MAX_BULK_BUFFER_NUM],
+        _data_length: *mut usize,
+        _data_toggle: *mut u8,
+        _timeout: usize,
+        _translator: *const TransactionTranslator,
+        _transfer_result: *mut u32,
+    ) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    unsafe extern "efiapi" fn async_interrupt_transfer(
+        _this: *mut Usb2HostController,
+        _device_address: u8,
+        _endpoint_address: u8,
+        _device_speed: UsbSpeed,
+        _maximum_packet_length: usize,
+        is_new_transfer: bool,
+        data_toggle: *mut u8,
+        _polling_interval: usize,
+        _data_length: usize,
+        _translator: *const TransactionTranslator,
+        callback_function: Option<AsyncTransferCallback>,
+        context: *mut c_void,
+    ) -> Status {
+        if is_new_transfer {
+            // Complete two transfers right away.
+            let callback = callback_function.unwrap();
+            let mut report = [0u8, 0, 4, 0, 0, 0, 0, 0];
+            assert_eq!(
+                callback(report.as_mut_ptr().cast(), report.len(), context, 0),
+                Status::SUCCESS
+            );
+            assert_eq!(
+                callback(ptr::null_mut(), 0, context, UsbTransferResult::NAK.bits()),
+                Status::SUCCESS
+            );
+        } else {
+            *data_toggle = 1;
+        }
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn sync_interrupt_transfer(
+        _this: *mut Usb2HostController,
+        _device_address: u8,
+        _endpoint_address: u8,
+        _device_speed: UsbSpeed,
+        _maximum_packet_length: usize,
+        _data: *mut c_void,
+        _data_length: *mut usize,
+        _data_toggle: *mut u8,
+        _timeout: usize,
+        _translator: *const TransactionTranslator,
+        _transfer_result: *mut u32,
+    ) -> Status {
+        Status::UNSUPPORTED
+    }
+
+    unsafe extern "efiapi" fn get_root_hub_port_status(
+        _this: *mut Usb2HostController,
+        port_number: u8,
+        port_status: *mut PortStatus,
+    ) -> Status {
+        *port_status = match port_number {
+            0 => PortStatus {
+                status: PortStatusFlags::CONNECTION
+                    | PortStatusFlags::ENABLE
+                    | PortStatusFlags::POWER
+                    | PortStatusFlags::HIGH_SPEED,
+                change: PortChangeFlags::CONNECTION,
+            },
+            1 => PortStatus {
+                status: PortStatusFlags::POWER,
+                change: PortChangeFlags::empty(),
+            },
+            _ => return Status::INVALID_PARAMETER,
+        };
+        Status::SUCCESS
+    }
+
+    unsafe extern "efiapi" fn port_feature(
+        _this: *mut Usb2HostController,
+        port_number: u8,
+        _feature: PortFeature,
+    ) -> Status {
+        if port_number < 2 {
+            Status::SUCCESS
+        } else {
+            Status::INVALID_PARAMETER
+        }
+    }
+
+    #[test]
+    fn test_usb2_host_controller() {
+        let mut firmware = MockFirmware::new();
+        let mut protocol = Usb2HostController {
+            get_capability,
+            reset,
+            get_state,
+            set_state,
+            control_transfer,
+            bulk_transfer,
+            async_interrupt_transfer,
+            sync_interrupt_transfer,
+            isochronous_transfer: 0,
+            async_isochronous_transfer: 0,
+            get_root_hub_port_status,
+            set_root_hub_port_feature: port_feature,
+            clear_root_hub_port_feature: port_feature,
+            major_revision: 2,
+            minor_revision: 0,
+            _no_send_or_sync: PhantomData,
+        };
+        let handle = unsafe { firmware.install_protocol(None, &mut protocol) };
+        let st = firmware.system_table();
+        let bt = st.boot_services();
+        let mut hc = bt
+            .open_protocol_exclusive::<Usb2HostController>(handle)
+            .unwrap();
+
+        assert_eq!(hc.revision(), (2, 0));
+        assert_eq!(
+            hc.capability().unwrap(),
+            HostControllerCapability {
+                max_speed: UsbSpeed::HIGH,
+                port_count: 2,
+                is_64bit_capable: true,
+            }
+        );
+        hc.reset(ResetAttributes::HOST_CONTROLLER).unwrap();
+        assert_eq!(
+            hc.reset(ResetAttributes::GLOBAL).unwrap_err().status(),
+            Status::UNSUPPORTED
+        );
+        assert_eq!(hc.state().unwrap(), HostControllerState::OPERATIONAL);
+        hc.set_state(HostControllerState::OPERATIONAL).unwrap();
+
+        // Root hub ports.
+        let status = hc.root_hub_port_status(0).unwrap();
+        assert!(status.status.contains(PortStatusFlags::CONNECTION));
+        assert_eq!(status.change, PortChangeFlags::CONNECTION);
+        hc.clear_root_hub_port_feature(0, PortFeature::CONNECT_CHANGE)
+            .unwrap();
+        hc.set_root_hub_port_feature(1, PortFeature::RESET).unwrap();
+        assert_eq!(
+            hc.root_hub_port_status(2).unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+
+        // Control transfers.
+        let request = DeviceRequest::get_descriptor(DeviceRequest::DESCRIPTOR_DEVICE, 0, 8);
+        let mut buffer = [0; 8];
+        let len = hc
+            .control_transfer(
+                1,
+                UsbSpeed::HIGH,
+                64,
+                &request,
+                DataDirection::IN,
+                &mut buffer,
+                1000,
+                None,
+            )
+            .unwrap();
+        assert_eq!(buffer[..len], DEVICE_DESCRIPTOR[..8]);
+        let err = hc
+            .control_transfer(
+                1,
+                UsbSpeed::HIGH,
+                64,
+                &DeviceRequest::set_address(2),
+                DataDirection::NO_DATA,
+                &mut [],
+                1000,
+                None,
+            )
+            .unwrap_err();
+        assert_eq!(err.status(), Status::DEVICE_ERROR);
+        assert_eq!(*err.data(), UsbTransferResult::STALL);
+
+        // Asynchronous interrupt transfers.
+        let mut reports = 0;
+        let mut errors = UsbTransferResult::empty();
+        let mut callback = |data: &[u8], result| {
+            if result == UsbTransferResult::empty() {
+                assert_eq!(data[2], 4);
+                reports += 1;
+            }
+            errors |= result;
+        };
+        let mut data_toggle = 0;
+        unsafe {
+            hc.start_async_interrupt_transfer(
+                1,
+                0x81,
+                UsbSpeed::HIGH,
+                8,
+                &mut data_toggle,
+                10,
+                8,
+                None,
+                &mut callback,
+            )
+        }
+        .unwrap();
+        hc.cancel_async_interrupt_transfer(1, 0x81, &mut data_toggle)
+            .unwrap();
+        assert_eq!(data_toggle, 1);
+        assert_eq!(reports, 1);
+        assert_eq!(errors, UsbTransferResult::NAK);
+    }
+}
@@ -0,0 +1,166 @@
+//! USB protocols.
+//!
+//! The [`Usb2HostController`] protocol gives access to a USB host
+//! controller, for drivers managing the devices attached to it.
+//!
+//! [`Usb2HostController`]: hc::Usb2HostController
+
+pub mod hc;
+
+use bitflags::bitflags;
+
+newtype_enum! {
+    /// Direction of the data stage of a transfer.
+    ///
+    /// The corresponding C type is `EFI_USB_DATA_DIRECTION`.
+    pub enum DataDirection: u32 => {
+        /// From the device to the host.
+        IN = 0,
+        /// From the host to the device.
+        OUT = 1,
+        /// No data stage.
+        NO_DATA = 2,
+    }
+}
+
+newtype_enum! {
+    /// Speed of a USB device.
+    pub enum UsbSpeed: u8 => {
+        /// 12 Mb/s.
+        FULL = 0,
+        /// 1.5 Mb/s.
+        LOW = 1,
+        /// 480 Mb/s.
+        HIGH = 2,
+        /// 5 Gb/s.
+        SUPER = 3,
+    }
+}
+
+/// The setup packet of a control transfer, as defined in the USB
+/// specification.
+///
+/// The corresponding C type is `EFI_USB_DEVICE_REQUEST`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(C)]
+pub struct DeviceRequest {
+    /// The direction, type and recipient of the request.
+    pub request_type: u8,
+    /// The request, such as [`Self::GET_DESCRIPTOR`].
+    pub request: u8,
+    /// A request-specific value.
+    pub value: u16,
+    /// A request-specific index, usually an interface or endpoint.
+    pub index: u16,
+    /// The length of the data stage.
+    pub length: u16,
+}
+
+impl DeviceRequest {
+    /// The `GET_STATUS` request.
+    pub const GET_STATUS: u8 = 0x00;
+    /// The `CLEAR_FEATURE` request.
+    pub const CLEAR_FEATURE: u8 = 0x01;
+    /// The `SET_FEATURE` request.
+    pub const SET_FEATURE: u8 = 0x03;
+    /// The `SET_ADDRESS` request.
+    pub const SET_ADDRESS: u8 = 0x05;
+    /// The `GET_DESCRIPTOR` request.
+    pub const GET_DESCRIPTOR: u8 = 0x06;
+    /// The `SET_DESCRIPTOR` request.
+    pub const SET_DESCRIPTOR: u8 = 0x07;
+    /// The `GET_CONFIGURATION` request.
+    pub const GET_CONFIGURATION: u8 = 0x08;
+    /// The `SET_CONFIGURATION` request.
+    pub const SET_CONFIGURATION: u8 = 0x09;
+
+    /// Descriptor type of a device descriptor.
+    pub const DESCRIPTOR_DEVICE: u8 = 1;
+    /// Descriptor type of a configuration descriptor.
+    pub const DESCRIPTOR_CONFIGURATION: u8 = 2;
+    /// Descriptor type of a string descriptor.
+    pub const DESCRIPTOR_STRING: u8 = 3;
+
+    /// Create a standard request reading the descriptor of type
+    /// `descriptor_type` and index `descriptor_index` from the device, in a
+    /// `length`-byte buffer.
+    #[must_use]
+    pub const fn get_descriptor(descriptor_type: u8, descriptor_index: u8, length: u16) -> Self {
+        Self {
+            // Device to host, standard request to the device.
+            request_type: 0x80,
+            request: Self::GET_DESCRIPTOR,
+            value: u16::from_be_bytes([descriptor_type, descriptor_index]),
+            index: 0,
+            length,
+        }
+    }
+
+    /// Create a standard request assigning `address` to the device.
+    #[must_use]
+    pub const fn set_address(address: u8) -> Self {
+        Self {
+            request_type: 0x00,
+            request: Self::SET_ADDRESS,
+            value: address as u16,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    /// Create a standard request selecting the configuration
+    /// `configuration` of the device.
+    #[must_use]
+    pub const fn set_configuration(configuration: u8) -> Self {
+        Self {
+            request_type: 0x00,
+            request: Self::SET_CONFIGURATION,
+            value: configuration as u16,
+            index: 0,
+            length: 0,
+        }
+    }
+}
+
+bitflags! {
+    /// Errors of a USB transfer.
+    ///
+    /// These are returned as the error data of the transfer functions.
+    #[repr(transparent)]
+    pub struct UsbTransferResult: u32 {
+        /// The transfer was not executed.
+        const NOT_EXECUTED = 0x0001;
+        /// The endpoint stalled.
+        const STALL = 0x0002;
+        /// A buffer error occurred.
+        const BUFFER = 0x0004;
+        /// The device sent more data than expected.
+        const BABBLE = 0x0008;
+        /// The device answered with a NAK.
+        const NAK = 0x0010;
+        /// A CRC error occurred.
+        const CRC = 0x0020;
+        /// The transfer timed out.
+        const TIMEOUT = 0x0040;
+        /// A bit stuffing error occurred.
+        const BIT_STUFF = 0x0080;
+        /// A system error occurred.
+        const SYSTEM = 0x0100;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+
+    #[test]
+    fn test_device_request() {
+        assert_eq!(mem::size_of::<DeviceRequest>(), 8);
+        let request = DeviceRequest::get_descriptor(DeviceRequest::DESCRIPTOR_STRING, 2, 255);
+        assert_eq!(request.request_type, 0x80);
+        assert_eq!(request.value, 0x0302);
+        assert_eq!(request.length, 255);
+        assert_eq!(DeviceRequest::set_address(5).value, 5);
+    }
+}
@@ -0,0 +1,21 @@
+//! Raw ABI definitions of UEFI tables and protocols.
+//!
+//! The types in this module are plain `#[repr(C)]` structs whose layout
+//! matches the C definitions in the UEFI specification. All fields are
+//! public and all function pointers are `unsafe`, so these types make no
+//! assumptions about how they are used. They can be used to implement
+//! firmware, to provide mock tables and protocols in tests, or to call into
+//! functionality not yet covered by the safe wrappers.
+//!
+//! # Coverage
+//!
+//! The raw layer is partial. It only covers the system table, the boot and
+//! runtime services tables, and the text input and output protocols, and
+//! only the safe wrappers of these are built on top of it. Every other
+//! protocol in [`crate::proto`] still declares its function pointer table
+//! next to its safe wrapper, privately, and has no raw definition here yet.
+//!
+//! Most code should use the safe wrappers instead.
+
+pub mod protocol;
+pub mod table;
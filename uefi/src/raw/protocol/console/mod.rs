@@ -0,0 +1,3 @@
+//! Raw console protocol interfaces.
+
+pub mod text;
@@ -0,0 +1,82 @@
+//! Raw text input and output protocol interfaces.
+
+use crate::{Char16, Event, Status};
+
+/// Raw interface of the Simple Text Input protocol.
+///
+/// Layout compatible with the C type `EFI_SIMPLE_TEXT_INPUT_PROTOCOL`.
+#[repr(C)]
+pub struct SimpleTextInputProtocol {
+    /// Resets the input device.
+    pub reset: unsafe extern "efiapi" fn(this: *mut Self, extended_verification: bool) -> Status,
+    /// Reads the next keystroke from the input device.
+    pub read_key_stroke: unsafe extern "efiapi" fn(this: *mut Self, key: *mut InputKey) -> Status,
+    /// Event signaled when a key is available.
+    pub wait_for_key: Event,
+}
+
+/// A keystroke read from a Simple Text Input device.
+///
+/// Layout compatible with the C type `EFI_INPUT_KEY`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct InputKey {
+    /// The key's scan code, or 0 if printable.
+    pub scan_code: u16,
+    /// The key's UCS-2 character, or 0 if not printable.
+    pub unicode_char: u16,
+}
+
+/// Raw interface of the Simple Text Output protocol.
+///
+/// Layout compatible with the C type `EFI_SIMPLE_TEXT_OUTPUT_PROTOCOL`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct SimpleTextOutputProtocol {
+    /// Resets the output device.
+    pub reset: unsafe extern "efiapi" fn(this: *mut Self, extended_verification: bool) -> Status,
+    /// Writes a null-terminated string to the output device.
+    pub output_string: unsafe extern "efiapi" fn(this: *mut Self, string: *const Char16) -> Status,
+    /// Checks whether the output device can display a null-terminated string.
+    pub test_string: unsafe extern "efiapi" fn(this: *mut Self, string: *const Char16) -> Status,
+    /// Returns the number of columns and rows of a text mode.
+    pub query_mode: unsafe extern "efiapi" fn(
+        this: *mut Self,
+        mode_number: usize,
+        columns: *mut usize,
+        rows: *mut usize,
+    ) -> Status,
+    /// Sets the current text mode.
+    pub set_mode: unsafe extern "efiapi" fn(this: *mut Self, mode_number: usize) -> Status,
+    /// Sets the foreground and background colors.
+    pub set_attribute: unsafe extern "efiapi" fn(this: *mut Self, attribute: usize) -> Status,
+    /// Clears the screen.
+    pub clear_screen: unsafe extern "efiapi" fn(this: *mut Self) -> Status,
+    /// Moves the cursor.
+    pub set_cursor_position:
+        unsafe extern "efiapi" fn(this: *mut Self, column: usize, row: usize) -> Status,
+    /// Shows or hides the cursor.
+    pub enable_cursor: unsafe extern "efiapi" fn(this: *mut Self, visible: bool) -> Status,
+    /// Current state of the output device.
+    pub mode: *mut SimpleTextOutputMode,
+}
+
+/// State of a Simple Text Output device.
+///
+/// Layout compatible with the C type `SIMPLE_TEXT_OUTPUT_MODE`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct SimpleTextOutputMode {
+    /// The number of modes supported by the device.
+    pub max_mode: i32,
+    /// The current output mode, or -1 if no valid mode is configured.
+    pub mode: i32,
+    /// The current character output attribute.
+    pub attribute: i32,
+    /// The cursor's column.
+    pub cursor_column: i32,
+    /// The cursor's row.
+    pub cursor_row: i32,
+    /// Whether the cursor is currently visible or not.
+    pub cursor_visible: bool,
+}
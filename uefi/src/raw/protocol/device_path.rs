@@ -0,0 +1,17 @@
+//! Raw device path protocol.
+
+/// Raw device path node header.
+///
+/// Layout compatible with the C type `EFI_DEVICE_PATH_PROTOCOL`. A device
+/// path is a sequence of nodes, each starting with this header and ending
+/// with an end-of-path node.
+#[derive(Debug)]
+#[repr(C)]
+pub struct DevicePathProtocol {
+    /// Type of the node.
+    pub major_type: u8,
+    /// Sub-type of the node.
+    pub sub_type: u8,
+    /// Size in bytes of the node, including this header, in little endian.
+    pub length: [u8; 2],
+}
@@ -0,0 +1,4 @@
+//! Raw protocol interfaces.
+
+pub mod console;
+pub mod device_path;
@@ -0,0 +1,130 @@
+//! Raw table definitions.
+
+pub mod boot;
+pub mod runtime;
+
+use self::boot::BootServices;
+use self::runtime::RuntimeServices;
+use crate::raw::protocol::console::text::{SimpleTextInputProtocol, SimpleTextOutputProtocol};
+use crate::{Char16, Guid, Handle};
+use core::ffi::c_void;
+
+/// All standard UEFI tables begin with a common header.
+///
+/// Layout compatible with the C type `EFI_TABLE_HEADER`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct Header {
+    /// Unique identifier for this table.
+    pub signature: u64,
+    /// Revision of the spec this table conforms to.
+    pub revision: Revision,
+    /// The size in bytes of the entire table.
+    pub size: u32,
+    /// 32-bit CRC-32-Castagnoli of the entire table,
+    /// calculated with this field set to 0.
+    pub crc: u32,
+    /// Reserved field that must be set to 0.
+    pub reserved: u32,
+}
+
+/// A revision of the UEFI specification.
+///
+/// The major revision number is incremented on major, API-incompatible changes.
+///
+/// The minor revision number is incremented on minor changes,
+/// it is stored as a two-digit binary-coded decimal.
+///
+/// # Display format
+///
+/// For major revision 2 and later, if the lower minor digit is zero,
+/// the revision is formatted as "major.minor-upper". Otherwise it's
+/// formatted as "major.minor-upper.minor-lower". This format is
+/// described in the "EFI System Table" section of the UEFI
+/// Specification.
+///
+/// Prior to major version 2, the revision is always formatted as
+/// "major.minor", with minor left-padded with zero if minor-upper is
+/// zero.
+///
+/// Examples:
+///
+/// ```
+/// use uefi::table::Revision;
+/// assert_eq!(Revision::EFI_1_02.to_string(), "1.02");
+/// assert_eq!(Revision::EFI_1_10.to_string(), "1.10");
+/// assert_eq!(Revision::EFI_2_00.to_string(), "2.0");
+/// assert_eq!(Revision::EFI_2_30.to_string(), "2.3");
+/// assert_eq!(Revision::EFI_2_31.to_string(), "2.3.1");
+/// assert_eq!(Revision::EFI_2_100.to_string(), "2.10");
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[repr(transparent)]
+pub struct Revision(pub u32);
+
+/// Raw UEFI System Table.
+///
+/// Layout compatible with the C type `EFI_SYSTEM_TABLE`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct SystemTable {
+    /// Table header.
+    pub header: Header,
+    /// Null-terminated string representing the firmware's vendor.
+    pub firmware_vendor: *const Char16,
+    /// Vendor-specific firmware revision.
+    pub firmware_revision: u32,
+    /// Handle of the console input device.
+    pub stdin_handle: Option<Handle>,
+    /// Console input protocol.
+    pub stdin: *mut SimpleTextInputProtocol,
+    /// Handle of the console output device.
+    pub stdout_handle: Option<Handle>,
+    /// Console output protocol.
+    pub stdout: *mut SimpleTextOutputProtocol,
+    /// Handle of the standard error device.
+    pub stderr_handle: Option<Handle>,
+    /// Standard error protocol.
+    pub stderr: *mut SimpleTextOutputProtocol,
+    /// Runtime services table.
+    pub runtime_services: *mut RuntimeServices,
+    /// Boot services table.
+    pub boot_services: *mut BootServices,
+    /// Number of entries in the configuration table.
+    pub number_of_configuration_table_entries: usize,
+    /// Pointer to the beginning of the configuration table.
+    pub configuration_table: *mut ConfigurationTable,
+}
+
+/// An entry of the configuration table.
+///
+/// Layout compatible with the C type `EFI_CONFIGURATION_TABLE`.
+#[derive(Debug)]
+#[repr(C)]
+pub struct ConfigurationTable {
+    /// The GUID identifying the table.
+    pub vendor_guid: Guid,
+    /// The address of the table.
+    pub vendor_table: *mut c_void,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw::protocol::console::text::SimpleTextOutputMode;
+    use core::mem::size_of;
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_layout() {
+        assert_eq!(size_of::<Header>(), 24);
+        assert_eq!(size_of::<SystemTable>(), 120);
+        assert_eq!(size_of::<ConfigurationTable>(), 24);
+        assert_eq!(size_of::<SimpleTextOutputMode>(), 24);
+        assert_eq!(size_of::<BootServices>(), 376);
+        assert_eq!(size_of::<RuntimeServices>(), 136);
+        assert_eq!(size_of::<runtime::Time>(), 16);
+        assert_eq!(size_of::<runtime::TimeCapabilities>(), 12);
+        assert_eq!(size_of::<boot::MemoryDescriptor>(), 40);
+    }
+}
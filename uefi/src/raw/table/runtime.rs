@@ -0,0 +1,137 @@
+//! Raw runtime services table.
+
+use super::boot::MemoryDescriptor;
+use super::Header;
+use crate::{Char16, Guid, Status};
+use core::ffi::c_void;
+
+/// Raw runtime services table.
+///
+/// Layout compatible with the C type `EFI_RUNTIME_SERVICES`. The capsule
+/// services are not bound by the safe wrappers, and are left as `usize`
+/// placeholders.
+#[derive(Debug)]
+#[repr(C)]
+pub struct RuntimeServices {
+    /// Table header.
+    pub header: Header,
+
+    /// Returns the current time and the capabilities of the real time clock.
+    pub get_time:
+        unsafe extern "efiapi" fn(time: *mut Time, capabilities: *mut TimeCapabilities) -> Status,
+    /// Sets the current time.
+    pub set_time: unsafe extern "efiapi" fn(time: *const Time) -> Status,
+    /// Returns the state of the wakeup alarm.
+    pub get_wakeup_time: unsafe extern "efiapi" fn(
+        enabled: *mut bool,
+        pending: *mut bool,
+        time: *mut Time,
+    ) -> Status,
+    /// Sets or disables the wakeup alarm.
+    pub set_wakeup_time: unsafe extern "efiapi" fn(enable: bool, time: *const Time) -> Status,
+
+    /// Switches the runtime services to virtual addressing.
+    pub set_virtual_address_map: unsafe extern "efiapi" fn(
+        map_size: usize,
+        desc_size: usize,
+        desc_version: u32,
+        virtual_map: *mut MemoryDescriptor,
+    ) -> Status,
+    /// Converts a pointer from a physical to a virtual address.
+    pub convert_pointer:
+        unsafe extern "efiapi" fn(debug_disposition: usize, address: *mut *const c_void) -> Status,
+
+    /// Returns the value of a variable.
+    pub get_variable: unsafe extern "efiapi" fn(
+        variable_name: *const Char16,
+        vendor_guid: *const Guid,
+        attributes: *mut u32,
+        data_size: *mut usize,
+        data: *mut u8,
+    ) -> Status,
+    /// Enumerates the names of the variables.
+    pub get_next_variable_name: unsafe extern "efiapi" fn(
+        variable_name_size: *mut usize,
+        variable_name: *mut u16,
+        vendor_guid: *mut Guid,
+    ) -> Status,
+    /// Sets the value of a variable.
+    pub set_variable: unsafe extern "efiapi" fn(
+        variable_name: *const Char16,
+        vendor_guid: *const Guid,
+        attributes: u32,
+        data_size: usize,
+        data: *const u8,
+    ) -> Status,
+
+    /// Returns the high 32 bits of the platform's monotonic counter.
+    pub get_next_high_monotonic_count: usize,
+    /// Resets the platform.
+    pub reset_system: unsafe extern "efiapi" fn(
+        reset_type: u32,
+        status: Status,
+        data_size: usize,
+        data: *const u8,
+    ) -> !,
+
+    /// Passes capsules to the firmware. Added in UEFI 2.0.
+    pub update_capsule: usize,
+    /// Returns whether capsules can be passed to the firmware. Added in
+    /// UEFI 2.0.
+    pub query_capsule_capabilities: usize,
+
+    /// Returns information about the variable storage. Added in UEFI 2.0.
+    pub query_variable_info: unsafe extern "efiapi" fn(
+        attributes: u32,
+        maximum_variable_storage_size: *mut u64,
+        remaining_variable_storage_size: *mut u64,
+        maximum_variable_size: *mut u64,
+    ) -> Status,
+}
+
+/// Raw date and time.
+///
+/// Layout compatible with the C type `EFI_TIME`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[repr(C)]
+pub struct Time {
+    /// Year in the range `1900..=9999`.
+    pub year: u16,
+    /// Month in the range `1..=12`.
+    pub month: u8,
+    /// Day in the range `1..=31`.
+    pub day: u8,
+    /// Hour in the range `0..=23`.
+    pub hour: u8,
+    /// Minute in the range `0..=59`.
+    pub minute: u8,
+    /// Second in the range `0..=59`.
+    pub second: u8,
+    /// Padding.
+    pub pad1: u8,
+    /// Nanosecond in the range `0..=999_999_999`.
+    pub nanosecond: u32,
+    /// Offset in minutes from UTC in the range `-1440..=1440`, or `0x07ff`
+    /// for local time.
+    pub time_zone: i16,
+    /// Daylight savings time information.
+    pub daylight: u8,
+    /// Padding.
+    pub pad2: u8,
+}
+
+/// Real time clock capabilities
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[repr(C)]
+pub struct TimeCapabilities {
+    /// Reporting resolution of the clock in counts per second. 1 for a normal
+    /// PC-AT CMOS RTC device, which reports the time with 1-second resolution.
+    pub resolution: u32,
+
+    /// Timekeeping accuracy in units of 1e-6 parts per million.
+    pub accuracy: u32,
+
+    /// Whether a time set operation clears the device's time below the
+    /// "resolution" reporting level. False for normal PC-AT CMOS RTC devices.
+    pub sets_to_zero: bool,
+}
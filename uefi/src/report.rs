@@ -0,0 +1,276 @@
+//! Machine-readable output for diagnostic helpers.
+//!
+//! The diagnostic helpers of the crate (`BootServices::dump_handle`,
+//! `BootServices::dump_memory_map`, `tcg::summary::LogSummary`, and the
+//! [`perf`](crate::perf) spans) write their output as a series of
+//! [`Record`]s. How a record is rendered depends on the crate-wide
+//! [`Format`], which can be changed with [`set_format`]:
+//!
+//! - [`Format::Human`] (the default) renders `name: key=value, key=value`.
+//! - [`Format::KeyValue`] renders `record=name key=value key="a b"`.
+//! - [`Format::Json`] renders `{"record":"name","key":"value"}`.
+//!
+//! Each record is written as a single line, so the output can be
+//! captured over a serial port and parsed line by line by CI or fleet
+//! tooling.
+//!
+//! # Example
+//!
+//! ```
+//! use core::fmt::Write;
+//! use uefi::report::{Format, Record};
+//!
+//! fn dump<W: Write>(w: &mut W) -> core::fmt::Result {
+//!     Record::with_format(w, Format::KeyValue, "memory")?
+//!         .field("type", "CONVENTIONAL")?
+//!         .field("pages", 16)?
+//!         .finish()
+//! }
+//! ```
+
+use core::fmt::{self, Display, Write};
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Output format used by [`Record`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+#[repr(u8)]
+pub enum Format {
+    /// Human-readable text.
+    #[default]
+    Human = 0,
+
+    /// Line-oriented `key=value` pairs separated by spaces. Values
+    /// containing whitespace, quotes, or `=` are quoted.
+    KeyValue = 1,
+
+    /// One JSON object per line. All values are emitted as strings.
+    Json = 2,
+}
+
+impl Format {
+    const fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::KeyValue,
+            2 => Self::Json,
+            _ => Self::Human,
+        }
+    }
+}
+
+static FORMAT: AtomicU8 = AtomicU8::new(Format::Human as u8);
+
+/// Set the crate-wide output format used by [`Record::new`].
+pub fn set_format(format: Format) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+/// Get the crate-wide output format used by [`Record::new`].
+#[must_use]
+pub fn format() -> Format {
+    Format::from_u8(FORMAT.load(Ordering::Relaxed))
+}
+
+/// Writer for a single line of output.
+///
+/// Create a record with [`Record::new`], add fields with
+/// [`Record::field`], and terminate the line with [`Record::finish`].
+#[derive(Debug)]
+pub struct Record<'w, W: Write> {
+    writer: &'w mut W,
+    format: Format,
+    first_field: bool,
+}
+
+impl<'w, W: Write> Record<'w, W> {
+    /// Start a new record named `name` using the crate-wide [`format`].
+    pub fn new(writer: &'w mut W, name: &str) -> Result<Self, fmt::Error> {
+        Self::with_format(writer, format(), name)
+    }
+
+    /// Start a new record named `name` using an explicit `format`.
+    pub fn with_format(writer: &'w mut W, format: Format, name: &str) -> Result<Self, fmt::Error> {
+        match format {
+            Format::Human => write!(writer, "{name}:")?,
+            Format::KeyValue => {
+                writer.write_str("record=")?;
+                write_kv_value(writer, &name)?;
+            }
+            Format::Json => {
+                writer.write_str("{\"record\":")?;
+                write_json_string(writer, &name)?;
+            }
+        }
+        Ok(Self {
+            writer,
+            format,
+            first_field: true,
+        })
+    }
+
+    /// Add a `key` and `value` to the record.
+    ///
+    /// Keys are written as-is and should be plain identifiers. Values
+    /// are escaped as needed by the output format.
+    pub fn field(mut self, key: &str, value: impl Display) -> Result<Self, fmt::Error> {
+        match self.format {
+            Format::Human => {
+                let sep = if self.first_field { " " } else { ", " };
+                write!(self.writer, "{sep}{key}={value}")?;
+            }
+            Format::KeyValue => {
+                write!(self.writer, " {key}=")?;
+                write_kv_value(self.writer, &value)?;
+            }
+            Format::Json => {
+                self.writer.write_char(',')?;
+                write_json_string(self.writer, &key)?;
+                self.writer.write_char(':')?;
+                write_json_string(self.writer, &value)?;
+            }
+        }
+        self.first_field = false;
+        Ok(self)
+    }
+
+    /// Terminate the record with a newline.
+    pub fn finish(self) -> fmt::Result {
+        if self.format == Format::Json {
+            self.writer.write_char('}')?;
+        }
+        self.writer.write_char('\n')
+    }
+}
+
+/// Check whether a key=value value must be quoted.
+fn kv_needs_quotes(value: &dyn Display) -> bool {
+    struct Checker {
+        empty: bool,
+        needs_quotes: bool,
+    }
+
+    impl Write for Checker {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            if !s.is_empty() {
+                self.empty = false;
+            }
+            if s.chars()
+                .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '"' | '=' | '\\'))
+            {
+                self.needs_quotes = true;
+            }
+            Ok(())
+        }
+    }
+
+    let mut checker = Checker {
+        empty: true,
+        needs_quotes: false,
+    };
+    // The checker never fails, and a `Display` impl that fails on its
+    // own will fail again when actually written.
+    let _ = write!(checker, "{value}");
+    checker.empty || checker.needs_quotes
+}
+
+/// Writer adapter that escapes quotes, backslashes, and control
+/// characters the same way for both key=value and JSON output.
+struct Escaper<'a, W: Write>(&'a mut W);
+
+impl<'a, W: Write> Write for Escaper<'a, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                '\t' => self.0.write_str("\\t")?,
+                c if c.is_control() => write!(self.0, "\\u{:04x}", u32::from(c))?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_kv_value<W: Write>(writer: &mut W, value: &dyn Display) -> fmt::Result {
+    if kv_needs_quotes(value) {
+        writer.write_char('"')?;
+        write!(Escaper(writer), "{value}")?;
+        writer.write_char('"')
+    } else {
+        write!(writer, "{value}")
+    }
+}
+
+fn write_json_string<W: Write>(writer: &mut W, value: &dyn Display) -> fmt::Result {
+    writer.write_char('"')?;
+    write!(Escaper(writer), "{value}")?;
+    writer.write_char('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::String;
+
+    fn render(format: Format) -> String {
+        let mut s = String::new();
+        Record::with_format(&mut s, format, "mem")
+            .unwrap()
+            .field("type", "LOADER_DATA")
+            .unwrap()
+            .field("pages", 4)
+            .unwrap()
+            .field("note", "a \"b\"=c")
+            .unwrap()
+            .field("empty", "")
+            .unwrap()
+            .finish()
+            .unwrap();
+        s
+    }
+
+    #[test]
+    fn test_human() {
+        assert_eq!(
+            render(Format::Human),
+            "mem: type=LOADER_DATA, pages=4, note=a \"b\"=c, empty=\n"
+        );
+    }
+
+    #[test]
+    fn test_key_value() {
+        assert_eq!(
+            render(Format::KeyValue),
+            "record=mem type=LOADER_DATA pages=4 note=\"a \\\"b\\\"=c\" empty=\"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        assert_eq!(
+            render(Format::Json),
+            "{\"record\":\"mem\",\"type\":\"LOADER_DATA\",\"pages\":\"4\",\"note\":\"a \\\"b\\\"=c\",\"empty\":\"\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_control_chars() {
+        let mut s = String::new();
+        Record::with_format(&mut s, Format::Json, "x")
+            .unwrap()
+            .field("v", "a\nb\u{1}")
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(s, "{\"record\":\"x\",\"v\":\"a\\nb\\u0001\"}\n");
+    }
+
+    #[test]
+    fn test_global_format() {
+        assert_eq!(Format::from_u8(Format::Json as u8), Format::Json);
+        assert_eq!(Format::from_u8(Format::KeyValue as u8), Format::KeyValue);
+        assert_eq!(Format::from_u8(0xff), Format::Human);
+    }
+}
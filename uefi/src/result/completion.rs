@@ -0,0 +1,92 @@
+use super::{Error, Result, Status};
+use log::warn;
+
+/// Value returned by an operation that succeeded, possibly with a warning.
+///
+/// UEFI functions may complete with a warning status, such as
+/// [`Status::WARN_UNKNOWN_GLYPH`] when a string was printed but some of its
+/// characters were skipped. Most of this crate converts warnings into
+/// errors, since they generally indicate an abnormal situation. Functions
+/// that return a `Completion` instead let the caller decide:
+///
+/// - [`value`] ignores the warning,
+/// - [`log_warning`] logs the warning and returns the value,
+/// - [`into_result`] turns the warning into an error.
+///
+/// [`value`]: Self::value
+/// [`log_warning`]: Self::log_warning
+/// [`into_result`]: Self::into_result
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[must_use]
+pub struct Completion<T> {
+    status: Status,
+    value: T,
+}
+
+impl<T> Completion<T> {
+    /// Create a `Completion` from a success or warning status.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `status` is an error.
+    pub fn new(status: Status, value: T) -> Self {
+        assert!(!status.is_error(), "{status} is not a warning");
+        Self { status, value }
+    }
+
+    /// Get the status of the operation, which is either [`Status::SUCCESS`]
+    /// or a warning.
+    pub const fn status(&self) -> Status {
+        self.status
+    }
+
+    /// Get the warning returned by the operation, if any.
+    #[must_use]
+    pub fn warning(&self) -> Option<Status> {
+        self.status.is_warning().then_some(self.status)
+    }
+
+    /// Get the value, ignoring the warning.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn value(self) -> T {
+        self.value
+    }
+
+    /// Split the completion into its status and value.
+    #[allow(clippy::missing_const_for_fn)]
+    pub fn split(self) -> (Status, T) {
+        (self.status, self.value)
+    }
+
+    /// Transform the value with `op`, keeping the status.
+    pub fn map<U>(self, op: impl FnOnce(T) -> U) -> Completion<U> {
+        Completion {
+            status: self.status,
+            value: op(self.value),
+        }
+    }
+
+    /// Log the warning, if any, and get the value.
+    pub fn log_warning(self) -> T {
+        if let Some(warning) = self.warning() {
+            warn!("UEFI warning: {warning}");
+        }
+        self.value
+    }
+
+    /// Get the value, or an error if the operation returned a warning. The
+    /// value is discarded in that case.
+    pub fn into_result(self) -> Result<T> {
+        if self.status.is_warning() {
+            Err(Error::new(self.status, ()))
+        } else {
+            Ok(self.value)
+        }
+    }
+}
+
+impl<T> From<T> for Completion<T> {
+    fn from(value: T) -> Self {
+        Self::new(Status::SUCCESS, value)
+    }
+}
@@ -1,18 +1,40 @@
 use super::Status;
 use core::fmt::{Debug, Display};
+use core::mem;
 
 /// Errors emitted from UEFI entry point must propagate erronerous UEFI statuses,
 /// and may optionally propagate additional entry point-specific data.
-#[derive(Debug, PartialEq, Eq)]
+///
+/// In addition to the status and data, an error may carry a short
+/// static description of the operation that failed (for example
+/// `"BootServices::locate_handle"`). The context is set with
+/// [`Error::with_context`] or [`ResultExt::with_context`] and is
+/// included in the [`Display`] output, but is ignored when comparing
+/// errors.
+///
+/// [`ResultExt::with_context`]: super::ResultExt::with_context
+#[derive(Debug)]
 pub struct Error<Data: Debug = ()> {
     status: Status,
     data: Data,
+    context: Option<&'static str>,
 }
 
 impl<Data: Debug> Error<Data> {
     /// Create an `Error`.
     pub const fn new(status: Status, data: Data) -> Self {
-        Self { status, data }
+        Self {
+            status,
+            data,
+            context: None,
+        }
+    }
+
+    /// Attach a description of the operation that failed to this error.
+    #[must_use]
+    pub const fn with_context(mut self, context: &'static str) -> Self {
+        self.context = Some(context);
+        self
     }
 
     /// Get error `Status`.
@@ -25,26 +47,138 @@ impl<Data: Debug> Error<Data> {
         &self.data
     }
 
+    /// Get the description of the operation that failed, if one was
+    /// attached with [`with_context`].
+    ///
+    /// [`with_context`]: Self::with_context
+    #[must_use]
+    pub const fn context(&self) -> Option<&'static str> {
+        self.context
+    }
+
     /// Split this error into its inner status and error data
     #[allow(clippy::missing_const_for_fn)]
     pub fn split(self) -> (Status, Data) {
         (self.status, self.data)
     }
+
+    /// Transform the error data with `op`, keeping the status and
+    /// context.
+    pub fn map_data<NewData: Debug>(self, op: impl FnOnce(Data) -> NewData) -> Error<NewData> {
+        Error {
+            status: self.status,
+            data: op(self.data),
+            context: self.context,
+        }
+    }
+
+    /// Discard the error data, keeping the status and context.
+    pub fn into_err_without_payload(self) -> Error<()> {
+        self.map_data(|_| ())
+    }
 }
 
+impl Error<Option<usize>> {
+    /// Get the buffer size required by the failed call.
+    ///
+    /// Functions that fill a caller-provided buffer report the required
+    /// size in the error data when they fail with
+    /// [`Status::BUFFER_TOO_SMALL`]. This returns that size, or `None`
+    /// if the error has a different status.
+    #[must_use]
+    pub fn required_size(&self) -> Option<usize> {
+        if self.status == Status::BUFFER_TOO_SMALL {
+            self.data
+        } else {
+            None
+        }
+    }
+}
+
+// The context only describes where the error came from, so errors with the
+// same status and data compare equal.
+
+impl<Data: Debug + PartialEq> PartialEq for Error<Data> {
+    fn eq(&self, other: &Self) -> bool {
+        self.status == other.status && self.data == other.data
+    }
+}
+
+impl<Data: Debug + Eq> Eq for Error<Data> {}
+
 // Errors without error data can be autogenerated from statuses
 
 impl From<Status> for Error<()> {
     fn from(status: Status) -> Self {
-        Self { status, data: () }
+        Self::new(status, ())
     }
 }
 
-impl<Data: Debug + Display> Display for Error<Data> {
+impl<Data: Debug> From<Error<Data>> for Status {
+    fn from(err: Error<Data>) -> Self {
+        err.status
+    }
+}
+
+impl<Data: Debug> Display for Error<Data> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "UEFI Error {}: {}", self.status(), self.data())
+        write!(f, "UEFI Error {}", self.status())?;
+        if let Some(context) = self.context {
+            write!(f, " in {context}")?;
+        }
+        // Don't print data for errors without a payload, e.g. `Error<()>`.
+        if mem::size_of::<Data>() != 0 {
+            write!(f, ": {:?}", self.data())?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(feature = "unstable")]
-impl<Data: Debug + Display> core::error::Error for Error<Data> {}
+impl<Data: Debug> core::error::Error for Error<Data> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_error_display() {
+        let err = Error::from(Status::NOT_FOUND);
+        assert_eq!(err.to_string(), "UEFI Error NOT_FOUND");
+
+        let err = err.with_context("locate_handle");
+        assert_eq!(err.context(), Some("locate_handle"));
+        assert_eq!(err.to_string(), "UEFI Error NOT_FOUND in locate_handle");
+
+        let err = Error::new(Status::BUFFER_TOO_SMALL, Some(32));
+        assert_eq!(err.to_string(), "UEFI Error BUFFER_TOO_SMALL: Some(32)");
+    }
+
+    #[test]
+    fn test_error_payload() {
+        let err = Error::new(Status::BUFFER_TOO_SMALL, Some(32)).with_context("get_info");
+        assert_eq!(err.required_size(), Some(32));
+
+        let err = err.into_err_without_payload();
+        assert_eq!(err.status(), Status::BUFFER_TOO_SMALL);
+        assert_eq!(err.context(), Some("get_info"));
+        assert_eq!(Status::from(err), Status::BUFFER_TOO_SMALL);
+
+        let err = Error::new(Status::DEVICE_ERROR, Some(32));
+        assert_eq!(err.required_size(), None);
+    }
+
+    #[test]
+    fn test_error_eq_ignores_context() {
+        let err = Error::from(Status::NOT_FOUND);
+        assert_eq!(
+            err.with_context("locate_handle"),
+            Error::from(Status::NOT_FOUND)
+        );
+        assert_ne!(
+            Error::new(Status::NOT_FOUND, 1),
+            Error::new(Status::NOT_FOUND, 2)
+        );
+    }
+}
@@ -9,6 +9,10 @@ pub use self::error::Error;
 mod status;
 pub use self::status::Status;
 
+/// Successful results that may carry a warning
+mod completion;
+pub use self::completion::Completion;
+
 /// Return type of most UEFI functions. Both success and error payloads are optional.
 ///
 /// Almost all UEFI operations provide a status code as an output which
@@ -18,7 +22,8 @@ pub use self::status::Status;
 /// which may carry optional inner `ErrData`.
 ///
 /// Warnings are treated as errors by default because they generally indicate
-/// an abnormal situation.
+/// an abnormal situation. [`ResultExt::allow_warnings`] and [`Completion`]
+/// can be used to handle them without treating them as failures.
 ///
 /// Some convenience methods are provided by the [`ResultExt`] trait.
 pub type Result<Output = (), ErrData = ()> = core::result::Result<Output, Error<ErrData>>;
@@ -31,6 +36,10 @@ pub trait ResultExt<Output, ErrData: Debug> {
     /// Transform the ErrData value to ()
     fn discard_errdata(self) -> Result<Output>;
 
+    /// Attach a description of the failed operation to the error, if
+    /// any. See [`Error::with_context`].
+    fn with_context(self, context: &'static str) -> Result<Output, ErrData>;
+
     /// Calls `op` if the result contains a warning, otherwise returns
     /// the result unchanged.
     ///
@@ -61,6 +70,26 @@ pub trait ResultExt<Output, ErrData: Debug> {
     fn handle_warning<O>(self, op: O) -> Result<Output, ErrData>
     where
         O: FnOnce(Error<ErrData>) -> Result<Output, ErrData>;
+
+    /// Converts a warning into a successful [`Completion`] carrying the
+    /// warning and the default output, so that only errors are treated as
+    /// failures. The error data of the warning is discarded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use uefi::{Result, ResultExt, Status};
+    ///
+    /// # fn x() -> uefi::Result {
+    /// # let some_result = Result::from(Status::WARN_UNKNOWN_GLYPH);
+    /// // Log warnings instead of failing.
+    /// some_result.allow_warnings()?.log_warning();
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn allow_warnings(self) -> Result<Completion<Output>, ErrData>
+    where
+        Output: Default;
 }
 
 impl<Output, ErrData: Debug> ResultExt<Output, ErrData> for Result<Output, ErrData> {
@@ -74,10 +103,14 @@ impl<Output, ErrData: Debug> ResultExt<Output, ErrData> for Result<Output, ErrDa
     fn discard_errdata(self) -> Result<Output> {
         match self {
             Ok(o) => Ok(o),
-            Err(e) => Err(e.status().into()),
+            Err(e) => Err(e.into_err_without_payload()),
         }
     }
 
+    fn with_context(self, context: &'static str) -> Result<Output, ErrData> {
+        self.map_err(|e| e.with_context(context))
+    }
+
     fn handle_warning<O>(self, op: O) -> Result<Output, ErrData>
     where
         O: FnOnce(Error<ErrData>) -> Result<Output, ErrData>,
@@ -93,4 +126,17 @@ impl<Output, ErrData: Debug> ResultExt<Output, ErrData> for Result<Output, ErrDa
             }
         }
     }
+
+    fn allow_warnings(self) -> Result<Completion<Output>, ErrData>
+    where
+        Output: Default,
+    {
+        match self {
+            Ok(output) => Ok(Completion::from(output)),
+            Err(err) if err.status().is_warning() => {
+                Ok(Completion::new(err.status(), Output::default()))
+            }
+            Err(err) => Err(err),
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use super::{Error, Result};
+use super::{Completion, Error, Result};
 use core::fmt::Debug;
 
 /// Bit indicating that an UEFI status code is an error
@@ -18,6 +18,10 @@ newtype_enum! {
 /// - [`Status::into_with`]
 /// - [`Status::into_with_val`]
 /// - [`Status::into_with_err`]
+///
+/// These treat warnings as errors. To handle warnings without treating them
+/// as failures, use [`Status::into_completion`] or
+/// [`Status::into_completion_with_val`].
 #[must_use]
 pub enum Status: usize => {
     /// The operation completed successfully.
@@ -109,6 +113,216 @@ pub enum Status: usize => {
     HTTP_ERROR              = ERROR_BIT | 35,
 }}
 
+/// Name and description of the status codes defined by the UEFI
+/// specification, in numerical order of warnings, then errors.
+const KNOWN_STATUSES: &[(Status, &str, &str)] = &[
+    (
+        Status::SUCCESS,
+        "EFI_SUCCESS",
+        "The operation completed successfully.",
+    ),
+    (
+        Status::WARN_UNKNOWN_GLYPH,
+        "EFI_WARN_UNKNOWN_GLYPH",
+        "The string contained characters that could not be rendered and were skipped.",
+    ),
+    (
+        Status::WARN_DELETE_FAILURE,
+        "EFI_WARN_DELETE_FAILURE",
+        "The handle was closed, but the file was not deleted.",
+    ),
+    (
+        Status::WARN_WRITE_FAILURE,
+        "EFI_WARN_WRITE_FAILURE",
+        "The handle was closed, but the data to the file was not flushed properly.",
+    ),
+    (
+        Status::WARN_BUFFER_TOO_SMALL,
+        "EFI_WARN_BUFFER_TOO_SMALL",
+        "The resulting buffer was too small, and the data was truncated.",
+    ),
+    (
+        Status::WARN_STALE_DATA,
+        "EFI_WARN_STALE_DATA",
+        "The data has not been updated within the timeframe set by local policy.",
+    ),
+    (
+        Status::WARN_FILE_SYSTEM,
+        "EFI_WARN_FILE_SYSTEM",
+        "The resulting buffer contains a UEFI-compliant file system.",
+    ),
+    (
+        Status::WARN_RESET_REQUIRED,
+        "EFI_WARN_RESET_REQUIRED",
+        "The operation will be processed across a system reset.",
+    ),
+    (
+        Status::LOAD_ERROR,
+        "EFI_LOAD_ERROR",
+        "The image failed to load.",
+    ),
+    (
+        Status::INVALID_PARAMETER,
+        "EFI_INVALID_PARAMETER",
+        "A parameter was incorrect.",
+    ),
+    (
+        Status::UNSUPPORTED,
+        "EFI_UNSUPPORTED",
+        "The operation is not supported.",
+    ),
+    (
+        Status::BAD_BUFFER_SIZE,
+        "EFI_BAD_BUFFER_SIZE",
+        "The buffer was not the proper size for the request.",
+    ),
+    (
+        Status::BUFFER_TOO_SMALL,
+        "EFI_BUFFER_TOO_SMALL",
+        "The buffer is not large enough to hold the requested data. The required buffer size is returned in the appropriate parameter.",
+    ),
+    (
+        Status::NOT_READY,
+        "EFI_NOT_READY",
+        "There is no data pending upon return.",
+    ),
+    (
+        Status::DEVICE_ERROR,
+        "EFI_DEVICE_ERROR",
+        "The physical device reported an error while attempting the operation.",
+    ),
+    (
+        Status::WRITE_PROTECTED,
+        "EFI_WRITE_PROTECTED",
+        "The device cannot be written to.",
+    ),
+    (
+        Status::OUT_OF_RESOURCES,
+        "EFI_OUT_OF_RESOURCES",
+        "A resource has run out.",
+    ),
+    (
+        Status::VOLUME_CORRUPTED,
+        "EFI_VOLUME_CORRUPTED",
+        "An inconsistency was detected on the file system.",
+    ),
+    (
+        Status::VOLUME_FULL,
+        "EFI_VOLUME_FULL",
+        "There is no more space on the file system.",
+    ),
+    (
+        Status::NO_MEDIA,
+        "EFI_NO_MEDIA",
+        "The device does not contain any medium to perform the operation.",
+    ),
+    (
+        Status::MEDIA_CHANGED,
+        "EFI_MEDIA_CHANGED",
+        "The medium in the device has changed since the last access.",
+    ),
+    (
+        Status::NOT_FOUND,
+        "EFI_NOT_FOUND",
+        "The item was not found.",
+    ),
+    (
+        Status::ACCESS_DENIED,
+        "EFI_ACCESS_DENIED",
+        "Access was denied.",
+    ),
+    (
+        Status::NO_RESPONSE,
+        "EFI_NO_RESPONSE",
+        "The server was not found or did not respond to the request.",
+    ),
+    (
+        Status::NO_MAPPING,
+        "EFI_NO_MAPPING",
+        "A mapping to a device does not exist.",
+    ),
+    (
+        Status::TIMEOUT,
+        "EFI_TIMEOUT",
+        "The timeout time expired.",
+    ),
+    (
+        Status::NOT_STARTED,
+        "EFI_NOT_STARTED",
+        "The protocol has not been started.",
+    ),
+    (
+        Status::ALREADY_STARTED,
+        "EFI_ALREADY_STARTED",
+        "The protocol has already been started.",
+    ),
+    (
+        Status::ABORTED,
+        "EFI_ABORTED",
+        "The operation was aborted.",
+    ),
+    (
+        Status::ICMP_ERROR,
+        "EFI_ICMP_ERROR",
+        "An ICMP error occurred during the network operation.",
+    ),
+    (
+        Status::TFTP_ERROR,
+        "EFI_TFTP_ERROR",
+        "A TFTP error occurred during the network operation.",
+    ),
+    (
+        Status::PROTOCOL_ERROR,
+        "EFI_PROTOCOL_ERROR",
+        "A protocol error occurred during the network operation.",
+    ),
+    (
+        Status::INCOMPATIBLE_VERSION,
+        "EFI_INCOMPATIBLE_VERSION",
+        "The function encountered an internal version that was incompatible with a version requested by the caller.",
+    ),
+    (
+        Status::SECURITY_VIOLATION,
+        "EFI_SECURITY_VIOLATION",
+        "The function was not performed due to a security violation.",
+    ),
+    (
+        Status::CRC_ERROR,
+        "EFI_CRC_ERROR",
+        "A CRC error was detected.",
+    ),
+    (
+        Status::END_OF_MEDIA,
+        "EFI_END_OF_MEDIA",
+        "Beginning or end of media was reached.",
+    ),
+    (
+        Status::END_OF_FILE,
+        "EFI_END_OF_FILE",
+        "The end of the file was reached.",
+    ),
+    (
+        Status::INVALID_LANGUAGE,
+        "EFI_INVALID_LANGUAGE",
+        "The language specified was invalid.",
+    ),
+    (
+        Status::COMPROMISED_DATA,
+        "EFI_COMPROMISED_DATA",
+        "The security status of the data is unknown or compromised and the data must be updated or replaced to restore a valid security status.",
+    ),
+    (
+        Status::IP_ADDRESS_CONFLICT,
+        "EFI_IP_ADDRESS_CONFLICT",
+        "There is an address conflict in address allocation.",
+    ),
+    (
+        Status::HTTP_ERROR,
+        "EFI_HTTP_ERROR",
+        "A HTTP error occurred during the network operation.",
+    ),
+];
+
 impl Status {
     /// Returns true if status code indicates success.
     #[inline]
@@ -131,6 +345,54 @@ impl Status {
         self.0 & ERROR_BIT != 0
     }
 
+    /// Returns the name of the status code in the UEFI specification, such
+    /// as `"EFI_WARN_UNKNOWN_GLYPH"`, or `None` if the code is not defined
+    /// by the specification.
+    #[must_use]
+    pub fn name(self) -> Option<&'static str> {
+        Self::lookup(self).map(|&(_, name, _)| name)
+    }
+
+    /// Returns the description of the status code from the UEFI
+    /// specification, or `None` if the code is not defined by the
+    /// specification.
+    #[must_use]
+    pub fn description(self) -> Option<&'static str> {
+        Self::lookup(self).map(|&(_, _, description)| description)
+    }
+
+    /// Returns an iterator over all the status codes defined by the UEFI
+    /// specification: success, then the warnings, then the errors.
+    pub fn known() -> impl Iterator<Item = Self> {
+        KNOWN_STATUSES.iter().map(|&(status, _, _)| status)
+    }
+
+    fn lookup(self) -> Option<&'static (Self, &'static str, &'static str)> {
+        KNOWN_STATUSES.iter().find(|(status, _, _)| *status == self)
+    }
+
+    /// Converts this status code into a [`uefi::Result`] that only treats
+    /// errors as failures. Success and warnings are returned as a
+    /// [`Completion`] with the value returned by `val`, from which the
+    /// warning can be inspected, logged, or turned into an error.
+    #[inline]
+    pub fn into_completion_with_val<T>(self, val: impl FnOnce() -> T) -> Result<Completion<T>, ()> {
+        if self.is_error() {
+            Err(self.into())
+        } else {
+            Ok(Completion::new(self, val()))
+        }
+    }
+
+    /// Converts this status code into a [`uefi::Result`] that only treats
+    /// errors as failures. See [`into_completion_with_val`].
+    ///
+    /// [`into_completion_with_val`]: Self::into_completion_with_val
+    #[inline]
+    pub fn into_completion(self) -> Result<Completion<()>, ()> {
+        self.into_completion_with_val(|| ())
+    }
+
     /// Converts this status code into a [`uefi::Result`] with a given `Ok` value.
     ///
     /// If the status does not indicate success, the status representing the specific error
@@ -238,4 +500,45 @@ mod tests {
             456
         );
     }
+
+    #[test]
+    fn test_status_names() {
+        assert_eq!(Status::SUCCESS.name(), Some("EFI_SUCCESS"));
+        assert_eq!(
+            Status::WARN_UNKNOWN_GLYPH.name(),
+            Some("EFI_WARN_UNKNOWN_GLYPH")
+        );
+        assert_eq!(
+            Status::NOT_FOUND.description(),
+            Some("The item was not found.")
+        );
+        assert_eq!(Status(ERROR_BIT | 29).name(), None);
+        assert_eq!(Status(ERROR_BIT | 29).description(), None);
+
+        // The table covers every named status.
+        assert_eq!(Status::known().count(), 41);
+        assert!(Status::known()
+            .all(|status| alloc::format!("{status:?}") == status.name().unwrap()["EFI_".len()..]));
+        assert!(Status::known().skip(1).take(7).all(Status::is_warning));
+        assert!(Status::known().skip(8).all(Status::is_error));
+    }
+
+    #[test]
+    fn test_status_to_completion() {
+        let completion = Status::SUCCESS.into_completion_with_val(|| 123).unwrap();
+        assert_eq!(completion.warning(), None);
+        assert_eq!(completion.value(), 123);
+
+        let completion = Status::WARN_UNKNOWN_GLYPH.into_completion().unwrap();
+        assert_eq!(completion.warning(), Some(Status::WARN_UNKNOWN_GLYPH));
+        assert_eq!(
+            completion.into_result().unwrap_err().status(),
+            Status::WARN_UNKNOWN_GLYPH
+        );
+
+        assert_eq!(
+            Status::DEVICE_ERROR.into_completion().unwrap_err().status(),
+            Status::DEVICE_ERROR
+        );
+    }
 }
@@ -0,0 +1,201 @@
+//! Conversion of pointers when the runtime services switch to virtual mode.
+//!
+//! Runtime drivers keep running after an operating system has called
+//! `SetVirtualAddressMap`, after which the runtime memory regions are
+//! only accessible at their new virtual addresses. Every pointer into
+//! runtime memory held by the driver, such as the pointer to the system
+//! table used by its logger or to its own global state, must be converted
+//! with [`RuntimeServices::convert_pointer`] during the switch.
+//!
+//! [`register_pointer`] adds a pointer to a registry, and [`install`]
+//! creates the `VIRTUAL_ADDRESS_CHANGE` event that converts all registered
+//! pointers. [`on_virtual_address_change`] runs a closure during the switch,
+//! e.g. to reconfigure a device with its new MMIO addresses.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::runtime_pointers;
+//! use uefi::table::runtime::RuntimeServices;
+//!
+//! static mut RUNTIME_SERVICES: *const RuntimeServices = core::ptr::null();
+//!
+//! fn init(st: &SystemTable<Boot>) -> uefi::Result {
+//!     unsafe {
+//!         RUNTIME_SERVICES = st.runtime_services();
+//!         runtime_pointers::register_pointer(core::ptr::addr_of_mut!(RUNTIME_SERVICES));
+//!     }
+//!     runtime_pointers::install(st)?;
+//!     Ok(())
+//! }
+//! ```
+
+use crate::mem::try_box;
+use crate::table::boot::{EventType, Tpl};
+use crate::table::runtime::RuntimeServices;
+use crate::table::{Boot, SystemTable};
+use crate::{Event, Result};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::ptr::{self, NonNull};
+
+/// Locations of the pointers registered with [`register_pointer`].
+///
+/// UEFI applications are single-threaded, and the list is only changed
+/// before boot services are exited, so there is no concurrent access.
+static mut POINTERS: Vec<*mut *const c_void> = Vec::new();
+
+/// Registers the pointer stored at `location` to be converted by
+/// [`convert_registered_pointers`]. Null pointers are left unchanged.
+///
+/// # Safety
+///
+/// `location` must stay valid until the runtime services have switched
+/// to virtual mode, and must therefore be in runtime memory, e.g. in a
+/// static of a runtime driver.
+pub unsafe fn register_pointer<T>(location: *mut *const T) {
+    (*ptr::addr_of_mut!(POINTERS)).push(location.cast());
+}
+
+/// Converts all the pointers registered with [`register_pointer`] to their
+/// virtual addresses.
+///
+/// This must be called from the notification function of a
+/// `VIRTUAL_ADDRESS_CHANGE` event, such as the one created by [`install`].
+///
+/// # Errors
+///
+/// Returns the first error of [`RuntimeServices::convert_pointer`]. The
+/// remaining pointers are still converted.
+pub fn convert_registered_pointers(rt: &RuntimeServices) -> Result {
+    let mut result = Ok(());
+    for location in unsafe { (*ptr::addr_of!(POINTERS)).iter() } {
+        let status = unsafe { rt.convert_pointer(&mut **location) };
+        if result.is_ok() {
+            result = status;
+        }
+    }
+    result
+}
+
+/// Runs `callback` while the runtime services switch to virtual mode.
+///
+/// This creates an event of type
+/// [`EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE`], which the firmware
+/// signals during `SetVirtualAddressMap`. The callback gets the runtime
+/// services, whose [`convert_pointer`] function it may call. Closing the
+/// returned event with [`BootServices::close_event`] before boot services
+/// are exited cancels the callback.
+///
+/// The closure runs after boot services have been exited. It must
+/// therefore not allocate or free memory, which includes dropping
+/// heap-allocated values. For the same reason, the closure itself is never
+/// freed.
+///
+/// # Errors
+///
+/// See [`BootServices::create_event`].
+///
+/// [`BootServices::close_event`]: crate::table::boot::BootServices::close_event
+/// [`BootServices::create_event`]: crate::table::boot::BootServices::create_event
+/// [`convert_pointer`]: RuntimeServices::convert_pointer
+pub fn on_virtual_address_change<F>(st: &SystemTable<Boot>, callback: F) -> Result<Event>
+where
+    F: FnOnce(&RuntimeServices) + 'static,
+{
+    struct Context<F> {
+        runtime_services: *const RuntimeServices,
+        callback: Option<F>,
+    }
+
+    unsafe extern "efiapi" fn notify<F: FnOnce(&RuntimeServices)>(
+        _event: Event,
+        ctx: Option<NonNull<c_void>>,
+    ) {
+        if let Some(ctx) = ctx {
+            let ctx = &mut *ctx.cast::<Context<F>>().as_ptr();
+            if let Some(callback) = ctx.callback.take() {
+                callback(&*ctx.runtime_services);
+            }
+        }
+    }
+
+    let bt = st.boot_services();
+    let ctx = Box::into_raw(try_box(Context {
+        runtime_services: st.runtime_services(),
+        callback: Some(callback),
+    })?);
+    let event = unsafe {
+        bt.create_event(
+            EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE,
+            Tpl::NOTIFY,
+            Some(notify::<F>),
+            NonNull::new(ctx.cast()),
+        )
+    };
+    if event.is_err() {
+        // The event was not created, so the context can be freed.
+        drop(unsafe { Box::from_raw(ctx) });
+    }
+    event
+}
+
+/// Converts the registered pointers while the runtime services switch to
+/// virtual mode. This should be called once, when the driver is loaded.
+///
+/// Errors of [`convert_registered_pointers`] are ignored, since they can't
+/// be reported from the event.
+///
+/// # Errors
+///
+/// See [`BootServices::create_event`].
+///
+/// [`BootServices::create_event`]: crate::table::boot::BootServices::create_event
+pub fn install(st: &SystemTable<Boot>) -> Result<Event> {
+    on_virtual_address_change(st, |rt| {
+        let _ = convert_registered_pointers(rt);
+    })
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use crate::table::boot::{MemoryAttribute, MemoryDescriptor, MemoryType};
+    use crate::table::runtime::VirtualAddressMap;
+
+    #[test]
+    fn test_convert_registered_pointers() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let rt = st.runtime_services();
+
+        static mut IN_RUNTIME_MEMORY: *const u8 = 0x10010 as *const u8;
+        static mut NULL: *const u8 = ptr::null();
+        unsafe {
+            register_pointer(ptr::addr_of_mut!(IN_RUNTIME_MEMORY));
+            register_pointer(ptr::addr_of_mut!(NULL));
+        }
+
+        let memory_map = [MemoryDescriptor {
+            ty: MemoryType::RUNTIME_SERVICES_DATA,
+            phys_start: 0x10000,
+            virt_start: 0,
+            page_count: 1,
+            att: MemoryAttribute::RUNTIME,
+        }];
+        let mut buffer = [MemoryDescriptor::default(); 1];
+        let mut map = VirtualAddressMap::new(&memory_map, &mut buffer)
+            .unwrap()
+            .with_offset(0x8000_0000);
+        unsafe { rt.set_virtual_address_map(&mut map) }.unwrap();
+
+        convert_registered_pointers(rt).unwrap();
+        unsafe {
+            assert_eq!(IN_RUNTIME_MEMORY as usize, 0x8001_0010);
+            assert!(NULL.is_null());
+        }
+    }
+}
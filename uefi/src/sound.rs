@@ -0,0 +1,125 @@
+//! Audible cues, e.g. for accessibility in boot menus.
+//!
+//! UEFI has no standard audio output protocol. [`beep`] uses the
+//! [`Speaker`] protocol when the firmware provides it, and otherwise, on
+//! x86 with the `arch-intrinsics` feature, programs the PC speaker
+//! directly. Audio devices such as HD Audio or virtio-sound controllers
+//! need a driver of their own, which is out of the scope of this module.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use core::time::Duration;
+//! use uefi::prelude::*;
+//! use uefi::sound;
+//!
+//! fn on_menu_entry_selected(bt: &BootServices) {
+//!     // A missing speaker is not worth failing for.
+//!     let _ = sound::beep(bt, 880, Duration::from_millis(100));
+//! }
+//! ```
+
+use crate::proto::speaker::Speaker;
+use crate::table::boot::BootServices;
+use crate::{Result, Status};
+use core::time::Duration;
+
+/// Frequency of the clock of the 8254 programmable interval timer (PIT),
+/// which drives the PC speaker, in Hz.
+pub const PIT_FREQUENCY: u32 = 1_193_182;
+
+/// Returns the divisor of the [`PIT_FREQUENCY`] that produces the tone
+/// closest to `frequency` Hz, or `None` if `frequency` is out of the
+/// range of the timer (19 Hz to 1.19 MHz).
+#[must_use]
+pub const fn pit_divisor(frequency: u32) -> Option<u16> {
+    if frequency == 0 {
+        return None;
+    }
+    let divisor = (PIT_FREQUENCY + frequency / 2) / frequency;
+    if divisor == 0 || divisor > u16::MAX as u32 {
+        None
+    } else {
+        Some(divisor as u16)
+    }
+}
+
+/// Plays a tone of `frequency` Hz for `duration`, and returns once it is
+/// done.
+///
+/// The [`Speaker`] protocol is used if it is installed. Otherwise, on x86
+/// with the `arch-intrinsics` feature, the PC speaker is programmed
+/// directly.
+///
+/// # Errors
+///
+/// * [`uefi::Status::INVALID_PARAMETER`]: `frequency` is out of the range
+///   given by [`pit_divisor`].
+/// * [`uefi::Status::UNSUPPORTED`]: there is no speaker to beep with.
+///
+/// See also [`Speaker::generate_beep`].
+pub fn beep(bt: &BootServices, frequency: u32, duration: Duration) -> Result {
+    let divisor = pit_divisor(frequency).ok_or(Status::INVALID_PARAMETER)?;
+
+    let handle = match bt.get_handle_for_protocol::<Speaker>() {
+        Ok(handle) => handle,
+        Err(err) if err.status() == Status::NOT_FOUND => {
+            return pc_speaker_beep(bt, divisor, duration)
+        }
+        Err(err) => return Err(err),
+    };
+    let mut speaker = bt.open_protocol_exclusive::<Speaker>(handle)?;
+    speaker.set_beep_frequency(divisor)?;
+    speaker.generate_beep(1, duration, Duration::ZERO)
+}
+
+/// Beeps with the PC speaker, through channel 2 of the PIT.
+#[cfg(all(
+    feature = "arch-intrinsics",
+    any(target_arch = "x86", target_arch = "x86_64")
+))]
+fn pc_speaker_beep(bt: &BootServices, divisor: u16, duration: Duration) -> Result {
+    use crate::arch::x86::{inb, outb};
+
+    const PIT_CHANNEL_2: u16 = 0x42;
+    const PIT_COMMAND: u16 = 0x43;
+    const SPEAKER_CONTROL: u16 = 0x61;
+    /// Enables the gate of channel 2 and connects it to the speaker.
+    const SPEAKER_ENABLE: u8 = 0b11;
+
+    let [low, high] = divisor.to_le_bytes();
+    unsafe {
+        // Channel 2, low then high byte, square wave generator.
+        outb(PIT_COMMAND, 0b1011_0110);
+        outb(PIT_CHANNEL_2, low);
+        outb(PIT_CHANNEL_2, high);
+        outb(SPEAKER_CONTROL, inb(SPEAKER_CONTROL) | SPEAKER_ENABLE);
+    }
+    bt.stall_duration(duration);
+    unsafe { outb(SPEAKER_CONTROL, inb(SPEAKER_CONTROL) & !SPEAKER_ENABLE) };
+    Ok(())
+}
+
+#[cfg(not(all(
+    feature = "arch-intrinsics",
+    any(target_arch = "x86", target_arch = "x86_64")
+)))]
+fn pc_speaker_beep(_bt: &BootServices, _divisor: u16, _duration: Duration) -> Result {
+    Err(Status::UNSUPPORTED.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pit_divisor() {
+        assert_eq!(pit_divisor(1000), Some(1193));
+        assert_eq!(pit_divisor(440), Some(2712));
+        assert_eq!(pit_divisor(19), Some(62799));
+        assert_eq!(pit_divisor(18), None);
+        assert_eq!(pit_divisor(PIT_FREQUENCY), Some(1));
+        assert_eq!(pit_divisor(u32::MAX), None);
+        assert_eq!(pit_divisor(0), None);
+    }
+}
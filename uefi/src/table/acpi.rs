@@ -0,0 +1,289 @@
+//! ACPI table lookup.
+//!
+//! The ACPI tables are not UEFI tables, but the firmware points to them
+//! from the [configuration table]. This module only locates the tables,
+//! parsing their contents is left to specialized crates or to the users of
+//! a specific table, such as [`Ibft`].
+//!
+//! [configuration table]: super::cfg
+//! [`Ibft`]: crate::proto::network::iscsi::Ibft
+
+use super::cfg::{ConfigTableEntry, ACPI2_GUID, ACPI_GUID};
+use core::{fmt, mem, ptr, slice};
+
+/// Signature of the RSDP.
+const RSDP_SIGNATURE: [u8; 8] = *b"RSD PTR ";
+
+/// The Root System Description Pointer, pointed to by the ACPI entries of
+/// the configuration table.
+#[repr(C, packed)]
+struct Rsdp {
+    signature: [u8; 8],
+    _checksum: u8,
+    _oem_id: [u8; 6],
+    revision: u8,
+    rsdt_address: u32,
+    // The following fields are only valid for revision 2 and later.
+    _length: u32,
+    xsdt_address: u64,
+    _extended_checksum: u8,
+    _reserved: [u8; 3],
+}
+
+/// The header shared by all the ACPI System Description Tables.
+///
+/// The header is followed in memory by the rest of the table, which can be
+/// accessed with [`as_bytes`].
+///
+/// The corresponding C type is `EFI_ACPI_DESCRIPTION_HEADER`.
+///
+/// [`as_bytes`]: Self::as_bytes
+#[repr(C, packed)]
+pub struct AcpiTableHeader {
+    signature: [u8; 4],
+    length: u32,
+    revision: u8,
+    _checksum: u8,
+    oem_id: [u8; 6],
+    oem_table_id: [u8; 8],
+    oem_revision: u32,
+    creator_id: u32,
+    creator_revision: u32,
+}
+
+impl AcpiTableHeader {
+    /// Get the signature of the table, such as `*b"FACP"`.
+    #[must_use]
+    pub const fn signature(&self) -> [u8; 4] {
+        self.signature
+    }
+
+    /// Get the length of the table in bytes, including the header.
+    #[must_use]
+    pub const fn length(&self) -> u32 {
+        self.length
+    }
+
+    /// Get the revision of the table format.
+    #[must_use]
+    pub const fn revision(&self) -> u8 {
+        self.revision
+    }
+
+    /// Get the ID of the vendor that created the table.
+    #[must_use]
+    pub const fn oem_id(&self) -> [u8; 6] {
+        self.oem_id
+    }
+
+    /// Get the vendor-defined ID of the table.
+    #[must_use]
+    pub const fn oem_table_id(&self) -> [u8; 8] {
+        self.oem_table_id
+    }
+
+    /// Get the vendor-defined revision of the table.
+    #[must_use]
+    pub const fn oem_revision(&self) -> u32 {
+        self.oem_revision
+    }
+
+    /// Get the ID of the tool that created the table.
+    #[must_use]
+    pub const fn creator_id(&self) -> u32 {
+        self.creator_id
+    }
+
+    /// Get the revision of the tool that created the table.
+    #[must_use]
+    pub const fn creator_revision(&self) -> u32 {
+        self.creator_revision
+    }
+
+    /// Get the bytes of the whole table, including the header.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        let len = usize::try_from(self.length)
+            .unwrap()
+            .max(mem::size_of::<Self>());
+        unsafe { slice::from_raw_parts((self as *const Self).cast::<u8>(), len) }
+    }
+
+    /// Check whether the bytes of the table sum to zero, as required by the
+    /// ACPI specification.
+    #[must_use]
+    pub fn is_checksum_valid(&self) -> bool {
+        checksum(self.as_bytes()) == 0
+    }
+}
+
+impl fmt::Debug for AcpiTableHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AcpiTableHeader")
+            .field("signature", &self.signature)
+            .field("length", &{ self.length })
+            .field("revision", &self.revision)
+            .field("oem_id", &self.oem_id)
+            .field("oem_table_id", &self.oem_table_id)
+            .field("oem_revision", &{ self.oem_revision })
+            .finish()
+    }
+}
+
+/// Sum of `bytes`, modulo 256.
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, b| sum.wrapping_add(*b))
+}
+
+/// Find the first ACPI table with the given `signature`, such as `*b"FACP"`,
+/// from the ACPI entries of `config_table`.
+///
+/// The ACPI 2 entry and its XSDT are preferred, the ACPI 1 entry and its
+/// RSDT are used otherwise. The tables are accessed by their physical
+/// address, which is only valid while the memory is identity mapped, as it
+/// is before exiting boot services. Returns `None` if the table is not
+/// found.
+///
+/// This is usually called with [`SystemTable::find_acpi_table`].
+///
+/// [`SystemTable::find_acpi_table`]: super::SystemTable::find_acpi_table
+#[must_use]
+pub fn find_table<'a>(
+    config_table: &'a [ConfigTableEntry],
+    signature: &[u8; 4],
+) -> Option<&'a AcpiTableHeader> {
+    tables(config_table).find(|table| table.signature == *signature)
+}
+
+/// Iterate over the ACPI tables listed in the XSDT or RSDT.
+fn tables(config_table: &[ConfigTableEntry]) -> impl Iterator<Item = &AcpiTableHeader> {
+    let rsdp = [ACPI2_GUID, ACPI_GUID].iter().find_map(|guid| {
+        let entry = config_table.iter().find(|entry| entry.guid == *guid)?;
+        let rsdp = unsafe { entry.address.cast::<Rsdp>().as_ref() }?;
+        (rsdp.signature == RSDP_SIGNATURE).then_some(rsdp)
+    });
+
+    // Use the 64-bit XSDT if available, and the 32-bit RSDT otherwise.
+    let (root, entry_size) = match rsdp {
+        Some(rsdp) if rsdp.revision >= 2 && rsdp.xsdt_address != 0 => {
+            (table_at(rsdp.xsdt_address), mem::size_of::<u64>())
+        }
+        Some(rsdp) => (
+            table_at(u64::from(rsdp.rsdt_address)),
+            mem::size_of::<u32>(),
+        ),
+        None => (None, mem::size_of::<u32>()),
+    };
+    let entries = root
+        .map(|root| &root.as_bytes()[mem::size_of::<AcpiTableHeader>()..])
+        .unwrap_or_default();
+
+    entries.chunks_exact(entry_size).filter_map(move |entry| {
+        let address = if entry_size == mem::size_of::<u64>() {
+            u64::from_le_bytes(entry.try_into().unwrap())
+        } else {
+            u64::from(u32::from_le_bytes(entry.try_into().unwrap()))
+        };
+        table_at(address)
+    })
+}
+
+/// Get the table at physical `address`, or `None` if null or out of the
+/// address space.
+fn table_at<'a>(address: u64) -> Option<&'a AcpiTableHeader> {
+    let address = usize::try_from(address).ok()?;
+    unsafe { ptr::NonNull::new(address as *mut AcpiTableHeader).map(|table| &*table.as_ptr()) }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::Guid;
+    use alloc::vec;
+    use alloc::vec::Vec;
+    use core::ffi::c_void;
+
+    /// Build an ACPI table with the given signature and body, with a valid
+    /// checksum.
+    fn table(signature: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut table = vec![0; mem::size_of::<AcpiTableHeader>()];
+        table[..4].copy_from_slice(signature);
+        let len = (table.len() + body.len()) as u32;
+        table[4..8].copy_from_slice(&len.to_le_bytes());
+        table[8] = 1;
+        table[10..16].copy_from_slice(b"UEFIRS");
+        table.extend_from_slice(body);
+        table[9] = 0u8.wrapping_sub(checksum(&table));
+        table
+    }
+
+    fn rsdp(revision: u8, rsdt: &[u8], xsdt: &[u8]) -> Rsdp {
+        Rsdp {
+            signature: RSDP_SIGNATURE,
+            _checksum: 0,
+            _oem_id: *b"UEFIRS",
+            revision,
+            rsdt_address: rsdt.as_ptr() as usize as u32,
+            _length: mem::size_of::<Rsdp>() as u32,
+            xsdt_address: xsdt.as_ptr() as u64,
+            _extended_checksum: 0,
+            _reserved: [0; 3],
+        }
+    }
+
+    fn config_table(guid: Guid, rsdp: &Rsdp) -> [ConfigTableEntry; 1] {
+        [ConfigTableEntry {
+            guid,
+            address: (rsdp as *const Rsdp).cast::<c_void>(),
+        }]
+    }
+
+    #[test]
+    fn test_find_table() {
+        let facp = table(b"FACP", &[1, 2, 3]);
+        let ibft = table(b"iBFT", &[4, 5]);
+
+        let mut xsdt_body = Vec::new();
+        for t in [&facp, &ibft] {
+            xsdt_body.extend_from_slice(&(t.as_ptr() as u64).to_le_bytes());
+        }
+        let xsdt = table(b"XSDT", &xsdt_body);
+
+        let rsdp = rsdp(2, &[], &xsdt);
+        let config_table = config_table(ACPI2_GUID, &rsdp);
+
+        let found = find_table(&config_table, b"iBFT").unwrap();
+        assert_eq!(found.signature(), *b"iBFT");
+        assert_eq!(found.length(), 38);
+        assert_eq!(found.oem_id(), *b"UEFIRS");
+        assert!(found.is_checksum_valid());
+        assert_eq!(&found.as_bytes()[36..], &[4, 5]);
+        assert_eq!(
+            find_table(&config_table, b"FACP").unwrap().as_bytes()[36..],
+            [1, 2, 3]
+        );
+        assert!(find_table(&config_table, b"APIC").is_none());
+        assert!(find_table(&[], b"FACP").is_none());
+    }
+
+    #[test]
+    fn test_find_table_rsdt() {
+        // The RSDT only holds 32-bit addresses, so this is only testable if
+        // the heap is in the low 4 GiB.
+        let ibft = table(b"iBFT", &[]);
+        if u32::try_from(ibft.as_ptr() as usize).is_err() {
+            return;
+        }
+        let rsdt = table(b"RSDT", &(ibft.as_ptr() as usize as u32).to_le_bytes());
+        if u32::try_from(rsdt.as_ptr() as usize).is_err() {
+            return;
+        }
+
+        let rsdp = rsdp(0, &rsdt, &[]);
+        let config_table = config_table(ACPI_GUID, &rsdp);
+        assert_eq!(
+            find_table(&config_table, b"iBFT").unwrap().signature(),
+            *b"iBFT"
+        );
+    }
+}
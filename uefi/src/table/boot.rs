@@ -1,23 +1,36 @@
 //! UEFI services available during boot.
 
-use super::{Header, Revision};
-use crate::data_types::{Align, PhysicalAddress, VirtualAddress};
-use crate::proto::device_path::{DevicePath, FfiDevicePath};
+use super::Revision;
+use crate::data_types::{Align, PhysicalAddress};
 #[cfg(feature = "alloc")]
-use crate::proto::{loaded_image::LoadedImage, media::fs::SimpleFileSystem};
+use crate::mem::{call_with_growing_buffer, try_box, try_to_vec};
+use crate::proto::device_path::DevicePath;
+use crate::proto::loaded_image::LoadedImage;
+#[cfg(feature = "alloc")]
+use crate::proto::media::fs::SimpleFileSystem;
 use crate::proto::{Protocol, ProtocolPointer};
-use crate::{Char16, Event, Guid, Handle, Result, Status};
+use crate::raw::table::boot as raw;
+#[cfg(feature = "alloc")]
+use crate::report::{self, Format, Record};
+use crate::util::div_ceil_u128;
+use crate::{guid, CStr16, Char16, Error, Event, Guid, Handle, Result, ResultExt, Status};
 #[cfg(feature = "alloc")]
-use ::alloc::vec::Vec;
-use bitflags::bitflags;
+use ::alloc::{boxed::Box, vec::Vec};
 use core::cell::UnsafeCell;
 use core::ffi::c_void;
 use core::fmt::{Debug, Formatter};
 use core::mem::{self, MaybeUninit};
-use core::ops::{Deref, DerefMut};
+use core::ops::{Deref, DerefMut, RangeInclusive};
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
 use core::{ptr, slice};
 
+pub use crate::raw::table::boot::{
+    EventNotifyFn, EventType, InterfaceType, MemoryAttribute, MemoryDescriptor, MemoryType,
+    OpenProtocolInformationEntry, Tpl,
+};
+
 // TODO: this similar to `SyncUnsafeCell`. Once that is stabilized we
 // can use it instead.
 struct GlobalImageHandle {
@@ -32,6 +45,53 @@ static IMAGE_HANDLE: GlobalImageHandle = GlobalImageHandle {
     handle: UnsafeCell::new(None),
 };
 
+// The watchdog settings can't be queried from the firmware, so keep
+// track of the last values set through `BootServices::set_watchdog_timer`
+// in order to restore them in `BootServices::with_watchdog_extended`.
+// The firmware arms a 5-minute watchdog before starting an image.
+static WATCHDOG_TIMEOUT: AtomicUsize = AtomicUsize::new(300);
+static WATCHDOG_CODE: AtomicU64 = AtomicU64::new(0x1_0000);
+
+// Innermost running `BootServices::with_watchdog_extended` call, so that a
+// nested call restores the extension of the enclosing one. With `std`, the
+// calls of different threads (such as tests run against the mock firmware)
+// are kept apart.
+#[cfg(not(feature = "std"))]
+static WATCHDOG_EXTENSION: core::sync::atomic::AtomicPtr<WatchdogExtension> =
+    core::sync::atomic::AtomicPtr::new(ptr::null_mut());
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static WATCHDOG_EXTENSION: core::cell::Cell<*const WatchdogExtension> =
+        const { core::cell::Cell::new(ptr::null()) };
+}
+
+#[cfg(not(feature = "std"))]
+fn current_watchdog_extension() -> *const WatchdogExtension {
+    WATCHDOG_EXTENSION.load(Ordering::Relaxed)
+}
+
+#[cfg(not(feature = "std"))]
+fn set_current_watchdog_extension(extension: *const WatchdogExtension) {
+    WATCHDOG_EXTENSION.store(extension as *mut WatchdogExtension, Ordering::Relaxed);
+}
+
+#[cfg(feature = "std")]
+fn current_watchdog_extension() -> *const WatchdogExtension {
+    WATCHDOG_EXTENSION.with(|extension| extension.get())
+}
+
+#[cfg(feature = "std")]
+fn set_current_watchdog_extension(extension: *const WatchdogExtension) {
+    WATCHDOG_EXTENSION.with(|current| current.set(extension));
+}
+
+/// Watchdog timeout used by the helpers of the crate which wrap long-running
+/// operations in [`BootServices::with_watchdog_extended`]. The watchdog is
+/// refreshed at half this interval, so it only has to cover a single
+/// blocking firmware call.
+pub(crate) const LONG_OPERATION_WATCHDOG_TIMEOUT: Duration = Duration::from_secs(300);
+
 /// Size in bytes of a UEFI page.
 ///
 /// Note that this is not necessarily the processor's page size. The UEFI page
@@ -88,192 +148,9 @@ pub const PAGE_SIZE: usize = 4096;
 ///
 /// [`Output`]: crate::proto::console::text::Output
 /// [`open_protocol`]: BootServices::open_protocol
-#[repr(C)]
+#[repr(transparent)]
 pub struct BootServices {
-    header: Header,
-
-    // Task Priority services
-    raise_tpl: unsafe extern "efiapi" fn(new_tpl: Tpl) -> Tpl,
-    restore_tpl: unsafe extern "efiapi" fn(old_tpl: Tpl),
-
-    // Memory allocation functions
-    allocate_pages: extern "efiapi" fn(
-        alloc_ty: u32,
-        mem_ty: MemoryType,
-        count: usize,
-        addr: &mut PhysicalAddress,
-    ) -> Status,
-    free_pages: extern "efiapi" fn(addr: PhysicalAddress, pages: usize) -> Status,
-    get_memory_map: unsafe extern "efiapi" fn(
-        size: &mut usize,
-        map: *mut MemoryDescriptor,
-        key: &mut MemoryMapKey,
-        desc_size: &mut usize,
-        desc_version: &mut u32,
-    ) -> Status,
-    allocate_pool:
-        extern "efiapi" fn(pool_type: MemoryType, size: usize, buffer: &mut *mut u8) -> Status,
-    free_pool: extern "efiapi" fn(buffer: *mut u8) -> Status,
-
-    // Event & timer functions
-    create_event: unsafe extern "efiapi" fn(
-        ty: EventType,
-        notify_tpl: Tpl,
-        notify_func: Option<EventNotifyFn>,
-        notify_ctx: Option<NonNull<c_void>>,
-        out_event: *mut Event,
-    ) -> Status,
-    set_timer: unsafe extern "efiapi" fn(event: Event, ty: u32, trigger_time: u64) -> Status,
-    wait_for_event: unsafe extern "efiapi" fn(
-        number_of_events: usize,
-        events: *mut Event,
-        out_index: *mut usize,
-    ) -> Status,
-    signal_event: extern "efiapi" fn(event: Event) -> Status,
-    close_event: unsafe extern "efiapi" fn(event: Event) -> Status,
-    check_event: unsafe extern "efiapi" fn(event: Event) -> Status,
-
-    // Protocol handlers
-    install_protocol_interface: unsafe extern "efiapi" fn(
-        handle: &mut Option<Handle>,
-        guid: &Guid,
-        interface_type: InterfaceType,
-        interface: *mut c_void,
-    ) -> Status,
-    reinstall_protocol_interface: unsafe extern "efiapi" fn(
-        handle: Handle,
-        protocol: &Guid,
-        old_interface: *mut c_void,
-        new_interface: *mut c_void,
-    ) -> Status,
-    uninstall_protocol_interface: unsafe extern "efiapi" fn(
-        handle: Handle,
-        protocol: &Guid,
-        interface: *mut c_void,
-    ) -> Status,
-    handle_protocol:
-        extern "efiapi" fn(handle: Handle, proto: &Guid, out_proto: &mut *mut c_void) -> Status,
-    _reserved: usize,
-    register_protocol_notify: extern "efiapi" fn(
-        protocol: &Guid,
-        event: Event,
-        registration: *mut ProtocolSearchKey,
-    ) -> Status,
-    locate_handle: unsafe extern "efiapi" fn(
-        search_ty: i32,
-        proto: Option<&Guid>,
-        key: Option<ProtocolSearchKey>,
-        buf_sz: &mut usize,
-        buf: *mut MaybeUninit<Handle>,
-    ) -> Status,
-    locate_device_path: unsafe extern "efiapi" fn(
-        proto: &Guid,
-        device_path: &mut *const FfiDevicePath,
-        out_handle: &mut MaybeUninit<Handle>,
-    ) -> Status,
-    install_configuration_table: usize,
-
-    // Image services
-    load_image: unsafe extern "efiapi" fn(
-        boot_policy: u8,
-        parent_image_handle: Handle,
-        device_path: *const FfiDevicePath,
-        source_buffer: *const u8,
-        source_size: usize,
-        image_handle: &mut MaybeUninit<Handle>,
-    ) -> Status,
-    start_image: unsafe extern "efiapi" fn(
-        image_handle: Handle,
-        exit_data_size: *mut usize,
-        exit_data: &mut *mut Char16,
-    ) -> Status,
-    exit: extern "efiapi" fn(
-        image_handle: Handle,
-        exit_status: Status,
-        exit_data_size: usize,
-        exit_data: *mut Char16,
-    ) -> !,
-    unload_image: extern "efiapi" fn(image_handle: Handle) -> Status,
-    exit_boot_services:
-        unsafe extern "efiapi" fn(image_handle: Handle, map_key: MemoryMapKey) -> Status,
-
-    // Misc services
-    get_next_monotonic_count: usize,
-    stall: extern "efiapi" fn(microseconds: usize) -> Status,
-    set_watchdog_timer: unsafe extern "efiapi" fn(
-        timeout: usize,
-        watchdog_code: u64,
-        data_size: usize,
-        watchdog_data: *const u16,
-    ) -> Status,
-
-    // Driver support services
-    connect_controller: unsafe extern "efiapi" fn(
-        controller: Handle,
-        driver_image: Option<Handle>,
-        remaining_device_path: *const FfiDevicePath,
-        recursive: bool,
-    ) -> Status,
-    disconnect_controller: unsafe extern "efiapi" fn(
-        controller: Handle,
-        driver_image: Option<Handle>,
-        child: Option<Handle>,
-    ) -> Status,
-
-    // Protocol open / close services
-    open_protocol: extern "efiapi" fn(
-        handle: Handle,
-        protocol: &Guid,
-        interface: &mut *mut c_void,
-        agent_handle: Handle,
-        controller_handle: Option<Handle>,
-        attributes: u32,
-    ) -> Status,
-    close_protocol: extern "efiapi" fn(
-        handle: Handle,
-        protocol: &Guid,
-        agent_handle: Handle,
-        controller_handle: Option<Handle>,
-    ) -> Status,
-    open_protocol_information: usize,
-
-    // Library services
-    protocols_per_handle: unsafe extern "efiapi" fn(
-        handle: Handle,
-        protocol_buffer: *mut *mut *const Guid,
-        protocol_buffer_count: *mut usize,
-    ) -> Status,
-    locate_handle_buffer: unsafe extern "efiapi" fn(
-        search_ty: i32,
-        proto: Option<&Guid>,
-        key: Option<ProtocolSearchKey>,
-        no_handles: &mut usize,
-        buf: &mut *mut Handle,
-    ) -> Status,
-    locate_protocol: extern "efiapi" fn(
-        proto: &Guid,
-        registration: *mut c_void,
-        out_proto: &mut *mut c_void,
-    ) -> Status,
-    install_multiple_protocol_interfaces: usize,
-    uninstall_multiple_protocol_interfaces: usize,
-
-    // CRC services
-    calculate_crc32: usize,
-
-    // Misc services
-    copy_mem: unsafe extern "efiapi" fn(dest: *mut u8, src: *const u8, len: usize),
-    set_mem: unsafe extern "efiapi" fn(buffer: *mut u8, len: usize, value: u8),
-
-    // New event functions (UEFI 2.0 or newer)
-    create_event_ex: unsafe extern "efiapi" fn(
-        ty: EventType,
-        notify_tpl: Tpl,
-        notify_fn: Option<EventNotifyFn>,
-        notify_ctx: Option<NonNull<c_void>>,
-        event_group: Option<NonNull<Guid>>,
-        out_event: *mut Event,
-    ) -> Status,
+    raw: raw::BootServices,
 }
 
 impl BootServices {
@@ -335,15 +212,17 @@ impl BootServices {
     pub unsafe fn raise_tpl(&self, tpl: Tpl) -> TplGuard<'_> {
         TplGuard {
             boot_services: self,
-            old_tpl: (self.raise_tpl)(tpl),
+            old_tpl: (self.raw.raise_tpl)(tpl),
         }
     }
 
     /// Allocates memory pages from the system.
     ///
-    /// UEFI OS loaders should allocate memory of the type `LoaderData`. An `u64`
-    /// is returned even on 32-bit platforms because some hardware configurations
-    /// like Intel PAE enable 64-bit physical addressing on a 32-bit processor.
+    /// UEFI OS loaders should allocate memory of the type `LoaderData`, or of a
+    /// [custom type](MemoryType::custom) to find the allocation in the memory map
+    /// later. An `u64` is returned even on 32-bit platforms because some hardware
+    /// configurations like Intel PAE enable 64-bit physical addressing on a 32-bit
+    /// processor.
     ///
     /// # Errors
     ///
@@ -363,7 +242,15 @@ impl BootServices {
             AllocateType::MaxAddress(addr) => (1, addr),
             AllocateType::Address(addr) => (2, addr),
         };
-        (self.allocate_pages)(ty, mem_ty, count, &mut addr).into_with_val(|| addr)
+        trace_status!(
+            "BootServices::allocate_pages",
+            unsafe { (self.raw.allocate_pages)(ty, mem_ty, count, &mut addr) },
+            "type={}, memory_type={:?}, pages={}",
+            ty,
+            mem_ty,
+            count
+        )
+        .into_with_val(|| addr)
     }
 
     /// Frees memory pages allocated by UEFI.
@@ -375,7 +262,14 @@ impl BootServices {
     /// * [`uefi::Status::NOT_FOUND`]
     /// * [`uefi::Status::INVALID_PARAMETER`]
     pub fn free_pages(&self, addr: PhysicalAddress, count: usize) -> Result {
-        (self.free_pages)(addr, count).into()
+        trace_status!(
+            "BootServices::free_pages",
+            unsafe { (self.raw.free_pages)(addr, count) },
+            "address={:#x}, pages={}",
+            addr,
+            count
+        )
+        .into()
     }
 
     /// Returns struct which contains the size of a single memory descriptor
@@ -391,15 +285,15 @@ impl BootServices {
         let mut entry_size = 0;
         let mut entry_version = 0;
 
-        let status = unsafe {
-            (self.get_memory_map)(
+        let status = trace_status!("BootServices::get_memory_map", unsafe {
+            (self.raw.get_memory_map)(
                 &mut map_size,
                 ptr::null_mut(),
-                &mut map_key,
+                &mut map_key.0,
                 &mut entry_size,
                 &mut entry_version,
             )
-        };
+        });
         assert_eq!(status, Status::BUFFER_TOO_SMALL);
 
         MemoryMapSize {
@@ -444,15 +338,20 @@ impl BootServices {
             "Memory map buffers must be aligned like a MemoryDescriptor"
         );
 
-        unsafe {
-            (self.get_memory_map)(
-                &mut map_size,
-                map_buffer,
-                &mut map_key,
-                &mut entry_size,
-                &mut entry_version,
-            )
-        }
+        trace_status!(
+            "BootServices::get_memory_map",
+            unsafe {
+                (self.raw.get_memory_map)(
+                    &mut map_size,
+                    map_buffer,
+                    &mut map_key.0,
+                    &mut entry_size,
+                    &mut entry_version,
+                )
+            },
+            "size={}",
+            buffer.len()
+        )
         .into_with_val(move || {
             let len = map_size / entry_size;
             let iter = MemoryMapIter {
@@ -475,7 +374,7 @@ impl BootServices {
     /// * [`uefi::Status::INVALID_PARAMETER`]
     pub fn allocate_pool(&self, mem_ty: MemoryType, size: usize) -> Result<*mut u8> {
         let mut buffer = ptr::null_mut();
-        (self.allocate_pool)(mem_ty, size, &mut buffer).into_with_val(|| buffer)
+        unsafe { (self.raw.allocate_pool)(mem_ty, size, &mut buffer) }.into_with_val(|| buffer)
     }
 
     /// Frees memory allocated from a pool.
@@ -485,8 +384,10 @@ impl BootServices {
     /// See section `EFI_BOOT_SERVICES.FreePool()` in the UEFI Specification for more details.
     ///
     /// * [`uefi::Status::INVALID_PARAMETER`]
+    // The firmware validates `addr`, and rejects pointers it didn't allocate.
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
     pub fn free_pool(&self, addr: *mut u8) -> Result {
-        (self.free_pool)(addr).into()
+        unsafe { (self.raw.free_pool)(addr) }.into()
     }
 
     /// Creates an event
@@ -522,19 +423,25 @@ impl BootServices {
         let mut event = MaybeUninit::<Event>::uninit();
 
         // Now we're ready to call UEFI
-        (self.create_event)(
+        trace_status!(
+            "BootServices::create_event",
+            (self.raw.create_event)(
+                event_ty,
+                notify_tpl,
+                notify_fn,
+                notify_ctx,
+                event.as_mut_ptr(),
+            ),
+            "type={:?}, tpl={:?}",
             event_ty,
-            notify_tpl,
-            notify_fn,
-            notify_ctx,
-            event.as_mut_ptr(),
+            notify_tpl
         )
         .into_with_val(|| event.assume_init())
     }
 
     /// Creates a new `Event` of type `event_type`. The event's notification function, context,
     /// and task priority are specified by `notify_fn`, `notify_ctx`, and `notify_tpl`, respectively.
-    /// The `Event` will be added to the group of `Event`s identified by `event_group`.
+    /// The `Event` will be added to `event_group`.
     ///
     /// If no group is specified by `event_group`, this function behaves as if the same parameters
     /// had been passed to `create_event()`.
@@ -543,13 +450,15 @@ impl BootServices {
     /// event is signaled, all other events are signaled and their individual notification actions
     /// are taken. All events are guaranteed to be signaled before the first notification action is
     /// taken. All notification functions will be executed in the order specified by their `Tpl`.
+    /// See [`EventGroup`] for the groups defined by the UEFI specification.
     ///
     /// A single event can only be part of a single event group. An event may be removed from an
     /// event group by using `close_event()`.
     ///
     /// The `EventType` of an event uses the same values as `create_event()`, except that
     /// `EventType::SIGNAL_EXIT_BOOT_SERVICES` and `EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE`
-    /// are not valid.
+    /// can't be combined with a group. Use the [`EventGroup::EXIT_BOOT_SERVICES`] and
+    /// [`EventGroup::VIRTUAL_ADDRESS_CHANGE`] groups with `EventType::NOTIFY_SIGNAL` instead.
     ///
     /// If `event_type` has `EventType::NOTIFY_SIGNAL` or `EventType::NOTIFY_WAIT`, then `notify_fn`
     /// mus be `Some` and `notify_tpl` must be a valid task priority level, otherwise these parameters
@@ -563,7 +472,8 @@ impl BootServices {
     ///
     /// # Safety
     ///
-    /// The caller must ensure they are passing a valid `Guid` as `event_group`, if applicable.
+    /// This function is unsafe because callbacks must handle exit from boot
+    /// services correctly.
     ///
     /// # Errors
     ///
@@ -577,25 +487,82 @@ impl BootServices {
         notify_tpl: Tpl,
         notify_fn: Option<EventNotifyFn>,
         notify_ctx: Option<NonNull<c_void>>,
-        event_group: Option<NonNull<Guid>>,
+        event_group: Option<EventGroup>,
     ) -> Result<Event> {
-        if self.header.revision < Revision::EFI_2_00 {
-            return Err(Status::UNSUPPORTED.into());
+        let create_event_ex = self
+            .raw
+            .header
+            .versioned_field(Revision::EFI_2_00, ptr::addr_of!(self.raw.create_event_ex))
+            .ok_or(Status::UNSUPPORTED)?;
+        if event_group.is_some()
+            && (event_type == EventType::SIGNAL_EXIT_BOOT_SERVICES
+                || event_type == EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE)
+        {
+            return Err(Status::INVALID_PARAMETER.into());
         }
 
         let mut event = MaybeUninit::<Event>::uninit();
 
-        (self.create_event_ex)(
+        // The firmware copies the group GUID.
+        trace_status!(
+            "BootServices::create_event_ex",
+            create_event_ex(
+                event_type,
+                notify_tpl,
+                notify_fn,
+                notify_ctx,
+                event_group.as_ref().map_or(ptr::null(), |group| &group.0),
+                event.as_mut_ptr(),
+            ),
+            "type={:?}, tpl={:?}, group={:?}",
             event_type,
             notify_tpl,
-            notify_fn,
-            notify_ctx,
-            event_group,
-            event.as_mut_ptr(),
+            event_group
         )
         .into_with_val(|| event.assume_init())
     }
 
+    /// Runs `callback` each time an event of `group` is signaled. Returns the
+    /// event created to join the group; closing it with
+    /// [`close_event`](Self::close_event) stops the callback.
+    ///
+    /// The callback runs in an event notification function at
+    /// [`Tpl::CALLBACK`]. Callbacks of the `EXIT_BOOT_SERVICES` and
+    /// `VIRTUAL_ADDRESS_CHANGE` groups run when boot services are exited,
+    /// and must therefore not allocate or free memory. For the same
+    /// reason, the callback itself is never freed.
+    ///
+    /// # Errors
+    ///
+    /// See [`create_event_ex`](Self::create_event_ex).
+    #[cfg(feature = "alloc")]
+    pub fn subscribe_event_group<F>(&self, group: EventGroup, callback: F) -> Result<Event>
+    where
+        F: FnMut() + 'static,
+    {
+        unsafe extern "efiapi" fn notify<F: FnMut()>(_event: Event, ctx: Option<NonNull<c_void>>) {
+            if let Some(ctx) = ctx {
+                (*ctx.cast::<F>().as_ptr())();
+            }
+        }
+
+        let callback = Box::into_raw(try_box(callback)?);
+        let event = unsafe {
+            self.create_event_ex(
+                EventType::NOTIFY_SIGNAL,
+                Tpl::CALLBACK,
+                Some(notify::<F>),
+                NonNull::new(callback.cast()),
+                Some(group),
+            )
+        };
+        if event.is_err() {
+            // The event was not created, so the callback can be freed.
+            drop(unsafe { Box::from_raw(callback) });
+        }
+        event
+    }
+
     /// Sets the trigger for `EventType::TIMER` event.
     ///
     /// # Errors
@@ -609,7 +576,14 @@ impl BootServices {
             TimerTrigger::Periodic(hundreds_ns) => (1, hundreds_ns),
             TimerTrigger::Relative(hundreds_ns) => (2, hundreds_ns),
         };
-        unsafe { (self.set_timer)(event.unsafe_clone(), ty, time) }.into()
+        trace_status!(
+            "BootServices::set_timer",
+            unsafe { (self.raw.set_timer)(event.unsafe_clone(), ty, time) },
+            "type={}, time={}",
+            ty,
+            time
+        )
+        .into()
     }
 
     /// Stops execution until an event is signaled.
@@ -649,7 +623,13 @@ impl BootServices {
     pub fn wait_for_event(&self, events: &mut [Event]) -> Result<usize, Option<usize>> {
         let (number_of_events, events) = (events.len(), events.as_mut_ptr());
         let mut index = MaybeUninit::<usize>::uninit();
-        unsafe { (self.wait_for_event)(number_of_events, events, index.as_mut_ptr()) }.into_with(
+        trace_status!(
+            "BootServices::wait_for_event",
+            unsafe { (self.raw.wait_for_event)(number_of_events, events, index.as_mut_ptr()) },
+            "events={}",
+            number_of_events
+        )
+        .into_with(
             || unsafe { index.assume_init() },
             |s| {
                 if s == Status::INVALID_PARAMETER {
@@ -682,7 +662,10 @@ impl BootServices {
     pub fn signal_event(&self, event: &Event) -> Result {
         // Safety: cloning this event should be safe, as we're directly passing it to firmware
         // and not keeping the clone around.
-        unsafe { (self.signal_event)(event.unsafe_clone()).into() }
+        trace_status!("BootServices::signal_event", unsafe {
+            (self.raw.signal_event)(event.unsafe_clone())
+        })
+        .into()
     }
 
     /// Removes `event` from any event group to which it belongs and closes it. If `event` was
@@ -699,7 +682,10 @@ impl BootServices {
     ///
     /// * [`uefi::Status::INVALID_PARAMETER`]
     pub fn close_event(&self, event: Event) -> Result {
-        unsafe { (self.close_event)(event).into() }
+        trace_status!("BootServices::close_event", unsafe {
+            (self.raw.close_event)(event)
+        })
+        .into()
     }
 
     /// Checks to see if an event is signaled, without blocking execution to wait for it.
@@ -716,7 +702,9 @@ impl BootServices {
     ///
     /// * [`uefi::Status::INVALID_PARAMETER`]
     pub fn check_event(&self, event: Event) -> Result<bool> {
-        let status = unsafe { (self.check_event)(event) };
+        let status = trace_status!("BootServices::check_event", unsafe {
+            (self.raw.check_event)(event)
+        });
         match status {
             Status::SUCCESS => Ok(true),
             Status::NOT_READY => Ok(false),
@@ -747,12 +735,18 @@ impl BootServices {
         protocol: &Guid,
         interface: *mut c_void,
     ) -> Result<Handle> {
-        ((self.install_protocol_interface)(
-            &mut handle,
-            protocol,
-            InterfaceType::NATIVE_INTERFACE,
-            interface,
-        ))
+        trace_status!(
+            "BootServices::install_protocol_interface",
+            (self.raw.install_protocol_interface)(
+                &mut handle,
+                protocol,
+                InterfaceType::NATIVE_INTERFACE,
+                interface,
+            ),
+            "handle={:?}, protocol={}",
+            handle,
+            protocol
+        )
         // this `unwrapped_unchecked` is safe, `handle` is guaranteed to be Some() if this call is
         // successful
         .into_with_val(|| handle.unwrap_unchecked())
@@ -784,7 +778,14 @@ impl BootServices {
         old_interface: *mut c_void,
         new_interface: *mut c_void,
     ) -> Result<()> {
-        (self.reinstall_protocol_interface)(handle, protocol, old_interface, new_interface).into()
+        trace_status!(
+            "BootServices::reinstall_protocol_interface",
+            (self.raw.reinstall_protocol_interface)(handle, protocol, old_interface, new_interface),
+            "handle={:?}, protocol={}",
+            handle,
+            protocol
+        )
+        .into()
     }
 
     /// Removes a protocol interface from a device handle.
@@ -812,7 +813,14 @@ impl BootServices {
         protocol: &Guid,
         interface: *mut c_void,
     ) -> Result<()> {
-        (self.uninstall_protocol_interface)(handle, protocol, interface).into()
+        trace_status!(
+            "BootServices::uninstall_protocol_interface",
+            (self.raw.uninstall_protocol_interface)(handle, protocol, interface),
+            "handle={:?}, protocol={}",
+            handle,
+            protocol
+        )
+        .into()
     }
 
     /// Query a handle for a certain protocol.
@@ -853,10 +861,18 @@ impl BootServices {
         handle: Handle,
     ) -> Result<&UnsafeCell<P>> {
         let mut ptr = ptr::null_mut();
-        (self.handle_protocol)(handle, &P::GUID, &mut ptr).into_with_val(|| {
+        trace_status!(
+            "BootServices::handle_protocol",
+            (self.raw.handle_protocol)(handle, &P::GUID, &mut ptr),
+            "handle={:?}, protocol={}",
+            handle,
+            P::GUID
+        )
+        .into_with_val(|| {
             let ptr = P::mut_ptr_from_ffi(ptr) as *const UnsafeCell<P>;
             &*ptr
         })
+        .with_context("BootServices::handle_protocol")
     }
 
     /// Registers `event` to be signalled whenever a protocol interface is registered for
@@ -882,14 +898,25 @@ impl BootServices {
     ) -> Result<(Event, SearchType)> {
         let mut key: MaybeUninit<ProtocolSearchKey> = MaybeUninit::uninit();
         // Safety: we clone `event` a couple times, but there will be only one left once we return.
-        unsafe { (self.register_protocol_notify)(protocol, event.unsafe_clone(), key.as_mut_ptr()) }
-            // Safety: as long as this call is successful, `key` will be valid.
-            .into_with_val(|| unsafe {
-                (
+        trace_status!(
+            "BootServices::register_protocol_notify",
+            unsafe {
+                (self.raw.register_protocol_notify)(
+                    protocol,
                     event.unsafe_clone(),
-                    SearchType::ByRegisterNotify(key.assume_init()),
+                    key.as_mut_ptr().cast(),
                 )
-            })
+            },
+            "protocol={}",
+            protocol
+        )
+        // Safety: as long as this call is successful, `key` will be valid.
+        .into_with_val(|| unsafe {
+            (
+                event.unsafe_clone(),
+                SearchType::ByRegisterNotify(key.assume_init()),
+            )
+        })
     }
 
     /// Enumerates all handles installed on the system which match a certain query.
@@ -911,31 +938,57 @@ impl BootServices {
         search_ty: SearchType,
         output: Option<&mut [MaybeUninit<Handle>]>,
     ) -> Result<usize> {
-        let handle_size = mem::size_of::<Handle>();
-
         const NULL_BUFFER: *mut MaybeUninit<Handle> = ptr::null_mut();
 
-        let (mut buffer_size, buffer) = match output {
-            Some(buffer) => (buffer.len() * handle_size, buffer.as_mut_ptr()),
-            None => (0, NULL_BUFFER),
-        };
-
-        // Obtain the needed data from the parameters.
-        let (ty, guid, key) = match search_ty {
-            SearchType::AllHandles => (0, None, None),
-            SearchType::ByRegisterNotify(registration) => (1, None, Some(registration)),
-            SearchType::ByProtocol(guid) => (2, Some(guid), None),
+        let (buffer, buffer_len) = match output {
+            Some(buffer) => (buffer.as_mut_ptr(), buffer.len()),
+            None => (NULL_BUFFER, 0),
         };
 
-        let status = unsafe { (self.locate_handle)(ty, guid, key, &mut buffer_size, buffer) };
-
-        // Must convert the returned size (in bytes) to length (number of elements).
-        let buffer_len = buffer_size / handle_size;
+        let (status, buffer_len) =
+            unsafe { self.locate_handle_unchecked(search_ty, buffer, buffer_len) };
 
         match (buffer, status) {
             (NULL_BUFFER, Status::BUFFER_TOO_SMALL) => Ok(buffer_len),
             (_, other_status) => other_status.into_with_val(|| buffer_len),
         }
+        .with_context("BootServices::locate_handle")
+    }
+
+    /// Call `LocateHandle` with a buffer of `buffer_len` handles.
+    ///
+    /// Returns the status of the call along with the number of handles
+    /// that were written, or the required number of handles if the
+    /// status is [`Status::BUFFER_TOO_SMALL`].
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be null or valid for writes of `buffer_len` handles.
+    unsafe fn locate_handle_unchecked(
+        &self,
+        search_ty: SearchType,
+        buffer: *mut MaybeUninit<Handle>,
+        buffer_len: usize,
+    ) -> (Status, usize) {
+        let handle_size = mem::size_of::<Handle>();
+        let input_size = buffer_len * handle_size;
+        let mut buffer_size = input_size;
+
+        // Obtain the needed data from the parameters.
+        let (ty, guid, key) = search_ty.to_raw();
+
+        // The firmware overwrites `buffer_size`, so both sizes are traced.
+        let status = trace_status!(
+            "BootServices::locate_handle",
+            (self.raw.locate_handle)(ty, guid, key, &mut buffer_size, buffer.cast()),
+            "type={}, size={}, returned_size={}",
+            ty,
+            input_size,
+            buffer_size
+        );
+
+        // Must convert the returned size (in bytes) to length (number of elements).
+        (status, buffer_size / handle_size)
     }
 
     /// Locates the handle to a device on the device path that supports the specified protocol.
@@ -960,14 +1013,19 @@ impl BootServices {
         device_path: &mut &DevicePath,
     ) -> Result<Handle> {
         let mut handle = MaybeUninit::uninit();
-        let mut device_path_ptr = device_path.as_ffi_ptr();
+        let mut device_path_ptr = device_path.as_ffi_ptr().cast();
         unsafe {
-            (self.locate_device_path)(&P::GUID, &mut device_path_ptr, &mut handle).into_with_val(
-                || {
-                    *device_path = DevicePath::from_ffi_ptr(device_path_ptr);
-                    handle.assume_init()
-                },
+            trace_status!(
+                "BootServices::locate_device_path",
+                (self.raw.locate_device_path)(&P::GUID, &mut device_path_ptr, handle.as_mut_ptr()),
+                "protocol={}",
+                P::GUID
             )
+            .into_with_val(|| {
+                *device_path = DevicePath::from_ffi_ptr(device_path_ptr.cast());
+                handle.assume_init()
+            })
+            .with_context("BootServices::locate_device_path")
         }
     }
 
@@ -1016,6 +1074,7 @@ impl BootServices {
             .first()
             .cloned()
             .ok_or_else(|| Status::NOT_FOUND.into())
+            .with_context("BootServices::get_handle_for_protocol")
     }
 
     /// Load an EFI image into memory and return a [`Handle`] to the image.
@@ -1054,46 +1113,51 @@ impl BootServices {
         parent_image_handle: Handle,
         source: LoadImageSource,
     ) -> uefi::Result<Handle> {
-        let boot_policy;
-        let device_path;
-        let source_buffer;
-        let source_size;
-        match source {
+        let (boot_policy, device_path, source_buffer, source_size) = match source {
             LoadImageSource::FromBuffer { buffer, file_path } => {
                 // Boot policy is ignored when loading from source buffer.
-                boot_policy = 0;
-
-                device_path = file_path.map(|p| p.as_ffi_ptr()).unwrap_or(ptr::null());
-                source_buffer = buffer.as_ptr();
-                source_size = buffer.len();
+                let device_path = file_path.map_or(ptr::null(), |p| p.as_ffi_ptr());
+                (0, device_path, buffer.as_ptr(), buffer.len())
             }
             LoadImageSource::FromFilePath {
                 file_path,
                 from_boot_manager,
-            } => {
-                boot_policy = u8::from(from_boot_manager);
-                device_path = file_path.as_ffi_ptr();
-                source_buffer = ptr::null();
-                source_size = 0;
-            }
+            } => (
+                u8::from(from_boot_manager),
+                file_path.as_ffi_ptr(),
+                ptr::null(),
+                0,
+            ),
         };
 
         let mut image_handle = MaybeUninit::uninit();
         unsafe {
-            (self.load_image)(
+            trace_status!(
+                "BootServices::load_image",
+                (self.raw.load_image)(
+                    boot_policy,
+                    parent_image_handle,
+                    device_path.cast(),
+                    source_buffer,
+                    source_size,
+                    image_handle.as_mut_ptr(),
+                ),
+                "boot_policy={}, parent={:?}, size={}",
                 boot_policy,
                 parent_image_handle,
-                device_path,
-                source_buffer,
-                source_size,
-                &mut image_handle,
+                source_size
             )
             .into_with_val(|| image_handle.assume_init())
+            .with_context("BootServices::load_image")
         }
     }
 
     /// Unload an EFI image.
     ///
+    /// An image that was loaded but not started is always unloaded. A
+    /// started image is only unloaded if it has an unload function, see
+    /// [`LoadedImage::set_unload`], which is called first and may refuse.
+    ///
     /// # Errors
     ///
     /// See section `EFI_BOOT_SERVICES.UnloadImage()` in the UEFI Specification for more details.
@@ -1106,7 +1170,13 @@ impl BootServices {
     /// * [`uefi::Status::UNSUPPORTED`]
     /// * [`uefi::Status::INVALID_PARAMETER`]
     pub fn unload_image(&self, image_handle: Handle) -> Result {
-        (self.unload_image)(image_handle).into()
+        trace_status!(
+            "BootServices::unload_image",
+            unsafe { (self.raw.unload_image)(image_handle) },
+            "image={:?}",
+            image_handle
+        )
+        .into()
     }
 
     /// Transfer control to a loaded image's entry point.
@@ -1122,17 +1192,112 @@ impl BootServices {
     ///
     /// * [`uefi::Status::UNSUPPORTED`]
     pub fn start_image(&self, image_handle: Handle) -> Result {
-        unsafe {
-            // TODO: implement returning exit data to the caller.
-            let mut exit_data_size: usize = 0;
-            let mut exit_data: *mut Char16 = ptr::null_mut();
-            (self.start_image)(image_handle, &mut exit_data_size, &mut exit_data).into()
+        // Any exit data is freed when dropped.
+        self.start_image_with_exit_data(image_handle)
+            .map(|_| ())
+            .map_err(|err| err.status().into())
+            .with_context("BootServices::start_image")
+    }
+
+    /// Transfer control to a loaded image's entry point, and return the exit
+    /// data the image passed to [`exit_with_data`], if any.
+    ///
+    /// The exit data is returned both on success and on error, since an
+    /// image typically uses it to describe why it failed.
+    ///
+    /// [`exit_with_data`]: BootServices::exit_with_data
+    ///
+    /// # Errors
+    ///
+    /// See [`start_image`]. The error payload is the exit data of the image,
+    /// if any.
+    ///
+    /// [`start_image`]: BootServices::start_image
+    pub fn start_image_with_exit_data(
+        &self,
+        image_handle: Handle,
+    ) -> Result<Option<ExitData<'_>>, Option<ExitData<'_>>> {
+        let mut exit_data_size: usize = 0;
+        let mut exit_data: *mut Char16 = ptr::null_mut();
+        let status = trace_status!(
+            "BootServices::start_image",
+            unsafe { (self.raw.start_image)(image_handle, &mut exit_data_size, &mut exit_data) },
+            "image={:?}",
+            image_handle
+        );
+        let data = || {
+            NonNull::new(exit_data).map(|buffer| ExitData {
+                boot_services: self,
+                buffer,
+                size: exit_data_size,
+            })
+        };
+        status.into_with(data, |_| data())
+    }
+
+    /// Load an EFI application from `path` and run it to completion.
+    ///
+    /// This combines [`load_image`], setting the [`LoadedImage`] load
+    /// options, and [`start_image`]. The image is loaded with the
+    /// current [`image_handle`] as its parent. If `options` is set, it
+    /// is passed to the image as its command line.
+    ///
+    /// On success, returns the exit status of the started image. Note
+    /// that this may be an error status; it is up to the caller to
+    /// decide how to handle it. If the image cannot be loaded or
+    /// prepared, the error is returned and the image is unloaded if
+    /// needed.
+    ///
+    /// [`image_handle`]: BootServices::image_handle
+    /// [`load_image`]: BootServices::load_image
+    /// [`start_image`]: BootServices::start_image
+    ///
+    /// # Errors
+    ///
+    /// Any error returned by [`load_image`] or by opening the
+    /// [`LoadedImage`] protocol on the new image.
+    pub fn launch(&self, path: &DevicePath, options: Option<&CStr16>) -> Result<Status> {
+        let image = self.load_image(
+            self.image_handle(),
+            LoadImageSource::FromFilePath {
+                file_path: path,
+                from_boot_manager: false,
+            },
+        )?;
+
+        if let Some(options) = options {
+            let set_options =
+                self.open_protocol_exclusive::<LoadedImage>(image)
+                    .and_then(|mut loaded_image| {
+                        let options = options.as_slice_with_nul();
+                        let size = u32::try_from(mem::size_of_val(options))
+                            .map_err(|_| Error::from(Status::INVALID_PARAMETER))?;
+                        // Safety: `options` outlives the call to `start_image`
+                        // below, which is the only time the image can access it.
+                        unsafe { loaded_image.set_load_options(options.as_ptr().cast(), size) };
+                        Ok(())
+                    });
+            if let Err(err) = set_options {
+                // Ignore unload errors; the original error is more useful.
+                let _ = self.unload_image(image);
+                return Err(err);
+            }
         }
+
+        Ok(match self.start_image(image) {
+            Ok(()) => Status::SUCCESS,
+            Err(err) => err.status(),
+        })
     }
 
     /// Exits the UEFI application and returns control to the UEFI component
     /// that started the UEFI application.
     ///
+    /// With the `alloc` feature, the callbacks registered with
+    /// [`cleanup::on_exit`] are run first.
+    ///
+    /// [`cleanup::on_exit`]: crate::cleanup::on_exit
+    ///
     /// # Safety
     ///
     /// This function is unsafe because it is up to the caller to ensure that
@@ -1146,7 +1311,50 @@ impl BootServices {
         exit_data_size: usize,
         exit_data: *mut Char16,
     ) -> ! {
-        (self.exit)(image_handle, exit_status, exit_data_size, exit_data)
+        #[cfg(feature = "alloc")]
+        crate::cleanup::run_exit_callbacks();
+
+        (self.raw.exit)(image_handle, exit_status, exit_data_size, exit_data)
+    }
+
+    /// Exits the UEFI application like [`exit`], passing exit data to the
+    /// caller of [`start_image_with_exit_data`].
+    ///
+    /// The exit data consists of `description`, which should be a
+    /// human-readable reason for exiting, followed by the optional `binary`
+    /// payload. It is copied to a pool allocation of type
+    /// [`MemoryType::LOADER_DATA`], which the caller frees. If that
+    /// allocation fails, the image exits without exit data.
+    ///
+    /// [`exit`]: BootServices::exit
+    /// [`start_image_with_exit_data`]: BootServices::start_image_with_exit_data
+    ///
+    /// # Safety
+    ///
+    /// See [`exit`].
+    pub unsafe fn exit_with_data(
+        &self,
+        image_handle: Handle,
+        exit_status: Status,
+        description: &CStr16,
+        binary: &[u8],
+    ) -> ! {
+        let description = description.as_slice_with_nul();
+        let description_size = mem::size_of_val(description);
+        let size = description_size + binary.len();
+
+        match self.allocate_pool(MemoryType::LOADER_DATA, size) {
+            Ok(buffer) => {
+                ptr::copy_nonoverlapping(description.as_ptr().cast(), buffer, description_size);
+                ptr::copy_nonoverlapping(
+                    binary.as_ptr(),
+                    buffer.add(description_size),
+                    binary.len(),
+                );
+                self.exit(image_handle, exit_status, size, buffer.cast())
+            }
+            Err(_) => self.exit(image_handle, exit_status, 0, ptr::null_mut()),
+        }
     }
 
     /// Exits the UEFI boot services
@@ -1170,14 +1378,30 @@ impl BootServices {
         image: Handle,
         mmap_key: MemoryMapKey,
     ) -> Result {
-        (self.exit_boot_services)(image, mmap_key).into()
+        (self.raw.exit_boot_services)(image, mmap_key.0).into()
     }
 
     /// Stalls the processor for an amount of time.
     ///
     /// The time is in microseconds.
     pub fn stall(&self, time: usize) {
-        assert_eq!((self.stall)(time), Status::SUCCESS);
+        assert_eq!(unsafe { (self.raw.stall)(time) }, Status::SUCCESS);
+    }
+
+    /// Stalls the processor for `duration`, rounded up to the nearest
+    /// microsecond.
+    ///
+    /// This busy-waits; for longer waits prefer [`time::sleep`], which
+    /// lets the firmware idle the CPU until a timer fires.
+    ///
+    /// [`time::sleep`]: crate::time::sleep
+    pub fn stall_duration(&self, duration: Duration) {
+        let mut micros = div_ceil_u128(duration.as_nanos(), 1000);
+        while micros > 0 {
+            let chunk = usize::try_from(micros).unwrap_or(usize::MAX);
+            self.stall(chunk);
+            micros -= chunk as u128;
+        }
     }
 
     /// Set the watchdog timer.
@@ -1227,7 +1451,102 @@ impl BootServices {
             })
             .unwrap_or((0, ptr::null_mut()));
 
-        unsafe { (self.set_watchdog_timer)(timeout, watchdog_code, data_len, data) }.into()
+        let status = trace_status!(
+            "BootServices::set_watchdog_timer",
+            unsafe { (self.raw.set_watchdog_timer)(timeout, watchdog_code, data_len, data) },
+            "timeout={}, code={:#x}",
+            timeout,
+            watchdog_code
+        );
+        if status.is_success() {
+            WATCHDOG_TIMEOUT.store(timeout, Ordering::Relaxed);
+            WATCHDOG_CODE.store(watchdog_code, Ordering::Relaxed);
+        }
+        status.into()
+    }
+
+    /// Run `f` with the watchdog timer extended to at least `timeout`.
+    ///
+    /// This is intended for long-running operations (such as copying or
+    /// hashing large files) that may not finish before the watchdog
+    /// fires. The watchdog is set to `timeout` (rounded up to whole
+    /// seconds), and a periodic timer event re-arms it at half that
+    /// interval for as long as `f` runs, so `timeout` only needs to cover
+    /// the longest stretch during which `f` blocks event processing.
+    ///
+    /// Afterwards, the watchdog is restored to the timeout and code most
+    /// recently passed to [`set_watchdog_timer`] (or the firmware's
+    /// default of five minutes). Nested calls extend the watchdog to the
+    /// larger of the two timeouts, and restore the extension of the
+    /// enclosing call when they return. The watchdog is also restored if
+    /// `f` panics and the panic unwinds. Note that restoring the watchdog
+    /// starts a new countdown.
+    ///
+    /// # Errors
+    ///
+    /// Errors from setting up the watchdog and timer event are returned
+    /// before `f` is called, after restoring the watchdog. If restoring
+    /// the watchdog fails, the error is returned and the output of `f` is
+    /// dropped.
+    ///
+    /// See [`set_watchdog_timer`], [`create_event`], and [`set_timer`].
+    ///
+    /// [`create_event`]: Self::create_event
+    /// [`set_timer`]: Self::set_timer
+    /// [`set_watchdog_timer`]: Self::set_watchdog_timer
+    pub fn with_watchdog_extended<R>(&self, timeout: Duration, f: impl FnOnce() -> R) -> Result<R> {
+        unsafe extern "efiapi" fn refresh_watchdog(_: Event, context: Option<NonNull<c_void>>) {
+            if let Some(context) = context {
+                let extension = context.cast::<WatchdogExtension>().as_ref();
+                // Nothing useful can be done with an error here.
+                let _ = extension.arm();
+            }
+        }
+
+        let outer = current_watchdog_extension();
+        // Safety: the enclosing extension is alive until its call returns.
+        let outer_ref = unsafe { outer.as_ref() };
+        let timeout_secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() != 0);
+        let extension = WatchdogExtension {
+            boot_services: self,
+            timeout: usize::try_from(timeout_secs.max(1))
+                .unwrap_or(usize::MAX)
+                .max(outer_ref.map_or(0, |outer| outer.timeout)),
+            code: outer_ref
+                .map_or_else(|| WATCHDOG_CODE.load(Ordering::Relaxed), |outer| outer.code),
+            outer,
+        };
+        // Refresh period in units of 100ns.
+        let period = (u64::try_from(extension.timeout).unwrap_or(u64::MAX) / 2)
+            .max(1)
+            .saturating_mul(10_000_000);
+
+        extension.arm().into_with_val(|| ())?;
+
+        // From here on the guard cancels the refresh and restores the
+        // watchdog, including when `f` panics. It is declared after
+        // `extension`, so it is dropped first.
+        let mut guard = WatchdogGuard {
+            extension: &extension,
+            event: None,
+            active: true,
+        };
+        set_current_watchdog_extension(&extension);
+
+        // Safety: the guard closes the event before `extension` is dropped.
+        let event = unsafe {
+            self.create_event(
+                EventType::TIMER | EventType::NOTIFY_SIGNAL,
+                Tpl::CALLBACK,
+                Some(refresh_watchdog),
+                Some(NonNull::from(&extension).cast()),
+            )
+        }?;
+        let event = guard.event.insert(event);
+        self.set_timer(event, TimerTrigger::Periodic(period))?;
+
+        let output = f();
+        guard.restore().into_with_val(|| output)
     }
 
     /// Connect one or more drivers to a controller.
@@ -1250,16 +1569,21 @@ impl BootServices {
         remaining_device_path: Option<&DevicePath>,
         recursive: bool,
     ) -> Result {
-        unsafe {
-            (self.connect_controller)(
-                controller,
-                driver_image,
-                remaining_device_path
-                    .map(|dp| dp.as_ffi_ptr())
-                    .unwrap_or(ptr::null()),
-                recursive,
-            )
-        }
+        trace_status!(
+            "BootServices::connect_controller",
+            unsafe {
+                (self.raw.connect_controller)(
+                    controller,
+                    driver_image,
+                    remaining_device_path.map_or(ptr::null(), |dp| dp.as_ffi_ptr().cast()),
+                    recursive,
+                )
+            },
+            "controller={:?}, driver={:?}, recursive={}",
+            controller,
+            driver_image,
+            recursive
+        )
         .into_with_err(|_| ())
     }
 
@@ -1280,8 +1604,15 @@ impl BootServices {
         driver_image: Option<Handle>,
         child: Option<Handle>,
     ) -> Result {
-        unsafe { (self.disconnect_controller)(controller, driver_image, child) }
-            .into_with_err(|_| ())
+        trace_status!(
+            "BootServices::disconnect_controller",
+            unsafe { (self.raw.disconnect_controller)(controller, driver_image, child) },
+            "controller={:?}, driver={:?}, child={:?}",
+            controller,
+            driver_image,
+            child
+        )
+        .into_with_err(|_| ())
     }
 
     /// Open a protocol interface for a handle.
@@ -1333,13 +1664,21 @@ impl BootServices {
         attributes: OpenProtocolAttributes,
     ) -> Result<ScopedProtocol<P>> {
         let mut interface = ptr::null_mut();
-        (self.open_protocol)(
+        let attributes = attributes as u32;
+        trace_status!(
+            "BootServices::open_protocol",
+            (self.raw.open_protocol)(
+                params.handle,
+                &P::GUID,
+                &mut interface,
+                params.agent,
+                params.controller,
+                attributes,
+            ),
+            "handle={:?}, protocol={}, attributes={:#x}",
             params.handle,
-            &P::GUID,
-            &mut interface,
-            params.agent,
-            params.controller,
-            attributes as u32,
+            P::GUID,
+            attributes
         )
         .into_with_val(|| {
             let interface = P::mut_ptr_from_ffi(interface) as *const UnsafeCell<P>;
@@ -1351,6 +1690,7 @@ impl BootServices {
                 boot_services: self,
             }
         })
+        .with_context("BootServices::open_protocol")
     }
 
     /// Open a protocol interface for a handle in exclusive mode.
@@ -1403,13 +1743,21 @@ impl BootServices {
     ) -> Result<()> {
         const TEST_PROTOCOL: u32 = 0x04;
         let mut interface = ptr::null_mut();
-        (self.open_protocol)(
+        trace_status!(
+            "BootServices::open_protocol",
+            unsafe {
+                (self.raw.open_protocol)(
+                    params.handle,
+                    &P::GUID,
+                    &mut interface,
+                    params.agent,
+                    params.controller,
+                    TEST_PROTOCOL,
+                )
+            },
+            "handle={:?}, protocol={}, attributes=TEST_PROTOCOL",
             params.handle,
-            &P::GUID,
-            &mut interface,
-            params.agent,
-            params.controller,
-            TEST_PROTOCOL,
+            P::GUID
         )
         .into_with_val(|| ())
     }
@@ -1427,7 +1775,12 @@ impl BootServices {
         let mut protocols = ptr::null_mut();
         let mut count = 0;
 
-        let mut status = unsafe { (self.protocols_per_handle)(handle, &mut protocols, &mut count) };
+        let mut status = trace_status!(
+            "BootServices::protocols_per_handle",
+            unsafe { (self.raw.protocols_per_handle)(handle, &mut protocols, &mut count) },
+            "handle={:?}",
+            handle
+        );
 
         if !status.is_error() {
             // Ensure that protocols isn't null, and that none of the GUIDs
@@ -1449,6 +1802,73 @@ impl BootServices {
         })
     }
 
+    /// Get the list of agents that have opened the protocol `protocol` on
+    /// `handle`.
+    ///
+    /// # Errors
+    ///
+    /// See section `EFI_BOOT_SERVICES.OpenProtocolInformation()` in the UEFI Specification for more details.
+    ///
+    /// * [`uefi::Status::NOT_FOUND`]
+    pub fn open_protocol_information(
+        &self,
+        handle: Handle,
+        protocol: &Guid,
+    ) -> Result<OpenProtocolInformation<'_>> {
+        let mut entries = ptr::null_mut();
+        let mut count = 0;
+
+        let mut status = trace_status!(
+            "BootServices::open_protocol_information",
+            unsafe {
+                (self.raw.open_protocol_information)(handle, protocol, &mut entries, &mut count)
+            },
+            "handle={:?}, protocol={}",
+            handle,
+            protocol
+        );
+        // The buffer may be null if there are no entries.
+        if status.is_success() && entries.is_null() && count != 0 {
+            status = Status::OUT_OF_RESOURCES;
+        }
+
+        status.into_with_val(|| OpenProtocolInformation {
+            boot_services: self,
+            entries,
+            count,
+        })
+    }
+
+    /// Get a description of all the protocols installed on `handle`, with
+    /// their names if they are well-known, and the agents that opened them.
+    ///
+    /// This is meant for diagnostics, such as debugging device enumeration.
+    /// The returned [`HandleDump`] can be printed with `Display`, as
+    /// [`report`](crate::report) records.
+    ///
+    /// # Errors
+    ///
+    /// See [`protocols_per_handle`] and [`open_protocol_information`].
+    ///
+    /// [`protocols_per_handle`]: Self::protocols_per_handle
+    /// [`open_protocol_information`]: Self::open_protocol_information
+    #[cfg(feature = "alloc")]
+    pub fn dump_handle(&self, handle: Handle) -> Result<HandleDump> {
+        let protocols = self
+            .protocols_per_handle(handle)?
+            .iter()
+            .map(|&&guid| {
+                let open_info = try_to_vec(&self.open_protocol_information(handle, &guid)?)?;
+                Ok(ProtocolDump {
+                    guid,
+                    name: crate::proto::protocol_name(&guid),
+                    open_info,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(HandleDump { handle, protocols })
+    }
+
     /// Returns an array of handles that support the requested protocol in a buffer allocated from
     /// pool.
     ///
@@ -1464,18 +1884,22 @@ impl BootServices {
         let mut buffer: *mut Handle = ptr::null_mut();
 
         // Obtain the needed data from the parameters.
-        let (ty, guid, key) = match search_ty {
-            SearchType::AllHandles => (0, None, None),
-            SearchType::ByRegisterNotify(registration) => (1, None, Some(registration)),
-            SearchType::ByProtocol(guid) => (2, Some(guid), None),
-        };
+        let (ty, guid, key) = search_ty.to_raw();
 
-        unsafe { (self.locate_handle_buffer)(ty, guid, key, &mut num_handles, &mut buffer) }
-            .into_with_val(|| HandleBuffer {
-                boot_services: self,
-                count: num_handles,
-                buffer,
-            })
+        trace_status!(
+            "BootServices::locate_handle_buffer",
+            unsafe {
+                (self.raw.locate_handle_buffer)(ty, guid, key, &mut num_handles, &mut buffer)
+            },
+            "type={}",
+            ty
+        )
+        .into_with_val(|| HandleBuffer {
+            boot_services: self,
+            count: num_handles,
+            buffer,
+        })
+        .with_context("BootServices::locate_handle_buffer")
     }
 
     /// Returns a protocol implementation, if present on the system.
@@ -1505,10 +1929,17 @@ impl BootServices {
     )]
     pub unsafe fn locate_protocol<P: ProtocolPointer + ?Sized>(&self) -> Result<&UnsafeCell<P>> {
         let mut ptr = ptr::null_mut();
-        (self.locate_protocol)(&P::GUID, ptr::null_mut(), &mut ptr).into_with_val(|| {
+        trace_status!(
+            "BootServices::locate_protocol",
+            (self.raw.locate_protocol)(&P::GUID, ptr::null_mut(), &mut ptr),
+            "protocol={}",
+            P::GUID
+        )
+        .into_with_val(|| {
             let ptr = P::mut_ptr_from_ffi(ptr) as *const UnsafeCell<P>;
             &*ptr
         })
+        .with_context("BootServices::locate_protocol")
     }
 
     /// Copies memory from source to destination. The buffers can overlap.
@@ -1518,7 +1949,7 @@ impl BootServices {
     /// This function is unsafe as it can be used to violate most safety
     /// invariants of the Rust type system.
     pub unsafe fn memmove(&self, dest: *mut u8, src: *const u8, size: usize) {
-        (self.copy_mem)(dest, src, size);
+        (self.raw.copy_mem)(dest, src, size);
     }
 
     /// Sets a buffer to a certain value.
@@ -1528,7 +1959,7 @@ impl BootServices {
     /// This function is unsafe as it can be used to violate most safety
     /// invariants of the Rust type system.
     pub unsafe fn set_mem(&self, buffer: *mut u8, size: usize, value: u8) {
-        (self.set_mem)(buffer, size, value);
+        (self.raw.set_mem)(buffer, size, value);
     }
 }
 
@@ -1545,23 +1976,92 @@ impl BootServices {
         // Search by protocol.
         let search_type = SearchType::from_proto::<P>();
 
-        // Determine how much we need to allocate.
-        let buffer_size = self.locate_handle(search_type, None)?;
+        call_with_growing_buffer(0, |buffer| {
+            let (status, len) = unsafe {
+                self.locate_handle_unchecked(search_type, buffer.as_mut_ptr(), buffer.len())
+            };
+            status.into_with(|| len, |_| Some(len))
+        })
+    }
+
+    /// Retrieves the current memory map into a newly allocated [`Vec`].
+    ///
+    /// Unlike [`memory_map`], the entries in the returned vector are
+    /// always `size_of::<MemoryDescriptor>()` apart, even if the firmware
+    /// uses a larger descriptor size. The returned key is valid until
+    /// the next allocation.
+    ///
+    /// # Errors
+    ///
+    /// See section `EFI_BOOT_SERVICES.GetMemoryMap()` in the UEFI Specification for more details.
+    ///
+    /// * [`uefi::Status::INVALID_PARAMETER`]
+    ///
+    /// [`memory_map`]: Self::memory_map
+    pub fn memory_map_vec(&self) -> Result<(MemoryMapKey, Vec<MemoryDescriptor>)> {
+        let desc_size = mem::size_of::<MemoryDescriptor>();
+        let mut map_key = MemoryMapKey(0);
 
-        // Allocate a large enough buffer without pointless initialization.
-        let mut handles = Vec::with_capacity(buffer_size);
-        let buffer = handles.spare_capacity_mut();
+        let map = call_with_growing_buffer(0, |buffer: &mut [MaybeUninit<MemoryDescriptor>]| {
+            let mut map_size = buffer.len() * desc_size;
+            let mut entry_size = 0;
+            let mut entry_version = 0;
+
+            let status = trace_status!(
+                "BootServices::get_memory_map",
+                unsafe {
+                    (self.raw.get_memory_map)(
+                        &mut map_size,
+                        buffer.as_mut_ptr().cast(),
+                        &mut map_key.0,
+                        &mut entry_size,
+                        &mut entry_version,
+                    )
+                },
+                "size={}, returned_size={}",
+                buffer.len() * desc_size,
+                map_size
+            );
+
+            if status == Status::BUFFER_TOO_SMALL {
+                // Allocating the buffer may add entries to the map, so
+                // leave room for a couple more.
+                let required_len = (map_size + entry_size * 2) / desc_size + 1;
+                return status.into_with(|| 0, |_| Some(required_len));
+            }
 
-        // Perform the search.
-        let buffer_size = self.locate_handle(search_type, Some(buffer))?;
+            status.into_with(
+                || {
+                    let len = map_size / entry_size;
+                    // Remove any padding between entries. Entries only
+                    // move towards the start of the buffer, so each copy
+                    // reads data that has not been overwritten yet.
+                    let base = buffer.as_mut_ptr().cast::<u8>();
+                    for i in 0..len {
+                        unsafe {
+                            ptr::copy(base.add(i * entry_size), base.add(i * desc_size), desc_size);
+                        }
+                    }
+                    len
+                },
+                |_| None,
+            )
+        })?;
 
-        // Mark the returned number of elements as initialized.
-        unsafe {
-            handles.set_len(buffer_size);
-        }
+        Ok((map_key, map))
+    }
 
-        // Emit output, with warnings
-        Ok(handles)
+    /// Retrieves the current memory map as a [`MemoryMapDump`], which can be
+    /// printed with `Display` as [`report`](crate::report) records.
+    ///
+    /// # Errors
+    ///
+    /// See [`memory_map_vec`].
+    ///
+    /// [`memory_map_vec`]: Self::memory_map_vec
+    pub fn dump_memory_map(&self) -> Result<MemoryMapDump> {
+        let (key, descriptors) = self.memory_map_vec()?;
+        Ok(MemoryMapDump { key, descriptors })
     }
 
     /// Retrieves the `SimpleFileSystem` protocol associated with
@@ -1604,125 +2104,143 @@ impl super::Table for BootServices {
 impl Debug for BootServices {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("BootServices")
-            .field("header", &self.header)
-            .field("raise_tpl (fn ptr)", &(self.raise_tpl as *const usize))
-            .field("restore_tpl (fn ptr)", &(self.restore_tpl as *const usize))
+            .field("header", &self.raw.header)
+            .field("raise_tpl (fn ptr)", &(self.raw.raise_tpl as *const usize))
+            .field(
+                "restore_tpl (fn ptr)",
+                &(self.raw.restore_tpl as *const usize),
+            )
             .field(
                 "allocate_pages (fn ptr)",
-                &(self.allocate_pages as *const usize),
+                &(self.raw.allocate_pages as *const usize),
+            )
+            .field(
+                "free_pages (fn ptr)",
+                &(self.raw.free_pages as *const usize),
             )
-            .field("free_pages (fn ptr)", &(self.free_pages as *const usize))
             .field(
                 "get_memory_map (fn ptr)",
-                &(self.get_memory_map as *const usize),
+                &(self.raw.get_memory_map as *const usize),
             )
             .field(
                 "allocate_pool (fn ptr)",
-                &(self.allocate_pool as *const usize),
+                &(self.raw.allocate_pool as *const usize),
             )
-            .field("free_pool (fn ptr)", &(self.free_pool as *const usize))
+            .field("free_pool (fn ptr)", &(self.raw.free_pool as *const usize))
             .field(
                 "create_event (fn ptr)",
-                &(self.create_event as *const usize),
+                &(self.raw.create_event as *const usize),
             )
-            .field("set_timer (fn ptr)", &(self.set_timer as *const usize))
+            .field("set_timer (fn ptr)", &(self.raw.set_timer as *const usize))
             .field(
                 "wait_for_event (fn ptr)",
-                &(self.wait_for_event as *const usize),
+                &(self.raw.wait_for_event as *const usize),
             )
-            .field("signal_event", &(self.signal_event as *const usize))
-            .field("close_event", &(self.close_event as *const usize))
-            .field("check_event", &(self.check_event as *const usize))
+            .field("signal_event", &(self.raw.signal_event as *const usize))
+            .field("close_event", &(self.raw.close_event as *const usize))
+            .field("check_event", &(self.raw.check_event as *const usize))
             .field(
                 "install_protocol_interface",
-                &(self.install_protocol_interface as *const usize),
+                &(self.raw.install_protocol_interface as *const usize),
             )
             .field(
                 "reinstall_protocol_interface",
-                &(self.reinstall_protocol_interface as *const usize),
+                &(self.raw.reinstall_protocol_interface as *const usize),
             )
             .field(
                 "uninstall_protocol_interface",
-                &(self.uninstall_protocol_interface as *const usize),
+                &(self.raw.uninstall_protocol_interface as *const usize),
             )
             .field(
                 "handle_protocol (fn ptr)",
-                &(self.handle_protocol as *const usize),
+                &(self.raw.handle_protocol as *const usize),
             )
             .field(
                 "register_protocol_notify",
-                &(self.register_protocol_notify as *const usize),
+                &(self.raw.register_protocol_notify as *const usize),
             )
             .field(
                 "locate_handle (fn ptr)",
-                &(self.locate_handle as *const usize),
+                &(self.raw.locate_handle as *const usize),
             )
             .field(
                 "locate_device_path (fn ptr)",
-                &(self.locate_device_path as *const usize),
+                &(self.raw.locate_device_path as *const usize),
             )
             .field(
                 "install_configuration_table",
-                &(self.install_configuration_table as *const usize),
+                &(self.raw.install_configuration_table as *const usize),
+            )
+            .field(
+                "load_image (fn ptr)",
+                &(self.raw.load_image as *const usize),
             )
-            .field("load_image (fn ptr)", &(self.load_image as *const usize))
-            .field("start_image (fn ptr)", &(self.start_image as *const usize))
-            .field("exit", &(self.exit as *const usize))
+            .field(
+                "start_image (fn ptr)",
+                &(self.raw.start_image as *const usize),
+            )
+            .field("exit", &(self.raw.exit as *const usize))
             .field(
                 "unload_image (fn ptr)",
-                &(self.unload_image as *const usize),
+                &(self.raw.unload_image as *const usize),
             )
             .field(
                 "exit_boot_services (fn ptr)",
-                &(self.exit_boot_services as *const usize),
+                &(self.raw.exit_boot_services as *const usize),
             )
             .field(
                 "get_next_monotonic_count",
-                &(self.get_next_monotonic_count as *const usize),
+                &(self.raw.get_next_monotonic_count as *const usize),
             )
-            .field("stall (fn ptr)", &(self.stall as *const usize))
+            .field("stall (fn ptr)", &(self.raw.stall as *const usize))
             .field(
                 "set_watchdog_timer (fn ptr)",
-                &(self.set_watchdog_timer as *const usize),
+                &(self.raw.set_watchdog_timer as *const usize),
             )
             .field(
                 "connect_controller",
-                &(self.connect_controller as *const usize),
+                &(self.raw.connect_controller as *const usize),
             )
             .field(
                 "disconnect_controller",
-                &(self.disconnect_controller as *const usize),
+                &(self.raw.disconnect_controller as *const usize),
             )
-            .field("open_protocol", &(self.open_protocol as *const usize))
-            .field("close_protocol", &(self.close_protocol as *const usize))
+            .field("open_protocol", &(self.raw.open_protocol as *const usize))
+            .field("close_protocol", &(self.raw.close_protocol as *const usize))
             .field(
                 "open_protocol_information",
-                &(self.open_protocol_information as *const usize),
+                &(self.raw.open_protocol_information as *const usize),
             )
             .field(
                 "protocols_per_handle",
-                &(self.protocols_per_handle as *const usize),
+                &(self.raw.protocols_per_handle as *const usize),
             )
             .field(
                 "locate_handle_buffer",
-                &(self.locate_handle_buffer as *const usize),
+                &(self.raw.locate_handle_buffer as *const usize),
             )
             .field(
                 "locate_protocol (fn ptr)",
-                &(self.locate_protocol as *const usize),
+                &(self.raw.locate_protocol as *const usize),
             )
             .field(
                 "install_multiple_protocol_interfaces",
-                &(self.install_multiple_protocol_interfaces as *const usize),
+                &(self.raw.install_multiple_protocol_interfaces as *const usize),
             )
             .field(
                 "uninstall_multiple_protocol_interfaces",
-                &(self.uninstall_multiple_protocol_interfaces as *const usize),
+                &(self.raw.uninstall_multiple_protocol_interfaces as *const usize),
+            )
+            .field(
+                "calculate_crc32",
+                &(self.raw.calculate_crc32 as *const usize),
+            )
+            .field("copy_mem (fn ptr)", &(self.raw.copy_mem as *const usize))
+            .field("set_mem (fn ptr)", &(self.raw.set_mem as *const usize))
+            .field(
+                "create_event_ex",
+                &(self.raw.create_event_ex as *const usize),
             )
-            .field("calculate_crc32", &(self.calculate_crc32 as *const usize))
-            .field("copy_mem (fn ptr)", &(self.copy_mem as *const usize))
-            .field("set_mem (fn ptr)", &(self.set_mem as *const usize))
-            .field("create_event_ex", &(self.create_event_ex as *const usize))
             .finish()
     }
 }
@@ -1758,31 +2276,6 @@ pub enum LoadImageSource<'a> {
     },
 }
 
-newtype_enum! {
-/// Task priority level.
-///
-/// Although the UEFI specification repeatedly states that only the variants
-/// specified below should be used in application-provided input, as the other
-/// are reserved for internal firmware use, it might still happen that the
-/// firmware accidentally discloses one of these internal TPLs to us.
-///
-/// Since feeding an unexpected variant to a Rust enum is UB, this means that
-/// this C enum must be interfaced via the newtype pattern.
-pub enum Tpl: usize => {
-    /// Normal task execution level.
-    APPLICATION = 4,
-    /// Async interrupt-style callbacks run at this TPL.
-    CALLBACK    = 8,
-    /// Notifications are masked at this level.
-    ///
-    /// This is used in critical sections of code.
-    NOTIFY      = 16,
-    /// Highest priority level.
-    ///
-    /// Even processor interrupts are disable at this level.
-    HIGH_LEVEL  = 31,
-}}
-
 /// RAII guard for task priority level changes
 ///
 /// Will automatically restore the former task priority level when dropped.
@@ -1794,11 +2287,87 @@ pub struct TplGuard<'boot> {
 impl Drop for TplGuard<'_> {
     fn drop(&mut self) {
         unsafe {
-            (self.boot_services.restore_tpl)(self.old_tpl);
+            (self.boot_services.raw.restore_tpl)(self.old_tpl);
+        }
+    }
+}
+
+/// State of a [`BootServices::with_watchdog_extended`] call, shared with
+/// the timer event that re-arms the watchdog.
+struct WatchdogExtension {
+    boot_services: *const BootServices,
+    timeout: usize,
+    code: u64,
+    /// The extension of the enclosing call, or null.
+    outer: *const WatchdogExtension,
+}
+
+impl WatchdogExtension {
+    /// Set the watchdog to the timeout and code of this extension.
+    fn arm(&self) -> Status {
+        // Safety: the boot services outlive the extension.
+        let bt = unsafe { &*self.boot_services };
+        trace_status!(
+            "BootServices::set_watchdog_timer",
+            unsafe { (bt.raw.set_watchdog_timer)(self.timeout, self.code, 0, ptr::null_mut()) },
+            "timeout={}, code={:#x}",
+            self.timeout,
+            self.code
+        )
+    }
+}
+
+/// Guard of a [`BootServices::with_watchdog_extended`] call.
+///
+/// Cancels and closes the refresh event and restores the watchdog when
+/// dropped, so this also happens if the closure panics.
+struct WatchdogGuard<'a> {
+    extension: &'a WatchdogExtension,
+    event: Option<Event>,
+    active: bool,
+}
+
+impl WatchdogGuard<'_> {
+    /// Stop refreshing the watchdog and restore the enclosing extension,
+    /// or the watchdog settings from before the outermost call. Only the
+    /// first call does anything.
+    fn restore(&mut self) -> Status {
+        // Safety: the boot services outlive the extension.
+        let bt = unsafe { &*self.extension.boot_services };
+        if let Some(event) = self.event.take() {
+            let _ = bt.set_timer(&event, TimerTrigger::Cancel);
+            let _ = bt.close_event(event);
+        }
+        if !mem::replace(&mut self.active, false) {
+            return Status::SUCCESS;
+        }
+
+        let outer = self.extension.outer;
+        set_current_watchdog_extension(outer);
+        // Safety: the enclosing extension is alive until its call returns.
+        match unsafe { outer.as_ref() } {
+            Some(outer) => outer.arm(),
+            None => {
+                let timeout = WATCHDOG_TIMEOUT.load(Ordering::Relaxed);
+                let code = WATCHDOG_CODE.load(Ordering::Relaxed);
+                trace_status!(
+                    "BootServices::set_watchdog_timer",
+                    unsafe { (bt.raw.set_watchdog_timer)(timeout, code, 0, ptr::null_mut()) },
+                    "timeout={}, code={:#x}",
+                    timeout,
+                    code
+                )
+            }
         }
     }
 }
 
+impl Drop for WatchdogGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
+
 // OpenProtocolAttributes is safe to model as a regular enum because it
 // is only used as an input. The attributes are bitflags, but all valid
 // combinations are listed in the spec and only ByDriver and Exclusive
@@ -1879,12 +2448,14 @@ pub struct ScopedProtocol<'a, P: Protocol + ?Sized> {
 
 impl<'a, P: Protocol + ?Sized> Drop for ScopedProtocol<'a, P> {
     fn drop(&mut self) {
-        let status = (self.boot_services.close_protocol)(
-            self.open_params.handle,
-            &P::GUID,
-            self.open_params.agent,
-            self.open_params.controller,
-        );
+        let status = unsafe {
+            (self.boot_services.raw.close_protocol)(
+                self.open_params.handle,
+                &P::GUID,
+                self.open_params.agent,
+                self.open_params.controller,
+            )
+        };
         // All of the error cases for close_protocol boil down to
         // calling it with a different set of parameters than what was
         // passed to open_protocol. The public API prevents such errors,
@@ -1925,81 +2496,62 @@ pub enum AllocateType {
     Address(PhysicalAddress),
 }
 
-newtype_enum! {
-/// The type of a memory range.
-///
-/// UEFI allows firmwares and operating systems to introduce new memory types
-/// in the 0x70000000..0xFFFFFFFF range. Therefore, we don't know the full set
-/// of memory types at compile time, and it is _not_ safe to model this C enum
-/// as a Rust enum.
-pub enum MemoryType: u32 => {
-    /// This enum variant is not used.
-    RESERVED                =  0,
-    /// The code portions of a loaded UEFI application.
-    LOADER_CODE             =  1,
-    /// The data portions of a loaded UEFI applications,
-    /// as well as any memory allocated by it.
-    LOADER_DATA             =  2,
-    /// Code of the boot drivers.
-    ///
-    /// Can be reused after OS is loaded.
-    BOOT_SERVICES_CODE      =  3,
-    /// Memory used to store boot drivers' data.
-    ///
-    /// Can be reused after OS is loaded.
-    BOOT_SERVICES_DATA      =  4,
-    /// Runtime drivers' code.
-    RUNTIME_SERVICES_CODE   =  5,
-    /// Runtime services' code.
-    RUNTIME_SERVICES_DATA   =  6,
-    /// Free usable memory.
-    CONVENTIONAL            =  7,
-    /// Memory in which errors have been detected.
-    UNUSABLE                =  8,
-    /// Memory that holds ACPI tables.
-    /// Can be reclaimed after they are parsed.
-    ACPI_RECLAIM            =  9,
-    /// Firmware-reserved addresses.
-    ACPI_NON_VOLATILE       = 10,
-    /// A region used for memory-mapped I/O.
-    MMIO                    = 11,
-    /// Address space used for memory-mapped port I/O.
-    MMIO_PORT_SPACE         = 12,
-    /// Address space which is part of the processor.
-    PAL_CODE                = 13,
-    /// Memory region which is usable and is also non-volatile.
-    PERSISTENT_MEMORY       = 14,
-}}
-
 impl MemoryType {
+    /// Range of memory types reserved for use by firmware vendors.
+    pub const OEM_RANGE: RangeInclusive<u32> = 0x7000_0000..=0x7fff_ffff;
+
+    /// Range of memory types reserved for use by OS loaders and operating
+    /// systems.
+    pub const OS_RANGE: RangeInclusive<u32> = 0x8000_0000..=0xffff_ffff;
+
     /// Construct a custom `MemoryType`. Values in the range `0x80000000..=0xffffffff` are free for use if you are
     /// an OS loader.
+    ///
+    /// Custom types can be passed to [`BootServices::allocate_pages`] and
+    /// [`BootServices::allocate_pool`], and are kept as is in the memory
+    /// map. This lets a loader tag its allocations, for instance the kernel
+    /// image or boot information, so that the kernel can find them in the
+    /// memory map after [`exit_boot_services`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is not in [`OS_RANGE`]. In const context, this is a
+    /// compile-time error.
+    ///
+    /// [`exit_boot_services`]: crate::table::SystemTable::exit_boot_services
+    /// [`OS_RANGE`]: Self::OS_RANGE
     #[must_use]
     pub const fn custom(value: u32) -> MemoryType {
-        assert!(value >= 0x80000000);
+        assert!(
+            value >= 0x80000000,
+            "custom memory types must be in the range 0x80000000..=0xffffffff"
+        );
         MemoryType(value)
     }
+
+    /// Check whether the memory type is in [`OEM_RANGE`], and so is defined
+    /// by the firmware vendor.
+    ///
+    /// [`OEM_RANGE`]: Self::OEM_RANGE
+    #[must_use]
+    pub const fn is_oem_defined(self) -> bool {
+        self.0 >= 0x7000_0000 && self.0 <= 0x7fff_ffff
+    }
+
+    /// Check whether the memory type is in [`OS_RANGE`], and so is defined
+    /// by the OS loader, for instance with [`custom`].
+    ///
+    /// [`OS_RANGE`]: Self::OS_RANGE
+    /// [`custom`]: Self::custom
+    #[must_use]
+    pub const fn is_os_defined(self) -> bool {
+        self.0 >= 0x8000_0000
+    }
 }
 
 /// Memory descriptor version number
 pub const MEMORY_DESCRIPTOR_VERSION: u32 = 1;
 
-/// A structure describing a region of memory.
-#[derive(Debug, Copy, Clone)]
-#[repr(C)]
-pub struct MemoryDescriptor {
-    /// Type of memory occupying this range.
-    pub ty: MemoryType,
-    /// Starting physical address.
-    pub phys_start: PhysicalAddress,
-    /// Starting virtual address.
-    pub virt_start: VirtualAddress,
-    /// Number of 4 KiB pages contained in this range.
-    pub page_count: u64,
-    /// The capability attributes of this memory range.
-    pub att: MemoryAttribute,
-}
-
 impl Default for MemoryDescriptor {
     fn default() -> MemoryDescriptor {
         MemoryDescriptor {
@@ -2018,55 +2570,6 @@ impl Align for MemoryDescriptor {
     }
 }
 
-bitflags! {
-    /// Flags describing the capabilities of a memory range.
-    pub struct MemoryAttribute: u64 {
-        /// Supports marking as uncacheable.
-        const UNCACHEABLE = 0x1;
-        /// Supports write-combining.
-        const WRITE_COMBINE = 0x2;
-        /// Supports write-through.
-        const WRITE_THROUGH = 0x4;
-        /// Support write-back.
-        const WRITE_BACK = 0x8;
-        /// Supports marking as uncacheable, exported and
-        /// supports the "fetch and add" semaphore mechanism.
-        const UNCACHABLE_EXPORTED = 0x10;
-        /// Supports write-protection.
-        const WRITE_PROTECT = 0x1000;
-        /// Supports read-protection.
-        const READ_PROTECT = 0x2000;
-        /// Supports disabling code execution.
-        const EXECUTE_PROTECT = 0x4000;
-        /// Persistent memory.
-        const NON_VOLATILE = 0x8000;
-        /// This memory region is more reliable than other memory.
-        const MORE_RELIABLE = 0x10000;
-        /// This memory range can be set as read-only.
-        const READ_ONLY = 0x20000;
-        /// This memory is earmarked for specific purposes such as for specific
-        /// device drivers or applications. This serves as a hint to the OS to
-        /// avoid this memory for core OS data or code that cannot be relocated.
-        const SPECIAL_PURPOSE = 0x4_0000;
-        /// This memory region is capable of being protected with the CPU's memory
-        /// cryptography capabilities.
-        const CPU_CRYPTO = 0x8_0000;
-        /// This memory must be mapped by the OS when a runtime service is called.
-        const RUNTIME = 0x8000_0000_0000_0000;
-        /// This memory region is described with additional ISA-specific memory
-        /// attributes as specified in `MemoryAttribute::ISA_MASK`.
-        const ISA_VALID = 0x4000_0000_0000_0000;
-        /// These bits are reserved for describing optional ISA-specific cache-
-        /// ability attributes that are not covered by the standard UEFI Memory
-        /// Attribute cacheability bits such as `UNCACHEABLE`, `WRITE_COMBINE`,
-        /// `WRITE_THROUGH`, `WRITE_BACK`, and `UNCACHEABLE_EXPORTED`.
-        ///
-        /// See Section 2.3 "Calling Conventions" in the UEFI Specification
-        /// for further information on each ISA that takes advantage of this.
-        const ISA_MASK = 0x0FFF_F000_0000_0000;
-    }
-}
-
 /// A unique identifier of a memory map.
 ///
 /// If the memory map changes, this value is no longer valid.
@@ -2092,6 +2595,24 @@ pub struct MemoryMapIter<'buf> {
     len: usize,
 }
 
+impl<'buf> MemoryMapIter<'buf> {
+    /// Create an iterator over `len` descriptors stored `entry_size`
+    /// bytes apart in `buffer`. Returns `None` if `entry_size` is smaller
+    /// than a [`MemoryDescriptor`], `buffer` is too small or `buffer` is
+    /// not aligned like a [`MemoryDescriptor`].
+    pub(crate) fn new(buffer: &'buf [u8], entry_size: usize, len: usize) -> Option<Self> {
+        let valid = entry_size >= mem::size_of::<MemoryDescriptor>()
+            && matches!(entry_size.checked_mul(len), Some(size) if buffer.len() >= size)
+            && buffer.as_ptr() as usize % mem::align_of::<MemoryDescriptor>() == 0;
+        valid.then_some(Self {
+            buffer,
+            entry_size,
+            index: 0,
+            len,
+        })
+    }
+}
+
 impl<'buf> Iterator for MemoryMapIter<'buf> {
     type Item = &'buf MemoryDescriptor;
 
@@ -2143,43 +2664,51 @@ impl<'guid> SearchType<'guid> {
     pub const fn from_proto<P: ProtocolPointer + ?Sized>() -> Self {
         SearchType::ByProtocol(&P::GUID)
     }
-}
 
-bitflags! {
-    /// Flags describing the type of an UEFI event and its attributes.
-    pub struct EventType: u32 {
-        /// The event is a timer event and may be passed to `BootServices::set_timer()`
-        /// Note that timers only function during boot services time.
-        const TIMER = 0x8000_0000;
-
-        /// The event is allocated from runtime memory.
-        /// This must be done if the event is to be signaled after ExitBootServices.
-        const RUNTIME = 0x4000_0000;
-
-        /// Calling wait_for_event or check_event will enqueue the notification
-        /// function if the event is not already in the signaled state.
-        /// Mutually exclusive with `NOTIFY_SIGNAL`.
-        const NOTIFY_WAIT = 0x0000_0100;
-
-        /// The notification function will be enqueued when the event is signaled
-        /// Mutually exclusive with `NOTIFY_WAIT`.
-        const NOTIFY_SIGNAL = 0x0000_0200;
-
-        /// The event will be signaled at ExitBootServices time.
-        /// This event type should not be combined with any other.
-        /// Its notification function must follow some special rules:
-        /// - Cannot use memory allocation services, directly or indirectly
-        /// - Cannot depend on timer events, since those will be deactivated
-        const SIGNAL_EXIT_BOOT_SERVICES = 0x0000_0201;
-
-        /// The event will be notified when SetVirtualAddressMap is performed.
-        /// This event type should not be combined with any other.
-        const SIGNAL_VIRTUAL_ADDRESS_CHANGE = 0x6000_0202;
+    /// Returns the search type, protocol and search key arguments of
+    /// `LocateHandle` and `LocateHandleBuffer`.
+    fn to_raw(self) -> (i32, *const Guid, *const c_void) {
+        match self {
+            SearchType::AllHandles => (0, ptr::null(), ptr::null()),
+            SearchType::ByRegisterNotify(registration) => {
+                (1, ptr::null(), registration.0.as_ptr() as *const c_void)
+            }
+            SearchType::ByProtocol(guid) => (2, guid, ptr::null()),
+        }
     }
 }
 
-/// Raw event notification function
-type EventNotifyFn = unsafe extern "efiapi" fn(event: Event, context: Option<NonNull<c_void>>);
+newtype_enum! {
+    /// GUID of an event group, passed to [`BootServices::create_event_ex`]
+    /// or [`BootServices::subscribe_event_group`]. Signaling one event of a
+    /// group signals all of them.
+    pub enum EventGroup: Guid => {
+        /// Signaled when `ExitBootServices` is called, like events of type
+        /// [`EventType::SIGNAL_EXIT_BOOT_SERVICES`].
+        EXIT_BOOT_SERVICES = guid!("27abf055-b1b8-4c26-8048-748f37baa2df"),
+
+        /// Signaled before `ExitBootServices` is called. Unlike the
+        /// `EXIT_BOOT_SERVICES` group, the memory map can still be changed.
+        BEFORE_EXIT_BOOT_SERVICES = guid!("8be0e274-3970-4b44-80c5-1ab9502f3bfc"),
+
+        /// Signaled when `SetVirtualAddressMap` is called, like events of
+        /// type [`EventType::SIGNAL_VIRTUAL_ADDRESS_CHANGE`].
+        VIRTUAL_ADDRESS_CHANGE = guid!("13fa7698-c831-49c7-87ea-8f43fcc25196"),
+
+        /// Signaled when the memory map changes.
+        MEMORY_MAP_CHANGE = guid!("78bee926-692f-48fd-9edb-01422ef0d7ab"),
+
+        /// Signaled by the boot manager before it attempts to boot a boot
+        /// option.
+        READY_TO_BOOT = guid!("7ce88fb3-4bd7-4679-87a8-a8d8dee50d2b"),
+
+        /// Signaled by the boot manager after the `READY_TO_BOOT` group.
+        AFTER_READY_TO_BOOT = guid!("3a2a00ad-98b9-4cdf-a478-702777f1c10b"),
+
+        /// Signaled when `ResetSystem` is called, before the reset.
+        RESET_SYSTEM = guid!("62da6a56-13fb-485a-a8da-a3dd7912cb6b"),
+    }
+}
 
 /// Timer events manipulation
 pub enum TimerTrigger {
@@ -2234,6 +2763,248 @@ impl<'a> ProtocolsPerHandle<'a> {
     }
 }
 
+/// Agents that have opened a protocol on a [`Handle`], as returned by
+/// [`BootServices::open_protocol_information`].
+pub struct OpenProtocolInformation<'a> {
+    // The buffer has to be freed with `free_pool`.
+    boot_services: &'a BootServices,
+    entries: *mut OpenProtocolInformationEntry,
+    count: usize,
+}
+
+impl Drop for OpenProtocolInformation<'_> {
+    fn drop(&mut self) {
+        if !self.entries.is_null() {
+            // Ignore the result, we can't do anything about an error here.
+            let _ = self.boot_services.free_pool(self.entries.cast::<u8>());
+        }
+    }
+}
+
+impl Deref for OpenProtocolInformation<'_> {
+    type Target = [OpenProtocolInformationEntry];
+
+    fn deref(&self) -> &Self::Target {
+        if self.entries.is_null() {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(self.entries, self.count) }
+        }
+    }
+}
+
+/// Description of the protocols installed on a handle, returned by
+/// [`BootServices::dump_handle`].
+///
+/// The `Display` implementation prints one protocol per line, followed by
+/// the agents that opened it.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct HandleDump {
+    /// The described handle.
+    pub handle: Handle,
+    /// The protocols installed on the handle.
+    pub protocols: Vec<ProtocolDump>,
+}
+
+/// Description of a protocol installed on a handle, part of a
+/// [`HandleDump`].
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct ProtocolDump {
+    /// GUID of the protocol.
+    pub guid: Guid,
+    /// Name of the protocol, if it is well-known. See
+    /// [`protocol_name`](crate::proto::protocol_name).
+    pub name: Option<&'static str>,
+    /// The agents that opened the protocol.
+    pub open_info: Vec<OpenProtocolInformationEntry>,
+}
+
+#[cfg(feature = "alloc")]
+impl HandleDump {
+    /// Find the description of the protocol `P`, if it is installed on the
+    /// handle.
+    #[must_use]
+    pub fn protocol<P: Protocol>(&self) -> Option<&ProtocolDump> {
+        self.protocols
+            .iter()
+            .find(|protocol| protocol.guid == P::GUID)
+    }
+
+    /// Write the description to `writer` in the crate-wide
+    /// [`report::format`]. It starts with a `handle` record, followed for
+    /// each protocol by a `protocol` record and an `open` record per agent
+    /// that opened it.
+    pub fn write<W: core::fmt::Write>(&self, writer: &mut W) -> core::fmt::Result {
+        self.write_with_format(writer, report::format())
+    }
+
+    /// Write the description to `writer` in the given `format`.
+    pub fn write_with_format<W: core::fmt::Write>(
+        &self,
+        writer: &mut W,
+        format: Format,
+    ) -> core::fmt::Result {
+        let ptr = |handle: Option<Handle>| handle.map_or(ptr::null_mut(), |h| h.as_ptr());
+        Record::with_format(writer, format, "handle")?
+            .field("handle", format_args!("{:?}", self.handle.as_ptr()))?
+            .field("protocols", self.protocols.len())?
+            .finish()?;
+        for protocol in &self.protocols {
+            let record =
+                Record::with_format(writer, format, "protocol")?.field("guid", protocol.guid)?;
+            match protocol.name {
+                Some(name) => record.field("name", name)?,
+                None => record,
+            }
+            .field("opens", protocol.open_info.len())?
+            .finish()?;
+            for entry in &protocol.open_info {
+                Record::with_format(writer, format, "open")?
+                    .field("agent", format_args!("{:?}", ptr(entry.agent_handle)))?
+                    .field(
+                        "controller",
+                        format_args!("{:?}", ptr(entry.controller_handle)),
+                    )?
+                    .field("attributes", format_args!("{:#04x}", entry.attributes))?
+                    .field("open_count", entry.open_count)?
+                    .finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::fmt::Display for HandleDump {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write(f)
+    }
+}
+
+/// Snapshot of the memory map, returned by
+/// [`BootServices::dump_memory_map`].
+///
+/// The `Display` implementation prints a summary line, followed by one
+/// line per descriptor.
+#[cfg(feature = "alloc")]
+#[derive(Clone, Debug)]
+pub struct MemoryMapDump {
+    /// The key of the memory map when it was retrieved.
+    pub key: MemoryMapKey,
+    /// The descriptors of the memory map.
+    pub descriptors: Vec<MemoryDescriptor>,
+}
+
+#[cfg(feature = "alloc")]
+impl MemoryMapDump {
+    /// Write the memory map to `writer` in the crate-wide
+    /// [`report::format`]. It starts with a `memory_map` record, followed
+    /// by a `memory` record per descriptor.
+    pub fn write<W: core::fmt::Write>(&self, writer: &mut W) -> core::fmt::Result {
+        self.write_with_format(writer, report::format())
+    }
+
+    /// Write the memory map to `writer` in the given `format`.
+    pub fn write_with_format<W: core::fmt::Write>(
+        &self,
+        writer: &mut W,
+        format: Format,
+    ) -> core::fmt::Result {
+        let pages: u64 = self.descriptors.iter().map(|desc| desc.page_count).sum();
+        Record::with_format(writer, format, "memory_map")?
+            .field("entries", self.descriptors.len())?
+            .field("pages", pages)?
+            .finish()?;
+        for desc in &self.descriptors {
+            Record::with_format(writer, format, "memory")?
+                .field("type", format_args!("{:?}", desc.ty))?
+                .field("phys_start", format_args!("{:#x}", desc.phys_start))?
+                .field("virt_start", format_args!("{:#x}", desc.virt_start))?
+                .field("pages", desc.page_count)?
+                .field("attributes", format_args!("{:#x}", desc.att.bits()))?
+                .finish()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::fmt::Display for MemoryMapDump {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        self.write(f)
+    }
+}
+
+/// Exit data of an image, returned by
+/// [`BootServices::start_image_with_exit_data`].
+///
+/// The data starts with a null-terminated description string, optionally
+/// followed by binary data. The buffer is freed when dropped.
+pub struct ExitData<'a> {
+    // The buffer is allocated by the exiting image with `allocate_pool`, and
+    // has to be freed by the caller of `start_image`.
+    boot_services: &'a BootServices,
+    buffer: NonNull<Char16>,
+    size: usize,
+}
+
+impl<'a> ExitData<'a> {
+    /// Returns the raw exit data.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.buffer.as_ptr().cast(), self.size) }
+    }
+
+    /// Returns the number of UCS-2 characters in the description, including
+    /// the null terminator, or `None` if the data has no null terminator.
+    fn description_len(&self) -> Option<usize> {
+        let chars = unsafe {
+            slice::from_raw_parts(
+                self.buffer.as_ptr().cast::<u16>(),
+                self.size / mem::size_of::<u16>(),
+            )
+        };
+        chars.iter().position(|&c| c == 0).map(|index| index + 1)
+    }
+
+    /// Returns the description at the start of the exit data, or `None` if
+    /// it is not a valid null-terminated UCS-2 string.
+    #[must_use]
+    pub fn description(&self) -> Option<&CStr16> {
+        let len = self.description_len()?;
+        let chars = unsafe { slice::from_raw_parts(self.buffer.as_ptr().cast::<u16>(), len) };
+        CStr16::from_u16_with_nul(chars).ok()
+    }
+
+    /// Returns the binary data following the description. This is empty if
+    /// there is no such data, or if the description is not null-terminated.
+    #[must_use]
+    pub fn binary(&self) -> &[u8] {
+        match self.description_len() {
+            Some(len) => &self.as_bytes()[len * mem::size_of::<u16>()..],
+            None => &[],
+        }
+    }
+}
+
+impl<'a> Debug for ExitData<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ExitData")
+            .field("description", &self.description())
+            .field("binary", &self.binary())
+            .finish()
+    }
+}
+
+impl<'a> Drop for ExitData<'a> {
+    fn drop(&mut self) {
+        // Ignore the result, we can't do anything about an error here.
+        let _ = self.boot_services.free_pool(self.buffer.as_ptr().cast());
+    }
+}
+
 /// A buffer that contains an array of [`Handles`][Handle] that support the requested protocol.
 /// Returned by [`BootServices::locate_handle_buffer`].
 pub struct HandleBuffer<'a> {
@@ -2271,16 +3042,6 @@ impl<'a> HandleBuffer<'a> {
     }
 }
 
-newtype_enum! {
-/// Interface type of a protocol interface
-///
-/// Only has one variant when this was written (v2.10 of the UEFI spec)
-pub enum InterfaceType: i32 => {
-    /// Native interface
-    NATIVE_INTERFACE    = 0,
-}
-}
-
 /// Opaque pointer returned by [`BootServices::register_protocol_notify`] to be used
 /// with [`BootServices::locate_handle`] via [`SearchType::ByRegisterNotify`].
 #[derive(Debug, Clone, Copy)]
@@ -7,9 +7,26 @@
 //! This module contains the actual entries of the configuration table,
 //! as well as GUIDs for many known vendor tables.
 
-use crate::{guid, Guid};
+use super::boot::MemoryMapIter;
+use crate::util::usize_from_u32;
+use crate::{guid, Guid, Identify, Result, Status};
 use bitflags::bitflags;
 use core::ffi::c_void;
+use core::{mem, slice};
+
+/// Marker trait for configuration tables with a known layout.
+///
+/// The [`Identify::GUID`] of the type is the GUID of its entry in the
+/// configuration table. Types implementing this trait can be looked up
+/// with [`SystemTable::find_config_table`].
+///
+/// # Safety
+///
+/// The table referenced by a configuration table entry with the GUID of
+/// the implementing type must be valid for reads as `Self`.
+///
+/// [`SystemTable::find_config_table`]: super::SystemTable::find_config_table
+pub unsafe trait ConfigTable: Identify {}
 
 /// Contains a set of GUID / pointer for a vendor-specific table.
 ///
@@ -24,6 +41,33 @@ pub struct ConfigTableEntry {
     /// Whether this is a physical or virtual address depends on the table.
     pub address: *const c_void,
 }
+
+impl ConfigTableEntry {
+    /// Get a short name for the table if its GUID is one of the
+    /// well-known GUIDs in this module.
+    #[must_use]
+    pub fn name(&self) -> Option<&'static str> {
+        let name = match self.guid {
+            ACPI_GUID => "ACPI",
+            ACPI2_GUID => "ACPI2",
+            SMBIOS_GUID => "SMBIOS",
+            SMBIOS3_GUID => "SMBIOS3",
+            PROPERTIES_TABLE_GUID => "PROPERTIES_TABLE",
+            ESRT_GUID => "ESRT",
+            DEVICE_TREE_GUID => "DEVICE_TREE",
+            MEMORY_ATTRIBUTES_TABLE_GUID => "MEMORY_ATTRIBUTES_TABLE",
+            HAND_OFF_BLOCK_LIST_GUID => "HAND_OFF_BLOCK_LIST",
+            MEMORY_TYPE_INFORMATION_GUID => "MEMORY_TYPE_INFORMATION",
+            MEMORY_STATUS_CODE_RECORD_GUID => "MEMORY_STATUS_CODE_RECORD",
+            DXE_SERVICES_GUID => "DXE_SERVICES",
+            LZMA_COMPRESS_GUID => "LZMA_COMPRESS",
+            TIANO_COMPRESS_GUID => "TIANO_COMPRESS",
+            DEBUG_IMAGE_INFO_GUID => "DEBUG_IMAGE_INFO",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
 /// Entry pointing to the old ACPI 1 RSDP.
 pub const ACPI_GUID: Guid = guid!("eb9d2d30-2d88-11d3-9a16-0090273fc14d");
 
@@ -57,6 +101,12 @@ pub struct PropertiesTable {
     pub memory_protection: MemoryProtectionAttribute,
 }
 
+unsafe impl Identify for PropertiesTable {
+    const GUID: Guid = PROPERTIES_TABLE_GUID;
+}
+
+unsafe impl ConfigTable for PropertiesTable {}
+
 bitflags! {
     /// Flags describing memory protection.
     pub struct MemoryProtectionAttribute: usize {
@@ -89,3 +139,239 @@ pub const TIANO_COMPRESS_GUID: Guid = guid!("a31280ad-481e-41b6-95e8-127f4c98477
 
 /// Pointer to the debug image info table.
 pub const DEBUG_IMAGE_INFO_GUID: Guid = guid!("49152e77-1ada-4764-b7a2-7afefed95e8b");
+
+/// Entry pointing to the EFI System Resource Table (ESRT).
+pub const ESRT_GUID: Guid = guid!("b122a263-3661-4f68-9929-78f8b0d62180");
+
+/// Entry pointing to a flattened device tree blob.
+pub const DEVICE_TREE_GUID: Guid = guid!("b1b621d5-f19c-41a5-830b-d9152c69aae0");
+
+/// Entry pointing to the memory attributes table.
+pub const MEMORY_ATTRIBUTES_TABLE_GUID: Guid = guid!("dcfa911d-26eb-469f-a220-38b7dc461220");
+
+/// The EFI System Resource Table (ESRT), which describes the firmware
+/// resources that can be updated with capsules.
+///
+/// Corresponds to the C type `EFI_SYSTEM_RESOURCE_TABLE`. The header is
+/// followed in memory by [`fw_resource_count`] entries, which can be
+/// accessed with [`entries`].
+///
+/// [`entries`]: Self::entries
+/// [`fw_resource_count`]: Self::fw_resource_count
+#[derive(Debug)]
+#[repr(C)]
+pub struct SystemResourceTable {
+    /// Number of entries in the table.
+    pub fw_resource_count: u32,
+    /// Maximum number of entries that fit in the memory allocated for
+    /// the table.
+    pub fw_resource_count_max: u32,
+    /// Version of the table format. The only valid version currently is 1.
+    pub fw_resource_version: u64,
+}
+
+impl SystemResourceTable {
+    /// Get the resource entries that follow the header.
+    #[must_use]
+    pub fn entries(&self) -> &[SystemResourceEntry] {
+        let first = unsafe {
+            (self as *const Self)
+                .cast::<u8>()
+                .add(mem::size_of::<Self>())
+                .cast::<SystemResourceEntry>()
+        };
+        unsafe { slice::from_raw_parts(first, usize_from_u32(self.fw_resource_count)) }
+    }
+}
+
+unsafe impl Identify for SystemResourceTable {
+    const GUID: Guid = ESRT_GUID;
+}
+
+unsafe impl ConfigTable for SystemResourceTable {}
+
+/// Entry in the [`SystemResourceTable`].
+///
+/// Corresponds to the C type `EFI_SYSTEM_RESOURCE_ENTRY`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct SystemResourceEntry {
+    /// GUID identifying the firmware component.
+    pub fw_class: Guid,
+    /// Type of the firmware component: 0 (unknown), 1 (system
+    /// firmware), 2 (device firmware), or 3 (UEFI driver).
+    pub fw_type: u32,
+    /// Current firmware version.
+    pub fw_version: u32,
+    /// Lowest firmware version that can be installed.
+    pub lowest_supported_fw_version: u32,
+    /// Capsule flags required to update the component.
+    pub capsule_flags: u32,
+    /// Status of the last update attempt.
+    pub last_attempt_version: u32,
+    /// Result of the last update attempt.
+    pub last_attempt_status: u32,
+}
+
+/// The memory attributes table, which describes the memory protections
+/// that the OS should apply to runtime services code and data regions
+/// once it takes over the page tables.
+///
+/// Corresponds to the C type `EFI_MEMORY_ATTRIBUTES_TABLE`. The header
+/// is followed in memory by [`number_of_entries`] memory descriptors,
+/// which can be accessed with [`entries`]. Each entry describes a
+/// region of [`RUNTIME_SERVICES_CODE`] or [`RUNTIME_SERVICES_DATA`]
+/// memory; code regions should be mapped read-only and data regions
+/// non-executable as indicated by the [`READ_ONLY`] and
+/// [`EXECUTE_PROTECT`] attributes of each entry.
+///
+/// [`EXECUTE_PROTECT`]: super::boot::MemoryAttribute::EXECUTE_PROTECT
+/// [`READ_ONLY`]: super::boot::MemoryAttribute::READ_ONLY
+/// [`RUNTIME_SERVICES_CODE`]: super::boot::MemoryType::RUNTIME_SERVICES_CODE
+/// [`RUNTIME_SERVICES_DATA`]: super::boot::MemoryType::RUNTIME_SERVICES_DATA
+/// [`entries`]: Self::entries
+/// [`number_of_entries`]: Self::number_of_entries
+#[derive(Debug)]
+#[repr(C)]
+pub struct MemoryAttributesTable {
+    /// Version of the table format. Versions 1 and 2 are currently defined.
+    pub version: u32,
+    /// Number of memory descriptors in the table.
+    pub number_of_entries: u32,
+    /// Size in bytes of each memory descriptor. This may be larger than
+    /// `size_of::<MemoryDescriptor>()`.
+    pub descriptor_size: u32,
+    /// Flags, see [`MemoryAttributesTableFlags`]. Only valid for
+    /// version 2 and later; reserved in version 1.
+    pub flags: MemoryAttributesTableFlags,
+}
+
+impl MemoryAttributesTable {
+    /// Iterator over the memory descriptors that follow the header.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INCOMPATIBLE_VERSION`]: the table version is not 1 or 2.
+    /// * [`Status::INVALID_PARAMETER`]: the descriptor size is smaller than
+    ///   `size_of::<MemoryDescriptor>()`, or the table is misaligned.
+    ///
+    /// [`MemoryDescriptor`]: super::boot::MemoryDescriptor
+    pub fn entries(&self) -> Result<MemoryMapIter<'_>> {
+        if !matches!(self.version, 1 | 2) {
+            return Err(Status::INCOMPATIBLE_VERSION.into());
+        }
+        let len = usize_from_u32(self.number_of_entries);
+        let entry_size = usize_from_u32(self.descriptor_size);
+        let size = len
+            .checked_mul(entry_size)
+            .ok_or(Status::INVALID_PARAMETER)?;
+        let buffer = unsafe {
+            slice::from_raw_parts(
+                (self as *const Self)
+                    .cast::<u8>()
+                    .add(mem::size_of::<Self>()),
+                size,
+            )
+        };
+        MemoryMapIter::new(buffer, entry_size, len).ok_or_else(|| Status::INVALID_PARAMETER.into())
+    }
+}
+
+unsafe impl Identify for MemoryAttributesTable {
+    const GUID: Guid = MEMORY_ATTRIBUTES_TABLE_GUID;
+}
+
+unsafe impl ConfigTable for MemoryAttributesTable {}
+
+bitflags! {
+    /// Flags in the [`MemoryAttributesTable`].
+    #[repr(transparent)]
+    pub struct MemoryAttributesTableFlags: u32 {
+        /// Runtime code regions have been compiled with forward
+        /// control-flow guard instructions (e.g. Intel CET `ENDBR` or ARM
+        /// `BTI`), so the OS may enable forward control-flow guards when
+        /// mapping them.
+        const RT_FORWARD_CONTROL_FLOW_GUARD = 0x1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::table::boot::{MemoryAttribute, MemoryDescriptor, MemoryType};
+
+    #[test]
+    fn test_memory_attributes_table() {
+        // Use a descriptor size larger than `MemoryDescriptor`, as
+        // firmware commonly does.
+        const DESC_SIZE: usize = 48;
+
+        #[repr(C, align(8))]
+        struct Buf([u8; 16 + 2 * DESC_SIZE]);
+
+        let mut buf = Buf([0; 16 + 2 * DESC_SIZE]);
+        let header = MemoryAttributesTable {
+            version: 2,
+            number_of_entries: 2,
+            descriptor_size: DESC_SIZE as u32,
+            flags: MemoryAttributesTableFlags::RT_FORWARD_CONTROL_FLOW_GUARD,
+        };
+        let descs = [
+            MemoryDescriptor {
+                ty: MemoryType::RUNTIME_SERVICES_CODE,
+                phys_start: 0x1000,
+                virt_start: 0,
+                page_count: 1,
+                att: MemoryAttribute::RUNTIME | MemoryAttribute::READ_ONLY,
+            },
+            MemoryDescriptor {
+                ty: MemoryType::RUNTIME_SERVICES_DATA,
+                phys_start: 0x2000,
+                virt_start: 0,
+                page_count: 2,
+                att: MemoryAttribute::RUNTIME | MemoryAttribute::EXECUTE_PROTECT,
+            },
+        ];
+        unsafe {
+            let base = buf.0.as_mut_ptr();
+            base.cast::<MemoryAttributesTable>().write(header);
+            for (i, desc) in descs.iter().enumerate() {
+                base.add(16 + i * DESC_SIZE)
+                    .cast::<MemoryDescriptor>()
+                    .write(*desc);
+            }
+        }
+
+        let table = unsafe { &*buf.0.as_ptr().cast::<MemoryAttributesTable>() };
+        assert_eq!(table.version, 2);
+        assert!(table
+            .flags
+            .contains(MemoryAttributesTableFlags::RT_FORWARD_CONTROL_FLOW_GUARD));
+
+        let entries: [&MemoryDescriptor; 2] = {
+            let mut iter = table.entries().unwrap();
+            assert_eq!(iter.len(), 2);
+            [iter.next().unwrap(), iter.next().unwrap()]
+        };
+        assert_eq!(entries[0].ty, MemoryType::RUNTIME_SERVICES_CODE);
+        assert_eq!(entries[0].phys_start, 0x1000);
+        assert_eq!(entries[1].ty, MemoryType::RUNTIME_SERVICES_DATA);
+        assert_eq!(entries[1].page_count, 2);
+        assert!(entries[1].att.contains(MemoryAttribute::EXECUTE_PROTECT));
+
+        // Unknown versions and descriptors smaller than `MemoryDescriptor`
+        // are rejected.
+        let table = unsafe { &mut *buf.0.as_mut_ptr().cast::<MemoryAttributesTable>() };
+        table.version = 3;
+        assert_eq!(
+            table.entries().unwrap_err().status(),
+            Status::INCOMPATIBLE_VERSION
+        );
+        table.version = 1;
+        table.descriptor_size = 8;
+        assert_eq!(
+            table.entries().unwrap_err().status(),
+            Status::INVALID_PARAMETER
+        );
+    }
+}
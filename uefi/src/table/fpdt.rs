@@ -0,0 +1,416 @@
+//! Firmware Performance Data Table (FPDT) parsing.
+//!
+//! The FPDT is an ACPI table pointing to the Firmware Basic Boot
+//! Performance Table (FBPT), in which the firmware records when the reset
+//! ended and when the OS loader was loaded and started. The timestamps are
+//! in nanoseconds, and zero if not recorded.
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::table::fpdt::Fpdt;
+//!
+//! fn log_boot_time(st: &SystemTable<Boot>) {
+//!     let perf = Fpdt::find(st)
+//!         .and_then(|fpdt| fpdt.fbpt())
+//!         .and_then(|fbpt| fbpt.boot_performance());
+//!     if let Some(perf) = perf {
+//!         log::info!("Firmware reset ended at {} ns", perf.reset_end);
+//!     }
+//! }
+//! ```
+
+use super::{Boot, SystemTable};
+use core::{fmt, ptr, slice};
+
+/// Signature of the FPDT.
+const FPDT_SIGNATURE: [u8; 4] = *b"FPDT";
+
+/// Signature of the FBPT.
+const FBPT_SIGNATURE: [u8; 4] = *b"FBPT";
+
+/// Signature of the S3PT.
+const S3PT_SIGNATURE: [u8; 4] = *b"S3PT";
+
+/// Size of the ACPI header of the FPDT.
+const FPDT_HEADER_SIZE: usize = 36;
+
+/// Size of the headers of the FBPT and S3PT.
+const TABLE_HEADER_SIZE: usize = 8;
+
+/// Size of the header of a performance record.
+const RECORD_HEADER_SIZE: usize = 4;
+
+/// Size of the records pointing to the FBPT and S3PT.
+const POINTER_RECORD_LEN: usize = 16;
+
+/// Size of the [`BootPerformance`] record.
+const BOOT_PERFORMANCE_LEN: usize = 48;
+
+/// The Firmware Performance Data Table, which points to the tables holding
+/// the performance records of the firmware.
+///
+/// The table is an ACPI table, which can be located with [`find`], or
+/// parsed from its bytes with [`new`].
+///
+/// [`find`]: Self::find
+/// [`new`]: Self::new
+#[derive(Clone, Copy, Debug)]
+pub struct Fpdt<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Fpdt<'a> {
+    /// Parse the FPDT from its bytes, including the ACPI header.
+    ///
+    /// Returns `None` if the signature or the length of the table is
+    /// invalid. The checksum is not verified.
+    #[must_use]
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.get(..4)? != FPDT_SIGNATURE {
+            return None;
+        }
+        let len = usize::try_from(read_u32(bytes, 4)?).ok()?;
+        if len < FPDT_HEADER_SIZE {
+            return None;
+        }
+        Some(Self {
+            bytes: bytes.get(..len)?,
+        })
+    }
+
+    /// Find the FPDT in the ACPI tables of the system.
+    #[must_use]
+    pub fn find(st: &'a SystemTable<Boot>) -> Option<Self> {
+        Self::new(st.find_acpi_table(&FPDT_SIGNATURE)?.as_bytes())
+    }
+
+    /// Get the raw bytes of the table.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get an iterator over the records of the table.
+    #[must_use]
+    pub fn records(&self) -> PerformanceRecords<'a> {
+        PerformanceRecords::new(&self.bytes[FPDT_HEADER_SIZE..])
+    }
+
+    /// Get the physical address of the FBPT, or `None` if the table has no
+    /// pointer to it.
+    #[must_use]
+    pub fn fbpt_address(&self) -> Option<u64> {
+        self.pointer(PerformanceRecord::FIRMWARE_BASIC_BOOT_POINTER)
+    }
+
+    /// Get the physical address of the S3 Performance Table, or `None` if
+    /// the table has no pointer to it.
+    #[must_use]
+    pub fn s3pt_address(&self) -> Option<u64> {
+        self.pointer(PerformanceRecord::S3_PERFORMANCE_POINTER)
+    }
+
+    /// Get the FBPT, or `None` if it is missing or invalid.
+    ///
+    /// The FBPT is accessed by its physical address, which is only valid
+    /// while the memory is identity mapped, as it is before exiting boot
+    /// services.
+    #[must_use]
+    pub fn fbpt(&self) -> Option<Fbpt<'a>> {
+        Fbpt::new(table_at(self.fbpt_address()?, FBPT_SIGNATURE)?)
+    }
+
+    /// Get the S3 Performance Table, or `None` if it is missing or
+    /// invalid.
+    ///
+    /// Like the FBPT, it is accessed by its physical address. Its records
+    /// are only filled after resuming from S3.
+    #[must_use]
+    pub fn s3pt(&self) -> Option<PerformanceRecords<'a>> {
+        let bytes = table_at(self.s3pt_address()?, S3PT_SIGNATURE)?;
+        Some(PerformanceRecords::new(&bytes[TABLE_HEADER_SIZE..]))
+    }
+
+    fn pointer(&self, record_type: u16) -> Option<u64> {
+        let record = self.records().find(|record| {
+            record.record_type() == record_type && record.bytes.len() >= POINTER_RECORD_LEN
+        })?;
+        read_u64(record.bytes, 8).filter(|address| *address != 0)
+    }
+}
+
+/// The Firmware Basic Boot Performance Table, which holds the performance
+/// records of the current boot.
+#[derive(Clone, Copy, Debug)]
+pub struct Fbpt<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Fbpt<'a> {
+    /// Parse the FBPT from its bytes, including its header.
+    ///
+    /// Returns `None` if the signature or the length of the table is
+    /// invalid.
+    #[must_use]
+    pub fn new(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.get(..4)? != FBPT_SIGNATURE {
+            return None;
+        }
+        let len = usize::try_from(read_u32(bytes, 4)?).ok()?;
+        if len < TABLE_HEADER_SIZE {
+            return None;
+        }
+        Some(Self {
+            bytes: bytes.get(..len)?,
+        })
+    }
+
+    /// Get the raw bytes of the table.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get an iterator over the records of the table.
+    ///
+    /// Besides the [`BootPerformance`] record, EDK2-based firmware adds
+    /// records for the start and end of its phases and modules.
+    #[must_use]
+    pub fn records(&self) -> PerformanceRecords<'a> {
+        PerformanceRecords::new(&self.bytes[TABLE_HEADER_SIZE..])
+    }
+
+    /// Get the timestamps of the boot, or `None` if the record is missing.
+    #[must_use]
+    pub fn boot_performance(&self) -> Option<BootPerformance> {
+        self.records().find_map(|record| record.boot_performance())
+    }
+}
+
+/// A record of an [`Fpdt`], an [`Fbpt`], or an S3 Performance Table.
+#[derive(Clone, Copy)]
+pub struct PerformanceRecord<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> PerformanceRecord<'a> {
+    /// Type of the FPDT record pointing to the FBPT.
+    pub const FIRMWARE_BASIC_BOOT_POINTER: u16 = 0x0000;
+    /// Type of the FPDT record pointing to the S3 Performance Table.
+    pub const S3_PERFORMANCE_POINTER: u16 = 0x0001;
+    /// Type of the S3 Performance Table record of the last resume.
+    pub const BASIC_S3_RESUME: u16 = 0x0000;
+    /// Type of the S3 Performance Table record of the last suspend.
+    pub const BASIC_S3_SUSPEND: u16 = 0x0001;
+    /// Type of the FBPT record holding the [`BootPerformance`].
+    pub const FIRMWARE_BASIC_BOOT: u16 = 0x0002;
+
+    /// Get the type of the record. The meaning of the type depends on the
+    /// table holding the record.
+    #[must_use]
+    pub fn record_type(&self) -> u16 {
+        u16::from_le_bytes([self.bytes[0], self.bytes[1]])
+    }
+
+    /// Get the revision of the record format.
+    #[must_use]
+    pub fn revision(&self) -> u8 {
+        self.bytes[3]
+    }
+
+    /// Get the raw bytes of the record, including its header.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Get the timestamps of the boot, if this is the FBPT record holding
+    /// them.
+    #[must_use]
+    pub fn boot_performance(&self) -> Option<BootPerformance> {
+        if self.record_type() != Self::FIRMWARE_BASIC_BOOT
+            || self.bytes.len() < BOOT_PERFORMANCE_LEN
+        {
+            return None;
+        }
+        let timestamp = |offset| read_u64(self.bytes, offset).unwrap();
+        Some(BootPerformance {
+            reset_end: timestamp(8),
+            os_loader_load_image_start: timestamp(16),
+            os_loader_start_image_start: timestamp(24),
+            exit_boot_services_entry: timestamp(32),
+            exit_boot_services_exit: timestamp(40),
+        })
+    }
+}
+
+impl fmt::Debug for PerformanceRecord<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PerformanceRecord")
+            .field("record_type", &self.record_type())
+            .field("length", &self.bytes.len())
+            .field("revision", &self.revision())
+            .finish()
+    }
+}
+
+/// Iterator over consecutive [`PerformanceRecord`]s.
+///
+/// Iteration stops at the first record with an invalid length.
+#[derive(Clone, Debug)]
+pub struct PerformanceRecords<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> PerformanceRecords<'a> {
+    /// Create an iterator over the records of `bytes`.
+    #[must_use]
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+}
+
+impl<'a> Iterator for PerformanceRecords<'a> {
+    type Item = PerformanceRecord<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let len = usize::from(*self.bytes.get(2)?);
+        if len < RECORD_HEADER_SIZE || len > self.bytes.len() {
+            self.bytes = &[];
+            return None;
+        }
+        let (record, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(PerformanceRecord { bytes: record })
+    }
+}
+
+/// The timestamps of the boot, in nanoseconds.
+///
+/// A timestamp is zero if the firmware hasn't recorded it. The
+/// `exit_boot_services` timestamps are only recorded when the OS loader
+/// exits boot services.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BootPerformance {
+    /// When the firmware started running after the reset. This is not
+    /// necessarily zero.
+    pub reset_end: u64,
+    /// When the OS loader started being loaded.
+    pub os_loader_load_image_start: u64,
+    /// When the OS loader started being run.
+    pub os_loader_start_image_start: u64,
+    /// When the OS loader called `ExitBootServices`.
+    pub exit_boot_services_entry: u64,
+    /// When `ExitBootServices` returned.
+    pub exit_boot_services_exit: u64,
+}
+
+/// Get the table at physical `address` with the given `signature`,
+/// including its header.
+fn table_at<'a>(address: u64, signature: [u8; 4]) -> Option<&'a [u8]> {
+    let address = usize::try_from(address).ok()?;
+    let header = ptr::NonNull::new(address as *mut u8)?;
+    let header = unsafe { slice::from_raw_parts(header.as_ptr(), TABLE_HEADER_SIZE) };
+    if header[..4] != signature {
+        return None;
+    }
+    let len = usize::try_from(read_u32(header, 4)?).ok()?;
+    if len < TABLE_HEADER_SIZE {
+        return None;
+    }
+    Some(unsafe { slice::from_raw_parts(header.as_ptr(), len) })
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let bytes = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let bytes = bytes.get(offset..offset + 8)?;
+    Some(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn record(record_type: u16, revision: u8, body: &[u8]) -> Vec<u8> {
+        let mut record = record_type.to_le_bytes().to_vec();
+        record.push((RECORD_HEADER_SIZE + body.len()) as u8);
+        record.push(revision);
+        record.extend_from_slice(body);
+        record
+    }
+
+    fn pointer(record_type: u16, address: u64) -> Vec<u8> {
+        let mut body = vec![0; 4];
+        body.extend_from_slice(&address.to_le_bytes());
+        record(record_type, 1, &body)
+    }
+
+    fn table(signature: [u8; 4], header_size: usize, records: &[Vec<u8>]) -> Vec<u8> {
+        let mut table = vec![0; header_size];
+        table[..4].copy_from_slice(&signature);
+        for record in records {
+            table.extend_from_slice(record);
+        }
+        let len = table.len() as u32;
+        table[4..8].copy_from_slice(&len.to_le_bytes());
+        table
+    }
+
+    #[test]
+    fn test_fpdt() {
+        let mut boot = vec![0; 4];
+        for timestamp in [1_000u64, 2_000, 3_000, 0, 0] {
+            boot.extend_from_slice(&timestamp.to_le_bytes());
+        }
+        let fbpt = table(
+            FBPT_SIGNATURE,
+            TABLE_HEADER_SIZE,
+            &[record(0x1010, 1, &[0; 8]), record(2, 2, &boot)],
+        );
+        let fpdt = table(
+            FPDT_SIGNATURE,
+            FPDT_HEADER_SIZE,
+            &[pointer(0, fbpt.as_ptr() as u64)],
+        );
+
+        let fpdt = Fpdt::new(&fpdt).unwrap();
+        assert_eq!(fpdt.fbpt_address(), Some(fbpt.as_ptr() as u64));
+        assert_eq!(fpdt.s3pt_address(), None);
+        assert!(fpdt.s3pt().is_none());
+
+        let fbpt = fpdt.fbpt().unwrap();
+        let types: Vec<_> = fbpt
+            .records()
+            .map(|record| (record.record_type(), record.revision()))
+            .collect();
+        assert_eq!(types, [(0x1010, 1), (2, 2)]);
+        assert_eq!(
+            fbpt.boot_performance(),
+            Some(BootPerformance {
+                reset_end: 1_000,
+                os_loader_load_image_start: 2_000,
+                os_loader_start_image_start: 3_000,
+                exit_boot_services_entry: 0,
+                exit_boot_services_exit: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Fpdt::new(b"FPDT").is_none());
+        assert!(Fbpt::new(&table(FPDT_SIGNATURE, TABLE_HEADER_SIZE, &[])).is_none());
+
+        // A record whose length overflows the table stops the iteration.
+        let mut bytes = record(2, 2, &[0; 8]);
+        bytes.extend_from_slice(&[2, 0, 48, 2]);
+        assert_eq!(PerformanceRecords::new(&bytes).count(), 1);
+        assert!(PerformanceRecords::new(&[0, 0, 0, 1]).next().is_none());
+    }
+}
@@ -1,18 +1,296 @@
 use super::Revision;
+use crate::util::{crc32, crc32_update};
+use core::{fmt, mem, slice};
 
-/// All standard UEFI tables begin with a common header.
-#[derive(Debug)]
-#[repr(C)]
-pub struct Header {
-    /// Unique identifier for this table.
-    pub signature: u64,
-    /// Revision of the spec this table conforms to.
-    pub revision: Revision,
-    /// The size in bytes of the entire table.
-    pub size: u32,
-    /// 32-bit CRC-32-Castagnoli of the entire table,
-    /// calculated with this field set to 0.
-    pub crc: u32,
-    /// Reserved field that must be set to 0.
-    _reserved: u32,
+pub use crate::raw::table::Header;
+
+/// The largest table size accepted by [`Header::validate`]. The standard
+/// tables are much smaller, so a larger size indicates a corrupted header.
+const MAX_TABLE_SIZE: u32 = 0x1000;
+
+impl Header {
+    /// Checks that this header belongs to a table with the given
+    /// `signature`, that the revision and size are plausible, and that the
+    /// CRC matches the contents of the table.
+    ///
+    /// # Safety
+    ///
+    /// The header must be at the start of a table that is readable for
+    /// at least `self.size` bytes, as long as the size is within the
+    /// limit checked by this function.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TableError`] describing the first check that failed.
+    pub unsafe fn validate(&self, signature: u64) -> Result<(), TableError> {
+        let error = |kind| TableError {
+            table: signature,
+            kind,
+        };
+
+        if self.signature != signature {
+            return Err(error(TableErrorKind::Signature(self.signature)));
+        }
+        if !matches!(self.revision.major(), 1 | 2) {
+            return Err(error(TableErrorKind::Revision(self.revision)));
+        }
+        if self.size < mem::size_of::<Self>() as u32 || self.size > MAX_TABLE_SIZE {
+            return Err(error(TableErrorKind::Size(self.size)));
+        }
+
+        // The CRC is computed with the CRC field set to zero.
+        let table = slice::from_raw_parts((self as *const Self).cast::<u8>(), self.size as usize);
+        let crc_offset = mem::size_of::<u64>() + 2 * mem::size_of::<u32>();
+        let (before, after) = table.split_at(crc_offset);
+        let crc = crc32(before);
+        let crc = crc32_update(crc, &[0; 4]);
+        let crc = crc32_update(crc, &after[4..]);
+        if crc != self.crc {
+            return Err(error(TableErrorKind::Crc {
+                expected: self.crc,
+                computed: crc,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Returns a reference to a field of the table that begins with this
+    /// header, or `None` if the field is not present in the table.
+    ///
+    /// Newer revisions of the specification append fields to the standard
+    /// tables. A field is only present if the table's revision is at least
+    /// `revision`, the revision that added the field, and if the size of the
+    /// table given in the header covers the field. Checking both guards
+    /// against firmware that reports a newer revision than it implements.
+    ///
+    /// # Safety
+    ///
+    /// `field` must point to a field of the table that begins with this
+    /// header, e.g. one obtained with [`core::ptr::addr_of!`].
+    #[must_use]
+    pub unsafe fn versioned_field<T>(&self, revision: Revision, field: *const T) -> Option<&T> {
+        let start = self as *const Self as usize;
+        let end = field as usize - start + mem::size_of::<T>();
+        if self.revision >= revision && end <= self.size as usize {
+            Some(&*field)
+        } else {
+            None
+        }
+    }
+}
+
+/// Error returned by [`Header::validate`] when a table is corrupted or is
+/// not the expected table.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TableError {
+    table: u64,
+    kind: TableErrorKind,
+}
+
+impl TableError {
+    pub(crate) const fn new(table: u64, kind: TableErrorKind) -> Self {
+        Self { table, kind }
+    }
+
+    /// Returns the signature of the table that was expected.
+    #[must_use]
+    pub const fn table(&self) -> u64 {
+        self.table
+    }
+
+    /// Returns what is wrong with the table.
+    #[must_use]
+    pub const fn kind(&self) -> TableErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid {} table: {}", Signature(self.table), self.kind)
+    }
+}
+
+#[cfg(feature = "unstable")]
+impl core::error::Error for TableError {}
+
+/// The reason for a [`TableError`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TableErrorKind {
+    /// The pointer to the table is null.
+    Null,
+    /// The header has this signature instead of the expected one.
+    Signature(u64),
+    /// The major revision is not 1 or 2.
+    Revision(Revision),
+    /// The size is smaller than the header or implausibly large.
+    Size(u32),
+    /// The CRC in the header doesn't match the table.
+    Crc {
+        /// CRC stored in the header.
+        expected: u32,
+        /// CRC computed from the contents of the table.
+        computed: u32,
+    },
+}
+
+impl fmt::Display for TableErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null pointer"),
+            Self::Signature(signature) => {
+                write!(f, "unexpected signature \"{}\"", Signature(*signature))
+            }
+            Self::Revision(revision) => write!(f, "unsupported revision {revision}"),
+            Self::Size(size) => write!(f, "invalid size {size}"),
+            Self::Crc { expected, computed } => {
+                write!(
+                    f,
+                    "CRC mismatch, expected {expected:#010x} but computed {computed:#010x}"
+                )
+            }
+        }
+    }
+}
+
+/// Displays a table signature as the ASCII characters it is made of,
+/// e.g. "IBI SYST" for the system table.
+struct Signature(u64);
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0.to_le_bytes() {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                write!(f, "{}", char::from(byte))?;
+            } else {
+                write!(f, "\\x{byte:02x}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ptr;
+
+    #[repr(C)]
+    struct Table {
+        header: Header,
+        old: u32,
+        new: u32,
+    }
+
+    const SIGNATURE: u64 = 0x5453_4554_2049_4249;
+    const BOOT_SIGNATURE: u64 = 0x5652_4553_544f_4f42;
+
+    fn table(revision: Revision, size: usize) -> Table {
+        Table {
+            header: Header {
+                signature: SIGNATURE,
+                revision,
+                size: size as u32,
+                crc: 0,
+                reserved: 0,
+            },
+            old: 1,
+            new: 2,
+        }
+    }
+
+    #[test]
+    fn test_versioned_field() {
+        let t = table(Revision::EFI_2_00, mem::size_of::<Table>());
+        unsafe {
+            let header = &t.header;
+            assert_eq!(
+                header.versioned_field(Revision::EFI_1_10, ptr::addr_of!(t.old)),
+                Some(&1)
+            );
+            assert_eq!(
+                header.versioned_field(Revision::EFI_2_00, ptr::addr_of!(t.new)),
+                Some(&2)
+            );
+            assert_eq!(
+                header.versioned_field(Revision::EFI_2_10, ptr::addr_of!(t.new)),
+                None
+            );
+        }
+
+        // The revision claims the field exists, but the table is too small.
+        let t = table(Revision::EFI_2_70, mem::size_of::<Header>() + 4);
+        unsafe {
+            let header = &t.header;
+            assert_eq!(
+                header.versioned_field(Revision::EFI_1_02, ptr::addr_of!(t.old)),
+                Some(&1)
+            );
+            assert_eq!(
+                header.versioned_field(Revision::EFI_2_00, ptr::addr_of!(t.new)),
+                None
+            );
+        }
+    }
+
+    fn update_crc(t: &mut Table) {
+        t.header.crc = 0;
+        let bytes = unsafe {
+            slice::from_raw_parts((t as *const Table).cast::<u8>(), mem::size_of::<Table>())
+        };
+        t.header.crc = crc32(bytes);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut t = table(Revision::EFI_2_70, mem::size_of::<Table>());
+        update_crc(&mut t);
+        unsafe {
+            assert_eq!(t.header.validate(SIGNATURE), Ok(()));
+            let error = t.header.validate(BOOT_SIGNATURE).unwrap_err();
+            assert_eq!(error.table(), BOOT_SIGNATURE);
+            assert_eq!(error.kind(), TableErrorKind::Signature(SIGNATURE));
+        }
+
+        // Change the table without updating the CRC.
+        let expected = t.header.crc;
+        t.new = 3;
+        update_crc(&mut t);
+        let computed = mem::replace(&mut t.header.crc, expected);
+        let error = unsafe { t.header.validate(SIGNATURE) }.unwrap_err();
+        assert_eq!(error.kind(), TableErrorKind::Crc { expected, computed });
+
+        t.header.size = 0x10_0000;
+        let error = unsafe { t.header.validate(SIGNATURE) }.unwrap_err();
+        assert_eq!(error.kind(), TableErrorKind::Size(0x10_0000));
+
+        t.header.size = mem::size_of::<Table>() as u32;
+        t.header.revision = Revision::new(7, 0);
+        let error = unsafe { t.header.validate(SIGNATURE) }.unwrap_err();
+        assert_eq!(error.kind(), TableErrorKind::Revision(Revision::new(7, 0)));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn test_error_display() {
+        use alloc::string::ToString;
+
+        let error = TableError::new(BOOT_SIGNATURE, TableErrorKind::Signature(SIGNATURE));
+        assert_eq!(
+            error.to_string(),
+            "invalid BOOTSERV table: unexpected signature \"IBI TEST\""
+        );
+        let error = TableError::new(
+            SIGNATURE,
+            TableErrorKind::Crc {
+                expected: 1,
+                computed: 2,
+            },
+        );
+        assert_eq!(
+            error.to_string(),
+            "invalid IBI TEST table: CRC mismatch, expected 0x00000001 but computed 0x00000002"
+        );
+    }
 }
@@ -8,15 +8,17 @@ pub trait Table {
 }
 
 mod header;
-pub use self::header::Header;
+pub use self::header::{Header, TableError, TableErrorKind};
 
 mod revision;
 pub use self::revision::Revision;
 
 mod system;
-pub use self::system::{Boot, Runtime, SystemTable};
+pub use self::system::{Boot, Runtime, SystemTable, SystemTableView};
 
 pub mod boot;
 pub mod runtime;
 
+pub mod acpi;
 pub mod cfg;
+pub mod fpdt;
@@ -1,38 +1,6 @@
 use core::fmt;
 
-/// A revision of the UEFI specification.
-///
-/// The major revision number is incremented on major, API-incompatible changes.
-///
-/// The minor revision number is incremented on minor changes,
-/// it is stored as a two-digit binary-coded decimal.
-///
-/// # Display format
-///
-/// For major revision 2 and later, if the lower minor digit is zero,
-/// the revision is formatted as "major.minor-upper". Otherwise it's
-/// formatted as "major.minor-upper.minor-lower". This format is
-/// described in the "EFI System Table" section of the UEFI
-/// Specification.
-///
-/// Prior to major version 2, the revision is always formatted as
-/// "major.minor", with minor left-padded with zero if minor-upper is
-/// zero.
-///
-/// Examples:
-///
-/// ```
-/// use uefi::table::Revision;
-/// assert_eq!(Revision::EFI_1_02.to_string(), "1.02");
-/// assert_eq!(Revision::EFI_1_10.to_string(), "1.10");
-/// assert_eq!(Revision::EFI_2_00.to_string(), "2.0");
-/// assert_eq!(Revision::EFI_2_30.to_string(), "2.3");
-/// assert_eq!(Revision::EFI_2_31.to_string(), "2.3.1");
-/// assert_eq!(Revision::EFI_2_100.to_string(), "2.10");
-/// ```
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
-#[repr(transparent)]
-pub struct Revision(u32);
+pub use crate::raw::table::Revision;
 
 // Allow missing docs, there's nothing useful to document about these
 // constants.
@@ -75,6 +43,35 @@ impl Revision {
     pub const fn minor(self) -> u16 {
         self.0 as u16
     }
+
+    /// Returns true if this revision is the given UEFI specification
+    /// version or later.
+    ///
+    /// For major revision 2 and later, `minor` is the minor version as it
+    /// appears in the name of the specification, without the errata digit,
+    /// so `is_at_least(2, 8)` checks for UEFI 2.8 ([`EFI_2_80`]) and
+    /// `is_at_least(2, 10)` for UEFI 2.10 ([`EFI_2_100`]). Prior to major
+    /// revision 2, `minor` is the raw minor revision, as in
+    /// [`Revision::new`].
+    ///
+    /// ```
+    /// use uefi::table::Revision;
+    /// assert!(Revision::EFI_2_80.is_at_least(2, 8));
+    /// assert!(Revision::EFI_2_100.is_at_least(2, 8));
+    /// assert!(!Revision::EFI_2_70.is_at_least(2, 8));
+    /// ```
+    ///
+    /// [`EFI_2_80`]: Self::EFI_2_80
+    /// [`EFI_2_100`]: Self::EFI_2_100
+    #[must_use]
+    pub const fn is_at_least(self, major: u16, minor: u16) -> bool {
+        let minor = if major >= 2 {
+            minor.saturating_mul(10)
+        } else {
+            minor
+        };
+        self.0 >= Self::new(major, minor).0
+    }
 }
 
 impl fmt::Display for Revision {
@@ -107,4 +104,14 @@ mod tests {
 
         assert!(Revision::EFI_1_10 < Revision::EFI_2_00);
     }
+
+    #[test]
+    fn test_revision_is_at_least() {
+        assert!(Revision::EFI_2_31.is_at_least(2, 3));
+        assert!(!Revision::EFI_2_31.is_at_least(2, 4));
+        assert!(Revision::EFI_2_100.is_at_least(2, 9));
+        assert!(Revision::EFI_2_00.is_at_least(1, 10));
+        assert!(Revision::EFI_1_10.is_at_least(1, 2));
+        assert!(!Revision::EFI_1_10.is_at_least(2, 0));
+    }
 }
@@ -1,19 +1,26 @@
 //! UEFI services available at runtime, even after the OS boots.
 
-use super::{Header, Revision};
+use super::Revision;
 #[cfg(feature = "alloc")]
 use crate::data_types::FromSliceWithNulError;
+use crate::data_types::{PhysicalAddress, VirtualAddress};
+#[cfg(feature = "alloc")]
+use crate::mem::{call_with_growing_buffer, try_reserve, try_to_vec, try_vec};
+use crate::raw::table::runtime as raw;
 use crate::result::Error;
-use crate::table::boot::MemoryDescriptor;
-use crate::{guid, CStr16, Char16, Guid, Result, Status};
+use crate::table::boot::{MemoryAttribute, MemoryDescriptor, MEMORY_DESCRIPTOR_VERSION};
+use crate::{cstr16, guid, CStr16, Guid, Result, ResultExt, Status};
 #[cfg(feature = "alloc")]
-use alloc::{vec, vec::Vec};
+use alloc::{boxed::Box, vec::Vec};
 use bitflags::bitflags;
+use core::convert::Infallible;
 use core::fmt::{Debug, Formatter};
-#[cfg(feature = "alloc")]
 use core::mem;
 use core::mem::MaybeUninit;
 use core::{fmt, ptr};
+
+pub use crate::raw::table::runtime::TimeCapabilities;
+
 /// Contains pointers to all of the runtime services.
 ///
 /// This table, and the function pointers it contains are valid
@@ -24,76 +31,29 @@ use core::{fmt, ptr};
 /// A reference to `RuntimeServices` can only be accessed by calling [`SystemTable::runtime_services`].
 ///
 /// [`SystemTable::runtime_services`]: crate::table::SystemTable::runtime_services
-#[repr(C)]
+#[repr(transparent)]
 pub struct RuntimeServices {
-    header: Header,
-    get_time:
-        unsafe extern "efiapi" fn(time: *mut Time, capabilities: *mut TimeCapabilities) -> Status,
-    set_time: unsafe extern "efiapi" fn(time: &Time) -> Status,
-    // Skip some useless functions.
-    _pad: [usize; 2],
-    pub(crate) set_virtual_address_map: unsafe extern "efiapi" fn(
-        map_size: usize,
-        desc_size: usize,
-        desc_version: u32,
-        virtual_map: *mut MemoryDescriptor,
-    ) -> Status,
-    _pad2: usize,
-    get_variable: unsafe extern "efiapi" fn(
-        variable_name: *const Char16,
-        vendor_guid: *const Guid,
-        attributes: *mut VariableAttributes,
-        data_size: *mut usize,
-        data: *mut u8,
-    ) -> Status,
-    get_next_variable_name: unsafe extern "efiapi" fn(
-        variable_name_size: *mut usize,
-        variable_name: *mut u16,
-        vendor_guid: *mut Guid,
-    ) -> Status,
-    set_variable: unsafe extern "efiapi" fn(
-        variable_name: *const Char16,
-        vendor_guid: *const Guid,
-        attributes: VariableAttributes,
-        data_size: usize,
-        data: *const u8,
-    ) -> Status,
-    _pad3: usize,
-    reset: unsafe extern "efiapi" fn(
-        rt: ResetType,
-
-        status: Status,
-        data_size: usize,
-        data: *const u8,
-    ) -> !,
-
-    // UEFI 2.0 Capsule Services.
-    update_capsule: usize,
-    query_capsule_capabilities: usize,
-
-    // Miscellaneous UEFI 2.0 Service.
-    query_variable_info: unsafe extern "efiapi" fn(
-        attributes: VariableAttributes,
-        maximum_variable_storage_size: *mut u64,
-        remaining_variable_storage_size: *mut u64,
-        maximum_variable_size: *mut u64,
-    ) -> Status,
+    raw: raw::RuntimeServices,
 }
 
 impl RuntimeServices {
     /// Query the current time and date information
     pub fn get_time(&self) -> Result<Time> {
         let mut time = MaybeUninit::<Time>::uninit();
-        unsafe { (self.get_time)(time.as_mut_ptr(), ptr::null_mut()) }
-            .into_with_val(|| unsafe { time.assume_init() })
+        trace_status!("RuntimeServices::get_time", unsafe {
+            (self.raw.get_time)(time.as_mut_ptr().cast(), ptr::null_mut())
+        })
+        .into_with_val(|| unsafe { time.assume_init() })
     }
 
     /// Query the current time and date information and the RTC capabilities
     pub fn get_time_and_caps(&self) -> Result<(Time, TimeCapabilities)> {
         let mut time = MaybeUninit::<Time>::uninit();
         let mut caps = MaybeUninit::<TimeCapabilities>::uninit();
-        unsafe { (self.get_time)(time.as_mut_ptr(), caps.as_mut_ptr()) }
-            .into_with_val(|| unsafe { (time.assume_init(), caps.assume_init()) })
+        trace_status!("RuntimeServices::get_time", unsafe {
+            (self.raw.get_time)(time.as_mut_ptr().cast(), caps.as_mut_ptr())
+        })
+        .into_with_val(|| unsafe { (time.assume_init(), caps.assume_init()) })
     }
 
     /// Sets the current local time and date information
@@ -106,28 +66,166 @@ impl RuntimeServices {
     /// Undefined behavior could happen if multiple tasks try to
     /// use this function at the same time without synchronisation.
     pub unsafe fn set_time(&mut self, time: &Time) -> Result {
-        (self.set_time)(time).into()
+        trace_status!(
+            "RuntimeServices::set_time",
+            (self.raw.set_time)((time as *const Time).cast()),
+            "time={}",
+            time
+        )
+        .into()
+    }
+
+    /// Query the state of the wakeup alarm, which powers the platform on
+    /// at a given time.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the platform doesn't have a wakeup alarm.
+    /// * [`Status::DEVICE_ERROR`]: the alarm couldn't be read.
+    pub fn get_wakeup_time(&self) -> Result<WakeupTime> {
+        let mut enabled = false;
+        let mut pending = false;
+        let mut time = MaybeUninit::<Time>::uninit();
+        trace_status!("RuntimeServices::get_wakeup_time", unsafe {
+            (self.raw.get_wakeup_time)(&mut enabled, &mut pending, time.as_mut_ptr().cast())
+        })
+        .into_with_val(|| WakeupTime {
+            enabled,
+            pending,
+            time: unsafe { time.assume_init() },
+        })
+    }
+
+    /// Returns whether the platform has a wakeup alarm, i.e. whether
+    /// [`set_wakeup_time`] can be used to power it on at a given time.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::DEVICE_ERROR`]: the alarm couldn't be read.
+    ///
+    /// [`set_wakeup_time`]: Self::set_wakeup_time
+    pub fn is_wakeup_supported(&self) -> Result<bool> {
+        match self.get_wakeup_time() {
+            Ok(_) => Ok(true),
+            Err(err) if err.status() == Status::UNSUPPORTED => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Sets the wakeup alarm to `time`, or disables it if `time` is `None`.
+    ///
+    /// The platform powers on at `time` if it is shut down, e.g. with
+    /// [`shutdown`], at that point.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::INVALID_PARAMETER`]: `time` is not a valid time.
+    /// * [`Status::UNSUPPORTED`]: the platform doesn't have a wakeup alarm.
+    /// * [`Status::DEVICE_ERROR`]: the alarm couldn't be set.
+    ///
+    /// [`shutdown`]: Self::shutdown
+    pub fn set_wakeup_time(&self, time: Option<&Time>) -> Result {
+        let time_ptr = time.map_or(ptr::null(), |time| (time as *const Time).cast());
+        trace_status!(
+            "RuntimeServices::set_wakeup_time",
+            unsafe { (self.raw.set_wakeup_time)(time.is_some(), time_ptr) },
+            "enable={}",
+            time.is_some()
+        )
+        .into()
+    }
+
+    /// Switch the runtime services from physical to virtual addressing,
+    /// using the virtual addresses of the runtime memory regions in `map`.
+    ///
+    /// This can only be called once, after exiting boot services. Before
+    /// returning, the firmware converts its own pointers, and signals the
+    /// events of the `VIRTUAL_ADDRESS_CHANGE` group.
+    ///
+    /// [`SystemTable::enter_virtual_mode`] also converts the address of the
+    /// system table, and should usually be used instead.
+    ///
+    /// # Safety
+    ///
+    /// Once this returns, the runtime regions must be mapped at their new
+    /// virtual addresses whenever a runtime service is called. Pointers into
+    /// runtime regions held by the caller, including the pointer to the
+    /// system table, must be converted with
+    /// [`VirtualAddressMap::convert_pointer`].
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: boot services are still active, or the
+    ///   runtime services are already in virtual mode.
+    /// * [`Status::INVALID_PARAMETER`]
+    /// * [`Status::NO_MAPPING`]: a runtime region has no virtual address in
+    ///   `map`.
+    /// * [`Status::NOT_FOUND`]: a region of `map` is not a runtime region.
+    ///
+    /// [`SystemTable::enter_virtual_mode`]: crate::table::SystemTable::enter_virtual_mode
+    pub unsafe fn set_virtual_address_map(&self, map: &mut VirtualAddressMap<'_>) -> Result {
+        let descriptors = map.descriptors_mut();
+        (self.raw.set_virtual_address_map)(
+            mem::size_of_val(descriptors),
+            mem::size_of::<MemoryDescriptor>(),
+            MEMORY_DESCRIPTOR_VERSION,
+            descriptors.as_mut_ptr(),
+        )
+        .into()
+    }
+
+    /// Convert the pointer at `ptr` from a physical to a virtual address.
+    /// Null pointers are left unchanged.
+    ///
+    /// This can only be called by the notification functions of
+    /// `VIRTUAL_ADDRESS_CHANGE` events, while the runtime services switch
+    /// to virtual mode. See [`runtime_pointers`] for a registry of pointers
+    /// that are converted automatically.
+    ///
+    /// # Safety
+    ///
+    /// Once converted, `ptr` can only be dereferenced when the virtual
+    /// address mapping is active.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: `ptr` is not in a runtime region.
+    /// * [`Status::UNSUPPORTED`]: the runtime services are not switching to
+    ///   virtual mode.
+    ///
+    /// [`runtime_pointers`]: crate::runtime_pointers
+    pub unsafe fn convert_pointer<T>(&self, ptr: &mut *const T) -> Result {
+        /// `EFI_OPTIONAL_PTR`: the pointer may be null.
+        const OPTIONAL_PTR: usize = 0x1;
+        (self.raw.convert_pointer)(OPTIONAL_PTR, (ptr as *mut *const T).cast()).into()
     }
 
     /// Get the size (in bytes) of a variable. This can be used to find out how
     /// big of a buffer should be passed in to `get_variable`.
     pub fn get_variable_size(&self, name: &CStr16, vendor: &VariableVendor) -> Result<usize> {
         let mut data_size = 0;
-        let status = unsafe {
-            (self.get_variable)(
-                name.as_ptr(),
-                &vendor.0,
-                ptr::null_mut(),
-                &mut data_size,
-                ptr::null_mut(),
-            )
-        };
+        let status = trace_status!(
+            "RuntimeServices::get_variable",
+            unsafe {
+                (self.raw.get_variable)(
+                    name.as_ptr(),
+                    &vendor.0,
+                    ptr::null_mut(),
+                    &mut data_size,
+                    ptr::null_mut(),
+                )
+            },
+            "name={}, vendor={}, size=0",
+            name,
+            vendor.0
+        );
 
         if status == Status::BUFFER_TOO_SMALL {
             Status::SUCCESS.into_with_val(|| data_size)
         } else {
             Err(Error::from(status))
         }
+        .with_context("RuntimeServices::get_variable_size")
     }
 
     /// Get the contents and attributes of a variable. The size of `buf` must
@@ -142,18 +240,66 @@ impl RuntimeServices {
         vendor: &VariableVendor,
         buf: &'a mut [u8],
     ) -> Result<(&'a [u8], VariableAttributes)> {
-        let mut attributes = VariableAttributes::empty();
+        let mut attributes = 0;
         let mut data_size = buf.len();
-        unsafe {
-            (self.get_variable)(
-                name.as_ptr(),
-                &vendor.0,
-                &mut attributes,
-                &mut data_size,
-                buf.as_mut_ptr(),
+        trace_status!(
+            "RuntimeServices::get_variable",
+            unsafe {
+                (self.raw.get_variable)(
+                    name.as_ptr(),
+                    &vendor.0,
+                    &mut attributes,
+                    &mut data_size,
+                    buf.as_mut_ptr(),
+                )
+            },
+            "name={}, vendor={}, size={}",
+            name,
+            vendor.0,
+            buf.len()
+        )
+        .into_with_val(move || {
+            (
+                &buf[..data_size],
+                VariableAttributes::from_bits_truncate(attributes),
             )
-            .into_with_val(move || (&buf[..data_size], attributes))
-        }
+        })
+        .with_context("RuntimeServices::get_variable")
+    }
+
+    /// Get the contents and attributes of a variable, allocating a
+    /// buffer of the required size.
+    #[cfg(feature = "alloc")]
+    pub fn get_variable_boxed(
+        &self,
+        name: &CStr16,
+        vendor: &VariableVendor,
+    ) -> Result<(Box<[u8]>, VariableAttributes)> {
+        let mut attributes = 0;
+        let data = call_with_growing_buffer(0, |buf: &mut [MaybeUninit<u8>]| {
+            let mut data_size = buf.len();
+            trace_status!(
+                "RuntimeServices::get_variable",
+                unsafe {
+                    (self.raw.get_variable)(
+                        name.as_ptr(),
+                        &vendor.0,
+                        &mut attributes,
+                        &mut data_size,
+                        buf.as_mut_ptr().cast(),
+                    )
+                },
+                "name={}, vendor={}, size={}",
+                name,
+                vendor.0,
+                buf.len()
+            )
+            .into_with(|| data_size, |_| Some(data_size))
+        })?;
+        Ok((
+            data.into_boxed_slice(),
+            VariableAttributes::from_bits_truncate(attributes),
+        ))
     }
 
     /// Get the names and vendor GUIDs of all currently-set variables.
@@ -163,32 +309,33 @@ impl RuntimeServices {
 
         // The initial value of name must start with a null character. Start
         // out with a reasonable size that likely won't need to be increased.
-        let mut name = vec![0u16; 32];
+        let mut name = try_vec(0u16, 32)?;
         // The initial value of vendor is ignored.
         let mut vendor = Guid::default();
 
         let mut status;
         loop {
             let mut name_size_in_bytes = name.len() * mem::size_of::<u16>();
-            status = unsafe {
-                (self.get_next_variable_name)(
+            status = trace_status!("RuntimeServices::get_next_variable_name", unsafe {
+                (self.raw.get_next_variable_name)(
                     &mut name_size_in_bytes,
                     name.as_mut_ptr(),
                     &mut vendor,
                 )
-            };
+            });
 
             match status {
                 Status::SUCCESS => {
                     // CStr16::from_u16_with_nul does not allow interior nulls,
                     // so make the copy exactly the right size.
                     let name = if let Some(nul_pos) = name.iter().position(|c| *c == 0) {
-                        name[..=nul_pos].to_vec()
+                        try_to_vec(&name[..=nul_pos])?
                     } else {
                         status = Status::ABORTED;
                         break;
                     };
 
+                    try_reserve(&mut all_variables, 1)?;
                     all_variables.push(VariableKey {
                         name,
                         vendor: VariableVendor(vendor),
@@ -197,7 +344,10 @@ impl RuntimeServices {
                 Status::BUFFER_TOO_SMALL => {
                     // The name buffer passed in was too small, resize it to be
                     // big enough for the next variable name.
-                    name.resize(name_size_in_bytes / 2, 0);
+                    let new_len = name_size_in_bytes / 2;
+                    let additional = new_len.saturating_sub(name.len());
+                    try_reserve(&mut name, additional)?;
+                    name.resize(new_len, 0);
                 }
                 Status::NOT_FOUND => {
                     // This status indicates the end of the list. The final
@@ -233,16 +383,25 @@ impl RuntimeServices {
         attributes: VariableAttributes,
         data: &[u8],
     ) -> Result {
-        unsafe {
-            (self.set_variable)(
-                name.as_ptr(),
-                &vendor.0,
-                attributes,
-                data.len(),
-                data.as_ptr(),
-            )
-            .into()
-        }
+        trace_status!(
+            "RuntimeServices::set_variable",
+            unsafe {
+                (self.raw.set_variable)(
+                    name.as_ptr(),
+                    &vendor.0,
+                    attributes.bits(),
+                    data.len(),
+                    data.as_ptr(),
+                )
+            },
+            "name={}, vendor={}, attributes={:?}, size={}",
+            name,
+            vendor.0,
+            attributes,
+            data.len()
+        )
+        .into_with_val(|| ())
+        .with_context("RuntimeServices::set_variable")
     }
 
     /// Get information about UEFI variable storage space for the type
@@ -256,20 +415,30 @@ impl RuntimeServices {
         &self,
         attributes: VariableAttributes,
     ) -> Result<VariableStorageInfo> {
-        if self.header.revision < Revision::EFI_2_00 {
-            return Err(Status::UNSUPPORTED.into());
+        // Safety: the pointer is to a field of this table.
+        let query_variable_info = unsafe {
+            self.raw.header.versioned_field(
+                Revision::EFI_2_00,
+                ptr::addr_of!(self.raw.query_variable_info),
+            )
         }
+        .ok_or(Status::UNSUPPORTED)?;
 
         let mut info = VariableStorageInfo::default();
-        unsafe {
-            (self.query_variable_info)(
-                attributes,
-                &mut info.maximum_variable_storage_size,
-                &mut info.remaining_variable_storage_size,
-                &mut info.maximum_variable_size,
-            )
-            .into_with_val(|| info)
-        }
+        trace_status!(
+            "RuntimeServices::query_variable_info",
+            unsafe {
+                query_variable_info(
+                    attributes.bits(),
+                    &mut info.maximum_variable_storage_size,
+                    &mut info.remaining_variable_storage_size,
+                    &mut info.maximum_variable_size,
+                )
+            },
+            "attributes={:?}",
+            attributes
+        )
+        .into_with_val(|| info)
     }
 
     /// Resets the computer.
@@ -285,7 +454,94 @@ impl RuntimeServices {
             None => (0, ptr::null()),
         };
 
-        unsafe { (self.reset)(rt, status, size, data) }
+        unsafe { (self.raw.reset_system)(rt as u32, status, size, data) }
+    }
+
+    /// Powers the platform off.
+    ///
+    /// This is [`reset`] with [`ResetType::Shutdown`]. Platforms that don't
+    /// support shutting down do a cold reset instead.
+    ///
+    /// [`reset`]: Self::reset
+    pub fn shutdown(&self) -> ! {
+        self.reset(ResetType::Shutdown, Status::SUCCESS, None)
+    }
+
+    /// Restarts the platform, with a cold or a warm reset.
+    ///
+    /// This is [`reset`] with the [`ResetType`] corresponding to `mode`.
+    ///
+    /// [`reset`]: Self::reset
+    pub fn reboot(&self, mode: RebootMode) -> ! {
+        self.reset(mode.into(), Status::SUCCESS, None)
+    }
+
+    /// Get the features supported by the firmware, from the
+    /// `OsIndicationsSupported` variable. Returns no features if the variable
+    /// doesn't exist.
+    pub fn os_indications_supported(&self) -> Result<OsIndications> {
+        self.read_os_indications(cstr16!("OsIndicationsSupported"))
+    }
+
+    /// Get the features requested to the firmware for the next boot, from
+    /// the `OsIndications` variable. Returns no features if the variable
+    /// doesn't exist.
+    pub fn os_indications(&self) -> Result<OsIndications> {
+        self.read_os_indications(cstr16!("OsIndications"))
+    }
+
+    /// Set the features requested to the firmware for the next boot, in the
+    /// `OsIndications` variable. The firmware clears them once handled.
+    ///
+    /// Use [`os_indications_supported`] to check that the firmware supports
+    /// the features.
+    ///
+    /// [`os_indications_supported`]: Self::os_indications_supported
+    pub fn set_os_indications(&self, indications: OsIndications) -> Result {
+        self.set_variable(
+            cstr16!("OsIndications"),
+            &VariableVendor::GLOBAL_VARIABLE,
+            VariableAttributes::NON_VOLATILE
+                | VariableAttributes::BOOTSERVICE_ACCESS
+                | VariableAttributes::RUNTIME_ACCESS,
+            &indications.bits().to_le_bytes(),
+        )
+    }
+
+    /// Reboot into the firmware user interface, such as the setup menu.
+    ///
+    /// This sets [`OsIndications::BOOT_TO_FW_UI`], keeping the other
+    /// requested features, and performs a cold reset. It only returns on
+    /// error.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::UNSUPPORTED`]: the firmware doesn't support booting to its
+    ///   user interface.
+    ///
+    /// Errors from [`RuntimeServices::set_variable`] are passed through.
+    pub fn reboot_to_firmware_ui(&self) -> Result<Infallible> {
+        if !self
+            .os_indications_supported()?
+            .contains(OsIndications::BOOT_TO_FW_UI)
+        {
+            return Err(Status::UNSUPPORTED.into());
+        }
+        self.set_os_indications(self.os_indications()? | OsIndications::BOOT_TO_FW_UI)?;
+        self.reset(ResetType::Cold, Status::SUCCESS, None)
+    }
+
+    fn read_os_indications(&self, name: &CStr16) -> Result<OsIndications> {
+        let mut buf = [0; 8];
+        match self.get_variable(name, &VariableVendor::GLOBAL_VARIABLE, &mut buf) {
+            Ok((data, _)) => {
+                let mut bits = [0; 8];
+                bits[..data.len()].copy_from_slice(data);
+                Ok(OsIndications::from_bits_truncate(u64::from_le_bytes(bits)))
+            }
+            Err(err) if err.status() == Status::NOT_FOUND => Ok(OsIndications::empty()),
+            Err(err) => Err(err),
+        }
     }
 }
 
@@ -296,14 +552,15 @@ impl super::Table for RuntimeServices {
 impl Debug for RuntimeServices {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         f.debug_struct("RuntimeServices")
-            .field("header", &self.header)
-            .field("get_time", &(self.get_time as *const u64))
-            .field("set_time", &(self.set_time as *const u64))
+            .field("header", &self.raw.header)
+            .field("get_time", &(self.raw.get_time as *const u64))
+            .field("set_time", &(self.raw.set_time as *const u64))
             .field(
                 "set_virtual_address_map",
-                &(self.set_virtual_address_map as *const u64),
+                &(self.raw.set_virtual_address_map as *const u64),
             )
-            .field("reset", &(self.reset as *const u64))
+            .field("convert_pointer", &(self.raw.convert_pointer as *const u64))
+            .field("reset", &(self.raw.reset_system as *const u64))
             .finish()
     }
 }
@@ -360,6 +617,11 @@ pub struct TimeParams {
 
 bitflags! {
     /// A bitmask containing daylight savings time information.
+    #[cfg_attr(
+        feature = "serde",
+        derive(serde::Serialize, serde::Deserialize),
+        serde(transparent)
+    )]
     pub struct Daylight: u8 {
         /// Time is affected by daylight savings time.
         const ADJUST_DAYLIGHT = 0x01;
@@ -493,6 +755,149 @@ impl Time {
     pub const fn daylight(&self) -> Daylight {
         self.daylight
     }
+
+    /// Create a UTC `Time` from the number of seconds since the Unix epoch
+    /// (1970-01-01 00:00:00 UTC), and a fraction of a second. If the time is
+    /// not in the range of years `1900..=9999`, or `nanosecond` is not less
+    /// than a second, [`TimeError`] is returned.
+    pub fn from_unix_timestamp(
+        seconds: i64,
+        nanosecond: u32,
+    ) -> core::result::Result<Self, TimeError> {
+        const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+        let days = seconds.div_euclid(SECONDS_PER_DAY);
+        let seconds = seconds.rem_euclid(SECONDS_PER_DAY);
+        // The range of days is limited, so that the computation of the date
+        // can't overflow, by a bound that is well outside the valid years.
+        if days.unsigned_abs() > 10_000 * 366 {
+            return Err(TimeError);
+        }
+        let (year, month, day) = civil_from_days(days);
+        Self::new(TimeParams {
+            year: u16::try_from(year).map_err(|_| TimeError)?,
+            month,
+            day,
+            hour: (seconds / 3600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8,
+            nanosecond,
+            time_zone: Some(0),
+            daylight: Daylight::empty(),
+        })
+    }
+
+    /// Get the number of seconds since the Unix epoch, ignoring
+    /// [`nanosecond`].
+    ///
+    /// A time with an unspecified time zone, which is usually the case for
+    /// the real-time clock and FAT timestamps, is taken as UTC. Returns
+    /// `None` if the time is not valid, or if it has a time zone other than
+    /// UTC.
+    ///
+    /// [`nanosecond`]: Self::nanosecond
+    #[must_use]
+    pub fn to_unix_timestamp(&self) -> Option<i64> {
+        if !self.is_valid() || !matches!(self.time_zone(), None | Some(0)) {
+            return None;
+        }
+        let days = days_from_civil(i64::from(self.year), self.month, self.day);
+        Some(
+            days * 24 * 60 * 60
+                + i64::from(self.hour) * 3600
+                + i64::from(self.minute) * 60
+                + i64::from(self.second),
+        )
+    }
+}
+
+/// Number of days from 1970-01-01 to the given date of the proleptic
+/// Gregorian calendar.
+fn days_from_civil(year: i64, month: u8, day: u8) -> i64 {
+    // Count years from March, so that leap days are at the end of the year.
+    let (month, day) = (i64::from(month), i64::from(day));
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Date of the proleptic Gregorian calendar that is `days` after
+/// 1970-01-01, as a year, month and day. This is the inverse of
+/// [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u8;
+    let month = ((month_from_march + 2) % 12 + 1) as u8;
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Serialized as a struct with the fields of [`TimeParams`].
+#[cfg(feature = "serde")]
+impl serde::Serialize for Time {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("Time", 9)?;
+        state.serialize_field("year", &self.year)?;
+        state.serialize_field("month", &self.month)?;
+        state.serialize_field("day", &self.day)?;
+        state.serialize_field("hour", &self.hour)?;
+        state.serialize_field("minute", &self.minute)?;
+        state.serialize_field("second", &self.second)?;
+        state.serialize_field("nanosecond", &self.nanosecond)?;
+        state.serialize_field("time_zone", &self.time_zone())?;
+        state.serialize_field("daylight", &self.daylight)?;
+        state.end()
+    }
+}
+
+/// Fails if the time is not valid, see [`Time::new`].
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Time {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Time")]
+        struct Fields {
+            year: u16,
+            month: u8,
+            day: u8,
+            hour: u8,
+            minute: u8,
+            second: u8,
+            nanosecond: u32,
+            time_zone: Option<i16>,
+            daylight: Daylight,
+        }
+
+        let fields = Fields::deserialize(deserializer)?;
+        Self::new(TimeParams {
+            year: fields.year,
+            month: fields.month,
+            day: fields.day,
+            hour: fields.hour,
+            minute: fields.minute,
+            second: fields.second,
+            nanosecond: fields.nanosecond,
+            time_zone: fields.time_zone,
+            daylight: fields.daylight,
+        })
+        .map_err(|_| serde::de::Error::custom("invalid time"))
+    }
 }
 
 impl fmt::Debug for Time {
@@ -558,20 +963,20 @@ impl PartialEq for Time {
 
 impl Eq for Time {}
 
-/// Real time clock capabilities
+/// State of the wakeup alarm, returned by
+/// [`RuntimeServices::get_wakeup_time`].
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-#[repr(C)]
-pub struct TimeCapabilities {
-    /// Reporting resolution of the clock in counts per second. 1 for a normal
-    /// PC-AT CMOS RTC device, which reports the time with 1-second resolution.
-    pub resolution: u32,
+pub struct WakeupTime {
+    /// Whether the alarm is enabled.
+    pub enabled: bool,
 
-    /// Timekeeping accuracy in units of 1e-6 parts per million.
-    pub accuracy: u32,
+    /// Whether the alarm has fired and is waiting to be acknowledged. An
+    /// alarm is acknowledged by setting or disabling it.
+    pub pending: bool,
 
-    /// Whether a time set operation clears the device's time below the
-    /// "resolution" reporting level. False for normal PC-AT CMOS RTC devices.
-    pub sets_to_zero: bool,
+    /// The time of the alarm. Its value is unspecified if the alarm was
+    /// never set.
+    pub time: Time,
 }
 
 bitflags! {
@@ -611,10 +1016,47 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Features of the firmware, found in the `OsIndicationsSupported` and
+    /// `OsIndications` variables.
+    ///
+    /// See [`RuntimeServices::os_indications`].
+    pub struct OsIndications: u64 {
+        /// Stop in the firmware user interface on the next boot.
+        const BOOT_TO_FW_UI = 0x01;
+
+        /// Timestamp based revocation is supported.
+        const TIMESTAMP_REVOCATION = 0x02;
+
+        /// Process the capsules in `\EFI\UpdateCapsule` on the next boot.
+        const FILE_CAPSULE_DELIVERY_SUPPORTED = 0x04;
+
+        /// Firmware management protocol capsules are supported.
+        const FMP_CAPSULE_SUPPORTED = 0x08;
+
+        /// Capsule result variables are supported.
+        const CAPSULE_RESULT_VAR_SUPPORTED = 0x10;
+
+        /// Start the OS-defined recovery on the next boot.
+        const START_OS_RECOVERY = 0x20;
+
+        /// Start the platform-defined recovery on the next boot.
+        const START_PLATFORM_RECOVERY = 0x40;
+
+        /// Refresh the JSON configuration data on the next boot.
+        const JSON_CONFIG_DATA_REFRESH = 0x80;
+    }
+}
+
 newtype_enum! {
     /// Variable vendor GUID. This serves as a namespace for variables to
     /// avoid naming conflicts between vendors. The UEFI specification
     /// defines some special values, and vendors will define their own.
+    #[cfg_attr(
+        feature = "serde",
+        derive(serde::Serialize, serde::Deserialize),
+        serde(transparent)
+    )]
     pub enum VariableVendor: Guid => {
         /// Used to access global variables.
         GLOBAL_VARIABLE = guid!("8be4df61-93ca-11d2-aa0d-00e098032b8c"),
@@ -641,6 +1083,26 @@ impl VariableKey {
     }
 }
 
+/// Serialized as a struct with the `name` and the `vendor` of the variable.
+/// Fails if the name is not a valid UCS-2 string.
+#[cfg(all(feature = "alloc", feature = "serde"))]
+impl serde::Serialize for VariableKey {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeStruct};
+
+        let name = self
+            .name()
+            .map_err(|_| S::Error::custom("invalid variable name"))?;
+        let mut state = serializer.serialize_struct("VariableKey", 2)?;
+        state.serialize_field("name", name)?;
+        state.serialize_field("vendor", &self.vendor)?;
+        state.end()
+    }
+}
+
 #[cfg(feature = "alloc")]
 impl fmt::Display for VariableKey {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -681,6 +1143,120 @@ pub struct VariableStorageInfo {
     pub maximum_variable_size: u64,
 }
 
+/// The virtual addresses of the runtime memory regions, passed to
+/// [`RuntimeServices::set_virtual_address_map`].
+///
+/// The map is built in a buffer provided by the caller, since memory can't
+/// be allocated once boot services have been exited. Regions start out
+/// identity mapped; [`with_offset`] and [`with_mapping`] assign other
+/// virtual addresses.
+///
+/// ```no_run
+/// use uefi::prelude::*;
+/// use uefi::table::boot::MemoryDescriptor;
+/// use uefi::table::runtime::VirtualAddressMap;
+///
+/// const KERNEL_BASE: u64 = 0xffff_8000_0000_0000;
+///
+/// fn enter_higher_half(st: SystemTable<Boot>) -> uefi::Result<SystemTable<uefi::table::Runtime>> {
+///     let (st, memory_map) = st.exit_boot_services();
+///     let mut buffer = [MemoryDescriptor::default(); 64];
+///     let mut map = VirtualAddressMap::new(memory_map, &mut buffer)
+///         .map_err(|err| err.status())?
+///         .with_offset(KERNEL_BASE);
+///     // The page tables mapping `map` must be set up here.
+///     unsafe { st.enter_virtual_mode(&mut map) }
+/// }
+/// ```
+///
+/// [`with_offset`]: Self::with_offset
+/// [`with_mapping`]: Self::with_mapping
+#[derive(Debug)]
+pub struct VirtualAddressMap<'a> {
+    descriptors: &'a mut [MemoryDescriptor],
+}
+
+impl<'a> VirtualAddressMap<'a> {
+    /// Copy the runtime regions of `memory_map` to `buffer`, with their
+    /// virtual addresses equal to their physical addresses.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::BUFFER_TOO_SMALL`]: `buffer` is too small. The number of
+    ///   runtime regions is returned in the error data.
+    pub fn new<'m, I>(memory_map: I, buffer: &'a mut [MemoryDescriptor]) -> Result<Self, usize>
+    where
+        I: IntoIterator<Item = &'m MemoryDescriptor>,
+    {
+        let mut len = 0;
+        for desc in memory_map {
+            if !desc.att.contains(MemoryAttribute::RUNTIME) {
+                continue;
+            }
+            if let Some(entry) = buffer.get_mut(len) {
+                *entry = *desc;
+                entry.virt_start = desc.phys_start;
+            }
+            len += 1;
+        }
+        if len > buffer.len() {
+            return Err(Error::new(Status::BUFFER_TOO_SMALL, len));
+        }
+        Ok(Self {
+            descriptors: &mut buffer[..len],
+        })
+    }
+
+    /// Map every runtime region at its physical address plus `offset`.
+    #[must_use]
+    pub fn with_offset(self, offset: u64) -> Self {
+        self.with_mapping(|desc| desc.phys_start.wrapping_add(offset))
+    }
+
+    /// Map every runtime region at the virtual address returned by
+    /// `f`, which must be page-aligned.
+    #[must_use]
+    pub fn with_mapping<F>(self, mut f: F) -> Self
+    where
+        F: FnMut(&MemoryDescriptor) -> VirtualAddress,
+    {
+        for desc in self.descriptors.iter_mut() {
+            desc.virt_start = f(desc);
+        }
+        self
+    }
+
+    /// Get the runtime regions and their virtual addresses.
+    #[must_use]
+    pub fn descriptors(&self) -> &[MemoryDescriptor] {
+        self.descriptors
+    }
+
+    fn descriptors_mut(&mut self) -> &mut [MemoryDescriptor] {
+        self.descriptors
+    }
+
+    /// Get the virtual address of the physical address `addr`, or `None`
+    /// if `addr` is not in a runtime region.
+    #[must_use]
+    pub fn to_virtual(&self, addr: PhysicalAddress) -> Option<VirtualAddress> {
+        self.descriptors.iter().find_map(|desc| {
+            let offset = addr.checked_sub(desc.phys_start)?;
+            (offset < desc.page_count.checked_mul(4096)?)
+                .then(|| desc.virt_start.wrapping_add(offset))
+        })
+    }
+
+    /// Convert a pointer into a runtime region to its virtual address, like
+    /// the `ConvertPointer` runtime service does for the firmware's own
+    /// pointers. Returns `None` if `ptr` is not in a runtime region.
+    #[must_use]
+    pub fn convert_pointer<T>(&self, ptr: *mut T) -> Option<*mut T> {
+        let addr = self.to_virtual(ptr as usize as PhysicalAddress)?;
+        Some(usize::try_from(addr).ok()? as *mut T)
+    }
+}
+
 /// The type of system reset.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[repr(u32)]
@@ -702,3 +1278,196 @@ pub enum ResetType {
     //         the firmware. Therefore, unexpected values can never come from
     //         the firmware, and modeling this as a Rust enum seems safe.
 }
+
+/// The type of restart done by [`RuntimeServices::reboot`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RebootMode {
+    /// Power cycle the platform, see [`ResetType::Cold`].
+    Cold,
+    /// Reset the processors, see [`ResetType::Warm`]. Platforms that don't
+    /// support warm resets do a cold reset instead.
+    Warm,
+}
+
+impl From<RebootMode> for ResetType {
+    fn from(mode: RebootMode) -> Self {
+        match mode {
+            RebootMode::Cold => Self::Cold,
+            RebootMode::Warm => Self::Warm,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::*;
+    use crate::mock::MockFirmware;
+    use crate::table::boot::MemoryType;
+
+    #[test]
+    fn test_os_indications() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let rt = st.runtime_services();
+
+        assert_eq!(
+            rt.os_indications_supported().unwrap(),
+            OsIndications::empty()
+        );
+        assert_eq!(
+            rt.reboot_to_firmware_ui().unwrap_err().status(),
+            Status::UNSUPPORTED
+        );
+
+        rt.set_os_indications(OsIndications::START_OS_RECOVERY)
+            .unwrap();
+        assert_eq!(
+            rt.os_indications().unwrap(),
+            OsIndications::START_OS_RECOVERY
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let rt = st.runtime_services();
+
+        let time = rt.get_time().unwrap();
+        let json = serde_json::to_string(&time).unwrap();
+        assert_eq!(
+            json,
+            r#"{"year":2000,"month":1,"day":1,"hour":0,"minute":0,"second":0,"nanosecond":0,"time_zone":null,"daylight":0}"#
+        );
+        assert_eq!(serde_json::from_str::<Time>(&json).unwrap(), time);
+        let invalid = json.replace(r#""month":1"#, r#""month":13"#);
+        assert!(serde_json::from_str::<Time>(&invalid).is_err());
+
+        rt.set_variable(
+            cstr16!("Test"),
+            &VariableVendor::GLOBAL_VARIABLE,
+            VariableAttributes::BOOTSERVICE_ACCESS,
+            &[1],
+        )
+        .unwrap();
+        let keys = rt.variable_keys().unwrap();
+        assert_eq!(
+            serde_json::to_string(&keys).unwrap(),
+            r#"[{"name":"Test","vendor":"8be4df61-93ca-11d2-aa0d-00e098032b8c"}]"#
+        );
+
+        let descriptor = MemoryDescriptor {
+            ty: MemoryType::CONVENTIONAL,
+            phys_start: 0x1000,
+            virt_start: 0,
+            page_count: 2,
+            att: MemoryAttribute::WRITE_BACK,
+        };
+        assert_eq!(
+            serde_json::to_string(&descriptor).unwrap(),
+            r#"{"ty":7,"phys_start":4096,"virt_start":0,"page_count":2,"att":8}"#
+        );
+    }
+
+    #[test]
+    fn test_wakeup_time() {
+        let firmware = MockFirmware::new();
+        let st = firmware.system_table();
+        let rt = st.runtime_services();
+
+        assert!(rt.is_wakeup_supported().unwrap());
+        assert!(!rt.get_wakeup_time().unwrap().enabled);
+
+        let time = Time::new(TimeParams {
+            year: 2024,
+            month: 2,
+            day: 29,
+            hour: 6,
+            minute: 30,
+            second: 0,
+            nanosecond: 0,
+            time_zone: None,
+            daylight: Daylight::empty(),
+        })
+        .unwrap();
+        rt.set_wakeup_time(Some(&time)).unwrap();
+        let wakeup = rt.get_wakeup_time().unwrap();
+        assert!(wakeup.enabled);
+        assert!(!wakeup.pending);
+        assert_eq!(wakeup.time, time);
+
+        rt.set_wakeup_time(None).unwrap();
+        assert!(!rt.get_wakeup_time().unwrap().enabled);
+        assert_eq!(ResetType::from(RebootMode::Warm), ResetType::Warm);
+    }
+
+    #[test]
+    fn test_virtual_address_map() {
+        let region = |ty, phys_start, page_count, att| MemoryDescriptor {
+            ty,
+            phys_start,
+            virt_start: 0,
+            page_count,
+            att,
+        };
+        let memory_map = [
+            region(MemoryType::CONVENTIONAL, 0, 16, MemoryAttribute::WRITE_BACK),
+            region(
+                MemoryType::RUNTIME_SERVICES_CODE,
+                0x10000,
+                2,
+                MemoryAttribute::RUNTIME,
+            ),
+            region(
+                MemoryType::RUNTIME_SERVICES_DATA,
+                0x20000,
+                1,
+                MemoryAttribute::RUNTIME,
+            ),
+        ];
+
+        let mut buffer = [MemoryDescriptor::default(); 1];
+        let err = VirtualAddressMap::new(&memory_map, &mut buffer).unwrap_err();
+        assert_eq!(err.status(), Status::BUFFER_TOO_SMALL);
+        assert_eq!(*err.data(), 2);
+
+        let mut buffer = [MemoryDescriptor::default(); 4];
+        let map = VirtualAddressMap::new(&memory_map, &mut buffer).unwrap();
+        assert_eq!(map.descriptors().len(), 2);
+        assert_eq!(map.to_virtual(0x10010), Some(0x10010));
+
+        let map = map.with_offset(0xffff_0000_0000);
+        assert_eq!(map.descriptors()[1].virt_start, 0xffff_0002_0000);
+        assert_eq!(map.to_virtual(0x11fff), Some(0xffff_0001_1fff));
+        assert_eq!(map.to_virtual(0x12000), None);
+        assert_eq!(map.to_virtual(0x1000), None);
+        assert_eq!(
+            map.convert_pointer(0x20008 as *mut u64),
+            Some(0xffff_0002_0008 as *mut u64)
+        );
+    }
+
+    #[test]
+    fn test_unix_timestamp() {
+        let epoch = Time::from_unix_timestamp(0, 0).unwrap();
+        assert_eq!((epoch.year(), epoch.month(), epoch.day()), (1970, 1, 1));
+        assert_eq!(epoch.time_zone(), Some(0));
+
+        // 2024-02-29 12:34:56 UTC.
+        let time = Time::from_unix_timestamp(1_709_210_096, 7).unwrap();
+        assert_eq!((time.year(), time.month(), time.day()), (2024, 2, 29));
+        assert_eq!((time.hour(), time.minute(), time.second()), (12, 34, 56));
+        assert_eq!(time.nanosecond(), 7);
+        assert_eq!(time.to_unix_timestamp(), Some(1_709_210_096));
+
+        let early = Time::from_unix_timestamp(-2_208_988_800, 0).unwrap();
+        assert_eq!((early.year(), early.month(), early.day()), (1900, 1, 1));
+        assert_eq!(early.to_unix_timestamp(), Some(-2_208_988_800));
+
+        assert_eq!(Time::from_unix_timestamp(-2_208_988_801, 0), Err(TimeError));
+        assert_eq!(Time::from_unix_timestamp(i64::MAX, 0), Err(TimeError));
+        assert_eq!(Time::from_unix_timestamp(0, 1_000_000_000), Err(TimeError));
+        assert_eq!(Time::invalid().to_unix_timestamp(), None);
+    }
+}
@@ -2,25 +2,56 @@ use core::ffi::c_void;
 use core::fmt::{Debug, Formatter};
 use core::marker::PhantomData;
 use core::ptr::NonNull;
-use core::{ptr, slice};
+use core::slice;
 
 use crate::proto::console::text;
-use crate::{CStr16, Char16, Handle, Result, Status};
+use crate::raw::table as raw;
+use crate::{CStr16, Guid, Handle, Result, Status};
 
+use super::acpi::{self, AcpiTableHeader};
 use super::boot::{BootServices, MemoryDescriptor, MemoryMapIter, MemoryType};
-use super::runtime::{ResetType, RuntimeServices};
-use super::{cfg, Header, Revision};
+use super::runtime::{ResetType, RuntimeServices, VirtualAddressMap};
+use super::{cfg, Revision, Table, TableError, TableErrorKind};
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for super::Boot {}
+    impl Sealed for super::Runtime {}
+}
 
 /// Marker trait used to provide different views of the UEFI System Table
-pub trait SystemTableView {}
+///
+/// The only views are [`Boot`] and [`Runtime`]; the trait is sealed, so that
+/// a [`SystemTable`] can't be created with a view giving access to boot
+/// services after they were exited. Functions that work in both views can
+/// be generic over the view:
+///
+/// ```
+/// use uefi::table::{SystemTable, SystemTableView};
+///
+/// fn log_firmware<View: SystemTableView>(st: &SystemTable<View>) {
+///     log::info!("{} rev {:#x}", st.firmware_vendor(), st.firmware_revision());
+///     if View::BOOT_SERVICES {
+///         log::info!("boot services are available");
+///     }
+/// }
+/// ```
+pub trait SystemTableView: sealed::Sealed {
+    /// Whether boot services are available in this view.
+    const BOOT_SERVICES: bool;
+}
 
 /// Marker struct associated with the boot view of the UEFI System Table
 pub struct Boot;
-impl SystemTableView for Boot {}
+impl SystemTableView for Boot {
+    const BOOT_SERVICES: bool = true;
+}
 
 /// Marker struct associated with the run-time view of the UEFI System Table
 pub struct Runtime;
-impl SystemTableView for Runtime {}
+impl SystemTableView for Runtime {
+    const BOOT_SERVICES: bool = false;
+}
 
 /// UEFI System Table interface
 ///
@@ -43,10 +74,32 @@ impl SystemTableView for Runtime {}
 /// table will be destroyed (which conveniently invalidates all references to
 /// UEFI boot services in the eye of the Rust borrow checker) and a runtime view
 /// will be provided to replace it.
+///
+/// Boot services, and the values borrowed from them, can therefore not be
+/// used after exiting them:
+///
+/// ```compile_fail,E0505
+/// use uefi::prelude::*;
+///
+/// fn boot(st: SystemTable<Boot>) {
+///     let bt = st.boot_services();
+///     let (st, _memory_map) = st.exit_boot_services();
+///     bt.stall(1000);
+/// }
+/// ```
+///
+/// ```compile_fail,E0599
+/// use uefi::prelude::*;
+/// use uefi::table::Runtime;
+///
+/// fn runtime(st: SystemTable<Runtime>) {
+///     st.boot_services().stall(1000);
+/// }
+/// ```
 #[repr(transparent)]
 #[derive(Debug)]
 pub struct SystemTable<View: SystemTableView> {
-    table: &'static SystemTableImpl,
+    table: &'static raw::SystemTable,
     _marker: PhantomData<View>,
 }
 
@@ -55,13 +108,13 @@ impl<View: SystemTableView> SystemTable<View> {
     /// Return the firmware vendor string
     #[must_use]
     pub fn firmware_vendor(&self) -> &CStr16 {
-        unsafe { CStr16::from_ptr(self.table.fw_vendor) }
+        unsafe { CStr16::from_ptr(self.table.firmware_vendor) }
     }
 
     /// Return the firmware revision
     #[must_use]
     pub const fn firmware_revision(&self) -> u32 {
-        self.table.fw_revision
+        self.table.firmware_revision
     }
 
     /// Returns the revision of this table, which is defined to be
@@ -76,7 +129,41 @@ impl<View: SystemTableView> SystemTable<View> {
     #[allow(clippy::missing_const_for_fn)] // Required until we bump the MSRV.
     #[must_use]
     pub fn config_table(&self) -> &[cfg::ConfigTableEntry] {
-        unsafe { slice::from_raw_parts(self.table.cfg_table, self.table.nr_cfg) }
+        unsafe {
+            slice::from_raw_parts(
+                self.table.configuration_table.cast(),
+                self.table.number_of_configuration_table_entries,
+            )
+        }
+    }
+
+    /// Find the config table entry with the given `guid`.
+    #[must_use]
+    pub fn find_config_table_entry(&self, guid: &Guid) -> Option<&cfg::ConfigTableEntry> {
+        self.config_table().iter().find(|entry| entry.guid == *guid)
+    }
+
+    /// Find a config table with a known layout, identified by the GUID
+    /// of `T`.
+    ///
+    /// Returns `None` if the table is not present or its address is null.
+    #[must_use]
+    pub fn find_config_table<T: cfg::ConfigTable>(&self) -> Option<&T> {
+        let entry = self.find_config_table_entry(&T::GUID)?;
+        // Safety: the `ConfigTable` trait guarantees that the table has
+        // the layout of `T`.
+        unsafe { entry.address.cast::<T>().as_ref() }
+    }
+
+    /// Find the first ACPI table with the given `signature`, such as
+    /// `*b"FACP"`.
+    ///
+    /// See [`acpi::find_table`] for details.
+    ///
+    /// [`acpi::find_table`]: super::acpi::find_table
+    #[must_use]
+    pub fn find_acpi_table(&self, signature: &[u8; 4]) -> Option<&AcpiTableHeader> {
+        acpi::find_table(self.config_table(), signature)
     }
 
     /// Creates a new `SystemTable<View>` from a raw address. The address might
@@ -104,6 +191,63 @@ impl<View: SystemTableView> SystemTable<View> {
             _marker: PhantomData,
         })
     }
+
+    /// Creates a new `SystemTable<View>` from a raw address like
+    /// [`from_ptr`](Self::from_ptr), and checks it with
+    /// [`validate`](Self::validate). Use this when the address comes from
+    /// a source that might hand over a wrong or corrupted table, such as a
+    /// kexec-style loader or a hypervisor.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be null or readable as a system table, and the
+    /// service table pointers in it must be null or readable for the
+    /// size in their headers. See [`Header::validate`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TableError`] if the pointer is null or a table fails
+    /// validation.
+    ///
+    /// [`Header::validate`]: super::Header::validate
+    pub unsafe fn from_ptr_validated(ptr: *mut c_void) -> core::result::Result<Self, TableError> {
+        let st =
+            Self::from_ptr(ptr).ok_or(TableError::new(Self::SIGNATURE, TableErrorKind::Null))?;
+        st.validate()?;
+        Ok(st)
+    }
+
+    /// Checks the signature, revision, size and CRC of the system table
+    /// and of the runtime services table. The boot services table is also
+    /// checked, unless its pointer is null, which the firmware sets it to
+    /// when boot services are exited.
+    ///
+    /// The firmware updates the CRC whenever it changes a table, so a
+    /// mismatch indicates that the table is corrupted or was modified
+    /// without updating the CRC.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`TableError`] describing the first table that failed
+    /// validation.
+    pub fn validate(&self) -> core::result::Result<(), TableError> {
+        // Safety: the system table is checked first, so its pointers are
+        // only followed if it is plausible.
+        unsafe {
+            self.table.header.validate(Self::SIGNATURE)?;
+
+            let rt = self.table.runtime_services.as_ref().ok_or(TableError::new(
+                RuntimeServices::SIGNATURE,
+                TableErrorKind::Null,
+            ))?;
+            rt.header.validate(RuntimeServices::SIGNATURE)?;
+
+            if let Some(bt) = self.table.boot_services.as_ref() {
+                bt.header.validate(BootServices::SIGNATURE)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 // These parts of the UEFI System Table interface may only be used until boot
@@ -111,7 +255,7 @@ impl<View: SystemTableView> SystemTable<View> {
 impl SystemTable<Boot> {
     /// Returns the standard input protocol.
     pub fn stdin(&mut self) -> &mut text::Input {
-        unsafe { &mut *self.table.stdin }
+        unsafe { &mut *self.table.stdin.cast() }
     }
 
     /// Returns the standard output protocol.
@@ -124,16 +268,34 @@ impl SystemTable<Boot> {
         unsafe { &mut *self.table.stderr.cast() }
     }
 
+    /// Returns the handle of the standard input device.
+    #[must_use]
+    pub const fn stdin_handle(&self) -> Option<Handle> {
+        self.table.stdin_handle
+    }
+
+    /// Returns the handle of the standard output device.
+    #[must_use]
+    pub const fn stdout_handle(&self) -> Option<Handle> {
+        self.table.stdout_handle
+    }
+
+    /// Returns the handle of the standard error device.
+    #[must_use]
+    pub const fn stderr_handle(&self) -> Option<Handle> {
+        self.table.stderr_handle
+    }
+
     /// Access runtime services
     #[must_use]
     pub const fn runtime_services(&self) -> &RuntimeServices {
-        self.table.runtime
+        unsafe { &*self.table.runtime_services.cast() }
     }
 
     /// Access boot services
     #[must_use]
     pub const fn boot_services(&self) -> &BootServices {
-        unsafe { &*self.table.boot }
+        unsafe { &*self.table.boot_services.cast() }
     }
 
     /// Get the size in bytes of the buffer to allocate for storing the memory
@@ -184,6 +346,9 @@ impl SystemTable<Boot> {
     /// `SystemTable<Boot>` view of the System Table and returning a more
     /// restricted `SystemTable<Runtime>` view as an output.
     ///
+    /// With the `alloc` feature, the callbacks registered with
+    /// [`cleanup::on_exit`] are run before boot services are exited.
+    ///
     /// The memory map at the time of exiting boot services is also
     /// returned. The map is backed by a [`MemoryType::LOADER_DATA`]
     /// allocation. Since the boot services function to free that memory is no
@@ -208,11 +373,19 @@ impl SystemTable<Boot> {
     /// now in an undefined state. Rather than returning control to the
     /// caller, the system will be reset.
     ///
+    /// [`cleanup::on_exit`]: crate::cleanup::on_exit
     /// [`global_allocator::exit_boot_services`]: crate::global_allocator::exit_boot_services
     /// [`Logger::disable`]: crate::logger::Logger::disable
     /// [`uefi_services::init`]: https://docs.rs/uefi-services/latest/uefi_services/fn.init.html
     #[must_use]
     pub fn exit_boot_services(self) -> (SystemTable<Runtime>, MemoryMapIter<'static>) {
+        // Release resources while boot services are still usable.
+        #[cfg(feature = "alloc")]
+        crate::cleanup::run_exit_callbacks();
+
+        #[cfg(all(feature = "alloc-stats", debug_assertions))]
+        crate::global_allocator::log_leaks();
+
         let boot_services = self.boot_services();
 
         // Reboot the device.
@@ -274,7 +447,24 @@ impl SystemTable<Boot> {
 
 impl Debug for SystemTable<Boot> {
     fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        self.table.fmt(f)
+        let table = self.table;
+        f.debug_struct("UefiSystemTable")
+            .field("header", &table.header)
+            .field("fw_vendor", &self.firmware_vendor())
+            .field("fw_revision", &table.firmware_revision)
+            .field("stdin_handle", &table.stdin_handle)
+            .field("stdin", &table.stdin)
+            .field("stdout_handle", &table.stdout_handle)
+            .field("stdout", &table.stdout)
+            .field("stderr_handle", &table.stderr_handle)
+            .field("stderr", &table.stderr)
+            .field("runtime", self.runtime_services())
+            // a little bit of extra work needed to call debug-fmt on the BootServices
+            // instead of printing the raw pointer
+            .field("boot", self.boot_services())
+            .field("nf_cfg", &table.number_of_configuration_table_entries)
+            .field("cfg_table", &table.configuration_table)
+            .finish()
     }
 }
 
@@ -291,7 +481,7 @@ impl SystemTable<Runtime> {
     /// "Calling Conventions" chapter of the UEFI specification for details.
     #[must_use]
     pub const unsafe fn runtime_services(&self) -> &RuntimeServices {
-        self.table.runtime
+        &*self.table.runtime_services.cast()
     }
 
     /// Changes the runtime addressing mode of EFI firmware from physical to virtual.
@@ -317,15 +507,48 @@ impl SystemTable<Runtime> {
         let entry_size = core::mem::size_of::<MemoryDescriptor>();
         let entry_version = crate::table::boot::MEMORY_DESCRIPTOR_VERSION;
         let map_ptr = map.as_mut_ptr();
-        (self.table.runtime.set_virtual_address_map)(map_size, entry_size, entry_version, map_ptr)
-            .into_with_val(|| {
-                let new_table_ref =
-                    &mut *(new_system_table_virtual_addr as usize as *mut SystemTableImpl);
-                Self {
-                    table: new_table_ref,
-                    _marker: PhantomData,
-                }
-            })
+        ((*self.table.runtime_services).set_virtual_address_map)(
+            map_size,
+            entry_size,
+            entry_version,
+            map_ptr,
+        )
+        .into_with_val(|| {
+            let new_table_ref =
+                &mut *(new_system_table_virtual_addr as usize as *mut raw::SystemTable);
+            Self {
+                table: new_table_ref,
+                _marker: PhantomData,
+            }
+        })
+    }
+
+    /// Switch the runtime services from physical to virtual addressing, and
+    /// return the system table at its new virtual address.
+    ///
+    /// See [`RuntimeServices::set_virtual_address_map`] for details.
+    ///
+    /// # Safety
+    ///
+    /// Once this returns, the runtime regions must be mapped at their new
+    /// virtual addresses whenever the system table or the runtime services
+    /// are used.
+    ///
+    /// # Errors
+    ///
+    /// * [`Status::NOT_FOUND`]: the system table is not in a runtime region
+    ///   of `map`.
+    ///
+    /// See [`RuntimeServices::set_virtual_address_map`] for other errors.
+    pub unsafe fn enter_virtual_mode(self, map: &mut VirtualAddressMap<'_>) -> Result<Self> {
+        let table = map
+            .convert_pointer(self.table as *const raw::SystemTable as *mut raw::SystemTable)
+            .ok_or(Status::NOT_FOUND)?;
+        self.runtime_services().set_virtual_address_map(map)?;
+        Ok(Self {
+            table: &*table,
+            _marker: PhantomData,
+        })
     }
 
     /// Return the address of the SystemTable that resides in a UEFI runtime services
@@ -336,51 +559,6 @@ impl SystemTable<Runtime> {
     }
 }
 
-/// The actual UEFI system table
-#[repr(C)]
-struct SystemTableImpl {
-    header: Header,
-    /// Null-terminated string representing the firmware's vendor.
-    fw_vendor: *const Char16,
-    fw_revision: u32,
-    stdin_handle: Handle,
-    stdin: *mut text::Input,
-    stdout_handle: Handle,
-    stdout: *mut text::Output<'static>,
-    stderr_handle: Handle,
-    stderr: *mut text::Output<'static>,
-    /// Runtime services table.
-    runtime: &'static RuntimeServices,
-    /// Boot services table.
-    boot: *const BootServices,
-    /// Number of entries in the configuration table.
-    nr_cfg: usize,
-    /// Pointer to beginning of the array.
-    cfg_table: *const cfg::ConfigTableEntry,
-}
-
-impl<View: SystemTableView> super::Table for SystemTable<View> {
+impl<View: SystemTableView> Table for SystemTable<View> {
     const SIGNATURE: u64 = 0x5453_5953_2049_4249;
 }
-
-impl Debug for SystemTableImpl {
-    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
-        f.debug_struct("UefiSystemTable")
-            .field("header", &self.header)
-            .field("fw_vendor", &(unsafe { CStr16::from_ptr(self.fw_vendor) }))
-            .field("fw_revision", &self.fw_revision)
-            .field("stdin_handle", &self.stdin_handle)
-            .field("stdin", &self.stdin)
-            .field("stdout_handle", &self.stdout_handle)
-            .field("stdout", &self.stdout)
-            .field("stderr_handle", &self.stderr_handle)
-            .field("stderr", &self.stderr)
-            .field("runtime", &self.runtime)
-            // a little bit of extra work needed to call debug-fmt on the BootServices
-            // instead of printing the raw pointer
-            .field("boot", &(unsafe { ptr::read(self.boot) }))
-            .field("nf_cfg", &self.nr_cfg)
-            .field("cfg_table", &self.cfg_table)
-            .finish()
-    }
-}
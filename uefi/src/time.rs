@@ -0,0 +1,64 @@
+//! Helpers for waiting.
+
+use crate::table::boot::{BootServices, EventType, TimerTrigger, Tpl};
+use crate::util::div_ceil_u128;
+use crate::Result;
+use core::time::Duration;
+
+/// Waits shorter than this are done with [`BootServices::stall_duration`],
+/// since a timer event cannot fire more often than the firmware's timer
+/// tick (typically 10ms).
+const STALL_THRESHOLD: Duration = Duration::from_millis(10);
+
+/// Wait for `duration` to elapse.
+///
+/// Short waits are done with [`BootServices::stall_duration`]. Longer
+/// waits create a timer event and wait for it with
+/// [`BootServices::wait_for_event`], so the firmware can idle the CPU
+/// instead of spinning.
+///
+/// # Errors
+///
+/// Returns an error if the timer event cannot be created or waited
+/// on. In particular, [`BootServices::wait_for_event`] fails with
+/// [`Status::UNSUPPORTED`] if the current task priority level is not
+/// [`Tpl::APPLICATION`].
+///
+/// [`Status::UNSUPPORTED`]: crate::Status::UNSUPPORTED
+pub fn sleep(bt: &BootServices, duration: Duration) -> Result {
+    if duration < STALL_THRESHOLD {
+        bt.stall_duration(duration);
+        return Ok(());
+    }
+
+    let event = unsafe { bt.create_event(EventType::TIMER, Tpl::APPLICATION, None, None) }?;
+    let result = bt
+        .set_timer(&event, TimerTrigger::Relative(timer_period(duration)))
+        .and_then(|()| {
+            let mut events = unsafe { [event.unsafe_clone()] };
+            bt.wait_for_event(&mut events)
+                .map(|_| ())
+                .map_err(|err| err.into_err_without_payload())
+        });
+    bt.close_event(event)?;
+    result
+}
+
+/// Convert `duration` to a timer period in units of 100ns, rounding up.
+pub(crate) fn timer_period(duration: Duration) -> u64 {
+    u64::try_from(div_ceil_u128(duration.as_nanos(), 100)).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_period() {
+        assert_eq!(timer_period(Duration::ZERO), 0);
+        assert_eq!(timer_period(Duration::from_nanos(1)), 1);
+        assert_eq!(timer_period(Duration::from_micros(1)), 10);
+        assert_eq!(timer_period(Duration::from_secs(1)), 10_000_000);
+        assert_eq!(timer_period(Duration::MAX), u64::MAX);
+    }
+}
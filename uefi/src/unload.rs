@@ -0,0 +1,156 @@
+//! Unloading of resident images.
+//!
+//! A driver stays resident after its entry point returns. It can only be
+//! unloaded with [`BootServices::unload_image`] if its [`LoadedImage`]
+//! protocol has an unload function, which must release everything the
+//! driver installed. [`set_unload_handler`] registers a closure as the
+//! unload function, which makes it possible to replace a driver during
+//! development without rebooting.
+//!
+//! When the image is unloaded:
+//!
+//! 1. The images registered with [`unload_with_image`], e.g. drivers
+//!    loaded by this one, are unloaded, most recently registered first.
+//! 2. The handler runs. If it fails, the image is not unloaded, and the
+//!    error is returned by [`BootServices::unload_image`].
+//! 3. The callbacks registered with [`cleanup::on_exit`] run.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use uefi::prelude::*;
+//! use uefi::unload;
+//!
+//! fn efi_main(_image: Handle, mut st: SystemTable<Boot>) -> Status {
+//!     // Install protocols, create events...
+//!     let result = unload::set_unload_handler(st.boot_services(), || {
+//!         // Uninstall the protocols, close the events...
+//!         Ok(())
+//!     });
+//!     Status::SUCCESS
+//! }
+//! ```
+//!
+//! [`cleanup::on_exit`]: crate::cleanup::on_exit
+
+use crate::cleanup::run_exit_callbacks;
+use crate::mem::{try_box, try_reserve};
+use crate::proto::loaded_image::LoadedImage;
+use crate::table::boot::BootServices;
+use crate::{Handle, Result, Status};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ptr;
+
+/// State of the handler registered with [`set_unload_handler`].
+struct UnloadHandler {
+    boot_services: *const BootServices,
+    callback: Box<dyn FnMut() -> Result>,
+}
+
+/// The handler registered with [`set_unload_handler`].
+///
+/// UEFI images are single-threaded, and `UnloadImage` is only called at
+/// `TPL_APPLICATION`, so there is no concurrent access.
+static mut HANDLER: Option<UnloadHandler> = None;
+
+/// Images registered with [`unload_with_image`], in registration order.
+static mut CHILDREN: Vec<Handle> = Vec::new();
+
+/// Registers `handler` to run when the current image is unloaded with
+/// [`BootServices::unload_image`]. See the [module documentation](self) for
+/// the order of operations.
+///
+/// The handler should return an error to refuse being unloaded, e.g. when
+/// one of its protocols is still in use. Registering a handler replaces the
+/// previous one.
+///
+/// # Errors
+///
+/// See [`BootServices::open_protocol_exclusive`] for opening the
+/// [`LoadedImage`] protocol of the current image.
+pub fn set_unload_handler(bt: &BootServices, handler: impl FnMut() -> Result + 'static) -> Result {
+    let callback = try_box(handler)?;
+    let mut loaded_image = bt.open_protocol_exclusive::<LoadedImage>(bt.image_handle())?;
+    unsafe {
+        *ptr::addr_of_mut!(HANDLER) = Some(UnloadHandler {
+            boot_services: bt,
+            callback,
+        });
+        loaded_image.set_unload(Some(unload));
+    }
+    Ok(())
+}
+
+/// Registers `image` to be unloaded when the current image is unloaded,
+/// before the handler registered with [`set_unload_handler`] runs.
+///
+/// This is meant for images loaded by the current one with
+/// [`BootServices::load_image`], which can't work without it.
+///
+/// # Errors
+///
+/// * [`uefi::Status::OUT_OF_RESOURCES`]: the image couldn't be registered.
+pub fn unload_with_image(image: Handle) -> Result {
+    let children = unsafe { &mut *ptr::addr_of_mut!(CHILDREN) };
+    try_reserve(children, 1)?;
+    children.push(image);
+    Ok(())
+}
+
+/// Runs the unload steps described in the [module documentation](self).
+fn run_unload(handler: &mut UnloadHandler) -> Result {
+    let children = unsafe { &mut *ptr::addr_of_mut!(CHILDREN) };
+    while let Some(&child) = children.last() {
+        // The children stay registered until they are unloaded, so that
+        // unloading can be retried if one of them refuses.
+        unsafe { &*handler.boot_services }.unload_image(child)?;
+        children.pop();
+    }
+
+    (handler.callback)()?;
+    run_exit_callbacks();
+    Ok(())
+}
+
+/// The unload function set in the [`LoadedImage`] protocol by
+/// [`set_unload_handler`].
+unsafe extern "efiapi" fn unload(_image_handle: Handle) -> Status {
+    let handler = match (*ptr::addr_of_mut!(HANDLER)).as_mut() {
+        Some(handler) => handler,
+        None => return Status::SUCCESS,
+    };
+    match run_unload(handler) {
+        Ok(()) => {
+            *ptr::addr_of_mut!(HANDLER) = None;
+            Status::SUCCESS
+        }
+        Err(err) => err.status(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::ffi::c_void;
+    use core::ptr::NonNull;
+
+    #[test]
+    fn test_unload_refused() {
+        let handle = unsafe { Handle::from_ptr(NonNull::<c_void>::dangling().as_ptr()) }.unwrap();
+
+        // Without a handler, there is nothing to release.
+        assert_eq!(unsafe { unload(handle) }, Status::SUCCESS);
+
+        unsafe {
+            *ptr::addr_of_mut!(HANDLER) = Some(UnloadHandler {
+                boot_services: ptr::null(),
+                callback: Box::new(|| Err(Status::ACCESS_DENIED.into())),
+            });
+        }
+        // The handler stays registered, so that unloading can be retried.
+        assert_eq!(unsafe { unload(handle) }, Status::ACCESS_DENIED);
+        assert!(unsafe { (*ptr::addr_of!(HANDLER)).is_some() });
+        unsafe { *ptr::addr_of_mut!(HANDLER) = None };
+    }
+}
@@ -1,5 +1,35 @@
 use core::mem;
 
+/// Evaluate a raw protocol call and return its [`Status`].
+///
+/// With the `trace-status` feature, the name of the call, the formatted
+/// parameters, and the returned status are also logged at trace level
+/// before the status is mapped into a [`Result`]. This makes it possible
+/// to report firmware misbehavior precisely without patching the crate.
+///
+/// Calls made by the logger itself (e.g. `Output::output_string`) must
+/// not be traced, as that would recurse. The same goes for the pool
+/// allocations of the global allocator, which a logger may use. Calls
+/// that exit boot services, or are only valid afterwards when the
+/// console is gone, are not traced either.
+///
+/// [`Result`]: crate::Result
+/// [`Status`]: crate::Status
+macro_rules! trace_status {
+    ($name:literal, $status:expr) => {
+        trace_status!($name, $status, "")
+    };
+    ($name:literal, $status:expr, $($arg:tt)+) => {{
+        let status: $crate::Status = $status;
+        #[cfg(feature = "trace-status")]
+        log::trace!("{}({}) -> {}", $name, format_args!($($arg)+), status);
+        // Avoid unused variable warnings for values that are only traced.
+        #[cfg(not(feature = "trace-status"))]
+        let _ = format_args!($($arg)+);
+        status
+    }};
+}
+
 /// Copy the bytes of `val` to `ptr`, then advance pointer to just after the
 /// newly-copied bytes.
 pub unsafe fn ptr_write_unaligned_and_add<T>(ptr: &mut *mut u8, val: T) {
@@ -24,6 +54,61 @@ pub const fn usize_from_u32(val: u32) -> usize {
     }
 }
 
+/// Define `const fn`s that divide two integers, rounding up. These stand
+/// in for the inherent `div_ceil` methods, which are newer than the minimum
+/// supported Rust version.
+macro_rules! define_div_ceil {
+    ($($name:ident: $t:ty),* $(,)?) => {
+        $(
+            /// Divide `n` by `d`, rounding up. Panics if `d` is zero.
+            #[allow(dead_code)]
+            pub const fn $name(n: $t, d: $t) -> $t {
+                n / d + (n % d != 0) as $t
+            }
+        )*
+    };
+}
+
+define_div_ceil!(div_ceil_u64: u64, div_ceil_u128: u128, div_ceil_usize: usize);
+
+/// Lookup table of the CRC-32 used by UEFI, with the reflected polynomial
+/// `0xedb88320`.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Compute the CRC-32 of `bytes`, as used in the table headers and in the
+/// GUID partition table. This is the same algorithm as the
+/// `CalculateCrc32` boot service, which is not available after boot
+/// services are exited.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    crc32_update(0, bytes)
+}
+
+/// Continue the CRC-32 `crc` of previous bytes with `bytes`, so that
+/// `crc32_update(crc32(a), b)` is the CRC-32 of `a` followed by `b`.
+pub fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!crc, |crc, &byte| {
+        CRC32_TABLE[((crc ^ u32::from(byte)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,4 +118,21 @@ mod tests {
         assert_eq!(usize_from_u32(0), 0usize);
         assert_eq!(usize_from_u32(u32::MAX), 4294967295usize);
     }
+
+    #[test]
+    fn test_div_ceil() {
+        assert_eq!(div_ceil_u64(0, 512), 0);
+        assert_eq!(div_ceil_u64(1, 512), 1);
+        assert_eq!(div_ceil_u64(512, 512), 1);
+        assert_eq!(div_ceil_u64(u64::MAX, 2), 1 << 63);
+        assert_eq!(div_ceil_u128(1001, 1000), 2);
+        assert_eq!(div_ceil_usize(26, 13), 2);
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32_update(crc32(b"1234"), b"56789"), 0xcbf4_3926);
+    }
 }
@@ -8,6 +8,9 @@ pub enum UefiArch {
     #[value(name = "ia32")]
     IA32,
 
+    #[value(name = "riscv64")]
+    RiscV64,
+
     #[value(name = "x86_64")]
     X86_64,
 }
@@ -17,6 +20,7 @@ impl UefiArch {
         match self {
             Self::AArch64 => "aarch64",
             Self::IA32 => "ia32",
+            Self::RiscV64 => "riscv64",
             Self::X86_64 => "x86_64",
         }
     }
@@ -25,6 +29,7 @@ impl UefiArch {
         match self {
             Self::AArch64 => "aarch64-unknown-uefi",
             Self::IA32 => "i686-unknown-uefi",
+            Self::RiscV64 => "riscv64gc-unknown-uefi",
             Self::X86_64 => "x86_64-unknown-uefi",
         }
     }
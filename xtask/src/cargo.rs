@@ -47,9 +47,18 @@ impl Package {
 pub enum Feature {
     // `uefi` features.
     Alloc,
+    AllocStats,
+    ArchIntrinsics,
+    Decompress,
+    FallibleAlloc,
+    Fat,
     GlobalAllocator,
     Logger,
+    Mock,
     PanicOnLoggerErrors,
+    Serde,
+    Std,
+    TraceStatus,
     Unstable,
 
     // `uefi-services` features.
@@ -67,9 +76,18 @@ impl Feature {
     fn as_str(&self) -> &'static str {
         match self {
             Self::Alloc => "alloc",
+            Self::AllocStats => "alloc-stats",
+            Self::ArchIntrinsics => "arch-intrinsics",
+            Self::Decompress => "decompress",
+            Self::FallibleAlloc => "fallible-alloc",
+            Self::Fat => "fat",
             Self::GlobalAllocator => "global_allocator",
             Self::Logger => "logger",
+            Self::Mock => "mock",
             Self::PanicOnLoggerErrors => "panic-on-logger-errors",
+            Self::Serde => "serde",
+            Self::Std => "std",
+            Self::TraceStatus => "trace-status",
             Self::Unstable => "unstable",
 
             Self::PanicHandler => "uefi-services/panic_handler",
@@ -82,14 +100,23 @@ impl Feature {
         }
     }
 
-    /// Get the features for the given package.
+    /// Get the features for the given package that can be built for the
+    /// UEFI targets. The `std` and `mock` features of `uefi` need `std`,
+    /// so they are only built on the host, see [`Self::host_only`].
     pub fn package_features(package: Package) -> Vec<Self> {
         match package {
             Package::Uefi => vec![
                 Self::Alloc,
+                Self::AllocStats,
+                Self::ArchIntrinsics,
+                Self::Decompress,
+                Self::FallibleAlloc,
+                Self::Fat,
                 Self::GlobalAllocator,
                 Self::Logger,
                 Self::PanicOnLoggerErrors,
+                Self::Serde,
+                Self::TraceStatus,
                 Self::Unstable,
             ],
             Package::UefiServices => vec![Self::PanicHandler, Self::Qemu, Self::ServicesLogger],
@@ -103,7 +130,13 @@ impl Feature {
     /// - `include_unstable` - add all functionality behind the `unstable` feature
     /// - `runtime_features` - add all functionality that effect the runtime of Rust
     pub fn more_code(include_unstable: bool, runtime_features: bool) -> Vec<Self> {
-        let mut base_features = vec![Self::Alloc, Self::Logger];
+        let mut base_features = vec![
+            Self::Alloc,
+            Self::Decompress,
+            Self::Fat,
+            Self::Logger,
+            Self::Serde,
+        ];
         if include_unstable {
             base_features.extend([Self::Unstable])
         }
@@ -113,6 +146,12 @@ impl Feature {
         base_features
     }
 
+    /// Features of the `uefi` crate that link against `std`, and can only
+    /// be built on the host. `mock` enables `std`, and the mock tests.
+    pub fn host_only() -> Vec<Self> {
+        vec![Self::Std, Self::Mock]
+    }
+
     fn comma_separated_string(features: &[Feature]) -> String {
         features
             .iter()
@@ -152,6 +191,9 @@ pub enum TargetTypes {
 
     /// Build bins, examples, and libs.
     BinsExamplesLib,
+
+    /// Build the lib and its unit tests.
+    LibTests,
 }
 
 impl TargetTypes {
@@ -166,6 +208,7 @@ impl TargetTypes {
                 // package can only include one lib.
                 "--lib",
             ],
+            TargetTypes::LibTests => &["--lib", "--tests"],
         }
     }
 }
@@ -331,19 +374,19 @@ mod tests {
     fn test_comma_separated_features() {
         assert_eq!(
             Feature::comma_separated_string(&Feature::more_code(false, false)),
-            "alloc,logger"
+            "alloc,decompress,fat,logger,serde"
         );
         assert_eq!(
             Feature::comma_separated_string(&Feature::more_code(false, true)),
-            "alloc,logger,global_allocator"
+            "alloc,decompress,fat,logger,serde,global_allocator"
         );
         assert_eq!(
             Feature::comma_separated_string(&Feature::more_code(true, false)),
-            "alloc,logger,unstable"
+            "alloc,decompress,fat,logger,serde,unstable"
         );
         assert_eq!(
             Feature::comma_separated_string(&Feature::more_code(true, true)),
-            "alloc,logger,unstable,global_allocator"
+            "alloc,decompress,fat,logger,serde,unstable,global_allocator"
         );
     }
 
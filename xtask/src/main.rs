@@ -73,6 +73,21 @@ fn clippy(opt: &ClippyOpt) -> Result<()> {
     };
     run_cmd(cargo.command()?)?;
 
+    // Run clippy on the host with the features that need `std`, which
+    // also covers the unit tests that use the mock firmware.
+    let mut features = Feature::more_code(true, false);
+    features.extend(Feature::host_only());
+    let cargo = Cargo {
+        action: CargoAction::Clippy,
+        features,
+        packages: vec![Package::Uefi],
+        release: false,
+        target: None,
+        warnings_as_errors: opt.warning.warnings_as_errors,
+        target_types: TargetTypes::LibTests,
+    };
+    run_cmd(cargo.command()?)?;
+
     // Run clippy on xtask.
     let cargo = Cargo {
         action: CargoAction::Clippy,
@@ -167,13 +182,17 @@ fn run_host_tests(test_opt: &TestOpt) -> Result<()> {
     run_cmd(cargo.command()?)?;
 
     // Run uefi-rs and uefi-macros tests.
+    //
+    // At least one unit test, for make_boxed() currently, has different behaviour dependent on
+    // the unstable feature. Because of this, we need to allow to test both variants. Runtime
+    // features is set to no as it is not possible as as soon a #[global_allocator] is
+    // registered, the Rust runtime executing the tests uses it as well. The host-only features
+    // enable the tests that use the mock firmware.
+    let mut features = Feature::more_code(test_opt.include_unstable, false);
+    features.extend(Feature::host_only());
     let cargo = Cargo {
         action: CargoAction::Test,
-        // At least one unit test, for make_boxed() currently, has different behaviour dependent on
-        // the unstable feature. Because of this, we need to allow to test both variants. Runtime
-        // features is set to no as it is not possible as as soon a #[global_allocator] is
-        // registered, the Rust runtime executing the tests uses it as well.
-        features: Feature::more_code(test_opt.include_unstable, false),
+        features,
         // Don't test uefi-services (or the packages that depend on it)
         // as it has lang items that conflict with `std`.
         packages: vec![Package::Uefi, Package::UefiMacros],
@@ -56,6 +56,11 @@ impl OvmfPaths {
                 code: "/usr/share/edk2-ovmf/ia32/OVMF_CODE.fd".into(),
                 vars: "/usr/share/edk2-ovmf/ia32/OVMF_VARS.fd".into(),
             },
+            // Package "edk2-riscv64".
+            UefiArch::RiscV64 => Self {
+                code: "/usr/share/edk2/riscv64/RISCV_VIRT_CODE.fd".into(),
+                vars: "/usr/share/edk2/riscv64/RISCV_VIRT_VARS.fd".into(),
+            },
             // Package "edk2-ovmf".
             UefiArch::X86_64 => Self {
                 code: "/usr/share/edk2-ovmf/x64/OVMF_CODE.fd".into(),
@@ -72,8 +77,8 @@ impl OvmfPaths {
                 code: "/usr/share/edk2/aarch64/QEMU_EFI-pflash.raw".into(),
                 vars: "/usr/share/edk2/aarch64/vars-template-pflash.raw".into(),
             }),
-            // There's no official ia32 package.
-            UefiArch::IA32 => None,
+            // There's no official ia32 or riscv64 package.
+            UefiArch::IA32 | UefiArch::RiscV64 => None,
             // Package "edk2-ovmf".
             UefiArch::X86_64 => Some(Self {
                 // Use the `.secboot` variant because the CentOS package
@@ -98,6 +103,11 @@ impl OvmfPaths {
                 code: "/usr/share/OVMF/OVMF32_CODE_4M.secboot.fd".into(),
                 vars: "/usr/share/OVMF/OVMF32_VARS_4M.fd".into(),
             },
+            // Package "qemu-efi-riscv64".
+            UefiArch::RiscV64 => Self {
+                code: "/usr/share/qemu-efi-riscv64/RISCV_VIRT_CODE.fd".into(),
+                vars: "/usr/share/qemu-efi-riscv64/RISCV_VIRT_VARS.fd".into(),
+            },
             // Package "ovmf".
             UefiArch::X86_64 => Self {
                 code: "/usr/share/OVMF/OVMF_CODE.fd".into(),
@@ -119,6 +129,11 @@ impl OvmfPaths {
                 code: "/usr/share/edk2/ovmf-ia32/OVMF_CODE.fd".into(),
                 vars: "/usr/share/edk2/ovmf-ia32/OVMF_VARS.fd".into(),
             },
+            // Package "edk2-riscv64".
+            UefiArch::RiscV64 => Self {
+                code: "/usr/share/edk2/riscv/RISCV_VIRT_CODE.fd".into(),
+                vars: "/usr/share/edk2/riscv/RISCV_VIRT_VARS.fd".into(),
+            },
             // Package "edk2-ovmf".
             UefiArch::X86_64 => Self {
                 code: "/usr/share/edk2/ovmf/OVMF_CODE.fd".into(),
@@ -138,6 +153,10 @@ impl OvmfPaths {
                 code: r"C:\Program Files\qemu\share\edk2-i386-code.fd".into(),
                 vars: r"C:\Program Files\qemu\share\edk2-i386-vars.fd".into(),
             },
+            UefiArch::RiscV64 => Self {
+                code: r"C:\Program Files\qemu\share\edk2-riscv-code.fd".into(),
+                vars: r"C:\Program Files\qemu\share\edk2-riscv-vars.fd".into(),
+            },
             UefiArch::X86_64 => Self {
                 code: r"C:\Program Files\qemu\share\edk2-x86_64-code.fd".into(),
                 // There's no x86_64 vars file, but the i386 one works.
@@ -285,6 +304,14 @@ impl Io {
 fn process_qemu_io(mut monitor_io: Io, mut serial_io: Io, tmp_dir: &Path) -> Result<()> {
     let mut tests_complete = false;
 
+    // Name of the test that has started but not yet passed or been
+    // skipped. If the app stops without reporting a failure, this is the
+    // test during which it crashed or hung.
+    let mut current_test: Option<String> = None;
+
+    // Name of the test reported as failed by the app's panic handler.
+    let mut failed_test: Option<String> = None;
+
     // This regex is used to detect and strip ANSI escape codes. These
     // escapes are added by the console output protocol when writing to
     // the serial device.
@@ -345,17 +372,57 @@ fn process_qemu_io(mut monitor_io: Io, mut serial_io: Io, tmp_dir: &Path) -> Res
 
             reply_ok()?;
         } else {
+            match parse_test_record(&line) {
+                Some((record, name)) if record == "TEST_START" => current_test = name,
+                Some((record, _)) if record == "TEST_PASS" || record == "TEST_SKIP" => {
+                    current_test = None;
+                }
+                Some((record, name)) if record == "TEST_FAIL" => failed_test = name,
+                _ => {}
+            }
             println!("{line}");
         }
     }
 
     if !tests_complete {
+        if let Some(name) = failed_test {
+            bail!("test {name} failed");
+        }
+        if let Some(name) = current_test {
+            bail!("test {name} did not complete");
+        }
         bail!("tests did not complete successfully");
     }
 
     Ok(())
 }
 
+/// Parse a test record printed by the app in any of the `uefi::report`
+/// formats. Returns the record name and its `name` field, if any.
+///
+/// Test names don't contain whitespace or commas, so the fields of the
+/// human and `key=value` formats are simply split on their separators.
+fn parse_test_record(line: &str) -> Option<(String, Option<String>)> {
+    if line.starts_with('{') {
+        let value: Value = serde_json::from_str(line).ok()?;
+        let field = |key| value.get(key)?.as_str().map(str::to_string);
+        return Some((field("record")?, field("name")));
+    }
+
+    let (record, fields, separator) = if let Some(rest) = line.strip_prefix("record=") {
+        let (record, fields) = rest.split_once(' ').unwrap_or((rest, ""));
+        (record, fields, " ")
+    } else {
+        let (record, fields) = line.split_once(": ")?;
+        (record, fields, ", ")
+    };
+    let name = fields
+        .split(separator)
+        .find_map(|field| field.strip_prefix("name="))
+        .map(|name| name.trim_matches('"').to_string());
+    Some((record.to_string(), name))
+}
+
 /// Create an EFI boot directory to pass into QEMU.
 fn build_esp_dir(opt: &QemuOpt) -> Result<PathBuf> {
     let build_mode = if opt.build_mode.release {
@@ -376,6 +443,7 @@ fn build_esp_dir(opt: &QemuOpt) -> Result<PathBuf> {
     let output_file = match *opt.target {
         UefiArch::AArch64 => "BootAA64.efi",
         UefiArch::IA32 => "BootIA32.efi",
+        UefiArch::RiscV64 => "BootRISCV64.efi",
         UefiArch::X86_64 => "BootX64.efi",
     };
     if !boot_dir.exists() {
@@ -416,6 +484,7 @@ pub fn run_qemu(arch: UefiArch, opt: &QemuOpt) -> Result<()> {
     let qemu_exe = match arch {
         UefiArch::AArch64 => "qemu-system-aarch64",
         UefiArch::IA32 | UefiArch::X86_64 => "qemu-system-x86_64",
+        UefiArch::RiscV64 => "qemu-system-riscv64",
     };
     let mut cmd = Command::new(qemu_exe);
 
@@ -437,6 +506,10 @@ pub fn run_qemu(arch: UefiArch, opt: &QemuOpt) -> Result<()> {
 
     cmd.args(["-device", "virtio-rng-pci"]);
 
+    // USB host controller, without any device attached, for the USB host
+    // controller protocol test.
+    cmd.args(["-device", "qemu-xhci"]);
+
     match arch {
         UefiArch::AArch64 => {
             // Use a generic ARM environment. Sadly qemu can't emulate a
@@ -449,6 +522,22 @@ pub fn run_qemu(arch: UefiArch, opt: &QemuOpt) -> Result<()> {
             // Graphics device.
             cmd.args(["-device", "virtio-gpu-pci"]);
         }
+        UefiArch::RiscV64 => {
+            // Use the generic RISC-V environment, which is the only
+            // machine supported by the EDK2 RISC-V firmware.
+            cmd.args(["-machine", "virt"]);
+
+            // Allocate some memory.
+            cmd.args(["-m", "256M"]);
+
+            // Graphics device.
+            cmd.args(["-device", "virtio-gpu-pci"]);
+
+            // Exit instead of rebooting in the CI.
+            if opt.ci {
+                cmd.arg("-no-reboot");
+            }
+        }
         UefiArch::IA32 | UefiArch::X86_64 => {
             // Use a modern machine.
             cmd.args(["-machine", "q35"]);
@@ -472,6 +561,10 @@ pub fn run_qemu(arch: UefiArch, opt: &QemuOpt) -> Result<()> {
                 cmd.arg("-no-reboot");
             }
 
+            // Second serial port (COM2), for the serial enumeration test.
+            cmd.args(["-chardev", "null,id=serial1"]);
+            cmd.args(["-device", "isa-serial,chardev=serial1,index=1"]);
+
             // Map the QEMU exit signal to port f4.
             cmd.args(["-device", "isa-debug-exit,iobase=0xf4,iosize=0x04"]);
 
@@ -578,7 +671,7 @@ pub fn run_qemu(arch: UefiArch, opt: &QemuOpt) -> Result<()> {
         .context(format!("qemu was terminated by a signal: {status:?}"))?;
 
     let successful_exit_code = match arch {
-        UefiArch::AArch64 | UefiArch::IA32 => 0,
+        UefiArch::AArch64 | UefiArch::IA32 | UefiArch::RiscV64 => 0,
 
         // The x86_64 version of uefi-test-runner uses exit code 3 to
         // indicate success. See the `shutdown` function in
@@ -598,3 +691,38 @@ pub fn run_qemu(arch: UefiArch, opt: &QemuOpt) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_test_record() {
+        let start = Some(("TEST_START".to_string(), Some("boot/memory".to_string())));
+        assert_eq!(parse_test_record("TEST_START: name=boot/memory"), start);
+        assert_eq!(
+            parse_test_record("record=TEST_START name=boot/memory"),
+            start
+        );
+        assert_eq!(
+            parse_test_record(r#"{"record":"TEST_START","name":"boot/memory"}"#),
+            start
+        );
+
+        let skip = Some(("TEST_SKIP".to_string(), Some("proto/pi/mp".to_string())));
+        assert_eq!(
+            parse_test_record("TEST_SKIP: name=proto/pi/mp, reason=not enabled"),
+            skip
+        );
+        assert_eq!(
+            parse_test_record(r#"record=TEST_SKIP name=proto/pi/mp reason="not enabled""#),
+            skip
+        );
+
+        assert_eq!(
+            parse_test_record("record=TEST_SUMMARY passed=1 skipped=2"),
+            Some(("TEST_SUMMARY".to_string(), None))
+        );
+        assert_eq!(parse_test_record("Testing memory functions"), None);
+    }
+}